@@ -0,0 +1,649 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_audio::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_transform::BaseTransformImplExt;
+use gst_base::subclass::base_transform::GenerateOutputSuccess;
+
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+use num_traits::float::Float;
+
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "audiocompressor",
+        gst::DebugColorFlags::empty(),
+        Some("Audio Compressor/Limiter"),
+    )
+});
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstAudioCompressorMode")]
+pub(crate) enum Mode {
+    #[enum_value(
+        name = "Compressor: Reduce levels above the threshold by the ratio",
+        nick = "compressor"
+    )]
+    Compressor = 0,
+    #[enum_value(
+        name = "Limiter: Never let levels exceed the threshold",
+        nick = "limiter"
+    )]
+    Limiter = 1,
+}
+
+const DEFAULT_MODE: Mode = Mode::Compressor;
+const DEFAULT_THRESHOLD: f64 = -20.0;
+const DEFAULT_RATIO: f64 = 4.0;
+const DEFAULT_KNEE: f64 = 6.0;
+const DEFAULT_MAKEUP_GAIN: f64 = 0.0;
+const DEFAULT_ATTACK: gst::ClockTime = gst::ClockTime::from_mseconds(10);
+const DEFAULT_RELEASE: gst::ClockTime = gst::ClockTime::from_mseconds(100);
+const DEFAULT_LOOKAHEAD: gst::ClockTime = gst::ClockTime::ZERO;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    mode: Mode,
+    threshold: f64,
+    ratio: f64,
+    knee: f64,
+    makeup_gain: f64,
+    attack: gst::ClockTime,
+    release: gst::ClockTime,
+    lookahead: gst::ClockTime,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: DEFAULT_MODE,
+            threshold: DEFAULT_THRESHOLD,
+            ratio: DEFAULT_RATIO,
+            knee: DEFAULT_KNEE,
+            makeup_gain: DEFAULT_MAKEUP_GAIN,
+            attack: DEFAULT_ATTACK,
+            release: DEFAULT_RELEASE,
+            lookahead: DEFAULT_LOOKAHEAD,
+        }
+    }
+}
+
+impl Settings {
+    // Static gain computer: output level in dB for an input level in dB
+    fn output_level(&self, level: f64) -> f64 {
+        let (level, ratio) = match self.mode {
+            // Make-up gain is applied before limiting so that it can't push the output over the
+            // threshold
+            Mode::Limiter => (level + self.makeup_gain, f64::INFINITY),
+            Mode::Compressor => (level, self.ratio),
+        };
+
+        let over = level - self.threshold;
+        if 2.0 * over < -self.knee {
+            level
+        } else if self.knee > 0.0 && 2.0 * over.abs() <= self.knee {
+            level + (1.0 / ratio - 1.0) * (over + self.knee / 2.0).powi(2) / (2.0 * self.knee)
+        } else {
+            self.threshold + over / ratio
+        }
+    }
+
+    // Gain in dB to apply for an input level in dB, not yet smoothed and without make-up gain
+    fn gain_reduction(&self, level: f64) -> f64 {
+        match self.mode {
+            Mode::Limiter => self.output_level(level) - level - self.makeup_gain,
+            Mode::Compressor => self.output_level(level) - level,
+        }
+    }
+}
+
+fn time_constant(time: gst::ClockTime, rate: u32) -> f64 {
+    let samples = time.nseconds() as f64 * rate as f64 / *gst::ClockTime::SECOND as f64;
+    if samples < 1.0 {
+        0.0
+    } else {
+        f64::exp(-1.0 / samples)
+    }
+}
+
+struct State {
+    info: gst_audio::AudioInfo,
+    // Delay line of `lookahead_frames` interleaved frames
+    delay: Box<[f64]>,
+    lookahead_frames: usize,
+    write_index: usize,
+    filled: usize,
+    // Smoothed gain reduction in dB
+    envelope: f64,
+    // Timestamp of the first output sample and number of samples output since then
+    base_pts: Option<gst::ClockTime>,
+    num_output_frames: u64,
+}
+
+impl State {
+    fn new(info: &gst_audio::AudioInfo, lookahead: gst::ClockTime) -> Self {
+        let lookahead_frames = lookahead
+            .mul_div_round(info.rate() as u64, *gst::ClockTime::SECOND)
+            .unwrap()
+            .nseconds() as usize;
+
+        State {
+            info: info.clone(),
+            delay: vec![0.0; lookahead_frames * info.channels() as usize].into_boxed_slice(),
+            lookahead_frames,
+            write_index: 0,
+            filled: 0,
+            envelope: 0.0,
+            base_pts: None,
+            num_output_frames: 0,
+        }
+    }
+
+    fn samples_to_time(&self, samples: u64) -> Option<gst::ClockTime> {
+        samples
+            .mul_div_round(*gst::ClockTime::SECOND, self.info.rate() as u64)
+            .map(gst::ClockTime::from_nseconds)
+    }
+
+    fn reset(&mut self) {
+        self.write_index = 0;
+        self.filled = 0;
+        self.envelope = 0.0;
+        self.base_pts = None;
+        self.num_output_frames = 0;
+    }
+
+    // Processes one frame and appends the (delayed) output frame, if any, to `out`. Without
+    // `input` this drains one frame from the delay line.
+    fn process_frame(&mut self, settings: &Settings, input: Option<&[f64]>, out: &mut Vec<f64>) {
+        let channels = self.info.channels() as usize;
+        let rate = self.info.rate();
+
+        // Stereo linked peak detection
+        let peak = input.map_or(0.0, |input| {
+            input.iter().fold(0.0f64, |peak, s| peak.max(s.abs()))
+        });
+        let level = 20.0 * f64::log10(peak.max(1e-9));
+
+        let target = settings.gain_reduction(level);
+        let coeff = if target < self.envelope {
+            time_constant(settings.attack, rate)
+        } else {
+            time_constant(settings.release, rate)
+        };
+        self.envelope = coeff * self.envelope + (1.0 - coeff) * target;
+
+        let gain = f64::powf(10.0, (self.envelope + settings.makeup_gain) / 20.0);
+        let ceiling = match settings.mode {
+            Mode::Limiter => f64::powf(10.0, settings.threshold / 20.0),
+            Mode::Compressor => f64::INFINITY,
+        };
+
+        if self.lookahead_frames == 0 {
+            if let Some(input) = input {
+                out.extend(input.iter().map(|s| (s * gain).clamp(-ceiling, ceiling)));
+            }
+            return;
+        }
+
+        if self.filled == self.lookahead_frames || input.is_none() {
+            let read_index =
+                (self.write_index + self.lookahead_frames - self.filled) % self.lookahead_frames;
+            let frame = &self.delay[read_index * channels..(read_index + 1) * channels];
+            out.extend(frame.iter().map(|s| (s * gain).clamp(-ceiling, ceiling)));
+            self.filled -= 1;
+        }
+
+        if let Some(input) = input {
+            self.delay[self.write_index * channels..(self.write_index + 1) * channels]
+                .copy_from_slice(input);
+            self.write_index = (self.write_index + 1) % self.lookahead_frames;
+            self.filled += 1;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AudioCompressor {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AudioCompressor {
+    const NAME: &'static str = "GstAudioCompressor";
+    type Type = super::AudioCompressor;
+    type ParentType = gst_audio::AudioFilter;
+}
+
+impl AudioCompressor {
+    fn process<F: Float + ToPrimitive + FromPrimitive + FromByteSlice>(
+        state: &mut State,
+        settings: &Settings,
+        input: Option<&[F]>,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let channels = state.info.channels() as usize;
+        let mut out = Vec::new();
+
+        match input {
+            Some(input) => {
+                let mut frame = vec![0.0; channels];
+                for in_frame in input.chunks_exact(channels) {
+                    for (f, s) in frame.iter_mut().zip(in_frame) {
+                        *f = s.to_f64().unwrap();
+                    }
+                    state.process_frame(settings, Some(&frame), &mut out);
+                }
+            }
+            None => {
+                while state.filled > 0 {
+                    state.process_frame(settings, None, &mut out);
+                }
+            }
+        }
+
+        let num_frames = (out.len() / channels) as u64;
+        let mut buffer = gst::Buffer::with_size(out.len() * std::mem::size_of::<F>())
+            .map_err(|_| gst::FlowError::Error)?;
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(
+                state
+                    .base_pts
+                    .opt_add(state.samples_to_time(state.num_output_frames)),
+            );
+            buffer.set_duration(state.samples_to_time(num_frames));
+
+            let mut map = buffer.map_writable().map_err(|_| gst::FlowError::Error)?;
+            let data = map.as_mut_slice_of::<F>().unwrap();
+            for (o, i) in data.iter_mut().zip(out) {
+                *o = F::from_f64(i).unwrap();
+            }
+        }
+        state.num_output_frames += num_frames;
+
+        Ok(buffer)
+    }
+
+    fn process_buffer(
+        &self,
+        state: &mut State,
+        input: Option<&gst::Buffer>,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+
+        let map = input
+            .map(|input| input.map_readable().map_err(|_| gst::FlowError::Error))
+            .transpose()?;
+
+        match state.info.format() {
+            gst_audio::AUDIO_FORMAT_F64 => {
+                let data = map.as_ref().map(|map| map.as_slice_of::<f64>().unwrap());
+                Self::process(state, &settings, data)
+            }
+            gst_audio::AUDIO_FORMAT_F32 => {
+                let data = map.as_ref().map(|map| map.as_slice_of::<f32>().unwrap());
+                Self::process(state, &settings, data)
+            }
+            _ => Err(gst::FlowError::NotNegotiated),
+        }
+    }
+
+    fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state_guard = self.state.lock().unwrap();
+        let Some(state) = state_guard.as_mut() else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        if state.filled == 0 {
+            state.reset();
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        gst::debug!(CAT, imp: self, "Draining {} frames", state.filled);
+
+        let buffer = self.process_buffer(state, None)?;
+        state.reset();
+        drop(state_guard);
+
+        self.obj().src_pad().push(buffer)
+    }
+}
+
+impl ObjectImpl for AudioCompressor {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("Compressor or brickwall limiter mode")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("threshold")
+                    .nick("Threshold")
+                    .blurb("Level in dBFS above which the gain is reduced")
+                    .minimum(-60.0)
+                    .maximum(0.0)
+                    .default_value(DEFAULT_THRESHOLD)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("ratio")
+                    .nick("Ratio")
+                    .blurb("Compression ratio above the threshold, ignored in limiter mode")
+                    .minimum(1.0)
+                    .maximum(100.0)
+                    .default_value(DEFAULT_RATIO)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("knee")
+                    .nick("Knee")
+                    .blurb("Width of the soft knee around the threshold in dB")
+                    .minimum(0.0)
+                    .maximum(24.0)
+                    .default_value(DEFAULT_KNEE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("makeup-gain")
+                    .nick("Make-up Gain")
+                    .blurb("Gain in dB applied after compression")
+                    .minimum(0.0)
+                    .maximum(40.0)
+                    .default_value(DEFAULT_MAKEUP_GAIN)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("attack")
+                    .nick("Attack")
+                    .blurb("Attack time in nanoseconds")
+                    .maximum(gst::ClockTime::SECOND.nseconds())
+                    .default_value(DEFAULT_ATTACK.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("release")
+                    .nick("Release")
+                    .blurb("Release time in nanoseconds")
+                    .maximum(10 * gst::ClockTime::SECOND.nseconds())
+                    .default_value(DEFAULT_RELEASE.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("lookahead")
+                    .nick("Lookahead")
+                    .blurb("Lookahead in nanoseconds, adds the same amount of latency (can't be changed in PLAYING or PAUSED state)")
+                    .maximum(100 * gst::ClockTime::MSECOND.nseconds())
+                    .default_value(DEFAULT_LOOKAHEAD.nseconds())
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "mode" => {
+                let mode = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing mode from {:?} to {:?}",
+                    settings.mode,
+                    mode
+                );
+                settings.mode = mode;
+            }
+            "threshold" => {
+                let threshold = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing threshold from {} to {}",
+                    settings.threshold,
+                    threshold
+                );
+                settings.threshold = threshold;
+            }
+            "ratio" => {
+                let ratio = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing ratio from {} to {}",
+                    settings.ratio,
+                    ratio
+                );
+                settings.ratio = ratio;
+            }
+            "knee" => {
+                let knee = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing knee from {} to {}",
+                    settings.knee,
+                    knee
+                );
+                settings.knee = knee;
+            }
+            "makeup-gain" => {
+                let makeup_gain = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing makeup-gain from {} to {}",
+                    settings.makeup_gain,
+                    makeup_gain
+                );
+                settings.makeup_gain = makeup_gain;
+            }
+            "attack" => {
+                let attack = value.get::<u64>().unwrap().nseconds();
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing attack from {} to {}",
+                    settings.attack,
+                    attack
+                );
+                settings.attack = attack;
+            }
+            "release" => {
+                let release = value.get::<u64>().unwrap().nseconds();
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing release from {} to {}",
+                    settings.release,
+                    release
+                );
+                settings.release = release;
+            }
+            "lookahead" => {
+                let lookahead = value.get::<u64>().unwrap().nseconds();
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing lookahead from {} to {}",
+                    settings.lookahead,
+                    lookahead
+                );
+                settings.lookahead = lookahead;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "mode" => settings.mode.to_value(),
+            "threshold" => settings.threshold.to_value(),
+            "ratio" => settings.ratio.to_value(),
+            "knee" => settings.knee.to_value(),
+            "makeup-gain" => settings.makeup_gain.to_value(),
+            "attack" => settings.attack.to_value(),
+            "release" => settings.release.to_value(),
+            "lookahead" => settings.lookahead.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for AudioCompressor {}
+
+impl ElementImpl for AudioCompressor {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Audio compressor",
+                "Filter/Effect/Audio",
+                "Dynamic range compressor and lookahead limiter",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+}
+
+impl BaseTransformImpl for AudioCompressor {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::NeverInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn generate_output(&self) -> Result<GenerateOutputSuccess, gst::FlowError> {
+        let Some(buffer) = self.take_queued_buffer() else {
+            return Ok(GenerateOutputSuccess::NoOutput);
+        };
+
+        if buffer.flags().contains(gst::BufferFlags::DISCONT) {
+            self.drain()?;
+        }
+
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(
+                self,
+                gst::CoreError::Negotiation,
+                ["Can not generate an output without State"]
+            );
+            gst::FlowError::NotNegotiated
+        })?;
+
+        if state.base_pts.is_none() {
+            state.base_pts = buffer.pts();
+        }
+
+        let mut outbuf = self.process_buffer(state, Some(&buffer))?;
+        if outbuf.size() == 0 {
+            // Everything went into the lookahead delay line
+            return Ok(GenerateOutputSuccess::NoOutput);
+        }
+
+        if buffer.flags().contains(gst::BufferFlags::DISCONT) {
+            outbuf
+                .get_mut()
+                .unwrap()
+                .set_flags(gst::BufferFlags::DISCONT);
+        }
+
+        Ok(GenerateOutputSuccess::Buffer(outbuf))
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        match event.view() {
+            EventView::Eos(_) => {
+                gst::debug!(CAT, imp: self, "Handling EOS");
+                if self.drain().is_err() {
+                    return false;
+                }
+            }
+            EventView::FlushStop(_) => {
+                if let Some(state) = self.state.lock().unwrap().as_mut() {
+                    state.reset();
+                }
+            }
+            _ => (),
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn query(&self, direction: gst::PadDirection, query: &mut gst::QueryRef) -> bool {
+        if direction == gst::PadDirection::Src {
+            if let gst::QueryViewMut::Latency(q) = query.view_mut() {
+                let mut upstream_query = gst::query::Latency::new();
+                if self.obj().sink_pad().peer_query(&mut upstream_query) {
+                    let (live, mut min, mut max) = upstream_query.result();
+                    let lookahead = self.settings.lock().unwrap().lookahead;
+                    gst::debug!(
+                        CAT,
+                        imp: self,
+                        "Peer latency: live {} min {} max {}, adding lookahead {}",
+                        live,
+                        min,
+                        max.display(),
+                        lookahead,
+                    );
+
+                    min += lookahead;
+                    max = max.opt_add(lookahead);
+                    q.set(live, min, max);
+                    return true;
+                }
+            }
+        }
+        BaseTransformImplExt::parent_query(self, direction, query)
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        // Drop state
+        let _ = self.state.lock().unwrap().take();
+
+        Ok(())
+    }
+}
+
+impl AudioFilterImpl for AudioCompressor {
+    fn allowed_caps() -> &'static gst::Caps {
+        static CAPS: Lazy<gst::Caps> = Lazy::new(|| {
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format_list([gst_audio::AUDIO_FORMAT_F32, gst_audio::AUDIO_FORMAT_F64])
+                .build()
+        });
+
+        &CAPS
+    }
+
+    fn setup(&self, info: &gst_audio::AudioInfo) -> Result<(), gst::LoggableError> {
+        // Flush previous state
+        if self.state.lock().unwrap().is_some() {
+            self.drain().map_err(|e| {
+                gst::loggable_error!(CAT, "Error flushing previous state data {:?}", e)
+            })?;
+        }
+
+        gst::debug!(CAT, imp: self, "Set caps to {:?}", info);
+
+        let lookahead = self.settings.lock().unwrap().lookahead;
+        *self.state.lock().unwrap() = Some(State::new(info, lookahead));
+
+        Ok(())
+    }
+}
@@ -37,6 +37,32 @@ const DEFAULT_LOUDNESS_TARGET: f64 = -24.0;
 const DEFAULT_LOUDNESS_RANGE_TARGET: f64 = 7.0;
 const DEFAULT_MAX_TRUE_PEAK: f64 = -2.0;
 const DEFAULT_OFFSET: f64 = 0.0;
+const DEFAULT_MODE: Mode = Mode::Dynamic;
+// Same defaults as ffmpeg, which are used to detect if the measurements were not provided
+const DEFAULT_MEASURED_LOUDNESS: f64 = 0.0;
+const DEFAULT_MEASURED_LOUDNESS_RANGE: f64 = 0.0;
+const DEFAULT_MEASURED_TRUE_PEAK: f64 = 99.0;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstAudioLoudNormMode")]
+pub(crate) enum Mode {
+    #[enum_value(
+        name = "Dynamic: Adjust the gain dynamically while following the loudness",
+        nick = "dynamic"
+    )]
+    Dynamic = 0,
+    #[enum_value(
+        name = "Measure: Pass through unchanged and only measure the loudness",
+        nick = "measure"
+    )]
+    Measure = 1,
+    #[enum_value(
+        name = "Linear: Apply a constant gain based on the measured loudness",
+        nick = "linear"
+    )]
+    Linear = 2,
+}
 
 #[derive(Debug, Clone, Copy)]
 struct Settings {
@@ -44,6 +70,10 @@ struct Settings {
     pub loudness_range_target: f64,
     pub max_true_peak: f64,
     pub offset: f64,
+    pub mode: Mode,
+    pub measured_loudness: f64,
+    pub measured_loudness_range: f64,
+    pub measured_true_peak: f64,
 }
 
 impl Default for Settings {
@@ -53,6 +83,63 @@ impl Default for Settings {
             loudness_range_target: DEFAULT_LOUDNESS_RANGE_TARGET,
             max_true_peak: DEFAULT_MAX_TRUE_PEAK,
             offset: DEFAULT_OFFSET,
+            mode: DEFAULT_MODE,
+            measured_loudness: DEFAULT_MEASURED_LOUDNESS,
+            measured_loudness_range: DEFAULT_MEASURED_LOUDNESS_RANGE,
+            measured_true_peak: DEFAULT_MEASURED_TRUE_PEAK,
+        }
+    }
+}
+
+impl Settings {
+    // Linear scale factor for the linear mode. If no measurements were provided, the loudness
+    // range is too big or the true peak would be above the maximum then this falls back to
+    // dynamic normalization like ffmpeg does.
+    fn linear_gain(&self) -> Option<f64> {
+        if self.mode != Mode::Linear {
+            return None;
+        }
+
+        if self.measured_loudness == DEFAULT_MEASURED_LOUDNESS
+            || self.measured_true_peak == DEFAULT_MEASURED_TRUE_PEAK
+        {
+            gst::warning!(
+                CAT,
+                "No measurements provided, falling back to dynamic normalization"
+            );
+            return None;
+        }
+
+        let gain = self.loudness_target - self.measured_loudness + self.offset;
+        if self.measured_true_peak + gain > self.max_true_peak {
+            gst::warning!(
+                CAT,
+                "True peak would be {} with gain {}, falling back to dynamic normalization",
+                self.measured_true_peak + gain,
+                gain
+            );
+            return None;
+        }
+
+        if self.measured_loudness_range > self.loudness_range_target {
+            gst::warning!(
+                CAT,
+                "Loudness range {} above target {}, falling back to dynamic normalization",
+                self.measured_loudness_range,
+                self.loudness_range_target
+            );
+            return None;
+        }
+
+        Some(f64::powf(10., gain / 20.))
+    }
+
+    // Latency introduced by the element
+    fn latency(&self) -> gst::ClockTime {
+        if self.mode == Mode::Measure || self.linear_gain().is_some() {
+            100.mseconds()
+        } else {
+            3.seconds()
         }
     }
 }
@@ -75,6 +162,7 @@ enum LimiterState {
 
 struct State {
     info: gst_audio::AudioInfo,
+    mode: Mode,
     adapter: gst_base::UniqueAdapter,
 
     // Current amount of sample we consume per iteration: for the first frame 3s, afterwards 100ms
@@ -157,18 +245,30 @@ impl State {
 
         let prev_smp = vec![0.0; info.channels() as usize].into_boxed_slice();
 
-        let current_samples_per_frame = GAIN_LOOKAHEAD;
+        // In measure and linear mode there is no analysis of the first 3s, all frames are
+        // processed linearly right away.
+        let (frame_type, current_samples_per_frame, offset) = match settings.mode {
+            Mode::Measure => (FrameType::Linear, FRAME_SIZE, 1.0),
+            _ => match settings.linear_gain() {
+                Some(gain) => (FrameType::Linear, FRAME_SIZE, gain),
+                None => (
+                    FrameType::First,
+                    GAIN_LOOKAHEAD,
+                    f64::powf(10., settings.offset / 20.),
+                ),
+            },
+        };
 
         let buf_index = 0;
         let prev_buf_index = 0;
         let limiter_buf_index = 0;
         let index = 1;
         let limiter_state = LimiterState::Out;
-        let offset = f64::powf(10., settings.offset / 20.);
         let target_tp = f64::powf(10., settings.max_true_peak / 20.);
 
         State {
             info,
+            mode: settings.mode,
             adapter: gst_base::UniqueAdapter::new(),
             current_samples_per_frame,
             offset,
@@ -189,7 +289,7 @@ impl State {
             limiter_state,
             env_cnt: 0,
             sustain_cnt: None,
-            frame_type: FrameType::First,
+            frame_type,
             above_threshold: false,
             r128_in,
             r128_out,
@@ -294,7 +394,7 @@ impl State {
 
         // If we already output something before then we go into final frame processing, otherwise
         // we drain any data we still have by doing linear processing.
-        if self.current_samples_per_frame == FRAME_SIZE {
+        if self.frame_type == FrameType::Inner {
             self.frame_type = FrameType::Final;
         } else if src.is_empty() {
             // Nothing to drain at all
@@ -320,6 +420,41 @@ impl State {
         Ok(outbuf)
     }
 
+    // Loudness statistics of the input and output, in the same form as ffmpeg so they can be
+    // used for a second pass in linear mode.
+    fn stats(&self) -> Result<gst::Structure, ebur128::Error> {
+        fn true_peak(r128: &ebur128::EbuR128, channels: u32) -> Result<f64, ebur128::Error> {
+            let mut true_peak = 0.0f64;
+            for c in 0..channels {
+                true_peak = true_peak.max(r128.sample_peak(c)?);
+            }
+
+            Ok(20. * f64::log10(true_peak))
+        }
+
+        let channels = self.info.channels();
+        let output_loudness = self.r128_out.loudness_global()?;
+
+        let normalization_type = match (self.mode, self.frame_type) {
+            (Mode::Measure, _) => "none",
+            (_, FrameType::Linear) => "linear",
+            _ => "dynamic",
+        };
+
+        Ok(gst::Structure::builder("loudnorm-stats")
+            .field("input-loudness", self.r128_in.loudness_global()?)
+            .field("input-loudness-range", self.r128_in.loudness_range()?)
+            .field("input-true-peak", true_peak(&self.r128_in, channels)?)
+            .field("input-threshold", self.r128_in.relative_threshold()?)
+            .field("output-loudness", output_loudness)
+            .field("output-loudness-range", self.r128_out.loudness_range()?)
+            .field("output-true-peak", true_peak(&self.r128_out, channels)?)
+            .field("output-threshold", self.r128_out.relative_threshold()?)
+            .field("normalization-type", normalization_type)
+            .field("target-offset", self.target_i - output_loudness)
+            .build())
+    }
+
     fn process_first_frame_is_last(&mut self, imp: &AudioLoudNorm) -> Result<(), gst::FlowError> {
         // Calculated loudness in LUFS
         let global = self
@@ -1615,12 +1750,22 @@ impl AudioLoudNorm {
             EventView::Eos(_) | EventView::Segment(_) => {
                 let mut state = self.state.borrow_mut();
                 let mut outbuf = None;
+                let mut stats = None;
                 if let Some(ref mut state) = &mut *state {
                     outbuf = match state.drain(self) {
                         Ok(outbuf) => Some(outbuf),
                         Err(gst::FlowError::Eos) => None,
                         Err(_) => return false,
                     };
+                    if event.type_() == gst::EventType::Eos {
+                        stats = match state.stats() {
+                            Ok(stats) => Some(stats),
+                            Err(err) => {
+                                gst::warning!(CAT, imp: self, "Failed to get stats: {}", err);
+                                None
+                            }
+                        };
+                    }
                     *state = State::new(&self.settings.lock().unwrap(), state.info.clone());
                 }
                 drop(state);
@@ -1638,6 +1783,14 @@ impl AudioLoudNorm {
                         return false;
                     }
                 }
+
+                if let Some(stats) = stats {
+                    gst::debug!(CAT, imp: self, "Posting message {}", stats);
+                    let msg = gst::message::Element::builder(stats)
+                        .src(&*self.obj())
+                        .build();
+                    let _ = self.obj().post_message(msg);
+                }
             }
             EventView::FlushStop(_) => {
                 // Resetting our whole state
@@ -1665,12 +1818,9 @@ impl AudioLoudNorm {
             QueryViewMut::Latency(q) => {
                 let mut peer_query = gst::query::Latency::new();
                 if self.sinkpad.peer_query(&mut peer_query) {
+                    let latency = self.settings.lock().unwrap().latency();
                     let (live, min_latency, max_latency) = peer_query.result();
-                    q.set(
-                        live,
-                        min_latency + 3.seconds(),
-                        max_latency.opt_add(3.seconds()),
-                    );
+                    q.set(live, min_latency + latency, max_latency.opt_add(latency));
                     true
                 } else {
                     false
@@ -1756,6 +1906,35 @@ impl ObjectImpl for AudioLoudNorm {
                     .default_value(DEFAULT_OFFSET)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("Normalization mode")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("measured-loudness")
+                    .nick("Measured Loudness")
+                    .blurb("Measured integrated loudness of the input in LUFS for linear mode")
+                    .minimum(-99.0)
+                    .maximum(0.0)
+                    .default_value(DEFAULT_MEASURED_LOUDNESS)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("measured-loudness-range")
+                    .nick("Measured Loudness Range")
+                    .blurb("Measured loudness range of the input in LU for linear mode")
+                    .minimum(0.0)
+                    .maximum(99.0)
+                    .default_value(DEFAULT_MEASURED_LOUDNESS_RANGE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("measured-true-peak")
+                    .nick("Measured True Peak")
+                    .blurb("Measured true peak of the input in dbTP for linear mode")
+                    .minimum(-99.0)
+                    .maximum(99.0)
+                    .default_value(DEFAULT_MEASURED_TRUE_PEAK)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -1788,6 +1967,22 @@ impl ObjectImpl for AudioLoudNorm {
                 let mut settings = self.settings.lock().unwrap();
                 settings.offset = value.get().expect("type checked upstream");
             }
+            "mode" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.mode = value.get().expect("type checked upstream");
+            }
+            "measured-loudness" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.measured_loudness = value.get().expect("type checked upstream");
+            }
+            "measured-loudness-range" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.measured_loudness_range = value.get().expect("type checked upstream");
+            }
+            "measured-true-peak" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.measured_true_peak = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -1810,6 +2005,22 @@ impl ObjectImpl for AudioLoudNorm {
                 let settings = self.settings.lock().unwrap();
                 settings.offset.to_value()
             }
+            "mode" => {
+                let settings = self.settings.lock().unwrap();
+                settings.mode.to_value()
+            }
+            "measured-loudness" => {
+                let settings = self.settings.lock().unwrap();
+                settings.measured_loudness.to_value()
+            }
+            "measured-loudness-range" => {
+                let settings = self.settings.lock().unwrap();
+                settings.measured_loudness_range.to_value()
+            }
+            "measured-true-peak" => {
+                let settings = self.settings.lock().unwrap();
+                settings.measured_true_peak.to_value()
+            }
             _ => unimplemented!(),
         }
     }
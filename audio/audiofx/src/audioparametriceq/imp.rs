@@ -0,0 +1,452 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_audio::subclass::prelude::*;
+
+use std::f64::consts::PI;
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+use num_traits::float::Float;
+
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "audioparametriceq",
+        gst::DebugColorFlags::empty(),
+        Some("Multiband Parametric Equalizer"),
+    )
+});
+
+const DEFAULT_BAND_TYPE: BandType = BandType::Peaking;
+const DEFAULT_FREQUENCY: f64 = 1000.0;
+const DEFAULT_GAIN: f64 = 0.0;
+const DEFAULT_Q: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum BandType {
+    Peaking,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+}
+
+impl BandType {
+    fn from_nick(nick: &str) -> Option<Self> {
+        match nick {
+            "peaking" => Some(BandType::Peaking),
+            "low-shelf" => Some(BandType::LowShelf),
+            "high-shelf" => Some(BandType::HighShelf),
+            "low-pass" => Some(BandType::LowPass),
+            "high-pass" => Some(BandType::HighPass),
+            "band-pass" => Some(BandType::BandPass),
+            "notch" => Some(BandType::Notch),
+            _ => None,
+        }
+    }
+
+    fn nick(self) -> &'static str {
+        match self {
+            BandType::Peaking => "peaking",
+            BandType::LowShelf => "low-shelf",
+            BandType::HighShelf => "high-shelf",
+            BandType::LowPass => "low-pass",
+            BandType::HighPass => "high-pass",
+            BandType::BandPass => "band-pass",
+            BandType::Notch => "notch",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Band {
+    band_type: BandType,
+    // Center or cutoff frequency in Hz
+    frequency: f64,
+    // Gain in dB, only used by the peaking and shelf filters
+    gain: f64,
+    q: f64,
+}
+
+impl Band {
+    fn from_structure(s: &gst::StructureRef) -> Result<Self, String> {
+        if s.name() != "band" {
+            return Err(format!("Unexpected structure name {}", s.name()));
+        }
+
+        let band_type = match s.get_optional::<&str>("type") {
+            Ok(Some(nick)) => {
+                BandType::from_nick(nick).ok_or_else(|| format!("Unknown band type {nick}"))?
+            }
+            Ok(None) => DEFAULT_BAND_TYPE,
+            Err(err) => return Err(format!("Invalid band type: {err}")),
+        };
+
+        let get_f64 = |name: &str, default: f64| -> Result<f64, String> {
+            match s.value(name) {
+                Ok(value) => value
+                    .get::<f64>()
+                    .or_else(|_| value.get::<i32>().map(f64::from))
+                    .map_err(|err| format!("Invalid {name}: {err}")),
+                Err(_) => Ok(default),
+            }
+        };
+
+        let frequency = get_f64("frequency", DEFAULT_FREQUENCY)?;
+        if frequency <= 0.0 {
+            return Err(format!("Invalid frequency {frequency}"));
+        }
+
+        let gain = get_f64("gain", DEFAULT_GAIN)?;
+        if !(-60.0..=60.0).contains(&gain) {
+            return Err(format!("Invalid gain {gain}"));
+        }
+
+        let q = get_f64("q", DEFAULT_Q)?;
+        if q <= 0.0 {
+            return Err(format!("Invalid q {q}"));
+        }
+
+        Ok(Band {
+            band_type,
+            frequency,
+            gain,
+            q,
+        })
+    }
+
+    fn to_structure(self) -> gst::Structure {
+        gst::Structure::builder("band")
+            .field("type", self.band_type.nick())
+            .field("frequency", self.frequency)
+            .field("gain", self.gain)
+            .field("q", self.q)
+            .build()
+    }
+
+    // Biquad coefficients from the Audio EQ Cookbook by Robert Bristow-Johnson, normalized by a0
+    fn coefficients(&self, rate: u32) -> Biquad {
+        let rate = rate as f64;
+        // Keep the frequency below Nyquist
+        let frequency = self.frequency.min(0.49 * rate);
+
+        let w0 = 2.0 * PI * frequency / rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * self.q);
+        let a = f64::powf(10.0, self.gain / 40.0);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+        let (b0, b1, b2, a0, a1, a2) = match self.band_type {
+            BandType::Peaking => (
+                1.0 + alpha * a,
+                -2.0 * cos,
+                1.0 - alpha * a,
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            BandType::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha,
+            ),
+            BandType::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a_alpha),
+                (a + 1.0) - (a - 1.0) * cos + sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - sqrt_a_alpha,
+            ),
+            BandType::LowPass => (
+                (1.0 - cos) / 2.0,
+                1.0 - cos,
+                (1.0 - cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            BandType::HighPass => (
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos,
+                1.0 - alpha,
+            ),
+            BandType::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BandType::Notch => (1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+        };
+
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Settings {
+    bands: Vec<Band>,
+}
+
+struct State {
+    info: gst_audio::AudioInfo,
+    // Bands the filters were configured for
+    bands: Vec<Band>,
+    filters: Vec<Biquad>,
+    // Transposed direct form II state per band and channel
+    history: Vec<[f64; 2]>,
+}
+
+impl State {
+    fn new(info: &gst_audio::AudioInfo) -> Self {
+        State {
+            info: info.clone(),
+            bands: Vec::new(),
+            filters: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    fn configure(&mut self, bands: &[Band]) {
+        let channels = self.info.channels() as usize;
+
+        // Keep the filter history if only the parameters of the bands changed to avoid clicks
+        if bands.len() != self.bands.len() {
+            self.history = vec![[0.0; 2]; bands.len() * channels];
+        }
+
+        self.bands = bands.to_vec();
+        self.filters = bands
+            .iter()
+            .map(|band| band.coefficients(self.info.rate()))
+            .collect();
+    }
+
+    fn reset(&mut self) {
+        for h in &mut self.history {
+            *h = [0.0; 2];
+        }
+    }
+
+    fn process<F: Float + ToPrimitive + FromPrimitive>(&mut self, data: &mut [F]) {
+        let channels = self.info.channels() as usize;
+
+        for frame in data.chunks_exact_mut(channels) {
+            for (c, sample) in frame.iter_mut().enumerate() {
+                let mut x = sample.to_f64().unwrap();
+
+                for (filter, history) in self
+                    .filters
+                    .iter()
+                    .zip(self.history.iter_mut().skip(c).step_by(channels))
+                {
+                    let y = filter.b0 * x + history[0];
+                    history[0] = filter.b1 * x - filter.a1 * y + history[1];
+                    history[1] = filter.b2 * x - filter.a2 * y;
+                    x = y;
+                }
+
+                *sample = F::from_f64(x).unwrap();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AudioParametricEq {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AudioParametricEq {
+    const NAME: &'static str = "GstAudioParametricEq";
+    type Type = super::AudioParametricEq;
+    type ParentType = gst_audio::AudioFilter;
+}
+
+impl ObjectImpl for AudioParametricEq {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![gst::ParamSpecArray::builder("bands")
+                .nick("Bands")
+                .blurb("Equalizer bands as band structures with type (peaking, low-shelf, high-shelf, low-pass, high-pass, band-pass, notch), frequency in Hz, gain in dB and q")
+                .element_spec(&glib::ParamSpecBoxed::builder::<gst::Structure>("band").build())
+                .mutable_playing()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "bands" => {
+                let mut bands = Vec::new();
+                for value in value
+                    .get::<gst::ArrayRef>()
+                    .expect("type checked upstream")
+                    .as_slice()
+                {
+                    let s = value
+                        .get::<gst::Structure>()
+                        .expect("type checked upstream");
+                    match Band::from_structure(&s) {
+                        Ok(band) => bands.push(band),
+                        Err(err) => {
+                            gst::warning!(CAT, imp: self, "Ignoring band {s}: {err}");
+                        }
+                    }
+                }
+
+                let mut settings = self.settings.lock().unwrap();
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing bands from {:?} to {:?}",
+                    settings.bands,
+                    bands
+                );
+                settings.bands = bands;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "bands" => {
+                gst::Array::new(settings.bands.iter().map(|band| band.to_structure())).to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for AudioParametricEq {}
+
+impl ElementImpl for AudioParametricEq {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Parametric equalizer",
+                "Filter/Effect/Audio",
+                "Multiband parametric equalizer with runtime configurable bands",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+}
+
+impl BaseTransformImpl for AudioParametricEq {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn transform_ip(&self, buf: &mut gst::BufferRef) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
+
+        {
+            let settings = self.settings.lock().unwrap();
+            if state.bands != settings.bands {
+                gst::debug!(CAT, imp: self, "Configuring bands {:?}", settings.bands);
+                state.configure(&settings.bands);
+            }
+        }
+
+        if state.filters.is_empty() {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        if buf.flags().contains(gst::BufferFlags::DISCONT) {
+            state.reset();
+        }
+
+        let mut map = buf.map_writable().map_err(|_| gst::FlowError::Error)?;
+
+        match state.info.format() {
+            gst_audio::AUDIO_FORMAT_F64 => {
+                let data = map.as_mut_slice_of::<f64>().unwrap();
+                state.process(data);
+            }
+            gst_audio::AUDIO_FORMAT_F32 => {
+                let data = map.as_mut_slice_of::<f32>().unwrap();
+                state.process(data);
+            }
+            _ => return Err(gst::FlowError::NotNegotiated),
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::FlushStop(_) = event.view() {
+            if let Some(state) = self.state.lock().unwrap().as_mut() {
+                state.reset();
+            }
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        // Drop state
+        let _ = self.state.lock().unwrap().take();
+
+        Ok(())
+    }
+}
+
+impl AudioFilterImpl for AudioParametricEq {
+    fn allowed_caps() -> &'static gst::Caps {
+        static CAPS: Lazy<gst::Caps> = Lazy::new(|| {
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format_list([gst_audio::AUDIO_FORMAT_F32, gst_audio::AUDIO_FORMAT_F64])
+                .build()
+        });
+
+        &CAPS
+    }
+
+    fn setup(&self, info: &gst_audio::AudioInfo) -> Result<(), gst::LoggableError> {
+        gst::debug!(CAT, imp: self, "Set caps to {:?}", info);
+
+        *self.state.lock().unwrap() = Some(State::new(info));
+
+        Ok(())
+    }
+}
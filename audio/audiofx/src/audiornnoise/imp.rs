@@ -7,7 +7,7 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use gst::glib;
 use gst::prelude::*;
@@ -17,7 +17,7 @@ use gst_base::prelude::*;
 use gst_base::subclass::base_transform::BaseTransformImplExt;
 use gst_base::subclass::base_transform::GenerateOutputSuccess;
 
-use nnnoiseless::DenoiseState;
+use nnnoiseless::{DenoiseState, RnnModel};
 
 use byte_slice_cast::*;
 
@@ -34,17 +34,25 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 });
 
 const DEFAULT_VOICE_ACTIVITY_THRESHOLD: f32 = 0.0;
+const DEFAULT_POST_MESSAGES: bool = false;
 const FRAME_SIZE: usize = DenoiseState::FRAME_SIZE;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct Settings {
     vad_threshold: f32,
+    post_messages: bool,
+    model_location: Option<String>,
+    // Contents of the model file, validated when loading. `None` for the built-in model
+    model: Option<Arc<[u8]>>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             vad_threshold: DEFAULT_VOICE_ACTIVITY_THRESHOLD,
+            post_messages: DEFAULT_POST_MESSAGES,
+            model_location: None,
+            model: None,
         }
     }
 }
@@ -55,9 +63,27 @@ struct ChannelDenoiser {
     out_chunk: Box<[f32; FRAME_SIZE]>,
 }
 
+impl ChannelDenoiser {
+    fn new(model: Option<&[u8]>) -> Self {
+        let denoiser = match model {
+            // The model was already validated when loading it
+            Some(model) => DenoiseState::with_model(RnnModel::from_bytes(model).unwrap()),
+            None => DenoiseState::new(),
+        };
+
+        ChannelDenoiser {
+            denoiser,
+            frame_chunk: Box::new([0.0; FRAME_SIZE]),
+            out_chunk: Box::new([0.0; FRAME_SIZE]),
+        }
+    }
+}
+
 struct State {
     in_info: gst_audio::AudioInfo,
     denoisers: Vec<ChannelDenoiser>,
+    // Model the denoisers were created with
+    model: Option<Arc<[u8]>>,
     adapter: gst_base::UniqueAdapter,
 }
 
@@ -99,6 +125,23 @@ impl State {
     fn needs_more_data(&self) -> bool {
         self.adapter.available() < (FRAME_SIZE * self.in_info.bpf() as usize)
     }
+
+    fn update_model(&mut self, model: &Option<Arc<[u8]>>) -> bool {
+        let changed = match (&self.model, model) {
+            (None, None) => false,
+            (Some(a), Some(b)) => !Arc::ptr_eq(a, b),
+            _ => true,
+        };
+
+        if changed {
+            self.model = model.clone();
+            for denoiser in &mut self.denoisers {
+                *denoiser = ChannelDenoiser::new(self.model.as_deref());
+            }
+        }
+
+        changed
+    }
 }
 
 impl AudioRNNoise {
@@ -111,7 +154,8 @@ impl AudioRNNoise {
             return Ok(gst::FlowSuccess::Ok);
         }
 
-        let settings = *self.settings.lock().unwrap();
+        let settings = self.settings.lock().unwrap().clone();
+        let mut messages = vec![];
         let mut buffer = gst::Buffer::with_size(available).map_err(|e| {
             gst::error!(CAT, imp: self, "Failed to allocate buffer at EOS {:?}", e);
             gst::FlowError::Flushing
@@ -132,23 +176,34 @@ impl AudioRNNoise {
             let (level, has_voice) = {
                 let mut out_map = buffer.map_writable().map_err(|_| gst::FlowError::Error)?;
                 let out_data = out_map.as_mut_slice_of::<f32>().unwrap();
-                self.process(state, &settings, in_data, out_data)
+                self.process(state, &settings, pts, in_data, out_data, &mut messages)
             };
 
             gst_audio::AudioLevelMeta::add(buffer, level, has_voice);
         }
 
+        drop(state_lock);
+        self.post_messages(messages);
+
         self.obj().src_pad().push(buffer)
     }
 
-    fn generate_output(&self, state: &mut State) -> Result<GenerateOutputSuccess, gst::FlowError> {
+    fn generate_output(
+        &self,
+        state: &mut State,
+        messages: &mut Vec<gst::Message>,
+    ) -> Result<GenerateOutputSuccess, gst::FlowError> {
         let available = state.adapter.available();
         let bpf = state.in_info.bpf() as usize;
         let output_size = available - (available % (FRAME_SIZE * bpf));
         let duration = state.buffer_duration(output_size as _);
         let pts = state.current_pts();
 
-        let settings = *self.settings.lock().unwrap();
+        let settings = self.settings.lock().unwrap().clone();
+        if state.update_model(&settings.model) {
+            gst::debug!(CAT, imp: self, "Switched to new model");
+        }
+
         let mut buffer = gst::Buffer::with_size(output_size).map_err(|_| gst::FlowError::Error)?;
 
         {
@@ -166,7 +221,7 @@ impl AudioRNNoise {
             let (level, has_voice) = {
                 let mut out_map = buffer.map_writable().map_err(|_| gst::FlowError::Error)?;
                 let out_data = out_map.as_mut_slice_of::<f32>().unwrap();
-                self.process(state, &settings, in_data, out_data)
+                self.process(state, &settings, pts, in_data, out_data, messages)
             };
 
             gst_audio::AudioLevelMeta::add(buffer, level, has_voice);
@@ -175,18 +230,67 @@ impl AudioRNNoise {
         Ok(GenerateOutputSuccess::Buffer(buffer))
     }
 
+    fn load_model(location: &str) -> Result<Arc<[u8]>, anyhow::Error> {
+        let data = std::fs::read(location)
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", location, err))?;
+
+        if RnnModel::from_bytes(&data).is_none() {
+            anyhow::bail!("{} is not a valid RNNoise model", location);
+        }
+
+        Ok(data.into())
+    }
+
+    fn post_messages(&self, messages: Vec<gst::Message>) {
+        for msg in messages {
+            let _ = self.obj().post_message(msg);
+        }
+    }
+
+    fn vad_message(
+        &self,
+        segment: Option<&gst::FormattedSegment<gst::ClockTime>>,
+        timestamp: Option<gst::ClockTime>,
+        duration: Option<gst::ClockTime>,
+        vad: f32,
+    ) -> gst::Message {
+        let running_time = segment.and_then(|s| s.to_running_time(timestamp));
+        let stream_time = segment.and_then(|s| s.to_stream_time(timestamp));
+
+        let s = gst::Structure::builder("audiornnoise")
+            .field("timestamp", timestamp)
+            .field("running-time", running_time)
+            .field("stream-time", stream_time)
+            .field("duration", duration)
+            .field("voice-activity", vad as f64)
+            .build();
+
+        gst::message::Element::builder(s).src(&*self.obj()).build()
+    }
+
     fn process(
         &self,
         state: &mut State,
         settings: &Settings,
+        pts: Option<gst::ClockTime>,
         input_plane: &[f32],
         output_plane: &mut [f32],
+        messages: &mut Vec<gst::Message>,
     ) -> (u8, bool) {
         let channels = state.in_info.channels() as usize;
         let size = FRAME_SIZE * channels;
         let mut has_voice = false;
 
-        for (out_frame, in_frame) in output_plane.chunks_mut(size).zip(input_plane.chunks(size)) {
+        let segment = settings
+            .post_messages
+            .then(|| self.obj().segment().downcast::<gst::ClockTime>().ok())
+            .flatten();
+
+        for (chunk, (out_frame, in_frame)) in output_plane
+            .chunks_mut(size)
+            .zip(input_plane.chunks(size))
+            .enumerate()
+        {
             for (index, item) in in_frame.iter().enumerate() {
                 let channel_index = index % channels;
                 let channel_denoiser = &mut state.denoisers[channel_index];
@@ -215,6 +319,14 @@ impl AudioRNNoise {
             }
 
             gst::trace!(CAT, imp: self, "Voice activity: {}", vad);
+            if settings.post_messages {
+                let offset = state.samples_to_time((chunk * FRAME_SIZE) as u64);
+                let duration = state.samples_to_time((in_frame.len() / channels) as u64);
+                let timestamp = pts.opt_add(offset);
+
+                messages.push(self.vad_message(segment.as_ref(), timestamp, duration, vad));
+            }
+
             if vad < settings.vad_threshold {
                 out_frame.fill(0.0);
             } else {
@@ -257,14 +369,29 @@ impl ObjectSubclass for AudioRNNoise {
 impl ObjectImpl for AudioRNNoise {
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-            vec![glib::ParamSpecFloat::builder("voice-activity-threshold")
-                .nick("Voice activity threshold")
-                .blurb("Threshold of the voice activity detector below which to mute the output")
-                .minimum(0.0)
-                .maximum(1.0)
-                .default_value(DEFAULT_VOICE_ACTIVITY_THRESHOLD)
-                .mutable_playing()
-                .build()]
+            vec![
+                glib::ParamSpecFloat::builder("voice-activity-threshold")
+                    .nick("Voice activity threshold")
+                    .blurb(
+                        "Threshold of the voice activity detector below which to mute the output",
+                    )
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_VOICE_ACTIVITY_THRESHOLD)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("post-messages")
+                    .nick("Post Messages")
+                    .blurb("Whether to post a message with the voice activity for each chunk")
+                    .default_value(DEFAULT_POST_MESSAGES)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("model-location")
+                    .nick("Model Location")
+                    .blurb("Location of an RNNoise model file to use instead of the built-in one")
+                    .mutable_playing()
+                    .build(),
+            ]
         });
 
         PROPERTIES.as_ref()
@@ -276,6 +403,46 @@ impl ObjectImpl for AudioRNNoise {
                 let mut settings = self.settings.lock().unwrap();
                 settings.vad_threshold = value.get().expect("type checked upstream");
             }
+            "post-messages" => {
+                let mut settings = self.settings.lock().unwrap();
+                let post_messages = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing post-messages from {} to {}",
+                    settings.post_messages,
+                    post_messages
+                );
+                settings.post_messages = post_messages;
+            }
+            "model-location" => {
+                let location = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+
+                let model = match location.as_deref().map(Self::load_model).transpose() {
+                    Ok(model) => model,
+                    Err(err) => {
+                        gst::element_imp_warning!(
+                            self,
+                            gst::ResourceError::OpenRead,
+                            ["Failed to load model: {}", err]
+                        );
+                        return;
+                    }
+                };
+
+                let mut settings = self.settings.lock().unwrap();
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing model-location from {:?} to {:?}",
+                    settings.model_location,
+                    location
+                );
+                settings.model_location = location;
+                settings.model = model;
+            }
             _ => unimplemented!(),
         }
     }
@@ -286,6 +453,14 @@ impl ObjectImpl for AudioRNNoise {
                 let settings = self.settings.lock().unwrap();
                 settings.vad_threshold.to_value()
             }
+            "post-messages" => {
+                let settings = self.settings.lock().unwrap();
+                settings.post_messages.to_value()
+            }
+            "model-location" => {
+                let settings = self.settings.lock().unwrap();
+                settings.model_location.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -335,7 +510,14 @@ impl BaseTransformImpl for AudioRNNoise {
 
             state.adapter.push(buffer);
             if !state.needs_more_data() {
-                return self.generate_output(state);
+                let mut messages = vec![];
+                let res = self.generate_output(state, &mut messages);
+
+                // Release the state while posting the messages
+                drop(state_guard);
+                self.post_messages(messages);
+
+                return res;
             }
         }
         Ok(GenerateOutputSuccess::NoOutput)
@@ -408,19 +590,16 @@ impl AudioFilterImpl for AudioRNNoise {
 
         gst::debug!(CAT, imp: self, "Set caps to {:?}", info);
 
-        let mut denoisers = vec![];
-        for _i in 0..info.channels() {
-            denoisers.push(ChannelDenoiser {
-                denoiser: DenoiseState::new(),
-                frame_chunk: Box::new([0.0; FRAME_SIZE]),
-                out_chunk: Box::new([0.0; FRAME_SIZE]),
-            })
-        }
+        let model = self.settings.lock().unwrap().model.clone();
+        let denoisers = (0..info.channels())
+            .map(|_| ChannelDenoiser::new(model.as_deref()))
+            .collect();
 
         let mut state_lock = self.state.borrow_mut();
         *state_lock = Some(State {
             in_info: info.clone(),
             denoisers,
+            model,
             adapter: gst_base::UniqueAdapter::new(),
         });
 
@@ -0,0 +1,317 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_audio::subclass::prelude::*;
+
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+use num_traits::float::Float;
+
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "audiostereowidth",
+        gst::DebugColorFlags::empty(),
+        Some("Stereo Width / Mid-Side Processor"),
+    )
+});
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstAudioStereoWidthMode")]
+pub(crate) enum Mode {
+    #[enum_value(
+        name = "Width: Scale the side signal of left/right channel pairs",
+        nick = "width"
+    )]
+    Width = 0,
+    #[enum_value(
+        name = "Encode: Convert left/right channel pairs to mid/side",
+        nick = "encode"
+    )]
+    Encode = 1,
+    #[enum_value(
+        name = "Decode: Convert mid/side channel pairs to left/right",
+        nick = "decode"
+    )]
+    Decode = 2,
+}
+
+const DEFAULT_MODE: Mode = Mode::Width;
+const DEFAULT_WIDTH: f64 = 1.0;
+
+// Channel positions that are processed as left/right pair, all other channels are passed through
+const PAIRS: [(
+    gst_audio::AudioChannelPosition,
+    gst_audio::AudioChannelPosition,
+); 6] = [
+    (
+        gst_audio::AudioChannelPosition::FrontLeft,
+        gst_audio::AudioChannelPosition::FrontRight,
+    ),
+    (
+        gst_audio::AudioChannelPosition::FrontLeftOfCenter,
+        gst_audio::AudioChannelPosition::FrontRightOfCenter,
+    ),
+    (
+        gst_audio::AudioChannelPosition::SideLeft,
+        gst_audio::AudioChannelPosition::SideRight,
+    ),
+    (
+        gst_audio::AudioChannelPosition::RearLeft,
+        gst_audio::AudioChannelPosition::RearRight,
+    ),
+    (
+        gst_audio::AudioChannelPosition::WideLeft,
+        gst_audio::AudioChannelPosition::WideRight,
+    ),
+    (
+        gst_audio::AudioChannelPosition::SurroundLeft,
+        gst_audio::AudioChannelPosition::SurroundRight,
+    ),
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    mode: Mode,
+    width: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: DEFAULT_MODE,
+            width: DEFAULT_WIDTH,
+        }
+    }
+}
+
+struct State {
+    info: gst_audio::AudioInfo,
+    // Indices of the left and right channel of each processed channel pair
+    pairs: Vec<(usize, usize)>,
+}
+
+impl State {
+    fn process<F: Float + ToPrimitive + FromPrimitive>(&self, data: &mut [F], settings: Settings) {
+        let channels = self.info.channels() as usize;
+
+        for frame in data.chunks_exact_mut(channels) {
+            for &(l, r) in &self.pairs {
+                let a = frame[l].to_f64().unwrap();
+                let b = frame[r].to_f64().unwrap();
+
+                let (a, b) = match settings.mode {
+                    Mode::Width => {
+                        let mid = (a + b) / 2.0;
+                        let side = settings.width * (a - b) / 2.0;
+                        (mid + side, mid - side)
+                    }
+                    // Mid is stored in the left and side in the right channel
+                    Mode::Encode => ((a + b) / 2.0, settings.width * (a - b) / 2.0),
+                    Mode::Decode => {
+                        let side = settings.width * b;
+                        (a + side, a - side)
+                    }
+                };
+
+                frame[l] = F::from_f64(a).unwrap();
+                frame[r] = F::from_f64(b).unwrap();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AudioStereoWidth {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AudioStereoWidth {
+    const NAME: &'static str = "GstAudioStereoWidth";
+    type Type = super::AudioStereoWidth;
+    type ParentType = gst_audio::AudioFilter;
+}
+
+impl ObjectImpl for AudioStereoWidth {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("Stereo width control, mid-side encoding or mid-side decoding")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("width")
+                    .nick("Width")
+                    .blurb(
+                        "Factor applied to the side signal (0 = mono, 1 = unchanged, >1 = wider)",
+                    )
+                    .minimum(0.0)
+                    .maximum(4.0)
+                    .default_value(DEFAULT_WIDTH)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "mode" => {
+                let mode = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing mode from {:?} to {:?}",
+                    settings.mode,
+                    mode
+                );
+                settings.mode = mode;
+            }
+            "width" => {
+                let width = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing width from {} to {}",
+                    settings.width,
+                    width
+                );
+                settings.width = width;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "mode" => settings.mode.to_value(),
+            "width" => settings.width.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for AudioStereoWidth {}
+
+impl ElementImpl for AudioStereoWidth {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Stereo width",
+                "Filter/Effect/Audio",
+                "Mid-side encoding/decoding and stereo width control",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+}
+
+impl BaseTransformImpl for AudioStereoWidth {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn transform_ip(&self, buf: &mut gst::BufferRef) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+
+        let state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_ref().ok_or(gst::FlowError::NotNegotiated)?;
+
+        // Nothing to do for streams without left/right pairs or if the width is unchanged
+        if state.pairs.is_empty() || (settings.mode == Mode::Width && settings.width == 1.0) {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let mut map = buf.map_writable().map_err(|_| gst::FlowError::Error)?;
+
+        match state.info.format() {
+            gst_audio::AUDIO_FORMAT_F64 => {
+                let data = map.as_mut_slice_of::<f64>().unwrap();
+                state.process(data, settings);
+            }
+            gst_audio::AUDIO_FORMAT_F32 => {
+                let data = map.as_mut_slice_of::<f32>().unwrap();
+                state.process(data, settings);
+            }
+            _ => return Err(gst::FlowError::NotNegotiated),
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        // Drop state
+        let _ = self.state.lock().unwrap().take();
+
+        Ok(())
+    }
+}
+
+impl AudioFilterImpl for AudioStereoWidth {
+    fn allowed_caps() -> &'static gst::Caps {
+        static CAPS: Lazy<gst::Caps> = Lazy::new(|| {
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format_list([gst_audio::AUDIO_FORMAT_F32, gst_audio::AUDIO_FORMAT_F64])
+                .build()
+        });
+
+        &CAPS
+    }
+
+    fn setup(&self, info: &gst_audio::AudioInfo) -> Result<(), gst::LoggableError> {
+        gst::debug!(CAT, imp: self, "Set caps to {:?}", info);
+
+        let pairs = match info.positions() {
+            Some(positions) => PAIRS
+                .iter()
+                .filter_map(|(left, right)| {
+                    let l = positions.iter().position(|p| p == left)?;
+                    let r = positions.iter().position(|p| p == right)?;
+                    Some((l, r))
+                })
+                .collect::<Vec<_>>(),
+            // Without channel positions only plain stereo can be handled
+            None if info.channels() == 2 => vec![(0, 1)],
+            None => Vec::new(),
+        };
+
+        if pairs.is_empty() {
+            gst::warning!(
+                CAT,
+                imp: self,
+                "No left/right channel pairs in {} channels, passing through",
+                info.channels()
+            );
+        } else {
+            gst::debug!(CAT, imp: self, "Processing channel pairs {:?}", pairs);
+        }
+
+        *self.state.lock().unwrap() = Some(State {
+            info: info.clone(),
+            pairs,
+        });
+
+        Ok(())
+    }
+}
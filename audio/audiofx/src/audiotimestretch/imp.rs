@@ -0,0 +1,576 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+use once_cell::sync::Lazy;
+
+use atomic_refcell::AtomicRefCell;
+
+use super::stretch::{Resampler, Wsola};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "audiotimestretch",
+        gst::DebugColorFlags::empty(),
+        Some("Audio Time Stretch"),
+    )
+});
+
+const DEFAULT_RATE: f64 = 1.0;
+const DEFAULT_PITCH: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    rate: f64,
+    pitch: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            rate: DEFAULT_RATE,
+            pitch: DEFAULT_PITCH,
+        }
+    }
+}
+
+struct State {
+    info: gst_audio::AudioInfo,
+    wsola: Wsola,
+    resampler: Resampler,
+
+    // Whether the data is currently stretched or passed through
+    processing: bool,
+    // Output timestamp of the first frame since processing started
+    base_pts: Option<gst::ClockTime>,
+    // Number of frames output since processing started
+    out_frames: u64,
+    discont: bool,
+}
+
+impl State {
+    fn new(info: gst_audio::AudioInfo) -> Self {
+        State {
+            wsola: Wsola::new(info.rate(), info.channels()),
+            resampler: Resampler::new(info.channels()),
+            info,
+            processing: false,
+            base_pts: None,
+            out_frames: 0,
+            discont: true,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.wsola.reset();
+        self.resampler.reset();
+        self.processing = false;
+        self.base_pts = None;
+        self.out_frames = 0;
+        self.discont = true;
+    }
+
+    // Stretching by tempo / pitch and then resampling by pitch changes the duration by
+    // 1 / tempo and the pitch by pitch.
+    fn process(&mut self, tempo: f64, pitch: f64, src: &[f32]) -> Vec<f32> {
+        let mut stretched = Vec::new();
+        self.wsola.push(src);
+        self.wsola.process(tempo / pitch, &mut stretched);
+
+        let mut out = Vec::with_capacity((stretched.len() as f64 / pitch) as usize + 1);
+        self.resampler.process(pitch, &stretched, &mut out);
+
+        out
+    }
+
+    fn drain(&mut self, tempo: f64, pitch: f64) -> Vec<f32> {
+        let mut stretched = Vec::new();
+        self.wsola.drain(tempo / pitch, &mut stretched);
+
+        let mut out = Vec::with_capacity((stretched.len() as f64 / pitch) as usize + 1);
+        self.resampler.process(pitch, &stretched, &mut out);
+        self.resampler.drain(pitch, &mut out);
+
+        out
+    }
+
+    fn output_buffer(&mut self, data: &[f32]) -> Result<Option<gst::Buffer>, gst::FlowError> {
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        let frames = (data.len() / self.info.channels() as usize) as u64;
+        let rate = self.info.rate() as u64;
+
+        let pts = self.base_pts.map(|base_pts| {
+            base_pts
+                + self
+                    .out_frames
+                    .mul_div_floor(*gst::ClockTime::SECOND, rate)
+                    .unwrap()
+                    .nseconds()
+        });
+        let end_pts = self.base_pts.map(|base_pts| {
+            base_pts
+                + (self.out_frames + frames)
+                    .mul_div_floor(*gst::ClockTime::SECOND, rate)
+                    .unwrap()
+                    .nseconds()
+        });
+        self.out_frames += frames;
+
+        let mut outbuf =
+            gst::Buffer::with_size(data.len() * 4).map_err(|_| gst::FlowError::Error)?;
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            outbuf.set_pts(pts);
+            outbuf.set_duration(end_pts.opt_checked_sub(pts).ok().flatten());
+            if self.discont {
+                outbuf.set_flags(gst::BufferFlags::DISCONT);
+                self.discont = false;
+            }
+
+            let mut map = outbuf.map_writable().map_err(|_| gst::FlowError::Error)?;
+            map.as_mut_slice_of::<f32>()
+                .map_err(|_| gst::FlowError::Error)?
+                .copy_from_slice(data);
+        }
+
+        Ok(Some(outbuf))
+    }
+}
+
+pub struct AudioTimeStretch {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    segment: Mutex<gst::FormattedSegment<gst::ClockTime>>,
+    state: AtomicRefCell<Option<State>>,
+}
+
+// Maps an input timestamp to the output timeline in which the segment rate was applied
+fn map_time(
+    segment: &gst::FormattedSegment<gst::ClockTime>,
+    tempo: f64,
+    time: gst::ClockTime,
+) -> gst::ClockTime {
+    let start = segment.start().unwrap_or(gst::ClockTime::ZERO);
+    match time.checked_sub(start) {
+        Some(diff) => {
+            start + gst::ClockTime::from_nseconds((diff.nseconds() as f64 / tempo) as u64)
+        }
+        None => start,
+    }
+}
+
+impl AudioTimeStretch {
+    fn sink_chain(
+        &self,
+        _pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, imp: self, "Handling buffer {:?}", buffer);
+
+        let settings = *self.settings.lock().unwrap();
+        let segment = self.segment.lock().unwrap().clone();
+
+        let mut state_guard = self.state.borrow_mut();
+        let state = match *state_guard {
+            None => {
+                gst::error!(CAT, imp: self, "Not negotiated yet");
+                return Err(gst::FlowError::NotNegotiated);
+            }
+            Some(ref mut state) => state,
+        };
+
+        let tempo = segment.rate() * settings.rate;
+        let pitch = settings.pitch;
+
+        let mut outbufs = vec![];
+        if buffer.flags().contains(gst::BufferFlags::DISCONT) && state.processing {
+            gst::debug!(CAT, imp: self, "Draining on discontinuity");
+            let data = state.drain(tempo, pitch);
+            outbufs.extend(state.output_buffer(&data)?);
+            state.reset();
+        }
+
+        // Reverse playback is not supported and passed through as is
+        let passthrough = segment.rate() < 0.0 || (tempo == 1.0 && pitch == 1.0);
+
+        if passthrough {
+            if state.processing {
+                gst::debug!(CAT, imp: self, "Draining before switching to passthrough");
+                let data = state.drain(tempo, pitch);
+                outbufs.extend(state.output_buffer(&data)?);
+                state.reset();
+            }
+
+            outbufs.push(buffer);
+        } else {
+            if !state.processing {
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Starting processing with tempo {} and pitch {}",
+                    tempo,
+                    pitch
+                );
+                state.processing = true;
+                state.base_pts = buffer.pts().map(|pts| map_time(&segment, tempo, pts));
+                state.out_frames = 0;
+            }
+
+            let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+            let src = map
+                .as_slice_of::<f32>()
+                .map_err(|_| gst::FlowError::Error)?;
+
+            let data = state.process(tempo, pitch, src);
+            outbufs.extend(state.output_buffer(&data)?);
+        }
+        drop(state_guard);
+
+        for buffer in outbufs {
+            gst::log!(CAT, imp: self, "Outputting buffer {:?}", buffer);
+            self.srcpad.push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    // Outputs everything that is still queued
+    fn drain(&self) -> Result<(), gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+        let segment = self.segment.lock().unwrap().clone();
+
+        let mut state = self.state.borrow_mut();
+        let mut outbuf = None;
+        if let Some(ref mut state) = &mut *state {
+            if state.processing {
+                let data = state.drain(segment.rate() * settings.rate, settings.pitch);
+                outbuf = state.output_buffer(&data)?;
+            }
+            state.reset();
+        }
+        drop(state);
+
+        if let Some(outbuf) = outbuf {
+            gst::log!(CAT, imp: self, "Outputting buffer {:?}", outbuf);
+            self.srcpad.push(outbuf)?;
+        }
+
+        Ok(())
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(c) => {
+                let caps = c.caps();
+                gst::info!(CAT, obj: pad, "Got caps {:?}", caps);
+
+                let info = match gst_audio::AudioInfo::from_caps(caps) {
+                    Ok(info) => info,
+                    Err(_) => {
+                        gst::error!(CAT, obj: pad, "Failed to parse caps");
+                        return false;
+                    }
+                };
+
+                if let Err(err) = self.drain() {
+                    gst::error!(CAT, imp: self, "Failed to push drained data: {}", err);
+                    return false;
+                }
+
+                *self.state.borrow_mut() = Some(State::new(info));
+            }
+            EventView::Segment(e) => {
+                let segment = match e.segment().downcast_ref::<gst::ClockTime>() {
+                    Some(segment) => segment.clone(),
+                    None => {
+                        gst::error!(CAT, obj: pad, "Only time segments are supported");
+                        return false;
+                    }
+                };
+
+                if let Err(err) = self.drain() {
+                    gst::error!(CAT, imp: self, "Failed to push drained data: {}", err);
+                    return false;
+                }
+
+                // The rate is applied to the data here, so downstream gets a segment with
+                // rate 1.0 and the rate moved to the applied rate
+                let mut out_segment = segment.clone();
+                if segment.rate() > 0.0 {
+                    let tempo = segment.rate() * self.settings.lock().unwrap().rate;
+
+                    out_segment.set_rate(1.0);
+                    out_segment.set_applied_rate(segment.applied_rate() * tempo);
+                    out_segment
+                        .set_stop(segment.stop().map(|stop| map_time(&segment, tempo, stop)));
+                    out_segment.set_position(
+                        segment
+                            .position()
+                            .map(|position| map_time(&segment, tempo, position)),
+                    );
+                }
+
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Got segment {:?}, outputting {:?}",
+                    segment,
+                    out_segment
+                );
+
+                *self.segment.lock().unwrap() = segment;
+
+                return self.srcpad.push_event(
+                    gst::event::Segment::builder(&out_segment)
+                        .seqnum(event.seqnum())
+                        .build(),
+                );
+            }
+            EventView::Eos(_) => {
+                if let Err(err) = self.drain() {
+                    gst::error!(
+                        CAT,
+                        imp: self,
+                        "Failed to push drained data on EOS: {}",
+                        err
+                    );
+                    return false;
+                }
+            }
+            EventView::FlushStop(_) => {
+                if let Some(ref mut state) = &mut *self.state.borrow_mut() {
+                    state.reset();
+                }
+            }
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    #[allow(clippy::single_match)]
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+        match query.view_mut() {
+            QueryViewMut::Latency(q) => {
+                let mut peer_query = gst::query::Latency::new();
+                if self.sinkpad.peer_query(&mut peer_query) {
+                    let settings = *self.settings.lock().unwrap();
+
+                    let latency = match *self.state.borrow() {
+                        Some(ref state) if settings.rate != 1.0 || settings.pitch != 1.0 => {
+                            (state.wsola.latency() as u64)
+                                .mul_div_ceil(*gst::ClockTime::SECOND, state.info.rate() as u64)
+                                .unwrap()
+                                .nseconds()
+                        }
+                        _ => gst::ClockTime::ZERO,
+                    };
+
+                    let (live, min_latency, max_latency) = peer_query.result();
+                    q.set(live, min_latency + latency, max_latency.opt_add(latency));
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AudioTimeStretch {
+    const NAME: &'static str = "GstAudioTimeStretch";
+    type Type = super::AudioTimeStretch;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                Self::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Self::catch_panic_pad_function(parent, || false, |this| this.sink_event(pad, event))
+            })
+            .flags(gst::PadFlags::PROXY_CAPS)
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                Self::catch_panic_pad_function(parent, || false, |this| this.src_query(pad, query))
+            })
+            .flags(gst::PadFlags::PROXY_CAPS)
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            settings: Mutex::new(Default::default()),
+            segment: Mutex::new(gst::FormattedSegment::new()),
+            state: AtomicRefCell::new(None),
+        }
+    }
+}
+
+impl ObjectImpl for AudioTimeStretch {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecDouble::builder("rate")
+                    .nick("Rate")
+                    .blurb("Tempo factor, applied in addition to the segment rate")
+                    .minimum(0.1)
+                    .maximum(10.0)
+                    .default_value(DEFAULT_RATE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("pitch")
+                    .nick("Pitch")
+                    .blurb("Pitch factor")
+                    .minimum(0.25)
+                    .maximum(4.0)
+                    .default_value(DEFAULT_PITCH)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "rate" => {
+                let rate = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing rate from {} to {}",
+                    settings.rate,
+                    rate
+                );
+                settings.rate = rate;
+            }
+            "pitch" => {
+                let pitch = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing pitch from {} to {}",
+                    settings.pitch,
+                    pitch
+                );
+                settings.pitch = pitch;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "rate" => settings.rate.to_value(),
+            "pitch" => settings.pitch.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for AudioTimeStretch {}
+
+impl ElementImpl for AudioTimeStretch {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Audio time stretch",
+                "Filter/Effect/Audio",
+                "Changes tempo and pitch of an audio stream independently",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_F32)
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let res = self.parent_change_state(transition);
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                // Drop state
+                *self.state.borrow_mut() = None;
+                *self.segment.lock().unwrap() = gst::FormattedSegment::new();
+            }
+            _ => (),
+        }
+
+        res
+    }
+}
@@ -0,0 +1,24 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod stretch;
+
+glib::wrapper! {
+    pub struct AudioTimeStretch(ObjectSubclass<imp::AudioTimeStretch>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "audiotimestretch",
+        gst::Rank::NONE,
+        AudioTimeStretch::static_type(),
+    )
+}
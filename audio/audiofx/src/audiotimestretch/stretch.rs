@@ -0,0 +1,250 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+// Length of the output produced per WSOLA step
+const STRIDE_MS: u32 = 30;
+// Length of the cross-fade between two consecutive windows
+const OVERLAP_MS: u32 = 6;
+// Range searched for the window best matching the previous one
+const SEARCH_MS: u32 = 14;
+
+/// Waveform similarity overlap-add time stretcher.
+///
+/// Each step outputs `stride` frames: the end of the previous window cross-faded with the
+/// window found in the search range after the nominal input position that is most similar
+/// to it, followed by the rest of that window. The nominal input position then advances by
+/// `stride * tempo` frames so that tempo is changed without changing the pitch.
+pub struct Wsola {
+    channels: usize,
+    stride: usize,
+    overlap: usize,
+    search: usize,
+
+    // Interleaved input, starting at the nominal input position
+    queue: Vec<f32>,
+    // End of the previous window, cross-faded with the start of the next one
+    overlap_buf: Vec<f32>,
+    // Frames to skip from the next input once the queue ran empty
+    skip: usize,
+    // Fractional part of the input advance
+    skip_frac: f64,
+    first: bool,
+}
+
+impl Wsola {
+    pub fn new(rate: u32, channels: u32) -> Self {
+        let channels = channels as usize;
+        let stride = (rate * STRIDE_MS / 1000) as usize;
+        let overlap = (rate * OVERLAP_MS / 1000).max(1) as usize;
+        let search = (rate * SEARCH_MS / 1000) as usize;
+
+        Wsola {
+            channels,
+            stride,
+            overlap,
+            search,
+            queue: Vec::new(),
+            overlap_buf: vec![0.0; overlap * channels],
+            skip: 0,
+            skip_frac: 0.0,
+            first: true,
+        }
+    }
+
+    /// Number of input frames needed before the first output is produced.
+    pub fn latency(&self) -> usize {
+        self.search + self.stride + self.overlap
+    }
+
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.skip = 0;
+        self.skip_frac = 0.0;
+        self.first = true;
+    }
+
+    pub fn push(&mut self, mut data: &[f32]) {
+        if self.skip > 0 {
+            let skip = usize::min(self.skip, data.len() / self.channels);
+            data = &data[(skip * self.channels)..];
+            self.skip -= skip;
+        }
+
+        self.queue.extend_from_slice(data);
+    }
+
+    /// Runs as many steps as possible with the queued input.
+    pub fn process(&mut self, tempo: f64, out: &mut Vec<f32>) {
+        while self.queue.len() / self.channels >= self.latency() {
+            self.step(tempo, out);
+        }
+    }
+
+    /// Outputs everything that is left in the queue, padding the input with silence.
+    pub fn drain(&mut self, tempo: f64, out: &mut Vec<f32>) {
+        let remaining = self.queue.len() / self.channels;
+        if remaining == 0 || self.first {
+            // Not a single window was output yet, pass through whatever is queued
+            out.extend_from_slice(&self.queue);
+            self.reset();
+            return;
+        }
+
+        let expected = out.len() + (remaining as f64 / tempo).round() as usize * self.channels;
+
+        self.queue
+            .resize(self.queue.len() + self.latency() * self.channels, 0.0);
+        while out.len() < expected {
+            if self.queue.len() / self.channels < self.latency() {
+                self.queue
+                    .resize(self.queue.len() + self.latency() * self.channels, 0.0);
+            }
+            self.step(tempo, out);
+        }
+        out.truncate(expected);
+
+        self.reset();
+    }
+
+    fn step(&mut self, tempo: f64, out: &mut Vec<f32>) {
+        let channels = self.channels;
+
+        let offset = if self.first {
+            self.overlap_buf
+                .copy_from_slice(&self.queue[..(self.overlap * channels)]);
+            self.first = false;
+            0
+        } else {
+            self.best_offset()
+        };
+
+        let window = &self.queue[(offset * channels)..((offset + self.stride) * channels)];
+
+        // Cross-fade from the end of the previous window to the start of this one
+        for (n, (prev, next)) in self
+            .overlap_buf
+            .chunks_exact(channels)
+            .zip(window.chunks_exact(channels))
+            .enumerate()
+        {
+            let weight = n as f32 / self.overlap as f32;
+            out.extend(
+                prev.iter()
+                    .zip(next.iter())
+                    .map(|(prev, next)| prev * (1.0 - weight) + next * weight),
+            );
+        }
+        out.extend_from_slice(&window[(self.overlap * channels)..]);
+
+        let end = (offset + self.stride) * channels;
+        self.overlap_buf
+            .copy_from_slice(&self.queue[end..(end + self.overlap * channels)]);
+
+        // Advance the nominal input position
+        let advance = self.stride as f64 * tempo + self.skip_frac;
+        let advance_frames = advance.floor() as usize;
+        self.skip_frac = advance - advance_frames as f64;
+
+        let queued = self.queue.len() / channels;
+        let drained = usize::min(advance_frames, queued);
+        self.queue.drain(..(drained * channels));
+        self.skip = advance_frames - drained;
+    }
+
+    // Offset in the search range at which the queue is most similar to the overlap buffer,
+    // according to the normalized cross-correlation.
+    fn best_offset(&self) -> usize {
+        let channels = self.channels;
+        let len = self.overlap * channels;
+
+        let mut best_offset = 0;
+        let mut best_corr = f32::NEG_INFINITY;
+        for offset in 0..=self.search {
+            let candidate = &self.queue[(offset * channels)..(offset * channels + len)];
+
+            let (corr, energy) = self
+                .overlap_buf
+                .iter()
+                .zip(candidate.iter())
+                .fold((0.0f32, 0.0f32), |(corr, energy), (a, b)| {
+                    (corr + a * b, energy + b * b)
+                });
+            let corr = if energy > 0.0 {
+                corr / energy.sqrt()
+            } else {
+                0.0
+            };
+
+            if corr > best_corr {
+                best_corr = corr;
+                best_offset = offset;
+            }
+        }
+
+        best_offset
+    }
+}
+
+/// Linearly interpolating resampler used to shift the pitch of the stretched audio.
+pub struct Resampler {
+    channels: usize,
+    // Interleaved input not fully consumed yet
+    buf: Vec<f32>,
+    // Position of the next output frame in `buf`
+    pos: f64,
+}
+
+impl Resampler {
+    pub fn new(channels: u32) -> Self {
+        Resampler {
+            channels: channels as usize,
+            buf: Vec::new(),
+            pos: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.pos = 0.0;
+    }
+
+    /// Resamples `input` so that it plays `factor` times faster.
+    pub fn process(&mut self, factor: f64, input: &[f32], out: &mut Vec<f32>) {
+        let channels = self.channels;
+        self.buf.extend_from_slice(input);
+
+        let frames = self.buf.len() / channels;
+        while self.pos + 1.0 < frames as f64 {
+            let idx = self.pos.floor() as usize;
+            let frac = (self.pos - idx as f64) as f32;
+
+            let cur = &self.buf[(idx * channels)..((idx + 1) * channels)];
+            let next = &self.buf[((idx + 1) * channels)..((idx + 2) * channels)];
+            out.extend(
+                cur.iter()
+                    .zip(next.iter())
+                    .map(|(cur, next)| cur + (next - cur) * frac),
+            );
+
+            self.pos += factor;
+        }
+
+        let consumed = usize::min(self.pos.floor() as usize, frames);
+        self.buf.drain(..(consumed * channels));
+        self.pos -= consumed as f64;
+    }
+
+    /// Outputs the last frame that is kept back for interpolation.
+    pub fn drain(&mut self, factor: f64, out: &mut Vec<f32>) {
+        if self.buf.is_empty() {
+            return;
+        }
+
+        let last = self.buf[(self.buf.len() - self.channels)..].to_vec();
+        self.process(factor, &last, out);
+        self.reset();
+    }
+}
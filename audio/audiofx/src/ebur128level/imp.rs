@@ -80,12 +80,14 @@ impl From<Mode> for ebur128::Mode {
 const DEFAULT_MODE: Mode = Mode::all();
 const DEFAULT_POST_MESSAGES: bool = true;
 const DEFAULT_INTERVAL: gst::ClockTime = gst::ClockTime::SECOND;
+const DEFAULT_PER_CHANNEL: bool = false;
 
 #[derive(Debug, Clone, Copy)]
 struct Settings {
     mode: Mode,
     post_messages: bool,
     interval: gst::ClockTime,
+    per_channel: bool,
 }
 
 impl Default for Settings {
@@ -94,13 +96,42 @@ impl Default for Settings {
             mode: DEFAULT_MODE,
             post_messages: DEFAULT_POST_MESSAGES,
             interval: DEFAULT_INTERVAL,
+            per_channel: DEFAULT_PER_CHANNEL,
         }
     }
 }
 
+type LoudnessFn = fn(&ebur128::EbuR128) -> Result<f64, ebur128::Error>;
+
+// Loudness metrics that are also reported per channel
+const CHANNEL_LOUDNESS: [(ebur128::Mode, &str, LoudnessFn); 4] = [
+    (
+        ebur128::Mode::M,
+        "channel-momentary-loudness",
+        ebur128::EbuR128::loudness_momentary,
+    ),
+    (
+        ebur128::Mode::S,
+        "channel-shortterm-loudness",
+        ebur128::EbuR128::loudness_shortterm,
+    ),
+    (
+        ebur128::Mode::I,
+        "channel-global-loudness",
+        ebur128::EbuR128::loudness_global,
+    ),
+    (
+        ebur128::Mode::LRA,
+        "channel-loudness-range",
+        ebur128::EbuR128::loudness_range,
+    ),
+];
+
 struct State {
     info: gst_audio::AudioInfo,
     ebur128: ebur128::EbuR128,
+    // One mono measurement per channel if per-channel loudness is enabled
+    channels: Vec<ebur128::EbuR128>,
     num_frames: u64,
     interval_frames: gst::ClockTime,
     interval_frames_remaining: gst::ClockTime,
@@ -162,6 +193,14 @@ impl ObjectImpl for EbuR128Level {
                     .default_value(DEFAULT_INTERVAL.nseconds())
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoolean::builder("per-channel")
+                    .nick("Per Channel")
+                    .blurb(
+                        "Whether to additionally measure the loudness of each channel separately",
+                    )
+                    .default_value(DEFAULT_PER_CHANNEL)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -204,6 +243,17 @@ impl ObjectImpl for EbuR128Level {
                 );
                 settings.interval = interval;
             }
+            "per-channel" => {
+                let per_channel = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing per-channel from {} to {}",
+                    settings.per_channel,
+                    per_channel,
+                );
+                settings.per_channel = per_channel;
+            }
             _ => unimplemented!(),
         }
     }
@@ -214,6 +264,7 @@ impl ObjectImpl for EbuR128Level {
             "mode" => settings.mode.to_value(),
             "post-messages" => settings.post_messages.to_value(),
             "interval" => settings.interval.to_value(),
+            "per-channel" => settings.per_channel.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -326,6 +377,9 @@ impl BaseTransformImpl for EbuR128Level {
                 .is_ok()
             {
                 state.ebur128.reset();
+                for ebur128 in &mut state.channels {
+                    ebur128.reset();
+                }
                 state.interval_frames_remaining = state.interval_frames;
                 state.num_frames = 0;
             }
@@ -336,7 +390,8 @@ impl BaseTransformImpl for EbuR128Level {
             );
 
             frames
-                .process(to_process, &mut state.ebur128)
+                .process_channels(to_process, &mut state.channels)
+                .and_then(|_| frames.process(to_process, &mut state.ebur128))
                 .map_err(|err| {
                     gst::element_imp_error!(
                         self,
@@ -453,6 +508,25 @@ impl BaseTransformImpl for EbuR128Level {
                         }
                     }
 
+                    for (mode, field, loudness) in CHANNEL_LOUDNESS {
+                        if state.channels.is_empty() || !state.ebur128.mode().contains(mode) {
+                            continue;
+                        }
+
+                        let values = state
+                            .channels
+                            .iter()
+                            .map(|ebur128| loudness(ebur128).map(|l| l.to_send_value()))
+                            .collect::<Result<gst::Array, _>>();
+
+                        match values {
+                            Ok(values) => s.set(field, values),
+                            Err(err) => {
+                                gst::error!(CAT, imp: self, "Failed to get {}: {}", field, err)
+                            }
+                        }
+                    }
+
                     gst::debug!(CAT, imp: self, "Posting message {}", s);
 
                     let msg = gst::message::Element::builder(s).src(&*self.obj()).build();
@@ -589,6 +663,19 @@ impl AudioFilterImpl for EbuR128Level {
                 .map_err(|err| gst::loggable_error!(CAT, "Failed to set channel map: {}", err))?;
         }
 
+        let channels = if settings.per_channel {
+            // Peaks are already reported per channel by the main measurement
+            let mode = settings.mode
+                & (Mode::MOMENTARY | Mode::SHORT_TERM | Mode::GLOBAL | Mode::LOUDNESS_RANGE);
+
+            (0..info.channels())
+                .map(|_| ebur128::EbuR128::new(1, info.rate(), mode.into()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| gst::loggable_error!(CAT, "Failed to create EBU R128: {}", err))?
+        } else {
+            vec![]
+        };
+
         let interval_frames = settings
             .interval
             .mul_div_floor(info.rate() as u64, *gst::ClockTime::SECOND)
@@ -597,6 +684,7 @@ impl AudioFilterImpl for EbuR128Level {
         *self.state.borrow_mut() = Some(State {
             info: info.clone(),
             ebur128,
+            channels,
             num_frames: 0,
             interval_frames,
             interval_frames_remaining: interval_frames,
@@ -671,6 +759,39 @@ impl<'a> Frames<'a> {
         }
     }
 
+    /// Process the next `num_frames` of each channel with the corresponding mono `ebur128`
+    /// without advancing.
+    fn process_channels(
+        &self,
+        num_frames: u64,
+        ebur128: &mut [ebur128::EbuR128],
+    ) -> Result<(), ebur128::Error> {
+        let num_frames = num_frames as usize;
+
+        for (c, ebur128) in ebur128.iter_mut().enumerate() {
+            match self {
+                Frames::S16(frames, channels) => {
+                    ebur128.add_frames_i16(&deinterleave(frames, *channels, c, num_frames))?
+                }
+                Frames::S32(frames, channels) => {
+                    ebur128.add_frames_i32(&deinterleave(frames, *channels, c, num_frames))?
+                }
+                Frames::F32(frames, channels) => {
+                    ebur128.add_frames_f32(&deinterleave(frames, *channels, c, num_frames))?
+                }
+                Frames::F64(frames, channels) => {
+                    ebur128.add_frames_f64(&deinterleave(frames, *channels, c, num_frames))?
+                }
+                Frames::S16P(channels) => ebur128.add_frames_i16(&channels[c][..num_frames])?,
+                Frames::S32P(channels) => ebur128.add_frames_i32(&channels[c][..num_frames])?,
+                Frames::F32P(channels) => ebur128.add_frames_f32(&channels[c][..num_frames])?,
+                Frames::F64P(channels) => ebur128.add_frames_f64(&channels[c][..num_frames])?,
+            }
+        }
+
+        Ok(())
+    }
+
     /// Process `num_frames` with `ebur128` and advance to the next frames.
     fn process(
         &mut self,
@@ -738,6 +859,21 @@ impl<'a> Frames<'a> {
     }
 }
 
+/// Extracts the first `num_frames` samples of `channel` from interleaved samples.
+fn deinterleave<T: Copy>(
+    frames: &[T],
+    channels: usize,
+    channel: usize,
+    num_frames: usize,
+) -> Vec<T> {
+    frames[..num_frames * channels]
+        .iter()
+        .skip(channel)
+        .step_by(channels)
+        .copied()
+        .collect()
+}
+
 /// Converts an interleaved audio buffer into a typed slice.
 fn interleaved_channel_data_into_slice<'a, T: FromByteSlice>(
     imp: &EbuR128Level,
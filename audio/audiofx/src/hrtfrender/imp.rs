@@ -37,6 +37,9 @@ static THREAD_POOL: Lazy<Mutex<Weak<ThreadPool>>> = Lazy::new(|| Mutex::new(Weak
 const DEFAULT_INTERPOLATION_STEPS: u64 = 8;
 const DEFAULT_BLOCK_LENGTH: u64 = 512;
 const DEFAULT_DISTANCE_GAIN: f32 = 1.0;
+const DEFAULT_YAW: f64 = 0.0;
+const DEFAULT_PITCH: f64 = 0.0;
+const DEFAULT_ROLL: f64 = 0.0;
 
 #[derive(Clone, Copy)]
 struct SpatialObject {
@@ -114,6 +117,45 @@ impl TryFrom<gst_audio::AudioChannelPosition> for SpatialObject {
     }
 }
 
+/// Orientation of the listener's head in degrees
+#[derive(Clone, Copy, Debug)]
+struct Orientation {
+    /// Rotation around the vertical axis, positive values turn the head to the right
+    yaw: f64,
+    /// Rotation around the lateral axis, positive values tilt the head up
+    pitch: f64,
+    /// Rotation around the longitudinal axis, positive values tilt the head to the right
+    roll: f64,
+}
+
+impl Default for Orientation {
+    fn default() -> Self {
+        Orientation {
+            yaw: DEFAULT_YAW,
+            pitch: DEFAULT_PITCH,
+            roll: DEFAULT_ROLL,
+        }
+    }
+}
+
+impl Orientation {
+    /// Transforms a position into the coordinate system of the listener's head by applying the
+    /// inverse of the head rotation (yaw, then pitch, then roll).
+    fn to_listener(self, position: Vec3) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.to_radians().sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.to_radians().sin_cos();
+        let (sin_roll, cos_roll) = self.roll.to_radians().sin_cos();
+
+        let (x, y, z) = (position.x as f64, position.y as f64, position.z as f64);
+
+        let (x, z) = (x * cos_yaw - z * sin_yaw, x * sin_yaw + z * cos_yaw);
+        let (y, z) = (y * cos_pitch - z * sin_pitch, y * sin_pitch + z * cos_pitch);
+        let (x, y) = (x * cos_roll - y * sin_roll, x * sin_roll + y * cos_roll);
+
+        Vec3::new(x as f32, y as f32, z as f32)
+    }
+}
+
 #[derive(Clone)]
 struct Settings {
     interpolation_steps: u64,
@@ -121,6 +163,9 @@ struct Settings {
     spatial_objects: Option<Vec<SpatialObject>>,
     hrir_raw_bytes: Option<glib::Bytes>,
     hrir_file_location: Option<String>,
+    /// Incremented whenever the impulse response is changed
+    hrir_generation: u64,
+    orientation: Orientation,
 }
 
 impl Default for Settings {
@@ -131,17 +176,21 @@ impl Default for Settings {
             spatial_objects: None,
             hrir_raw_bytes: None,
             hrir_file_location: None,
+            hrir_generation: 0,
+            orientation: Orientation::default(),
         }
     }
 }
 
 impl Settings {
     fn position(&self, channel: usize) -> Result<Vec3, gst::FlowError> {
-        Ok(self
+        let position = self
             .spatial_objects
             .as_ref()
             .ok_or(gst::FlowError::NotNegotiated)?[channel]
-            .position)
+            .position;
+
+        Ok(self.orientation.to_listener(position))
     }
 
     fn distance_gain(&self, channel: usize) -> Result<f32, gst::FlowError> {
@@ -175,12 +224,90 @@ struct ChannelProcessor {
     processor: HrtfProcessor,
 }
 
+impl ChannelProcessor {
+    fn new(sphere: HrirSphere, steps: usize, blklen: usize) -> Self {
+        let block_samples = steps * blklen;
+
+        ChannelProcessor {
+            prev_left_samples: vec![0.0; block_samples],
+            prev_right_samples: vec![0.0; block_samples],
+            prev_sample_vector: None,
+            prev_distance_gain: None,
+            indata_scratch: vec![0.0; block_samples].into_boxed_slice(),
+            outdata_scratch: vec![(0.0, 0.0); block_samples].into_boxed_slice(),
+            processor: HrtfProcessor::new(sphere, steps, blklen),
+        }
+    }
+
+    fn process_block(
+        &mut self,
+        settings: &Settings,
+        indata: &[f32],
+        channel: usize,
+        channels: usize,
+    ) -> Result<(), gst::FlowError> {
+        let new_distance_gain = settings.distance_gain(channel)?;
+        let new_sample_vector = settings.position(channel)?;
+
+        // Convert to Right Handed, this is what HRTF crate expects
+        let new_sample_vector = Vec3 {
+            z: new_sample_vector.z * -1.0,
+            ..new_sample_vector
+        };
+
+        // Deinterleave single channel to scratch buffer
+        for (x, y) in Iterator::zip(
+            indata.iter().skip(channel).step_by(channels),
+            self.indata_scratch.iter_mut(),
+        ) {
+            *y = *x;
+        }
+
+        // The processor interpolates between the previous and the new position over the
+        // interpolation steps, which keeps head movements smooth
+        self.processor.process_samples(HrtfContext {
+            source: &self.indata_scratch,
+            output: &mut self.outdata_scratch,
+            new_sample_vector,
+            new_distance_gain,
+            prev_sample_vector: self.prev_sample_vector.unwrap_or(new_sample_vector),
+            prev_distance_gain: self.prev_distance_gain.unwrap_or(new_distance_gain),
+            prev_left_samples: &mut self.prev_left_samples,
+            prev_right_samples: &mut self.prev_right_samples,
+        });
+
+        self.prev_sample_vector = Some(new_sample_vector);
+        self.prev_distance_gain = Some(new_distance_gain);
+
+        Ok(())
+    }
+
+    /// Mixes the processed block into `outdata`, weighting each frame by `weight`.
+    fn mix_block(&mut self, outdata: &mut [f32], weight: impl Fn(usize) -> f32) {
+        for (i, (x, y)) in
+            Iterator::zip(self.outdata_scratch.iter(), outdata.chunks_exact_mut(2)).enumerate()
+        {
+            let w = weight(i);
+            y[0] += x.0 * w;
+            y[1] += x.1 * w;
+        }
+
+        // HRTF is mixing processed samples with samples in output buffer, we need to
+        // reset scratch so it is not mixed with the next frame
+        self.outdata_scratch.fill((0.0, 0.0));
+    }
+}
+
 struct State {
     ininfo: gst_audio::AudioInfo,
     outinfo: gst_audio::AudioInfo,
     adapter: gst_base::UniqueAdapter,
     block_samples: usize,
     channel_processors: Vec<ChannelProcessor>,
+    /// Processors for a new impulse response that are crossfaded with the current ones over the
+    /// next block
+    next_channel_processors: Option<Vec<ChannelProcessor>>,
+    hrir_generation: u64,
 }
 
 impl State {
@@ -193,6 +320,10 @@ impl State {
     }
 
     fn reset_processors(&mut self) {
+        if let Some(next_channel_processors) = self.next_channel_processors.take() {
+            self.channel_processors = next_channel_processors;
+        }
+
         for cp in self.channel_processors.iter_mut() {
             cp.prev_left_samples.fill(0.0);
             cp.prev_right_samples.fill(0.0);
@@ -260,61 +391,41 @@ impl HrtfRender {
 
             let indata = inbuf.plane_data(0).unwrap().as_slice_of::<f32>().unwrap();
 
-            thread_pool.install(|| -> Result<(), gst::FlowError> {
-                state
-                    .channel_processors
-                    .par_iter_mut()
-                    .enumerate()
-                    .try_for_each(|(i, cp)| -> Result<(), gst::FlowError> {
-                        let new_distance_gain = settings.distance_gain(i)?;
-                        let new_sample_vector = settings.position(i)?;
-
-                        // Convert to Right Handed, this is what HRTF crate expects
-                        let new_sample_vector = Vec3 {
-                            z: new_sample_vector.z * -1.0,
-                            ..new_sample_vector
-                        };
-
-                        // Deinterleave single channel to scratch buffer
-                        for (x, y) in Iterator::zip(
-                            indata.iter().skip(i).step_by(channels as usize),
-                            cp.indata_scratch.iter_mut(),
-                        ) {
-                            *y = *x;
-                        }
-
-                        cp.processor.process_samples(HrtfContext {
-                            source: &cp.indata_scratch,
-                            output: &mut cp.outdata_scratch,
-                            new_sample_vector,
-                            new_distance_gain,
-                            prev_sample_vector: cp.prev_sample_vector.unwrap_or(new_sample_vector),
-                            prev_distance_gain: cp.prev_distance_gain.unwrap_or(new_distance_gain),
-                            prev_left_samples: &mut cp.prev_left_samples,
-                            prev_right_samples: &mut cp.prev_right_samples,
-                        });
-
-                        cp.prev_sample_vector = Some(new_sample_vector);
-                        cp.prev_distance_gain = Some(new_distance_gain);
-
-                        Ok(())
-                    })
-            })?;
+            let process = |processors: &mut Vec<ChannelProcessor>| {
+                thread_pool.install(|| -> Result<(), gst::FlowError> {
+                    processors
+                        .par_iter_mut()
+                        .enumerate()
+                        .try_for_each(|(i, cp)| {
+                            cp.process_block(settings, indata, i, channels as usize)
+                        })
+                })
+            };
+
+            let outblock = &mut outdata[2 * written_samples..][..2 * state.block_samples];
+
+            process(&mut state.channel_processors)?;
 
-            // unpack output scratch to output buffer
-            state.channel_processors.iter_mut().for_each(|cp| {
-                for (x, y) in Iterator::zip(
-                    cp.outdata_scratch.iter(),
-                    outdata[2 * written_samples..].chunks_exact_mut(2),
-                ) {
-                    y[0] += x.0;
-                    y[1] += x.1;
+            if let Some(mut next_channel_processors) = state.next_channel_processors.take() {
+                // Crossfade linearly from the old to the new impulse response over this block
+                process(&mut next_channel_processors)?;
+
+                let block_samples = state.block_samples as f32;
+                for cp in state.channel_processors.iter_mut() {
+                    cp.mix_block(outblock, |i| 1.0 - i as f32 / block_samples);
+                }
+                for cp in next_channel_processors.iter_mut() {
+                    cp.mix_block(outblock, |i| i as f32 / block_samples);
                 }
 
-                // HRTF is mixing processed samples with samples in output buffer, we need to
-                // reset scratch so it is not mixed with the next frame
-                cp.outdata_scratch.fill((0.0, 0.0));
-            });
+                gst::debug!(CAT, imp: self, "Switched to new impulse response");
+                state.channel_processors = next_channel_processors;
+            } else {
+                // unpack output scratch to output buffer
+                for cp in state.channel_processors.iter_mut() {
+                    cp.mix_block(outblock, |_| 1.0);
+                }
+            }
 
             written_samples += state.block_samples;
         }
@@ -326,6 +437,45 @@ impl HrtfRender {
         Ok(gst::FlowSuccess::Ok)
     }
 
+    fn update_hrir(&self, state: &mut State, settings: &Settings) {
+        if state.hrir_generation == settings.hrir_generation {
+            return;
+        }
+        state.hrir_generation = settings.hrir_generation;
+
+        let sphere = match settings.sphere(state.ininfo.rate()) {
+            Ok(sphere) => sphere,
+            Err(err) => {
+                gst::element_imp_warning!(
+                    self,
+                    gst::ResourceError::OpenRead,
+                    [
+                        "Failed to load new impulse response, keeping the current one: {:?}",
+                        err
+                    ]
+                );
+                return;
+            }
+        };
+
+        let steps = settings.interpolation_steps as usize;
+        let blklen = settings.block_length as usize;
+
+        let next_channel_processors = state
+            .channel_processors
+            .iter()
+            .map(|cp| {
+                let mut next = ChannelProcessor::new(sphere.to_owned(), steps, blklen);
+                next.prev_sample_vector = cp.prev_sample_vector;
+                next.prev_distance_gain = cp.prev_distance_gain;
+                next
+            })
+            .collect();
+
+        gst::debug!(CAT, imp: self, "Loaded new impulse response");
+        state.next_channel_processors = Some(next_channel_processors);
+    }
+
     fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
         let settings = &self.settings.lock().unwrap();
 
@@ -405,12 +555,12 @@ impl ObjectImpl for HrtfRender {
                 glib::ParamSpecBoxed::builder::<glib::Bytes>("hrir-raw")
                     .nick("Head Transform Impulse Response")
                     .blurb("Head Transform Impulse Response raw bytes")
-                    .mutable_ready()
+                    .mutable_playing()
                     .build(),
                 glib::ParamSpecString::builder("hrir-file")
                     .nick("Head Transform Impulse Response")
                     .blurb("Head Transform Impulse Response file location to read from")
-                    .mutable_ready()
+                    .mutable_playing()
                     .build(),
                 glib::ParamSpecUInt64::builder("interpolation-steps")
                     .nick("Interpolation Steps")
@@ -437,6 +587,33 @@ impl ObjectImpl for HrtfRender {
                     .blurb("Spatial object Metadata to apply on input channels")
                     .mutable_playing()
                     .build(),
+                glib::ParamSpecDouble::builder("yaw")
+                    .nick("Yaw")
+                    .blurb("Rotation of the listener's head around the vertical axis in degrees, positive values turn to the right")
+                    .minimum(-360.0)
+                    .maximum(360.0)
+                    .default_value(DEFAULT_YAW)
+                    .mutable_playing()
+                    .controllable()
+                    .build(),
+                glib::ParamSpecDouble::builder("pitch")
+                    .nick("Pitch")
+                    .blurb("Rotation of the listener's head around the lateral axis in degrees, positive values tilt up")
+                    .minimum(-360.0)
+                    .maximum(360.0)
+                    .default_value(DEFAULT_PITCH)
+                    .mutable_playing()
+                    .controllable()
+                    .build(),
+                glib::ParamSpecDouble::builder("roll")
+                    .nick("Roll")
+                    .blurb("Rotation of the listener's head around the longitudinal axis in degrees, positive values tilt to the right")
+                    .minimum(-360.0)
+                    .maximum(360.0)
+                    .default_value(DEFAULT_ROLL)
+                    .mutable_playing()
+                    .controllable()
+                    .build(),
             ]
         });
 
@@ -448,10 +625,12 @@ impl ObjectImpl for HrtfRender {
             "hrir-raw" => {
                 let mut settings = self.settings.lock().unwrap();
                 settings.hrir_raw_bytes = value.get().expect("type checked upstream");
+                settings.hrir_generation += 1;
             }
             "hrir-file" => {
                 let mut settings = self.settings.lock().unwrap();
                 settings.hrir_file_location = value.get().expect("type checked upstream");
+                settings.hrir_generation += 1;
             }
             "interpolation-steps" => {
                 let mut settings = self.settings.lock().unwrap();
@@ -490,6 +669,18 @@ impl ObjectImpl for HrtfRender {
 
                 settings.spatial_objects = if objs.is_empty() { None } else { Some(objs) };
             }
+            "yaw" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.orientation.yaw = value.get().expect("type checked upstream");
+            }
+            "pitch" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.orientation.pitch = value.get().expect("type checked upstream");
+            }
+            "roll" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.orientation.roll = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -524,6 +715,18 @@ impl ObjectImpl for HrtfRender {
                     .collect::<gst::Array>()
                     .to_value()
             }
+            "yaw" => {
+                let settings = self.settings.lock().unwrap();
+                settings.orientation.yaw.to_value()
+            }
+            "pitch" => {
+                let settings = self.settings.lock().unwrap();
+                settings.orientation.pitch.to_value()
+            }
+            "roll" => {
+                let settings = self.settings.lock().unwrap();
+                settings.orientation.roll.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -586,6 +789,20 @@ impl BaseTransformImpl for HrtfRender {
     const PASSTHROUGH_ON_SAME_CAPS: bool = false;
     const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
 
+    fn before_transform(&self, inbuf: &gst::BufferRef) {
+        // Update controlled properties, e.g. the head orientation
+        let stream_time = self
+            .obj()
+            .segment()
+            .downcast::<gst::ClockTime>()
+            .ok()
+            .and_then(|segment| segment.to_stream_time(inbuf.pts()));
+
+        if let Some(stream_time) = stream_time {
+            let _ = self.obj().sync_values(stream_time);
+        }
+    }
+
     fn transform(
         &self,
         inbuf: &gst::Buffer,
@@ -596,6 +813,8 @@ impl BaseTransformImpl for HrtfRender {
         let mut state_guard = self.state.lock().unwrap();
         let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
 
+        self.update_hrir(state, settings);
+
         state.adapter.push(inbuf.clone());
 
         if state.adapter.available() >= state.input_block_size() {
@@ -722,15 +941,7 @@ impl BaseTransformImpl for HrtfRender {
             .ok_or_else(|| gst::loggable_error!(CAT, "Not enough memory for frame allocation"))?;
 
         let channel_processors = (0..ininfo.channels())
-            .map(|_| ChannelProcessor {
-                prev_left_samples: vec![0.0; block_samples],
-                prev_right_samples: vec![0.0; block_samples],
-                prev_sample_vector: None,
-                prev_distance_gain: None,
-                indata_scratch: vec![0.0; block_samples].into_boxed_slice(),
-                outdata_scratch: vec![(0.0, 0.0); block_samples].into_boxed_slice(),
-                processor: HrtfProcessor::new(sphere.to_owned(), steps, blklen),
-            })
+            .map(|_| ChannelProcessor::new(sphere.to_owned(), steps, blklen))
             .collect();
 
         *self.state.lock().unwrap() = Some(State {
@@ -738,6 +949,8 @@ impl BaseTransformImpl for HrtfRender {
             outinfo,
             block_samples,
             channel_processors,
+            next_channel_processors: None,
+            hrir_generation: settings.hrir_generation,
             adapter: gst_base::UniqueAdapter::new(),
         });
 
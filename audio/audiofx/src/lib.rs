@@ -14,16 +14,24 @@
  */
 use gst::glib;
 
+mod audiocompressor;
 mod audioecho;
 mod audioloudnorm;
+mod audioparametriceq;
 mod audiornnoise;
+mod audiostereowidth;
+mod audiotimestretch;
 mod ebur128level;
 mod hrtfrender;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    audiocompressor::register(plugin)?;
     audioecho::register(plugin)?;
     audioloudnorm::register(plugin)?;
+    audioparametriceq::register(plugin)?;
     audiornnoise::register(plugin)?;
+    audiostereowidth::register(plugin)?;
+    audiotimestretch::register(plugin)?;
     ebur128level::register(plugin)?;
     hrtfrender::register(plugin)?;
     Ok(())
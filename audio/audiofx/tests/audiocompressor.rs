@@ -0,0 +1,130 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+const RATE: usize = 48000;
+
+fn sine(amplitude: f64, num_samples: usize) -> Vec<f64> {
+    (0..num_samples)
+        .map(|i| amplitude * f64::sin(2.0 * std::f64::consts::PI * 440.0 * i as f64 / RATE as f64))
+        .collect()
+}
+
+fn setup(properties: &[(&str, &str)]) -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("audiocompressor");
+    {
+        let compressor = h.element().unwrap();
+        for (name, value) in properties {
+            compressor.set_property_from_str(name, value);
+        }
+    }
+
+    let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F64)
+        .rate(RATE as i32)
+        .channels(1)
+        .build();
+    h.set_src_caps(caps);
+    h.play();
+
+    h
+}
+
+// Pushes `input` in buffers of 10ms and returns all output samples after EOS
+fn run(h: &mut gst_check::Harness, input: &[f64]) -> Vec<f64> {
+    for (i, chunk) in input.chunks(RATE / 100).enumerate() {
+        let mut buffer = gst::Buffer::from_mut_slice(chunk.as_byte_slice().to_vec());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(10 * i as u64));
+            buffer.set_duration(gst::ClockTime::from_mseconds(10));
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let mut output = vec![];
+    while let Some(buffer) = h.pull_until_eos().unwrap() {
+        let map = buffer.map_readable().unwrap();
+        output.extend_from_slice(map.as_slice_of::<f64>().unwrap());
+    }
+
+    output
+}
+
+#[test]
+fn test_below_threshold() {
+    init();
+
+    let mut h = setup(&[("threshold", "-20"), ("knee", "0")]);
+    let input = sine(0.01, RATE / 10);
+    let output = run(&mut h, &input);
+
+    assert_eq!(output.len(), input.len());
+    for (o, i) in output.iter().zip(input.iter()) {
+        assert!((o - i).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_compression() {
+    init();
+
+    let mut h = setup(&[("threshold", "-20"), ("ratio", "4"), ("knee", "0")]);
+    let input = sine(1.0, RATE / 2);
+    let output = run(&mut h, &input);
+
+    assert_eq!(output.len(), input.len());
+
+    // 0dBFS peaks are reduced to about -15dBFS after the attack phase, modulo the ripple of the
+    // gain envelope
+    let peak = output[RATE / 4..]
+        .iter()
+        .fold(0.0f64, |peak, s| peak.max(s.abs()));
+    let peak = 20.0 * f64::log10(peak);
+    assert!((-16.0..-12.0).contains(&peak), "{peak}");
+}
+
+#[test]
+fn test_limiter_lookahead() {
+    init();
+
+    let lookahead = gst::ClockTime::from_mseconds(5);
+    let mut h = setup(&[
+        ("mode", "limiter"),
+        ("threshold", "-6"),
+        ("makeup-gain", "3"),
+        ("attack", "5000000"),
+        ("lookahead", "5000000"),
+    ]);
+
+    let mut q = gst::query::Latency::new();
+    assert!(h.element().unwrap().src_pads()[0].query(&mut q));
+    let (_live, min, _max) = q.result();
+    assert!(min >= lookahead);
+
+    let input = sine(0.9, RATE / 10);
+    let output = run(&mut h, &input);
+
+    // Output is delayed by the lookahead but all samples come out after EOS
+    assert_eq!(output.len(), input.len());
+
+    let ceiling = f64::powf(10.0, -6.0 / 20.0);
+    assert!(output.iter().all(|s| s.abs() <= ceiling + 1e-9));
+}
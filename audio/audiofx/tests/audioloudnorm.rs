@@ -255,3 +255,109 @@ fn limiter_on_first_frame() {
         -24.0,
     );
 }
+
+fn run_pass(input: &str, properties: &str) -> (Vec<f64>, gst::Structure) {
+    init();
+
+    let format = if cfg!(target_endian = "little") {
+        "audio/x-raw,format=F64LE,rate=192000,channels=1"
+    } else {
+        "audio/x-raw,format=F64BE,rate=192000,channels=1"
+    };
+
+    let pipeline = gst::parse::launch(&format!(
+        "audiotestsrc {input} num-buffers=1000 samplesperbuffer=1920 ! {format} ! audioloudnorm {properties} ! appsink name=sink",
+    ))
+    .unwrap()
+    .downcast::<gst::Pipeline>()
+    .unwrap();
+    let sink = pipeline
+        .by_name("sink")
+        .unwrap()
+        .downcast::<gst_app::AppSink>()
+        .unwrap();
+    sink.set_sync(false);
+
+    let samples = Arc::new(Mutex::new(Vec::new()));
+
+    let samples_clone = samples.clone();
+    sink.set_callbacks(
+        gst_app::AppSinkCallbacks::builder()
+            .new_sample(move |sink| {
+                let sample = sink.pull_sample().unwrap();
+                let buf = sample.buffer().unwrap();
+                let map = buf.map_readable().unwrap();
+
+                let mut samples = samples_clone.lock().unwrap();
+                samples.extend_from_slice(map.as_slice_of::<f64>().unwrap());
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build(),
+    );
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let mut stats = None;
+    let bus = pipeline.bus().unwrap();
+    while let Some(msg) = bus.timed_pop(gst::ClockTime::NONE) {
+        use gst::MessageView;
+        match msg.view() {
+            MessageView::Eos(..) => break,
+            MessageView::Error(..) => unreachable!(),
+            MessageView::Element(e) => {
+                let s = e.structure().unwrap();
+                if s.name() == "loudnorm-stats" {
+                    stats = Some(s.to_owned());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    let samples = samples.lock().unwrap().clone();
+    (samples, stats.expect("no stats"))
+}
+
+#[test]
+fn two_pass_linear() {
+    let (measured, stats) = run_pass("wave=sine volume=0.5", "mode=measure");
+    assert_eq!(stats.get::<&str>("normalization-type").unwrap(), "none");
+
+    let input_loudness = stats.get::<f64>("input-loudness").unwrap();
+    let input_loudness_range = stats.get::<f64>("input-loudness-range").unwrap();
+    let input_true_peak = stats.get::<f64>("input-true-peak").unwrap();
+    assert!(
+        f64::abs(input_loudness - stats.get::<f64>("output-loudness").unwrap()) < 0.01,
+        "Measure pass changed the loudness"
+    );
+
+    let (normalized, stats) = run_pass(
+        "wave=sine volume=0.5",
+        &format!(
+            "mode=linear measured-loudness={input_loudness} measured-loudness-range={input_loudness_range} measured-true-peak={input_true_peak}"
+        ),
+    );
+    assert_eq!(stats.get::<&str>("normalization-type").unwrap(), "linear");
+
+    let output_loudness = stats.get::<f64>("output-loudness").unwrap();
+    assert!(
+        f64::abs(output_loudness - -24.0) < 0.1,
+        "Loudness is {output_loudness} instead of -24.0",
+    );
+
+    // The same constant gain has to be applied to all samples
+    assert_eq!(measured.len(), normalized.len());
+    let gain = f64::powf(10., (-24.0 - input_loudness) / 20.);
+    for (m, n) in measured.iter().zip(normalized.iter()) {
+        assert!(f64::abs(m * gain - n) < 1e-9);
+    }
+}
+
+#[test]
+fn linear_fallback_to_dynamic() {
+    // Without measurements linear mode falls back to dynamic normalization
+    let (_, stats) = run_pass("wave=sine volume=0.5", "mode=linear");
+    assert_eq!(stats.get::<&str>("normalization-type").unwrap(), "dynamic");
+}
@@ -0,0 +1,159 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+const RATE: usize = 48000;
+
+fn sine(frequency: f64, num_samples: usize) -> Vec<f64> {
+    (0..num_samples)
+        .map(|i| 0.1 * f64::sin(2.0 * std::f64::consts::PI * frequency * i as f64 / RATE as f64))
+        .collect()
+}
+
+fn rms(samples: &[f64]) -> f64 {
+    (samples.iter().map(|s| s * s).sum::<f64>() / samples.len() as f64).sqrt()
+}
+
+fn band(band_type: &str, frequency: f64, gain: f64, q: f64) -> gst::Structure {
+    gst::Structure::builder("band")
+        .field("type", band_type)
+        .field("frequency", frequency)
+        .field("gain", gain)
+        .field("q", q)
+        .build()
+}
+
+fn setup(bands: &[gst::Structure]) -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("audioparametriceq");
+    h.element()
+        .unwrap()
+        .set_property("bands", gst::Array::new(bands));
+
+    let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F64)
+        .rate(RATE as i32)
+        .channels(1)
+        .build();
+    h.set_src_caps(caps);
+    h.play();
+
+    h
+}
+
+// Pushes `input` in buffers of 10ms and returns all output samples
+fn run(h: &mut gst_check::Harness, input: &[f64]) -> Vec<f64> {
+    let mut output = Vec::new();
+
+    for (i, chunk) in input.chunks(RATE / 100).enumerate() {
+        let mut buffer = gst::Buffer::from_mut_slice(chunk.as_byte_slice().to_vec());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(10 * i as u64));
+            buffer.set_duration(gst::ClockTime::from_mseconds(10));
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+
+        let buffer = h.pull().unwrap();
+        let map = buffer.map_readable().unwrap();
+        output.extend_from_slice(map.as_slice_of::<f64>().unwrap());
+    }
+
+    output
+}
+
+// Ratio in dB between the output and input level after the filters settled
+fn gain(bands: &[gst::Structure], frequency: f64) -> f64 {
+    let mut h = setup(bands);
+    let input = sine(frequency, RATE / 2);
+    let output = run(&mut h, &input);
+    assert_eq!(input.len(), output.len());
+
+    20.0 * f64::log10(rms(&output[RATE / 4..]) / rms(&input[RATE / 4..]))
+}
+
+#[test]
+fn test_no_bands() {
+    init();
+
+    let mut h = setup(&[]);
+    let input = sine(1000.0, RATE / 10);
+    let output = run(&mut h, &input);
+
+    assert_eq!(input, output);
+}
+
+#[test]
+fn test_peaking() {
+    init();
+
+    let bands = [band("peaking", 1000.0, 12.0, 1.0)];
+    let boost = gain(&bands, 1000.0);
+    assert!((boost - 12.0).abs() < 0.1, "{boost}");
+
+    // Far away from the center frequency the level is unchanged
+    let boost = gain(&bands, 20.0);
+    assert!(boost.abs() < 0.5, "{boost}");
+
+    let cut = gain(&[band("peaking", 1000.0, -12.0, 1.0)], 1000.0);
+    assert!((cut + 12.0).abs() < 0.1, "{cut}");
+}
+
+#[test]
+fn test_shelves() {
+    init();
+
+    let bands = [band("low-shelf", 200.0, 6.0, 0.7)];
+    let low = gain(&bands, 20.0);
+    let high = gain(&bands, 5000.0);
+    assert!((low - 6.0).abs() < 0.5, "{low}");
+    assert!(high.abs() < 0.5, "{high}");
+
+    let bands = [band("high-shelf", 2000.0, -6.0, 0.7)];
+    let low = gain(&bands, 50.0);
+    let high = gain(&bands, 15000.0);
+    assert!(low.abs() < 0.5, "{low}");
+    assert!((high + 6.0).abs() < 0.5, "{high}");
+}
+
+#[test]
+fn test_multiple_bands() {
+    init();
+
+    // Low-pass and notch filters in series
+    let bands = [
+        band("low-pass", 500.0, 0.0, 0.7),
+        band("notch", 100.0, 0.0, 2.0),
+    ];
+    let mut h = setup(&bands);
+
+    let eq = h.element().unwrap();
+    let configured = eq.property::<gst::Array>("bands");
+    assert_eq!(configured.len(), 2);
+    let s = configured[0].get::<gst::Structure>().unwrap();
+    assert_eq!(s.get::<&str>("type").unwrap(), "low-pass");
+    assert_eq!(s.get::<f64>("frequency").unwrap(), 500.0);
+
+    let input = sine(5000.0, RATE / 2);
+    let output = run(&mut h, &input);
+    let attenuation = 20.0 * f64::log10(rms(&output[RATE / 4..]) / rms(&input[RATE / 4..]));
+    assert!(attenuation < -30.0, "{attenuation}");
+
+    let notch = gain(&bands, 100.0);
+    assert!(notch < -30.0, "{notch}");
+}
@@ -7,6 +7,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use byte_slice_cast::*;
+use gst::prelude::*;
 
 fn init() {
     use std::sync::Once;
@@ -78,3 +79,66 @@ fn test_rnnoise(audio_info: &gst_audio::AudioInfo, buffer_size: usize) {
     // total amount of samples pulled from it.
     assert_eq!(total_processed, num_buffers * buffer_size);
 }
+
+#[test]
+fn test_rnnoise_vad_messages() {
+    init();
+    let audio_info = gst_audio::AudioInfo::builder(gst_audio::AUDIO_FORMAT_F32, 48000, 1)
+        .build()
+        .unwrap();
+
+    let filter = gst::ElementFactory::make("audiornnoise")
+        .property("post-messages", true)
+        .build()
+        .unwrap();
+    let bus = gst::Bus::new();
+    filter.set_bus(Some(&bus));
+
+    let mut h = gst_check::Harness::with_element(&filter, Some("sink"), Some("src"));
+    let caps = audio_info.to_caps().unwrap();
+    h.set_caps(caps.clone(), caps);
+    h.play();
+
+    // 4 chunks of 10ms each
+    let mut buffer = gst::Buffer::from_mut_slice(vec![0u8; 4 * 480 * audio_info.bpf() as usize]);
+    buffer.get_mut().unwrap().set_pts(gst::ClockTime::ZERO);
+    h.push_and_pull(buffer).unwrap();
+
+    let mut timestamps = vec![];
+    while let Some(msg) = bus.pop() {
+        if let gst::MessageView::Element(msg) = msg.view() {
+            let s = msg.structure().unwrap();
+            assert_eq!(s.name(), "audiornnoise");
+            let vad = s.get::<f64>("voice-activity").unwrap();
+            assert!((0.0..=1.0).contains(&vad));
+            assert_eq!(
+                s.get::<gst::ClockTime>("duration").unwrap(),
+                gst::ClockTime::from_mseconds(10)
+            );
+            timestamps.push(s.get::<gst::ClockTime>("timestamp").unwrap());
+        }
+    }
+
+    assert_eq!(
+        timestamps,
+        (0..4)
+            .map(|i| gst::ClockTime::from_mseconds(10 * i))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_rnnoise_invalid_model() {
+    init();
+
+    let path = std::env::temp_dir().join(format!("audiornnoise-{}.rnn", std::process::id()));
+    std::fs::write(&path, b"not a model").unwrap();
+
+    let filter = gst::ElementFactory::make("audiornnoise").build().unwrap();
+    filter.set_property("model-location", path.to_str().unwrap());
+
+    // Invalid models are rejected and the built-in model is kept
+    assert_eq!(filter.property::<Option<String>>("model-location"), None);
+
+    std::fs::remove_file(&path).unwrap();
+}
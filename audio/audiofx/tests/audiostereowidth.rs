@@ -0,0 +1,107 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+fn run(mode: &str, width: f64, caps: gst::Caps, input: &[f64]) -> Vec<f64> {
+    let mut h = gst_check::Harness::new("audiostereowidth");
+    {
+        let element = h.element().unwrap();
+        element.set_property_from_str("mode", mode);
+        element.set_property("width", width);
+    }
+    h.set_src_caps(caps);
+    h.play();
+
+    let mut buffer = gst::Buffer::from_mut_slice(input.as_byte_slice().to_vec());
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::ZERO);
+    }
+    assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+
+    let buffer = h.pull().unwrap();
+    let map = buffer.map_readable().unwrap();
+    map.as_slice_of::<f64>().unwrap().to_vec()
+}
+
+fn stereo_caps() -> gst::Caps {
+    gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F64)
+        .rate(48000)
+        .channels(2)
+        .build()
+}
+
+fn assert_samples_eq(output: &[f64], expected: &[f64]) {
+    assert_eq!(output.len(), expected.len());
+    for (o, e) in output.iter().zip(expected) {
+        assert!((o - e).abs() < 1e-9, "{output:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn test_width() {
+    init();
+
+    let input = [0.5, 0.1, -0.2, 0.4, 0.3, 0.3];
+
+    // Unchanged width
+    let output = run("width", 1.0, stereo_caps(), &input);
+    assert_samples_eq(&output, &input);
+
+    // Downmix to mono
+    let output = run("width", 0.0, stereo_caps(), &input);
+    assert_samples_eq(&output, &[0.3, 0.3, 0.1, 0.1, 0.3, 0.3]);
+
+    // Double the side signal
+    let output = run("width", 2.0, stereo_caps(), &input);
+    assert_samples_eq(&output, &[0.7, -0.1, -0.5, 0.7, 0.3, 0.3]);
+}
+
+#[test]
+fn test_encode_decode() {
+    init();
+
+    let input = [0.5, 0.1, -0.2, 0.4, 0.3, 0.3];
+
+    let encoded = run("encode", 1.0, stereo_caps(), &input);
+    assert_samples_eq(&encoded, &[0.3, 0.2, 0.1, -0.3, 0.3, 0.0]);
+
+    let decoded = run("decode", 1.0, stereo_caps(), &encoded);
+    assert_samples_eq(&decoded, &input);
+}
+
+#[test]
+fn test_channel_mask() {
+    init();
+
+    // 5.1: front left, front right, front center, LFE, rear left, rear right
+    let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F64)
+        .rate(48000)
+        .channels(6)
+        .channel_mask(0x3f)
+        .build();
+
+    let input = [0.5, 0.1, 0.8, 0.9, -0.2, 0.4];
+    let output = run("width", 0.0, caps, &input);
+
+    // Only the front and rear pairs are processed, center and LFE are left untouched
+    assert_samples_eq(&output, &[0.3, 0.3, 0.8, 0.9, 0.1, 0.1]);
+}
@@ -0,0 +1,188 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+const RATE: usize = 48000;
+
+fn sine(frequency: f64, num_samples: usize) -> Vec<f32> {
+    (0..num_samples)
+        .map(|i| {
+            0.5 * f64::sin(2.0 * std::f64::consts::PI * frequency * i as f64 / RATE as f64) as f32
+        })
+        .collect()
+}
+
+// Estimates the frequency of a sine from the number of rising zero crossings
+fn frequency(samples: &[f32]) -> f64 {
+    let crossings = samples
+        .windows(2)
+        .filter(|w| w[0] < 0.0 && w[1] >= 0.0)
+        .count();
+
+    crossings as f64 * RATE as f64 / samples.len() as f64
+}
+
+fn setup(properties: &[(&str, &str)]) -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("audiotimestretch");
+    {
+        let stretch = h.element().unwrap();
+        for (name, value) in properties {
+            stretch.set_property_from_str(name, value);
+        }
+    }
+
+    let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F32)
+        .rate(RATE as i32)
+        .channels(1)
+        .build();
+    h.set_src_caps(caps);
+    h.play();
+
+    h
+}
+
+// Pushes `input` in buffers of 10ms and returns all output buffers after EOS
+fn run(h: &mut gst_check::Harness, input: &[f32]) -> Vec<gst::Buffer> {
+    for (i, chunk) in input.chunks(RATE / 100).enumerate() {
+        let mut buffer = gst::Buffer::from_mut_slice(chunk.as_byte_slice().to_vec());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(10 * i as u64));
+            buffer.set_duration(gst::ClockTime::from_mseconds(10));
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let mut output = vec![];
+    while let Some(buffer) = h.pull_until_eos().unwrap() {
+        output.push(buffer);
+    }
+
+    output
+}
+
+fn samples(buffers: &[gst::Buffer]) -> Vec<f32> {
+    let mut samples = vec![];
+    for buffer in buffers {
+        let map = buffer.map_readable().unwrap();
+        samples.extend_from_slice(map.as_slice_of::<f32>().unwrap());
+    }
+
+    samples
+}
+
+#[test]
+fn test_passthrough() {
+    init();
+
+    let mut h = setup(&[]);
+    let input = sine(440.0, RATE);
+    let output = samples(&run(&mut h, &input));
+
+    assert_eq!(output, input);
+}
+
+#[test]
+fn test_rate() {
+    init();
+
+    let mut h = setup(&[("rate", "2.0")]);
+    let input = sine(440.0, RATE);
+    let buffers = run(&mut h, &input);
+    let output = samples(&buffers);
+
+    // Half the duration with the same pitch
+    assert!(
+        (output.len() as i64 - RATE as i64 / 2).abs() < RATE as i64 / 100,
+        "{} samples",
+        output.len()
+    );
+    let freq = frequency(&output[(RATE / 10)..(RATE / 2 - RATE / 10)]);
+    assert!((freq - 440.0).abs() < 10.0, "frequency {freq}");
+
+    // Timestamps are continuous and start at the beginning
+    assert_eq!(buffers[0].pts(), Some(gst::ClockTime::ZERO));
+    let mut expected_pts = gst::ClockTime::ZERO;
+    for buffer in &buffers {
+        let pts = buffer.pts().unwrap();
+        assert!(
+            pts.max(expected_pts) - pts.min(expected_pts) <= gst::ClockTime::NSECOND,
+            "pts {pts} instead of {expected_pts}"
+        );
+        expected_pts = pts + buffer.duration().unwrap();
+    }
+}
+
+#[test]
+fn test_pitch() {
+    init();
+
+    let mut h = setup(&[("pitch", "2.0")]);
+    let input = sine(440.0, RATE);
+    let output = samples(&run(&mut h, &input));
+
+    // Same duration with twice the pitch
+    assert!(
+        (output.len() as i64 - RATE as i64).abs() < RATE as i64 / 100,
+        "{} samples",
+        output.len()
+    );
+    let freq = frequency(&output[(RATE / 10)..(RATE - RATE / 10)]);
+    assert!((freq - 880.0).abs() < 20.0, "frequency {freq}");
+}
+
+#[test]
+fn test_segment_rate() {
+    init();
+
+    let mut h = setup(&[]);
+
+    let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    segment.set_rate(2.0);
+    assert!(h.push_event(gst::event::Segment::new(&segment)));
+
+    let input = sine(440.0, RATE);
+    let output = samples(&run(&mut h, &input));
+
+    // The rate is applied and moved to the applied rate
+    let mut out_segment = None;
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Segment(e) = event.view() {
+            out_segment = Some(
+                e.segment()
+                    .downcast_ref::<gst::ClockTime>()
+                    .unwrap()
+                    .clone(),
+            );
+        }
+    }
+    let out_segment = out_segment.unwrap();
+    assert_eq!(out_segment.rate(), 1.0);
+    assert_eq!(out_segment.applied_rate(), 2.0);
+
+    assert!(
+        (output.len() as i64 - RATE as i64 / 2).abs() < RATE as i64 / 100,
+        "{} samples",
+        output.len()
+    );
+    let freq = frequency(&output[(RATE / 10)..(RATE / 2 - RATE / 10)]);
+    assert!((freq - 440.0).abs() < 10.0, "frequency {freq}");
+}
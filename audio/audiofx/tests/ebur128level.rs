@@ -151,3 +151,46 @@ fn run_test(layout: gst_audio::AudioLayout, format: gst_audio::AudioFormat) {
 
     assert_eq!(num_msgs, 10);
 }
+
+#[test]
+fn test_ebur128level_per_channel() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse(
+        "audiotestsrc num-buffers=5 samplesperbuffer=48000 ! \
+         audioconvert ! \
+         audio/x-raw,layout=interleaved,format=F32,channels=2,rate=48000 ! \
+         ebur128level interval=500000000 per-channel=true mode=momentary+short-term",
+    );
+    let bus = gst::Bus::new();
+    h.element().unwrap().set_bus(Some(&bus));
+    h.play();
+
+    while let Some(_buffer) = h.pull_until_eos().unwrap() {}
+
+    let mut num_msgs = 0;
+    while let Some(msg) = bus.pop() {
+        match msg.view() {
+            gst::MessageView::Element(msg) => {
+                let s = msg.structure().unwrap();
+                if s.name() == "ebur128-level" {
+                    num_msgs += 1;
+
+                    for field in ["channel-momentary-loudness", "channel-shortterm-loudness"] {
+                        let loudness = s.get::<gst::ArrayRef>(field).unwrap();
+                        assert_eq!(loudness.as_slice().len(), 2);
+                        assert_eq!(loudness.as_slice()[0].type_(), glib::Type::F64);
+                    }
+
+                    // Only the selected metrics are reported
+                    assert!(!s.has_field("channel-global-loudness"));
+                    assert!(!s.has_field("channel-loudness-range"));
+                    assert!(!s.has_field("true-peak"));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    assert_eq!(num_msgs, 10);
+}
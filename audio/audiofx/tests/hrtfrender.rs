@@ -244,3 +244,103 @@ fn test_hrtfrender_multiple_instances_sharing_thread_pool() {
         h.push_event(gst::event::Eos::new());
     }
 }
+
+// Renders a block of noise from a source in front of the listener and returns the energy of the
+// left and right output channel
+fn render_front_source(yaw: f64) -> (f32, f32) {
+    use byte_slice_cast::*;
+
+    let src_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F32)
+        .rate(44_100)
+        .channels(1)
+        .build();
+
+    let sink_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F32)
+        .rate(44_100)
+        .channels(2)
+        .build();
+
+    let (mut h, hrtf) = build_harness(src_caps, sink_caps);
+
+    let obj = gst::Structure::builder("application/spatial-object")
+        .field("x", 0f32)
+        .field("y", 0f32)
+        .field("z", 1f32)
+        .field("distance-gain", 1f32)
+        .build();
+    hrtf.set_property("spatial-objects", gst::Array::new([obj]));
+    hrtf.set_property("yaw", yaw);
+
+    h.play();
+
+    let full_block = 512 * 8;
+    let mut seed = 1u32;
+    let samples = (0..full_block)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) as f32 / 65536.0 - 0.5
+        })
+        .collect::<Vec<f32>>();
+
+    let mut buffer = gst::Buffer::from_mut_slice(samples.as_byte_slice().to_vec());
+    buffer.get_mut().unwrap().set_pts(gst::ClockTime::ZERO);
+
+    let buffer = h.push_and_pull(buffer).unwrap();
+    let map = buffer.map_readable().unwrap();
+    let data = map.as_slice_of::<f32>().unwrap();
+
+    data.chunks_exact(2)
+        .fold((0.0, 0.0), |(left, right), frame| {
+            (left + frame[0] * frame[0], right + frame[1] * frame[1])
+        })
+}
+
+#[test]
+fn test_hrtfrender_head_orientation() {
+    init();
+
+    // Turning the head to the right moves a source in front of the listener to the left
+    let (left, right) = render_front_source(90.0);
+    assert!(left > right, "{left} <= {right}");
+
+    let (left, right) = render_front_source(-90.0);
+    assert!(left < right, "{left} >= {right}");
+}
+
+#[test]
+fn test_hrtfrender_switch_hrir() {
+    init();
+
+    let src_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F32)
+        .rate(44_100)
+        .channels(1)
+        .channel_mask(0x1)
+        .build();
+
+    let sink_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F32)
+        .rate(44_100)
+        .channels(2)
+        .build();
+
+    let (mut h, hrtf) = build_harness(src_caps, sink_caps);
+    h.play();
+
+    let inbpf = 4;
+    let outbpf = 8;
+    let full_block = 512 * 8;
+
+    for i in 0..3 {
+        if i == 1 {
+            // The new impulse response is crossfaded in over the next block
+            hrtf.set_property("hrir-raw", &*CONFIG);
+        }
+
+        let buffer = gst::Buffer::with_size(full_block * inbpf).unwrap();
+        let buffer = h.push_and_pull(buffer).unwrap();
+        assert_eq!(buffer.size(), full_block * outbpf);
+    }
+}
@@ -9,16 +9,20 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use gst::glib;
+use gst::prelude::*;
 use gst::subclass::prelude::*;
 use gst_audio::prelude::*;
 use gst_audio::subclass::prelude::*;
 
 use std::io::Cursor;
+use std::sync::Mutex;
 
 use atomic_refcell::AtomicRefCell;
 
 use byte_slice_cast::*;
 
+use md5::{Digest, Md5};
+
 use once_cell::sync::Lazy;
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
@@ -29,13 +33,78 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     )
 });
 
+const DEFAULT_VERIFY_MD5: bool = false;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    verify_md5: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            verify_md5: DEFAULT_VERIFY_MD5,
+        }
+    }
+}
+
 struct State {
     audio_info: Option<gst_audio::AudioInfo>,
+    bits_per_sample: u32,
+    // MD5 signature of the unencoded audio from the STREAMINFO, if known
+    md5sum: Option<[u8; 16]>,
+    // Running MD5 of the decoded samples, only while the whole stream is decoded in order
+    md5: Option<Md5>,
+}
+
+impl State {
+    fn new(audio_info: Option<gst_audio::AudioInfo>) -> Self {
+        State {
+            audio_info,
+            bits_per_sample: 0,
+            md5sum: None,
+            md5: None,
+        }
+    }
+
+    fn set_streaminfo(&mut self, streaminfo: &claxon::metadata::StreamInfo, verify_md5: bool) {
+        self.bits_per_sample = streaminfo.bits_per_sample;
+
+        // An all-zero signature means that the encoder did not calculate it
+        if streaminfo.md5sum != [0; 16] {
+            self.md5sum = Some(streaminfo.md5sum);
+            self.md5 = verify_md5.then(Md5::new);
+        } else {
+            self.md5sum = None;
+            self.md5 = None;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SeekPoint {
+    /// Sample number of the first sample in the target frame
+    sample: u64,
+    /// Offset in bytes from the first frame header to the target frame header
+    offset: u64,
+}
+
+/// State shared with the source pad event handler for seeking via the SEEKTABLE
+#[derive(Debug, Default)]
+struct SeekState {
+    seektable: Vec<SeekPoint>,
+    // Size of the stream marker and all metadata blocks before the first frame
+    headers_size: u64,
+    rate: u32,
+    // Sample to start output at after a seek to a seek point before it
+    target: Option<u64>,
 }
 
 #[derive(Default)]
 pub struct ClaxonDec {
+    settings: Mutex<Settings>,
     state: AtomicRefCell<Option<State>>,
+    seek_state: Mutex<SeekState>,
 }
 
 #[glib::object_subclass]
@@ -45,7 +114,48 @@ impl ObjectSubclass for ClaxonDec {
     type ParentType = gst_audio::AudioDecoder;
 }
 
-impl ObjectImpl for ClaxonDec {}
+impl ObjectImpl for ClaxonDec {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecBoolean::builder("verify-md5")
+                .nick("Verify MD5")
+                .blurb("Verify the decoded samples against the MD5 signature of the stream at EOS")
+                .default_value(DEFAULT_VERIFY_MD5)
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "verify-md5" => {
+                let mut settings = self.settings.lock().unwrap();
+                let verify_md5 = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing verify-md5 from {} to {}",
+                    settings.verify_md5,
+                    verify_md5
+                );
+                settings.verify_md5 = verify_md5;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "verify-md5" => {
+                let settings = self.settings.lock().unwrap();
+                settings.verify_md5.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
 
 impl GstObjectImpl for ClaxonDec {}
 
@@ -109,7 +219,8 @@ impl AudioDecoderImpl for ClaxonDec {
     }
 
     fn start(&self) -> Result<(), gst::ErrorMessage> {
-        *self.state.borrow_mut() = Some(State { audio_info: None });
+        *self.state.borrow_mut() = Some(State::new(None));
+        *self.seek_state.lock().unwrap() = SeekState::default();
 
         Ok(())
     }
@@ -118,6 +229,7 @@ impl AudioDecoderImpl for ClaxonDec {
         gst::debug!(CAT, imp: self, "Setting format {:?}", caps);
 
         let mut audio_info: Option<gst_audio::AudioInfo> = None;
+        let mut streaminfo = None;
 
         let s = caps.structure(0).unwrap();
         if let Ok(Some(streamheaders)) = s.get_optional::<gst::ArrayRef>("streamheader") {
@@ -149,17 +261,68 @@ impl AudioDecoderImpl for ClaxonDec {
 
                             audio_info = Some(taudio_info);
                         }
+                        streaminfo = Some(tstreaminfo);
                     }
                 }
             }
         }
 
+        if let Some(ref audio_info) = audio_info {
+            self.seek_state.lock().unwrap().rate = audio_info.rate();
+        }
+
+        let mut state = State::new(audio_info);
+        if let Some(ref streaminfo) = streaminfo {
+            state.set_streaminfo(streaminfo, self.settings.lock().unwrap().verify_md5);
+        }
+
         let mut state_guard = self.state.borrow_mut();
-        *state_guard = Some(State { audio_info });
+        *state_guard = Some(state);
 
         Ok(())
     }
 
+    fn flush(&self, _hard: bool) {
+        gst::debug!(CAT, imp: self, "Flushing");
+
+        // After a seek not all samples are decoded anymore
+        let mut state_guard = self.state.borrow_mut();
+        if let Some(ref mut state) = *state_guard {
+            if state.md5.take().is_some() {
+                gst::debug!(CAT, imp: self, "Disabling MD5 verification after flush");
+            }
+        }
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            self.verify_md5();
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn src_event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Seek(seek) = event.view() {
+            // Upstream, usually flacparse, can handle time seeks on its own
+            if seek.get().3.format() == gst::Format::Time
+                && self.obj().sink_pad().push_event(event.clone())
+            {
+                return true;
+            }
+
+            if let Some(seek) = self.seektable_seek(seek) {
+                gst::debug!(CAT, imp: self, "Seeking upstream via SEEKTABLE: {:?}", seek);
+                if self.obj().sink_pad().push_event(seek) {
+                    return true;
+                }
+                self.seek_state.lock().unwrap().target = None;
+            }
+        }
+
+        self.parent_src_event(event)
+    }
+
     #[allow(clippy::verbose_bit_mask)]
     fn handle_frame(
         &self,
@@ -182,20 +345,29 @@ impl AudioDecoderImpl for ClaxonDec {
 
         if inmap.as_slice() == b"fLaC" {
             gst::debug!(CAT, imp: self, "fLaC buffer received");
-        } else if inmap[0] & 0x7F == 0x00 {
-            gst::debug!(CAT, imp: self, "Streaminfo header buffer received");
-            return self.handle_streaminfo_header(state, inmap.as_ref());
+            self.seek_state.lock().unwrap().headers_size = inmap.len() as u64;
         } else if inmap[0] == 0b1111_1111 && inmap[1] & 0b1111_1100 == 0b1111_1000 {
             gst::debug!(CAT, imp: self, "Data buffer received");
             return self.handle_data(state, inmap.as_ref());
         } else {
-            // info about other headers in flacparse and https://xiph.org/flac/format.html
-            gst::debug!(
-                CAT,
-                imp: self,
-                "Other header buffer received {:?}",
-                inmap[0] & 0x7F
-            );
+            // Everything else is a metadata block that comes before the first frame
+            self.seek_state.lock().unwrap().headers_size += inmap.len() as u64;
+
+            if inmap[0] & 0x7F == 0x00 {
+                gst::debug!(CAT, imp: self, "Streaminfo header buffer received");
+                return self.handle_streaminfo_header(state, inmap.as_ref());
+            } else if inmap[0] & 0x7F == 0x03 {
+                gst::debug!(CAT, imp: self, "Seektable header buffer received");
+                self.handle_seektable_header(inmap.as_ref());
+            } else {
+                // info about other headers in flacparse and https://xiph.org/flac/format.html
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Other header buffer received {:?}",
+                    inmap[0] & 0x7F
+                );
+            }
         }
 
         self.obj().finish_frame(None, 1)
@@ -229,11 +401,124 @@ impl ClaxonDec {
         element.set_output_format(&audio_info)?;
         element.negotiate()?;
 
+        self.seek_state.lock().unwrap().rate = audio_info.rate();
+        state.set_streaminfo(&streaminfo, self.settings.lock().unwrap().verify_md5);
         state.audio_info = Some(audio_info);
 
         element.finish_frame(None, 1)
     }
 
+    fn handle_seektable_header(&self, indata: &[u8]) {
+        // Skip the metadata block header, every seek point is 18 bytes
+        let seektable = indata
+            .get(4..)
+            .unwrap_or_default()
+            .chunks_exact(18)
+            .filter_map(|point| {
+                let sample = u64::from_be_bytes(point[0..8].try_into().unwrap());
+                // Placeholder points
+                if sample == u64::MAX {
+                    return None;
+                }
+                let offset = u64::from_be_bytes(point[8..16].try_into().unwrap());
+
+                Some(SeekPoint { sample, offset })
+            })
+            .collect::<Vec<_>>();
+
+        gst::debug!(CAT, imp: self, "Got {} seek points", seektable.len());
+
+        self.seek_state.lock().unwrap().seektable = seektable;
+    }
+
+    /// Converts a time seek into a byte seek to the closest seek point before the target.
+    fn seektable_seek(&self, seek: &gst::event::Seek) -> Option<gst::Event> {
+        let (rate, flags, start_type, start, _, _) = seek.get();
+        if rate <= 0.0 || start_type != gst::SeekType::Set {
+            return None;
+        }
+
+        let start = match start {
+            gst::GenericFormattedValue::Time(Some(start)) => start,
+            _ => return None,
+        };
+
+        let mut seek_state = self.seek_state.lock().unwrap();
+        if seek_state.rate == 0 {
+            return None;
+        }
+
+        let sample = start
+            .nseconds()
+            .mul_div_floor(seek_state.rate as u64, *gst::ClockTime::SECOND)?;
+        // Seek points are sorted by sample number
+        let point = *seek_state
+            .seektable
+            .iter()
+            .rev()
+            .find(|point| point.sample <= sample)?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Seeking to sample {} via seek point {:?}",
+            sample,
+            point
+        );
+
+        // Frames before the target are decoded but not output for accurate seeks
+        seek_state.target = flags.contains(gst::SeekFlags::ACCURATE).then_some(sample);
+
+        Some(gst::event::Seek::new(
+            rate,
+            flags,
+            gst::SeekType::Set,
+            Some(gst::format::Bytes::from_u64(
+                seek_state.headers_size + point.offset,
+            )),
+            gst::SeekType::None,
+            gst::format::Bytes::NONE,
+        ))
+    }
+
+    /// Returns the number of samples at the start of a frame that come before the seek target.
+    fn samples_before_target(&self, time: u64, duration: u64) -> u64 {
+        let mut seek_state = self.seek_state.lock().unwrap();
+        let Some(target) = seek_state.target else {
+            return 0;
+        };
+
+        if time + duration <= target {
+            return duration;
+        }
+
+        seek_state.target = None;
+        target.saturating_sub(time)
+    }
+
+    fn verify_md5(&self) {
+        let mut state_guard = self.state.borrow_mut();
+        let Some(state) = state_guard.as_mut() else {
+            return;
+        };
+
+        let (Some(md5), Some(md5sum)) = (state.md5.take(), state.md5sum) else {
+            return;
+        };
+
+        let digest = md5.finalize();
+        if digest.as_slice() == md5sum {
+            gst::debug!(CAT, imp: self, "MD5 signature verified");
+        } else {
+            gst::element_imp_warning!(
+                self,
+                gst::StreamError::Decode,
+                ["Decoded audio does not match the MD5 signature of the stream"],
+                ["Expected {:02x?}, got {:02x?}", md5sum, digest.as_slice()]
+            );
+        }
+    }
+
     fn handle_data(
         &self,
         state: &mut State,
@@ -273,7 +558,10 @@ impl ClaxonDec {
 
         assert_eq!(cursor.position(), indata.len() as u64);
 
-        let v = if channels != 1 {
+        let time = result.time();
+        let duration = result.duration() as u64;
+
+        let mut v = if channels != 1 {
             let mut v: Vec<i32> = vec![0; result.len() as usize];
 
             for (o, i) in v.chunks_exact_mut(channels).enumerate() {
@@ -286,6 +574,30 @@ impl ClaxonDec {
             result.into_buffer()
         };
 
+        if let Some(ref mut md5) = state.md5 {
+            // The signature is over the samples in little endian with the minimum number of bytes
+            let bytes = ((state.bits_per_sample + 7) / 8) as usize;
+            let mut data = Vec::with_capacity(v.len() * bytes);
+            for s in &v {
+                data.extend_from_slice(&s.to_le_bytes()[..bytes]);
+            }
+            md5.update(&data);
+        }
+
+        let skip = self.samples_before_target(time, duration) as usize;
+        if skip > 0 {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Skipping {} samples before the seek target",
+                skip
+            );
+            if skip >= duration as usize {
+                return self.obj().finish_frame(None, 1);
+            }
+            v.drain(..skip * channels);
+        }
+
         let depth_adjusted = depth.adjust_samples(v);
         let outbuf = gst::Buffer::from_mut_slice(depth_adjusted);
         self.obj().finish_frame(Some(outbuf), 1)
@@ -0,0 +1,803 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+// FLAC frame and metadata block encoding, see https://xiph.org/flac/format.html
+//
+// All frames use a fixed block size. Every channel is encoded as the smallest of a constant,
+// verbatim, fixed predictor or LPC subframe, and stereo input can additionally use the
+// left/side, right/side and mid/side channel decorrelation modes.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderParams {
+    pub block_size: u32,
+    pub max_lpc_order: usize,
+    pub max_partition_order: u32,
+    pub stereo_decorrelation: bool,
+    pub exhaustive_model_search: bool,
+}
+
+impl EncoderParams {
+    /// Parameters for the compression levels 0 to 8, similar to the ones of the reference encoder
+    pub fn from_compression_level(level: u32) -> Self {
+        let (block_size, max_lpc_order, max_partition_order, stereo_decorrelation) = match level {
+            0 => (1152, 0, 3, false),
+            1 => (1152, 0, 3, true),
+            2 => (1152, 0, 4, true),
+            3 => (4096, 6, 4, false),
+            4 => (4096, 8, 4, true),
+            5 => (4096, 8, 5, true),
+            6 => (4096, 8, 6, true),
+            7 => (4096, 12, 6, true),
+            _ => (4096, 12, 6, true),
+        };
+
+        EncoderParams {
+            block_size,
+            max_lpc_order,
+            max_partition_order,
+            stereo_decorrelation,
+            exhaustive_model_search: level >= 8,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, n: u32) {
+        debug_assert!(n <= 32);
+        if n == 0 {
+            return;
+        }
+
+        self.acc = (self.acc << n) | (value & ((1 << n) - 1));
+        self.bits += n;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.data.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1 << self.bits) - 1;
+    }
+
+    fn write_signed(&mut self, value: i64, n: u32) {
+        self.write(value as u64, n);
+    }
+
+    fn write_unary(&mut self, mut value: u64) {
+        while value >= 32 {
+            self.write(0, 32);
+            value -= 32;
+        }
+        self.write(1, value as u32 + 1);
+    }
+
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+
+        let len = match value {
+            0..=0x7ff => 2,
+            0x800..=0xffff => 3,
+            0x1_0000..=0x1f_ffff => 4,
+            0x20_0000..=0x3ff_ffff => 5,
+            0x400_0000..=0x7fff_ffff => 6,
+            _ => 7,
+        };
+
+        self.write(((0xff00 >> len) & 0xff) | (value >> (6 * (len - 1))), 8);
+        for i in (0..len - 1).rev() {
+            self.write(0x80 | ((value >> (6 * i)) & 0x3f), 8);
+        }
+    }
+
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |mut crc, byte| {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[derive(Debug)]
+struct Rice {
+    partition_order: u32,
+    params: Vec<u32>,
+}
+
+#[derive(Debug)]
+enum SubframeKind {
+    Constant(i64),
+    Verbatim,
+    Fixed {
+        order: usize,
+        residual: Vec<i64>,
+        rice: Rice,
+    },
+    Lpc {
+        precision: u32,
+        shift: u32,
+        coefs: Vec<i32>,
+        residual: Vec<i64>,
+        rice: Rice,
+    },
+}
+
+#[derive(Debug)]
+struct Subframe<'a> {
+    samples: &'a [i64],
+    bps: u32,
+    kind: SubframeKind,
+    bits: u64,
+}
+
+/// Estimated number of bits for Rice coding `n` values with sum `sum` using parameter `k`
+fn rice_bits(sum: u64, n: u64, k: u32) -> u64 {
+    n * (k as u64 + 1) + (sum >> k)
+}
+
+fn best_rice_param(sum: u64, n: u64) -> (u32, u64) {
+    (0..=30)
+        .map(|k| (k, rice_bits(sum, n, k)))
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap()
+}
+
+/// Selects the partition order and Rice parameters for a residual of a block of `block_size`
+/// samples with `order` warm-up samples
+fn choose_rice(
+    residual: &[i64],
+    block_size: usize,
+    order: usize,
+    max_partition_order: u32,
+) -> (Rice, u64) {
+    let mut max_order = 0;
+    for o in 0..=max_partition_order {
+        if block_size % (1 << o) != 0 || (block_size >> o) <= order {
+            break;
+        }
+        max_order = o;
+    }
+
+    // Sums of the zigzag encoded residuals for the partitions of the highest order, lower
+    // orders are merged from those
+    let partition_size = block_size >> max_order;
+    let mut sums = Vec::with_capacity(1 << max_order);
+    let mut counts = Vec::with_capacity(1 << max_order);
+    let mut start = 0;
+    for p in 0..(1 << max_order) {
+        let len = if p == 0 {
+            partition_size - order
+        } else {
+            partition_size
+        };
+        let sum = residual[start..start + len]
+            .iter()
+            .map(|r| ((r << 1) ^ (r >> 63)) as u64)
+            .sum::<u64>();
+        sums.push(sum);
+        counts.push(len as u64);
+        start += len;
+    }
+
+    let mut best: Option<(Rice, u64)> = None;
+    loop {
+        let params = sums
+            .iter()
+            .zip(counts.iter())
+            .map(|(sum, n)| best_rice_param(*sum, *n))
+            .collect::<Vec<_>>();
+        let param_bits = if params.iter().any(|(k, _)| *k > 14) {
+            5
+        } else {
+            4
+        };
+        let bits = 2 + 4 + params.iter().map(|(_, b)| param_bits + b).sum::<u64>();
+
+        let is_better = match best {
+            Some((_, best_bits)) => bits < best_bits,
+            None => true,
+        };
+        if is_better {
+            best = Some((
+                Rice {
+                    partition_order: (sums.len() as u32).trailing_zeros(),
+                    params: params.into_iter().map(|(k, _)| k).collect(),
+                },
+                bits,
+            ));
+        }
+
+        if sums.len() == 1 {
+            break;
+        }
+        sums = sums.chunks_exact(2).map(|s| s[0] + s[1]).collect();
+        counts = counts.chunks_exact(2).map(|c| c[0] + c[1]).collect();
+    }
+
+    best.unwrap()
+}
+
+fn fits_residual(residual: &[i64]) -> bool {
+    residual
+        .iter()
+        .all(|r| (i32::MIN as i64..=i32::MAX as i64).contains(r))
+}
+
+fn fixed_residual(samples: &[i64], order: usize) -> Vec<i64> {
+    samples
+        .windows(order + 1)
+        .map(|w| match order {
+            0 => w[0],
+            1 => w[1] - w[0],
+            2 => w[2] - 2 * w[1] + w[0],
+            3 => w[3] - 3 * w[2] + 3 * w[1] - w[0],
+            _ => w[4] - 4 * w[3] + 6 * w[2] - 4 * w[1] + w[0],
+        })
+        .collect()
+}
+
+/// Linear prediction coefficients for all orders up to `max_order` and their prediction error,
+/// calculated from a Tukey(0.5) windowed signal
+fn lpc_coefs(samples: &[i64], max_order: usize) -> Vec<(Vec<f64>, f64)> {
+    let n = samples.len();
+    let taper = n / 4;
+    let windowed = samples
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let w = if taper > 0 && i < taper {
+                0.5 - 0.5 * (std::f64::consts::PI * i as f64 / taper as f64).cos()
+            } else if taper > 0 && i >= n - taper {
+                0.5 - 0.5 * (std::f64::consts::PI * (n - 1 - i) as f64 / taper as f64).cos()
+            } else {
+                1.0
+            };
+            *s as f64 * w
+        })
+        .collect::<Vec<_>>();
+
+    let autoc = (0..=max_order)
+        .map(|lag| {
+            windowed[lag..]
+                .iter()
+                .zip(windowed.iter())
+                .map(|(a, b)| a * b)
+                .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut res = Vec::with_capacity(max_order);
+    if autoc[0] <= 0.0 {
+        return res;
+    }
+
+    // Levinson-Durbin recursion
+    let mut coefs = vec![0.0; max_order];
+    let mut err = autoc[0];
+    for i in 0..max_order {
+        let k = (autoc[i + 1]
+            - coefs[..i]
+                .iter()
+                .enumerate()
+                .map(|(j, c)| c * autoc[i - j])
+                .sum::<f64>())
+            / err;
+
+        let prev = coefs[..i].to_vec();
+        coefs[i] = k;
+        for (j, c) in coefs[..i].iter_mut().enumerate() {
+            *c = prev[j] - k * prev[i - 1 - j];
+        }
+
+        err *= 1.0 - k * k;
+        if err <= 0.0 || !err.is_finite() {
+            break;
+        }
+        res.push((coefs[..=i].to_vec(), err));
+    }
+
+    res
+}
+
+fn qlp_precision(block_size: usize) -> u32 {
+    match block_size {
+        0..=192 => 7,
+        193..=384 => 8,
+        385..=576 => 9,
+        577..=1152 => 10,
+        1153..=2304 => 11,
+        2305..=4608 => 12,
+        _ => 13,
+    }
+}
+
+/// Quantizes the coefficients to `precision` bits and returns them with the shift
+fn quantize_coefs(coefs: &[f64], precision: u32) -> Option<(Vec<i32>, u32)> {
+    let cmax = coefs.iter().fold(0.0f64, |m, c| m.max(c.abs()));
+    if cmax <= 0.0 || !cmax.is_finite() {
+        return None;
+    }
+
+    let qmax = (1i32 << (precision - 1)) - 1;
+    let qmin = -(1i32 << (precision - 1));
+
+    let log2cmax = cmax.log2().floor() as i32 + 1;
+    // Negative shifts are not allowed and the shift is stored as 5 bit signed integer
+    let shift = (precision as i32 - 1 - log2cmax).clamp(0, 15) as u32;
+
+    let mut err = 0.0;
+    let qcoefs = coefs
+        .iter()
+        .map(|c| {
+            err += c * (1 << shift) as f64;
+            let q = (err.round() as i32).clamp(qmin, qmax);
+            err -= q as f64;
+            q
+        })
+        .collect();
+
+    Some((qcoefs, shift))
+}
+
+fn lpc_residual(samples: &[i64], coefs: &[i32], shift: u32) -> Vec<i64> {
+    let order = coefs.len();
+    samples
+        .windows(order + 1)
+        .map(|w| {
+            let prediction = coefs
+                .iter()
+                .enumerate()
+                .map(|(j, c)| *c as i64 * w[order - 1 - j])
+                .sum::<i64>();
+            w[order] - (prediction >> shift)
+        })
+        .collect()
+}
+
+fn encode_subframe<'a>(samples: &'a [i64], bps: u32, params: &EncoderParams) -> Subframe<'a> {
+    let n = samples.len();
+
+    if samples.iter().all(|s| *s == samples[0]) {
+        return Subframe {
+            samples,
+            bps,
+            kind: SubframeKind::Constant(samples[0]),
+            bits: 8 + bps as u64,
+        };
+    }
+
+    let mut best = Subframe {
+        samples,
+        bps,
+        kind: SubframeKind::Verbatim,
+        bits: 8 + n as u64 * bps as u64,
+    };
+
+    for order in 0..=usize::min(4, n - 1) {
+        let residual = fixed_residual(samples, order);
+        if !fits_residual(&residual) {
+            continue;
+        }
+
+        let (rice, rice_bits) = choose_rice(&residual, n, order, params.max_partition_order);
+        let bits = 8 + order as u64 * bps as u64 + rice_bits;
+        if bits < best.bits {
+            best = Subframe {
+                samples,
+                bps,
+                kind: SubframeKind::Fixed {
+                    order,
+                    residual,
+                    rice,
+                },
+                bits,
+            };
+        }
+    }
+
+    let max_lpc_order = usize::min(params.max_lpc_order, n - 1);
+    if max_lpc_order == 0 {
+        return best;
+    }
+
+    let candidates = lpc_coefs(samples, max_lpc_order);
+    if candidates.is_empty() {
+        return best;
+    }
+
+    let precision = qlp_precision(n);
+    let orders = if params.exhaustive_model_search {
+        (1..=candidates.len()).collect::<Vec<_>>()
+    } else {
+        // Pick the order with the lowest estimated size based on the prediction error
+        let estimate = |order: usize| {
+            let err = candidates[order - 1].1;
+            let bits_per_residual = f64::max(0.0, 0.5 * (err / n as f64).log2());
+            bits_per_residual * (n - order) as f64 + (order as u32 * (precision + bps)) as f64
+        };
+        let order = (1..=candidates.len())
+            .min_by(|a, b| estimate(*a).total_cmp(&estimate(*b)))
+            .unwrap();
+        vec![order]
+    };
+
+    for order in orders {
+        let Some((coefs, shift)) = quantize_coefs(&candidates[order - 1].0, precision) else {
+            continue;
+        };
+
+        let residual = lpc_residual(samples, &coefs, shift);
+        if !fits_residual(&residual) {
+            continue;
+        }
+
+        let (rice, rice_bits) = choose_rice(&residual, n, order, params.max_partition_order);
+        let bits = 8 + order as u64 * (bps + precision) as u64 + 4 + 5 + rice_bits;
+        if bits < best.bits {
+            best = Subframe {
+                samples,
+                bps,
+                kind: SubframeKind::Lpc {
+                    precision,
+                    shift,
+                    coefs,
+                    residual,
+                    rice,
+                },
+                bits,
+            };
+        }
+    }
+
+    best
+}
+
+fn write_residual(w: &mut BitWriter, residual: &[i64], order: usize, rice: &Rice) {
+    let param_bits = if rice.params.iter().any(|k| *k > 14) {
+        5
+    } else {
+        4
+    };
+
+    w.write(if param_bits == 5 { 0b01 } else { 0b00 }, 2);
+    w.write(rice.partition_order as u64, 4);
+
+    let partition_size = (residual.len() + order) >> rice.partition_order;
+    let mut start = 0;
+    for (p, k) in rice.params.iter().enumerate() {
+        let len = if p == 0 {
+            partition_size - order
+        } else {
+            partition_size
+        };
+
+        w.write(*k as u64, param_bits);
+        for r in &residual[start..start + len] {
+            let u = ((r << 1) ^ (r >> 63)) as u64;
+            w.write_unary(u >> k);
+            w.write(u, *k);
+        }
+        start += len;
+    }
+}
+
+fn write_subframe(w: &mut BitWriter, subframe: &Subframe) {
+    let bps = subframe.bps;
+    let samples = subframe.samples;
+
+    // Zero padding bit, 6 bit type, no wasted bits
+    match subframe.kind {
+        SubframeKind::Constant(value) => {
+            w.write(0b0000_0000, 8);
+            w.write_signed(value, bps);
+        }
+        SubframeKind::Verbatim => {
+            w.write(0b0000_0010, 8);
+            for s in samples {
+                w.write_signed(*s, bps);
+            }
+        }
+        SubframeKind::Fixed {
+            order,
+            ref residual,
+            ref rice,
+        } => {
+            w.write((0b001000 | order as u64) << 1, 8);
+            for s in &samples[..order] {
+                w.write_signed(*s, bps);
+            }
+            write_residual(w, residual, order, rice);
+        }
+        SubframeKind::Lpc {
+            precision,
+            shift,
+            ref coefs,
+            ref residual,
+            ref rice,
+        } => {
+            let order = coefs.len();
+            w.write((0b100000 | (order as u64 - 1)) << 1, 8);
+            for s in &samples[..order] {
+                w.write_signed(*s, bps);
+            }
+            w.write(precision as u64 - 1, 4);
+            w.write(shift as u64, 5);
+            for c in coefs {
+                w.write_signed(*c as i64, precision);
+            }
+            write_residual(w, residual, order, rice);
+        }
+    }
+}
+
+fn block_size_code(block_size: u32) -> (u64, Option<(u64, u32)>) {
+    match block_size {
+        192 => (0b0001, None),
+        576 => (0b0010, None),
+        1152 => (0b0011, None),
+        2304 => (0b0100, None),
+        4608 => (0b0101, None),
+        256 => (0b1000, None),
+        512 => (0b1001, None),
+        1024 => (0b1010, None),
+        2048 => (0b1011, None),
+        4096 => (0b1100, None),
+        8192 => (0b1101, None),
+        16384 => (0b1110, None),
+        32768 => (0b1111, None),
+        1..=256 => (0b0110, Some((block_size as u64 - 1, 8))),
+        _ => (0b0111, Some((block_size as u64 - 1, 16))),
+    }
+}
+
+fn sample_rate_code(rate: u32) -> (u64, Option<(u64, u32)>) {
+    match rate {
+        88_200 => (0b0001, None),
+        176_400 => (0b0010, None),
+        192_000 => (0b0011, None),
+        8_000 => (0b0100, None),
+        16_000 => (0b0101, None),
+        22_050 => (0b0110, None),
+        24_000 => (0b0111, None),
+        32_000 => (0b1000, None),
+        44_100 => (0b1001, None),
+        48_000 => (0b1010, None),
+        96_000 => (0b1011, None),
+        _ if rate % 1000 == 0 && rate / 1000 <= 255 => (0b1100, Some((rate as u64 / 1000, 8))),
+        _ if rate <= 65535 => (0b1101, Some((rate as u64, 16))),
+        _ if rate % 10 == 0 && rate / 10 <= 65535 => (0b1110, Some((rate as u64 / 10, 16))),
+        // Taken from the STREAMINFO
+        _ => (0b0000, None),
+    }
+}
+
+fn sample_size_code(bps: u32) -> u64 {
+    match bps {
+        8 => 0b001,
+        12 => 0b010,
+        16 => 0b100,
+        20 => 0b101,
+        24 => 0b110,
+        32 => 0b111,
+        _ => 0b000,
+    }
+}
+
+/// Encodes one frame from the samples of each channel, all channels must have the same
+/// number of samples.
+pub fn encode_frame(
+    params: &EncoderParams,
+    frame_number: u64,
+    sample_rate: u32,
+    bps: u32,
+    channels: &[Vec<i64>],
+) -> Vec<u8> {
+    let block_size = channels[0].len() as u32;
+    debug_assert!(channels.iter().all(|c| c.len() == block_size as usize));
+
+    let (channel_assignment, mut subframes) = if channels.len() == 2 && params.stereo_decorrelation
+    {
+        let (left, right) = (&channels[0], &channels[1]);
+        let side = left
+            .iter()
+            .zip(right.iter())
+            .map(|(l, r)| l - r)
+            .collect::<Vec<_>>();
+        let mid = left
+            .iter()
+            .zip(right.iter())
+            .map(|(l, r)| (l + r) >> 1)
+            .collect::<Vec<_>>();
+
+        let left = encode_subframe(left, bps, params);
+        let right = encode_subframe(right, bps, params);
+        let side = encode_subframe(&side, bps + 1, params);
+        let mid = encode_subframe(&mid, bps, params);
+
+        let independent = left.bits + right.bits;
+        let left_side = left.bits + side.bits;
+        let right_side = side.bits + right.bits;
+        let mid_side = mid.bits + side.bits;
+
+        let best = independent.min(left_side).min(right_side).min(mid_side);
+        let mut w = BitWriter::default();
+        let assignment = if best == independent {
+            write_subframe(&mut w, &left);
+            write_subframe(&mut w, &right);
+            0b0001
+        } else if best == left_side {
+            write_subframe(&mut w, &left);
+            write_subframe(&mut w, &side);
+            0b1000
+        } else if best == right_side {
+            write_subframe(&mut w, &side);
+            write_subframe(&mut w, &right);
+            0b1001
+        } else {
+            write_subframe(&mut w, &mid);
+            write_subframe(&mut w, &side);
+            0b1010
+        };
+
+        (assignment, w)
+    } else {
+        let mut w = BitWriter::default();
+        for channel in channels {
+            let subframe = encode_subframe(channel, bps, params);
+            write_subframe(&mut w, &subframe);
+        }
+
+        (channels.len() as u64 - 1, w)
+    };
+
+    let (block_size_code, block_size_extra) = block_size_code(block_size);
+    let (sample_rate_code, sample_rate_extra) = sample_rate_code(sample_rate);
+
+    let mut w = BitWriter::default();
+    // Sync code, reserved bit and fixed block size strategy
+    w.write(0b1111_1111_1111_1000, 16);
+    w.write(block_size_code, 4);
+    w.write(sample_rate_code, 4);
+    w.write(channel_assignment, 4);
+    w.write(sample_size_code(bps), 3);
+    w.write(0, 1);
+    w.write_utf8(frame_number);
+    if let Some((value, bits)) = block_size_extra {
+        w.write(value, bits);
+    }
+    if let Some((value, bits)) = sample_rate_extra {
+        w.write(value, bits);
+    }
+    let crc = crc8(&w.data);
+    w.write(crc as u64, 8);
+
+    subframes.align();
+    w.data.extend_from_slice(&subframes.data);
+
+    let crc = crc16(&w.data);
+    w.write(crc as u64, 16);
+
+    w.data
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekPoint {
+    pub sample: u64,
+    pub offset: u64,
+    pub samples: u16,
+}
+
+impl SeekPoint {
+    pub const PLACEHOLDER: SeekPoint = SeekPoint {
+        sample: u64::MAX,
+        offset: 0,
+        samples: 0,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub block_size: u16,
+    pub min_frame_size: u32,
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bits_per_sample: u32,
+    pub total_samples: u64,
+    pub md5sum: [u8; 16],
+}
+
+fn metadata_block_header(w: &mut BitWriter, last: bool, block_type: u64, len: usize) {
+    w.write(last as u64, 1);
+    w.write(block_type, 7);
+    w.write(len as u64, 24);
+}
+
+impl StreamInfo {
+    pub fn to_bytes(&self, last: bool) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        metadata_block_header(&mut w, last, 0, 34);
+        w.write(self.block_size as u64, 16);
+        w.write(self.block_size as u64, 16);
+        w.write(self.min_frame_size as u64, 24);
+        w.write(self.max_frame_size as u64, 24);
+        w.write(self.sample_rate as u64, 20);
+        w.write(self.channels as u64 - 1, 3);
+        w.write(self.bits_per_sample as u64 - 1, 5);
+        w.write(self.total_samples >> 32, 4);
+        w.write(self.total_samples & 0xffff_ffff, 32);
+        w.data.extend_from_slice(&self.md5sum);
+
+        w.data
+    }
+}
+
+/// SEEKTABLE block with `count` points, unused points are filled with placeholders
+pub fn seektable_to_bytes(points: &[SeekPoint], count: usize, last: bool) -> Vec<u8> {
+    let mut w = BitWriter::default();
+    metadata_block_header(&mut w, last, 3, 18 * count);
+    for point in points
+        .iter()
+        .chain(std::iter::repeat(&SeekPoint::PLACEHOLDER))
+        .take(count)
+    {
+        w.write(point.sample >> 32, 32);
+        w.write(point.sample & 0xffff_ffff, 32);
+        w.write(point.offset >> 32, 32);
+        w.write(point.offset & 0xffff_ffff, 32);
+        w.write(point.samples as u64, 16);
+    }
+
+    w.data
+}
+
+/// VORBIS_COMMENT block with only the vendor string
+pub fn vorbis_comment_to_bytes(vendor: &str, last: bool) -> Vec<u8> {
+    let mut w = BitWriter::default();
+    metadata_block_header(&mut w, last, 4, 4 + vendor.len() + 4);
+    w.data
+        .extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    w.data.extend_from_slice(vendor.as_bytes());
+    w.data.extend_from_slice(&0u32.to_le_bytes());
+
+    w.data
+}
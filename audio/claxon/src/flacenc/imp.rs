@@ -0,0 +1,503 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_audio::prelude::*;
+use gst_audio::subclass::prelude::*;
+
+use std::sync::Mutex;
+
+use atomic_refcell::AtomicRefCell;
+
+use byte_slice_cast::*;
+
+use md5::{Digest, Md5};
+
+use once_cell::sync::Lazy;
+
+use super::encoder::{self, EncoderParams, SeekPoint, StreamInfo};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsflacenc",
+        gst::DebugColorFlags::empty(),
+        Some("Rust FLAC encoder"),
+    )
+});
+
+const DEFAULT_COMPRESSION_LEVEL: u32 = 5;
+const DEFAULT_SEEKPOINT_INTERVAL: u32 = 10;
+
+// Upper bound for the number of seek points to keep the SEEKTABLE reasonably small
+const MAX_SEEKPOINTS: u64 = 32768;
+
+const VENDOR: &str = concat!("gst-plugin-claxon ", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    compression_level: u32,
+    seekpoint_interval: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            seekpoint_interval: DEFAULT_SEEKPOINT_INTERVAL,
+        }
+    }
+}
+
+struct State {
+    params: EncoderParams,
+    audio_info: gst_audio::AudioInfo,
+    bits_per_sample: u32,
+    frame_number: u64,
+    total_samples: u64,
+    // Size of all frames so far, i.e. the offset of the next frame from the first one
+    frames_size: u64,
+    min_frame_size: u32,
+    max_frame_size: u32,
+    md5: Md5,
+    seektable_size: usize,
+    seekpoint_samples: u64,
+    seekpoints: Vec<SeekPoint>,
+    eos: bool,
+}
+
+impl State {
+    fn streaminfo(&self) -> StreamInfo {
+        StreamInfo {
+            block_size: self.params.block_size as u16,
+            min_frame_size: self.min_frame_size,
+            max_frame_size: self.max_frame_size,
+            sample_rate: self.audio_info.rate(),
+            channels: self.audio_info.channels(),
+            bits_per_sample: self.bits_per_sample,
+            total_samples: self.total_samples,
+            md5sum: [0; 16],
+        }
+    }
+
+    /// Header buffers as pushed before the first frame: the stream marker, STREAMINFO,
+    /// SEEKTABLE if any and VORBIS_COMMENT
+    fn header_buffers(&self, streaminfo: &StreamInfo) -> Vec<gst::Buffer> {
+        let mut headers = vec![b"fLaC".to_vec(), streaminfo.to_bytes(false)];
+        if self.seektable_size > 0 {
+            headers.push(encoder::seektable_to_bytes(
+                &self.seekpoints,
+                self.seektable_size,
+                false,
+            ));
+        }
+        headers.push(encoder::vorbis_comment_to_bytes(VENDOR, true));
+
+        headers
+            .into_iter()
+            .map(|header| {
+                let mut buffer = gst::Buffer::from_mut_slice(header);
+                buffer
+                    .get_mut()
+                    .unwrap()
+                    .set_flags(gst::BufferFlags::HEADER);
+                buffer
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct FlacEnc {
+    settings: Mutex<Settings>,
+    state: AtomicRefCell<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FlacEnc {
+    const NAME: &'static str = "GstRsFlacEnc";
+    type Type = super::FlacEnc;
+    type ParentType = gst_audio::AudioEncoder;
+}
+
+impl ObjectImpl for FlacEnc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt::builder("compression-level")
+                    .nick("Compression Level")
+                    .blurb("Compression level from fastest (0) to smallest (8)")
+                    .maximum(8)
+                    .default_value(DEFAULT_COMPRESSION_LEVEL)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("seekpoint-interval")
+                    .nick("Seekpoint Interval")
+                    .blurb("Interval between seek points in seconds (0 = no SEEKTABLE)")
+                    .default_value(DEFAULT_SEEKPOINT_INTERVAL)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "compression-level" => {
+                let mut settings = self.settings.lock().unwrap();
+                let compression_level = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing compression-level from {} to {}",
+                    settings.compression_level,
+                    compression_level
+                );
+                settings.compression_level = compression_level;
+            }
+            "seekpoint-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                let seekpoint_interval = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing seekpoint-interval from {} to {}",
+                    settings.seekpoint_interval,
+                    seekpoint_interval
+                );
+                settings.seekpoint_interval = seekpoint_interval;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "compression-level" => {
+                let settings = self.settings.lock().unwrap();
+                settings.compression_level.to_value()
+            }
+            "seekpoint-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.seekpoint_interval.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for FlacEnc {}
+
+impl ElementImpl for FlacEnc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Rust FLAC encoder",
+                "Codec/Encoder/Audio",
+                "Encodes audio to FLAC",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format_list([gst_audio::AUDIO_FORMAT_S16, gst_audio::AUDIO_FORMAT_S2432])
+                .rate_range(1..=655_350)
+                .channels_range(1..=8)
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &sink_caps,
+            )
+            .unwrap();
+
+            let src_caps = gst::Caps::builder("audio/x-flac")
+                .field("framed", true)
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &src_caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl AudioEncoderImpl for FlacEnc {
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = None;
+
+        Ok(())
+    }
+
+    fn set_format(&self, info: &gst_audio::AudioInfo) -> Result<(), gst::LoggableError> {
+        gst::debug!(CAT, imp: self, "Setting format {:?}", info);
+
+        let settings = *self.settings.lock().unwrap();
+        let params = EncoderParams::from_compression_level(settings.compression_level);
+
+        let bits_per_sample = match info.format() {
+            gst_audio::AUDIO_FORMAT_S16 => 16,
+            gst_audio::AUDIO_FORMAT_S2432 => 24,
+            _ => return Err(gst::loggable_error!(CAT, "Unsupported format")),
+        };
+
+        // The SEEKTABLE needs to have its final size in the initial headers
+        let mut seektable_size = 0;
+        let mut seekpoint_samples = 0;
+        if settings.seekpoint_interval > 0 {
+            match self
+                .obj()
+                .sink_pad()
+                .peer_query_duration::<gst::ClockTime>()
+            {
+                Some(duration) => {
+                    seektable_size = (duration.seconds() / settings.seekpoint_interval as u64 + 1)
+                        .min(MAX_SEEKPOINTS) as usize;
+                    seekpoint_samples = settings.seekpoint_interval as u64 * info.rate() as u64;
+                }
+                None => {
+                    gst::debug!(CAT, imp: self, "Unknown duration, not writing a SEEKTABLE");
+                }
+            }
+        }
+
+        let state = State {
+            params,
+            audio_info: info.clone(),
+            bits_per_sample,
+            frame_number: 0,
+            total_samples: 0,
+            frames_size: 0,
+            min_frame_size: 0,
+            max_frame_size: 0,
+            md5: Md5::new(),
+            seektable_size,
+            seekpoint_samples,
+            seekpoints: Vec::new(),
+            eos: false,
+        };
+
+        // Frame sizes, number of samples and MD5 are unknown until EOS
+        let headers = state.header_buffers(&state.streaminfo());
+
+        // The first streamheader uses the Ogg FLAC mapping like flacparse and flacenc
+        let mut mapped = vec![0x7f, b'F', b'L', b'A', b'C', 0x01, 0x00];
+        mapped.extend_from_slice(&(headers.len() as u16 - 2).to_be_bytes());
+        for header in &headers[..2] {
+            mapped.extend_from_slice(&header.map_readable().unwrap());
+        }
+        let mut streamheader = vec![gst::Buffer::from_mut_slice(mapped)];
+        streamheader.extend_from_slice(&headers[2..]);
+
+        let caps = gst::Caps::builder("audio/x-flac")
+            .field("framed", true)
+            .field("rate", info.rate() as i32)
+            .field("channels", info.channels() as i32)
+            .field("streamheader", gst::Array::new(streamheader))
+            .build();
+
+        let element = self.obj();
+        element
+            .set_output_format(&caps)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to set output format"))?;
+        element.set_headers(headers);
+        element.set_frame_samples_min(params.block_size as i32);
+        element.set_frame_samples_max(params.block_size as i32);
+        element.set_frame_max(1);
+
+        *self.state.borrow_mut() = Some(state);
+
+        Ok(())
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            if let Some(ref mut state) = *self.state.borrow_mut() {
+                state.eos = true;
+            }
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn handle_frame(
+        &self,
+        inbuf: Option<&gst::Buffer>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, imp: self, "Handling buffer {:?}", inbuf);
+
+        let mut state_guard = self.state.borrow_mut();
+        let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
+
+        let inbuf = match inbuf {
+            Some(inbuf) => inbuf,
+            None => {
+                if state.eos {
+                    self.update_headers(state);
+                }
+                return Ok(gst::FlowSuccess::Ok);
+            }
+        };
+
+        let inmap = inbuf.map_readable().map_err(|_| {
+            gst::error!(CAT, imp: self, "Failed to map buffer readable");
+            gst::FlowError::Error
+        })?;
+
+        let channels = state.audio_info.channels() as usize;
+        let interleaved = match state.bits_per_sample {
+            16 => inmap
+                .as_slice_of::<i16>()
+                .map_err(|_| gst::FlowError::Error)?
+                .iter()
+                .map(|s| *s as i64)
+                .collect::<Vec<_>>(),
+            _ => inmap
+                .as_slice_of::<i32>()
+                .map_err(|_| gst::FlowError::Error)?
+                .iter()
+                // Sign extend from the lower 24 bits
+                .map(|s| ((*s << 8) >> 8) as i64)
+                .collect::<Vec<_>>(),
+        };
+
+        let samples = interleaved.len() / channels;
+        if samples == 0 {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        // The signature is over the samples in little endian with the minimum number of bytes
+        let bytes = (state.bits_per_sample / 8) as usize;
+        let mut md5_data = Vec::with_capacity(interleaved.len() * bytes);
+        for s in &interleaved {
+            md5_data.extend_from_slice(&s.to_le_bytes()[..bytes]);
+        }
+        state.md5.update(&md5_data);
+
+        let planes = (0..channels)
+            .map(|c| {
+                interleaved
+                    .iter()
+                    .skip(c)
+                    .step_by(channels)
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let frame = encoder::encode_frame(
+            &state.params,
+            state.frame_number,
+            state.audio_info.rate(),
+            state.bits_per_sample,
+            &planes,
+        );
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Encoded frame {} with {} samples to {} bytes",
+            state.frame_number,
+            samples,
+            frame.len()
+        );
+
+        let sample = state.total_samples;
+        if state.seektable_size > 0 {
+            // Seek point for every interval start that falls into this frame
+            let next_seekpoint = state.seekpoints.len() as u64 * state.seekpoint_samples;
+            if state.seekpoints.len() < state.seektable_size
+                && next_seekpoint < sample + samples as u64
+            {
+                state.seekpoints.push(SeekPoint {
+                    sample,
+                    offset: state.frames_size,
+                    samples: samples as u16,
+                });
+            }
+        }
+
+        let frame_size = frame.len() as u32;
+        state.min_frame_size = if state.frame_number == 0 {
+            frame_size
+        } else {
+            state.min_frame_size.min(frame_size)
+        };
+        state.max_frame_size = state.max_frame_size.max(frame_size);
+        state.frame_number += 1;
+        state.total_samples += samples as u64;
+        state.frames_size += frame.len() as u64;
+
+        drop(inmap);
+        drop(state_guard);
+
+        self.obj()
+            .finish_frame(Some(gst::Buffer::from_mut_slice(frame)), samples as i32)
+    }
+}
+
+impl FlacEnc {
+    /// Rewrites the headers with the final STREAMINFO and SEEKTABLE if downstream allows
+    /// seeking back to the beginning.
+    fn update_headers(&self, state: &State) {
+        let element = self.obj();
+        let srcpad = element.src_pad();
+
+        let mut q = gst::query::Seeking::new(gst::Format::Bytes);
+        if !srcpad.peer_query(&mut q) || !q.result().0 {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Downstream not seekable, not updating headers"
+            );
+            return;
+        }
+
+        let mut streaminfo = state.streaminfo();
+        streaminfo
+            .md5sum
+            .copy_from_slice(&state.md5.clone().finalize());
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Updating headers with {:?} and {} seek points",
+            streaminfo,
+            state.seekpoints.len()
+        );
+
+        let segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+        if !srcpad.push_event(gst::event::Segment::new(&segment)) {
+            gst::warning!(CAT, imp: self, "Failed to seek back to the headers");
+            return;
+        }
+
+        for header in state.header_buffers(&streaminfo) {
+            if let Err(err) = srcpad.push(header) {
+                gst::warning!(CAT, imp: self, "Failed to rewrite headers: {:?}", err);
+                return;
+            }
+        }
+    }
+}
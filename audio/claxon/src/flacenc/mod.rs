@@ -0,0 +1,46 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/**
+ * element-rsflacenc:
+ * @title: rsflacenc
+ *
+ * Pure Rust FLAC encoder for 16 and 24 bit audio with 1 to 8 channels.
+ *
+ * The `compression-level` property selects a trade-off between encoding speed and size
+ * similar to the one of the reference encoder. If the duration of the stream is known a
+ * SEEKTABLE with a seek point every `seekpoint-interval` seconds is generated, and if
+ * downstream is seekable in bytes the STREAMINFO and SEEKTABLE are updated at EOS with the
+ * final values.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 audiotestsrc num-buffers=100 ! rsflacenc ! filesink location=test.flac
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod encoder;
+mod imp;
+
+glib::wrapper! {
+    pub struct FlacEnc(ObjectSubclass<imp::FlacEnc>) @extends gst_audio::AudioEncoder, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsflacenc",
+        gst::Rank::NONE,
+        FlacEnc::static_type(),
+    )
+}
@@ -17,9 +17,11 @@
 use gst::glib;
 
 mod claxondec;
+mod flacenc;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
-    claxondec::register(plugin)
+    claxondec::register(plugin)?;
+    flacenc::register(plugin)
 }
 
 gst::plugin_define!(
@@ -105,3 +105,59 @@ fn do_test(data: &'static [u8], packet_sizes: &[usize], decoded_samples: &[usize
         .current_caps()
         .expect("pad has no caps")
 }
+
+fn run_md5_test(data: &[u8], packet_sizes: &[usize]) -> Option<gst::Message> {
+    init();
+
+    let dec = gst::ElementFactory::make("claxondec")
+        .property("verify-md5", true)
+        .build()
+        .unwrap();
+    let bus = gst::Bus::new();
+    dec.set_bus(Some(&bus));
+
+    let mut h = gst_check::Harness::with_element(&dec, Some("sink"), Some("src"));
+    h.play();
+
+    let caps = gst::Caps::builder("audio/x-flac")
+        .field("framed", true)
+        .build();
+    h.set_src_caps(caps);
+
+    let mut offset = 0;
+    for size in packet_sizes {
+        let buffer = gst::Buffer::from_slice(data[offset..offset + size].to_vec());
+        h.push(buffer).unwrap();
+        offset += size;
+    }
+
+    h.push_event(gst::event::Eos::new());
+
+    bus.iter()
+        .find(|msg| msg.type_() == gst::MessageType::Warning)
+}
+
+#[test]
+fn test_md5_verification() {
+    let data = include_bytes!("test_mono_s16.flac");
+    let packet_sizes = [4, 38, 66, 18];
+
+    assert!(run_md5_test(data, &packet_sizes).is_none());
+}
+
+#[test]
+fn test_md5_mismatch() {
+    let mut data = include_bytes!("test_mono_s16.flac").to_vec();
+    let packet_sizes = [4, 38, 66, 18];
+
+    // Corrupt the MD5 signature at the end of the STREAMINFO block
+    data[4 + 38 - 1] ^= 0xff;
+
+    let msg = run_md5_test(&data, &packet_sizes).expect("no warning posted");
+    match msg.view() {
+        gst::MessageView::Warning(warning) => {
+            assert!(warning.error().matches(gst::StreamError::Decode));
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,117 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstclaxon::plugin_register_static().expect("claxon test");
+    });
+}
+
+/// Sine with some noise and a silent part, the 24 bit variant is shifted up
+fn generate_samples(channels: usize, num_samples: usize, bits: u32) -> Vec<i32> {
+    let amplitude = ((1i64 << (bits - 1)) - 1) as f64 * 0.8;
+    let mut seed = 0x1234_5678u32;
+
+    let mut samples = Vec::with_capacity(channels * num_samples);
+    for i in 0..num_samples {
+        for c in 0..channels {
+            if (5000..6000).contains(&i) {
+                samples.push(0);
+                continue;
+            }
+
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let noise = ((seed >> 16) as i32 & 0xff) - 128;
+            let phase =
+                i as f64 * 2.0 * std::f64::consts::PI * (440.0 + 110.0 * c as f64) / 44_100.0;
+            samples.push((phase.sin() * amplitude) as i32 + noise);
+        }
+    }
+
+    samples
+}
+
+fn roundtrip(format: gst_audio::AudioFormat, channels: usize, compression_level: u32) {
+    init();
+
+    let bits = if format == gst_audio::AUDIO_FORMAT_S16 {
+        16
+    } else {
+        24
+    };
+    let samples = generate_samples(channels, 20_000, bits);
+
+    let mut h = gst_check::Harness::new_parse(&format!(
+        "rsflacenc compression-level={compression_level} ! claxondec"
+    ));
+
+    let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(format)
+        .rate(44_100)
+        .channels(channels as i32)
+        .build();
+    h.set_src_caps(caps);
+    h.play();
+
+    for chunk in samples.chunks(channels * 1000) {
+        let buffer = if bits == 16 {
+            let data = chunk.iter().map(|s| *s as i16).collect::<Vec<_>>();
+            gst::Buffer::from_mut_slice(data.as_byte_slice().to_vec())
+        } else {
+            gst::Buffer::from_mut_slice(chunk.as_byte_slice().to_vec())
+        };
+        h.push(buffer).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let mut decoded = Vec::new();
+    while let Some(buffer) = h.try_pull() {
+        let map = buffer.map_readable().unwrap();
+        if bits == 16 {
+            decoded.extend(map.as_slice_of::<i16>().unwrap().iter().map(|s| *s as i32));
+        } else {
+            decoded.extend_from_slice(map.as_slice_of::<i32>().unwrap());
+        }
+    }
+
+    assert_eq!(decoded.len(), samples.len());
+    assert!(decoded == samples, "decoded samples differ from input");
+}
+
+#[test]
+fn test_mono_s16_fastest() {
+    roundtrip(gst_audio::AUDIO_FORMAT_S16, 1, 0);
+}
+
+#[test]
+fn test_mono_s16() {
+    roundtrip(gst_audio::AUDIO_FORMAT_S16, 1, 5);
+}
+
+#[test]
+fn test_stereo_s16() {
+    roundtrip(gst_audio::AUDIO_FORMAT_S16, 2, 5);
+}
+
+#[test]
+fn test_stereo_s24_best() {
+    roundtrip(gst_audio::AUDIO_FORMAT_S2432, 2, 8);
+}
+
+#[test]
+fn test_surround_s24() {
+    roundtrip(gst_audio::AUDIO_FORMAT_S2432, 6, 3);
+}
@@ -16,7 +16,8 @@ use gst_base::subclass::prelude::*;
 
 use std::sync::atomic::{AtomicBool, Ordering};
 
-use std::sync::Mutex;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
 
 use byte_slice_cast::*;
 
@@ -66,6 +67,8 @@ pub struct CsoundFilter {
     state: Mutex<Option<State>>,
     csound: Mutex<Csound>,
     compiled: AtomicBool,
+    // Raw MIDI bytes to be read by Csound on the next k-cycle
+    midi_queue: Arc<Mutex<VecDeque<u8>>>,
 }
 
 impl State {
@@ -159,6 +162,37 @@ impl CsoundFilter {
         Ok(())
     }
 
+    fn send_score_event(&self, score: &str) -> bool {
+        if !self.compiled.load(Ordering::SeqCst) {
+            gst::warning!(CAT, imp: self, "Can't send score events before starting");
+            return false;
+        }
+
+        gst::debug!(CAT, imp: self, "Sending score event {:?}", score);
+
+        let csound = self.csound.lock().unwrap();
+        match csound.read_score(score) {
+            Ok(_) => true,
+            Err(err) => {
+                gst::warning!(CAT, imp: self, "Failed to send score event: {:?}", err);
+                false
+            }
+        }
+    }
+
+    fn send_midi(&self, data: &[u8]) -> bool {
+        // Status bytes have the most significant bit set
+        if data.first().map_or(true, |status| status & 0x80 == 0) {
+            gst::warning!(CAT, imp: self, "Invalid MIDI message {:?}", data);
+            return false;
+        }
+
+        gst::debug!(CAT, imp: self, "Queueing MIDI message {:?}", data);
+
+        self.midi_queue.lock().unwrap().extend(data);
+        true
+    }
+
     fn message_callback(msg_type: MessageType, msg: &str) {
         match msg_type {
             MessageType::CSOUNDMSG_ERROR => gst::error!(CAT, "{}", msg),
@@ -312,16 +346,84 @@ impl ObjectSubclass for CsoundFilter {
         csound.set_host_implemented_audioIO(1, 0);
         // We don't want csound to write samples to our HW
         csound.set_option("--nosound").unwrap();
+
+        // MIDI input is also host implemented and fed from the send-midi signal
+        let midi_queue = Arc::new(Mutex::new(VecDeque::new()));
+        csound.set_host_implemented_midiIO(1);
+        csound.set_option("-M0").unwrap();
+        csound.midi_in_open_callback(|_device| ());
+        csound.midi_read_callback({
+            let midi_queue = midi_queue.clone();
+            move |buffer: &mut [u8]| {
+                let mut midi_queue = midi_queue.lock().unwrap();
+                let len = usize::min(buffer.len(), midi_queue.len());
+                for (o, i) in buffer.iter_mut().zip(midi_queue.drain(..len)) {
+                    *o = i;
+                }
+                len
+            }
+        });
+
         Self {
             settings: Mutex::new(Default::default()),
             state: Mutex::new(None),
             csound: Mutex::new(csound),
             compiled: AtomicBool::new(false),
+            midi_queue,
         }
     }
 }
 
 impl ObjectImpl for CsoundFilter {
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![
+                /**
+                 * GstCsoundFilter::send-score-event:
+                 * @score: Score statements, e.g. `i 1 0 2 0.5`
+                 *
+                 * Sends score statements to the running Csound instance. Event start times are
+                 * relative to the current performance time.
+                 *
+                 * Returns: %TRUE if the statements were accepted.
+                 */
+                glib::subclass::Signal::builder("send-score-event")
+                    .param_types([String::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::CsoundFilter>().expect("signal arg");
+                        let score = args[1].get::<String>().expect("signal arg");
+
+                        Some(element.imp().send_score_event(&score).to_value())
+                    })
+                    .build(),
+                /**
+                 * GstCsoundFilter::send-midi:
+                 * @data: Raw MIDI message, starting with a status byte
+                 *
+                 * Queues a MIDI message to be read by Csound on the next control cycle. The
+                 * score must route the MIDI events to instruments, e.g. via `massign`.
+                 *
+                 * Returns: %TRUE if the message was queued.
+                 */
+                glib::subclass::Signal::builder("send-midi")
+                    .param_types([glib::Bytes::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::CsoundFilter>().expect("signal arg");
+                        let data = args[1].get::<glib::Bytes>().expect("signal arg");
+
+                        Some(element.imp().send_midi(&data).to_value())
+                    })
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
             vec![
@@ -479,6 +581,8 @@ impl BaseTransformImpl for CsoundFilter {
         let csound = self.csound.lock().unwrap();
         csound.stop();
         csound.reset();
+        self.compiled.store(false, Ordering::SeqCst);
+        self.midi_queue.lock().unwrap().clear();
         let _ = self.state.lock().unwrap().take();
 
         gst::info!(CAT, imp: self, "Stopped");
@@ -379,3 +379,59 @@ fn csound_filter_caps_negotiation_fail() {
 
     assert!(current_caps.is_none());
 }
+
+// Sends a score event to a running instance whose score doesn't start any instrument,
+// so the output must be silent before the event and not afterwards.
+#[test]
+fn csound_filter_score_event() {
+    init();
+
+    let csd = "
+        <CsoundSynthesizer>
+        <CsOptions>
+        </CsOptions>
+        <CsInstruments>
+        sr = 44100
+        ksmps = 32
+        nchnls_i = 1
+        nchnls = 1
+        0dbfs  = 1
+
+        instr 1
+            out 0.5
+        endin
+        </CsInstruments>
+        <CsScore>
+        f 0 10
+        e
+        </CsScore>
+        </CsoundSynthesizer>";
+
+    let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+        .format(gst_audio::AUDIO_FORMAT_F64)
+        .rate(44_100)
+        .channels(1)
+        .build();
+
+    let mut h = build_harness(caps.clone(), caps, csd);
+    h.play();
+
+    let filter = h.element().unwrap();
+
+    let push_and_pull = |h: &mut gst_check::Harness| {
+        let mut buffer = gst::Buffer::with_size(1024 * 8).unwrap();
+        buffer.get_mut().unwrap().map_writable().unwrap().fill(0);
+        h.push_and_pull(buffer).unwrap()
+    };
+
+    let buffer = push_and_pull(&mut h);
+    let map = buffer.map_readable().unwrap();
+    assert!(map.as_slice_of::<f64>().unwrap().iter().all(|s| *s == 0.0));
+    drop(map);
+
+    assert!(filter.emit_by_name::<bool>("send-score-event", &[&"i 1 0 1"]));
+
+    let buffer = push_and_pull(&mut h);
+    let map = buffer.map_readable().unwrap();
+    assert!(map.as_slice_of::<f64>().unwrap().iter().any(|s| *s == 0.5));
+}
@@ -30,6 +30,24 @@ struct State {
     pwr: lewton::audio::PreviousWindowRight,
     audio_info: Option<gst_audio::AudioInfo>,
     reorder_map: Option<[usize; 8]>,
+    // Absolute sample position at the end of the last decoded packet, if known
+    position: Option<i64>,
+    // Number of samples output since the position became unknown
+    unpositioned_samples: i64,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            header_bufs: (None, None, None),
+            headerset: None,
+            pwr: lewton::audio::PreviousWindowRight::new(),
+            audio_info: None,
+            reorder_map: None,
+            position: None,
+            unpositioned_samples: 0,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -108,13 +126,7 @@ impl AudioDecoderImpl for LewtonDec {
     }
 
     fn start(&self) -> Result<(), gst::ErrorMessage> {
-        *self.state.borrow_mut() = Some(State {
-            header_bufs: (None, None, None),
-            headerset: None,
-            pwr: lewton::audio::PreviousWindowRight::new(),
-            audio_info: None,
-            reorder_map: None,
-        });
+        *self.state.borrow_mut() = Some(State::new());
 
         Ok(())
     }
@@ -124,13 +136,7 @@ impl AudioDecoderImpl for LewtonDec {
 
         // When the caps are changing we require new headers
         let mut state_guard = self.state.borrow_mut();
-        *state_guard = Some(State {
-            header_bufs: (None, None, None),
-            headerset: None,
-            pwr: lewton::audio::PreviousWindowRight::new(),
-            audio_info: None,
-            reorder_map: None,
-        });
+        *state_guard = Some(State::new());
 
         let state = state_guard.as_mut().unwrap();
 
@@ -162,6 +168,9 @@ impl AudioDecoderImpl for LewtonDec {
         let mut state_guard = self.state.borrow_mut();
         if let Some(ref mut state) = *state_guard {
             state.pwr = lewton::audio::PreviousWindowRight::new();
+            // After a seek the position is only known again from the next granule position
+            state.position = None;
+            state.unpositioned_samples = 0;
         }
     }
 
@@ -201,12 +210,24 @@ impl AudioDecoderImpl for LewtonDec {
             return self.handle_header(state, inbuf, inmap.as_ref());
         }
 
+        // Discontinuities, e.g. after non-flushing seeks, need the decoder to start from scratch
+        if inbuf.flags().contains(gst::BufferFlags::DISCONT) {
+            gst::debug!(CAT, imp: self, "Resetting decoder on discontinuity");
+            state.pwr = lewton::audio::PreviousWindowRight::new();
+            state.position = None;
+            state.unpositioned_samples = 0;
+        }
+
         // If it's a data packet then try to initialize the headerset now if we didn't yet
         if state.headerset.is_none() {
             self.initialize(state)?;
         }
 
-        self.handle_data(state, inmap.as_ref())
+        let granulepos = Some(inbuf.offset_end())
+            .filter(|granulepos| *granulepos != gst::BUFFER_OFFSET_NONE)
+            .and_then(|granulepos| i64::try_from(granulepos).ok());
+
+        self.handle_data(state, inmap.as_ref(), granulepos)
     }
 }
 
@@ -347,6 +368,7 @@ impl LewtonDec {
             "Successfully parsed headers: {:?}",
             audio_info
         );
+
         state.headerset = Some((ident, comment, setup));
         state.audio_info = Some(audio_info.clone());
         state.reorder_map = reorder_map;
@@ -357,17 +379,105 @@ impl LewtonDec {
         Ok(())
     }
 
+    /// Returns the range of samples of a decoded packet that are to be output.
+    ///
+    /// The granule position of a packet is the absolute position of its last sample, so if the
+    /// first packet decodes to more samples than its granule position the beginning has to be
+    /// trimmed, and if the last packet decodes to more samples than its granule position
+    /// advanced the end has to be trimmed.
+    fn output_range(
+        &self,
+        state: &mut State,
+        granulepos: Option<i64>,
+        sample_count: usize,
+    ) -> std::ops::Range<usize> {
+        let count = sample_count as i64;
+
+        let start = match (state.position, granulepos) {
+            (Some(position), _) => position,
+            (None, Some(granulepos)) => {
+                let start = granulepos - count;
+
+                // The samples that were output while the position was unknown directly precede
+                // this packet, so the position of the first of them can be computed now
+                let first = start - state.unpositioned_samples;
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Position {} known after {} unpositioned samples",
+                    first,
+                    state.unpositioned_samples
+                );
+
+                // If the stream has to be trimmed at the start then these samples come first,
+                // only the remainder can still be removed from this packet
+                let already_output = (-first).clamp(0, state.unpositioned_samples);
+                if already_output > 0 {
+                    gst::warning!(
+                        CAT,
+                        imp: self,
+                        "Can't trim {} samples at the start that were already output",
+                        already_output
+                    );
+                }
+                state.unpositioned_samples = 0;
+
+                start
+            }
+            (None, None) => {
+                // Without any position information only count the samples so that the position
+                // can be computed once the next granule position is known
+                state.unpositioned_samples += count;
+                return 0..sample_count;
+            }
+        };
+        let mut end = start + count;
+
+        let mut clip_end = end;
+
+        if let Some(granulepos) = granulepos {
+            if granulepos < end {
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Trimming {} samples at the end",
+                    end - granulepos
+                );
+                clip_end = granulepos;
+            }
+            end = granulepos;
+        }
+
+        state.position = Some(end);
+
+        // Samples before position 0 are not part of the stream
+        let range_start = (-start).clamp(0, count) as usize;
+        let range_end = (clip_end - start).clamp(range_start as i64, count) as usize;
+
+        if range_start > 0 {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Trimming {} samples at the start",
+                range_start
+            );
+        }
+
+        range_start..range_end
+    }
+
     fn handle_data(
         &self,
         state: &mut State,
         indata: &[u8],
+        granulepos: Option<i64>,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
         // We ensured above that we have headers here now
         let headerset = state.headerset.as_ref().unwrap();
-        let audio_info = state.audio_info.as_ref().unwrap();
+        let audio_info = state.audio_info.clone().unwrap();
 
         // Decode the input packet
-        let decoded = match lewton::audio::read_audio_packet_generic::<
+        let mut decoded = match lewton::audio::read_audio_packet_generic::<
             lewton::samples::InterleavedSamples<f32>,
         >(&headerset.0, &headerset.2, indata, &mut state.pwr)
         {
@@ -395,9 +505,17 @@ impl LewtonDec {
             );
         }
 
-        let sample_count = decoded.samples.len() / audio_info.channels() as usize;
+        let channels = audio_info.channels() as usize;
+        let sample_count = decoded.samples.len() / channels;
         gst::debug!(CAT, imp: self, "Got {} decoded samples", sample_count);
 
+        let range = self.output_range(state, granulepos, sample_count);
+        if range.len() != sample_count {
+            decoded.samples.truncate(range.end * channels);
+            decoded.samples.drain(..range.start * channels);
+        }
+
+        let sample_count = range.len();
         if sample_count == 0 {
             return self.obj().finish_frame(None, 1);
         }
@@ -420,7 +538,6 @@ impl LewtonDec {
                 })?;
 
                 let outdata = outmap.as_mut_slice_of::<f32>().unwrap();
-                let channels = audio_info.channels() as usize;
                 assert!(reorder_map.len() >= channels);
                 assert!(reorder_map[..channels].iter().all(|c| *c < channels));
 
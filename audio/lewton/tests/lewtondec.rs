@@ -100,3 +100,129 @@ fn run_test(inline_headers: bool) {
             .build()
     );
 }
+
+#[test]
+fn test_granulepos_trimming() {
+    let data = include_bytes!("test.vorbis");
+    let packet_sizes = [30, 99, 3189, 43, 20, 56, 56, 21, 20, 22, 21, 22, 22, 43];
+    let packet_offsets = packet_sizes
+        .iter()
+        .scan(0, |state, &size| {
+            *state += size;
+            Some(*state)
+        })
+        .collect::<Vec<usize>>();
+    let decoded_samples = [0u64, 128, 576, 1472, 128, 128, 128, 128, 128, 128, 128];
+
+    init();
+
+    let mut h = gst_check::Harness::new("lewtondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-vorbis").build());
+
+    for (offset_start, offset_end) in std::iter::once(&0)
+        .chain(packet_offsets.iter())
+        .zip(packet_offsets.iter())
+        .take(3)
+    {
+        let buffer = gst::Buffer::from_slice(&data[*offset_start..*offset_end]);
+        h.push(buffer).unwrap();
+    }
+
+    // The first packet with samples has a granule position that is 28 samples short of its
+    // decoded samples, and the last one ends 72 samples before its decoded samples
+    let total_samples = decoded_samples.iter().sum::<u64>();
+    let mut granulepos = 0;
+    for (i, (offset_start, offset_end)) in packet_offsets
+        .iter()
+        .zip(packet_offsets.iter().skip(1))
+        .skip(2)
+        .enumerate()
+    {
+        granulepos += decoded_samples[i];
+        let mut buffer = gst::Buffer::from_slice(&data[*offset_start..*offset_end]);
+        if i > 0 {
+            let granulepos = if i == decoded_samples.len() - 1 {
+                total_samples - 28 - 72
+            } else {
+                granulepos - 28
+            };
+            buffer.get_mut().unwrap().set_offset_end(granulepos);
+        }
+        h.push(buffer).unwrap();
+    }
+
+    h.push_event(gst::event::Eos::new());
+
+    let mut expected_samples = decoded_samples[1..].to_vec();
+    expected_samples[0] -= 28;
+    *expected_samples.last_mut().unwrap() -= 72;
+
+    for samples in expected_samples {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.size() as u64, 4 * samples);
+    }
+}
+
+#[test]
+fn test_granulepos_after_unpositioned_packets() {
+    let data = include_bytes!("test.vorbis");
+    let packet_sizes = [30, 99, 3189, 43, 20, 56, 56, 21, 20, 22, 21, 22, 22, 43];
+    let packet_offsets = packet_sizes
+        .iter()
+        .scan(0, |state, &size| {
+            *state += size;
+            Some(*state)
+        })
+        .collect::<Vec<usize>>();
+    let decoded_samples = [0u64, 128, 576, 1472, 128, 128, 128, 128, 128, 128, 128];
+
+    init();
+
+    let mut h = gst_check::Harness::new("lewtondec");
+    h.play();
+    h.set_src_caps(gst::Caps::builder("audio/x-vorbis").build());
+
+    for (offset_start, offset_end) in std::iter::once(&0)
+        .chain(packet_offsets.iter())
+        .zip(packet_offsets.iter())
+        .take(3)
+    {
+        let buffer = gst::Buffer::from_slice(&data[*offset_start..*offset_end]);
+        h.push(buffer).unwrap();
+    }
+
+    // The first packets have no granule position, the position is only known from the fourth
+    // packet on and the last one ends 72 samples before its decoded samples
+    let total_samples = decoded_samples.iter().sum::<u64>();
+    let mut granulepos = 0;
+    for (i, (offset_start, offset_end)) in packet_offsets
+        .iter()
+        .zip(packet_offsets.iter().skip(1))
+        .skip(2)
+        .enumerate()
+    {
+        granulepos += decoded_samples[i];
+        let mut buffer = gst::Buffer::from_slice(&data[*offset_start..*offset_end]);
+        if i > 3 {
+            let granulepos = if i == decoded_samples.len() - 1 {
+                total_samples - 72
+            } else {
+                granulepos
+            };
+            buffer.get_mut().unwrap().set_offset_end(granulepos);
+        }
+        h.push(buffer).unwrap();
+    }
+
+    h.push_event(gst::event::Eos::new());
+
+    // Nothing is trimmed at the start as the back-computed position of the first samples is 0
+    let mut expected_samples = decoded_samples[1..].to_vec();
+    *expected_samples.last_mut().unwrap() -= 72;
+
+    for samples in expected_samples {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.size() as u64, 4 * samples);
+    }
+}
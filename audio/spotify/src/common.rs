@@ -6,7 +6,7 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 
 use gst::glib;
 use gst::prelude::*;
@@ -15,6 +15,7 @@ use librespot::core::{
     cache::Cache, config::SessionConfig, session::Session, spotify_id::SpotifyId,
 };
 use librespot::discovery::Credentials;
+use librespot::metadata::{Album, Metadata, Playlist, Show};
 
 #[derive(Default, Debug, Clone)]
 pub struct Settings {
@@ -60,7 +61,7 @@ impl Settings {
                     .build(),
                 glib::ParamSpecString::builder("track")
                     .nick("Spotify URI")
-                    .blurb("Spotify track URI, in the form 'spotify:track:$SPOTIFY_ID'")
+                    .blurb("Spotify URI of a track, episode, album, playlist or show, in the form 'spotify:track:$SPOTIFY_ID'")
                     .default_value(Some(""))
                     .mutable_ready()
                     .build(),
@@ -103,14 +104,7 @@ impl Settings {
         }
     }
 
-    pub async fn connect_session<T>(
-        &self,
-        src: T,
-        cat: &gst::DebugCategory,
-    ) -> anyhow::Result<Session>
-    where
-        T: IsA<glib::Object>,
-    {
+    fn cache(&self) -> anyhow::Result<Cache> {
         let credentials_cache = if self.cache_credentials.is_empty() {
             None
         } else {
@@ -131,6 +125,21 @@ impl Settings {
 
         let cache = Cache::new(credentials_cache, None, files_cache, max_size)?;
 
+        Ok(cache)
+    }
+
+    /// Connects a new session, returning it with the reusable credentials that can be used
+    /// for reconnecting later.
+    pub async fn connect_session<T>(
+        &self,
+        src: T,
+        cat: &gst::DebugCategory,
+    ) -> anyhow::Result<(Session, Credentials)>
+    where
+        T: IsA<glib::Object>,
+    {
+        let cache = self.cache()?;
+
         if let Some(cached_cred) = cache.credentials() {
             if !self.username.is_empty() && self.username != cached_cred.username {
                 gst::debug!(
@@ -147,7 +156,7 @@ impl Settings {
                     "reuse cached credentials for user {}",
                     cached_cred.username
                 );
-                if let Ok((session, _credentials)) = Session::connect(
+                if let Ok(res) = Session::connect(
                     SessionConfig::default(),
                     cached_cred,
                     Some(cache.clone()),
@@ -155,7 +164,7 @@ impl Settings {
                 )
                 .await
                 {
-                    return Ok(session);
+                    return Ok(res);
                 }
             }
         }
@@ -175,20 +184,62 @@ impl Settings {
 
         let cred = Credentials::with_password(&self.username, &self.password);
 
-        let (session, _credentials) =
-            Session::connect(SessionConfig::default(), cred, Some(cache), true).await?;
+        let res = Session::connect(SessionConfig::default(), cred, Some(cache), true).await?;
+
+        Ok(res)
+    }
+
+    /// Connects a new session with the credentials of an expired session, the refreshed
+    /// credentials are stored in the credentials cache if any.
+    pub async fn reconnect_session(
+        &self,
+        credentials: Credentials,
+    ) -> anyhow::Result<(Session, Credentials)> {
+        let cache = self.cache()?;
+
+        let res =
+            Session::connect(SessionConfig::default(), credentials, Some(cache), true).await?;
 
-        Ok(session)
+        Ok(res)
     }
 
-    pub fn track_id(&self) -> anyhow::Result<SpotifyId> {
+    /// Returns the playable items referred to by the URI, i.e. the track or episode itself or
+    /// the tracks and episodes of an album, playlist or show.
+    pub async fn resolve_tracks(&self, session: &Session) -> anyhow::Result<Vec<SpotifyId>> {
         if self.track.is_empty() {
             bail!("track is not set");
         }
-        let track = SpotifyId::from_uri(&self.track).map_err(|_| {
-            anyhow::anyhow!("failed to create Spotify URI from track {}", self.track)
-        })?;
+        let id = SpotifyId::from_uri(&self.track)
+            .map_err(|_| anyhow!("failed to create Spotify URI from track {}", self.track))?;
+
+        // The type is the part before the ID, e.g. 'spotify:user:$USER:playlist:$SPOTIFY_ID'
+        let tracks = match self.track.rsplit(':').nth(1) {
+            Some("track") | Some("episode") => vec![id],
+            Some("album") => {
+                Album::get(session, id)
+                    .await
+                    .map_err(|_| anyhow!("failed to get album {}", self.track))?
+                    .tracks
+            }
+            Some("playlist") => {
+                Playlist::get(session, id)
+                    .await
+                    .map_err(|_| anyhow!("failed to get playlist {}", self.track))?
+                    .tracks
+            }
+            Some("show") => {
+                Show::get(session, id)
+                    .await
+                    .map_err(|_| anyhow!("failed to get show {}", self.track))?
+                    .episodes
+            }
+            _ => bail!("unsupported Spotify URI {}", self.track),
+        };
+
+        if tracks.is_empty() {
+            bail!("no tracks in {}", self.track);
+        }
 
-        Ok(track)
+        Ok(tracks)
     }
 }
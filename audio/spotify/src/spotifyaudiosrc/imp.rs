@@ -7,7 +7,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
+use anyhow::anyhow;
 use futures::future::{AbortHandle, Abortable, Aborted};
 use once_cell::sync::Lazy;
 use tokio::{runtime, task::JoinHandle};
@@ -17,16 +19,22 @@ use gst::prelude::*;
 use gst::subclass::prelude::*;
 use gst_base::subclass::{base_src::CreateSuccess, prelude::*};
 
+use librespot::core::{
+    authentication::Credentials,
+    session::Session,
+    spotify_id::{SpotifyAudioType, SpotifyId},
+};
+use librespot::metadata::{Album, Artist, Episode, Metadata, Show, Track};
 use librespot::playback::{
     audio_backend::{Sink, SinkResult},
-    config::PlayerConfig,
+    config::{Bitrate as PlayerBitrate, PlayerConfig},
     convert::Converter,
     decoder::AudioPacket,
     mixer::NoOpVolume,
     player::{Player, PlayerEvent},
 };
 
-use super::Bitrate;
+use super::{Bitrate, Reconnect};
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
@@ -44,6 +52,11 @@ static RUNTIME: Lazy<runtime::Runtime> = Lazy::new(|| {
         .unwrap()
 });
 
+/// How often to check if the session expired while waiting for data
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Sample rate of all the Spotify Vorbis streams, used to convert granule positions
+const SPOTIFY_RATE: u64 = 44_100;
+
 /// Messages from the librespot thread
 enum Message {
     Buffer(gst::Buffer),
@@ -53,9 +66,24 @@ enum Message {
 
 struct State {
     player: Player,
+    session: Session,
+    /// reusable credentials of the session, used to log in again when it expires
+    credentials: Credentials,
+    bitrate: PlayerBitrate,
+
+    /// tracks or episodes to play
+    tracks: Vec<SpotifyId>,
+    /// index of the currently playing track
+    current: usize,
+    /// set when a new track started playing and its first buffer was not produced yet
+    track_changed: Option<gst::TagList>,
+    /// position in the current track of the last buffer, in milliseconds
+    position_ms: u32,
 
     /// receiver sending buffer to streaming thread
     receiver: mpsc::Receiver<Message>,
+    /// kept to create new players when reconnecting
+    sender: mpsc::SyncSender<Message>,
     /// thread receiving player events from librespot
     player_channel_handle: JoinHandle<()>,
 }
@@ -64,6 +92,7 @@ struct State {
 struct Settings {
     common: crate::common::Settings,
     bitrate: Bitrate,
+    reconnect: Reconnect,
 }
 
 #[derive(Default)]
@@ -111,6 +140,26 @@ impl ObjectSubclass for SpotifyAudioSrc {
 }
 
 impl ObjectImpl for SpotifyAudioSrc {
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![
+                /**
+                 * GstSpotifyAudioSrc::track-changed:
+                 * @index: Index of the track in the album, playlist or show
+                 * @uri: Spotify URI of the track
+                 *
+                 * Emitted from the streaming thread when a new track starts, right before its
+                 * first buffer and tags are pushed downstream.
+                 */
+                glib::subclass::Signal::builder("track-changed")
+                    .param_types([u32::static_type(), String::static_type()])
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
             let mut props = crate::common::Settings::properties();
@@ -123,6 +172,16 @@ impl ObjectImpl for SpotifyAudioSrc {
                     .mutable_ready()
                     .build(),
             );
+            props.push(
+                glib::ParamSpecEnum::builder_with_default::<Reconnect>(
+                    "reconnect",
+                    default.reconnect,
+                )
+                .nick("Reconnect")
+                .blurb("Behavior when the session expires while playing")
+                .mutable_playing()
+                .build(),
+            );
             props
         });
 
@@ -136,6 +195,9 @@ impl ObjectImpl for SpotifyAudioSrc {
             "bitrate" => {
                 settings.bitrate = value.get().expect("type checked upstream");
             }
+            "reconnect" => {
+                settings.reconnect = value.get().expect("type checked upstream");
+            }
             _ => settings.common.set_property(value, pspec),
         }
     }
@@ -145,6 +207,7 @@ impl ObjectImpl for SpotifyAudioSrc {
 
         match pspec.name() {
             "bitrate" => settings.bitrate.to_value(),
+            "reconnect" => settings.reconnect.to_value(),
             _ => settings.common.property(pspec),
         }
     }
@@ -290,29 +353,169 @@ impl PushSrcImpl for SpotifyAudioSrc {
             }
         }
 
-        let state = self.state.lock().unwrap();
-        let state = state.as_ref().unwrap();
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().unwrap();
 
-        match state.receiver.recv().unwrap() {
-            Message::Buffer(buffer) => {
-                gst::log!(CAT, imp: self, "got buffer of size {}", buffer.size());
-                Ok(CreateSuccess::NewBuffer(buffer))
-            }
-            Message::Eos => {
-                gst::debug!(CAT, imp: self, "eos");
-                Err(gst::FlowError::Eos)
+        let buffer = loop {
+            let msg = match state.receiver.recv_timeout(SESSION_CHECK_INTERVAL) {
+                Ok(msg) => msg,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if state.session.is_invalid() {
+                        self.reconnect(state)?;
+                    }
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => unreachable!(),
+            };
+
+            match msg {
+                Message::Buffer(buffer) => {
+                    gst::log!(CAT, imp: self, "got buffer of size {}", buffer.size());
+                    if let Some(position_ms) = ogg_position_ms(&buffer) {
+                        state.position_ms = position_ms;
+                    }
+                    break buffer;
+                }
+                Message::Eos => {
+                    gst::debug!(CAT, imp: self, "end of track {}", state.current);
+                    if !self.next_track(state) {
+                        gst::debug!(CAT, imp: self, "eos");
+                        return Err(gst::FlowError::Eos);
+                    }
+                }
+                Message::Unavailable => {
+                    let track = state.tracks[state.current];
+                    if !self.next_track(state) {
+                        gst::error!(CAT, imp: self, "track is not available");
+                        gst::element_imp_error!(
+                            self,
+                            gst::ResourceError::NotFound,
+                            ["track is not available"]
+                        );
+                        return Err(gst::FlowError::Error);
+                    }
+
+                    gst::element_imp_warning!(
+                        self,
+                        gst::ResourceError::NotFound,
+                        ["track {} is not available, skipping", uri(&track)]
+                    );
+                }
             }
-            Message::Unavailable => {
-                gst::error!(CAT, imp: self, "track is not available");
-                gst::element_imp_error!(
-                    self,
-                    gst::ResourceError::NotFound,
-                    ["track is not available"]
-                );
-                Err(gst::FlowError::Error)
+        };
+
+        let track_changed = state.track_changed.take().map(|tags| {
+            (
+                state.current as u32,
+                uri(&state.tracks[state.current]),
+                tags,
+            )
+        });
+        drop(state_guard);
+
+        if let Some((index, uri, tags)) = track_changed {
+            gst::debug!(CAT, imp: self, "starting track {index} {uri} with tags {tags:?}");
+
+            // Queued by basesrc and pushed before the buffer
+            self.obj().send_event(gst::event::Tag::new(tags));
+            self.obj()
+                .emit_by_name::<()>("track-changed", &[&index, &uri]);
+        }
+
+        Ok(CreateSuccess::NewBuffer(buffer))
+    }
+}
+
+/// Position in milliseconds of the end of the Ogg page in `buffer`, if known
+fn ogg_position_ms(buffer: &gst::Buffer) -> Option<u32> {
+    let map = buffer.map_readable().ok()?;
+    if map.len() < 14 || &map[0..4] != b"OggS" {
+        return None;
+    }
+
+    let granulepos = i64::from_le_bytes(map[6..14].try_into().unwrap());
+    if granulepos < 0 {
+        return None;
+    }
+
+    u32::try_from(granulepos as u64 * 1000 / SPOTIFY_RATE).ok()
+}
+
+fn uri(id: &SpotifyId) -> String {
+    id.to_uri()
+}
+
+/// Fetches the tags of a track or episode
+async fn track_tags(session: &Session, id: SpotifyId) -> anyhow::Result<gst::TagList> {
+    let (title, artists, album, duration, covers) = match id.audio_type {
+        SpotifyAudioType::Podcast => {
+            let episode = Episode::get(session, id)
+                .await
+                .map_err(|_| anyhow!("failed to get episode metadata"))?;
+            let show = Show::get(session, episode.show)
+                .await
+                .map_err(|_| anyhow!("failed to get show metadata"))?;
+
+            let covers = if episode.covers.is_empty() {
+                show.covers
+            } else {
+                episode.covers
+            };
+
+            (
+                episode.name,
+                vec![show.publisher],
+                show.name,
+                episode.duration,
+                covers,
+            )
+        }
+        _ => {
+            let track = Track::get(session, id)
+                .await
+                .map_err(|_| anyhow!("failed to get track metadata"))?;
+            let album = Album::get(session, track.album)
+                .await
+                .map_err(|_| anyhow!("failed to get album metadata"))?;
+
+            let mut artists = Vec::with_capacity(track.artists.len());
+            for artist in track.artists {
+                let artist = Artist::get(session, artist)
+                    .await
+                    .map_err(|_| anyhow!("failed to get artist metadata"))?;
+                artists.push(artist.name);
             }
+
+            (
+                track.name,
+                artists,
+                album.name,
+                track.duration,
+                album.covers,
+            )
+        }
+    };
+
+    let mut tags = gst::TagList::new();
+    {
+        let tags = tags.get_mut().unwrap();
+        tags.add::<gst::tags::Title>(&title.as_str(), gst::TagMergeMode::Append);
+        for artist in &artists {
+            tags.add::<gst::tags::Artist>(&artist.as_str(), gst::TagMergeMode::Append);
+        }
+        tags.add::<gst::tags::Album>(&album.as_str(), gst::TagMergeMode::Append);
+        tags.add::<gst::tags::Duration>(
+            &gst::ClockTime::from_mseconds(duration.max(0) as u64),
+            gst::TagMergeMode::Append,
+        );
+        // There is no tag for image URLs, so the cover art is passed as extended comment
+        if let Some(cover) = covers.first() {
+            let comment = format!("ART_URL=https://i.scdn.co/image/{}", cover.to_base16());
+            tags.add::<gst::tags::ExtendedComment>(&comment.as_str(), gst::TagMergeMode::Append);
         }
     }
+
+    Ok(tags)
 }
 
 struct BufferSink {
@@ -334,6 +537,44 @@ impl Sink for BufferSink {
     }
 }
 
+/// Creates a player pushing its data and events to `sender`
+fn create_player(
+    session: Session,
+    bitrate: PlayerBitrate,
+    sender: mpsc::SyncSender<Message>,
+) -> (Player, JoinHandle<()>) {
+    let player_config = PlayerConfig {
+        passthrough: true,
+        bitrate,
+        ..Default::default()
+    };
+
+    let sender_clone = sender.clone();
+
+    let (player, mut player_event_channel) =
+        Player::new(player_config, session, Box::new(NoOpVolume), || {
+            Box::new(BufferSink { sender })
+        });
+
+    let player_channel_handle = RUNTIME.spawn(async move {
+        let sender = sender_clone;
+
+        while let Some(event) = player_event_channel.recv().await {
+            match event {
+                PlayerEvent::EndOfTrack { .. } => {
+                    let _ = sender.send(Message::Eos);
+                }
+                PlayerEvent::Unavailable { .. } => {
+                    let _ = sender.send(Message::Unavailable);
+                }
+                _ => {}
+            }
+        }
+    });
+
+    (player, player_channel_handle)
+}
+
 impl URIHandlerImpl for SpotifyAudioSrc {
     const URI_TYPE: gst::URIType = gst::URIType::Src;
 
@@ -410,7 +651,7 @@ impl SpotifyAudioSrc {
 
         let src = self.obj();
 
-        let (session, track, bitrate) = {
+        let (session, credentials, tracks, bitrate) = {
             let (common, bitrate) = {
                 let settings = self.settings.lock().unwrap();
                 let bitrate = settings.bitrate.into();
@@ -418,54 +659,124 @@ impl SpotifyAudioSrc {
                 (settings.common.clone(), bitrate)
             };
 
-            let session = common.connect_session(src.clone(), &CAT).await?;
-            let track = common.track_id()?;
+            let (session, credentials) = common.connect_session(src.clone(), &CAT).await?;
+            let tracks = common.resolve_tracks(&session).await?;
+            gst::debug!(CAT, imp: self, "Playing {} tracks", tracks.len());
             gst::debug!(CAT, imp: self, "Requesting bitrate {:?}", bitrate);
 
-            (session, track, bitrate)
+            (session, credentials, tracks, bitrate)
         };
 
-        let player_config = PlayerConfig {
-            passthrough: true,
-            bitrate,
-            ..Default::default()
-        };
+        let tags = self.fetch_tags(&session, tracks[0]).await;
 
         // use a sync channel to prevent buffering the whole track inside the channel
         let (sender, receiver) = mpsc::sync_channel(2);
-        let sender_clone = sender.clone();
 
-        let (mut player, mut player_event_channel) =
-            Player::new(player_config, session, Box::new(NoOpVolume), || {
-                Box::new(BufferSink { sender })
-            });
+        let (mut player, player_channel_handle) =
+            create_player(session.clone(), bitrate, sender.clone());
 
-        player.load(track, true, 0);
-
-        let player_channel_handle = RUNTIME.spawn(async move {
-            let sender = sender_clone;
-
-            while let Some(event) = player_event_channel.recv().await {
-                match event {
-                    PlayerEvent::EndOfTrack { .. } => {
-                        let _ = sender.send(Message::Eos);
-                    }
-                    PlayerEvent::Unavailable { .. } => {
-                        let _ = sender.send(Message::Unavailable);
-                    }
-                    _ => {}
-                }
-            }
-        });
+        player.load(tracks[0], true, 0);
 
         let mut state = self.state.lock().unwrap();
 
         state.replace(State {
             player,
+            session,
+            credentials,
+            bitrate,
+            tracks,
+            current: 0,
+            track_changed: Some(tags),
+            position_ms: 0,
             receiver,
+            sender,
             player_channel_handle,
         });
 
         Ok(())
     }
+
+    /// Logs in again after the session expired and continues playing the current track
+    /// according to the `reconnect` property.
+    fn reconnect(&self, state: &mut State) -> Result<(), gst::FlowError> {
+        let (common, reconnect) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.common.clone(), settings.reconnect)
+        };
+
+        if reconnect == Reconnect::Never {
+            gst::error!(CAT, imp: self, "session expired");
+            gst::element_imp_error!(self, gst::ResourceError::Read, ["session expired"]);
+            return Err(gst::FlowError::Error);
+        }
+
+        gst::info!(CAT, imp: self, "session expired, reconnecting");
+
+        let (session, credentials) = RUNTIME
+            .block_on(common.reconnect_session(state.credentials.clone()))
+            .map_err(|err| {
+                gst::error!(CAT, imp: self, "failed to reconnect: {err:?}");
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::OpenRead,
+                    ["failed to reconnect: {err:?}"]
+                );
+                gst::FlowError::Error
+            })?;
+
+        state.player.stop();
+        state.player_channel_handle.abort();
+        // drop pending data of the expired player so it does not block when shutting down
+        while state.receiver.try_recv().is_ok() {}
+
+        let (player, player_channel_handle) =
+            create_player(session.clone(), state.bitrate, state.sender.clone());
+
+        let position_ms = match reconnect {
+            Reconnect::Resume => state.position_ms,
+            _ => 0,
+        };
+        let track = state.tracks[state.current];
+        gst::debug!(
+            CAT,
+            imp: self,
+            "reloading track {} at {position_ms} ms",
+            uri(&track)
+        );
+        player.load(track, true, position_ms);
+
+        state.player = player;
+        state.player_channel_handle = player_channel_handle;
+        state.session = session;
+        state.credentials = credentials;
+
+        Ok(())
+    }
+
+    async fn fetch_tags(&self, session: &Session, track: SpotifyId) -> gst::TagList {
+        match track_tags(session, track).await {
+            Ok(tags) => tags,
+            Err(err) => {
+                gst::warning!(CAT, imp: self, "failed to get tags of {}: {err:?}", uri(&track));
+                gst::TagList::new()
+            }
+        }
+    }
+
+    /// Starts playing the next track, if any.
+    fn next_track(&self, state: &mut State) -> bool {
+        if state.current + 1 >= state.tracks.len() {
+            return false;
+        }
+
+        state.current += 1;
+        state.position_ms = 0;
+        let track = state.tracks[state.current];
+        gst::debug!(CAT, imp: self, "loading track {} {}", state.current, uri(&track));
+
+        state.player.load(track, true, 0);
+        state.track_changed = Some(RUNTIME.block_on(self.fetch_tags(&state.session, track)));
+
+        true
+    }
 }
@@ -39,6 +39,30 @@ impl From<Bitrate> for librespot::playback::config::Bitrate {
     }
 }
 
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRsSpotifyReconnect")]
+enum Reconnect {
+    #[enum_value(name = "Fail with an error when the session expires", nick = "never")]
+    Never,
+    #[enum_value(
+        name = "Log in again and restart the current track",
+        nick = "restart-track"
+    )]
+    RestartTrack,
+    #[enum_value(
+        name = "Log in again and resume the current track where it stopped",
+        nick = "resume"
+    )]
+    Resume,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self::Resume
+    }
+}
+
 glib::wrapper! {
     pub struct SpotifyAudioSrc(ObjectSubclass<imp::SpotifyAudioSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object, @implements gst::URIHandler;
 }
@@ -46,6 +70,8 @@ glib::wrapper! {
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     #[cfg(feature = "doc")]
     Bitrate::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    #[cfg(feature = "doc")]
+    Reconnect::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
 
     gst::Element::register(
         Some(plugin),
@@ -0,0 +1,104 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use anyhow::{anyhow, bail, Context};
+
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Local speech synthesis engine producing mono S16 audio.
+pub trait Engine: Send {
+    /// Sample rate of the produced audio.
+    fn rate(&self) -> u32;
+
+    /// Synthesizes `text` into samples.
+    fn synthesize(&mut self, text: &str) -> anyhow::Result<Vec<i16>>;
+}
+
+/// Runs the Piper command line tool once per text.
+pub struct Piper {
+    executable: PathBuf,
+    model: PathBuf,
+    speaker: Option<u32>,
+    rate: u32,
+}
+
+impl Piper {
+    pub fn new(executable: &Path, model: &Path, speaker: Option<u32>) -> anyhow::Result<Self> {
+        // The voice configuration is stored next to the model as `<model>.json`
+        let mut config_path = OsString::from(model.as_os_str());
+        config_path.push(".json");
+        let config_path = PathBuf::from(config_path);
+
+        let config = std::fs::read(&config_path)
+            .with_context(|| format!("Failed to read voice config {}", config_path.display()))?;
+        let config: serde_json::Value = serde_json::from_slice(&config)
+            .with_context(|| format!("Failed to parse voice config {}", config_path.display()))?;
+
+        let rate = config
+            .pointer("/audio/sample_rate")
+            .and_then(|rate| rate.as_u64())
+            .and_then(|rate| u32::try_from(rate).ok())
+            .filter(|rate| *rate > 0)
+            .ok_or_else(|| anyhow!("No sample rate in voice config {}", config_path.display()))?;
+
+        Ok(Piper {
+            executable: executable.to_path_buf(),
+            model: model.to_path_buf(),
+            speaker,
+            rate,
+        })
+    }
+}
+
+impl Engine for Piper {
+    fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn synthesize(&mut self, text: &str) -> anyhow::Result<Vec<i16>> {
+        let mut command = Command::new(&self.executable);
+        command
+            .arg("--model")
+            .arg(&self.model)
+            .arg("--output_raw")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(speaker) = self.speaker {
+            command.arg("--speaker").arg(speaker.to_string());
+        }
+
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.executable.display()))?;
+
+        // Piper synthesizes one utterance per line
+        {
+            let mut stdin = child.stdin.take().unwrap();
+            let line = text.replace(|c| c == '\r' || c == '\n', " ");
+            writeln!(stdin, "{line}").context("Failed to write text")?;
+        }
+
+        let output = child.wait_with_output().context("Failed to read audio")?;
+        if !output.status.success() {
+            bail!(
+                "{} failed with {}: {}",
+                self.executable.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        Ok(output
+            .stdout
+            .chunks_exact(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+            .collect())
+    }
+}
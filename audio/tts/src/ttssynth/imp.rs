@@ -0,0 +1,523 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::engine::{Engine, Piper};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "ttssynth",
+        gst::DebugColorFlags::empty(),
+        Some("Text-To-Speech Synthesizer"),
+    )
+});
+
+const DEFAULT_ENGINE_PATH: &str = "piper";
+const DEFAULT_SPEAKER: i32 = -1;
+
+// Maximum duration of the silence buffers filling the time between speech
+const MAX_SILENCE_DURATION: gst::ClockTime = gst::ClockTime::SECOND;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    engine_path: String,
+    model: Option<String>,
+    speaker: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            engine_path: String::from(DEFAULT_ENGINE_PATH),
+            model: None,
+            speaker: DEFAULT_SPEAKER,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    segment: gst::FormattedSegment<gst::ClockTime>,
+    // End of the audio output so far
+    next_pts: Option<gst::ClockTime>,
+    discont: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            segment: gst::FormattedSegment::new(),
+            next_pts: None,
+            discont: true,
+        }
+    }
+}
+
+pub struct TtsSynth {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    // Kept separate from the state as synthesizing takes a while
+    engine: Mutex<Option<Box<dyn Engine>>>,
+}
+
+fn samples_to_duration(samples: usize, rate: u32) -> gst::ClockTime {
+    gst::ClockTime::SECOND
+        .mul_div_floor(samples as u64, rate as u64)
+        .unwrap()
+}
+
+fn duration_to_samples(duration: gst::ClockTime, rate: u32) -> usize {
+    duration
+        .mul_div_floor(rate as u64, *gst::ClockTime::SECOND)
+        .unwrap()
+        .nseconds() as usize
+}
+
+impl TtsSynth {
+    fn rate(&self) -> Option<u32> {
+        self.engine
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|engine| engine.rate())
+    }
+
+    fn output_buffer(
+        &self,
+        state: &mut State,
+        pts: gst::ClockTime,
+        samples: &[i16],
+        rate: u32,
+        gap: bool,
+    ) -> gst::Buffer {
+        let duration = samples_to_duration(samples.len(), rate);
+
+        let data = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect::<Vec<u8>>();
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(pts);
+            buffer.set_duration(duration);
+            if gap {
+                buffer.set_flags(gst::BufferFlags::GAP);
+            }
+            if state.discont {
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+                state.discont = false;
+            }
+        }
+
+        state.next_pts = Some(pts + duration);
+
+        buffer
+    }
+
+    // Silence from the end of the previous output, or the start of the segment, up to `end`
+    fn silence(&self, state: &mut State, end: gst::ClockTime, rate: u32) -> Vec<gst::Buffer> {
+        let mut buffers = vec![];
+
+        let Some(mut pts) = state.next_pts.or(state.segment.start()) else {
+            return buffers;
+        };
+
+        while pts < end {
+            let duration = (end - pts).min(MAX_SILENCE_DURATION);
+            let samples = duration_to_samples(duration, rate);
+            if samples == 0 {
+                break;
+            }
+
+            buffers.push(self.output_buffer(state, pts, &vec![0; samples], rate, true));
+            pts = state.next_pts.unwrap();
+        }
+
+        buffers
+    }
+
+    fn push_buffers(&self, buffers: Vec<gst::Buffer>) -> Result<gst::FlowSuccess, gst::FlowError> {
+        for buffer in buffers {
+            gst::log!(CAT, imp: self, "Pushing {:?}", buffer);
+            self.srcpad.push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let Some(pts) = buffer.pts() else {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Timed text buffers require a timestamp"]
+            );
+            return Err(gst::FlowError::Error);
+        };
+
+        let text = {
+            let data = buffer.map_readable().map_err(|_| {
+                gst::error!(CAT, obj: pad, "Can't map buffer readable");
+                gst::FlowError::Error
+            })?;
+
+            std::str::from_utf8(&data)
+                .map_err(|err| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::StreamError::Decode,
+                        ["Failed to decode utf8: {}", err]
+                    );
+                    gst::FlowError::Error
+                })?
+                .trim()
+                .to_string()
+        };
+
+        let Some(rate) = self.rate() else {
+            return Err(gst::FlowError::Flushing);
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let Some((Some(pts), _)) = state.segment.clip(pts, pts) else {
+            gst::debug!(CAT, obj: pad, "Dropping buffer outside the segment");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+        let mut buffers = self.silence(&mut state, pts, rate);
+        drop(state);
+
+        if !text.is_empty() {
+            gst::debug!(CAT, obj: pad, "Synthesizing '{}' at {}", text, pts);
+
+            let samples = {
+                let mut engine = self.engine.lock().unwrap();
+                let Some(engine) = engine.as_mut() else {
+                    return Err(gst::FlowError::Flushing);
+                };
+
+                engine.synthesize(&text).map_err(|err| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::LibraryError::Failed,
+                        ["Failed to synthesize speech: {:#}", err]
+                    );
+                    gst::FlowError::Error
+                })?
+            };
+
+            let mut state = self.state.lock().unwrap();
+            let start = state.next_pts.map_or(pts, |next_pts| next_pts.max(pts));
+            if start > pts {
+                gst::warning!(
+                    CAT,
+                    obj: pad,
+                    "Previous speech still playing, delaying speech by {}",
+                    start - pts
+                );
+            }
+            buffers.push(self.output_buffer(&mut state, start, &samples, rate, false));
+        }
+
+        self.push_buffers(buffers)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                let Some(rate) = self.rate() else {
+                    return false;
+                };
+
+                // We send our own caps downstream
+                let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                    .format(gst_audio::AudioFormat::S16le)
+                    .rate(rate as i32)
+                    .channels(1)
+                    .build();
+                self.srcpad.push_event(gst::event::Caps::new(&caps))
+            }
+            EventView::Segment(ev) => {
+                let segment = match ev.segment().clone().downcast::<gst::ClockTime>() {
+                    Ok(segment) => segment,
+                    Err(_) => {
+                        gst::element_imp_error!(
+                            self,
+                            gst::CoreError::Event,
+                            ["Only time segments are supported"]
+                        );
+                        return false;
+                    }
+                };
+
+                let mut state = self.state.lock().unwrap();
+                state.segment = segment;
+                state.next_pts = None;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Gap(ev) => {
+                let Some(rate) = self.rate() else {
+                    return false;
+                };
+
+                // Output silence instead so that the audio is continuous
+                let (timestamp, duration) = ev.get();
+                let end = timestamp.opt_add(duration).unwrap_or(timestamp);
+
+                let mut state = self.state.lock().unwrap();
+                let buffers = self.silence(&mut state, end, rate);
+                drop(state);
+
+                self.push_buffers(buffers).is_ok()
+            }
+            EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.next_pts = None;
+                state.discont = true;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let Some(model) = settings.model else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No model configured"]
+            ));
+        };
+
+        let speaker = u32::try_from(settings.speaker).ok();
+        let engine = Piper::new(Path::new(&settings.engine_path), Path::new(&model), speaker)
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Failed to load voice: {:#}", err]
+                )
+            })?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Loaded voice {} with rate {}",
+            model,
+            engine.rate()
+        );
+
+        *self.engine.lock().unwrap() = Some(Box::new(engine));
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+
+    fn stop(&self) {
+        *self.engine.lock().unwrap() = None;
+        *self.state.lock().unwrap() = State::default();
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TtsSynth {
+    const NAME: &'static str = "GstTtsSynth";
+    type Type = super::TtsSynth;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                TtsSynth::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |synth| synth.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                TtsSynth::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |synth| synth.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::from_template(&templ);
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+            engine: Mutex::new(None),
+        }
+    }
+}
+
+impl ObjectImpl for TtsSynth {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("engine-path")
+                    .nick("Engine Path")
+                    .blurb("Path of the Piper executable")
+                    .default_value(Some(DEFAULT_ENGINE_PATH))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("model")
+                    .nick("Model")
+                    .blurb("Path of the ONNX voice model, with its configuration next to it as <model>.json")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecInt::builder("speaker")
+                    .nick("Speaker")
+                    .blurb("Speaker to use with multi-speaker voice models (-1=default)")
+                    .minimum(-1)
+                    .default_value(DEFAULT_SPEAKER)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "engine-path" => {
+                settings.engine_path = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| String::from(DEFAULT_ENGINE_PATH));
+            }
+            "model" => {
+                settings.model = value.get().expect("type checked upstream");
+            }
+            "speaker" => {
+                settings.speaker = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "engine-path" => settings.engine_path.to_value(),
+            "model" => settings.model.to_value(),
+            "speaker" => settings.speaker.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for TtsSynth {}
+
+impl ElementImpl for TtsSynth {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Text-To-Speech Synthesizer",
+                "Text/Audio/Converter",
+                "Synthesizes speech from timed text with a local engine",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", "utf8")
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AudioFormat::S16le)
+                .channels(1)
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if transition == gst::StateChange::ReadyToPaused {
+            self.start().map_err(|err| {
+                self.post_error_message(err);
+                gst::StateChangeError
+            })?;
+        }
+
+        let ret = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            self.stop();
+        }
+
+        Ok(ret)
+    }
+}
@@ -0,0 +1,46 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-ttssynth:
+ *
+ * `ttssynth` converts timed text into speech with a local, offline speech synthesis engine,
+ * e.g. to generate an audio description track from a subtitle stream.
+ *
+ * Each text buffer is synthesized and output at its timestamp, with silence in between. If
+ * the speech for a buffer is still playing when the next buffer starts, the next buffer is
+ * delayed until the speech is finished.
+ *
+ * Currently the [Piper](https://github.com/rhasspy/piper) engine is supported: the `piper`
+ * executable is run with the voice model given by the `model` property, and the sample rate
+ * is read from the voice configuration next to the model.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 filesrc location=descriptions.srt ! subparse ! ttssynth model=en_US-lessac-medium.onnx ! audioconvert ! autoaudiosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod engine;
+mod imp;
+
+glib::wrapper! {
+    pub struct TtsSynth(ObjectSubclass<imp::TtsSynth>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "ttssynth",
+        gst::Rank::NONE,
+        TtsSynth::static_type(),
+    )
+}
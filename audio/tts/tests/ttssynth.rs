@@ -0,0 +1,117 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+#![cfg(unix)]
+
+use gst::prelude::*;
+
+use std::os::unix::fs::PermissionsExt;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gsttts::plugin_register_static().unwrap();
+    });
+}
+
+const RATE: u64 = 22050;
+
+// Sets up a fake Piper that outputs 100ms of audio for any text
+fn setup_engine(dir: &std::path::Path) -> (String, String) {
+    let engine = dir.join("piper");
+    std::fs::write(
+        &engine,
+        "#!/bin/sh\n\
+         [ \"$1\" = --model ] || exit 1\n\
+         cat > /dev/null\n\
+         yes | head -c 4410\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&engine, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let model = dir.join("voice.onnx");
+    std::fs::write(&model, b"").unwrap();
+    std::fs::write(
+        dir.join("voice.onnx.json"),
+        format!("{{\"audio\": {{\"sample_rate\": {RATE}}}}}"),
+    )
+    .unwrap();
+
+    (
+        engine.to_str().unwrap().to_string(),
+        model.to_str().unwrap().to_string(),
+    )
+}
+
+fn new_buffer(text: &str, pts: gst::ClockTime, duration: gst::ClockTime) -> gst::Buffer {
+    let mut buf = gst::Buffer::from_mut_slice(text.as_bytes().to_vec());
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+        buf.set_duration(duration);
+    }
+
+    buf
+}
+
+#[test]
+fn test_synthesize() {
+    init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let (engine, model) = setup_engine(dir.path());
+
+    let mut h = gst_check::Harness::new("ttssynth");
+    {
+        let synth = h.element().unwrap();
+        synth.set_property("engine-path", &engine);
+        synth.set_property("model", &model);
+    }
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    assert_eq!(
+        h.push(new_buffer("hello", 1500.mseconds(), 1.seconds())),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    // Overlaps with the previous speech
+    assert_eq!(
+        h.push(new_buffer("world", 1550.mseconds(), 1.seconds())),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    h.push_event(gst::event::Gap::builder(2.seconds()).build());
+    h.push_event(gst::event::Eos::new());
+
+    let caps = h.sinkpad().unwrap().current_caps().unwrap();
+    let info = gst_audio::AudioInfo::from_caps(&caps).unwrap();
+    assert_eq!(info.rate() as u64, RATE);
+    assert_eq!(info.channels(), 1);
+
+    let mut buffers = vec![];
+    while let Some(buf) = h.pull_until_eos().unwrap() {
+        buffers.push((
+            buf.pts().unwrap(),
+            buf.size() as u64 / 2,
+            buf.flags().contains(gst::BufferFlags::GAP),
+        ));
+    }
+
+    assert_eq!(
+        buffers,
+        vec![
+            // Silence up to the first text
+            (gst::ClockTime::ZERO, RATE, true),
+            (1.seconds(), RATE / 2, true),
+            (1500.mseconds(), RATE / 10, false),
+            // Delayed until the first speech is finished
+            (1600.mseconds(), RATE / 10, false),
+            // Silence up to the gap
+            (1700.mseconds(), RATE * 3 / 10, true),
+        ]
+    );
+}
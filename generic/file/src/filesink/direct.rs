@@ -0,0 +1,104 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use std::alloc::{self, Layout};
+use std::fs::File;
+use std::io::{self, Write};
+use std::ptr::NonNull;
+
+/// Alignment of the memory, offsets and sizes of `O_DIRECT` writes.
+///
+/// This covers the logical block size of all common storage devices.
+pub const ALIGNMENT: usize = 4096;
+
+const CAPACITY: usize = 256 * ALIGNMENT;
+
+/// Accumulates data into aligned blocks for writing to a file opened with `O_DIRECT`.
+pub struct DirectWriter {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// Safety: the buffer is exclusively owned
+unsafe impl Send for DirectWriter {}
+
+impl DirectWriter {
+    fn layout() -> Layout {
+        Layout::from_size_align(CAPACITY, ALIGNMENT).unwrap()
+    }
+
+    pub fn new() -> Self {
+        // Safety: the layout has a non-zero size
+        let ptr = unsafe { alloc::alloc(Self::layout()) };
+        let ptr = NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(Self::layout()));
+
+        DirectWriter { ptr, len: 0 }
+    }
+
+    fn buffer(&mut self) -> &mut [u8] {
+        // Safety: the allocation is `CAPACITY` bytes long and owned by `self`
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), CAPACITY) }
+    }
+
+    pub fn write(&mut self, file: &mut File, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let len = self.len;
+            let size = usize::min(data.len(), CAPACITY - len);
+            self.buffer()[len..len + size].copy_from_slice(&data[..size]);
+            self.len += size;
+            data = &data[size..];
+
+            if self.len == CAPACITY {
+                self.flush_blocks(file)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes all complete blocks, keeping the incomplete last one buffered.
+    pub fn flush_blocks(&mut self, file: &mut File) -> io::Result<()> {
+        let aligned = self.len - self.len % ALIGNMENT;
+        if aligned == 0 {
+            return Ok(());
+        }
+
+        let len = self.len;
+        let buffer = self.buffer();
+        file.write_all(&buffer[..aligned])?;
+        buffer.copy_within(aligned..len, 0);
+        self.len -= aligned;
+
+        Ok(())
+    }
+
+    /// Writes all buffered data, padded to a complete block, and truncates the
+    /// file to its actual `size`.
+    pub fn finish(&mut self, file: &mut File, size: u64) -> io::Result<()> {
+        self.flush_blocks(file)?;
+
+        if self.len > 0 {
+            let len = self.len;
+            let buffer = self.buffer();
+            buffer[len..ALIGNMENT].fill(0);
+            file.write_all(&buffer[..ALIGNMENT])?;
+            self.len = 0;
+
+            file.set_len(size)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for DirectWriter {
+    fn drop(&mut self) {
+        // Safety: allocated in `new()` with the same layout
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), Self::layout()) }
+    }
+}
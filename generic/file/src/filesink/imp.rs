@@ -16,25 +16,35 @@ use gst::subclass::prelude::*;
 use gst_base::prelude::*;
 use gst_base::subclass::prelude::*;
 
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use url::Url;
 
 use crate::file_location::FileLocation;
 
 const DEFAULT_LOCATION: Option<FileLocation> = None;
+const DEFAULT_DIRECT_IO: bool = false;
+const DEFAULT_PREALLOCATE_SIZE: u64 = 0;
+const DEFAULT_FSYNC_INTERVAL: u64 = 0;
 
 #[derive(Debug)]
 struct Settings {
     location: Option<FileLocation>,
+    direct_io: bool,
+    preallocate_size: u64,
+    fsync_interval: u64,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             location: DEFAULT_LOCATION,
+            direct_io: DEFAULT_DIRECT_IO,
+            preallocate_size: DEFAULT_PREALLOCATE_SIZE,
+            fsync_interval: DEFAULT_FSYNC_INTERVAL,
         }
     }
 }
@@ -46,6 +56,13 @@ enum State {
     Started {
         file: File,
         position: u64,
+        #[cfg(target_os = "linux")]
+        direct: Option<super::direct::DirectWriter>,
+        preallocate_size: u64,
+        // End of the space allocated for the file
+        preallocated: u64,
+        fsync_interval: Option<Duration>,
+        last_sync: Instant,
     },
 }
 
@@ -120,10 +137,30 @@ impl ObjectImpl for FileSink {
 
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-            vec![glib::ParamSpecString::builder("location")
-                .nick("File Location")
-                .blurb("Location of the file to write")
-                .build()]
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location of the file to write")
+                    .build(),
+                glib::ParamSpecBoolean::builder("direct-io")
+                    .nick("Direct I/O")
+                    .blurb("Write with O_DIRECT, bypassing the page cache (Linux only)")
+                    .default_value(DEFAULT_DIRECT_IO)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("preallocate-size")
+                    .nick("Preallocate Size")
+                    .blurb("Size of the chunks of disk space allocated ahead of the written data (0 = disabled, Linux only)")
+                    .default_value(DEFAULT_PREALLOCATE_SIZE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("fsync-interval")
+                    .nick("Fsync Interval")
+                    .blurb("Interval (in ms) at which the written data is synced to disk, and synced when stopping (0 = disabled)")
+                    .default_value(DEFAULT_FSYNC_INTERVAL)
+                    .mutable_ready()
+                    .build(),
+            ]
         });
 
         PROPERTIES.as_ref()
@@ -143,6 +180,18 @@ impl ObjectImpl for FileSink {
                     gst::error!(CAT, imp: self, "Failed to set property `location`: {}", err);
                 }
             }
+            "direct-io" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.direct_io = value.get().expect("type checked upstream");
+            }
+            "preallocate-size" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.preallocate_size = value.get().expect("type checked upstream");
+            }
+            "fsync-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.fsync_interval = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         };
     }
@@ -158,6 +207,18 @@ impl ObjectImpl for FileSink {
 
                 location.to_value()
             }
+            "direct-io" => {
+                let settings = self.settings.lock().unwrap();
+                settings.direct_io.to_value()
+            }
+            "preallocate-size" => {
+                let settings = self.settings.lock().unwrap();
+                settings.preallocate_size.to_value()
+            }
+            "fsync-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.fsync_interval.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -212,7 +273,25 @@ impl BaseSinkImpl for FileSink {
             )
         })?;
 
-        let file = File::create(location).map_err(|err| {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        if settings.direct_io {
+            #[cfg(target_os = "linux")]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.custom_flags(libc::O_DIRECT);
+            }
+            #[cfg(not(target_os = "linux"))]
+            {
+                return Err(gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["Direct I/O is only supported on Linux"]
+                ));
+            }
+        }
+
+        let file = options.open(location).map_err(|err| {
             gst::error_msg!(
                 gst::ResourceError::OpenWrite,
                 [
@@ -224,7 +303,26 @@ impl BaseSinkImpl for FileSink {
         })?;
         gst::debug!(CAT, imp: self, "Opened file {:?}", file);
 
-        *state = State::Started { file, position: 0 };
+        let preallocate_size = if cfg!(target_os = "linux") {
+            settings.preallocate_size
+        } else {
+            if settings.preallocate_size > 0 {
+                gst::warning!(CAT, imp: self, "Preallocation is only supported on Linux");
+            }
+            0
+        };
+
+        *state = State::Started {
+            file,
+            position: 0,
+            #[cfg(target_os = "linux")]
+            direct: settings.direct_io.then(super::direct::DirectWriter::new),
+            preallocate_size,
+            preallocated: 0,
+            fsync_interval: (settings.fsync_interval > 0)
+                .then(|| Duration::from_millis(settings.fsync_interval)),
+            last_sync: Instant::now(),
+        };
         gst::info!(CAT, imp: self, "Started");
 
         Ok(())
@@ -239,6 +337,34 @@ impl BaseSinkImpl for FileSink {
             ));
         }
 
+        if let State::Started {
+            ref mut file,
+            position,
+            #[cfg(target_os = "linux")]
+            ref mut direct,
+            fsync_interval,
+            ..
+        } = *state
+        {
+            #[cfg(target_os = "linux")]
+            if let Some(direct) = direct {
+                direct.finish(file, position).map_err(|err| {
+                    gst::error_msg!(
+                        gst::ResourceError::Write,
+                        ["Failed to write buffered data: {}", err]
+                    )
+                })?;
+            }
+            #[cfg(not(target_os = "linux"))]
+            let _ = position;
+
+            if fsync_interval.is_some() {
+                file.sync_all().map_err(|err| {
+                    gst::error_msg!(gst::ResourceError::Sync, ["Failed to sync file: {}", err])
+                })?;
+            }
+        }
+
         *state = State::Stopped;
         gst::info!(CAT, imp: self, "Stopped");
 
@@ -249,15 +375,19 @@ impl BaseSinkImpl for FileSink {
 
     fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
         let mut state = self.state.lock().unwrap();
-        let (file, position) = match *state {
-            State::Started {
-                ref mut file,
-                ref mut position,
-            } => (file, position),
-            State::Stopped => {
-                gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
-                return Err(gst::FlowError::Error);
-            }
+        let State::Started {
+            ref mut file,
+            ref mut position,
+            #[cfg(target_os = "linux")]
+            ref mut direct,
+            ref mut preallocate_size,
+            ref mut preallocated,
+            fsync_interval,
+            ref mut last_sync,
+        } = *state
+        else {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            return Err(gst::FlowError::Error);
         };
 
         gst::trace!(CAT, imp: self, "Rendering {:?}", buffer);
@@ -266,7 +396,48 @@ impl BaseSinkImpl for FileSink {
             gst::FlowError::Error
         })?;
 
-        file.write_all(map.as_ref()).map_err(|err| {
+        let end = *position + map.len() as u64;
+
+        #[cfg(target_os = "linux")]
+        if *preallocate_size > 0 && end > *preallocated {
+            use std::os::unix::io::AsRawFd;
+
+            let target = end + *preallocate_size;
+            // Safety: plain system call on a valid file descriptor
+            let res = unsafe {
+                libc::fallocate(
+                    file.as_raw_fd(),
+                    libc::FALLOC_FL_KEEP_SIZE,
+                    *preallocated as libc::off_t,
+                    (target - *preallocated) as libc::off_t,
+                )
+            };
+
+            if res == 0 {
+                gst::trace!(CAT, imp: self, "Preallocated up to {}", target);
+                *preallocated = target;
+            } else {
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Failed to preallocate, disabling: {}",
+                    std::io::Error::last_os_error()
+                );
+                *preallocate_size = 0;
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = (preallocate_size, preallocated);
+
+        #[cfg(target_os = "linux")]
+        let res = match direct {
+            Some(direct) => direct.write(file, map.as_ref()),
+            None => file.write_all(map.as_ref()),
+        };
+        #[cfg(not(target_os = "linux"))]
+        let res = file.write_all(map.as_ref());
+
+        res.map_err(|err| {
             gst::element_imp_error!(
                 self,
                 gst::ResourceError::Write,
@@ -275,7 +446,33 @@ impl BaseSinkImpl for FileSink {
             gst::FlowError::Error
         })?;
 
-        *position += map.len() as u64;
+        *position = end;
+
+        if let Some(fsync_interval) = fsync_interval {
+            if last_sync.elapsed() >= fsync_interval {
+                // Only complete blocks can be written with direct I/O, the
+                // last one is synced once completed or when stopping
+                #[cfg(target_os = "linux")]
+                let res = match direct {
+                    Some(direct) => direct.flush_blocks(file),
+                    None => Ok(()),
+                };
+                #[cfg(not(target_os = "linux"))]
+                let res: std::io::Result<()> = Ok(());
+
+                res.and_then(|_| file.sync_data()).map_err(|err| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::ResourceError::Sync,
+                        ["Failed to sync file: {}", err]
+                    );
+                    gst::FlowError::Error
+                })?;
+
+                gst::trace!(CAT, imp: self, "Synced file at {}", end);
+                *last_sync = Instant::now();
+            }
+        }
 
         Ok(gst::FlowSuccess::Ok)
     }
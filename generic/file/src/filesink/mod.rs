@@ -13,6 +13,8 @@
 use gst::glib;
 use gst::prelude::*;
 
+#[cfg(target_os = "linux")]
+mod direct;
 mod imp;
 
 glib::wrapper! {
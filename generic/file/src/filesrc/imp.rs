@@ -17,27 +17,43 @@ use gst_base::subclass::prelude::*;
 
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
-use std::sync::Mutex;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
 
 use url::Url;
 
 use crate::file_location::FileLocation;
 
+use super::ReadMode;
+
 const DEFAULT_LOCATION: Option<FileLocation> = None;
+const DEFAULT_READ_MODE: ReadMode = ReadMode::Read;
+const DEFAULT_READ_AHEAD: u64 = 0;
 
 #[derive(Debug)]
 struct Settings {
     location: Option<FileLocation>,
+    read_mode: ReadMode,
+    read_ahead: u64,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             location: DEFAULT_LOCATION,
+            read_mode: DEFAULT_READ_MODE,
+            read_ahead: DEFAULT_READ_AHEAD,
         }
     }
 }
 
+enum Reader {
+    Read,
+    Mmap(Arc<memmap2::Mmap>),
+    #[cfg(target_os = "linux")]
+    IoUring(super::uring::UringReader),
+}
+
 #[derive(Default)]
 enum State {
     #[default]
@@ -45,9 +61,23 @@ enum State {
     Started {
         file: File,
         position: u64,
+        reader: Reader,
+        read_ahead: u64,
     },
 }
 
+// Range of a memory-mapped file wrapped in a buffer, keeping the mapping alive
+struct MappedRange {
+    map: Arc<memmap2::Mmap>,
+    range: Range<usize>,
+}
+
+impl AsRef<[u8]> for MappedRange {
+    fn as_ref(&self) -> &[u8] {
+        &self.map[self.range.clone()]
+    }
+}
+
 #[derive(Default)]
 pub struct FileSrc {
     settings: Mutex<Settings>,
@@ -127,11 +157,25 @@ impl ObjectSubclass for FileSrc {
 impl ObjectImpl for FileSrc {
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-            vec![glib::ParamSpecString::builder("location")
-                .nick("File Location")
-                .blurb("Location of the file to read from")
-                .mutable_ready()
-                .build()]
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location of the file to read from")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("read-mode", DEFAULT_READ_MODE)
+                    .nick("Read Mode")
+                    .blurb("How the file is read. The file must not be truncated while memory-mapped")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("read-ahead")
+                    .nick("Read Ahead")
+                    .blurb("Number of bytes to read ahead of the requested ranges in mmap and io-uring modes (0 = disabled)")
+                    .default_value(DEFAULT_READ_AHEAD)
+                    .maximum(u32::MAX as u64)
+                    .mutable_ready()
+                    .build(),
+            ]
         });
 
         PROPERTIES.as_ref()
@@ -151,6 +195,16 @@ impl ObjectImpl for FileSrc {
                     gst::error!(CAT, imp: self, "Failed to set property `location`: {}", err);
                 }
             }
+            "read-mode" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.read_mode = value.get().expect("type checked upstream");
+                gst::info!(CAT, imp: self, "Setting `read-mode` to {:?}", settings.read_mode);
+            }
+            "read-ahead" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.read_ahead = value.get().expect("type checked upstream");
+                gst::info!(CAT, imp: self, "Setting `read-ahead` to {}", settings.read_ahead);
+            }
             _ => unimplemented!(),
         };
     }
@@ -166,6 +220,14 @@ impl ObjectImpl for FileSrc {
 
                 location.to_value()
             }
+            "read-mode" => {
+                let settings = self.settings.lock().unwrap();
+                settings.read_mode.to_value()
+            }
+            "read-ahead" => {
+                let settings = self.settings.lock().unwrap();
+                settings.read_ahead.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -252,7 +314,48 @@ impl BaseSrcImpl for FileSrc {
 
         gst::debug!(CAT, imp: self, "Opened file {:?}", file);
 
-        *state = State::Started { file, position: 0 };
+        let reader = match settings.read_mode {
+            ReadMode::Mmap => {
+                // Safety: the mapping is only read from. Other processes
+                // truncating the file while it's mapped is not supported
+                let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|err| {
+                    gst::error_msg!(
+                        gst::ResourceError::OpenRead,
+                        ["Could not map file {}: {}", location, err.to_string()]
+                    )
+                })?;
+                Reader::Mmap(Arc::new(map))
+            }
+            #[cfg(target_os = "linux")]
+            ReadMode::IoUring => {
+                let reader = super::uring::UringReader::new(settings.read_ahead as usize).map_err(
+                    |err| {
+                        gst::error_msg!(
+                            gst::ResourceError::OpenRead,
+                            ["Could not set up io_uring: {}", err.to_string()]
+                        )
+                    },
+                )?;
+                Reader::IoUring(reader)
+            }
+            #[cfg(not(target_os = "linux"))]
+            ReadMode::IoUring => {
+                return Err(gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["io_uring is only supported on Linux"]
+                ));
+            }
+            ReadMode::Read => Reader::Read,
+        };
+
+        gst::debug!(CAT, imp: self, "Reading with {:?}", settings.read_mode);
+
+        *state = State::Started {
+            file,
+            position: 0,
+            reader,
+            read_ahead: settings.read_ahead,
+        };
 
         gst::info!(CAT, imp: self, "Started");
 
@@ -275,6 +378,75 @@ impl BaseSrcImpl for FileSrc {
         Ok(())
     }
 
+    fn create(
+        &self,
+        offset: u64,
+        buffer: Option<&mut gst::BufferRef>,
+        length: u32,
+    ) -> Result<gst_base::subclass::base_src::CreateSuccess, gst::FlowError> {
+        use gst_base::subclass::base_src::CreateSuccess;
+
+        let state = self.state.lock().unwrap();
+        let (map, read_ahead) = match *state {
+            State::Started {
+                reader: Reader::Mmap(ref map),
+                read_ahead,
+                ..
+            } => (map.clone(), read_ahead),
+            _ => {
+                drop(state);
+                return self.parent_create(offset, buffer, length);
+            }
+        };
+        drop(state);
+
+        if offset >= map.len() as u64 {
+            return Err(gst::FlowError::Eos);
+        }
+
+        let start = offset as usize;
+        let end = usize::min(start + length as usize, map.len());
+
+        #[cfg(unix)]
+        if read_ahead > 0 && end < map.len() {
+            let len = usize::min(read_ahead as usize, map.len() - end);
+            if let Err(err) = map.advise_range(memmap2::Advice::WillNeed, end, len) {
+                gst::warning!(CAT, imp: self, "Failed to advise read-ahead: {}", err);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = read_ahead;
+
+        if let Some(buffer) = buffer {
+            let size = {
+                let mut buffer_map = buffer.map_writable().map_err(|_| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::LibraryError::Failed,
+                        ["Failed to map buffer"]
+                    );
+                    gst::FlowError::Error
+                })?;
+
+                let size = usize::min(end - start, buffer_map.len());
+                buffer_map[..size].copy_from_slice(&map[start..start + size]);
+                size
+            };
+            buffer.set_size(size);
+
+            return Ok(CreateSuccess::FilledBuffer);
+        }
+
+        gst::trace!(CAT, imp: self, "Outputting mapped range {}..{}", start, end);
+
+        Ok(CreateSuccess::NewBuffer(gst::Buffer::from_slice(
+            MappedRange {
+                map,
+                range: start..end,
+            },
+        )))
+    }
+
     fn fill(
         &self,
         offset: u64,
@@ -283,17 +455,48 @@ impl BaseSrcImpl for FileSrc {
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
         let mut state = self.state.lock().unwrap();
 
-        let (file, position) = match *state {
+        let (file, position, reader) = match *state {
             State::Started {
                 ref mut file,
                 ref mut position,
-            } => (file, position),
+                ref mut reader,
+                ..
+            } => (file, position, reader),
             State::Stopped => {
                 gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
                 return Err(gst::FlowError::Error);
             }
         };
 
+        #[cfg(target_os = "linux")]
+        if let Reader::IoUring(ref mut reader) = reader {
+            let size = {
+                let mut map = buffer.map_writable().map_err(|_| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::LibraryError::Failed,
+                        ["Failed to map buffer"]
+                    );
+                    gst::FlowError::Error
+                })?;
+
+                reader.read(file, offset, map.as_mut()).map_err(|err| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::LibraryError::Failed,
+                        ["Failed to read at {}: {}", offset, err.to_string()]
+                    );
+                    gst::FlowError::Error
+                })?
+            };
+
+            buffer.set_size(size);
+
+            return Ok(gst::FlowSuccess::Ok);
+        }
+        #[cfg(not(target_os = "linux"))]
+        let _ = reader;
+
         if *position != offset {
             file.seek(SeekFrom::Start(offset)).map_err(|err| {
                 gst::element_imp_error!(
@@ -13,12 +13,33 @@ use gst::glib;
 use gst::prelude::*;
 
 mod imp;
+#[cfg(target_os = "linux")]
+mod uring;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRsFileSrcReadMode")]
+#[non_exhaustive]
+pub enum ReadMode {
+    #[enum_value(name = "Read: Read with regular read() calls", nick = "read")]
+    Read = 0,
+    #[enum_value(
+        name = "Mmap: Memory-map the file and output its memory without copying",
+        nick = "mmap"
+    )]
+    Mmap = 1,
+    #[enum_value(name = "IoUring: Read with io_uring (Linux only)", nick = "io-uring")]
+    IoUring = 2,
+}
 
 glib::wrapper! {
     pub struct FileSrc(ObjectSubclass<imp::FileSrc>) @extends gst_base::BaseSrc, gst::Element, gst::Object, @implements gst::URIHandler;
 }
 
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    ReadMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
     gst::Element::register(
         Some(plugin),
         "rsfilesrc",
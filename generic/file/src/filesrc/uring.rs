@@ -0,0 +1,191 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use io_uring::{opcode, types, IoUring};
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+const READ: u64 = 1;
+const READ_AHEAD: u64 = 2;
+
+// Read that was submitted ahead of time, while the previous data is processed
+#[derive(Debug)]
+struct ReadAhead {
+    offset: u64,
+    data: Vec<u8>,
+    // `None` while the read is still in flight
+    result: Option<usize>,
+}
+
+/// Reads a file with io_uring, optionally reading ahead of the requested
+/// ranges in the background.
+pub struct UringReader {
+    ring: IoUring,
+    read_ahead_size: usize,
+    read_ahead: Option<ReadAhead>,
+    // Allocation of the last consumed read-ahead, for reuse
+    spare: Vec<u8>,
+}
+
+impl UringReader {
+    pub fn new(read_ahead_size: usize) -> io::Result<Self> {
+        Ok(UringReader {
+            ring: IoUring::new(4)?,
+            read_ahead_size,
+            read_ahead: None,
+            spare: Vec::new(),
+        })
+    }
+
+    // Submits a read and waits for its completion, returning its result.
+    //
+    // Safety: `ptr` must be valid for writing `len` bytes until the read is
+    // completed.
+    unsafe fn submit(
+        &mut self,
+        file: &File,
+        offset: u64,
+        ptr: *mut u8,
+        len: usize,
+        user_data: u64,
+    ) -> io::Result<()> {
+        let entry = opcode::Read::new(types::Fd(file.as_raw_fd()), ptr, len as u32)
+            .offset(offset)
+            .build()
+            .user_data(user_data);
+
+        self.ring
+            .submission()
+            .push(&entry)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "Submission queue is full"))?;
+
+        self.ring.submit()?;
+
+        Ok(())
+    }
+
+    fn wait(&mut self) -> io::Result<(u64, io::Result<usize>)> {
+        loop {
+            if let Some(cqe) = self.ring.completion().next() {
+                let res = if cqe.result() < 0 {
+                    Err(io::Error::from_raw_os_error(-cqe.result()))
+                } else {
+                    Ok(cqe.result() as usize)
+                };
+
+                return Ok((cqe.user_data(), res));
+            }
+
+            match self.ring.submit_and_wait(1) {
+                Ok(_) => (),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    // Waits for the read-ahead in flight, if any
+    fn complete_read_ahead(&mut self) -> io::Result<()> {
+        let Some(read_ahead) = self.read_ahead.as_ref() else {
+            return Ok(());
+        };
+        if read_ahead.result.is_some() {
+            return Ok(());
+        }
+
+        let (user_data, res) = self.wait()?;
+        assert_eq!(user_data, READ_AHEAD);
+
+        match res {
+            Ok(size) => self.read_ahead.as_mut().unwrap().result = Some(size),
+            // Read again when it's actually requested
+            Err(_) => self.spare = self.read_ahead.take().unwrap().data,
+        }
+
+        Ok(())
+    }
+
+    fn read_sync(&mut self, file: &File, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        // Safety: `buf` outlives the read as we wait for its completion
+        unsafe {
+            self.submit(file, offset, buf.as_mut_ptr(), buf.len(), READ)?;
+        }
+
+        let (user_data, res) = self.wait()?;
+        assert_eq!(user_data, READ);
+
+        res
+    }
+
+    /// Reads at `offset` into `buf`, returning the number of bytes read.
+    pub fn read(&mut self, file: &File, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        // Only a single operation is in flight at any time
+        self.complete_read_ahead()?;
+
+        let mut size = 0;
+        let mut eof = false;
+        if let Some(read_ahead) = self.read_ahead.take() {
+            let available = read_ahead.result.unwrap();
+            let end = read_ahead.offset + available as u64;
+
+            if (read_ahead.offset..end).contains(&offset) {
+                let start = (offset - read_ahead.offset) as usize;
+                size = usize::min(buf.len(), available - start);
+                buf[..size].copy_from_slice(&read_ahead.data[start..start + size]);
+                // A short read-ahead means the end of the file was reached
+                eof = available < read_ahead.data.len() && start + size == available;
+            }
+
+            self.spare = read_ahead.data;
+        }
+
+        while size < buf.len() && !eof {
+            let read = self.read_sync(file, offset + size as u64, &mut buf[size..])?;
+            if read == 0 {
+                eof = true;
+            }
+            size += read;
+        }
+
+        if self.read_ahead_size > 0 && !eof {
+            let mut data = std::mem::take(&mut self.spare);
+            data.resize(self.read_ahead_size, 0);
+
+            let read_ahead = ReadAhead {
+                offset: offset + size as u64,
+                data,
+                result: None,
+            };
+            let (ptr, len) = (read_ahead.data.as_ptr() as *mut u8, read_ahead.data.len());
+            let read_ahead_offset = read_ahead.offset;
+            self.read_ahead = Some(read_ahead);
+
+            // Safety: the heap allocation of the data is kept alive in
+            // `self.read_ahead`, or on drop, until the read is completed
+            unsafe {
+                if self
+                    .submit(file, read_ahead_offset, ptr, len, READ_AHEAD)
+                    .is_err()
+                {
+                    self.spare = self.read_ahead.take().unwrap().data;
+                }
+            }
+        }
+
+        Ok(size)
+    }
+}
+
+impl Drop for UringReader {
+    fn drop(&mut self) {
+        // The kernel might still write into the read-ahead buffer
+        let _ = self.complete_read_ahead();
+    }
+}
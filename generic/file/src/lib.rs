@@ -19,10 +19,12 @@ use gst::glib;
 mod file_location;
 mod filesink;
 mod filesrc;
+mod splitfilesink;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     filesink::register(plugin)?;
     filesrc::register(plugin)?;
+    splitfilesink::register(plugin)?;
     Ok(())
 }
 
@@ -0,0 +1,453 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use chrono::format::{Item, StrftimeItems};
+
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rssplitfilesink",
+        gst::DebugColorFlags::empty(),
+        Some("Split File Sink"),
+    )
+});
+
+const DEFAULT_LOCATION: Option<String> = None;
+const DEFAULT_START_INDEX: u32 = 0;
+const DEFAULT_MAX_SIZE: u64 = 0;
+const DEFAULT_MAX_DURATION: u64 = 0;
+
+const SPLIT_EVENT_NAME: &str = "split-now";
+
+#[derive(Debug, Clone)]
+struct Settings {
+    location: Option<String>,
+    start_index: u32,
+    max_size: u64,
+    max_duration: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            location: DEFAULT_LOCATION,
+            start_index: DEFAULT_START_INDEX,
+            max_size: DEFAULT_MAX_SIZE,
+            max_duration: DEFAULT_MAX_DURATION,
+        }
+    }
+}
+
+struct OpenFile {
+    file: File,
+    location: String,
+    size: u64,
+    running_time: Option<gst::ClockTime>,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started {
+        settings: Settings,
+        index: u32,
+        current: Option<OpenFile>,
+        split_pending: bool,
+    },
+}
+
+#[derive(Default)]
+pub struct SplitFileSink {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+/// Builds a file name from `pattern`, expanding strftime specifiers with the
+/// local time and then the printf specifier with `index`.
+fn format_location(pattern: &str, index: u32) -> Result<String, String> {
+    let items = StrftimeItems::new(pattern).collect::<Vec<_>>();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(format!("Invalid strftime pattern {pattern}"));
+    }
+
+    let expanded = chrono::Local::now()
+        .format_with_items(items.into_iter())
+        .to_string();
+
+    sprintf::sprintf!(&expanded, index).map_err(|err| format!("{err:?}"))
+}
+
+impl SplitFileSink {
+    fn open_file(
+        &self,
+        settings: &Settings,
+        index: u32,
+        running_time: Option<gst::ClockTime>,
+    ) -> Result<OpenFile, gst::ErrorMessage> {
+        let pattern = settings.location.as_deref().ok_or_else(|| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["File location is not defined"]
+            )
+        })?;
+
+        let location = format_location(pattern, index).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["Failed to format location {}: {}", pattern, err]
+            )
+        })?;
+
+        let file = File::create(&location).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenWrite,
+                ["Could not open file {} for writing: {}", location, err]
+            )
+        })?;
+
+        gst::debug!(CAT, imp: self, "Opened file {} for index {}", location, index);
+
+        Ok(OpenFile {
+            file,
+            location,
+            size: 0,
+            running_time,
+        })
+    }
+
+    fn close_file(&self, current: OpenFile, index: u32) -> Result<(), gst::ErrorMessage> {
+        let OpenFile {
+            mut file,
+            location,
+            size,
+            running_time,
+        } = current;
+
+        file.flush().map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Close,
+                ["Failed to close file {}: {}", location, err]
+            )
+        })?;
+        drop(file);
+
+        gst::debug!(CAT, imp: self, "Closed file {} with {} bytes", location, size);
+
+        let s = gst::Structure::builder("splitfilesink-file-closed")
+            .field("location", &location)
+            .field("index", index)
+            .field("size", size)
+            .field("running-time", running_time)
+            .build();
+        let _ = self
+            .obj()
+            .post_message(gst::message::Element::builder(s).src(&*self.obj()).build());
+
+        Ok(())
+    }
+
+    fn running_time(&self, buffer: &gst::Buffer) -> Option<gst::ClockTime> {
+        let segment = self.obj().segment();
+        let segment = segment.downcast_ref::<gst::ClockTime>()?;
+
+        segment.to_running_time(buffer.pts())
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SplitFileSink {
+    const NAME: &'static str = "GstRsSplitFileSink";
+    type Type = super::SplitFileSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for SplitFileSink {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().set_sync(false);
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Pattern of the file names, with strftime specifiers followed by an escaped printf index specifier (e.g. rec-%Y%m%d-%%05d.bin)")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("start-index")
+                    .nick("Start Index")
+                    .blurb("Index of the first file")
+                    .default_value(DEFAULT_START_INDEX)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-size")
+                    .nick("Max Size")
+                    .blurb("Maximum size (in bytes) of a file before starting a new one (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_SIZE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-duration")
+                    .nick("Max Duration")
+                    .blurb("Maximum running time (in ns) spanned by a file before starting a new one (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_DURATION)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "location" => {
+                settings.location = value.get().expect("type checked upstream");
+                gst::info!(CAT, imp: self, "Setting `location` to {:?}", settings.location);
+            }
+            "start-index" => {
+                settings.start_index = value.get().expect("type checked upstream");
+            }
+            "max-size" => {
+                settings.max_size = value.get().expect("type checked upstream");
+            }
+            "max-duration" => {
+                settings.max_duration = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+
+        // Limits are taken into account for the next buffer
+        if let State::Started {
+            settings: ref mut state_settings,
+            ..
+        } = *self.state.lock().unwrap()
+        {
+            state_settings.max_size = settings.max_size;
+            state_settings.max_duration = settings.max_duration;
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "location" => settings.location.to_value(),
+            "start-index" => settings.start_index.to_value(),
+            "max-size" => settings.max_size.to_value(),
+            "max-duration" => settings.max_duration.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for SplitFileSink {}
+
+impl ElementImpl for SplitFileSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Split File Sink",
+                "Sink/File",
+                "Write stream to a sequence of files rotated by size, duration or on request",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for SplitFileSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+        if settings.location.is_none() {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["File location is not defined"]
+            ));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        *state = State::Started {
+            index: settings.start_index,
+            settings,
+            current: None,
+            split_pending: false,
+        };
+        gst::info!(CAT, imp: self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let mut state = self.state.lock().unwrap();
+        if let State::Started {
+            ref mut current,
+            index,
+            ..
+        } = *state
+        {
+            if let Some(current) = current.take() {
+                self.close_file(current, index)?;
+            }
+        }
+
+        *state = State::Stopped;
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        match event.view() {
+            gst::EventView::CustomDownstream(ev)
+                if ev
+                    .structure()
+                    .map_or(false, |s| s.has_name(SPLIT_EVENT_NAME)) =>
+            {
+                gst::debug!(CAT, imp: self, "Split requested");
+                if let State::Started {
+                    ref mut split_pending,
+                    ..
+                } = *self.state.lock().unwrap()
+                {
+                    *split_pending = true;
+                }
+            }
+            // Finalize the last file before the EOS message is posted
+            gst::EventView::Eos(_) => {
+                if let State::Started {
+                    ref mut current,
+                    ref mut index,
+                    ..
+                } = *self.state.lock().unwrap()
+                {
+                    if let Some(open) = current.take() {
+                        if let Err(err) = self.close_file(open, *index) {
+                            self.post_error_message(err);
+                            return false;
+                        }
+                        *index += 1;
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        self.parent_event(event)
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let running_time = self.running_time(buffer);
+
+        let mut state = self.state.lock().unwrap();
+        let State::Started {
+            ref settings,
+            ref mut index,
+            ref mut current,
+            ref mut split_pending,
+        } = *state
+        else {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        if let Some(open) = current.as_ref() {
+            let size_exceeded =
+                settings.max_size > 0 && open.size + map.len() as u64 > settings.max_size;
+            let duration_exceeded = settings.max_duration > 0
+                && running_time
+                    .opt_saturating_sub(open.running_time)
+                    .opt_ge(settings.max_duration.nseconds())
+                    .unwrap_or(false);
+
+            if *split_pending || size_exceeded || duration_exceeded {
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Splitting (requested: {}, size: {}, duration: {})",
+                    split_pending,
+                    size_exceeded,
+                    duration_exceeded,
+                );
+
+                let open = current.take().unwrap();
+                self.close_file(open, *index).map_err(|err| {
+                    self.post_error_message(err);
+                    gst::FlowError::Error
+                })?;
+                *index += 1;
+            }
+        }
+        *split_pending = false;
+
+        if current.is_none() {
+            *current = Some(
+                self.open_file(settings, *index, running_time)
+                    .map_err(|err| {
+                        self.post_error_message(err);
+                        gst::FlowError::Error
+                    })?,
+            );
+        }
+
+        let open = current.as_mut().unwrap();
+        if open.running_time.is_none() {
+            open.running_time = running_time;
+        }
+
+        gst::trace!(CAT, imp: self, "Rendering {:?} to {}", buffer, open.location);
+        open.file.write_all(map.as_ref()).map_err(|err| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Write,
+                ["Failed to write buffer to {}: {}", open.location, err]
+            );
+            gst::FlowError::Error
+        })?;
+        open.size += map.len() as u64;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,48 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/**
+ * element-rssplitfilesink:
+ *
+ * Writes a byte stream to a sequence of files, starting a new file once the
+ * current one reaches `max-size` bytes or spans `max-duration` of running
+ * time, or when a custom downstream event named `split-now` is received.
+ *
+ * File names are built from the `location` pattern: strftime specifiers are
+ * expanded first with the local time at which the file is opened, then a
+ * printf integer specifier is replaced by the index of the file. The latter
+ * therefore needs its `%` escaped, as in `rec-%Y%m%d-%H%M%S-%%05d.ts`.
+ *
+ * An element message named `splitfilesink-file-closed` is posted for every
+ * finalized file, with its `location`, `index`, `size` and the `running-time`
+ * of its first buffer.
+ *
+ * ## Example launch line
+ * ```
+ * gst-launch-1.0 videotestsrc ! x264enc ! mpegtsmux ! rssplitfilesink location=rec-%Y%m%d-%H%M%S-%%03d.ts max-duration=60000000000
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct SplitFileSink(ObjectSubclass<imp::SplitFileSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rssplitfilesink",
+        gst::Rank::NONE,
+        SplitFileSink::static_type(),
+    )
+}
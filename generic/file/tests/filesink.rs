@@ -0,0 +1,54 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsfile::plugin_register_static().unwrap();
+    });
+}
+
+#[test]
+fn test_preallocate_and_fsync() {
+    init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.bin");
+
+    let mut h = gst_check::Harness::new("rsfilesink");
+    {
+        let filesink = h.element().unwrap();
+        filesink.set_property("location", path.to_str().unwrap());
+        filesink.set_property("preallocate-size", 1024u64 * 1024);
+        filesink.set_property("fsync-interval", 1u64);
+    }
+
+    h.play();
+
+    let mut expected = Vec::new();
+    for i in 0..10u8 {
+        let data = vec![i; 1000];
+        expected.extend_from_slice(&data);
+        assert_eq!(
+            h.push(gst::Buffer::from_mut_slice(data)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+
+    h.push_event(gst::event::Eos::new());
+    h.element().unwrap().set_state(gst::State::Null).unwrap();
+
+    // Preallocated space is not part of the file size
+    let data = std::fs::read(&path).unwrap();
+    assert_eq!(data, expected);
+}
@@ -0,0 +1,66 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+use std::io::Write;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsfile::plugin_register_static().unwrap();
+    });
+}
+
+fn read_file(path: &std::path::Path, read_mode: &str, read_ahead: u64) -> Vec<u8> {
+    let mut h = gst_check::Harness::new("rsfilesrc");
+    {
+        let filesrc = h.element().unwrap();
+        filesrc.set_property("location", path.to_str().unwrap());
+        filesrc.set_property_from_str("read-mode", read_mode);
+        filesrc.set_property("read-ahead", read_ahead);
+        filesrc.set_property("blocksize", 1000u32);
+    }
+
+    h.play();
+
+    let mut data = Vec::new();
+    while let Some(buffer) = h.pull_until_eos().unwrap() {
+        let map = buffer.map_readable().unwrap();
+        assert!(map.len() <= 1000);
+        data.extend_from_slice(&map);
+    }
+
+    data
+}
+
+#[test]
+fn test_read_modes() {
+    init();
+
+    let expected = (0..10_500u32).map(|i| i as u8).collect::<Vec<_>>();
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&expected).unwrap();
+    file.flush().unwrap();
+
+    let mut modes = vec!["read", "mmap"];
+    if cfg!(target_os = "linux") {
+        modes.push("io-uring");
+    }
+
+    for mode in modes {
+        for read_ahead in [0, 4096] {
+            let data = read_file(file.path(), mode, read_ahead);
+            assert_eq!(data, expected, "{mode} with read-ahead {read_ahead}");
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsfile::plugin_register_static().unwrap();
+    });
+}
+
+fn setup(dir: &tempfile::TempDir) -> (gst_check::Harness, gst::Bus) {
+    let mut h = gst_check::Harness::new("rssplitfilesink");
+    let bus = gst::Bus::new();
+
+    let sink = h.element().unwrap();
+    sink.set_bus(Some(&bus));
+    sink.set_property(
+        "location",
+        dir.path().join("out-%%03d.bin").to_str().unwrap(),
+    );
+
+    h.set_src_caps_str("application/octet-stream");
+    h.play();
+
+    (h, bus)
+}
+
+fn push(h: &mut gst_check::Harness, value: u8, pts: gst::ClockTime) {
+    let mut buffer = gst::Buffer::from_mut_slice(vec![value; 1000]);
+    buffer.get_mut().unwrap().set_pts(pts);
+    assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+}
+
+fn closed_files(bus: &gst::Bus) -> Vec<(String, u64)> {
+    let mut files = Vec::new();
+    while let Some(msg) = bus.pop_filtered(&[gst::MessageType::Element]) {
+        let s = msg.structure().unwrap();
+        if s.name() == "splitfilesink-file-closed" {
+            files.push((
+                s.get::<String>("location").unwrap(),
+                s.get::<u64>("size").unwrap(),
+            ));
+        }
+    }
+
+    files
+}
+
+#[test]
+fn test_max_size() {
+    init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let (mut h, bus) = setup(&dir);
+    h.element().unwrap().set_property("max-size", 2500u64);
+
+    for i in 0..5 {
+        push(&mut h, i, gst::ClockTime::from_seconds(i as u64));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let files = closed_files(&bus);
+    assert_eq!(files.len(), 3);
+    for (i, (location, size)) in files.iter().enumerate() {
+        let expected_location = dir.path().join(format!("out-{i:03}.bin"));
+        assert_eq!(location, expected_location.to_str().unwrap());
+        assert_eq!(*size, if i < 2 { 2000 } else { 1000 });
+        assert_eq!(std::fs::metadata(location).unwrap().len(), *size);
+    }
+}
+
+#[test]
+fn test_max_duration_and_split_event() {
+    init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let (mut h, bus) = setup(&dir);
+    h.element()
+        .unwrap()
+        .set_property("max-duration", gst::ClockTime::from_seconds(2).nseconds());
+
+    push(&mut h, 0, gst::ClockTime::ZERO);
+    push(&mut h, 1, gst::ClockTime::from_seconds(1));
+    // Starts a new file as it's 2s after the first buffer
+    push(&mut h, 2, gst::ClockTime::from_seconds(2));
+    h.push_event(gst::event::CustomDownstream::new(
+        gst::Structure::new_empty("split-now"),
+    ));
+    push(&mut h, 3, gst::ClockTime::from_seconds(3));
+    h.push_event(gst::event::Eos::new());
+
+    let files = closed_files(&bus);
+    let sizes = files.iter().map(|(_, size)| *size).collect::<Vec<_>>();
+    assert_eq!(sizes, [2000, 1000, 1000]);
+
+    let data = std::fs::read(&files[1].0).unwrap();
+    assert_eq!(data, vec![2; 1000]);
+}
@@ -0,0 +1,624 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-gopswitch
+ *
+ * #gopswitch selects one of multiple compressed input streams, for example the renditions of an
+ * encoded ABR ladder or the outputs of redundant encoders, and switches between them without
+ * decoding. A switch requested via the #gopswitch:active-pad property only happens once the new
+ * input receives a buffer without the DELTA_UNIT flag, i.e. at the start of a GOP (Group of
+ * Pictures), and until then data of the previously active input is passed through. By default an
+ * upstream force-key-unit event is sent to the new input to speed up the switch.
+ *
+ * Output buffers are timestamped in running time. With #gopswitch:adjust-timestamps the
+ * timestamps of the new input are shifted so that its first buffer directly follows the last
+ * buffer of the previous input, which keeps the output continuous when switching between
+ * encoders with unrelated timelines.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 gopswitch name=s ! h264parse ! fakesink \
+ *   videotestsrc is-live=true ! tee name=t \
+ *   t. ! queue ! x264enc bitrate=2000 key-int-max=30 ! s.sink_0 \
+ *   t. ! queue ! x264enc bitrate=500 key-int-max=30 ! s.sink_1
+ * ]|
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "gopswitch",
+        gst::DebugColorFlags::empty(),
+        Some("GopSwitch Element"),
+    )
+});
+
+const DEFAULT_ADJUST_TIMESTAMPS: bool = true;
+const DEFAULT_REQUEST_KEYFRAME: bool = true;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    adjust_timestamps: bool,
+    request_keyframe: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            adjust_timestamps: DEFAULT_ADJUST_TIMESTAMPS,
+            request_keyframe: DEFAULT_REQUEST_KEYFRAME,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PadState {
+    segment: Option<gst::FormattedSegment<gst::ClockTime>>,
+}
+
+#[derive(Debug)]
+struct State {
+    pads: HashMap<gst::Pad, PadState>,
+    pad_serial: u32,
+    active_pad: Option<gst::Pad>,
+    // Pad that becomes active with its next keyframe
+    pending_pad: Option<gst::Pad>,
+    need_keyframe: bool,
+    need_stream_start: bool,
+    need_caps: bool,
+    need_segment: bool,
+    discont: bool,
+    // Offset in nanoseconds that is added to the running time of the active pad
+    ts_offset: i64,
+    // All times are in output running time
+    last_running_time: Option<gst::ClockTime>,
+    next_running_time: Option<gst::ClockTime>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            pads: HashMap::new(),
+            pad_serial: 0,
+            active_pad: None,
+            pending_pad: None,
+            need_keyframe: true,
+            need_stream_start: true,
+            need_caps: true,
+            need_segment: true,
+            discont: true,
+            ts_offset: 0,
+            last_running_time: None,
+            next_running_time: None,
+        }
+    }
+}
+
+impl State {
+    fn reset(&mut self) {
+        for pad_state in self.pads.values_mut() {
+            pad_state.segment = None;
+        }
+        self.need_keyframe = true;
+        self.need_stream_start = true;
+        self.need_caps = true;
+        self.need_segment = true;
+        self.discont = true;
+        self.ts_offset = 0;
+        self.last_running_time = None;
+        self.next_running_time = None;
+    }
+
+    fn is_active(&self, pad: &gst::Pad) -> bool {
+        self.active_pad.as_ref() == Some(pad)
+    }
+
+    fn is_pending(&self, pad: &gst::Pad) -> bool {
+        self.pending_pad.as_ref() == Some(pad)
+    }
+}
+
+pub(crate) struct GopSwitch {
+    srcpad: gst::Pad,
+    // Serializes data flow from the sink pads
+    stream_lock: Mutex<()>,
+    state: Mutex<State>,
+    settings: Mutex<Settings>,
+}
+
+impl GopSwitch {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        mut buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let is_keyframe = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+
+        {
+            // Drop data of inactive pads without waiting for the active pad
+            let state = self.state.lock().unwrap();
+            if !state.is_active(pad) && !(is_keyframe && state.is_pending(pad)) {
+                gst::trace!(CAT, obj: pad, "Dropping buffer of inactive pad");
+                return Ok(gst::FlowSuccess::Ok);
+            }
+        }
+
+        let _stream_lock = self.stream_lock.lock().unwrap();
+        let settings = *self.settings.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+
+        let switched = is_keyframe && state.is_pending(pad);
+        if switched {
+            gst::info!(CAT, obj: pad, "Switching at keyframe {}", buffer.pts().display());
+            state.active_pad = state.pending_pad.take();
+            state.need_caps = true;
+        }
+
+        if !state.is_active(pad) {
+            gst::trace!(CAT, obj: pad, "Dropping buffer of inactive pad");
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        if state.need_keyframe {
+            if !is_keyframe {
+                gst::debug!(CAT, obj: pad, "Dropping delta unit while waiting for keyframe");
+                return Ok(gst::FlowSuccess::Ok);
+            }
+            state.need_keyframe = false;
+        }
+
+        let Some(segment) = state.pads.get(pad).and_then(|p| p.segment.clone()) else {
+            gst::element_imp_error!(self, gst::CoreError::Clock, ["Got buffer before segment"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        let pts = buffer.pts().and_then(|pts| segment.to_running_time(pts));
+        let dts = buffer.dts().and_then(|dts| segment.to_running_time(dts));
+        let Some(running_time) = dts.or(pts) else {
+            gst::error!(CAT, obj: pad, "Require timestamped buffers!");
+            return Err(gst::FlowError::Error);
+        };
+
+        if switched {
+            state.ts_offset = match state.next_running_time {
+                Some(next_running_time) if settings.adjust_timestamps => {
+                    next_running_time.nseconds() as i64 - running_time.nseconds() as i64
+                }
+                _ => 0,
+            };
+            state.discont = true;
+            gst::debug!(CAT, obj: pad, "Timestamp offset {}ns", state.ts_offset);
+        }
+
+        let ts_offset = state.ts_offset;
+        let adjust = |ts: gst::ClockTime| {
+            gst::ClockTime::from_nseconds(ts.nseconds().saturating_add_signed(ts_offset))
+        };
+
+        let running_time = adjust(running_time);
+        {
+            let buffer = buffer.make_mut();
+            buffer.set_pts(pts.map(adjust));
+            buffer.set_dts(dts.map(adjust));
+            if state.discont {
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+                state.discont = false;
+            }
+        }
+
+        // Without durations, assume that the next buffer follows with the same distance as
+        // the last one
+        let duration = buffer.duration().or_else(|| {
+            state
+                .last_running_time
+                .and_then(|last| running_time.checked_sub(last))
+        });
+        state.last_running_time = Some(running_time);
+        state.next_running_time = Some(running_time + duration.unwrap_or(gst::ClockTime::ZERO));
+
+        let mut events = Vec::new();
+        if state.need_stream_start {
+            if let Some(event) = pad.sticky_event::<gst::event::StreamStart>(0) {
+                events.push(event);
+            }
+            state.need_stream_start = false;
+        }
+        if state.need_caps {
+            if let Some(caps) = pad.current_caps() {
+                events.push(gst::event::Caps::new(&caps));
+            }
+            state.need_caps = false;
+        }
+        if state.need_segment {
+            let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+            events.push(gst::event::Segment::new(&segment));
+            state.need_segment = false;
+        }
+        drop(state);
+
+        if switched {
+            self.obj().notify("active-pad");
+        }
+
+        for event in events {
+            self.srcpad.push_event(event);
+        }
+
+        self.srcpad.push(buffer)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        let obj = self.obj();
+
+        match event.view() {
+            gst::EventView::Segment(segment) => {
+                let Ok(segment) = segment.segment().clone().downcast::<gst::ClockTime>() else {
+                    gst::error!(CAT, obj: pad, "Non TIME segments are not supported");
+                    return false;
+                };
+                let mut state = self.state.lock().unwrap();
+                if let Some(pad_state) = state.pads.get_mut(pad) {
+                    pad_state.segment = Some(segment);
+                }
+                // The output has its own segment in running time
+                return true;
+            }
+            gst::EventView::StreamStart(_) => {
+                // Only the first stream-start of the active pad is forwarded
+                return true;
+            }
+            gst::EventView::Caps(_) => {
+                // Forwarded before the next buffer of the pad once it is active
+                let mut state = self.state.lock().unwrap();
+                if state.is_active(pad) {
+                    state.need_caps = true;
+                }
+                return true;
+            }
+            _ => (),
+        }
+
+        if !event.is_serialized() {
+            let mut state = self.state.lock().unwrap();
+            if !state.is_active(pad) {
+                return true;
+            }
+
+            if event.type_() == gst::EventType::FlushStart {
+                state.need_keyframe = true;
+            }
+            drop(state);
+
+            return self.srcpad.push_event(event);
+        }
+
+        let _stream_lock = self.stream_lock.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+        if !state.is_active(pad) {
+            return true;
+        }
+
+        let event = match event.view() {
+            gst::EventView::FlushStop(_) => {
+                state.need_keyframe = true;
+                state.need_segment = true;
+                state.discont = true;
+                state.last_running_time = None;
+                state.next_running_time = None;
+                event
+            }
+            gst::EventView::Gap(gap) => {
+                let Some(segment) = state.pads.get(pad).and_then(|p| p.segment.clone()) else {
+                    return true;
+                };
+                let (timestamp, duration) = gap.get();
+                let Some(running_time) = segment.to_running_time(timestamp) else {
+                    return true;
+                };
+                let running_time = gst::ClockTime::from_nseconds(
+                    running_time
+                        .nseconds()
+                        .saturating_add_signed(state.ts_offset),
+                );
+                gst::event::Gap::builder(running_time)
+                    .duration(duration)
+                    .build()
+            }
+            _ => event,
+        };
+        drop(state);
+
+        gst::log!(CAT, obj: obj, "Forwarding event {:?}", event);
+        self.srcpad.push_event(event)
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        let active_pad = self.state.lock().unwrap().active_pad.clone();
+
+        match active_pad {
+            Some(active_pad) => active_pad.peer_query(query),
+            None => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+
+    fn set_active_pad(&self, pad: Option<gst::Pad>) {
+        let settings = *self.settings.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+
+        let Some(pad) = pad else {
+            state.pending_pad = None;
+            return;
+        };
+
+        if !state.pads.contains_key(&pad) {
+            gst::warning!(CAT, imp: self, "Pad {} is not a sink pad", pad.name());
+            return;
+        }
+
+        if state.active_pad.is_none() {
+            gst::debug!(CAT, imp: self, "Activating {}", pad.name());
+            state.active_pad = Some(pad);
+            state.need_keyframe = true;
+            state.need_caps = true;
+            return;
+        }
+
+        if state.is_active(&pad) {
+            state.pending_pad = None;
+            return;
+        }
+
+        gst::debug!(CAT, imp: self, "Switching to {} at next keyframe", pad.name());
+        state.pending_pad = Some(pad.clone());
+        drop(state);
+
+        if settings.request_keyframe {
+            let event = gst_video::UpstreamForceKeyUnitEvent::builder()
+                .all_headers(true)
+                .build();
+            pad.push_event(event);
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for GopSwitch {
+    const NAME: &'static str = "GstGopSwitch";
+    type Type = super::GopSwitch;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                GopSwitch::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |gopswitch| gopswitch.src_query(pad, query),
+                )
+            })
+            .build();
+
+        Self {
+            srcpad,
+            stream_lock: Mutex::new(()),
+            state: Mutex::new(State::default()),
+            settings: Mutex::new(Settings::default()),
+        }
+    }
+}
+
+impl ObjectImpl for GopSwitch {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecObject::builder::<gst::Pad>("active-pad")
+                    .nick("Active Pad")
+                    .blurb("Currently active pad, setting switches at the next keyframe")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("adjust-timestamps")
+                    .nick("Adjust Timestamps")
+                    .blurb("Continue the timestamps of the previous input after switching")
+                    .default_value(DEFAULT_ADJUST_TIMESTAMPS)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("request-keyframe")
+                    .nick("Request Keyframe")
+                    .blurb("Request a keyframe upstream from the new input when switching")
+                    .default_value(DEFAULT_REQUEST_KEYFRAME)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        &PROPERTIES
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "active-pad" => {
+                let pad = value
+                    .get::<Option<gst::Pad>>()
+                    .expect("type checked upstream");
+                self.set_active_pad(pad);
+            }
+            "adjust-timestamps" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.adjust_timestamps = value.get().expect("type checked upstream");
+            }
+            "request-keyframe" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.request_keyframe = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "active-pad" => {
+                let state = self.state.lock().unwrap();
+                state.active_pad.to_value()
+            }
+            "adjust-timestamps" => {
+                let settings = self.settings.lock().unwrap();
+                settings.adjust_timestamps.to_value()
+            }
+            "request-keyframe" => {
+                let settings = self.settings.lock().unwrap();
+                settings.request_keyframe.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for GopSwitch {}
+
+impl ElementImpl for GopSwitch {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "GopSwitch",
+                "Generic",
+                "Switches between compressed streams at GOP boundaries",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink_%u",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Request,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn request_new_pad(
+        &self,
+        templ: &gst::PadTemplate,
+        _name: Option<&str>,
+        _caps: Option<&gst::Caps>,
+    ) -> Option<gst::Pad> {
+        let mut state = self.state.lock().unwrap();
+
+        let id = state.pad_serial;
+        state.pad_serial += 1;
+
+        let sinkpad = gst::Pad::builder_from_template(templ)
+            .name(format!("sink_{id}").as_str())
+            .chain_function(|pad, parent, buffer| {
+                GopSwitch::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |gopswitch| gopswitch.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                GopSwitch::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |gopswitch| gopswitch.sink_event(pad, event),
+                )
+            })
+            .flags(gst::PadFlags::PROXY_CAPS | gst::PadFlags::PROXY_ALLOCATION)
+            .build();
+
+        state.pads.insert(sinkpad.clone(), PadState::default());
+
+        // The first pad is active until switching
+        let activated = state.active_pad.is_none();
+        if activated {
+            state.active_pad = Some(sinkpad.clone());
+            state.need_keyframe = true;
+            state.need_caps = true;
+        }
+        drop(state);
+
+        let obj = self.obj();
+        obj.add_pad(&sinkpad).unwrap();
+        if activated {
+            obj.notify("active-pad");
+        }
+
+        Some(sinkpad)
+    }
+
+    fn release_pad(&self, pad: &gst::Pad) {
+        let mut state = self.state.lock().unwrap();
+        state.pads.remove(pad);
+
+        if state.is_pending(pad) {
+            state.pending_pad = None;
+        }
+
+        let deactivated = state.is_active(pad);
+        if deactivated {
+            state.active_pad = state.pending_pad.take();
+            state.need_keyframe = true;
+            state.need_caps = true;
+        }
+        drop(state);
+
+        let obj = self.obj();
+        obj.remove_pad(pad).unwrap();
+        if deactivated {
+            obj.notify("active-pad");
+        }
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let res = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            self.state.lock().unwrap().reset();
+        }
+
+        Ok(res)
+    }
+}
@@ -15,9 +15,12 @@
 use gst::glib;
 
 mod gopbuffer;
+mod gopswitch;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
-    gopbuffer::register(plugin)
+    gopbuffer::register(plugin)?;
+    gopswitch::register(plugin)?;
+    Ok(())
 }
 
 gst::plugin_define!(
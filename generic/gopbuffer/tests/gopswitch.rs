@@ -0,0 +1,129 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+//
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstgopbuffer::plugin_register_static().unwrap();
+    });
+}
+
+fn push_buffer(h: &mut gst_check::Harness, pts: gst::ClockTime, keyframe: bool) {
+    let mut buffer = gst::Buffer::with_size(1).unwrap();
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(pts);
+        buffer.set_dts(pts);
+        buffer.set_duration(gst::ClockTime::from_mseconds(100));
+        if !keyframe {
+            buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+        }
+    }
+    assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+}
+
+#[test]
+fn test_switch_at_keyframe() {
+    init();
+
+    let mut h0 = gst_check::Harness::with_padnames("gopswitch", Some("sink_0"), Some("src"));
+    let element = h0.element().unwrap();
+    let mut h1 = gst_check::Harness::with_element(&element, Some("sink_1"), None);
+
+    h0.set_src_caps_str("video/x-test,stream=0");
+    h1.set_src_caps_str("video/x-test,stream=1");
+    h0.play();
+
+    let sink_0 = element.static_pad("sink_0").unwrap();
+    let sink_1 = element.static_pad("sink_1").unwrap();
+    assert_eq!(
+        element.property::<Option<gst::Pad>>("active-pad"),
+        Some(sink_0)
+    );
+
+    // Output starts at the first keyframe
+    push_buffer(&mut h0, gst::ClockTime::ZERO, false);
+    for i in 1..4 {
+        push_buffer(&mut h0, gst::ClockTime::from_mseconds(i * 100), i == 1);
+    }
+    for i in 1..4 {
+        let buffer = h0.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(i * 100)));
+    }
+    assert_eq!(h0.buffers_in_queue(), 0);
+
+    // Data of the old input is forwarded until the new input has a keyframe
+    element.set_property("active-pad", &sink_1);
+    push_buffer(&mut h1, gst::ClockTime::from_seconds(5), false);
+    push_buffer(&mut h0, gst::ClockTime::from_mseconds(400), false);
+    let buffer = h0.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(400)));
+    assert_eq!(h0.buffers_in_queue(), 0);
+
+    // The new input continues with the timestamps of the old one
+    push_buffer(&mut h1, gst::ClockTime::from_mseconds(5100), true);
+    push_buffer(&mut h0, gst::ClockTime::from_mseconds(500), false);
+    push_buffer(&mut h1, gst::ClockTime::from_mseconds(5200), false);
+
+    let buffer = h0.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(500)));
+    assert!(buffer.flags().contains(gst::BufferFlags::DISCONT));
+    assert!(!buffer.flags().contains(gst::BufferFlags::DELTA_UNIT));
+    let buffer = h0.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(600)));
+    assert_eq!(h0.buffers_in_queue(), 0);
+
+    assert_eq!(
+        element.property::<Option<gst::Pad>>("active-pad"),
+        Some(sink_1)
+    );
+
+    // The caps of the new input are forwarded before its first buffer
+    let mut caps = None;
+    while let Some(event) = h0.try_pull_event() {
+        if let gst::EventView::Caps(ev) = event.view() {
+            caps = Some(ev.caps_owned());
+        }
+    }
+    assert_eq!(
+        caps,
+        Some(
+            gst::Caps::builder("video/x-test")
+                .field("stream", 1)
+                .build()
+        )
+    );
+}
+
+#[test]
+fn test_switch_without_adjust_timestamps() {
+    init();
+
+    let mut h0 = gst_check::Harness::with_padnames("gopswitch", Some("sink_0"), Some("src"));
+    let element = h0.element().unwrap();
+    element.set_property("adjust-timestamps", false);
+    let mut h1 = gst_check::Harness::with_element(&element, Some("sink_1"), None);
+
+    h0.set_src_caps_str("video/x-test");
+    h1.set_src_caps_str("video/x-test");
+    h0.play();
+
+    push_buffer(&mut h0, gst::ClockTime::ZERO, true);
+    assert_eq!(h0.pull().unwrap().pts(), Some(gst::ClockTime::ZERO));
+
+    element.set_property("active-pad", element.static_pad("sink_1").unwrap());
+    push_buffer(&mut h1, gst::ClockTime::from_seconds(5), true);
+    assert_eq!(
+        h0.pull().unwrap().pts(),
+        Some(gst::ClockTime::from_seconds(5))
+    );
+}
@@ -50,6 +50,28 @@ impl InterSink {
         let settings = self.settings.lock().unwrap();
         InterStreamProducer::release(&settings.producer_name);
     }
+
+    /// Caps supported by all the consumers, or `None` if there are none.
+    fn consumers_caps(&self, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
+        let producer_name = self.settings.lock().unwrap().producer_name.clone();
+        let consumers = InterStreamProducer::consumers(&producer_name);
+        if consumers.is_empty() {
+            return None;
+        }
+
+        let mut caps = filter.cloned().unwrap_or_else(gst::Caps::new_any);
+        for consumer in consumers {
+            let srcpad = consumer.static_pad("src").unwrap();
+            caps = srcpad.peer_query_caps(Some(&caps));
+            if caps.is_empty() {
+                break;
+            }
+        }
+
+        gst::debug!(CAT, imp: self, "Consumers support caps {}", caps);
+
+        Some(caps)
+    }
 }
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
@@ -147,6 +169,40 @@ impl ObjectImpl for InterSink {
         let state = self.state.lock().unwrap();
         obj.add(&state.appsink).unwrap();
         obj.add_pad(&state.sinkpad).unwrap();
+
+        // Let the producer pipeline negotiate caps supported by the consumers
+        let this = obj.downgrade();
+        state
+            .sinkpad
+            .add_probe(gst::PadProbeType::QUERY_DOWNSTREAM, move |_pad, info| {
+                let Some(this) = this.upgrade() else {
+                    return gst::PadProbeReturn::Ok;
+                };
+                let Some(gst::PadProbeData::Query(ref mut query)) = info.data else {
+                    return gst::PadProbeReturn::Ok;
+                };
+
+                match query.view_mut() {
+                    gst::QueryViewMut::Caps(q) => {
+                        let Some(caps) = this.imp().consumers_caps(q.filter_owned().as_ref())
+                        else {
+                            return gst::PadProbeReturn::Ok;
+                        };
+                        q.set_result(&caps);
+                        gst::PadProbeReturn::Handled
+                    }
+                    gst::QueryViewMut::AcceptCaps(q) => {
+                        let Some(caps) = this.imp().consumers_caps(None) else {
+                            return gst::PadProbeReturn::Ok;
+                        };
+                        let accepted = q.caps().can_intersect(&caps);
+                        q.set_result(accepted);
+                        gst::PadProbeReturn::Handled
+                    }
+                    _ => gst::PadProbeReturn::Ok,
+                }
+            })
+            .unwrap();
         state
             .sinkpad
             .set_target(Some(&state.appsink.static_pad("sink").unwrap()))
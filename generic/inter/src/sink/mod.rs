@@ -14,6 +14,12 @@ mod imp;
  * You can access the underlying appsink element through the static name
  * "appsink".
  *
+ * Caps queries are answered with the caps supported by all the #intersrc
+ * currently consuming from it, so that the producer pipeline negotiates a
+ * format they can all handle. Reconfigure and QoS events from the consumers
+ * are forwarded to the producer pipeline, the QoS timestamps being converted
+ * to its running time.
+ *
  * #intersink should not reside in the same pipeline as the #intersrc
  * that consumes from it, here is an example of how to use those elements
  * in separate pipelines:
@@ -52,6 +52,42 @@ impl InterSrc {
 
         InterStreamProducer::unsubscribe(&settings.producer_name, &state.appsrc);
     }
+
+    /// Forwards upstream events affecting the producer to its pipeline.
+    fn forward_upstream_event(&self, event: &gst::Event) {
+        let producer_name = self.settings.lock().unwrap().producer_name.clone();
+        let Some(appsink) = InterStreamProducer::appsink(&producer_name) else {
+            return;
+        };
+        let sinkpad = appsink.static_pad("sink").unwrap();
+
+        match event.view() {
+            // Lets the producer renegotiate caps with the changed consumer
+            gst::EventView::Reconfigure(_) => {
+                gst::debug!(CAT, imp: self, "Forwarding reconfigure to producer");
+                sinkpad.push_event(gst::event::Reconfigure::new());
+            }
+            gst::EventView::Qos(ev) => {
+                let (type_, proportion, diff, timestamp) = ev.get();
+
+                // Convert the running time of the consumer pipeline to the one
+                // of the producer pipeline
+                let (Some(timestamp), Some(base_time), Some(producer_base_time)) =
+                    (timestamp, self.obj().base_time(), appsink.base_time())
+                else {
+                    return;
+                };
+                let Some(timestamp) = (timestamp + base_time).checked_sub(producer_base_time)
+                else {
+                    return;
+                };
+
+                gst::trace!(CAT, imp: self, "Forwarding QoS to producer at {}", timestamp);
+                sinkpad.push_event(gst::event::Qos::new(type_, proportion, diff, timestamp));
+            }
+            _ => (),
+        }
+    }
 }
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
@@ -133,6 +169,20 @@ impl ObjectImpl for InterSrc {
         gst_utils::StreamProducer::configure_consumer(&state.appsrc);
         obj.add(&state.appsrc).unwrap();
         obj.add_pad(&state.srcpad).unwrap();
+
+        let this = obj.downgrade();
+        state
+            .srcpad
+            .add_probe(gst::PadProbeType::EVENT_UPSTREAM, move |_pad, info| {
+                if let (Some(this), Some(gst::PadProbeData::Event(ref event))) =
+                    (this.upgrade(), &info.data)
+                {
+                    this.imp().forward_upstream_event(event);
+                }
+
+                gst::PadProbeReturn::Ok
+            })
+            .unwrap();
         state
             .srcpad
             .set_target(Some(&state.appsrc.static_pad("src").unwrap()))
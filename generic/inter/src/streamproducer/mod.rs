@@ -144,6 +144,26 @@ impl InterStreamProducer {
         }
     }
 
+    /// The appsink of the active producer with this name, if any.
+    pub fn appsink(name: &str) -> Option<gst_app::AppSink> {
+        let producers = PRODUCERS.lock().unwrap();
+
+        match producers.get(name) {
+            Some(InterStreamProducer::Active { producer, .. }) => Some(producer.appsink().clone()),
+            _ => None,
+        }
+    }
+
+    /// The consumers linked to the active producer with this name.
+    pub fn consumers(name: &str) -> Vec<gst_app::AppSrc> {
+        let producers = PRODUCERS.lock().unwrap();
+
+        match producers.get(name) {
+            Some(InterStreamProducer::Active { links, .. }) => links.keys().cloned().collect(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn unsubscribe(name: &str, consumer: &gst_app::AppSrc) -> bool {
         let mut producers = PRODUCERS.lock().unwrap();
 
@@ -136,3 +136,38 @@ fn test_change_producer_name() {
     element1.set_state(gst::State::Null).unwrap();
     element2.set_state(gst::State::Null).unwrap();
 }
+
+#[test]
+#[serial]
+fn test_caps_negotiation() {
+    init();
+
+    let mut hc = start_consumer("p1");
+    hc.set_sink_caps_str("video/x-raw, format=(string)I420");
+
+    let element = gst::ElementFactory::make("intersink").build().unwrap();
+    element.set_property("producer-name", "p1");
+    element.set_state(gst::State::Playing).unwrap();
+
+    let sinkpad = element.static_pad("sink").unwrap();
+    let caps = sinkpad.query_caps(Some(&gst::Caps::builder("video/x-raw").build()));
+    assert_eq!(
+        caps,
+        gst::Caps::builder("video/x-raw")
+            .field("format", "I420")
+            .build()
+    );
+
+    assert!(sinkpad.query_accept_caps(
+        &gst::Caps::builder("video/x-raw")
+            .field("format", "I420")
+            .build()
+    ));
+    assert!(!sinkpad.query_accept_caps(
+        &gst::Caps::builder("video/x-raw")
+            .field("format", "RGB")
+            .build()
+    ));
+
+    element.set_state(gst::State::Null).unwrap();
+}
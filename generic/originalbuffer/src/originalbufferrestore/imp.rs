@@ -13,6 +13,8 @@ use gst_video::prelude::*;
 
 use atomic_refcell::AtomicRefCell;
 
+use std::sync::Mutex;
+
 use crate::originalbuffermeta;
 use crate::originalbuffermeta::OriginalBufferMeta;
 
@@ -30,14 +32,65 @@ impl Default for CapsState {
     }
 }
 
+const DEFAULT_KEEP_ALL_METAS: bool = true;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    keep_all_metas: bool,
+    keep_metas: Vec<String>,
+    keep_caps_fields: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            keep_all_metas: DEFAULT_KEEP_ALL_METAS,
+            keep_metas: Vec::new(),
+            keep_caps_fields: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    fn keeps_meta(&self, meta: &gst::MetaRef<gst::Meta>) -> bool {
+        self.keep_all_metas || self.keep_metas.iter().any(|name| meta.api().name() == name)
+    }
+
+    // Caps of the original buffers, with the selected fields replaced by the
+    // ones of the processed stream
+    fn src_caps(&self, original_caps: &gst::Caps, sinkpad_caps: &gst::Caps) -> gst::Caps {
+        if self.keep_caps_fields.is_empty() {
+            return original_caps.clone();
+        }
+
+        let Some(processed_s) = sinkpad_caps.structure(0) else {
+            return original_caps.clone();
+        };
+
+        let mut caps = original_caps.clone();
+        for s in caps.make_mut().iter_mut() {
+            for field in &self.keep_caps_fields {
+                match processed_s.value(field.as_str()) {
+                    Ok(value) => s.set_value(field.as_str(), value.clone()),
+                    Err(_) => s.remove_field(field.as_str()),
+                }
+            }
+        }
+
+        caps
+    }
+}
+
 #[derive(Default)]
 struct State {
     sinkpad_caps: CapsState,
     meta_caps: CapsState,
+    src_caps: Option<gst::Caps>,
     sinkpad_segment: Option<gst::Event>,
 }
 
 pub struct OriginalBufferRestore {
+    settings: Mutex<Settings>,
     state: AtomicRefCell<State>,
     src_pad: gst::Pad,
     sink_pad: gst::Pad,
@@ -100,12 +153,76 @@ impl ObjectSubclass for OriginalBufferRestore {
         Self {
             src_pad,
             sink_pad,
+            settings: Default::default(),
             state: Default::default(),
         }
     }
 }
 
 impl ObjectImpl for OriginalBufferRestore {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecBoolean::builder("keep-all-metas")
+                    .nick("Keep All Metas")
+                    .blurb("Keep all metas of the processed buffers, instead of only the ones listed in keep-metas")
+                    .default_value(DEFAULT_KEEP_ALL_METAS)
+                    .mutable_playing()
+                    .build(),
+                gst::ParamSpecArray::builder("keep-metas")
+                    .nick("Keep Metas")
+                    .blurb("Names of the meta APIs of the processed buffers to keep when keep-all-metas is disabled (e.g. GstVideoRegionOfInterestMetaAPI)")
+                    .element_spec(&glib::ParamSpecString::builder("meta-api").build())
+                    .mutable_playing()
+                    .build(),
+                gst::ParamSpecArray::builder("keep-caps-fields")
+                    .nick("Keep Caps Fields")
+                    .blurb("Names of the caps fields of the processed stream replacing the ones of the original stream")
+                    .element_spec(&glib::ParamSpecString::builder("field").build())
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "keep-all-metas" => {
+                settings.keep_all_metas = value.get().expect("type checked upstream");
+            }
+            "keep-metas" => {
+                settings.keep_metas = value
+                    .get::<gst::ArrayRef>()
+                    .expect("type checked upstream")
+                    .iter()
+                    .map(|v| v.get::<String>().expect("type checked upstream"))
+                    .collect();
+            }
+            "keep-caps-fields" => {
+                settings.keep_caps_fields = value
+                    .get::<gst::ArrayRef>()
+                    .expect("type checked upstream")
+                    .iter()
+                    .map(|v| v.get::<String>().expect("type checked upstream"))
+                    .collect();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "keep-all-metas" => settings.keep_all_metas.to_value(),
+            "keep-metas" => gst::Array::new(&settings.keep_metas).to_value(),
+            "keep-caps-fields" => gst::Array::new(&settings.keep_caps_fields).to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
     fn constructed(&self) {
         self.parent_constructed();
 
@@ -247,16 +364,22 @@ impl OriginalBufferRestore {
             //gst::element_warning!(self, gst::StreamError::Failed, ["Buffer {} is missing the GstOriginalBufferMeta, put originalbuffersave upstream in your pipeline", buffer]);
             return Ok(gst::FlowSuccess::Ok);
         };
+        let settings = self.settings.lock().unwrap().clone();
         let mut state = self.state.borrow_mut();
         let meta_caps = &mut state.meta_caps;
         if &meta_caps.caps != ometa.caps() {
-            if !self.src_pad.push_event(gst::event::Caps::new(ometa.caps())) {
-                return Err(gst::FlowError::NotNegotiated);
-            }
             meta_caps.caps = ometa.caps().clone();
             meta_caps.vinfo = gst_video::VideoInfo::from_caps(&meta_caps.caps).ok();
         }
 
+        let src_caps = settings.src_caps(ometa.caps(), &state.sinkpad_caps.caps);
+        if state.src_caps.as_ref() != Some(&src_caps) {
+            if !self.src_pad.push_event(gst::event::Caps::new(&src_caps)) {
+                return Err(gst::FlowError::NotNegotiated);
+            }
+            state.src_caps = Some(src_caps);
+        }
+
         let mut outbuf = ometa.original().copy();
 
         inbuf
@@ -278,6 +401,10 @@ impl OriginalBufferRestore {
                 continue;
             }
 
+            if !settings.keeps_meta(&meta) {
+                continue;
+            }
+
             if meta.has_tag::<gst_video::video_meta::tags::Size>() {
                 if let (Some(ref meta_vinfo), Some(ref sink_vinfo)) =
                     (&state.meta_caps.vinfo, &state.sinkpad_caps.vinfo)
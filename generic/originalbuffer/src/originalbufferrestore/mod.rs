@@ -11,6 +11,14 @@
  * SECTION:element-originalbufferrestore
  *
  * See originalbuffersave for details
+ *
+ * By default, all metas of the processed buffers are kept and the caps of the
+ * original stream are restored. With `keep-all-metas` disabled, only the metas
+ * whose API is listed in `keep-metas` are kept, for example only the detection
+ * results of an analysis element. The caps fields listed in `keep-caps-fields`
+ * are taken from the processed stream instead of the original one.
+ *
+ * `... ! originalbufferrestore keep-all-metas=false keep-metas="<GstVideoRegionOfInterestMetaAPI>" ! ...`
  */
 use gst::glib;
 use gst::prelude::*;
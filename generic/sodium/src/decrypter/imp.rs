@@ -25,10 +25,14 @@
 use gst::glib;
 use gst::prelude::*;
 use gst::subclass::prelude::*;
-use sodiumoxide::crypto::box_;
+use sodiumoxide::crypto::pwhash::argon2id13;
+use sodiumoxide::crypto::{box_, secretbox};
 
+use std::collections::HashMap;
 use std::sync::Mutex;
 
+use crate::format::{self, Layout};
+
 use once_cell::sync::Lazy;
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
@@ -42,18 +46,64 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 struct Props {
     receiver_key: Option<glib::Bytes>,
     sender_key: Option<glib::Bytes>,
+    passphrase: Option<String>,
+}
+
+#[derive(Debug)]
+enum Credentials {
+    KeyPair {
+        sender_key: box_::PublicKey,
+        receiver_key: box_::SecretKey,
+    },
+    Passphrase(String),
+}
+
+#[derive(Debug)]
+enum Unwrapping {
+    // gst-sodium20: index of our wrapped key among the recipients
+    Recipient(usize),
+    // gst-sodium21: key derived from the passphrase
+    Passphrase(secretbox::Key),
+}
+
+#[derive(Debug)]
+enum Opener {
+    // gst-sodium10: the blocks are sealed with the sender/receiver key pair
+    Pair(box_::PrecomputedKey),
+    // gst-sodium20/21: the blocks are sealed with stream keys wrapped in the
+    // headers and key rotation markers
+    Stream {
+        unwrapping: Unwrapping,
+        keys: HashMap<u64, secretbox::Key>,
+    },
 }
 
 #[derive(Debug)]
 struct State {
     adapter: gst_base::UniqueAdapter,
     initial_nonce: Option<box_::Nonce>,
-    precomputed_key: box_::PrecomputedKey,
-    block_size: Option<u32>,
+    credentials: Credentials,
+    opener: Option<Opener>,
+    layout: Option<Layout>,
 }
 
 impl State {
     fn from_props(props: &Props) -> Result<Self, gst::ErrorMessage> {
+        let credentials = match props.passphrase {
+            Some(ref passphrase) => Credentials::Passphrase(passphrase.clone()),
+            None => Self::key_pair(props)?,
+        };
+
+        Ok(Self {
+            adapter: gst_base::UniqueAdapter::new(),
+            initial_nonce: None,
+            credentials,
+            opener: None,
+            layout: None,
+        })
+    }
+
+    fn key_pair(props: &Props) -> Result<Credentials, gst::ErrorMessage> {
         let sender_key = props
             .sender_key
             .as_ref()
@@ -82,16 +132,43 @@ impl State {
                 )
             })?;
 
-        let precomputed_key = box_::precompute(&sender_key, &receiver_key);
-
-        Ok(Self {
-            adapter: gst_base::UniqueAdapter::new(),
-            precomputed_key,
-            initial_nonce: None,
-            block_size: None,
+        Ok(Credentials::KeyPair {
+            sender_key,
+            receiver_key,
         })
     }
 
+    // Unwrap a stream key from the headers or a key rotation marker
+    fn unwrap_stream_key(&self, wrapped: &[u8]) -> Option<secretbox::Key> {
+        match (&self.opener, &self.credentials) {
+            (
+                Some(Opener::Stream {
+                    unwrapping: Unwrapping::Passphrase(key),
+                    ..
+                }),
+                _,
+            ) => format::unwrap_key_with(wrapped, key),
+            (
+                _,
+                Credentials::KeyPair {
+                    sender_key,
+                    receiver_key,
+                },
+            ) => format::unwrap_key(wrapped, sender_key, receiver_key),
+            _ => None,
+        }
+    }
+
+    fn open_block(&self, block: &[u8], nonce: &box_::Nonce, epoch: u64) -> Result<Vec<u8>, ()> {
+        match self.opener.as_ref().expect("Headers weren't parsed") {
+            Opener::Pair(precomputed_key) => box_::open_precomputed(block, nonce, precomputed_key),
+            Opener::Stream { keys, .. } => {
+                let key = keys.get(&epoch).ok_or(())?;
+                secretbox::open(block, &secretbox::Nonce(nonce.0), key)
+            }
+        }
+    }
+
     // Split the buffer into N(`chunk_index`) chunks of `block_size`,
     // decrypt them, and push them to the internal adapter for further
     // retrieval
@@ -115,18 +192,20 @@ impl State {
         gst::debug!(CAT, obj: pad, "Returned pull size: {}", map.len());
 
         let mut nonce = add_nonce(self.initial_nonce.unwrap(), chunk_index);
-        let block_size = self.block_size.expect("Block size wasn't set") as usize + box_::MACBYTES;
+        let layout = self.layout.expect("Headers weren't parsed");
+        let block_size = layout.block_size as usize + box_::MACBYTES;
+        // All the blocks of the buffer are sealed with the same key
+        let epoch = layout.epoch(chunk_index);
 
         for subbuffer in map.chunks(block_size) {
-            let plain =
-                box_::open_precomputed(subbuffer, &nonce, &self.precomputed_key).map_err(|_| {
-                    gst::element_imp_error!(
-                        imp,
-                        gst::StreamError::Format,
-                        ["Failed to decrypt buffer"]
-                    );
-                    gst::FlowError::Error
-                })?;
+            let plain = self.open_block(subbuffer, &nonce, epoch).map_err(|_| {
+                gst::element_imp_error!(
+                    imp,
+                    gst::StreamError::Format,
+                    ["Failed to decrypt buffer"]
+                );
+                gst::FlowError::Error
+            })?;
             // assumes little endian
             nonce.increment_le_inplace();
             self.adapter.push(gst::Buffer::from_mut_slice(plain));
@@ -325,14 +404,11 @@ impl Decrypter {
                     Some(s) => s,
                 };
 
-                // subtract static offsets
-                let size = size - crate::HEADERS_SIZE as u64;
-
-                // calculate the number of chunks that exist in the stream
-                let total_chunks =
-                    (size - 1) / state.block_size.expect("Block size wasn't set") as u64;
-                // subtrack the MAC of each block
-                let size = size - total_chunks * box_::MACBYTES as u64;
+                // subtract the headers, the MAC of each block and the key rotation markers
+                let size = state
+                    .layout
+                    .expect("Headers weren't parsed")
+                    .plain_size(size);
 
                 gst::debug!(CAT, obj: pad, "Setting duration bytes: {}", size);
                 q.set(size.bytes());
@@ -377,6 +453,14 @@ impl Decrypter {
         })?;
 
         let sodium_header_slice = &map[..crate::TYPEFIND_HEADER_SIZE];
+        if sodium_header_slice == format::TYPEFIND_HEADER_V2 {
+            drop(map);
+            return self.check_headers_v2(&buffer);
+        }
+        if sodium_header_slice == format::TYPEFIND_HEADER_PASSPHRASE {
+            drop(map);
+            return self.check_headers_passphrase();
+        }
         if sodium_header_slice != crate::TYPEFIND_HEADER {
             let err = gst::loggable_error!(CAT, "Buffer has wrong typefind header");
             return Err(err);
@@ -401,28 +485,284 @@ impl Decrypter {
         let mut state = self.state.lock().unwrap();
         let state = state.as_mut().unwrap();
 
+        let Credentials::KeyPair {
+            sender_key,
+            receiver_key,
+        } = &state.credentials
+        else {
+            let err = gst::loggable_error!(CAT, "Stream requires a key pair");
+            return Err(err);
+        };
+        let precomputed_key = box_::precompute(sender_key, receiver_key);
+
         state.initial_nonce = Some(nonce);
         gst::debug!(CAT, imp: self, "Setting nonce to: {:?}", nonce.0);
-        state.block_size = Some(block_size);
+        state.layout = Some(Layout::v1(block_size));
         gst::debug!(CAT, imp: self, "Setting block size to: {}", block_size);
+        state.opener = Some(Opener::Pair(precomputed_key));
+
+        Ok(())
+    }
+
+    // Parse the headers of a stream with per-recipient stream keys, given
+    // the start of the headers
+    fn check_headers_v2(&self, buffer: &gst::Buffer) -> Result<(), gst::LoggableError> {
+        let (block_size, rotation_interval, n_recipients) = {
+            let map = buffer.map_readable().map_err(|_| {
+                let err = gst::loggable_error!(CAT, "Failed to map buffer readable");
+                err
+            })?;
+            assert!(map.len() >= format::FIXED_HEADERS_SIZE_V2);
+
+            let slice = &map[crate::TYPEFIND_HEADER_SIZE..format::FIXED_HEADERS_SIZE_V2];
+            (
+                u32::from_le_bytes([slice[0], slice[1], slice[2], slice[3]]),
+                u32::from_le_bytes([slice[4], slice[5], slice[6], slice[7]]),
+                u16::from_le_bytes([slice[8], slice[9]]) as usize,
+            )
+        };
+
+        if block_size == 0 {
+            let err = gst::loggable_error!(CAT, "Invalid block size");
+            return Err(err);
+        }
+
+        let headers_size = format::headers_size_v2(n_recipients);
+        let buffer = self
+            .sinkpad
+            .pull_range(0, headers_size as u32)
+            .map_err(|err| {
+                let err = gst::loggable_error!(
+                    CAT,
+                    "Failed to pull headers from the stream, reason: {:?}",
+                    err
+                );
+                err
+            })?;
+
+        if buffer.size() != headers_size {
+            let err = gst::loggable_error!(CAT, "Headers buffer has wrong size");
+            return Err(err);
+        }
+
+        let map = buffer.map_readable().map_err(|_| {
+            let err = gst::loggable_error!(CAT, "Failed to map buffer readable");
+            err
+        })?;
+
+        let nonce =
+            box_::Nonce::from_slice(&map[headers_size - box_::NONCEBYTES..]).ok_or_else(|| {
+                let err = gst::loggable_error!(CAT, "Failed to create nonce from buffer");
+                err
+            })?;
+
+        let mut state = self.state.lock().unwrap();
+        let state = state.as_mut().unwrap();
+
+        let Credentials::KeyPair { receiver_key, .. } = &state.credentials else {
+            let err = gst::loggable_error!(CAT, "Stream requires a key pair");
+            return Err(err);
+        };
+
+        // Find the stream key that was wrapped for us
+        let public_key = receiver_key.public_key();
+        let (recipient, key) = map[format::FIXED_HEADERS_SIZE_V2..headers_size - box_::NONCEBYTES]
+            .chunks_exact(format::RECIPIENT_SIZE)
+            .enumerate()
+            .find(|(_, entry)| entry[..box_::PUBLICKEYBYTES] == public_key.0)
+            .map(|(recipient, entry)| (recipient, &entry[box_::PUBLICKEYBYTES..]))
+            .ok_or_else(|| {
+                gst::loggable_error!(CAT, "Receiver is not a recipient of the stream")
+            })?;
+
+        let key = state
+            .unwrap_stream_key(key)
+            .ok_or_else(|| gst::loggable_error!(CAT, "Failed to decrypt the stream key"))?;
+
+        state.initial_nonce = Some(nonce);
+        gst::debug!(CAT, imp: self, "Setting nonce to: {:?}", nonce.0);
+        state.layout = Some(Layout::v2(block_size, rotation_interval, n_recipients));
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Setting block size to: {}, key rotation interval: {}, recipient {} of {}",
+            block_size,
+            rotation_interval,
+            recipient,
+            n_recipients
+        );
+        state.opener = Some(Opener::Stream {
+            unwrapping: Unwrapping::Recipient(recipient),
+            keys: HashMap::from([(0, key)]),
+        });
+
+        Ok(())
+    }
+
+    // Parse the headers of a stream with a passphrase protected stream key
+    fn check_headers_passphrase(&self) -> Result<(), gst::LoggableError> {
+        let passphrase = {
+            let state = self.state.lock().unwrap();
+            match state.as_ref().unwrap().credentials {
+                Credentials::Passphrase(ref passphrase) => passphrase.clone(),
+                Credentials::KeyPair { .. } => {
+                    let err = gst::loggable_error!(CAT, "Stream requires a passphrase");
+                    return Err(err);
+                }
+            }
+        };
+
+        let buffer = self
+            .sinkpad
+            .pull_range(0, format::HEADERS_SIZE_PASSPHRASE as u32)
+            .map_err(|err| {
+                let err = gst::loggable_error!(
+                    CAT,
+                    "Failed to pull headers from the stream, reason: {:?}",
+                    err
+                );
+                err
+            })?;
+
+        if buffer.size() != format::HEADERS_SIZE_PASSPHRASE {
+            let err = gst::loggable_error!(CAT, "Headers buffer has wrong size");
+            return Err(err);
+        }
+
+        let map = buffer.map_readable().map_err(|_| {
+            let err = gst::loggable_error!(CAT, "Failed to map buffer readable");
+            err
+        })?;
+
+        let slice = &map[crate::TYPEFIND_HEADER_SIZE..format::FIXED_HEADERS_SIZE_PASSPHRASE];
+        let block_size = u32::from_le_bytes(slice[0..4].try_into().unwrap());
+        let rotation_interval = u32::from_le_bytes(slice[4..8].try_into().unwrap());
+        let opslimit = u64::from_le_bytes(slice[8..16].try_into().unwrap());
+        let memlimit = u64::from_le_bytes(slice[16..24].try_into().unwrap());
+        let salt = argon2id13::Salt::from_slice(&slice[24..]).unwrap();
+
+        if block_size == 0 {
+            let err = gst::loggable_error!(CAT, "Invalid block size");
+            return Err(err);
+        }
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Deriving key with ops limit {} and mem limit {}",
+            opslimit,
+            memlimit
+        );
+        let wrapping_key = format::derive_key(&passphrase, &salt, opslimit, memlimit)
+            .ok_or_else(|| gst::loggable_error!(CAT, "Failed to derive key from passphrase"))?;
+
+        let wrapped = &map[format::FIXED_HEADERS_SIZE_PASSPHRASE
+            ..format::HEADERS_SIZE_PASSPHRASE - box_::NONCEBYTES];
+        let key = format::unwrap_key_with(wrapped, &wrapping_key)
+            .ok_or_else(|| gst::loggable_error!(CAT, "Wrong passphrase"))?;
+
+        let nonce =
+            box_::Nonce::from_slice(&map[format::HEADERS_SIZE_PASSPHRASE - box_::NONCEBYTES..])
+                .ok_or_else(|| {
+                    let err = gst::loggable_error!(CAT, "Failed to create nonce from buffer");
+                    err
+                })?;
+
+        let mut state = self.state.lock().unwrap();
+        let state = state.as_mut().unwrap();
+
+        state.initial_nonce = Some(nonce);
+        gst::debug!(CAT, imp: self, "Setting nonce to: {:?}", nonce.0);
+        state.layout = Some(Layout::passphrase(block_size, rotation_interval));
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Setting block size to: {}, key rotation interval: {}",
+            block_size,
+            rotation_interval
+        );
+        state.opener = Some(Opener::Stream {
+            unwrapping: Unwrapping::Passphrase(wrapping_key),
+            keys: HashMap::from([(0, key)]),
+        });
+
+        Ok(())
+    }
+
+    // Make sure the stream key of the given epoch is known, reading it from
+    // the key rotation marker if needed
+    fn load_stream_key(&self, layout: &Layout, epoch: u64) -> Result<(), gst::FlowError> {
+        let index = {
+            let state = self.state.lock().unwrap();
+            match state.as_ref().unwrap().opener {
+                Some(Opener::Stream {
+                    ref unwrapping,
+                    ref keys,
+                }) if !keys.contains_key(&epoch) => match unwrapping {
+                    Unwrapping::Recipient(recipient) => *recipient,
+                    Unwrapping::Passphrase(_) => 0,
+                },
+                _ => return Ok(()),
+            }
+        };
+
+        let offset = layout.marker_offset(epoch);
+        gst::debug!(CAT, imp: self, "Reading key of epoch {} at {}", epoch, offset);
+
+        let buffer = self.sinkpad.pull_range(offset, layout.marker_size as u32)?;
+        if buffer.size() != layout.marker_size as usize {
+            return Err(gst::FlowError::Eos);
+        }
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Failed to map buffer readable"]
+            );
+            gst::FlowError::Error
+        })?;
+
+        let entry = format::MARKER_HEADER_SIZE + index * format::WRAPPED_KEY_SIZE;
+        if &map[..format::MARKER_HEADER.len()] != format::MARKER_HEADER
+            || map[format::MARKER_HEADER.len()..format::MARKER_HEADER_SIZE] != epoch.to_le_bytes()
+        {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Invalid key rotation marker for epoch {}", epoch]
+            );
+            return Err(gst::FlowError::Error);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let state = state.as_mut().unwrap();
+
+        let key = state
+            .unwrap_stream_key(&map[entry..entry + format::WRAPPED_KEY_SIZE])
+            .ok_or_else(|| {
+                gst::element_imp_error!(
+                    self,
+                    gst::StreamError::Decrypt,
+                    ["Failed to decrypt the stream key of epoch {}", epoch]
+                );
+                gst::FlowError::Error
+            })?;
+
+        if let Some(Opener::Stream { ref mut keys, .. }) = state.opener {
+            keys.insert(epoch, key);
+        }
 
         Ok(())
     }
 
-    fn pull_requested_buffer(
+    // Calculate how many chunks are needed for the requested size
+    fn requested_chunks(
         &self,
         pad: &gst::Pad,
         requested_size: u32,
         block_size: u32,
-        chunk_index: u64,
-    ) -> Result<gst::Buffer, gst::FlowError> {
-        let pull_offset = crate::HEADERS_SIZE as u64
-            + (chunk_index * block_size as u64)
-            + (chunk_index * box_::MACBYTES as u64);
-
-        gst::debug!(CAT, obj: pad, "Pull offset: {}", pull_offset);
-        gst::debug!(CAT, obj: pad, "block size: {}", block_size);
-
+    ) -> Result<u32, gst::FlowError> {
         // calculate how many chunks are needed, if we need something like 3.2
         // round the number to 4 and cut the buffer afterwards.
         let checked = requested_size.checked_add(block_size).ok_or_else(|| {
@@ -442,8 +782,8 @@ impl Decrypter {
         let total_chunks = u32::max((checked - 1) / block_size, 1);
         gst::debug!(CAT, obj: pad, "Blocks to be pulled: {}", total_chunks);
 
-        // Pull a buffer of all the chunks we will need
-        let checked_size = total_chunks.checked_mul(block_size).ok_or_else(|| {
+        // Make sure a buffer of all the chunks we will need can be pulled
+        total_chunks.checked_mul(block_size).ok_or_else(|| {
             gst::element_imp_error!(
                 self,
                 gst::LibraryError::Failed,
@@ -456,7 +796,23 @@ impl Decrypter {
             gst::FlowError::Error
         })?;
 
-        let total_size = checked_size + (total_chunks * box_::MACBYTES as u32);
+        Ok(total_chunks)
+    }
+
+    // Pull `total_chunks` consecutive chunks, which must all be sealed with the same key
+    fn pull_chunks(
+        &self,
+        pad: &gst::Pad,
+        layout: &Layout,
+        chunk_index: u64,
+        total_chunks: u32,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let pull_offset = layout.block_offset(chunk_index);
+
+        gst::debug!(CAT, obj: pad, "Pull offset: {}", pull_offset);
+        gst::debug!(CAT, obj: pad, "block size: {}", layout.block_size);
+
+        let total_size = total_chunks * (layout.block_size as u32 + box_::MACBYTES as u32);
         gst::debug!(CAT, obj: pad, "Requested pull size: {}", total_size);
 
         self.sinkpad.pull_range(pull_offset, total_size).map_err(|err| {
@@ -483,15 +839,16 @@ impl Decrypter {
         buffer: Option<&mut gst::BufferRef>,
         requested_size: u32,
     ) -> Result<gst::PadGetRangeSuccess, gst::FlowError> {
-        let block_size = {
+        let layout = {
             let mut mutex_state = self.state.lock().unwrap();
             // This will only be run after READY state,
             // and will be guaranteed to be initialized
             let state = mutex_state.as_mut().unwrap();
             // Cleanup the adapter
             state.adapter.clear();
-            state.block_size.expect("Block size wasn't set")
+            state.layout.expect("Headers weren't parsed")
         };
+        let block_size = layout.block_size as u32;
 
         gst::debug!(CAT, obj: pad, "Requested offset: {}", offset);
         gst::debug!(CAT, obj: pad, "Requested size: {}", requested_size);
@@ -503,16 +860,44 @@ impl Decrypter {
         assert!(pull_offset <= u32::MAX as u64);
         let pull_offset = pull_offset as u32;
 
-        let pulled_buffer =
-            self.pull_requested_buffer(pad, requested_size + pull_offset, block_size, chunk_index)?;
+        let total_chunks = self.requested_chunks(pad, requested_size + pull_offset, block_size)?;
+        let end_index = chunk_index + total_chunks as u64;
+
+        // Chunks sealed with different stream keys are separated by key
+        // rotation markers, so pull them separately
+        let mut index = chunk_index;
+        while index < end_index {
+            let run_end = layout.epoch_end(index).min(end_index);
+            let run_chunks = (run_end - index) as u32;
+
+            let res = self
+                .load_stream_key(&layout, layout.epoch(index))
+                .and_then(|_| self.pull_chunks(pad, &layout, index, run_chunks));
+            let pulled_buffer = match res {
+                Ok(buffer) => buffer,
+                // The stream ended in a previous run
+                Err(gst::FlowError::Eos) if index > chunk_index => break,
+                Err(err) => return Err(err),
+            };
+            let short_read =
+                pulled_buffer.size() < run_chunks as usize * (block_size as usize + box_::MACBYTES);
+
+            let mut state = self.state.lock().unwrap();
+            // This will only be run after READY state,
+            // and will be guaranteed to be initialized
+            let state = state.as_mut().unwrap();
+
+            state.decrypt_into_adapter(self, &self.srcpad, &pulled_buffer, index)?;
+
+            if short_read {
+                break;
+            }
+            index = run_end;
+        }
 
         let mut state = self.state.lock().unwrap();
-        // This will only be run after READY state,
-        // and will be guaranteed to be initialized
         let state = state.as_mut().unwrap();
 
-        state.decrypt_into_adapter(self, &self.srcpad, &pulled_buffer, chunk_index)?;
-
         let adapter_offset = pull_offset as usize;
         state.requested_buffer(&self.srcpad, buffer, requested_size, adapter_offset)
     }
@@ -583,6 +968,11 @@ impl ObjectImpl for Decrypter {
                     .blurb("The public key of the Sender")
                     .write_only()
                     .build(),
+                glib::ParamSpecString::builder("passphrase")
+                    .nick("Passphrase")
+                    .blurb("Passphrase the key was derived from, instead of using key pairs")
+                    .write_only()
+                    .build(),
             ]
         });
 
@@ -609,6 +999,11 @@ impl ObjectImpl for Decrypter {
                 props.receiver_key = value.get().expect("type checked upstream");
             }
 
+            "passphrase" => {
+                let mut props = self.props.lock().unwrap();
+                props.passphrase = value.get().expect("type checked upstream");
+            }
+
             _ => unimplemented!(),
         }
     }
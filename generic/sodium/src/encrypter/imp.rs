@@ -26,7 +26,10 @@ use gst::glib;
 use gst::prelude::*;
 use gst::subclass::prelude::*;
 use smallvec::SmallVec;
-use sodiumoxide::crypto::box_;
+use sodiumoxide::crypto::pwhash::argon2id13;
+use sodiumoxide::crypto::{box_, secretbox};
+
+use crate::format::{self, Layout};
 
 type BufferVec = SmallVec<[gst::Buffer; 16]>;
 
@@ -41,34 +44,126 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     )
 });
 
+// Key derivation parameters for passphrases, stored in the headers
+const OPSLIMIT: u64 = argon2id13::OPSLIMIT_INTERACTIVE.0 as u64;
+const MEMLIMIT: u64 = argon2id13::MEMLIMIT_INTERACTIVE.0 as u64;
+
 #[derive(Debug, Clone)]
 struct Props {
     receiver_key: Option<glib::Bytes>,
+    receiver_keys: Vec<glib::Bytes>,
     sender_key: Option<glib::Bytes>,
+    passphrase: Option<String>,
     block_size: u32,
+    key_rotation_interval: u32,
 }
 
 impl Default for Props {
     fn default() -> Self {
         Props {
             receiver_key: None,
+            receiver_keys: Vec::new(),
             sender_key: None,
+            passphrase: None,
             block_size: 32768,
+            key_rotation_interval: 0,
         }
     }
 }
 
+#[derive(Debug)]
+enum KeyWrapping {
+    // gst-sodium20: the stream key is wrapped for every recipient
+    Recipients {
+        sender_key: box_::SecretKey,
+        recipients: Vec<box_::PublicKey>,
+    },
+    // gst-sodium21: the stream key is wrapped with a key derived from a passphrase
+    Passphrase {
+        key: secretbox::Key,
+        salt: argon2id13::Salt,
+    },
+}
+
+impl KeyWrapping {
+    // Appends the wrapped `key` for everyone who can decrypt the stream
+    fn wrap(&self, out: &mut Vec<u8>, key: &secretbox::Key) {
+        match self {
+            KeyWrapping::Recipients {
+                sender_key,
+                recipients,
+            } => {
+                for recipient in recipients {
+                    format::wrap_key(out, key, recipient, sender_key);
+                }
+            }
+            KeyWrapping::Passphrase {
+                key: wrapping_key, ..
+            } => format::wrap_key_with(out, key, wrapping_key),
+        }
+    }
+
+    fn n_wrapped_keys(&self) -> usize {
+        match self {
+            KeyWrapping::Recipients { recipients, .. } => recipients.len(),
+            KeyWrapping::Passphrase { .. } => 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Sealer {
+    // gst-sodium10: the blocks are sealed with the sender/receiver key pair
+    Pair(box_::PrecomputedKey),
+    // gst-sodium20/21: the blocks are sealed with a stream key that is
+    // wrapped in the headers, and optionally rotated
+    Stream {
+        wrapping: KeyWrapping,
+        key: secretbox::Key,
+        rotation_interval: u32,
+    },
+}
+
 #[derive(Debug)]
 struct State {
     adapter: gst_base::UniqueAdapter,
     nonce: box_::Nonce,
-    precomputed_key: box_::PrecomputedKey,
+    sealer: Sealer,
     block_size: u32,
+    // Index of the next block to be sealed
+    block_index: u64,
     write_headers: bool,
 }
 
 impl State {
     fn from_props(props: &Props) -> Result<Self, gst::ErrorMessage> {
+        // This env variable is only meant to bypass nonce regeneration during
+        // tests to get deterministic results. It should never be used outside
+        // of testing environments.
+        let nonce = if let Ok(val) = std::env::var("GST_SODIUM_ENCRYPT_NONCE") {
+            let bytes = hex::decode(val).expect("Failed to decode hex variable");
+            assert_eq!(bytes.len(), box_::NONCEBYTES);
+            box_::Nonce::from_slice(&bytes).unwrap()
+        } else {
+            box_::gen_nonce()
+        };
+
+        let sealer = match props.passphrase {
+            Some(ref passphrase) => Self::passphrase_sealer(props, passphrase)?,
+            None => Self::key_pair_sealer(props)?,
+        };
+
+        Ok(Self {
+            adapter: gst_base::UniqueAdapter::new(),
+            nonce,
+            sealer,
+            block_size: props.block_size,
+            block_index: 0,
+            write_headers: true,
+        })
+    }
+
+    fn key_pair_sealer(props: &Props) -> Result<Sealer, gst::ErrorMessage> {
         let sender_key = props
             .sender_key
             .as_ref()
@@ -83,45 +178,187 @@ impl State {
                 )
             })?;
 
-        let receiver_key = props
+        let receiver_keys = props
             .receiver_key
-            .as_ref()
-            .and_then(|k| box_::PublicKey::from_slice(k))
-            .ok_or_else(|| {
-                gst::error_msg!(
-                    gst::ResourceError::NotFound,
-                    [
-                        "Failed to set Receiver's Key from property: {:?}",
-                        props.receiver_key
-                    ]
-                )
-            })?;
+            .iter()
+            .chain(props.receiver_keys.iter())
+            .map(|k| {
+                box_::PublicKey::from_slice(k).ok_or_else(|| {
+                    gst::error_msg!(
+                        gst::ResourceError::NotFound,
+                        ["Failed to set Receiver's Key from property: {:?}", k]
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // This env variable is only meant to bypass nonce regeneration during
-        // tests to get deterministic results. It should never be used outside
-        // of testing environments.
-        let nonce = if let Ok(val) = std::env::var("GST_SODIUM_ENCRYPT_NONCE") {
-            let bytes = hex::decode(val).expect("Failed to decode hex variable");
-            assert_eq!(bytes.len(), box_::NONCEBYTES);
-            box_::Nonce::from_slice(&bytes).unwrap()
-        } else {
-            box_::gen_nonce()
-        };
+        if receiver_keys.is_empty() {
+            return Err(gst::error_msg!(
+                gst::ResourceError::NotFound,
+                ["No Receiver's Key provided"]
+            ));
+        }
 
-        let precomputed_key = box_::precompute(&receiver_key, &sender_key);
+        if receiver_keys.len() > u16::MAX as usize {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["Too many Receiver's Keys: {}", receiver_keys.len()]
+            ));
+        }
 
-        Ok(Self {
-            adapter: gst_base::UniqueAdapter::new(),
-            precomputed_key,
-            nonce,
-            block_size: props.block_size,
-            write_headers: true,
+        // Keep writing the original format when it is sufficient so that
+        // older decrypters can still read the stream
+        if receiver_keys.len() == 1 && props.key_rotation_interval == 0 {
+            return Ok(Sealer::Pair(box_::precompute(
+                &receiver_keys[0],
+                &sender_key,
+            )));
+        }
+
+        Ok(Sealer::Stream {
+            wrapping: KeyWrapping::Recipients {
+                sender_key,
+                recipients: receiver_keys,
+            },
+            key: secretbox::gen_key(),
+            rotation_interval: props.key_rotation_interval,
         })
     }
 
+    fn passphrase_sealer(props: &Props, passphrase: &str) -> Result<Sealer, gst::ErrorMessage> {
+        if props.receiver_key.is_some() || !props.receiver_keys.is_empty() {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["Receiver's Keys can't be used together with a passphrase"]
+            ));
+        }
+
+        let salt = argon2id13::gen_salt();
+        let key = format::derive_key(passphrase, &salt, OPSLIMIT, MEMLIMIT).ok_or_else(|| {
+            gst::error_msg!(
+                gst::LibraryError::Failed,
+                ["Failed to derive key from passphrase"]
+            )
+        })?;
+
+        Ok(Sealer::Stream {
+            wrapping: KeyWrapping::Passphrase { key, salt },
+            key: secretbox::gen_key(),
+            rotation_interval: props.key_rotation_interval,
+        })
+    }
+
+    fn layout(&self) -> Layout {
+        match &self.sealer {
+            Sealer::Pair(_) => Layout::v1(self.block_size),
+            Sealer::Stream {
+                wrapping: KeyWrapping::Recipients { recipients, .. },
+                rotation_interval,
+                ..
+            } => Layout::v2(self.block_size, *rotation_interval, recipients.len()),
+            Sealer::Stream {
+                wrapping: KeyWrapping::Passphrase { .. },
+                rotation_interval,
+                ..
+            } => Layout::passphrase(self.block_size, *rotation_interval),
+        }
+    }
+
+    fn headers(&self) -> Vec<u8> {
+        match &self.sealer {
+            Sealer::Pair(_) => {
+                let mut headers = Vec::with_capacity(crate::HEADERS_SIZE);
+                headers.extend_from_slice(crate::TYPEFIND_HEADER);
+                // Write the Nonce used into the stream.
+                headers.extend_from_slice(self.nonce.as_ref());
+                // Write the block_size into the stream
+                headers.extend_from_slice(&self.block_size.to_le_bytes());
+                headers
+            }
+            Sealer::Stream {
+                wrapping:
+                    KeyWrapping::Recipients {
+                        sender_key,
+                        recipients,
+                    },
+                key,
+                rotation_interval,
+            } => {
+                let mut headers = Vec::with_capacity(format::headers_size_v2(recipients.len()));
+                headers.extend_from_slice(format::TYPEFIND_HEADER_V2);
+                headers.extend_from_slice(&self.block_size.to_le_bytes());
+                headers.extend_from_slice(&rotation_interval.to_le_bytes());
+                headers.extend_from_slice(&(recipients.len() as u16).to_le_bytes());
+                for recipient in recipients {
+                    headers.extend_from_slice(&recipient.0);
+                    format::wrap_key(&mut headers, key, recipient, sender_key);
+                }
+                headers.extend_from_slice(self.nonce.as_ref());
+                headers
+            }
+            Sealer::Stream {
+                wrapping: wrapping @ KeyWrapping::Passphrase { salt, .. },
+                key,
+                rotation_interval,
+            } => {
+                let mut headers = Vec::with_capacity(format::HEADERS_SIZE_PASSPHRASE);
+                headers.extend_from_slice(format::TYPEFIND_HEADER_PASSPHRASE);
+                headers.extend_from_slice(&self.block_size.to_le_bytes());
+                headers.extend_from_slice(&rotation_interval.to_le_bytes());
+                headers.extend_from_slice(&OPSLIMIT.to_le_bytes());
+                headers.extend_from_slice(&MEMLIMIT.to_le_bytes());
+                headers.extend_from_slice(&salt.0);
+                wrapping.wrap(&mut headers, key);
+                headers.extend_from_slice(self.nonce.as_ref());
+                headers
+            }
+        }
+    }
+
+    // Replaces the stream key if the next block starts a new epoch, and
+    // returns the marker announcing the new key
+    fn rotate_key(&mut self) -> Option<gst::Buffer> {
+        let Sealer::Stream {
+            wrapping,
+            key,
+            rotation_interval,
+        } = &mut self.sealer
+        else {
+            return None;
+        };
+
+        let rotation_interval = *rotation_interval as u64;
+        if rotation_interval == 0
+            || self.block_index == 0
+            || self.block_index % rotation_interval != 0
+        {
+            return None;
+        }
+
+        *key = secretbox::gen_key();
+
+        let epoch = self.block_index / rotation_interval;
+        let mut marker = Vec::with_capacity(
+            format::MARKER_HEADER_SIZE + wrapping.n_wrapped_keys() * format::WRAPPED_KEY_SIZE,
+        );
+        marker.extend_from_slice(format::MARKER_HEADER);
+        marker.extend_from_slice(&epoch.to_le_bytes());
+        wrapping.wrap(&mut marker, key);
+
+        Some(gst::Buffer::from_mut_slice(marker))
+    }
+
     fn seal(&mut self, message: &[u8]) -> Vec<u8> {
-        let ciphertext = box_::seal_precomputed(message, &self.nonce, &self.precomputed_key);
+        let ciphertext = match &self.sealer {
+            Sealer::Pair(precomputed_key) => {
+                box_::seal_precomputed(message, &self.nonce, precomputed_key)
+            }
+            Sealer::Stream { key, .. } => {
+                secretbox::seal(message, &secretbox::Nonce(self.nonce.0), key)
+            }
+        };
         self.nonce.increment_le_inplace();
+        self.block_index += 1;
         ciphertext
     }
 
@@ -144,6 +381,7 @@ impl State {
         // is sent.
         while self.adapter.available() >= block_size {
             let buffer = self.adapter.take_buffer(block_size).unwrap();
+            buffers.extend(self.rotate_key());
             let out_buf = self.encrypt_message(&buffer);
 
             buffers.push(out_buf);
@@ -173,14 +411,7 @@ impl Encrypter {
         let state = state_guard.as_mut().unwrap();
 
         if state.write_headers {
-            let mut headers = Vec::with_capacity(40);
-            headers.extend_from_slice(crate::TYPEFIND_HEADER);
-            // Write the Nonce used into the stream.
-            headers.extend_from_slice(state.nonce.as_ref());
-            // Write the block_size into the stream
-            headers.extend_from_slice(&state.block_size.to_le_bytes());
-
-            buffers.push(gst::Buffer::from_mut_slice(headers));
+            buffers.push(gst::Buffer::from_mut_slice(state.headers()));
             state.write_headers = false;
         }
 
@@ -303,13 +534,8 @@ impl Encrypter {
                     Some(s) => s,
                 };
 
-                // calculate the number of chunks that exist in the stream
-                let total_chunks = (size + state.block_size as u64 - 1) / state.block_size as u64;
-                // add the MAC of each block
-                let size = size + total_chunks * box_::MACBYTES as u64;
-
-                // add static offsets
-                let size = size + crate::HEADERS_SIZE as u64;
+                // add the headers, the MAC of each block and the key rotation markers
+                let size = state.layout().encrypted_size(size);
 
                 gst::debug!(CAT, obj: pad, "Setting duration bytes: {}", size);
                 q.set(size.bytes());
@@ -384,17 +610,34 @@ impl ObjectImpl for Encrypter {
                     .nick("Receiver Key")
                     .blurb("The public key of the Receiver")
                     .build(),
+                gst::ParamSpecArray::builder("receiver-keys")
+                    .nick("Receiver Keys")
+                    .blurb("The public keys of additional Receivers")
+                    .element_spec(
+                        &glib::ParamSpecBoxed::builder::<glib::Bytes>("receiver-key").build(),
+                    )
+                    .build(),
                 glib::ParamSpecBoxed::builder::<glib::Bytes>("sender-key")
                     .nick("Sender Key")
                     .blurb("The private key of the Sender")
                     .write_only()
                     .build(),
+                glib::ParamSpecString::builder("passphrase")
+                    .nick("Passphrase")
+                    .blurb("Passphrase to derive the key from, instead of using key pairs")
+                    .write_only()
+                    .build(),
                 glib::ParamSpecUInt::builder("block-size")
                     .nick("Block Size")
                     .blurb("The block-size of the chunks")
                     .minimum(1024)
                     .default_value(32768)
                     .build(),
+                glib::ParamSpecUInt::builder("key-rotation-interval")
+                    .nick("Key Rotation Interval")
+                    .blurb("Number of blocks after which the stream key is replaced (0 = never)")
+                    .default_value(0)
+                    .build(),
             ]
         });
 
@@ -421,11 +664,32 @@ impl ObjectImpl for Encrypter {
                 props.receiver_key = value.get().expect("type checked upstream");
             }
 
+            "passphrase" => {
+                let mut props = self.props.lock().unwrap();
+                props.passphrase = value.get().expect("type checked upstream");
+            }
+
+            "receiver-keys" => {
+                let mut props = self.props.lock().unwrap();
+                props.receiver_keys = value
+                    .get::<gst::ArrayRef>()
+                    .expect("type checked upstream")
+                    .as_slice()
+                    .iter()
+                    .map(|key| key.get::<glib::Bytes>().expect("type checked upstream"))
+                    .collect();
+            }
+
             "block-size" => {
                 let mut props = self.props.lock().unwrap();
                 props.block_size = value.get().expect("type checked upstream");
             }
 
+            "key-rotation-interval" => {
+                let mut props = self.props.lock().unwrap();
+                props.key_rotation_interval = value.get().expect("type checked upstream");
+            }
+
             _ => unimplemented!(),
         }
     }
@@ -437,11 +701,21 @@ impl ObjectImpl for Encrypter {
                 props.receiver_key.to_value()
             }
 
+            "receiver-keys" => {
+                let props = self.props.lock().unwrap();
+                gst::Array::new(props.receiver_keys.iter().cloned()).to_value()
+            }
+
             "block-size" => {
                 let props = self.props.lock().unwrap();
                 props.block_size.to_value()
             }
 
+            "key-rotation-interval" => {
+                let props = self.props.lock().unwrap();
+                props.key_rotation_interval.to_value()
+            }
+
             _ => unimplemented!(),
         }
     }
@@ -0,0 +1,299 @@
+// format.rs
+//
+// SPDX-License-Identifier: MIT
+
+//! Layout of the `gst-sodium20` stream format.
+//!
+//! Instead of sealing the blocks with the sender/receiver key pair directly,
+//! the blocks are sealed with a random stream key. The stream key is wrapped
+//! for every recipient in the headers, so that any of them can decrypt the
+//! stream. The stream key can be rotated every N blocks, in which case a
+//! marker with the new key wrapped for every recipient is placed in front of
+//! the first block using it.
+//!
+//! ```text
+//! headers: "gst-sodium20" | block size (u32 LE) | rotation interval (u32 LE)
+//!          | number of recipients (u16 LE)
+//!          | recipients: public key | nonce | wrapped stream key
+//!          | data nonce
+//! marker:  "gst-skey" | epoch (u64 LE) | per recipient: nonce | wrapped stream key
+//! ```
+//!
+//! Alternatively, the stream key is wrapped with a key derived from a
+//! passphrase with Argon2id, in which case the headers contain the derivation
+//! parameters instead of the recipients, and the markers a single wrapped key:
+//!
+//! ```text
+//! headers: "gst-sodium21" | block size (u32 LE) | rotation interval (u32 LE)
+//!          | Argon2id ops limit (u64 LE) | Argon2id mem limit (u64 LE) | salt
+//!          | nonce | wrapped stream key
+//!          | data nonce
+//! ```
+
+use sodiumoxide::crypto::pwhash::argon2id13;
+use sodiumoxide::crypto::{box_, secretbox};
+
+pub(crate) const TYPEFIND_HEADER_V2: &[u8; 12] = b"gst-sodium20";
+
+/// Typefind header, block size, rotation interval and number of recipients.
+pub(crate) const FIXED_HEADERS_SIZE_V2: usize =
+    crate::TYPEFIND_HEADER_SIZE + 2 * std::mem::size_of::<u32>() + std::mem::size_of::<u16>();
+
+/// A stream key sealed for one recipient, preceded by the nonce used.
+pub(crate) const WRAPPED_KEY_SIZE: usize = box_::NONCEBYTES + secretbox::KEYBYTES + box_::MACBYTES;
+
+pub(crate) const RECIPIENT_SIZE: usize = box_::PUBLICKEYBYTES + WRAPPED_KEY_SIZE;
+
+pub(crate) const TYPEFIND_HEADER_PASSPHRASE: &[u8; 12] = b"gst-sodium21";
+
+/// Typefind header, block size, rotation interval and key derivation parameters.
+pub(crate) const FIXED_HEADERS_SIZE_PASSPHRASE: usize = crate::TYPEFIND_HEADER_SIZE
+    + 2 * std::mem::size_of::<u32>()
+    + 2 * std::mem::size_of::<u64>()
+    + argon2id13::SALTBYTES;
+
+pub(crate) const HEADERS_SIZE_PASSPHRASE: usize =
+    FIXED_HEADERS_SIZE_PASSPHRASE + WRAPPED_KEY_SIZE + box_::NONCEBYTES;
+
+// Upper bounds of the key derivation parameters accepted from a stream, so
+// that a crafted stream can't make us use an unreasonable amount of resources
+pub(crate) const MAX_OPSLIMIT: u64 = argon2id13::OPSLIMIT_SENSITIVE.0 as u64;
+pub(crate) const MAX_MEMLIMIT: u64 = argon2id13::MEMLIMIT_SENSITIVE.0 as u64;
+
+pub(crate) const MARKER_HEADER: &[u8; 8] = b"gst-skey";
+pub(crate) const MARKER_HEADER_SIZE: usize = 8 + std::mem::size_of::<u64>();
+
+pub(crate) fn headers_size_v2(n_recipients: usize) -> usize {
+    FIXED_HEADERS_SIZE_V2 + n_recipients * RECIPIENT_SIZE + box_::NONCEBYTES
+}
+
+/// Byte layout of an encrypted stream, mapping blocks to their position.
+///
+/// All blocks but the last one have the same size and markers only appear at
+/// fixed block intervals, so the position of any plaintext offset in the
+/// encrypted stream is computed directly. Seeking only requires decrypting
+/// the blocks containing the requested range, and no index of the blocks is
+/// stored in the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub headers_size: u64,
+    pub block_size: u64,
+    /// Number of blocks per stream key, 0 if the key is never rotated.
+    pub rotation_interval: u64,
+    pub marker_size: u64,
+}
+
+impl Layout {
+    pub fn v1(block_size: u32) -> Self {
+        Layout {
+            headers_size: crate::HEADERS_SIZE as u64,
+            block_size: block_size as u64,
+            rotation_interval: 0,
+            marker_size: 0,
+        }
+    }
+
+    pub fn v2(block_size: u32, rotation_interval: u32, n_recipients: usize) -> Self {
+        Self::with_stream_key(
+            headers_size_v2(n_recipients),
+            block_size,
+            rotation_interval,
+            n_recipients,
+        )
+    }
+
+    pub fn passphrase(block_size: u32, rotation_interval: u32) -> Self {
+        Self::with_stream_key(HEADERS_SIZE_PASSPHRASE, block_size, rotation_interval, 1)
+    }
+
+    fn with_stream_key(
+        headers_size: usize,
+        block_size: u32,
+        rotation_interval: u32,
+        n_wrapped_keys: usize,
+    ) -> Self {
+        Layout {
+            headers_size: headers_size as u64,
+            block_size: block_size as u64,
+            rotation_interval: rotation_interval as u64,
+            marker_size: if rotation_interval == 0 {
+                0
+            } else {
+                (MARKER_HEADER_SIZE + n_wrapped_keys * WRAPPED_KEY_SIZE) as u64
+            },
+        }
+    }
+
+    fn sealed_block_size(&self) -> u64 {
+        self.block_size + box_::MACBYTES as u64
+    }
+
+    /// Index of the stream key used for sealing the block.
+    pub fn epoch(&self, block: u64) -> u64 {
+        if self.rotation_interval == 0 {
+            0
+        } else {
+            block / self.rotation_interval
+        }
+    }
+
+    /// First block that is sealed with a different key than `block`.
+    pub fn epoch_end(&self, block: u64) -> u64 {
+        if self.rotation_interval == 0 {
+            u64::MAX
+        } else {
+            (self.epoch(block) + 1).saturating_mul(self.rotation_interval)
+        }
+    }
+
+    pub fn block_offset(&self, block: u64) -> u64 {
+        self.headers_size + block * self.sealed_block_size() + self.epoch(block) * self.marker_size
+    }
+
+    /// Offset of the marker introducing the key of `epoch`, which must be at least 1.
+    pub fn marker_offset(&self, epoch: u64) -> u64 {
+        assert!(epoch > 0 && self.rotation_interval > 0);
+        self.block_offset(epoch * self.rotation_interval) - self.marker_size
+    }
+
+    pub fn encrypted_size(&self, plain_size: u64) -> u64 {
+        let blocks = (plain_size + self.block_size - 1) / self.block_size;
+        let markers = blocks.checked_sub(1).map_or(0, |last| self.epoch(last));
+
+        self.headers_size + plain_size + blocks * box_::MACBYTES as u64 + markers * self.marker_size
+    }
+
+    pub fn plain_size(&self, encrypted_size: u64) -> u64 {
+        let size = encrypted_size.saturating_sub(self.headers_size);
+        let sealed_block_size = self.sealed_block_size();
+
+        // Consider every epoch as a marker followed by its blocks, including
+        // the first one that has no marker
+        let (epochs, rem) = if self.rotation_interval == 0 {
+            (0, size)
+        } else {
+            let epoch_size = self.marker_size + self.rotation_interval * sealed_block_size;
+            let size = size + self.marker_size;
+            (
+                size / epoch_size,
+                (size % epoch_size).saturating_sub(self.marker_size),
+            )
+        };
+
+        let blocks = (rem + sealed_block_size - 1) / sealed_block_size;
+        epochs * self.rotation_interval * self.block_size
+            + rem.saturating_sub(blocks * box_::MACBYTES as u64)
+    }
+}
+
+/// Seals `key` for `recipient` and appends it to `out`.
+pub(crate) fn wrap_key(
+    out: &mut Vec<u8>,
+    key: &secretbox::Key,
+    recipient: &box_::PublicKey,
+    sender: &box_::SecretKey,
+) {
+    let nonce = box_::gen_nonce();
+    out.extend_from_slice(&nonce.0);
+    out.extend_from_slice(&box_::seal(&key.0, &nonce, recipient, sender));
+}
+
+pub(crate) fn unwrap_key(
+    wrapped: &[u8],
+    sender: &box_::PublicKey,
+    receiver: &box_::SecretKey,
+) -> Option<secretbox::Key> {
+    if wrapped.len() != WRAPPED_KEY_SIZE {
+        return None;
+    }
+
+    let nonce = box_::Nonce::from_slice(&wrapped[..box_::NONCEBYTES])?;
+    let key = box_::open(&wrapped[box_::NONCEBYTES..], &nonce, sender, receiver).ok()?;
+    secretbox::Key::from_slice(&key)
+}
+
+/// Derives the key wrapping the stream keys from a passphrase.
+pub(crate) fn derive_key(
+    passphrase: &str,
+    salt: &argon2id13::Salt,
+    opslimit: u64,
+    memlimit: u64,
+) -> Option<secretbox::Key> {
+    if opslimit > MAX_OPSLIMIT || memlimit > MAX_MEMLIMIT {
+        return None;
+    }
+
+    let mut key = [0; secretbox::KEYBYTES];
+    argon2id13::derive_key(
+        &mut key,
+        passphrase.as_bytes(),
+        salt,
+        argon2id13::OpsLimit(opslimit as usize),
+        argon2id13::MemLimit(memlimit as usize),
+    )
+    .ok()?;
+
+    secretbox::Key::from_slice(&key)
+}
+
+/// Seals `key` with the passphrase derived `wrapping_key` and appends it to `out`.
+pub(crate) fn wrap_key_with(
+    out: &mut Vec<u8>,
+    key: &secretbox::Key,
+    wrapping_key: &secretbox::Key,
+) {
+    let nonce = secretbox::gen_nonce();
+    out.extend_from_slice(&nonce.0);
+    out.extend_from_slice(&secretbox::seal(&key.0, &nonce, wrapping_key));
+}
+
+pub(crate) fn unwrap_key_with(
+    wrapped: &[u8],
+    wrapping_key: &secretbox::Key,
+) -> Option<secretbox::Key> {
+    if wrapped.len() != WRAPPED_KEY_SIZE {
+        return None;
+    }
+
+    let nonce = secretbox::Nonce::from_slice(&wrapped[..secretbox::NONCEBYTES])?;
+    let key = secretbox::open(&wrapped[secretbox::NONCEBYTES..], &nonce, wrapping_key).ok()?;
+    secretbox::Key::from_slice(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_sizes() {
+        let layouts = [
+            Layout::v1(1024),
+            Layout::v2(1024, 0, 2),
+            Layout::v2(1024, 3, 2),
+            Layout::passphrase(1024, 3),
+        ];
+
+        for layout in layouts {
+            for plain_size in [0, 1, 1023, 1024, 1025, 3072, 3073, 10_000] {
+                let encrypted_size = layout.encrypted_size(plain_size);
+                assert_eq!(layout.plain_size(encrypted_size), plain_size, "{layout:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout_offsets() {
+        let layout = Layout::v2(1024, 2, 1);
+        let sealed = 1024 + box_::MACBYTES as u64;
+        let marker = (MARKER_HEADER_SIZE + WRAPPED_KEY_SIZE) as u64;
+
+        assert_eq!(layout.block_offset(0), layout.headers_size);
+        assert_eq!(layout.block_offset(1), layout.headers_size + sealed);
+        assert_eq!(layout.marker_offset(1), layout.headers_size + 2 * sealed);
+        assert_eq!(
+            layout.block_offset(2),
+            layout.headers_size + 2 * sealed + marker
+        );
+        assert_eq!(layout.epoch_end(2), 4);
+    }
+}
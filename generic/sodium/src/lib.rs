@@ -39,6 +39,7 @@ const HEADERS_SIZE: usize =
 
 mod decrypter;
 mod encrypter;
+mod format;
 
 fn typefind_register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     use gst::{Caps, TypeFind, TypeFindProbability};
@@ -51,7 +52,10 @@ fn typefind_register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
         Some(&Caps::builder("application/x-sodium-encrypted").build()),
         |typefind| {
             if let Some(data) = typefind.peek(0, TYPEFIND_HEADER_SIZE as u32) {
-                if data == TYPEFIND_HEADER {
+                if data == TYPEFIND_HEADER
+                    || data == format::TYPEFIND_HEADER_V2
+                    || data == format::TYPEFIND_HEADER_PASSPHRASE
+                {
                     typefind.suggest(
                         TypeFindProbability::Maximum,
                         &Caps::builder("application/x-sodium-encrypted").build(),
@@ -24,7 +24,9 @@
 
 use gst::glib;
 use gst::prelude::*;
+use sodiumoxide::crypto::box_;
 
+use std::io::Write;
 use std::sync::{Arc, Mutex};
 
 use std::path::PathBuf;
@@ -316,3 +318,183 @@ fn test_state_changes() {
         assert!(dec.change_state(gst::StateChange::NullToReady).is_ok());
     }
 }
+
+fn encrypt(enc: &gst::Element, input: &[u8]) -> Vec<u8> {
+    let mut h = gst_check::Harness::with_element(enc, Some("sink"), Some("src"));
+    h.set_src_caps_str("application/octet-stream");
+
+    let buf = gst::Buffer::from_mut_slice(Vec::from(input));
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+    h.push_event(gst::event::Eos::new());
+
+    let mut output = vec![];
+    while let Some(buf) = h.pull_until_eos().unwrap() {
+        output.extend_from_slice(&buf.map_readable().unwrap());
+    }
+
+    output
+}
+
+// Sets up filesrc ! sodiumdecrypter in READY, returning the decrypter's src pad
+fn decrypter_pipeline(location: &std::path::Path, dec: &gst::Element) -> (gst::Pipeline, gst::Pad) {
+    let pipeline = gst::Pipeline::builder().build();
+    let filesrc = gst::ElementFactory::make("filesrc")
+        .property("location", location.to_str().unwrap())
+        .build()
+        .unwrap();
+
+    pipeline.add_many([&filesrc, dec]).unwrap();
+    filesrc.link(dec).unwrap();
+    pipeline.set_state(gst::State::Ready).unwrap();
+
+    (pipeline, dec.static_pad("src").unwrap())
+}
+
+fn encrypt_for(input: &[u8], sender: &box_::SecretKey, receivers: &[box_::PublicKey]) -> Vec<u8> {
+    let receiver_keys = gst::Array::new(
+        receivers
+            .iter()
+            .map(|key| glib::Bytes::from_owned(key.0))
+            .collect::<Vec<_>>(),
+    );
+    let enc = gst::ElementFactory::make("sodiumencrypter")
+        .property("sender-key", glib::Bytes::from_owned(sender.0))
+        .property("receiver-keys", receiver_keys)
+        .property("block-size", 1024u32)
+        .property("key-rotation-interval", 2u32)
+        .build()
+        .unwrap();
+
+    encrypt(&enc, input)
+}
+
+#[test]
+fn test_multiple_recipients() {
+    use rand::RngCore;
+
+    init();
+
+    let (sender_public, sender_secret) = box_::gen_keypair();
+    let recipients = [
+        box_::gen_keypair(),
+        box_::gen_keypair(),
+        box_::gen_keypair(),
+    ];
+
+    // 5 blocks, so that the key is rotated twice
+    let mut input = vec![0u8; 4 * 1024 + 100];
+    rand::thread_rng().fill_bytes(&mut input);
+
+    let public_keys = recipients
+        .iter()
+        .map(|(public, _)| public.clone())
+        .collect::<Vec<_>>();
+    let encrypted = encrypt_for(&input, &sender_secret, &public_keys);
+    assert_eq!(&encrypted[..12], b"gst-sodium20");
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&encrypted).unwrap();
+    file.flush().unwrap();
+
+    let decrypt = |receiver_secret: &box_::SecretKey| {
+        let dec = gst::ElementFactory::make("sodiumdecrypter")
+            .property("sender-key", glib::Bytes::from_owned(sender_public.0))
+            .property("receiver-key", glib::Bytes::from_owned(receiver_secret.0))
+            .build()
+            .unwrap();
+
+        decrypter_pipeline(file.path(), &dec)
+    };
+
+    for (_, receiver_secret) in &recipients[1..] {
+        let (pipeline, srcpad) = decrypt(receiver_secret);
+        srcpad.activate_mode(gst::PadMode::Pull, true).unwrap();
+
+        let mut q = gst::query::Duration::new(gst::Format::Bytes);
+        assert!(srcpad.query(&mut q));
+        assert_eq!(q.result(), (input.len() as u64).bytes().into());
+
+        // Everything, across all key rotations
+        let buf = srcpad.range(0, 42000).unwrap();
+        assert_eq!(&buf.map_readable().unwrap()[..], &input[..]);
+
+        // Across a single key rotation
+        let buf = srcpad.range(1500, 1200).unwrap();
+        assert_eq!(&buf.map_readable().unwrap()[..], &input[1500..2700]);
+
+        // Starting after the second key rotation
+        let buf = srcpad.range(4100, 100).unwrap();
+        assert_eq!(&buf.map_readable().unwrap()[..], &input[4100..]);
+
+        assert_eq!(
+            srcpad.range(input.len() as u64, 100),
+            Err(gst::FlowError::Eos)
+        );
+
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+
+    // Not a recipient of the stream
+    let (_, other_secret) = box_::gen_keypair();
+    let (pipeline, srcpad) = decrypt(&other_secret);
+    assert!(srcpad.activate_mode(gst::PadMode::Pull, true).is_err());
+    pipeline.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_passphrase() {
+    use rand::RngCore;
+
+    init();
+
+    let mut input = vec![0u8; 3 * 1024 + 10];
+    rand::thread_rng().fill_bytes(&mut input);
+
+    let enc = gst::ElementFactory::make("sodiumencrypter")
+        .property("passphrase", "correct horse battery staple")
+        .property("block-size", 1024u32)
+        .property("key-rotation-interval", 2u32)
+        .build()
+        .unwrap();
+    let encrypted = encrypt(&enc, &input);
+    assert_eq!(&encrypted[..12], b"gst-sodium21");
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(&encrypted).unwrap();
+    file.flush().unwrap();
+
+    let decrypt = |passphrase: &str| {
+        let dec = gst::ElementFactory::make("sodiumdecrypter")
+            .property("passphrase", passphrase)
+            .build()
+            .unwrap();
+
+        decrypter_pipeline(file.path(), &dec)
+    };
+
+    let (pipeline, srcpad) = decrypt("correct horse battery staple");
+    srcpad.activate_mode(gst::PadMode::Pull, true).unwrap();
+
+    let buf = srcpad.range(0, 42000).unwrap();
+    assert_eq!(&buf.map_readable().unwrap()[..], &input[..]);
+
+    let buf = srcpad.range(2000, 1000).unwrap();
+    assert_eq!(&buf.map_readable().unwrap()[..], &input[2000..3000]);
+
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    let (pipeline, srcpad) = decrypt("wrong horse battery staple");
+    assert!(srcpad.activate_mode(gst::PadMode::Pull, true).is_err());
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    // Passphrases and key pairs are exclusive
+    let enc = gst::ElementFactory::make("sodiumencrypter")
+        .property("passphrase", "correct horse battery staple")
+        .property(
+            "receiver-key",
+            glib::Bytes::from_owned(box_::gen_keypair().0 .0),
+        )
+        .build()
+        .unwrap();
+    assert!(enc.change_state(gst::StateChange::NullToReady).is_err());
+}
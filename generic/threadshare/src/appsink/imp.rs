@@ -0,0 +1,571 @@
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use futures::prelude::*;
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst::EventView;
+
+use once_cell::sync::Lazy;
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::runtime::prelude::*;
+use crate::runtime::PadSink;
+
+const DEFAULT_CAPS: Option<gst::Caps> = None;
+const DEFAULT_MAX_BUFFERS: u32 = 0;
+const DEFAULT_DROP: bool = false;
+const DEFAULT_EMIT_SIGNALS: bool = false;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    caps: Option<gst::Caps>,
+    max_buffers: u32,
+    drop: bool,
+    emit_signals: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            caps: DEFAULT_CAPS,
+            max_buffers: DEFAULT_MAX_BUFFERS,
+            drop: DEFAULT_DROP,
+            emit_signals: DEFAULT_EMIT_SIGNALS,
+        }
+    }
+}
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "ts-appsink",
+        gst::DebugColorFlags::empty(),
+        Some("Thread-sharing app sink"),
+    )
+});
+
+#[derive(Debug)]
+struct State {
+    samples: VecDeque<gst::Sample>,
+    caps: Option<gst::Caps>,
+    segment: Option<gst::Segment>,
+    is_flushing: bool,
+    is_eos: bool,
+    // Pending `pull_sample` futures, woken up when a sample is queued or on EOS / flushing
+    pull_waiters: Vec<oneshot::Sender<()>>,
+    // Streaming thread waiting for space in the queue
+    space_waiter: Option<oneshot::Sender<()>>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            samples: VecDeque::new(),
+            caps: None,
+            segment: None,
+            is_flushing: true,
+            is_eos: false,
+            pull_waiters: Vec::new(),
+            space_waiter: None,
+        }
+    }
+}
+
+impl State {
+    fn wake_pull_waiters(&mut self) {
+        for waiter in self.pull_waiters.drain(..) {
+            let _ = waiter.send(());
+        }
+    }
+
+    fn wake_space_waiter(&mut self) {
+        if let Some(waiter) = self.space_waiter.take() {
+            let _ = waiter.send(());
+        }
+    }
+
+    fn flush(&mut self) {
+        self.samples.clear();
+        self.wake_pull_waiters();
+        self.wake_space_waiter();
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AppSinkPadHandler;
+
+impl PadSinkHandler for AppSinkPadHandler {
+    type ElementImpl = AppSink;
+
+    fn sink_chain(
+        self,
+        _pad: gst::Pad,
+        elem: super::AppSink,
+        buffer: gst::Buffer,
+    ) -> BoxFuture<'static, Result<gst::FlowSuccess, gst::FlowError>> {
+        async move { elem.imp().handle_buffer(buffer).await }.boxed()
+    }
+
+    fn sink_chain_list(
+        self,
+        _pad: gst::Pad,
+        elem: super::AppSink,
+        list: gst::BufferList,
+    ) -> BoxFuture<'static, Result<gst::FlowSuccess, gst::FlowError>> {
+        async move {
+            let imp = elem.imp();
+            for buffer in list.iter_owned() {
+                imp.handle_buffer(buffer).await?;
+            }
+
+            Ok(gst::FlowSuccess::Ok)
+        }
+        .boxed()
+    }
+
+    fn sink_event(self, pad: &gst::Pad, imp: &AppSink, event: gst::Event) -> bool {
+        gst::debug!(CAT, obj: pad, "Handling non-serialized {:?}", event);
+
+        if let EventView::FlushStart(..) = event.view() {
+            let mut state = imp.state.lock().unwrap();
+            state.is_flushing = true;
+            state.flush();
+        }
+
+        true
+    }
+
+    fn sink_event_serialized(
+        self,
+        pad: gst::Pad,
+        elem: super::AppSink,
+        event: gst::Event,
+    ) -> BoxFuture<'static, bool> {
+        async move {
+            gst::debug!(CAT, obj: pad, "Handling serialized {:?}", event);
+
+            let imp = elem.imp();
+
+            match event.view() {
+                EventView::StreamStart(..) => {
+                    imp.state.lock().unwrap().is_eos = false;
+                }
+                EventView::Caps(e) => {
+                    imp.state.lock().unwrap().caps = Some(e.caps_owned());
+                }
+                EventView::Segment(e) => {
+                    imp.state.lock().unwrap().segment = Some(e.segment().clone());
+                }
+                EventView::FlushStop(..) => {
+                    let mut state = imp.state.lock().unwrap();
+                    state.is_flushing = false;
+                    state.is_eos = false;
+                    state.segment = None;
+                }
+                EventView::Eos(..) => {
+                    {
+                        let mut state = imp.state.lock().unwrap();
+                        state.is_eos = true;
+                        state.wake_pull_waiters();
+                    }
+
+                    if imp.settings.lock().unwrap().emit_signals {
+                        elem.emit_by_name::<()>("eos", &[]);
+                    }
+
+                    let _ = elem.post_message(gst::message::Eos::builder().src(&elem).build());
+                }
+                _ => (),
+            }
+
+            true
+        }
+        .boxed()
+    }
+
+    fn sink_query(self, pad: &gst::Pad, imp: &AppSink, query: &mut gst::QueryRef) -> bool {
+        gst::log!(CAT, obj: pad, "Handling {:?}", query);
+
+        use gst::QueryViewMut;
+        match query.view_mut() {
+            QueryViewMut::Caps(q) => {
+                let caps = if let Some(caps) = imp.settings.lock().unwrap().caps.as_ref() {
+                    q.filter()
+                        .map(|f| f.intersect_with_mode(caps, gst::CapsIntersectMode::First))
+                        .unwrap_or_else(|| caps.clone())
+                } else {
+                    q.filter()
+                        .map(|f| f.to_owned())
+                        .unwrap_or_else(gst::Caps::new_any)
+                };
+
+                q.set_result(&caps);
+
+                true
+            }
+            QueryViewMut::AcceptCaps(q) => {
+                let res = imp
+                    .settings
+                    .lock()
+                    .unwrap()
+                    .caps
+                    .as_ref()
+                    .map_or(true, |caps| q.caps().can_intersect(caps));
+                q.set_result(res);
+
+                true
+            }
+            _ => gst::Pad::query_default(pad, Some(&*imp.obj()), query),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AppSink {
+    sink_pad: PadSink,
+    state: Mutex<State>,
+    settings: Mutex<Settings>,
+}
+
+impl AppSink {
+    async fn handle_buffer(&self, buffer: gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, imp: self, "Handling {:?}", buffer);
+
+        let (max_buffers, drop, emit_signals) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.max_buffers, settings.drop, settings.emit_signals)
+        };
+
+        let mut buffer = Some(buffer);
+        loop {
+            let space_receiver = {
+                let mut state = self.state.lock().unwrap();
+
+                if state.is_flushing {
+                    gst::debug!(CAT, imp: self, "Flushing");
+                    return Err(gst::FlowError::Flushing);
+                }
+                if state.is_eos {
+                    gst::debug!(CAT, imp: self, "Already received EOS");
+                    return Err(gst::FlowError::Eos);
+                }
+
+                let is_full = max_buffers > 0 && state.samples.len() >= max_buffers as usize;
+                if !is_full || drop {
+                    if is_full {
+                        let old = state.samples.pop_front();
+                        gst::debug!(CAT, imp: self, "Queue full, dropping {:?}", old);
+                    }
+
+                    let mut builder = gst::Sample::builder().buffer(buffer.as_ref().unwrap());
+                    if let Some(ref caps) = state.caps {
+                        builder = builder.caps(caps);
+                    }
+                    if let Some(ref segment) = state.segment {
+                        builder = builder.segment(segment);
+                    }
+                    let sample = builder.build();
+
+                    state.samples.push_back(sample);
+                    state.wake_pull_waiters();
+                    buffer = None;
+
+                    None
+                } else {
+                    gst::log!(CAT, imp: self, "Queue full, waiting for space");
+
+                    let (sender, receiver) = oneshot::channel();
+                    state.space_waiter = Some(sender);
+                    Some(receiver)
+                }
+            };
+
+            match space_receiver {
+                // Woken up on pull or on flushing, check again
+                Some(receiver) => {
+                    let _ = receiver.await;
+                }
+                None => break,
+            }
+        }
+
+        if emit_signals {
+            return self
+                .obj()
+                .emit_by_name::<gst::FlowReturn>("new-sample", &[])
+                .into_result();
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    pub(super) async fn pull_sample(&self) -> Option<gst::Sample> {
+        loop {
+            let receiver = {
+                let mut state = self.state.lock().unwrap();
+
+                if let Some(sample) = state.samples.pop_front() {
+                    state.wake_space_waiter();
+                    return Some(sample);
+                }
+
+                if state.is_flushing || state.is_eos {
+                    return None;
+                }
+
+                let (sender, receiver) = oneshot::channel();
+                state.pull_waiters.push(sender);
+                receiver
+            };
+
+            let _ = receiver.await;
+        }
+    }
+
+    pub(super) fn try_pull_sample(&self) -> Option<gst::Sample> {
+        let mut state = self.state.lock().unwrap();
+        let sample = state.samples.pop_front();
+        if sample.is_some() {
+            state.wake_space_waiter();
+        }
+
+        sample
+    }
+
+    pub(super) fn is_eos(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.is_eos && state.samples.is_empty()
+    }
+
+    fn start(&self) {
+        gst::debug!(CAT, imp: self, "Starting");
+        let mut state = self.state.lock().unwrap();
+        state.is_flushing = false;
+        state.is_eos = false;
+        gst::debug!(CAT, imp: self, "Started");
+    }
+
+    fn stop(&self) {
+        gst::debug!(CAT, imp: self, "Stopping");
+        let mut state = self.state.lock().unwrap();
+        state.is_flushing = true;
+        state.caps = None;
+        state.segment = None;
+        state.flush();
+        gst::debug!(CAT, imp: self, "Stopped");
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AppSink {
+    const NAME: &'static str = "GstTsAppSink";
+    type Type = super::AppSink;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        Self {
+            sink_pad: PadSink::new(
+                gst::Pad::from_template(&klass.pad_template("sink").unwrap()),
+                AppSinkPadHandler,
+            ),
+            state: Default::default(),
+            settings: Default::default(),
+        }
+    }
+}
+
+impl ObjectImpl for AppSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
+                    .nick("Caps")
+                    .blurb("The allowed caps for the sink pad")
+                    .build(),
+                glib::ParamSpecUInt::builder("max-buffers")
+                    .nick("Max Buffers")
+                    .blurb("Maximum number of buffers to queue up (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_BUFFERS)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("drop")
+                    .nick("Drop")
+                    .blurb("Drop old buffers when the queue is full instead of waiting for space")
+                    .default_value(DEFAULT_DROP)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("emit-signals")
+                    .nick("Emit Signals")
+                    .blurb("Emit new-sample and eos signals")
+                    .default_value(DEFAULT_EMIT_SIGNALS)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![
+                /**
+                 * ts-appsink::new-sample:
+                 * @self: A ts-appsink
+                 *
+                 * Emitted from the streaming context when a new sample was queued, if
+                 * `emit-signals` is enabled. The handler must not block and can retrieve the
+                 * sample with `try-pull-sample`.
+                 *
+                 * Returns: a #GstFlowReturn that is returned upstream
+                 */
+                glib::subclass::Signal::builder("new-sample")
+                    .return_type::<gst::FlowReturn>()
+                    .build(),
+                /**
+                 * ts-appsink::eos:
+                 * @self: A ts-appsink
+                 *
+                 * Emitted from the streaming context when EOS was received, if `emit-signals`
+                 * is enabled.
+                 */
+                glib::subclass::Signal::builder("eos").build(),
+                /**
+                 * ts-appsink::try-pull-sample:
+                 * @self: A ts-appsink
+                 *
+                 * Returns: the next queued sample, or %NULL if no sample is queued
+                 */
+                glib::subclass::Signal::builder("try-pull-sample")
+                    .return_type::<Option<gst::Sample>>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSink>().expect("signal arg");
+
+                        Some(elem.imp().try_pull_sample().to_value())
+                    })
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "caps" => {
+                settings.caps = value.get().expect("type checked upstream");
+            }
+            "max-buffers" => {
+                settings.max_buffers = value.get().expect("type checked upstream");
+                drop(settings);
+
+                // Let a waiting streaming thread check the new limit
+                self.state.lock().unwrap().wake_space_waiter();
+            }
+            "drop" => {
+                settings.drop = value.get().expect("type checked upstream");
+                drop(settings);
+
+                self.state.lock().unwrap().wake_space_waiter();
+            }
+            "emit-signals" => {
+                settings.emit_signals = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "caps" => settings.caps.to_value(),
+            "max-buffers" => settings.max_buffers.to_value(),
+            "drop" => settings.drop.to_value(),
+            "emit-signals" => settings.emit_signals.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(self.sink_pad.gst_pad()).unwrap();
+        obj.set_element_flags(gst::ElementFlags::SINK);
+    }
+}
+
+impl GstObjectImpl for AppSink {}
+
+impl ElementImpl for AppSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Thread-sharing app sink",
+                "Sink/Generic",
+                "Thread-sharing app sink",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        if transition == gst::StateChange::PausedToReady {
+            self.stop();
+        }
+
+        let success = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::ReadyToPaused {
+            self.start();
+        }
+
+        Ok(success)
+    }
+}
@@ -0,0 +1,64 @@
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+//! Thread-sharing app sink.
+//!
+//! Applications can retrieve the samples either with the `try-pull-sample` action signal, e.g.
+//! from a `new-sample` signal handler, or by awaiting [`AppSink::pull_sample`] from any executor,
+//! which doesn't require a blocking thread per stream.
+
+use futures::prelude::*;
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct AppSink(ObjectSubclass<imp::AppSink>) @extends gst::Element, gst::Object;
+}
+
+impl AppSink {
+    /// Returns a `Future` resolving to the next sample.
+    ///
+    /// Resolves to `None` once the sink reached EOS and all samples were pulled, or if the sink
+    /// is flushing or not started.
+    pub fn pull_sample(&self) -> impl Future<Output = Option<gst::Sample>> + Send + 'static {
+        let this = self.clone();
+        async move { this.imp().pull_sample().await }
+    }
+
+    /// Returns the next sample if one is queued, without waiting.
+    pub fn try_pull_sample(&self) -> Option<gst::Sample> {
+        self.imp().try_pull_sample()
+    }
+
+    /// Returns `true` once the sink received EOS and all samples were pulled.
+    pub fn is_eos(&self) -> bool {
+        self.imp().is_eos()
+    }
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "ts-appsink",
+        gst::Rank::NONE,
+        AppSink::static_type(),
+    )
+}
@@ -222,6 +222,39 @@ impl DataQueue {
         Ok(())
     }
 
+    /// Removes the oldest buffer or buffer list from the queue, keeping all events.
+    pub fn pop_oldest_buffer(&self) -> Option<DataQueueItem> {
+        let mut inner = self.0.lock().unwrap();
+
+        let idx = inner
+            .queue
+            .iter()
+            .position(|item| !matches!(item, DataQueueItem::Event(_)))?;
+        let item = inner.queue.remove(idx).unwrap();
+
+        gst::debug!(DATA_QUEUE_CAT, obj: inner.element, "Dropping item {:?}", item);
+
+        let (count, bytes) = item.size();
+        inner.cur_size_buffers -= count;
+        inner.cur_size_bytes -= bytes;
+
+        Some(item)
+    }
+
+    /// Returns the current number of buffers, bytes and duration in the queue.
+    pub fn levels(&self) -> (u32, u32, gst::ClockTime) {
+        let inner = self.0.lock().unwrap();
+
+        let first_ts = inner.queue.iter().find_map(|i| i.timestamp());
+        let last_ts = inner.queue.iter().rev().find_map(|i| i.timestamp());
+        let level_time = match (first_ts, last_ts) {
+            (Some(first_ts), Some(last_ts)) => last_ts.saturating_sub(first_ts),
+            _ => gst::ClockTime::ZERO,
+        };
+
+        (inner.cur_size_buffers, inner.cur_size_bytes, level_time)
+    }
+
     // TODO: implement as a Stream now that we use a StdMutex
     #[allow(clippy::should_implement_trait)]
     pub async fn next(&mut self) -> Option<DataQueueItem> {
@@ -16,6 +16,7 @@
 #[macro_use]
 pub mod runtime;
 
+pub mod appsink;
 mod appsrc;
 mod audiotestsrc;
 pub mod dataqueue;
@@ -31,6 +32,7 @@ mod udpsrc;
 use gst::glib;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    appsink::register(plugin)?;
     appsrc::register(plugin)?;
     audiotestsrc::register(plugin)?;
     inputselector::register(plugin)?;
@@ -37,6 +37,8 @@ use crate::runtime::{Context, PadSink, PadSinkWeak, PadSrc, PadSrcWeak, Task};
 
 use crate::dataqueue::{DataQueue, DataQueueItem};
 
+use super::ProxyLeaky;
+
 static PROXY_CONTEXTS: Lazy<Mutex<HashMap<String, Weak<Mutex<ProxyContextInner>>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 static PROXY_SRC_PADS: Lazy<Mutex<HashMap<String, PadSrcWeak>>> =
@@ -51,6 +53,7 @@ const DEFAULT_MAX_SIZE_BYTES: u32 = 1024 * 1024;
 const DEFAULT_MAX_SIZE_TIME: gst::ClockTime = gst::ClockTime::SECOND;
 const DEFAULT_CONTEXT: &str = "";
 const DEFAULT_CONTEXT_WAIT: Duration = Duration::ZERO;
+const DEFAULT_LEAKY: ProxyLeaky = ProxyLeaky::No;
 
 #[derive(Debug, Clone)]
 struct SettingsSink {
@@ -70,6 +73,7 @@ struct SettingsSrc {
     max_size_buffers: u32,
     max_size_bytes: u32,
     max_size_time: gst::ClockTime,
+    leaky: ProxyLeaky,
     context: String,
     context_wait: Duration,
     proxy_context: String,
@@ -81,6 +85,7 @@ impl Default for SettingsSrc {
             max_size_buffers: DEFAULT_MAX_SIZE_BUFFERS,
             max_size_bytes: DEFAULT_MAX_SIZE_BYTES,
             max_size_time: DEFAULT_MAX_SIZE_TIME,
+            leaky: DEFAULT_LEAKY,
             context: DEFAULT_CONTEXT.into(),
             context_wait: DEFAULT_CONTEXT_WAIT,
             proxy_context: DEFAULT_PROXY_CONTEXT.into(),
@@ -109,10 +114,48 @@ struct ProxyContextInner {
     dataqueue: Option<DataQueue>,
     last_res: Result<gst::FlowSuccess, gst::FlowError>,
     pending_queue: Option<PendingQueue>,
+    leaky: ProxyLeaky,
+    dropped: u64,
     have_sink: bool,
     have_src: bool,
 }
 
+impl ProxyContextInner {
+    fn push_leaky(&mut self, item: DataQueueItem) -> Result<(), DataQueueItem> {
+        let Some(dataqueue) = self.dataqueue.clone() else {
+            return Err(item);
+        };
+
+        let mut item = match dataqueue.push(item) {
+            Ok(()) => return Ok(()),
+            Err(item) => item,
+        };
+
+        // New buffers are dropped in upstream mode. Events are never dropped but make room
+        // by dropping the oldest buffers, like all items in downstream mode.
+        if self.leaky == ProxyLeaky::Upstream && !matches!(item, DataQueueItem::Event(_)) {
+            self.dropped += 1;
+            return Ok(());
+        }
+
+        if self.leaky == ProxyLeaky::No {
+            return Err(item);
+        }
+
+        loop {
+            if dataqueue.pop_oldest_buffer().is_none() {
+                return Err(item);
+            }
+            self.dropped += 1;
+
+            item = match dataqueue.push(item) {
+                Ok(()) => return Ok(()),
+                Err(item) => item,
+            };
+        }
+    }
+}
+
 impl Drop for ProxyContextInner {
     fn drop(&mut self) {
         let mut proxy_ctxs = PROXY_CONTEXTS.lock().unwrap();
@@ -172,6 +215,8 @@ impl ProxyContext {
                 dataqueue: None,
                 last_res: Err(gst::FlowError::Flushing),
                 pending_queue: None,
+                leaky: DEFAULT_LEAKY,
+                dropped: 0,
                 have_sink: as_sink,
                 have_src: !as_sink,
             }));
@@ -189,6 +234,28 @@ impl ProxyContext {
     }
 }
 
+fn proxy_stats(proxy_ctx: &Mutex<Option<ProxyContext>>) -> gst::Structure {
+    let proxy_ctx = proxy_ctx.lock().unwrap();
+    let shared_ctx = proxy_ctx.as_ref().map(ProxyContext::lock_shared);
+    let shared_ctx = shared_ctx.as_deref();
+
+    let (buffers, bytes, time) = shared_ctx
+        .and_then(|shared_ctx| shared_ctx.dataqueue.as_ref())
+        .map_or((0, 0, gst::ClockTime::ZERO), DataQueue::levels);
+    let pending = shared_ctx
+        .and_then(|shared_ctx| shared_ctx.pending_queue.as_ref())
+        .map_or(0, |pending_queue| pending_queue.items.len() as u32);
+    let dropped = shared_ctx.map_or(0, |shared_ctx| shared_ctx.dropped);
+
+    gst::Structure::builder("ts-proxy-stats")
+        .field("queued-buffers", buffers)
+        .field("queued-bytes", bytes)
+        .field("queued-time", time)
+        .field("pending-items", pending)
+        .field("dropped", dropped)
+        .build()
+}
+
 impl Drop for ProxyContext {
     fn drop(&mut self) {
         let mut shared_ctx = self.lock_shared();
@@ -376,36 +443,39 @@ impl ProxySink {
             shared_ctx.last_res?;
 
             let item = {
-                let ProxyContextInner {
-                    ref mut pending_queue,
-                    ref dataqueue,
-                    ..
-                } = *shared_ctx;
-
-                match (pending_queue, dataqueue) {
-                    (None, Some(ref dataqueue)) => dataqueue.push(item),
-                    (Some(ref mut pending_queue), Some(ref dataqueue)) => {
-                        if !pending_queue.scheduled {
-                            let mut failed_item = None;
-                            while let Some(item) = pending_queue.items.pop_front() {
-                                if let Err(item) = dataqueue.push(item) {
-                                    failed_item = Some(item);
-                                    break;
+                if shared_ctx.pending_queue.is_none() {
+                    shared_ctx.push_leaky(item)
+                } else {
+                    let ProxyContextInner {
+                        ref mut pending_queue,
+                        ref dataqueue,
+                        ..
+                    } = *shared_ctx;
+
+                    match (pending_queue, dataqueue) {
+                        (Some(ref mut pending_queue), Some(ref dataqueue)) => {
+                            if !pending_queue.scheduled {
+                                let mut failed_item = None;
+                                while let Some(item) = pending_queue.items.pop_front() {
+                                    if let Err(item) = dataqueue.push(item) {
+                                        failed_item = Some(item);
+                                        break;
+                                    }
                                 }
-                            }
 
-                            if let Some(failed_item) = failed_item {
-                                pending_queue.items.push_front(failed_item);
+                                if let Some(failed_item) = failed_item {
+                                    pending_queue.items.push_front(failed_item);
 
-                                Err(item)
+                                    Err(item)
+                                } else {
+                                    dataqueue.push(item)
+                                }
                             } else {
-                                dataqueue.push(item)
+                                Err(item)
                             }
-                        } else {
-                            Err(item)
                         }
+                        _ => Err(item),
                     }
-                    _ => Err(item),
                 }
             };
 
@@ -553,11 +623,18 @@ impl ObjectSubclass for ProxySink {
 impl ObjectImpl for ProxySink {
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-            vec![glib::ParamSpecString::builder("proxy-context")
-                .nick("Proxy Context")
-                .blurb("Context name of the proxy to share with")
-                .default_value(Some(DEFAULT_PROXY_CONTEXT))
-                .build()]
+            vec![
+                glib::ParamSpecString::builder("proxy-context")
+                    .nick("Proxy Context")
+                    .blurb("Context name of the proxy to share with")
+                    .default_value(Some(DEFAULT_PROXY_CONTEXT))
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Queue levels and dropped items of the proxy")
+                    .read_only()
+                    .build(),
+            ]
         });
 
         PROPERTIES.as_ref()
@@ -580,6 +657,7 @@ impl ObjectImpl for ProxySink {
         let settings = self.settings.lock().unwrap();
         match pspec.name() {
             "proxy-context" => settings.proxy_context.to_value(),
+            "stats" => proxy_stats(&self.proxy_ctx).to_value(),
             _ => unimplemented!(),
         }
     }
@@ -978,6 +1056,7 @@ impl ProxySrc {
         {
             let mut shared_ctx = proxy_ctx.lock_shared();
             shared_ctx.dataqueue = Some(dataqueue.clone());
+            shared_ctx.leaky = settings.leaky;
 
             let mut proxy_src_pads = PROXY_SRC_PADS.lock().unwrap();
             assert!(!proxy_src_pads.contains_key(&settings.proxy_context));
@@ -1091,6 +1170,16 @@ impl ObjectImpl for ProxySrc {
                     .maximum(u64::MAX - 1)
                     .default_value(DEFAULT_MAX_SIZE_TIME.nseconds())
                     .build(),
+                glib::ParamSpecEnum::builder_with_default("leaky", DEFAULT_LEAKY)
+                    .nick("Leaky")
+                    .blurb("Where to drop buffers when the proxy is full")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Queue levels and dropped items of the proxy")
+                    .read_only()
+                    .build(),
             ]
         });
 
@@ -1109,6 +1198,12 @@ impl ObjectImpl for ProxySrc {
             "max-size-time" => {
                 settings.max_size_time = value.get::<u64>().unwrap().nseconds();
             }
+            "leaky" => {
+                settings.leaky = value.get().expect("type checked upstream");
+                if let Some(ref proxy_ctx) = *self.proxy_ctx.lock().unwrap() {
+                    proxy_ctx.lock_shared().leaky = settings.leaky;
+                }
+            }
             "context" => {
                 settings.context = value
                     .get::<Option<String>>()
@@ -1136,6 +1231,8 @@ impl ObjectImpl for ProxySrc {
             "max-size-buffers" => settings.max_size_buffers.to_value(),
             "max-size-bytes" => settings.max_size_bytes.to_value(),
             "max-size-time" => settings.max_size_time.nseconds().to_value(),
+            "leaky" => settings.leaky.to_value(),
+            "stats" => proxy_stats(&self.proxy_ctx).to_value(),
             "context" => settings.context.to_value(),
             "context-wait" => (settings.context_wait.as_millis() as u32).to_value(),
             "proxy-context" => settings.proxy_context.to_value(),
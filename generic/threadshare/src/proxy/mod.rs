@@ -22,6 +22,26 @@ use gst::prelude::*;
 
 mod imp;
 
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsProxyLeaky")]
+pub enum ProxyLeaky {
+    #[enum_value(name = "No: Block upstream when the proxy is full.", nick = "no")]
+    No = 0,
+
+    #[enum_value(
+        name = "Upstream: Drop new buffers when the proxy is full.",
+        nick = "upstream"
+    )]
+    Upstream = 1,
+
+    #[enum_value(
+        name = "Downstream: Drop the oldest buffers when the proxy is full.",
+        nick = "downstream"
+    )]
+    Downstream = 2,
+}
+
 glib::wrapper! {
     pub struct ProxySink(ObjectSubclass<imp::ProxySink>) @extends gst::Element, gst::Object;
 }
@@ -31,6 +51,9 @@ glib::wrapper! {
 }
 
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    ProxyLeaky::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
     gst::Element::register(
         Some(plugin),
         "ts-proxysink",
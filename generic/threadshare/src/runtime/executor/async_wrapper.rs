@@ -31,6 +31,7 @@ use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 use crate::runtime::RUNTIME_CAT;
 
 use super::scheduler::{self, Scheduler};
+use super::task::poll_proceed;
 use super::{Reactor, Readable, ReadableOwned, Registration, Source, Writable, WritableOwned};
 
 /// Async adapter for I/O types.
@@ -345,6 +346,7 @@ impl<T: Send + 'static> Async<T> {
     /// The closure receives a shared reference to the I/O handle.
     pub async fn read_with<R>(&self, op: impl FnMut(&T) -> io::Result<R>) -> io::Result<R> {
         let mut op = op;
+        future::poll_fn(poll_proceed).await;
         loop {
             match op(self.get_ref()) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -367,6 +369,7 @@ impl<T: Send + 'static> Async<T> {
         op: impl FnMut(&mut T) -> io::Result<R>,
     ) -> io::Result<R> {
         let mut op = op;
+        future::poll_fn(poll_proceed).await;
         loop {
             match op(self.get_mut()) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -386,6 +389,7 @@ impl<T: Send + 'static> Async<T> {
     /// The closure receives a shared reference to the I/O handle.
     pub async fn write_with<R>(&self, op: impl FnMut(&T) -> io::Result<R>) -> io::Result<R> {
         let mut op = op;
+        future::poll_fn(poll_proceed).await;
         loop {
             match op(self.get_ref()) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -408,6 +412,7 @@ impl<T: Send + 'static> Async<T> {
         op: impl FnMut(&mut T) -> io::Result<R>,
     ) -> io::Result<R> {
         let mut op = op;
+        future::poll_fn(poll_proceed).await;
         loop {
             match op(self.get_mut()) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -549,6 +554,7 @@ impl<T: Read + Send + 'static> AsyncRead for Async<T> {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
+        ready!(poll_proceed(cx));
         loop {
             match (*self).get_mut().read(buf) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -563,6 +569,7 @@ impl<T: Read + Send + 'static> AsyncRead for Async<T> {
         cx: &mut Context<'_>,
         bufs: &mut [IoSliceMut<'_>],
     ) -> Poll<io::Result<usize>> {
+        ready!(poll_proceed(cx));
         loop {
             match (*self).get_mut().read_vectored(bufs) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -584,6 +591,7 @@ where
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
+        ready!(poll_proceed(cx));
         loop {
             match (*self).get_ref().read(buf) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -598,6 +606,7 @@ where
         cx: &mut Context<'_>,
         bufs: &mut [IoSliceMut<'_>],
     ) -> Poll<io::Result<usize>> {
+        ready!(poll_proceed(cx));
         loop {
             match (*self).get_ref().read_vectored(bufs) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -614,6 +623,7 @@ impl<T: Write + Send + 'static> AsyncWrite for Async<T> {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
+        ready!(poll_proceed(cx));
         loop {
             match (*self).get_mut().write(buf) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -628,6 +638,7 @@ impl<T: Write + Send + 'static> AsyncWrite for Async<T> {
         cx: &mut Context<'_>,
         bufs: &[IoSlice<'_>],
     ) -> Poll<io::Result<usize>> {
+        ready!(poll_proceed(cx));
         loop {
             match (*self).get_mut().write_vectored(bufs) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -661,6 +672,7 @@ where
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
+        ready!(poll_proceed(cx));
         loop {
             match (*self).get_ref().write(buf) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
@@ -675,6 +687,7 @@ where
         cx: &mut Context<'_>,
         bufs: &[IoSlice<'_>],
     ) -> Poll<io::Result<usize>> {
+        ready!(poll_proceed(cx));
         loop {
             match (*self).get_ref().write_vectored(bufs) {
                 Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
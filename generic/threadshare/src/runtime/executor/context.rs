@@ -14,7 +14,10 @@ use std::sync::{Arc, Mutex};
 use std::task::{self, Poll};
 use std::time::Duration;
 
-use super::{Handle, HandleWeak, JoinHandle, Scheduler, SubTaskOutput, TaskId};
+use super::task::DEFAULT_TASK_BUDGET;
+use super::{
+    ContextMetrics, ContextScope, Handle, HandleWeak, JoinHandle, Scheduler, SubTaskOutput, TaskId,
+};
 use crate::runtime::RUNTIME_CAT;
 
 // We are bound to using `sync` for the `runtime` `Mutex`es. Attempts to use `async` `Mutex`es
@@ -148,9 +151,39 @@ impl PartialEq for Context {
 impl Eq for Context {}
 
 impl Context {
+    /// Default duration of a tick in the timer wheels of a `Context`.
+    pub const DEFAULT_TIMER_RESOLUTION: Duration = Scheduler::DEFAULT_TIMER_RESOLUTION;
+
+    /// Default number of I/O operations a task can perform in a single poll.
+    pub const DEFAULT_TASK_BUDGET: u32 = DEFAULT_TASK_BUDGET;
+
     pub fn acquire(context_name: &str, wait: Duration) -> Result<Self, io::Error> {
+        Self::acquire_with_timer_resolution(context_name, wait, Self::DEFAULT_TIMER_RESOLUTION)
+    }
+
+    /// Acquires the `Context` with the provided name or creates it.
+    ///
+    /// `timer_resolution` is the duration of a tick in the timer wheels of the `Context`.
+    /// Timers expiring within the same tick are grouped together, so a coarser resolution
+    /// reduces the overhead of handling many concurrent timers. Timers accuracy doesn't
+    /// depend on the resolution: regular timers fire within `wait` / 2 of their deadline
+    /// and "at least" timers never fire before their deadline.
+    ///
+    /// `wait` & `timer_resolution` are ignored when joining an existing `Context`.
+    pub fn acquire_with_timer_resolution(
+        context_name: &str,
+        wait: Duration,
+        timer_resolution: Duration,
+    ) -> Result<Self, io::Error> {
         assert_ne!(context_name, Scheduler::DUMMY_NAME);
 
+        if timer_resolution.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Timer resolution can't be null",
+            ));
+        }
+
         let mut contexts = CONTEXTS.lock().unwrap();
 
         if let Some(context_weak) = contexts.get(context_name) {
@@ -160,14 +193,19 @@ impl Context {
             }
         }
 
-        let context = Context(Scheduler::start(context_name, wait));
+        let context = Context(Scheduler::start_with_timer_resolution(
+            context_name,
+            wait,
+            timer_resolution,
+        ));
         contexts.insert(context_name.into(), context.downgrade());
 
         gst::debug!(
             RUNTIME_CAT,
-            "New Context '{}' throttling {:?}",
+            "New Context '{}' throttling {:?}, timer resolution {:?}",
             context.name(),
             wait,
+            timer_resolution,
         );
         Ok(context)
     }
@@ -187,6 +225,53 @@ impl Context {
         self.0.max_throttling()
     }
 
+    pub fn timer_resolution(&self) -> Duration {
+        self.0.timer_resolution()
+    }
+
+    /// Number of I/O operations a task can perform in a single poll.
+    pub fn task_budget(&self) -> u32 {
+        self.0.task_budget()
+    }
+
+    /// Sets the number of I/O operations a task can perform in a single poll.
+    ///
+    /// When a task exhausts its budget, its pending I/O operation yields so that
+    /// the other tasks sharing this `Context` can make progress, e.g. when a
+    /// source element is flooded with incoming packets. A warning is logged when
+    /// a task exhausts its budget repeatedly.
+    ///
+    /// Use `0` to disable the budget. Defaults to [`Self::DEFAULT_TASK_BUDGET`].
+    pub fn set_task_budget(&self, budget: u32) {
+        self.0.set_task_budget(budget);
+    }
+
+    /// Returns a snapshot of the scheduling metrics of this `Context`.
+    ///
+    /// See the [`metrics`] module to get them periodically.
+    ///
+    /// [`metrics`]: metrics/index.html
+    pub fn metrics(&self) -> ContextMetrics {
+        self.0.metrics()
+    }
+
+    /// Maximum duration the `Context` keeps running its pending tasks
+    /// once the last reference to it is dropped.
+    ///
+    /// This allows in-flight operations, such as sending a final packet,
+    /// to complete before the `Context` thread exits.
+    pub fn shutdown_grace_period(&self) -> Duration {
+        self.0.shutdown_grace_period()
+    }
+
+    /// Sets the maximum duration the `Context` keeps running its pending tasks
+    /// once the last reference to it is dropped.
+    ///
+    /// Defaults to 100ms.
+    pub fn set_shutdown_grace_period(&self, grace_period: Duration) {
+        self.0.set_shutdown_grace_period(grace_period);
+    }
+
     /// Total duration the scheduler spent parked.
     ///
     /// This is only useful for performance evaluation.
@@ -265,6 +350,16 @@ impl Context {
         self.0.spawn_and_unpark(future)
     }
 
+    /// Creates a [`ContextScope`] to spawn `Future`s, timeouts & intervals on this `Context`.
+    ///
+    /// All the `Future`s spawned via the [`ContextScope`] are cancelled when it is dropped,
+    /// which allows tying them to the lifecycle of an `Element`.
+    ///
+    /// [`ContextScope`]: struct.ContextScope.html
+    pub fn scope(&self) -> ContextScope {
+        ContextScope::new(self.clone())
+    }
+
     /// Forces the scheduler to unpark.
     ///
     /// This is not needed by elements implementors as they are
@@ -567,4 +662,129 @@ mod tests {
         // Due to throttling, `Delay` may be fired earlier
         assert!(elapsed + SLEEP_DURATION / 2 >= DELAY);
     }
+
+    #[test]
+    fn scope_timeout_interval() {
+        gst::init().unwrap();
+
+        let context = Context::acquire("scope_timeout_interval", SLEEP_DURATION).unwrap();
+        let scope = context.scope();
+
+        let start = Instant::now();
+        let join_handle = scope.timeout(DELAY, || async { 42 });
+        assert_eq!(futures::executor::block_on(join_handle).unwrap(), Some(42));
+        assert!(start.elapsed() + SLEEP_DURATION / 2 >= DELAY);
+
+        let (sender, mut receiver) = mpsc::channel(0);
+        let _ = scope
+            .interval(DELAY, move || {
+                let mut sender = sender.clone();
+                async move {
+                    let _ = sender.send(()).await;
+                }
+            })
+            .unwrap();
+
+        for _ in 0..3 {
+            futures::executor::block_on(receiver.next()).unwrap();
+        }
+
+        assert!(scope.interval(Duration::ZERO, || async {}).is_err());
+
+        // Cancelling ends the interval, which drops the sender
+        scope.cancel();
+        assert!(futures::executor::block_on(receiver.next()).is_none());
+    }
+
+    #[test]
+    fn scope_cancel_on_drop() {
+        gst::init().unwrap();
+
+        let context = Context::acquire("scope_cancel_on_drop", SLEEP_DURATION).unwrap();
+        let scope = context.scope();
+
+        let join_handle = scope.spawn(futures::future::pending::<()>());
+        drop(scope);
+        assert_eq!(futures::executor::block_on(join_handle).unwrap(), None);
+
+        let scope = context.scope();
+        scope.cancel();
+        assert!(scope.is_cancelled());
+        let join_handle = scope.spawn(async { 42 });
+        assert_eq!(futures::executor::block_on(join_handle).unwrap(), None);
+    }
+
+    #[test]
+    fn metrics() {
+        gst::init().unwrap();
+
+        let (sender, mut receiver) = mpsc::channel(1);
+        let sender = std::sync::Mutex::new(sender);
+        crate::runtime::executor::metrics::set_hook(SLEEP_DURATION, move |name, metrics| {
+            if name == "metrics" {
+                let _ = sender.lock().unwrap().try_send(*metrics);
+            }
+        });
+
+        let context = Context::acquire("metrics", SLEEP_DURATION).unwrap();
+        futures::executor::block_on(context.spawn(async {
+            crate::runtime::timer::delay_for(DELAY).await;
+        }))
+        .unwrap();
+
+        let metrics = context.metrics();
+        assert!(metrics.iterations > 0);
+        assert!(metrics.tasks_polled >= 2);
+        assert!(metrics.max_tasks_per_iteration > 0);
+        assert!(metrics.timers_fired > 0);
+
+        let reported = futures::executor::block_on(receiver.next()).unwrap();
+        assert!(reported.iterations > 0);
+
+        crate::runtime::executor::metrics::unset_hook();
+    }
+
+    #[test]
+    fn task_budget() {
+        use super::super::task::poll_proceed;
+        use std::task::Poll;
+
+        gst::init().unwrap();
+
+        const OPERATIONS: u32 = 64;
+
+        async fn count_yields() -> u32 {
+            let mut yields = 0;
+            for _ in 0..OPERATIONS {
+                future::poll_fn(|cx| {
+                    let res = poll_proceed(cx);
+                    if res.is_pending() {
+                        yields += 1;
+                    }
+                    res
+                })
+                .await;
+            }
+
+            yields
+        }
+
+        let context = Context::acquire("task_budget", SLEEP_DURATION).unwrap();
+        assert_eq!(context.task_budget(), Context::DEFAULT_TASK_BUDGET);
+
+        context.set_task_budget(16);
+        let yields = futures::executor::block_on(context.spawn(count_yields())).unwrap();
+        assert_eq!(yields, OPERATIONS / 16 - 1);
+
+        // Unconstrained
+        context.set_task_budget(0);
+        let yields = futures::executor::block_on(context.spawn(count_yields())).unwrap();
+        assert_eq!(yields, 0);
+
+        // Outside of a Context
+        assert_eq!(
+            futures::executor::block_on(future::poll_fn(|cx| Poll::Ready(poll_proceed(cx)))),
+            Poll::Ready(())
+        );
+    }
 }
@@ -0,0 +1,141 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! Scheduling metrics for the [`Context`]s.
+//!
+//! Each [`Context`] maintains counters describing the behaviour of its scheduler loop.
+//! They can be retrieved on demand with [`Context::metrics`] or periodically by installing
+//! a hook with [`set_hook`], e.g. from a tracer which wants to record them alongside pad
+//! timings.
+//!
+//! [`Context`]: ../struct.Context.html
+//! [`Context::metrics`]: ../struct.Context.html#method.metrics
+//! [`set_hook`]: fn.set_hook.html
+
+use once_cell::sync::Lazy;
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// A snapshot of the scheduling metrics of a [`Context`].
+///
+/// All values are cumulated since the [`Context`] was created.
+///
+/// [`Context`]: ../struct.Context.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContextMetrics {
+    /// Number of iterations of the scheduler loop.
+    pub iterations: u64,
+    /// Number of times the scheduler was unparked before the end of its throttling sleep.
+    pub wakeups: u64,
+    /// Number of tasks polled.
+    pub tasks_polled: u64,
+    /// Max number of tasks polled in a single iteration.
+    pub max_tasks_per_iteration: u64,
+    /// Number of I/O events received by the reactor.
+    pub io_events: u64,
+    /// Number of timers fired by the reactor.
+    pub timers_fired: u64,
+    /// Number of times the scheduler slept due to throttling.
+    pub throttle_sleeps: u64,
+    /// Total duration of the throttling sleeps.
+    pub throttle_sleep_duration: Duration,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Counters {
+    iterations: AtomicU64,
+    wakeups: AtomicU64,
+    tasks_polled: AtomicU64,
+    max_tasks_per_iteration: AtomicU64,
+    io_events: AtomicU64,
+    timers_fired: AtomicU64,
+    throttle_sleeps: AtomicU64,
+    throttle_sleep_nanos: AtomicU64,
+}
+
+impl Counters {
+    pub fn add_iteration(&self, tasks_polled: usize) {
+        let tasks_polled = tasks_polled as u64;
+        self.iterations.fetch_add(1, Ordering::Relaxed);
+        self.tasks_polled.fetch_add(tasks_polled, Ordering::Relaxed);
+        self.max_tasks_per_iteration
+            .fetch_max(tasks_polled, Ordering::Relaxed);
+    }
+
+    pub fn add_reaction(&self, io_events: usize, timers_fired: usize) {
+        self.io_events
+            .fetch_add(io_events as u64, Ordering::Relaxed);
+        self.timers_fired
+            .fetch_add(timers_fired as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_throttle_sleep(&self, duration: Duration) {
+        self.throttle_sleeps.fetch_add(1, Ordering::Relaxed);
+        self.throttle_sleep_nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_wakeup(&self) {
+        self.wakeups.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ContextMetrics {
+        ContextMetrics {
+            iterations: self.iterations.load(Ordering::Relaxed),
+            wakeups: self.wakeups.load(Ordering::Relaxed),
+            tasks_polled: self.tasks_polled.load(Ordering::Relaxed),
+            max_tasks_per_iteration: self.max_tasks_per_iteration.load(Ordering::Relaxed),
+            io_events: self.io_events.load(Ordering::Relaxed),
+            timers_fired: self.timers_fired.load(Ordering::Relaxed),
+            throttle_sleeps: self.throttle_sleeps.load(Ordering::Relaxed),
+            throttle_sleep_duration: Duration::from_nanos(
+                self.throttle_sleep_nanos.load(Ordering::Relaxed),
+            ),
+        }
+    }
+}
+
+type HookFn = dyn Fn(&str, &ContextMetrics) + Send + Sync;
+
+#[derive(Clone)]
+pub(super) struct Hook {
+    pub period: Duration,
+    pub func: Arc<HookFn>,
+}
+
+static HOOK_IS_SET: AtomicBool = AtomicBool::new(false);
+static HOOK: Lazy<RwLock<Option<Hook>>> = Lazy::new(|| RwLock::new(None));
+
+/// Installs a hook which is called with the metrics of each [`Context`] every `period`.
+///
+/// The hook is called from the [`Context`] thread with the name of the [`Context`],
+/// so it must not block. It replaces any previously installed hook.
+///
+/// [`Context`]: ../struct.Context.html
+pub fn set_hook<F>(period: Duration, func: F)
+where
+    F: Fn(&str, &ContextMetrics) + Send + Sync + 'static,
+{
+    *HOOK.write().unwrap() = Some(Hook {
+        period,
+        func: Arc::new(func),
+    });
+    HOOK_IS_SET.store(true, Ordering::Release);
+}
+
+/// Removes the hook installed with [`set_hook`], if any.
+///
+/// [`set_hook`]: fn.set_hook.html
+pub fn unset_hook() {
+    HOOK_IS_SET.store(false, Ordering::Release);
+    *HOOK.write().unwrap() = None;
+}
+
+pub(super) fn hook() -> Option<Hook> {
+    if !HOOK_IS_SET.load(Ordering::Acquire) {
+        return None;
+    }
+
+    HOOK.read().unwrap().clone()
+}
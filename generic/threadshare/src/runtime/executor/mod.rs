@@ -37,9 +37,15 @@ use reactor::{Reactor, Readable, ReadableOwned, Registration, Source, Writable,
 mod scheduler;
 use scheduler::{Handle, HandleWeak, Scheduler};
 
+mod scope;
+pub use scope::ContextScope;
+
 mod task;
 pub use task::{SubTaskOutput, TaskId};
 
+pub mod metrics;
+pub use metrics::ContextMetrics;
+
 pub mod timer;
 
 struct CallOnDrop<F: FnOnce()>(Option<F>);
@@ -13,12 +13,10 @@ use slab::Slab;
 
 use std::borrow::Borrow;
 use std::cell::RefCell;
-use std::collections::BTreeMap;
 use std::fmt;
 use std::future::Future;
 use std::io;
 use std::marker::PhantomData;
-use std::mem;
 use std::panic;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -51,6 +49,9 @@ cfg_if::cfg_if! {
     }
 }
 
+mod wheel;
+use wheel::TimerWheel;
+
 use crate::runtime::{Async, RUNTIME_CAT};
 
 const READ: usize = 0;
@@ -95,21 +96,19 @@ pub(super) struct Reactor {
     /// Holding a lock on this event list implies the exclusive right to poll I/O.
     events: Events,
 
-    /// An ordered map of registered regular timers.
+    /// A wheel of registered regular timers.
     ///
-    /// Timers are in the order in which they fire. The `RegularTimerId` distinguishes
-    /// timers that fire at the same time. The `Waker` represents the task awaiting the
-    /// timer.
-    timers: BTreeMap<(Instant, RegularTimerId), Waker>,
+    /// The `RegularTimerId` distinguishes timers that fire at the same time.
+    /// The `Waker` represents the task awaiting the timer.
+    timers: TimerWheel<RegularTimerId>,
 
-    /// An ordered map of registered after timers.
+    /// A wheel of registered after timers.
     ///
     /// These timers are guaranteed to fire no sooner than their expected time.
     ///
-    /// Timers are in the order in which they fire. The `AfterTimerId` distinguishes
-    /// timers that fire at the same time. The `Waker` represents the task awaiting the
-    /// timer.
-    after_timers: BTreeMap<(Instant, AfterTimerId), Waker>,
+    /// The `AfterTimerId` distinguishes timers that fire at the same time.
+    /// The `Waker` represents the task awaiting the timer.
+    after_timers: TimerWheel<AfterTimerId>,
 
     /// A queue of timer operations (insert and remove).
     ///
@@ -119,7 +118,9 @@ pub(super) struct Reactor {
 }
 
 impl Reactor {
-    fn new(max_throttling: Duration) -> Self {
+    fn new(max_throttling: Duration, timer_resolution: Duration) -> Self {
+        let now = Instant::now();
+
         Reactor {
             poller: Poller::new().expect("cannot initialize I/O event notification"),
             ticker: AtomicUsize::new(0),
@@ -129,18 +130,18 @@ impl Reactor {
             wakers: Vec::new(),
             sources: Slab::new(),
             events: Events::new(),
-            timers: BTreeMap::new(),
-            after_timers: BTreeMap::new(),
+            timers: TimerWheel::new(now, timer_resolution),
+            after_timers: TimerWheel::new(now, timer_resolution),
             timer_ops: ConcurrentQueue::bounded(1000),
         }
     }
 
     /// Initializes the reactor for current thread.
-    pub fn init(max_throttling: Duration) {
+    pub fn init(max_throttling: Duration, timer_resolution: Duration) {
         CURRENT_REACTOR.with(|cur| {
             let mut cur = cur.borrow_mut();
             if cur.is_none() {
-                *cur = Some(Reactor::new(max_throttling));
+                *cur = Some(Reactor::new(max_throttling, timer_resolution));
             }
         })
     }
@@ -231,6 +232,10 @@ impl Reactor {
         self.time_slice_end
     }
 
+    pub fn timer_resolution(&self) -> Duration {
+        self.timers.resolution()
+    }
+
     /// Registers an I/O source in the reactor.
     pub fn insert_io(&mut self, raw: Registration) -> io::Result<Arc<Source>> {
         // Create an I/O source for this file descriptor.
@@ -311,10 +316,10 @@ impl Reactor {
     }
 
     /// Deregisters a timer from the reactor.
-    pub fn remove_timer(&mut self, when: Instant, id: impl Into<TimerId>) {
+    pub fn remove_timer(&mut self, id: impl Into<TimerId>) {
         // Push a remove operation.
         let id = id.into();
-        while self.timer_ops.push(TimerOp::Remove(when, id)).is_err() {
+        while self.timer_ops.push(TimerOp::Remove(id)).is_err() {
             gst::warning!(RUNTIME_CAT, "react: timer_ops is full");
             // If the queue is full, drain it and try again.
             self.process_timer_ops();
@@ -328,49 +333,36 @@ impl Reactor {
         self.timers_check_instant = now;
         self.time_slice_end = now + self.half_max_throttling;
 
-        // Split regular timers into ready and pending timers.
-        //
-        // Careful to split just *after* current time slice end,
-        // so that a timer set to fire in current time slice is
-        // considered ready.
-        let pending = self
-            .timers
-            .split_off(&(self.time_slice_end, RegularTimerId::NONE));
-        let ready = mem::replace(&mut self.timers, pending);
-
-        // Add wakers to the list.
-        if !ready.is_empty() {
+        // Fire regular timers set to fire in current time slice.
+        let wakers_len = self.wakers.len();
+        let time_slice_end = self.time_slice_end;
+        self.timers.advance(
+            time_slice_end,
+            |when| when < time_slice_end,
+            &mut self.wakers,
+        );
+
+        if self.wakers.len() > wakers_len {
             gst::trace!(
                 RUNTIME_CAT,
-                "process_timers (regular): {} ready wakers",
-                ready.len()
+                "process_timers (regular): {} ready wakers, {} pending",
+                self.wakers.len() - wakers_len,
+                self.timers.len(),
             );
-
-            for (_, waker) in ready {
-                self.wakers.push(waker);
-            }
         }
 
-        // Split "at least" timers into ready and pending timers.
-        //
-        // Careful to split just *after* `now`,
-        // so that a timer set for exactly `now` is considered ready.
-        let pending = self
-            .after_timers
-            .split_off(&(self.timers_check_instant, AfterTimerId::NONE));
-        let ready = mem::replace(&mut self.after_timers, pending);
-
-        // Add wakers to the list.
-        if !ready.is_empty() {
+        // Fire "at least" timers set for `now` or earlier.
+        let wakers_len = self.wakers.len();
+        self.after_timers
+            .advance(now, |when| when <= now, &mut self.wakers);
+
+        if self.wakers.len() > wakers_len {
             gst::trace!(
                 RUNTIME_CAT,
-                "process_timers (after): {} ready wakers",
-                ready.len()
+                "process_timers (after): {} ready wakers, {} pending",
+                self.wakers.len() - wakers_len,
+                self.after_timers.len(),
             );
-
-            for (_, waker) in ready {
-                self.wakers.push(waker);
-            }
         }
     }
 
@@ -381,16 +373,16 @@ impl Reactor {
         for _ in 0..self.timer_ops.capacity().unwrap() {
             match self.timer_ops.pop() {
                 Ok(TimerOp::Insert(when, TimerId::Regular(id), waker)) => {
-                    self.timers.insert((when, id), waker);
+                    self.timers.insert(when, id, waker);
                 }
                 Ok(TimerOp::Insert(when, TimerId::After(id), waker)) => {
-                    self.after_timers.insert((when, id), waker);
+                    self.after_timers.insert(when, id, waker);
                 }
-                Ok(TimerOp::Remove(when, TimerId::Regular(id))) => {
-                    self.timers.remove(&(when, id));
+                Ok(TimerOp::Remove(TimerId::Regular(id))) => {
+                    self.timers.remove(id);
                 }
-                Ok(TimerOp::Remove(when, TimerId::After(id))) => {
-                    self.after_timers.remove(&(when, id));
+                Ok(TimerOp::Remove(TimerId::After(id))) => {
+                    self.after_timers.remove(id);
                 }
                 Err(_) => break,
             }
@@ -398,11 +390,13 @@ impl Reactor {
     }
 
     /// Processes new events.
-    pub fn react(&mut self, now: Instant) -> io::Result<()> {
+    pub fn react(&mut self, now: Instant) -> io::Result<Reaction> {
         debug_assert!(self.wakers.is_empty());
 
         // Process ready timers.
         self.process_timers(now);
+        let timers_fired = self.wakers.len();
+        let mut io_events = 0;
 
         // Bump the ticker before polling I/O.
         let tick = self.ticker.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
@@ -414,7 +408,9 @@ impl Reactor {
             // No I/O events occurred.
             Ok(0) => Ok(()),
             // At least one I/O event occurred.
-            Ok(_) => {
+            Ok(count) => {
+                io_events = count;
+
                 for ev in self.events.iter() {
                     // Check if there is a source in the table with this key.
                     if let Some(source) = self.sources.get(ev.key) {
@@ -466,24 +462,30 @@ impl Reactor {
             }
         }
 
-        res
+        res.map(|_| Reaction {
+            io_events,
+            timers_fired,
+        })
     }
 }
 
+/// The outcome of a [`Reactor::react`] call.
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Reaction {
+    /// Number of I/O events received.
+    pub io_events: usize,
+    /// Number of timers fired.
+    pub timers_fired: usize,
+}
+
 /// Timer will fire in its time slice.
 /// This can happen before of after the expected time.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct RegularTimerId(usize);
-impl RegularTimerId {
-    const NONE: RegularTimerId = RegularTimerId(0);
-}
 
 /// Timer is guaranteed to fire after the expected time.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct AfterTimerId(usize);
-impl AfterTimerId {
-    const NONE: AfterTimerId = AfterTimerId(0);
-}
 
 /// Any Timer Ids.
 #[derive(Copy, Clone, Debug)]
@@ -507,7 +509,7 @@ impl From<AfterTimerId> for TimerId {
 /// A single timer operation.
 enum TimerOp {
     Insert(Instant, TimerId, Waker),
-    Remove(Instant, TimerId),
+    Remove(TimerId),
 }
 
 /// A registered source of I/O events.
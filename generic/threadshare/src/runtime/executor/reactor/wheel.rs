@@ -0,0 +1,324 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+//! A hierarchical timer wheel.
+//!
+//! Timers are assigned to slots according to their deadline expressed in ticks of
+//! `resolution` since the wheel's origin. Level `0` holds the timers which expire
+//! within the next `SLOTS` ticks, level `1` those which expire within the next
+//! `SLOTS²` ticks, and so on. When the wheel crosses a slot boundary on a level,
+//! the timers from the matching slot are moved down to the lower levels.
+//!
+//! Contrary to an ordered map, inserting and removing a timer don't depend on the
+//! number of registered timers. The resolution only affects how timers are grouped:
+//! whether a timer is due is always decided based on its actual deadline.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+const SLOT_BITS: usize = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const SLOT_MASK: u64 = SLOTS as u64 - 1;
+const LEVELS: usize = 6;
+/// Max ticks between current tick and a deadline.
+///
+/// Timers with a farther deadline are parked on the last level
+/// and re-assigned when that level is cascaded.
+const MAX_TICKS: u64 = (1 << (SLOT_BITS * LEVELS)) - 1;
+
+#[derive(Debug)]
+struct Entry<Id> {
+    id: Id,
+    when: Instant,
+    waker: Waker,
+}
+
+#[derive(Debug)]
+pub(super) struct TimerWheel<Id> {
+    origin: Instant,
+    resolution: Duration,
+    /// Current tick. Timers in the level `0` slot for this tick might not be due yet.
+    elapsed: u64,
+    levels: Vec<Vec<Vec<Entry<Id>>>>,
+    /// Level & slot of each registered timer.
+    locations: HashMap<Id, (usize, usize)>,
+}
+
+impl<Id: Copy + Eq + Hash> TimerWheel<Id> {
+    pub fn new(origin: Instant, resolution: Duration) -> Self {
+        assert!(!resolution.is_zero(), "Timer resolution can't be null");
+
+        TimerWheel {
+            origin,
+            resolution,
+            elapsed: 0,
+            levels: (0..LEVELS)
+                .map(|_| (0..SLOTS).map(|_| Vec::new()).collect())
+                .collect(),
+            locations: HashMap::new(),
+        }
+    }
+
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        for level in self.levels.iter_mut() {
+            for slot in level.iter_mut() {
+                slot.clear();
+            }
+        }
+        self.locations.clear();
+    }
+
+    pub fn insert(&mut self, when: Instant, id: Id, waker: Waker) {
+        self.insert_entry(Entry { id, when, waker });
+    }
+
+    pub fn remove(&mut self, id: Id) {
+        if let Some((level, slot)) = self.locations.remove(&id) {
+            let slot = &mut self.levels[level][slot];
+            if let Some(idx) = slot.iter().position(|entry| entry.id == id) {
+                slot.swap_remove(idx);
+            }
+        }
+    }
+
+    /// Moves the wheel forward up to `target` and pushes the wakers of due timers.
+    ///
+    /// `is_due` decides whether a timer is due based on its deadline. It must be
+    /// consistent with `target`: all the timers with a deadline before the tick of
+    /// `target` must be due.
+    pub fn advance(
+        &mut self,
+        target: Instant,
+        is_due: impl Fn(Instant) -> bool,
+        wakers: &mut Vec<Waker>,
+    ) {
+        let target_tick = self.tick_for(target);
+
+        loop {
+            self.fire_current(&is_due, wakers);
+
+            if self.elapsed >= target_tick {
+                break;
+            }
+
+            // Jump to the next tick at which timers must be fired or cascaded.
+            // Remaining timers in current slot are due by now.
+            self.elapsed = self
+                .next_occupied_tick()
+                .map_or(target_tick, |next| next.min(target_tick));
+            self.cascade();
+        }
+    }
+
+    /// Returns the first tick after current tick which starts an occupied slot.
+    fn next_occupied_tick(&self) -> Option<u64> {
+        if self.locations.is_empty() {
+            return None;
+        }
+
+        let mut next = None;
+        for (level, slots) in self.levels.iter().enumerate() {
+            let shift = level * SLOT_BITS;
+            let level_range = 1u64 << (shift + SLOT_BITS);
+            let level_start = self.elapsed & !(level_range - 1);
+            let cur = ((self.elapsed >> shift) & SLOT_MASK) as usize;
+
+            let tick = (cur + 1..SLOTS)
+                .find(|&slot| !slots[slot].is_empty())
+                .map(|slot| level_start + ((slot as u64) << shift))
+                .or_else(|| {
+                    if level + 1 < LEVELS {
+                        return None;
+                    }
+
+                    // Last level wraps around
+                    (0..cur)
+                        .find(|&slot| !slots[slot].is_empty())
+                        .map(|slot| level_start + level_range + ((slot as u64) << shift))
+                });
+
+            next = match (next, tick) {
+                (Some(next), Some(tick)) => Some(u64::min(next, tick)),
+                (None, tick) => tick,
+                (next, None) => next,
+            };
+        }
+
+        next
+    }
+
+    fn tick_for(&self, when: Instant) -> u64 {
+        let ticks =
+            when.saturating_duration_since(self.origin).as_nanos() / self.resolution.as_nanos();
+        u64::try_from(ticks).unwrap_or(u64::MAX)
+    }
+
+    fn insert_entry(&mut self, entry: Entry<Id>) {
+        let tick = self
+            .tick_for(entry.when)
+            .clamp(self.elapsed, self.elapsed.saturating_add(MAX_TICKS));
+
+        let mut masked = (self.elapsed ^ tick) | SLOT_MASK;
+        if masked > MAX_TICKS {
+            masked = MAX_TICKS;
+        }
+        let level = (63 - masked.leading_zeros() as usize) / SLOT_BITS;
+        let slot = ((tick >> (level * SLOT_BITS)) & SLOT_MASK) as usize;
+
+        self.locations.insert(entry.id, (level, slot));
+        self.levels[level][slot].push(entry);
+    }
+
+    fn fire_current(&mut self, is_due: &impl Fn(Instant) -> bool, wakers: &mut Vec<Waker>) {
+        let slot = &mut self.levels[0][(self.elapsed & SLOT_MASK) as usize];
+
+        let mut idx = 0;
+        while idx < slot.len() {
+            if is_due(slot[idx].when) {
+                let entry = slot.swap_remove(idx);
+                self.locations.remove(&entry.id);
+                wakers.push(entry.waker);
+            } else {
+                idx += 1;
+            }
+        }
+    }
+
+    /// Moves down the timers from the slots which start at current tick.
+    fn cascade(&mut self) {
+        for level in (1..LEVELS).rev() {
+            let shift = level * SLOT_BITS;
+            if self.elapsed & ((1u64 << shift) - 1) != 0 {
+                continue;
+            }
+
+            let slot = ((self.elapsed >> shift) & SLOT_MASK) as usize;
+            for entry in mem::take(&mut self.levels[level][slot]) {
+                self.insert_entry(entry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Waker;
+    use std::time::{Duration, Instant};
+
+    use super::TimerWheel;
+
+    const RESOLUTION: Duration = Duration::from_millis(1);
+
+    fn counting_waker() -> (Waker, Arc<AtomicUsize>) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_clone = Arc::clone(&count);
+        let waker = waker_fn::waker_fn(move || {
+            count_clone.fetch_add(1, Ordering::SeqCst);
+        });
+
+        (waker, count)
+    }
+
+    fn advance(wheel: &mut TimerWheel<usize>, target: Instant) -> usize {
+        let mut wakers = Vec::new();
+        wheel.advance(target, |when| when <= target, &mut wakers);
+        let fired = wakers.len();
+        wakers.into_iter().for_each(Waker::wake);
+
+        fired
+    }
+
+    #[test]
+    fn fire_in_order() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, RESOLUTION);
+        let (waker, count) = counting_waker();
+
+        // Spread over the first 3 levels
+        for (id, ms) in [(1, 3), (2, 70), (3, 5000), (4, 5001)] {
+            wheel.insert(origin + Duration::from_millis(ms), id, waker.clone());
+        }
+        assert_eq!(wheel.len(), 4);
+
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(2)), 0);
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(3)), 1);
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(69)), 0);
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(80)), 1);
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(4999)), 0);
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(5001)), 2);
+
+        assert!(wheel.is_empty());
+        assert_eq!(count.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn within_tick() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, Duration::from_millis(10));
+        let (waker, _) = counting_waker();
+
+        let when = origin + Duration::from_millis(15);
+        wheel.insert(when, 1, waker);
+
+        // Same tick, but not due yet
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(12)), 0);
+        assert_eq!(advance(&mut wheel, when), 1);
+    }
+
+    #[test]
+    fn past_and_remove() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, RESOLUTION);
+        let (waker, _) = counting_waker();
+
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(100)), 0);
+
+        wheel.insert(origin + Duration::from_millis(10), 1, waker.clone());
+        wheel.insert(origin + Duration::from_millis(200), 2, waker.clone());
+        wheel.insert(origin + Duration::from_millis(300), 3, waker);
+        wheel.remove(2);
+        // Removing an unknown timer is a no-op
+        wheel.remove(4);
+        assert_eq!(wheel.len(), 2);
+
+        // Timer in the past fires on next advance
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(101)), 1);
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(250)), 0);
+        assert_eq!(advance(&mut wheel, origin + Duration::from_millis(300)), 1);
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn far_future() {
+        let origin = Instant::now();
+        let mut wheel = TimerWheel::new(origin, Duration::from_secs(1));
+        let (waker, _) = counting_waker();
+
+        // Beyond the wheel's range
+        let when = origin + Duration::from_secs((1 << 37) + 5);
+        wheel.insert(when, 1, waker);
+
+        assert_eq!(
+            advance(&mut wheel, origin + Duration::from_secs(1 << 36)),
+            0
+        );
+        assert_eq!(advance(&mut wheel, when - Duration::from_secs(1)), 0);
+        assert_eq!(advance(&mut wheel, when), 1);
+    }
+}
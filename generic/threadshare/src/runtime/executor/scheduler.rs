@@ -22,6 +22,7 @@ use std::time::{Duration, Instant};
 
 use waker_fn::waker_fn;
 
+use super::metrics::{self, ContextMetrics, Counters};
 use super::task::{SubTaskOutput, TaskId, TaskQueue};
 use super::{CallOnDrop, JoinHandle, Reactor};
 use crate::runtime::RUNTIME_CAT;
@@ -34,9 +35,12 @@ thread_local! {
 pub(super) struct Scheduler {
     context_name: Arc<str>,
     max_throttling: Duration,
+    timer_resolution: Duration,
     tasks: TaskQueue,
+    shutdown_grace_period: Mutex<Duration>,
     must_unpark: Mutex<bool>,
     must_unpark_cvar: Condvar,
+    metrics: Counters,
     #[cfg(feature = "tuning")]
     parked_duration: AtomicU64,
 }
@@ -44,8 +48,22 @@ pub(super) struct Scheduler {
 impl Scheduler {
     pub const DUMMY_NAME: &'static str = "DUMMY";
     const MAX_SUCCESSIVE_TASKS: usize = 64;
+    pub const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_millis(100);
+    pub const DEFAULT_TIMER_RESOLUTION: Duration = Duration::from_millis(1);
 
     pub fn start(context_name: &str, max_throttling: Duration) -> Handle {
+        Self::start_with_timer_resolution(
+            context_name,
+            max_throttling,
+            Self::DEFAULT_TIMER_RESOLUTION,
+        )
+    }
+
+    pub fn start_with_timer_resolution(
+        context_name: &str,
+        max_throttling: Duration,
+        timer_resolution: Duration,
+    ) -> Handle {
         // Name the thread so that it appears in panic messages.
         let thread = thread::Builder::new().name(context_name.to_string());
 
@@ -60,18 +78,52 @@ impl Scheduler {
                     thread_ctx_name
                 );
 
-                let handle = Scheduler::init(Arc::clone(&thread_ctx_name), max_throttling);
+                let handle = Scheduler::init(
+                    Arc::clone(&thread_ctx_name),
+                    max_throttling,
+                    timer_resolution,
+                );
                 let this = Arc::clone(&handle.0.scheduler);
                 let must_shutdown = handle.0.must_shutdown.clone();
                 let handle_weak = handle.downgrade();
                 handle_sender.send(handle).unwrap();
 
+                // When shutting down, keep running the pending tasks
+                // until they complete or the grace period elapses.
+                let draining = Arc::clone(&this);
+                let mut drain_deadline = None;
                 let shutdown_fut = poll_fn(move |_| {
-                    if must_shutdown.load(Ordering::SeqCst) {
-                        Poll::Ready(())
-                    } else {
-                        Poll::Pending
+                    if !must_shutdown.load(Ordering::SeqCst) {
+                        return Poll::Pending;
                     }
+
+                    let pending_tasks = draining.tasks.pending_count();
+                    if pending_tasks == 0 {
+                        return Poll::Ready(());
+                    }
+
+                    let deadline = *drain_deadline.get_or_insert_with(|| {
+                        gst::debug!(
+                            RUNTIME_CAT,
+                            "Draining {} pending tasks before shutting down Context {}",
+                            pending_tasks,
+                            draining.context_name,
+                        );
+                        Instant::now() + draining.shutdown_grace_period()
+                    });
+
+                    if Instant::now() < deadline {
+                        return Poll::Pending;
+                    }
+
+                    gst::warning!(
+                        RUNTIME_CAT,
+                        "Shutting down Context {} with {} pending tasks",
+                        draining.context_name,
+                        pending_tasks,
+                    );
+
+                    Poll::Ready(())
                 });
 
                 // Blocking on `shutdown_fut` which is cheap to `poll`.
@@ -106,7 +158,11 @@ impl Scheduler {
         handle
     }
 
-    fn init(context_name: Arc<str>, max_throttling: Duration) -> Handle {
+    fn init(
+        context_name: Arc<str>,
+        max_throttling: Duration,
+        timer_resolution: Duration,
+    ) -> Handle {
         let handle = CURRENT_SCHEDULER.with(|cur_scheduler| {
             let mut cur_scheduler = cur_scheduler.borrow_mut();
             if cur_scheduler.is_some() {
@@ -116,9 +172,12 @@ impl Scheduler {
             let handle = Handle::new(Arc::new(Scheduler {
                 context_name: context_name.clone(),
                 max_throttling,
+                timer_resolution,
                 tasks: TaskQueue::new(context_name),
+                shutdown_grace_period: Mutex::new(Scheduler::DEFAULT_SHUTDOWN_GRACE_PERIOD),
                 must_unpark: Mutex::new(false),
                 must_unpark_cvar: Condvar::new(),
+                metrics: Counters::default(),
                 #[cfg(feature = "tuning")]
                 parked_duration: AtomicU64::new(0),
             }));
@@ -128,7 +187,7 @@ impl Scheduler {
             handle
         });
 
-        Reactor::init(handle.max_throttling());
+        Reactor::init(handle.max_throttling(), handle.timer_resolution());
 
         handle
     }
@@ -143,7 +202,11 @@ impl Scheduler {
             "Attempt to block within an existing Scheduler thread."
         );
 
-        let handle = Scheduler::init(Scheduler::DUMMY_NAME.into(), Duration::ZERO);
+        let handle = Scheduler::init(
+            Scheduler::DUMMY_NAME.into(),
+            Duration::ZERO,
+            Scheduler::DEFAULT_TIMER_RESOLUTION,
+        );
         let this = Arc::clone(&handle.0.scheduler);
 
         // Move the (only) handle for this scheduler in the main task.
@@ -202,13 +265,19 @@ impl Scheduler {
         let mut now;
         // This is to ensure reactor invocation on the first iteration.
         let mut last_react = Instant::now().checked_sub(self.max_throttling).unwrap();
+        let mut last_metrics_report = Instant::now();
         let mut tasks_checked;
         'main: loop {
             // Only check I/O and timers every `max_throttling`.
             now = Instant::now();
             if now - last_react >= self.max_throttling {
                 last_react = now;
-                Reactor::with_mut(|reactor| reactor.react(now).ok());
+                if let Some(reaction) = Reactor::with_mut(|reactor| reactor.react(now).ok()) {
+                    self.metrics
+                        .add_reaction(reaction.io_events, reaction.timers_fired);
+                }
+
+                self.report_metrics(now, &mut last_metrics_report);
             }
 
             if let Poll::Ready(t) = termination_future.as_mut().poll(cx) {
@@ -230,6 +299,8 @@ impl Scheduler {
 
                     tasks_checked += 1;
                 } else {
+                    self.metrics.add_iteration(tasks_checked);
+
                     let mut must_unpark = self.must_unpark.lock().unwrap();
                     loop {
                         if *must_unpark {
@@ -246,12 +317,17 @@ impl Scheduler {
                                 Ordering::Relaxed,
                             );
 
+                            let parking_start = Instant::now();
                             let result = self
                                 .must_unpark_cvar
                                 .wait_timeout(must_unpark, parking_duration)
                                 .unwrap();
+                            self.metrics.add_throttle_sleep(parking_start.elapsed());
 
                             must_unpark = result.0;
+                            if *must_unpark && !result.1.timed_out() {
+                                self.metrics.add_wakeup();
+                            }
                         } else {
                             *must_unpark = false;
                             continue 'main;
@@ -259,9 +335,28 @@ impl Scheduler {
                     }
                 }
             }
+
+            self.metrics.add_iteration(tasks_checked);
+        }
+    }
+
+    fn report_metrics(&self, now: Instant, last_report: &mut Instant) {
+        if &*self.context_name == Self::DUMMY_NAME {
+            return;
+        }
+
+        if let Some(hook) = metrics::hook() {
+            if now.saturating_duration_since(*last_report) >= hook.period {
+                *last_report = now;
+                (hook.func)(&self.context_name, &self.metrics.snapshot());
+            }
         }
     }
 
+    fn shutdown_grace_period(&self) -> Duration {
+        *self.shutdown_grace_period.lock().unwrap()
+    }
+
     fn unpark(&self) {
         let mut must_unpark = self.must_unpark.lock().unwrap();
         *must_unpark = true;
@@ -387,6 +482,30 @@ impl Handle {
         self.0.scheduler.max_throttling
     }
 
+    pub fn timer_resolution(&self) -> Duration {
+        self.0.scheduler.timer_resolution
+    }
+
+    pub fn metrics(&self) -> ContextMetrics {
+        self.0.scheduler.metrics.snapshot()
+    }
+
+    pub fn task_budget(&self) -> u32 {
+        self.0.scheduler.tasks.budget()
+    }
+
+    pub fn set_task_budget(&self, budget: u32) {
+        self.0.scheduler.tasks.set_budget(budget);
+    }
+
+    pub fn shutdown_grace_period(&self) -> Duration {
+        self.0.scheduler.shutdown_grace_period()
+    }
+
+    pub fn set_shutdown_grace_period(&self, grace_period: Duration) {
+        *self.0.scheduler.shutdown_grace_period.lock().unwrap() = grace_period;
+    }
+
     #[cfg(feature = "tuning")]
     pub fn parked_duration(&self) -> Duration {
         Duration::from_nanos(self.0.scheduler.parked_duration.load(Ordering::Relaxed))
@@ -476,8 +595,11 @@ mod tests {
         let (shutdown_sender, shutdown_receiver) = oneshot::channel();
 
         std::thread::spawn(move || {
-            let handle =
-                Scheduler::init("block_on_task_join_handle".into(), Duration::from_millis(2));
+            let handle = Scheduler::init(
+                "block_on_task_join_handle".into(),
+                Duration::from_millis(2),
+                Scheduler::DEFAULT_TIMER_RESOLUTION,
+            );
             let join_handle = handle.spawn(async {
                 timer::delay_for(Duration::from_millis(5)).await;
                 42
@@ -513,4 +635,35 @@ mod tests {
         handle.enter(|| flag = true);
         assert!(flag);
     }
+
+    #[test]
+    fn drain_on_shutdown() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let handle = Scheduler::start("drain_on_shutdown", Duration::from_millis(2));
+
+        let done = Arc::new(AtomicBool::new(false));
+        let done_clone = Arc::clone(&done);
+        // Detach the task: the `JoinHandle` would keep the `Scheduler` alive
+        drop(handle.spawn_and_unpark(async move {
+            timer::delay_for(Duration::from_millis(20)).await;
+            done_clone.store(true, Ordering::SeqCst);
+        }));
+
+        drop(handle);
+        assert!(done.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn drain_on_shutdown_grace_period() {
+        let handle = Scheduler::start("drain_on_shutdown_grace_period", Duration::from_millis(2));
+        handle.set_shutdown_grace_period(Duration::from_millis(10));
+
+        drop(handle.spawn_and_unpark(futures::future::pending::<()>()));
+
+        let start = std::time::Instant::now();
+        drop(handle);
+        assert!(start.elapsed() < Scheduler::DEFAULT_SHUTDOWN_GRACE_PERIOD);
+    }
 }
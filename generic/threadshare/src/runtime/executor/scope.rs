@@ -0,0 +1,158 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use futures::channel::oneshot;
+use futures::future::{self, Shared};
+use futures::prelude::*;
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::timer::{self, IntervalError};
+use super::{Context, JoinHandle};
+use crate::runtime::RUNTIME_CAT;
+
+/// A set of `Future`s spawned on a [`Context`] on behalf of an `Element`.
+///
+/// All the `Future`s spawned via a `ContextScope` are cancelled when [`cancel`] is called or when
+/// the `ContextScope` is dropped. `Element`s usually create the `ContextScope` when preparing and
+/// drop it when unpreparing, along with the [`Context`] they acquired.
+///
+/// Cancelled `Future`s are dropped the next time their [`Context`] polls them.
+///
+/// [`Context`]: struct.Context.html
+/// [`cancel`]: struct.ContextScope.html#method.cancel
+pub struct ContextScope {
+    context: Context,
+    cancel_sender: Mutex<Option<oneshot::Sender<()>>>,
+    cancelled: Shared<oneshot::Receiver<()>>,
+}
+
+impl ContextScope {
+    pub(super) fn new(context: Context) -> Self {
+        let (cancel_sender, cancel_receiver) = oneshot::channel();
+
+        ContextScope {
+            context,
+            cancel_sender: Mutex::new(Some(cancel_sender)),
+            cancelled: cancel_receiver.shared(),
+        }
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Returns `true` if the `Future`s of this scope were cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_sender.lock().unwrap().is_none()
+    }
+
+    /// Spawns `future` on the [`Context`].
+    ///
+    /// The returned [`JoinHandle`] resolves to `None` if the scope
+    /// was cancelled before `future` completed.
+    ///
+    /// [`Context`]: struct.Context.html
+    /// [`JoinHandle`]: struct.JoinHandle.html
+    pub fn spawn<Fut>(&self, future: Fut) -> JoinHandle<Option<Fut::Output>>
+    where
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let cancelled = self.cancelled.clone();
+        self.context.spawn_and_unpark(async move {
+            futures::pin_mut!(future);
+            // Poll `cancelled` first so that a cancelled scope never runs `future`
+            match future::select(cancelled, future).await {
+                future::Either::Left(_) => {
+                    gst::trace!(RUNTIME_CAT, "Scoped Future cancelled");
+                    None
+                }
+                future::Either::Right((output, _)) => Some(output),
+            }
+        })
+    }
+
+    /// Spawns the `Future` returned by `func` on the [`Context`] after `delay` elapsed.
+    ///
+    /// The returned [`JoinHandle`] resolves to `None` if the scope
+    /// was cancelled before the `Future` completed.
+    ///
+    /// See [`timer::delay_for`] for the accuracy of the `delay`.
+    ///
+    /// [`Context`]: struct.Context.html
+    /// [`JoinHandle`]: struct.JoinHandle.html
+    /// [`timer::delay_for`]: timer/fn.delay_for.html
+    pub fn timeout<F, Fut>(&self, delay: Duration, func: F) -> JoinHandle<Option<Fut::Output>>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        self.spawn(async move {
+            timer::delay_for(delay).await;
+            func().await
+        })
+    }
+
+    /// Spawns the `Future` returned by `func` on the [`Context`] every `period`.
+    ///
+    /// The first tick occurs as soon as possible. Ticks are skipped
+    /// while the `Future` from the previous tick is still pending.
+    ///
+    /// Returns an error if `period` is zero.
+    ///
+    /// See [`timer::interval`] for the accuracy of the `period`.
+    ///
+    /// [`Context`]: struct.Context.html
+    /// [`timer::interval`]: timer/fn.interval.html
+    pub fn interval<F, Fut>(
+        &self,
+        period: Duration,
+        mut func: F,
+    ) -> Result<JoinHandle<Option<()>>, IntervalError>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if period.is_zero() {
+            return Err(IntervalError);
+        }
+
+        Ok(self.spawn(async move {
+            let mut interval = timer::interval(period).expect("period checked above");
+            while interval.next().await.is_some() {
+                func().await;
+            }
+        }))
+    }
+
+    /// Cancels all the `Future`s spawned via this scope.
+    ///
+    /// `Future`s spawned after this call are cancelled immediately.
+    pub fn cancel(&self) {
+        if self.cancel_sender.lock().unwrap().take().is_some() {
+            gst::debug!(
+                RUNTIME_CAT,
+                "Cancelling scoped Futures on Context {}",
+                self.context.name()
+            );
+        }
+    }
+}
+
+impl Drop for ContextScope {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl fmt::Debug for ContextScope {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ContextScope")
+            .field("context", &self.context.name())
+            .field("is_cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
@@ -17,6 +17,7 @@ use std::cell::Cell;
 use std::collections::VecDeque;
 use std::fmt;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::task::Poll;
 
@@ -25,6 +26,36 @@ use crate::runtime::RUNTIME_CAT;
 
 thread_local! {
     static CURRENT_TASK_ID: Cell<Option<TaskId>> = const { Cell::new(None) };
+    static BUDGET: Cell<Option<u32>> = const { Cell::new(None) };
+    static BUDGET_EXHAUSTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Default number of operations a task can perform in a single poll.
+pub(super) const DEFAULT_TASK_BUDGET: u32 = 128;
+
+/// Number of successive polls exhausting the budget before warning.
+const BUDGET_EXHAUSTED_WARN_THRESHOLD: u32 = 64;
+
+/// Consumes one unit of the current task's budget.
+///
+/// Returns `Poll::Pending` and schedules the task again if the budget is
+/// exhausted, which lets the other tasks of the `Context` make progress.
+/// Always returns `Poll::Ready` outside of a task with a budget.
+pub(super) fn poll_proceed(cx: &mut std::task::Context<'_>) -> Poll<()> {
+    BUDGET
+        .try_with(|budget| match budget.get() {
+            None => Poll::Ready(()),
+            Some(0) => {
+                BUDGET_EXHAUSTED.with(|exhausted| exhausted.set(true));
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Some(remaining) => {
+                budget.set(Some(remaining - 1));
+                Poll::Ready(())
+            }
+        })
+        .unwrap_or(Poll::Ready(()))
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
@@ -41,6 +72,9 @@ pub type SubTaskOutput = Result<(), gst::FlowError>;
 pin_project! {
     pub(super) struct TaskFuture<F: Future> {
         id: TaskId,
+        context_name: Arc<str>,
+        budget: Arc<AtomicU32>,
+        exhausted_polls: u32,
         #[pin]
         future: F,
     }
@@ -53,22 +87,49 @@ impl<F: Future> Future for TaskFuture<F> {
     fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
         struct TaskIdGuard {
             prev_task_id: Option<TaskId>,
+            prev_budget: Option<u32>,
+            prev_exhausted: bool,
         }
 
         impl Drop for TaskIdGuard {
             fn drop(&mut self) {
                 let _ = CURRENT_TASK_ID.try_with(|cur| cur.replace(self.prev_task_id.take()));
+                let _ = BUDGET.try_with(|cur| cur.replace(self.prev_budget.take()));
+                let _ = BUDGET_EXHAUSTED.try_with(|cur| cur.replace(self.prev_exhausted));
             }
         }
 
         let task_id = self.id;
         let project = self.project();
 
+        // A null budget means unconstrained
+        let budget = Some(project.budget.load(Ordering::Relaxed)).filter(|budget| *budget > 0);
+
         let _guard = TaskIdGuard {
             prev_task_id: CURRENT_TASK_ID.with(|cur| cur.replace(Some(task_id))),
+            prev_budget: BUDGET.with(|cur| cur.replace(budget)),
+            prev_exhausted: BUDGET_EXHAUSTED.with(|cur| cur.replace(false)),
         };
 
-        project.future.poll(cx)
+        let res = project.future.poll(cx);
+
+        if BUDGET_EXHAUSTED.with(Cell::get) {
+            *project.exhausted_polls += 1;
+            if *project.exhausted_polls >= BUDGET_EXHAUSTED_WARN_THRESHOLD {
+                gst::warning!(
+                    RUNTIME_CAT,
+                    "{:?} on context {} exhausted its budget in {} successive polls, it might starve other tasks",
+                    task_id,
+                    project.context_name,
+                    *project.exhausted_polls,
+                );
+                *project.exhausted_polls = 0;
+            }
+        } else {
+            *project.exhausted_polls = 0;
+        }
+
+        res
     }
 }
 
@@ -111,6 +172,7 @@ pub(super) struct TaskQueue {
     // a HashMap.
     tasks: Arc<Mutex<Slab<Task>>>,
     context_name: Arc<str>,
+    budget: Arc<AtomicU32>,
 }
 
 impl TaskQueue {
@@ -119,9 +181,18 @@ impl TaskQueue {
             runnables: Arc::new(ConcurrentQueue::unbounded()),
             tasks: Arc::new(Mutex::new(Slab::new())),
             context_name,
+            budget: Arc::new(AtomicU32::new(DEFAULT_TASK_BUDGET)),
         }
     }
 
+    pub fn budget(&self) -> u32 {
+        self.budget.load(Ordering::Relaxed)
+    }
+
+    pub fn set_budget(&self, budget: u32) {
+        self.budget.store(budget, Ordering::Relaxed);
+    }
+
     pub fn add<F>(&self, future: F) -> (TaskId, async_task::Task<<F as Future>::Output>)
     where
         F: Future + Send + 'static,
@@ -132,6 +203,7 @@ impl TaskQueue {
         let task_id = TaskId(tasks.vacant_entry().key());
 
         let context_name = Arc::clone(&self.context_name);
+        let budget = Arc::clone(&self.budget);
         let task_fut = async move {
             gst::trace!(
                 RUNTIME_CAT,
@@ -140,6 +212,7 @@ impl TaskQueue {
                 context_name
             );
 
+            let task_context_name = Arc::clone(&context_name);
             let _guard = CallOnDrop::new(move || {
                 if let Some(task) = tasks_clone.lock().unwrap().try_remove(task_id.0) {
                     if !task.sub_tasks.is_empty() {
@@ -163,6 +236,9 @@ impl TaskQueue {
 
             TaskFuture {
                 id: task_id,
+                context_name: task_context_name,
+                budget,
+                exhausted_polls: 0,
                 future,
             }
             .await
@@ -232,6 +308,11 @@ impl TaskQueue {
         task
     }
 
+    /// Returns the number of tasks which are not completed yet.
+    pub fn pending_count(&self) -> usize {
+        self.tasks.lock().unwrap().len()
+    }
+
     pub fn pop_runnable(&self) -> Result<Runnable, concurrent_queue::PopError> {
         self.runnables.pop()
     }
@@ -209,7 +209,7 @@ impl Drop for Oneshot {
     fn drop(&mut self) {
         if let Some((id, _)) = self.id_and_waker.take() {
             Reactor::with_mut(|reactor| {
-                reactor.remove_timer(self.when, id);
+                reactor.remove_timer(id);
             });
         }
     }
@@ -223,7 +223,7 @@ impl Future for Oneshot {
             if reactor.time_slice_end() >= self.when {
                 if let Some((id, _)) = self.id_and_waker.take() {
                     // Deregister the timer from the reactor.
-                    reactor.remove_timer(self.when, id);
+                    reactor.remove_timer(id);
                 }
 
                 Poll::Ready(())
@@ -236,7 +236,7 @@ impl Future for Oneshot {
                     }
                     Some((id, w)) if !w.will_wake(cx.waker()) => {
                         // Deregister the timer from the reactor to remove the old waker.
-                        reactor.remove_timer(self.when, *id);
+                        reactor.remove_timer(*id);
 
                         // Register the timer in the reactor with the new waker.
                         let id = reactor.insert_regular_timer(self.when, cx.waker());
@@ -279,7 +279,7 @@ impl Drop for OneshotAfter {
     fn drop(&mut self) {
         if let Some((id, _)) = self.id_and_waker.take() {
             Reactor::with_mut(|reactor| {
-                reactor.remove_timer(self.when, id);
+                reactor.remove_timer(id);
             });
         }
     }
@@ -293,7 +293,7 @@ impl Future for OneshotAfter {
             if reactor.timers_check_instant() >= self.when {
                 if let Some((id, _)) = self.id_and_waker.take() {
                     // Deregister the timer from the reactor.
-                    reactor.remove_timer(self.when, id);
+                    reactor.remove_timer(id);
                 }
 
                 Poll::Ready(())
@@ -306,7 +306,7 @@ impl Future for OneshotAfter {
                     }
                     Some((id, w)) if !w.will_wake(cx.waker()) => {
                         // Deregister the timer from the reactor to remove the old waker.
-                        reactor.remove_timer(self.when, *id);
+                        reactor.remove_timer(*id);
 
                         // Register the timer in the reactor with the new waker.
                         let id = reactor.insert_after_timer(self.when, cx.waker());
@@ -353,7 +353,7 @@ impl Drop for Interval {
     fn drop(&mut self) {
         if let Some((id, _)) = self.id_and_waker.take() {
             Reactor::with_mut(|reactor| {
-                reactor.remove_timer(self.when, id);
+                reactor.remove_timer(id);
             });
         }
     }
@@ -368,7 +368,7 @@ impl Stream for Interval {
             if time_slice_end >= self.when {
                 if let Some((id, _)) = self.id_and_waker.take() {
                     // Deregister the timer from the reactor.
-                    reactor.remove_timer(self.when, id);
+                    reactor.remove_timer(id);
                 }
                 // Compute the next tick making sure we are not so late
                 // that we would need to tick again right now.
@@ -391,7 +391,7 @@ impl Stream for Interval {
                     }
                     Some((id, w)) if !w.will_wake(cx.waker()) => {
                         // Deregister the timer from the reactor to remove the old waker.
-                        reactor.remove_timer(self.when, *id);
+                        reactor.remove_timer(*id);
 
                         // Register the timer in the reactor with the new waker.
                         let id = reactor.insert_regular_timer(self.when, cx.waker());
@@ -444,7 +444,7 @@ impl Drop for IntervalAfter {
     fn drop(&mut self) {
         if let Some((id, _)) = self.id_and_waker.take() {
             Reactor::with_mut(|reactor| {
-                reactor.remove_timer(self.when, id);
+                reactor.remove_timer(id);
             });
         }
     }
@@ -459,7 +459,7 @@ impl Stream for IntervalAfter {
             if timers_check_instant >= self.when {
                 if let Some((id, _)) = self.id_and_waker.take() {
                     // Deregister the timer from the reactor.
-                    reactor.remove_timer(self.when, id);
+                    reactor.remove_timer(id);
                 }
                 // Compute the next tick making sure we are not so late
                 // that we would need to tick again right now.
@@ -482,7 +482,7 @@ impl Stream for IntervalAfter {
                     }
                     Some((id, w)) if !w.will_wake(cx.waker()) => {
                         // Deregister the timer from the reactor to remove the old waker.
-                        reactor.remove_timer(self.when, *id);
+                        reactor.remove_timer(*id);
 
                         // Register the timer in the reactor with the new waker.
                         let id = reactor.insert_after_timer(self.when, cx.waker());
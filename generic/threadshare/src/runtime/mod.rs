@@ -21,7 +21,8 @@
 //! Current implementation uses a custom executor mostly based on the [`smol`] ecosystem.
 //!
 //! Most `Element`s implementations should use the high-level features provided by [`PadSrc`] &
-//! [`PadSink`].
+//! [`PadSink`]. One-to-one filters processing the buffers in the upstream `Context` can rely on
+//! [`TransformPads`] instead.
 //!
 //! [talk]: https://gstconf.ubicast.tv/videos/when-adding-more-threads-adds-more-problems-thread-sharing-between-elements-in-gstreamer/
 //! [slides]: https://gstreamer.freedesktop.org/data/events/gstreamer-conference/2018/Sebastian%20Dr%C3%B6ge%20-%20When%20adding%20more%20threads%20adds%20more%20problems:%20Thread-sharing%20between%20elements%20in%20GStreamer.pdf
@@ -29,9 +30,12 @@
 //! [`smol`]: https://github.com/smol-rs/
 //! [`PadSrc`]: pad/struct.PadSrc.html
 //! [`PadSink`]: pad/struct.PadSink.html
+//! [`TransformPads`]: transform/struct.TransformPads.html
 
 pub mod executor;
-pub use executor::{timer, Async, Context, JoinHandle, SubTaskOutput};
+pub use executor::{
+    metrics, timer, Async, Context, ContextMetrics, ContextScope, JoinHandle, SubTaskOutput,
+};
 
 pub mod pad;
 pub use pad::{PadSink, PadSinkRef, PadSinkWeak, PadSrc, PadSrcRef, PadSrcWeak};
@@ -39,9 +43,13 @@ pub use pad::{PadSink, PadSinkRef, PadSinkWeak, PadSrc, PadSrcRef, PadSrcWeak};
 pub mod task;
 pub use task::{Task, TaskState};
 
+pub mod transform;
+pub use transform::TransformPads;
+
 pub mod prelude {
     pub use super::pad::{PadSinkHandler, PadSrcHandler};
     pub use super::task::TaskImpl;
+    pub use super::transform::TransformImpl;
 }
 
 use once_cell::sync::Lazy;
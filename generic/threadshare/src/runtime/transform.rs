@@ -0,0 +1,314 @@
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+//! A helper to implement one-to-one asynchronous filters.
+//!
+//! [`TransformPads`] owns a [`PadSink`] & a [`PadSrc`] and installs handlers which take care of
+//! the boilerplate shared by most filters:
+//!
+//! * forwarding events & queries between both pads,
+//! * tracking the flushing state and rejecting buffers while flushing,
+//! * keeping track of the current `Segment`.
+//!
+//! The `Element` implements [`TransformImpl::transform`] which is called for each incoming
+//! `Buffer` (including those from `BufferList`s) and can return a new `Buffer`, or `None` to drop
+//! it. Caps are passed through unchanged.
+//!
+//! There is no queueing: `transform` is awaited and its result pushed downstream from the chain
+//! function, in the upstream `Context`. Filters which need to be decoupled from upstream can be
+//! combined with a `ts-queue`.
+//!
+//! The `Element` must add both pads in `constructed` and call [`TransformPads::start`] &
+//! [`TransformPads::stop`] from `change_state`, respectively after `ReadyToPaused` and before
+//! `PausedToReady`.
+//!
+//! [`PadSink`]: ../pad/struct.PadSink.html
+//! [`PadSrc`]: ../pad/struct.PadSrc.html
+//! [`TransformPads`]: struct.TransformPads.html
+//! [`TransformImpl::transform`]: trait.TransformImpl.html#tymethod.transform
+//! [`TransformPads::start`]: struct.TransformPads.html#method.start
+//! [`TransformPads::stop`]: struct.TransformPads.html#method.stop
+
+use futures::future::BoxFuture;
+use futures::prelude::*;
+
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use super::pad::{PadSink, PadSinkHandler, PadSrc, PadSrcHandler};
+use super::RUNTIME_CAT;
+
+/// A trait to implement the processing of a filter built on [`TransformPads`].
+///
+/// [`TransformPads`]: struct.TransformPads.html
+pub trait TransformImpl: ElementImpl + ObjectSubclass
+where
+    <Self as ObjectSubclass>::Type: IsA<gst::Element> + Send,
+{
+    /// Returns the [`TransformPads`] owned by the `Element`.
+    ///
+    /// [`TransformPads`]: struct.TransformPads.html
+    fn transform_pads(&self) -> &TransformPads;
+
+    /// Processes `buffer`, returning the `Buffer` to push downstream or `None` to drop it.
+    fn transform(
+        &self,
+        buffer: gst::Buffer,
+    ) -> BoxFuture<'_, Result<Option<gst::Buffer>, gst::FlowError>>;
+
+    /// Resets the processing state on `FlushStop` and when stopping.
+    fn reset(&self) {}
+}
+
+#[derive(Debug, Default)]
+struct TransformState {
+    flushing: bool,
+    segment: Option<gst::Segment>,
+}
+
+/// The `Pad`s & state of a filter built on [`TransformImpl`].
+///
+/// [`TransformImpl`]: trait.TransformImpl.html
+#[derive(Debug)]
+pub struct TransformPads {
+    sink_pad: PadSink,
+    src_pad: PadSrc,
+    state: Mutex<TransformState>,
+}
+
+impl TransformPads {
+    pub fn new<T>(sink_pad: gst::Pad, src_pad: gst::Pad) -> Self
+    where
+        T: TransformImpl,
+        <T as ObjectSubclass>::Type: IsA<gst::Element> + Send,
+    {
+        TransformPads {
+            sink_pad: PadSink::new(sink_pad, TransformPadSinkHandler::<T>::default()),
+            src_pad: PadSrc::new(src_pad, TransformPadSrcHandler::<T>::default()),
+            state: Mutex::new(TransformState {
+                flushing: true,
+                segment: None,
+            }),
+        }
+    }
+
+    pub fn sink_pad(&self) -> &PadSink {
+        &self.sink_pad
+    }
+
+    pub fn src_pad(&self) -> &PadSrc {
+        &self.src_pad
+    }
+
+    /// Returns the last `Segment` received on the sink pad, if any.
+    pub fn segment(&self) -> Option<gst::Segment> {
+        self.state.lock().unwrap().segment.clone()
+    }
+
+    pub fn start(&self) {
+        gst::debug!(RUNTIME_CAT, obj: self.sink_pad.gst_pad(), "Starting");
+        self.state.lock().unwrap().flushing = false;
+    }
+
+    pub fn stop(&self) {
+        gst::debug!(RUNTIME_CAT, obj: self.sink_pad.gst_pad(), "Stopping");
+        *self.state.lock().unwrap() = TransformState {
+            flushing: true,
+            segment: None,
+        };
+    }
+
+    fn is_flushing(&self) -> bool {
+        self.state.lock().unwrap().flushing
+    }
+}
+
+struct TransformPadSinkHandler<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for TransformPadSinkHandler<T> {
+    fn default() -> Self {
+        TransformPadSinkHandler(PhantomData)
+    }
+}
+
+impl<T> Clone for TransformPadSinkHandler<T> {
+    fn clone(&self) -> Self {
+        TransformPadSinkHandler(PhantomData)
+    }
+}
+
+impl<T> PadSinkHandler for TransformPadSinkHandler<T>
+where
+    T: TransformImpl,
+    <T as ObjectSubclass>::Type: IsA<gst::Element> + Send,
+{
+    type ElementImpl = T;
+
+    fn sink_chain(
+        self,
+        pad: gst::Pad,
+        elem: <T as ObjectSubclass>::Type,
+        buffer: gst::Buffer,
+    ) -> BoxFuture<'static, Result<gst::FlowSuccess, gst::FlowError>> {
+        async move {
+            gst::log!(RUNTIME_CAT, obj: pad, "Handling {:?}", buffer);
+
+            let imp = elem.imp();
+            let pads = imp.transform_pads();
+            if pads.is_flushing() {
+                gst::debug!(RUNTIME_CAT, obj: pad, "Flushing, dropping {:?}", buffer);
+                return Err(gst::FlowError::Flushing);
+            }
+
+            match imp.transform(buffer).await? {
+                Some(buffer) => pads.src_pad.push(buffer).await,
+                None => Ok(gst::FlowSuccess::Ok),
+            }
+        }
+        .boxed()
+    }
+
+    fn sink_chain_list(
+        self,
+        pad: gst::Pad,
+        elem: <T as ObjectSubclass>::Type,
+        list: gst::BufferList,
+    ) -> BoxFuture<'static, Result<gst::FlowSuccess, gst::FlowError>> {
+        async move {
+            gst::log!(RUNTIME_CAT, obj: pad, "Handling {:?}", list);
+
+            let imp = elem.imp();
+            let pads = imp.transform_pads();
+            if pads.is_flushing() {
+                gst::debug!(RUNTIME_CAT, obj: pad, "Flushing, dropping {:?}", list);
+                return Err(gst::FlowError::Flushing);
+            }
+
+            let mut out_list = gst::BufferList::new_sized(list.len());
+            {
+                let out_list = out_list.get_mut().unwrap();
+                for buffer in list.iter_owned() {
+                    if let Some(buffer) = imp.transform(buffer).await? {
+                        out_list.add(buffer);
+                    }
+                }
+            }
+
+            if out_list.is_empty() {
+                return Ok(gst::FlowSuccess::Ok);
+            }
+
+            pads.src_pad.push_list(out_list).await
+        }
+        .boxed()
+    }
+
+    fn sink_event(self, pad: &gst::Pad, imp: &T, event: gst::Event) -> bool {
+        gst::log!(RUNTIME_CAT, obj: pad, "Handling non-serialized {:?}", event);
+
+        let pads = imp.transform_pads();
+        if let gst::EventView::FlushStart(..) = event.view() {
+            pads.state.lock().unwrap().flushing = true;
+        }
+
+        gst::log!(RUNTIME_CAT, obj: pad, "Forwarding non-serialized {:?}", event);
+        pads.src_pad.gst_pad().push_event(event)
+    }
+
+    fn sink_event_serialized(
+        self,
+        pad: gst::Pad,
+        elem: <T as ObjectSubclass>::Type,
+        event: gst::Event,
+    ) -> BoxFuture<'static, bool> {
+        async move {
+            gst::log!(RUNTIME_CAT, obj: pad, "Handling serialized {:?}", event);
+
+            let imp = elem.imp();
+            let pads = imp.transform_pads();
+
+            match event.view() {
+                gst::EventView::FlushStop(..) => {
+                    {
+                        let mut state = pads.state.lock().unwrap();
+                        state.flushing = false;
+                        state.segment = None;
+                    }
+                    imp.reset();
+                }
+                gst::EventView::Segment(ev) => {
+                    pads.state.lock().unwrap().segment = Some(ev.segment().clone());
+                }
+                _ => (),
+            }
+
+            gst::log!(RUNTIME_CAT, obj: pad, "Forwarding serialized {:?}", event);
+            pads.src_pad.push_event(event).await
+        }
+        .boxed()
+    }
+
+    fn sink_query(self, pad: &gst::Pad, imp: &T, query: &mut gst::QueryRef) -> bool {
+        if query.is_serialized() {
+            gst::log!(RUNTIME_CAT, obj: pad, "Dropping serialized {:?}", query);
+            false
+        } else {
+            gst::log!(RUNTIME_CAT, obj: pad, "Forwarding {:?}", query);
+            imp.transform_pads().src_pad.gst_pad().peer_query(query)
+        }
+    }
+}
+
+struct TransformPadSrcHandler<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for TransformPadSrcHandler<T> {
+    fn default() -> Self {
+        TransformPadSrcHandler(PhantomData)
+    }
+}
+
+impl<T> Clone for TransformPadSrcHandler<T> {
+    fn clone(&self) -> Self {
+        TransformPadSrcHandler(PhantomData)
+    }
+}
+
+impl<T> PadSrcHandler for TransformPadSrcHandler<T>
+where
+    T: TransformImpl,
+    <T as ObjectSubclass>::Type: IsA<gst::Element> + Send,
+{
+    type ElementImpl = T;
+
+    fn src_event(self, pad: &gst::Pad, imp: &T, event: gst::Event) -> bool {
+        gst::log!(RUNTIME_CAT, obj: pad, "Forwarding {:?}", event);
+        imp.transform_pads().sink_pad.gst_pad().push_event(event)
+    }
+
+    fn src_query(self, pad: &gst::Pad, imp: &T, query: &mut gst::QueryRef) -> bool {
+        if query.is_serialized() {
+            gst::log!(RUNTIME_CAT, obj: pad, "Dropping serialized {:?}", query);
+            false
+        } else {
+            gst::log!(RUNTIME_CAT, obj: pad, "Forwarding {:?}", query);
+            imp.transform_pads().sink_pad.gst_pad().peer_query(query)
+        }
+    }
+}
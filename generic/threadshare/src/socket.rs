@@ -189,6 +189,353 @@ impl<T: SocketRead> Drop for Socket<T> {
     }
 }
 
+/// Size of the receive buffers if UDP generic receive offload is enabled.
+///
+/// With GRO the kernel can coalesce multiple datagrams of the same flow into a single
+/// receive, which can be up to the maximum IP packet size.
+pub const GRO_BUFFER_SIZE: u32 = 65_535;
+
+/// A single datagram as received by [`UdpBatchSocket::recv_batch`].
+#[derive(Debug)]
+struct Datagram {
+    len: usize,
+    saddr: Option<std::net::SocketAddr>,
+    /// Size of the coalesced segments if this datagram was received via GRO.
+    segment_size: Option<usize>,
+}
+
+/// UDP socket reading as many datagrams as are available, up to a configured batch size, per
+/// wakeup.
+///
+/// On Linux this uses `recvmmsg()` to receive the whole batch with a single syscall and
+/// optionally enables generic receive offload (GRO), in which case coalesced datagrams are split
+/// again into one buffer per datagram without copying. On other platforms datagrams are received
+/// one by one until the socket would block.
+pub struct UdpBatchSocket {
+    element: gst::Element,
+    buffer_pool: gst::BufferPool,
+    socket: Async<UdpSocket>,
+    batch_size: usize,
+    gro: bool,
+    mapped_buffers: Vec<gst::MappedBuffer<gst::buffer::Writable>>,
+    clock: Option<gst::Clock>,
+    base_time: Option<gst::ClockTime>,
+}
+
+impl UdpBatchSocket {
+    /// Creates a new batch socket.
+    ///
+    /// If `gro` is set, the buffers of `buffer_pool` must be at least [`GRO_BUFFER_SIZE`] bytes
+    /// large.
+    pub fn try_new(
+        element: gst::Element,
+        buffer_pool: gst::BufferPool,
+        socket: Async<UdpSocket>,
+        batch_size: u32,
+        gro: bool,
+    ) -> Result<Self, glib::BoolError> {
+        let gro = gro && {
+            match enable_gro(socket.get_ref()) {
+                Ok(()) => true,
+                Err(err) => {
+                    gst::warning!(
+                        SOCKET_CAT,
+                        obj: element,
+                        "Failed to enable UDP GRO: {}",
+                        err
+                    );
+                    false
+                }
+            }
+        };
+
+        buffer_pool.set_active(true).map_err(|err| {
+            gst::error!(
+                SOCKET_CAT,
+                obj: element,
+                "Failed to prepare socket: {}",
+                err
+            );
+
+            err
+        })?;
+
+        let batch_size = std::cmp::max(batch_size, 1) as usize;
+
+        Ok(UdpBatchSocket {
+            element,
+            buffer_pool,
+            socket,
+            batch_size,
+            gro,
+            mapped_buffers: Vec::with_capacity(batch_size),
+            clock: None,
+            base_time: None,
+        })
+    }
+
+    pub fn set_clock(&mut self, clock: Option<gst::Clock>, base_time: Option<gst::ClockTime>) {
+        self.clock = clock;
+        self.base_time = base_time;
+    }
+
+    /// Waits for the socket to become readable and returns all datagrams that could be received
+    /// at once, with at most one datagram per buffer.
+    ///
+    /// All buffers of a batch have the same DTS.
+    pub async fn try_next(
+        &mut self,
+    ) -> Result<Vec<(gst::Buffer, Option<std::net::SocketAddr>)>, SocketError> {
+        gst::log!(SOCKET_CAT, obj: self.element, "Trying to read data");
+
+        while self.mapped_buffers.len() < self.batch_size {
+            match self.buffer_pool.acquire_buffer(None) {
+                Ok(buffer) => {
+                    self.mapped_buffers
+                        .push(buffer.into_mapped_buffer_writable().unwrap());
+                }
+                Err(err) => {
+                    gst::debug!(SOCKET_CAT, obj: self.element, "Failed to acquire buffer {:?}", err);
+                    return Err(SocketError::Gst(err));
+                }
+            }
+        }
+
+        let UdpBatchSocket {
+            ref socket,
+            ref mut mapped_buffers,
+            gro,
+            ..
+        } = *self;
+
+        let datagrams = match socket
+            .read_with(|socket| Self::recv_batch(socket, mapped_buffers, gro))
+            .await
+        {
+            Ok(datagrams) => datagrams,
+            Err(err) => {
+                gst::debug!(SOCKET_CAT, obj: self.element, "Read error {:?}", err);
+
+                return Err(SocketError::Io(err));
+            }
+        };
+
+        let time = self.clock.as_ref().unwrap().time();
+        let dts = time.opt_checked_sub(self.base_time).ok().flatten();
+        gst::debug!(
+            SOCKET_CAT,
+            obj: self.element,
+            "Read {} datagrams at {} (clock {})",
+            datagrams.len(),
+            dts.display(),
+            time.display(),
+        );
+
+        // Only the filled buffers are taken, the remaining ones stay mapped for the next read
+        let n_datagrams = datagrams.len();
+        let mut buffers = Vec::with_capacity(n_datagrams);
+        for (datagram, mapped_buffer) in Iterator::zip(
+            datagrams.into_iter(),
+            self.mapped_buffers.drain(..n_datagrams),
+        ) {
+            let mut buffer = mapped_buffer.into_buffer();
+            {
+                let buffer = buffer.get_mut().unwrap();
+                if datagram.len < buffer.size() {
+                    buffer.set_size(datagram.len);
+                }
+                buffer.set_dts(dts);
+            }
+
+            match datagram.segment_size {
+                Some(segment_size) if segment_size > 0 && segment_size < datagram.len => {
+                    gst::trace!(
+                        SOCKET_CAT,
+                        obj: self.element,
+                        "Splitting {} bytes into segments of {} bytes",
+                        datagram.len,
+                        segment_size,
+                    );
+
+                    for offset in (0..datagram.len).step_by(segment_size) {
+                        let end = std::cmp::min(offset + segment_size, datagram.len);
+                        let segment = buffer
+                            .copy_region(
+                                gst::BufferCopyFlags::MEMORY | gst::BufferCopyFlags::TIMESTAMPS,
+                                offset..end,
+                            )
+                            .map_err(|_| SocketError::Gst(gst::FlowError::Error))?;
+                        buffers.push((segment, datagram.saddr));
+                    }
+                }
+                _ => buffers.push((buffer, datagram.saddr)),
+            }
+        }
+
+        Ok(buffers)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn recv_batch(
+        socket: &UdpSocket,
+        mapped_buffers: &mut [gst::MappedBuffer<gst::buffer::Writable>],
+        gro: bool,
+    ) -> io::Result<Vec<Datagram>> {
+        use std::mem;
+
+        // Enough space for a single `UDP_GRO` control message, aligned for `cmsghdr`
+        type ControlBuffer = [u64; 8];
+
+        let n = mapped_buffers.len();
+
+        let mut iovecs = mapped_buffers
+            .iter_mut()
+            .map(|mapped_buffer| libc::iovec {
+                iov_base: mapped_buffer.as_mut_slice().as_mut_ptr() as *mut libc::c_void,
+                iov_len: mapped_buffer.len(),
+            })
+            .collect::<Vec<_>>();
+        // SAFETY: All-zero is a valid value for these plain C structs
+        let mut addrs = vec![unsafe { mem::zeroed::<libc::sockaddr_storage>() }; n];
+        let mut controls = vec![ControlBuffer::default(); if gro { n } else { 0 }];
+
+        let mut msgs = (0..n)
+            .map(|i| {
+                // SAFETY: All-zero is a valid value for this plain C struct
+                let mut msg = unsafe { mem::zeroed::<libc::mmsghdr>() };
+                msg.msg_hdr.msg_name = &mut addrs[i] as *mut _ as *mut libc::c_void;
+                msg.msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as _;
+                msg.msg_hdr.msg_iov = &mut iovecs[i];
+                msg.msg_hdr.msg_iovlen = 1;
+                if gro {
+                    msg.msg_hdr.msg_control = controls[i].as_mut_ptr() as *mut libc::c_void;
+                    msg.msg_hdr.msg_controllen = mem::size_of::<ControlBuffer>() as _;
+                }
+                msg
+            })
+            .collect::<Vec<_>>();
+
+        // SAFETY: All pointers in the message headers point to memory that stays valid and
+        // unaliased for the duration of the call
+        let res = unsafe {
+            libc::recvmmsg(
+                socket.as_raw_fd(),
+                msgs.as_mut_ptr(),
+                n as _,
+                libc::MSG_DONTWAIT as _,
+                std::ptr::null_mut(),
+            )
+        };
+        if res < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let datagrams = msgs[..res as usize]
+            .iter()
+            .zip(addrs.iter())
+            .map(|(msg, addr)| {
+                // SAFETY: The address was filled by the kernel with the given length
+                let saddr =
+                    unsafe { socket2::SockAddr::new(*addr, msg.msg_hdr.msg_namelen) }.as_socket();
+
+                let mut segment_size = None;
+                if gro {
+                    // SAFETY: The control buffer was filled by the kernel and the control
+                    // message macros stay within its bounds
+                    unsafe {
+                        let mut cmsg = libc::CMSG_FIRSTHDR(&msg.msg_hdr);
+                        while !cmsg.is_null() {
+                            if (*cmsg).cmsg_level == libc::SOL_UDP
+                                && (*cmsg).cmsg_type == libc::UDP_GRO
+                            {
+                                let size = std::ptr::read_unaligned(
+                                    libc::CMSG_DATA(cmsg) as *const libc::c_int
+                                );
+                                segment_size = Some(size as usize);
+                            }
+                            cmsg = libc::CMSG_NXTHDR(&msg.msg_hdr, cmsg);
+                        }
+                    }
+                }
+
+                Datagram {
+                    len: msg.msg_len as usize,
+                    saddr,
+                    segment_size,
+                }
+            })
+            .collect();
+
+        Ok(datagrams)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn recv_batch(
+        socket: &UdpSocket,
+        mapped_buffers: &mut [gst::MappedBuffer<gst::buffer::Writable>],
+        _gro: bool,
+    ) -> io::Result<Vec<Datagram>> {
+        let mut datagrams = Vec::with_capacity(mapped_buffers.len());
+
+        for mapped_buffer in mapped_buffers.iter_mut() {
+            match socket.recv_from(mapped_buffer.as_mut_slice()) {
+                Ok((len, saddr)) => datagrams.push(Datagram {
+                    len,
+                    saddr: Some(saddr),
+                    segment_size: None,
+                }),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock && !datagrams.is_empty() => {
+                    break
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(datagrams)
+    }
+}
+
+impl Drop for UdpBatchSocket {
+    fn drop(&mut self) {
+        // Return the buffers to the pool before deactivating it
+        self.mapped_buffers.clear();
+
+        if let Err(err) = self.buffer_pool.set_active(false) {
+            gst::error!(SOCKET_CAT, obj: self.element, "Failed to unprepare socket: {}", err);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn enable_gro(socket: &UdpSocket) -> io::Result<()> {
+    let enable: libc::c_int = 1;
+
+    // SAFETY: The option value is a valid `c_int` for the duration of the call
+    let res = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_UDP,
+            libc::UDP_GRO,
+            &enable as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn enable_gro(_socket: &UdpSocket) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "UDP GRO is only supported on Linux",
+    ))
+}
+
 // Send/Sync struct for passing around a gio::Socket
 // and getting the raw fd from it
 //
@@ -35,7 +35,9 @@ use std::time::Duration;
 use crate::runtime::prelude::*;
 use crate::runtime::{task, Async, Context, PadSrc, Task, TaskState};
 
-use crate::socket::{wrap_socket, GioSocketWrapper, Socket, SocketError, SocketRead};
+use crate::socket::{
+    wrap_socket, GioSocketWrapper, Socket, SocketError, SocketRead, UdpBatchSocket, GRO_BUFFER_SIZE,
+};
 use futures::channel::mpsc::{channel, Receiver, Sender};
 use futures::pin_mut;
 
@@ -49,6 +51,8 @@ const DEFAULT_USED_SOCKET: Option<GioSocketWrapper> = None;
 const DEFAULT_CONTEXT: &str = "";
 const DEFAULT_CONTEXT_WAIT: Duration = Duration::ZERO;
 const DEFAULT_RETRIEVE_SENDER_ADDRESS: bool = true;
+const DEFAULT_BATCH_SIZE: u32 = 1;
+const DEFAULT_GRO: bool = false;
 
 #[derive(Debug, Default)]
 struct State {
@@ -67,6 +71,8 @@ struct Settings {
     context: String,
     context_wait: Duration,
     retrieve_sender_address: bool,
+    batch_size: u32,
+    gro: bool,
 }
 
 impl Default for Settings {
@@ -82,6 +88,8 @@ impl Default for Settings {
             context: DEFAULT_CONTEXT.into(),
             context_wait: DEFAULT_CONTEXT_WAIT,
             retrieve_sender_address: DEFAULT_RETRIEVE_SENDER_ADDRESS,
+            batch_size: DEFAULT_BATCH_SIZE,
+            gro: DEFAULT_GRO,
         }
     }
 }
@@ -181,9 +189,28 @@ impl PadSrcHandler for UdpSrcPadHandler {
     }
 }
 
+fn add_address_meta(buffer: &mut gst::BufferRef, saddr: Option<SocketAddr>) {
+    if let Some(saddr) = saddr {
+        NetAddressMeta::add(buffer, &gio::InetSocketAddress::from(saddr));
+    }
+}
+
+#[derive(Debug)]
+enum UdpSrcItem {
+    Buffer(gst::Buffer),
+    BufferList(gst::BufferList),
+}
+
+enum Received {
+    Single((gst::Buffer, Option<SocketAddr>)),
+    Batch(Vec<(gst::Buffer, Option<SocketAddr>)>),
+}
+
 struct UdpSrcTask {
     element: super::UdpSrc,
     socket: Option<Socket<UdpReader>>,
+    /// Used instead of `socket` if batched reception is enabled.
+    batch_socket: Option<UdpBatchSocket>,
     retrieve_sender_address: bool,
     need_initial_events: bool,
     need_segment: bool,
@@ -195,6 +222,7 @@ impl UdpSrcTask {
         UdpSrcTask {
             element,
             socket: None,
+            batch_socket: None,
             retrieve_sender_address: DEFAULT_RETRIEVE_SENDER_ADDRESS,
             need_initial_events: true,
             need_segment: true,
@@ -204,7 +232,7 @@ impl UdpSrcTask {
 }
 
 impl TaskImpl for UdpSrcTask {
-    type Item = gst::Buffer;
+    type Item = UdpSrcItem;
 
     fn prepare(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
         async move {
@@ -372,9 +400,16 @@ impl TaskImpl for UdpSrcTask {
                 settings = udpsrc.settings.lock().unwrap();
             };
 
+            // With GRO, multiple datagrams can be received into a single buffer
+            let buffer_size = if settings.gro {
+                std::cmp::max(settings.mtu, GRO_BUFFER_SIZE)
+            } else {
+                settings.mtu
+            };
+
             let buffer_pool = gst::BufferPool::new();
             let mut config = buffer_pool.config();
-            config.set_params(None, settings.mtu, 0, 0);
+            config.set_params(None, buffer_size, 0, 0);
             buffer_pool.set_config(config).map_err(|err| {
                 gst::error_msg!(
                     gst::ResourceError::Settings,
@@ -382,21 +417,49 @@ impl TaskImpl for UdpSrcTask {
                 )
             })?;
 
+            let batch_size = settings.batch_size;
+            let gro = settings.gro;
             drop(settings);
 
-            self.socket = Some(
-                Socket::try_new(
-                    self.element.clone().upcast(),
-                    buffer_pool,
-                    UdpReader::new(socket),
-                )
-                .map_err(|err| {
-                    gst::error_msg!(
-                        gst::ResourceError::OpenRead,
-                        ["Failed to prepare socket {:?}", err]
+            if batch_size > 1 || gro {
+                gst::debug!(
+                    CAT,
+                    obj: self.element,
+                    "Receiving batches of up to {} packets (GRO {})",
+                    batch_size,
+                    gro,
+                );
+
+                self.batch_socket = Some(
+                    UdpBatchSocket::try_new(
+                        self.element.clone().upcast(),
+                        buffer_pool,
+                        socket,
+                        batch_size,
+                        gro,
+                    )
+                    .map_err(|err| {
+                        gst::error_msg!(
+                            gst::ResourceError::OpenRead,
+                            ["Failed to prepare socket {:?}", err]
+                        )
+                    })?,
+                );
+            } else {
+                self.socket = Some(
+                    Socket::try_new(
+                        self.element.clone().upcast(),
+                        buffer_pool,
+                        UdpReader::new(socket),
                     )
-                })?,
-            );
+                    .map_err(|err| {
+                        gst::error_msg!(
+                            gst::ResourceError::OpenRead,
+                            ["Failed to prepare socket {:?}", err]
+                        )
+                    })?,
+                );
+            }
 
             self.element.notify("used-socket");
 
@@ -418,20 +481,34 @@ impl TaskImpl for UdpSrcTask {
     fn start(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
         async move {
             gst::log!(CAT, obj: self.element, "Starting task");
-            self.socket
-                .as_mut()
-                .unwrap()
-                .set_clock(self.element.clock(), self.element.base_time());
+            if let Some(ref mut batch_socket) = self.batch_socket {
+                batch_socket.set_clock(self.element.clock(), self.element.base_time());
+            } else {
+                self.socket
+                    .as_mut()
+                    .unwrap()
+                    .set_clock(self.element.clock(), self.element.base_time());
+            }
             gst::log!(CAT, obj: self.element, "Task started");
             Ok(())
         }
         .boxed()
     }
 
-    fn try_next(&mut self) -> BoxFuture<'_, Result<gst::Buffer, gst::FlowError>> {
+    fn try_next(&mut self) -> BoxFuture<'_, Result<UdpSrcItem, gst::FlowError>> {
         async move {
             let event_fut = self.event_receiver.next().fuse();
-            let socket_fut = self.socket.as_mut().unwrap().try_next().fuse();
+            let socket = &mut self.socket;
+            let batch_socket = &mut self.batch_socket;
+            let retrieve_sender_address = self.retrieve_sender_address;
+            let socket_fut = async move {
+                if let Some(batch_socket) = batch_socket {
+                    batch_socket.try_next().await.map(Received::Batch)
+                } else {
+                    socket.as_mut().unwrap().try_next().await.map(Received::Single)
+                }
+            }
+            .fuse();
 
             pin_mut!(event_fut);
             pin_mut!(socket_fut);
@@ -455,17 +532,35 @@ impl TaskImpl for UdpSrcTask {
                     }
                 },
                 socket_res = socket_fut => match socket_res {
-                    Ok((mut buffer, saddr)) => {
-                        if let Some(saddr) = saddr {
-                            if self.retrieve_sender_address {
-                                NetAddressMeta::add(
-                                    buffer.get_mut().unwrap(),
-                                    &gio::InetSocketAddress::from(saddr),
-                                );
-                            }
+                    Ok(Received::Single((mut buffer, saddr))) => {
+                        if retrieve_sender_address {
+                            add_address_meta(buffer.get_mut().unwrap(), saddr);
                         }
 
-                        Ok(buffer)
+                        Ok(UdpSrcItem::Buffer(buffer))
+                    },
+                    Ok(Received::Batch(mut buffers)) => {
+                        if buffers.len() == 1 {
+                            let (mut buffer, saddr) = buffers.pop().unwrap();
+                            if retrieve_sender_address {
+                                add_address_meta(buffer.get_mut().unwrap(), saddr);
+                            }
+
+                            Ok(UdpSrcItem::Buffer(buffer))
+                        } else {
+                            let mut list = gst::BufferList::new_sized(buffers.len());
+                            {
+                                let list = list.get_mut().unwrap();
+                                for (mut buffer, saddr) in buffers {
+                                    if retrieve_sender_address {
+                                        add_address_meta(buffer.get_mut().unwrap(), saddr);
+                                    }
+                                    list.add(buffer);
+                                }
+                            }
+
+                            Ok(UdpSrcItem::BufferList(list))
+                        }
                     },
                     Err(err) => {
                         gst::error!(CAT, obj: self.element, "Got error {err:#}");
@@ -497,9 +592,9 @@ impl TaskImpl for UdpSrcTask {
         .boxed()
     }
 
-    fn handle_item(&mut self, buffer: gst::Buffer) -> BoxFuture<'_, Result<(), gst::FlowError>> {
+    fn handle_item(&mut self, item: UdpSrcItem) -> BoxFuture<'_, Result<(), gst::FlowError>> {
         async {
-            gst::log!(CAT, obj: self.element, "Handling {:?}", buffer);
+            gst::log!(CAT, obj: self.element, "Handling {:?}", item);
             let udpsrc = self.element.imp();
 
             if self.need_initial_events {
@@ -532,9 +627,12 @@ impl TaskImpl for UdpSrcTask {
                 self.need_segment = false;
             }
 
-            let res = udpsrc.src_pad.push(buffer).await.map(drop);
+            let res = match item {
+                UdpSrcItem::Buffer(buffer) => udpsrc.src_pad.push(buffer).await.map(drop),
+                UdpSrcItem::BufferList(list) => udpsrc.src_pad.push_list(list).await.map(drop),
+            };
             match res {
-                Ok(_) => gst::log!(CAT, obj: self.element, "Successfully pushed buffer"),
+                Ok(_) => gst::log!(CAT, obj: self.element, "Successfully pushed item"),
                 Err(gst::FlowError::Flushing) => gst::debug!(CAT, obj: self.element, "Flushing"),
                 Err(gst::FlowError::Eos) => {
                     gst::debug!(CAT, obj: self.element, "EOS");
@@ -757,6 +855,18 @@ impl ObjectImpl for UdpSrc {
                     .blurb("Whether to retrieve the sender address and add it to buffers as meta. Disabling this might result in minor performance improvements in certain scenarios")
                     .default_value(DEFAULT_RETRIEVE_SENDER_ADDRESS)
                     .build(),
+                glib::ParamSpecUInt::builder("batch-size")
+                    .nick("Batch Size")
+                    .blurb("Maximum number of packets to receive per read. Packets received together are pushed downstream as a buffer list. Uses recvmmsg() on Linux")
+                    .minimum(1)
+                    .maximum(1024)
+                    .default_value(DEFAULT_BATCH_SIZE)
+                    .build(),
+                glib::ParamSpecBoolean::builder("gro")
+                    .nick("GRO")
+                    .blurb("Enable UDP generic receive offload (Linux only). Datagrams coalesced by the kernel are split into one buffer per datagram")
+                    .default_value(DEFAULT_GRO)
+                    .build(),
             ];
 
             #[cfg(not(windows))]
@@ -823,6 +933,12 @@ impl ObjectImpl for UdpSrc {
             "retrieve-sender-address" => {
                 settings.retrieve_sender_address = value.get().expect("type checked upstream");
             }
+            "batch-size" => {
+                settings.batch_size = value.get().expect("type checked upstream");
+            }
+            "gro" => {
+                settings.gro = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -848,6 +964,8 @@ impl ObjectImpl for UdpSrc {
             "context" => settings.context.to_value(),
             "context-wait" => (settings.context_wait.as_millis() as u32).to_value(),
             "retrieve-sender-address" => settings.retrieve_sender_address.to_value(),
+            "batch-size" => settings.batch_size.to_value(),
+            "gro" => settings.gro.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -0,0 +1,183 @@
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+use gst::prelude::*;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use gstthreadshare::appsink::AppSink;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstthreadshare::plugin_register_static().expect("gstthreadshare appsink test");
+    });
+}
+
+fn setup() -> (gst_check::Harness, AppSink) {
+    let mut h = gst_check::Harness::new("ts-appsink");
+    h.set_src_caps_str("foo/bar");
+    let appsink = h.element().unwrap().downcast::<AppSink>().unwrap();
+
+    (h, appsink)
+}
+
+fn buffer(pts: u64) -> gst::Buffer {
+    let mut buffer = gst::Buffer::new();
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_pts(gst::ClockTime::from_seconds(pts));
+    buffer
+}
+
+#[test]
+fn pull() {
+    init();
+
+    let (mut h, appsink) = setup();
+    h.play();
+
+    for pts in 0..3 {
+        assert_eq!(h.push(buffer(pts)), Ok(gst::FlowSuccess::Ok));
+    }
+
+    for pts in 0..3 {
+        let sample = futures::executor::block_on(appsink.pull_sample()).unwrap();
+        assert_eq!(
+            sample.buffer().unwrap().pts(),
+            Some(gst::ClockTime::from_seconds(pts))
+        );
+        assert_eq!(
+            sample.caps_owned(),
+            Some(gst::Caps::builder("foo/bar").build())
+        );
+        assert!(sample.segment().is_some());
+    }
+    assert!(appsink.try_pull_sample().is_none());
+    assert!(!appsink.is_eos());
+
+    assert!(h.push_event(gst::event::Eos::new()));
+    assert!(appsink.is_eos());
+    assert!(futures::executor::block_on(appsink.pull_sample()).is_none());
+    assert_eq!(h.push(buffer(3)), Err(gst::FlowError::Eos));
+}
+
+#[test]
+fn drop_old_buffers() {
+    init();
+
+    let (mut h, appsink) = setup();
+    appsink.set_property("max-buffers", 2u32);
+    appsink.set_property("drop", true);
+    h.play();
+
+    for pts in 0..3 {
+        assert_eq!(h.push(buffer(pts)), Ok(gst::FlowSuccess::Ok));
+    }
+
+    for pts in 1..3 {
+        let sample = appsink
+            .emit_by_name::<Option<gst::Sample>>("try-pull-sample", &[])
+            .unwrap();
+        assert_eq!(
+            sample.buffer().unwrap().pts(),
+            Some(gst::ClockTime::from_seconds(pts))
+        );
+    }
+    assert!(appsink
+        .emit_by_name::<Option<gst::Sample>>("try-pull-sample", &[])
+        .is_none());
+}
+
+#[test]
+fn wait_for_space() {
+    init();
+
+    let (mut h, appsink) = setup();
+    appsink.set_property("max-buffers", 1u32);
+    h.play();
+
+    assert_eq!(h.push(buffer(0)), Ok(gst::FlowSuccess::Ok));
+
+    let puller = std::thread::spawn({
+        let appsink = appsink.clone();
+        move || {
+            std::thread::sleep(Duration::from_millis(100));
+            futures::executor::block_on(appsink.pull_sample()).unwrap()
+        }
+    });
+
+    // Blocks until the first sample was pulled
+    assert_eq!(h.push(buffer(1)), Ok(gst::FlowSuccess::Ok));
+
+    let sample = puller.join().unwrap();
+    assert_eq!(sample.buffer().unwrap().pts(), Some(gst::ClockTime::ZERO));
+
+    let sample = appsink.try_pull_sample().unwrap();
+    assert_eq!(
+        sample.buffer().unwrap().pts(),
+        Some(gst::ClockTime::from_seconds(1))
+    );
+}
+
+#[test]
+fn signals() {
+    init();
+
+    let (mut h, appsink) = setup();
+    appsink.set_property("emit-signals", true);
+
+    let n_samples = Arc::new(AtomicU32::new(0));
+    let n_eos = Arc::new(AtomicU32::new(0));
+
+    appsink.connect("new-sample", false, {
+        let n_samples = n_samples.clone();
+        move |args| {
+            let appsink = args[0].get::<gst::Element>().unwrap();
+            let sample = appsink.emit_by_name::<Option<gst::Sample>>("try-pull-sample", &[]);
+            assert!(sample.is_some());
+            n_samples.fetch_add(1, Ordering::SeqCst);
+
+            Some(gst::FlowReturn::Ok.to_value())
+        }
+    });
+
+    appsink.connect("eos", false, {
+        let n_eos = n_eos.clone();
+        move |_| {
+            n_eos.fetch_add(1, Ordering::SeqCst);
+            None
+        }
+    });
+
+    h.play();
+
+    for pts in 0..3 {
+        assert_eq!(h.push(buffer(pts)), Ok(gst::FlowSuccess::Ok));
+    }
+    assert!(h.push_event(gst::event::Eos::new()));
+
+    assert_eq!(n_samples.load(Ordering::SeqCst), 3);
+    assert_eq!(n_eos.load(Ordering::SeqCst), 1);
+    assert!(appsink.is_eos());
+}
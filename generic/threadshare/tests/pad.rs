@@ -36,7 +36,7 @@ use std::sync::Mutex;
 use std::time::Duration;
 
 use gstthreadshare::runtime::prelude::*;
-use gstthreadshare::runtime::{Context, PadSink, PadSrc, Task, TaskState};
+use gstthreadshare::runtime::{Context, PadSink, PadSrc, Task, TaskState, TransformPads};
 
 const DEFAULT_CONTEXT: &str = "";
 const THROTTLING_DURATION: Duration = Duration::from_millis(2);
@@ -678,6 +678,128 @@ glib::wrapper! {
     pub struct ElementSinkTest(ObjectSubclass<imp_sink::ElementSinkTest>) @extends gst::Element, gst::Object;
 }
 
+// Transform
+mod imp_transform {
+    use super::*;
+
+    #[derive(Debug)]
+    pub struct ElementTransformTest {
+        transform_pads: TransformPads,
+    }
+
+    impl TransformImpl for ElementTransformTest {
+        fn transform_pads(&self) -> &TransformPads {
+            &self.transform_pads
+        }
+
+        fn transform(
+            &self,
+            buffer: gst::Buffer,
+        ) -> BoxFuture<'_, Result<Option<gst::Buffer>, gst::FlowError>> {
+            async move {
+                // Drop empty buffers, forward the others
+                if buffer.size() == 0 {
+                    Ok(None)
+                } else {
+                    Ok(Some(buffer))
+                }
+            }
+            .boxed()
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ElementTransformTest {
+        const NAME: &'static str = "TsElementTransformTest";
+        type Type = super::ElementTransformTest;
+        type ParentType = gst::Element;
+
+        fn with_class(klass: &Self::Class) -> Self {
+            ElementTransformTest {
+                transform_pads: TransformPads::new::<Self>(
+                    gst::Pad::from_template(&klass.pad_template("sink").unwrap()),
+                    gst::Pad::from_template(&klass.pad_template("src").unwrap()),
+                ),
+            }
+        }
+    }
+
+    impl ObjectImpl for ElementTransformTest {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            let obj = self.obj();
+            obj.add_pad(self.transform_pads.sink_pad().gst_pad())
+                .unwrap();
+            obj.add_pad(self.transform_pads.src_pad().gst_pad())
+                .unwrap();
+        }
+    }
+
+    impl GstObjectImpl for ElementTransformTest {}
+
+    impl ElementImpl for ElementTransformTest {
+        fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+            static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+                gst::subclass::ElementMetadata::new(
+                    "Thread-sharing Test Transform Element",
+                    "Generic",
+                    "Transform Element for Pad Test",
+                    "agent <agent@local>",
+                )
+            });
+
+            Some(&*ELEMENT_METADATA)
+        }
+
+        fn pad_templates() -> &'static [gst::PadTemplate] {
+            static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+                let caps = gst::Caps::new_any();
+                let sink_pad_template = gst::PadTemplate::new(
+                    "sink",
+                    gst::PadDirection::Sink,
+                    gst::PadPresence::Always,
+                    &caps,
+                )
+                .unwrap();
+
+                let src_pad_template = gst::PadTemplate::new(
+                    "src",
+                    gst::PadDirection::Src,
+                    gst::PadPresence::Always,
+                    &caps,
+                )
+                .unwrap();
+
+                vec![sink_pad_template, src_pad_template]
+            });
+
+            PAD_TEMPLATES.as_ref()
+        }
+
+        fn change_state(
+            &self,
+            transition: gst::StateChange,
+        ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+            if let gst::StateChange::PausedToReady = transition {
+                self.transform_pads.stop();
+            }
+
+            let success = self.parent_change_state(transition)?;
+
+            if let gst::StateChange::ReadyToPaused = transition {
+                self.transform_pads.start();
+            }
+
+            Ok(success)
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct ElementTransformTest(ObjectSubclass<imp_transform::ElementTransformTest>) @extends gst::Element, gst::Object;
+}
+
 fn setup(
     context_name: &str,
     mut middle_element_1: Option<gst::Element>,
@@ -885,6 +1007,19 @@ fn src_sink_nominal() {
     nominal_scenario(name, pipeline, src_element, receiver);
 }
 
+#[test]
+fn src_transform_sink_nominal() {
+    init();
+
+    let name = "src_transform_sink";
+
+    let transform = glib::Object::new::<ElementTransformTest>();
+    let (pipeline, src_element, _sink_element, receiver) =
+        setup(name, Some(transform.upcast()), None);
+
+    nominal_scenario(name, pipeline, src_element, receiver);
+}
+
 #[test]
 fn src_tsqueue_sink_nominal() {
     init();
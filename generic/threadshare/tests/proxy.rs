@@ -181,3 +181,56 @@ fn test_from_pipeline_to_pipeline_and_back() {
     pipe_1.set_state(gst::State::Null).unwrap();
     pipe_2.set_state(gst::State::Null).unwrap();
 }
+
+#[test]
+fn test_leaky_downstream() {
+    init();
+
+    let pipeline = gst::Pipeline::default();
+    let fakesrc = gst::ElementFactory::make("fakesrc")
+        .property("num-buffers", 20i32)
+        .build()
+        .unwrap();
+    let proxysink = gst::ElementFactory::make("ts-proxysink")
+        .name("proxysink::test4")
+        .property("proxy-context", "proxy::test4_proxy")
+        .build()
+        .unwrap();
+    let proxysrc = gst::ElementFactory::make("ts-proxysrc")
+        .name("proxysrc::test4")
+        .property("proxy-context", "proxy::test4_proxy")
+        .property("context", "proxy::test")
+        .property("max-size-buffers", 2u32)
+        .property("max-size-bytes", 0u32)
+        .property("max-size-time", 0u64)
+        .property_from_str("leaky", "downstream")
+        .build()
+        .unwrap();
+    // The sink prerolls on the first buffer and blocks the proxysrc task
+    let appsink = gst_app::AppSink::builder().build();
+
+    pipeline
+        .add_many([&fakesrc, &proxysink, &proxysrc, appsink.upcast_ref()])
+        .unwrap();
+    fakesrc.link(&proxysink).unwrap();
+    proxysrc.link(&appsink).unwrap();
+
+    pipeline.set_state(gst::State::Paused).unwrap();
+
+    let mut stats = proxysrc.property::<gst::Structure>("stats");
+    for _ in 0..50 {
+        if stats.get::<u64>("dropped").unwrap() > 0 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        stats = proxysrc.property::<gst::Structure>("stats");
+    }
+
+    assert!(stats.get::<u64>("dropped").unwrap() > 0);
+    assert!(stats.get::<u32>("queued-buffers").unwrap() <= 2);
+
+    let sink_stats = proxysink.property::<gst::Structure>("stats");
+    assert!(sink_stats.get::<u64>("dropped").unwrap() > 0);
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
@@ -97,6 +97,54 @@ fn test_push() {
     assert!(n_events >= 2);
 }
 
+#[test]
+#[cfg(not(windows))]
+fn test_push_batched() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-udpsrc");
+
+    {
+        let udpsrc = h.element().unwrap();
+        udpsrc.set_property("port", 5002i32);
+        udpsrc.set_property("context", "test-push-batched");
+        udpsrc.set_property("batch-size", 8u32);
+    }
+
+    h.play();
+
+    thread::spawn(move || {
+        use std::net;
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+        use std::time;
+
+        // Sleep 50ms to allow for the udpsrc to be ready to actually receive data
+        thread::sleep(time::Duration::from_millis(50));
+
+        let socket = net::UdpSocket::bind("0.0.0.0:0").unwrap();
+
+        let ipaddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+        let dest = SocketAddr::new(ipaddr, 5002u16);
+
+        for i in 0..20u8 {
+            let buffer = [i; 160];
+            socket.send_to(&buffer, dest).unwrap();
+        }
+    });
+
+    // Buffer lists are split into individual buffers by the harness
+    for i in 0..20u8 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.size(), 160);
+
+        let map = buffer.map_readable().unwrap();
+        assert!(map.iter().all(|b| *b == i));
+        drop(map);
+
+        assert!(buffer.meta::<gst_net::NetAddressMeta>().is_some());
+    }
+}
+
 #[test]
 #[cfg(not(windows))]
 fn test_socket_reuse() {
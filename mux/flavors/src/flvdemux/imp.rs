@@ -33,6 +33,9 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     )
 });
 
+/// Minimum distance between the index entries built from audio tags, if there is no video.
+const AUDIO_INDEX_INTERVAL: gst::ClockTime = gst::ClockTime::SECOND;
+
 pub struct FlvDemux {
     sinkpad: gst::Pad,
     audio_srcpad: Mutex<Option<gst::Pad>>,
@@ -40,6 +43,28 @@ pub struct FlvDemux {
     adapter: Mutex<gst_base::UniqueAdapter>,
     flow_combiner: Mutex<gst_base::UniqueFlowCombiner>,
     state: Mutex<State>,
+    segment: Mutex<SegmentState>,
+    // Latest tag and TOC events, also sent to pads that are added later
+    sticky_events: Mutex<Vec<gst::Event>>,
+}
+
+struct SegmentState {
+    segment: gst::FormattedSegment<gst::ClockTime>,
+    seqnum: gst::Seqnum,
+    need_segment: bool,
+    // Segment to use after the flush caused by a seek
+    pending_seek: Option<(gst::FormattedSegment<gst::ClockTime>, gst::Seqnum)>,
+}
+
+impl Default for SegmentState {
+    fn default() -> Self {
+        SegmentState {
+            segment: gst::FormattedSegment::new(),
+            seqnum: gst::Seqnum::next(),
+            need_segment: false,
+            pending_seek: None,
+        }
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -50,6 +75,7 @@ enum State {
         audio: bool,
         video: bool,
         skip_left: u32,
+        offset: u64,
     },
     Streaming(StreamingState),
 }
@@ -60,11 +86,13 @@ enum Stream {
     Video,
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone)]
 enum Event {
     StreamChanged(Stream, gst::Caps),
     Buffer(Stream, gst::Buffer),
     HaveAllStreams,
+    Tags(gst::TagList),
+    Toc(gst::Toc),
 }
 
 struct StreamingState {
@@ -75,7 +103,14 @@ struct StreamingState {
     got_all_streams: bool,
     last_position: Option<gst::ClockTime>,
 
+    // Byte offset of the next tag, including its previous tag size
+    offset: u64,
+    // Keyframes seen so far, sorted by offset
+    index: Vec<IndexEntry>,
+
     metadata: Option<Metadata>,
+    // From the metadata and the onCuePoint script tags, sorted by time
+    cue_points: Vec<CuePoint>,
 
     aac_sequence_header: Option<gst::Buffer>,
     avc_sequence_header: Option<gst::Buffer>,
@@ -109,8 +144,13 @@ struct Metadata {
     creation_date: Option<String>,
     creator: Option<String>,
     title: Option<String>,
-    metadata_creator: Option<String>, /* TODO: seek_table: _,
-                                       * filepositions / times metadata arrays */
+    metadata_creator: Option<String>,
+    encoder: Option<String>,
+    cue_points: Vec<CuePoint>,
+    // Fields without a special meaning, as name / value pairs
+    custom: Vec<(String, String)>,
+    // From the keyframes times / filepositions arrays, sorted by time
+    seek_table: Vec<IndexEntry>,
 
     audio_bitrate: Option<u32>,
 
@@ -121,6 +161,19 @@ struct Metadata {
     video_bitrate: Option<u32>,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct CuePoint {
+    time: gst::ClockTime,
+    name: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct IndexEntry {
+    time: gst::ClockTime,
+    // Byte offset of the tag header
+    offset: u64,
+}
+
 #[glib::object_subclass]
 impl ObjectSubclass for FlvDemux {
     const NAME: &'static str = "GstRsFlvDemux";
@@ -175,6 +228,8 @@ impl ObjectSubclass for FlvDemux {
             state: Mutex::new(State::Stopped),
             adapter: Mutex::new(gst_base::UniqueAdapter::new()),
             flow_combiner: Mutex::new(gst_base::UniqueFlowCombiner::new()),
+            segment: Mutex::new(SegmentState::default()),
+            sticky_events: Mutex::new(Vec::new()),
         }
     }
 }
@@ -343,6 +398,8 @@ impl FlvDemux {
 
     fn start(&self, _mode: gst::PadMode) {
         *self.state.lock().unwrap() = State::NeedHeader;
+        *self.segment.lock().unwrap() = SegmentState::default();
+        self.sticky_events.lock().unwrap().clear();
     }
 
     fn stop(&self) {
@@ -372,16 +429,31 @@ impl FlvDemux {
                 // TODO implement
                 gst::Pad::event_default(pad, Some(&*self.obj()), event)
             }
-            EventView::Segment(..) => {
-                // TODO implement
-                gst::Pad::event_default(pad, Some(&*self.obj()), event)
-            }
-            EventView::FlushStart(..) => {
-                // TODO implement
-                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            EventView::Segment(e) => {
+                // Upstream works in bytes, the next tag is expected at the start of the segment.
+                // We send a gst::Format::Time segment event before the next buffer
+                if let Some(segment) = e.segment().downcast_ref::<gst::format::Bytes>() {
+                    if let State::Streaming(ref mut sstate) = *self.state.lock().unwrap() {
+                        sstate.offset = segment.start().map_or(0, |start| *start);
+                        gst::debug!(CAT, obj: pad, "Continuing at offset {}", sstate.offset);
+                    }
+                }
+
+                gst::log!(CAT, obj: pad, "Dropping segment event");
+                true
             }
             EventView::FlushStop(..) => {
-                // TODO implement
+                self.adapter.lock().unwrap().clear();
+                self.flow_combiner.lock().unwrap().reset();
+
+                let mut segment = self.segment.lock().unwrap();
+                if let Some((new_segment, seqnum)) = segment.pending_seek.take() {
+                    segment.segment = new_segment;
+                    segment.seqnum = seqnum;
+                }
+                segment.need_segment = true;
+                drop(segment);
+
                 gst::Pad::event_default(pad, Some(&*self.obj()), event)
             }
             _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
@@ -392,6 +464,30 @@ impl FlvDemux {
         use gst::QueryViewMut;
 
         match query.view_mut() {
+            QueryViewMut::Seeking(q) => {
+                if q.format() != gst::Format::Time {
+                    return false;
+                }
+
+                if self.sinkpad.peer_query(q.query_mut()) && q.result().0 {
+                    return true;
+                }
+
+                let (has_index, duration) = match *self.state.lock().unwrap() {
+                    State::Streaming(ref sstate) => (
+                        !sstate.index().is_empty(),
+                        sstate.metadata.as_ref().and_then(|m| m.duration),
+                    ),
+                    _ => (false, None),
+                };
+
+                let mut peer_query = gst::query::Seeking::new(gst::Format::Bytes);
+                let seekable =
+                    has_index && self.sinkpad.peer_query(&mut peer_query) && peer_query.result().0;
+
+                q.set(seekable, gst::ClockTime::ZERO, duration);
+                true
+            }
             QueryViewMut::Position(q) => {
                 let fmt = q.format();
                 if fmt == gst::Format::Time {
@@ -439,15 +535,111 @@ impl FlvDemux {
     fn src_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
         use gst::EventView;
 
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
         match event.view() {
-            EventView::Seek(..) => {
-                // TODO: Implement
-                false
+            EventView::Seek(e) => {
+                // Upstream might be able to seek in time itself
+                if self.sinkpad.push_event(event.clone()) {
+                    return true;
+                }
+
+                self.perform_seek(e)
             }
             _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
         }
     }
 
+    fn perform_seek(&self, event: &gst::event::Seek) -> bool {
+        let (rate, flags, start_type, start, stop_type, stop) = event.get();
+
+        let (start, stop): (Option<gst::ClockTime>, Option<gst::ClockTime>) =
+            match (start.try_into(), stop.try_into()) {
+                (Ok(start), Ok(stop)) => (start, stop),
+                _ => {
+                    gst::error!(CAT, imp: self, "seek has invalid format");
+                    return false;
+                }
+            };
+
+        if !flags.contains(gst::SeekFlags::FLUSH) {
+            gst::error!(CAT, imp: self, "only flushing seeks are supported");
+            return false;
+        }
+
+        if rate <= 0.0 {
+            gst::error!(CAT, imp: self, "only forward playback is supported");
+            return false;
+        }
+
+        if start_type == gst::SeekType::End || stop_type == gst::SeekType::End {
+            gst::error!(CAT, imp: self, "Relative seeks are not supported");
+            return false;
+        }
+
+        let seek_seqnum = event.seqnum();
+
+        let mut segment = self.segment.lock().unwrap().segment.clone();
+        let mut seek_segment = segment.clone();
+        seek_segment.do_seek(rate, flags, start_type, start, stop_type, stop);
+        let target = seek_segment.start().unwrap_or(gst::ClockTime::ZERO);
+
+        let entry = match *self.state.lock().unwrap() {
+            State::Streaming(ref sstate) => {
+                sstate.find_keyframe(target, flags.contains(gst::SeekFlags::SNAP_AFTER))
+            }
+            _ => None,
+        };
+
+        let Some(entry) = entry else {
+            gst::error!(CAT, imp: self, "No keyframe known for seeking to {}", target);
+            return false;
+        };
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Seeking to {} from keyframe {:?}",
+            target,
+            entry
+        );
+
+        // Otherwise everything before the target is clipped downstream
+        if flags.contains(gst::SeekFlags::KEY_UNIT) {
+            segment.do_seek(
+                rate,
+                flags,
+                gst::SeekType::Set,
+                Some(entry.time),
+                stop_type,
+                stop,
+            );
+        } else {
+            segment = seek_segment;
+        }
+
+        self.segment.lock().unwrap().pending_seek = Some((segment, seek_seqnum));
+
+        // Start at the previous tag size in front of the keyframe
+        let event = gst::event::Seek::builder(
+            1.0,
+            flags,
+            gst::SeekType::Set,
+            Some(gst::format::Bytes::from_u64(entry.offset.saturating_sub(4))),
+            gst::SeekType::None,
+            None::<gst::format::Bytes>,
+        )
+        .seqnum(seek_seqnum)
+        .build();
+
+        if !self.sinkpad.push_event(event) {
+            gst::error!(CAT, imp: self, "Upstream failed to seek to {}", entry.offset);
+            self.segment.lock().unwrap().pending_seek = None;
+            return false;
+        }
+
+        true
+    }
+
     fn sink_chain(
         &self,
         pad: &gst::Pad,
@@ -481,14 +673,16 @@ impl FlvDemux {
                         audio: header.audio,
                         video: header.video,
                         skip_left: skip,
+                        offset: 9 + skip as u64,
                     };
                 }
                 State::Skipping {
                     audio,
                     video,
                     skip_left: 0,
+                    offset,
                 } => {
-                    *state = State::Streaming(StreamingState::new(audio, video));
+                    *state = State::Streaming(StreamingState::new(audio, video, offset));
                 }
                 State::Skipping {
                     ref mut skip_left, ..
@@ -593,8 +787,35 @@ impl FlvDemux {
                         }
                     };
 
+                    let mut segment = self.segment.lock().unwrap();
+                    if segment.need_segment {
+                        let event = gst::event::Segment::builder(&segment.segment)
+                            .seqnum(segment.seqnum)
+                            .build();
+                        segment.need_segment = false;
+                        drop(segment);
+
+                        for pad in self.obj().src_pads() {
+                            pad.push_event(event.clone());
+                        }
+
+                        segment = self.segment.lock().unwrap();
+                    }
+
+                    let after_stop = segment
+                        .segment
+                        .stop()
+                        .opt_le(buffer.dts_or_pts())
+                        .unwrap_or(false);
+                    drop(segment);
+
                     if let Some(pad) = pad {
-                        let res = pad.push(buffer);
+                        let res = if after_stop {
+                            gst::debug!(CAT, obj: pad, "Reached the end of the segment");
+                            Err(gst::FlowError::Eos)
+                        } else {
+                            pad.push(buffer)
+                        };
                         gst::trace!(
                             CAT,
                             imp: self,
@@ -612,6 +833,12 @@ impl FlvDemux {
                 Event::HaveAllStreams => {
                     self.obj().no_more_pads();
                 }
+                Event::Tags(tags) => {
+                    self.push_sticky_event(gst::event::Tag::new(tags));
+                }
+                Event::Toc(toc) => {
+                    self.push_sticky_event(gst::event::Toc::new(&toc, false));
+                }
             }
         }
 
@@ -644,9 +871,18 @@ impl FlvDemux {
         srcpad.push_event(gst::event::StreamStart::new(&full_stream_id));
         srcpad.push_event(gst::event::Caps::new(caps));
 
-        // FIXME proper segment handling
-        let segment = gst::FormattedSegment::<gst::ClockTime>::default();
-        srcpad.push_event(gst::event::Segment::new(&segment));
+        let event = {
+            let segment = self.segment.lock().unwrap();
+            gst::event::Segment::builder(&segment.segment)
+                .seqnum(segment.seqnum)
+                .build()
+        };
+        srcpad.push_event(event);
+
+        let sticky_events = self.sticky_events.lock().unwrap().clone();
+        for event in sticky_events {
+            srcpad.push_event(event);
+        }
 
         self.flow_combiner.lock().unwrap().add_pad(&srcpad);
 
@@ -654,10 +890,24 @@ impl FlvDemux {
 
         srcpad
     }
+
+    /// Pushes a tag or TOC event on all source pads and remembers it for pads that are
+    /// added later, replacing the previous event of the same type.
+    fn push_sticky_event(&self, event: gst::Event) {
+        {
+            let mut sticky_events = self.sticky_events.lock().unwrap();
+            sticky_events.retain(|e| e.type_() != event.type_());
+            sticky_events.push(event.clone());
+        }
+
+        for pad in self.obj().src_pads() {
+            pad.push_event(event.clone());
+        }
+    }
 }
 
 impl StreamingState {
-    fn new(audio: bool, video: bool) -> StreamingState {
+    fn new(audio: bool, video: bool, offset: u64) -> StreamingState {
         StreamingState {
             audio: None,
             expect_audio: audio,
@@ -665,12 +915,102 @@ impl StreamingState {
             expect_video: video,
             got_all_streams: false,
             last_position: gst::ClockTime::NONE,
+            offset,
+            index: Vec::new(),
             metadata: None,
+            cue_points: Vec::new(),
             aac_sequence_header: None,
             avc_sequence_header: None,
         }
     }
 
+    /// Returns the seek table of the metadata if any, or the index built while streaming.
+    fn index(&self) -> &[IndexEntry] {
+        match self.metadata {
+            Some(Metadata { ref seek_table, .. }) if !seek_table.is_empty() => seek_table,
+            _ => &self.index,
+        }
+    }
+
+    fn find_keyframe(&self, time: gst::ClockTime, snap_after: bool) -> Option<IndexEntry> {
+        let index = self.index();
+
+        let idx = match index.binary_search_by_key(&time, |entry| entry.time) {
+            Ok(idx) => idx,
+            Err(idx) if snap_after && idx < index.len() => idx,
+            Err(idx) => idx.saturating_sub(1),
+        };
+
+        index.get(idx).copied()
+    }
+
+    fn add_index_entry(&mut self, imp: &FlvDemux, entry: IndexEntry, min_distance: gst::ClockTime) {
+        let idx = match self.index.binary_search_by_key(&entry.offset, |e| e.offset) {
+            Ok(_) => return,
+            Err(idx) => idx,
+        };
+
+        if idx > 0 && entry.time < self.index[idx - 1].time + min_distance {
+            return;
+        }
+
+        gst::trace!(CAT, imp: imp, "Adding index entry {:?}", entry);
+        self.index.insert(idx, entry);
+    }
+
+    fn add_cue_point(&mut self, cue_point: CuePoint) {
+        // Cue points from the metadata are usually repeated by onCuePoint script tags
+        if self.cue_points.contains(&cue_point) {
+            return;
+        }
+
+        let idx = self
+            .cue_points
+            .partition_point(|c| c.time <= cue_point.time);
+        self.cue_points.insert(idx, cue_point);
+    }
+
+    /// Creates a TOC with one chapter per cue point, each lasting until the next one.
+    fn toc(&self) -> Option<gst::Toc> {
+        if self.cue_points.is_empty() {
+            return None;
+        }
+
+        let duration = self.metadata.as_ref().and_then(|m| m.duration);
+
+        let mut toc = gst::Toc::new(gst::TocScope::Global);
+        {
+            let toc = toc.get_mut().unwrap();
+            for (idx, cue_point) in self.cue_points.iter().enumerate() {
+                let stop = self
+                    .cue_points
+                    .get(idx + 1)
+                    .map(|next| next.time)
+                    .or(duration);
+
+                let mut entry =
+                    gst::TocEntry::new(gst::TocEntryType::Chapter, &format!("cue{idx}"));
+                {
+                    let entry = entry.get_mut().unwrap();
+                    entry.set_start_stop_times(
+                        cue_point.time.nseconds() as i64,
+                        stop.map_or(-1, |stop| stop.nseconds() as i64),
+                    );
+
+                    let mut tags = gst::TagList::new();
+                    tags.get_mut().unwrap().add::<gst::tags::Title>(
+                        &cue_point.name.as_str(),
+                        gst::TagMergeMode::Replace,
+                    );
+                    entry.set_tags(tags);
+                }
+                toc.append_entry(entry);
+            }
+        }
+
+        Some(toc)
+    }
+
     fn handle_tag(
         &mut self,
         imp: &FlvDemux,
@@ -713,6 +1053,9 @@ impl StreamingState {
 
         adapter.flush(15);
 
+        let tag_offset = self.offset + 4;
+        self.offset += 15 + tag_header.data_size as u64;
+
         match tag_header.tag_type {
             flavors::TagType::Script => {
                 gst::trace!(CAT, imp: imp, "Found script tag");
@@ -722,12 +1065,12 @@ impl StreamingState {
             flavors::TagType::Audio => {
                 gst::trace!(CAT, imp: imp, "Found audio tag");
 
-                self.handle_audio_tag(imp, &tag_header, adapter)
+                self.handle_audio_tag(imp, &tag_header, tag_offset, adapter)
             }
             flavors::TagType::Video => {
                 gst::trace!(CAT, imp: imp, "Found video tag");
 
-                self.handle_video_tag(imp, &tag_header, adapter)
+                self.handle_video_tag(imp, &tag_header, tag_offset, adapter)
             }
         }
         .map(Option::Some)
@@ -762,7 +1105,15 @@ impl StreamingState {
                     .as_mut()
                     .map(|v| v.update_with_metadata(&metadata))
                     .unwrap_or(false);
+
+                events.push(Event::Tags(metadata.to_tags()));
+                for cue_point in &metadata.cue_points {
+                    self.add_cue_point(cue_point.clone());
+                }
                 self.metadata = Some(metadata);
+                if let Some(toc) = self.toc() {
+                    events.push(Event::Toc(toc));
+                }
 
                 if audio_changed || video_changed {
                     if audio_changed {
@@ -777,6 +1128,18 @@ impl StreamingState {
                     }
                 }
             }
+            Ok((_, ref script_data)) if script_data.name == "onCuePoint" => {
+                gst::trace!(CAT, imp: imp, "Got script tag: {:?}", script_data);
+
+                if let Some(cue_point) = CuePoint::new(&script_data.arguments) {
+                    gst::debug!(CAT, imp: imp, "Got cue point: {:?}", cue_point);
+
+                    self.add_cue_point(cue_point);
+                    if let Some(toc) = self.toc() {
+                        events.push(Event::Toc(toc));
+                    }
+                }
+            }
             Ok((_, ref script_data)) => {
                 gst::trace!(CAT, imp: imp, "Got script tag: {:?}", script_data);
             }
@@ -891,6 +1254,7 @@ impl StreamingState {
         &mut self,
         imp: &FlvDemux,
         tag_header: &flavors::TagHeader,
+        tag_offset: u64,
         adapter: &mut gst_base::UniqueAdapter,
     ) -> Result<SmallVec<[Event; 4]>, gst::ErrorMessage> {
         assert!(adapter.available() >= tag_header.data_size as usize);
@@ -932,6 +1296,15 @@ impl StreamingState {
             return Ok(events);
         }
 
+        // Every audio tag can be seeked to if there are no video keyframes
+        if !self.expect_video {
+            let entry = IndexEntry {
+                time: (tag_header.timestamp as u64).mseconds(),
+                offset: tag_offset,
+            };
+            self.add_index_entry(imp, entry, AUDIO_INDEX_INTERVAL);
+        }
+
         let mut buffer = adapter
             .take_buffer((tag_header.data_size - offset) as usize)
             .unwrap();
@@ -1064,6 +1437,7 @@ impl StreamingState {
         &mut self,
         imp: &FlvDemux,
         tag_header: &flavors::TagHeader,
+        tag_offset: u64,
         adapter: &mut gst_base::UniqueAdapter,
     ) -> Result<SmallVec<[Event; 4]>, gst::ErrorMessage> {
         assert!(adapter.available() >= tag_header.data_size as usize);
@@ -1112,6 +1486,14 @@ impl StreamingState {
 
         let is_keyframe = data_header.frame_type == flavors::FrameType::Key;
 
+        if is_keyframe {
+            let entry = IndexEntry {
+                time: (tag_header.timestamp as u64).mseconds(),
+                offset: tag_offset,
+            };
+            self.add_index_entry(imp, entry, gst::ClockTime::ZERO);
+        }
+
         let skip = match data_header.codec_id {
             flavors::CodecId::VP6 | flavors::CodecId::VP6A => 1,
             _ => 0,
@@ -1526,6 +1908,26 @@ impl Metadata {
                 ("videodatarate", &flavors::ScriptDataValue::Number(datarate)) => {
                     metadata.video_bitrate = Some((datarate * 1024.0) as u32);
                 }
+                ("keyframes", &flavors::ScriptDataValue::Object(ref keyframes))
+                | ("keyframes", &flavors::ScriptDataValue::ECMAArray(ref keyframes)) => {
+                    metadata.seek_table = Metadata::parse_seek_table(keyframes);
+                }
+                ("encoder", &flavors::ScriptDataValue::String(encoder)) => {
+                    metadata.encoder = Some(String::from(encoder));
+                }
+                ("cuePoints", &flavors::ScriptDataValue::StrictArray(ref cue_points)) => {
+                    metadata.cue_points = cue_points.iter().filter_map(CuePoint::new).collect();
+                }
+                (name, value) if !Metadata::KNOWN_FIELDS.contains(&name) => {
+                    let value = match *value {
+                        flavors::ScriptDataValue::Number(number) => number.to_string(),
+                        flavors::ScriptDataValue::Boolean(boolean) => boolean.to_string(),
+                        flavors::ScriptDataValue::String(string)
+                        | flavors::ScriptDataValue::LongString(string) => String::from(string),
+                        _ => continue,
+                    };
+                    metadata.custom.push((String::from(name), value));
+                }
                 _ => {}
             }
         }
@@ -1536,4 +1938,231 @@ impl Metadata {
 
         metadata
     }
+
+    /// Fields that describe the streams and are not exposed as tags.
+    const KNOWN_FIELDS: &'static [&'static str] = &[
+        "duration",
+        "filesize",
+        "width",
+        "height",
+        "framerate",
+        "AspectRatioX",
+        "AspectRatioY",
+        "videodatarate",
+        "videocodecid",
+        "audiodatarate",
+        "audiocodecid",
+        "audiosamplerate",
+        "audiosamplesize",
+        "audiodelay",
+        "stereo",
+        "hasVideo",
+        "hasAudio",
+        "hasMetadata",
+        "hasKeyframes",
+        "hasCuePoints",
+        "canSeekToEnd",
+        "datasize",
+        "videosize",
+        "audiosize",
+        "lasttimestamp",
+        "lastkeyframetimestamp",
+        "lastkeyframelocation",
+    ];
+
+    fn to_tags(&self) -> gst::TagList {
+        let mut tags = gst::TagList::new();
+        {
+            let tags = tags.get_mut().unwrap();
+            tags.set_scope(gst::TagScope::Global);
+
+            if let Some(ref title) = self.title {
+                tags.add::<gst::tags::Title>(&title.as_str(), gst::TagMergeMode::Replace);
+            }
+            if let Some(ref creator) = self.creator {
+                tags.add::<gst::tags::Artist>(&creator.as_str(), gst::TagMergeMode::Replace);
+            }
+            if let Some(ref encoder) = self.encoder {
+                tags.add::<gst::tags::Encoder>(&encoder.as_str(), gst::TagMergeMode::Replace);
+            }
+            if let Some(ref metadata_creator) = self.metadata_creator {
+                tags.add::<gst::tags::ApplicationName>(
+                    &metadata_creator.as_str(),
+                    gst::TagMergeMode::Replace,
+                );
+            }
+            if let Some(duration) = self.duration {
+                tags.add::<gst::tags::Duration>(&duration, gst::TagMergeMode::Replace);
+            }
+            if let Some(ref creation_date) = self.creation_date {
+                // Usually not ISO 8601 but e.g. "Mon Jan 01 12:00:00 2018"
+                match gst::DateTime::from_iso8601_string(creation_date) {
+                    Ok(date_time) => {
+                        tags.add::<gst::tags::DateTime>(&date_time, gst::TagMergeMode::Replace);
+                    }
+                    Err(_) => {
+                        tags.add::<gst::tags::ExtendedComment>(
+                            &format!("creationdate={creation_date}").as_str(),
+                            gst::TagMergeMode::Append,
+                        );
+                    }
+                }
+            }
+            for (name, value) in &self.custom {
+                tags.add::<gst::tags::ExtendedComment>(
+                    &format!("{name}={value}").as_str(),
+                    gst::TagMergeMode::Append,
+                );
+            }
+        }
+
+        tags
+    }
+
+    fn parse_seek_table(keyframes: &[flavors::ScriptDataObject]) -> Vec<IndexEntry> {
+        let mut times = None;
+        let mut positions = None;
+
+        for object in keyframes {
+            match (object.name, &object.data) {
+                ("times", &flavors::ScriptDataValue::StrictArray(ref values)) => {
+                    times = Some(values);
+                }
+                ("filepositions", &flavors::ScriptDataValue::StrictArray(ref values)) => {
+                    positions = Some(values);
+                }
+                _ => {}
+            }
+        }
+
+        let (Some(times), Some(positions)) = (times, positions) else {
+            return Vec::new();
+        };
+
+        let mut seek_table = times
+            .iter()
+            .zip(positions.iter())
+            .filter_map(|(time, position)| match (time, position) {
+                (
+                    &flavors::ScriptDataValue::Number(time),
+                    &flavors::ScriptDataValue::Number(position),
+                ) if time >= 0.0 && position >= 0.0 => Some(IndexEntry {
+                    time: ((time * 1000.0 * 1000.0 * 1000.0) as u64).nseconds(),
+                    offset: position as u64,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // Not all muxers write them in order
+        seek_table.sort_by_key(|entry| entry.time);
+        seek_table.dedup_by_key(|entry| entry.time);
+
+        seek_table
+    }
+}
+
+impl CuePoint {
+    fn new(value: &flavors::ScriptDataValue) -> Option<CuePoint> {
+        let objects = match *value {
+            flavors::ScriptDataValue::Object(ref objects)
+            | flavors::ScriptDataValue::ECMAArray(ref objects) => objects,
+            _ => return None,
+        };
+
+        let mut time = None;
+        let mut name = None;
+
+        for object in objects {
+            match (object.name, &object.data) {
+                // In seconds
+                ("time", &flavors::ScriptDataValue::Number(t)) if t >= 0.0 => {
+                    time = Some(((t * 1000.0 * 1000.0 * 1000.0) as u64).nseconds());
+                }
+                ("name", &flavors::ScriptDataValue::String(n)) => {
+                    name = Some(String::from(n));
+                }
+                _ => {}
+            }
+        }
+
+        Some(CuePoint {
+            time: time?,
+            name: name.unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(values: &[f64]) -> flavors::ScriptDataValue<'static> {
+        flavors::ScriptDataValue::StrictArray(
+            values
+                .iter()
+                .map(|value| flavors::ScriptDataValue::Number(*value))
+                .collect(),
+        )
+    }
+
+    fn entry(time_ms: u64, offset: u64) -> IndexEntry {
+        IndexEntry {
+            time: gst::ClockTime::from_mseconds(time_ms),
+            offset,
+        }
+    }
+
+    #[test]
+    fn test_parse_seek_table() {
+        let keyframes = [
+            flavors::ScriptDataObject {
+                name: "times",
+                data: numbers(&[0.0, 2.0, 1.0, 1.0, -1.0, 3.0]),
+            },
+            flavors::ScriptDataObject {
+                name: "filepositions",
+                data: numbers(&[13.0, 500.0, 300.0, 300.0, 20.0, -1.0]),
+            },
+        ];
+
+        // Sorted by time, without duplicates and negative values
+        assert_eq!(
+            Metadata::parse_seek_table(&keyframes),
+            [entry(0, 13), entry(1000, 300), entry(2000, 500)]
+        );
+
+        // Both arrays are needed
+        assert!(Metadata::parse_seek_table(&keyframes[..1]).is_empty());
+        assert!(Metadata::parse_seek_table(&keyframes[1..]).is_empty());
+    }
+
+    #[test]
+    fn test_find_keyframe() {
+        let mut sstate = StreamingState::new(false, true, 13);
+        assert_eq!(sstate.find_keyframe(gst::ClockTime::ZERO, false), None);
+
+        sstate.index = vec![entry(0, 13), entry(1000, 300), entry(2000, 500)];
+
+        let find = |sstate: &StreamingState, time_ms, snap_after| {
+            sstate.find_keyframe(gst::ClockTime::from_mseconds(time_ms), snap_after)
+        };
+
+        assert_eq!(find(&sstate, 0, false), Some(entry(0, 13)));
+        assert_eq!(find(&sstate, 1000, false), Some(entry(1000, 300)));
+        assert_eq!(find(&sstate, 1000, true), Some(entry(1000, 300)));
+        assert_eq!(find(&sstate, 1500, false), Some(entry(1000, 300)));
+        assert_eq!(find(&sstate, 1500, true), Some(entry(2000, 500)));
+        // Nothing to snap to after the last keyframe
+        assert_eq!(find(&sstate, 2500, false), Some(entry(2000, 500)));
+        assert_eq!(find(&sstate, 2500, true), Some(entry(2000, 500)));
+
+        // The seek table of the metadata is preferred
+        sstate.metadata = Some(Metadata {
+            seek_table: vec![entry(0, 13), entry(1500, 400)],
+            ..Default::default()
+        });
+        assert_eq!(find(&sstate, 1700, false), Some(entry(1500, 400)));
+        assert_eq!(find(&sstate, 1200, true), Some(entry(1500, 400)));
+    }
 }
@@ -0,0 +1,950 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use super::tags::{self, ScriptValue};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsflvmux",
+        gst::DebugColorFlags::empty(),
+        Some("Rust FLV muxer"),
+    )
+});
+
+const DEFAULT_STREAMABLE: bool = false;
+const DEFAULT_METADATA_CREATOR: &str = "GStreamer Rust FLV muxer";
+
+#[derive(Debug, Clone)]
+struct Settings {
+    streamable: bool,
+    metadata_creator: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            streamable: DEFAULT_STREAMABLE,
+            metadata_creator: DEFAULT_METADATA_CREATOR.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Sound format, rate, size and type of the audio tags
+    Audio(u8),
+    /// Codec id of the video tags
+    Video(u8),
+}
+
+impl Format {
+    /// Returns the format and the codec data for the caps, `None` if they can't be muxed.
+    fn from_caps(caps: &gst::CapsRef) -> Option<(Format, Option<gst::Buffer>)> {
+        let s = caps.structure(0)?;
+        let codec_data = s.get::<gst::Buffer>("codec_data").ok();
+
+        let format = match s.name().as_str() {
+            "video/x-h264" => Format::Video(tags::CODEC_ID_H264),
+            "video/x-flash-video" => Format::Video(tags::CODEC_ID_SORENSON_H263),
+            "video/x-flash-screen" => Format::Video(tags::CODEC_ID_SCREEN),
+            "video/x-vp6-flash" => Format::Video(tags::CODEC_ID_VP6),
+            "video/x-vp6-flash-alpha" => Format::Video(tags::CODEC_ID_VP6A),
+            "video/x-flash-screen2" => Format::Video(tags::CODEC_ID_SCREEN2),
+            name => {
+                let rate = s.get::<i32>("rate").ok();
+                let channels = s.get::<i32>("channels").unwrap_or(1);
+
+                let (sound_format, width_16bit) = match name {
+                    "audio/mpeg" => match s.get::<i32>("mpegversion").ok()? {
+                        1 if rate == Some(8_000) => (tags::SOUND_FORMAT_MP3_8KHZ, true),
+                        1 => (tags::SOUND_FORMAT_MP3, true),
+                        4 => (tags::SOUND_FORMAT_AAC, true),
+                        _ => return None,
+                    },
+                    "audio/x-raw" => match s.get::<&str>("format").ok()? {
+                        "U8" => (tags::SOUND_FORMAT_PCM_LE, false),
+                        "S16LE" => (tags::SOUND_FORMAT_PCM_LE, true),
+                        _ => return None,
+                    },
+                    "audio/x-adpcm" => (tags::SOUND_FORMAT_ADPCM, true),
+                    "audio/x-nellymoser" => match rate {
+                        Some(16_000) => (tags::SOUND_FORMAT_NELLYMOSER_16KHZ_MONO, true),
+                        Some(8_000) => (tags::SOUND_FORMAT_NELLYMOSER_8KHZ_MONO, true),
+                        _ => (tags::SOUND_FORMAT_NELLYMOSER, true),
+                    },
+                    "audio/x-alaw" => (tags::SOUND_FORMAT_PCM_ALAW, true),
+                    "audio/x-mulaw" => (tags::SOUND_FORMAT_PCM_ULAW, true),
+                    "audio/x-speex" => (tags::SOUND_FORMAT_SPEEX, true),
+                    _ => return None,
+                };
+
+                let rate_index = match (sound_format, rate) {
+                    // Always signalled as 44.1kHz, the decoder uses the AudioSpecificConfig
+                    (tags::SOUND_FORMAT_AAC, _) => 3,
+                    // Implied by the sound format
+                    (tags::SOUND_FORMAT_MP3_8KHZ, _)
+                    | (tags::SOUND_FORMAT_NELLYMOSER_16KHZ_MONO, _)
+                    | (tags::SOUND_FORMAT_NELLYMOSER_8KHZ_MONO, _)
+                    | (tags::SOUND_FORMAT_PCM_ALAW, _)
+                    | (tags::SOUND_FORMAT_PCM_ULAW, _)
+                    | (tags::SOUND_FORMAT_SPEEX, _) => 0,
+                    (_, Some(5_512)) => 0,
+                    (_, Some(11_025)) => 1,
+                    (_, Some(22_050)) => 2,
+                    (_, Some(44_100)) => 3,
+                    _ => return None,
+                };
+
+                let stereo = sound_format == tags::SOUND_FORMAT_AAC || channels == 2;
+
+                Format::Audio(
+                    sound_format << 4 | rate_index << 2 | (width_16bit as u8) << 1 | stereo as u8,
+                )
+            }
+        };
+
+        Some((format, codec_data))
+    }
+
+    fn is_aac(&self) -> bool {
+        matches!(self, Format::Audio(flags) if flags >> 4 == tags::SOUND_FORMAT_AAC)
+    }
+
+    fn is_h264(&self) -> bool {
+        *self == Format::Video(tags::CODEC_ID_H264)
+    }
+
+    fn tag_type(&self) -> u8 {
+        match self {
+            Format::Audio(_) => tags::TAG_TYPE_AUDIO,
+            Format::Video(_) => tags::TAG_TYPE_VIDEO,
+        }
+    }
+}
+
+struct Stream {
+    sinkpad: gst_base::AggregatorPad,
+    caps: gst::Caps,
+    format: Format,
+    /// AudioSpecificConfig for AAC, AVCDecoderConfigurationRecord for H.264
+    codec_data: Option<gst::Buffer>,
+}
+
+impl Stream {
+    fn new(sinkpad: gst_base::AggregatorPad, caps: gst::Caps) -> Result<Stream, gst::FlowError> {
+        let (format, codec_data) = Format::from_caps(&caps).ok_or_else(|| {
+            gst::error!(CAT, obj: sinkpad, "Unsupported caps {}", caps);
+            gst::FlowError::NotNegotiated
+        })?;
+
+        if (format.is_aac() || format.is_h264()) && codec_data.is_none() {
+            gst::error!(CAT, obj: sinkpad, "No codec_data in caps {}", caps);
+            return Err(gst::FlowError::NotNegotiated);
+        }
+
+        Ok(Stream {
+            sinkpad,
+            caps,
+            format,
+            codec_data,
+        })
+    }
+
+    /// Creates the AAC or H.264 sequence header tag, if any.
+    fn sequence_header(&self, timestamp: u32) -> Option<gst::Buffer> {
+        let codec_data = self.codec_data.clone()?;
+
+        let prefix = match self.format {
+            Format::Audio(flags) if self.format.is_aac() => {
+                vec![flags, tags::PACKET_TYPE_SEQUENCE_HEADER]
+            }
+            Format::Video(codec_id) if self.format.is_h264() => vec![
+                tags::FRAME_TYPE_KEY << 4 | codec_id,
+                tags::PACKET_TYPE_SEQUENCE_HEADER,
+                0,
+                0,
+                0,
+            ],
+            _ => return None,
+        };
+
+        Some(tags::tag(
+            self.format.tag_type(),
+            timestamp,
+            &prefix,
+            Some(codec_data),
+        ))
+    }
+
+    /// Creates the bytes in front of the buffer data in the tag.
+    fn tag_prefix(&self, keyframe: bool, composition_time: i32) -> Vec<u8> {
+        match self.format {
+            Format::Audio(flags) if self.format.is_aac() => vec![flags, tags::PACKET_TYPE_DATA],
+            Format::Audio(flags) => vec![flags],
+            Format::Video(codec_id) => {
+                let frame_type = if keyframe {
+                    tags::FRAME_TYPE_KEY
+                } else {
+                    tags::FRAME_TYPE_INTER
+                };
+                let header = frame_type << 4 | codec_id;
+
+                match codec_id {
+                    tags::CODEC_ID_H264 => {
+                        let cts = composition_time.to_be_bytes();
+                        vec![header, tags::PACKET_TYPE_DATA, cts[1], cts[2], cts[3]]
+                    }
+                    // No size adjustment
+                    tags::CODEC_ID_VP6 | tags::CODEC_ID_VP6A => vec![header, 0],
+                    _ => vec![header],
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    streams: Vec<Stream>,
+
+    /// Running time that is mapped to timestamp zero
+    start_time: Option<gst::Signed<gst::ClockTime>>,
+    /// End of the latest buffer relative to the start time
+    end_time: Option<gst::ClockTime>,
+
+    current_offset: u64,
+
+    title: Option<String>,
+    creator: Option<String>,
+
+    /// Values of the onMetaData script data
+    metadata: Vec<(&'static str, ScriptValue)>,
+    /// Offset of the onMetaData tag, if it is rewritten at EOS
+    metadata_offset: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct FlvMux {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl FlvMux {
+    fn create_streams(&self, state: &mut State) -> Result<(), gst::FlowError> {
+        for pad in self.obj().sink_pads() {
+            let pad = pad.downcast::<gst_base::AggregatorPad>().unwrap();
+
+            // The caps of all streams are known once they have a buffer
+            if pad.peek_buffer().is_none() && !pad.is_eos() {
+                gst::trace!(CAT, obj: pad, "Waiting for data");
+                state.streams.clear();
+                return Err(gst_base::AGGREGATOR_FLOW_NEED_DATA);
+            }
+
+            let Some(caps) = pad.current_caps() else {
+                gst::warning!(CAT, obj: pad, "Skipping stream without caps");
+                continue;
+            };
+
+            gst::info!(CAT, obj: pad, "Creating stream for caps {}", caps);
+
+            state.streams.push(Stream::new(pad, caps)?);
+        }
+
+        if state.streams.is_empty() {
+            gst::element_imp_error!(self, gst::CoreError::Negotiation, ["No streams available"]);
+            return Err(gst::FlowError::Error);
+        }
+
+        Ok(())
+    }
+
+    fn metadata(&self, settings: &Settings, state: &State) -> Vec<(&'static str, ScriptValue)> {
+        let mut metadata = Vec::new();
+
+        // Only known at EOS
+        if !settings.streamable {
+            metadata.push(("duration", ScriptValue::Number(0.0)));
+            metadata.push(("filesize", ScriptValue::Number(0.0)));
+        }
+
+        for stream in &state.streams {
+            let s = stream.caps.structure(0).unwrap();
+
+            match stream.format {
+                Format::Video(codec_id) => {
+                    metadata.push(("videocodecid", ScriptValue::Number(codec_id.into())));
+
+                    if let (Ok(width), Ok(height)) = (s.get::<i32>("width"), s.get::<i32>("height"))
+                    {
+                        metadata.push(("width", ScriptValue::Number(width.into())));
+                        metadata.push(("height", ScriptValue::Number(height.into())));
+                    }
+
+                    if let Ok(framerate) = s.get::<gst::Fraction>("framerate") {
+                        if framerate.numer() > 0 && framerate.denom() > 0 {
+                            metadata.push((
+                                "framerate",
+                                ScriptValue::Number(
+                                    framerate.numer() as f64 / framerate.denom() as f64,
+                                ),
+                            ));
+                        }
+                    }
+
+                    if let Ok(par) = s.get::<gst::Fraction>("pixel-aspect-ratio") {
+                        if par.numer() > 0 && par.denom() > 0 {
+                            metadata
+                                .push(("AspectRatioX", ScriptValue::Number(par.numer().into())));
+                            metadata
+                                .push(("AspectRatioY", ScriptValue::Number(par.denom().into())));
+                        }
+                    }
+                }
+                Format::Audio(flags) => {
+                    metadata.push(("audiocodecid", ScriptValue::Number((flags >> 4).into())));
+
+                    if let Ok(rate) = s.get::<i32>("rate") {
+                        metadata.push(("audiosamplerate", ScriptValue::Number(rate.into())));
+                    }
+
+                    let width = if flags & 0x02 != 0 { 16.0 } else { 8.0 };
+                    metadata.push(("audiosamplesize", ScriptValue::Number(width)));
+                    metadata.push(("stereo", ScriptValue::Boolean(flags & 0x01 != 0)));
+                }
+            }
+        }
+
+        if let Some(ref title) = state.title {
+            metadata.push(("title", ScriptValue::String(title.clone())));
+        }
+
+        if let Some(ref creator) = state.creator {
+            metadata.push(("creator", ScriptValue::String(creator.clone())));
+        }
+
+        metadata.push((
+            "metadatacreator",
+            ScriptValue::String(settings.metadata_creator.clone()),
+        ));
+
+        if let Ok(date) = glib::DateTime::now_utc().and_then(|date| date.format_iso8601()) {
+            metadata.push(("creationdate", ScriptValue::String(date.to_string())));
+        }
+
+        metadata
+    }
+
+    /// Creates the FLV header, the onMetaData tag and the sequence headers.
+    fn create_headers(
+        &self,
+        settings: &Settings,
+        state: &mut State,
+        seekable: bool,
+    ) -> Vec<gst::Buffer> {
+        let audio = state
+            .streams
+            .iter()
+            .any(|stream| matches!(stream.format, Format::Audio(_)));
+        let video = state
+            .streams
+            .iter()
+            .any(|stream| matches!(stream.format, Format::Video(_)));
+
+        let mut headers = vec![tags::header(audio, video)];
+        state.current_offset = headers[0].size() as u64;
+
+        state.metadata = self.metadata(settings, state);
+        gst::debug!(CAT, imp: self, "Creating metadata {:?}", state.metadata);
+
+        let metadata = tags::tag(
+            tags::TAG_TYPE_SCRIPT,
+            0,
+            &tags::on_metadata(&state.metadata),
+            None,
+        );
+        state.metadata_offset = (!settings.streamable && seekable).then_some(state.current_offset);
+        state.current_offset += metadata.size() as u64;
+        headers.push(metadata);
+
+        for stream in &state.streams {
+            if let Some(sequence_header) = stream.sequence_header(0) {
+                state.current_offset += sequence_header.size() as u64;
+                headers.push(sequence_header);
+            }
+        }
+
+        for header in &mut headers {
+            header.make_mut().set_flags(gst::BufferFlags::HEADER);
+        }
+
+        headers
+    }
+
+    /// Returns the index of the stream with the earliest buffer, and its DTS or PTS running time.
+    fn find_earliest_stream(
+        &self,
+        state: &State,
+    ) -> Result<(usize, gst::Signed<gst::ClockTime>), gst::FlowError> {
+        let mut earliest = None;
+        let mut all_eos = true;
+
+        for (idx, stream) in state.streams.iter().enumerate() {
+            let Some(buffer) = stream.sinkpad.peek_buffer() else {
+                if stream.sinkpad.is_eos() {
+                    continue;
+                }
+
+                gst::trace!(CAT, obj: stream.sinkpad, "Waiting for data");
+                return Err(gst_base::AGGREGATOR_FLOW_NEED_DATA);
+            };
+            all_eos = false;
+
+            let Some(timestamp) = buffer.dts_or_pts() else {
+                gst::error!(CAT, obj: stream.sinkpad, "Require timestamped buffers");
+                return Err(gst::FlowError::Error);
+            };
+
+            let segment = stream
+                .sinkpad
+                .segment()
+                .downcast::<gst::ClockTime>()
+                .map_err(|_| {
+                    gst::error!(CAT, obj: stream.sinkpad, "Got buffer before segment");
+                    gst::FlowError::Error
+                })?;
+            let running_time = segment.to_running_time_full(timestamp).unwrap();
+
+            if earliest.map_or(true, |(_, earliest_time)| running_time < earliest_time) {
+                earliest = Some((idx, running_time));
+            }
+        }
+
+        if all_eos {
+            gst::info!(CAT, imp: self, "All streams are EOS");
+            return Err(gst::FlowError::Eos);
+        }
+
+        Ok(earliest.unwrap())
+    }
+
+    /// Creates the tags for the next buffer of the stream.
+    fn mux_buffer(
+        &self,
+        state: &mut State,
+        idx: usize,
+        running_time: gst::Signed<gst::ClockTime>,
+        buffers: &mut gst::BufferListRef,
+    ) -> Result<(), gst::FlowError> {
+        let start_time = *state.start_time.get_or_insert(running_time);
+        let has_video = state
+            .streams
+            .iter()
+            .any(|stream| matches!(stream.format, Format::Video(_)));
+
+        let stream = &mut state.streams[idx];
+        let buffer = stream.sinkpad.pop_buffer().unwrap();
+        let segment = stream
+            .sinkpad
+            .segment()
+            .downcast::<gst::ClockTime>()
+            .unwrap();
+
+        let timestamp = running_time
+            .checked_sub(start_time)
+            .and_then(|timestamp| timestamp.positive())
+            .unwrap_or(gst::ClockTime::ZERO);
+        // Wraps around after ~49 days like in any other FLV stream
+        let timestamp_ms = timestamp.mseconds() as u32;
+
+        if let Some(caps) = stream.sinkpad.current_caps() {
+            if caps != stream.caps {
+                gst::debug!(CAT, obj: stream.sinkpad, "Caps changed to {}", caps);
+
+                let sinkpad = stream.sinkpad.clone();
+                let new_stream = Stream::new(sinkpad, caps)?;
+                let codec_data_changed = new_stream.codec_data != stream.codec_data;
+                *stream = new_stream;
+
+                if codec_data_changed {
+                    if let Some(sequence_header) = stream.sequence_header(timestamp_ms) {
+                        state.current_offset += sequence_header.size() as u64;
+                        buffers.add(sequence_header);
+                    }
+                }
+            }
+        }
+
+        let pts = buffer
+            .pts()
+            .and_then(|pts| segment.to_running_time_full(pts))
+            .unwrap_or(running_time);
+        let composition_time = match pts.checked_sub(running_time) {
+            Some(gst::Signed::Positive(cts)) => cts.mseconds().min(0x7f_ff_ff) as i32,
+            Some(gst::Signed::Negative(cts)) => -(cts.mseconds().min(0x80_00_00) as i32),
+            None => 0,
+        };
+
+        let keyframe = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+        let prefix = stream.tag_prefix(keyframe, composition_time);
+
+        if prefix.len() + buffer.size() > tags::MAX_DATA_SIZE {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Mux,
+                ["Buffer of {} bytes is too big for a tag", buffer.size()]
+            );
+            return Err(gst::FlowError::Error);
+        }
+
+        gst::trace!(
+            CAT,
+            obj: stream.sinkpad,
+            "Muxing buffer {:?} at timestamp {} ms, composition time {} ms",
+            buffer,
+            timestamp_ms,
+            composition_time
+        );
+
+        let end_time = pts
+            .checked_sub(start_time)
+            .and_then(|pts| pts.positive())
+            .unwrap_or(timestamp)
+            + buffer.duration().unwrap_or(gst::ClockTime::ZERO);
+        state.end_time = state.end_time.opt_max(end_time).or(Some(end_time));
+
+        let mut tag = tags::tag(
+            stream.format.tag_type(),
+            timestamp_ms,
+            &prefix,
+            Some(buffer),
+        );
+        {
+            let tag = tag.get_mut().unwrap();
+            tag.set_pts(timestamp);
+            tag.set_dts(timestamp);
+            // Only video keyframes are suitable starting points for new clients
+            if has_video && (!keyframe || matches!(stream.format, Format::Audio(_))) {
+                tag.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+
+        state.current_offset += tag.size() as u64;
+        buffers.add(tag);
+
+        Ok(())
+    }
+
+    /// Creates the onMetaData tag with the final duration and file size.
+    fn finish_metadata(&self, state: &mut State) -> Option<(u64, gst::Buffer)> {
+        let offset = state.metadata_offset?;
+
+        let duration = state.end_time.unwrap_or(gst::ClockTime::ZERO);
+        let filesize = state.current_offset;
+
+        gst::info!(
+            CAT,
+            imp: self,
+            "Updating metadata with duration {} and file size {}",
+            duration,
+            filesize
+        );
+
+        for (name, value) in &mut state.metadata {
+            match *name {
+                "duration" => {
+                    *value = ScriptValue::Number(duration.nseconds() as f64 / 1_000_000_000.0)
+                }
+                "filesize" => *value = ScriptValue::Number(filesize as f64),
+                _ => (),
+            }
+        }
+
+        let metadata = tags::tag(
+            tags::TAG_TYPE_SCRIPT,
+            0,
+            &tags::on_metadata(&state.metadata),
+            None,
+        );
+
+        Some((offset, metadata))
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FlvMux {
+    const NAME: &'static str = "GstRsFlvMux";
+    type Type = super::FlvMux;
+    type ParentType = gst_base::Aggregator;
+}
+
+impl ObjectImpl for FlvMux {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecBoolean::builder("streamable")
+                    .nick("Streamable")
+                    .blurb("Don't write the duration and file size, and don't rewrite the metadata at EOS")
+                    .default_value(DEFAULT_STREAMABLE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("metadatacreator")
+                    .nick("Metadata creator")
+                    .blurb("Value of the metadatacreator field in the metadata")
+                    .default_value(Some(DEFAULT_METADATA_CREATOR))
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "streamable" => {
+                settings.streamable = value.get().expect("type checked upstream");
+            }
+            "metadatacreator" => {
+                settings.metadata_creator = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_default();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "streamable" => settings.streamable.to_value(),
+            "metadatacreator" => settings.metadata_creator.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for FlvMux {}
+
+impl ElementImpl for FlvMux {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "FLV Muxer",
+                "Codec/Muxer",
+                "Muxes audio and video into an FLV stream",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("video/x-flv").build(),
+            )
+            .unwrap();
+
+            let caps = [
+                gst::Structure::builder("audio/mpeg")
+                    .field("mpegversion", 1i32)
+                    .field("layer", 3i32)
+                    .field(
+                        "rate",
+                        gst::List::new([5_512i32, 8_000, 11_025, 22_050, 44_100]),
+                    )
+                    .field("channels", gst::IntRange::new(1i32, 2))
+                    .build(),
+                gst::Structure::builder("audio/mpeg")
+                    .field("mpegversion", 4i32)
+                    .field("framed", true)
+                    .field("stream-format", "raw")
+                    .build(),
+                gst::Structure::builder("audio/x-raw")
+                    .field("format", gst::List::new(["U8", "S16LE"]))
+                    .field("layout", "interleaved")
+                    .field("rate", gst::List::new([5_512i32, 11_025, 22_050, 44_100]))
+                    .field("channels", gst::IntRange::new(1i32, 2))
+                    .build(),
+                gst::Structure::builder("audio/x-adpcm")
+                    .field("layout", "swf")
+                    .field("rate", gst::List::new([5_512i32, 11_025, 22_050, 44_100]))
+                    .field("channels", gst::IntRange::new(1i32, 2))
+                    .build(),
+                gst::Structure::builder("audio/x-nellymoser")
+                    .field(
+                        "rate",
+                        gst::List::new([5_512i32, 8_000, 11_025, 16_000, 22_050, 44_100]),
+                    )
+                    .field("channels", 1i32)
+                    .build(),
+                gst::Structure::builder("audio/x-alaw")
+                    .field("rate", 8_000i32)
+                    .field("channels", 1i32)
+                    .build(),
+                gst::Structure::builder("audio/x-mulaw")
+                    .field("rate", 8_000i32)
+                    .field("channels", 1i32)
+                    .build(),
+                gst::Structure::builder("audio/x-speex")
+                    .field("rate", 16_000i32)
+                    .field("channels", 1i32)
+                    .build(),
+            ]
+            .into_iter()
+            .collect::<gst::Caps>();
+            let audio_pad_template = gst::PadTemplate::new(
+                "audio",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Request,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = [
+                gst::Structure::builder("video/x-h264")
+                    .field("stream-format", "avc")
+                    .field("alignment", "au")
+                    .build(),
+                gst::Structure::builder("video/x-flash-video")
+                    .field("flvversion", 1i32)
+                    .build(),
+                gst::Structure::new_empty("video/x-flash-screen"),
+                gst::Structure::new_empty("video/x-vp6-flash"),
+                gst::Structure::new_empty("video/x-vp6-flash-alpha"),
+                gst::Structure::new_empty("video/x-flash-screen2"),
+            ]
+            .into_iter()
+            .collect::<gst::Caps>();
+            let video_pad_template = gst::PadTemplate::new(
+                "video",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Request,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, audio_pad_template, video_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl AggregatorImpl for FlvMux {
+    fn create_new_pad(
+        &self,
+        templ: &gst::PadTemplate,
+        _req_name: Option<&str>,
+        _caps: Option<&gst::Caps>,
+    ) -> Option<gst_base::AggregatorPad> {
+        if !self.state.lock().unwrap().streams.is_empty() {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Can't request new pads after stream was started"
+            );
+            return None;
+        }
+
+        // At most one audio and one video stream
+        let name = templ.name_template();
+        if self.obj().static_pad(&name).is_some() {
+            gst::error!(CAT, imp: self, "Already have an {} pad", name);
+            return None;
+        }
+
+        Some(gst::PadBuilder::<gst_base::AggregatorPad>::from_template(templ).build())
+    }
+
+    fn next_time(&self) -> Option<gst::ClockTime> {
+        None
+    }
+
+    fn sink_event(&self, aggregator_pad: &gst_base::AggregatorPad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::trace!(CAT, obj: aggregator_pad, "Handling event {event:?}");
+
+        if let EventView::Tag(ev) = event.view() {
+            let tags = ev.tag();
+            let mut state = self.state.lock().unwrap();
+
+            if let Some(title) = tags.get::<gst::tags::Title>() {
+                state.title = Some(title.get().to_string());
+            }
+            if let Some(artist) = tags.get::<gst::tags::Artist>() {
+                state.creator = Some(artist.get().to_string());
+            }
+        }
+
+        self.parent_sink_event(aggregator_pad, event)
+    }
+
+    fn src_query(&self, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::trace!(CAT, imp: self, "Handling query {query:?}");
+
+        match query.view_mut() {
+            QueryViewMut::Seeking(q) => {
+                q.set(false, gst::ClockTime::ZERO, gst::ClockTime::NONE);
+                true
+            }
+            _ => self.parent_src_query(query),
+        }
+    }
+
+    fn src_event(&self, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::trace!(CAT, imp: self, "Handling event {event:?}");
+
+        match event.view() {
+            EventView::Seek(_ev) => false,
+            _ => self.parent_src_event(event),
+        }
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        gst::trace!(CAT, imp: self, "Stopping");
+
+        let _ = self.parent_stop();
+
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        gst::trace!(CAT, imp: self, "Starting");
+
+        self.parent_start()?;
+
+        // Always output a BYTES segment
+        let segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+        self.obj().update_segment(&segment);
+
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+
+    fn negotiate(&self) -> bool {
+        true
+    }
+
+    fn aggregate(&self, _timeout: bool) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+
+        let mut buffers = gst::BufferList::new();
+        let mut caps = None;
+
+        // If no streams were created yet, create them now and write the headers
+        if state.streams.is_empty() {
+            // The metadata can only be updated at EOS if downstream is seekable
+            drop(state);
+
+            let mut seekable = false;
+            if !settings.streamable {
+                let mut q = gst::query::Seeking::new(gst::Format::Bytes);
+                seekable = self.obj().src_pad().peer_query(&mut q) && q.result().0;
+                if !seekable {
+                    gst::warning!(
+                        CAT,
+                        imp: self,
+                        "Downstream is not seekable, can't update the metadata at EOS"
+                    );
+                }
+            }
+
+            state = self.state.lock().unwrap();
+            self.create_streams(&mut state)?;
+
+            let headers = self.create_headers(&settings, &mut state, seekable);
+            caps = Some(
+                gst::Caps::builder("video/x-flv")
+                    .field("streamheader", gst::Array::new(headers.iter().cloned()))
+                    .build(),
+            );
+
+            let buffers = buffers.get_mut().unwrap();
+            for header in headers {
+                buffers.add(header);
+            }
+        }
+
+        let res = match self
+            .find_earliest_stream(&state)
+            .and_then(|(idx, running_time)| {
+                self.mux_buffer(&mut state, idx, running_time, buffers.get_mut().unwrap())
+            }) {
+            Ok(_) => Ok(gst::FlowSuccess::Ok),
+            Err(err @ gst::FlowError::Eos) | Err(err @ gst_base::AGGREGATOR_FLOW_NEED_DATA) => {
+                Err(err)
+            }
+            Err(err) => return Err(err),
+        };
+
+        let metadata = if res == Err(gst::FlowError::Eos) {
+            self.finish_metadata(&mut state)
+        } else {
+            None
+        };
+
+        drop(state);
+
+        if let Some(ref caps) = caps {
+            self.obj().set_src_caps(caps);
+        }
+
+        if !buffers.is_empty() {
+            if let Err(err) = self.obj().finish_buffer_list(buffers) {
+                gst::error!(CAT, imp: self, "Failed pushing buffers: {err:?}");
+                return Err(err);
+            }
+        }
+
+        if let Some((offset, metadata)) = metadata {
+            gst::info!(CAT, imp: self, "Rewriting metadata at offset {offset}");
+
+            let mut segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+            segment.set_start(gst::format::Bytes::from_u64(offset));
+            self.obj().update_segment(&segment);
+
+            if let Err(err) = self.obj().finish_buffer(metadata) {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Failed pushing updated metadata downstream: {err:?}",
+                );
+            }
+        }
+
+        res
+    }
+}
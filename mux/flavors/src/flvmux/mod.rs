@@ -0,0 +1,53 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/**
+ * element-rsflvmux:
+ *
+ * `rsflvmux` muxes at most one audio and one video stream into FLV. Next to
+ * H.264 and AAC, all the legacy FLV codecs that `rsflvdemux` handles are
+ * supported.
+ *
+ * The stream properties, and the title and artist from upstream tags, are
+ * written into the `onMetaData` script data at the start. Unless `streamable`
+ * is set, it also contains the duration and file size, which are filled in at
+ * EOS if downstream is seekable.
+ *
+ * The caps of the source pad contain the FLV header, the metadata and the
+ * codec sequence headers as `streamheader`, and all buffers apart from the
+ * video keyframes are marked as delta units, so that the output can be served
+ * to clients joining at any time.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 videotestsrc num-buffers=300 ! x264enc ! h264parse ! mux.video \
+ *     audiotestsrc num-buffers=440 ! avenc_aac ! aacparse ! mux.audio \
+ *     rsflvmux name=mux ! filesink location=test.flv
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod tags;
+
+glib::wrapper! {
+    pub struct FlvMux(ObjectSubclass<imp::FlvMux>) @extends gst_base::Aggregator, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsflvmux",
+        gst::Rank::NONE,
+        FlvMux::static_type(),
+    )
+}
@@ -0,0 +1,152 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Writing of the FLV header, tags and the `onMetaData` script data.
+
+use crate::bytes::*;
+use std::io::Write;
+
+pub const TAG_TYPE_AUDIO: u8 = 8;
+pub const TAG_TYPE_VIDEO: u8 = 9;
+pub const TAG_TYPE_SCRIPT: u8 = 18;
+
+pub const SOUND_FORMAT_PCM_NE: u8 = 0;
+pub const SOUND_FORMAT_ADPCM: u8 = 1;
+pub const SOUND_FORMAT_MP3: u8 = 2;
+pub const SOUND_FORMAT_PCM_LE: u8 = 3;
+pub const SOUND_FORMAT_NELLYMOSER_16KHZ_MONO: u8 = 4;
+pub const SOUND_FORMAT_NELLYMOSER_8KHZ_MONO: u8 = 5;
+pub const SOUND_FORMAT_NELLYMOSER: u8 = 6;
+pub const SOUND_FORMAT_PCM_ALAW: u8 = 7;
+pub const SOUND_FORMAT_PCM_ULAW: u8 = 8;
+pub const SOUND_FORMAT_AAC: u8 = 10;
+pub const SOUND_FORMAT_SPEEX: u8 = 11;
+pub const SOUND_FORMAT_MP3_8KHZ: u8 = 14;
+
+pub const CODEC_ID_SORENSON_H263: u8 = 2;
+pub const CODEC_ID_SCREEN: u8 = 3;
+pub const CODEC_ID_VP6: u8 = 4;
+pub const CODEC_ID_VP6A: u8 = 5;
+pub const CODEC_ID_SCREEN2: u8 = 6;
+pub const CODEC_ID_H264: u8 = 7;
+
+pub const FRAME_TYPE_KEY: u8 = 1;
+pub const FRAME_TYPE_INTER: u8 = 2;
+
+pub const PACKET_TYPE_SEQUENCE_HEADER: u8 = 0;
+pub const PACKET_TYPE_DATA: u8 = 1;
+
+/// Size of the tag header in front of the tag data.
+pub const TAG_HEADER_SIZE: usize = 11;
+/// Maximum size of the tag data.
+pub const MAX_DATA_SIZE: usize = 0xff_ff_ff;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptValue {
+    Number(f64),
+    Boolean(bool),
+    String(String),
+}
+
+/// Creates the FLV header, including the first previous tag size.
+pub fn header(audio: bool, video: bool) -> gst::Buffer {
+    let mut data = Vec::with_capacity(9 + 4);
+
+    data.write_all(b"FLV").unwrap();
+    data.write_u8(1).unwrap(); // version
+    data.write_u8(if audio { 0x04 } else { 0 } | if video { 0x01 } else { 0 })
+        .unwrap();
+    data.write_u32be(9).unwrap(); // data offset
+    data.write_u32be(0).unwrap(); // previous tag size
+
+    gst::Buffer::from_mut_slice(data)
+}
+
+/// Creates a tag from the codec specific `prefix` and the `payload`, followed by the previous tag
+/// size. The size of the data must not exceed `MAX_DATA_SIZE`.
+pub fn tag(
+    tag_type: u8,
+    timestamp: u32,
+    prefix: &[u8],
+    payload: Option<gst::Buffer>,
+) -> gst::Buffer {
+    let data_size = prefix.len() + payload.as_ref().map_or(0, |payload| payload.size());
+    assert!(data_size <= MAX_DATA_SIZE);
+
+    let mut data = Vec::with_capacity(TAG_HEADER_SIZE + prefix.len());
+    data.write_u8(tag_type).unwrap();
+    data.write_uintbe(data_size as u64, 3).unwrap();
+    // Lower 24 bits, followed by the upper 8 bits
+    data.write_uintbe((timestamp & 0xff_ff_ff) as u64, 3)
+        .unwrap();
+    data.write_u8((timestamp >> 24) as u8).unwrap();
+    data.write_uintbe(0, 3).unwrap(); // stream id
+    data.write_all(prefix).unwrap();
+
+    let mut buffer = gst::Buffer::from_mut_slice(data);
+    if let Some(payload) = payload {
+        buffer = buffer.append(payload);
+    }
+
+    let previous_tag_size = ((TAG_HEADER_SIZE + data_size) as u32).to_be_bytes();
+    buffer.append(gst::Buffer::from_slice(previous_tag_size))
+}
+
+/// Creates the data of the `onMetaData` script tag.
+///
+/// The size only depends on the names and the types and sizes of the values, which allows
+/// rewriting the numbers later.
+pub fn on_metadata(values: &[(&str, ScriptValue)]) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    write_script_string(&mut data, "onMetaData");
+
+    // ECMA array
+    data.write_u8(8).unwrap();
+    data.write_u32be(values.len() as u32).unwrap();
+    for (name, value) in values {
+        write_script_name(&mut data, name);
+
+        match value {
+            ScriptValue::Number(number) => {
+                data.write_u8(0).unwrap();
+                data.write_f64be(*number).unwrap();
+            }
+            ScriptValue::Boolean(boolean) => {
+                data.write_u8(1).unwrap();
+                data.write_u8(*boolean as u8).unwrap();
+            }
+            ScriptValue::String(string) => {
+                write_script_string(&mut data, string);
+            }
+        }
+    }
+
+    // Object end marker
+    data.write_uintbe(9, 3).unwrap();
+
+    data
+}
+
+fn write_script_name(data: &mut Vec<u8>, name: &str) {
+    let name = &name.as_bytes()[..name.len().min(u16::MAX as usize)];
+    data.write_u16be(name.len() as u16).unwrap();
+    data.write_all(name).unwrap();
+}
+
+fn write_script_string(data: &mut Vec<u8>, string: &str) {
+    if string.len() <= u16::MAX as usize {
+        data.write_u8(2).unwrap();
+        data.write_u16be(string.len() as u16).unwrap();
+    } else {
+        // Long string
+        data.write_u8(12).unwrap();
+        data.write_u32be(string.len() as u32).unwrap();
+    }
+    data.write_all(string.as_bytes()).unwrap();
+}
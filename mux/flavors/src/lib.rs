@@ -18,9 +18,11 @@ use gst::glib;
 
 mod bytes;
 mod flvdemux;
+mod flvmux;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
-    flvdemux::register(plugin)
+    flvdemux::register(plugin)?;
+    flvmux::register(plugin)
 }
 
 gst::plugin_define!(
@@ -0,0 +1,349 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+use std::sync::{Arc, Mutex};
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsflv::plugin_register_static().expect("flv test");
+    });
+}
+
+#[derive(Debug)]
+struct Tag {
+    tag_type: u8,
+    timestamp: u32,
+    data: Vec<u8>,
+}
+
+/// Parses the FLV header flags and all tags of the stream.
+fn parse(data: &[u8]) -> (u8, Vec<Tag>) {
+    assert_eq!(&data[0..3], b"FLV");
+    assert_eq!(&data[5..13], &[0, 0, 0, 9, 0, 0, 0, 0]);
+    let flags = data[4];
+
+    let mut tags = Vec::new();
+    let mut data = &data[13..];
+    while !data.is_empty() {
+        let tag_type = data[0];
+        let size = u32::from_be_bytes([0, data[1], data[2], data[3]]) as usize;
+        let timestamp = u32::from_be_bytes([data[7], data[4], data[5], data[6]]);
+        let previous_size = u32::from_be_bytes(data[11 + size..15 + size].try_into().unwrap());
+        assert_eq!(previous_size as usize, 11 + size);
+
+        tags.push(Tag {
+            tag_type,
+            timestamp,
+            data: data[11..11 + size].to_vec(),
+        });
+        data = &data[15 + size..];
+    }
+
+    (flags, tags)
+}
+
+fn pull(h: &mut gst_check::Harness, num_buffers: usize) -> Vec<u8> {
+    let mut data = Vec::new();
+    for _ in 0..num_buffers {
+        let buffer = h.pull().unwrap();
+        data.extend_from_slice(&buffer.map_readable().unwrap());
+    }
+    assert!(h.try_pull().is_none());
+
+    data
+}
+
+#[test]
+fn test_h264() {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("rsflvmux", Some("video"), Some("src"));
+    h.set_src_caps(
+        gst::Caps::builder("video/x-h264")
+            .field("stream-format", "avc")
+            .field("alignment", "au")
+            .field("width", 320i32)
+            .field("height", 240i32)
+            .field("framerate", gst::Fraction::new(25, 1))
+            .field("codec_data", gst::Buffer::from_slice([1u8, 2, 3, 4]))
+            .build(),
+    );
+    h.play();
+
+    for i in 0..3u64 {
+        let mut buffer = gst::Buffer::from_slice(vec![i as u8; 10]);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(i * 40 + 80));
+            buffer.set_dts(gst::ClockTime::from_mseconds(i * 40));
+            buffer.set_duration(gst::ClockTime::from_mseconds(40));
+            if i > 0 {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // Header, metadata and sequence header, then the frames
+    let (flags, tags) = parse(&pull(&mut h, 6));
+
+    let caps = h.sinkpad().unwrap().current_caps().unwrap();
+    let s = caps.structure(0).unwrap();
+    assert_eq!(s.name(), "video/x-flv");
+    assert_eq!(s.get::<gst::Array>("streamheader").unwrap().len(), 3);
+
+    assert_eq!(flags, 0x01);
+    assert_eq!(tags.len(), 5);
+
+    assert_eq!(tags[0].tag_type, 18);
+    assert!(tags[0]
+        .data
+        .windows(b"onMetaData".len())
+        .any(|w| w == b"onMetaData"));
+
+    // Sequence header
+    assert_eq!(tags[1].tag_type, 9);
+    assert_eq!(tags[1].data, [0x17, 0, 0, 0, 0, 1, 2, 3, 4]);
+
+    for (i, tag) in tags[2..].iter().enumerate() {
+        assert_eq!(tag.tag_type, 9);
+        assert_eq!(tag.timestamp, i as u32 * 40);
+        assert_eq!(tag.data[0], if i == 0 { 0x17 } else { 0x27 });
+        assert_eq!(tag.data[1], 1);
+        // Composition time of 80ms
+        assert_eq!(&tag.data[2..5], &[0, 0, 80]);
+        assert_eq!(&tag.data[5..], &[i as u8; 10]);
+    }
+}
+
+#[test]
+fn test_mp3() {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("rsflvmux", Some("audio"), Some("src"));
+    h.element().unwrap().set_property("streamable", true);
+    h.set_src_caps(
+        gst::Caps::builder("audio/mpeg")
+            .field("mpegversion", 1i32)
+            .field("layer", 3i32)
+            .field("rate", 44_100i32)
+            .field("channels", 2i32)
+            .build(),
+    );
+    h.play();
+
+    for i in 0..2u64 {
+        let mut buffer = gst::Buffer::from_slice(vec![i as u8; 4]);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(1000 + i * 26));
+            buffer.set_duration(gst::ClockTime::from_mseconds(26));
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let (flags, tags) = parse(&pull(&mut h, 4));
+    assert_eq!(flags, 0x04);
+    assert_eq!(tags.len(), 3);
+    assert_eq!(tags[0].tag_type, 18);
+    assert!(!tags[0]
+        .data
+        .windows(b"filesize".len())
+        .any(|w| w == b"filesize"));
+
+    for (i, tag) in tags[1..].iter().enumerate() {
+        assert_eq!(tag.tag_type, 8);
+        // Relative to the first buffer
+        assert_eq!(tag.timestamp, i as u32 * 26);
+        // MP3, 44.1kHz, 16 bit, stereo
+        assert_eq!(tag.data[0], 0x2f);
+        assert_eq!(&tag.data[1..], &[i as u8; 4]);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Output {
+    Segment(Option<gst::ClockTime>),
+    Buffer(Option<gst::ClockTime>),
+}
+
+/// Waits for EOS and returns the segments and buffers output since the last call.
+fn wait_for_eos(pipeline: &gst::Pipeline, outputs: &Mutex<Vec<Output>>) -> Vec<Output> {
+    let msg = pipeline
+        .bus()
+        .unwrap()
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(10),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        )
+        .expect("No EOS");
+    assert_eq!(msg.type_(), gst::MessageType::Eos, "{msg:?}");
+
+    std::mem::take(&mut *outputs.lock().unwrap())
+}
+
+fn num_buffers(output: &[Output]) -> usize {
+    output
+        .iter()
+        .filter(|output| matches!(output, Output::Buffer(_)))
+        .count()
+}
+
+#[test]
+fn test_demux_seek() {
+    init();
+
+    // 3s of video with a keyframe every second
+    let mut h = gst_check::Harness::with_padnames("rsflvmux", Some("video"), Some("src"));
+    h.set_src_caps(
+        gst::Caps::builder("video/x-h264")
+            .field("stream-format", "avc")
+            .field("alignment", "au")
+            .field("width", 320i32)
+            .field("height", 240i32)
+            .field("framerate", gst::Fraction::new(10, 1))
+            .field("codec_data", gst::Buffer::from_slice([1u8, 2, 3, 4]))
+            .build(),
+    );
+    h.play();
+
+    for i in 0..30u64 {
+        let mut buffer = gst::Buffer::from_slice(vec![i as u8; 100]);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(i * 100));
+            buffer.set_dts(gst::ClockTime::from_mseconds(i * 100));
+            buffer.set_duration(gst::ClockTime::from_mseconds(100));
+            if i % 10 != 0 {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // Header, metadata and sequence header, then the frames
+    let data = pull(&mut h, 33);
+    let path = std::env::temp_dir().join(format!("flvmux-seek-{}.flv", std::process::id()));
+    std::fs::write(&path, data).unwrap();
+
+    let pipeline = gst::Pipeline::new();
+    let src = gst::ElementFactory::make("filesrc")
+        .property("location", path.to_str().unwrap())
+        .build()
+        .unwrap();
+    let demux = gst::ElementFactory::make("rsflvdemux").build().unwrap();
+    let sink = gst::ElementFactory::make("fakesink")
+        .property("sync", false)
+        .property("signal-handoffs", true)
+        .build()
+        .unwrap();
+    pipeline.add_many([&src, &demux, &sink]).unwrap();
+    src.link(&demux).unwrap();
+
+    let sink_weak = sink.downgrade();
+    demux.connect_pad_added(move |_, pad| {
+        let Some(sink) = sink_weak.upgrade() else {
+            return;
+        };
+        pad.link(&sink.static_pad("sink").unwrap()).unwrap();
+    });
+
+    let outputs = Arc::new(Mutex::new(Vec::new()));
+    let outputs_clone = outputs.clone();
+    sink.connect("handoff", false, move |args| {
+        let buffer = args[1].get::<gst::Buffer>().unwrap();
+        outputs_clone
+            .lock()
+            .unwrap()
+            .push(Output::Buffer(buffer.pts()));
+        None
+    });
+    let outputs_clone = outputs.clone();
+    sink.static_pad("sink").unwrap().add_probe(
+        gst::PadProbeType::EVENT_DOWNSTREAM,
+        move |_, info| {
+            if let Some(gst::PadProbeData::Event(ref event)) = info.data {
+                if let gst::EventView::Segment(e) = event.view() {
+                    let segment = e.segment().downcast_ref::<gst::ClockTime>().unwrap();
+                    outputs_clone
+                        .lock()
+                        .unwrap()
+                        .push(Output::Segment(segment.start()));
+                }
+            }
+            gst::PadProbeReturn::Ok
+        },
+    );
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    // The keyframes are indexed while playing
+    let output = wait_for_eos(&pipeline, &outputs);
+    assert_eq!(num_buffers(&output), 30);
+    assert_eq!(output[0], Output::Segment(Some(gst::ClockTime::ZERO)));
+
+    // Starts at the previous keyframe, and so does the segment
+    pipeline
+        .seek_simple(
+            gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
+            gst::ClockTime::from_mseconds(1500),
+        )
+        .unwrap();
+    let output = wait_for_eos(&pipeline, &outputs);
+    assert_eq!(
+        output[0],
+        Output::Segment(Some(gst::ClockTime::from_seconds(1)))
+    );
+    assert_eq!(
+        output[1],
+        Output::Buffer(Some(gst::ClockTime::from_seconds(1)))
+    );
+    assert_eq!(num_buffers(&output), 20);
+
+    pipeline
+        .seek_simple(
+            gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT | gst::SeekFlags::SNAP_AFTER,
+            gst::ClockTime::from_mseconds(1500),
+        )
+        .unwrap();
+    let output = wait_for_eos(&pipeline, &outputs);
+    assert_eq!(
+        output[0],
+        Output::Segment(Some(gst::ClockTime::from_seconds(2)))
+    );
+    assert_eq!(
+        output[1],
+        Output::Buffer(Some(gst::ClockTime::from_seconds(2)))
+    );
+    assert_eq!(num_buffers(&output), 10);
+
+    // Without KEY_UNIT, decoding starts at the previous keyframe but the segment at the target
+    pipeline
+        .seek_simple(gst::SeekFlags::FLUSH, gst::ClockTime::from_mseconds(1500))
+        .unwrap();
+    let output = wait_for_eos(&pipeline, &outputs);
+    assert_eq!(
+        output[0],
+        Output::Segment(Some(gst::ClockTime::from_mseconds(1500)))
+    );
+    assert_eq!(
+        output[1],
+        Output::Buffer(Some(gst::ClockTime::from_seconds(1)))
+    );
+
+    pipeline.set_state(gst::State::Null).unwrap();
+    let _ = std::fs::remove_file(&path);
+}
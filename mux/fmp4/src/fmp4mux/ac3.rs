@@ -0,0 +1,81 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use bitstream_io::{BigEndian, BitRead, BitReader, BitWrite, BitWriter};
+use std::io::{self, Cursor};
+
+/// Parses the header of the AC-3 syncframe at the start of `data` and returns the content of
+/// the corresponding `dac3` box (ETSI TS 102 366 Annex F.4).
+pub fn read_dac3_bytes(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = BitReader::endian(Cursor::new(data), BigEndian);
+
+    let syncword = reader.read::<u16>(16)?;
+    if syncword != 0x0b77 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no syncword"));
+    }
+
+    // crc1
+    reader.skip(16)?;
+
+    let fscod = reader.read::<u8>(2)?;
+    let frmsizecod = reader.read::<u8>(6)?;
+    let bsid = reader.read::<u8>(5)?;
+    let bsmod = reader.read::<u8>(3)?;
+    let acmod = reader.read::<u8>(3)?;
+
+    if fscod == 0b11 || frmsizecod > 37 || bsid > 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported syncframe",
+        ));
+    }
+
+    // cmixlev, surmixlev and dsurmod, depending on the channel configuration
+    if acmod & 0b001 != 0 && acmod != 0b001 {
+        reader.skip(2)?;
+    }
+    if acmod & 0b100 != 0 {
+        reader.skip(2)?;
+    }
+    if acmod == 0b010 {
+        reader.skip(2)?;
+    }
+    let lfeon = reader.read_bit()?;
+
+    let mut dac3 = Vec::with_capacity(3);
+    let mut writer = BitWriter::endian(&mut dac3, BigEndian);
+    writer.write(2, fscod)?;
+    writer.write(5, bsid)?;
+    writer.write(3, bsmod)?;
+    writer.write(3, acmod)?;
+    writer.write_bit(lfeon)?;
+    // bit_rate_code
+    writer.write(5, frmsizecod >> 1)?;
+    // reserved
+    writer.write(5, 0u8)?;
+    drop(writer);
+
+    Ok(dac3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_dac3_bytes() {
+        // 48kHz, 192kbps, bsid 8, 3/2 with LFE
+        let data = [0x0b, 0x77, 0x00, 0x00, 0x14, 0x40, 0xe1, 0x00];
+        let dac3 = read_dac3_bytes(&data).unwrap();
+        // fscod 0, bsid 8, bsmod 0, acmod 7, lfeon 1, bit_rate_code 10
+        assert_eq!(dac3, [0x10, 0x3d, 0x40]);
+    }
+
+    #[test]
+    fn test_read_dac3_bytes_invalid() {
+        assert!(read_dac3_bytes(&[0x00, 0x00, 0x00, 0x00, 0x14, 0x40, 0xe1]).is_err());
+    }
+}
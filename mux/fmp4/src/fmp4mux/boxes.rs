@@ -161,6 +161,9 @@ fn cmaf_brands_from_caps(caps: &gst::CapsRef, compatible_brands: &mut Vec<&'stat
         "audio/mpeg" => {
             compatible_brands.push(b"caac");
         }
+        "audio/x-ac3" => {
+            compatible_brands.push(b"ca3e");
+        }
         "video/x-av1" => {
             compatible_brands.push(b"av01");
             compatible_brands.push(b"cmf2");
@@ -609,8 +612,8 @@ fn write_tkhd(
     // Volume
     let s = stream.caps.structure(0).unwrap();
     match s.name().as_str() {
-        "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-alaw" | "audio/x-mulaw"
-        | "audio/x-adpcm" => v.extend((1u16 << 8).to_be_bytes()),
+        "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-ac3" | "audio/x-alaw"
+        | "audio/x-mulaw" | "audio/x-adpcm" => v.extend((1u16 << 8).to_be_bytes()),
         _ => v.extend(0u16.to_be_bytes()),
     }
 
@@ -751,8 +754,8 @@ fn write_hdlr(
     let (handler_type, name) = match s.name().as_str() {
         "video/x-h264" | "video/x-h265" | "video/x-vp8" | "video/x-vp9" | "video/x-av1"
         | "image/jpeg" => (b"vide", b"VideoHandler\0".as_slice()),
-        "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-alaw" | "audio/x-mulaw"
-        | "audio/x-adpcm" => (b"soun", b"SoundHandler\0".as_slice()),
+        "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-ac3" | "audio/x-alaw"
+        | "audio/x-mulaw" | "audio/x-adpcm" => (b"soun", b"SoundHandler\0".as_slice()),
         "application/x-onvif-metadata" => (b"meta", b"MetadataHandler\0".as_slice()),
         _ => unreachable!(),
     };
@@ -782,8 +785,8 @@ fn write_minf(
             // Flags are always 1 for unspecified reasons
             write_full_box(v, b"vmhd", FULL_BOX_VERSION_0, 1, |v| write_vmhd(v, cfg))?
         }
-        "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-alaw" | "audio/x-mulaw"
-        | "audio/x-adpcm" => {
+        "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-ac3" | "audio/x-alaw"
+        | "audio/x-mulaw" | "audio/x-adpcm" => {
             write_full_box(v, b"smhd", FULL_BOX_VERSION_0, FULL_BOX_FLAGS_NONE, |v| {
                 write_smhd(v, cfg)
             })?
@@ -892,8 +895,8 @@ fn write_stsd(
     match s.name().as_str() {
         "video/x-h264" | "video/x-h265" | "video/x-vp8" | "video/x-vp9" | "video/x-av1"
         | "image/jpeg" => write_visual_sample_entry(v, cfg, stream)?,
-        "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-alaw" | "audio/x-mulaw"
-        | "audio/x-adpcm" => write_audio_sample_entry(v, cfg, stream)?,
+        "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-ac3" | "audio/x-alaw"
+        | "audio/x-mulaw" | "audio/x-adpcm" => write_audio_sample_entry(v, cfg, stream)?,
         "application/x-onvif-metadata" => write_xml_meta_data_sample_entry(v, cfg, stream)?,
         _ => unreachable!(),
     }
@@ -1310,6 +1313,7 @@ fn write_audio_sample_entry(
         "audio/mpeg" => b"mp4a",
         "audio/x-opus" => b"Opus",
         "audio/x-flac" => b"fLaC",
+        "audio/x-ac3" => b"ac-3",
         "audio/x-alaw" => b"alaw",
         "audio/x-mulaw" => b"ulaw",
         "audio/x-adpcm" => {
@@ -1377,6 +1381,16 @@ fn write_audio_sample_entry(
             "audio/x-flac" => {
                 write_dfla(v, &stream.caps)?;
             }
+            "audio/x-ac3" => {
+                let dac3 = stream
+                    .extra_header_data
+                    .as_ref()
+                    .context("no AC-3 syncframe header")?;
+                write_box(v, b"dac3", move |v| {
+                    v.extend_from_slice(dac3);
+                    Ok(())
+                })?;
+            }
             "audio/x-alaw" | "audio/x-mulaw" | "audio/x-adpcm" => {
                 // Nothing to do here
             }
@@ -16,6 +16,7 @@ use std::collections::VecDeque;
 use std::mem;
 use std::sync::Mutex;
 
+use crate::fmp4mux::ac3::read_dac3_bytes;
 use crate::fmp4mux::obu::read_seq_header_obu_bytes;
 use once_cell::sync::Lazy;
 
@@ -98,6 +99,7 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 const DEFAULT_FRAGMENT_DURATION: gst::ClockTime = gst::ClockTime::from_seconds(10);
 const DEFAULT_CHUNK_DURATION: Option<gst::ClockTime> = gst::ClockTime::NONE;
 const DEFAULT_HEADER_UPDATE_MODE: super::HeaderUpdateMode = super::HeaderUpdateMode::None;
+const DEFAULT_MARK_INDEPENDENT_CHUNKS: bool = false;
 const DEFAULT_WRITE_MFRA: bool = false;
 const DEFAULT_WRITE_MEHD: bool = false;
 const DEFAULT_INTERLEAVE_BYTES: Option<u64> = None;
@@ -114,6 +116,7 @@ struct Settings {
     interleave_time: Option<gst::ClockTime>,
     movie_timescale: u32,
     offset_to_zero: bool,
+    mark_independent_chunks: bool,
 }
 
 impl Default for Settings {
@@ -128,6 +131,7 @@ impl Default for Settings {
             interleave_time: DEFAULT_INTERLEAVE_TIME,
             movie_timescale: 0,
             offset_to_zero: false,
+            mark_independent_chunks: DEFAULT_MARK_INDEPENDENT_CHUNKS,
         }
     }
 }
@@ -819,6 +823,18 @@ impl FMP4Mux {
                 })?;
             }
 
+            // For AC-3 the 'dac3' box is filled from the header of the first syncframe.
+            if s.name().as_str() == "audio/x-ac3" && stream.extra_header_data.is_none() {
+                let buf_map = buffer.map_readable().map_err(|_| {
+                    gst::error!(CAT, obj: stream.sinkpad, "Failed to map buffer");
+                    gst::FlowError::Error
+                })?;
+                stream.extra_header_data = Some(read_dac3_bytes(buf_map.as_slice()).map_err(|_| {
+                    gst::error!(CAT, obj: stream.sinkpad, "Failed to parse AC-3 syncframe header");
+                    gst::FlowError::Error
+                })?);
+            }
+
             let gop = Gop {
                 start_pts: pts,
                 start_dts: dts,
@@ -2403,6 +2419,26 @@ impl FMP4Mux {
                 .copy_into(buffer, gst::BufferCopyFlags::META, ..);
         }
 
+        // A chunk is independent if it starts with a keyframe in every stream, in which case
+        // it can be decoded without the previous chunks of the fragment.
+        let independent_chunk = !fragment_start
+            && settings.mark_independent_chunks
+            && streams.iter().enumerate().all(|(idx, _)| {
+                interleaved_buffers
+                    .iter()
+                    .find(|buffer| buffer.idx == idx)
+                    .map_or(true, |buffer| {
+                        !buffer.buffer.flags().contains(gst::BufferFlags::DELTA_UNIT)
+                    })
+            });
+        if independent_chunk {
+            gst::debug!(CAT, imp: self, "Chunk starts with keyframes in all streams");
+            fmp4_fragment_header
+                .get_mut()
+                .unwrap()
+                .unset_flags(gst::BufferFlags::DELTA_UNIT);
+        }
+
         let moof_offset = state.current_offset
             + fmp4_header.as_ref().map(|h| h.size()).unwrap_or(0) as u64
             + moof_offset;
@@ -2633,6 +2669,7 @@ impl FMP4Mux {
                         return Err(gst::FlowError::NotNegotiated);
                     };
                 }
+                "audio/x-ac3" => (),
                 "audio/x-alaw" | "audio/x-mulaw" => (),
                 "audio/x-adpcm" => (),
                 "application/x-onvif-metadata" => (),
@@ -2774,6 +2811,22 @@ impl FMP4Mux {
         Ok(Some((list, caps)))
     }
 
+    /// Emits the `header-updated` signal with the header from the `streamheader` of the caps.
+    ///
+    /// Must be called without the state lock.
+    fn emit_header_updated(&self, caps: &gst::Caps, at_eos: bool) {
+        let Some(header) = caps
+            .structure(0)
+            .and_then(|s| s.get::<gst::ArrayRef>("streamheader").ok())
+            .and_then(|a| a.first().and_then(|v| v.get::<gst::Buffer>().ok()))
+        else {
+            return;
+        };
+
+        self.obj()
+            .emit_by_name::<()>("header-updated", &[&header, &at_eos]);
+    }
+
     /// Finish the stream be rewriting / updating headers.
     fn finish(&self, settings: &Settings) {
         // Do remaining EOS handling after the end of the stream was pushed.
@@ -2788,6 +2841,8 @@ impl FMP4Mux {
         let updated_header = self.update_header(&mut self.state.lock().unwrap(), settings, true);
         match updated_header {
             Ok(Some((buffer_list, caps))) => {
+                self.emit_header_updated(&caps, true);
+
                 match settings.header_update_mode {
                     super::HeaderUpdateMode::None => unreachable!(),
                     super::HeaderUpdateMode::Rewrite => {
@@ -2907,12 +2962,50 @@ impl ObjectImpl for FMP4Mux {
                     .blurb("Timescale to use for the movie (units per second, 0 is automatic)")
                     .mutable_ready()
                     .build(),
+                /**
+                 * GstFMP4Mux:mark-independent-chunks:
+                 *
+                 * Don't set the `DELTA_UNIT` flag on the headers of chunks that start with a
+                 * keyframe in every stream. Such chunks can be decoded independently of the
+                 * previous chunks of the fragment, e.g. for `INDEPENDENT=YES` LL-HLS parts.
+                 *
+                 * Since: plugins-rs-0.13.0
+                 */
+                glib::ParamSpecBoolean::builder("mark-independent-chunks")
+                    .nick("Mark Independent Chunks")
+                    .blurb("Don't mark chunks starting with keyframes in all streams as delta units")
+                    .default_value(DEFAULT_MARK_INDEPENDENT_CHUNKS)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
         &PROPERTIES
     }
 
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![
+                /**
+                 * GstFMP4Mux::header-updated:
+                 * @header: the initialization header
+                 * @at_eos: whether this is the updated header at the end of the stream
+                 *
+                 * Emitted whenever a new initialization header (`ftyp` and `moov`) was created,
+                 * right before it is set on the caps and before any fragments using it are
+                 * pushed downstream.
+                 *
+                 * Since: plugins-rs-0.13.0
+                 */
+                glib::subclass::Signal::builder("header-updated")
+                    .param_types([gst::Buffer::static_type(), bool::static_type()])
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
         match pspec.name() {
             "fragment-duration" => {
@@ -2977,6 +3070,11 @@ impl ObjectImpl for FMP4Mux {
                 settings.movie_timescale = value.get().expect("type checked upstream");
             }
 
+            "mark-independent-chunks" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.mark_independent_chunks = value.get().expect("type checked upstream");
+            }
+
             _ => unimplemented!(),
         }
     }
@@ -3023,6 +3121,11 @@ impl ObjectImpl for FMP4Mux {
                 settings.movie_timescale.to_value()
             }
 
+            "mark-independent-chunks" => {
+                let settings = self.settings.lock().unwrap();
+                settings.mark_independent_chunks.to_value()
+            }
+
             _ => unimplemented!(),
         }
     }
@@ -3248,6 +3351,22 @@ impl AggregatorImpl for FMP4Mux {
 
         self.parent_start()?;
 
+        {
+            let settings = self.settings.lock().unwrap();
+            if let Some(chunk_duration) = settings.chunk_duration {
+                if chunk_duration > settings.fragment_duration {
+                    return Err(gst::error_msg!(
+                        gst::CoreError::Failed,
+                        [
+                            "Chunk duration {} is longer than the fragment duration {}",
+                            chunk_duration,
+                            settings.fragment_duration
+                        ]
+                    ));
+                }
+            }
+        }
+
         // For non-single-stream variants configure a default segment that allows for negative
         // DTS so that we can correctly re-timestamp buffers with their running times.
         let aggregator = self.obj();
@@ -3331,6 +3450,7 @@ impl AggregatorImpl for FMP4Mux {
 
         if let Some(caps) = caps {
             gst::debug!(CAT, imp: self, "Setting caps on source pad: {:?}", caps);
+            self.emit_header_updated(&caps, false);
             self.obj().set_src_caps(&caps);
         }
 
@@ -3523,6 +3643,12 @@ impl ElementImpl for ISOFMP4Mux {
                         .field("channels", gst::IntRange::<i32>::new(1, 8))
                         .field("rate", gst::IntRange::<i32>::new(1, 10 * u16::MAX as i32))
                         .build(),
+                    gst::Structure::builder("audio/x-ac3")
+                        .field("framed", true)
+                        .field("alignment", "frame")
+                        .field("channels", gst::IntRange::<i32>::new(1, 6))
+                        .field("rate", gst::List::new([32000i32, 44100, 48000]))
+                        .build(),
                 ]
                 .into_iter()
                 .collect::<gst::Caps>(),
@@ -3619,6 +3745,22 @@ impl ElementImpl for CMAFMux {
                         .field("channels", gst::IntRange::new(1, u16::MAX as i32))
                         .field("rate", gst::IntRange::new(1, i32::MAX))
                         .build(),
+                    gst::Structure::builder("audio/x-opus")
+                        .field("channel-mapping-family", gst::IntRange::new(0i32, 255))
+                        .field("channels", gst::IntRange::new(1i32, 8))
+                        .field("rate", gst::IntRange::new(1, i32::MAX))
+                        .build(),
+                    gst::Structure::builder("audio/x-flac")
+                        .field("framed", true)
+                        .field("channels", gst::IntRange::<i32>::new(1, 8))
+                        .field("rate", gst::IntRange::<i32>::new(1, 10 * u16::MAX as i32))
+                        .build(),
+                    gst::Structure::builder("audio/x-ac3")
+                        .field("framed", true)
+                        .field("alignment", "frame")
+                        .field("channels", gst::IntRange::<i32>::new(1, 6))
+                        .field("rate", gst::List::new([32000i32, 44100, 48000]))
+                        .build(),
                 ]
                 .into_iter()
                 .collect::<gst::Caps>(),
@@ -3732,6 +3874,17 @@ impl ElementImpl for DASHMP4Mux {
                         .field("channels", gst::IntRange::new(1i32, 8))
                         .field("rate", gst::IntRange::new(1, i32::MAX))
                         .build(),
+                    gst::Structure::builder("audio/x-flac")
+                        .field("framed", true)
+                        .field("channels", gst::IntRange::<i32>::new(1, 8))
+                        .field("rate", gst::IntRange::<i32>::new(1, 10 * u16::MAX as i32))
+                        .build(),
+                    gst::Structure::builder("audio/x-ac3")
+                        .field("framed", true)
+                        .field("alignment", "frame")
+                        .field("channels", gst::IntRange::<i32>::new(1, 6))
+                        .field("rate", gst::List::new([32000i32, 44100, 48000]))
+                        .build(),
                 ]
                 .into_iter()
                 .collect::<gst::Caps>(),
@@ -9,6 +9,7 @@
 use gst::glib;
 use gst::prelude::*;
 
+mod ac3;
 mod boxes;
 mod imp;
 
@@ -1486,6 +1486,100 @@ fn test_chunking_single_stream() {
     assert_eq!(ev.type_(), gst::EventType::Eos);
 }
 
+#[test]
+fn test_chunking_independent_chunks() {
+    init();
+
+    let caps = gst::Caps::builder("video/x-h264")
+        .field("width", 1920i32)
+        .field("height", 1080i32)
+        .field("framerate", gst::Fraction::new(30, 1))
+        .field("stream-format", "avc")
+        .field("alignment", "au")
+        .field("codec_data", gst::Buffer::with_size(1).unwrap())
+        .build();
+
+    let mut h = gst_check::Harness::new("cmafmux");
+
+    // 5s fragment duration, 1s chunk duration
+    h.element()
+        .unwrap()
+        .set_property("fragment-duration", 5.seconds());
+    h.element()
+        .unwrap()
+        .set_property("chunk-duration", 1.seconds());
+    h.element()
+        .unwrap()
+        .set_property("mark-independent-chunks", true);
+
+    let headers = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let headers_clone = headers.clone();
+    h.element()
+        .unwrap()
+        .connect("header-updated", false, move |args| {
+            let header = args[1].get::<gst::Buffer>().unwrap();
+            let at_eos = args[2].get::<bool>().unwrap();
+            headers_clone.lock().unwrap().push((header, at_eos));
+            None
+        });
+
+    h.set_src_caps(caps);
+    h.play();
+
+    // Push 15 buffers of 0.5s each, 1st, 5th and 11th buffer without DELTA_UNIT flag
+    for i in 0..15 {
+        let mut buffer = gst::Buffer::with_size(1).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(i * 500.mseconds());
+            buffer.set_dts(i * 500.mseconds());
+            buffer.set_duration(500.mseconds());
+            if i != 0 && i != 4 && i != 10 {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+
+    // Crank the clock: this should bring us to the end of the first fragment
+    h.crank_single_clock_wait().unwrap();
+
+    let header = h.pull().unwrap();
+    assert_eq!(
+        header.flags(),
+        gst::BufferFlags::HEADER | gst::BufferFlags::DISCONT
+    );
+    {
+        let headers = headers.lock().unwrap();
+        assert_eq!(headers.len(), 1);
+        assert!(!headers[0].1);
+        assert_eq!(
+            headers[0].0.map_readable().unwrap().as_slice(),
+            header.map_readable().unwrap().as_slice()
+        );
+    }
+
+    // There should be 7 chunks now, the 1st and 6th are starting a fragment and the 3rd
+    // starts with a keyframe. Each chunk should have two buffers.
+    for chunk in 0..7 {
+        let chunk_header = h.pull().unwrap();
+        if chunk == 0 || chunk == 2 || chunk == 5 {
+            assert_eq!(chunk_header.flags(), gst::BufferFlags::HEADER);
+        } else {
+            assert_eq!(
+                chunk_header.flags(),
+                gst::BufferFlags::HEADER | gst::BufferFlags::DELTA_UNIT
+            );
+        }
+        assert_eq!(chunk_header.pts(), Some(chunk * 1.seconds()));
+
+        for _ in 0..2 {
+            let buffer = h.pull().unwrap();
+            assert!(buffer.flags().contains(gst::BufferFlags::DELTA_UNIT));
+        }
+    }
+}
+
 #[test]
 fn test_chunking_multi_stream() {
     init();
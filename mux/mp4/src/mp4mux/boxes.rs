@@ -423,7 +423,7 @@ fn write_tkhd(
     // Width/height
     match s.name().as_str() {
         "video/x-h264" | "video/x-h265" | "video/x-vp8" | "video/x-vp9" | "video/x-av1"
-        | "image/jpeg" => {
+        | "video/x-raw" | "image/jpeg" => {
             let width = s.get::<i32>("width").context("video caps without width")? as u32;
             let height = s
                 .get::<i32>("height")
@@ -527,7 +527,7 @@ fn write_hdlr(
     let s = stream.caps.structure(0).unwrap();
     let (handler_type, name) = match s.name().as_str() {
         "video/x-h264" | "video/x-h265" | "video/x-vp8" | "video/x-vp9" | "video/x-av1"
-        | "image/jpeg" => (b"vide", b"VideoHandler\0".as_slice()),
+        | "video/x-raw" | "image/jpeg" => (b"vide", b"VideoHandler\0".as_slice()),
         "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-alaw" | "audio/x-mulaw"
         | "audio/x-adpcm" => (b"soun", b"SoundHandler\0".as_slice()),
         "application/x-onvif-metadata" => (b"meta", b"MetadataHandler\0".as_slice()),
@@ -555,7 +555,7 @@ fn write_minf(
 
     match s.name().as_str() {
         "video/x-h264" | "video/x-h265" | "video/x-vp8" | "video/x-vp9" | "video/x-av1"
-        | "image/jpeg" => {
+        | "video/x-raw" | "image/jpeg" => {
             // Flags are always 1 for unspecified reasons
             write_full_box(v, b"vmhd", FULL_BOX_VERSION_0, 1, |v| write_vmhd(v, header))?
         }
@@ -716,7 +716,7 @@ fn write_stsd(
     let s = stream.caps.structure(0).unwrap();
     match s.name().as_str() {
         "video/x-h264" | "video/x-h265" | "video/x-vp8" | "video/x-vp9" | "video/x-av1"
-        | "image/jpeg" => write_visual_sample_entry(v, header, stream)?,
+        | "video/x-raw" | "image/jpeg" => write_visual_sample_entry(v, header, stream)?,
         "audio/mpeg" | "audio/x-opus" | "audio/x-flac" | "audio/x-alaw" | "audio/x-mulaw"
         | "audio/x-adpcm" => write_audio_sample_entry(v, header, stream)?,
         "application/x-onvif-metadata" => write_xml_meta_data_sample_entry(v, header, stream)?,
@@ -769,6 +769,7 @@ fn write_visual_sample_entry(
         "video/x-vp8" => b"vp08",
         "video/x-vp9" => b"vp09",
         "video/x-av1" => b"av01",
+        "video/x-raw" => b"uncv",
         _ => unreachable!(),
     };
 
@@ -983,6 +984,13 @@ fn write_visual_sample_entry(
                     Ok(())
                 })?;
             }
+            "video/x-raw" => {
+                let info =
+                    gst_video::VideoInfo::from_caps(&stream.caps).context("invalid raw caps")?;
+                let layout = UncompressedLayout::from_format(info.format())
+                    .context("unsupported raw video format")?;
+                write_uncompressed_video_boxes(v, &layout)?;
+            }
             "video/x-vp8" | "image/jpeg" => {
                 // Nothing to do here
             }
@@ -1088,6 +1096,291 @@ fn write_visual_sample_entry(
     Ok(())
 }
 
+/// Component types of the `cmpd` box.
+const COMPONENT_TYPE_MONOCHROME: u16 = 0;
+const COMPONENT_TYPE_Y: u16 = 1;
+const COMPONENT_TYPE_CB: u16 = 2;
+const COMPONENT_TYPE_CR: u16 = 3;
+const COMPONENT_TYPE_RED: u16 = 4;
+const COMPONENT_TYPE_GREEN: u16 = 5;
+const COMPONENT_TYPE_BLUE: u16 = 6;
+const COMPONENT_TYPE_ALPHA: u16 = 7;
+
+const SAMPLING_TYPE_444: u8 = 0;
+const SAMPLING_TYPE_422: u8 = 1;
+const SAMPLING_TYPE_420: u8 = 2;
+
+const INTERLEAVE_TYPE_COMPONENT: u8 = 0;
+const INTERLEAVE_TYPE_PIXEL: u8 = 1;
+const INTERLEAVE_TYPE_MIXED: u8 = 2;
+const INTERLEAVE_TYPE_MULTI_Y: u8 = 5;
+
+/// Layout of the samples of an uncompressed video track (ISO/IEC 23001-17).
+///
+/// This describes the default memory layout of the corresponding GStreamer raw video format, i.e.
+/// planes without padding between them and rows aligned to `row_align_size` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct UncompressedLayout {
+    /// Profile if the layout exactly matches one, otherwise 0.
+    profile: [u8; 4],
+    /// Component type and bit depth of each component in the order they are stored.
+    components: &'static [(u16, u8)],
+    sampling_type: u8,
+    interleave_type: u8,
+    block_size: u8,
+    block_little_endian: bool,
+    block_reversed: bool,
+    row_align_size: u32,
+}
+
+impl UncompressedLayout {
+    pub(super) fn from_format(format: gst_video::VideoFormat) -> Option<Self> {
+        use gst_video::VideoFormat;
+
+        let layout = UncompressedLayout {
+            profile: [0; 4],
+            components: &[],
+            sampling_type: SAMPLING_TYPE_444,
+            interleave_type: INTERLEAVE_TYPE_PIXEL,
+            block_size: 0,
+            block_little_endian: false,
+            block_reversed: false,
+            // Rows of most formats are aligned to 4 bytes by default
+            row_align_size: 4,
+        };
+
+        Some(match format {
+            VideoFormat::Gray8 => UncompressedLayout {
+                components: &[(COMPONENT_TYPE_MONOCHROME, 8)],
+                interleave_type: INTERLEAVE_TYPE_COMPONENT,
+                ..layout
+            },
+            VideoFormat::Rgb => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_RED, 8),
+                    (COMPONENT_TYPE_GREEN, 8),
+                    (COMPONENT_TYPE_BLUE, 8),
+                ],
+                ..layout
+            },
+            VideoFormat::Bgr => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_BLUE, 8),
+                    (COMPONENT_TYPE_GREEN, 8),
+                    (COMPONENT_TYPE_RED, 8),
+                ],
+                ..layout
+            },
+            VideoFormat::Rgba => UncompressedLayout {
+                profile: *b"rgba",
+                components: &[
+                    (COMPONENT_TYPE_RED, 8),
+                    (COMPONENT_TYPE_GREEN, 8),
+                    (COMPONENT_TYPE_BLUE, 8),
+                    (COMPONENT_TYPE_ALPHA, 8),
+                ],
+                row_align_size: 0,
+                ..layout
+            },
+            VideoFormat::Abgr => UncompressedLayout {
+                profile: *b"abgr",
+                components: &[
+                    (COMPONENT_TYPE_ALPHA, 8),
+                    (COMPONENT_TYPE_BLUE, 8),
+                    (COMPONENT_TYPE_GREEN, 8),
+                    (COMPONENT_TYPE_RED, 8),
+                ],
+                row_align_size: 0,
+                ..layout
+            },
+            VideoFormat::Argb => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_ALPHA, 8),
+                    (COMPONENT_TYPE_RED, 8),
+                    (COMPONENT_TYPE_GREEN, 8),
+                    (COMPONENT_TYPE_BLUE, 8),
+                ],
+                row_align_size: 0,
+                ..layout
+            },
+            VideoFormat::Bgra => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_BLUE, 8),
+                    (COMPONENT_TYPE_GREEN, 8),
+                    (COMPONENT_TYPE_RED, 8),
+                    (COMPONENT_TYPE_ALPHA, 8),
+                ],
+                row_align_size: 0,
+                ..layout
+            },
+            VideoFormat::Uyvy => UncompressedLayout {
+                profile: *b"2vuy",
+                components: &[
+                    (COMPONENT_TYPE_CB, 8),
+                    (COMPONENT_TYPE_Y, 8),
+                    (COMPONENT_TYPE_CR, 8),
+                    (COMPONENT_TYPE_Y, 8),
+                ],
+                sampling_type: SAMPLING_TYPE_422,
+                interleave_type: INTERLEAVE_TYPE_MULTI_Y,
+                row_align_size: 0,
+                ..layout
+            },
+            VideoFormat::Yuy2 => UncompressedLayout {
+                profile: *b"yuv2",
+                components: &[
+                    (COMPONENT_TYPE_Y, 8),
+                    (COMPONENT_TYPE_CB, 8),
+                    (COMPONENT_TYPE_Y, 8),
+                    (COMPONENT_TYPE_CR, 8),
+                ],
+                sampling_type: SAMPLING_TYPE_422,
+                interleave_type: INTERLEAVE_TYPE_MULTI_Y,
+                row_align_size: 0,
+                ..layout
+            },
+            VideoFormat::V210 => UncompressedLayout {
+                profile: *b"v210",
+                components: &[
+                    (COMPONENT_TYPE_CB, 10),
+                    (COMPONENT_TYPE_Y, 10),
+                    (COMPONENT_TYPE_CR, 10),
+                    (COMPONENT_TYPE_Y, 10),
+                ],
+                sampling_type: SAMPLING_TYPE_422,
+                interleave_type: INTERLEAVE_TYPE_MULTI_Y,
+                // Three components per little endian 32 bit word, starting at the LSB
+                block_size: 4,
+                block_little_endian: true,
+                block_reversed: true,
+                // Rows are made of groups of 48 pixels
+                row_align_size: 128,
+            },
+            VideoFormat::I420 => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_Y, 8),
+                    (COMPONENT_TYPE_CB, 8),
+                    (COMPONENT_TYPE_CR, 8),
+                ],
+                sampling_type: SAMPLING_TYPE_420,
+                interleave_type: INTERLEAVE_TYPE_COMPONENT,
+                ..layout
+            },
+            VideoFormat::Nv12 => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_Y, 8),
+                    (COMPONENT_TYPE_CB, 8),
+                    (COMPONENT_TYPE_CR, 8),
+                ],
+                sampling_type: SAMPLING_TYPE_420,
+                interleave_type: INTERLEAVE_TYPE_MIXED,
+                ..layout
+            },
+            VideoFormat::Nv21 => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_Y, 8),
+                    (COMPONENT_TYPE_CR, 8),
+                    (COMPONENT_TYPE_CB, 8),
+                ],
+                sampling_type: SAMPLING_TYPE_420,
+                interleave_type: INTERLEAVE_TYPE_MIXED,
+                ..layout
+            },
+            VideoFormat::Y42b => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_Y, 8),
+                    (COMPONENT_TYPE_CB, 8),
+                    (COMPONENT_TYPE_CR, 8),
+                ],
+                sampling_type: SAMPLING_TYPE_422,
+                interleave_type: INTERLEAVE_TYPE_COMPONENT,
+                ..layout
+            },
+            VideoFormat::Y444 => UncompressedLayout {
+                components: &[
+                    (COMPONENT_TYPE_Y, 8),
+                    (COMPONENT_TYPE_CB, 8),
+                    (COMPONENT_TYPE_CR, 8),
+                ],
+                interleave_type: INTERLEAVE_TYPE_COMPONENT,
+                ..layout
+            },
+            _ => return None,
+        })
+    }
+
+    /// Checks if the dimensions are compatible with the chroma subsampling.
+    pub(super) fn supports_size(&self, width: u32, height: u32) -> bool {
+        match self.sampling_type {
+            SAMPLING_TYPE_422 => width % 2 == 0,
+            SAMPLING_TYPE_420 => width % 2 == 0 && height % 2 == 0,
+            _ => true,
+        }
+    }
+}
+
+/// Writes the `cmpd` and `uncC` boxes of the `uncv` sample entry.
+fn write_uncompressed_video_boxes(
+    v: &mut Vec<u8>,
+    layout: &UncompressedLayout,
+) -> Result<(), Error> {
+    // Each component type is defined once and referenced by index from the `uncC` box
+    let mut component_types = Vec::<u16>::new();
+    for (component_type, _) in layout.components {
+        if !component_types.contains(component_type) {
+            component_types.push(*component_type);
+        }
+    }
+
+    write_box(v, b"cmpd", |v| {
+        v.extend((component_types.len() as u32).to_be_bytes());
+        for component_type in &component_types {
+            v.extend(component_type.to_be_bytes());
+        }
+
+        Ok(())
+    })?;
+
+    write_full_box(v, b"uncC", FULL_BOX_VERSION_0, FULL_BOX_FLAGS_NONE, |v| {
+        v.extend(layout.profile);
+
+        v.extend((layout.components.len() as u32).to_be_bytes());
+        for (component_type, bit_depth) in layout.components {
+            let index = component_types
+                .iter()
+                .position(|t| t == component_type)
+                .unwrap();
+            v.extend((index as u16).to_be_bytes());
+            // Bit depth minus one
+            v.push(bit_depth - 1);
+            // Format: unsigned integer
+            v.push(0);
+            // Align size: not aligned
+            v.push(0);
+        }
+
+        v.push(layout.sampling_type);
+        v.push(layout.interleave_type);
+        v.push(layout.block_size);
+
+        // components_little_endian | block_pad_lsb | block_little_endian | block_reversed |
+        // pad_unknown | reserved
+        v.push(((layout.block_little_endian as u8) << 5) | ((layout.block_reversed as u8) << 4));
+
+        // Pixel size
+        v.extend(0u32.to_be_bytes());
+        // Row align size
+        v.extend(layout.row_align_size.to_be_bytes());
+        // Tile align size
+        v.extend(0u32.to_be_bytes());
+        // Number of tile columns / rows minus one
+        v.extend(0u32.to_be_bytes());
+        v.extend(0u32.to_be_bytes());
+
+        Ok(())
+    })
+}
+
 fn av1_seq_level_idx(level: Option<&str>) -> u8 {
     match level {
         Some("2.0") => 0,
@@ -138,6 +138,9 @@ struct Stream {
     running_time_utc_time_mapping: Option<(gst::Signed<gst::ClockTime>, gst::ClockTime)>,
 
     extra_header_data: Option<Vec<u8>>,
+
+    /// Video info for uncompressed video streams.
+    video_info: Option<gst_video::VideoInfo>,
 }
 
 #[derive(Default)]
@@ -197,6 +200,68 @@ impl MP4Mux {
         Ok(())
     }
 
+    /// Copies uncompressed video frames that don't have the default memory layout, e.g.
+    /// because of padding between planes or rows, into a new buffer with the default layout.
+    fn repack_raw_video(
+        buffer: gst::Buffer,
+        sinkpad: &super::MP4MuxPad,
+        info: &gst_video::VideoInfo,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let default_layout = match buffer.meta::<gst_video::VideoMeta>() {
+            None => {
+                if buffer.size() != info.size() {
+                    gst::error!(
+                        CAT,
+                        obj: sinkpad,
+                        "Unexpected raw video buffer size {} (expected {})",
+                        buffer.size(),
+                        info.size()
+                    );
+                    return Err(gst::FlowError::Error);
+                }
+                true
+            }
+            Some(meta) => {
+                meta.offset() == info.offset()
+                    && meta.stride() == info.stride()
+                    && buffer.size() == info.size()
+            }
+        };
+
+        if default_layout {
+            return Ok(buffer);
+        }
+
+        gst::trace!(CAT, obj: sinkpad, "Repacking raw video buffer {buffer:?}");
+
+        let mut outbuf = gst::Buffer::with_size(info.size()).map_err(|_| {
+            gst::error!(CAT, obj: sinkpad, "Failed to allocate buffer");
+            gst::FlowError::Error
+        })?;
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            buffer
+                .copy_into(
+                    outbuf,
+                    gst::BufferCopyFlags::FLAGS | gst::BufferCopyFlags::TIMESTAMPS,
+                    ..,
+                )
+                .unwrap();
+        }
+
+        let frame = gst_video::VideoFrame::from_buffer_readable(buffer, info).map_err(|_| {
+            gst::error!(CAT, obj: sinkpad, "Failed to map raw video buffer");
+            gst::FlowError::Error
+        })?;
+        let mut out_frame = gst_video::VideoFrame::from_buffer_writable(outbuf, info).unwrap();
+        frame.copy(&mut out_frame).map_err(|_| {
+            gst::error!(CAT, obj: sinkpad, "Failed to copy raw video frame");
+            gst::FlowError::Error
+        })?;
+
+        Ok(out_frame.into_buffer())
+    }
+
     fn peek_buffer(
         &self,
         sinkpad: &super::MP4MuxPad,
@@ -437,6 +502,11 @@ impl MP4Mux {
             }
         };
 
+        let buffer = match stream.video_info {
+            Some(ref info) => Self::repack_raw_video(buffer, &stream.sinkpad, info)?,
+            None => buffer,
+        };
+
         Ok(Some((segment, buffer)))
     }
 
@@ -906,6 +976,7 @@ impl MP4Mux {
 
             let mut delta_frames = super::DeltaFrames::IntraOnly;
             let mut discard_header_buffers = false;
+            let mut video_info = None;
             match s.name().as_str() {
                 "video/x-h264" | "video/x-h265" => {
                     if !s.has_field_with_type("codec_data", gst::Buffer::static_type()) {
@@ -927,6 +998,21 @@ impl MP4Mux {
                 "video/x-av1" => {
                     delta_frames = super::DeltaFrames::PredictiveOnly;
                 }
+                "video/x-raw" => {
+                    let info = gst_video::VideoInfo::from_caps(&caps).map_err(|_| {
+                        gst::error!(CAT, obj: pad, "Received invalid raw video caps");
+                        gst::FlowError::NotNegotiated
+                    })?;
+                    let supported = boxes::UncompressedLayout::from_format(info.format())
+                        .map_or(false, |layout| {
+                            layout.supports_size(info.width(), info.height())
+                        });
+                    if !supported {
+                        gst::error!(CAT, obj: pad, "Unsupported raw video caps {caps:?}");
+                        return Err(gst::FlowError::NotNegotiated);
+                    }
+                    video_info = Some(info);
+                }
                 "image/jpeg" => (),
                 "audio/mpeg" => {
                     if !s.has_field_with_type("codec_data", gst::Buffer::static_type()) {
@@ -977,6 +1063,7 @@ impl MP4Mux {
                 end_pts: None,
                 running_time_utc_time_mapping: None,
                 extra_header_data: None,
+                video_info,
             });
         }
 
@@ -1586,6 +1673,17 @@ impl ElementImpl for ISOMP4Mux {
                         .field("width", gst::IntRange::new(1, u16::MAX as i32))
                         .field("height", gst::IntRange::new(1, u16::MAX as i32))
                         .build(),
+                    gst::Structure::builder("video/x-raw")
+                        .field(
+                            "format",
+                            gst::List::new([
+                                "GRAY8", "RGB", "BGR", "RGBA", "ABGR", "ARGB", "BGRA", "UYVY",
+                                "YUY2", "v210", "I420", "NV12", "NV21", "Y42B", "Y444",
+                            ]),
+                        )
+                        .field("width", gst::IntRange::new(1, u16::MAX as i32))
+                        .field("height", gst::IntRange::new(1, u16::MAX as i32))
+                        .build(),
                     gst::Structure::builder("audio/mpeg")
                         .field("mpegversion", 4i32)
                         .field("stream-format", "raw")
@@ -172,3 +172,20 @@ fn test_roundtrip_av1_aac() {
         pipeline.into_completion();
     })
 }
+
+#[test]
+fn test_uncompressed_nv12_aac() {
+    init();
+    test_basic_with(
+        "videoconvert ! video/x-raw,format=NV12",
+        "avenc_aac ! aacparse",
+        |location| {
+            let data = std::fs::read(location).expect("Failed to read MP4 file");
+
+            let contains = |fourcc: &[u8]| data.windows(4).any(|w| w == fourcc);
+            assert!(contains(b"uncv"), "No uncv sample entry");
+            assert!(contains(b"cmpd"), "No cmpd box");
+            assert!(contains(b"uncC"), "No uncC box");
+        },
+    )
+}
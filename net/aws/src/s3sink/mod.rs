@@ -29,6 +29,57 @@ pub(crate) enum OnError {
     DoNothing,
 }
 
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstS3SinkServerSideEncryption")]
+pub(crate) enum ServerSideEncryption {
+    #[default]
+    #[enum_value(name = "None: Use the bucket's default encryption.", nick = "none")]
+    None,
+    #[enum_value(name = "AES256: Use S3 managed keys (SSE-S3).", nick = "aes256")]
+    Aes256,
+    #[enum_value(name = "AwsKms: Use AWS KMS managed keys (SSE-KMS).", nick = "aws-kms")]
+    AwsKms,
+}
+
+impl ServerSideEncryption {
+    // A KMS key ID only makes sense with SSE-KMS, so imply it if nothing else was selected
+    pub(crate) fn to_sdk(
+        self,
+        kms_key_id: Option<&str>,
+    ) -> Option<aws_sdk_s3::types::ServerSideEncryption> {
+        match self {
+            ServerSideEncryption::None if kms_key_id.is_some() => {
+                Some(aws_sdk_s3::types::ServerSideEncryption::AwsKms)
+            }
+            ServerSideEncryption::None => None,
+            ServerSideEncryption::Aes256 => Some(aws_sdk_s3::types::ServerSideEncryption::Aes256),
+            ServerSideEncryption::AwsKms => Some(aws_sdk_s3::types::ServerSideEncryption::AwsKms),
+        }
+    }
+}
+
+// Server-side encryption with customer-provided keys can't be combined with any of the other
+// server-side encryption modes
+pub(crate) fn sse_customer_key(
+    server_side_encryption: ServerSideEncryption,
+    kms_key_id: Option<&str>,
+    customer_key: Option<&str>,
+) -> Result<Option<crate::s3utils::SseCustomerKey>, gst::ErrorMessage> {
+    let Some(customer_key) = customer_key else {
+        return Ok(None);
+    };
+
+    if server_side_encryption.to_sdk(kms_key_id).is_some() {
+        return Err(gst::error_msg!(
+            gst::ResourceError::Settings,
+            ["sse-customer-key can't be combined with server-side-encryption or sse-kms-key-id"]
+        ));
+    }
+
+    crate::s3utils::SseCustomerKey::new(customer_key).map(Some)
+}
+
 glib::wrapper! {
     pub struct S3Sink(ObjectSubclass<multipartsink::S3Sink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
 }
@@ -33,9 +33,12 @@ use std::sync::Mutex;
 use std::time::Duration;
 
 use crate::s3url::*;
-use crate::s3utils::{self, duration_from_millis, duration_to_millis, WaitError};
+use crate::s3utils::{
+    self, duration_from_millis, duration_to_millis, SseCustomerKey, WaitError,
+    SSE_CUSTOMER_ALGORITHM,
+};
 
-use super::OnError;
+use super::{OnError, ServerSideEncryption};
 
 const DEFAULT_FORCE_PATH_STYLE: bool = false;
 const DEFAULT_RETRY_ATTEMPTS: u32 = 5;
@@ -59,16 +62,23 @@ struct Started {
     upload_id: String,
     part_number: i64,
     completed_parts: Vec<CompletedPart>,
+    sse_customer_key: Option<SseCustomerKey>,
 }
 
 impl Started {
-    pub fn new(client: Client, buffer: Vec<u8>, upload_id: String) -> Started {
+    pub fn new(
+        client: Client,
+        buffer: Vec<u8>,
+        upload_id: String,
+        sse_customer_key: Option<SseCustomerKey>,
+    ) -> Started {
         Started {
             client,
             buffer,
             upload_id,
             part_number: 0,
             completed_parts: Vec::new(),
+            sse_customer_key,
         }
     }
 
@@ -118,6 +128,9 @@ struct Settings {
     request_timeout: Duration,
     endpoint_uri: Option<String>,
     force_path_style: bool,
+    server_side_encryption: ServerSideEncryption,
+    sse_kms_key_id: Option<String>,
+    sse_customer_key: Option<String>,
 }
 
 impl Settings {
@@ -176,6 +189,9 @@ impl Default for Settings {
             request_timeout: Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MSEC),
             endpoint_uri: None,
             force_path_style: DEFAULT_FORCE_PATH_STYLE,
+            server_side_encryption: ServerSideEncryption::default(),
+            sse_kms_key_id: None,
+            sse_customer_key: None,
         }
     }
 }
@@ -315,6 +331,8 @@ impl S3Sink {
         let key = Some(url.as_ref().unwrap().object.to_owned());
         let upload_id = Some(state.upload_id.to_owned());
 
+        let sse_customer_key = state.sse_customer_key.as_ref();
+
         let client = &state.client;
         let upload_part = client
             .upload_part()
@@ -322,7 +340,10 @@ impl S3Sink {
             .set_bucket(bucket)
             .set_key(key)
             .set_upload_id(upload_id)
-            .set_part_number(Some(part_number as i32));
+            .set_part_number(Some(part_number as i32))
+            .set_sse_customer_algorithm(sse_customer_key.map(|_| SSE_CUSTOMER_ALGORITHM.into()))
+            .set_sse_customer_key(sse_customer_key.map(|k| k.key.clone()))
+            .set_sse_customer_key_md5(sse_customer_key.map(|k| k.key_md5.clone()));
 
         Ok(upload_part)
     }
@@ -346,6 +367,7 @@ impl S3Sink {
         let key = Some(url.as_ref().unwrap().object.to_owned());
         let upload_id = Some(started_state.upload_id.to_owned());
         let multipart_upload = Some(completed_upload);
+        let sse_customer_key = started_state.sse_customer_key.as_ref();
 
         client
             .complete_multipart_upload()
@@ -353,6 +375,9 @@ impl S3Sink {
             .set_key(key)
             .set_upload_id(upload_id)
             .set_multipart_upload(multipart_upload)
+            .set_sse_customer_algorithm(sse_customer_key.map(|_| SSE_CUSTOMER_ALGORITHM.into()))
+            .set_sse_customer_key(sse_customer_key.map(|k| k.key.clone()))
+            .set_sse_customer_key_md5(sse_customer_key.map(|k| k.key_md5.clone()))
     }
 
     fn create_create_multipart_upload_request(
@@ -360,6 +385,7 @@ impl S3Sink {
         client: &Client,
         url: &GstS3Url,
         settings: &Settings,
+        sse_customer_key: Option<&SseCustomerKey>,
     ) -> CreateMultipartUploadFluentBuilder {
         let bucket = Some(url.bucket.clone());
         let key = Some(url.object.clone());
@@ -369,6 +395,10 @@ impl S3Sink {
         let content_encoding = settings.content_encoding.clone();
         let content_language = settings.content_language.clone();
         let metadata = settings.to_metadata(self);
+        let server_side_encryption = settings
+            .server_side_encryption
+            .to_sdk(settings.sse_kms_key_id.as_deref());
+        let sse_kms_key_id = settings.sse_kms_key_id.clone();
 
         client
             .create_multipart_upload()
@@ -380,6 +410,11 @@ impl S3Sink {
             .set_content_encoding(content_encoding)
             .set_content_language(content_language)
             .set_metadata(metadata)
+            .set_server_side_encryption(server_side_encryption)
+            .set_ssekms_key_id(sse_kms_key_id)
+            .set_sse_customer_algorithm(sse_customer_key.map(|_| SSE_CUSTOMER_ALGORITHM.into()))
+            .set_sse_customer_key(sse_customer_key.map(|k| k.key.clone()))
+            .set_sse_customer_key_md5(sse_customer_key.map(|k| k.key_md5.clone()))
     }
 
     fn create_abort_multipart_upload_request(
@@ -506,6 +541,12 @@ impl S3Sink {
             }
         };
 
+        let sse_customer_key = super::sse_customer_key(
+            settings.server_side_encryption,
+            settings.sse_kms_key_id.as_deref(),
+            settings.sse_customer_key.as_deref(),
+        )?;
+
         let timeout_config = s3utils::timeout_config(settings.request_timeout);
 
         let cred = match (
@@ -549,8 +590,12 @@ impl S3Sink {
 
         let client = Client::from_conf(config);
 
-        let create_multipart_req =
-            self.create_create_multipart_upload_request(&client, &s3url, &settings);
+        let create_multipart_req = self.create_create_multipart_upload_request(
+            &client,
+            &s3url,
+            &settings,
+            sse_customer_key.as_ref(),
+        );
         let create_multipart_req_future = create_multipart_req.send();
 
         let response = s3utils::wait(&self.canceller, create_multipart_req_future).map_err(
@@ -579,6 +624,7 @@ impl S3Sink {
             client,
             Vec::with_capacity(settings.buffer_size as usize),
             upload_id,
+            sse_customer_key,
         ));
 
         Ok(())
@@ -794,6 +840,21 @@ impl ObjectImpl for S3Sink {
                     .blurb("Force client to use path-style addressing for buckets")
                     .default_value(DEFAULT_FORCE_PATH_STYLE)
                     .build(),
+                glib::ParamSpecEnum::builder_with_default(
+                    "server-side-encryption",
+                    ServerSideEncryption::default(),
+                )
+                .nick("Server-side encryption")
+                .blurb("Server-side encryption algorithm to use for the uploaded object")
+                .build(),
+                glib::ParamSpecString::builder("sse-kms-key-id")
+                    .nick("SSE KMS key ID")
+                    .blurb("AWS KMS key ID to use for server-side encryption")
+                    .build(),
+                glib::ParamSpecString::builder("sse-customer-key")
+                    .nick("SSE customer key")
+                    .blurb("Base64-encoded 256-bit customer-provided key for server-side encryption")
+                    .build(),
             ]
         });
 
@@ -925,6 +986,21 @@ impl ObjectImpl for S3Sink {
             "force-path-style" => {
                 settings.force_path_style = value.get::<bool>().expect("type checked upstream");
             }
+            "server-side-encryption" => {
+                settings.server_side_encryption = value
+                    .get::<ServerSideEncryption>()
+                    .expect("type checked upstream");
+            }
+            "sse-kms-key-id" => {
+                settings.sse_kms_key_id = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
+            "sse-customer-key" => {
+                settings.sse_customer_key = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -970,6 +1046,9 @@ impl ObjectImpl for S3Sink {
             "content-encoding" => settings.content_encoding.to_value(),
             "content-language" => settings.content_language.to_value(),
             "force-path-style" => settings.force_path_style.to_value(),
+            "server-side-encryption" => settings.server_side_encryption.to_value(),
+            "sse-kms-key-id" => settings.sse_kms_key_id.to_value(),
+            "sse-customer-key" => settings.sse_customer_key.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -981,7 +1060,11 @@ impl ElementImpl for S3Sink {
     fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
         static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
             #[cfg(feature = "doc")]
-            OnError::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+            {
+                OnError::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+                ServerSideEncryption::static_type()
+                    .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+            }
             gst::subclass::ElementMetadata::new(
                 "Amazon S3 sink",
                 "Source/Network",
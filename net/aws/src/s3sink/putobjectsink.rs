@@ -28,7 +28,12 @@ use std::sync::Mutex;
 use std::time::Duration;
 
 use crate::s3url::*;
-use crate::s3utils::{self, duration_from_millis, duration_to_millis, WaitError};
+use crate::s3utils::{
+    self, duration_from_millis, duration_to_millis, SseCustomerKey, WaitError,
+    SSE_CUSTOMER_ALGORITHM,
+};
+
+use super::ServerSideEncryption;
 
 const DEFAULT_RETRY_ATTEMPTS: u32 = 5;
 const DEFAULT_FLUSH_INTERVAL_BUFFERS: u64 = 1;
@@ -46,16 +51,22 @@ struct Started {
     start_pts: Option<gst::ClockTime>,
     num_buffers: u64,
     need_flush: bool,
+    sse_customer_key: Option<SseCustomerKey>,
 }
 
 impl Started {
-    pub fn new(client: Client, buffer: Vec<u8>) -> Started {
+    pub fn new(
+        client: Client,
+        buffer: Vec<u8>,
+        sse_customer_key: Option<SseCustomerKey>,
+    ) -> Started {
         Started {
             client,
             buffer,
             start_pts: gst::ClockTime::NONE,
             num_buffers: 0,
             need_flush: false,
+            sse_customer_key,
         }
     }
 }
@@ -88,6 +99,9 @@ struct Settings {
     flush_interval_bytes: u64,
     flush_interval_time: Option<gst::ClockTime>,
     flush_on_error: bool,
+    server_side_encryption: ServerSideEncryption,
+    sse_kms_key_id: Option<String>,
+    sse_customer_key: Option<String>,
 }
 
 impl Settings {
@@ -148,6 +162,9 @@ impl Default for Settings {
             flush_interval_bytes: DEFAULT_FLUSH_INTERVAL_BYTES,
             flush_interval_time: Some(DEFAULT_FLUSH_INTERVAL_TIME),
             flush_on_error: DEFAULT_FLUSH_ON_ERROR,
+            server_side_encryption: ServerSideEncryption::default(),
+            sse_kms_key_id: None,
+            sse_customer_key: None,
         }
     }
 }
@@ -242,6 +259,11 @@ impl S3PutObjectSink {
         let content_encoding = settings.content_encoding.clone();
         let content_language = settings.content_language.clone();
         let metadata = settings.to_metadata(self);
+        let server_side_encryption = settings
+            .server_side_encryption
+            .to_sdk(settings.sse_kms_key_id.as_deref());
+        let sse_kms_key_id = settings.sse_kms_key_id.clone();
+        let sse_customer_key = state.sse_customer_key.as_ref();
 
         let client = &state.client;
 
@@ -256,6 +278,11 @@ impl S3PutObjectSink {
             .set_content_language(content_language)
             .set_key(key)
             .set_metadata(metadata)
+            .set_server_side_encryption(server_side_encryption)
+            .set_ssekms_key_id(sse_kms_key_id)
+            .set_sse_customer_algorithm(sse_customer_key.map(|_| SSE_CUSTOMER_ALGORITHM.into()))
+            .set_sse_customer_key(sse_customer_key.map(|k| k.key.clone()))
+            .set_sse_customer_key_md5(sse_customer_key.map(|k| k.key_md5.clone()))
     }
 
     fn start(&self) -> Result<(), gst::ErrorMessage> {
@@ -279,6 +306,12 @@ impl S3PutObjectSink {
             }
         };
 
+        let sse_customer_key = super::sse_customer_key(
+            settings.server_side_encryption,
+            settings.sse_kms_key_id.as_deref(),
+            settings.sse_customer_key.as_deref(),
+        )?;
+
         let timeout_config = s3utils::timeout_config(settings.request_timeout);
 
         let cred = match (
@@ -322,7 +355,7 @@ impl S3PutObjectSink {
 
         let client = Client::from_conf(config);
 
-        *state = State::Started(Started::new(client, Vec::new()));
+        *state = State::Started(Started::new(client, Vec::new(), sse_customer_key));
 
         Ok(())
     }
@@ -474,6 +507,24 @@ impl ObjectImpl for S3PutObjectSink {
                     .blurb("Force client to use path-style addressing for buckets")
                     .default_value(DEFAULT_FORCE_PATH_STYLE)
                     .build(),
+                glib::ParamSpecEnum::builder_with_default(
+                    "server-side-encryption",
+                    ServerSideEncryption::default(),
+                )
+                .nick("Server-side encryption")
+                .blurb("Server-side encryption algorithm to use for the uploaded object")
+                .mutable_ready()
+                .build(),
+                glib::ParamSpecString::builder("sse-kms-key-id")
+                    .nick("SSE KMS key ID")
+                    .blurb("AWS KMS key ID to use for server-side encryption")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sse-customer-key")
+                    .nick("SSE customer key")
+                    .blurb("Base64-encoded 256-bit customer-provided key for server-side encryption")
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -588,6 +639,21 @@ impl ObjectImpl for S3PutObjectSink {
             "force-path-style" => {
                 settings.force_path_style = value.get::<bool>().expect("type checked upstream");
             }
+            "server-side-encryption" => {
+                settings.server_side_encryption = value
+                    .get::<ServerSideEncryption>()
+                    .expect("type checked upstream");
+            }
+            "sse-kms-key-id" => {
+                settings.sse_kms_key_id = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
+            "sse-customer-key" => {
+                settings.sse_customer_key = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -625,6 +691,9 @@ impl ObjectImpl for S3PutObjectSink {
             "flush-interval-time" => settings.flush_interval_time.to_value(),
             "flush-on-error" => settings.flush_on_error.to_value(),
             "force-path-style" => settings.force_path_style.to_value(),
+            "server-side-encryption" => settings.server_side_encryption.to_value(),
+            "sse-kms-key-id" => settings.sse_kms_key_id.to_value(),
+            "sse-customer-key" => settings.sse_customer_key.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -26,7 +26,10 @@ use gst_base::subclass::base_src::CreateSuccess;
 use gst_base::subclass::prelude::*;
 
 use crate::s3url::*;
-use crate::s3utils::{self, duration_from_millis, duration_to_millis, WaitError};
+use crate::s3utils::{
+    self, duration_from_millis, duration_to_millis, SseCustomerKey, WaitError,
+    SSE_CUSTOMER_ALGORITHM,
+};
 
 const DEFAULT_FORCE_PATH_STYLE: bool = false;
 const DEFAULT_RETRY_ATTEMPTS: u32 = 5;
@@ -42,6 +45,7 @@ enum StreamingState {
         url: GstS3Url,
         client: Client,
         size: Option<u64>,
+        sse_customer_key: Option<SseCustomerKey>,
     },
 }
 
@@ -54,6 +58,7 @@ struct Settings {
     request_timeout: Duration,
     endpoint_uri: Option<String>,
     force_path_style: bool,
+    sse_customer_key: Option<String>,
 }
 
 impl Default for Settings {
@@ -68,6 +73,7 @@ impl Default for Settings {
             request_timeout: duration,
             endpoint_uri: None,
             force_path_style: DEFAULT_FORCE_PATH_STYLE,
+            sse_customer_key: None,
         }
     }
 }
@@ -168,12 +174,16 @@ impl S3Src {
         self: &S3Src,
         client: &Client,
         url: &GstS3Url,
+        sse_customer_key: Option<&SseCustomerKey>,
     ) -> Result<Option<u64>, gst::ErrorMessage> {
         let head_object = client
             .head_object()
             .set_bucket(Some(url.bucket.clone()))
             .set_key(Some(url.object.clone()))
-            .set_version_id(url.version.clone());
+            .set_version_id(url.version.clone())
+            .set_sse_customer_algorithm(sse_customer_key.map(|_| SSE_CUSTOMER_ALGORITHM.into()))
+            .set_sse_customer_key(sse_customer_key.map(|k| k.key.clone()))
+            .set_sse_customer_key_md5(sse_customer_key.map(|k| k.key_md5.clone()));
         let head_object_future = head_object.send();
 
         let output =
@@ -204,12 +214,13 @@ impl S3Src {
     fn get(self: &S3Src, offset: u64, length: u64) -> Result<Bytes, Option<gst::ErrorMessage>> {
         let state = self.state.lock().unwrap();
 
-        let (url, client) = match *state {
+        let (url, client, sse_customer_key) = match *state {
             StreamingState::Started {
                 ref url,
                 ref client,
+                ref sse_customer_key,
                 ..
-            } => (url, client),
+            } => (url, client, sse_customer_key.as_ref()),
             StreamingState::Stopped => {
                 return Err(Some(gst::error_msg!(
                     gst::LibraryError::Failed,
@@ -223,7 +234,10 @@ impl S3Src {
             .set_bucket(Some(url.bucket.clone()))
             .set_key(Some(url.object.clone()))
             .set_range(Some(format!("bytes={}-{}", offset, offset + length - 1)))
-            .set_version_id(url.version.clone());
+            .set_version_id(url.version.clone())
+            .set_sse_customer_algorithm(sse_customer_key.map(|_| SSE_CUSTOMER_ALGORITHM.into()))
+            .set_sse_customer_key(sse_customer_key.map(|k| k.key.clone()))
+            .set_sse_customer_key_md5(sse_customer_key.map(|k| k.key_md5.clone()));
 
         gst::debug!(
             CAT,
@@ -316,6 +330,11 @@ impl ObjectImpl for S3Src {
                     .blurb("Force client to use path-style addressing for buckets")
                     .default_value(DEFAULT_FORCE_PATH_STYLE)
                     .build(),
+                glib::ParamSpecString::builder("sse-customer-key")
+                    .nick("SSE customer key")
+                    .blurb("Base64-encoded 256-bit customer-provided key the object was encrypted with")
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -368,6 +387,11 @@ impl ObjectImpl for S3Src {
             "force-path-style" => {
                 settings.force_path_style = value.get::<bool>().expect("type checked upstream");
             }
+            "sse-customer-key" => {
+                settings.sse_customer_key = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -395,6 +419,7 @@ impl ObjectImpl for S3Src {
             "retry-attempts" => settings.retry_attempts.to_value(),
             "endpoint-uri" => settings.endpoint_uri.to_value(),
             "force-path-style" => settings.force_path_style.to_value(),
+            "sse-customer-key" => settings.sse_customer_key.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -491,15 +516,21 @@ impl BaseSrcImpl for S3Src {
                 ));
             }
         };
+        let sse_customer_key = settings
+            .sse_customer_key
+            .as_deref()
+            .map(SseCustomerKey::new)
+            .transpose()?;
         drop(settings);
 
         if let Ok(s3client) = self.connect(&s3url) {
-            let size = self.head(&s3client, &s3url)?;
+            let size = self.head(&s3client, &s3url, sse_customer_key.as_ref())?;
 
             *state = StreamingState::Started {
                 url: s3url,
                 client: s3client,
                 size,
+                sse_customer_key,
             };
 
             Ok(())
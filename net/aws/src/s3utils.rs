@@ -16,6 +16,7 @@ use aws_types::sdk_config::SdkConfig;
 
 use bytes::{buf::BufMut, Bytes, BytesMut};
 use futures::{future, Future};
+use md5::{Digest, Md5};
 use once_cell::sync::Lazy;
 use std::fmt;
 use std::sync::Mutex;
@@ -190,6 +191,46 @@ pub fn wait_config(
     res
 }
 
+// Only algorithm supported by S3 for server-side encryption with customer-provided keys
+pub const SSE_CUSTOMER_ALGORITHM: &str = "AES256";
+
+/// Parameters for server-side encryption with a customer-provided key (SSE-C), which have to be
+/// sent with every request accessing the object.
+#[derive(Debug, Clone)]
+pub struct SseCustomerKey {
+    pub key: String,
+    pub key_md5: String,
+}
+
+impl SseCustomerKey {
+    /// Validates the base64-encoded 256-bit key and computes its MD5 digest
+    pub fn new(key: &str) -> Result<Self, gst::ErrorMessage> {
+        let decoded = data_encoding::BASE64
+            .decode(key.trim().as_bytes())
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["Invalid SSE customer key, expected base64: {}", err]
+                )
+            })?;
+
+        if decoded.len() != 32 {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                [
+                    "Invalid SSE customer key, expected 256 bits but got {}",
+                    decoded.len() * 8
+                ]
+            ));
+        }
+
+        Ok(SseCustomerKey {
+            key: data_encoding::BASE64.encode(&decoded),
+            key_md5: data_encoding::BASE64.encode(&Md5::digest(&decoded)),
+        })
+    }
+}
+
 pub fn duration_from_millis(millis: i64) -> Duration {
     match millis {
         -1 => Duration::MAX,
@@ -37,7 +37,7 @@ use super::transcribe::{TranscriberSettings, TranscriberStream, TranscriptEvent,
 use super::translate::{TranslateLoop, TranslatedItem};
 use super::{
     AwsTranscriberResultStability, AwsTranscriberVocabularyFilterMethod,
-    TranslationTokenizationMethod, CAT,
+    TranslationTokenizationMethod, CAT, SPEAKER_META_NAME,
 };
 
 static RUNTIME: Lazy<runtime::Runtime> = Lazy::new(|| {
@@ -71,6 +71,7 @@ pub const DEFAULT_INPUT_LANG_CODE: &str = "en-US";
 const DEFAULT_STABILITY: AwsTranscriberResultStability = AwsTranscriberResultStability::Low;
 const DEFAULT_VOCABULARY_FILTER_METHOD: AwsTranscriberVocabularyFilterMethod =
     AwsTranscriberVocabularyFilterMethod::Mask;
+const DEFAULT_SHOW_SPEAKER_LABEL: bool = false;
 
 // The period at which the event loops will check if they need to push
 // anything downstream when no other events show up.
@@ -81,6 +82,12 @@ const DEFAULT_OUTPUT_LANG_CODE: Option<&str> = None;
 
 const TRANSLATION_TOKENIZATION_PROPERTY: &str = "tokenization-method";
 
+const SPEAKER_COLORS_PROPERTY: &str = "speaker-colors";
+const DEFAULT_SPEAKER_COLORS: bool = false;
+
+// Styles understood by the cea608 json format, assigned to speakers in order of appearance
+const SPEAKER_STYLES: [&str; 7] = ["White", "Yellow", "Cyan", "Green", "Magenta", "Red", "Blue"];
+
 #[derive(Debug, Clone)]
 pub(super) struct Settings {
     transcribe_latency: gst::ClockTime,
@@ -96,6 +103,7 @@ pub(super) struct Settings {
     session_token: Option<String>,
     pub vocabulary_filter: Option<String>,
     pub vocabulary_filter_method: AwsTranscriberVocabularyFilterMethod,
+    pub show_speaker_label: bool,
 }
 
 impl Default for Settings {
@@ -114,6 +122,7 @@ impl Default for Settings {
             session_token: None,
             vocabulary_filter: None,
             vocabulary_filter_method: DEFAULT_VOCABULARY_FILTER_METHOD,
+            show_speaker_label: DEFAULT_SHOW_SPEAKER_LABEL,
         }
     }
 }
@@ -123,6 +132,7 @@ struct OutputItem {
     pts: gst::ClockTime,
     duration: gst::ClockTime,
     content: String,
+    speaker: Option<String>,
 }
 
 impl From<&TranscriptItem> for OutputItem {
@@ -131,6 +141,7 @@ impl From<&TranscriptItem> for OutputItem {
             pts: item.pts,
             duration: item.duration,
             content: item.content.clone(),
+            speaker: item.speaker.clone(),
         }
     }
 }
@@ -141,6 +152,7 @@ impl From<TranslatedItem> for OutputItem {
             pts: item.pts,
             duration: item.duration,
             content: item.content,
+            speaker: item.speaker,
         }
     }
 }
@@ -337,6 +349,13 @@ impl Transcriber {
     }
 }
 
+fn is_speaker_change(last_item: &TranscriptItem, item: &TranscriptItem) -> bool {
+    match (last_item.speaker.as_ref(), item.speaker.as_ref()) {
+        (Some(last_speaker), Some(speaker)) => last_speaker != speaker,
+        _ => false,
+    }
+}
+
 #[derive(Default)]
 struct TranslateQueue {
     items: VecDeque<TranscriptItem>,
@@ -351,6 +370,19 @@ impl TranslateQueue {
     ///
     /// Returns `Some(..)` if items are ready for translation.
     fn push(&mut self, transcript_item: &TranscriptItem) -> Option<Vec<TranscriptItem>> {
+        // A change of speaker also makes a good chunk for translation,
+        // and we don't want to mix speakers in a single translation.
+        if self
+            .items
+            .back()
+            .is_some_and(|last_item| is_speaker_change(last_item, transcript_item))
+        {
+            let items = self.items.drain(..).collect();
+            self.items.push_back(transcript_item.clone());
+
+            return Some(items);
+        }
+
         // Keep track of the item individually so we can schedule translation precisely.
         self.items.push_back(transcript_item.clone());
 
@@ -390,7 +422,7 @@ impl TranslateQueue {
 
         let mut items_acc = vec![self.items.pop_front().unwrap()];
         while let Some(item) = self.items.front() {
-            if item.pts > limit {
+            if item.pts > limit || is_speaker_change(items_acc.last().unwrap(), item) {
                 break;
             }
 
@@ -766,6 +798,12 @@ impl ObjectImpl for Transcriber {
                     .blurb("Defines how filtered words will be edited, has no effect when vocabulary-filter-name isn't set")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoolean::builder("show-speaker-label")
+                    .nick("Show Speaker Label")
+                    .blurb("Identify the speakers in the transcript (speaker diarization)")
+                    .default_value(DEFAULT_SHOW_SPEAKER_LABEL)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -849,6 +887,10 @@ impl ObjectImpl for Transcriber {
                     .get::<AwsTranscriberVocabularyFilterMethod>()
                     .expect("type checked upstream");
             }
+            "show-speaker-label" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.show_speaker_label = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -909,6 +951,10 @@ impl ObjectImpl for Transcriber {
                 let settings = self.settings.lock().unwrap();
                 settings.vocabulary_filter_method.to_value()
             }
+            "show-speaker-label" => {
+                let settings = self.settings.lock().unwrap();
+                settings.show_speaker_label.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -932,8 +978,17 @@ impl ElementImpl for Transcriber {
 
     fn pad_templates() -> &'static [gst::PadTemplate] {
         static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
-            let src_caps = gst::Caps::builder("text/x-raw")
-                .field("format", "utf8")
+            let src_caps = gst::Caps::builder_full()
+                .structure(
+                    gst::Structure::builder("text/x-raw")
+                        .field("format", "utf8")
+                        .build(),
+                )
+                .structure(
+                    gst::Structure::builder("application/x-json")
+                        .field("format", "cea608")
+                        .build(),
+                )
                 .build();
             let src_pad_template = gst::PadTemplate::with_gtype(
                 "src",
@@ -1105,6 +1160,10 @@ struct TranslationPadTask {
     seqnum: gst::Seqnum,
     send_eos: bool,
     pending_translations: usize,
+    speaker_colors: bool,
+    json_output: bool,
+    // speakers in order of appearance, used to assign colors
+    speakers: Vec<String>,
 }
 
 impl TranslationPadTask {
@@ -1117,7 +1176,7 @@ impl TranslationPadTask {
         let mut to_translate_tx = None;
         let mut from_translate_rx = None;
 
-        let (our_latency, transcript_event_rx, needs_translate);
+        let (our_latency, transcript_event_rx, needs_translate, speaker_colors);
 
         {
             let elem_imp = elem.imp();
@@ -1126,6 +1185,7 @@ impl TranslationPadTask {
             let pad_settings = pad.settings.lock().unwrap();
 
             our_latency = TranslateSrcPad::our_latency(&elem_settings, &pad_settings);
+            speaker_colors = pad_settings.speaker_colors;
             if our_latency + elem_settings.lateness <= 2 * GRANULARITY {
                 let err = format!(
                     "total latency + lateness must be greater than {}",
@@ -1182,6 +1242,9 @@ impl TranslationPadTask {
             seqnum: gst::Seqnum::next(),
             send_eos: false,
             pending_translations: 0,
+            speaker_colors,
+            json_output: false,
+            speakers: Vec::new(),
         })
     }
 }
@@ -1376,14 +1439,26 @@ impl TranslationPadTask {
                     pts: item_pts,
                     mut duration,
                     content,
+                    speaker,
                 } = self.output_items.pop_front().unwrap();
 
                 let mut pts = start_time + item_pts;
 
-                let mut buf = gst::Buffer::from_mut_slice(content.clone().into_bytes());
+                let data = if self.json_output {
+                    self.to_json_lines(&content, speaker.as_deref())
+                } else {
+                    content.clone().into_bytes()
+                };
+
+                let mut buf = gst::Buffer::from_mut_slice(data);
                 {
                     let buf = buf.get_mut().unwrap();
 
+                    if let Some(ref speaker) = speaker {
+                        let mut meta = gst::meta::CustomMeta::add(buf, SPEAKER_META_NAME).unwrap();
+                        meta.mut_structure().set("speaker", speaker.as_str());
+                    }
+
                     if discont_pending {
                         buf.set_flags(gst::BufferFlags::DISCONT);
                         discont_pending = false;
@@ -1497,6 +1572,36 @@ impl TranslationPadTask {
         true
     }
 
+    /// Formats the item as cea608 json lines, with a color assigned to each speaker.
+    fn to_json_lines(&mut self, content: &str, speaker: Option<&str>) -> Vec<u8> {
+        let style = match speaker {
+            Some(speaker) => {
+                let idx = match self.speakers.iter().position(|s| s == speaker) {
+                    Some(idx) => idx,
+                    None => {
+                        self.speakers.push(speaker.to_string());
+                        self.speakers.len() - 1
+                    }
+                };
+
+                SPEAKER_STYLES[idx % SPEAKER_STYLES.len()]
+            }
+            None => SPEAKER_STYLES[0],
+        };
+
+        let lines = serde_json::json!({
+            "lines": [{
+                "chunks": [{
+                    "style": style,
+                    "underline": false,
+                    "text": content,
+                }],
+            }],
+        });
+
+        serde_json::to_vec(&lines).unwrap()
+    }
+
     fn ensure_init_events(&mut self) -> Result<(), gst::ErrorMessage> {
         if !self.send_events {
             return Ok(());
@@ -1520,9 +1625,22 @@ impl TranslationPadTask {
                     .build(),
             );
 
-            let caps = gst::Caps::builder("text/x-raw")
+            let text_caps = gst::Caps::builder("text/x-raw")
                 .field("format", "utf8")
                 .build();
+            let json_caps = gst::Caps::builder("application/x-json")
+                .field("format", "cea608")
+                .build();
+
+            // Only output json if requested and supported downstream
+            self.json_output =
+                self.speaker_colors && self.pad.obj().peer_query_accept_caps(&json_caps);
+
+            let caps = if self.json_output {
+                json_caps
+            } else {
+                text_caps
+            };
             events.push(gst::event::Caps::builder(&caps).seqnum(self.seqnum).build());
 
             events.push(
@@ -1570,6 +1688,7 @@ impl Default for TranslationPadState {
 struct TranslatePadSettings {
     language_code: Option<String>,
     tokenization_method: TranslationTokenizationMethod,
+    speaker_colors: bool,
 }
 
 #[derive(Debug, Default)]
@@ -1765,6 +1884,12 @@ impl ObjectImpl for TranslateSrcPad {
                     .default_value(TranslationTokenizationMethod::default())
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoolean::builder(SPEAKER_COLORS_PROPERTY)
+                    .nick("Speaker colors")
+                    .blurb("Color-code speakers when outputting cea608 json")
+                    .default_value(DEFAULT_SPEAKER_COLORS)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -1779,6 +1904,9 @@ impl ObjectImpl for TranslateSrcPad {
             TRANSLATION_TOKENIZATION_PROPERTY => {
                 self.settings.lock().unwrap().tokenization_method = value.get().unwrap()
             }
+            SPEAKER_COLORS_PROPERTY => {
+                self.settings.lock().unwrap().speaker_colors = value.get().unwrap()
+            }
             _ => unimplemented!(),
         }
     }
@@ -1789,6 +1917,7 @@ impl ObjectImpl for TranslateSrcPad {
             TRANSLATION_TOKENIZATION_PROPERTY => {
                 self.settings.lock().unwrap().tokenization_method.to_value()
             }
+            SPEAKER_COLORS_PROPERTY => self.settings.lock().unwrap().speaker_colors.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -23,6 +23,10 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     )
 });
 
+// Custom meta holding the `speaker` label of the transcript item when speaker
+// partitioning is enabled with the `show-speaker-label` property.
+pub const SPEAKER_META_NAME: &str = "AwsTranscriberSpeakerMeta";
+
 use aws_sdk_transcribestreaming::types::{PartialResultsStability, VocabularyFilterMethod};
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
@@ -113,6 +117,9 @@ pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
             .mark_as_plugin_api(gst::PluginAPIFlags::empty());
         TranslateSrcPad::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     }
+
+    gst::meta::CustomMeta::register(SPEAKER_META_NAME, &[]);
+
     gst::Element::register(
         Some(plugin),
         "awstranscriber",
@@ -31,6 +31,7 @@ pub struct TranscriberSettings {
     vocabulary_filter_method: types::VocabularyFilterMethod,
     session_id: Option<String>,
     results_stability: types::PartialResultsStability,
+    show_speaker_label: bool,
 }
 
 impl TranscriberSettings {
@@ -43,6 +44,7 @@ impl TranscriberSettings {
             vocabulary_filter_method: settings.vocabulary_filter_method.into(),
             session_id: settings.session_id.clone(),
             results_stability: settings.results_stability.into(),
+            show_speaker_label: settings.show_speaker_label,
         }
     }
 }
@@ -53,6 +55,7 @@ pub struct TranscriptItem {
     pub duration: gst::ClockTime,
     pub content: String,
     pub is_punctuation: bool,
+    pub speaker: Option<String>,
 }
 
 impl TranscriptItem {
@@ -75,6 +78,7 @@ impl TranscriptItem {
             duration: end_time - start_time,
             content,
             is_punctuation: matches!(item.r#type, Some(types::ItemType::Punctuation)),
+            speaker: item.speaker,
         })
     }
 }
@@ -161,6 +165,10 @@ impl TranscriberStream {
                 .vocabulary_filter_method(settings.vocabulary_filter_method);
         }
 
+        if settings.show_speaker_label {
+            transcribe_builder = transcribe_builder.show_speaker_label(true);
+        }
+
         let output = transcribe_builder
             .audio_stream(chunk_stream.into())
             .send()
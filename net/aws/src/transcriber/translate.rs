@@ -29,6 +29,7 @@ pub struct TranslatedItem {
     pub pts: gst::ClockTime,
     pub duration: gst::ClockTime,
     pub content: String,
+    pub speaker: Option<String>,
 }
 
 impl From<&TranscriptItem> for TranslatedItem {
@@ -37,6 +38,7 @@ impl From<&TranscriptItem> for TranslatedItem {
             pts: transcript_item.pts,
             duration: transcript_item.duration,
             content: transcript_item.content.clone(),
+            speaker: transcript_item.speaker.clone(),
         }
     }
 }
@@ -136,6 +138,9 @@ impl TranslateLoop {
 
             let content: String = content.join("");
 
+            // Items are split on speaker changes before being sent to translation
+            let speaker = transcript_items[0].speaker.clone();
+
             gst::debug!(CAT, imp: self.pad, "Translating {content} with {ts_duration_list:?}");
 
             let translated_text = self
@@ -168,9 +173,18 @@ impl TranslateLoop {
                         pts: first_pts,
                         duration: last_pts.saturating_sub(first_pts) + last_duration,
                         content: translated_text,
+                        speaker,
                     }]
                 }
-                Tokenization::SpanBased => span_tokenize_items(&translated_text, ts_duration_list),
+                Tokenization::SpanBased => {
+                    let mut translated_items =
+                        span_tokenize_items(&translated_text, ts_duration_list);
+                    for item in translated_items.iter_mut() {
+                        item.speaker = speaker.clone();
+                    }
+
+                    translated_items
+                }
             };
 
             gst::trace!(CAT, imp: self.pad, "Sending {translated_items:?}");
@@ -276,6 +290,7 @@ fn span_tokenize_items(
                 pts,
                 duration,
                 content,
+                speaker: None,
             });
 
             content = String::new();
@@ -303,6 +318,7 @@ fn span_tokenize_items(
                 pts,
                 duration,
                 content,
+                speaker: None,
             });
         } else if let Some(last_item) = translated_items.last_mut() {
             // No more pts and duration in the index
@@ -26,6 +26,7 @@ const DEFAULT_PLAYLIST_LENGTH: u32 = 5;
 const DEFAULT_PROGRAM_DATE_TIME_TAG: bool = false;
 const DEFAULT_CLOCK_TRACKING_FOR_PDT: bool = true;
 const DEFAULT_ENDLIST: bool = true;
+const DEFAULT_CAN_SKIP_UNTIL: u32 = 0;
 
 const SIGNAL_GET_PLAYLIST_STREAM: &str = "get-playlist-stream";
 const SIGNAL_GET_FRAGMENT_STREAM: &str = "get-fragment-stream";
@@ -47,6 +48,8 @@ struct Settings {
     enable_program_date_time: bool,
     pdt_follows_pipeline_clock: bool,
     enable_endlist: bool,
+    can_skip_until: u32,
+    delta_playlist_location: Option<String>,
 }
 
 impl Default for Settings {
@@ -59,6 +62,8 @@ impl Default for Settings {
             enable_program_date_time: DEFAULT_PROGRAM_DATE_TIME_TAG,
             pdt_follows_pipeline_clock: DEFAULT_CLOCK_TRACKING_FOR_PDT,
             enable_endlist: DEFAULT_ENDLIST,
+            can_skip_until: DEFAULT_CAN_SKIP_UNTIL,
+            delta_playlist_location: None,
         }
     }
 }
@@ -70,6 +75,7 @@ pub struct PlaylistContext {
     old_segment_locations: Vec<String>,
     segment_template: String,
     playlist_location: String,
+    delta_playlist_location: Option<String>,
     max_num_segment_files: usize,
     playlist_length: u32,
 }
@@ -141,6 +147,15 @@ impl ObjectImpl for HlsBaseSink {
                     .blurb("Write \"EXT-X-ENDLIST\" tag to manifest at the end of stream")
                     .default_value(DEFAULT_ENDLIST)
                     .build(),
+                glib::ParamSpecUInt::builder("can-skip-until")
+                    .nick("Can Skip Until")
+                    .blurb("Allow delta playlist updates skipping segments older than this many seconds from the end of the playlist (0 = disabled). Values lower than 6 times the target duration are raised to that minimum.")
+                    .default_value(DEFAULT_CAN_SKIP_UNTIL)
+                    .build(),
+                glib::ParamSpecString::builder("delta-playlist-location")
+                    .nick("Delta Playlist Location")
+                    .blurb("Location of the delta playlist to write, to be served to clients requesting _HLS_skip=YES. Defaults to the playlist location with a \"_delta\" suffix.")
+                    .build(),
             ]
         });
 
@@ -177,6 +192,14 @@ impl ObjectImpl for HlsBaseSink {
             "enable-endlist" => {
                 settings.enable_endlist = value.get().expect("type checked upstream");
             }
+            "can-skip-until" => {
+                settings.can_skip_until = value.get().expect("type checked upstream");
+            }
+            "delta-playlist-location" => {
+                settings.delta_playlist_location = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
             _ => unimplemented!(),
         };
     }
@@ -194,6 +217,8 @@ impl ObjectImpl for HlsBaseSink {
             "enable-program-date-time" => settings.enable_program_date_time.to_value(),
             "pdt-follows-pipeline-clock" => settings.pdt_follows_pipeline_clock.to_value(),
             "enable-endlist" => settings.enable_endlist.to_value(),
+            "can-skip-until" => settings.can_skip_until.to_value(),
+            "delta-playlist-location" => settings.delta_playlist_location.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -292,9 +317,29 @@ impl BinImpl for HlsBaseSink {}
 impl HlsBaseSinkImpl for HlsBaseSink {}
 
 impl HlsBaseSink {
-    pub fn open_playlist(&self, playlist: Playlist, segment_template: String) {
+    pub fn open_playlist(&self, mut playlist: Playlist, segment_template: String) {
         let mut state = self.state.lock().unwrap();
         let settings = self.settings.lock().unwrap();
+
+        let delta_playlist_location = if settings.can_skip_until > 0 {
+            playlist.set_can_skip_until(settings.can_skip_until as f32);
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Enabling delta updates, CAN-SKIP-UNTIL {:?}",
+                playlist.can_skip_until()
+            );
+
+            Some(
+                settings
+                    .delta_playlist_location
+                    .clone()
+                    .unwrap_or_else(|| delta_location(&settings.playlist_location)),
+            )
+        } else {
+            None
+        };
+
         state.context = Some(PlaylistContext {
             pdt_base_utc: None,
             pdt_base_running_time: None,
@@ -302,6 +347,7 @@ impl HlsBaseSink {
             old_segment_locations: Vec::new(),
             segment_template,
             playlist_location: settings.playlist_location.clone(),
+            delta_playlist_location,
             max_num_segment_files: settings.max_num_segment_files,
             playlist_length: settings.playlist_length,
         });
@@ -467,12 +513,38 @@ impl HlsBaseSink {
 
         // Acquires the playlist file handle so we can update it with new content. By default, this
         // is expected to be the same file every time.
+        self.write_playlist_stream(&context.playlist_location, |w| context.playlist.write_to(w))?;
+
+        if let Some(ref delta_playlist_location) = context.delta_playlist_location {
+            self.write_playlist_stream(delta_playlist_location, |w| {
+                context.playlist.write_delta_to(w)
+            })?;
+        }
+
+        if context.playlist.is_type_undefined() && context.max_num_segment_files > 0 {
+            // Cleanup old segments from filesystem
+            while context.old_segment_locations.len() > context.max_num_segment_files {
+                let old_segment_location = context.old_segment_locations.remove(0);
+                if !self
+                    .obj()
+                    .emit_by_name::<bool>(SIGNAL_DELETE_FRAGMENT, &[&old_segment_location])
+                {
+                    gst::error!(CAT, imp: self, "Could not delete fragment");
+                }
+            }
+        }
+
+        gst::debug!(CAT, imp: self, "Wrote new playlist file!");
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn write_playlist_stream<F>(&self, location: &str, write: F) -> Result<(), gst::FlowError>
+    where
+        F: FnOnce(&mut gio::OutputStreamWrite<gio::OutputStream>) -> std::io::Result<()>,
+    {
         let mut playlist_stream = self
             .obj()
-            .emit_by_name::<Option<gio::OutputStream>>(
-                SIGNAL_GET_PLAYLIST_STREAM,
-                &[&context.playlist_location],
-            )
+            .emit_by_name::<Option<gio::OutputStream>>(SIGNAL_GET_PLAYLIST_STREAM, &[&location])
             .ok_or_else(|| {
                 gst::error!(
                     CAT,
@@ -483,18 +555,15 @@ impl HlsBaseSink {
             })?
             .into_write();
 
-        context
-            .playlist
-            .write_to(&mut playlist_stream)
-            .map_err(|err| {
-                gst::error!(
-                    CAT,
-                    imp: self,
-                    "Could not write new playlist: {}",
-                    err.to_string()
-                );
-                gst::FlowError::Error
-            })?;
+        write(&mut playlist_stream).map_err(|err| {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Could not write new playlist: {}",
+                err.to_string()
+            );
+            gst::FlowError::Error
+        })?;
         playlist_stream.flush().map_err(|err| {
             gst::error!(
                 CAT,
@@ -503,23 +572,7 @@ impl HlsBaseSink {
                 err.to_string()
             );
             gst::FlowError::Error
-        })?;
-
-        if context.playlist.is_type_undefined() && context.max_num_segment_files > 0 {
-            // Cleanup old segments from filesystem
-            while context.old_segment_locations.len() > context.max_num_segment_files {
-                let old_segment_location = context.old_segment_locations.remove(0);
-                if !self
-                    .obj()
-                    .emit_by_name::<bool>(SIGNAL_DELETE_FRAGMENT, &[&old_segment_location])
-                {
-                    gst::error!(CAT, imp: self, "Could not delete fragment");
-                }
-            }
-        }
-
-        gst::debug!(CAT, imp: self, "Wrote new playlist file!");
-        Ok(gst::FlowSuccess::Ok)
+        })
     }
 
     pub fn new_file_stream<P>(&self, location: &P) -> Result<gio::OutputStream, String>
@@ -555,3 +608,22 @@ impl HlsBaseSink {
         });
     }
 }
+
+/// Derives the delta playlist location from the playlist location, e.g. `playlist.m3u8` becomes
+/// `playlist_delta.m3u8`.
+fn delta_location(playlist_location: &str) -> String {
+    let location = path::Path::new(playlist_location);
+    let stem = location
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let file_name = match location.extension() {
+        Some(extension) => format!("{stem}_delta.{}", extension.to_string_lossy()),
+        None => format!("{stem}_delta"),
+    };
+
+    location
+        .with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
@@ -6,9 +6,13 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use m3u8_rs::{MediaPlaylist, MediaPlaylistType, MediaSegment};
+use m3u8_rs::{ExtTag, MediaPlaylist, MediaPlaylistType, MediaSegment};
 use std::io::Write;
 
+/// Minimum value of `CAN-SKIP-UNTIL`, in multiples of the target duration, as required by the
+/// HLS specification.
+const MIN_CAN_SKIP_UNTIL_TARGET_DURATIONS: f32 = 6.0;
+
 /// An HLS playlist.
 ///
 /// Controls the changes that needs to happen in the playlist as new segments are added. This
@@ -21,6 +25,7 @@ pub struct Playlist {
     status: PlaylistRenderState,
     turn_vod: bool,
     is_cmaf: bool,
+    can_skip_until: Option<f32>,
 }
 
 impl Playlist {
@@ -31,9 +36,26 @@ impl Playlist {
             status: PlaylistRenderState::Init,
             turn_vod,
             is_cmaf,
+            can_skip_until: None,
         }
     }
 
+    /// Enables delta playlist updates, allowing clients to skip segments older than
+    /// `can_skip_until` seconds from the end of the playlist.
+    ///
+    /// The value is raised to six times the target duration if lower, as required by the HLS
+    /// specification.
+    pub fn set_can_skip_until(&mut self, can_skip_until: f32) {
+        self.can_skip_until = Some(
+            can_skip_until.max(MIN_CAN_SKIP_UNTIL_TARGET_DURATIONS * self.inner.target_duration),
+        );
+    }
+
+    /// Returns the effective `CAN-SKIP-UNTIL` value if delta updates are enabled.
+    pub fn can_skip_until(&self) -> Option<f32> {
+        self.can_skip_until
+    }
+
     /// Adds a new segment to the playlist.
     pub fn add_segment(&mut self, segment: MediaSegment) {
         self.start();
@@ -103,7 +125,96 @@ impl Playlist {
 
     /// Writes the playlist in textual format to the provided `Write` reference.
     pub fn write_to<T: Write>(&self, w: &mut T) -> std::io::Result<()> {
-        self.inner.write_to(w)
+        match self.server_control_tag() {
+            Some(tag) => {
+                let mut playlist = self.inner.clone();
+                if let Some(first) = playlist.segments.first_mut() {
+                    first.unknown_tags.insert(0, tag);
+                }
+                playlist.write_to(w)
+            }
+            None => self.inner.write_to(w),
+        }
+    }
+
+    /// Writes the delta update of the playlist in textual format to the provided `Write`
+    /// reference.
+    ///
+    /// Segments ending more than `CAN-SKIP-UNTIL` seconds before the end of the playlist are
+    /// replaced by an `EXT-X-SKIP` tag. If no segment can be skipped, the full playlist is
+    /// written instead.
+    pub fn write_delta_to<T: Write>(&self, w: &mut T) -> std::io::Result<()> {
+        let skipped = self.skippable_segments();
+        if skipped == 0 {
+            return self.write_to(w);
+        }
+
+        let mut playlist = self.inner.clone();
+        // EXT-X-SKIP requires protocol version 9
+        playlist.version = Some(playlist.version.unwrap_or(1).max(9));
+
+        let removed = playlist.segments.drain(0..skipped).collect::<Vec<_>>();
+        let first = &mut playlist.segments[0];
+        if self.is_cmaf && first.map.is_none() {
+            first.map = removed.into_iter().rev().find_map(|segment| segment.map);
+        }
+
+        let mut tags = Vec::with_capacity(2);
+        tags.extend(self.server_control_tag());
+        tags.push(ExtTag {
+            tag: String::from("X-SKIP"),
+            rest: Some(format!("SKIPPED-SEGMENTS={skipped}")),
+        });
+        first.unknown_tags.splice(0..0, tags);
+
+        playlist.write_to(w)
+    }
+
+    /// Returns the `EXT-X-SERVER-CONTROL` tag advertising delta updates, if enabled and the
+    /// playlist is live.
+    fn server_control_tag(&self) -> Option<ExtTag> {
+        let can_skip_until = self.can_skip_until?;
+        if !self.is_type_undefined() {
+            return None;
+        }
+
+        Some(ExtTag {
+            tag: String::from("X-SERVER-CONTROL"),
+            rest: Some(format!("CAN-SKIP-UNTIL={can_skip_until:.3}")),
+        })
+    }
+
+    /// Returns the number of segments at the start of the playlist that can be skipped in a
+    /// delta update.
+    fn skippable_segments(&self) -> usize {
+        let Some(can_skip_until) = self.can_skip_until else {
+            return 0;
+        };
+        if !self.is_type_undefined() {
+            return 0;
+        }
+
+        let total = self
+            .inner
+            .segments
+            .iter()
+            .map(|segment| segment.duration)
+            .sum::<f32>();
+        let skip_boundary = total - can_skip_until;
+
+        let mut end = 0.0;
+        let skipped = self
+            .inner
+            .segments
+            .iter()
+            .take_while(|segment| {
+                end += segment.duration;
+                end <= skip_boundary
+            })
+            .count();
+
+        // Always keep at least one segment in the delta update
+        skipped.min(self.inner.segments.len().saturating_sub(1))
     }
 }
 
@@ -440,3 +440,111 @@ segments/my-own-filename-000.ts
 
     Ok(())
 }
+
+#[test]
+fn test_hlssink3_delta_playlist_updates() -> Result<(), ()> {
+    init();
+
+    const BUFFER_NB: i32 = 250;
+
+    let pipeline = gst::Pipeline::with_name("video_pipeline");
+
+    let video_src = try_create_element!("videotestsrc");
+    video_src.set_property("is-live", true);
+    video_src.set_property("num-buffers", BUFFER_NB);
+
+    let x264enc = try_create_element!("x264enc");
+    let h264parse = try_create_element!("h264parse");
+
+    let hlssink3 = gst::ElementFactory::make("hlssink3")
+        .name("test_hlssink3")
+        .property("target-duration", 1u32)
+        .property("playlist-length", 0u32)
+        .property("can-skip-until", 1u32)
+        .build()
+        .expect("Must be able to instantiate hlssink3");
+
+    let playlist_content = Arc::new(Mutex::new(String::from("")));
+    let delta_playlist_content = Arc::new(Mutex::new(String::from("")));
+
+    hlssink3.connect("get-playlist-stream", false, {
+        let playlist_content = playlist_content.clone();
+        let delta_playlist_content = delta_playlist_content.clone();
+        move |args| {
+            let location = args[1].get::<String>().expect("No location given");
+
+            let handler = match location.as_str() {
+                "playlist.m3u8" => Arc::clone(&playlist_content),
+                "playlist_delta.m3u8" => Arc::clone(&delta_playlist_content),
+                _ => unreachable!(),
+            };
+
+            let playlist = MemoryPlaylistFile { handler };
+            playlist.clear_content();
+            let output = gio::WriteOutputStream::new(playlist);
+            Some(output.to_value())
+        }
+    });
+
+    hlssink3.connect("get-fragment-stream", false, move |_args| {
+        let stream = gio::MemoryOutputStream::new_resizable();
+        Some(stream.to_value())
+    });
+
+    hlssink3.connect("delete-fragment", false, move |_args| Some(true.to_value()));
+
+    try_or_pause!(pipeline.add_many([&video_src, &x264enc, &h264parse, &hlssink3,]));
+    try_or_pause!(gst::Element::link_many([
+        &video_src, &x264enc, &h264parse, &hlssink3
+    ]));
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let mut eos = false;
+    let bus = pipeline.bus().unwrap();
+    while let Some(msg) = bus.timed_pop(gst::ClockTime::NONE) {
+        use gst::MessageView;
+        match msg.view() {
+            MessageView::Eos(..) => {
+                eos = true;
+                break;
+            }
+            MessageView::Error(..) => unreachable!(),
+            _ => (),
+        }
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+    assert!(eos);
+
+    let count_segments = |playlist: &str| {
+        playlist
+            .lines()
+            .filter(|line| line.starts_with("#EXTINF"))
+            .count()
+    };
+
+    // CAN-SKIP-UNTIL is raised to 6 times the target duration
+    let contents = playlist_content.lock().unwrap();
+    assert!(contents.contains("#EXT-X-SERVER-CONTROL:CAN-SKIP-UNTIL=6.000\n"));
+    assert!(!contents.contains("#EXT-X-SKIP"));
+
+    let delta_contents = delta_playlist_content.lock().unwrap();
+    assert!(delta_contents.contains("#EXT-X-VERSION:9\n"));
+    assert!(delta_contents.contains("#EXT-X-SERVER-CONTROL:CAN-SKIP-UNTIL=6.000\n"));
+    assert!(delta_contents.contains("#EXT-X-ENDLIST\n"));
+
+    let skipped = delta_contents
+        .lines()
+        .find_map(|line| line.strip_prefix("#EXT-X-SKIP:SKIPPED-SEGMENTS="))
+        .expect("No EXT-X-SKIP tag in delta playlist")
+        .parse::<usize>()
+        .unwrap();
+    assert!(skipped > 0);
+    assert_eq!(
+        count_segments(&contents),
+        count_segments(&delta_contents) + skipped
+    );
+
+    Ok(())
+}
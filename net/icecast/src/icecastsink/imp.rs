@@ -0,0 +1,866 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use futures::future;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::runtime;
+
+use super::IcecastProtocol;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "icecastsink",
+        gst::DebugColorFlags::empty(),
+        Some("Icecast Sink"),
+    )
+});
+
+static RUNTIME: Lazy<runtime::Runtime> = Lazy::new(|| {
+    runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(1)
+        .thread_name("gst-icecast-runtime")
+        .build()
+        .unwrap()
+});
+
+const DEFAULT_USERNAME: &str = "source";
+const DEFAULT_PROTOCOL: IcecastProtocol = IcecastProtocol::Http;
+const DEFAULT_PUBLIC: bool = false;
+const DEFAULT_RECONNECT_INTERVAL: u32 = 2000;
+const DEFAULT_TIMEOUT: u32 = 10000;
+const DEFAULT_TITLE_UPDATES: bool = true;
+
+/// Icecast's default port, used for `icecast://` locations without port.
+const DEFAULT_PORT: u16 = 8000;
+
+/// Maximum size of the response headers of the server.
+const MAX_RESPONSE_SIZE: usize = 8192;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    location: Option<String>,
+    username: String,
+    password: Option<String>,
+    protocol: IcecastProtocol,
+    content_type: Option<String>,
+    stream_name: Option<String>,
+    description: Option<String>,
+    genre: Option<String>,
+    stream_url: Option<String>,
+    public: bool,
+    reconnect_interval: u32,
+    timeout: u32,
+    title_updates: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            location: None,
+            username: DEFAULT_USERNAME.to_string(),
+            password: None,
+            protocol: DEFAULT_PROTOCOL,
+            content_type: None,
+            stream_name: None,
+            description: None,
+            genre: None,
+            stream_url: None,
+            public: DEFAULT_PUBLIC,
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            title_updates: DEFAULT_TITLE_UPDATES,
+        }
+    }
+}
+
+#[derive(Default)]
+enum Canceller {
+    #[default]
+    None,
+    Handle(future::AbortHandle),
+    Cancelled,
+}
+
+impl Canceller {
+    fn abort(&mut self) {
+        if let Canceller::Handle(ref canceller) = *self {
+            canceller.abort();
+        }
+
+        *self = Canceller::Cancelled;
+    }
+}
+
+#[derive(Debug)]
+enum Error {
+    Io(std::io::Error),
+    Timeout,
+    InvalidResponse,
+    Status(u16, String),
+}
+
+impl Error {
+    /// Whether reconnecting can't help.
+    fn is_fatal(&self) -> bool {
+        matches!(*self, Error::Status(401, _))
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Timeout => f.write_str("timed out"),
+            Error::InvalidResponse => f.write_str("invalid response"),
+            Error::Status(code, reason) => write!(f, "server replied {code} {reason}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Server and mount point to stream to, from the location.
+#[derive(Debug, Clone)]
+struct Target {
+    host: String,
+    port: u16,
+    mount: String,
+    authorization: String,
+}
+
+impl Target {
+    fn new(settings: &Settings) -> Result<Self, gst::ErrorMessage> {
+        let Some(ref location) = settings.location else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No location specified"]
+            ));
+        };
+
+        let url = url::Url::parse(location).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["Invalid location {}: {}", location, err]
+            )
+        })?;
+
+        let port = match url.scheme() {
+            "http" | "icecast" => url.port().unwrap_or(DEFAULT_PORT),
+            scheme => {
+                return Err(gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["Unsupported scheme {}", scheme]
+                ));
+            }
+        };
+
+        let Some(host) = url.host_str() else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No host in location {}", location]
+            ));
+        };
+
+        if url.path().len() < 2 {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No mount point in location {}", location]
+            ));
+        }
+
+        let credentials = format!(
+            "{}:{}",
+            settings.username,
+            settings.password.as_deref().unwrap_or_default()
+        );
+
+        Ok(Target {
+            host: host.to_string(),
+            port,
+            mount: url.path().to_string(),
+            authorization: format!(
+                "Basic {}",
+                data_encoding::BASE64.encode(credentials.as_bytes())
+            ),
+        })
+    }
+
+    fn request_line(&self, method: &str, path: &str, version: &str) -> String {
+        format!(
+            "{method} {path} {version}\r\n\
+            Host: {}:{}\r\n\
+            Authorization: {}\r\n\
+            User-Agent: GStreamer icecastsink/{}\r\n",
+            self.host,
+            self.port,
+            self.authorization,
+            env!("CARGO_PKG_VERSION"),
+        )
+    }
+
+    async fn connect(&self) -> Result<TcpStream, Error> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        stream.set_nodelay(true)?;
+
+        Ok(stream)
+    }
+}
+
+/// Strips line breaks from header values.
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+/// Reads the response headers and checks the status code.
+async fn read_response(stream: &mut TcpStream) -> Result<(), Error> {
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+
+    while !response.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || response.len() + n > MAX_RESPONSE_SIZE {
+            return Err(Error::InvalidResponse);
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().ok_or(Error::InvalidResponse)?;
+
+    let mut parts = status_line.splitn(3, ' ');
+    let (Some(version), Some(code)) = (parts.next(), parts.next()) else {
+        return Err(Error::InvalidResponse);
+    };
+    if !version.starts_with("HTTP/") && !version.starts_with("ICE/") {
+        return Err(Error::InvalidResponse);
+    }
+    let code = code.parse::<u16>().map_err(|_| Error::InvalidResponse)?;
+
+    match code {
+        100 | 200..=299 => Ok(()),
+        _ => Err(Error::Status(
+            code,
+            parts.next().unwrap_or_default().to_string(),
+        )),
+    }
+}
+
+/// Sends a request updating the title of a stream without in-band metadata.
+async fn update_title(target: Target, title: String) -> Result<(), Error> {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query
+        .append_pair("mode", "updinfo")
+        .append_pair("mount", &target.mount)
+        .append_pair("song", &title);
+    let path = format!("/admin/metadata?{}", query.finish());
+
+    let mut request = target.request_line("GET", &path, "HTTP/1.0");
+    request.push_str("\r\n");
+
+    let mut stream = target.connect().await?;
+    stream.write_all(request.as_bytes()).await?;
+    read_response(&mut stream).await
+}
+
+struct Started {
+    target: Target,
+    settings: Settings,
+    stream: Option<TcpStream>,
+    /// Whether the stream was connected before, in which case the headers
+    /// are sent again when reconnecting
+    connected_before: bool,
+    content_type: Option<String>,
+    headers: Vec<gst::Buffer>,
+    title: Option<String>,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started(Started),
+}
+
+#[derive(Default)]
+pub struct IcecastSink {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    canceller: Mutex<Canceller>,
+}
+
+impl IcecastSink {
+    /// Runs the future to completion on the runtime, unless aborted with
+    /// `unlock()`.
+    fn wait<F, T>(&self, future: F) -> Result<T, future::Aborted>
+    where
+        F: Send + std::future::Future<Output = T>,
+        T: Send + 'static,
+    {
+        let mut canceller = self.canceller.lock().unwrap();
+        if matches!(*canceller, Canceller::Cancelled) {
+            return Err(future::Aborted);
+        }
+        let (abort_handle, abort_registration) = future::AbortHandle::new_pair();
+        *canceller = Canceller::Handle(abort_handle);
+        drop(canceller);
+
+        let res = RUNTIME.block_on(future::Abortable::new(future, abort_registration));
+
+        let mut canceller = self.canceller.lock().unwrap();
+        if matches!(*canceller, Canceller::Cancelled) {
+            return Err(future::Aborted);
+        }
+        *canceller = Canceller::None;
+
+        res
+    }
+
+    /// Connects to the server and announces the stream, sending the stream
+    /// headers again after reconnecting.
+    fn connect(&self, state: &mut Started) -> Result<(), gst::FlowError> {
+        let settings = &state.settings;
+        let target = &state.target;
+
+        let Some(ref content_type) = state.content_type else {
+            gst::element_imp_error!(self, gst::CoreError::Negotiation, ["No caps set"]);
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        let (method, version) = match settings.protocol {
+            IcecastProtocol::Http => ("PUT", "HTTP/1.1"),
+            IcecastProtocol::Source => ("SOURCE", "HTTP/1.0"),
+        };
+
+        let mut request = target.request_line(method, &target.mount, version);
+        request.push_str(&format!("Content-Type: {}\r\n", header_value(content_type)));
+        request.push_str(&format!("ice-public: {}\r\n", settings.public as u8));
+        for (name, value) in [
+            ("ice-name", &settings.stream_name),
+            ("ice-description", &settings.description),
+            ("ice-genre", &settings.genre),
+            ("ice-url", &settings.stream_url),
+        ] {
+            if let Some(value) = value {
+                request.push_str(&format!("{name}: {}\r\n", header_value(value)));
+            }
+        }
+        if settings.protocol == IcecastProtocol::Http {
+            request.push_str("Expect: 100-continue\r\n");
+        }
+        request.push_str("\r\n");
+
+        let headers = if state.connected_before {
+            state.headers.clone()
+        } else {
+            vec![]
+        };
+
+        let timeout = Duration::from_millis(settings.timeout.into());
+        let reconnect_interval = Duration::from_millis(settings.reconnect_interval.into());
+        let mut reconnecting = state.connected_before;
+
+        loop {
+            if reconnecting {
+                gst::debug!(CAT, imp: self, "Reconnecting in {:?}", reconnect_interval);
+                self.wait(tokio::time::sleep(reconnect_interval))
+                    .map_err(|_| gst::FlowError::Flushing)?;
+            }
+
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Connecting to {}:{}{}",
+                target.host,
+                target.port,
+                target.mount
+            );
+
+            let res = self.wait(tokio::time::timeout(timeout, async {
+                let mut stream = target.connect().await?;
+                stream.write_all(request.as_bytes()).await?;
+                read_response(&mut stream).await?;
+
+                for header in &headers {
+                    let map = header.map_readable().map_err(|_| Error::InvalidResponse)?;
+                    stream.write_all(&map).await?;
+                }
+
+                Ok::<_, Error>(stream)
+            }));
+
+            let err = match res {
+                Ok(Ok(Ok(stream))) => {
+                    gst::info!(CAT, imp: self, "Connected");
+                    state.stream = Some(stream);
+                    state.connected_before = true;
+                    break;
+                }
+                Ok(Ok(Err(err))) => err,
+                Ok(Err(_)) => Error::Timeout,
+                Err(_) => return Err(gst::FlowError::Flushing),
+            };
+
+            if err.is_fatal() || !state.connected_before || settings.reconnect_interval == 0 {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::OpenWrite,
+                    [
+                        "Failed to connect to {}:{}{}: {}",
+                        target.host,
+                        target.port,
+                        target.mount,
+                        err
+                    ]
+                );
+                return Err(gst::FlowError::Error);
+            }
+
+            gst::warning!(CAT, imp: self, "Failed to reconnect: {}", err);
+            reconnecting = true;
+        }
+
+        if let Some(ref title) = state.title {
+            self.send_title(state, title.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Sends the title update in the background, if supported by the format.
+    fn send_title(&self, state: &Started, title: String) {
+        if !state.settings.title_updates
+            || !matches!(
+                state.content_type.as_deref(),
+                Some("audio/mpeg") | Some("audio/aac")
+            )
+        {
+            return;
+        }
+
+        gst::debug!(CAT, imp: self, "Updating title to '{}'", title);
+
+        let element = self.obj().clone();
+        let target = state.target.clone();
+        let timeout = Duration::from_millis(state.settings.timeout.into());
+
+        RUNTIME.spawn(async move {
+            let res = match tokio::time::timeout(timeout, update_title(target, title)).await {
+                Ok(res) => res,
+                Err(_) => Err(Error::Timeout),
+            };
+
+            if let Err(err) = res {
+                gst::warning!(CAT, obj: element, "Failed to update title: {}", err);
+            }
+        });
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for IcecastSink {
+    const NAME: &'static str = "GstIcecastSink";
+    type Type = super::IcecastSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for IcecastSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("Location")
+                    .blurb("Location of the mount point, e.g. http://localhost:8000/stream.mp3")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("username")
+                    .nick("Username")
+                    .blurb("Username to authenticate with")
+                    .default_value(Some(DEFAULT_USERNAME))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("password")
+                    .nick("Password")
+                    .blurb("Password to authenticate with")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("protocol", DEFAULT_PROTOCOL)
+                    .nick("Protocol")
+                    .blurb("Protocol used to send the stream")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("content-type")
+                    .nick("Content type")
+                    .blurb("Content type of the stream (derived from the caps if not set)")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("stream-name")
+                    .nick("Stream name")
+                    .blurb("Name of the stream")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("description")
+                    .nick("Description")
+                    .blurb("Description of the stream")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("genre")
+                    .nick("Genre")
+                    .blurb("Genre of the stream")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("stream-url")
+                    .nick("Stream URL")
+                    .blurb("URL of the website of the stream")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("public")
+                    .nick("Public")
+                    .blurb("Whether the stream is listed in directories")
+                    .default_value(DEFAULT_PUBLIC)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("reconnect-interval")
+                    .nick("Reconnect interval")
+                    .blurb("Interval in ms between attempts to reconnect after losing the connection (0 = fail)")
+                    .default_value(DEFAULT_RECONNECT_INTERVAL)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("timeout")
+                    .nick("Timeout")
+                    .blurb("Timeout in ms for connecting and sending data")
+                    .minimum(1)
+                    .default_value(DEFAULT_TIMEOUT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("title-updates")
+                    .nick("Title updates")
+                    .blurb("Update the title of MP3 and AAC streams from the title and artist tags")
+                    .default_value(DEFAULT_TITLE_UPDATES)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => {
+                settings.location = value.get().expect("type checked upstream");
+            }
+            "username" => {
+                settings.username = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_USERNAME.to_string());
+            }
+            "password" => {
+                settings.password = value.get().expect("type checked upstream");
+            }
+            "protocol" => {
+                settings.protocol = value.get().expect("type checked upstream");
+            }
+            "content-type" => {
+                settings.content_type = value.get().expect("type checked upstream");
+            }
+            "stream-name" => {
+                settings.stream_name = value.get().expect("type checked upstream");
+            }
+            "description" => {
+                settings.description = value.get().expect("type checked upstream");
+            }
+            "genre" => {
+                settings.genre = value.get().expect("type checked upstream");
+            }
+            "stream-url" => {
+                settings.stream_url = value.get().expect("type checked upstream");
+            }
+            "public" => {
+                settings.public = value.get().expect("type checked upstream");
+            }
+            "reconnect-interval" => {
+                settings.reconnect_interval = value.get().expect("type checked upstream");
+            }
+            "timeout" => {
+                settings.timeout = value.get().expect("type checked upstream");
+            }
+            "title-updates" => {
+                settings.title_updates = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => settings.location.to_value(),
+            "username" => settings.username.to_value(),
+            "password" => settings.password.to_value(),
+            "protocol" => settings.protocol.to_value(),
+            "content-type" => settings.content_type.to_value(),
+            "stream-name" => settings.stream_name.to_value(),
+            "description" => settings.description.to_value(),
+            "genre" => settings.genre.to_value(),
+            "stream-url" => settings.stream_url.to_value(),
+            "public" => settings.public.to_value(),
+            "reconnect-interval" => settings.reconnect_interval.to_value(),
+            "timeout" => settings.timeout.to_value(),
+            "title-updates" => settings.title_updates.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for IcecastSink {}
+
+impl ElementImpl for IcecastSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Icecast Sink",
+                "Sink/Network",
+                "Streams to an Icecast server",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder_full()
+                .structure(
+                    gst::Structure::builder("audio/mpeg")
+                        .field("mpegversion", 1i32)
+                        .build(),
+                )
+                .structure(
+                    gst::Structure::builder("audio/mpeg")
+                        .field("mpegversion", gst::List::new([2i32, 4i32]))
+                        .field("stream-format", "adts")
+                        .build(),
+                )
+                .structure(gst::Structure::new_empty("application/ogg"))
+                .structure(gst::Structure::new_empty("audio/ogg"))
+                .structure(gst::Structure::new_empty("video/ogg"))
+                .structure(gst::Structure::new_empty("audio/webm"))
+                .structure(gst::Structure::new_empty("video/webm"))
+                .build();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for IcecastSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+        let target = Target::new(&settings)?;
+
+        gst::info!(
+            CAT,
+            imp: self,
+            "Started, streaming to {}:{}{}",
+            target.host,
+            target.port,
+            target.mount
+        );
+
+        *self.state.lock().unwrap() = State::Started(Started {
+            target,
+            content_type: settings.content_type.clone(),
+            settings,
+            stream: None,
+            connected_before: false,
+            headers: vec![],
+            title: None,
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::Stopped;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let mut state = self.state.lock().unwrap();
+        let State::Started(ref mut state) = *state else {
+            return Err(gst::loggable_error!(CAT, "Not started yet"));
+        };
+
+        let s = caps.structure(0).unwrap();
+
+        if state.settings.content_type.is_none() {
+            let content_type = match s.name().as_str() {
+                "audio/mpeg" if s.get::<i32>("mpegversion") == Ok(1) => "audio/mpeg",
+                "audio/mpeg" => "audio/aac",
+                name => name,
+            };
+
+            if state.stream.is_some() && state.content_type.as_deref() != Some(content_type) {
+                return Err(gst::loggable_error!(
+                    CAT,
+                    "Can't change content type to {} while streaming",
+                    content_type
+                ));
+            }
+
+            state.content_type = Some(content_type.to_string());
+        }
+
+        // Sent again after reconnecting, e.g. the Ogg headers
+        state.headers = s
+            .get::<gst::ArrayRef>("streamheader")
+            .map(|headers| {
+                headers
+                    .iter()
+                    .filter_map(|header| header.get::<gst::Buffer>().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Content type {:?} with {} stream headers",
+            state.content_type,
+            state.headers.len()
+        );
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let State::Started(ref mut state) = *state else {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        let timeout = Duration::from_millis(state.settings.timeout.into());
+
+        loop {
+            if state.stream.is_none() {
+                self.connect(state)?;
+            }
+            let mut stream = state.stream.take().unwrap();
+
+            gst::trace!(CAT, imp: self, "Sending {} bytes", map.len());
+
+            let data = map.as_slice();
+            let res = self.wait(async {
+                let res = tokio::time::timeout(timeout, stream.write_all(data)).await;
+                (stream, res)
+            });
+
+            let err = match res {
+                Ok((stream, Ok(Ok(())))) => {
+                    state.stream = Some(stream);
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+                Ok((_, Ok(Err(err)))) => Error::Io(err),
+                Ok((_, Err(_))) => Error::Timeout,
+                // The buffer was possibly sent partially, reconnect
+                Err(_) => return Err(gst::FlowError::Flushing),
+            };
+
+            if state.settings.reconnect_interval == 0 {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Write,
+                    ["Failed to send data: {}", err]
+                );
+                return Err(gst::FlowError::Error);
+            }
+
+            gst::warning!(CAT, imp: self, "Connection lost: {}", err);
+        }
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Tag(ev) = event.view() {
+            let tags = ev.tag();
+            let title = tags.get::<gst::tags::Title>().map(|v| v.get().to_string());
+            let artist = tags.get::<gst::tags::Artist>().map(|v| v.get().to_string());
+
+            let title = match (artist, title) {
+                (Some(artist), Some(title)) => Some(format!("{artist} - {title}")),
+                (None, Some(title)) => Some(title),
+                _ => None,
+            };
+
+            if let Some(title) = title {
+                let mut state = self.state.lock().unwrap();
+                if let State::Started(ref mut state) = *state {
+                    if state.title.as_ref() != Some(&title) {
+                        // Otherwise sent once connected
+                        if state.stream.is_some() {
+                            self.send_title(state, title.clone());
+                        }
+                        state.title = Some(title);
+                    }
+                }
+            }
+        }
+
+        self.parent_event(event)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.canceller.lock().unwrap().abort();
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        let mut canceller = self.canceller.lock().unwrap();
+        if matches!(*canceller, Canceller::Cancelled) {
+            *canceller = Canceller::None;
+        }
+
+        Ok(())
+    }
+}
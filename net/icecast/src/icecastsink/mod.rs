@@ -0,0 +1,61 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-icecastsink:
+ *
+ * `icecastsink` streams Ogg, WebM, MP3 or AAC to a mount point of an Icecast
+ * server, e.g. for webradio streaming. The stream is announced with an HTTP
+ * `PUT` request as supported since Icecast 2.4, or with the `SOURCE` request
+ * of older servers, authenticating with `username` and `password`.
+ *
+ * The stream is sent at the pace of the clock, as listeners are served the
+ * data as it arrives. Once the connection is lost, it's reestablished every
+ * `reconnect-interval` milliseconds, and the stream headers from the caps,
+ * e.g. of `oggmux`, are sent again.
+ *
+ * For MP3 and AAC streams, the title displayed by players is updated from the
+ * title and artist tags. Ogg and WebM streams carry their metadata in-band.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 uridecodebin uri=file:///music/playlist.ogg ! audioconvert ! \
+ *     vorbisenc ! oggmux ! icecastsink location=http://radio.local:8000/live.ogg password=hackme
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstIcecastProtocol")]
+pub enum IcecastProtocol {
+    #[enum_value(name = "HTTP: HTTP PUT request, Icecast 2.4 and newer.", nick = "http")]
+    Http = 0,
+    #[enum_value(name = "Source: Legacy SOURCE request.", nick = "source")]
+    Source = 1,
+}
+
+glib::wrapper! {
+    pub struct IcecastSink(ObjectSubclass<imp::IcecastSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    IcecastProtocol::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "icecastsink",
+        gst::Rank::NONE,
+        IcecastSink::static_type(),
+    )
+}
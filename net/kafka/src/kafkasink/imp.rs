@@ -0,0 +1,581 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use rdkafka::config::{ClientConfig, RDKafkaLogLevel};
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer};
+use rdkafka::ClientContext;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::KafkaCompression;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "kafkasink",
+        gst::DebugColorFlags::empty(),
+        Some("Apache Kafka Sink"),
+    )
+});
+
+const DEFAULT_BROKERS: &str = "localhost:9092";
+const DEFAULT_KEY_FIELD: &str = "key";
+const DEFAULT_COMPRESSION: KafkaCompression = KafkaCompression::None;
+const DEFAULT_BATCH_SIZE: u32 = 1_000_000;
+const DEFAULT_LINGER: u32 = 5;
+const DEFAULT_MESSAGE_TIMEOUT: u32 = 300_000;
+const DEFAULT_FLUSH_TIMEOUT: u32 = 10_000;
+
+/// Interval at which producing is retried while the producer queue is full.
+const PRODUCE_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone)]
+struct Settings {
+    brokers: String,
+    topic: Option<String>,
+    key: Option<String>,
+    key_meta: Option<String>,
+    key_field: String,
+    compression: KafkaCompression,
+    batch_size: u32,
+    linger: u32,
+    message_timeout: u32,
+    flush_timeout: u32,
+    config: Option<gst::Structure>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            brokers: DEFAULT_BROKERS.to_string(),
+            topic: None,
+            key: None,
+            key_meta: None,
+            key_field: DEFAULT_KEY_FIELD.to_string(),
+            compression: DEFAULT_COMPRESSION,
+            batch_size: DEFAULT_BATCH_SIZE,
+            linger: DEFAULT_LINGER,
+            message_timeout: DEFAULT_MESSAGE_TIMEOUT,
+            flush_timeout: DEFAULT_FLUSH_TIMEOUT,
+            config: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    produced: u64,
+    delivered: u64,
+    failed: u64,
+}
+
+/// Receives the delivery reports and logs of the producer.
+struct Context {
+    element: glib::WeakRef<super::KafkaSink>,
+}
+
+impl ClientContext for Context {
+    fn log(&self, level: RDKafkaLogLevel, fac: &str, log_message: &str) {
+        let Some(element) = self.element.upgrade() else {
+            return;
+        };
+
+        let level = match level {
+            RDKafkaLogLevel::Emerg
+            | RDKafkaLogLevel::Alert
+            | RDKafkaLogLevel::Critical
+            | RDKafkaLogLevel::Error => gst::DebugLevel::Error,
+            RDKafkaLogLevel::Warning => gst::DebugLevel::Warning,
+            RDKafkaLogLevel::Notice | RDKafkaLogLevel::Info => gst::DebugLevel::Info,
+            RDKafkaLogLevel::Debug => gst::DebugLevel::Debug,
+        };
+
+        gst::log_with_level!(CAT, level: level, obj: element, "{}: {}", fac, log_message);
+    }
+}
+
+impl ProducerContext for Context {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, delivery_result: &DeliveryResult<'_>, _delivery_opaque: ()) {
+        if let Some(element) = self.element.upgrade() {
+            element.imp().delivered(delivery_result);
+        }
+    }
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started {
+        producer: ThreadedProducer<Context>,
+        topic: String,
+    },
+}
+
+#[derive(Default)]
+pub struct KafkaSink {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    stats: Mutex<Stats>,
+    /// First delivery error since starting
+    delivery_error: Mutex<Option<KafkaError>>,
+    flushing: AtomicBool,
+}
+
+impl KafkaSink {
+    fn delivered(&self, delivery_result: &DeliveryResult<'_>) {
+        let mut stats = self.stats.lock().unwrap();
+
+        match delivery_result {
+            Ok(_) => {
+                stats.delivered += 1;
+            }
+            Err((err, _)) => {
+                stats.failed += 1;
+                drop(stats);
+
+                gst::warning!(CAT, imp: self, "Failed to deliver message: {}", err);
+
+                let mut delivery_error = self.delivery_error.lock().unwrap();
+                if delivery_error.is_none() {
+                    *delivery_error = Some(err.clone());
+                    drop(delivery_error);
+
+                    // The error is only returned from the streaming thread with the next buffer,
+                    // let the application know right away
+                    gst::element_imp_warning!(
+                        self,
+                        gst::ResourceError::Write,
+                        ["Failed to deliver message: {}", err]
+                    );
+                }
+            }
+        }
+    }
+
+    /// Fails if a message couldn't be delivered since starting.
+    fn check_delivery(&self) -> Result<(), gst::FlowError> {
+        if let Some(ref err) = *self.delivery_error.lock().unwrap() {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Write,
+                ["Failed to deliver message: {}", err]
+            );
+            return Err(gst::FlowError::Error);
+        }
+
+        Ok(())
+    }
+
+    /// Key of the message, from the custom meta or else the `key` property.
+    fn key(&self, settings: &Settings, buffer: &gst::BufferRef) -> Option<Vec<u8>> {
+        if let Some(ref key_meta) = settings.key_meta {
+            let key = gst::meta::CustomMeta::from_buffer(buffer, key_meta)
+                .ok()
+                .and_then(|meta| {
+                    let value = meta.structure().value(&settings.key_field).ok()?;
+
+                    match value.get::<&str>() {
+                        Ok(key) => Some(key.as_bytes().to_vec()),
+                        Err(_) => value.serialize().ok().map(|key| key.as_bytes().to_vec()),
+                    }
+                });
+
+            if key.is_some() {
+                return key;
+            }
+
+            gst::trace!(
+                CAT,
+                imp: self,
+                "No {} field in {} meta",
+                settings.key_field,
+                key_meta
+            );
+        }
+
+        settings.key.as_ref().map(|key| key.as_bytes().to_vec())
+    }
+
+    fn flush(&self) -> Result<(), gst::ErrorMessage> {
+        let flush_timeout = self.settings.lock().unwrap().flush_timeout;
+
+        let state = self.state.lock().unwrap();
+        let State::Started { ref producer, .. } = *state else {
+            return Ok(());
+        };
+
+        gst::debug!(CAT, imp: self, "Waiting for outstanding messages");
+
+        producer
+            .flush(Duration::from_millis(flush_timeout.into()))
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Write,
+                    ["Failed to flush outstanding messages: {}", err]
+                )
+            })
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for KafkaSink {
+    const NAME: &'static str = "GstKafkaSink";
+    type Type = super::KafkaSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for KafkaSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("brokers")
+                    .nick("Brokers")
+                    .blurb("Comma separated list of host:port of the initial brokers")
+                    .default_value(Some(DEFAULT_BROKERS))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("topic")
+                    .nick("Topic")
+                    .blurb("Topic to produce the buffers to")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("key")
+                    .nick("Key")
+                    .blurb("Key of the messages, unless taken from the buffer meta")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("key-meta")
+                    .nick("Key meta")
+                    .blurb("Name of the custom meta to take the message keys from")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("key-field")
+                    .nick("Key field")
+                    .blurb("Field of the custom meta holding the message key")
+                    .default_value(Some(DEFAULT_KEY_FIELD))
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("compression", DEFAULT_COMPRESSION)
+                    .nick("Compression")
+                    .blurb("Compression codec of the message batches")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("batch-size")
+                    .nick("Batch size")
+                    .blurb("Maximum size in bytes of the message batches")
+                    .minimum(1)
+                    .default_value(DEFAULT_BATCH_SIZE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("linger")
+                    .nick("Linger")
+                    .blurb("Time in ms to wait for more messages before sending a batch")
+                    .maximum(900_000)
+                    .default_value(DEFAULT_LINGER)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("message-timeout")
+                    .nick("Message timeout")
+                    .blurb("Time in ms after which undelivered messages fail (0 = infinite)")
+                    .default_value(DEFAULT_MESSAGE_TIMEOUT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("flush-timeout")
+                    .nick("Flush timeout")
+                    .blurb("Time in ms to wait for the outstanding messages to be delivered on EOS and when stopping")
+                    .default_value(DEFAULT_FLUSH_TIMEOUT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("config")
+                    .nick("Configuration")
+                    .blurb("Additional librdkafka configuration properties, overriding the other properties")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Number of produced, delivered and failed messages")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "brokers" => {
+                settings.brokers = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_BROKERS.to_string());
+            }
+            "topic" => {
+                settings.topic = value.get().expect("type checked upstream");
+            }
+            "key" => {
+                settings.key = value.get().expect("type checked upstream");
+            }
+            "key-meta" => {
+                settings.key_meta = value.get().expect("type checked upstream");
+            }
+            "key-field" => {
+                settings.key_field = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_KEY_FIELD.to_string());
+            }
+            "compression" => {
+                settings.compression = value.get().expect("type checked upstream");
+            }
+            "batch-size" => {
+                settings.batch_size = value.get().expect("type checked upstream");
+            }
+            "linger" => {
+                settings.linger = value.get().expect("type checked upstream");
+            }
+            "message-timeout" => {
+                settings.message_timeout = value.get().expect("type checked upstream");
+            }
+            "flush-timeout" => {
+                settings.flush_timeout = value.get().expect("type checked upstream");
+            }
+            "config" => {
+                settings.config = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        if pspec.name() == "stats" {
+            let stats = self.stats.lock().unwrap();
+
+            return gst::Structure::builder("application/x-kafkasink-stats")
+                .field("messages-produced", stats.produced)
+                .field("messages-delivered", stats.delivered)
+                .field("messages-failed", stats.failed)
+                .build()
+                .to_value();
+        }
+
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "brokers" => settings.brokers.to_value(),
+            "topic" => settings.topic.to_value(),
+            "key" => settings.key.to_value(),
+            "key-meta" => settings.key_meta.to_value(),
+            "key-field" => settings.key_field.to_value(),
+            "compression" => settings.compression.to_value(),
+            "batch-size" => settings.batch_size.to_value(),
+            "linger" => settings.linger.to_value(),
+            "message-timeout" => settings.message_timeout.to_value(),
+            "flush-timeout" => settings.flush_timeout.to_value(),
+            "config" => settings.config.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for KafkaSink {}
+
+impl ElementImpl for KafkaSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Apache Kafka Sink",
+                "Sink/Network",
+                "Produces buffers as messages to an Apache Kafka topic",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for KafkaSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let Some(topic) = settings.topic else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No topic specified"]
+            ));
+        };
+
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", &settings.brokers)
+            .set("compression.type", settings.compression.as_str())
+            .set("batch.size", settings.batch_size.to_string())
+            .set("linger.ms", settings.linger.to_string())
+            .set("message.timeout.ms", settings.message_timeout.to_string());
+
+        if let Some(ref extra) = settings.config {
+            for (name, value) in extra.iter() {
+                let value = match value.get::<&str>() {
+                    Ok(value) => value.to_string(),
+                    Err(_) => value.serialize().map(String::from).map_err(|_| {
+                        gst::error_msg!(
+                            gst::ResourceError::Settings,
+                            ["Invalid value for configuration property {}", name]
+                        )
+                    })?,
+                };
+                config.set(name.as_str(), value);
+            }
+        }
+
+        let context = Context {
+            element: self.obj().downgrade(),
+        };
+
+        let producer = config
+            .create_with_context::<_, ThreadedProducer<_>>(context)
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["Failed to create producer: {}", err]
+                )
+            })?;
+
+        *self.stats.lock().unwrap() = Stats::default();
+        *self.delivery_error.lock().unwrap() = None;
+        *self.state.lock().unwrap() = State::Started { producer, topic };
+
+        gst::info!(CAT, imp: self, "Started, producing to {}", settings.brokers);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        // Messages not delivered within the timeout are lost
+        if let Err(err) = self.flush() {
+            gst::warning!(CAT, imp: self, "{}", err);
+        }
+
+        *self.state.lock().unwrap() = State::Stopped;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        self.check_delivery()?;
+
+        let key = {
+            let settings = self.settings.lock().unwrap();
+            self.key(&settings, buffer)
+        };
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        let state = self.state.lock().unwrap();
+        let State::Started {
+            ref producer,
+            ref topic,
+        } = *state
+        else {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        let mut record = BaseRecord::<[u8], [u8]>::to(topic).payload(map.as_slice());
+        if let Some(ref key) = key {
+            record = record.key(key.as_slice());
+        }
+
+        gst::trace!(CAT, imp: self, "Producing message of {} bytes", map.len());
+
+        loop {
+            match producer.send(record) {
+                Ok(()) => break,
+                // Retry once the producer catches up, see batch-size and linger
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), unsent)) => {
+                    record = unsent;
+                }
+                Err((err, _)) => {
+                    gst::element_imp_error!(
+                        self,
+                        gst::ResourceError::Write,
+                        ["Failed to produce message: {}", err]
+                    );
+                    return Err(gst::FlowError::Error);
+                }
+            }
+
+            if self.flushing.load(Ordering::SeqCst) {
+                return Err(gst::FlowError::Flushing);
+            }
+
+            std::thread::sleep(PRODUCE_RETRY_INTERVAL);
+        }
+
+        self.stats.lock().unwrap().produced += 1;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            if let Err(err) = self.flush() {
+                self.post_error_message(err);
+                return false;
+            }
+
+            if self.check_delivery().is_err() {
+                return false;
+            }
+        }
+
+        self.parent_event(event)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-kafkasink:
+ *
+ * `kafkasink` produces each buffer as a message to the Apache Kafka `topic`,
+ * e.g. JSON or serialized metadata of an analytics pipeline.
+ *
+ * The message key is taken from the `key-field` field of the custom meta named
+ * `key-meta` on the buffer if any, and otherwise from the `key` property.
+ *
+ * Messages are batched for up to `linger` milliseconds or `batch-size` bytes,
+ * and the batches compressed according to `compression`. Any other librdkafka
+ * configuration property can be set with the `config` structure, e.g.
+ * `config="config,acks=all,enable.idempotence=true"`.
+ *
+ * Messages are delivered asynchronously. Once a message failed to be
+ * delivered, i.e. it wasn't acknowledged by the broker within
+ * `message-timeout` milliseconds, a warning is posted and the following buffers
+ * are refused with an error. On EOS, the outstanding messages are waited for up to
+ * `flush-timeout` milliseconds.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 filesrc location=detections.json ! jsonparse ! \
+ *     kafkasink brokers=kafka1:9092,kafka2:9092 topic=detections key=camera-1 compression=zstd
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstKafkaCompression")]
+pub(crate) enum KafkaCompression {
+    #[enum_value(name = "None: No compression.", nick = "none")]
+    None,
+    #[enum_value(name = "Gzip: Gzip compression.", nick = "gzip")]
+    Gzip,
+    #[enum_value(name = "Snappy: Snappy compression.", nick = "snappy")]
+    Snappy,
+    #[enum_value(name = "LZ4: LZ4 compression.", nick = "lz4")]
+    Lz4,
+    #[enum_value(name = "Zstd: Zstandard compression.", nick = "zstd")]
+    Zstd,
+}
+
+impl KafkaCompression {
+    /// Value of the librdkafka `compression.type` property.
+    fn as_str(&self) -> &'static str {
+        match self {
+            KafkaCompression::None => "none",
+            KafkaCompression::Gzip => "gzip",
+            KafkaCompression::Snappy => "snappy",
+            KafkaCompression::Lz4 => "lz4",
+            KafkaCompression::Zstd => "zstd",
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct KafkaSink(ObjectSubclass<imp::KafkaSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    KafkaCompression::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "kafkasink",
+        gst::Rank::NONE,
+        KafkaSink::static_type(),
+    )
+}
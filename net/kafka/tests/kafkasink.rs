@@ -0,0 +1,204 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::mocking::MockCluster;
+use rdkafka::Message;
+
+use std::time::{Duration, Instant};
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstkafka::plugin_register_static().expect("kafka test");
+        gst::meta::CustomMeta::register("KafkaTestMeta", &[]);
+    });
+}
+
+/// Consumes `count` messages of `topic` as `(key, payload)`.
+fn consume(brokers: &str, topic: &str, count: usize) -> Vec<(Option<Vec<u8>>, Vec<u8>)> {
+    let consumer: BaseConsumer = ClientConfig::new()
+        .set("bootstrap.servers", brokers)
+        .set("group.id", "kafkasink-test")
+        .set("auto.offset.reset", "earliest")
+        .create()
+        .unwrap();
+    consumer.subscribe(&[topic]).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut messages = Vec::new();
+    while messages.len() < count {
+        assert!(Instant::now() < deadline, "Timed out waiting for messages");
+
+        let Some(message) = consumer.poll(Duration::from_millis(100)) else {
+            continue;
+        };
+        let message = message.unwrap();
+        messages.push((
+            message.key().map(|key| key.to_vec()),
+            message.payload().unwrap_or_default().to_vec(),
+        ));
+    }
+
+    messages
+}
+
+fn produce(brokers: &str, topic: &str, compression: &str) {
+    let mut h = gst_check::Harness::new("kafkasink");
+    {
+        let sink = h.element().unwrap();
+        sink.set_property("brokers", brokers);
+        sink.set_property("topic", topic);
+        sink.set_property("key", "camera-1");
+        sink.set_property("key-meta", "KafkaTestMeta");
+        sink.set_property_from_str("compression", compression);
+        sink.set_property("linger", 0u32);
+    }
+    h.set_src_caps_str("application/json");
+    h.play();
+
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(br#"{"detections": [1]}"#)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    // Key from the custom meta instead of the property
+    let mut buffer = gst::Buffer::from_slice(br#"{"detections": [2]}"#);
+    {
+        let buffer = buffer.get_mut().unwrap();
+        let mut meta = gst::meta::CustomMeta::add(buffer, "KafkaTestMeta").unwrap();
+        meta.mut_structure().set("key", "camera-2");
+    }
+    assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+
+    // Waits for the outstanding messages
+    assert!(h.push_event(gst::event::Eos::new()));
+
+    let stats = h.element().unwrap().property::<gst::Structure>("stats");
+    assert_eq!(stats.get::<u64>("messages-produced").unwrap(), 2);
+    assert_eq!(stats.get::<u64>("messages-delivered").unwrap(), 2);
+    assert_eq!(stats.get::<u64>("messages-failed").unwrap(), 0);
+}
+
+#[test]
+fn test_requires_topic() {
+    init();
+
+    let sink = gst::ElementFactory::make("kafkasink").build().unwrap();
+    assert!(sink.set_state(gst::State::Paused).is_err());
+    sink.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_invalid_config() {
+    init();
+
+    let sink = gst::ElementFactory::make("kafkasink")
+        .property("topic", "test")
+        .property(
+            "config",
+            gst::Structure::builder("config")
+                .field("no.such.property", "1")
+                .build(),
+        )
+        .build()
+        .unwrap();
+    assert!(sink.set_state(gst::State::Paused).is_err());
+    sink.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_produce() {
+    init();
+
+    let cluster = MockCluster::new(1).unwrap();
+    cluster.create_topic("detections", 1, 1).unwrap();
+    let brokers = cluster.bootstrap_servers();
+
+    produce(&brokers, "detections", "none");
+
+    assert_eq!(
+        consume(&brokers, "detections", 2),
+        vec![
+            (
+                Some(b"camera-1".to_vec()),
+                br#"{"detections": [1]}"#.to_vec()
+            ),
+            (
+                Some(b"camera-2".to_vec()),
+                br#"{"detections": [2]}"#.to_vec()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_produce_compressed() {
+    init();
+
+    let cluster = MockCluster::new(1).unwrap();
+    cluster.create_topic("detections", 1, 1).unwrap();
+    let brokers = cluster.bootstrap_servers();
+
+    produce(&brokers, "detections", "gzip");
+
+    let messages = consume(&brokers, "detections", 2);
+    assert_eq!(messages[0].1, br#"{"detections": [1]}"#.to_vec());
+    assert_eq!(messages[1].1, br#"{"detections": [2]}"#.to_vec());
+}
+
+#[test]
+fn test_unreachable_broker() {
+    init();
+
+    let mut h = gst_check::Harness::new("kafkasink");
+    let bus = gst::Bus::new();
+    {
+        let sink = h.element().unwrap();
+        sink.set_bus(Some(&bus));
+        sink.set_property("brokers", "127.0.0.1:1");
+        sink.set_property("topic", "test");
+        sink.set_property("key", "key");
+        sink.set_property("message-timeout", 500u32);
+        sink.set_property("flush-timeout", 100u32);
+    }
+    h.set_src_caps_str("application/json");
+    h.play();
+
+    // Queued until delivered
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(br#"{"detections": []}"#)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    let stats = h.element().unwrap().property::<gst::Structure>("stats");
+    assert_eq!(stats.get::<u64>("messages-produced").unwrap(), 1);
+
+    // Fails to be delivered
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(30),
+            &[gst::MessageType::Warning],
+        )
+        .expect("No delivery failure");
+    let gst::MessageView::Warning(warning) = msg.view() else {
+        unreachable!();
+    };
+    assert!(warning.error().matches(gst::ResourceError::Write));
+
+    let stats = h.element().unwrap().property::<gst::Structure>("stats");
+    assert_eq!(stats.get::<u64>("messages-failed").unwrap(), 1);
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(br#"{"detections": []}"#)),
+        Err(gst::FlowError::Error)
+    );
+}
@@ -0,0 +1,406 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use rumqttc::{Connection, Event, MqttOptions, Outgoing, Packet, QoS, TlsConfiguration, Transport};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) const DEFAULT_HOST: &str = "localhost";
+pub(crate) const DEFAULT_PORT: u16 = 1883;
+pub(crate) const DEFAULT_QOS: MqttQos = MqttQos::AtMostOnce;
+pub(crate) const DEFAULT_KEEP_ALIVE: u32 = 60;
+pub(crate) const DEFAULT_RECONNECT_INTERVAL: u32 = 1000;
+pub(crate) const DEFAULT_CLEAN_SESSION: bool = true;
+pub(crate) const DEFAULT_MAX_PACKET_SIZE: u32 = 1024 * 1024;
+
+/// Capacity of the request channel between the element and the event loop.
+const REQUEST_CHANNEL_CAPACITY: usize = 16;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstMqttQos")]
+pub enum MqttQos {
+    #[enum_value(name = "At most once: Messages may be lost.", nick = "at-most-once")]
+    AtMostOnce = 0,
+
+    #[enum_value(
+        name = "At least once: Messages may be duplicated.",
+        nick = "at-least-once"
+    )]
+    AtLeastOnce = 1,
+
+    #[enum_value(
+        name = "Exactly once: Messages are delivered once.",
+        nick = "exactly-once"
+    )]
+    ExactlyOnce = 2,
+}
+
+impl From<MqttQos> for QoS {
+    fn from(qos: MqttQos) -> Self {
+        match qos {
+            MqttQos::AtMostOnce => QoS::AtMostOnce,
+            MqttQos::AtLeastOnce => QoS::AtLeastOnce,
+            MqttQos::ExactlyOnce => QoS::ExactlyOnce,
+        }
+    }
+}
+
+/// Broker connection settings, common to the source and the sink.
+#[derive(Clone, Debug)]
+pub(crate) struct ConnectionSettings {
+    pub host: String,
+    pub port: u16,
+    pub client_id: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: bool,
+    pub ca_file: Option<PathBuf>,
+    pub certificate_file: Option<PathBuf>,
+    pub private_key_file: Option<PathBuf>,
+    pub keep_alive: u32,
+    pub reconnect_interval: u32,
+    pub clean_session: bool,
+    pub max_packet_size: u32,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        ConnectionSettings {
+            host: DEFAULT_HOST.to_string(),
+            port: DEFAULT_PORT,
+            client_id: None,
+            username: None,
+            password: None,
+            tls: false,
+            ca_file: None,
+            certificate_file: None,
+            private_key_file: None,
+            keep_alive: DEFAULT_KEEP_ALIVE,
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+            clean_session: DEFAULT_CLEAN_SESSION,
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+        }
+    }
+}
+
+impl ConnectionSettings {
+    pub fn properties() -> Vec<glib::ParamSpec> {
+        vec![
+            glib::ParamSpecString::builder("host")
+                .nick("Host")
+                .blurb("Host name or address of the MQTT broker")
+                .default_value(Some(DEFAULT_HOST))
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("port")
+                .nick("Port")
+                .blurb("Port of the MQTT broker")
+                .minimum(1)
+                .maximum(u16::MAX as u32)
+                .default_value(DEFAULT_PORT as u32)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("client-id")
+                .nick("Client ID")
+                .blurb("Client identifier, derived from the element name if not set")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("username")
+                .nick("Username")
+                .blurb("Username to authenticate with")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("password")
+                .nick("Password")
+                .blurb("Password to authenticate with")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecBoolean::builder("tls")
+                .nick("TLS")
+                .blurb("Connect to the broker with TLS")
+                .default_value(false)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("ca-file")
+                .nick("CA file")
+                .blurb("PEM file with the certificate authorities to verify the broker with, instead of the system ones")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("certificate-file")
+                .nick("Certificate file")
+                .blurb("PEM file with the client certificate chain, for client authentication")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("private-key-file")
+                .nick("Private key file")
+                .blurb("PEM file with the private key of the client certificate")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("keep-alive")
+                .nick("Keep alive")
+                .blurb("Keep alive interval in seconds")
+                .minimum(5)
+                .default_value(DEFAULT_KEEP_ALIVE)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("reconnect-interval")
+                .nick("Reconnect interval")
+                .blurb("Time in ms to wait before reconnecting after a connection error")
+                .default_value(DEFAULT_RECONNECT_INTERVAL)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecBoolean::builder("clean-session")
+                .nick("Clean session")
+                .blurb("Start a clean session instead of resuming the previous one of the client ID")
+                .default_value(DEFAULT_CLEAN_SESSION)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("max-packet-size")
+                .nick("Maximum packet size")
+                .blurb("Maximum size in bytes of the packets sent and received")
+                .minimum(1024)
+                .default_value(DEFAULT_MAX_PACKET_SIZE)
+                .mutable_ready()
+                .build(),
+        ]
+    }
+
+    /// Sets the property if it's a connection property, returns `false` otherwise.
+    pub fn set_property(&mut self, value: &glib::Value, pspec: &glib::ParamSpec) -> bool {
+        match pspec.name() {
+            "host" => {
+                self.host = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_HOST.to_string());
+            }
+            "port" => {
+                self.port = value.get::<u32>().expect("type checked upstream") as u16;
+            }
+            "client-id" => {
+                self.client_id = value.get().expect("type checked upstream");
+            }
+            "username" => {
+                self.username = value.get().expect("type checked upstream");
+            }
+            "password" => {
+                self.password = value.get().expect("type checked upstream");
+            }
+            "tls" => {
+                self.tls = value.get().expect("type checked upstream");
+            }
+            "ca-file" => {
+                self.ca_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "certificate-file" => {
+                self.certificate_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "private-key-file" => {
+                self.private_key_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "keep-alive" => {
+                self.keep_alive = value.get().expect("type checked upstream");
+            }
+            "reconnect-interval" => {
+                self.reconnect_interval = value.get().expect("type checked upstream");
+            }
+            "clean-session" => {
+                self.clean_session = value.get().expect("type checked upstream");
+            }
+            "max-packet-size" => {
+                self.max_packet_size = value.get().expect("type checked upstream");
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub fn property(&self, pspec: &glib::ParamSpec) -> Option<glib::Value> {
+        let value = match pspec.name() {
+            "host" => self.host.to_value(),
+            "port" => (self.port as u32).to_value(),
+            "client-id" => self.client_id.to_value(),
+            "username" => self.username.to_value(),
+            "password" => self.password.to_value(),
+            "tls" => self.tls.to_value(),
+            "ca-file" => self.ca_file.as_ref().and_then(|f| f.to_str()).to_value(),
+            "certificate-file" => self
+                .certificate_file
+                .as_ref()
+                .and_then(|f| f.to_str())
+                .to_value(),
+            "private-key-file" => self
+                .private_key_file
+                .as_ref()
+                .and_then(|f| f.to_str())
+                .to_value(),
+            "keep-alive" => self.keep_alive.to_value(),
+            "reconnect-interval" => self.reconnect_interval.to_value(),
+            "clean-session" => self.clean_session.to_value(),
+            "max-packet-size" => self.max_packet_size.to_value(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    fn read_file(path: &PathBuf) -> Result<Vec<u8>, gst::ErrorMessage> {
+        std::fs::read(path).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Failed to read {}: {}", path.display(), err]
+            )
+        })
+    }
+
+    pub fn mqtt_options(&self, element: &gst::Element) -> Result<MqttOptions, gst::ErrorMessage> {
+        let client_id = self
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("gst-{}-{}", element.name(), std::process::id()));
+
+        let mut options = MqttOptions::new(client_id, &self.host, self.port);
+        options
+            .set_keep_alive(Duration::from_secs(self.keep_alive.into()))
+            .set_clean_session(self.clean_session)
+            .set_max_packet_size(self.max_packet_size as usize, self.max_packet_size as usize);
+
+        if let Some(ref username) = self.username {
+            options.set_credentials(username, self.password.as_deref().unwrap_or_default());
+        }
+
+        if self.tls {
+            let client_auth = match (&self.certificate_file, &self.private_key_file) {
+                (Some(cert), Some(key)) => Some((Self::read_file(cert)?, Self::read_file(key)?)),
+                (None, None) => None,
+                _ => {
+                    return Err(gst::error_msg!(
+                        gst::ResourceError::Settings,
+                        ["Both a certificate and a private key file are needed for client authentication"]
+                    ))
+                }
+            };
+
+            let transport = match (&self.ca_file, client_auth) {
+                (Some(ca_file), client_auth) => Transport::Tls(TlsConfiguration::Simple {
+                    ca: Self::read_file(ca_file)?,
+                    alpn: None,
+                    client_auth,
+                }),
+                (None, None) => Transport::tls_with_default_config(),
+                (None, Some(_)) => {
+                    return Err(gst::error_msg!(
+                        gst::ResourceError::Settings,
+                        ["A CA file is needed for client authentication"]
+                    ))
+                }
+            };
+
+            options.set_transport(transport);
+        }
+
+        Ok(options)
+    }
+
+    pub fn reconnect_interval(&self) -> Duration {
+        Duration::from_millis(self.reconnect_interval.into())
+    }
+}
+
+pub(crate) fn client(options: MqttOptions) -> (rumqttc::Client, Connection) {
+    rumqttc::Client::new(options, REQUEST_CHANNEL_CAPACITY)
+}
+
+/// Drives the connection to the broker from a new thread, reconnecting after
+/// errors.
+///
+/// A warning message is posted for the first error after each successful
+/// connection, further reconnection attempts are only logged.
+///
+/// The thread stops once the disconnection request is sent, the element is
+/// disposed, or on errors after `shutdown` is set.
+pub(crate) fn spawn_event_loop<F>(
+    cat: gst::DebugCategory,
+    element: &gst::Element,
+    mut connection: Connection,
+    reconnect_interval: Duration,
+    shutdown: Arc<AtomicBool>,
+    mut handle_event: F,
+) -> Result<(), gst::ErrorMessage>
+where
+    F: FnMut(&gst::Element, Event) + Send + 'static,
+{
+    let element_weak = element.downgrade();
+
+    std::thread::Builder::new()
+        .name(format!("{}-mqtt", element.name()))
+        .spawn(move || {
+            let mut post_warning = true;
+
+            for notification in connection.iter() {
+                let Some(element) = element_weak.upgrade() else {
+                    break;
+                };
+
+                match notification {
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(event) => {
+                        if let Event::Incoming(Packet::ConnAck(_)) = event {
+                            post_warning = true;
+                        }
+                        handle_event(&element, event);
+                    }
+                    Err(_) if shutdown.load(Ordering::SeqCst) => break,
+                    Err(err) => {
+                        gst::warning!(
+                            cat,
+                            obj: element,
+                            "Connection error: {}, reconnecting in {:?}",
+                            err,
+                            reconnect_interval
+                        );
+                        if post_warning {
+                            gst::element_warning!(
+                                element,
+                                gst::ResourceError::OpenReadWrite,
+                                ["Connection error: {}", err]
+                            );
+                            post_warning = false;
+                        }
+                        drop(element);
+
+                        // The next iteration reconnects
+                        std::thread::sleep(reconnect_interval);
+                    }
+                }
+            }
+
+            gst::debug!(cat, "Event loop stopped");
+        })
+        .map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Failed,
+                ["Failed to spawn event loop thread: {}", err]
+            )
+        })?;
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+#![allow(clippy::non_send_fields_in_send_ty, unused_doc_comments)]
+
+/**
+ * plugin-mqtt:
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+#[cfg(feature = "doc")]
+use gst::prelude::*;
+
+mod common;
+mod mqttsink;
+mod mqttsrc;
+
+fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    common::MqttQos::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    mqttsink::register(plugin)?;
+    mqttsrc::register(plugin)?;
+
+    Ok(())
+}
+
+gst::plugin_define!(
+    mqtt,
+    env!("CARGO_PKG_DESCRIPTION"),
+    plugin_init,
+    concat!(env!("CARGO_PKG_VERSION"), "-", env!("COMMIT_ID")),
+    // FIXME: MPL-2.0 is only allowed since 1.18.3 (as unknown) and 1.20 (as known)
+    "MPL",
+    env!("CARGO_PKG_NAME"),
+    env!("CARGO_PKG_NAME"),
+    env!("CARGO_PKG_REPOSITORY"),
+    env!("BUILD_REL_DATE")
+);
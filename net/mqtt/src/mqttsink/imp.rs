@@ -0,0 +1,295 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use rumqttc::{ClientError, Event, Packet, Request};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::common::{self, ConnectionSettings, MqttQos, DEFAULT_QOS};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new("mqttsink", gst::DebugColorFlags::empty(), Some("MQTT Sink"))
+});
+
+/// Interval at which publishing is retried while the request channel is full.
+const PUBLISH_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone)]
+struct Settings {
+    connection: ConnectionSettings,
+    topic: Option<String>,
+    qos: MqttQos,
+    retain: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            connection: ConnectionSettings::default(),
+            topic: None,
+            qos: DEFAULT_QOS,
+            retain: false,
+        }
+    }
+}
+
+struct Started {
+    client: rumqttc::Client,
+    shutdown: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started(Started),
+}
+
+#[derive(Default)]
+pub struct MqttSink {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    flushing: AtomicBool,
+}
+
+impl MqttSink {
+    fn publish(&self, client: &rumqttc::Client, payload: Vec<u8>) -> Result<(), gst::FlowError> {
+        let (topic, qos, retain) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                settings.topic.clone().unwrap(),
+                settings.qos,
+                settings.retain,
+            )
+        };
+
+        let mut payload = payload;
+        loop {
+            match client.try_publish(&topic, qos.into(), retain, payload) {
+                Ok(()) => return Ok(()),
+                // Retry until the event loop, possibly reconnecting, catches up
+                Err(ClientError::TryRequest(Request::Publish(publish))) => {
+                    payload = publish.payload.into();
+                }
+                Err(err) => {
+                    gst::element_imp_error!(
+                        self,
+                        gst::ResourceError::Write,
+                        ["Failed to publish message: {}", err]
+                    );
+                    return Err(gst::FlowError::Error);
+                }
+            }
+
+            if self.flushing.load(Ordering::SeqCst) {
+                return Err(gst::FlowError::Flushing);
+            }
+
+            std::thread::sleep(PUBLISH_RETRY_INTERVAL);
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MqttSink {
+    const NAME: &'static str = "GstMqttSink";
+    type Type = super::MqttSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for MqttSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            let mut props = ConnectionSettings::properties();
+            props.extend([
+                glib::ParamSpecString::builder("topic")
+                    .nick("Topic")
+                    .blurb("Topic to publish the buffers to")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("qos", DEFAULT_QOS)
+                    .nick("QoS")
+                    .blurb("Quality of service of the published messages")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("retain")
+                    .nick("Retain")
+                    .blurb("Have the broker retain the last message for new subscribers")
+                    .default_value(false)
+                    .mutable_ready()
+                    .build(),
+            ]);
+            props
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        if settings.connection.set_property(value, pspec) {
+            return;
+        }
+
+        match pspec.name() {
+            "topic" => {
+                settings.topic = value.get().expect("type checked upstream");
+            }
+            "qos" => {
+                settings.qos = value.get().expect("type checked upstream");
+            }
+            "retain" => {
+                settings.retain = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        if let Some(value) = settings.connection.property(pspec) {
+            return value;
+        }
+
+        match pspec.name() {
+            "topic" => settings.topic.to_value(),
+            "qos" => settings.qos.to_value(),
+            "retain" => settings.retain.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for MqttSink {}
+
+impl ElementImpl for MqttSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "MQTT Sink",
+                "Sink/Network",
+                "Publishes buffers as messages to an MQTT topic",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for MqttSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        if settings.topic.is_none() {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No topic specified"]
+            ));
+        }
+
+        let element = self.obj();
+        let options = settings
+            .connection
+            .mqtt_options(element.upcast_ref::<gst::Element>())?;
+        let (client, connection) = common::client(options);
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        common::spawn_event_loop(
+            *CAT,
+            element.upcast_ref(),
+            connection,
+            settings.connection.reconnect_interval(),
+            shutdown.clone(),
+            |element, event| {
+                if let Event::Incoming(Packet::ConnAck(connack)) = event {
+                    gst::info!(CAT, obj: element, "Connected: {:?}", connack.code);
+                }
+            },
+        )?;
+
+        *self.state.lock().unwrap() = State::Started(Started { client, shutdown });
+
+        gst::info!(
+            CAT,
+            imp: self,
+            "Started, connecting to {}:{}",
+            settings.connection.host,
+            settings.connection.port
+        );
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let State::Started(started) = std::mem::take(&mut *self.state.lock().unwrap()) {
+            // Pending messages are sent before disconnecting, unless the
+            // connection is currently lost
+            let _ = started.client.try_disconnect();
+            started.shutdown.store(true, Ordering::SeqCst);
+        }
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let client = match *self.state.lock().unwrap() {
+            State::Started(ref started) => started.client.clone(),
+            State::Stopped => {
+                gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+                return Err(gst::FlowError::Error);
+            }
+        };
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        gst::trace!(CAT, imp: self, "Publishing {} bytes", map.len());
+
+        self.publish(&client, map.to_vec())?;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-mqttsink:
+ *
+ * `mqttsink` publishes each buffer as a message to the MQTT `topic`, e.g. JSON
+ * metadata or JPEG snapshots of a camera analytics pipeline.
+ *
+ * The connection to the broker is established in the background when starting,
+ * and reestablished `reconnect-interval` milliseconds after it's lost. A
+ * warning message is posted when connecting fails, once until the connection is
+ * established again. Buffers are queued while disconnected, until the internal
+ * queue is full and the streaming thread blocks. Messages with a `qos` above `at-most-once` are
+ * resent after reconnecting if the session is resumed, see `clean-session`.
+ *
+ * With the `tls` property set, the broker is verified with the system
+ * certificate authorities or the ones from `ca-file`, and the client can
+ * authenticate with `certificate-file` and `private-key-file`.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 videotestsrc ! videorate ! video/x-raw,framerate=1/5 ! jpegenc ! \
+ *     mqttsink host=broker.local topic=camera/1/snapshot qos=at-least-once
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct MqttSink(ObjectSubclass<imp::MqttSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "mqttsink",
+        gst::Rank::NONE,
+        MqttSink::static_type(),
+    )
+}
@@ -0,0 +1,379 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use rumqttc::{Event, Packet, Publish};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::common::{self, ConnectionSettings, MqttQos, DEFAULT_QOS};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "mqttsrc",
+        gst::DebugColorFlags::empty(),
+        Some("MQTT Source"),
+    )
+});
+
+const DEFAULT_MAX_QUEUED: u32 = 100;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    connection: ConnectionSettings,
+    topics: Vec<String>,
+    qos: MqttQos,
+    caps: gst::Caps,
+    max_queued: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            connection: ConnectionSettings::default(),
+            topics: Vec::new(),
+            qos: DEFAULT_QOS,
+            caps: gst::Caps::new_any(),
+            max_queued: DEFAULT_MAX_QUEUED,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    messages: VecDeque<Publish>,
+    flushing: bool,
+}
+
+/// Messages received by the event loop, waiting to be output.
+#[derive(Default)]
+struct MessageQueue {
+    queue: Mutex<Queue>,
+    cond: Condvar,
+}
+
+struct Started {
+    client: rumqttc::Client,
+    shutdown: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started(Started),
+}
+
+#[derive(Default)]
+pub struct MqttSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    messages: Arc<MessageQueue>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MqttSrc {
+    const NAME: &'static str = "GstMqttSrc";
+    type Type = super::MqttSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for MqttSrc {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(true);
+        obj.set_format(gst::Format::Time);
+        obj.set_do_timestamp(true);
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            let mut props = ConnectionSettings::properties();
+            props.extend([
+                gst::ParamSpecArray::builder("topics")
+                    .nick("Topics")
+                    .blurb("Topic filters to subscribe to, possibly with wildcards")
+                    .element_spec(&glib::ParamSpecString::builder("topic").build())
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("qos", DEFAULT_QOS)
+                    .nick("QoS")
+                    .blurb("Maximum quality of service of the received messages")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
+                    .nick("Caps")
+                    .blurb("The caps of the source pad")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-queued")
+                    .nick("Maximum queued")
+                    .blurb("Maximum number of received messages waiting to be output, older ones are dropped (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_QUEUED)
+                    .mutable_playing()
+                    .build(),
+            ]);
+            props
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        if settings.connection.set_property(value, pspec) {
+            return;
+        }
+
+        match pspec.name() {
+            "topics" => {
+                settings.topics = value
+                    .get::<gst::ArrayRef>()
+                    .expect("type checked upstream")
+                    .as_slice()
+                    .iter()
+                    .map(|topic| {
+                        topic
+                            .get::<&str>()
+                            .expect("type checked upstream")
+                            .to_string()
+                    })
+                    .collect();
+            }
+            "qos" => {
+                settings.qos = value.get().expect("type checked upstream");
+            }
+            "caps" => {
+                settings.caps = value
+                    .get::<Option<gst::Caps>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(gst::Caps::new_any);
+            }
+            "max-queued" => {
+                settings.max_queued = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        if let Some(value) = settings.connection.property(pspec) {
+            return value;
+        }
+
+        match pspec.name() {
+            "topics" => gst::Array::new(settings.topics.iter().map(|t| t.as_str())).to_value(),
+            "qos" => settings.qos.to_value(),
+            "caps" => settings.caps.to_value(),
+            "max-queued" => settings.max_queued.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for MqttSrc {}
+
+impl ElementImpl for MqttSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "MQTT Source",
+                "Source/Network",
+                "Receives messages of MQTT topics as buffers",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for MqttSrc {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        if settings.topics.is_empty() {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No topics specified"]
+            ));
+        }
+
+        let element = self.obj();
+        let options = settings
+            .connection
+            .mqtt_options(element.upcast_ref::<gst::Element>())?;
+        let (client, connection) = common::client(options);
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        self.messages.queue.lock().unwrap().messages.clear();
+
+        let subscriber = client.clone();
+        let messages = self.messages.clone();
+        let topics = settings.topics.clone();
+        let qos = settings.qos;
+
+        common::spawn_event_loop(
+            *CAT,
+            element.upcast_ref(),
+            connection,
+            settings.connection.reconnect_interval(),
+            shutdown.clone(),
+            move |element, event| match event {
+                // Subscriptions are lost with clean sessions, subscribe again
+                // on every connection
+                Event::Incoming(Packet::ConnAck(connack)) => {
+                    gst::info!(CAT, obj: element, "Connected: {:?}", connack.code);
+
+                    for topic in &topics {
+                        if let Err(err) = subscriber.try_subscribe(topic, qos.into()) {
+                            gst::error!(CAT, obj: element, "Failed to subscribe to {}: {}", topic, err);
+                        }
+                    }
+                }
+                Event::Incoming(Packet::Publish(publish)) => {
+                    gst::trace!(
+                        CAT,
+                        obj: element,
+                        "Received {} bytes on {}",
+                        publish.payload.len(),
+                        publish.topic
+                    );
+
+                    let max_queued: usize = element
+                        .property::<u32>("max-queued")
+                        .try_into()
+                        .unwrap_or(usize::MAX);
+
+                    let mut queue = messages.queue.lock().unwrap();
+                    if max_queued > 0 && queue.messages.len() >= max_queued {
+                        gst::warning!(CAT, obj: element, "Too many queued messages, dropping oldest");
+                        queue.messages.pop_front();
+                    }
+                    queue.messages.push_back(publish);
+                    messages.cond.notify_one();
+                }
+                _ => (),
+            },
+        )?;
+
+        *self.state.lock().unwrap() = State::Started(Started { client, shutdown });
+
+        gst::info!(
+            CAT,
+            imp: self,
+            "Started, connecting to {}:{}",
+            settings.connection.host,
+            settings.connection.port
+        );
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let State::Started(started) = std::mem::take(&mut *self.state.lock().unwrap()) {
+            let _ = started.client.try_disconnect();
+            started.shutdown.store(true, Ordering::SeqCst);
+        }
+
+        self.messages.queue.lock().unwrap().messages.clear();
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn caps(&self, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
+        let caps = self.settings.lock().unwrap().caps.clone();
+
+        match filter {
+            Some(filter) => Some(filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First)),
+            None => Some(caps),
+        }
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        let mut queue = self.messages.queue.lock().unwrap();
+        queue.flushing = true;
+        self.messages.cond.notify_one();
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.messages.queue.lock().unwrap().flushing = false;
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for MqttSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let mut queue = self.messages.queue.lock().unwrap();
+
+        let publish = loop {
+            if queue.flushing {
+                gst::debug!(CAT, imp: self, "Flushing");
+                return Err(gst::FlowError::Flushing);
+            }
+
+            if let Some(publish) = queue.messages.pop_front() {
+                break publish;
+            }
+
+            queue = self.messages.cond.wait(queue).unwrap();
+        };
+        drop(queue);
+
+        gst::log!(
+            CAT,
+            imp: self,
+            "Outputting message of {} bytes from {}",
+            publish.payload.len(),
+            publish.topic
+        );
+
+        Ok(CreateSuccess::NewBuffer(gst::Buffer::from_slice(
+            publish.payload,
+        )))
+    }
+}
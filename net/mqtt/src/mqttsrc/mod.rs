@@ -0,0 +1,46 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-mqttsrc:
+ *
+ * `mqttsrc` is a live source subscribing to the MQTT topic filters of the
+ * `topics` property and outputting each received message as a buffer,
+ * timestamped with its reception time. The source pad caps are set with the
+ * `caps` property.
+ *
+ * The topics are subscribed to again every time the connection to the broker
+ * is reestablished. If messages are received faster than they are consumed,
+ * the oldest ones are dropped once `max-queued` are waiting.
+ *
+ * See `mqttsink` for the connection and TLS properties.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 mqttsrc host=broker.local topics="<camera/+/snapshot>" caps=image/jpeg ! \
+ *     jpegdec ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct MqttSrc(ObjectSubclass<imp::MqttSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "mqttsrc",
+        gst::Rank::NONE,
+        MqttSrc::static_type(),
+    )
+}
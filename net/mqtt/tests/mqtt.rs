@@ -0,0 +1,326 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstmqtt::plugin_register_static().expect("mqtt test");
+    });
+}
+
+/// Minimal MQTT 3.1.1 broker, only supporting QoS 0 and forwarding published
+/// messages to the connections subscribed to a matching topic filter.
+struct Broker {
+    port: u16,
+    /// Topic filters, as they are subscribed to.
+    subscriptions: mpsc::Receiver<String>,
+}
+
+type Subscribers = Arc<Mutex<Vec<(String, TcpStream)>>>;
+
+impl Broker {
+    fn start() -> Broker {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, subscriptions) = mpsc::channel();
+        let subscribers = Subscribers::default();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    break;
+                };
+
+                let subscribers = subscribers.clone();
+                let sender = sender.clone();
+                std::thread::spawn(move || Broker::serve(stream, subscribers, sender));
+            }
+        });
+
+        Broker {
+            port,
+            subscriptions,
+        }
+    }
+
+    fn read_packet(stream: &mut TcpStream) -> std::io::Result<(u8, Vec<u8>)> {
+        let mut byte = [0u8; 1];
+        stream.read_exact(&mut byte)?;
+        let header = byte[0];
+
+        let mut len = 0;
+        for shift in (0..28).step_by(7) {
+            stream.read_exact(&mut byte)?;
+            len |= ((byte[0] & 0x7f) as usize) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body)?;
+
+        Ok((header, body))
+    }
+
+    fn write_packet(stream: &mut TcpStream, header: u8, body: &[u8]) -> std::io::Result<()> {
+        let mut packet = vec![header];
+
+        let mut len = body.len();
+        loop {
+            let byte = (len & 0x7f) as u8;
+            len >>= 7;
+            if len == 0 {
+                packet.push(byte);
+                break;
+            }
+            packet.push(byte | 0x80);
+        }
+        packet.extend_from_slice(body);
+
+        stream.write_all(&packet)
+    }
+
+    fn matches(filter: &str, topic: &str) -> bool {
+        let mut levels = topic.split('/');
+
+        for filter_level in filter.split('/') {
+            match (filter_level, levels.next()) {
+                ("#", _) => return true,
+                ("+", Some(_)) => (),
+                (filter_level, Some(level)) if filter_level == level => (),
+                _ => return false,
+            }
+        }
+
+        levels.next().is_none()
+    }
+
+    fn serve(mut stream: TcpStream, subscribers: Subscribers, sender: mpsc::Sender<String>) {
+        while let Ok((header, body)) = Broker::read_packet(&mut stream) {
+            let res = match header >> 4 {
+                // CONNECT
+                1 => Broker::write_packet(&mut stream, 0x20, &[0, 0]),
+                // PUBLISH
+                3 => {
+                    assert_eq!(header & 0x06, 0, "Only QoS 0 is supported");
+
+                    let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                    let topic = std::str::from_utf8(&body[2..2 + topic_len]).unwrap();
+
+                    for (filter, subscriber) in subscribers.lock().unwrap().iter_mut() {
+                        if Broker::matches(filter, topic) {
+                            let _ = Broker::write_packet(subscriber, 0x30, &body);
+                        }
+                    }
+
+                    Ok(())
+                }
+                // SUBSCRIBE
+                8 => {
+                    let mut granted = vec![];
+                    let mut pos = 2;
+                    while pos < body.len() {
+                        let len = u16::from_be_bytes([body[pos], body[pos + 1]]) as usize;
+                        let filter =
+                            String::from_utf8(body[pos + 2..pos + 2 + len].to_vec()).unwrap();
+                        // Followed by the requested QoS
+                        pos += 2 + len + 1;
+
+                        subscribers
+                            .lock()
+                            .unwrap()
+                            .push((filter.clone(), stream.try_clone().unwrap()));
+                        let _ = sender.send(filter);
+                        granted.push(0);
+                    }
+
+                    let mut suback = body[..2].to_vec();
+                    suback.extend(granted);
+                    Broker::write_packet(&mut stream, 0x90, &suback)
+                }
+                // PINGREQ
+                12 => Broker::write_packet(&mut stream, 0xd0, &[]),
+                // DISCONNECT
+                14 => break,
+                _ => Ok(()),
+            };
+
+            if res.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_requires_topic() {
+    init();
+
+    let sink = gst::ElementFactory::make("mqttsink").build().unwrap();
+    assert!(sink.set_state(gst::State::Paused).is_err());
+    sink.set_state(gst::State::Null).unwrap();
+
+    let src = gst::ElementFactory::make("mqttsrc").build().unwrap();
+    assert!(src.set_state(gst::State::Paused).is_err());
+    src.set_state(gst::State::Null).unwrap();
+}
+
+fn wait_for_connection_error(element: &gst::Element, bus: &gst::Bus) {
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(10),
+            &[gst::MessageType::Warning],
+        )
+        .expect("No connection error");
+
+    let gst::MessageView::Warning(warning) = msg.view() else {
+        unreachable!();
+    };
+    assert_eq!(msg.src(), Some(element.upcast_ref::<gst::Object>()));
+    assert!(warning.error().matches(gst::ResourceError::OpenReadWrite));
+}
+
+#[test]
+fn test_unreachable_broker() {
+    init();
+
+    // Nothing listens on this port: the elements keep trying to reconnect in
+    // the background without blocking state changes
+    let src = gst::ElementFactory::make("mqttsrc")
+        .property("host", "127.0.0.1")
+        .property("port", 1u32)
+        .property("topics", gst::Array::new(["test/#"]))
+        .property("reconnect-interval", 100u32)
+        .build()
+        .unwrap();
+    let bus = gst::Bus::new();
+    src.set_bus(Some(&bus));
+
+    assert_eq!(
+        src.set_state(gst::State::Playing),
+        Ok(gst::StateChangeSuccess::NoPreroll)
+    );
+    wait_for_connection_error(&src, &bus);
+
+    // Only posted once while reconnecting
+    assert!(bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_mseconds(300),
+            &[gst::MessageType::Warning]
+        )
+        .is_none());
+    src.set_state(gst::State::Null).unwrap();
+
+    let mut h = gst_check::Harness::new("mqttsink");
+    let sink = h.element().unwrap();
+    let bus = gst::Bus::new();
+    sink.set_bus(Some(&bus));
+    sink.set_property("host", "127.0.0.1");
+    sink.set_property("port", 1u32);
+    sink.set_property("topic", "test/topic");
+    sink.set_property_from_str("qos", "at-least-once");
+    h.set_src_caps_str("application/json");
+    h.play();
+
+    // Queued until the connection is established
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(br#"{"detections": []}"#)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    wait_for_connection_error(&sink, &bus);
+
+    sink.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_roundtrip() {
+    init();
+
+    let broker = Broker::start();
+
+    let mut src_h = gst_check::Harness::new("mqttsrc");
+    {
+        let src = src_h.element().unwrap();
+        src.set_property("host", "127.0.0.1");
+        src.set_property("port", broker.port as u32);
+        src.set_property("topics", gst::Array::new(["camera/+/meta", "other/#"]));
+        src.set_property("caps", gst::Caps::builder("application/json").build());
+    }
+    src_h.play();
+
+    let mut subscribed = vec![];
+    for _ in 0..2 {
+        subscribed.push(
+            broker
+                .subscriptions
+                .recv_timeout(Duration::from_secs(10))
+                .expect("Not subscribed"),
+        );
+    }
+    subscribed.sort();
+    assert_eq!(subscribed, ["camera/+/meta", "other/#"]);
+
+    let mut sink_hs = vec![];
+    for topic in ["camera/1/meta", "camera/1/snapshot", "other/topic"] {
+        let mut h = gst_check::Harness::new("mqttsink");
+        {
+            let sink = h.element().unwrap();
+            sink.set_property("host", "127.0.0.1");
+            sink.set_property("port", broker.port as u32);
+            sink.set_property("topic", topic);
+        }
+        h.set_src_caps_str("application/json");
+        h.play();
+        sink_hs.push(h);
+    }
+
+    // Messages from a single connection arrive in order
+    for payload in [&br#"{"id": 0}"#[..], br#"{"id": 1}"#] {
+        assert_eq!(
+            sink_hs[0].push(gst::Buffer::from_slice(payload)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    for payload in [&br#"{"id": 0}"#[..], br#"{"id": 1}"#] {
+        let buffer = src_h.pull().unwrap();
+        assert_eq!(buffer.map_readable().unwrap().as_slice(), payload);
+        assert!(buffer.pts().is_some());
+    }
+
+    // Not matching any of the topic filters
+    assert_eq!(
+        sink_hs[1].push(gst::Buffer::from_slice(b"jpeg")),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        sink_hs[2].push(gst::Buffer::from_slice(br#"{"id": 2}"#)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    let buffer = src_h.pull().unwrap();
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), br#"{"id": 2}"#);
+
+    let caps = src_h.sinkpad().unwrap().current_caps().unwrap();
+    assert_eq!(caps, gst::Caps::builder("application/json").build());
+
+    for h in sink_hs {
+        h.element().unwrap().set_state(gst::State::Null).unwrap();
+    }
+    src_h
+        .element()
+        .unwrap()
+        .set_state(gst::State::Null)
+        .unwrap();
+}
@@ -0,0 +1,369 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use async_nats::HeaderMap;
+use futures::future;
+use futures::prelude::*;
+use gst::glib;
+use gst::prelude::*;
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::runtime;
+
+pub(crate) const DEFAULT_SERVER: &str = "nats://localhost:4222";
+pub(crate) const DEFAULT_CONNECT_TIMEOUT: u32 = 5000;
+
+/// Maximum time to wait for the pending messages to be sent when stopping.
+pub(crate) const STOP_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Headers carrying the buffer timestamps, in nanoseconds.
+pub(crate) const PTS_HEADER: &str = "Gst-Pts";
+pub(crate) const DTS_HEADER: &str = "Gst-Dts";
+pub(crate) const DURATION_HEADER: &str = "Gst-Duration";
+
+pub(crate) static RUNTIME: Lazy<runtime::Runtime> = Lazy::new(|| {
+    runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(1)
+        .thread_name("gst-nats-runtime")
+        .build()
+        .unwrap()
+});
+
+#[derive(Default)]
+pub(crate) enum Canceller {
+    #[default]
+    None,
+    Handle(future::AbortHandle),
+    Cancelled,
+}
+
+impl Canceller {
+    pub fn abort(&mut self) {
+        if let Canceller::Handle(ref canceller) = *self {
+            canceller.abort();
+        }
+
+        *self = Canceller::Cancelled;
+    }
+}
+
+/// Runs the future to completion on the runtime, unless aborted with the
+/// canceller.
+pub(crate) fn wait<F, T>(
+    canceller_mutex: &Mutex<Canceller>,
+    future: F,
+) -> Result<T, future::Aborted>
+where
+    F: Send + Future<Output = T>,
+    T: Send + 'static,
+{
+    let mut canceller = canceller_mutex.lock().unwrap();
+    if matches!(*canceller, Canceller::Cancelled) {
+        return Err(future::Aborted);
+    }
+    let (abort_handle, abort_registration) = future::AbortHandle::new_pair();
+    *canceller = Canceller::Handle(abort_handle);
+    drop(canceller);
+
+    let res = RUNTIME.block_on(future::Abortable::new(future, abort_registration));
+
+    let mut canceller = canceller_mutex.lock().unwrap();
+    if matches!(*canceller, Canceller::Cancelled) {
+        return Err(future::Aborted);
+    }
+    *canceller = Canceller::None;
+
+    res
+}
+
+/// Server connection settings, common to the source and the sink.
+#[derive(Clone, Debug)]
+pub(crate) struct ConnectionSettings {
+    pub server: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+    pub credentials_file: Option<PathBuf>,
+    pub tls: bool,
+    pub ca_file: Option<PathBuf>,
+    pub connect_timeout: u32,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        ConnectionSettings {
+            server: DEFAULT_SERVER.to_string(),
+            username: None,
+            password: None,
+            token: None,
+            credentials_file: None,
+            tls: false,
+            ca_file: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+        }
+    }
+}
+
+impl ConnectionSettings {
+    pub fn properties() -> Vec<glib::ParamSpec> {
+        vec![
+            glib::ParamSpecString::builder("server")
+                .nick("Server")
+                .blurb("Comma separated list of NATS server URLs")
+                .default_value(Some(DEFAULT_SERVER))
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("username")
+                .nick("Username")
+                .blurb("Username to authenticate with")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("password")
+                .nick("Password")
+                .blurb("Password to authenticate with")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("token")
+                .nick("Token")
+                .blurb("Token to authenticate with")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("credentials-file")
+                .nick("Credentials file")
+                .blurb("NATS credentials file with the JWT and NKey seed to authenticate with")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecBoolean::builder("tls")
+                .nick("TLS")
+                .blurb("Require TLS for the connection")
+                .default_value(false)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("ca-file")
+                .nick("CA file")
+                .blurb("PEM file with additional certificate authorities to verify the server with")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("connect-timeout")
+                .nick("Connect timeout")
+                .blurb("Timeout in ms for connecting to the server when starting")
+                .minimum(1)
+                .default_value(DEFAULT_CONNECT_TIMEOUT)
+                .mutable_ready()
+                .build(),
+        ]
+    }
+
+    /// Sets the property if it's a connection property, returns `false` otherwise.
+    pub fn set_property(&mut self, value: &glib::Value, pspec: &glib::ParamSpec) -> bool {
+        match pspec.name() {
+            "server" => {
+                self.server = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_SERVER.to_string());
+            }
+            "username" => {
+                self.username = value.get().expect("type checked upstream");
+            }
+            "password" => {
+                self.password = value.get().expect("type checked upstream");
+            }
+            "token" => {
+                self.token = value.get().expect("type checked upstream");
+            }
+            "credentials-file" => {
+                self.credentials_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "tls" => {
+                self.tls = value.get().expect("type checked upstream");
+            }
+            "ca-file" => {
+                self.ca_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "connect-timeout" => {
+                self.connect_timeout = value.get().expect("type checked upstream");
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub fn property(&self, pspec: &glib::ParamSpec) -> Option<glib::Value> {
+        let value = match pspec.name() {
+            "server" => self.server.to_value(),
+            "username" => self.username.to_value(),
+            "password" => self.password.to_value(),
+            "token" => self.token.to_value(),
+            "credentials-file" => self
+                .credentials_file
+                .as_ref()
+                .and_then(|f| f.to_str())
+                .to_value(),
+            "tls" => self.tls.to_value(),
+            "ca-file" => self.ca_file.as_ref().and_then(|f| f.to_str()).to_value(),
+            "connect-timeout" => self.connect_timeout.to_value(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Connects to the server. The client reconnects by itself afterwards.
+    pub async fn connect(
+        &self,
+        element: &gst::Element,
+    ) -> Result<async_nats::Client, gst::ErrorMessage> {
+        let mut options = async_nats::ConnectOptions::new()
+            .name(format!("gst-{}", element.name()))
+            .connection_timeout(Duration::from_millis(self.connect_timeout.into()))
+            .require_tls(self.tls);
+
+        if let Some(ref credentials_file) = self.credentials_file {
+            options = options
+                .credentials_file(credentials_file)
+                .await
+                .map_err(|err| {
+                    gst::error_msg!(
+                        gst::ResourceError::OpenRead,
+                        [
+                            "Failed to read credentials file {}: {}",
+                            credentials_file.display(),
+                            err
+                        ]
+                    )
+                })?;
+        }
+
+        if let Some(ref username) = self.username {
+            options = options
+                .user_and_password(username.clone(), self.password.clone().unwrap_or_default());
+        }
+
+        if let Some(ref token) = self.token {
+            options = options.token(token.clone());
+        }
+
+        if let Some(ref ca_file) = self.ca_file {
+            options = options.add_root_certificates(ca_file.clone());
+        }
+
+        let servers = self
+            .server
+            .split(',')
+            .map(|server| server.trim().parse::<async_nats::ServerAddr>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["Invalid server {}: {}", self.server, err]
+                )
+            })?;
+
+        options.connect(servers).await.map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenReadWrite,
+                ["Failed to connect to {}: {}", self.server, err]
+            )
+        })
+    }
+}
+
+/// Headers carrying the timestamps of the buffer.
+pub(crate) fn timestamp_headers(buffer: &gst::BufferRef) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    for (name, ts) in [
+        (PTS_HEADER, buffer.pts()),
+        (DTS_HEADER, buffer.dts()),
+        (DURATION_HEADER, buffer.duration()),
+    ] {
+        if let Some(ts) = ts {
+            headers.insert(name, ts.nseconds().to_string());
+        }
+    }
+
+    headers
+}
+
+/// Sets the timestamps of the buffer from the headers, if any.
+pub(crate) fn apply_timestamp_headers(headers: &HeaderMap, buffer: &mut gst::BufferRef) {
+    let get = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.as_str().parse::<u64>().ok())
+            .map(gst::ClockTime::from_nseconds)
+    };
+
+    buffer.set_pts(get(PTS_HEADER));
+    buffer.set_dts(get(DTS_HEADER));
+    buffer.set_duration(get(DURATION_HEADER));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_headers() {
+        gst::init().unwrap();
+
+        let mut buffer = gst::Buffer::new();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_seconds(1));
+            buffer.set_duration(gst::ClockTime::from_mseconds(40));
+        }
+
+        let headers = timestamp_headers(&buffer);
+        assert_eq!(
+            headers.get(PTS_HEADER).map(|value| value.as_str()),
+            Some("1000000000")
+        );
+        assert!(headers.get(DTS_HEADER).is_none());
+        assert_eq!(
+            headers.get(DURATION_HEADER).map(|value| value.as_str()),
+            Some("40000000")
+        );
+
+        let mut restored = gst::Buffer::new();
+        {
+            let restored = restored.get_mut().unwrap();
+            // Timestamps missing from the headers are unset
+            restored.set_dts(gst::ClockTime::from_seconds(2));
+            apply_timestamp_headers(&headers, restored);
+        }
+        assert_eq!(restored.pts(), Some(gst::ClockTime::from_seconds(1)));
+        assert_eq!(restored.dts(), None);
+        assert_eq!(restored.duration(), Some(gst::ClockTime::from_mseconds(40)));
+    }
+
+    #[test]
+    fn test_invalid_timestamp_headers() {
+        gst::init().unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(PTS_HEADER, "not a timestamp");
+        headers.insert(DTS_HEADER, "-1");
+        headers.insert(DURATION_HEADER, "20000000");
+
+        let mut buffer = gst::Buffer::new();
+        apply_timestamp_headers(&headers, buffer.get_mut().unwrap());
+        assert_eq!(buffer.pts(), None);
+        assert_eq!(buffer.dts(), None);
+        assert_eq!(buffer.duration(), Some(gst::ClockTime::from_mseconds(20)));
+    }
+}
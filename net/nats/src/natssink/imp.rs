@@ -0,0 +1,304 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use crate::common::{self, Canceller, ConnectionSettings};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new("natssink", gst::DebugColorFlags::empty(), Some("NATS Sink"))
+});
+
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    connection: ConnectionSettings,
+    subject: Option<String>,
+    jetstream: bool,
+}
+
+#[derive(Clone)]
+enum Publisher {
+    Core(async_nats::Client),
+    JetStream(async_nats::jetstream::Context),
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started {
+        client: async_nats::Client,
+        publisher: Publisher,
+        subject: String,
+    },
+}
+
+#[derive(Default)]
+pub struct NatsSink {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    canceller: Mutex<Canceller>,
+}
+
+impl NatsSink {
+    fn flush(&self) -> Result<(), gst::FlowError> {
+        let client = match *self.state.lock().unwrap() {
+            State::Started { ref client, .. } => client.clone(),
+            State::Stopped => return Ok(()),
+        };
+
+        gst::debug!(CAT, imp: self, "Flushing");
+
+        match common::wait(&self.canceller, client.flush()) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(err)) => {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Write,
+                    ["Failed to flush: {}", err]
+                );
+                Err(gst::FlowError::Error)
+            }
+            Err(_) => Err(gst::FlowError::Flushing),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for NatsSink {
+    const NAME: &'static str = "GstNatsSink";
+    type Type = super::NatsSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for NatsSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            let mut props = ConnectionSettings::properties();
+            props.extend([
+                glib::ParamSpecString::builder("subject")
+                    .nick("Subject")
+                    .blurb("Subject to publish the buffers to")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("jetstream")
+                    .nick("JetStream")
+                    .blurb("Publish to a JetStream stream and wait for each message to be acknowledged")
+                    .default_value(false)
+                    .mutable_ready()
+                    .build(),
+            ]);
+            props
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        if settings.connection.set_property(value, pspec) {
+            return;
+        }
+
+        match pspec.name() {
+            "subject" => {
+                settings.subject = value.get().expect("type checked upstream");
+            }
+            "jetstream" => {
+                settings.jetstream = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        if let Some(value) = settings.connection.property(pspec) {
+            return value;
+        }
+
+        match pspec.name() {
+            "subject" => settings.subject.to_value(),
+            "jetstream" => settings.jetstream.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for NatsSink {}
+
+impl ElementImpl for NatsSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "NATS Sink",
+                "Sink/Network",
+                "Publishes buffers as messages to a NATS subject",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for NatsSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let Some(subject) = settings.subject else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No subject specified"]
+            ));
+        };
+
+        let element = self.obj();
+        let client = common::RUNTIME.block_on(
+            settings
+                .connection
+                .connect(element.upcast_ref::<gst::Element>()),
+        )?;
+
+        let publisher = if settings.jetstream {
+            Publisher::JetStream(async_nats::jetstream::new(client.clone()))
+        } else {
+            Publisher::Core(client.clone())
+        };
+
+        *self.state.lock().unwrap() = State::Started {
+            client,
+            publisher,
+            subject,
+        };
+
+        gst::info!(CAT, imp: self, "Started, connected to {}", settings.connection.server);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let State::Started { client, .. } = std::mem::take(&mut *self.state.lock().unwrap()) {
+            // Sends the pending messages before closing the connection
+            let res = common::RUNTIME.block_on(tokio::time::timeout(
+                common::STOP_FLUSH_TIMEOUT,
+                client.flush(),
+            ));
+            if !matches!(res, Ok(Ok(()))) {
+                gst::warning!(CAT, imp: self, "Failed to flush pending messages");
+            }
+        }
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let (publisher, subject) = match *self.state.lock().unwrap() {
+            State::Started {
+                ref publisher,
+                ref subject,
+                ..
+            } => (publisher.clone(), subject.clone()),
+            State::Stopped => {
+                gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+                return Err(gst::FlowError::Error);
+            }
+        };
+
+        let payload = {
+            let map = buffer.map_readable().map_err(|_| {
+                gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+                gst::FlowError::Error
+            })?;
+            Bytes::copy_from_slice(&map)
+        };
+        let headers = common::timestamp_headers(buffer);
+
+        gst::trace!(CAT, imp: self, "Publishing {} bytes to {}", payload.len(), subject);
+
+        let res = common::wait(&self.canceller, async move {
+            match publisher {
+                Publisher::Core(client) => client
+                    .publish_with_headers(subject, headers, payload)
+                    .await
+                    .map_err(|err| err.to_string()),
+                Publisher::JetStream(context) => {
+                    let ack = context
+                        .publish_with_headers(subject, headers, payload)
+                        .await
+                        .map_err(|err| err.to_string())?;
+
+                    ack.await.map(|_| ()).map_err(|err| err.to_string())
+                }
+            }
+        });
+
+        match res {
+            Ok(Ok(())) => Ok(gst::FlowSuccess::Ok),
+            Ok(Err(err)) => {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Write,
+                    ["Failed to publish message: {}", err]
+                );
+                Err(gst::FlowError::Error)
+            }
+            Err(_) => Err(gst::FlowError::Flushing),
+        }
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        if let gst::EventView::Eos(_) = event.view() {
+            if self.flush().is_err() {
+                return false;
+            }
+        }
+
+        self.parent_event(event)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.canceller.lock().unwrap().abort();
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        let mut canceller = self.canceller.lock().unwrap();
+        if matches!(*canceller, Canceller::Cancelled) {
+            *canceller = Canceller::None;
+        }
+
+        Ok(())
+    }
+}
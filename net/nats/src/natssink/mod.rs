@@ -0,0 +1,46 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-natssink:
+ *
+ * `natssink` publishes each buffer as a message to the NATS `subject`. The
+ * buffer timestamps are carried in the `Gst-Pts`, `Gst-Dts` and
+ * `Gst-Duration` headers, in nanoseconds.
+ *
+ * With `jetstream`, the messages are published to the JetStream stream
+ * capturing the subject, and each message is waited for to be acknowledged
+ * before rendering the next buffer.
+ *
+ * The connection to the server is established when starting, and
+ * reestablished by the client once lost.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 videotestsrc ! videorate ! video/x-raw,framerate=1/5 ! jpegenc ! \
+ *     natssink server=nats://nats.local:4222 subject=camera.1.snapshot
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct NatsSink(ObjectSubclass<imp::NatsSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "natssink",
+        gst::Rank::NONE,
+        NatsSink::static_type(),
+    )
+}
@@ -0,0 +1,416 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use async_nats::jetstream;
+use futures::prelude::*;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use crate::common::{self, Canceller, ConnectionSettings};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "natssrc",
+        gst::DebugColorFlags::empty(),
+        Some("NATS Source"),
+    )
+});
+
+#[derive(Debug, Clone)]
+struct Settings {
+    connection: ConnectionSettings,
+    subject: Option<String>,
+    queue_group: Option<String>,
+    jetstream_stream: Option<String>,
+    jetstream_consumer: Option<String>,
+    caps: gst::Caps,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            connection: ConnectionSettings::default(),
+            subject: None,
+            queue_group: None,
+            jetstream_stream: None,
+            jetstream_consumer: None,
+            caps: gst::Caps::new_any(),
+        }
+    }
+}
+
+enum Messages {
+    Core(async_nats::Subscriber),
+    JetStream(jetstream::consumer::pull::Stream),
+}
+
+impl Messages {
+    /// Waits for the next message, acknowledging it for JetStream.
+    async fn next(&mut self) -> Option<Result<async_nats::Message, String>> {
+        match self {
+            Messages::Core(subscriber) => subscriber.next().await.map(Ok),
+            Messages::JetStream(stream) => {
+                let res = stream.next().await?;
+                Some(
+                    async {
+                        let message = res.map_err(|err| err.to_string())?;
+                        message.ack().await.map_err(|err| err.to_string())?;
+
+                        Ok(message.message)
+                    }
+                    .await,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started {
+        // Keeps the connection open
+        _client: async_nats::Client,
+    },
+}
+
+#[derive(Default)]
+pub struct NatsSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    messages: Mutex<Option<Messages>>,
+    canceller: Mutex<Canceller>,
+}
+
+impl NatsSrc {
+    async fn subscribe(
+        client: &async_nats::Client,
+        settings: &Settings,
+    ) -> Result<Messages, gst::ErrorMessage> {
+        if let Some(ref stream_name) = settings.jetstream_stream {
+            let context = jetstream::new(client.clone());
+
+            let stream = context.get_stream(stream_name).await.map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::NotFound,
+                    ["Failed to get stream {}: {}", stream_name, err]
+                )
+            })?;
+
+            let config = jetstream::consumer::pull::Config {
+                durable_name: settings.jetstream_consumer.clone(),
+                filter_subject: settings.subject.clone().unwrap_or_default(),
+                ..Default::default()
+            };
+
+            let consumer = match settings.jetstream_consumer {
+                Some(ref name) => stream.get_or_create_consumer(name, config).await,
+                None => stream.create_consumer(config).await,
+            }
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Failed to create consumer: {}", err]
+                )
+            })?;
+
+            let messages = consumer.messages().await.map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Failed to consume messages: {}", err]
+                )
+            })?;
+
+            return Ok(Messages::JetStream(messages));
+        }
+
+        let Some(ref subject) = settings.subject else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No subject or JetStream stream specified"]
+            ));
+        };
+
+        let res = match settings.queue_group {
+            Some(ref queue_group) => {
+                client
+                    .queue_subscribe(subject.clone(), queue_group.clone())
+                    .await
+            }
+            None => client.subscribe(subject.clone()).await,
+        };
+
+        res.map(Messages::Core).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Failed to subscribe to {}: {}", subject, err]
+            )
+        })
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for NatsSrc {
+    const NAME: &'static str = "GstNatsSrc";
+    type Type = super::NatsSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for NatsSrc {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(true);
+        obj.set_format(gst::Format::Time);
+        obj.set_do_timestamp(true);
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            let mut props = ConnectionSettings::properties();
+            props.extend([
+                glib::ParamSpecString::builder("subject")
+                    .nick("Subject")
+                    .blurb("Subject to subscribe to, possibly with wildcards, or to filter the JetStream stream with")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("queue-group")
+                    .nick("Queue group")
+                    .blurb("Queue group to join, to distribute the messages among its subscribers")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("jetstream-stream")
+                    .nick("JetStream stream")
+                    .blurb("JetStream stream to consume the messages of, instead of subscribing")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("jetstream-consumer")
+                    .nick("JetStream consumer")
+                    .blurb("Name of the durable JetStream consumer, resuming where it stopped (ephemeral if not set)")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
+                    .nick("Caps")
+                    .blurb("The caps of the source pad")
+                    .mutable_ready()
+                    .build(),
+            ]);
+            props
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        if settings.connection.set_property(value, pspec) {
+            return;
+        }
+
+        match pspec.name() {
+            "subject" => {
+                settings.subject = value.get().expect("type checked upstream");
+            }
+            "queue-group" => {
+                settings.queue_group = value.get().expect("type checked upstream");
+            }
+            "jetstream-stream" => {
+                settings.jetstream_stream = value.get().expect("type checked upstream");
+            }
+            "jetstream-consumer" => {
+                settings.jetstream_consumer = value.get().expect("type checked upstream");
+            }
+            "caps" => {
+                settings.caps = value
+                    .get::<Option<gst::Caps>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(gst::Caps::new_any);
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        if let Some(value) = settings.connection.property(pspec) {
+            return value;
+        }
+
+        match pspec.name() {
+            "subject" => settings.subject.to_value(),
+            "queue-group" => settings.queue_group.to_value(),
+            "jetstream-stream" => settings.jetstream_stream.to_value(),
+            "jetstream-consumer" => settings.jetstream_consumer.to_value(),
+            "caps" => settings.caps.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for NatsSrc {}
+
+impl ElementImpl for NatsSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "NATS Source",
+                "Source/Network",
+                "Receives messages of NATS subjects as buffers",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for NatsSrc {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        if settings.subject.is_none() && settings.jetstream_stream.is_none() {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No subject or JetStream stream specified"]
+            ));
+        }
+
+        let element = self.obj();
+        let (client, messages) = common::RUNTIME.block_on(async {
+            let client = settings
+                .connection
+                .connect(element.upcast_ref::<gst::Element>())
+                .await?;
+            let messages = Self::subscribe(&client, &settings).await?;
+
+            Ok::<_, gst::ErrorMessage>((client, messages))
+        })?;
+
+        *self.messages.lock().unwrap() = Some(messages);
+        *self.state.lock().unwrap() = State::Started { _client: client };
+
+        gst::info!(CAT, imp: self, "Started, connected to {}", settings.connection.server);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.messages.lock().unwrap() = None;
+        *self.state.lock().unwrap() = State::Stopped;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn caps(&self, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
+        let caps = self.settings.lock().unwrap().caps.clone();
+
+        match filter {
+            Some(filter) => Some(filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First)),
+            None => Some(caps),
+        }
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.canceller.lock().unwrap().abort();
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        let mut canceller = self.canceller.lock().unwrap();
+        if matches!(*canceller, Canceller::Cancelled) {
+            *canceller = Canceller::None;
+        }
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for NatsSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let mut messages = self.messages.lock().unwrap();
+        let Some(messages) = messages.as_mut() else {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        let message = match common::wait(&self.canceller, messages.next()) {
+            Ok(Some(Ok(message))) => message,
+            Ok(Some(Err(err))) => {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to receive message: {}", err]
+                );
+                return Err(gst::FlowError::Error);
+            }
+            Ok(None) => {
+                gst::debug!(CAT, imp: self, "Subscription closed");
+                return Err(gst::FlowError::Eos);
+            }
+            Err(_) => {
+                gst::debug!(CAT, imp: self, "Flushing");
+                return Err(gst::FlowError::Flushing);
+            }
+        };
+
+        gst::log!(
+            CAT,
+            imp: self,
+            "Outputting message of {} bytes from {}",
+            message.payload.len(),
+            message.subject
+        );
+
+        let mut buffer = gst::Buffer::from_slice(message.payload);
+
+        // Without timestamping by the base class, the timestamps of the
+        // published buffers are restored
+        if !self.obj().do_timestamp() {
+            if let Some(ref headers) = message.headers {
+                common::apply_timestamp_headers(headers, buffer.get_mut().unwrap());
+            }
+        }
+
+        Ok(CreateSuccess::NewBuffer(buffer))
+    }
+}
@@ -0,0 +1,47 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-natssrc:
+ *
+ * `natssrc` is a live source subscribing to the NATS `subject`, optionally as
+ * part of a `queue-group`, and outputting each received message as a buffer.
+ * The source pad caps are set with the `caps` property.
+ *
+ * With `jetstream-stream`, the messages of the JetStream stream are consumed
+ * instead, filtered by `subject` if set. A durable consumer named
+ * `jetstream-consumer` resumes after the last message it acknowledged.
+ *
+ * The buffers are timestamped with their reception time, unless
+ * `do-timestamp` is disabled: the timestamps published by `natssink` are then
+ * restored from the message headers.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 natssrc server=nats://nats.local:4222 subject="camera.*.snapshot" caps=image/jpeg ! \
+ *     jpegdec ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct NatsSrc(ObjectSubclass<imp::NatsSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "natssrc",
+        gst::Rank::NONE,
+        NatsSrc::static_type(),
+    )
+}
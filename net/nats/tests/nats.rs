@@ -0,0 +1,420 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstnats::plugin_register_static().expect("nats test");
+    });
+}
+
+struct Subscription {
+    connection: usize,
+    sid: String,
+    subject: String,
+    queue_group: Option<String>,
+    writer: Arc<Mutex<TcpStream>>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    subscriptions: Vec<Subscription>,
+    /// JetStream stream names and the subject filter they capture.
+    streams: Vec<(&'static str, &'static str)>,
+    sequence: u64,
+}
+
+/// Minimal NATS server, routing messages with headers between the
+/// subscriptions of its connections. Publishing with a reply subject to a
+/// subject captured by one of its streams is acknowledged like by JetStream.
+struct Server {
+    port: u16,
+    /// Subjects, as they are subscribed to.
+    subscriptions: mpsc::Receiver<String>,
+}
+
+impl Server {
+    fn start(streams: &[(&'static str, &'static str)]) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (sender, subscriptions) = mpsc::channel();
+        let state = Arc::new(Mutex::new(ServerState {
+            streams: streams.to_vec(),
+            ..Default::default()
+        }));
+
+        std::thread::spawn(move || {
+            for (connection, stream) in listener.incoming().enumerate() {
+                let Ok(stream) = stream else {
+                    break;
+                };
+
+                let state = state.clone();
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    let _ = Server::serve(connection, stream, port, state, sender);
+                });
+            }
+        });
+
+        Server {
+            port,
+            subscriptions,
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("nats://127.0.0.1:{}", self.port)
+    }
+
+    fn wait_for_subscription(&self, subject: &str) {
+        loop {
+            let subscribed = self
+                .subscriptions
+                .recv_timeout(Duration::from_secs(10))
+                .expect("Not subscribed");
+            if subscribed == subject {
+                break;
+            }
+        }
+    }
+
+    fn matches(filter: &str, subject: &str) -> bool {
+        let mut tokens = subject.split('.');
+
+        for filter_token in filter.split('.') {
+            match (filter_token, tokens.next()) {
+                (">", Some(_)) => return true,
+                ("*", Some(_)) => (),
+                (filter_token, Some(token)) if filter_token == token => (),
+                _ => return false,
+            }
+        }
+
+        tokens.next().is_none()
+    }
+
+    /// Delivers the message to the matching subscriptions, once per queue
+    /// group. Returns whether there was any.
+    fn route(
+        state: &ServerState,
+        subject: &str,
+        reply: Option<&str>,
+        headers: Option<&[u8]>,
+        payload: &[u8],
+    ) -> bool {
+        let mut queue_groups = HashSet::new();
+        let mut delivered = false;
+
+        for subscription in &state.subscriptions {
+            if !Server::matches(&subscription.subject, subject) {
+                continue;
+            }
+            if let Some(ref queue_group) = subscription.queue_group {
+                if !queue_groups.insert(queue_group) {
+                    continue;
+                }
+            }
+
+            let reply = reply.map(|reply| format!(" {reply}")).unwrap_or_default();
+            let mut message = match headers {
+                Some(headers) => format!(
+                    "HMSG {subject} {}{reply} {} {}\r\n",
+                    subscription.sid,
+                    headers.len(),
+                    headers.len() + payload.len()
+                )
+                .into_bytes(),
+                None => format!(
+                    "MSG {subject} {}{reply} {}\r\n",
+                    subscription.sid,
+                    payload.len()
+                )
+                .into_bytes(),
+            };
+            message.extend_from_slice(headers.unwrap_or_default());
+            message.extend_from_slice(payload);
+            message.extend_from_slice(b"\r\n");
+
+            let _ = subscription.writer.lock().unwrap().write_all(&message);
+            delivered = true;
+        }
+
+        delivered
+    }
+
+    fn publish(
+        state: &mut ServerState,
+        subject: &str,
+        reply: Option<&str>,
+        headers: Option<&[u8]>,
+        payload: &[u8],
+    ) {
+        let delivered = Server::route(state, subject, reply, headers, payload);
+
+        let Some(reply) = reply else {
+            return;
+        };
+
+        let stream = state
+            .streams
+            .iter()
+            .find(|(_, filter)| Server::matches(filter, subject))
+            .map(|(name, _)| *name);
+
+        if let Some(stream) = stream {
+            state.sequence += 1;
+            let ack = format!(r#"{{"stream":"{stream}","seq":{}}}"#, state.sequence);
+            Server::route(state, reply, None, None, ack.as_bytes());
+        } else if !delivered {
+            Server::route(state, reply, None, Some(&b"NATS/1.0 503\r\n\r\n"[..]), &[]);
+        }
+    }
+
+    fn serve(
+        connection: usize,
+        stream: TcpStream,
+        port: u16,
+        state: Arc<Mutex<ServerState>>,
+        sender: mpsc::Sender<String>,
+    ) -> std::io::Result<()> {
+        let writer = Arc::new(Mutex::new(stream.try_clone()?));
+        let mut reader = BufReader::new(stream);
+
+        writer.lock().unwrap().write_all(
+            format!(
+                "INFO {{\"server_id\":\"test\",\"server_name\":\"test\",\"version\":\"2.10.0\",\
+                 \"host\":\"127.0.0.1\",\"port\":{port},\"headers\":true,\
+                 \"max_payload\":1048576,\"proto\":1}}\r\n"
+            )
+            .as_bytes(),
+        )?;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+
+            let args = line.split_whitespace().collect::<Vec<_>>();
+            let Some(op) = args.first() else {
+                continue;
+            };
+
+            match (op.to_ascii_uppercase().as_str(), &args[1..]) {
+                ("PING", _) => writer.lock().unwrap().write_all(b"PONG\r\n")?,
+                ("SUB", [subject, queue_group @ .., sid]) => {
+                    state.lock().unwrap().subscriptions.push(Subscription {
+                        connection,
+                        sid: sid.to_string(),
+                        subject: subject.to_string(),
+                        queue_group: queue_group.first().map(|group| group.to_string()),
+                        writer: writer.clone(),
+                    });
+                    let _ = sender.send(subject.to_string());
+                }
+                ("UNSUB", [sid, ..]) => {
+                    state.lock().unwrap().subscriptions.retain(|subscription| {
+                        subscription.connection != connection || subscription.sid != *sid
+                    });
+                }
+                ("PUB", [subject, reply @ .., len]) => {
+                    let mut payload = vec![0; len.parse::<usize>().unwrap() + 2];
+                    reader.read_exact(&mut payload)?;
+                    payload.truncate(payload.len() - 2);
+
+                    let mut state = state.lock().unwrap();
+                    Server::publish(&mut state, subject, reply.first().copied(), None, &payload);
+                }
+                ("HPUB", [subject, reply @ .., headers_len, len]) => {
+                    let headers_len = headers_len.parse::<usize>().unwrap();
+                    let mut message = vec![0; len.parse::<usize>().unwrap() + 2];
+                    reader.read_exact(&mut message)?;
+                    message.truncate(message.len() - 2);
+
+                    let (headers, payload) = message.split_at(headers_len);
+                    let mut state = state.lock().unwrap();
+                    Server::publish(
+                        &mut state,
+                        subject,
+                        reply.first().copied(),
+                        Some(headers),
+                        payload,
+                    );
+                }
+                // CONNECT and PONG
+                _ => (),
+            }
+        }
+
+        state
+            .lock()
+            .unwrap()
+            .subscriptions
+            .retain(|subscription| subscription.connection != connection);
+
+        Ok(())
+    }
+}
+
+fn sink_harness(server: &Server, subject: &str, jetstream: bool) -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("natssink");
+    {
+        let sink = h.element().unwrap();
+        sink.set_property("server", server.url());
+        sink.set_property("subject", subject);
+        sink.set_property("jetstream", jetstream);
+    }
+    h.set_src_caps_str("application/json");
+    h.play();
+
+    h
+}
+
+fn src_harness(server: &Server, subject: &str) -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("natssrc");
+    {
+        let src = h.element().unwrap();
+        src.set_property("server", server.url());
+        src.set_property("subject", subject);
+        src.set_property("caps", gst::Caps::builder("application/json").build());
+        src.set_property("do-timestamp", false);
+    }
+    h.play();
+    server.wait_for_subscription(subject);
+
+    h
+}
+
+fn buffer(payload: &'static [u8], pts: u64, dts: Option<u64>) -> gst::Buffer {
+    let mut buffer = gst::Buffer::from_slice(payload);
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::from_mseconds(pts));
+        buffer.set_dts(dts.map(gst::ClockTime::from_mseconds));
+        buffer.set_duration(gst::ClockTime::from_mseconds(40));
+    }
+
+    buffer
+}
+
+#[test]
+fn test_requires_subject() {
+    init();
+
+    let sink = gst::ElementFactory::make("natssink").build().unwrap();
+    assert!(sink.set_state(gst::State::Paused).is_err());
+    sink.set_state(gst::State::Null).unwrap();
+
+    let src = gst::ElementFactory::make("natssrc").build().unwrap();
+    assert!(src.set_state(gst::State::Paused).is_err());
+    src.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_unreachable_server() {
+    init();
+
+    let sink = gst::ElementFactory::make("natssink")
+        .property("server", "nats://127.0.0.1:1")
+        .property("subject", "test")
+        .property("connect-timeout", 100u32)
+        .build()
+        .unwrap();
+    assert!(sink.set_state(gst::State::Paused).is_err());
+    sink.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_roundtrip() {
+    init();
+
+    let server = Server::start(&[]);
+    let mut src_h = src_harness(&server, "camera.*.meta");
+    let mut sink_h = sink_harness(&server, "camera.1.meta", false);
+    let mut other_sink_h = sink_harness(&server, "camera.1.snapshot", false);
+
+    // Not matching the subject
+    assert_eq!(
+        other_sink_h.push(buffer(b"jpeg", 0, Some(0))),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert!(other_sink_h.push_event(gst::event::Eos::new()));
+
+    // The timestamps are restored from the headers with do-timestamp disabled
+    assert_eq!(
+        sink_h.push(buffer(br#"{"id": 0}"#, 1000, Some(960))),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        sink_h.push(buffer(br#"{"id": 1}"#, 1040, None)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert!(sink_h.push_event(gst::event::Eos::new()));
+
+    let buffer = src_h.pull().unwrap();
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), br#"{"id": 0}"#);
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(1000)));
+    assert_eq!(buffer.dts(), Some(gst::ClockTime::from_mseconds(960)));
+    assert_eq!(buffer.duration(), Some(gst::ClockTime::from_mseconds(40)));
+
+    let buffer = src_h.pull().unwrap();
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), br#"{"id": 1}"#);
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(1040)));
+    assert_eq!(buffer.dts(), None);
+
+    let caps = src_h.sinkpad().unwrap().current_caps().unwrap();
+    assert_eq!(caps, gst::Caps::builder("application/json").build());
+
+    assert!(src_h.try_pull().is_none());
+}
+
+#[test]
+fn test_jetstream_publish() {
+    init();
+
+    let server = Server::start(&[("CAMERAS", "camera.>")]);
+    let mut src_h = src_harness(&server, "camera.>");
+    let mut sink_h = sink_harness(&server, "camera.1.meta", true);
+
+    // Each buffer is only rendered once acknowledged by the stream
+    for i in 0..3 {
+        assert_eq!(
+            sink_h.push(buffer(b"{}", i * 40, None)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+
+    for i in 0..3 {
+        let buffer = src_h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(i * 40)));
+    }
+}
+
+#[test]
+fn test_jetstream_no_stream() {
+    init();
+
+    let server = Server::start(&[("CAMERAS", "camera.>")]);
+    let mut sink_h = sink_harness(&server, "other.1.meta", true);
+
+    // No stream captures the subject
+    assert_eq!(
+        sink_h.push(buffer(b"{}", 0, None)),
+        Err(gst::FlowError::Error)
+    );
+}
@@ -474,7 +474,7 @@ pub struct VideoFrame(VideoFrameInner);
 
 #[derive(Debug)]
 enum VideoFrameInner {
-    //Owned(NDIlib_video_frame_v2_t, Option<ffi::CString>, Option<Vec<u8>>),
+    Owned(NDIlib_video_frame_v2_t, Option<ffi::CString>, Vec<u8>),
     BorrowedRecv(NDIlib_video_frame_v2_t, Arc<RecvInstancePtr>),
     BorrowedGst(
         NDIlib_video_frame_v2_t,
@@ -512,51 +512,56 @@ impl VideoFrame {
     pub fn xres(&self) -> i32 {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => frame.xres,
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => frame.xres,
         }
     }
 
     pub fn yres(&self) -> i32 {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => frame.yres,
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => frame.yres,
         }
     }
 
     pub fn fourcc(&self) -> NDIlib_FourCC_video_type_e {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => frame.FourCC,
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => frame.FourCC,
         }
     }
 
     pub fn frame_rate(&self) -> (i32, i32) {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => {
-                (frame.frame_rate_N, frame.frame_rate_D)
-            }
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => (frame.frame_rate_N, frame.frame_rate_D),
         }
     }
 
     pub fn picture_aspect_ratio(&self) -> f32 {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => frame.picture_aspect_ratio,
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => frame.picture_aspect_ratio,
         }
     }
 
     pub fn frame_format_type(&self) -> NDIlib_frame_format_type_e {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => frame.frame_format_type,
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => frame.frame_format_type,
         }
     }
 
     pub fn timecode(&self) -> i64 {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => frame.timecode,
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => frame.timecode,
         }
     }
 
@@ -580,21 +585,33 @@ impl VideoFrame {
         {
             // FIXME: Unclear if this is correct. Needs to be validated against an actual
             // interlaced stream
-            let frame_size = if self.frame_format_type()
+            let height = if self.frame_format_type()
                 == NDIlib_frame_format_type_e::NDIlib_frame_format_type_field_0
                 || self.frame_format_type()
                     == NDIlib_frame_format_type_e::NDIlib_frame_format_type_field_1
             {
-                self.yres() * self.line_stride_or_data_size_in_bytes() / 2
+                self.yres() / 2
             } else {
-                self.yres() * self.line_stride_or_data_size_in_bytes()
+                self.yres()
+            };
+            let plane_size = height * self.line_stride_or_data_size_in_bytes();
+
+            let frame_size = match fourcc {
+                // Followed by an 8 bit alpha plane with a stride of xres
+                NDIlib_FourCC_video_type_UYVA => plane_size + height * self.xres(),
+                // Followed by the interleaved UV plane with the same stride
+                NDIlib_FourCC_video_type_P216 => 2 * plane_size,
+                // Followed by the interleaved UV plane and the alpha plane with the same stride
+                NDIlib_FourCC_video_type_PA16 => 3 * plane_size,
+                _ => plane_size,
             };
 
             return unsafe {
                 use std::slice;
                 match self.0 {
                     VideoFrameInner::BorrowedRecv(ref frame, ..)
-                    | VideoFrameInner::BorrowedGst(ref frame, ..) => Some(slice::from_raw_parts(
+                    | VideoFrameInner::BorrowedGst(ref frame, ..)
+                    | VideoFrameInner::Owned(ref frame, ..) => Some(slice::from_raw_parts(
                         frame.p_data as *const u8,
                         frame_size as usize,
                     )),
@@ -617,7 +634,8 @@ impl VideoFrame {
                 use std::slice;
                 match self.0 {
                     VideoFrameInner::BorrowedRecv(ref frame, ..)
-                    | VideoFrameInner::BorrowedGst(ref frame, ..) => Some(slice::from_raw_parts(
+                    | VideoFrameInner::BorrowedGst(ref frame, ..)
+                    | VideoFrameInner::Owned(ref frame, ..) => Some(slice::from_raw_parts(
                         frame.p_data as *const u8,
                         frame.line_stride_or_data_size_in_bytes as usize,
                     )),
@@ -654,7 +672,8 @@ impl VideoFrame {
 
             let data = match self.0 {
                 VideoFrameInner::BorrowedRecv(ref frame, ..)
-                | VideoFrameInner::BorrowedGst(ref frame, ..) => slice::from_raw_parts(
+                | VideoFrameInner::BorrowedGst(ref frame, ..)
+                | VideoFrameInner::Owned(ref frame, ..) => slice::from_raw_parts(
                     frame.p_data as *const u8,
                     frame.line_stride_or_data_size_in_bytes as usize,
                 ),
@@ -703,7 +722,8 @@ impl VideoFrame {
     pub fn line_stride_or_data_size_in_bytes(&self) -> i32 {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => {
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => {
                 let stride = frame.line_stride_or_data_size_in_bytes;
 
                 if stride != 0 {
@@ -734,7 +754,8 @@ impl VideoFrame {
         unsafe {
             match self.0 {
                 VideoFrameInner::BorrowedRecv(ref frame, _)
-                | VideoFrameInner::BorrowedGst(ref frame, ..) => {
+                | VideoFrameInner::BorrowedGst(ref frame, ..)
+                | VideoFrameInner::Owned(ref frame, ..) => {
                     if frame.p_metadata.is_null() {
                         None
                     } else {
@@ -748,14 +769,16 @@ impl VideoFrame {
     pub fn timestamp(&self) -> i64 {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => frame.timestamp,
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => frame.timestamp,
         }
     }
 
     pub fn as_ptr(&self) -> *const NDIlib_video_frame_v2_t {
         match self.0 {
             VideoFrameInner::BorrowedRecv(ref frame, _)
-            | VideoFrameInner::BorrowedGst(ref frame, ..) => frame,
+            | VideoFrameInner::BorrowedGst(ref frame, ..)
+            | VideoFrameInner::Owned(ref frame, ..) => frame,
         }
     }
 
@@ -825,6 +848,9 @@ impl VideoFrame {
             gst_video::VideoFormat::Bgrx => ndisys::NDIlib_FourCC_video_type_BGRX,
             gst_video::VideoFormat::Rgba => ndisys::NDIlib_FourCC_video_type_RGBA,
             gst_video::VideoFormat::Rgbx => ndisys::NDIlib_FourCC_video_type_RGBX,
+            // Converted below as NDI uses a different layout for alpha and high bit depth
+            gst_video::VideoFormat::Ayuv => ndisys::NDIlib_FourCC_video_type_UYVA,
+            gst_video::VideoFormat::Ayuv64 => ndisys::NDIlib_FourCC_video_type_PA16,
             _ => return Err(TryFromVideoFrameError),
         };
 
@@ -854,7 +880,7 @@ impl VideoFrame {
         let picture_aspect_ratio =
             picture_aspect_ratio.numer() as f32 / picture_aspect_ratio.denom() as f32;
 
-        let ndi_frame = NDIlib_video_frame_v2_t {
+        let mut ndi_frame = NDIlib_video_frame_v2_t {
             xres: frame.width() as i32,
             yres: frame.height() as i32,
             FourCC: format,
@@ -869,12 +895,111 @@ impl VideoFrame {
             timestamp: 0,
         };
 
-        Ok(VideoFrame(VideoFrameInner::BorrowedGst(
-            ndi_frame, frame, metadata,
+        let data = match format {
+            ndisys::NDIlib_FourCC_video_type_UYVA => ayuv_to_uyva(&frame),
+            ndisys::NDIlib_FourCC_video_type_PA16 => ayuv64_to_pa16(&frame),
+            _ => {
+                return Ok(VideoFrame(VideoFrameInner::BorrowedGst(
+                    ndi_frame, frame, metadata,
+                )));
+            }
+        };
+
+        // Both converted formats start with a plane of 2 bytes per pixel
+        ndi_frame.p_data = data.as_ptr() as *const ::std::os::raw::c_char;
+        ndi_frame.line_stride_or_data_size_in_bytes = 2 * frame.width() as i32;
+
+        Ok(VideoFrame(VideoFrameInner::Owned(
+            ndi_frame, metadata, data,
         )))
     }
 }
 
+/// Converts packed 4:4:4 AYUV to NDI's UYVA: a 4:2:2 UYVY plane followed by an alpha plane.
+fn ayuv_to_uyva(frame: &gst_video::VideoFrame<gst_video::video_frame::Readable>) -> Vec<u8> {
+    let width = frame.width() as usize;
+    let height = frame.height() as usize;
+    let src_stride = frame.plane_stride()[0] as usize;
+    let src = frame.plane_data(0).unwrap();
+
+    let uyvy_stride = 2 * width;
+    let mut data = vec![0u8; height * (uyvy_stride + width)];
+    let (uyvy, alpha) = data.split_at_mut(height * uyvy_stride);
+
+    for ((src, uyvy), alpha) in src
+        .chunks(src_stride)
+        .zip(uyvy.chunks_exact_mut(uyvy_stride))
+        .zip(alpha.chunks_exact_mut(width))
+    {
+        let src = &src[..4 * width];
+
+        for (x, pixel) in src.chunks_exact(4).enumerate() {
+            alpha[x] = pixel[0];
+            uyvy[2 * x + 1] = pixel[1];
+        }
+
+        // Average the chroma of each pair of pixels
+        for (pair, uyvy) in src.chunks(8).zip(uyvy.chunks_exact_mut(4)) {
+            let (u0, v0) = (pair[2] as u16, pair[3] as u16);
+            let (u1, v1) = pair
+                .get(4..8)
+                .map_or((u0, v0), |p| (p[2] as u16, p[3] as u16));
+            uyvy[0] = ((u0 + u1 + 1) / 2) as u8;
+            uyvy[2] = ((v0 + v1 + 1) / 2) as u8;
+        }
+    }
+
+    data
+}
+
+/// Converts packed 4:4:4 AYUV64 to NDI's PA16: a 16 bit 4:2:2 Y plane, followed by an
+/// interleaved UV plane and an alpha plane, all with the same stride.
+fn ayuv64_to_pa16(frame: &gst_video::VideoFrame<gst_video::video_frame::Readable>) -> Vec<u8> {
+    let width = frame.width() as usize;
+    let height = frame.height() as usize;
+    let src_stride = frame.plane_stride()[0] as usize;
+    let src = frame.plane_data(0).unwrap();
+
+    let stride = 2 * width;
+    let mut data = vec![0u8; 3 * height * stride];
+    let (y_plane, rest) = data.split_at_mut(height * stride);
+    let (uv_plane, a_plane) = rest.split_at_mut(height * stride);
+
+    let component = |pixel: &[u8], idx: usize| -> u16 {
+        u16::from_ne_bytes([pixel[2 * idx], pixel[2 * idx + 1]])
+    };
+
+    for (((src, y_line), uv_line), a_line) in src
+        .chunks(src_stride)
+        .zip(y_plane.chunks_exact_mut(stride))
+        .zip(uv_plane.chunks_exact_mut(stride))
+        .zip(a_plane.chunks_exact_mut(stride))
+    {
+        let src = &src[..8 * width];
+
+        for ((pixel, y), a) in src
+            .chunks_exact(8)
+            .zip(y_line.chunks_exact_mut(2))
+            .zip(a_line.chunks_exact_mut(2))
+        {
+            a.copy_from_slice(&component(pixel, 0).to_le_bytes());
+            y.copy_from_slice(&component(pixel, 1).to_le_bytes());
+        }
+
+        // Average the chroma of each pair of pixels
+        for (pair, uv) in src.chunks(16).zip(uv_line.chunks_exact_mut(4)) {
+            let (u0, v0) = (component(pair, 2) as u32, component(pair, 3) as u32);
+            let (u1, v1) = pair.get(8..16).map_or((u0, v0), |p| {
+                (component(p, 2) as u32, component(p, 3) as u32)
+            });
+            uv[0..2].copy_from_slice(&(((u0 + u1 + 1) / 2) as u16).to_le_bytes());
+            uv[2..4].copy_from_slice(&(((v0 + v1 + 1) / 2) as u16).to_le_bytes());
+        }
+    }
+
+    data
+}
+
 #[derive(Debug)]
 pub struct AudioFrame(AudioFrameInner);
 
@@ -133,6 +133,8 @@ impl ElementImpl for NdiSink {
                                 gst_video::VideoFormat::Bgrx.to_str(),
                                 gst_video::VideoFormat::Rgba.to_str(),
                                 gst_video::VideoFormat::Rgbx.to_str(),
+                                gst_video::VideoFormat::Ayuv.to_str(),
+                                gst_video::VideoFormat::Ayuv64.to_str(),
                             ]),
                         )
                         .field("width", gst::IntRange::<i32>::new(1, i32::MAX))
@@ -100,6 +100,8 @@ impl ElementImpl for NdiSinkCombiner {
                     gst_video::VideoFormat::Bgrx,
                     gst_video::VideoFormat::Rgba,
                     gst_video::VideoFormat::Rgbx,
+                    gst_video::VideoFormat::Ayuv,
+                    gst_video::VideoFormat::Ayuv64,
                 ])
                 .framerate_range(gst::Fraction::new(1, i32::MAX)..gst::Fraction::new(i32::MAX, 1))
                 .build();
@@ -769,15 +769,20 @@ impl NdiSrcDemux {
             ndisys::NDIlib_FourCC_video_type_BGRA,
             ndisys::NDIlib_FourCC_video_type_BGRX,
             ndisys::NDIlib_FourCC_video_type_RGBA,
-            ndisys::NDIlib_FourCC_video_type_BGRX,
+            ndisys::NDIlib_FourCC_video_type_RGBX,
+            ndisys::NDIlib_FourCC_video_type_P216,
+            ndisys::NDIlib_FourCC_video_type_PA16,
         ]
         .contains(&fourcc)
         {
             // YV12 and I420 are swapped in the NDI SDK compared to GStreamer
             let format = match video_frame.fourcc() {
                 ndisys::NDIlib_FourCC_video_type_UYVY => gst_video::VideoFormat::Uyvy,
-                // FIXME: This drops the alpha plane!
-                ndisys::NDIlib_FourCC_video_type_UYVA => gst_video::VideoFormat::Uyvy,
+                // UYVA, P216 and PA16 have no GStreamer equivalent and are converted
+                ndisys::NDIlib_FourCC_video_type_UYVA => gst_video::VideoFormat::Ayuv,
+                ndisys::NDIlib_FourCC_video_type_P216 | ndisys::NDIlib_FourCC_video_type_PA16 => {
+                    gst_video::VideoFormat::Ayuv64
+                }
                 ndisys::NDIlib_FourCC_video_type_YV12 => gst_video::VideoFormat::I420,
                 ndisys::NDIlib_FourCC_video_type_NV12 => gst_video::VideoFormat::Nv12,
                 ndisys::NDIlib_FourCC_video_type_I420 => gst_video::VideoFormat::Yv12,
@@ -793,8 +798,7 @@ impl NdiSrcDemux {
                     );
 
                     return Err(gst::FlowError::NotNegotiated);
-                } // TODO: NDIlib_FourCC_video_type_P216 and NDIlib_FourCC_video_type_PA16 not
-                  // supported by GStreamer
+                }
             };
 
             let mut builder = gst_video::VideoInfo::builder(
@@ -1282,6 +1286,66 @@ impl NdiSrcDemux {
                             Ok(vframe.into_buffer())
                         }
                     }
+                    gst_video::VideoFormat::Ayuv | gst_video::VideoFormat::Ayuv64 => {
+                        gst::trace!(gst::CAT_PERFORMANCE, imp: self, "Converting raw video frame");
+
+                        let src = video_frame.data().ok_or(gst::FlowError::Error)?;
+                        let src_stride = video_frame.line_stride_or_data_size_in_bytes() as usize;
+                        let fourcc = video_frame.fourcc();
+
+                        // UYVA: UYVY plane followed by an 8 bit alpha plane
+                        // P216: 16 bit Y plane followed by an interleaved 16 bit UV plane
+                        // PA16: P216 followed by a 16 bit alpha plane
+                        let width = video_frame.xres() as usize;
+                        let plane_size = video_frame.yres() as usize * src_stride;
+                        let (line_bytes, frame_size) = match fourcc {
+                            ndisys::NDIlib_FourCC_video_type_UYVA => {
+                                (2 * width, plane_size + video_frame.yres() as usize * width)
+                            }
+                            ndisys::NDIlib_FourCC_video_type_P216 => (2 * width, 2 * plane_size),
+                            _ => (2 * width, 3 * plane_size),
+                        };
+
+                        if src.len() < frame_size || src_stride < line_bytes {
+                            gst::error!(CAT, imp: self, "Video packet has wrong stride or size");
+                            gst::element_imp_error!(
+                                self,
+                                gst::StreamError::Format,
+                                ["Video packet has wrong stride or size"]
+                            );
+                            return Err(gst::FlowError::Error);
+                        }
+
+                        if state.video_buffer_pool.is_none() {
+                            state.video_buffer_pool = Some(self.create_video_buffer_pool(info));
+                        };
+                        let pool = state.video_buffer_pool.as_ref().unwrap();
+                        let buffer = pool.acquire_buffer(None)?;
+
+                        let mut vframe =
+                            gst_video::VideoFrame::from_buffer_writable(buffer, info).unwrap();
+
+                        let dest_stride = vframe.plane_stride()[0] as usize;
+                        let dest = vframe.plane_data_mut(0).unwrap();
+
+                        if fourcc == ndisys::NDIlib_FourCC_video_type_UYVA {
+                            uyva_to_ayuv(src, src_stride, width, plane_size, dest, dest_stride);
+                        } else {
+                            let alpha = (fourcc == ndisys::NDIlib_FourCC_video_type_PA16)
+                                .then(|| &src[2 * plane_size..][..plane_size]);
+                            p216_to_ayuv64(
+                                &src[..plane_size],
+                                &src[plane_size..][..plane_size],
+                                alpha,
+                                src_stride,
+                                width,
+                                dest,
+                                dest_stride,
+                            );
+                        }
+
+                        Ok(vframe.into_buffer())
+                    }
                     _ => unreachable!(),
                 }
             }
@@ -2028,3 +2092,60 @@ impl Observations {
         Some((out_time.nseconds(), duration, false))
     }
 }
+
+fn uyva_to_ayuv(
+    src: &[u8],
+    src_stride: usize,
+    width: usize,
+    plane_size: usize,
+    dest: &mut [u8],
+    dest_stride: usize,
+) {
+    let (uyvy, alpha) = src.split_at(plane_size);
+
+    for ((dest, uyvy), alpha) in dest
+        .chunks_exact_mut(dest_stride)
+        .zip(uyvy.chunks_exact(src_stride))
+        .zip(alpha.chunks_exact(width))
+    {
+        for (x, dest) in dest[..4 * width].chunks_exact_mut(4).enumerate() {
+            let pair = &uyvy[(x / 2) * 4..][..4];
+            let y = if x % 2 == 0 { pair[1] } else { pair[3] };
+
+            dest.copy_from_slice(&[alpha[x], y, pair[0], pair[2]]);
+        }
+    }
+}
+
+fn p216_to_ayuv64(
+    y_plane: &[u8],
+    uv_plane: &[u8],
+    alpha_plane: Option<&[u8]>,
+    src_stride: usize,
+    width: usize,
+    dest: &mut [u8],
+    dest_stride: usize,
+) {
+    let read = |line: &[u8], idx: usize| u16::from_le_bytes([line[2 * idx], line[2 * idx + 1]]);
+
+    for (row, dest) in dest
+        .chunks_exact_mut(dest_stride)
+        .take(y_plane.len() / src_stride)
+        .enumerate()
+    {
+        let y_line = &y_plane[row * src_stride..][..src_stride];
+        let uv_line = &uv_plane[row * src_stride..][..src_stride];
+        let alpha_line = alpha_plane.map(|alpha| &alpha[row * src_stride..][..src_stride]);
+
+        for (x, dest) in dest[..8 * width].chunks_exact_mut(8).enumerate() {
+            let a = alpha_line.map_or(0xffff, |alpha| read(alpha, x));
+            let y = read(y_line, x);
+            let u = read(uv_line, x & !1);
+            let v = read(uv_line, x | 1);
+
+            for (dest, c) in dest.chunks_exact_mut(2).zip([a, y, u, v]) {
+                dest.copy_from_slice(&c.to_ne_bytes());
+            }
+        }
+    }
+}
@@ -20,6 +20,7 @@ mod onvifmetadatadepay;
 mod onvifmetadataoverlay;
 mod onvifmetadataparse;
 mod onvifmetadatapay;
+mod onvifmetadatatoroi;
 
 // ONVIF Timed Metadata schema
 pub(crate) const ONVIF_METADATA_SCHEMA: &str = "http://www.onvif.org/ver10/schema";
@@ -119,6 +120,7 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     onvifmetadatacombiner::register(plugin)?;
     onvifmetadataoverlay::register(plugin)?;
     onvifmetadataparse::register(plugin)?;
+    onvifmetadatatoroi::register(plugin)?;
 
     gst::meta::CustomMeta::register("OnvifXMLFrameMeta", &[]);
 
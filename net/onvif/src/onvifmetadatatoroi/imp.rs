@@ -0,0 +1,301 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+use gst_video::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "onvifmetadatatoroi",
+        gst::DebugColorFlags::empty(),
+        Some("ONVIF metadata to region of interest meta element"),
+    )
+});
+
+const DEFAULT_ROI_TYPE: &str = "onvif-object";
+
+// Object detected by the analytics, in pixel coordinates with (0, 0) at the top left
+#[derive(Debug)]
+struct Region {
+    object_id: String,
+    class: Option<String>,
+    likelihood: Option<f64>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Default)]
+struct State {
+    video_info: Option<gst_video::VideoInfo>,
+}
+
+#[derive(Default)]
+pub struct OnvifMetadataToRoi {
+    state: Mutex<State>,
+}
+
+// Converts a normalized ONVIF coordinate, in the range [-1, 1] with the origin in the center, to
+// a pixel coordinate
+fn to_pixels(val: f64, size: u32) -> u32 {
+    (((val.clamp(-1., 1.) + 1.) / 2.) * size as f64).round() as u32
+}
+
+impl OnvifMetadataToRoi {
+    fn parse_object(&self, object: &xmltree::Element, width: u32, height: u32) -> Option<Region> {
+        gst::trace!(CAT, imp: self, "Handling object {:?}", object);
+
+        let Some(object_id) = object.attributes.get("ObjectId") else {
+            gst::warning!(CAT, imp: self, "XML Object with no ObjectId");
+            return None;
+        };
+
+        let appearance = object.get_child(("Appearance", crate::ONVIF_METADATA_SCHEMA))?;
+        let shape = appearance.get_child(("Shape", crate::ONVIF_METADATA_SCHEMA))?;
+
+        let class_type = appearance
+            .get_child(("Class", crate::ONVIF_METADATA_SCHEMA))
+            .and_then(|class| class.get_child(("Type", crate::ONVIF_METADATA_SCHEMA)));
+        let class = class_type
+            .and_then(|t| t.get_text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty());
+        let likelihood = class_type
+            .and_then(|t| t.attributes.get("Likelihood"))
+            .and_then(|val| val.parse().ok());
+
+        let Some(bbox) = shape.get_child(("BoundingBox", crate::ONVIF_METADATA_SCHEMA)) else {
+            gst::warning!(CAT, imp: self, "XML Shape with no BoundingBox");
+            return None;
+        };
+
+        let mut coords = [0f64; 4];
+        for (coord, name) in coords.iter_mut().zip(["left", "top", "right", "bottom"]) {
+            let Some(val) = bbox.attributes.get(name).and_then(|val| val.parse().ok()) else {
+                gst::warning!(CAT, imp: self, "BoundingBox with no {name} attribute");
+                return None;
+            };
+            *coord = val;
+        }
+        let [left, top, right, bottom] = coords;
+
+        // The vertical axis points up in ONVIF coordinates
+        let x1 = to_pixels(left.min(right), width);
+        let x2 = to_pixels(left.max(right), width);
+        let y1 = to_pixels(-top.max(bottom), height);
+        let y2 = to_pixels(-top.min(bottom), height);
+
+        Some(Region {
+            object_id: object_id.to_string(),
+            class,
+            likelihood,
+            x: x1,
+            y: y1,
+            width: x2 - x1,
+            height: y2 - y1,
+        })
+    }
+
+    fn parse_regions(
+        &self,
+        frames: &gst::BufferList,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<Region>, gst::FlowError> {
+        let mut regions = Vec::new();
+
+        // Metadata for multiple frames may be attached to this buffer. As in
+        // onvifmetadataoverlay, iterate them in reverse to start with the most recent and
+        // deduplicate by object id.
+        let mut object_ids = HashSet::new();
+
+        for buffer in frames.iter().rev() {
+            let root = crate::xml_from_buffer(&buffer.to_owned()).map_err(|err| {
+                self.post_error_message(err);
+
+                gst::FlowError::Error
+            })?;
+
+            for res in crate::iterate_video_analytics_frames(&root) {
+                let (_dt, frame) = res.map_err(|err| {
+                    self.post_error_message(err);
+
+                    gst::FlowError::Error
+                })?;
+
+                for object in frame
+                    .children
+                    .iter()
+                    .filter_map(|n| n.as_element())
+                    .filter(|e| {
+                        e.name == "Object"
+                            && e.namespace.as_deref() == Some(crate::ONVIF_METADATA_SCHEMA)
+                    })
+                {
+                    let Some(region) = self.parse_object(object, width, height) else {
+                        continue;
+                    };
+
+                    if !object_ids.insert(region.object_id.clone()) {
+                        gst::debug!(
+                            CAT,
+                            imp: self,
+                            "Skipping older version of object {}",
+                            region.object_id
+                        );
+                        continue;
+                    }
+
+                    regions.push(region);
+                }
+            }
+        }
+
+        Ok(regions)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for OnvifMetadataToRoi {
+    const NAME: &'static str = "GstOnvifMetadataToRoi";
+    type Type = super::OnvifMetadataToRoi;
+    type ParentType = gst_base::BaseTransform;
+}
+
+impl ObjectImpl for OnvifMetadataToRoi {}
+
+impl GstObjectImpl for OnvifMetadataToRoi {}
+
+impl ElementImpl for OnvifMetadataToRoi {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "ONVIF Metadata to ROI",
+                "Video/Metadata",
+                "Converts ONVIF analytics meta to video region of interest metas",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_video::VideoFormat::iter_raw()
+                .into_video_caps()
+                .unwrap()
+                .build();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for OnvifMetadataToRoi {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+
+    fn set_caps(&self, incaps: &gst::Caps, _outcaps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let video_info = gst_video::VideoInfo::from_caps(incaps)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to parse caps {}", incaps))?;
+
+        gst::debug!(CAT, imp: self, "Configured for caps {}", incaps);
+
+        self.state.lock().unwrap().video_info = Some(video_info);
+
+        Ok(())
+    }
+
+    fn transform_ip(
+        &self,
+        buffer: &mut gst::BufferRef,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let frames = match gst::meta::CustomMeta::from_buffer(buffer, "OnvifXMLFrameMeta") {
+            Ok(meta) => match meta.structure().get::<gst::BufferList>("frames") {
+                Ok(frames) => frames,
+                Err(_) => return Ok(gst::FlowSuccess::Ok),
+            },
+            Err(_) => return Ok(gst::FlowSuccess::Ok),
+        };
+
+        let (width, height) = {
+            let state = self.state.lock().unwrap();
+            let video_info = state
+                .video_info
+                .as_ref()
+                .ok_or(gst::FlowError::NotNegotiated)?;
+            (video_info.width(), video_info.height())
+        };
+
+        let regions = self.parse_regions(&frames, width, height)?;
+
+        gst::log!(
+            CAT,
+            imp: self,
+            "Adding {} regions of interest from {} frames",
+            regions.len(),
+            frames.len()
+        );
+
+        for (i, region) in regions.into_iter().enumerate() {
+            let mut meta = gst_video::VideoRegionOfInterestMeta::add(
+                buffer,
+                region.class.as_deref().unwrap_or(DEFAULT_ROI_TYPE),
+                (region.x, region.y, region.width, region.height),
+            );
+            meta.set_id(i as i32);
+
+            let mut s = gst::Structure::builder(DEFAULT_ROI_TYPE)
+                .field("object-id", region.object_id)
+                .build();
+            if let Some(class) = region.class {
+                s.set("class", class);
+            }
+            if let Some(likelihood) = region.likelihood {
+                s.set("likelihood", likelihood);
+            }
+            meta.add_param(s);
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
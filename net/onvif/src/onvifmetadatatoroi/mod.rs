@@ -0,0 +1,47 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-onvifmetadatatoroi:
+ * @short_description: Converts ONVIF analytics metadata to video region of interest metas.
+ *
+ * Reads the ONVIF analytics frames attached to video buffers by `onvifmetadatacombiner` or
+ * `onvifmetadataparse` and adds a `GstVideoRegionOfInterestMeta` for every detected object, so
+ * that generic elements that work with regions of interest can consume the camera analytics.
+ *
+ * The region of interest type is the class of the object, or `onvif-object` if the object has
+ * no class. Each meta carries an `onvif-object` parameter structure with the `object-id` and,
+ * if available, the `class` and its `likelihood`.
+ *
+ * As with `onvifmetadataoverlay`, only the most recent version of each object is converted if
+ * metadata for multiple frames is attached to a buffer.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 rtspsrc location=rtsp://camera/stream name=src \
+ *   src. ! rtph264depay ! avdec_h264 ! combiner.media \
+ *   src. ! application/x-rtp,media=application ! rtponvifmetadatadepay ! combiner.meta \
+ *   onvifmetadatacombiner name=combiner ! onvifmetadatatoroi ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct OnvifMetadataToRoi(ObjectSubclass<imp::OnvifMetadataToRoi>) @extends gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "onvifmetadatatoroi",
+        gst::Rank::NONE,
+        OnvifMetadataToRoi::static_type(),
+    )
+}
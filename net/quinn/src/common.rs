@@ -25,6 +25,19 @@ pub(crate) static DEFAULT_BIND_PORT: u16 = 0;
 pub(crate) const DEFAULT_ALPN: &str = "gst-quinn";
 pub(crate) const DEFAULT_TIMEOUT: u32 = 15;
 pub(crate) const DEFAULT_SECURE_CONNECTION: bool = true;
+pub(crate) const DEFAULT_MAX_DATAGRAM_SIZE: u32 = 0;
+pub(crate) const DEFAULT_WEBTRANSPORT_PATH: &str = "/";
+pub(crate) const DEFAULT_ROQ_FLOW_ID: u64 = 0;
+pub(crate) const DEFAULT_STREAM_MAPPING: QuinnQuicStreamMapping = QuinnQuicStreamMapping::Single;
+pub(crate) const DEFAULT_CERTIFICATE_RELOAD_INTERVAL: u32 = 0;
+pub(crate) const DEFAULT_CONGESTION_CONTROL: QuinnQuicCongestionControl =
+    QuinnQuicCongestionControl::Cubic;
+pub(crate) const DEFAULT_INITIAL_WINDOW: u64 = 0;
+/*
+ * Initial RTT estimate before the first RTT sample, as recommended by
+ * <https://datatracker.ietf.org/doc/html/rfc9002#section-6.2.2>
+ */
+pub(crate) const DEFAULT_INITIAL_RTT: u32 = 333;
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
 #[repr(u32)]
@@ -36,3 +49,37 @@ pub enum QuinnQuicRole {
     #[enum_value(name = "Client: Act as QUIC client.", nick = "client")]
     Client,
 }
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstQuinnQuicStreamMapping")]
+pub enum QuinnQuicStreamMapping {
+    #[enum_value(name = "Single: All buffers on a single stream.", nick = "single")]
+    Single,
+
+    #[enum_value(name = "Buffer: Each buffer on its own stream.", nick = "buffer")]
+    Buffer,
+
+    #[enum_value(
+        name = "GOP: Each group of pictures on its own stream, starting at key units.",
+        nick = "gop"
+    )]
+    Gop,
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstQuinnQuicCongestionControl")]
+pub enum QuinnQuicCongestionControl {
+    #[enum_value(name = "Cubic: Loss based, RFC 8312.", nick = "cubic")]
+    Cubic,
+
+    #[enum_value(name = "NewReno: Loss based, RFC 6582.", nick = "new-reno")]
+    NewReno,
+
+    #[enum_value(
+        name = "BBR: Model based, keeping queues short for lower latency (experimental).",
+        nick = "bbr"
+    )]
+    Bbr,
+}
@@ -22,12 +22,18 @@ use gst::prelude::*;
 mod common;
 mod quinnquicsink;
 mod quinnquicsrc;
+mod roq;
 mod utils;
+mod webtransport;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     #[cfg(feature = "doc")]
     {
         common::QuinnQuicRole::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        common::QuinnQuicStreamMapping::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        common::QuinnQuicCongestionControl::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
     }
     quinnquicsink::register(plugin)?;
     quinnquicsrc::register(plugin)?;
@@ -8,20 +8,27 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use crate::utils::{
-    client_endpoint, make_socket_addr, server_endpoint, wait, WaitError, CONNECTION_CLOSE_CODE,
-    CONNECTION_CLOSE_MSG,
+    client_endpoint, make_socket_addr, server_endpoint, wait, CertificateResolver, Certificates,
+    CongestionControl, WaitError, CONNECTION_CLOSE_CODE, CONNECTION_CLOSE_MSG,
 };
-use crate::{common::*, utils};
+use crate::webtransport::{self, BoxError, SendStream, Transport};
+use crate::{common::*, roq, utils};
 use bytes::Bytes;
 use futures::future;
 use gst::{glib, prelude::*, subclass::prelude::*};
 use gst_base::subclass::prelude::*;
 use once_cell::sync::Lazy;
-use quinn::{Connection, SendStream};
+use std::collections::VecDeque;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 const DEFAULT_ROLE: QuinnQuicRole = QuinnQuicRole::Client;
+const DEFAULT_STREAM_PRIORITY: i32 = 0;
+const DEFAULT_STALE_STREAM_TIMEOUT: u64 = 0;
+
+/// Application error code of the streams reset for being stale.
+const STALE_STREAM_RESET_CODE: u32 = 1;
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
@@ -32,8 +39,14 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 });
 
 struct Started {
-    connection: Connection,
+    transport: Transport,
     stream: Option<SendStream>,
+    stream_opened: Instant,
+    /// Previous streams possibly still being sent, with their opening time
+    pending_streams: VecDeque<(Instant, SendStream)>,
+    streams_cancelled: u64,
+    datagrams_sent: u64,
+    datagrams_dropped: u64,
 }
 
 #[derive(Default)]
@@ -56,8 +69,21 @@ struct Settings {
     keep_alive_interval: u64,
     secure_conn: bool,
     use_datagram: bool,
+    max_datagram_size: u32,
+    webtransport: bool,
+    webtransport_path: String,
+    roq: bool,
+    roq_flow_id: u64,
+    stream_mapping: QuinnQuicStreamMapping,
+    stream_priority: i32,
+    stale_stream_timeout: u64,
     certificate_file: Option<PathBuf>,
     private_key_file: Option<PathBuf>,
+    ca_file: Option<PathBuf>,
+    certificate_reload_interval: u32,
+    congestion_control: QuinnQuicCongestionControl,
+    initial_window: u64,
+    initial_rtt: u32,
 }
 
 impl Default for Settings {
@@ -74,8 +100,21 @@ impl Default for Settings {
             keep_alive_interval: 0,
             secure_conn: DEFAULT_SECURE_CONNECTION,
             use_datagram: false,
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            webtransport: false,
+            webtransport_path: DEFAULT_WEBTRANSPORT_PATH.to_string(),
+            roq: false,
+            roq_flow_id: DEFAULT_ROQ_FLOW_ID,
+            stream_mapping: DEFAULT_STREAM_MAPPING,
+            stream_priority: DEFAULT_STREAM_PRIORITY,
+            stale_stream_timeout: DEFAULT_STALE_STREAM_TIMEOUT,
             certificate_file: None,
             private_key_file: None,
+            ca_file: None,
+            certificate_reload_interval: DEFAULT_CERTIFICATE_RELOAD_INTERVAL,
+            congestion_control: DEFAULT_CONGESTION_CONTROL,
+            initial_window: DEFAULT_INITIAL_WINDOW,
+            initial_rtt: DEFAULT_INITIAL_RTT,
         }
     }
 }
@@ -84,6 +123,7 @@ pub struct QuinnQuicSink {
     settings: Mutex<Settings>,
     state: Mutex<State>,
     canceller: Mutex<utils::Canceller>,
+    certificates: Mutex<Option<Certificates>>,
 }
 
 impl Default for QuinnQuicSink {
@@ -92,6 +132,7 @@ impl Default for QuinnQuicSink {
             settings: Mutex::new(Settings::default()),
             state: Mutex::new(State::default()),
             canceller: Mutex::new(utils::Canceller::default()),
+            certificates: Mutex::new(None),
         }
     }
 }
@@ -159,6 +200,21 @@ impl ObjectImpl for QuinnQuicSink {
         self.parent_constructed();
     }
 
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![glib::subclass::Signal::builder("reload-certificates")
+                .action()
+                .return_type::<bool>()
+                .class_handler(|_, args| {
+                    let element = args[0].get::<super::QuinnQuicSink>().expect("signal arg");
+                    Some(element.imp().reload_certificates().to_value())
+                })
+                .build()]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
             vec![
@@ -223,11 +279,82 @@ impl ObjectImpl for QuinnQuicSink {
                     .nick("Private key file")
                     .blurb("Path to a PKCS8 or RSA private key file")
                     .build(),
+                glib::ParamSpecString::builder("ca-file")
+                    .nick("CA file")
+                    .blurb("Path to the CA certificates to verify the peer's certificate with (default: the certificate chain of certificate-file)")
+                    .build(),
+                glib::ParamSpecUInt::builder("certificate-reload-interval")
+                    .nick("Certificate reload interval")
+                    .blurb("Interval in seconds at which the certificate and private key files are checked for modifications and reloaded for the next handshakes (0 = disabled)")
+                    .default_value(DEFAULT_CERTIFICATE_RELOAD_INTERVAL)
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("congestion-control", DEFAULT_CONGESTION_CONTROL)
+                    .nick("Congestion control")
+                    .blurb("Congestion control algorithm of the QUIC connection")
+                    .build(),
+                glib::ParamSpecUInt64::builder("initial-window")
+                    .nick("Initial congestion window")
+                    .blurb("Initial congestion window in bytes (0 = default of the congestion control algorithm)")
+                    .default_value(DEFAULT_INITIAL_WINDOW)
+                    .build(),
+                glib::ParamSpecUInt::builder("initial-rtt")
+                    .nick("Initial RTT")
+                    .blurb("RTT in ms assumed until the first measurement, which together with the initial window determines the initial pacing rate")
+                    .minimum(1)
+                    .maximum(60_000)
+                    .default_value(DEFAULT_INITIAL_RTT)
+                    .build(),
                 glib::ParamSpecBoolean::builder("use-datagram")
                     .nick("Use datagram")
                     .blurb("Use datagram for lower latency, unreliable messaging")
                     .default_value(false)
                     .build(),
+                glib::ParamSpecUInt::builder("max-datagram-size")
+                    .nick("Maximum datagram size")
+                    .blurb("Buffers larger than this are dropped in datagram mode, in addition to the ones larger than the path allows (0 = path limit only)")
+                    .default_value(DEFAULT_MAX_DATAGRAM_SIZE)
+                    .build(),
+                glib::ParamSpecBoolean::builder("webtransport")
+                    .nick("WebTransport")
+                    .blurb("Send over a WebTransport session on top of the QUIC connection, using the h3 ALPN")
+                    .default_value(false)
+                    .build(),
+                glib::ParamSpecString::builder("webtransport-path")
+                    .nick("WebTransport path")
+                    .blurb("Path of the WebTransport session requested in client role")
+                    .default_value(Some(DEFAULT_WEBTRANSPORT_PATH))
+                    .build(),
+                glib::ParamSpecBoolean::builder("roq")
+                    .nick("RTP over QUIC")
+                    .blurb("Send RTP packets with the RTP over QUIC (RoQ) mapping, each packet on its own stream or in a datagram")
+                    .default_value(false)
+                    .build(),
+                glib::ParamSpecUInt64::builder("roq-flow-id")
+                    .nick("RoQ flow identifier")
+                    .blurb("Flow identifier of the RTP session in RoQ mode")
+                    .maximum(roq::MAX_VARINT)
+                    .default_value(DEFAULT_ROQ_FLOW_ID)
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("stream-mapping", DEFAULT_STREAM_MAPPING)
+                    .nick("Stream mapping")
+                    .blurb("How buffers are mapped to QUIC streams when not using datagrams")
+                    .build(),
+                glib::ParamSpecInt::builder("stream-priority")
+                    .nick("Stream priority")
+                    .blurb("Priority of the stream being sent. Previous streams get a lower priority so newer data is sent first")
+                    .minimum(i32::MIN + 1)
+                    .default_value(DEFAULT_STREAM_PRIORITY)
+                    .build(),
+                glib::ParamSpecUInt64::builder("stale-stream-timeout")
+                    .nick("Stale stream timeout")
+                    .blurb("Reset previous streams not fully received after this time in ms since their opening, abandoning their data (0 = never)")
+                    .default_value(DEFAULT_STALE_STREAM_TIMEOUT)
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Statistics: datagrams sent, dropped for being too large, and transmitted by the connection, and streams cancelled for being stale")
+                    .read_only()
+                    .build(),
             ]
         });
 
@@ -286,9 +413,55 @@ impl ObjectImpl for QuinnQuicSink {
                 let value: String = value.get().unwrap();
                 settings.private_key_file = Some(value.into());
             }
+            "ca-file" => {
+                settings.ca_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "certificate-reload-interval" => {
+                settings.certificate_reload_interval = value.get().expect("type checked upstream");
+            }
+            "congestion-control" => {
+                settings.congestion_control = value
+                    .get::<QuinnQuicCongestionControl>()
+                    .expect("type checked upstream");
+            }
+            "initial-window" => {
+                settings.initial_window = value.get().expect("type checked upstream");
+            }
+            "initial-rtt" => {
+                settings.initial_rtt = value.get().expect("type checked upstream");
+            }
             "use-datagram" => {
                 settings.use_datagram = value.get().expect("type checked upstream");
             }
+            "max-datagram-size" => {
+                settings.max_datagram_size = value.get().expect("type checked upstream");
+            }
+            "webtransport" => {
+                settings.webtransport = value.get().expect("type checked upstream");
+            }
+            "webtransport-path" => {
+                settings.webtransport_path = value.get::<String>().expect("type checked upstream");
+            }
+            "roq" => {
+                settings.roq = value.get().expect("type checked upstream");
+            }
+            "roq-flow-id" => {
+                settings.roq_flow_id = value.get().expect("type checked upstream");
+            }
+            "stream-mapping" => {
+                settings.stream_mapping = value
+                    .get::<QuinnQuicStreamMapping>()
+                    .expect("type checked upstream");
+            }
+            "stream-priority" => {
+                settings.stream_priority = value.get().expect("type checked upstream");
+            }
+            "stale-stream-timeout" => {
+                settings.stale_stream_timeout = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -324,7 +497,24 @@ impl ObjectImpl for QuinnQuicSink {
                 let privkey = settings.private_key_file.as_ref();
                 privkey.and_then(|file| file.to_str()).to_value()
             }
+            "ca-file" => {
+                let cafile = settings.ca_file.as_ref();
+                cafile.and_then(|file| file.to_str()).to_value()
+            }
+            "certificate-reload-interval" => settings.certificate_reload_interval.to_value(),
+            "congestion-control" => settings.congestion_control.to_value(),
+            "initial-window" => settings.initial_window.to_value(),
+            "initial-rtt" => settings.initial_rtt.to_value(),
             "use-datagram" => settings.use_datagram.to_value(),
+            "max-datagram-size" => settings.max_datagram_size.to_value(),
+            "webtransport" => settings.webtransport.to_value(),
+            "webtransport-path" => settings.webtransport_path.to_value(),
+            "roq" => settings.roq.to_value(),
+            "roq-flow-id" => settings.roq_flow_id.to_value(),
+            "stream-mapping" => settings.stream_mapping.to_value(),
+            "stream-priority" => settings.stream_priority.to_value(),
+            "stale-stream-timeout" => settings.stale_stream_timeout.to_value(),
+            "stats" => self.stats().to_value(),
             _ => unimplemented!(),
         }
     }
@@ -343,17 +533,24 @@ impl BaseSinkImpl for QuinnQuicSink {
         let timeout = settings.timeout;
         drop(settings);
 
+        let certificates = self.load_certificates()?;
+
         let mut state = self.state.lock().unwrap();
 
         if let State::Started { .. } = *state {
             unreachable!("QuicSink is already started");
         }
 
-        match wait(&self.canceller, self.init_connection(), timeout) {
-            Ok(Ok((c, s))) => {
+        match wait(&self.canceller, self.init_connection(certificates), timeout) {
+            Ok(Ok((t, s))) => {
                 *state = State::Started(Started {
-                    connection: c,
+                    transport: t,
                     stream: s,
+                    stream_opened: Instant::now(),
+                    pending_streams: VecDeque::new(),
+                    streams_cancelled: 0,
+                    datagrams_sent: 0,
+                    datagrams_dropped: 0,
                 });
 
                 gst::info!(CAT, imp: self, "Started");
@@ -386,22 +583,17 @@ impl BaseSinkImpl for QuinnQuicSink {
     fn stop(&self) -> Result<(), gst::ErrorMessage> {
         let settings = self.settings.lock().unwrap();
         let timeout = settings.timeout;
-        let use_datagram = settings.use_datagram;
         drop(settings);
 
         let mut state = self.state.lock().unwrap();
 
         if let State::Started(ref mut state) = *state {
-            let connection = &state.connection;
             let mut close_msg = CONNECTION_CLOSE_MSG.to_string();
 
-            if !use_datagram {
-                let send = &mut state.stream.as_mut().unwrap();
-
+            // With multiple streams, this is the last one opened
+            if let Some(send) = state.stream.as_mut() {
                 // Shutdown stream gracefully
-                // send.finish() may fail, but the error is harmless.
-                let _ = send.finish();
-                match wait(&self.canceller, send.stopped(), timeout) {
+                match wait(&self.canceller, send.finish(), timeout) {
                     Ok(r) => {
                         if let Err(e) = r {
                             close_msg = format!("Stream finish request error: {}", e);
@@ -421,10 +613,13 @@ impl BaseSinkImpl for QuinnQuicSink {
                 };
             }
 
-            connection.close(CONNECTION_CLOSE_CODE.into(), close_msg.as_bytes());
+            state
+                .transport
+                .close(CONNECTION_CLOSE_CODE, close_msg.as_bytes());
         }
 
         *state = State::Stopped;
+        *self.certificates.lock().unwrap() = None;
 
         gst::info!(CAT, imp: self, "Stopped");
 
@@ -444,7 +639,9 @@ impl BaseSinkImpl for QuinnQuicSink {
             gst::FlowError::Error
         })?;
 
-        match self.send_buffer(&map) {
+        let delta_unit = buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+
+        match self.send_buffer(&map, delta_unit) {
             Ok(_) => Ok(gst::FlowSuccess::Ok),
             Err(err) => match err {
                 Some(error_message) => {
@@ -476,19 +673,95 @@ impl BaseSinkImpl for QuinnQuicSink {
 }
 
 impl QuinnQuicSink {
-    fn send_buffer(&self, src: &[u8]) -> Result<(), Option<gst::ErrorMessage>> {
+    fn stats(&self) -> gst::Structure {
+        let state = self.state.lock().unwrap();
+
+        let (sent, dropped, transmitted, cancelled) = match *state {
+            State::Started(ref started) => (
+                started.datagrams_sent,
+                started.datagrams_dropped,
+                started.transport.connection().stats().frame_tx.datagram,
+                started.streams_cancelled,
+            ),
+            State::Stopped => (0, 0, 0, 0),
+        };
+
+        gst::Structure::builder("application/x-quinnquicsink-stats")
+            .field("datagrams-sent", sent)
+            .field("datagrams-dropped", dropped)
+            .field("datagrams-transmitted", transmitted)
+            .field("streams-cancelled", cancelled)
+            .build()
+    }
+
+    /// Finishes the current stream before opening a new one, and resets the
+    /// previous streams which became stale.
+    fn retire_stream(
+        &self,
+        started: &mut Started,
+        stream_priority: i32,
+        stale_stream_timeout: Option<Duration>,
+    ) {
+        if let Some(mut send) = started.stream.take() {
+            send.finish_nowait();
+            send.set_priority(stream_priority - 1);
+
+            if stale_stream_timeout.is_some() {
+                started
+                    .pending_streams
+                    .push_back((started.stream_opened, send));
+            }
+        }
+
+        let Some(stale_stream_timeout) = stale_stream_timeout else {
+            return;
+        };
+
+        let mut cancelled = 0;
+        started.pending_streams.retain_mut(|(opened, send)| {
+            if send.is_done() {
+                return false;
+            }
+
+            if opened.elapsed() < stale_stream_timeout {
+                return true;
+            }
+
+            send.reset(STALE_STREAM_RESET_CODE);
+            cancelled += 1;
+            false
+        });
+
+        if cancelled > 0 {
+            gst::debug!(CAT, imp: self, "Reset {} stale streams", cancelled);
+            started.streams_cancelled += cancelled;
+        }
+    }
+
+    fn send_buffer(&self, src: &[u8], delta_unit: bool) -> Result<(), Option<gst::ErrorMessage>> {
         let settings = self.settings.lock().unwrap();
         let timeout = settings.timeout;
         let use_datagram = settings.use_datagram;
+        let max_datagram_size = settings.max_datagram_size;
+        let use_roq = settings.roq;
+        let roq_flow_id = settings.roq_flow_id;
+        // RoQ sends each packet on its own stream
+        let stream_mapping = if use_roq {
+            QuinnQuicStreamMapping::Buffer
+        } else {
+            settings.stream_mapping
+        };
+        let stream_priority = settings.stream_priority;
+        let stale_stream_timeout = match settings.stale_stream_timeout {
+            0 => None,
+            timeout => Some(Duration::from_millis(timeout)),
+        };
         drop(settings);
 
         let mut state = self.state.lock().unwrap();
 
-        let (conn, stream) = match *state {
-            State::Started(Started {
-                ref connection,
-                ref mut stream,
-            }) => (connection, stream),
+        let started = match *state {
+            State::Started(ref mut started) => started,
             State::Stopped => {
                 return Err(Some(gst::error_msg!(
                     gst::LibraryError::Failed,
@@ -496,19 +769,95 @@ impl QuinnQuicSink {
                 )));
             }
         };
-
         if use_datagram {
-            match conn.send_datagram(Bytes::copy_from_slice(src)) {
-                Ok(_) => Ok(()),
+            let transport = &started.transport;
+            let Some(path_max_size) = transport.max_datagram_size() else {
+                return Err(Some(gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["Datagrams are not supported by the peer"]
+                )));
+            };
+
+            let max_size = if max_datagram_size > 0 {
+                usize::min(path_max_size, max_datagram_size as usize)
+            } else {
+                path_max_size
+            };
+
+            let data = if use_roq {
+                roq::datagram(roq_flow_id, src)
+            } else {
+                Bytes::copy_from_slice(src)
+            };
+
+            if data.len() > max_size {
+                started.datagrams_dropped += 1;
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Dropping {} bytes datagram, larger than the maximum of {} bytes",
+                    data.len(),
+                    max_size
+                );
+                return Ok(());
+            }
+
+            match transport.send_datagram(data) {
+                Ok(true) => {
+                    started.datagrams_sent += 1;
+                    Ok(())
+                }
+                // The path MTU may shrink after checking the maximum size
+                Ok(false) => {
+                    started.datagrams_dropped += 1;
+                    gst::warning!(CAT, imp: self, "Dropping datagram larger than the path allows");
+                    Ok(())
+                }
                 Err(e) => Err(Some(gst::error_msg!(
                     gst::ResourceError::Failed,
                     ["Sending data failed: {}", e]
                 ))),
             }
         } else {
-            let send = &mut stream.as_mut().unwrap();
+            let new_stream = match stream_mapping {
+                QuinnQuicStreamMapping::Single => false,
+                QuinnQuicStreamMapping::Buffer => true,
+                QuinnQuicStreamMapping::Gop => started.stream.is_none() || !delta_unit,
+            };
+
+            if new_stream {
+                self.retire_stream(started, stream_priority, stale_stream_timeout);
+                started.stream_opened = Instant::now();
+            }
+
+            let Started {
+                ref transport,
+                ref mut stream,
+                ..
+            } = *started;
+
+            let future = async {
+                if new_stream {
+                    let send = transport.open_uni().await?;
+                    send.set_priority(stream_priority);
+                    *stream = Some(send);
+                }
+
+                let send = stream.as_mut().unwrap();
+                if use_roq {
+                    send.write_all(&roq::stream(roq_flow_id, src)).await?;
+                } else {
+                    send.write_all(src).await?;
+                }
+
+                if stream_mapping == QuinnQuicStreamMapping::Buffer {
+                    send.finish_nowait();
+                }
+
+                Ok::<_, BoxError>(())
+            };
 
-            match wait(&self.canceller, send.write_all(src), timeout) {
+            match wait(&self.canceller, future, timeout) {
                 Ok(Ok(_)) => Ok(()),
                 Ok(Err(e)) => Err(Some(gst::error_msg!(
                     gst::ResourceError::Failed,
@@ -528,7 +877,78 @@ impl QuinnQuicSink {
         }
     }
 
-    async fn init_connection(&self) -> Result<(Connection, Option<SendStream>), WaitError> {
+    /// Loads the certificates of a secure connection, watching their files for
+    /// modifications if enabled.
+    fn load_certificates(&self) -> Result<Option<Arc<CertificateResolver>>, gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        if !settings.secure_conn {
+            return Ok(None);
+        }
+
+        let mut certificates =
+            Certificates::load(settings.certificate_file, settings.private_key_file).map_err(
+                |err| {
+                    gst::error_msg!(
+                        gst::ResourceError::Settings,
+                        ["Failed to load certificates: {}", err]
+                    )
+                },
+            )?;
+
+        if settings.certificate_reload_interval > 0 {
+            let element = self.obj().downgrade();
+            certificates.watch(
+                Duration::from_secs(settings.certificate_reload_interval.into()),
+                move |res| {
+                    let Some(element) = element.upgrade() else {
+                        return;
+                    };
+
+                    match res {
+                        Ok(()) => gst::info!(CAT, obj: element, "Reloaded modified certificates"),
+                        Err(err) => gst::warning!(
+                            CAT,
+                            obj: element,
+                            "Failed to reload modified certificates: {}",
+                            err
+                        ),
+                    }
+                },
+            );
+        }
+
+        let resolver = certificates.resolver();
+        *self.certificates.lock().unwrap() = Some(certificates);
+
+        Ok(Some(resolver))
+    }
+
+    /// Reloads the certificates for the next handshakes, established
+    /// connections are kept.
+    fn reload_certificates(&self) -> bool {
+        let certificates = self.certificates.lock().unwrap();
+        let Some(ref certificates) = *certificates else {
+            gst::warning!(CAT, imp: self, "No certificates to reload");
+            return false;
+        };
+
+        match certificates.resolver().reload() {
+            Ok(()) => {
+                gst::info!(CAT, imp: self, "Reloaded certificates");
+                true
+            }
+            Err(err) => {
+                gst::warning!(CAT, imp: self, "Failed to reload certificates: {}", err);
+                false
+            }
+        }
+    }
+
+    async fn init_connection(
+        &self,
+        certificates: Option<Arc<CertificateResolver>>,
+    ) -> Result<(Transport, Option<SendStream>), WaitError> {
         let client_addr;
         let server_addr;
         let server_name;
@@ -536,9 +956,14 @@ impl QuinnQuicSink {
         let role;
         let use_datagram;
         let keep_alive_interval;
+        let congestion_control;
         let secure_conn;
-        let cert_file;
-        let private_key_file;
+        let ca_file;
+        let use_webtransport;
+        let webtransport_path;
+        let use_roq;
+        let stream_mapping;
+        let stream_priority;
 
         {
             let settings = self.settings.lock().unwrap();
@@ -551,13 +976,26 @@ impl QuinnQuicSink {
                 make_socket_addr(format!("{}:{}", settings.address, settings.port).as_str())?;
 
             server_name = settings.server_name.clone();
-            alpns = settings.alpns.clone();
+            use_webtransport = settings.webtransport;
+            webtransport_path = settings.webtransport_path.clone();
+            use_roq = settings.roq;
+            stream_mapping = settings.stream_mapping;
+            stream_priority = settings.stream_priority;
+            alpns = if use_webtransport {
+                vec![webtransport::ALPN.to_string()]
+            } else {
+                settings.alpns.clone()
+            };
             role = settings.role;
             use_datagram = settings.use_datagram;
             keep_alive_interval = settings.keep_alive_interval;
+            congestion_control = CongestionControl {
+                algorithm: settings.congestion_control,
+                initial_window: settings.initial_window,
+                initial_rtt: Duration::from_millis(settings.initial_rtt.into()),
+            };
             secure_conn = settings.secure_conn;
-            cert_file = settings.certificate_file.clone();
-            private_key_file = settings.private_key_file.clone();
+            ca_file = settings.ca_file.clone();
         }
 
         let connection;
@@ -569,8 +1007,11 @@ impl QuinnQuicSink {
                     &server_name,
                     secure_conn,
                     alpns,
-                    cert_file,
-                    private_key_file,
+                    certificates,
+                    ca_file,
+                    use_webtransport,
+                    false,
+                    &congestion_control,
                 )
                 .map_err(|err| {
                     WaitError::FutureError(gst::error_msg!(
@@ -593,9 +1034,10 @@ impl QuinnQuicSink {
                     client_addr,
                     secure_conn,
                     alpns,
-                    cert_file,
-                    private_key_file,
+                    certificates,
+                    ca_file,
                     keep_alive_interval,
+                    &congestion_control,
                 )
                 .map_err(|err| {
                     WaitError::FutureError(gst::error_msg!(
@@ -617,19 +1059,51 @@ impl QuinnQuicSink {
             }
         }
 
-        let stream = if !use_datagram {
-            let res = connection.open_uni().await.map_err(|err| {
+        let transport = if !use_webtransport {
+            Transport::quic(connection)
+        } else if role == QuinnQuicRole::Server {
+            let (transport, url) = Transport::accept(connection).await.map_err(|err| {
                 WaitError::FutureError(gst::error_msg!(
                     gst::ResourceError::Failed,
-                    ["Failed to open stream: {}", err]
+                    ["WebTransport session error: {}", err]
                 ))
             })?;
 
-            Some(res)
+            gst::info!(CAT, imp: self, "Accepted WebTransport session for {}", url);
+
+            transport
         } else {
-            None
+            Transport::connect(
+                connection,
+                &server_name,
+                server_addr.port(),
+                &webtransport_path,
+            )
+            .await
+            .map_err(|err| {
+                WaitError::FutureError(gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["WebTransport session error: {}", err]
+                ))
+            })?
         };
 
-        Ok((connection, stream))
+        // Otherwise, streams are opened when sending buffers
+        let stream =
+            if !use_datagram && !use_roq && stream_mapping == QuinnQuicStreamMapping::Single {
+                let res = transport.open_uni().await.map_err(|err| {
+                    WaitError::FutureError(gst::error_msg!(
+                        gst::ResourceError::Failed,
+                        ["Failed to open stream: {}", err]
+                    ))
+                })?;
+                res.set_priority(stream_priority);
+
+                Some(res)
+            } else {
+                None
+            };
+
+        Ok((transport, stream))
     }
 }
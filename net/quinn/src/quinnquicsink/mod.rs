@@ -19,6 +19,74 @@
  * address="127.0.0.1" port=6000 certificate-file="certificates/fullchain.pem" \
  * private-key-file="certificates/privkey.pem"
  * ```
+ *
+ * ## Certificates
+ *
+ * With `secure-connection`, both peers present the certificate chain of
+ * `certificate-file` and verify the certificate of the other peer, i.e. the
+ * server also authenticates the client. The peer's certificate is verified with
+ * the CA certificates of `ca-file`, or by default with the own certificate
+ * chain.
+ *
+ * The certificate and private key can be rotated at runtime, either with the
+ * `reload-certificates` action signal or by setting
+ * `certificate-reload-interval` to check the files for modifications
+ * periodically. The reloaded certificate is presented in the following
+ * handshakes, the established connection is kept.
+ *
+ * ## WebTransport
+ *
+ * With the `webtransport` property set, the data is sent over a WebTransport
+ * session established on top of the QUIC connection with an HTTP/3 CONNECT
+ * request, so that it can be consumed by a browser with the WebTransport API.
+ * In client role, the session is requested for `webtransport-path`. In server
+ * role, the session of the connecting client is accepted whatever its path and
+ * the data is sent on a unidirectional stream opened by the sink, or in
+ * WebTransport datagrams if `use-datagram` is set. The `h3` ALPN is used and
+ * the `alpn-protocols` property is ignored.
+ *
+ * ```bash
+ * gst-launch-1.0 -v -e audiotestsrc ! opusenc ! oggmux ! \
+ * quinnquicsink role=server webtransport=true address="0.0.0.0" port=4433 \
+ * server-name="quic.net" certificate-file="certificates/fullchain.pem" \
+ * private-key-file="certificates/privkey.pem"
+ * ```
+ *
+ * ## Stream mapping
+ *
+ * By default, all buffers are sent on a single stream. With `stream-mapping`
+ * set to `buffer`, each buffer is sent on its own stream, and with `gop` a new
+ * stream is started at each buffer without the `DELTA_UNIT` flag. The stream
+ * being sent has the `stream-priority` priority and the previous ones get a
+ * lower priority, so that newer data is not blocked by older data. Previous
+ * streams not fully received `stale-stream-timeout` milliseconds after their
+ * opening are reset, abandoning their remaining data. The receiver must use the
+ * same `stream-mapping`.
+ *
+ * ## RTP over QUIC
+ *
+ * With the `roq` property set, each buffer is sent as an RTP packet following
+ * the [RTP over QUIC](https://datatracker.ietf.org/doc/html/draft-ietf-avtcore-rtp-over-quic)
+ * (RoQ) mapping, tagged with the `roq-flow-id` flow identifier. Each packet is
+ * sent on its own unidirectional stream, or in a datagram if `use-datagram` is
+ * set.
+ *
+ * ```bash
+ * gst-launch-1.0 -v -e videotestsrc ! x264enc tune=zerolatency ! rtph264pay ! \
+ * quinnquicsink roq=true roq-flow-id=1 use-datagram=true address="127.0.0.1" \
+ * port=6000 secure-connection=false
+ * ```
+ *
+ * ## Congestion control
+ *
+ * The `congestion-control` property selects the congestion controller of the
+ * connection: the loss based `cubic` (default) or `new-reno`, or the
+ * experimental model based `bbr` which keeps queues shorter and thus latency
+ * lower at the expense of some throughput. Packets are always paced out at the
+ * rate given by the congestion window and the RTT. Until the first RTT sample,
+ * `initial-rtt` is assumed, and `initial-window` allows starting with a larger
+ * window than the default of the algorithm for a faster ramp-up. The
+ * congestion controller mostly matters on the sending side of the data.
  */
 use gst::glib;
 use gst::prelude::*;
@@ -8,10 +8,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use crate::utils::{
-    client_endpoint, make_socket_addr, server_endpoint, wait, Canceller, WaitError,
-    CONNECTION_CLOSE_CODE, CONNECTION_CLOSE_MSG,
+    client_endpoint, make_socket_addr, server_endpoint, wait, Canceller, CertificateResolver,
+    Certificates, CongestionControl, WaitError, CONNECTION_CLOSE_CODE, CONNECTION_CLOSE_MSG,
 };
-use crate::{common::*, utils};
+use crate::webtransport::{self, BoxError, RecvStream, Transport};
+use crate::{common::*, roq, utils};
 use bytes::Bytes;
 use futures::future;
 use gst::{glib, prelude::*, subclass::prelude::*};
@@ -19,12 +20,17 @@ use gst_base::prelude::*;
 use gst_base::subclass::base_src::CreateSuccess;
 use gst_base::subclass::prelude::*;
 use once_cell::sync::Lazy;
-use quinn::{Connection, ConnectionError, ReadError, RecvStream};
+use quinn::{ConnectionError, ReadError};
+use std::collections::VecDeque;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 const DEFAULT_ROLE: QuinnQuicRole = QuinnQuicRole::Server;
 
+/// Largest stream read as a single buffer with the buffer stream mapping.
+const MAX_BUFFER_STREAM_SIZE: usize = 64 * 1024 * 1024;
+
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
         "quinnquicsrc",
@@ -34,8 +40,11 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 });
 
 struct Started {
-    connection: Connection,
+    transport: Transport,
     stream: Option<RecvStream>,
+    roq_packets: VecDeque<Bytes>,
+    datagrams_received: u64,
+    datagrams_dropped: u64,
 }
 
 #[derive(Default)]
@@ -59,8 +68,19 @@ struct Settings {
     secure_conn: bool,
     caps: gst::Caps,
     use_datagram: bool,
+    max_datagram_size: u32,
+    webtransport: bool,
+    webtransport_path: String,
+    roq: bool,
+    roq_flow_id: u64,
+    stream_mapping: QuinnQuicStreamMapping,
     certificate_file: Option<PathBuf>,
     private_key_file: Option<PathBuf>,
+    ca_file: Option<PathBuf>,
+    certificate_reload_interval: u32,
+    congestion_control: QuinnQuicCongestionControl,
+    initial_window: u64,
+    initial_rtt: u32,
 }
 
 impl Default for Settings {
@@ -78,8 +98,19 @@ impl Default for Settings {
             secure_conn: DEFAULT_SECURE_CONNECTION,
             caps: gst::Caps::new_any(),
             use_datagram: false,
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            webtransport: false,
+            webtransport_path: DEFAULT_WEBTRANSPORT_PATH.to_string(),
+            roq: false,
+            roq_flow_id: DEFAULT_ROQ_FLOW_ID,
+            stream_mapping: DEFAULT_STREAM_MAPPING,
             certificate_file: None,
             private_key_file: None,
+            ca_file: None,
+            certificate_reload_interval: DEFAULT_CERTIFICATE_RELOAD_INTERVAL,
+            congestion_control: DEFAULT_CONGESTION_CONTROL,
+            initial_window: DEFAULT_INITIAL_WINDOW,
+            initial_rtt: DEFAULT_INITIAL_RTT,
         }
     }
 }
@@ -88,6 +119,7 @@ pub struct QuinnQuicSrc {
     settings: Mutex<Settings>,
     state: Mutex<State>,
     canceller: Mutex<utils::Canceller>,
+    certificates: Mutex<Option<Certificates>>,
 }
 
 impl Default for QuinnQuicSrc {
@@ -96,6 +128,7 @@ impl Default for QuinnQuicSrc {
             settings: Mutex::new(Settings::default()),
             state: Mutex::new(State::default()),
             canceller: Mutex::new(utils::Canceller::default()),
+            certificates: Mutex::new(None),
         }
     }
 }
@@ -164,6 +197,21 @@ impl ObjectImpl for QuinnQuicSrc {
         self.obj().set_format(gst::Format::Bytes);
     }
 
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![glib::subclass::Signal::builder("reload-certificates")
+                .action()
+                .return_type::<bool>()
+                .class_handler(|_, args| {
+                    let element = args[0].get::<super::QuinnQuicSrc>().expect("signal arg");
+                    Some(element.imp().reload_certificates().to_value())
+                })
+                .build()]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
             vec![
@@ -228,6 +276,31 @@ impl ObjectImpl for QuinnQuicSrc {
                     .nick("Private key file")
                     .blurb("Path to a PKCS8 or RSA private key file")
                     .build(),
+                glib::ParamSpecString::builder("ca-file")
+                    .nick("CA file")
+                    .blurb("Path to the CA certificates to verify the peer's certificate with (default: the certificate chain of certificate-file)")
+                    .build(),
+                glib::ParamSpecUInt::builder("certificate-reload-interval")
+                    .nick("Certificate reload interval")
+                    .blurb("Interval in seconds at which the certificate and private key files are checked for modifications and reloaded for the next handshakes (0 = disabled)")
+                    .default_value(DEFAULT_CERTIFICATE_RELOAD_INTERVAL)
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("congestion-control", DEFAULT_CONGESTION_CONTROL)
+                    .nick("Congestion control")
+                    .blurb("Congestion control algorithm of the QUIC connection")
+                    .build(),
+                glib::ParamSpecUInt64::builder("initial-window")
+                    .nick("Initial congestion window")
+                    .blurb("Initial congestion window in bytes (0 = default of the congestion control algorithm)")
+                    .default_value(DEFAULT_INITIAL_WINDOW)
+                    .build(),
+                glib::ParamSpecUInt::builder("initial-rtt")
+                    .nick("Initial RTT")
+                    .blurb("RTT in ms assumed until the first measurement, which together with the initial window determines the initial pacing rate")
+                    .minimum(1)
+                    .maximum(60_000)
+                    .default_value(DEFAULT_INITIAL_RTT)
+                    .build(),
                 glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
                     .nick("caps")
                     .blurb("The caps of the source pad")
@@ -237,6 +310,41 @@ impl ObjectImpl for QuinnQuicSrc {
                     .blurb("Use datagram for lower latency, unreliable messaging")
                     .default_value(false)
                     .build(),
+                glib::ParamSpecUInt::builder("max-datagram-size")
+                    .nick("Maximum datagram size")
+                    .blurb("Received datagrams larger than this are dropped in datagram mode (0 = no limit)")
+                    .default_value(DEFAULT_MAX_DATAGRAM_SIZE)
+                    .build(),
+                glib::ParamSpecBoolean::builder("webtransport")
+                    .nick("WebTransport")
+                    .blurb("Receive over a WebTransport session on top of the QUIC connection, using the h3 ALPN")
+                    .default_value(false)
+                    .build(),
+                glib::ParamSpecString::builder("webtransport-path")
+                    .nick("WebTransport path")
+                    .blurb("Path of the WebTransport session requested in client role")
+                    .default_value(Some(DEFAULT_WEBTRANSPORT_PATH))
+                    .build(),
+                glib::ParamSpecBoolean::builder("roq")
+                    .nick("RTP over QUIC")
+                    .blurb("Receive RTP packets with the RTP over QUIC (RoQ) mapping, from streams and datagrams")
+                    .default_value(false)
+                    .build(),
+                glib::ParamSpecUInt64::builder("roq-flow-id")
+                    .nick("RoQ flow identifier")
+                    .blurb("Flow identifier of the RTP session in RoQ mode, packets of other flows are ignored")
+                    .maximum(roq::MAX_VARINT)
+                    .default_value(DEFAULT_ROQ_FLOW_ID)
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("stream-mapping", DEFAULT_STREAM_MAPPING)
+                    .nick("Stream mapping")
+                    .blurb("How buffers are mapped to QUIC streams by the sender when not using datagrams")
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Datagram statistics: datagrams received, dropped for being too large, and received by the connection")
+                    .read_only()
+                    .build(),
             ]
         });
 
@@ -304,9 +412,49 @@ impl ObjectImpl for QuinnQuicSrc {
                 let value: String = value.get().unwrap();
                 settings.private_key_file = Some(value.into());
             }
+            "ca-file" => {
+                settings.ca_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "certificate-reload-interval" => {
+                settings.certificate_reload_interval = value.get().expect("type checked upstream");
+            }
+            "congestion-control" => {
+                settings.congestion_control = value
+                    .get::<QuinnQuicCongestionControl>()
+                    .expect("type checked upstream");
+            }
+            "initial-window" => {
+                settings.initial_window = value.get().expect("type checked upstream");
+            }
+            "initial-rtt" => {
+                settings.initial_rtt = value.get().expect("type checked upstream");
+            }
             "use-datagram" => {
                 settings.use_datagram = value.get().expect("type checked upstream");
             }
+            "max-datagram-size" => {
+                settings.max_datagram_size = value.get().expect("type checked upstream");
+            }
+            "webtransport" => {
+                settings.webtransport = value.get().expect("type checked upstream");
+            }
+            "webtransport-path" => {
+                settings.webtransport_path = value.get::<String>().expect("type checked upstream");
+            }
+            "roq" => {
+                settings.roq = value.get().expect("type checked upstream");
+            }
+            "roq-flow-id" => {
+                settings.roq_flow_id = value.get().expect("type checked upstream");
+            }
+            "stream-mapping" => {
+                settings.stream_mapping = value
+                    .get::<QuinnQuicStreamMapping>()
+                    .expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -343,7 +491,22 @@ impl ObjectImpl for QuinnQuicSrc {
                 let privkey = settings.private_key_file.as_ref();
                 privkey.and_then(|file| file.to_str()).to_value()
             }
+            "ca-file" => {
+                let cafile = settings.ca_file.as_ref();
+                cafile.and_then(|file| file.to_str()).to_value()
+            }
+            "certificate-reload-interval" => settings.certificate_reload_interval.to_value(),
+            "congestion-control" => settings.congestion_control.to_value(),
+            "initial-window" => settings.initial_window.to_value(),
+            "initial-rtt" => settings.initial_rtt.to_value(),
             "use-datagram" => settings.use_datagram.to_value(),
+            "max-datagram-size" => settings.max_datagram_size.to_value(),
+            "webtransport" => settings.webtransport.to_value(),
+            "webtransport-path" => settings.webtransport_path.to_value(),
+            "roq" => settings.roq.to_value(),
+            "roq-flow-id" => settings.roq_flow_id.to_value(),
+            "stream-mapping" => settings.stream_mapping.to_value(),
+            "stats" => self.stats().to_value(),
             _ => unimplemented!(),
         }
     }
@@ -366,17 +529,22 @@ impl BaseSrcImpl for QuinnQuicSrc {
         let timeout = settings.timeout;
         drop(settings);
 
+        let certificates = self.load_certificates()?;
+
         let mut state = self.state.lock().unwrap();
 
         if let State::Started { .. } = *state {
             unreachable!("QuicSrc already started");
         }
 
-        match wait(&self.canceller, self.init_connection(), timeout) {
-            Ok(Ok((c, s))) => {
+        match wait(&self.canceller, self.init_connection(certificates), timeout) {
+            Ok(Ok((t, s))) => {
                 *state = State::Started(Started {
-                    connection: c,
+                    transport: t,
                     stream: s,
+                    roq_packets: VecDeque::new(),
+                    datagrams_received: 0,
+                    datagrams_dropped: 0,
                 });
 
                 gst::info!(CAT, imp: self, "Started");
@@ -403,15 +571,13 @@ impl BaseSrcImpl for QuinnQuicSrc {
         let mut state = self.state.lock().unwrap();
 
         if let State::Started(ref mut state) = *state {
-            let connection = &state.connection;
-
-            connection.close(
-                CONNECTION_CLOSE_CODE.into(),
-                CONNECTION_CLOSE_MSG.as_bytes(),
-            );
+            state
+                .transport
+                .close(CONNECTION_CLOSE_CODE, CONNECTION_CLOSE_MSG.as_bytes());
         }
 
         *state = State::Stopped;
+        *self.certificates.lock().unwrap() = None;
 
         Ok(())
     }
@@ -502,19 +668,51 @@ impl BaseSrcImpl for QuinnQuicSrc {
 }
 
 impl QuinnQuicSrc {
+    fn stats(&self) -> gst::Structure {
+        let state = self.state.lock().unwrap();
+
+        let (received, dropped, connection_received) = match *state {
+            State::Started(ref started) => (
+                started.datagrams_received,
+                started.datagrams_dropped,
+                started.transport.connection().stats().frame_rx.datagram,
+            ),
+            State::Stopped => (0, 0, 0),
+        };
+
+        gst::Structure::builder("application/x-quinnquicsrc-stats")
+            .field("datagrams-received", received)
+            .field("datagrams-dropped", dropped)
+            .field("datagrams-connection-received", connection_received)
+            .build()
+    }
+
     fn get(&self, _offset: u64, length: u64) -> Result<Bytes, Option<gst::ErrorMessage>> {
         let settings = self.settings.lock().unwrap();
         let timeout = settings.timeout;
         let use_datagram = settings.use_datagram;
+        let max_datagram_size = settings.max_datagram_size as usize;
+        let use_roq = settings.roq;
+        let roq_flow_id = settings.roq_flow_id;
+        let stream_mapping = settings.stream_mapping;
         drop(settings);
 
         let mut state = self.state.lock().unwrap();
 
-        let (conn, stream) = match *state {
+        let (transport, stream, roq_packets, datagrams_received, datagrams_dropped) = match *state {
             State::Started(Started {
-                ref connection,
+                ref transport,
                 ref mut stream,
-            }) => (connection, stream),
+                ref mut roq_packets,
+                ref mut datagrams_received,
+                ref mut datagrams_dropped,
+            }) => (
+                transport,
+                stream,
+                roq_packets,
+                datagrams_received,
+                datagrams_dropped,
+            ),
             State::Stopped => {
                 return Err(Some(gst::error_msg!(
                     gst::LibraryError::Failed,
@@ -523,55 +721,132 @@ impl QuinnQuicSrc {
             }
         };
 
+        // A RoQ stream may carry several packets
+        if let Some(packet) = roq_packets.pop_front() {
+            return Ok(packet);
+        }
+
         let future = async {
             if use_datagram {
-                match conn.read_datagram().await {
+                let res = loop {
+                    let bytes = match transport.read_datagram().await {
+                        Ok(bytes) => bytes,
+                        Err(err) => break Err(err),
+                    };
+
+                    if max_datagram_size > 0 && bytes.len() > max_datagram_size {
+                        *datagrams_dropped += 1;
+                        gst::warning!(
+                            CAT,
+                            imp: self,
+                            "Dropping {} bytes datagram, larger than the maximum of {} bytes",
+                            bytes.len(),
+                            max_datagram_size
+                        );
+                        continue;
+                    }
+
+                    let bytes = if use_roq {
+                        match roq::parse_datagram(&bytes) {
+                            Some((flow_id, packet)) if flow_id == roq_flow_id => packet,
+                            Some((flow_id, _)) => {
+                                gst::trace!(CAT, imp: self, "Ignoring datagram of flow {}", flow_id);
+                                continue;
+                            }
+                            None => {
+                                gst::warning!(CAT, imp: self, "Dropping invalid RoQ datagram");
+                                *datagrams_dropped += 1;
+                                continue;
+                            }
+                        }
+                    } else {
+                        bytes
+                    };
+
+                    if bytes.is_empty() {
+                        continue;
+                    }
+
+                    *datagrams_received += 1;
+                    break Ok(bytes);
+                };
+
+                match res {
                     Ok(bytes) => Ok(bytes),
-                    Err(err) => match err {
-                        ConnectionError::ApplicationClosed(ac) => {
-                            gst::info!(CAT, imp: self, "Application closed connection, {}", ac);
-                            Ok(Bytes::new())
+                    Err(err) => self.handle_read_error(transport, err, "Datagram"),
+                }
+            } else if use_roq {
+                loop {
+                    let mut recv = match transport.accept_uni().await {
+                        Ok(recv) => recv,
+                        Err(err) => break self.handle_read_error(transport, err, "Stream"),
+                    };
+
+                    // Streams may be reset by the sender to abandon late packets
+                    let data = match recv.read_to_end(roq::MAX_STREAM_SIZE).await {
+                        Ok(data) => data,
+                        Err(err) if transport.connection().close_reason().is_none() => {
+                            gst::warning!(CAT, imp: self, "Skipping RoQ stream: {}", err);
+                            continue;
                         }
-                        ConnectionError::ConnectionClosed(cc) => {
-                            gst::info!(CAT, imp: self, "Transport closed connection, {}", cc);
-                            Ok(Bytes::new())
+                        Err(err) => break self.handle_read_error(transport, err, "Stream"),
+                    };
+
+                    match roq::parse_stream(&data) {
+                        Some((flow_id, packets)) if flow_id == roq_flow_id => {
+                            roq_packets.extend(packets.into_iter().filter(|p| !p.is_empty()));
+                            if let Some(packet) = roq_packets.pop_front() {
+                                break Ok(packet);
+                            }
                         }
-                        _ => Err(WaitError::FutureError(gst::error_msg!(
-                            gst::ResourceError::Failed,
-                            ["Datagram read error: {}", err]
-                        ))),
-                    },
+                        Some((flow_id, _)) => {
+                            gst::trace!(CAT, imp: self, "Ignoring stream of flow {}", flow_id);
+                        }
+                        None => {
+                            gst::warning!(CAT, imp: self, "Skipping invalid RoQ stream");
+                        }
+                    }
                 }
-            } else {
+            } else if stream_mapping == QuinnQuicStreamMapping::Single {
                 let recv = stream.as_mut().unwrap();
 
-                match recv.read_chunk(length as usize, true).await {
-                    Ok(Some(chunk)) => Ok(chunk.bytes),
+                match recv.read_chunk(length as usize).await {
+                    Ok(Some(bytes)) => Ok(bytes),
                     Ok(None) => Ok(Bytes::new()),
-                    Err(err) => match err {
-                        ReadError::ConnectionLost(conn_err) => match conn_err {
-                            ConnectionError::ConnectionClosed(cc) => {
-                                gst::info!(CAT, imp: self, "Transport closed connection, {}", cc);
-                                Ok(Bytes::new())
-                            }
-                            ConnectionError::ApplicationClosed(ac) => {
-                                gst::info!(CAT, imp: self, "Application closed connection, {}", ac);
-                                Ok(Bytes::new())
+                    Err(err) => self.handle_read_error(transport, err, "Stream"),
+                }
+            } else {
+                loop {
+                    if stream.is_none() {
+                        match transport.accept_uni().await {
+                            Ok(recv) => *stream = Some(recv),
+                            Err(err) => break self.handle_read_error(transport, err, "Stream"),
+                        }
+                    }
+
+                    let recv = stream.as_mut().unwrap();
+                    let res = if stream_mapping == QuinnQuicStreamMapping::Buffer {
+                        recv.read_to_end(MAX_BUFFER_STREAM_SIZE).await.map(Some)
+                    } else {
+                        recv.read_chunk(length as usize).await
+                    };
+
+                    match res {
+                        Ok(Some(bytes)) if !bytes.is_empty() => {
+                            if stream_mapping == QuinnQuicStreamMapping::Buffer {
+                                *stream = None;
                             }
-                            _ => Err(WaitError::FutureError(gst::error_msg!(
-                                gst::ResourceError::Failed,
-                                ["Stream read error: {}", conn_err]
-                            ))),
-                        },
-                        ReadError::ClosedStream => {
-                            gst::info!(CAT, imp: self, "Stream closed");
-                            Ok(Bytes::new())
+                            break Ok(bytes);
                         }
-                        _ => Err(WaitError::FutureError(gst::error_msg!(
-                            gst::ResourceError::Failed,
-                            ["Stream read error: {}", err]
-                        ))),
-                    },
+                        // Continue with the next stream
+                        Ok(_) => *stream = None,
+                        // Stale streams may be reset by the sender
+                        Err(err) if transport.connection().close_reason().is_none() => {
+                            gst::debug!(CAT, imp: self, "Skipping stream: {}", err);
+                            *stream = None;
+                        }
+                        Err(err) => break self.handle_read_error(transport, err, "Stream"),
+                    }
                 }
             }
         };
@@ -591,7 +866,110 @@ impl QuinnQuicSrc {
         }
     }
 
-    async fn init_connection(&self) -> Result<(Connection, Option<RecvStream>), WaitError> {
+    /// Maps read errors caused by the peer closing the connection or the
+    /// stream to EOS.
+    fn handle_read_error(
+        &self,
+        transport: &Transport,
+        err: BoxError,
+        kind: &str,
+    ) -> Result<Bytes, WaitError> {
+        match transport.connection().close_reason() {
+            Some(ConnectionError::ApplicationClosed(ac)) => {
+                gst::info!(CAT, imp: self, "Application closed connection, {}", ac);
+                Ok(Bytes::new())
+            }
+            Some(ConnectionError::ConnectionClosed(cc)) => {
+                gst::info!(CAT, imp: self, "Transport closed connection, {}", cc);
+                Ok(Bytes::new())
+            }
+            _ if matches!(
+                err.downcast_ref::<ReadError>(),
+                Some(ReadError::ClosedStream)
+            ) =>
+            {
+                gst::info!(CAT, imp: self, "Stream closed");
+                Ok(Bytes::new())
+            }
+            _ => Err(WaitError::FutureError(gst::error_msg!(
+                gst::ResourceError::Failed,
+                ["{} read error: {}", kind, err]
+            ))),
+        }
+    }
+
+    /// Loads the certificates of a secure connection, watching their files for
+    /// modifications if enabled.
+    fn load_certificates(&self) -> Result<Option<Arc<CertificateResolver>>, gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        if !settings.secure_conn {
+            return Ok(None);
+        }
+
+        let mut certificates =
+            Certificates::load(settings.certificate_file, settings.private_key_file).map_err(
+                |err| {
+                    gst::error_msg!(
+                        gst::ResourceError::Settings,
+                        ["Failed to load certificates: {}", err]
+                    )
+                },
+            )?;
+
+        if settings.certificate_reload_interval > 0 {
+            let element = self.obj().downgrade();
+            certificates.watch(
+                Duration::from_secs(settings.certificate_reload_interval.into()),
+                move |res| {
+                    let Some(element) = element.upgrade() else {
+                        return;
+                    };
+
+                    match res {
+                        Ok(()) => gst::info!(CAT, obj: element, "Reloaded modified certificates"),
+                        Err(err) => gst::warning!(
+                            CAT,
+                            obj: element,
+                            "Failed to reload modified certificates: {}",
+                            err
+                        ),
+                    }
+                },
+            );
+        }
+
+        let resolver = certificates.resolver();
+        *self.certificates.lock().unwrap() = Some(certificates);
+
+        Ok(Some(resolver))
+    }
+
+    /// Reloads the certificates for the next handshakes, established
+    /// connections are kept.
+    fn reload_certificates(&self) -> bool {
+        let certificates = self.certificates.lock().unwrap();
+        let Some(ref certificates) = *certificates else {
+            gst::warning!(CAT, imp: self, "No certificates to reload");
+            return false;
+        };
+
+        match certificates.resolver().reload() {
+            Ok(()) => {
+                gst::info!(CAT, imp: self, "Reloaded certificates");
+                true
+            }
+            Err(err) => {
+                gst::warning!(CAT, imp: self, "Failed to reload certificates: {}", err);
+                false
+            }
+        }
+    }
+
+    async fn init_connection(
+        &self,
+        certificates: Option<Arc<CertificateResolver>>,
+    ) -> Result<(Transport, Option<RecvStream>), WaitError> {
         let server_addr;
         let server_name;
         let client_addr;
@@ -599,9 +977,13 @@ impl QuinnQuicSrc {
         let role;
         let use_datagram;
         let keep_alive_interval;
+        let congestion_control;
         let secure_conn;
-        let cert_file;
-        let private_key_file;
+        let ca_file;
+        let use_webtransport;
+        let webtransport_path;
+        let use_roq;
+        let stream_mapping;
 
         {
             let settings = self.settings.lock().unwrap();
@@ -614,13 +996,25 @@ impl QuinnQuicSrc {
                 make_socket_addr(format!("{}:{}", settings.address, settings.port).as_str())?;
 
             server_name = settings.server_name.clone();
-            alpns = settings.alpns.clone();
+            use_webtransport = settings.webtransport;
+            webtransport_path = settings.webtransport_path.clone();
+            use_roq = settings.roq;
+            stream_mapping = settings.stream_mapping;
+            alpns = if use_webtransport {
+                vec![webtransport::ALPN.to_string()]
+            } else {
+                settings.alpns.clone()
+            };
             role = settings.role;
             use_datagram = settings.use_datagram;
             keep_alive_interval = settings.keep_alive_interval;
+            congestion_control = CongestionControl {
+                algorithm: settings.congestion_control,
+                initial_window: settings.initial_window,
+                initial_rtt: Duration::from_millis(settings.initial_rtt.into()),
+            };
             secure_conn = settings.secure_conn;
-            cert_file = settings.certificate_file.clone();
-            private_key_file = settings.private_key_file.clone();
+            ca_file = settings.ca_file.clone();
         }
 
         let connection;
@@ -632,8 +1026,11 @@ impl QuinnQuicSrc {
                     &server_name,
                     secure_conn,
                     alpns,
-                    cert_file,
-                    private_key_file,
+                    certificates,
+                    ca_file,
+                    use_webtransport,
+                    use_roq || stream_mapping != QuinnQuicStreamMapping::Single,
+                    &congestion_control,
                 )
                 .map_err(|err| {
                     WaitError::FutureError(gst::error_msg!(
@@ -656,9 +1053,10 @@ impl QuinnQuicSrc {
                     client_addr,
                     secure_conn,
                     alpns,
-                    cert_file,
-                    private_key_file,
+                    certificates,
+                    ca_file,
                     keep_alive_interval,
+                    &congestion_control,
                 )
                 .map_err(|err| {
                     WaitError::FutureError(gst::error_msg!(
@@ -680,26 +1078,59 @@ impl QuinnQuicSrc {
             }
         }
 
-        let stream = if !use_datagram {
-            let res = connection.accept_uni().await.map_err(|err| {
+        let remote_address = connection.remote_address();
+
+        let transport = if !use_webtransport {
+            Transport::quic(connection)
+        } else if role == QuinnQuicRole::Server {
+            let (transport, url) = Transport::accept(connection).await.map_err(|err| {
                 WaitError::FutureError(gst::error_msg!(
                     gst::ResourceError::Failed,
-                    ["Failed to open stream: {}", err]
+                    ["WebTransport session error: {}", err]
                 ))
             })?;
 
-            Some(res)
+            gst::info!(CAT, imp: self, "Accepted WebTransport session for {}", url);
+
+            transport
         } else {
-            None
+            Transport::connect(
+                connection,
+                &server_name,
+                server_addr.port(),
+                &webtransport_path,
+            )
+            .await
+            .map_err(|err| {
+                WaitError::FutureError(gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["WebTransport session error: {}", err]
+                ))
+            })?
         };
 
+        // Otherwise, streams are accepted when reading
+        let stream =
+            if !use_datagram && !use_roq && stream_mapping == QuinnQuicStreamMapping::Single {
+                let res = transport.accept_uni().await.map_err(|err| {
+                    WaitError::FutureError(gst::error_msg!(
+                        gst::ResourceError::Failed,
+                        ["Failed to open stream: {}", err]
+                    ))
+                })?;
+
+                Some(res)
+            } else {
+                None
+            };
+
         gst::info!(
             CAT,
             imp: self,
             "Remote connection accepted: {}",
-            connection.remote_address()
+            remote_address
         );
 
-        Ok((connection, stream))
+        Ok((transport, stream))
     }
 }
@@ -19,6 +19,66 @@
  * audio/x-raw,format=S16LE,rate=48000,channels=2,layout=interleaved ! \
  * audioconvert ! autoaudiosink
  * ```
+ *
+ * ## Certificates
+ *
+ * With `secure-connection`, both peers present the certificate chain of
+ * `certificate-file` and verify the certificate of the other peer, i.e. the
+ * server also authenticates the client. The peer's certificate is verified with
+ * the CA certificates of `ca-file`, or by default with the own certificate
+ * chain.
+ *
+ * The certificate and private key can be rotated at runtime, either with the
+ * `reload-certificates` action signal or by setting
+ * `certificate-reload-interval` to check the files for modifications
+ * periodically. The reloaded certificate is presented in the following
+ * handshakes, the established connection is kept.
+ *
+ * ## WebTransport
+ *
+ * With the `webtransport` property set, the data is received over a
+ * WebTransport session established on top of the QUIC connection with an
+ * HTTP/3 CONNECT request, so that a browser can feed the pipeline with the
+ * WebTransport API. In client role, the session is requested for
+ * `webtransport-path`. In server role, the session of the connecting client is
+ * accepted whatever its path. The data is read from the first unidirectional
+ * or bidirectional stream opened by the peer, or from WebTransport datagrams if
+ * `use-datagram` is set. The `h3` ALPN is used and the `alpn-protocols`
+ * property is ignored.
+ *
+ * ## Stream mapping
+ *
+ * The `stream-mapping` property must match the one of the sender. With
+ * `buffer`, each stream opened by the peer is output as a single buffer. With
+ * `gop`, the streams are read one after the other. Streams reset by the sender
+ * for being stale are skipped.
+ *
+ * ## RTP over QUIC
+ *
+ * With the `roq` property set, RTP packets are received following the
+ * [RTP over QUIC](https://datatracker.ietf.org/doc/html/draft-ietf-avtcore-rtp-over-quic)
+ * (RoQ) mapping, from any number of unidirectional streams opened by the peer,
+ * or from datagrams if `use-datagram` is set. Only the packets of the
+ * `roq-flow-id` flow are output, one per buffer. As streams can be received
+ * out of order, an `rtpjitterbuffer` is usually needed downstream.
+ *
+ * ```bash
+ * gst-launch-1.0 -v -e quinnquicsrc roq=true roq-flow-id=1 use-datagram=true \
+ * caps="application/x-rtp,media=video,clock-rate=90000,encoding-name=H264" \
+ * address="127.0.0.1" port=6000 secure-connection=false ! rtpjitterbuffer ! \
+ * rtph264depay ! avdec_h264 ! videoconvert ! autovideosink
+ * ```
+ *
+ * ## Congestion control
+ *
+ * The `congestion-control` property selects the congestion controller of the
+ * connection: the loss based `cubic` (default) or `new-reno`, or the
+ * experimental model based `bbr` which keeps queues shorter and thus latency
+ * lower at the expense of some throughput. Packets are always paced out at the
+ * rate given by the congestion window and the RTT. Until the first RTT sample,
+ * `initial-rtt` is assumed, and `initial-window` allows starting with a larger
+ * window than the default of the algorithm for a faster ramp-up. The
+ * congestion controller mostly matters on the sending side of the data.
  */
 use gst::glib;
 use gst::prelude::*;
@@ -0,0 +1,101 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! RTP over QUIC (RoQ) framing.
+//!
+//! See <https://datatracker.ietf.org/doc/html/draft-ietf-avtcore-rtp-over-quic>.
+//!
+//! A datagram carries the flow identifier followed by a single RTP packet. A
+//! stream starts with the flow identifier followed by length prefixed RTP
+//! packets. All integers are QUIC variable-length integers.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Largest value of a QUIC variable-length integer.
+pub const MAX_VARINT: u64 = (1 << 62) - 1;
+
+/// Largest stream accepted by the receiver.
+pub const MAX_STREAM_SIZE: usize = 1024 * 1024;
+
+pub fn varint_size(value: u64) -> usize {
+    match value {
+        0..=0x3f => 1,
+        0x40..=0x3fff => 2,
+        0x4000..=0x3fff_ffff => 4,
+        _ => 8,
+    }
+}
+
+pub fn put_varint(buf: &mut BytesMut, value: u64) {
+    assert!(value <= MAX_VARINT);
+
+    match varint_size(value) {
+        1 => buf.put_u8(value as u8),
+        2 => buf.put_u16(0x4000 | value as u16),
+        4 => buf.put_u32(0x8000_0000 | value as u32),
+        _ => buf.put_u64(0xc000_0000_0000_0000 | value),
+    }
+}
+
+/// Parses a variable-length integer, returning it with its size.
+pub fn get_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let first = *data.first()?;
+    let size = 1 << (first >> 6);
+    let bytes = data.get(..size)?;
+
+    let value = bytes[1..]
+        .iter()
+        .fold(u64::from(first & 0x3f), |acc, b| (acc << 8) | u64::from(*b));
+
+    Some((value, size))
+}
+
+pub fn datagram(flow_id: u64, packet: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(varint_size(flow_id) + packet.len());
+    put_varint(&mut buf, flow_id);
+    buf.put_slice(packet);
+
+    buf.freeze()
+}
+
+/// Splits a datagram into its flow identifier and RTP packet.
+pub fn parse_datagram(datagram: &Bytes) -> Option<(u64, Bytes)> {
+    let (flow_id, size) = get_varint(datagram)?;
+
+    Some((flow_id, datagram.slice(size..)))
+}
+
+/// Frames a single RTP packet for sending on its own stream.
+pub fn stream(flow_id: u64, packet: &[u8]) -> Bytes {
+    let len = packet.len() as u64;
+    let mut buf = BytesMut::with_capacity(varint_size(flow_id) + varint_size(len) + packet.len());
+    put_varint(&mut buf, flow_id);
+    put_varint(&mut buf, len);
+    buf.put_slice(packet);
+
+    buf.freeze()
+}
+
+/// Splits the complete content of a stream into its flow identifier and RTP
+/// packets.
+pub fn parse_stream(data: &Bytes) -> Option<(u64, Vec<Bytes>)> {
+    let (flow_id, mut offset) = get_varint(data)?;
+    let mut packets = Vec::new();
+
+    while offset < data.len() {
+        let (len, size) = get_varint(&data[offset..])?;
+        let start = offset + size;
+        let end = start.checked_add(usize::try_from(len).ok()?)?;
+        if end > data.len() {
+            return None;
+        }
+
+        packets.push(data.slice(start..end));
+        offset = end;
+    }
+
+    Some((flow_id, packets))
+}
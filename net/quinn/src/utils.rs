@@ -7,27 +7,76 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+use crate::common::QuinnQuicCongestionControl;
 use futures::future;
 use futures::prelude::*;
 use gst::ErrorMessage;
 use once_cell::sync::Lazy;
 use quinn::{
-    crypto::rustls::QuicClientConfig, crypto::rustls::QuicServerConfig, ClientConfig, Endpoint,
-    ServerConfig, TransportConfig,
+    congestion::{BbrConfig, CubicConfig, NewRenoConfig},
+    crypto::rustls::QuicClientConfig,
+    crypto::rustls::QuicServerConfig,
+    ClientConfig, Endpoint, ServerConfig, TransportConfig,
 };
+use rustls::sign::CertifiedKey;
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 use tokio::runtime;
 
 pub const CONNECTION_CLOSE_CODE: u32 = 0;
 pub const CONNECTION_CLOSE_MSG: &str = "Stopped";
 
+const MULTI_STREAM_MAX_CONCURRENT_STREAMS: u32 = 128;
+
+/// Congestion controller and the parameters it starts with.
+#[derive(Clone, Copy, Debug)]
+pub struct CongestionControl {
+    pub algorithm: QuinnQuicCongestionControl,
+    /// Initial congestion window in bytes, 0 for quinn's default.
+    pub initial_window: u64,
+    /// RTT assumed until the first sample. Together with the congestion window this
+    /// determines the rate at which packets are paced out initially.
+    pub initial_rtt: Duration,
+}
+
+impl CongestionControl {
+    fn configure(&self, transport: &mut TransportConfig) {
+        let initial_window = (self.initial_window > 0).then_some(self.initial_window);
+
+        match self.algorithm {
+            QuinnQuicCongestionControl::Cubic => {
+                let mut config = CubicConfig::default();
+                if let Some(initial_window) = initial_window {
+                    config.initial_window(initial_window);
+                }
+                transport.congestion_controller_factory(Arc::new(config));
+            }
+            QuinnQuicCongestionControl::NewReno => {
+                let mut config = NewRenoConfig::default();
+                if let Some(initial_window) = initial_window {
+                    config.initial_window(initial_window);
+                }
+                transport.congestion_controller_factory(Arc::new(config));
+            }
+            QuinnQuicCongestionControl::Bbr => {
+                let mut config = BbrConfig::default();
+                if let Some(initial_window) = initial_window {
+                    config.initial_window(initial_window);
+                }
+                transport.congestion_controller_factory(Arc::new(config));
+            }
+        }
+
+        transport.initial_rtt(self.initial_rtt);
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum WaitError {
     #[error("Future aborted")]
@@ -196,21 +245,198 @@ impl rustls::client::danger::ServerCertVerifier for SkipServerVerification {
     }
 }
 
+/// Certificate chain and private key presented in the handshakes, which can
+/// be reloaded from their files at runtime. Established connections are not
+/// affected, the reloaded ones are used for the following handshakes.
+#[derive(Debug)]
+pub struct CertificateResolver {
+    certificate_file: PathBuf,
+    private_key_file: PathBuf,
+    key: Mutex<Arc<CertifiedKey>>,
+    /// Modification times of the files when they were last loaded
+    modified: Mutex<Option<(SystemTime, SystemTime)>>,
+}
+
+impl CertificateResolver {
+    fn new(certificate_file: PathBuf, private_key_file: PathBuf) -> Result<Self, Box<dyn Error>> {
+        let modified = Self::files_modified(&certificate_file, &private_key_file);
+        let key = Self::load(&certificate_file, &private_key_file)?;
+
+        Ok(Self {
+            certificate_file,
+            private_key_file,
+            key: Mutex::new(key),
+            modified: Mutex::new(modified),
+        })
+    }
+
+    fn load(
+        certificate_file: &Path,
+        private_key_file: &Path,
+    ) -> Result<Arc<CertifiedKey>, Box<dyn Error>> {
+        let (certs, key) = read_certs_from_file(
+            Some(certificate_file.to_path_buf()),
+            Some(private_key_file.to_path_buf()),
+        )?;
+        let key = rustls::crypto::ring::sign::any_supported_type(&key)?;
+
+        Ok(Arc::new(CertifiedKey::new(certs, key)))
+    }
+
+    fn files_modified(
+        certificate_file: &Path,
+        private_key_file: &Path,
+    ) -> Option<(SystemTime, SystemTime)> {
+        let certificate_modified = std::fs::metadata(certificate_file).ok()?.modified().ok()?;
+        let private_key_modified = std::fs::metadata(private_key_file).ok()?.modified().ok()?;
+
+        Some((certificate_modified, private_key_modified))
+    }
+
+    /// Whether the files were modified since they were last loaded.
+    fn changed(&self) -> bool {
+        let modified = Self::files_modified(&self.certificate_file, &self.private_key_file);
+
+        modified.is_some() && modified != *self.modified.lock().unwrap()
+    }
+
+    fn certificate_chain(&self) -> Vec<rustls_pki_types::CertificateDer<'static>> {
+        self.key.lock().unwrap().cert.clone()
+    }
+
+    /// Reloads the certificate chain and private key, keeping the current ones
+    /// if they can't be loaded.
+    pub fn reload(&self) -> Result<(), Box<dyn Error>> {
+        let modified = Self::files_modified(&self.certificate_file, &self.private_key_file);
+        let key = Self::load(&self.certificate_file, &self.private_key_file)?;
+
+        *self.key.lock().unwrap() = key;
+        *self.modified.lock().unwrap() = modified;
+
+        Ok(())
+    }
+}
+
+impl rustls::server::ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: rustls::server::ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(self.key.lock().unwrap().clone())
+    }
+}
+
+impl rustls::client::ResolvesClientCert for CertificateResolver {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        _sigschemes: &[rustls::SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        Some(self.key.lock().unwrap().clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+/// Certificates of a started element, possibly reloaded when their files
+/// change.
+pub struct Certificates {
+    resolver: Arc<CertificateResolver>,
+    watcher: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl Certificates {
+    pub fn load(
+        certificate_file: Option<PathBuf>,
+        private_key_file: Option<PathBuf>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let certificate_file = certificate_file.ok_or("No certificate file provided")?;
+        let private_key_file = private_key_file.ok_or("No private key file provided")?;
+
+        Ok(Self {
+            resolver: Arc::new(CertificateResolver::new(
+                certificate_file,
+                private_key_file,
+            )?),
+            watcher: None,
+        })
+    }
+
+    pub fn resolver(&self) -> Arc<CertificateResolver> {
+        self.resolver.clone()
+    }
+
+    /// Checks the files for modifications every `interval`, reloading them
+    /// and passing the result to `on_reload`.
+    pub fn watch<F>(&mut self, interval: Duration, on_reload: F)
+    where
+        F: Fn(Result<(), String>) + Send + 'static,
+    {
+        let resolver = self.resolver.clone();
+
+        self.watcher = Some(RUNTIME.spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+
+                // Retried on the next tick if the files are still being written
+                if resolver.changed() {
+                    on_reload(resolver.reload().map_err(|err| err.to_string()));
+                }
+            }
+        }));
+    }
+}
+
+impl Drop for Certificates {
+    fn drop(&mut self) {
+        if let Some(watcher) = self.watcher.take() {
+            watcher.abort();
+        }
+    }
+}
+
+/// Trust anchors to verify the peer's certificate with, from `ca_file` or by
+/// default the own certificate chain.
+fn root_cert_store(
+    ca_file: Option<PathBuf>,
+    resolver: &CertificateResolver,
+) -> Result<rustls::RootCertStore, Box<dyn Error>> {
+    let certs = match ca_file {
+        Some(ca_file) => {
+            let mut rdr = BufReader::new(File::open(ca_file.as_path())?);
+            rustls_pemfile::certs(&mut rdr).collect::<Result<Vec<_>, _>>()?
+        }
+        None => resolver.certificate_chain(),
+    };
+
+    let mut cert_store = rustls::RootCertStore::empty();
+    let (added, _) = cert_store.add_parsable_certificates(certs);
+    if added == 0 {
+        return Err("No valid CA certificate found".into());
+    }
+
+    Ok(cert_store)
+}
+
 fn configure_client(
     secure_conn: bool,
-    certificate_file: Option<PathBuf>,
-    private_key_file: Option<PathBuf>,
+    certificates: Option<Arc<CertificateResolver>>,
+    ca_file: Option<PathBuf>,
     alpns: Vec<String>,
     keep_alive_interval_ms: u64,
+    congestion_control: &CongestionControl,
 ) -> Result<ClientConfig, Box<dyn Error>> {
     let mut crypto = if secure_conn {
-        let (certs, key) = read_certs_from_file(certificate_file, private_key_file)?;
-        let mut cert_store = rustls::RootCertStore::empty();
-        cert_store.add_parsable_certificates(certs.clone());
+        let certificates = certificates.ok_or("No certificates provided")?;
+        let cert_store = root_cert_store(ca_file, &certificates)?;
 
         rustls::ClientConfig::builder()
             .with_root_certificates(Arc::new(cert_store))
-            .with_client_auth_cert(certs, key)?
+            .with_client_cert_resolver(certificates)
     } else {
         rustls::ClientConfig::builder()
             .dangerous()
@@ -227,11 +453,12 @@ fn configure_client(
 
     let mut client_config = ClientConfig::new(Arc::new(QuicClientConfig::try_from(crypto)?));
 
+    let mut transport_config = TransportConfig::default();
     if keep_alive_interval_ms > 0 {
-        let mut transport_config = TransportConfig::default();
         transport_config.keep_alive_interval(Some(Duration::from_millis(keep_alive_interval_ms)));
-        client_config.transport_config(Arc::new(transport_config));
     }
+    congestion_control.configure(&mut transport_config);
+    client_config.transport_config(Arc::new(transport_config));
 
     Ok(client_config)
 }
@@ -293,15 +520,26 @@ fn read_certs_from_file(
     Ok((certs, key))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn configure_server(
     server_name: &str,
     secure_conn: bool,
-    certificate_file: Option<PathBuf>,
-    private_key_file: Option<PathBuf>,
+    certificates: Option<Arc<CertificateResolver>>,
+    ca_file: Option<PathBuf>,
     alpns: Vec<String>,
-) -> Result<(ServerConfig, Vec<rustls_pki_types::CertificateDer>), Box<dyn Error>> {
-    let (certs, key) = if secure_conn {
-        read_certs_from_file(certificate_file, private_key_file)?
+    webtransport: bool,
+    multi_stream: bool,
+    congestion_control: &CongestionControl,
+) -> Result<ServerConfig, Box<dyn Error>> {
+    let mut crypto = if secure_conn {
+        let certificates = certificates.ok_or("No certificates provided")?;
+        let cert_store = root_cert_store(ca_file, &certificates)?;
+
+        let auth_client =
+            rustls::server::WebPkiClientVerifier::builder(Arc::new(cert_store)).build()?;
+        rustls::ServerConfig::builder()
+            .with_client_cert_verifier(auth_client)
+            .with_cert_resolver(certificates)
     } else {
         let rcgen::CertifiedKey { cert: _, key_pair } =
             rcgen::generate_simple_self_signed(vec![server_name.into()]).unwrap();
@@ -309,24 +547,10 @@ fn configure_server(
         let priv_key = rustls_pki_types::PrivateKeyDer::try_from(cert_der.clone()).unwrap();
         let cert_chain = vec![rustls_pki_types::CertificateDer::from(cert_der)];
 
-        (cert_chain, priv_key)
-    };
-
-    let mut crypto = if secure_conn {
-        let mut cert_store = rustls::RootCertStore::empty();
-        cert_store.add_parsable_certificates(certs.clone());
-
-        let auth_client = rustls::server::WebPkiClientVerifier::builder(Arc::new(cert_store))
-            .build()
-            .unwrap();
-        rustls::ServerConfig::builder()
-            .with_client_cert_verifier(auth_client)
-            .with_single_cert(certs.clone(), key)
-    } else {
         rustls::ServerConfig::builder()
             .with_no_client_auth()
-            .with_single_cert(certs.clone(), key)
-    }?;
+            .with_single_cert(cert_chain, priv_key)?
+    };
 
     let alpn_protocols: Vec<Vec<u8>> = alpns
         .iter()
@@ -337,28 +561,53 @@ fn configure_server(
     let mut server_config =
         ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(crypto)?));
 
-    Arc::get_mut(&mut server_config.transport)
-        .unwrap()
-        .max_concurrent_bidi_streams(0_u8.into())
-        .max_concurrent_uni_streams(1_u8.into());
+    let transport = Arc::get_mut(&mut server_config.transport).unwrap();
+    if webtransport {
+        /*
+         * On top of the data stream, HTTP/3 needs a bidirectional stream for
+         * the CONNECT request and unidirectional control and QPACK streams.
+         * Browsers may also send the data on a bidirectional stream.
+         */
+        transport
+            .max_concurrent_bidi_streams(4_u8.into())
+            .max_concurrent_uni_streams(8_u8.into());
+    } else {
+        transport
+            .max_concurrent_bidi_streams(0_u8.into())
+            .max_concurrent_uni_streams(1_u8.into());
+    }
+
+    if multi_stream {
+        // The peer may send each packet, buffer or GOP on its own stream
+        transport.max_concurrent_uni_streams(MULTI_STREAM_MAX_CONCURRENT_STREAMS.into());
+    }
 
-    Ok((server_config, certs))
+    congestion_control.configure(transport);
+
+    Ok(server_config)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn server_endpoint(
     server_addr: SocketAddr,
     server_name: &str,
     secure_conn: bool,
     alpns: Vec<String>,
-    certificate_file: Option<PathBuf>,
-    private_key_file: Option<PathBuf>,
+    certificates: Option<Arc<CertificateResolver>>,
+    ca_file: Option<PathBuf>,
+    webtransport: bool,
+    multi_stream: bool,
+    congestion_control: &CongestionControl,
 ) -> Result<Endpoint, Box<dyn Error>> {
-    let (server_config, _) = configure_server(
+    let server_config = configure_server(
         server_name,
         secure_conn,
-        certificate_file,
-        private_key_file,
+        certificates,
+        ca_file,
         alpns,
+        webtransport,
+        multi_stream,
+        congestion_control,
     )?;
     let endpoint = Endpoint::server(server_config, server_addr)?;
 
@@ -369,16 +618,18 @@ pub fn client_endpoint(
     client_addr: SocketAddr,
     secure_conn: bool,
     alpns: Vec<String>,
-    certificate_file: Option<PathBuf>,
-    private_key_file: Option<PathBuf>,
+    certificates: Option<Arc<CertificateResolver>>,
+    ca_file: Option<PathBuf>,
     keep_alive_interval_ms: u64,
+    congestion_control: &CongestionControl,
 ) -> Result<Endpoint, Box<dyn Error>> {
     let client_cfg = configure_client(
         secure_conn,
-        certificate_file,
-        private_key_file,
+        certificates,
+        ca_file,
         alpns,
         keep_alive_interval_ms,
+        congestion_control,
     )?;
     let mut endpoint = Endpoint::client(client_addr)?;
 
@@ -0,0 +1,250 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! WebTransport sessions over HTTP/3 on top of the QUIC connections.
+//!
+//! With WebTransport, the QUIC connection carries an HTTP/3 extended CONNECT
+//! request establishing a session. The data is then sent on a unidirectional
+//! WebTransport stream or in WebTransport datagrams of the session, which
+//! browsers can read and write with the WebTransport API.
+
+use bytes::{Bytes, BytesMut};
+use futures::future::{self, Either};
+use futures::FutureExt;
+use quinn::{Connection, SendDatagramError};
+use std::error::Error;
+use url::Url;
+
+/// ALPN of HTTP/3, which WebTransport requires.
+pub const ALPN: &str = "h3";
+
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+/// A QUIC connection, optionally carrying a WebTransport session.
+pub struct Transport {
+    connection: Connection,
+    session: Option<web_transport_quinn::Session>,
+}
+
+impl Transport {
+    pub fn quic(connection: Connection) -> Self {
+        Transport {
+            connection,
+            session: None,
+        }
+    }
+
+    /// Sends the CONNECT request for `path` and waits for the server to accept it.
+    pub async fn connect(
+        connection: Connection,
+        server_name: &str,
+        port: u16,
+        path: &str,
+    ) -> Result<Self, BoxError> {
+        let url = Url::parse(&format!("https://{server_name}:{port}"))?.join(path)?;
+        let session = web_transport_quinn::Session::connect(connection.clone(), &url).await?;
+
+        Ok(Transport {
+            connection,
+            session: Some(session),
+        })
+    }
+
+    /// Waits for the CONNECT request of the client and accepts it, whatever
+    /// its path. Returns the requested URL along with the transport.
+    pub async fn accept(connection: Connection) -> Result<(Self, Url), BoxError> {
+        let request = web_transport_quinn::accept(connection.clone()).await?;
+        let url = request.url().clone();
+        let session = request.ok().await?;
+
+        Ok((
+            Transport {
+                connection,
+                session: Some(session),
+            },
+            url,
+        ))
+    }
+
+    /// The underlying QUIC connection.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn close(&self, code: u32, reason: &[u8]) {
+        match self.session {
+            Some(ref session) => session.close(code, reason),
+            None => self.connection.close(code.into(), reason),
+        }
+    }
+
+    pub async fn open_uni(&self) -> Result<SendStream, BoxError> {
+        match self.session {
+            Some(ref session) => Ok(SendStream::WebTransport(session.open_uni().await?)),
+            None => Ok(SendStream::Quic(self.connection.open_uni().await?)),
+        }
+    }
+
+    /// Accepts the first stream opened by the peer. With WebTransport, this
+    /// can be a bidirectional stream, as commonly used by browsers, of which
+    /// only the receiving half is used.
+    pub async fn accept_uni(&self) -> Result<RecvStream, BoxError> {
+        let Some(ref session) = self.session else {
+            return Ok(RecvStream::Quic(self.connection.accept_uni().await?));
+        };
+
+        let uni = Box::pin(session.accept_uni());
+        let bi = Box::pin(session.accept_bi());
+
+        match future::select(uni, bi).await {
+            Either::Left((res, _)) => Ok(RecvStream::WebTransport(res?)),
+            Either::Right((res, _)) => {
+                let (_send, recv) = res?;
+                Ok(RecvStream::WebTransport(recv))
+            }
+        }
+    }
+
+    /// Maximum size of a datagram, `None` if the peer doesn't support datagrams.
+    pub fn max_datagram_size(&self) -> Option<usize> {
+        match self.session {
+            Some(ref session) => Some(session.max_datagram_size()),
+            None => self.connection.max_datagram_size(),
+        }
+    }
+
+    /// Sends a datagram. Returns `Ok(false)` if it was too large to be sent.
+    pub fn send_datagram(&self, data: Bytes) -> Result<bool, BoxError> {
+        match self.session {
+            Some(ref session) => session.send_datagram(data)?,
+            None => match self.connection.send_datagram(data) {
+                Ok(()) => (),
+                Err(SendDatagramError::TooLarge) => return Ok(false),
+                Err(err) => return Err(err.into()),
+            },
+        }
+
+        Ok(true)
+    }
+
+    pub async fn read_datagram(&self) -> Result<Bytes, BoxError> {
+        match self.session {
+            Some(ref session) => Ok(session.read_datagram().await?),
+            None => Ok(self.connection.read_datagram().await?),
+        }
+    }
+}
+
+pub enum SendStream {
+    Quic(quinn::SendStream),
+    WebTransport(web_transport_quinn::SendStream),
+}
+
+impl SendStream {
+    pub async fn write_all(&mut self, buf: &[u8]) -> Result<(), BoxError> {
+        match self {
+            SendStream::Quic(stream) => stream.write_all(buf).await?,
+            SendStream::WebTransport(stream) => stream.write_all(buf).await?,
+        }
+
+        Ok(())
+    }
+
+    /// Finishes the stream and waits for the peer to have received all data.
+    pub async fn finish(&mut self) -> Result<(), BoxError> {
+        // finish() may fail, but the error is harmless.
+        match self {
+            SendStream::Quic(stream) => {
+                let _ = stream.finish();
+                stream.stopped().await?;
+            }
+            SendStream::WebTransport(stream) => {
+                let _ = stream.finish();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finishes the stream without waiting for the peer.
+    pub fn finish_nowait(&mut self) {
+        // finish() may fail, but the error is harmless.
+        match self {
+            SendStream::Quic(stream) => {
+                let _ = stream.finish();
+            }
+            SendStream::WebTransport(stream) => {
+                let _ = stream.finish();
+            }
+        }
+    }
+
+    /// Abandons the stream, data not yet received by the peer is discarded.
+    pub fn reset(&mut self, code: u32) {
+        // reset() fails if the stream is already closed, which is harmless.
+        match self {
+            SendStream::Quic(stream) => {
+                let _ = stream.reset(code.into());
+            }
+            SendStream::WebTransport(stream) => {
+                let _ = stream.reset(code);
+            }
+        }
+    }
+
+    /// Sets the priority of the stream, higher priority streams are sent first.
+    pub fn set_priority(&self, priority: i32) {
+        match self {
+            SendStream::Quic(stream) => {
+                let _ = stream.set_priority(priority);
+            }
+            SendStream::WebTransport(stream) => {
+                let _ = stream.set_priority(priority);
+            }
+        }
+    }
+
+    /// Whether the peer received all data of the finished stream, or stopped it.
+    ///
+    /// This is only known for plain QUIC streams.
+    pub fn is_done(&mut self) -> bool {
+        match self {
+            SendStream::Quic(stream) => stream.stopped().now_or_never().is_some(),
+            SendStream::WebTransport(_) => false,
+        }
+    }
+}
+
+pub enum RecvStream {
+    Quic(quinn::RecvStream),
+    WebTransport(web_transport_quinn::RecvStream),
+}
+
+impl RecvStream {
+    /// Reads up to `max_length` bytes, `None` once the stream is finished.
+    pub async fn read_chunk(&mut self, max_length: usize) -> Result<Option<Bytes>, BoxError> {
+        let chunk = match self {
+            RecvStream::Quic(stream) => stream.read_chunk(max_length, true).await?,
+            RecvStream::WebTransport(stream) => stream.read_chunk(max_length, true).await?,
+        };
+
+        Ok(chunk.map(|chunk| chunk.bytes))
+    }
+
+    /// Reads the whole stream, failing if it's larger than `size_limit` bytes.
+    pub async fn read_to_end(&mut self, size_limit: usize) -> Result<Bytes, BoxError> {
+        let mut data = BytesMut::new();
+
+        while let Some(chunk) = self.read_chunk(usize::MAX).await? {
+            if data.len() + chunk.len() > size_limit {
+                return Err(format!("Stream larger than {size_limit} bytes").into());
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        Ok(data.freeze())
+    }
+}
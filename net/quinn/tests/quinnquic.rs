@@ -68,6 +68,49 @@ fn test_send_receive_without_datagram() {
     drop(h2);
 }
 
+#[test]
+#[serial]
+fn test_send_receive_congestion_control() {
+    init();
+
+    let content = "Hello, world!\n".as_bytes();
+
+    thread::spawn(move || {
+        let mut h1 = gst_check::Harness::new_empty();
+        h1.add_parse(
+            "quinnquicsink secure-connection=false congestion-control=bbr initial-window=100000 initial-rtt=50",
+        );
+
+        h1.set_src_caps(gst::Caps::builder("text/plain").build());
+
+        h1.play();
+
+        assert!(h1.push(make_buffer(content)) == Ok(gst::FlowSuccess::Ok));
+
+        h1.push_event(gst::event::Eos::new());
+
+        h1.element().unwrap().set_state(gst::State::Null).unwrap();
+
+        drop(h1);
+    });
+
+    let mut h2 = gst_check::Harness::new_empty();
+    h2.add_parse("quinnquicsrc secure-connection=false congestion-control=new-reno");
+
+    h2.play();
+
+    let buf = h2.pull_until_eos().unwrap().unwrap();
+
+    assert_eq!(
+        content,
+        buf.into_mapped_buffer_readable().unwrap().as_slice()
+    );
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+
+    drop(h2);
+}
+
 #[test]
 #[serial]
 fn test_send_receive_with_datagram() {
@@ -112,3 +155,295 @@ fn test_send_receive_with_datagram() {
 
     drop(h2);
 }
+
+#[test]
+#[serial]
+fn test_datagram_max_size() {
+    init();
+
+    let content = "Hello, world!\n".as_bytes();
+
+    thread::spawn(move || {
+        let mut h1 = gst_check::Harness::new_empty();
+        h1.add_parse(
+            "quinnquicsrc use-datagram=true address=127.0.0.1 port=6002 secure-connection=false",
+        );
+
+        h1.play();
+
+        // The oversized datagram never makes it to the source
+        let buf = h1.pull_until_eos().unwrap().unwrap();
+
+        assert_eq!(
+            content,
+            buf.into_mapped_buffer_readable().unwrap().as_slice()
+        );
+
+        h1.element().unwrap().set_state(gst::State::Null).unwrap();
+
+        drop(h1);
+    });
+
+    let mut h2 = gst_check::Harness::new_empty();
+    h2.add_parse("quinnquicsink use-datagram=true max-datagram-size=32 bind-address=127.0.0.1 bind-port=6003 address=127.0.0.1 port=6002 secure-connection=false");
+
+    h2.set_src_caps(gst::Caps::builder("text/plain").build());
+
+    h2.play();
+
+    assert!(h2.push(make_buffer(&[0; 64])) == Ok(gst::FlowSuccess::Ok));
+    assert!(h2.push(make_buffer(content)) == Ok(gst::FlowSuccess::Ok));
+
+    let stats = h2.element().unwrap().property::<gst::Structure>("stats");
+    assert_eq!(stats.get::<u64>("datagrams-sent").unwrap(), 1);
+    assert_eq!(stats.get::<u64>("datagrams-dropped").unwrap(), 1);
+
+    h2.push_event(gst::event::Eos::new());
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+
+    drop(h2);
+}
+
+#[test]
+#[serial]
+fn test_send_receive_webtransport() {
+    init();
+
+    let content = "Hello, world!\n".as_bytes();
+
+    thread::spawn(move || {
+        let mut h1 = gst_check::Harness::new_empty();
+        h1.add_parse("quinnquicsink webtransport=true webtransport-path=/live bind-address=127.0.0.1 bind-port=6005 address=127.0.0.1 port=6004 secure-connection=false");
+
+        h1.set_src_caps(gst::Caps::builder("text/plain").build());
+
+        h1.play();
+
+        assert!(h1.push(make_buffer(content)) == Ok(gst::FlowSuccess::Ok));
+
+        h1.push_event(gst::event::Eos::new());
+
+        h1.element().unwrap().set_state(gst::State::Null).unwrap();
+
+        drop(h1);
+    });
+
+    let mut h2 = gst_check::Harness::new_empty();
+    h2.add_parse(
+        "quinnquicsrc webtransport=true address=127.0.0.1 port=6004 secure-connection=false",
+    );
+
+    h2.play();
+
+    let buf = h2.pull_until_eos().unwrap().unwrap();
+
+    assert_eq!(
+        content,
+        buf.into_mapped_buffer_readable().unwrap().as_slice()
+    );
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+
+    drop(h2);
+}
+
+#[test]
+#[serial]
+fn test_send_receive_roq_streams() {
+    init();
+
+    let packets: [&[u8]; 2] = [b"first RTP packet", b"second RTP packet"];
+
+    thread::spawn(move || {
+        let mut h1 = gst_check::Harness::new_empty();
+        h1.add_parse("quinnquicsink roq=true roq-flow-id=7 bind-address=127.0.0.1 bind-port=6007 address=127.0.0.1 port=6006 secure-connection=false");
+
+        h1.set_src_caps(gst::Caps::builder("application/x-rtp").build());
+
+        h1.play();
+
+        for packet in packets {
+            assert!(h1.push(make_buffer(packet)) == Ok(gst::FlowSuccess::Ok));
+        }
+
+        h1.push_event(gst::event::Eos::new());
+
+        h1.element().unwrap().set_state(gst::State::Null).unwrap();
+
+        drop(h1);
+    });
+
+    let mut h2 = gst_check::Harness::new_empty();
+    h2.add_parse(
+        "quinnquicsrc roq=true roq-flow-id=7 address=127.0.0.1 port=6006 secure-connection=false",
+    );
+
+    h2.play();
+
+    // Each packet is received in its own buffer, possibly out of order
+    let mut received = (0..packets.len())
+        .map(|_| {
+            let buf = h2.pull().unwrap();
+            buf.map_readable().unwrap().to_vec()
+        })
+        .collect::<Vec<_>>();
+    received.sort();
+
+    assert_eq!(received, packets.map(|p| p.to_vec()));
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+
+    drop(h2);
+}
+
+#[test]
+#[serial]
+fn test_send_receive_stream_per_buffer() {
+    init();
+
+    let buffers: [&[u8]; 3] = [b"first buffer", b"second buffer", b"third buffer"];
+
+    thread::spawn(move || {
+        let mut h1 = gst_check::Harness::new_empty();
+        h1.add_parse("quinnquicsink stream-mapping=buffer stale-stream-timeout=1000 bind-address=127.0.0.1 bind-port=6009 address=127.0.0.1 port=6008 secure-connection=false");
+
+        h1.set_src_caps(gst::Caps::builder("text/plain").build());
+
+        h1.play();
+
+        for buffer in buffers {
+            assert!(h1.push(make_buffer(buffer)) == Ok(gst::FlowSuccess::Ok));
+        }
+
+        h1.push_event(gst::event::Eos::new());
+
+        h1.element().unwrap().set_state(gst::State::Null).unwrap();
+
+        drop(h1);
+    });
+
+    let mut h2 = gst_check::Harness::new_empty();
+    h2.add_parse(
+        "quinnquicsrc stream-mapping=buffer address=127.0.0.1 port=6008 secure-connection=false",
+    );
+
+    h2.play();
+
+    // Each stream is received as a whole buffer
+    let mut received = (0..buffers.len())
+        .map(|_| h2.pull().unwrap().map_readable().unwrap().to_vec())
+        .collect::<Vec<_>>();
+    received.sort();
+
+    let mut expected = buffers.map(|b| b.to_vec());
+    expected.sort();
+    assert_eq!(received, expected);
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+
+    drop(h2);
+}
+
+/// Writes a certificate signed by the CA and its private key, returning their
+/// paths.
+fn write_certificate(
+    dir: &std::path::Path,
+    name: &str,
+    ca: &rcgen::Certificate,
+    ca_key: &rcgen::KeyPair,
+) -> (String, String) {
+    let key = rcgen::KeyPair::generate().unwrap();
+    let cert = rcgen::CertificateParams::new(vec!["localhost".to_string()])
+        .unwrap()
+        .signed_by(&key, ca, ca_key)
+        .unwrap();
+
+    let cert_path = dir.join(format!("{name}.pem"));
+    let key_path = dir.join(format!("{name}-key.pem"));
+    std::fs::write(&cert_path, cert.pem()).unwrap();
+    std::fs::write(&key_path, key.serialize_pem()).unwrap();
+
+    (
+        cert_path.to_str().unwrap().to_string(),
+        key_path.to_str().unwrap().to_string(),
+    )
+}
+
+#[test]
+#[serial]
+fn test_send_receive_mutual_tls() {
+    init();
+
+    let dir = std::env::temp_dir().join(format!("gst-quinn-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let ca_key = rcgen::KeyPair::generate().unwrap();
+    let mut ca_params = rcgen::CertificateParams::new(vec![]).unwrap();
+    ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+    let ca_path = dir.join("ca.pem");
+    std::fs::write(&ca_path, ca.pem()).unwrap();
+    let ca_path = ca_path.to_str().unwrap().to_string();
+
+    let (server_cert, server_key) = write_certificate(&dir, "server", &ca, &ca_key);
+    let (client_cert, client_key) = write_certificate(&dir, "client", &ca, &ca_key);
+
+    let content = "Hello, world!\n".as_bytes();
+
+    let sink_ca_path = ca_path.clone();
+    thread::spawn(move || {
+        let mut h1 = gst_check::Harness::new_empty();
+        h1.add_parse(&format!(
+            "quinnquicsink bind-address=127.0.0.1 bind-port=6011 address=127.0.0.1 port=6010 \
+            certificate-file={client_cert} private-key-file={client_key} ca-file={sink_ca_path}"
+        ));
+
+        h1.set_src_caps(gst::Caps::builder("text/plain").build());
+
+        h1.play();
+
+        assert!(h1.push(make_buffer(content)) == Ok(gst::FlowSuccess::Ok));
+
+        h1.push_event(gst::event::Eos::new());
+
+        h1.element().unwrap().set_state(gst::State::Null).unwrap();
+
+        drop(h1);
+    });
+
+    let mut h2 = gst_check::Harness::new_empty();
+    h2.add_parse(&format!(
+        "quinnquicsrc address=127.0.0.1 port=6010 certificate-file={server_cert} \
+        private-key-file={server_key} ca-file={ca_path}"
+    ));
+
+    h2.play();
+
+    let buf = h2.pull_until_eos().unwrap().unwrap();
+
+    assert_eq!(
+        content,
+        buf.into_mapped_buffer_readable().unwrap().as_slice()
+    );
+
+    // Rotated certificates are used for the next handshakes
+    let (server_cert, server_key) = write_certificate(&dir, "server", &ca, &ca_key);
+    let src = h2.element().unwrap();
+    assert_eq!(
+        src.property::<Option<String>>("certificate-file"),
+        Some(server_cert)
+    );
+    assert_eq!(
+        src.property::<Option<String>>("private-key-file"),
+        Some(server_key)
+    );
+    assert!(src.emit_by_name::<bool>("reload-certificates", &[]));
+
+    src.set_state(gst::State::Null).unwrap();
+    assert!(!src.emit_by_name::<bool>("reload-certificates", &[]));
+
+    drop(h2);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
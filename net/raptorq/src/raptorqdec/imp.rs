@@ -13,7 +13,7 @@ use gst_rtp::RTPBuffer;
 
 use once_cell::sync::Lazy;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::iter;
 use std::ops::Range;
 use std::sync::Mutex;
@@ -32,11 +32,16 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 
 const DEFAULT_REPAIR_WINDOW_TOLERANCE: u32 = 500;
 const DEFAULT_MEDIA_PACKETS_RESET_THRESHOLD: u32 = 5000;
+const DEFAULT_REORDER_TOLERANCE: u32 = 0;
+
+// Number of completed Source Blocks remembered to detect late repair packets
+const MAX_FINISHED_SOURCE_BLOCKS: usize = 1024;
 
 #[derive(Debug, Clone, Copy)]
 struct Settings {
     repair_window_tolerance: u32,
     media_packets_reset_threshold: u32,
+    reorder_tolerance: u32,
 }
 
 impl Default for Settings {
@@ -44,6 +49,7 @@ impl Default for Settings {
         Self {
             repair_window_tolerance: DEFAULT_REPAIR_WINDOW_TOLERANCE,
             media_packets_reset_threshold: DEFAULT_MEDIA_PACKETS_RESET_THRESHOLD,
+            reorder_tolerance: DEFAULT_REORDER_TOLERANCE,
         }
     }
 }
@@ -53,6 +59,11 @@ struct Stats {
     recv: u64,
     lost: u64,
     recovered: u64,
+    recovered_blocks: u64,
+    unrecoverable_blocks: u64,
+    used_repair_packets: u64,
+    overhead_symbols: u64,
+    late_repair_packets: u64,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -95,10 +106,12 @@ struct State {
     repair_packets: BTreeMap<u64, Vec<RepairPacketItem>>,
     expirations: BTreeMap<u64, Option<gst::ClockTime>>,
     source_block_info: BTreeMap<u64, SourceBlockInfo>,
+    finished_source_blocks: BTreeSet<u64>,
     extended_media_seq: Option<u64>,
     extended_repair_seq: Option<u64>,
     symbol_size: usize,
     media_packets_reset_threshold: usize,
+    reorder_tolerance: u64,
     repair_window: Option<gst::ClockTime>,
     max_arrival_time: Option<gst::ClockTime>,
     stats: Stats,
@@ -109,13 +122,33 @@ impl State {
         if let Some(info) = self.source_block_info.get(&seq) {
             let (seq_lo, seq_hi) = (info.seq_range().start, info.seq_range().end);
 
-            self.media_packets.retain(|&k, _| k >= seq_hi);
+            // Keep media packets preceding this Source Block within the reorder
+            // tolerance, their repair packets might still be on the way.
+            let keep_from = seq_lo.saturating_sub(self.reorder_tolerance);
+            self.media_packets
+                .retain(|&k, _| k >= seq_hi || (k >= keep_from && k < seq_lo));
             self.repair_packets.remove(&seq_lo);
             self.source_block_info.remove(&seq_lo);
             self.expirations.remove(&seq_lo);
+
+            self.finished_source_blocks.insert(seq_lo);
+            while self.finished_source_blocks.len() > MAX_FINISHED_SOURCE_BLOCKS {
+                self.finished_source_blocks.pop_first();
+            }
         }
     }
 
+    fn missing_packets_num(&self, seq: u64) -> usize {
+        self.source_block_info.get(&seq).map_or(0, |info| {
+            info.packets_num()
+                - self
+                    .media_packets
+                    .range(info.seq_range())
+                    .count()
+                    .min(info.packets_num())
+        })
+    }
+
     fn expire_packets(&mut self) -> Vec<u64> {
         let expired = self
             .expirations
@@ -130,6 +163,9 @@ impl State {
             .collect::<Vec<_>>();
 
         for seq in &expired {
+            if self.missing_packets_num(*seq) > 0 {
+                self.stats.unrecoverable_blocks += 1;
+            }
             self.drop_source_block(*seq);
         }
 
@@ -244,6 +280,7 @@ impl RaptorqDec {
             let config = ObjectTransmissionInformation::new(0, symbolsz as u16, 1, 1, 8);
             let mut decoder = SourceBlockDecoder::new2(0, &config, blocksz);
             let mut result = None;
+            let mut symbols_used = 0;
 
             for (esi, symbol) in
                 Iterator::zip(esi.iter(), source_block.chunks_exact(state.symbol_size))
@@ -251,6 +288,7 @@ impl RaptorqDec {
                 let payload_id = PayloadId::new(0, *esi as u32);
                 let encoding_packet = EncodingPacket::new(payload_id, symbol.to_vec());
 
+                symbols_used += 1;
                 result = decoder.decode(iter::once(encoding_packet));
                 if result.is_some() {
                     break;
@@ -291,8 +329,17 @@ impl RaptorqDec {
                     })
                     .collect::<Vec<_>>();
 
+                // Media symbols are fed to the decoder first, the remaining
+                // ones come from repair packets.
+                let repair_symbols_used =
+                    symbols_used.saturating_sub(data_packets_num as u64 * info.symbols_per_packet);
+
                 state.drop_source_block(seq_lo);
                 state.stats.lost += missing_indices.len() as u64;
+                state.stats.recovered_blocks += 1;
+                state.stats.used_repair_packets +=
+                    (repair_symbols_used + info.symbols_per_packet - 1) / info.symbols_per_packet;
+                state.stats.overhead_symbols += symbols_used.saturating_sub(info.symbols_per_block);
 
                 for packet in recovered_packets {
                     {
@@ -459,6 +506,20 @@ impl RaptorqDec {
 
         state.extended_repair_seq = Some(this_seq);
 
+        // Repair packets can arrive after their Source Block was already
+        // recovered, fully received or expired, don't start a new one for those.
+        if state.finished_source_blocks.contains(&this_seq) {
+            gst::trace!(
+                CAT,
+                imp: self,
+                "Dropping late repair packet for Source Block ({})",
+                i
+            );
+
+            state.stats.late_repair_packets += 1;
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
         let expire_at = state.max_arrival_time.opt_add(state.repair_window);
         let scheduled = state.expirations.entry(this_seq).or_insert(expire_at);
 
@@ -552,6 +613,7 @@ impl RaptorqDec {
         let repair_window = Some(repair_window + tolerance);
 
         let media_packets_reset_threshold = settings.media_packets_reset_threshold as usize;
+        let reorder_tolerance = settings.reorder_tolerance as u64;
 
         gst::debug!(CAT, imp: self, "Configured for caps {}", incaps);
 
@@ -560,6 +622,7 @@ impl RaptorqDec {
         state.symbol_size = symbol_size;
         state.repair_window = repair_window;
         state.media_packets_reset_threshold = media_packets_reset_threshold;
+        state.reorder_tolerance = reorder_tolerance;
 
         Ok(())
     }
@@ -575,6 +638,7 @@ impl RaptorqDec {
         state.repair_packets.clear();
         state.source_block_info.clear();
         state.expirations.clear();
+        state.finished_source_blocks.clear();
         state.extended_media_seq = None;
         state.extended_repair_seq = None;
         state.max_arrival_time = gst::ClockTime::NONE;
@@ -653,6 +717,15 @@ impl ObjectImpl for RaptorqDec {
                     .default_value(DEFAULT_MEDIA_PACKETS_RESET_THRESHOLD)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecUInt::builder("reorder-tolerance")
+                    .nick("Reorder Tolerance")
+                    .blurb("Number of media packets preceding a processed Source Block that are kept \
+                     around, so that repair packets arriving late for an earlier Source Block can \
+                     still be used for recovery (0 - disable)")
+                    .maximum(u16::MAX as u32 / 2)
+                    .default_value(DEFAULT_REORDER_TOLERANCE)
+                    .mutable_ready()
+                    .build(),
                 glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
                     .nick("Statistics")
                     .blurb("Various statistics")
@@ -677,6 +750,11 @@ impl ObjectImpl for RaptorqDec {
                 let val = value.get().expect("type checked upstream");
                 settings.media_packets_reset_threshold = val;
             }
+            "reorder-tolerance" => {
+                let mut settings = self.settings.lock().unwrap();
+                let val = value.get().expect("type checked upstream");
+                settings.reorder_tolerance = val;
+            }
             _ => unimplemented!(),
         }
     }
@@ -691,6 +769,10 @@ impl ObjectImpl for RaptorqDec {
                 let settings = self.settings.lock().unwrap();
                 settings.media_packets_reset_threshold.to_value()
             }
+            "reorder-tolerance" => {
+                let settings = self.settings.lock().unwrap();
+                settings.reorder_tolerance.to_value()
+            }
             "stats" => {
                 let state = self.state.lock().unwrap();
                 let stats = state.stats;
@@ -709,6 +791,11 @@ impl ObjectImpl for RaptorqDec {
                     .field("recovered-packets", stats.recovered)
                     .field("buffered-media-packets", media_packets)
                     .field("buffered-repair-packets", repair_packets)
+                    .field("recovered-blocks", stats.recovered_blocks)
+                    .field("unrecoverable-blocks", stats.unrecoverable_blocks)
+                    .field("used-repair-packets", stats.used_repair_packets)
+                    .field("overhead-symbols", stats.overhead_symbols)
+                    .field("late-repair-packets", stats.late_repair_packets)
                     .build();
 
                 s.to_value()
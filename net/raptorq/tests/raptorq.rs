@@ -619,3 +619,122 @@ fn test_raptorq_repair_window_tolerance() {
         0
     );
 }
+
+#[test]
+fn test_raptorq_decoder_stats() {
+    init();
+
+    let enc = gst::ElementFactory::make("raptorqenc")
+        .property("repair-window", 1000u32)
+        .property("protected-packets", 5u32)
+        .property("repair-packets", 5u32)
+        .build()
+        .unwrap();
+
+    let mut h_enc = gst_check::Harness::with_element(&enc, Some("sink"), Some("src"));
+    let mut h_enc_fec = gst_check::Harness::with_element(&enc, None, Some("fec_0"));
+
+    h_enc.set_src_caps_str("application/x-rtp,clock-rate=8000");
+
+    for i in 0u16..5 {
+        let mut buf = gst::Buffer::new_rtp_with_sizes(42, 0, 0).unwrap();
+
+        let buf_mut = buf.get_mut().unwrap();
+        buf_mut.set_pts(gst::ClockTime::ZERO);
+        buf_mut.set_dts(gst::ClockTime::ZERO);
+
+        let mut rtpbuf = RTPBuffer::from_buffer_writable(buf_mut).unwrap();
+        rtpbuf.set_seq(i);
+
+        drop(rtpbuf);
+
+        let result = h_enc.push(buf);
+        assert!(result.is_ok());
+    }
+
+    let media_packets = (0..5)
+        .map(|_| {
+            let result = h_enc.pull();
+            assert!(result.is_ok());
+            result.unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let repair_packets = (1..=5u64)
+        .map(|i| {
+            h_enc_fec.set_time(200.mseconds() * i).unwrap();
+            h_enc_fec.crank_single_clock_wait().unwrap();
+
+            let result = h_enc_fec.pull();
+            assert!(result.is_ok());
+            result.unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let caps = loop {
+        let event = h_enc_fec.pull_event();
+
+        if let Ok(event) = event {
+            #[allow(clippy::single_match)]
+            match event.view() {
+                gst::EventView::Caps(c) => {
+                    break c.caps_owned();
+                }
+                _ => (),
+            }
+        }
+    };
+
+    let dec = gst::ElementFactory::make("raptorqdec").build().unwrap();
+
+    let mut h_dec = gst_check::Harness::with_element(&dec, Some("sink"), Some("src"));
+    let mut h_dec_fec = gst_check::Harness::with_element(&dec, Some("fec_0"), None);
+
+    h_dec.set_src_caps_str("application/x-rtp");
+    h_dec_fec.set_src_caps(caps);
+
+    // Lose the second media packet
+    for (i, buf) in media_packets.iter().enumerate() {
+        if i != 1 {
+            assert!(h_dec.push(buf.clone()).is_ok());
+        }
+    }
+
+    for buf in &repair_packets[..3] {
+        assert!(h_dec_fec.push(buf.clone()).is_ok());
+    }
+
+    // Push a media packet to run the recovery
+    assert!(h_dec.push(media_packets[4].clone()).is_ok());
+
+    let stats = h_dec.element().unwrap().property::<gst::Structure>("stats");
+    assert_eq!(stats.get::<u64>("recovered-blocks").expect("type error"), 1);
+    assert_eq!(
+        stats.get::<u64>("recovered-packets").expect("type error"),
+        1
+    );
+    assert_eq!(
+        stats
+            .get::<u64>("unrecoverable-blocks")
+            .expect("type error"),
+        0
+    );
+    assert!(stats.get::<u64>("used-repair-packets").expect("type error") >= 1);
+
+    // Remaining repair packets arrive after the Source Block was recovered
+    for buf in &repair_packets[3..] {
+        assert!(h_dec_fec.push(buf.clone()).is_ok());
+    }
+
+    let stats = h_dec.element().unwrap().property::<gst::Structure>("stats");
+    assert_eq!(
+        stats.get::<u64>("late-repair-packets").expect("type error"),
+        2
+    );
+    assert_eq!(
+        stats
+            .get::<u64>("buffered-repair-packets")
+            .expect("type error"),
+        0
+    );
+}
@@ -8,7 +8,7 @@
 //
 // SPDX-License-Identifier: MIT OR Apache-2.0
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use futures::future;
 use futures::prelude::*;
@@ -37,6 +37,11 @@ const DEFAULT_TIMEOUT: u32 = 15;
 const DEFAULT_COMPRESS: bool = false;
 const DEFAULT_IRADIO_MODE: bool = true;
 const DEFAULT_KEEP_ALIVE: bool = true;
+const DEFAULT_MAX_BITRATE: u64 = 0;
+const DEFAULT_STATS_INTERVAL: u32 = 0;
+
+// Period over which the current throughput is measured
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone)]
 struct Settings {
@@ -59,6 +64,8 @@ struct Settings {
     // Nullable fields that behave normally:
     proxy_id: Option<String>,
     proxy_pw: Option<String>,
+    max_bitrate: u64,
+    stats_interval: u32,
 }
 
 impl Default for Settings {
@@ -80,6 +87,8 @@ impl Default for Settings {
             },
             proxy_id: None,
             proxy_pw: None,
+            max_bitrate: DEFAULT_MAX_BITRATE,
+            stats_interval: DEFAULT_STATS_INTERVAL,
         }
     }
 }
@@ -138,6 +147,47 @@ enum State {
     },
 }
 
+#[derive(Debug, Default)]
+struct Stats {
+    bytes_received: u64,
+    request_count: u64,
+    cache_hits: u64,
+    bitrate: u64,
+    // Start and size of the current throughput measurement window
+    window_start: Option<Instant>,
+    window_bytes: u64,
+    // Start and size of the data received for the current response, used for throttling
+    response_start: Option<Instant>,
+    response_bytes: u64,
+    last_posted: Option<Instant>,
+}
+
+impl Stats {
+    fn to_structure(&self) -> gst::Structure {
+        gst::Structure::builder("application/x-reqwesthttpsrc-stats")
+            .field("bytes-received", self.bytes_received)
+            .field("bitrate", self.bitrate)
+            .field("request-count", self.request_count)
+            .field("cache-hits", self.cache_hits)
+            .build()
+    }
+}
+
+// Whether the response was served by a cache on the way instead of the origin server
+fn is_cache_hit(headers: &reqwest::header::HeaderMap) -> bool {
+    // https://www.rfc-editor.org/rfc/rfc9111#section-5.1
+    if headers.contains_key(reqwest::header::AGE) {
+        return true;
+    }
+
+    // Non-standard but widely used by CDNs and caching proxies
+    ["x-cache", "x-cache-status", "cf-cache-status"]
+        .iter()
+        .filter_map(|name| headers.get(*name))
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.trim_start().to_ascii_uppercase().starts_with("HIT"))
+}
+
 #[derive(Default)]
 enum Canceller {
     #[default]
@@ -163,6 +213,7 @@ pub struct ReqwestHttpSrc {
     settings: Mutex<Settings>,
     state: Mutex<State>,
     canceller: Mutex<Canceller>,
+    stats: Mutex<Stats>,
 }
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
@@ -498,6 +549,16 @@ impl ReqwestHttpSrc {
 
         gst::debug!(CAT, imp: self, "Received response: {:?}", res);
 
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.request_count += 1;
+            stats.response_start = None;
+            stats.response_bytes = 0;
+            if res.status().is_success() && is_cache_hit(res.headers()) {
+                stats.cache_hits += 1;
+            }
+        }
+
         if !res.status().is_success() {
             match res.status() {
                 StatusCode::NOT_FOUND => {
@@ -634,7 +695,18 @@ impl ReqwestHttpSrc {
         T: Send + 'static,
     {
         let timeout = self.settings.lock().unwrap().timeout;
+        self.wait_with_timeout(future, timeout)
+    }
 
+    fn wait_with_timeout<F, T>(
+        &self,
+        future: F,
+        timeout: u32,
+    ) -> Result<T, Option<gst::ErrorMessage>>
+    where
+        F: Send + Future<Output = Result<T, gst::ErrorMessage>>,
+        T: Send + 'static,
+    {
         let mut canceller = self.canceller.lock().unwrap();
         if matches!(*canceller, Canceller::Cancelled) {
             return Err(None);
@@ -682,6 +754,85 @@ impl ReqwestHttpSrc {
 
         res
     }
+
+    fn stats(&self) -> gst::Structure {
+        self.stats.lock().unwrap().to_structure()
+    }
+
+    /// Updates the statistics for newly received data, posts them if the interval elapsed and
+    /// waits as long as needed to stay below the configured maximum bitrate.
+    fn handle_received(&self, size: usize) -> Result<(), gst::FlowError> {
+        let (max_bitrate, stats_interval) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.max_bitrate, settings.stats_interval)
+        };
+
+        let now = Instant::now();
+        let (delay, stats) = {
+            let mut stats = self.stats.lock().unwrap();
+            stats.bytes_received += size as u64;
+
+            let window_start = *stats.window_start.get_or_insert(now);
+            stats.window_bytes += size as u64;
+            let elapsed = now - window_start;
+            if elapsed >= THROUGHPUT_WINDOW {
+                stats.bitrate = (stats.window_bytes as f64 * 8.0 / elapsed.as_secs_f64()) as u64;
+                stats.window_start = Some(now);
+                stats.window_bytes = 0;
+            }
+
+            let response_start = *stats.response_start.get_or_insert(now);
+            stats.response_bytes += size as u64;
+            let delay = if max_bitrate > 0 {
+                let expected =
+                    Duration::from_secs_f64(stats.response_bytes as f64 * 8.0 / max_bitrate as f64);
+                expected.checked_sub(now - response_start)
+            } else {
+                None
+            };
+
+            let post = stats_interval > 0
+                && stats.last_posted.map_or(true, |last| {
+                    now - last >= Duration::from_millis(stats_interval.into())
+                });
+            let s = if post {
+                stats.last_posted = Some(now);
+                Some(stats.to_structure())
+            } else {
+                None
+            };
+
+            (delay, s)
+        };
+
+        if let Some(s) = stats {
+            let _ = self
+                .obj()
+                .post_message(gst::message::Element::builder(s).src(&*self.obj()).build());
+        }
+
+        if let Some(delay) = delay {
+            gst::trace!(CAT, imp: self, "Throttling for {:?}", delay);
+            let future = async {
+                tokio::time::sleep(delay).await;
+                Ok(())
+            };
+            // No timeout, the delay can be long with a low maximum bitrate
+            match self.wait_with_timeout(future, 0) {
+                Ok(()) => (),
+                Err(Some(err)) => {
+                    self.post_error_message(err);
+                    return Err(gst::FlowError::Error);
+                }
+                Err(None) => {
+                    gst::debug!(CAT, imp: self, "Flushing");
+                    return Err(gst::FlowError::Flushing);
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl ObjectImpl for ReqwestHttpSrc {
@@ -790,6 +941,25 @@ impl ObjectImpl for ReqwestHttpSrc {
                     .readwrite()
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecUInt64::builder("max-bitrate")
+                    .nick("Maximum Bitrate")
+                    .blurb("Maximum download rate in bits per second (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_BITRATE)
+                    .readwrite()
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("stats-interval")
+                    .nick("Statistics Interval")
+                    .blurb("Interval in milliseconds for posting the statistics as element messages (0 = disabled)")
+                    .default_value(DEFAULT_STATS_INTERVAL)
+                    .readwrite()
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Transfer statistics")
+                    .read_only()
+                    .build(),
             ]
         });
 
@@ -891,6 +1061,16 @@ impl ObjectImpl for ReqwestHttpSrc {
                     &mut settings.proxy_pw
                 })
             }
+            "max-bitrate" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_bitrate = value.get().expect("type checked upstream");
+                Ok(())
+            }
+            "stats-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.stats_interval = value.get().expect("type checked upstream");
+                Ok(())
+            }
             _ => unimplemented!(),
         };
 
@@ -961,6 +1141,9 @@ impl ObjectImpl for ReqwestHttpSrc {
                 .to_value(),
             "proxy-id" => self.settings.lock().unwrap().proxy_id.to_value(),
             "proxy-pw" => self.settings.lock().unwrap().proxy_pw.to_value(),
+            "max-bitrate" => self.settings.lock().unwrap().max_bitrate.to_value(),
+            "stats-interval" => self.settings.lock().unwrap().stats_interval.to_value(),
+            "stats" => self.stats().to_value(),
             _ => unimplemented!(),
         }
     }
@@ -1079,6 +1262,8 @@ impl BaseSrcImpl for ReqwestHttpSrc {
 
         gst::debug!(CAT, imp: self, "Starting for URI {}", uri);
 
+        *self.stats.lock().unwrap() = Stats::default();
+
         *state = self.do_request(uri, 0, None).map_err(|err| {
             err.unwrap_or_else(|| {
                 gst::error_msg!(gst::LibraryError::Failed, ["Interrupted during start"])
@@ -1270,6 +1455,9 @@ impl PushSrcImpl for ReqwestHttpSrc {
                     buffer.set_offset_end(offset + size as u64);
                 }
 
+                drop(state);
+                self.handle_received(size)?;
+
                 Ok(CreateSuccess::NewBuffer(buffer))
             }
             None => {
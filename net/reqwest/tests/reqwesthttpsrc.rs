@@ -1304,6 +1304,67 @@ fn test_proxy() {
     let _ = futures::executor::block_on(proxy_handle);
 }
 
+#[test]
+fn test_max_bitrate_and_stats() {
+    init();
+
+    // 4000 bytes at 16000 bits per second need 2 seconds
+    let body = vec![0u8; 4000];
+    let mut h = Harness::new(
+        move |_req| {
+            hyper::Response::builder()
+                .header("Age", "10")
+                .body(full_body(body.clone()))
+                .unwrap()
+        },
+        |src| {
+            src.set_property("max-bitrate", 16_000u64);
+            src.set_property("stats-interval", 100u32);
+        },
+    );
+
+    let start = std::time::Instant::now();
+    h.run(|src| {
+        src.set_state(gst::State::Playing).unwrap();
+    });
+
+    let mut num_bytes = 0;
+    let mut stats_posted = false;
+    loop {
+        match h.receiver.as_mut().unwrap().recv().unwrap() {
+            Message::ServerError(err) => {
+                panic!("Got server error: {err}");
+            }
+            Message::Event(ev) => match ev.view() {
+                gst::EventView::Eos(_) => break,
+                _ => (),
+            },
+            Message::Message(msg) => match msg.view() {
+                gst::MessageView::Error(err) => {
+                    panic!("Got error: {} ({:?})", err.error(), err.debug());
+                }
+                gst::MessageView::Element(msg) => {
+                    let s = msg.structure().unwrap();
+                    if s.name() == "application/x-reqwesthttpsrc-stats" {
+                        stats_posted = true;
+                    }
+                }
+                _ => (),
+            },
+            Message::Buffer(buffer) => num_bytes += buffer.size(),
+        }
+    }
+
+    assert_eq!(num_bytes, 4000);
+    assert!(start.elapsed() >= std::time::Duration::from_millis(1500));
+    assert!(stats_posted);
+
+    let stats = h.src.property::<gst::Structure>("stats");
+    assert_eq!(stats.get::<u64>("bytes-received").unwrap(), 4000);
+    assert_eq!(stats.get::<u64>("request-count").unwrap(), 1);
+    assert_eq!(stats.get::<u64>("cache-hits").unwrap(), 1);
+}
+
 /// Adapter from tokio IO traits to hyper IO traits.
 mod tokio_io {
     use pin_project_lite::pin_project;
@@ -24,16 +24,31 @@
  * ]| This will depayload an incoming RTP Opus audio stream. You can use the #opusenc and
  * #rtpopuspay2 elements to create such an RTP stream.
  *
+ * Packet loss notifications from an upstream #rtpjitterbuffer (with `do-lost=true`) and gaps
+ * caused by discontinuous transmission (DTX) on the sender side are signalled downstream as GAP
+ * events. This allows #opusdec to perform packet loss concealment or, if `use-inband-fec=true`
+ * is set on it, recover lost packets from the in-band FEC data of the following packet.
+ *
  * Since: plugins-rs-0.13.0
  */
-use gst::{glib, subclass::prelude::*};
+use atomic_refcell::AtomicRefCell;
+
+use gst::{glib, prelude::*, subclass::prelude::*};
 
 use once_cell::sync::Lazy;
 
-use crate::basedepay::{RtpBaseDepay2Ext, RtpBaseDepay2Impl};
+use crate::basedepay::{RtpBaseDepay2Ext, RtpBaseDepay2Impl, RtpBaseDepay2ImplExt};
 
 #[derive(Default)]
-pub struct RtpOpusDepay {}
+struct State {
+    // Expected RTP timestamp of the next packet if there's no gap
+    next_ext_timestamp: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct RtpOpusDepay {
+    state: AtomicRefCell<State>,
+}
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
@@ -138,6 +153,45 @@ impl RtpBaseDepay2Impl for RtpOpusDepay {
         // Parse frames in Opus packet to figure out duration
         let duration = self.parse_opus_packet(packet.payload());
 
+        let mut state = self.state.borrow_mut();
+
+        // With DTX the sender stops sending packets during silence and the RTP timestamp
+        // jumps forward at the start of the next talkspurt. Signal the gap downstream so the
+        // decoder can fill it.
+        if let Some(next_ext_timestamp) = state.next_ext_timestamp {
+            if packet.marker_bit()
+                && !packet.discont()
+                && packet.ext_timestamp() > next_ext_timestamp
+            {
+                let gap_duration = gst::ClockTime::from_nseconds(
+                    (packet.ext_timestamp() - next_ext_timestamp)
+                        .mul_div_floor(*gst::ClockTime::SECOND, 48000)
+                        .unwrap(),
+                );
+
+                if let Some(gap_start) = packet.pts().and_then(|pts| pts.checked_sub(gap_duration))
+                {
+                    gst::debug!(CAT, imp: self, "DTX gap of {gap_duration} at {gap_start}");
+
+                    self.obj().finish_pending_buffers()?;
+                    let _ = self.obj().src_pad().push_event(
+                        gst::event::Gap::builder(gap_start)
+                            .duration(gap_duration)
+                            .build(),
+                    );
+                }
+            }
+        }
+
+        state.next_ext_timestamp = duration.map(|duration| {
+            packet.ext_timestamp()
+                + duration
+                    .nseconds()
+                    .mul_div_floor(48000, *gst::ClockTime::SECOND)
+                    .unwrap()
+        });
+        drop(state);
+
         let mut outbuf = packet.payload_buffer();
         let outbuf_ref = outbuf.get_mut().unwrap();
 
@@ -154,6 +208,54 @@ impl RtpBaseDepay2Impl for RtpOpusDepay {
 
         self.obj().queue_buffer(packet.into(), outbuf)
     }
+
+    fn sink_event(&self, event: gst::Event) -> Result<gst::FlowSuccess, gst::FlowError> {
+        if let gst::EventView::CustomDownstream(ev) = event.view() {
+            if let Some(s) = ev.structure().filter(|s| s.name() == "GstRTPPacketLost") {
+                let timestamp = s.get::<gst::ClockTime>("timestamp").ok();
+                let duration = s.get::<Option<gst::ClockTime>>("duration").ok().flatten();
+
+                // Convert packet loss notifications into GAP events so the decoder can
+                // conceal the loss, but only once data was output for the current stream
+                let mut state = self.state.borrow_mut();
+                if let (Some(timestamp), true) = (timestamp, state.next_ext_timestamp.is_some()) {
+                    // The following packet does not continue the previous one anymore
+                    state.next_ext_timestamp = None;
+                    drop(state);
+
+                    gst::debug!(CAT, imp: self, "Packet lost at {timestamp}, duration {}", duration.display());
+
+                    self.obj().finish_pending_buffers()?;
+                    let gap = gst::event::Gap::builder(timestamp)
+                        .duration(duration)
+                        .seqnum(event.seqnum())
+                        .build();
+
+                    return self.parent_sink_event(gap);
+                }
+
+                return Ok(gst::FlowSuccess::Ok);
+            }
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn flush(&self) {
+        *self.state.borrow_mut() = State::default();
+    }
 }
 
 // Default is mono according to the RFC, but we still default to stereo here since there's
@@ -23,6 +23,12 @@
  * ]|
  * for 5.1 surround sound audio.
  *
+ * The receiver preferences `usedtx`, `useinbandfec` and `maxaveragebitrate` are picked up from
+ * the downstream caps and signalled in the output caps, so they end up in the SDP. If the
+ * receiver asked for discontinuous transmission via `usedtx=1`, empty DTX packets are dropped
+ * even if the #rtpopuspay2:dtx property is not set. The upstream Opus encoder still needs to be
+ * configured accordingly, e.g. via the `dtx`, `inband-fec` and `bitrate` properties of #opusenc.
+ *
  * [rfc-7587]: https://www.rfc-editor.org/rfc/rfc7587.html
  * [libwebrtc-multiopus]: https://webrtc-review.googlesource.com/c/src/+/129768
  *
@@ -47,12 +53,15 @@ use std::sync::atomic::AtomicBool;
 
 struct State {
     marker_pending: bool,
+    // Receiver asked for discontinuous transmission via usedtx=1
+    negotiated_dtx: bool,
 }
 
 impl Default for State {
     fn default() -> Self {
         State {
             marker_pending: true,
+            negotiated_dtx: false,
         }
     }
 }
@@ -271,6 +280,34 @@ impl RtpBasePay2Impl for RtpOpusPay {
             src_caps = src_caps.field("sprop-maxcapturerate", rate.to_string());
         }
 
+        // Relay receiver preferences for DTX, in-band FEC and the maximum average bitrate
+        // https://www.rfc-editor.org/rfc/rfc7587.html#section-6.1
+        let peer_caps = self.obj().src_pad().peer_query_caps(None);
+        let mut negotiated_dtx = false;
+        if let Some(peer_s) = peer_caps.structure(0) {
+            gst::trace!(CAT, imp: self, "Peer preference structure: {peer_s}");
+
+            if let Some(usedtx) = fmtp_param(peer_s, "usedtx") {
+                negotiated_dtx = usedtx == 1;
+                src_caps = src_caps.field("usedtx", usedtx.to_string());
+            }
+
+            if let Some(useinbandfec) = fmtp_param(peer_s, "useinbandfec") {
+                src_caps = src_caps.field("useinbandfec", useinbandfec.to_string());
+            }
+
+            if let Some(maxaveragebitrate) =
+                fmtp_param(peer_s, "maxaveragebitrate").filter(|&v| (6000..=510000).contains(&v))
+            {
+                src_caps = src_caps.field("maxaveragebitrate", maxaveragebitrate.to_string());
+            }
+        }
+
+        if negotiated_dtx {
+            gst::debug!(CAT, imp: self, "Receiver requested discontinuous transmission");
+        }
+        self.state.borrow_mut().negotiated_dtx = negotiated_dtx;
+
         self.obj().set_src_caps(&src_caps.build());
 
         true
@@ -295,7 +332,8 @@ impl RtpBasePay2Impl for RtpOpusPay {
 
         let data = map.as_slice();
 
-        let dtx = self.settings.dtx.load(std::sync::atomic::Ordering::Relaxed);
+        let dtx =
+            self.settings.dtx.load(std::sync::atomic::Ordering::Relaxed) || state.negotiated_dtx;
 
         // Don't output DTX packets if discontinuous transmission was enabled (in encoder and here)
         // (Although seeing that it's opt-in in the encoder already one wonders whether we
@@ -424,3 +462,13 @@ impl RtpBasePay2Impl for RtpOpusPay {
 }
 
 impl RtpOpusPay {}
+
+// Parses a fixed integer fmtp parameter from the given caps structure. These are usually
+// strings in the caps as they come from the SDP, but also accept plain integers.
+fn fmtp_param(s: &gst::StructureRef, name: &str) -> Option<i32> {
+    if let Ok(v) = s.get::<&str>(name) {
+        v.trim().parse::<i32>().ok()
+    } else {
+        s.get::<i32>(name).ok()
+    }
+}
@@ -589,3 +589,160 @@ fn test_opus_depay_pay_multichannel() {
 
     assert!(output_caps.can_intersect(&expected_output_caps));
 }
+
+// test_opus_pay_negotiated_dtx
+//
+// Check that receiver preferences for DTX and in-band FEC are signalled in the output caps, and
+// that DTX packets are dropped if the receiver asked for discontinuous transmission.
+//
+#[test]
+fn test_opus_pay_negotiated_dtx() {
+    const OPUS_BUFFER_SILENCE: &[u8] = &[0xf8, 0xff, 0xfe];
+    const OPUS_BUFFER_SILENCE_DTX: &[u8] = &[0xf8];
+
+    init();
+
+    let mut h = Harness::new("rtpopuspay2");
+
+    h.set_sink_caps_str(
+        "application/x-rtp, media=audio, encoding-name=OPUS, clock-rate=48000, \
+         usedtx=(string)1, useinbandfec=(string)1, maxaveragebitrate=(string)32000",
+    );
+
+    let input_caps = gst::Caps::builder("audio/x-opus")
+        .field("rate", 48000i32)
+        .field("channels", 1i32)
+        .field("channel-mapping-family", 0i32)
+        .build();
+
+    h.set_src_caps(input_caps);
+
+    h.push(make_buffer(
+        OPUS_BUFFER_SILENCE,
+        gst::ClockTime::ZERO,
+        gst::ClockTime::from_mseconds(20),
+        gst::BufferFlags::DISCONT,
+    ))
+    .unwrap();
+    h.push(make_buffer(
+        OPUS_BUFFER_SILENCE_DTX,
+        gst::ClockTime::from_mseconds(20),
+        gst::ClockTime::from_mseconds(20),
+        gst::BufferFlags::empty(),
+    ))
+    .unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let _output_buffer = h.pull().expect("Didn't get output buffer");
+    assert!(h.try_pull().is_none());
+
+    let output_caps = h
+        .sinkpad()
+        .expect("harness sinkpad")
+        .current_caps()
+        .expect("output caps");
+
+    eprintln!("Output caps: {output_caps}");
+
+    let s = output_caps.structure(0).unwrap();
+    assert_eq!(s.get::<&str>("usedtx"), Ok("1"));
+    assert_eq!(s.get::<&str>("useinbandfec"), Ok("1"));
+    assert_eq!(s.get::<&str>("maxaveragebitrate"), Ok("32000"));
+}
+
+// test_opus_depay_gaps
+//
+// Check that DTX gaps and lost packets are signalled downstream as GAP events.
+//
+#[test]
+fn test_opus_depay_gaps() {
+    // 20ms CELT FB frame
+    const OPUS_PAYLOAD: &[u8] = &[0xf8, 0xff, 0xfe];
+
+    init();
+
+    fn make_rtp_buffer(
+        seqnum: u16,
+        rtp_time: u32,
+        marker: bool,
+        pts: gst::ClockTime,
+    ) -> gst::Buffer {
+        let packet = rtp_types::RtpPacketBuilder::new()
+            .payload_type(96)
+            .sequence_number(seqnum)
+            .timestamp(rtp_time)
+            .marker_bit(marker)
+            .payload(OPUS_PAYLOAD);
+        let mut buf = gst::Buffer::from_mut_slice(packet.write_vec().unwrap());
+        buf.get_mut().unwrap().set_pts(pts);
+        buf
+    }
+
+    fn pull_gap(h: &mut Harness) -> (gst::ClockTime, Option<gst::ClockTime>) {
+        loop {
+            let event = h.pull_event().expect("Didn't get GAP event");
+            if let gst::EventView::Gap(gap) = event.view() {
+                return gap.get();
+            }
+        }
+    }
+
+    let mut h = Harness::new("rtpopusdepay2");
+
+    h.set_src_caps_str(
+        "application/x-rtp, media=audio, encoding-name=OPUS, clock-rate=48000, payload=96",
+    );
+
+    h.push(make_rtp_buffer(0, 0, true, gst::ClockTime::ZERO))
+        .unwrap();
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::ZERO));
+
+    // Talkspurt after 80ms of DTX
+    h.push(make_rtp_buffer(
+        1,
+        5 * 960,
+        true,
+        gst::ClockTime::from_mseconds(100),
+    ))
+    .unwrap();
+    assert_eq!(
+        pull_gap(&mut h),
+        (
+            gst::ClockTime::from_mseconds(20),
+            Some(gst::ClockTime::from_mseconds(80))
+        )
+    );
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(100)));
+
+    // Lost packet notification from the jitterbuffer
+    h.push_event(
+        gst::event::CustomDownstream::builder(
+            gst::Structure::builder("GstRTPPacketLost")
+                .field("seqnum", 2u32)
+                .field("timestamp", gst::ClockTime::from_mseconds(120))
+                .field("duration", gst::ClockTime::from_mseconds(20))
+                .field("retry", 0u32)
+                .build(),
+        )
+        .build(),
+    );
+    assert_eq!(
+        pull_gap(&mut h),
+        (
+            gst::ClockTime::from_mseconds(120),
+            Some(gst::ClockTime::from_mseconds(20))
+        )
+    );
+
+    h.push(make_rtp_buffer(
+        3,
+        7 * 960,
+        false,
+        gst::ClockTime::from_mseconds(140),
+    ))
+    .unwrap();
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(140)));
+}
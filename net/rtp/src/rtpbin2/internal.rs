@@ -197,6 +197,13 @@ impl SharedSessionInner {
         self.pt_map.iter().map(|(&k, v)| (k, v))
     }
 
+    /// The RTP header extension id negotiated for the MID header extension, if any
+    pub(crate) fn mid_ext_id(&self) -> Option<u8> {
+        self.pt_map
+            .values()
+            .find_map(|caps| mid_ext_id_from_caps(caps))
+    }
+
     pub fn stats(&self) -> gst::Structure {
         let mut session_stats = gst::Structure::builder("application/x-rtpbin2-session-stats")
             .field("id", self.id as u64);
@@ -369,6 +376,83 @@ pub fn pt_clock_rate_from_caps(caps: &gst::CapsRef) -> Option<(u8, u32)> {
     }
 }
 
+/// URI of the RTP header extension carrying the media identification (RFC 8843)
+pub(crate) const RTP_MID_EXT_URI: &str = "urn:ietf:params:rtp-hdrext:sdes:mid";
+
+pub(crate) fn mid_ext_id_from_caps(caps: &gst::CapsRef) -> Option<u8> {
+    let s = caps.structure(0)?;
+
+    s.iter().find_map(|(k, v)| {
+        let ext_id = k.strip_prefix("extmap-")?.parse::<u8>().ok()?;
+        let uri = if let Ok(uri) = v.get::<String>() {
+            uri
+        } else {
+            v.get::<gst::ArrayRef>()
+                .ok()?
+                .get(1)
+                .and_then(|v| v.get::<String>().ok())?
+        };
+
+        (uri == RTP_MID_EXT_URI).then_some(ext_id)
+    })
+}
+
+/// Look up the value of the RTP header extension with the given id in either the one-byte or
+/// the two-byte header extension format (RFC 8285)
+pub(crate) fn find_rtp_header_extension<'a>(
+    rtp: &rtp_types::RtpPacket<'a>,
+    ext_id: u8,
+) -> Option<&'a [u8]> {
+    let (profile, mut data) = rtp.extension()?;
+
+    let two_bytes = if profile == 0xbede {
+        false
+    } else if profile & 0xfff0 == 0x1000 {
+        true
+    } else {
+        return None;
+    };
+
+    while !data.is_empty() {
+        let id = if two_bytes { data[0] } else { data[0] >> 4 };
+        // padding
+        if id == 0 {
+            data = &data[1..];
+            continue;
+        }
+
+        let (len, hdr_len) = if two_bytes {
+            if data.len() < 2 {
+                return None;
+            }
+            (data[1] as usize, 2)
+        } else {
+            // reserved for future extensions, stop parsing
+            if id == 15 {
+                return None;
+            }
+            ((data[0] & 0x0f) as usize + 1, 1)
+        };
+
+        if data.len() < hdr_len + len {
+            return None;
+        }
+
+        if id == ext_id {
+            return Some(&data[hdr_len..hdr_len + len]);
+        }
+
+        data = &data[hdr_len + len..];
+    }
+
+    None
+}
+
+/// Whether the packet is an RTCP packet that was multiplexed onto the RTP stream (RFC 5761)
+pub(crate) fn is_muxed_rtcp(data: &[u8]) -> bool {
+    data.len() >= 2 && (64..=95).contains(&(data[1] & 0x7f))
+}
+
 static RUST_CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
         "rust-log",
@@ -11,7 +11,10 @@ use futures::StreamExt;
 use gst::{glib, prelude::*, subclass::prelude::*};
 use once_cell::sync::Lazy;
 
-use super::internal::{pt_clock_rate_from_caps, GstRustLogger, SharedRtpState, SharedSession};
+use super::internal::{
+    find_rtp_header_extension, is_muxed_rtcp, pt_clock_rate_from_caps, GstRustLogger,
+    SharedRtpState, SharedSession,
+};
 use super::jitterbuffer::{self, JitterBuffer};
 use super::session::{
     KeyUnitRequestType, RecvReply, RequestRemoteKeyUnitReply, RtcpRecvReply, RtpProfile,
@@ -151,6 +154,7 @@ struct JitterBufferStore {
 struct RtpRecvSrcPad {
     pt: u8,
     ssrc: u32,
+    mid: Option<String>,
     pad: gst::Pad,
     jitter_buffer_store: Arc<Mutex<JitterBufferStore>>,
 }
@@ -167,14 +171,21 @@ impl RtpRecvSrcPad {
     fn activate(&mut self, state: MutexGuard<State>, session_id: usize) {
         let session = state.session_by_id(session_id).unwrap();
         let seqnum = session.rtp_recv_sink_seqnum.unwrap();
-        let stream_id = format!("{}/{}", self.pt, self.ssrc);
+        let stream_id = if let Some(ref mid) = self.mid {
+            format!("{}/{}/{}", mid, self.pt, self.ssrc)
+        } else {
+            format!("{}/{}", self.pt, self.ssrc)
+        };
         let stream_start = gst::event::StreamStart::builder(&stream_id)
             .group_id(session.rtp_recv_sink_group_id.unwrap())
             .seqnum(seqnum)
             .build();
 
         let session_inner = session.internal_session.inner.lock().unwrap();
-        let caps = session_inner.caps_from_pt(self.pt);
+        let mut caps = session_inner.caps_from_pt(self.pt);
+        if let Some(ref mid) = self.mid {
+            caps.make_mut().set("a-mid", mid);
+        }
         let caps = gst::event::Caps::builder(&caps).seqnum(seqnum).build();
         drop(session_inner);
 
@@ -211,6 +222,9 @@ struct RecvSession {
 
     recv_store: Vec<HeldRecvBuffer>,
 
+    // MID of each remote SSRC as signalled via the RTP header extension when bundling
+    ssrc_mids: HashMap<u32, String>,
+
     rtp_recv_srcpads: Vec<RtpRecvSrcPad>,
     recv_flow_combiner: Arc<Mutex<gst_base::UniqueFlowCombiner>>,
 
@@ -232,6 +246,8 @@ impl RecvSession {
 
             recv_store: vec![],
 
+            ssrc_mids: HashMap::new(),
+
             rtp_recv_srcpads: vec![],
             recv_flow_combiner: Arc::new(Mutex::new(gst_base::UniqueFlowCombiner::new())),
 
@@ -377,6 +393,7 @@ impl RecvSession {
             let recv_pad = RtpRecvSrcPad {
                 pt,
                 ssrc,
+                mid: self.ssrc_mids.get(&ssrc).cloned(),
                 pad: srcpad.clone(),
                 jitter_buffer_store: Arc::new(Mutex::new(JitterBufferStore {
                     waker: None,
@@ -428,6 +445,9 @@ impl State {
                 let mut jb_stats = pad.jitter_buffer_store.lock().unwrap().jitterbuffer.stats();
                 jb_stats.set_value("ssrc", (pad.ssrc as i32).to_send_value());
                 jb_stats.set_value("pt", (pad.pt as i32).to_send_value());
+                if let Some(ref mid) = pad.mid {
+                    jb_stats.set_value("mid", mid.to_send_value());
+                }
                 jb_stats
             }));
 
@@ -589,18 +609,17 @@ impl RtpRecv {
             gst::error!(CAT, imp: self, "Failed to map input buffer {e:?}");
             gst::FlowError::Error
         })?;
+        // RTCP packets multiplexed with the RTP stream are distinguished by their packet type
+        // which overlaps with the RTP payload type range 64-95 (RFC 5761)
+        if is_muxed_rtcp(&mapped) {
+            drop(mapped);
+            drop(state);
+            return Self::rtcp_recv_sink_chain(self, id, buffer);
+        }
+
         let rtp = match rtp_types::RtpPacket::parse(&mapped) {
             Ok(rtp) => rtp,
             Err(e) => {
-                // If this is a valid RTCP packet then it was muxed with the RTP stream and can be
-                // handled just fine.
-                if rtcp_types::Compound::parse(&mapped).map_or(false, |mut rtcp| {
-                    rtcp.next().map_or(false, |rtcp| rtcp.is_ok())
-                }) {
-                    drop(mapped);
-                    return Self::rtcp_recv_sink_chain(self, id, buffer);
-                }
-
                 gst::error!(CAT, imp: self, "Failed to parse input as valid rtp packet: {e:?}");
                 return Ok(gst::FlowSuccess::Ok);
             }
@@ -610,6 +629,15 @@ impl RtpRecv {
 
         let mut session_inner = internal_session.inner.lock().unwrap();
 
+        // When bundling, the MID header extension identifies the media section each SSRC
+        // belongs to
+        let mid = session_inner.mid_ext_id().and_then(|ext_id| {
+            find_rtp_header_extension(&rtp, ext_id)
+                .and_then(|mid| std::str::from_utf8(mid).ok())
+                .filter(|mid| !mid.is_empty())
+                .map(String::from)
+        });
+
         if state
             .sync_context
             .as_ref()
@@ -635,6 +663,12 @@ impl RtpRecv {
             arrival_time.nseconds(),
         );
         let session = state.mut_session_by_id(id).unwrap();
+        if let Some(mid) = mid {
+            if session.ssrc_mids.get(&rtp.ssrc()) != Some(&mid) {
+                gst::debug!(CAT, imp: self, "SSRC {:#010x} is using MID {mid}", rtp.ssrc());
+                session.ssrc_mids.insert(rtp.ssrc(), mid);
+            }
+        }
         let segment = session.rtp_recv_sink_segment.as_ref().unwrap();
         let pts = segment
             .position_from_running_time(gst::ClockTime::from_nseconds(pts))
@@ -19,6 +19,7 @@ use crate::rtpbin2::RUNTIME;
 
 const DEFAULT_MIN_RTCP_INTERVAL: Duration = RTCP_MIN_REPORT_INTERVAL;
 const DEFAULT_REDUCED_SIZE_RTCP: bool = false;
+const DEFAULT_RTCP_MUX: bool = false;
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
@@ -63,6 +64,7 @@ struct Settings {
     min_rtcp_interval: Duration,
     profile: Profile,
     reduced_size_rtcp: bool,
+    rtcp_mux: bool,
 }
 
 impl Default for Settings {
@@ -72,6 +74,7 @@ impl Default for Settings {
             min_rtcp_interval: DEFAULT_MIN_RTCP_INTERVAL,
             profile: Profile::default(),
             reduced_size_rtcp: DEFAULT_REDUCED_SIZE_RTCP,
+            rtcp_mux: DEFAULT_RTCP_MUX,
         }
     }
 }
@@ -145,6 +148,9 @@ struct SendSession {
     rtp_send_srcpad: Option<gst::Pad>,
 
     rtcp_send_srcpad: Option<gst::Pad>,
+
+    // Whether RTCP is sent multiplexed on the RTP source pad
+    rtcp_mux: bool,
 }
 
 impl SendSession {
@@ -174,9 +180,14 @@ impl SendSession {
             rtp_send_sinkpad: None,
             rtp_send_srcpad: None,
             rtcp_send_srcpad: None,
+            rtcp_mux: settings.rtcp_mux,
         }
     }
 
+    fn needs_rtcp_task(&self) -> bool {
+        self.rtcp_send_srcpad.is_some() || (self.rtcp_mux && self.rtp_send_srcpad.is_some())
+    }
+
     fn start_rtcp_task(&self, state: Arc<Mutex<State>>) {
         let mut rtcp_task = self.rtcp_task.lock().unwrap();
 
@@ -210,7 +221,19 @@ impl SendSession {
                 };
                 match reply {
                     RtcpSendReply::Data(data) => {
-                        session.rtcp_send_srcpad.clone().map(|pad| (pad, data))
+                        if session.rtcp_mux {
+                            // Only send RTCP on the RTP pad once the RTP stream has started
+                            session
+                                .rtp_send_srcpad
+                                .clone()
+                                .filter(|pad| pad.sticky_event::<gst::event::Segment>(0).is_some())
+                                .map(|pad| (pad, session.rtp_send_sinkpad.clone(), data))
+                        } else {
+                            session
+                                .rtcp_send_srcpad
+                                .clone()
+                                .map(|pad| (pad, None, data))
+                        }
                     }
                     RtcpSendReply::SsrcBye(ssrc) => {
                         session
@@ -222,9 +245,11 @@ impl SendSession {
                 }
             };
 
-            if let Some((rtcp_srcpad, data)) = send {
+            if let Some((rtcp_srcpad, rtp_sinkpad, data)) = send {
                 let acquired = sem.clone().acquire_owned().await;
                 RUNTIME.spawn_blocking(move || {
+                    // Serialize with the RTP data flow when multiplexing
+                    let _stream_lock = rtp_sinkpad.as_ref().map(|pad| pad.stream_lock());
                     let buffer = gst::Buffer::from_mut_slice(data);
                     if let Err(e) = rtcp_srcpad.push(buffer) {
                         gst::warning!(CAT, obj: rtcp_srcpad, "Failed to send rtcp data: flow return {e:?}");
@@ -484,6 +509,12 @@ impl ObjectImpl for RtpSend {
                     .default_value(DEFAULT_REDUCED_SIZE_RTCP)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoolean::builder("rtcp-mux")
+                    .nick("RTCP Mux")
+                    .blurb("Send RTCP multiplexed with RTP on the rtp_src pad instead of on the rtcp_src pad (RFC 5761)")
+                    .default_value(DEFAULT_RTCP_MUX)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -510,6 +541,10 @@ impl ObjectImpl for RtpSend {
                 let mut settings = self.settings.lock().unwrap();
                 settings.reduced_size_rtcp = value.get::<bool>().expect("Type checked upstream");
             }
+            "rtcp-mux" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.rtcp_mux = value.get::<bool>().expect("Type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -536,6 +571,10 @@ impl ObjectImpl for RtpSend {
                 let settings = self.settings.lock().unwrap();
                 settings.reduced_size_rtcp.to_value()
             }
+            "rtcp-mux" => {
+                let settings = self.settings.lock().unwrap();
+                settings.rtcp_mux.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -694,6 +733,9 @@ impl ElementImpl for RtpSend {
                         .lock()
                         .unwrap()
                         .rtp_send_sinkpad = Some(sinkpad.clone());
+                    if session.rtcp_mux {
+                        session.start_rtcp_task(state_clone);
+                    }
                     Some((sinkpad, Some(srcpad), id, vec![]))
                 };
 
@@ -858,6 +900,7 @@ impl ElementImpl for RtpSend {
             gst::StateChange::NullToReady => {
                 let settings = self.settings.lock().unwrap();
                 let rtp_id = settings.rtp_id.clone();
+                let rtcp_mux = settings.rtcp_mux;
                 drop(settings);
 
                 let state_clone = self.state.clone();
@@ -877,7 +920,8 @@ impl ElementImpl for RtpSend {
                     }
                 }
                 for session in state.sessions.iter_mut() {
-                    if session.rtcp_send_srcpad.is_some() {
+                    session.rtcp_mux = rtcp_mux;
+                    if session.needs_rtcp_task() {
                         session.start_rtcp_task(state_clone.clone());
                     }
                 }
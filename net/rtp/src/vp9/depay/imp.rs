@@ -24,6 +24,22 @@
  * This will depayload and decode an incoming RTP VP9 video stream. You can use the #rtpvp9pay2
  * and #vp9enc elements to create such an RTP stream.
  *
+ * ## Scalable streams
+ *
+ * All layer frames of a picture are output together in a single buffer. If the stream uses
+ * flexible mode or carries layer information then a `GstVP9Meta` custom meta is attached to the
+ * output buffers with the following fields:
+ *
+ * * `picture-id` (`u32`): Picture ID of the picture, if any.
+ * * `flexible-mode` (`bool`): Whether flexible mode is used.
+ * * `spatial-layer-id`, `temporal-layer-id` (`u32`), `switching-point`, `inter-layer-dependency`
+ *   (`bool`): Layer information of the first layer frame of the picture, if any.
+ * * `reference-picture-diffs` (`GstValueArray` of `u32`): Picture ID differences of the
+ *   reference pictures in flexible mode.
+ * * `num-spatial-layers` (`u32`): Number of spatial layers from the scalability structure of the
+ *   last key picture, if any.
+ * * `end-of-picture` (`bool`): Always `true`.
+ *
  * Since: plugins-rs-0.13.0
  */
 use std::{io::Cursor, mem, sync::Mutex};
@@ -38,6 +54,7 @@ use once_cell::sync::Lazy;
 use crate::basedepay::{PacketToBufferRelation, RtpBaseDepay2Ext};
 use crate::vp9::frame_header::FrameHeader;
 use crate::vp9::payload_descriptor::{PayloadDescriptor, PictureId};
+use crate::vp9::VP9_META_NAME;
 
 #[derive(Clone, Default)]
 struct Settings {
@@ -545,6 +562,52 @@ impl crate::basedepay::RtpBaseDepay2Impl for RtpVp9Depay {
             // Set MARKER flag on the output so that the parser knows that this buffer ends a full
             // picture and potentially can operate a bit faster.
             buffer.set_flags(gst::BufferFlags::MARKER);
+
+            let num_spatial_layers = state
+                .last_key_picture_payload_descriptor
+                .as_ref()
+                .and_then(|payload_descriptor| payload_descriptor.scalability_structure.as_ref())
+                .map(|scalability_structure| scalability_structure.num_spatial_layers);
+
+            if current_picture_payload_descriptor.flexible_mode
+                || current_picture_payload_descriptor.layer_index.is_some()
+                || num_spatial_layers.is_some()
+            {
+                let mut meta = gst::meta::CustomMeta::add(buffer, VP9_META_NAME).unwrap();
+                let s = meta.mut_structure();
+
+                if let Some(picture_id) = current_picture_payload_descriptor.picture_id {
+                    s.set("picture-id", u32::from(u16::from(picture_id)));
+                }
+                s.set(
+                    "flexible-mode",
+                    current_picture_payload_descriptor.flexible_mode,
+                );
+                if let Some(ref layer_index) = current_picture_payload_descriptor.layer_index {
+                    s.set("spatial-layer-id", layer_index.spatial_layer_id as u32);
+                    s.set("temporal-layer-id", layer_index.temporal_layer_id as u32);
+                    s.set("switching-point", layer_index.switching_point);
+                    s.set(
+                        "inter-layer-dependency",
+                        layer_index.inter_layer_dependency_used,
+                    );
+                }
+                if current_picture_payload_descriptor.flexible_mode {
+                    s.set(
+                        "reference-picture-diffs",
+                        gst::Array::new(
+                            current_picture_payload_descriptor
+                                .reference_indices
+                                .iter()
+                                .map(|diff| *diff as u32),
+                        ),
+                    );
+                }
+                if let Some(num_spatial_layers) = num_spatial_layers {
+                    s.set("num-spatial-layers", num_spatial_layers as u32);
+                }
+                s.set("end-of-picture", true);
+            }
         }
 
         state.current_picture_payload_descriptor = None;
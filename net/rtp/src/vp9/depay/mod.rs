@@ -18,6 +18,8 @@ glib::wrapper! {
 }
 
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    super::register_meta();
+
     gst::Element::register(
         Some(plugin),
         "rtpvp9depay2",
@@ -14,3 +14,17 @@ mod payload_descriptor;
 
 #[cfg(test)]
 mod tests;
+
+/// Name of the custom meta carrying VP9 layer information.
+///
+/// The payloader reads this from its input buffers to fill the layer indices and reference
+/// indices of the payload descriptor, and the depayloader attaches it to its output buffers.
+pub(crate) const VP9_META_NAME: &str = "GstVP9Meta";
+
+pub(crate) fn register_meta() {
+    static REGISTER: std::sync::Once = std::sync::Once::new();
+
+    REGISTER.call_once(|| {
+        gst::meta::CustomMeta::register(VP9_META_NAME, &[]);
+    });
+}
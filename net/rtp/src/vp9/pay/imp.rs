@@ -22,12 +22,36 @@
  * ]| This will create and payload a VP9 video stream with a test pattern and
  * send it out via UDP to localhost port 5004.
  *
+ * ## Scalable streams
+ *
+ * Layer information for spatially and temporally scalable streams is taken from the `GstVP9Meta`
+ * custom meta on the input buffers, if present. Each buffer is expected to contain a single layer
+ * frame and the meta can contain the following fields:
+ *
+ * * `spatial-layer-id` (`u32`): Spatial layer of the frame.
+ * * `temporal-layer-id` (`u32`): Temporal layer of the frame.
+ * * `switching-point` (`bool`): Whether the frame is a temporal layer switching point.
+ * * `inter-layer-dependency` (`bool`): Whether the frame depends on the lower spatial layer.
+ * * `reference-picture-diffs` (`GstValueArray` of `u32`): Picture ID differences of the
+ *   reference pictures. Only used in flexible mode.
+ * * `not-reference-for-upper-layers` (`bool`): Whether the frame is not used as reference by
+ *   frames of higher spatial layers of the same picture.
+ * * `end-of-picture` (`bool`): Whether this is the last layer frame of the picture.
+ * * `num-spatial-layers` (`u32`): Number of spatial layers. Used for the scalability structure
+ *   that is sent with key pictures.
+ *
+ * If #rtpvp9pay2:flexible-mode is enabled then the reference pictures are signalled explicitly
+ * in every packet as per the flexible mode of the payload format, otherwise the temporal layer zero
+ * index is signalled for layered streams.
+ *
  * Since: plugins-rs-0.13.0
  */
 use gst::{glib, prelude::*, subclass::prelude::*};
 use smallvec::SmallVec;
 use std::{cmp, sync::Mutex};
 
+use atomic_refcell::AtomicRefCell;
+
 use bitstream_io::{BigEndian, BitRead as _, BitReader, ByteWrite as _, ByteWriter};
 use once_cell::sync::Lazy;
 
@@ -35,7 +59,8 @@ use crate::{
     basepay::{RtpBasePay2Ext, RtpBasePay2ImplExt},
     vp9::{
         frame_header::FrameHeader,
-        payload_descriptor::{PayloadDescriptor, PictureId},
+        payload_descriptor::{LayerIndex, PayloadDescriptor, PictureId, ScalabilityStructure},
+        VP9_META_NAME,
     },
 };
 
@@ -51,11 +76,23 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 struct Settings {
     picture_id_mode: super::PictureIdMode,
     picture_id_offset: Option<u16>,
+    flexible_mode: bool,
+}
+
+#[derive(Default)]
+struct State {
+    /// Only set if a VP9 custom meta with layer information was ever received for this stream in
+    /// non-flexible mode. Incremented after every picture with temporal-layer-id=0.
+    temporal_layer_zero_index: Option<u8>,
+    /// Whether the current picture is a key picture, i.e. its base spatial layer frame is a
+    /// keyframe.
+    key_picture: bool,
 }
 
 #[derive(Default)]
 pub struct RtpVp9Pay {
     settings: Mutex<Settings>,
+    state: AtomicRefCell<State>,
     /// Current picture ID.
     ///
     /// Reset to `None` in `Null` / `Ready` state and initialized to the offset when going to
@@ -101,6 +138,21 @@ impl ObjectImpl for RtpVp9Pay {
                     .maximum(0x7fff)
                     .read_only()
                     .build(),
+                /**
+                 * GstRtpVp9Pay2:flexible-mode:
+                 *
+                 * Use the flexible mode of the payload format and explicitly signal the reference
+                 * pictures of each frame. This requires picture IDs, so if #rtpvp9pay2:picture-id-mode
+                 * is set to `none` then 15 bit picture IDs are used.
+                 *
+                 * Since: plugins-rs-0.13.0
+                 */
+                glib::ParamSpecBoolean::builder("flexible-mode")
+                    .nick("Flexible Mode")
+                    .blurb("Use flexible mode and signal reference pictures explicitly")
+                    .default_value(Settings::default().flexible_mode)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -117,6 +169,9 @@ impl ObjectImpl for RtpVp9Pay {
                 self.settings.lock().unwrap().picture_id_offset =
                     (v != -1).then_some((v & 0x7fff) as u16);
             }
+            "flexible-mode" => {
+                self.settings.lock().unwrap().flexible_mode = value.get().unwrap();
+            }
             _ => unimplemented!(),
         };
     }
@@ -140,6 +195,7 @@ impl ObjectImpl for RtpVp9Pay {
                     .unwrap_or(-1)
                     .to_value()
             }
+            "flexible-mode" => self.settings.lock().unwrap().flexible_mode.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -206,13 +262,23 @@ impl crate::basepay::RtpBasePay2Impl for RtpVp9Pay {
             rng.gen::<u16>()
         });
 
-        let picture_id = PictureId::new(settings.picture_id_mode, picture_id_offset);
+        // Flexible mode requires picture IDs
+        let picture_id_mode =
+            if settings.flexible_mode && settings.picture_id_mode == super::PictureIdMode::None {
+                gst::debug!(CAT, imp: self, "Using 15 bit picture IDs for flexible mode");
+                super::PictureIdMode::FifteenBit
+            } else {
+                settings.picture_id_mode
+            };
+
+        let picture_id = PictureId::new(picture_id_mode, picture_id_offset);
         *self.picture_id.lock().unwrap() = picture_id;
 
         Ok(())
     }
 
     fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
         *self.picture_id.lock().unwrap() = None;
 
         Ok(())
@@ -252,6 +318,8 @@ impl crate::basepay::RtpBasePay2Impl for RtpVp9Pay {
         buffer: &gst::Buffer,
         id: u64,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.borrow_mut();
+        let flexible_mode = self.settings.lock().unwrap().flexible_mode;
         let max_payload_size = self.obj().max_payload_size();
 
         gst::trace!(CAT, imp: self, "received buffer of size {}", buffer.size());
@@ -266,9 +334,7 @@ impl crate::basepay::RtpBasePay2Impl for RtpVp9Pay {
             gst::FlowError::Error
         })?;
 
-        // TODO: We assume 1 spatial and 1 temporal layer. Scalable VP9 streams are not really
-        // supported by GStreamer so far and require further design work.
-        // FIXME: We also assume that each buffer contains a single VP9 frame. The VP9 caps are
+        // FIXME: We assume that each buffer contains a single VP9 frame. The VP9 caps are
         // misdesigned unfortunately and there's no enforced alignment so this could theoretically
         // also contain a whole superframe. A receiver is likely not going to fail on this.
 
@@ -282,32 +348,113 @@ impl crate::basepay::RtpBasePay2Impl for RtpVp9Pay {
         // necessarily providing correctly parsed information. This is mostly for compatibility
         // with `rtpvp9pay`.
         let mut r = BitReader::endian(map.as_slice(), BigEndian);
-        let key_frame = match r.parse::<FrameHeader>() {
+        let (key_frame, frame_size) = match r.parse::<FrameHeader>() {
             Ok(frame_header) => {
                 gst::trace!(CAT, imp: self, "Parsed frame header: {frame_header:?}");
                 // show_existing_frame assumes that there is an existing frame to show so this is
                 // clearly not a keyframe
-                frame_header.is_keyframe.unwrap_or(false)
+                (
+                    frame_header.is_keyframe.unwrap_or(false),
+                    frame_header
+                        .keyframe_info
+                        .map(|keyframe_info| keyframe_info.frame_size),
+                )
             }
             Err(err) => {
                 gst::trace!(CAT, imp: self, "Failed parsing frame header: {err:?}");
-                !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT)
+                (!buffer.flags().contains(gst::BufferFlags::DELTA_UNIT), None)
+            }
+        };
+
+        let meta = VP9Meta::from_buffer(buffer);
+        let layer = meta.as_ref().and_then(|meta| meta.layer.as_ref());
+
+        // Initialize temporal layer zero index the first time we receive a meta with layer
+        // information in non-flexible mode.
+        if !flexible_mode && layer.is_some() && state.temporal_layer_zero_index.is_none() {
+            gst::trace!(CAT, imp: self, "Detected scalable stream");
+            state.temporal_layer_zero_index = Some(0);
+        }
+
+        // The frames of the upper spatial layers of a key picture are only predicted from the
+        // lower spatial layers, never from previous pictures.
+        let spatial_layer_id = layer.map_or(0, |layer| layer.spatial_layer_id);
+        if spatial_layer_id == 0 {
+            state.key_picture = key_frame;
+        }
+        let inter_picture_predicted_frame = !key_frame && !state.key_picture;
+
+        let layer_index = layer.map(|layer| LayerIndex {
+            temporal_layer_id: layer.temporal_layer_id,
+            switching_point: layer.switching_point,
+            spatial_layer_id: layer.spatial_layer_id,
+            inter_layer_dependency_used: layer.inter_layer_dependency_used,
+            temporal_layer_zero_index: state.temporal_layer_zero_index,
+        });
+
+        // Reference pictures are only signalled in flexible mode. Without information from
+        // upstream assume that the previous picture is referenced.
+        let reference_indices = if flexible_mode && inter_picture_predicted_frame {
+            let mut reference_indices = meta
+                .as_ref()
+                .map(|meta| meta.reference_picture_diffs.clone())
+                .unwrap_or_default();
+            if reference_indices.is_empty() {
+                reference_indices.push(1);
             }
+            reference_indices
+        } else {
+            SmallVec::new()
         };
 
+        // Send the scalability structure with the first packet of each key picture. For
+        // non-flexible scalable streams this requires picture IDs so only do that if they're
+        // enabled.
+        let num_spatial_layers = meta
+            .as_ref()
+            .and_then(|meta| meta.num_spatial_layers)
+            .unwrap_or(1);
+        let scalability_structure = if key_frame
+            && spatial_layer_id == 0
+            && (flexible_mode || (layer.is_some() && picture_id.is_some()))
+        {
+            Some(ScalabilityStructure {
+                num_spatial_layers,
+                // Resolutions of the other spatial layers are not known here
+                spatial_layer_frame_resolutions: match frame_size {
+                    Some((width, height)) if num_spatial_layers == 1 => {
+                        smallvec::smallvec![(width as u16, height as u16)]
+                    }
+                    _ => SmallVec::new(),
+                },
+                picture_description: SmallVec::new(),
+            })
+        } else {
+            None
+        };
+
+        let not_reference_frame_for_upper_layers = meta
+            .as_ref()
+            .map_or(true, |meta| meta.not_reference_frame_for_upper_layers);
+        let end_of_picture = meta.as_ref().map_or(true, |meta| meta.end_of_picture);
+
         let mut first = true;
         let mut data = map.as_slice();
         while !data.is_empty() {
             let mut payload_descriptor = PayloadDescriptor {
                 picture_id,
-                layer_index: None,
-                inter_picture_predicted_frame: !key_frame,
-                flexible_mode: false,
-                reference_indices: Default::default(),
+                layer_index: layer_index.clone(),
+                inter_picture_predicted_frame,
+                flexible_mode,
+                reference_indices: reference_indices.clone(),
                 start_of_frame: first,
                 end_of_frame: false, // reset later
-                scalability_structure: None,
-                not_reference_frame_for_upper_layers: true,
+                scalability_structure: if first {
+                    scalability_structure.clone()
+                } else {
+                    None
+                },
+                not_reference_frame_for_upper_layers,
             };
 
             let payload_descriptor_size = payload_descriptor.size().map_err(|err| {
@@ -350,7 +497,7 @@ impl crate::basepay::RtpBasePay2Impl for RtpVp9Pay {
             self.obj().queue_packet(
                 id.into(),
                 rtp_types::RtpPacketBuilder::new()
-                    .marker_bit(data.len() == payload_size)
+                    .marker_bit(end_of_picture && data.len() == payload_size)
                     .payload(payload_descriptor_buffer.as_slice())
                     .payload(&data[..payload_size]),
             )?;
@@ -359,9 +506,103 @@ impl crate::basepay::RtpBasePay2Impl for RtpVp9Pay {
             first = false;
         }
 
-        let next_picture_id = picture_id.map(PictureId::increment);
-        *self.picture_id.lock().unwrap() = next_picture_id;
+        if end_of_picture {
+            // If this picture was on temporal layer zero then increment the temporal layer zero
+            // index.
+            if layer.map_or(true, |layer| layer.temporal_layer_id == 0) {
+                if let Some(ref mut temporal_layer_zero_index) = state.temporal_layer_zero_index {
+                    *temporal_layer_zero_index = temporal_layer_zero_index.wrapping_add(1);
+                    gst::trace!(CAT, imp: self, "Updated temporal layer zero index to {temporal_layer_zero_index}");
+                }
+            }
+
+            let next_picture_id = picture_id.map(PictureId::increment);
+            *self.picture_id.lock().unwrap() = next_picture_id;
+        }
 
         Ok(gst::FlowSuccess::Ok)
     }
+
+    fn transform_meta(
+        &self,
+        in_buf: &gst::BufferRef,
+        meta: &gst::MetaRef<gst::Meta>,
+        out_buf: &mut gst::BufferRef,
+    ) {
+        // Drop VP9 custom meta, handle all other metas normally.
+        if meta
+            .try_as_custom_meta()
+            .map_or(false, |meta| meta.has_name(VP9_META_NAME))
+        {
+            return;
+        }
+
+        self.parent_transform_meta(in_buf, meta, out_buf)
+    }
+}
+
+struct VP9Layer {
+    spatial_layer_id: u8,
+    temporal_layer_id: u8,
+    switching_point: bool,
+    inter_layer_dependency_used: bool,
+}
+
+struct VP9Meta {
+    layer: Option<VP9Layer>,
+    reference_picture_diffs: SmallVec<[u8; 3]>,
+    not_reference_frame_for_upper_layers: bool,
+    end_of_picture: bool,
+    num_spatial_layers: Option<u8>,
+}
+
+impl VP9Meta {
+    fn from_buffer(buffer: &gst::BufferRef) -> Option<Self> {
+        let meta = gst::meta::CustomMeta::from_buffer(buffer, VP9_META_NAME).ok()?;
+
+        let s = meta.structure();
+
+        let spatial_layer_id = s.get_optional::<u32>("spatial-layer-id").ok()?;
+        let temporal_layer_id = s.get_optional::<u32>("temporal-layer-id").ok()?;
+        let layer = if spatial_layer_id.is_some() || temporal_layer_id.is_some() {
+            Some(VP9Layer {
+                spatial_layer_id: cmp::min(spatial_layer_id.unwrap_or(0), 0b111) as u8,
+                temporal_layer_id: cmp::min(temporal_layer_id.unwrap_or(0), 0b111) as u8,
+                switching_point: s.get::<bool>("switching-point").unwrap_or(false),
+                inter_layer_dependency_used: s
+                    .get::<bool>("inter-layer-dependency")
+                    .unwrap_or(false),
+            })
+        } else {
+            None
+        };
+
+        let reference_picture_diffs = s
+            .get::<gst::Array>("reference-picture-diffs")
+            .map(|diffs| {
+                diffs
+                    .iter()
+                    .filter_map(|diff| diff.get::<u32>().ok())
+                    .filter(|diff| (1..=0b0111_1111).contains(diff))
+                    .take(3)
+                    .map(|diff| diff as u8)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let num_spatial_layers = s
+            .get_optional::<u32>("num-spatial-layers")
+            .ok()?
+            .map(|num_spatial_layers| num_spatial_layers.clamp(1, 8) as u8);
+
+        Some(VP9Meta {
+            layer,
+            reference_picture_diffs,
+            not_reference_frame_for_upper_layers: s
+                .get::<bool>("not-reference-for-upper-layers")
+                .unwrap_or(true),
+            end_of_picture: s.get::<bool>("end-of-picture").unwrap_or(true),
+            num_spatial_layers,
+        })
+    }
 }
@@ -23,6 +23,8 @@ pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
         PictureIdMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     }
 
+    super::register_meta();
+
     gst::Element::register(
         Some(plugin),
         "rtpvp9pay2",
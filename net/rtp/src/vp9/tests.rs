@@ -218,3 +218,100 @@ fn test_vp9_small_mtu() {
 
     run_test_pipeline(Source::Bin(src), pay, depay, expected_pay, expected_depay);
 }
+
+#[test]
+fn test_vp9_flexible_mode() {
+    init();
+
+    // Generates encoded frames of sizes 1342 (key), 96, 41, 55, 41
+    let src = "videotestsrc num-buffers=5 pattern=gradient ! video/x-raw,format=I420,width=1920,height=1080,framerate=25/1 ! vp9enc target-bitrate=4000000";
+    let pay = "rtpvp9pay2 mtu=1200 flexible-mode=true";
+    let depay = "rtpvp9depay2";
+
+    let expected_pay = vec![
+        vec![
+            // First frame is split into two packets, the first one carrying the scalability
+            // structure
+            ExpectedPacket::builder()
+                .pts(gst::ClockTime::from_mseconds(0))
+                .flags(gst::BufferFlags::DISCONT)
+                .pt(96)
+                .rtp_time(0)
+                .marker_bit(false)
+                .size(1200)
+                .build(),
+            ExpectedPacket::builder()
+                .pts(gst::ClockTime::from_mseconds(0))
+                .flags(gst::BufferFlags::MARKER)
+                .pt(96)
+                .rtp_time(0)
+                .marker_bit(true)
+                .size(177)
+                .build(),
+        ],
+        // Second and following frames have one reference index each
+        vec![ExpectedPacket::builder()
+            .pts(gst::ClockTime::from_mseconds(40))
+            .flags(gst::BufferFlags::MARKER)
+            .pt(96)
+            .rtp_time(3_600)
+            .marker_bit(true)
+            .size(112)
+            .build()],
+        vec![ExpectedPacket::builder()
+            .pts(gst::ClockTime::from_mseconds(80))
+            .flags(gst::BufferFlags::MARKER)
+            .pt(96)
+            .rtp_time(7_200)
+            .marker_bit(true)
+            .size(57)
+            .build()],
+        vec![ExpectedPacket::builder()
+            .pts(gst::ClockTime::from_mseconds(120))
+            .flags(gst::BufferFlags::MARKER)
+            .pt(96)
+            .rtp_time(10_800)
+            .marker_bit(true)
+            .size(71)
+            .build()],
+        vec![ExpectedPacket::builder()
+            .pts(gst::ClockTime::from_mseconds(160))
+            .flags(gst::BufferFlags::MARKER)
+            .pt(96)
+            .rtp_time(14_400)
+            .marker_bit(true)
+            .size(57)
+            .build()],
+    ];
+
+    let expected_depay = vec![
+        // One buffer per frame
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::from_mseconds(0))
+            .size(1342)
+            .flags(gst::BufferFlags::DISCONT | gst::BufferFlags::MARKER)
+            .build()],
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::from_mseconds(40))
+            .size(96)
+            .flags(gst::BufferFlags::MARKER | gst::BufferFlags::DELTA_UNIT)
+            .build()],
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::from_mseconds(80))
+            .size(41)
+            .flags(gst::BufferFlags::MARKER | gst::BufferFlags::DELTA_UNIT)
+            .build()],
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::from_mseconds(120))
+            .size(55)
+            .flags(gst::BufferFlags::MARKER | gst::BufferFlags::DELTA_UNIT)
+            .build()],
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::from_mseconds(160))
+            .size(41)
+            .flags(gst::BufferFlags::MARKER | gst::BufferFlags::DELTA_UNIT)
+            .build()],
+    ];
+
+    run_test_pipeline(Source::Bin(src), pay, depay, expected_pay, expected_depay);
+}
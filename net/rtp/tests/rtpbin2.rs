@@ -260,3 +260,137 @@ fn test_receive_flush() {
     };
     assert_eq!(fs.seqnum(), seqnum);
 }
+
+fn generate_rtp_buffer_with_mid(
+    seqno: u16,
+    rtpts: u32,
+    payload_len: usize,
+    mid: &str,
+) -> gst::Buffer {
+    // one-byte header extension with id 1, padded to a multiple of 4 bytes
+    let mut ext_data = vec![(1 << 4) | (mid.len() as u8 - 1)];
+    ext_data.extend_from_slice(mid.as_bytes());
+    ext_data.resize((ext_data.len() + 3) / 4 * 4, 0);
+
+    let payload = vec![4; payload_len];
+    let packet = RtpPacketBuilder::new()
+        .ssrc(TEST_SSRC)
+        .payload_type(TEST_PT)
+        .sequence_number(seqno)
+        .timestamp(rtpts)
+        .extension(0xbede, ext_data.as_slice())
+        .payload(payload.as_slice());
+    let size = packet.calculate_size().unwrap();
+    let mut data = vec![0; size];
+    packet.write_into(&mut data).unwrap();
+    gst::Buffer::from_mut_slice(data)
+}
+
+fn generate_rtcp_sr_buffer(ssrc: u32, packet_count: u32) -> gst::Buffer {
+    use rtcp_types::RtcpPacketWriter;
+
+    let sr = rtcp_types::Compound::builder().add_packet(
+        rtcp_types::SenderReport::builder(ssrc)
+            .packet_count(packet_count)
+            .octet_count(packet_count * 10)
+            .rtp_timestamp(30),
+    );
+    let mut data = vec![0; sr.calculate_size().unwrap()];
+    sr.write_into(&mut data).unwrap();
+    gst::Buffer::from_mut_slice(data)
+}
+
+#[test]
+fn test_receive_bundle() {
+    init();
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .build()
+        .unwrap();
+    let h = Arc::new(Mutex::new(Harness::with_element(
+        &elem,
+        Some("rtp_sink_0"),
+        None,
+    )));
+    let weak_h = Arc::downgrade(&h);
+    let mut inner = h.lock().unwrap();
+    inner
+        .element()
+        .unwrap()
+        .connect_pad_added(move |_elem, pad| {
+            weak_h
+                .upgrade()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .add_element_src_pad(pad)
+        });
+    inner.play();
+
+    let caps = Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("payload", TEST_PT as i32)
+        .field("clock-rate", TEST_CLOCK_RATE as i32)
+        .field("encoding-name", "custom-test")
+        .field("extmap-1", "urn:ietf:params:rtp-hdrext:sdes:mid")
+        .build();
+    inner.set_src_caps(caps);
+
+    // Cannot push with harness lock as the 'pad-added' handler needs to add the newly created pad to
+    // the harness and needs to also take the harness lock.  Workaround by pushing from the
+    // internal harness pad directly.
+    let push_pad = inner
+        .element()
+        .unwrap()
+        .static_pad("rtp_sink_0")
+        .unwrap()
+        .peer()
+        .unwrap();
+    drop(inner);
+    push_pad
+        .push(generate_rtp_buffer_with_mid(500, 20, 9, "audio0"))
+        .unwrap();
+    // RTCP multiplexed on the RTP pad
+    push_pad
+        .push(generate_rtcp_sr_buffer(TEST_SSRC, 1))
+        .unwrap();
+    push_pad
+        .push(generate_rtp_buffer_with_mid(501, 30, 11, "audio0"))
+        .unwrap();
+    let mut inner = h.lock().unwrap();
+
+    let buffer = inner.pull().unwrap();
+    let mapped = buffer.map_readable().unwrap();
+    let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+    assert_eq!(rtp.sequence_number(), 500);
+
+    let buffer = inner.pull().unwrap();
+    let mapped = buffer.map_readable().unwrap();
+    let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+    assert_eq!(rtp.sequence_number(), 501);
+
+    let caps = inner.sinkpad().unwrap().current_caps().unwrap();
+    let s = caps.structure(0).unwrap();
+    assert_eq!(s.get::<&str>("a-mid").unwrap(), "audio0");
+
+    let stats = inner.element().unwrap().property::<gst::Structure>("stats");
+
+    let session_stats = stats.get::<gst::Structure>("0").unwrap();
+    let source_stats = session_stats
+        .get::<gst::Structure>(TEST_SSRC.to_string())
+        .unwrap();
+    assert_eq!(source_stats.get::<u64>("packets-received").unwrap(), 2);
+    assert_eq!(source_stats.get::<u32>("sr-packet-count").unwrap(), 1);
+
+    let jitterbuffers_stats = session_stats
+        .get::<gst::List>("jitterbuffer-stats")
+        .unwrap();
+    let jitterbuffer_stats = jitterbuffers_stats
+        .first()
+        .unwrap()
+        .get::<gst::Structure>()
+        .unwrap();
+    assert_eq!(jitterbuffer_stats.get::<&str>("mid").unwrap(), "audio0");
+}
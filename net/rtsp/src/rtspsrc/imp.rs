@@ -11,7 +11,7 @@
 //
 // https://www.rfc-editor.org/rfc/rfc2326.html
 
-use std::collections::{btree_set::BTreeSet, HashMap};
+use std::collections::{btree_set::BTreeSet, HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::fmt;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
@@ -34,7 +34,7 @@ use tokio::time;
 use rtsp_types::headers::{
     CSeq, NptRange, NptTime, Public, Range, RtpInfos, RtpLowerTransport, RtpProfile, RtpTransport,
     RtpTransportParameters, Session, Transport, TransportMode, Transports, ACCEPT, CONTENT_BASE,
-    CONTENT_LOCATION, USER_AGENT,
+    CONTENT_LOCATION, RANGE, REQUIRE, SCALE, USER_AGENT,
 };
 use rtsp_types::{Message, Method, Request, Response, StatusCode, Version};
 
@@ -54,16 +54,32 @@ use super::transport::RtspTransportInfo;
 const DEFAULT_LOCATION: Option<Url> = None;
 const DEFAULT_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(5);
 const DEFAULT_PORT_START: u16 = 0;
+const DEFAULT_PORT_END: u16 = 0;
 // Priority list has multicast first, because we want to prefer multicast if it's available
 const DEFAULT_PROTOCOLS: &str = "udp-mcast,udp,tcp";
 // Equal to MTU + 8 by default to avoid incorrectly detecting an MTU sized buffer as having
 // possibly overflown our receive buffer, and triggering a doubling of the buffer sizes.
 const DEFAULT_RECEIVE_MTU: u32 = 1500 + 8;
+const DEFAULT_BACKCHANNEL: RtspBackchannel = RtspBackchannel::None;
+const DEFAULT_NAT_METHOD: RtspNatMethod = RtspNatMethod::Dummy;
 
 const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
 const MAX_BIND_PORT_RETRY: u16 = 100;
 const UDP_PACKET_MAX_SIZE: u32 = 65535 - 8;
 const RTCP_ADDR_CACHE_SIZE: usize = 100;
+// Number of backchannel packets that can be queued for sending before dropping
+const BACKCHANNEL_QUEUE_SIZE: usize = 32;
+
+// https://www.onvif.org/specs/stream/ONVIF-Streaming-Spec.pdf section 5.3
+const ONVIF_BACKCHANNEL_REQUIRE: &str = "www.onvif.org/ver20/backchannel";
+// Encodings supported for sending audio back to the server: G.711 and AAC
+const BACKCHANNEL_ENCODINGS: &[&str] = &["PCMU", "PCMA", "MPEG4-GENERIC"];
+
+// Sent to the server ports to open a pinhole in NATs and firewalls: an RTP packet without payload
+// (version 2, PT 0, zero SSRC) and an empty RTCP RR (version 2, PT 201, length 1, zero SSRC), same
+// as rtspsrc.
+const DUMMY_RTP_PACKET: [u8; 12] = [0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+const DUMMY_RTCP_PACKET: [u8; 8] = [0x80, 0xc9, 0x00, 0x01, 0, 0, 0, 0];
 
 static RTCP_CAPS: Lazy<gst::Caps> =
     Lazy::new(|| gst::Caps::from(gst::Structure::new_empty("application/x-rtcp")));
@@ -93,13 +109,38 @@ impl fmt::Display for RtspProtocol {
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRtspSrc2Backchannel")]
+pub enum RtspBackchannel {
+    #[default]
+    #[enum_value(name = "No backchannel", nick = "none")]
+    None,
+    #[enum_value(name = "ONVIF audio backchannel", nick = "onvif")]
+    Onvif,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRtspSrc2NatMethod")]
+pub enum RtspNatMethod {
+    #[enum_value(name = "None", nick = "none")]
+    None,
+    #[default]
+    #[enum_value(name = "Send dummy packets", nick = "dummy")]
+    Dummy,
+}
+
 #[derive(Debug, Clone)]
 struct Settings {
     location: Option<Url>,
     port_start: u16,
+    port_end: u16,
     protocols: Vec<RtspProtocol>,
     timeout: gst::ClockTime,
     receive_mtu: u32,
+    backchannel: RtspBackchannel,
+    nat_method: RtspNatMethod,
 }
 
 impl Default for Settings {
@@ -107,19 +148,56 @@ impl Default for Settings {
         Settings {
             location: DEFAULT_LOCATION,
             port_start: DEFAULT_PORT_START,
+            port_end: DEFAULT_PORT_END,
             timeout: DEFAULT_TIMEOUT,
             protocols: parse_protocols_str(DEFAULT_PROTOCOLS).unwrap(),
             receive_mtu: DEFAULT_RECEIVE_MTU,
+            backchannel: DEFAULT_BACKCHANNEL,
+            nat_method: DEFAULT_NAT_METHOD,
         }
     }
 }
 
+#[derive(Debug)]
+struct SeekParams {
+    rate: f64,
+    flush: bool,
+    start: Option<gst::ClockTime>,
+    stop: Option<gst::ClockTime>,
+    seqnum: gst::Seqnum,
+}
+
 #[derive(Debug)]
 enum Commands {
     Play,
     //Pause,
     Teardown(Option<oneshot::Sender<()>>),
     Data(rtsp_types::Data<Body>),
+    // No data was received on any of the UDP sockets
+    UdpTimeout,
+    Seek(SeekParams),
+}
+
+// Position in the presentation for recorded (VOD) streams, applied to the segments pushed on the
+// source pads
+#[derive(Debug)]
+struct VodState {
+    // Only known for VOD, live streams are not seekable
+    duration: Option<gst::ClockTime>,
+    position: gst::ClockTime,
+    applied_rate: f64,
+    seek_seqnum: Option<gst::Seqnum>,
+}
+
+impl Default for VodState {
+    fn default() -> Self {
+        VodState {
+            duration: None,
+            position: gst::ClockTime::ZERO,
+            applied_rate: 1.0,
+            seek_seqnum: None,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -127,6 +205,7 @@ pub struct RtspSrc {
     settings: Mutex<Settings>,
     task_handle: Mutex<Option<JoinHandle<()>>>,
     command_queue: Mutex<Option<mpsc::Sender<Commands>>>,
+    vod: Mutex<VodState>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -145,6 +224,8 @@ pub enum RtspError {
     InvalidMessage(&'static str),
     #[error("Fatal error")]
     Fatal(String),
+    #[error("No data received over UDP")]
+    UdpTimeout,
 }
 
 pub(crate) static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
@@ -288,13 +369,21 @@ impl ObjectImpl for RtspSrc {
                 // on how many streams the media contains, and whether the server wants RTCP or
                 // RTCP-mux, or no RTCP. This property can be used to specify the start of the
                 // valid range, and if the user wants to know how many ports were used, we can
-                // add API for that later.
+                // add API for that later. port-end can be used to bound the range, for instance
+                // when only some ports are forwarded by a firewall.
                 glib::ParamSpecUInt::builder("port-start")
                     .nick("Port start")
                     .blurb("Port number to start allocating client ports for receiving RTP and RTCP data, eg. 3000 (0 = automatic selection)")
                     .default_value(DEFAULT_PORT_START.into())
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecUInt::builder("port-end")
+                    .nick("Port end")
+                    .blurb("Last port number that can be allocated for receiving RTP and RTCP data when port-start is set, eg. 3010 (0 = no limit)")
+                    .maximum(u16::MAX.into())
+                    .default_value(DEFAULT_PORT_END.into())
+                    .mutable_ready()
+                    .build(),
                 glib::ParamSpecString::builder("protocols")
                     .nick("Protocols")
                     .blurb("Allowed lower transport protocols, in order of preference")
@@ -308,6 +397,16 @@ impl ObjectImpl for RtspSrc {
                     .default_value(DEFAULT_TIMEOUT.into())
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecEnum::builder_with_default("backchannel", DEFAULT_BACKCHANNEL)
+                    .nick("Backchannel")
+                    .blurb("The type of backchannel to request from the server, exposed as backchannel_%u sink pads")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("nat-method", DEFAULT_NAT_METHOD)
+                    .nick("NAT Method")
+                    .blurb("Method to use for traversing firewalls and NATs when receiving over UDP")
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -338,6 +437,20 @@ impl ObjectImpl for RtspSrc {
                     )),
                 }
             }
+            "port-end" => {
+                let mut settings = self.settings.lock().unwrap();
+                let end = value.get::<u32>().expect("type checked upstream");
+                match u16::try_from(end) {
+                    Ok(end) => {
+                        settings.port_end = end;
+                        Ok(())
+                    }
+                    Err(err) => Err(glib::Error::new(
+                        gst::CoreError::Failed,
+                        &format!("Failed to set port end: {err:?}"),
+                    )),
+                }
+            }
             "protocols" => {
                 let protocols = value.get::<Option<&str>>().expect("type checked upstream");
                 self.set_protocols(protocols)
@@ -348,6 +461,16 @@ impl ObjectImpl for RtspSrc {
                 settings.timeout = timeout;
                 Ok(())
             }
+            "backchannel" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.backchannel = value.get().expect("type checked upstream");
+                Ok(())
+            }
+            "nat-method" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.nat_method = value.get().expect("type checked upstream");
+                Ok(())
+            }
             name => unimplemented!("Property '{name}'"),
         };
 
@@ -378,6 +501,10 @@ impl ObjectImpl for RtspSrc {
                 let settings = self.settings.lock().unwrap();
                 (settings.port_start as u32).to_value()
             }
+            "port-end" => {
+                let settings = self.settings.lock().unwrap();
+                (settings.port_end as u32).to_value()
+            }
             "protocols" => {
                 let settings = self.settings.lock().unwrap();
                 (settings
@@ -392,6 +519,14 @@ impl ObjectImpl for RtspSrc {
                 let settings = self.settings.lock().unwrap();
                 settings.timeout.to_value()
             }
+            "backchannel" => {
+                let settings = self.settings.lock().unwrap();
+                settings.backchannel.to_value()
+            }
+            "nat-method" => {
+                let settings = self.settings.lock().unwrap();
+                settings.nat_method.to_value()
+            }
             name => unimplemented!("Property '{name}'"),
         }
     }
@@ -431,7 +566,15 @@ impl ElementImpl for RtspSrc {
             )
             .unwrap();
 
-            vec![src_pad_template]
+            let backchannel_pad_template = gst::PadTemplate::new(
+                "backchannel_%u",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Sometimes,
+                &gst::Caps::new_empty_simple("application/x-rtp"),
+            )
+            .unwrap();
+
+            vec![src_pad_template, backchannel_pad_template]
         });
 
         PAD_TEMPLATES.as_ref()
@@ -497,6 +640,13 @@ impl ElementImpl for RtspSrc {
 
         Ok(ret)
     }
+
+    fn send_event(&self, event: gst::Event) -> bool {
+        match event.view() {
+            gst::EventView::Seek(seek) => self.handle_seek(seek),
+            _ => self.parent_send_event(event),
+        }
+    }
 }
 
 impl BinImpl for RtspSrc {}
@@ -530,7 +680,15 @@ impl RtspSrc {
     }
 
     fn start(&self) -> Result<(), gst::ErrorMessage> {
-        let Some(url) = self.settings.lock().unwrap().location.clone() else {
+        let (url, backchannel, mut protocols) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                settings.location.clone(),
+                settings.backchannel,
+                settings.protocols.clone(),
+            )
+        };
+        let Some(url) = url else {
             return Err(gst::error_msg!(
                 gst::ResourceError::Settings,
                 ["No location set"]
@@ -549,11 +707,13 @@ impl RtspSrc {
             "Starting RTSP connection thread.. "
         );
 
+        *self.vod.lock().unwrap() = VodState::default();
+
         let task_src = self.ref_counted();
 
         let mut task_handle = self.task_handle.lock().unwrap();
 
-        let (tx, rx) = mpsc::channel(1);
+        let (tx, mut rx) = mpsc::channel(1);
         {
             let mut cmd_queue_opt = self.command_queue.lock().unwrap();
             debug_assert!(cmd_queue_opt.is_none());
@@ -561,74 +721,79 @@ impl RtspSrc {
         }
 
         let join_handle = RUNTIME.spawn(async move {
-            gst::info!(CAT, "Connecting to {url} ..");
-            let hostname_port =
-                format!("{}:{}", url.host_str().unwrap(), url.port().unwrap_or(554));
-
-            // TODO: Add TLS support
-            let s = match TcpStream::connect(hostname_port).await {
-                Ok(s) => s,
-                Err(err) => {
-                    gst::element_imp_error!(
-                        task_src,
-                        gst::ResourceError::OpenRead,
-                        ["Failed to connect to RTSP server: {err:#?}"]
-                    );
-                    return;
-                }
-            };
-            let _ = s.set_nodelay(true);
-
-            gst::info!(CAT, "Connected!");
-
-            let (read, write) = s.into_split();
-
-            let stream = Box::pin(super::tcp_message::async_read(read, MAX_MESSAGE_SIZE).fuse());
-            let sink = Box::pin(super::tcp_message::async_write(write));
-
-            let mut state = RtspTaskState::new(url, stream, sink);
-
-            let task_ret = task_src.rtsp_task(&mut state, rx).await;
-            gst::info!(CAT, "Exited rtsp_task");
-
-            // Cleanup after stopping
-            for h in &state.handles {
-                h.abort();
-            }
-            for h in state.handles {
-                let _ = h.await;
-            }
-            let obj = task_src.obj();
-            for e in obj.iterate_sorted() {
-                let Ok(e) = e else {
-                    continue;
-                };
-                if let Err(err) = e.set_state(gst::State::Null) {
-                    gst::warning!(CAT, "{} failed to go to Null state: {err:?}", e.name());
-                }
-            }
-            for pad in obj.src_pads() {
-                if let Err(err) = obj.remove_pad(&pad) {
-                    gst::warning!(CAT, "Failed to remove pad {}: {err:?}", pad.name());
-                }
-            }
-            for e in obj.iterate_sorted() {
-                let Ok(e) = e else {
-                    continue;
+            let mut playing = false;
+            loop {
+                gst::info!(CAT, "Connecting to {url} ..");
+                let hostname_port =
+                    format!("{}:{}", url.host_str().unwrap(), url.port().unwrap_or(554));
+
+                // TODO: Add TLS support
+                let s = match TcpStream::connect(hostname_port).await {
+                    Ok(s) => s,
+                    Err(err) => {
+                        gst::element_imp_error!(
+                            task_src,
+                            gst::ResourceError::OpenRead,
+                            ["Failed to connect to RTSP server: {err:#?}"]
+                        );
+                        return;
+                    }
                 };
-                if let Err(err) = obj.remove(&e) {
-                    gst::warning!(CAT, "Failed to remove element {}: {err:?}", e.name());
+                let _ = s.set_nodelay(true);
+
+                gst::info!(CAT, "Connected!");
+
+                let (read, write) = s.into_split();
+
+                let stream =
+                    Box::pin(super::tcp_message::async_read(read, MAX_MESSAGE_SIZE).fuse());
+                let sink = Box::pin(super::tcp_message::async_write(write));
+
+                let mut state = RtspTaskState::new(url.clone(), backchannel, stream, sink);
+                state.playing = playing;
+
+                let task_ret = task_src.rtsp_task(&mut state, &mut rx, &protocols).await;
+                gst::info!(CAT, "Exited rtsp_task");
+
+                playing = state.playing;
+                task_src.cleanup(state).await;
+
+                match task_ret {
+                    Err(err)
+                        if matches!(
+                            err.downcast_ref::<RtspError>(),
+                            Some(RtspError::UdpTimeout)
+                        ) =>
+                    {
+                        gst::warning!(CAT, "No data received over UDP, retrying with TCP");
+                        protocols = vec![RtspProtocol::Tcp];
+                        // Commands queued for the previous connection are stale now, except for
+                        // the ones that change the state of the whole element
+                        while let Ok(cmd) = rx.try_recv() {
+                            match cmd {
+                                Commands::Play => playing = true,
+                                Commands::Teardown(tx) => {
+                                    if let Some(tx) = tx {
+                                        let _ = tx.send(());
+                                    }
+                                    return;
+                                }
+                                Commands::Data(_) | Commands::UdpTimeout | Commands::Seek(_) => (),
+                            }
+                        }
+                    }
+                    // Post the element error after cleanup
+                    Err(err) => {
+                        gst::element_imp_error!(
+                            task_src,
+                            gst::CoreError::Failed,
+                            ["RTSP task exited: {err:#?}"]
+                        );
+                        break;
+                    }
+                    Ok(()) => break,
                 }
             }
-
-            // Post the element error after cleanup
-            if let Err(err) = task_ret {
-                gst::element_imp_error!(
-                    task_src,
-                    gst::CoreError::Failed,
-                    ["RTSP task exited: {err:#?}"]
-                );
-            }
             gst::info!(CAT, "Cleanup complete");
         });
 
@@ -702,7 +867,41 @@ impl RtspSrc {
         let templ = obj.pad_template("stream_%u").unwrap();
         let ghostpad = gst::GhostPad::builder_from_template(&templ)
             .name(format!("stream_{}", rtpsession_n))
+            .event_function(|pad, parent, event| {
+                RtspSrc::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |imp| imp.src_event(pad.upcast_ref(), event),
+                )
+            })
+            .query_function(|pad, parent, query| {
+                RtspSrc::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |imp| imp.src_query(pad.upcast_ref(), query),
+                )
+            })
             .build();
+        if self.vod.lock().unwrap().duration.is_some() {
+            ghostpad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, |pad, info| {
+                let Some(gst::PadProbeData::Event(ref ev)) = info.data else {
+                    return gst::PadProbeReturn::Ok;
+                };
+                let gst::EventView::Segment(ev) = ev.view() else {
+                    return gst::PadProbeReturn::Ok;
+                };
+                let Some(obj) = pad
+                    .parent()
+                    .and_then(|p| p.downcast::<super::RtspSrc>().ok())
+                else {
+                    return gst::PadProbeReturn::Ok;
+                };
+                if let Some(event) = obj.imp().vod_segment_event(ev) {
+                    info.data = Some(gst::PadProbeData::Event(event));
+                }
+                gst::PadProbeReturn::Ok
+            });
+        }
         gst::info!(CAT, "Adding ghost srcpad {}", ghostpad.name());
         obj.add_pad(&ghostpad)
             .expect("Adding a ghostpad should never fail");
@@ -710,6 +909,147 @@ impl RtspSrc {
         Ok(appsrc)
     }
 
+    fn src_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        match event.view() {
+            gst::EventView::Seek(seek) => self.handle_seek(seek),
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        match query.view_mut() {
+            gst::QueryViewMut::Duration(q) if q.format() == gst::Format::Time => {
+                let Some(duration) = self.vod.lock().unwrap().duration else {
+                    return gst::Pad::query_default(pad, Some(&*self.obj()), query);
+                };
+                q.set(duration);
+                true
+            }
+            gst::QueryViewMut::Seeking(q) if q.format() == gst::Format::Time => {
+                match self.vod.lock().unwrap().duration {
+                    Some(duration) => q.set(true, gst::ClockTime::ZERO, duration),
+                    None => q.set(false, gst::ClockTime::ZERO, gst::ClockTime::NONE),
+                }
+                true
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+
+    fn handle_seek(&self, seek: &gst::event::Seek) -> bool {
+        let (rate, flags, start_type, start, stop_type, stop) = seek.get();
+
+        let start: Option<gst::ClockTime> = match start.try_into() {
+            Ok(start) => start,
+            Err(_) => {
+                gst::error!(CAT, imp: self, "seek has invalid format");
+                return false;
+            }
+        };
+        let stop: Option<gst::ClockTime> = match stop.try_into() {
+            Ok(stop) => stop,
+            Err(_) => {
+                gst::error!(CAT, imp: self, "seek has invalid format");
+                return false;
+            }
+        };
+
+        if start_type == gst::SeekType::End || stop_type == gst::SeekType::End {
+            gst::error!(CAT, imp: self, "Relative seeks are not supported");
+            return false;
+        }
+
+        {
+            let mut vod = self.vod.lock().unwrap();
+            if vod.duration.is_none() {
+                gst::debug!(CAT, imp: self, "Can't seek in a live stream");
+                return false;
+            }
+            // The same seek will be sent upstream on each source pad
+            if vod.seek_seqnum == Some(seek.seqnum()) {
+                return true;
+            }
+            vod.seek_seqnum = Some(seek.seqnum());
+        }
+
+        let Some(cmd_queue) = self.command_queue.lock().unwrap().clone() else {
+            gst::error!(CAT, imp: self, "Can't seek, not started");
+            return false;
+        };
+
+        let flush = flags.contains(gst::SeekFlags::FLUSH);
+        if flush {
+            // Unblock the streaming threads right away, the flush is stopped once the new
+            // range has been requested from the server
+            let event = gst::event::FlushStart::builder()
+                .seqnum(seek.seqnum())
+                .build();
+            for appsrc in self.rtp_appsrcs() {
+                appsrc.send_event(event.clone());
+            }
+        }
+
+        let params = SeekParams {
+            rate,
+            flush,
+            start: if start_type == gst::SeekType::Set {
+                start
+            } else {
+                None
+            },
+            stop: if stop_type == gst::SeekType::Set {
+                stop
+            } else {
+                None
+            },
+            seqnum: seek.seqnum(),
+        };
+        gst::debug!(CAT, imp: self, "Seeking: {params:?}");
+        RUNTIME.spawn(async move { cmd_queue.send(Commands::Seek(params)).await });
+
+        true
+    }
+
+    fn rtp_appsrcs(&self) -> Vec<gst_app::AppSrc> {
+        self.obj()
+            .iterate_elements()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.name().starts_with("rtp_appsrc_"))
+            .filter_map(|e| e.downcast::<gst_app::AppSrc>().ok())
+            .collect()
+    }
+
+    fn vod_segment_event(&self, ev: &gst::event::Segment) -> Option<gst::Event> {
+        let mut segment = ev.segment().downcast_ref::<gst::ClockTime>()?.clone();
+        let vod = self.vod.lock().unwrap();
+        segment.set_time(vod.position);
+        segment.set_applied_rate(vod.applied_rate);
+        segment.set_duration(vod.duration);
+        Some(
+            gst::event::Segment::builder(&segment)
+                .seqnum(vod.seek_seqnum.unwrap_or_else(|| ev.seqnum()))
+                .build(),
+        )
+    }
+
+    fn update_vod_position(&self, rsp: &Response<Body>) {
+        let mut vod = self.vod.lock().unwrap();
+        if let Some((Some(start), _)) = rsp
+            .header(&RANGE)
+            .and_then(|v| sdp::parse_npt_range(v.as_str()))
+        {
+            vod.position = start;
+        }
+        if let Some(scale) = rsp
+            .header(&SCALE)
+            .and_then(|v| v.as_str().trim().parse::<f64>().ok())
+        {
+            vod.applied_rate = scale;
+        }
+        gst::debug!(CAT, imp: self, "VOD position {} applied rate {}", vod.position, vod.applied_rate);
+    }
+
     fn make_rtcp_appsrc(
         &self,
         rtpsession_n: usize,
@@ -765,6 +1105,60 @@ impl RtspSrc {
         Ok(())
     }
 
+    fn make_backchannel_appsink<
+        F: FnMut(&gst_app::AppSink) -> Result<gst::FlowSuccess, gst::FlowError> + Send + 'static,
+    >(
+        &self,
+        rtpsession_n: usize,
+        caps: &gst::Caps,
+        on_sample: F,
+    ) -> Result<()> {
+        // Only keep the fields that a payloader can produce, the SDP attributes would prevent
+        // negotiation otherwise
+        let s = caps.structure(0).unwrap();
+        let mut b = gst::Structure::builder("application/x-rtp");
+        for field in [
+            "media",
+            "payload",
+            "clock-rate",
+            "encoding-name",
+            "encoding-params",
+        ] {
+            if let Ok(v) = s.value(field) {
+                b = b.field(field, v.clone());
+            }
+        }
+        let caps = gst::Caps::from(b.build());
+
+        let cbs = gst_app::app_sink::AppSinkCallbacks::builder()
+            .new_sample(on_sample)
+            .build();
+
+        let appsink = gst_app::AppSink::builder()
+            .name(format!("backchannel_appsink_{rtpsession_n}"))
+            .caps(&caps)
+            .sync(false)
+            .async_(false)
+            .callbacks(cbs)
+            .build();
+        let obj = self.obj();
+        obj.add(&appsink)?;
+        let templ = obj.pad_template("backchannel_%u").unwrap();
+        let ghostpad = gst::GhostPad::builder_from_template(&templ)
+            .name(format!("backchannel_{}", rtpsession_n))
+            .build();
+        ghostpad.set_target(Some(&appsink.static_pad("sink").unwrap()))?;
+        gst::info!(
+            CAT,
+            "Adding ghost sinkpad {} with caps {caps}",
+            ghostpad.name()
+        );
+        obj.add_pad(&ghostpad)
+            .expect("Adding a ghostpad should never fail");
+        appsink.sync_state_with_parent()?;
+        Ok(())
+    }
+
     fn post_start(&self, code: &str, text: &str) {
         let obj = self.obj();
         let msg = gst::message::Progress::builder(gst::ProgressType::Start, code, text)
@@ -789,10 +1183,42 @@ impl RtspSrc {
         let _ = obj.post_message(msg);
     }
 
+    async fn cleanup(&self, state: RtspTaskState) {
+        for h in &state.handles {
+            h.abort();
+        }
+        for h in state.handles {
+            let _ = h.await;
+        }
+        let obj = self.obj();
+        for e in obj.iterate_sorted() {
+            let Ok(e) = e else {
+                continue;
+            };
+            if let Err(err) = e.set_state(gst::State::Null) {
+                gst::warning!(CAT, "{} failed to go to Null state: {err:?}", e.name());
+            }
+        }
+        for pad in obj.pads() {
+            if let Err(err) = obj.remove_pad(&pad) {
+                gst::warning!(CAT, "Failed to remove pad {}: {err:?}", pad.name());
+            }
+        }
+        for e in obj.iterate_sorted() {
+            let Ok(e) = e else {
+                continue;
+            };
+            if let Err(err) = obj.remove(&e) {
+                gst::warning!(CAT, "Failed to remove element {}: {err:?}", e.name());
+            }
+        }
+    }
+
     async fn rtsp_task(
         &self,
         state: &mut RtspTaskState,
-        mut cmd_rx: mpsc::Receiver<Commands>,
+        cmd_rx: &mut mpsc::Receiver<Commands>,
+        protocols: &[RtspProtocol],
     ) -> Result<()> {
         let cmd_tx = self.cmd_queue();
 
@@ -811,11 +1237,13 @@ impl RtspSrc {
                 .setup(
                     &mut session,
                     settings.port_start,
-                    &settings.protocols,
+                    settings.port_end,
+                    protocols,
                     TransportMode::Play,
                 )
                 .await?
         };
+        self.vod.lock().unwrap().duration = state.duration;
         let manager = RtspManager::new(std::env::var("USE_RTP2").is_ok_and(|s| s == "1"));
 
         let obj = self.obj();
@@ -823,8 +1251,70 @@ impl RtspSrc {
             .add_to(obj.upcast_ref::<gst::Bin>())
             .expect("Adding the manager cannot fail");
 
+        // Only fall back to TCP if we were also trying UDP, and TCP is allowed
+        let tcp_fallback = protocols.contains(&RtspProtocol::Tcp)
+            && protocols.iter().any(|p| *p != RtspProtocol::Tcp);
+
         let mut tcp_interleave_appsrcs = HashMap::new();
         for (rtpsession_n, p) in state.setup_params.iter_mut().enumerate() {
+            if p.backchannel {
+                match &mut p.transport {
+                    RtspTransportInfo::Udp {
+                        source,
+                        server_port,
+                        client_port: _,
+                        sockets,
+                    } => {
+                        let Some((rtp_socket, _)) = sockets.take() else {
+                            gst::warning!(
+                                CAT,
+                                "Skipping backchannel: no UDP sockets for {rtpsession_n}: {:#?}",
+                                p.transport
+                            );
+                            continue;
+                        };
+                        let dest = match (source, server_port) {
+                            (Some(ip), Some((rtp_port, _))) => {
+                                ip.parse().ok().map(|ip| SocketAddr::new(ip, *rtp_port))
+                            }
+                            _ => None,
+                        };
+                        let Some(dest) = dest else {
+                            gst::warning!(
+                                CAT,
+                                "Skipping backchannel: no server port for {rtpsession_n}: {:#?}",
+                                p.transport
+                            );
+                            continue;
+                        };
+
+                        let (tx, rx) = mpsc::channel(BACKCHANNEL_QUEUE_SIZE);
+                        self.make_backchannel_appsink(rtpsession_n, &p.caps, move |appsink| {
+                            on_backchannel_udp(appsink, tx.clone())
+                        })?;
+                        state.handles.push(RUNTIME.spawn(async move {
+                            udp_backchannel_task(&rtp_socket, dest, rx).await
+                        }));
+                    }
+                    RtspTransportInfo::Tcp {
+                        channels: (rtp_channel, _),
+                    } => {
+                        let rtp_channel = *rtp_channel;
+                        let cmd_tx = cmd_tx.clone();
+                        self.make_backchannel_appsink(rtpsession_n, &p.caps, move |appsink| {
+                            on_data_tcp(appsink, cmd_tx.clone(), rtp_channel)
+                        })?;
+                    }
+                    RtspTransportInfo::UdpMulticast { .. } => {
+                        gst::warning!(
+                            CAT,
+                            "Skipping backchannel {rtpsession_n}: multicast is not supported"
+                        );
+                    }
+                }
+                continue;
+            }
+
             let (tx, rx) = mpsc::channel(1);
             let on_rtcp = move |appsink: &_| on_rtcp_udp(appsink, tx.clone());
             match &mut p.transport {
@@ -885,6 +1375,7 @@ impl RtspSrc {
                     let rtp_appsrc = self.make_rtp_appsrc(rtpsession_n, &p.caps, &manager)?;
                     p.rtp_appsrc = Some(rtp_appsrc.clone());
                     // Spawn RTP udp receive task
+                    let fallback_tx = tcp_fallback.then(|| cmd_tx.clone());
                     state.handles.push(RUNTIME.spawn(async move {
                         udp_rtp_task(
                             &rtp_socket,
@@ -892,6 +1383,7 @@ impl RtspSrc {
                             settings.timeout,
                             settings.receive_mtu,
                             None,
+                            fallback_tx,
                         )
                         .await
                     }));
@@ -934,9 +1426,19 @@ impl RtspSrc {
                         _ => (None, None),
                     };
 
+                    if settings.nat_method == RtspNatMethod::Dummy {
+                        if let Some(addr) = rtp_sender_addr {
+                            send_dummy_packet(&rtp_socket, &DUMMY_RTP_PACKET, addr).await;
+                        }
+                        if let (Some(socket), Some(addr)) = (&rtcp_socket, rtcp_sender_addr) {
+                            send_dummy_packet(socket, &DUMMY_RTCP_PACKET, addr).await;
+                        }
+                    }
+
                     // Spawn RTP udp receive task
                     let rtp_appsrc = self.make_rtp_appsrc(rtpsession_n, &p.caps, &manager)?;
                     p.rtp_appsrc = Some(rtp_appsrc.clone());
+                    let fallback_tx = tcp_fallback.then(|| cmd_tx.clone());
                     state.handles.push(RUNTIME.spawn(async move {
                         udp_rtp_task(
                             &rtp_socket,
@@ -944,6 +1446,7 @@ impl RtspSrc {
                             settings.timeout,
                             settings.receive_mtu,
                             rtp_sender_addr,
+                            fallback_tx,
                         )
                         .await
                     }));
@@ -973,7 +1476,7 @@ impl RtspSrc {
                         let rtcp_channel = *rtcp_channel;
                         let cmd_tx = cmd_tx.clone();
                         self.make_rtcp_appsink(rtpsession_n, &manager, move |appsink| {
-                            on_rtcp_tcp(appsink, cmd_tx.clone(), rtcp_channel)
+                            on_data_tcp(appsink, cmd_tx.clone(), rtcp_channel)
                         })?;
                     }
                 }
@@ -1028,7 +1531,20 @@ impl RtspSrc {
             }
         });
 
-        let mut expected_response: Option<(Method, u32)> = None;
+        // Responses arrive in the same order as the requests were sent
+        let mut expected_responses: VecDeque<(Method, u32)> = VecDeque::new();
+        if state.playing {
+            // We reconnected after having already started playback
+            let Some(s) = &session else {
+                return Err(RtspError::InvalidMessage("Can't PLAY, no SETUP").into());
+            };
+            self.post_start("request", "PLAY request sent");
+            let cseq = state.play(s).await.map_err(|err| {
+                self.post_cancelled("request", "PLAY request cancelled");
+                err
+            })?;
+            expected_responses.push_back((Method::Play, cseq));
+        }
         loop {
             tokio::select! {
                 msg = state.stream.next() => match msg {
@@ -1061,7 +1577,7 @@ impl RtspSrc {
                     }
                     Some(Ok(rtsp_types::Message::Response(rsp))) => {
                         gst::debug!(CAT, "<-- {rsp:#?}");
-                        let Some((expected, cseq)) = &expected_response else {
+                        let Some((expected, cseq)) = expected_responses.pop_front() else {
                             continue;
                         };
                         let Some(s) = &session else {
@@ -1069,10 +1585,12 @@ impl RtspSrc {
                         };
                         match expected {
                             Method::Play => {
-                                state.play_response(&rsp, *cseq, s).await?;
+                                state.play_response(&rsp, cseq, s).await?;
+                                self.update_vod_position(&rsp);
                                 self.post_complete("request", "PLAY response received");
                             }
-                            Method::Teardown => state.teardown_response(&rsp, *cseq, s).await?,
+                            Method::Pause => state.pause_response(&rsp, cseq, s).await?,
+                            Method::Teardown => state.teardown_response(&rsp, cseq, s).await?,
                             m => unreachable!("BUG: unexpected response method: {m:?}"),
                         };
                     }
@@ -1089,6 +1607,7 @@ impl RtspSrc {
                 },
                 Some(cmd) = cmd_rx.recv() => match cmd {
                     Commands::Play => {
+                        state.playing = true;
                         let Some(s) = &session else {
                             return Err(RtspError::InvalidMessage("Can't PLAY, no SETUP").into());
                         };
@@ -1097,7 +1616,7 @@ impl RtspSrc {
                             self.post_cancelled("request", "PLAY request cancelled");
                             err
                         })?;
-                        expected_response = Some((Method::Play, cseq));
+                        expected_responses.push_back((Method::Play, cseq));
                     },
                     Commands::Teardown(tx) => {
                         gst::info!(CAT, "Received Teardown command");
@@ -1111,10 +1630,50 @@ impl RtspSrc {
                         break;
                     }
                     Commands::Data(data) => {
-                        // We currently only send RTCP RR as data messages, this will change when
-                        // we support TCP ONVIF backchannels
+                        // RTCP RR and backchannel RTP packets
+                        let channel_id = data.channel_id();
                         state.sink.send(Message::Data(data)).await?;
-                        gst::debug!(CAT, "Sent RTCP RR over TCP");
+                        gst::trace!(CAT, "Sent data over TCP on channel {channel_id}");
+                    }
+                    Commands::UdpTimeout => {
+                        gst::info!(CAT, "Received UdpTimeout command, tearing down");
+                        if let Some(s) = &session {
+                            let _ = state.teardown(s).await;
+                        }
+                        return Err(RtspError::UdpTimeout.into());
+                    }
+                    Commands::Seek(params) => {
+                        let Some(s) = &session else {
+                            return Err(RtspError::InvalidMessage("Can't seek, no SETUP").into());
+                        };
+                        state.range = (params.start, params.stop);
+                        state.scale = params.rate;
+                        {
+                            let mut vod = self.vod.lock().unwrap();
+                            vod.position = params.start.unwrap_or(vod.position);
+                            vod.applied_rate = params.rate;
+                        }
+                        if params.flush {
+                            let event = gst::event::FlushStop::builder(true)
+                                .seqnum(params.seqnum)
+                                .build();
+                            for appsrc in state.setup_params.iter().filter_map(|p| p.rtp_appsrc.as_ref()) {
+                                appsrc.send_event(event.clone());
+                            }
+                        }
+                        // Otherwise the new range is requested with the next PLAY
+                        if state.playing {
+                            // A PLAY received while playing is queued by the server until the
+                            // current range has been played, so pause first
+                            let cseq = state.pause(s).await?;
+                            expected_responses.push_back((Method::Pause, cseq));
+                            self.post_start("request", "PLAY request sent");
+                            let cseq = state.play(s).await.map_err(|err| {
+                                self.post_cancelled("request", "PLAY request cancelled");
+                                err
+                            })?;
+                            expected_responses.push_back((Method::Play, cseq));
+                        }
                     }
                 },
                 else => {
@@ -1219,6 +1778,7 @@ struct RtspTaskState {
     cseq: u32,
     url: Url,
     version: Version,
+    backchannel: RtspBackchannel,
     content_base_or_location: Option<String>,
     aggregate_control: Option<Url>,
     sdp: Option<sdp_types::Session>,
@@ -1229,6 +1789,13 @@ struct RtspTaskState {
 
     setup_params: Vec<RtspSetupParams>,
     handles: Vec<JoinHandle<()>>,
+    // PLAY was requested by the element
+    playing: bool,
+    // From the SDP, only known for VOD
+    duration: Option<gst::ClockTime>,
+    // Range and scale requested by the last seek
+    range: (Option<gst::ClockTime>, Option<gst::ClockTime>),
+    scale: f64,
 }
 
 struct RtspSetupParams {
@@ -1236,14 +1803,17 @@ struct RtspSetupParams {
     transport: RtspTransportInfo,
     rtp_appsrc: Option<gst_app::AppSrc>,
     caps: gst::Caps,
+    // Stream sent from us to the server
+    backchannel: bool,
 }
 
 impl RtspTaskState {
-    fn new(url: Url, stream: RtspStream, sink: RtspSink) -> Self {
+    fn new(url: Url, backchannel: RtspBackchannel, stream: RtspStream, sink: RtspSink) -> Self {
         RtspTaskState {
             cseq: 0u32,
             url,
             version: Version::V1_0,
+            backchannel,
             content_base_or_location: None,
             aggregate_control: None,
             sdp: None,
@@ -1251,6 +1821,10 @@ impl RtspTaskState {
             sink,
             setup_params: Vec::new(),
             handles: Vec::new(),
+            playing: false,
+            duration: None,
+            range: (None, None),
+            scale: 1.0,
         }
     }
 
@@ -1363,8 +1937,13 @@ impl RtspTaskState {
             .typed_header::<CSeq>(&self.cseq.into())
             .header(USER_AGENT, DEFAULT_USER_AGENT)
             .header(ACCEPT, "application/sdp")
-            .request_uri(self.url.clone())
-            .build(Body::default());
+            .request_uri(self.url.clone());
+        let req = if self.backchannel == RtspBackchannel::Onvif {
+            req.header(REQUIRE, ONVIF_BACKCHANNEL_REQUIRE)
+        } else {
+            req
+        };
+        let req = req.build(Body::default());
 
         gst::debug!(CAT, "-->> {req:#?}");
         self.sink.send(req.into()).await?;
@@ -1451,6 +2030,7 @@ impl RtspTaskState {
         &mut self,
         session: &mut Option<Session>,
         port_start: u16,
+        port_end: u16,
         protocols: &[RtspProtocol],
         mode: TransportMode,
     ) -> Result<Vec<RtspSetupParams>, RtspError> {
@@ -1468,7 +2048,25 @@ impl RtspTaskState {
             .and_then(|v| sdp::parse_control_path(v, &base));
         let mut b = gst::Structure::builder("application/x-rtp");
 
-        // TODO: parse range for VOD
+        let range = sdp
+            .get_first_attribute_value("range")
+            .ok()
+            .flatten()
+            .or_else(|| {
+                sdp.medias
+                    .iter()
+                    .find_map(|m| m.get_first_attribute_value("range").ok().flatten())
+            });
+        // Live streams have an open range, e.g. npt=now- or npt=0-
+        self.duration = range
+            .and_then(sdp::parse_npt_range)
+            .and_then(|(_, end)| end);
+        gst::debug!(
+            CAT,
+            "Presentation range {range:?}, duration {:?}",
+            self.duration
+        );
+
         let skip_attrs = ["control", "range"];
         for sdp_types::Attribute { attribute, value } in &sdp.attributes {
             if skip_attrs.contains(&attribute.as_str()) {
@@ -1487,6 +2085,8 @@ impl RtspTaskState {
             .filter(|c| !c.is_empty())
             .unwrap_or_else(|| base.host_str().unwrap());
         let mut port_next = port_start;
+        // The end of the range is only meaningful if we're not letting the OS pick the ports
+        let port_end = if port_start == 0 { 0 } else { port_end };
         let mut stream_num = 0;
         let mut setup_params: Vec<RtspSetupParams> = Vec::new();
         for m in &sdp.medias {
@@ -1532,18 +2132,41 @@ impl RtspTaskState {
                 continue;
             }
 
+            // The server marks the media we're supposed to send to it as sendonly
+            let backchannel = m.attributes.iter().any(|a| a.attribute == "sendonly");
+            if backchannel {
+                if self.backchannel == RtspBackchannel::None {
+                    gst::info!(CAT, "Ignoring sendonly media {} {}", m.media, m.fmt);
+                    continue;
+                }
+                let encoding_name = s.get::<&str>("encoding-name").unwrap_or_default();
+                if media != "audio" || !BACKCHANNEL_ENCODINGS.contains(&encoding_name) {
+                    gst::warning!(
+                        CAT,
+                        "Ignoring unsupported backchannel media {} {}: {encoding_name}",
+                        m.media,
+                        m.fmt
+                    );
+                    continue;
+                }
+            }
+
             // SETUP
             let mut rtp_socket: Option<UdpSocket> = None;
             let mut rtcp_socket: Option<UdpSocket> = None;
             let mut transports = Vec::new();
             let (conn_protocols, is_ipv4) = sdp::parse_connections(&m.connections);
 
-            let protocols = if !conn_protocols.is_empty() {
+            let mut protocols = if !conn_protocols.is_empty() {
                 let p = protocols.iter().cloned().collect::<BTreeSet<_>>();
                 p.intersection(&conn_protocols).cloned().collect::<Vec<_>>()
             } else {
                 protocols.to_owned()
             };
+            if backchannel {
+                // We need a unicast destination to send to
+                protocols.retain(|p| *p != RtspProtocol::UdpMulticast);
+            }
 
             if protocols.is_empty() {
                 gst::error!(CAT, "No available protocols left, skipping media");
@@ -1563,10 +2186,14 @@ impl RtspTaskState {
                 }));
             }
             if protocols.contains(&RtspProtocol::Udp) {
-                let (sock1, rtp_port) = bind_start_port(port_next, is_ipv4).await;
+                let (sock1, rtp_port) = bind_start_port(port_next, port_end, is_ipv4).await?;
                 // Get the actual port that was successfully bound
                 port_next = rtp_port;
-                let (sock2, rtcp_port) = bind_start_port(rtp_port + 1, is_ipv4).await;
+                let rtcp_port_start = rtp_port.checked_add(1).ok_or_else(|| {
+                    RtspError::Fatal(format!("No port left for RTCP after {rtp_port}"))
+                })?;
+                let (sock2, rtcp_port) =
+                    bind_start_port(rtcp_port_start, port_end, is_ipv4).await?;
                 rtp_socket = Some(sock1);
                 rtcp_socket = Some(sock2);
                 let params = RtpTransportParameters {
@@ -1607,6 +2234,11 @@ impl RtspTaskState {
             } else {
                 req
             };
+            let req = if self.backchannel == RtspBackchannel::Onvif {
+                req.header(REQUIRE, ONVIF_BACKCHANNEL_REQUIRE)
+            } else {
+                req
+            };
             let req = req.build(Body::default());
             let cseq = self.cseq;
 
@@ -1703,6 +2335,7 @@ impl RtspTaskState {
                 transport: parsed_transport,
                 rtp_appsrc: None,
                 caps,
+                backchannel,
             });
         }
         Ok(setup_params)
@@ -1713,10 +2346,25 @@ impl RtspTaskState {
         let request_uri = self.aggregate_control.as_ref().unwrap_or(&self.url).clone();
         let req = Request::builder(Method::Play, self.version)
             .typed_header::<CSeq>(&self.cseq.into())
-            .typed_header::<Range>(&Range::Npt(NptRange::From(NptTime::Now)))
             .header(USER_AGENT, DEFAULT_USER_AGENT)
             .request_uri(request_uri)
             .typed_header::<Session>(session);
+        let req = match self.range {
+            (None, None) => req.typed_header::<Range>(&Range::Npt(NptRange::From(NptTime::Now))),
+            (start, stop) => req.header(
+                RANGE,
+                format!(
+                    "npt={}-{}",
+                    start.map_or_else(|| "now".to_string(), sdp::format_npt_time),
+                    stop.map(sdp::format_npt_time).unwrap_or_default()
+                ),
+            ),
+        };
+        let req = if self.scale != 1.0 {
+            req.header(SCALE, self.scale.to_string())
+        } else {
+            req
+        };
 
         let req = req.build(Body::default());
         gst::debug!(CAT, "-->> {req:#?}");
@@ -1758,6 +2406,31 @@ impl RtspTaskState {
         Ok(())
     }
 
+    async fn pause(&mut self, session: &Session) -> Result<u32, RtspError> {
+        self.cseq += 1;
+        let request_uri = self.aggregate_control.as_ref().unwrap_or(&self.url).clone();
+        let req = Request::builder(Method::Pause, self.version)
+            .typed_header::<CSeq>(&self.cseq.into())
+            .header(USER_AGENT, DEFAULT_USER_AGENT)
+            .request_uri(request_uri)
+            .typed_header::<Session>(session);
+
+        let req = req.build(Body::default());
+        gst::debug!(CAT, "-->> {req:#?}");
+        self.sink.send(req.into()).await?;
+        Ok(self.cseq)
+    }
+
+    async fn pause_response(
+        &mut self,
+        rsp: &Response<Body>,
+        cseq: u32,
+        session: &Session,
+    ) -> Result<(), RtspError> {
+        Self::check_response(rsp, cseq, Method::Pause, Some(session))?;
+        Ok(())
+    }
+
     async fn teardown(&mut self, session: &Session) -> Result<u32, RtspError> {
         self.cseq += 1;
         let request_uri = self.aggregate_control.as_ref().unwrap_or(&self.url).clone();
@@ -1811,31 +2484,49 @@ fn bind_port(port: u16, is_ipv4: bool) -> Result<UdpSocket, std::io::Error> {
     UdpSocket::from_std(sock.into())
 }
 
-async fn bind_start_port(port: u16, is_ipv4: bool) -> (UdpSocket, u16) {
+async fn bind_start_port(
+    port: u16,
+    port_end: u16,
+    is_ipv4: bool,
+) -> Result<(UdpSocket, u16), RtspError> {
+    // Without an explicit end of the range, don't try forever doing a hot-loop
+    let last_port = if port_end != 0 {
+        port_end
+    } else {
+        port.saturating_add(MAX_BIND_PORT_RETRY)
+    };
     let mut next_port = port;
     loop {
         match bind_port(next_port, is_ipv4) {
             Ok(socket) => {
                 if next_port != 0 {
-                    return (socket, next_port);
+                    return Ok((socket, next_port));
                 }
                 let addr = socket
                     .local_addr()
                     .expect("Newly-bound port should not fail");
-                return (socket, addr.port());
+                return Ok((socket, addr.port()));
             }
             Err(err) => {
                 gst::debug!(CAT, "Failed to bind to {next_port}: {err:?}, trying next");
-                next_port += 1;
-                // If we fail too much, panic instead of forever doing a hot-loop
-                if (next_port - MAX_BIND_PORT_RETRY) > port {
-                    panic!("Failed to allocate any ports from {port} to {next_port}");
+                if next_port == 0 || next_port >= last_port {
+                    return Err(RtspError::Fatal(format!(
+                        "Failed to allocate any ports from {port} to {next_port}"
+                    )));
                 }
+                next_port += 1;
             }
         };
     }
 }
 
+async fn send_dummy_packet(socket: &UdpSocket, data: &[u8], addr: SocketAddr) {
+    match socket.send_to(data, addr).await {
+        Ok(_) => gst::debug!(CAT, "Sent dummy packet to {addr:?}"),
+        Err(err) => gst::warning!(CAT, "Failed to send dummy packet to {addr:?}: {err:?}"),
+    }
+}
+
 fn on_rtcp_udp(
     appsink: &gst_app::AppSink,
     tx: mpsc::Sender<MappedBuffer<Readable>>,
@@ -1863,10 +2554,38 @@ fn on_rtcp_udp(
     }
 }
 
-fn on_rtcp_tcp(
+fn on_backchannel_udp(
+    appsink: &gst_app::AppSink,
+    tx: mpsc::Sender<MappedBuffer<Readable>>,
+) -> Result<gst::FlowSuccess, gst::FlowError> {
+    let Ok(sample) = appsink.pull_sample() else {
+        return Err(gst::FlowError::Error);
+    };
+    let Some(buffer) = sample.buffer_owned() else {
+        return Ok(gst::FlowSuccess::Ok);
+    };
+    let map = buffer.into_mapped_buffer_readable();
+    match map {
+        Ok(map) => match tx.try_send(map) {
+            Ok(_) => Ok(gst::FlowSuccess::Ok),
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                // Audio is real-time, rather drop than block the upstream pipeline
+                gst::warning!(CAT, "Backchannel send queue is full, dropping packet");
+                Ok(gst::FlowSuccess::Ok)
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(gst::FlowError::Eos),
+        },
+        Err(err) => {
+            gst::error!(CAT, "Failed to map buffer: {err:?}");
+            Err(gst::FlowError::Error)
+        }
+    }
+}
+
+fn on_data_tcp(
     appsink: &gst_app::AppSink,
     cmd_tx: mpsc::Sender<Commands>,
-    rtcp_channel: u8,
+    channel: u8,
 ) -> Result<gst::FlowSuccess, gst::FlowError> {
     let Ok(sample) = appsink.pull_sample() else {
         return Err(gst::FlowError::Error);
@@ -1877,8 +2596,7 @@ fn on_rtcp_tcp(
     let map = buffer.into_mapped_buffer_readable();
     match map {
         Ok(map) => {
-            let data: rtsp_types::Data<Body> =
-                rtsp_types::Data::new(rtcp_channel, Body::mapped(map));
+            let data: rtsp_types::Data<Body> = rtsp_types::Data::new(channel, Body::mapped(map));
             let cmd_tx = cmd_tx.clone();
             RUNTIME.spawn(async move { cmd_tx.send(Commands::Data(data)).await });
             Ok(gst::FlowSuccess::Ok)
@@ -1896,6 +2614,7 @@ async fn udp_rtp_task(
     timeout: gst::ClockTime,
     receive_mtu: u32,
     sender_addr: Option<SocketAddr>,
+    fallback_tx: Option<mpsc::Sender<Commands>>,
 ) {
     let t = Duration::from_secs(timeout.into());
     let sender_addr = match sender_addr {
@@ -1906,11 +2625,18 @@ async fn udp_rtp_task(
         None => {
             let ret = match time::timeout(t, socket.peek_sender()).await {
                 Ok(Ok(addr)) => Ok(addr),
-                Ok(Err(_elapsed)) => Err(format!(
-                    "No data after {} seconds, exiting",
-                    timeout.seconds()
-                )),
-                Err(err) => Err(format!("UDP socket was closed: {err:?}")),
+                Ok(Err(err)) => Err(format!("UDP socket was closed: {err:?}")),
+                Err(_elapsed) => {
+                    if let Some(tx) = fallback_tx {
+                        gst::warning!(CAT, "No data after {} seconds", timeout.seconds());
+                        let _ = tx.send(Commands::UdpTimeout).await;
+                        return;
+                    }
+                    Err(format!(
+                        "No data after {} seconds, exiting",
+                        timeout.seconds()
+                    ))
+                }
             };
             match ret {
                 Ok(addr) => addr,
@@ -1938,6 +2664,7 @@ async fn udp_rtp_task(
     config.set_params(caps.as_ref(), size, 2, 0);
     pool.set_config(config).unwrap();
     pool.set_active(true).unwrap();
+    let mut received = false;
     let error = loop {
         let Ok(buffer) = pool.acquire_buffer(None) else {
             break "Failed to acquire buffer".to_string();
@@ -1951,6 +2678,7 @@ async fn udp_rtp_task(
                 if addr != sender_addr {
                     continue;
                 }
+                received = true;
                 if size < UDP_PACKET_MAX_SIZE && len == size as usize {
                     gst::warning!(
                         CAT,
@@ -1979,10 +2707,16 @@ async fn udp_rtp_task(
                     break format!("UDP buffer push failed: {err:?}");
                 }
             }
-            Ok(Err(_elapsed)) => {
-                break format!("No data after {} seconds, exiting", timeout.seconds())
+            Ok(Err(err)) => break format!("UDP socket was closed: {err:?}"),
+            Err(_elapsed) => {
+                // If nothing ever arrived, UDP is probably being blocked somewhere on the way
+                if let (false, Some(tx)) = (received, &fallback_tx) {
+                    gst::warning!(CAT, "No data after {} seconds", timeout.seconds());
+                    let _ = tx.send(Commands::UdpTimeout).await;
+                    return;
+                }
+                break format!("No data after {} seconds, exiting", timeout.seconds());
             }
-            Err(err) => break format!("UDP socket was closed: {err:?}"),
         };
     };
     gst::element_error!(
@@ -2060,6 +2794,22 @@ async fn udp_rtcp_task(
     );
 }
 
+async fn udp_backchannel_task(
+    socket: &UdpSocket,
+    dest: SocketAddr,
+    mut rx: mpsc::Receiver<MappedBuffer<Readable>>,
+) {
+    gst::info!(CAT, "Sending backchannel to address {dest:?}");
+    while let Some(data) = rx.recv().await {
+        if let Err(err) = socket.send_to(data.as_ref(), dest).await {
+            gst::error!(CAT, "Backchannel send error: {err:?}, stopping task");
+            break;
+        }
+        gst::trace!(CAT, "Sent backchannel RTP packet");
+    }
+    rx.close();
+}
+
 #[glib::object_subclass]
 impl ObjectSubclass for RtspSrc {
     const NAME: &'static str = "GstRtspSrc2";
@@ -21,14 +21,52 @@
  * * RTCP-based A/V sync
  * * Lower transport selection and priority (NEW!)
  *   - Also supports different lower transports for each SETUP
+ * * ONVIF audio backchannel (G.711 and AAC)
+ * * NAT hole punching and automatic fallback to TCP when nothing is received over UDP
+ * * Seeking and rate changes with VOD
  *
  * Some missing features:
  * * SET_PARAMETER/GET_PARAMETER messages
  * * SRTP support
- * * VOD support: PAUSE, seeking, etc
- * * ONVIF backchannel and trick mode support
+ * * VOD support: PAUSE, etc
+ * * ONVIF trick mode support
  * * and more
  *
+ * ## ONVIF backchannel
+ *
+ * When the `backchannel` property is set to `onvif`, the ONVIF backchannel is requested from the
+ * server and a `backchannel_%u` sink pad is added for each backchannel media announced by the
+ * server. RTP packets pushed into that pad are sent back to the server over the transport that
+ * was negotiated for it, e.g. to play back audio on a camera's speaker.
+ *
+ * The caps of the sink pad describe the payload the server expects, so the audio should be
+ * encoded and payloaded accordingly, e.g.
+ *
+ * |[
+ * gst-launch-1.0 rtspsrc2 name=src location=rtsp://camera.local/onvif backchannel=onvif \
+ *     src. ! decodebin3 ! autovideosink \
+ *     audiotestsrc is-live=true ! mulawenc ! rtppcmupay ! src.backchannel_1
+ * ]|
+ *
+ * ## NAT traversal
+ *
+ * When receiving over UDP, by default a dummy RTP and RTCP packet is sent to the server ports
+ * after SETUP to open a pinhole in NATs and firewalls (`nat-method=dummy`). The client ports can
+ * be restricted to the range forwarded by a firewall with the `port-start` and `port-end`
+ * properties.
+ *
+ * If no data is received over UDP before `timeout` expires and `tcp` is one of the allowed
+ * `protocols`, the session is torn down and set up again using TCP interleaved transport. The
+ * source pads are removed and added again in that case.
+ *
+ * ## Seeking
+ *
+ * Recorded streams, e.g. from an NVR, announce the duration of the presentation in the `range`
+ * attribute of the SDP. Such streams can be seeked in `GST_FORMAT_TIME`, which is done with a
+ * `PLAY` request with the corresponding `Range` header. The seek rate is requested from the server
+ * with the `Scale` header, and the rate applied by the server is reported in the `applied-rate` of
+ * the segment. Live streams are not seekable.
+ *
  * Please see the [README](https://gitlab.freedesktop.org/gstreamer/gst-plugins-rs/-/blob/main/net/rtsp/README.md)
  * for a complete and up-to-date list.
  */
@@ -46,6 +84,11 @@ glib::wrapper! {
 }
 
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    imp::RtspBackchannel::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    #[cfg(feature = "doc")]
+    imp::RtspNatMethod::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
     gst::Element::register(
         Some(plugin),
         "rtspsrc2",
@@ -429,3 +429,47 @@ pub fn parse_connections(conns: &Vec<Connection>) -> (BTreeSet<RtspProtocol>, bo
     }
     (conn_protocols, is_ipv4)
 }
+
+// https://www.rfc-editor.org/rfc/rfc2326.html#section-3.6
+fn parse_npt_time(npt: &str) -> Option<gst::ClockTime> {
+    let npt = npt.trim();
+    let (hms, frac) = match npt.split_once('.') {
+        Some((hms, frac)) => (hms, frac),
+        None => (npt, ""),
+    };
+    let mut secs = 0u64;
+    for part in hms.split(':') {
+        secs = secs
+            .checked_mul(60)?
+            .checked_add(part.parse::<u64>().ok()?)?;
+    }
+    let nsecs = if frac.is_empty() {
+        0
+    } else {
+        let digits = &frac[..frac.len().min(9)];
+        digits.parse::<u64>().ok()? * 10u64.pow(9 - digits.len() as u32)
+    };
+    gst::ClockTime::from_seconds(secs).checked_add(gst::ClockTime::from_nseconds(nsecs))
+}
+
+/// Parses an npt range as used in the SDP `range` attribute and the `Range` header, e.g.
+/// `npt=0-34.5`. Open ends and `now` are returned as `None`.
+pub fn parse_npt_range(range: &str) -> Option<(Option<gst::ClockTime>, Option<gst::ClockTime>)> {
+    let range = range.trim().strip_prefix("npt=")?;
+    // The Range header can have a time parameter
+    let range = range.split(';').next().unwrap_or_default();
+    let (start, end) = range.split_once('-')?;
+    let start = match start.trim() {
+        "" | "now" => None,
+        s => Some(parse_npt_time(s)?),
+    };
+    let end = match end.trim() {
+        "" => None,
+        s => Some(parse_npt_time(s)?),
+    };
+    Some((start, end))
+}
+
+pub fn format_npt_time(t: gst::ClockTime) -> String {
+    format!("{}.{:03}", t.seconds(), t.mseconds() % 1000)
+}
@@ -0,0 +1,183 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::sync::{Mutex, OnceLock};
+
+use crate::listener::{Event, Listener, Session};
+use crate::protocol;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "sapdeviceprovider",
+        gst::DebugColorFlags::empty(),
+        Some("SAP Device Provider"),
+    )
+});
+
+#[derive(Default)]
+pub struct DeviceProvider {
+    listener: Mutex<Option<Listener>>,
+    current_devices: Mutex<Vec<super::Device>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for DeviceProvider {
+    const NAME: &'static str = "GstSapDeviceProvider";
+    type Type = super::DeviceProvider;
+    type ParentType = gst::DeviceProvider;
+}
+
+impl ObjectImpl for DeviceProvider {}
+
+impl GstObjectImpl for DeviceProvider {}
+
+impl DeviceProviderImpl for DeviceProvider {
+    fn metadata() -> Option<&'static gst::subclass::DeviceProviderMetadata> {
+        static METADATA: Lazy<gst::subclass::DeviceProviderMetadata> = Lazy::new(|| {
+            gst::subclass::DeviceProviderMetadata::new(
+                "SAP Device Provider",
+                "Source/Network",
+                "Lists multicast RTP sessions announced via SAP",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*METADATA)
+    }
+
+    fn probe(&self) -> Vec<gst::Device> {
+        self.current_devices
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|d| d.clone().upcast())
+            .collect()
+    }
+
+    fn start(&self) -> Result<(), gst::LoggableError> {
+        let mut listener_guard = self.listener.lock().unwrap();
+        if listener_guard.is_some() {
+            gst::log!(CAT, imp: self, "Device provider already started");
+            return Ok(());
+        }
+
+        let groups = protocol::parse_groups(protocol::DEFAULT_SAP_GROUPS).unwrap();
+
+        let imp_weak = self.downgrade();
+        let listener = Listener::start(&groups, move |event| {
+            if let Some(imp) = imp_weak.upgrade() {
+                imp.handle_event(event);
+            }
+        })
+        .map_err(|err| gst::loggable_error!(CAT, "Failed to start listener: {err:#}"))?;
+
+        *listener_guard = Some(listener);
+
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let listener = self.listener.lock().unwrap().take();
+        drop(listener);
+
+        self.current_devices.lock().unwrap().clear();
+    }
+}
+
+impl DeviceProvider {
+    fn handle_event(&self, event: Event) {
+        let mut current_devices_guard = self.current_devices.lock().unwrap();
+
+        match event {
+            Event::Added(session) => {
+                gst::log!(CAT, imp: self, "Session '{}' appeared", session.name);
+                let device = super::Device::new(&session);
+                self.obj().device_add(&device);
+                current_devices_guard.push(device);
+            }
+            Event::Updated(session) => {
+                let Some(pos) = current_devices_guard
+                    .iter()
+                    .position(|d| d.imp().session.get().unwrap().key == session.key)
+                else {
+                    return;
+                };
+
+                gst::log!(CAT, imp: self, "Session '{}' changed", session.name);
+                let device = super::Device::new(&session);
+                let old_device = std::mem::replace(&mut current_devices_guard[pos], device.clone());
+                self.obj().device_changed(&device, &old_device);
+            }
+            Event::Removed(session) => {
+                let Some(pos) = current_devices_guard
+                    .iter()
+                    .position(|d| d.imp().session.get().unwrap().key == session.key)
+                else {
+                    return;
+                };
+
+                gst::log!(CAT, imp: self, "Session '{}' disappeared", session.name);
+                let old_device = current_devices_guard.remove(pos);
+                self.obj().device_remove(&old_device);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Device {
+    session: OnceLock<Session>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Device {
+    const NAME: &'static str = "GstSapDevice";
+    type Type = super::Device;
+    type ParentType = gst::Device;
+}
+
+impl ObjectImpl for Device {}
+
+impl GstObjectImpl for Device {}
+
+impl DeviceImpl for Device {
+    fn create_element(&self, name: Option<&str>) -> Result<gst::Element, gst::LoggableError> {
+        let session = self.session.get().unwrap();
+
+        gst::ElementFactory::make("sdpsrc")
+            .property("name", name)
+            .property("sdp", &session.sdp)
+            .build()
+            .map_err(|_| gst::loggable_error!(CAT, "Missing element 'sdpsrc' from gst-plugins-bad"))
+    }
+}
+
+impl super::Device {
+    fn new(session: &Session) -> super::Device {
+        let extra_properties = gst::Structure::builder("properties")
+            .field("name", &session.name)
+            .field("origin", session.origin.to_string())
+            .field("sdp", &session.sdp_text)
+            .field("uri", session.uri())
+            .build();
+
+        let device = glib::Object::builder::<super::Device>()
+            .property("caps", gst::Caps::builder("application/x-rtp").build())
+            .property("display-name", &session.name)
+            .property("device-class", "Source/Network")
+            .property("properties", extra_properties)
+            .build();
+
+        device.imp().session.set(session.clone()).unwrap();
+
+        device
+    }
+}
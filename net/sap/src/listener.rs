@@ -0,0 +1,357 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! SAP listener keeping track of the currently announced sessions.
+
+use anyhow::Context as _;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::protocol::{MessageType, Packet, SAP_PORT};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "saplistener",
+        gst::DebugColorFlags::empty(),
+        Some("SAP Listener"),
+    )
+});
+
+/// How often the listener threads wake up to check for expired sessions and shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Timeout of sessions that were only announced once so far, as per RFC 2974 section 4.
+const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Number of announcement intervals after which a session is considered gone.
+const SESSION_TIMEOUT_INTERVALS: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// Identifies the session independent of its version.
+    ///
+    /// This is the `o=` line of the SDP without the session version, or the origin and message
+    /// ID hash of the SAP packet if the SDP has no origin.
+    pub key: String,
+    /// Session name from the `s=` line.
+    pub name: String,
+    /// Announcing host.
+    pub origin: IpAddr,
+    pub sdp: gst_sdp::SDPMessage,
+    pub sdp_text: String,
+    msg_id_hash: u16,
+    last_seen: Instant,
+    interval: Option<Duration>,
+}
+
+impl Session {
+    fn timeout(&self) -> Duration {
+        self.interval
+            .map(|interval| interval * SESSION_TIMEOUT_INTERVALS)
+            .unwrap_or(DEFAULT_SESSION_TIMEOUT)
+    }
+
+    /// `sdp://` URI of the session, which can be used with `sdpsrc`.
+    pub fn uri(&self) -> String {
+        format!(
+            "sdp://data:application/sdp;base64,{}",
+            data_encoding::BASE64.encode(self.sdp_text.as_bytes())
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Added(Session),
+    Updated(Session),
+    Removed(Session),
+}
+
+type Callback = dyn Fn(Event) + Send + Sync + 'static;
+
+/// Listens for SAP announcements on a set of groups and notifies about sessions appearing,
+/// changing and disappearing.
+///
+/// The listener is stopped when dropped.
+pub struct Listener {
+    running: Arc<AtomicBool>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl Listener {
+    pub fn start(
+        groups: &[IpAddr],
+        callback: impl Fn(Event) + Send + Sync + 'static,
+    ) -> Result<Self, anyhow::Error> {
+        let running = Arc::new(AtomicBool::new(true));
+        let sessions = Arc::new(Mutex::new(HashMap::<String, Session>::new()));
+        let callback: Arc<Callback> = Arc::new(callback);
+
+        let mut sockets = vec![];
+        let v4_groups = groups
+            .iter()
+            .filter(|group| group.is_ipv4())
+            .collect::<Vec<_>>();
+        if !v4_groups.is_empty() {
+            sockets.push(bind(&v4_groups).context("Failed to create IPv4 socket")?);
+        }
+        let v6_groups = groups
+            .iter()
+            .filter(|group| group.is_ipv6())
+            .collect::<Vec<_>>();
+        if !v6_groups.is_empty() {
+            sockets.push(bind(&v6_groups).context("Failed to create IPv6 socket")?);
+        }
+
+        let threads = sockets
+            .into_iter()
+            .map(|socket| {
+                let running = running.clone();
+                let sessions = sessions.clone();
+                let callback = callback.clone();
+
+                thread::Builder::new()
+                    .name("sap-listener".into())
+                    .spawn(move || receive_loop(socket, &running, &sessions, &*callback))
+                    .context("Failed to spawn thread")
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Listener { running, threads })
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn bind(groups: &[&IpAddr]) -> Result<UdpSocket, anyhow::Error> {
+    let ipv6 = groups[0].is_ipv6();
+
+    let socket = socket2::Socket::new(
+        if ipv6 {
+            socket2::Domain::IPV6
+        } else {
+            socket2::Domain::IPV4
+        },
+        socket2::Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+
+    // Multiple listeners in the same or different processes must be able to share the port
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    if ipv6 {
+        socket.set_only_v6(true)?;
+    }
+
+    let bind_addr = if ipv6 {
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    } else {
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+    };
+    socket
+        .bind(&SocketAddr::new(bind_addr, SAP_PORT).into())
+        .with_context(|| format!("Failed to bind to port {SAP_PORT}"))?;
+
+    for group in groups {
+        match group {
+            IpAddr::V4(group) => socket.join_multicast_v4(group, &Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(group) => socket.join_multicast_v6(group, 0),
+        }
+        .with_context(|| format!("Failed to join group {group}"))?;
+    }
+
+    socket.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    Ok(socket.into())
+}
+
+fn receive_loop(
+    socket: UdpSocket,
+    running: &AtomicBool,
+    sessions: &Mutex<HashMap<String, Session>>,
+    callback: &Callback,
+) {
+    let mut buf = [0u8; 65_536];
+
+    while running.load(Ordering::SeqCst) {
+        match socket.recv_from(&mut buf) {
+            Ok((len, saddr)) => match Packet::parse(&buf[..len]) {
+                Ok(packet) => handle_packet(packet, sessions, callback),
+                Err(err) => {
+                    gst::debug!(CAT, "Ignoring invalid SAP packet from {saddr}: {err:#}");
+                }
+            },
+            Err(err)
+                if err.kind() == std::io::ErrorKind::WouldBlock
+                    || err.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(err) => {
+                gst::warning!(CAT, "Failed to receive: {err}");
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+
+        expire_sessions(sessions, callback);
+    }
+}
+
+/// Returns the `o=` line of `sdp` without the session version.
+fn origin_key(sdp: &str) -> Option<String> {
+    let origin = sdp
+        .lines()
+        .find_map(|line| line.trim_end().strip_prefix("o="))?;
+
+    let fields = origin.split_ascii_whitespace().collect::<Vec<_>>();
+    if fields.len() != 6 {
+        return None;
+    }
+
+    Some(format!(
+        "{} {} {} {} {}",
+        fields[0], fields[1], fields[3], fields[4], fields[5]
+    ))
+}
+
+fn handle_packet(packet: Packet, sessions: &Mutex<HashMap<String, Session>>, callback: &Callback) {
+    let key = origin_key(&packet.sdp)
+        .unwrap_or_else(|| format!("{} {:04x}", packet.origin, packet.msg_id_hash));
+
+    let event = {
+        let mut sessions = sessions.lock().unwrap();
+
+        match packet.message_type {
+            MessageType::Deletion => {
+                // Deletions only need to contain the origin line, or nothing at all in which
+                // case the message ID hash and origin identify the session.
+                let key = if sessions.contains_key(&key) {
+                    Some(key)
+                } else {
+                    sessions
+                        .values()
+                        .find(|session| {
+                            session.origin == packet.origin
+                                && session.msg_id_hash == packet.msg_id_hash
+                        })
+                        .map(|session| session.key.clone())
+                };
+
+                let Some(session) = key.and_then(|key| sessions.remove(&key)) else {
+                    return;
+                };
+
+                gst::debug!(CAT, "Session '{}' ({}) deleted", session.name, session.key);
+
+                Event::Removed(session)
+            }
+            MessageType::Announcement => {
+                let now = Instant::now();
+
+                if let Some(session) = sessions.get_mut(&key) {
+                    session.interval = Some(now.duration_since(session.last_seen));
+                    session.last_seen = now;
+
+                    if session.msg_id_hash == packet.msg_id_hash && session.sdp_text == packet.sdp {
+                        return;
+                    }
+                }
+
+                let sdp = match gst_sdp::SDPMessage::parse_buffer(packet.sdp.as_bytes()) {
+                    Ok(sdp) => sdp,
+                    Err(err) => {
+                        gst::debug!(CAT, "Ignoring announcement with invalid SDP: {err}");
+                        return;
+                    }
+                };
+
+                let name = sdp.session_name().unwrap_or_default().to_string();
+
+                if let Some(session) = sessions.get_mut(&key) {
+                    gst::debug!(CAT, "Session '{}' ({}) updated", name, key);
+
+                    session.name = name;
+                    session.msg_id_hash = packet.msg_id_hash;
+                    session.sdp = sdp;
+                    session.sdp_text = packet.sdp;
+
+                    Event::Updated(session.clone())
+                } else {
+                    gst::debug!(CAT, "Session '{}' ({}) announced", name, key);
+
+                    let session = Session {
+                        key: key.clone(),
+                        name,
+                        origin: packet.origin,
+                        sdp,
+                        sdp_text: packet.sdp,
+                        msg_id_hash: packet.msg_id_hash,
+                        last_seen: now,
+                        interval: None,
+                    };
+                    sessions.insert(key, session.clone());
+
+                    Event::Added(session)
+                }
+            }
+        }
+    };
+
+    callback(event);
+}
+
+fn expire_sessions(sessions: &Mutex<HashMap<String, Session>>, callback: &Callback) {
+    let now = Instant::now();
+
+    let expired = {
+        let mut sessions = sessions.lock().unwrap();
+
+        let expired_keys = sessions
+            .values()
+            .filter(|session| now.duration_since(session.last_seen) > session.timeout())
+            .map(|session| session.key.clone())
+            .collect::<Vec<_>>();
+
+        expired_keys
+            .into_iter()
+            .filter_map(|key| sessions.remove(&key))
+            .collect::<Vec<_>>()
+    };
+
+    for session in expired {
+        gst::debug!(
+            CAT,
+            "Session '{}' ({}) timed out",
+            session.name,
+            session.key
+        );
+        callback(Event::Removed(session));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_key() {
+        assert_eq!(
+            origin_key("v=0\r\no=- 1234 5 IN IP4 10.0.0.1\r\ns=Test\r\n").as_deref(),
+            Some("- 1234 IN IP4 10.0.0.1")
+        );
+        assert_eq!(origin_key("v=0\r\ns=Test\r\n"), None);
+    }
+}
@@ -0,0 +1,256 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Parsing and writing of SAP packets as per [RFC 2974].
+//!
+//! [RFC 2974]: https://datatracker.ietf.org/doc/html/rfc2974
+
+use anyhow::{bail, Context as _};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Well-known UDP port of SAP.
+pub const SAP_PORT: u16 = 9875;
+
+/// SAP group for the IPv4 global scope, 224.0.1.0 - 238.255.255.255.
+pub const SAP_GROUP_IPV4_GLOBAL: Ipv4Addr = Ipv4Addr::new(224, 2, 127, 254);
+
+/// SAP group for the IPv4 local administrative scope, 239.255.0.0/16.
+///
+/// This is what AES67 and SMPTE 2110 devices commonly announce on.
+pub const SAP_GROUP_IPV4_LOCAL: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 255);
+
+/// Groups listened on by default.
+pub const DEFAULT_SAP_GROUPS: &str = "224.2.127.254,239.255.255.255";
+
+/// MIME type of SDP payloads.
+const SDP_PAYLOAD_TYPE: &str = "application/sdp";
+
+const VERSION: u8 = 1;
+const A_BIT: u8 = 0b0001_0000;
+const T_BIT: u8 = 0b0000_0100;
+const E_BIT: u8 = 0b0000_0010;
+const C_BIT: u8 = 0b0000_0001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Announcement,
+    Deletion,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub message_type: MessageType,
+    pub msg_id_hash: u16,
+    pub origin: IpAddr,
+    pub sdp: String,
+}
+
+impl Packet {
+    pub fn parse(data: &[u8]) -> Result<Self, anyhow::Error> {
+        if data.len() < 4 {
+            bail!("Too short packet");
+        }
+
+        let flags = data[0];
+        let version = flags >> 5;
+        if version != VERSION {
+            bail!("Unsupported version {version}");
+        }
+        if flags & E_BIT != 0 {
+            bail!("Encrypted payloads not supported");
+        }
+        if flags & C_BIT != 0 {
+            bail!("Compressed payloads not supported");
+        }
+
+        let message_type = if flags & T_BIT != 0 {
+            MessageType::Deletion
+        } else {
+            MessageType::Announcement
+        };
+
+        let auth_len = data[1] as usize * 4;
+        let msg_id_hash = u16::from_be_bytes([data[2], data[3]]);
+
+        let mut data = &data[4..];
+        let origin = if flags & A_BIT != 0 {
+            let octets: [u8; 16] = data
+                .get(..16)
+                .context("Too short IPv6 origin")?
+                .try_into()
+                .unwrap();
+            data = &data[16..];
+            IpAddr::V6(Ipv6Addr::from(octets))
+        } else {
+            let octets: [u8; 4] = data
+                .get(..4)
+                .context("Too short IPv4 origin")?
+                .try_into()
+                .unwrap();
+            data = &data[4..];
+            IpAddr::V4(Ipv4Addr::from(octets))
+        };
+
+        // Authentication data is not verified
+        data = data
+            .get(auth_len..)
+            .context("Too short authentication data")?;
+
+        // The payload type is optional and if missing the payload is SDP, which always starts
+        // with the version line.
+        if !data.starts_with(b"v=0") {
+            let end = data
+                .iter()
+                .position(|b| *b == 0)
+                .context("No payload type")?;
+            let payload_type = std::str::from_utf8(&data[..end]).context("Invalid payload type")?;
+            if !payload_type.eq_ignore_ascii_case(SDP_PAYLOAD_TYPE) {
+                bail!("Unsupported payload type {payload_type}");
+            }
+            data = &data[end + 1..];
+        }
+
+        let sdp = std::str::from_utf8(data)
+            .context("Invalid SDP")?
+            .to_string();
+
+        Ok(Packet {
+            message_type,
+            msg_id_hash,
+            origin,
+            sdp,
+        })
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(24 + SDP_PAYLOAD_TYPE.len() + 1 + self.sdp.len());
+
+        let mut flags = VERSION << 5;
+        if self.origin.is_ipv6() {
+            flags |= A_BIT;
+        }
+        if self.message_type == MessageType::Deletion {
+            flags |= T_BIT;
+        }
+
+        data.push(flags);
+        data.push(0);
+        data.extend_from_slice(&self.msg_id_hash.to_be_bytes());
+        match self.origin {
+            IpAddr::V4(addr) => data.extend_from_slice(&addr.octets()),
+            IpAddr::V6(addr) => data.extend_from_slice(&addr.octets()),
+        }
+        data.extend_from_slice(SDP_PAYLOAD_TYPE.as_bytes());
+        data.push(0);
+        data.extend_from_slice(self.sdp.as_bytes());
+
+        data
+    }
+}
+
+/// Returns the SAP group to announce sessions with the given multicast address on.
+///
+/// As per RFC 2974 section 3, sessions are announced in the highest address of the scope they
+/// are in. Only the IPv4 local scope and IPv6 scopes are handled specially, everything else is
+/// announced in the global scope.
+pub fn sap_group_for(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V4(addr) if addr.octets()[0] == 239 && addr.octets()[1] == 255 => {
+            IpAddr::V4(SAP_GROUP_IPV4_LOCAL)
+        }
+        IpAddr::V4(_) => IpAddr::V4(SAP_GROUP_IPV4_GLOBAL),
+        IpAddr::V6(addr) => {
+            // FF0X:0:0:0:0:0:2:7FFE with the scope of the session address
+            let scope = addr.segments()[0] & 0x000f;
+            IpAddr::V6(Ipv6Addr::new(0xff00 | scope, 0, 0, 0, 0, 0, 2, 0x7ffe))
+        }
+    }
+}
+
+/// Parses a comma-separated list of SAP groups.
+pub fn parse_groups(groups: &str) -> Result<Vec<IpAddr>, anyhow::Error> {
+    groups
+        .split(',')
+        .map(str::trim)
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            let addr = group
+                .parse::<IpAddr>()
+                .with_context(|| format!("Invalid address '{group}'"))?;
+            if !addr.is_multicast() {
+                bail!("'{group}' is not a multicast address");
+            }
+            Ok(addr)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDP: &str = "v=0\r\no=- 1 1 IN IP4 192.168.1.2\r\ns=Test\r\nc=IN IP4 239.255.0.1/32\r\nt=0 0\r\nm=audio 5004 RTP/AVP 96\r\na=rtpmap:96 L24/48000/2\r\n";
+
+    #[test]
+    fn test_roundtrip() {
+        for origin in [
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 2)),
+            IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+        ] {
+            let packet = Packet {
+                message_type: MessageType::Announcement,
+                msg_id_hash: 0x1234,
+                origin,
+                sdp: SDP.to_string(),
+            };
+
+            let data = packet.write();
+            assert_eq!(Packet::parse(&data).unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn test_parse_without_payload_type() {
+        let mut data = vec![0b0010_0100, 0, 0xab, 0xcd, 10, 0, 0, 1];
+        data.extend_from_slice(SDP.as_bytes());
+
+        let packet = Packet::parse(&data).unwrap();
+        assert_eq!(packet.message_type, MessageType::Deletion);
+        assert_eq!(packet.msg_id_hash, 0xabcd);
+        assert_eq!(packet.origin, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(packet.sdp, SDP);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        // Wrong version
+        assert!(Packet::parse(&[0b0100_0000, 0, 0, 0, 10, 0, 0, 1]).is_err());
+        // Compressed
+        assert!(Packet::parse(&[0b0010_0001, 0, 0, 0, 10, 0, 0, 1]).is_err());
+        // Truncated origin
+        assert!(Packet::parse(&[0b0010_0000, 0, 0, 0, 10, 0]).is_err());
+        // Unsupported payload type
+        let mut data = vec![0b0010_0000, 0, 0, 0, 10, 0, 0, 1];
+        data.extend_from_slice(b"text/plain\0hello");
+        assert!(Packet::parse(&data).is_err());
+    }
+
+    #[test]
+    fn test_sap_group_for() {
+        assert_eq!(
+            sap_group_for("239.255.0.1".parse().unwrap()),
+            IpAddr::V4(SAP_GROUP_IPV4_LOCAL)
+        );
+        assert_eq!(
+            sap_group_for("233.1.2.3".parse().unwrap()),
+            IpAddr::V4(SAP_GROUP_IPV4_GLOBAL)
+        );
+        assert_eq!(
+            sap_group_for("ff05::1234".parse().unwrap()),
+            "ff05::2:7ffe".parse::<IpAddr>().unwrap()
+        );
+    }
+}
@@ -0,0 +1,556 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::protocol::{self, MessageType, Packet, SAP_PORT};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new("sapsink", gst::DebugColorFlags::empty(), Some("SAP Sink"))
+});
+
+const DEFAULT_PORT: i32 = 5004;
+const DEFAULT_TTL: u32 = 1;
+const DEFAULT_SESSION_NAME: &str = "GStreamer";
+const DEFAULT_ANNOUNCE_INTERVAL: u32 = 30;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    address: Option<String>,
+    port: i32,
+    ttl: u32,
+    session_name: String,
+    sap_group: Option<String>,
+    announce_interval: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            address: None,
+            port: DEFAULT_PORT,
+            ttl: DEFAULT_TTL,
+            session_name: DEFAULT_SESSION_NAME.to_string(),
+            sap_group: None,
+            announce_interval: DEFAULT_ANNOUNCE_INTERVAL,
+        }
+    }
+}
+
+#[derive(Default)]
+struct AnnouncerState {
+    packet: Option<Packet>,
+    stopped: bool,
+}
+
+/// Thread sending the current announcement periodically and the deletion when stopped.
+struct Announcer {
+    state: Arc<(Mutex<AnnouncerState>, Condvar)>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Announcer {
+    fn start(
+        element: super::SapSink,
+        socket: UdpSocket,
+        dest: SocketAddr,
+        interval: Duration,
+    ) -> Self {
+        let state = Arc::new((Mutex::new(AnnouncerState::default()), Condvar::new()));
+
+        let thread_state = state.clone();
+        let thread = thread::spawn(move || {
+            let (lock, cond) = &*thread_state;
+            let mut state = lock.lock().unwrap();
+
+            loop {
+                if let Some(ref packet) = state.packet {
+                    if state.stopped {
+                        let deletion = Packet {
+                            message_type: MessageType::Deletion,
+                            ..packet.clone()
+                        };
+                        gst::debug!(CAT, obj: element, "Sending deletion to {dest}");
+                        if let Err(err) = socket.send_to(&deletion.write(), dest) {
+                            gst::warning!(CAT, obj: element, "Failed to send deletion: {err}");
+                        }
+                        break;
+                    }
+
+                    gst::trace!(CAT, obj: element, "Sending announcement to {dest}");
+                    if let Err(err) = socket.send_to(&packet.write(), dest) {
+                        gst::warning!(CAT, obj: element, "Failed to send announcement: {err}");
+                    }
+                } else if state.stopped {
+                    break;
+                }
+
+                // Woken up early if the packet changes or the announcer is stopped
+                state = cond.wait_timeout(state, interval).unwrap().0;
+            }
+        });
+
+        Announcer {
+            state,
+            thread: Some(thread),
+        }
+    }
+
+    fn set_packet(&self, packet: Packet) {
+        let (lock, cond) = &*self.state;
+        lock.lock().unwrap().packet = Some(packet);
+        cond.notify_one();
+    }
+}
+
+impl Drop for Announcer {
+    fn drop(&mut self) {
+        let (lock, cond) = &*self.state;
+        lock.lock().unwrap().stopped = true;
+        cond.notify_one();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Started {
+    udpsink: gst::Element,
+    announcer: Announcer,
+    address: IpAddr,
+    origin: IpAddr,
+    session_id: u32,
+    session_version: u32,
+    sdp: Option<String>,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started(Started),
+}
+
+pub struct SapSink {
+    sinkpad: gst::GhostPad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl SapSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let address = settings
+            .address
+            .as_deref()
+            .ok_or_else(|| gst::error_msg!(gst::ResourceError::Settings, ["No address set"]))?
+            .parse::<IpAddr>()
+            .map_err(|err| {
+                gst::error_msg!(gst::ResourceError::Settings, ["Invalid address: {err}"])
+            })?;
+        if !address.is_multicast() {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["{address} is not a multicast address"]
+            ));
+        }
+
+        let sap_group = match settings.sap_group {
+            Some(ref sap_group) => sap_group.parse::<IpAddr>().map_err(|err| {
+                gst::error_msg!(gst::ResourceError::Settings, ["Invalid SAP group: {err}"])
+            })?,
+            None => protocol::sap_group_for(address),
+        };
+        if sap_group.is_ipv4() != address.is_ipv4() {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["SAP group and address must be of the same address family"]
+            ));
+        }
+
+        let socket = announcement_socket(sap_group, settings.ttl).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenWrite,
+                ["Failed to create announcement socket: {err}"]
+            )
+        })?;
+        let origin = local_address(address);
+
+        let udpsink = gst::ElementFactory::make("udpsink")
+            .property("host", address.to_string())
+            .property("port", settings.port)
+            .property("ttl-mc", settings.ttl as i32)
+            .property("auto-multicast", true)
+            .build()
+            .map_err(|_| {
+                gst::error_msg!(gst::CoreError::MissingPlugin, ["Missing element 'udpsink'"])
+            })?;
+
+        self.obj().add(&udpsink).unwrap();
+        self.sinkpad
+            .set_target(Some(&udpsink.static_pad("sink").unwrap()))
+            .unwrap();
+
+        let announcer = Announcer::start(
+            self.obj().clone(),
+            socket,
+            SocketAddr::new(sap_group, SAP_PORT),
+            Duration::from_secs(settings.announce_interval.into()),
+        );
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Announcing session for {address} from {origin} on {sap_group}"
+        );
+
+        *self.state.lock().unwrap() = State::Started(Started {
+            udpsink,
+            announcer,
+            address,
+            origin,
+            session_id: rand::random(),
+            session_version: 0,
+            sdp: None,
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let State::Started(started) = std::mem::take(&mut *self.state.lock().unwrap()) else {
+            return;
+        };
+
+        // Sends the deletion
+        drop(started.announcer);
+
+        let _ = self.sinkpad.set_target(None::<&gst::Pad>);
+        let _ = started.udpsink.set_state(gst::State::Null);
+        let _ = self.obj().remove(&started.udpsink);
+    }
+
+    fn update_sdp(&self, caps: &gst::CapsRef) -> Result<(), anyhow::Error> {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+        let State::Started(ref mut started) = *state else {
+            return Ok(());
+        };
+
+        started.session_version += 1;
+
+        let addrtype = |addr: &IpAddr| if addr.is_ipv4() { "IP4" } else { "IP6" };
+
+        let mut sdp = gst_sdp::SDPMessage::new();
+        sdp.set_version("0");
+        sdp.set_origin(
+            "-",
+            &started.session_id.to_string(),
+            &started.session_version.to_string(),
+            "IN",
+            addrtype(&started.origin),
+            &started.origin.to_string(),
+        );
+        sdp.set_session_name(&settings.session_name);
+        sdp.set_connection(
+            "IN",
+            addrtype(&started.address),
+            &started.address.to_string(),
+            if started.address.is_ipv4() {
+                settings.ttl
+            } else {
+                0
+            },
+            0,
+        );
+        sdp.add_time("0", "0", &[]);
+
+        let mut media = gst_sdp::SDPMedia::new();
+        gst_sdp::SDPMedia::set_media_from_caps(&caps.to_owned(), &mut media)?;
+        media.set_port_info(settings.port as u32, 1);
+        media.set_proto("RTP/AVP");
+        sdp.add_media(media);
+
+        let sdp = sdp.as_text()?;
+        gst::debug!(CAT, imp: self, "Announcing SDP {sdp}");
+
+        started.announcer.set_packet(Packet {
+            message_type: MessageType::Announcement,
+            msg_id_hash: rand::random(),
+            origin: started.origin,
+            sdp: sdp.clone(),
+        });
+        started.sdp = Some(sdp);
+        drop(state);
+
+        self.obj().notify("sdp");
+
+        Ok(())
+    }
+
+    fn sink_event(&self, pad: &gst::GhostPad, event: gst::Event) -> bool {
+        if let gst::EventView::Caps(ev) = event.view() {
+            if let Err(err) = self.update_sdp(ev.caps()) {
+                gst::element_imp_error!(
+                    self,
+                    gst::StreamError::Format,
+                    ["Failed to create SDP from caps {:?}: {err}", ev.caps()]
+                );
+                return false;
+            }
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+}
+
+/// Creates the socket for sending announcements to `sap_group`.
+fn announcement_socket(sap_group: IpAddr, ttl: u32) -> std::io::Result<UdpSocket> {
+    let socket = if sap_group.is_ipv4() {
+        let socket = socket2::Socket::new(
+            socket2::Domain::IPV4,
+            socket2::Type::DGRAM,
+            Some(socket2::Protocol::UDP),
+        )?;
+        socket.set_multicast_ttl_v4(ttl)?;
+        socket.bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0).into())?;
+        socket
+    } else {
+        let socket = socket2::Socket::new(
+            socket2::Domain::IPV6,
+            socket2::Type::DGRAM,
+            Some(socket2::Protocol::UDP),
+        )?;
+        socket.set_multicast_hops_v6(ttl)?;
+        socket.bind(&SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0).into())?;
+        socket
+    };
+
+    Ok(socket.into())
+}
+
+/// Returns the local address that is used for sending to `address`.
+///
+/// Connecting a UDP socket doesn't send anything but selects the route and thus the local
+/// address.
+fn local_address(address: IpAddr) -> IpAddr {
+    let unspecified = if address.is_ipv4() {
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+    } else {
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    };
+
+    UdpSocket::bind(SocketAddr::new(unspecified, 0))
+        .and_then(|socket| {
+            socket.connect(SocketAddr::new(address, SAP_PORT))?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .unwrap_or(unspecified)
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SapSink {
+    const NAME: &'static str = "GstSapSink";
+    type Type = super::SapSink;
+    type ParentType = gst::Bin;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::GhostPad::builder_from_template(&templ)
+            .event_function(|pad, parent, event| {
+                SapSink::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |imp| imp.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        Self {
+            sinkpad,
+            settings: Default::default(),
+            state: Default::default(),
+        }
+    }
+}
+
+impl ObjectImpl for SapSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("address")
+                    .nick("Address")
+                    .blurb("Multicast group to send the stream to")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecInt::builder("port")
+                    .nick("Port")
+                    .blurb("Port to send the stream to")
+                    .minimum(0)
+                    .maximum(u16::MAX as i32)
+                    .default_value(DEFAULT_PORT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("ttl")
+                    .nick("TTL")
+                    .blurb("Multicast TTL of the stream and the announcements")
+                    .maximum(255)
+                    .default_value(DEFAULT_TTL)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("session-name")
+                    .nick("Session Name")
+                    .blurb("Name of the announced session")
+                    .default_value(Some(DEFAULT_SESSION_NAME))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sap-group")
+                    .nick("SAP Group")
+                    .blurb("Multicast group to send announcements to (None = derived from the address scope)")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("announce-interval")
+                    .nick("Announce Interval")
+                    .blurb("Interval between announcements in seconds")
+                    .minimum(1)
+                    .maximum(3600)
+                    .default_value(DEFAULT_ANNOUNCE_INTERVAL)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sdp")
+                    .nick("SDP")
+                    .blurb("SDP of the currently announced session")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "address" => {
+                settings.address = value.get().expect("type checked upstream");
+            }
+            "port" => {
+                settings.port = value.get().expect("type checked upstream");
+            }
+            "ttl" => {
+                settings.ttl = value.get().expect("type checked upstream");
+            }
+            "session-name" => {
+                settings.session_name = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_SESSION_NAME.to_string());
+            }
+            "sap-group" => {
+                settings.sap_group = value.get().expect("type checked upstream");
+            }
+            "announce-interval" => {
+                settings.announce_interval = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "address" => settings.address.to_value(),
+            "port" => settings.port.to_value(),
+            "ttl" => settings.ttl.to_value(),
+            "session-name" => settings.session_name.to_value(),
+            "sap-group" => settings.sap_group.to_value(),
+            "announce-interval" => settings.announce_interval.to_value(),
+            "sdp" => match *self.state.lock().unwrap() {
+                State::Started(ref started) => started.sdp.to_value(),
+                State::Stopped => None::<String>.to_value(),
+            },
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.set_element_flags(gst::ElementFlags::SINK);
+    }
+}
+
+impl GstObjectImpl for SapSink {}
+
+impl ElementImpl for SapSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "SAP Sink",
+                "Sink/Network",
+                "Sends an RTP stream to a multicast group and announces it via SAP",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("application/x-rtp").build(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {transition:?}");
+
+        if transition == gst::StateChange::NullToReady {
+            self.start().map_err(|err| {
+                self.post_error_message(err);
+                gst::StateChangeError
+            })?;
+        }
+
+        let res = self.parent_change_state(transition);
+
+        if transition == gst::StateChange::ReadyToNull || res.is_err() {
+            self.stop();
+        }
+
+        res
+    }
+}
+
+impl BinImpl for SapSink {}
@@ -0,0 +1,45 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-sapsink:
+ *
+ * `sapsink` sends an RTP stream to a multicast group with `udpsink` and announces it via
+ * [SAP] so that receivers like `sapsrc` can discover it.
+ *
+ * The SDP of the session is generated from the RTP caps and announced every
+ * `announce-interval` seconds on the SAP group corresponding to the scope of `address`, or
+ * on `sap-group` if set. Whenever the caps change, the session version is increased and the
+ * new SDP is announced right away. When shutting down, the session is deleted again.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 audiotestsrc ! audio/x-raw,rate=48000,channels=2 ! audioconvert ! \
+ *     rtpL24pay ! sapsink address=239.255.0.1 port=5004 session-name="Studio 1"
+ * ```
+ *
+ * [SAP]: https://datatracker.ietf.org/doc/html/rfc2974
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct SapSink(ObjectSubclass<imp::SapSink>) @extends gst::Bin, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "sapsink",
+        gst::Rank::NONE,
+        SapSink::static_type(),
+    )
+}
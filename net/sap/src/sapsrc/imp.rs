@@ -0,0 +1,390 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use crate::listener::{Event, Listener, Session};
+use crate::protocol;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new("sapsrc", gst::DebugColorFlags::empty(), Some("SAP Source"))
+});
+
+#[derive(Debug, Clone)]
+struct Settings {
+    session_name: Option<String>,
+    sap_groups: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            session_name: None,
+            sap_groups: protocol::DEFAULT_SAP_GROUPS.to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    listener: Option<Listener>,
+    session: Option<Session>,
+    sdpsrc: Option<gst::Element>,
+}
+
+#[derive(Default)]
+pub struct SapSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl SapSrc {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let groups =
+            protocol::parse_groups(&self.settings.lock().unwrap().sap_groups).map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["Invalid SAP groups: {err:#}"]
+                )
+            })?;
+
+        let obj_weak = self.obj().downgrade();
+        let listener = Listener::start(&groups, move |event| {
+            if let Some(obj) = obj_weak.upgrade() {
+                obj.imp().handle_event(event);
+            }
+        })
+        .map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Failed to listen for SAP announcements: {err:#}"]
+            )
+        })?;
+
+        gst::debug!(CAT, imp: self, "Listening for SAP announcements on {groups:?}");
+
+        self.state.lock().unwrap().listener = Some(listener);
+
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let (listener, sdpsrc) = {
+            let mut state = self.state.lock().unwrap();
+            state.session = None;
+            (state.listener.take(), state.sdpsrc.take())
+        };
+
+        // Dropping the listener waits for its threads, which might currently be calling into
+        // handle_event() and taking the state lock.
+        drop(listener);
+
+        if let Some(sdpsrc) = sdpsrc {
+            let _ = sdpsrc.set_state(gst::State::Null);
+            let _ = self.obj().remove(&sdpsrc);
+        }
+
+        for pad in self.obj().src_pads() {
+            let _ = self.obj().remove_pad(&pad);
+        }
+    }
+
+    fn handle_event(&self, event: Event) {
+        match event {
+            Event::Added(session) | Event::Updated(session) => {
+                let mut state = self.state.lock().unwrap();
+
+                if let Some(ref mut current) = state.session {
+                    if current.key == session.key {
+                        gst::debug!(
+                            CAT,
+                            imp: self,
+                            "Selected session '{}' was updated, keeping the current configuration",
+                            session.name,
+                        );
+                        *current = session;
+                        drop(state);
+                        self.obj().notify("sdp");
+                    }
+                    return;
+                }
+
+                let session_name = self.settings.lock().unwrap().session_name.clone();
+                if session_name.map_or(false, |name| name != session.name) {
+                    gst::trace!(CAT, imp: self, "Ignoring session '{}'", session.name);
+                    return;
+                }
+
+                gst::info!(CAT, imp: self, "Selecting session '{}' from {}", session.name, session.origin);
+
+                let sdpsrc = match self.create_sdpsrc(&session) {
+                    Ok(sdpsrc) => sdpsrc,
+                    Err(err) => {
+                        drop(state);
+                        self.post_error_message(err);
+                        return;
+                    }
+                };
+
+                let msg = gst::message::Element::builder(
+                    gst::Structure::builder("sap-session")
+                        .field("name", &session.name)
+                        .field("origin", session.origin.to_string())
+                        .field("sdp", &session.sdp_text)
+                        .build(),
+                )
+                .src(&*self.obj())
+                .build();
+
+                state.session = Some(session);
+                state.sdpsrc = Some(sdpsrc.clone());
+                drop(state);
+
+                self.obj().notify("sdp");
+                let _ = self.obj().post_message(msg);
+
+                if let Err(err) = self.obj().add(&sdpsrc) {
+                    gst::element_imp_error!(
+                        self,
+                        gst::CoreError::Failed,
+                        ["Failed to add sdpsrc: {err}"]
+                    );
+                    return;
+                }
+
+                if let Err(err) = sdpsrc.sync_state_with_parent() {
+                    gst::element_imp_error!(
+                        self,
+                        gst::CoreError::StateChange,
+                        ["Failed to start sdpsrc: {err}"]
+                    );
+                }
+            }
+            Event::Removed(session) => {
+                let state = self.state.lock().unwrap();
+
+                if state
+                    .session
+                    .as_ref()
+                    .map_or(true, |current| current.key != session.key)
+                {
+                    return;
+                }
+
+                let sdpsrc = state.sdpsrc.clone();
+                drop(state);
+
+                gst::info!(CAT, imp: self, "Session '{}' was removed", session.name);
+
+                let msg = gst::message::Element::builder(
+                    gst::Structure::builder("sap-session-removed")
+                        .field("name", &session.name)
+                        .field("origin", session.origin.to_string())
+                        .build(),
+                )
+                .src(&*self.obj())
+                .build();
+                let _ = self.obj().post_message(msg);
+
+                if let Some(sdpsrc) = sdpsrc {
+                    sdpsrc.send_event(gst::event::Eos::new());
+                }
+            }
+        }
+    }
+
+    fn create_sdpsrc(&self, session: &Session) -> Result<gst::Element, gst::ErrorMessage> {
+        let sdpsrc = gst::ElementFactory::make("sdpsrc")
+            .property("sdp", &session.sdp)
+            .build()
+            .map_err(|_| {
+                gst::error_msg!(
+                    gst::CoreError::MissingPlugin,
+                    ["Missing element 'sdpsrc' from gst-plugins-bad"]
+                )
+            })?;
+
+        let obj_weak = self.obj().downgrade();
+        sdpsrc.connect_pad_added(move |_, pad| {
+            let Some(obj) = obj_weak.upgrade() else {
+                return;
+            };
+
+            gst::debug!(CAT, obj: obj, "Exposing pad {}", pad.name());
+
+            let templ = obj.pad_template("stream_%u").unwrap();
+            let ghost_pad = gst::GhostPad::builder_from_template_with_target(&templ, pad)
+                .unwrap()
+                .name(pad.name())
+                .build();
+            let _ = ghost_pad.set_active(true);
+            let _ = obj.add_pad(&ghost_pad);
+        });
+
+        let obj_weak = self.obj().downgrade();
+        sdpsrc.connect_pad_removed(move |_, pad| {
+            let Some(obj) = obj_weak.upgrade() else {
+                return;
+            };
+
+            if let Some(ghost_pad) = obj.static_pad(&pad.name()) {
+                let _ = obj.remove_pad(&ghost_pad);
+            }
+        });
+
+        let obj_weak = self.obj().downgrade();
+        sdpsrc.connect_no_more_pads(move |_| {
+            if let Some(obj) = obj_weak.upgrade() {
+                obj.no_more_pads();
+            }
+        });
+
+        Ok(sdpsrc)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SapSrc {
+    const NAME: &'static str = "GstSapSrc";
+    type Type = super::SapSrc;
+    type ParentType = gst::Bin;
+}
+
+impl ObjectImpl for SapSrc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("session-name")
+                    .nick("Session Name")
+                    .blurb("Name of the session to receive (None = first announced session)")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sap-groups")
+                    .nick("SAP Groups")
+                    .blurb(
+                        "Comma-separated list of multicast groups to listen for announcements on",
+                    )
+                    .default_value(Some(protocol::DEFAULT_SAP_GROUPS))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sdp")
+                    .nick("SDP")
+                    .blurb("SDP of the currently received session")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "session-name" => {
+                settings.session_name = value.get().expect("type checked upstream");
+            }
+            "sap-groups" => {
+                settings.sap_groups = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| protocol::DEFAULT_SAP_GROUPS.to_string());
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "session-name" => self.settings.lock().unwrap().session_name.to_value(),
+            "sap-groups" => self.settings.lock().unwrap().sap_groups.to_value(),
+            "sdp" => self
+                .state
+                .lock()
+                .unwrap()
+                .session
+                .as_ref()
+                .map(|session| session.sdp_text.clone())
+                .to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().set_element_flags(gst::ElementFlags::SOURCE);
+    }
+}
+
+impl GstObjectImpl for SapSrc {}
+
+impl ElementImpl for SapSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "SAP Source",
+                "Source/Network",
+                "Receives a multicast RTP session announced via SAP",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "stream_%u",
+                gst::PadDirection::Src,
+                gst::PadPresence::Sometimes,
+                &gst::Caps::builder("application/x-rtp").build(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {transition:?}");
+
+        if transition == gst::StateChange::NullToReady {
+            self.start().map_err(|err| {
+                self.post_error_message(err);
+                gst::StateChangeError
+            })?;
+        }
+
+        let mut success = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::ReadyToPaused | gst::StateChange::PlayingToPaused => {
+                success = gst::StateChangeSuccess::NoPreroll;
+            }
+            gst::StateChange::ReadyToNull => {
+                self.stop();
+            }
+            _ => (),
+        }
+
+        Ok(success)
+    }
+}
+
+impl BinImpl for SapSrc {}
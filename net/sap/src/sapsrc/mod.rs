@@ -0,0 +1,48 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-sapsrc:
+ *
+ * `sapsrc` listens for [SAP] announcements of multicast RTP sessions and receives the first
+ * announced session, or the one named `session-name`. The session is received with `sdpsrc`
+ * from gst-plugins-bad, which provides one `stream_%u` pad per media of the session.
+ *
+ * Once a session is selected, a `sap-session` element message with the `name`, `origin` and
+ * `sdp` of the session is posted on the bus. If the session is deleted by the announcer or
+ * times out, a `sap-session-removed` element message is posted and EOS is sent.
+ *
+ * To list the announced sessions without receiving any of them, use the `sapdeviceprovider`,
+ * e.g. via `gst-device-monitor-1.0 Source/Network`.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 sapsrc session-name="Studio 1" ! rtpjitterbuffer ! rtpL24depay ! \
+ *     audioconvert ! autoaudiosink
+ * ```
+ *
+ * [SAP]: https://datatracker.ietf.org/doc/html/rfc2974
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct SapSrc(ObjectSubclass<imp::SapSrc>) @extends gst::Bin, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "sapsrc",
+        gst::Rank::NONE,
+        SapSrc::static_type(),
+    )
+}
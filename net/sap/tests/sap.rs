@@ -0,0 +1,82 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsap::plugin_register_static().expect("sap test");
+    });
+}
+
+#[test]
+fn test_sink_requires_multicast_address() {
+    init();
+
+    let sink = gst::ElementFactory::make("sapsink").build().unwrap();
+    assert!(sink.set_state(gst::State::Ready).is_err());
+    sink.set_state(gst::State::Null).unwrap();
+
+    let sink = gst::ElementFactory::make("sapsink")
+        .property("address", "127.0.0.1")
+        .build()
+        .unwrap();
+    assert!(sink.set_state(gst::State::Ready).is_err());
+    sink.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_src_invalid_groups() {
+    init();
+
+    let src = gst::ElementFactory::make("sapsrc")
+        .property("sap-groups", "not-an-address")
+        .build()
+        .unwrap();
+    assert!(src.set_state(gst::State::Ready).is_err());
+    src.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_sink_sdp_from_caps() {
+    init();
+
+    let sink = gst::ElementFactory::make("sapsink")
+        .property("address", "239.255.0.1")
+        .property("port", 5004i32)
+        .property("session-name", "Test Session")
+        .property("announce-interval", 1u32)
+        .build()
+        .unwrap();
+
+    let mut h = gst_check::Harness::with_element(&sink, Some("sink"), None);
+    assert_eq!(sink.property::<Option<String>>("sdp"), None);
+
+    h.set_src_caps(
+        gst::Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("payload", 96i32)
+            .field("clock-rate", 48_000i32)
+            .field("encoding-name", "L24")
+            .field("encoding-params", "2")
+            .build(),
+    );
+    h.play();
+
+    let sdp = sink.property::<Option<String>>("sdp").unwrap();
+    assert!(sdp.contains("s=Test Session\r\n"), "{sdp}");
+    assert!(sdp.contains("c=IN IP4 239.255.0.1/1\r\n"), "{sdp}");
+    assert!(sdp.contains("m=audio 5004 RTP/AVP 96\r\n"), "{sdp}");
+    assert!(sdp.contains("a=rtpmap:96 L24/48000/2\r\n"), "{sdp}");
+
+    drop(h);
+    sink.set_state(gst::State::Null).unwrap();
+    assert_eq!(sink.property::<Option<String>>("sdp"), None);
+}
@@ -145,25 +145,51 @@ impl Signaller {
         let mut uri = self.uri();
         uri.set_query(None);
 
-        gst::info!(CAT, imp: self, "connecting to {}", uri.to_string());
-
-        let mut req = uri.into_client_request()?;
-        let req_headers = req.headers_mut();
-        if let Some(headers) = self.headers() {
-            for (key, value) in headers {
-                req_headers.insert(
-                    HeaderName::from_bytes(key.as_bytes()).unwrap(),
-                    HeaderValue::from_bytes(value.as_bytes()).unwrap(),
-                );
+        // Give the application a chance to provide up to date credentials
+        self.refresh_auth_headers(0);
+
+        let mut auth_retried = false;
+        let ws = loop {
+            gst::info!(CAT, imp: self, "connecting to {}", uri.to_string());
+
+            let mut req = uri.clone().into_client_request()?;
+            let req_headers = req.headers_mut();
+            if let Some(headers) = self.headers() {
+                for (key, value) in headers {
+                    req_headers.insert(
+                        HeaderName::from_bytes(key.as_bytes()).unwrap(),
+                        HeaderValue::from_bytes(value.as_bytes()).unwrap(),
+                    );
+                }
             }
-        }
 
-        let (ws, _) = timeout(
-            // FIXME: Make the timeout configurable
-            Duration::from_secs(20),
-            async_tungstenite::tokio::connect_async_with_tls_connector(req, connector),
-        )
-        .await??;
+            let res = timeout(
+                // FIXME: Make the timeout configurable
+                Duration::from_secs(20),
+                async_tungstenite::tokio::connect_async_with_tls_connector(req, connector.clone()),
+            )
+            .await?;
+
+            match res {
+                Ok((ws, _)) => break ws,
+                Err(async_tungstenite::tungstenite::Error::Http(resp))
+                    if !auth_retried && matches!(resp.status().as_u16(), 401 | 403) =>
+                {
+                    let status = resp.status().as_u16() as u32;
+                    gst::warning!(
+                        CAT,
+                        imp: self,
+                        "connection rejected with status {status}, refreshing authentication"
+                    );
+
+                    if !self.refresh_auth_headers(status) {
+                        return Err(anyhow!("Authentication failed with status {status}"));
+                    }
+                    auth_retried = true;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
 
         gst::info!(CAT, imp: self, "connected");
 
@@ -266,6 +292,26 @@ impl Signaller {
         settings.producer_peer_id.clone()
     }
 
+    /// Asks the application for new authentication headers.
+    ///
+    /// `status` is the HTTP status returned by the server, or `0` before connecting.
+    /// Returns `true` if new headers were provided, in which case they replace the
+    /// current `headers`.
+    fn refresh_auth_headers(&self, status: u32) -> bool {
+        let headers = self
+            .obj()
+            .emit_by_name::<Option<gst::Structure>>("refresh-auth-headers", &[&status]);
+
+        match headers {
+            Some(headers) => {
+                gst::debug!(CAT, imp: self, "Got new authentication headers");
+                self.settings.lock().unwrap().headers = Some(headers);
+                true
+            }
+            None => false,
+        }
+    }
+
     fn headers(&self) -> Option<HashMap<String, String>> {
         self.settings
             .lock()
@@ -562,6 +608,31 @@ impl ObjectImpl for Signaller {
         PROPS.as_ref()
     }
 
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![
+                /**
+                 * GstWebRTCSignaller::refresh-auth-headers:
+                 * @status: The HTTP status returned by the server (401 or 403), or 0
+                 *   when the signaller is about to connect.
+                 *
+                 * This signal can be used to supply up to date authentication headers,
+                 * e.g. after a token was rotated. When the server rejects the connection
+                 * with @status, the connection is attempted once more with the new headers.
+                 *
+                 * Returns: (nullable): the headers replacing #GstWebRTCSignaller:headers,
+                 * or %NULL to keep the current ones.
+                 */
+                glib::subclass::Signal::builder("refresh-auth-headers")
+                    .param_types([u32::static_type()])
+                    .return_type::<Option<gst::Structure>>()
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
         match pspec.name() {
             "uri" => {
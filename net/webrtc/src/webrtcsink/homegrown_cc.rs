@@ -92,6 +92,10 @@ pub struct CongestionController {
 
     min_bitrate: u32,
     max_bitrate: u32,
+
+    /// Limits set by the application for all video streams,
+    /// applied on top of the estimation
+    bitrate_limits: Option<(u32, u32)>,
 }
 
 impl CongestionController {
@@ -105,6 +109,32 @@ impl CongestionController {
             peer_id: peer_id.to_string(),
             min_bitrate,
             max_bitrate,
+            bitrate_limits: None,
+        }
+    }
+
+    /// Returns the current bitrate estimation for all video streams.
+    pub fn estimated_bitrate(&self) -> u32 {
+        i32::min(self.target_bitrate_on_delay, self.target_bitrate_on_loss).max(0) as u32
+    }
+
+    /// Sets the limits within which the bitrate for all video streams
+    /// is clamped and applies them immediately.
+    pub fn set_bitrate_limits(
+        &mut self,
+        element: &super::BaseWebRTCSink,
+        encoders: &mut [VideoEncoder],
+        limits: Option<(u32, u32)>,
+    ) {
+        self.bitrate_limits = limits;
+
+        if !encoders.is_empty() {
+            self.apply_control_op(
+                element,
+                encoders,
+                CongestionControlOp::Hold,
+                ControllerType::Delay,
+            );
         }
     }
 
@@ -388,6 +418,14 @@ impl CongestionController {
                 self.max_bitrate as i32 * n_encoders,
             ) / n_encoders;
 
+        let target_bitrate = match self.bitrate_limits {
+            Some((min, max)) => {
+                ((target_bitrate as i64 * n_encoders as i64).clamp(min as i64, max as i64)
+                    / n_encoders as i64) as i32
+            }
+            None => target_bitrate,
+        };
+
         if target_bitrate != prev_bitrate {
             gst::info!(
                 CAT,
@@ -18,6 +18,7 @@ use std::collections::HashMap;
 
 use std::ops::Mul;
 use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
 use super::homegrown_cc::CongestionController;
 use super::{
@@ -61,6 +62,7 @@ const DEFAULT_DO_CLOCK_SIGNALLING: bool = false;
 const DEFAULT_ENABLE_DATA_CHANNEL_NAVIGATION: bool = false;
 const DEFAULT_ICE_TRANSPORT_POLICY: WebRTCICETransportPolicy = WebRTCICETransportPolicy::All;
 const DEFAULT_START_BITRATE: u32 = 2048000;
+const DEFAULT_MIN_KEYFRAME_REQUEST_INTERVAL: u32 = 0;
 /* Start adding some FEC when the bitrate > 2Mbps as we found experimentally
  * that it is not worth it below that threshold */
 #[cfg(feature = "v1_22")]
@@ -89,6 +91,7 @@ struct Settings {
     meta: Option<gst::Structure>,
     ice_transport_policy: WebRTCICETransportPolicy,
     signaller: Signallable,
+    min_keyframe_request_interval: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -247,11 +250,17 @@ struct Session {
     webrtc_pads: HashMap<u32, WebRTCPad>,
     peer_id: String,
     encoders: Vec<VideoEncoder>,
+    min_keyframe_request_interval: Duration,
+    keyframe_request_limiters: Vec<KeyframeRequestLimiter>,
 
     // Our Homegrown controller (if cc_info.heuristic == Homegrown)
     congestion_controller: Option<CongestionController>,
     // Our BandwidthEstimator (if cc_info.heuristic == GoogleCongestionControl)
     rtpgccbwe: Option<gst::Element>,
+    // Last bitrate estimated by rtpgccbwe
+    estimated_bitrate: Option<u32>,
+    // Limits set by the application, applied on top of the estimation
+    bitrate_limits: Option<(u32, u32)>,
 
     sdp: Option<gst_sdp::SDPMessage>,
     stats: gst::Structure,
@@ -520,6 +529,7 @@ impl Default for Settings {
             meta: None,
             ice_transport_policy: DEFAULT_ICE_TRANSPORT_POLICY,
             signaller: signaller.upcast(),
+            min_keyframe_request_interval: DEFAULT_MIN_KEYFRAME_REQUEST_INTERVAL,
         }
     }
 }
@@ -689,6 +699,131 @@ fn add_nv4l2enc_force_keyunit_workaround(enc: &gst::Element) {
         .unwrap();
 }
 
+/// Rate limits upstream force-keyunit events, as generated from incoming
+/// PLI / FIR, before they reach the encoder. Requests received less than
+/// `min_interval` after the last honored one are dropped and aggregated
+/// into a single request, forwarded with the next buffer once the interval
+/// has elapsed.
+#[derive(Debug, Clone)]
+struct KeyframeRequestLimiter {
+    min_interval: Duration,
+    state: Arc<Mutex<KeyframeRequestLimiterState>>,
+}
+
+#[derive(Debug, Default)]
+struct KeyframeRequestLimiterState {
+    last_honored: Option<Instant>,
+    /// Aggregated request waiting for the interval to elapse
+    pending: Option<gst::Event>,
+    /// Sequence number of the aggregated request we are forwarding
+    forwarding: Option<gst::Seqnum>,
+    received: u64,
+    honored: u64,
+}
+
+impl KeyframeRequestLimiter {
+    fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            state: Default::default(),
+        }
+    }
+
+    /// Installs the limiter on `pad`, a sink pad from which the
+    /// keyframe requests are pushed upstream
+    fn install(&self, pad: &gst::Pad) {
+        let state = self.state.clone();
+        let min_interval = self.min_interval;
+
+        pad.add_probe(
+            gst::PadProbeType::BUFFER
+                | gst::PadProbeType::BUFFER_LIST
+                | gst::PadProbeType::EVENT_UPSTREAM,
+            move |pad, info| {
+                match info.data {
+                    Some(gst::PadProbeData::Event(ref ev))
+                        if gst_video::ForceKeyUnitEvent::is(ev) =>
+                    {
+                        let mut state = state.lock().unwrap();
+
+                        if state.forwarding == Some(ev.seqnum()) {
+                            state.forwarding = None;
+                            return gst::PadProbeReturn::Ok;
+                        }
+
+                        state.received += 1;
+
+                        let now = Instant::now();
+                        if state
+                            .last_honored
+                            .map_or(true, |last| now.duration_since(last) >= min_interval)
+                        {
+                            state.last_honored = Some(now);
+                            state.honored += 1;
+                            state.pending = None;
+                        } else {
+                            let all_headers = |ev: &gst::Event| {
+                                gst_video::UpstreamForceKeyUnitEvent::parse(ev)
+                                    .map_or(false, |fku| fku.all_headers)
+                            };
+
+                            // Make sure the aggregated request asks for headers
+                            // if any of the dropped ones did
+                            if state
+                                .pending
+                                .as_ref()
+                                .map_or(true, |pending| !all_headers(pending) && all_headers(ev))
+                            {
+                                state.pending = Some(ev.clone());
+                            }
+
+                            gst::trace!(CAT, obj: pad, "Rate limiting keyframe request");
+
+                            return gst::PadProbeReturn::Drop;
+                        }
+                    }
+                    Some(gst::PadProbeData::Buffer(..))
+                    | Some(gst::PadProbeData::BufferList(..)) => {
+                        let pending = {
+                            let mut state = state.lock().unwrap();
+                            let now = Instant::now();
+
+                            if state.pending.is_some()
+                                && state
+                                    .last_honored
+                                    .map_or(true, |last| now.duration_since(last) >= min_interval)
+                            {
+                                let ev = state.pending.take().unwrap();
+                                state.forwarding = Some(ev.seqnum());
+                                state.last_honored = Some(now);
+                                state.honored += 1;
+                                Some(ev)
+                            } else {
+                                None
+                            }
+                        };
+
+                        if let Some(ev) = pending {
+                            gst::debug!(CAT, obj: pad, "Forwarding aggregated keyframe request");
+                            pad.push_event(ev);
+                        }
+                    }
+                    _ => (),
+                }
+
+                gst::PadProbeReturn::Ok
+            },
+        )
+        .unwrap();
+    }
+
+    /// Returns the number of received and honored keyframe requests
+    fn stats(&self) -> (u64, u64) {
+        let state = self.state.lock().unwrap();
+        (state.received, state.honored)
+    }
+}
+
 /// Default configuration for known encoders, can be disabled
 /// by returning True from an encoder-setup handler.
 fn configure_encoder(enc: &gst::Element, start_bitrate: u32) {
@@ -789,7 +924,7 @@ fn configure_payloader(pay: &gst::Element) {
     pay.set_property("mtu", 1200_u32);
 
     match pay.factory().unwrap().name().as_str() {
-        "rtpvp8pay" | "rtpvp9pay" => {
+        "rtpvp8pay" | "rtpvp9pay" | "rtpvp8pay2" | "rtpvp9pay2" => {
             pay.set_property_from_str("picture-id-mode", "15-bit");
         }
         "rtph264pay" | "rtph265pay" => {
@@ -1201,6 +1336,7 @@ impl Session {
         congestion_controller: Option<CongestionController>,
         rtpgccbwe: Option<gst::Element>,
         cc_info: CCInfo,
+        min_keyframe_request_interval: Duration,
     ) -> Self {
         Self {
             id,
@@ -1208,10 +1344,14 @@ impl Session {
             webrtcbin,
             peer_id,
             cc_info,
+            min_keyframe_request_interval,
+            keyframe_request_limiters: Vec::new(),
             #[cfg(feature = "v1_22")]
             rtprtxsend: None,
             congestion_controller,
             rtpgccbwe,
+            estimated_bitrate: None,
+            bitrate_limits: None,
             stats: gst::Structure::new_empty("application/x-webrtc-stats"),
             sdp: None,
             webrtc_pads: HashMap::new(),
@@ -1233,8 +1373,18 @@ impl Session {
             .map(|s| s.to_send_value())
             .collect::<gst::Array>();
 
+        let (keyframe_requests_received, keyframe_requests_honored) = self
+            .keyframe_request_limiters
+            .iter()
+            .map(KeyframeRequestLimiter::stats)
+            .fold((0u64, 0u64), |acc, stats| {
+                (acc.0 + stats.0, acc.1 + stats.1)
+            });
+
         let our_stats = gst::Structure::builder("application/x-webrtcsink-consumer-stats")
             .field("video-encoders", encoder_stats)
+            .field("keyframe-requests-received", keyframe_requests_received)
+            .field("keyframe-requests-honored", keyframe_requests_honored)
             .build();
 
         ret.set("consumer-stats", our_stats);
@@ -1242,6 +1392,62 @@ impl Session {
         ret
     }
 
+    /// Returns the bitrate estimated for all the video streams, before applying
+    /// the limits set by the application.
+    fn estimated_bitrate(&self) -> u32 {
+        if let Some(congestion_controller) = self.congestion_controller.as_ref() {
+            congestion_controller.estimated_bitrate()
+        } else if self.rtpgccbwe.is_some() {
+            self.estimated_bitrate.unwrap_or(self.cc_info.start_bitrate)
+        } else {
+            self.cc_info
+                .max_bitrate
+                .saturating_mul(self.encoders.len() as u32)
+        }
+    }
+
+    /// Clamps `bitrate` within the limits set by the application, if any.
+    fn clamp_to_limits(&self, bitrate: u32) -> u32 {
+        match self.bitrate_limits {
+            Some((min, max)) => bitrate.clamp(min, max),
+            None => bitrate,
+        }
+    }
+
+    fn gather_bitrate_estimate(&self) -> gst::Structure {
+        let estimated_bitrate = self.estimated_bitrate();
+
+        let mut s = gst::Structure::builder("application/x-webrtcsink-bitrate-estimate")
+            .field("estimated-bitrate", estimated_bitrate)
+            .field("target-bitrate", self.clamp_to_limits(estimated_bitrate))
+            .build();
+
+        if let Some((min, max)) = self.bitrate_limits {
+            s.set("min-bitrate-limit", min);
+            s.set("max-bitrate-limit", max);
+        }
+
+        s
+    }
+
+    /// Applies the limits set by the application to the encoders, when the
+    /// bitrate is not driven by rtpgccbwe.
+    fn apply_bitrate_limits(&mut self, element: &super::BaseWebRTCSink) {
+        if let Some(congestion_controller) = self.congestion_controller.as_mut() {
+            congestion_controller.set_bitrate_limits(
+                element,
+                &mut self.encoders,
+                self.bitrate_limits,
+            );
+        } else if self.rtpgccbwe.is_none() && !self.encoders.is_empty() {
+            let n_encoders = self.encoders.len() as u32;
+            let bitrate = self.clamp_to_limits(self.estimated_bitrate()) / n_encoders;
+            for encoder in self.encoders.iter_mut() {
+                let _ = encoder.set_bitrate(element, bitrate.min(i32::MAX as u32) as i32);
+            }
+        }
+    }
+
     /// Called when we have received an answer, connects an InputStream
     /// to a given WebRTCPad
     fn connect_input_stream(
@@ -1413,6 +1619,10 @@ impl Session {
 
                 self.encoders.push(enc);
 
+                if self.bitrate_limits.is_some() {
+                    self.apply_bitrate_limits(element);
+                }
+
                 if let Some(rtpgccbwe) = self.rtpgccbwe.as_ref() {
                     let max_bitrate = self.cc_info.max_bitrate * (self.encoders.len() as u32);
                     rtpgccbwe.set_property("max-bitrate", max_bitrate);
@@ -1428,6 +1638,12 @@ impl Session {
 
         encoding_chain.pay_filter.link(&pay_filter)?;
 
+        if codec.is_video() {
+            let limiter = KeyframeRequestLimiter::new(self.min_keyframe_request_interval);
+            limiter.install(&pay_filter.static_pad("sink").unwrap());
+            self.keyframe_request_limiters.push(limiter);
+        }
+
         let srcpad = pay_filter.static_pad("src").unwrap();
 
         srcpad
@@ -1452,7 +1668,11 @@ impl Drop for PipelineWrapper {
 
 impl InputStream {
     /// Called when transitioning state up to Paused
-    fn prepare(&mut self, element: &super::BaseWebRTCSink) -> Result<(), Error> {
+    fn prepare(
+        &mut self,
+        element: &super::BaseWebRTCSink,
+        min_keyframe_request_interval: Duration,
+    ) -> Result<(), Error> {
         let clocksync = make_element("clocksync", None)?;
         let appsink = make_element("appsink", None)?
             .downcast::<gst_app::AppSink>()
@@ -1473,6 +1693,13 @@ impl InputStream {
             .set_target(Some(&clocksync.static_pad("sink").unwrap()))
             .unwrap();
 
+        // Pre-encoded streams are shared among all consumers, aggregate
+        // their keyframe requests before they reach the upstream encoder
+        if self.is_video {
+            KeyframeRequestLimiter::new(min_keyframe_request_interval)
+                .install(&appsink.static_pad("sink").unwrap());
+        }
+
         self.producer = Some(StreamProducer::from(&appsink));
 
         Ok(())
@@ -1965,12 +2192,16 @@ impl BaseWebRTCSink {
     fn prepare(&self, element: &super::BaseWebRTCSink) -> Result<(), Error> {
         gst::debug!(CAT, obj: element, "preparing");
 
+        let min_keyframe_request_interval = Duration::from_millis(
+            self.settings.lock().unwrap().min_keyframe_request_interval as u64,
+        );
+
         self.state
             .lock()
             .unwrap()
             .streams
             .iter_mut()
-            .try_for_each(|(_, stream)| stream.prepare(element))?;
+            .try_for_each(|(_, stream)| stream.prepare(element, min_keyframe_request_interval))?;
 
         Ok(())
     }
@@ -2787,6 +3018,7 @@ impl BaseWebRTCSink {
             },
             rtpgccbwe,
             settings.cc_info,
+            Duration::from_millis(settings.min_keyframe_request_interval as u64),
         );
 
         let rtpbin = webrtcbin
@@ -3119,6 +3351,9 @@ impl BaseWebRTCSink {
         if let Some(session) = state.sessions.get_mut(session_id) {
             let session = session.unwrap_mut();
 
+            session.estimated_bitrate = Some(bitrate);
+            let bitrate = session.clamp_to_limits(bitrate);
+
             let n_encoders = session.encoders.len();
 
             let fec_ratio = {
@@ -3671,6 +3906,71 @@ impl BaseWebRTCSink {
         )
     }
 
+    fn gather_bitrate_estimates(&self) -> gst::Structure {
+        gst::Structure::from_iter(
+            "application/x-webrtcsink-bitrate-estimates",
+            self.state
+                .lock()
+                .unwrap()
+                .sessions
+                .iter()
+                .map(|(name, consumer)| {
+                    (
+                        name.as_str(),
+                        consumer.unwrap().gather_bitrate_estimate().to_send_value(),
+                    )
+                }),
+        )
+    }
+
+    /// Called by the application to clamp or override the bitrate of a session
+    fn set_bitrate_limits(
+        &self,
+        element: &super::BaseWebRTCSink,
+        session_id: &str,
+        min_bitrate: u32,
+        max_bitrate: u32,
+    ) -> bool {
+        let limits = match (min_bitrate, max_bitrate) {
+            (0, 0) => None,
+            (min, max) if min <= max => Some((min, max)),
+            (min, max) => {
+                gst::warning!(
+                    CAT,
+                    obj: element,
+                    "Invalid bitrate limits for session {session_id}: min {min} > max {max}"
+                );
+                return false;
+            }
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let Some(session) = state.sessions.get_mut(session_id) else {
+            gst::warning!(CAT, obj: element, "No session with id {session_id}");
+            return false;
+        };
+        let session = session.unwrap_mut();
+
+        gst::info!(
+            CAT,
+            obj: element,
+            "Setting bitrate limits for session {session_id}: {limits:?}"
+        );
+        session.bitrate_limits = limits;
+
+        #[cfg(feature = "v1_22")]
+        if session.rtpgccbwe.is_some() {
+            let estimated_bitrate = session.estimated_bitrate();
+            drop(state);
+            self.set_bitrate(element, session_id, estimated_bitrate);
+            return true;
+        }
+
+        session.apply_bitrate_limits(element);
+
+        true
+    }
+
     /// Check if the caps of a sink pad can be changed from `current` to `new` without requiring a WebRTC renegotiation
     fn input_caps_change_allowed(&self, current: &gst::CapsRef, new: &gst::CapsRef) -> bool {
         let Some(current) = current.structure(0) else {
@@ -3956,6 +4256,11 @@ impl ObjectImpl for BaseWebRTCSink {
                     .blurb("Statistics for the current consumers")
                     .read_only()
                     .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("bitrate-estimates")
+                    .nick("Bitrate estimates")
+                    .blurb("Bitrate estimated by the congestion control for the current consumers")
+                    .read_only()
+                    .build(),
                 glib::ParamSpecBoolean::builder("do-fec")
                     .nick("Do Forward Error Correction")
                     .blurb("Whether the element should negotiate and send FEC data")
@@ -3989,6 +4294,13 @@ impl ObjectImpl for BaseWebRTCSink {
                     .blurb("The policy to apply for ICE transport")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecUInt::builder("min-keyframe-request-interval")
+                    .nick("Minimum keyframe request interval")
+                    .blurb("Minimum interval (in ms) between keyframe requests (PLI / FIR) forwarded to the encoder, \
+                        requests received in between are aggregated (0 = no rate limiting)")
+                    .default_value(DEFAULT_MIN_KEYFRAME_REQUEST_INTERVAL)
+                    .mutable_ready()
+                    .build(),
                 glib::ParamSpecObject::builder::<Signallable>("signaller")
                     .flags(glib::ParamFlags::READABLE | gst::PARAM_FLAG_MUTABLE_READY)
                     .blurb("The Signallable object to use to handle WebRTC Signalling")
@@ -4072,6 +4384,11 @@ impl ObjectImpl for BaseWebRTCSink {
                     .get::<WebRTCICETransportPolicy>()
                     .expect("type checked upstream");
             }
+            "min-keyframe-request-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.min_keyframe_request_interval =
+                    value.get::<u32>().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -4127,6 +4444,7 @@ impl ObjectImpl for BaseWebRTCSink {
                 settings.enable_data_channel_navigation.to_value()
             }
             "stats" => self.gather_stats().to_value(),
+            "bitrate-estimates" => self.gather_bitrate_estimates().to_value(),
             "meta" => {
                 let settings = self.settings.lock().unwrap();
                 settings.meta.to_value()
@@ -4135,6 +4453,10 @@ impl ObjectImpl for BaseWebRTCSink {
                 let settings = self.settings.lock().unwrap();
                 settings.ice_transport_policy.to_value()
             }
+            "min-keyframe-request-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.min_keyframe_request_interval.to_value()
+            }
             "signaller" => self.settings.lock().unwrap().signaller.to_value(),
             _ => unimplemented!(),
         }
@@ -4208,6 +4530,46 @@ impl ObjectImpl for BaseWebRTCSink {
                     })
                     .return_type::<Vec<String>>()
                     .build(),
+                /**
+                 * GstBaseWebRTCSink::set-bitrate-limits:
+                 * @session_id: Identifier of the session
+                 * @min_bitrate: The minimal bitrate (in bit/sec) for all video streams
+                 * @max_bitrate: The maximal bitrate (in bit/sec) for all video streams
+                 *
+                 * Clamps the bitrate computed by the congestion control for the
+                 * session within [@min_bitrate, @max_bitrate]. Setting both to the
+                 * same value overrides the estimation, setting both to 0 removes
+                 * the limits.
+                 *
+                 * The current estimation can be read from
+                 * #GstBaseWebRTCSink:bitrate-estimates.
+                 *
+                 * Returns: True if the limits were applied
+                 */
+                glib::subclass::Signal::builder("set-bitrate-limits")
+                    .param_types([
+                        String::static_type(),
+                        u32::static_type(),
+                        u32::static_type(),
+                    ])
+                    .action()
+                    .class_handler(|_, args| {
+                        let element = args[0].get::<super::BaseWebRTCSink>().expect("signal arg");
+                        let session_id = args[1].get::<String>().expect("signal arg");
+                        let min_bitrate = args[2].get::<u32>().expect("signal arg");
+                        let max_bitrate = args[3].get::<u32>().expect("signal arg");
+
+                        let res = element.imp().set_bitrate_limits(
+                            &element,
+                            &session_id,
+                            min_bitrate,
+                            max_bitrate,
+                        );
+
+                        Some(res.to_value())
+                    })
+                    .return_type::<bool>()
+                    .build(),
                 /**
                  * GstBaseWebRTCSink::encoder-setup:
                  * @consumer_id: Identifier of the consumer, or "discovery"
@@ -4669,6 +5031,32 @@ pub(super) mod whip {
 
             Some(&*ELEMENT_METADATA)
         }
+
+        fn request_new_pad(
+            &self,
+            templ: &gst::PadTemplate,
+            name: Option<&str>,
+            caps: Option<&gst::Caps>,
+        ) -> Option<gst::Pad> {
+            let pad = self.parent_request_new_pad(templ, name, caps)?;
+
+            // WHIP endpoints map each MediaStream to a published stream, so group the
+            // tracks with the same index, e.g. audio_0 with video_0, and give further
+            // tracks a MediaStream of their own unless the application chose one.
+            if pad.property::<Option<String>>("msid").is_none() {
+                let index = pad
+                    .name()
+                    .rsplit_once('_')
+                    .map(|(_, index)| index.to_string())
+                    .unwrap_or_default();
+                let msid = format!("{}-{}", self.obj().name(), index);
+
+                gst::debug!(CAT, imp: self, "Using msid {msid} for pad {}", pad.name());
+                pad.set_property("msid", msid);
+            }
+
+            Some(pad)
+        }
     }
 
     impl BinImpl for WhipWebRTCSink {}
@@ -15,7 +15,7 @@ use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 use std::sync::atomic::AtomicU16;
 use std::sync::atomic::Ordering;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use url::Url;
 
 const DEFAULT_STUN_SERVER: Option<&str> = Some("stun://stun.l.google.com:19302");
@@ -113,7 +113,8 @@ impl ObjectImpl for BaseWebRTCSrc {
                     .build(),
                 glib::ParamSpecBoolean::builder("do-retransmission")
                     .nick("Enable retransmission")
-                    .blurb("Send retransmission events upstream when a packet is late")
+                    .blurb("Send retransmission events upstream when a packet is late, \
+                        default for the `do-retransmission` property of new pads")
                     .default_value(DEFAULT_DO_RETRANSMISSION)
                     .mutable_ready()
                     .build(),
@@ -242,6 +243,17 @@ impl ObjectImpl for BaseWebRTCSrc {
                     ])
                     .return_type::<gst::Element>()
                     .build(),
+                /**
+                 * GstBaseWebRTCSrc::session-ended:
+                 * @session_id: Identifier of the session
+                 *
+                 * This signal is emitted once a session ended and the source
+                 * pads of the session, which can be identified thanks to
+                 * #GstWebRTCSrcPad:session-id, were removed.
+                 */
+                glib::subclass::Signal::builder("session-ended")
+                    .param_types([String::static_type()])
+                    .build(),
             ]
         });
 
@@ -312,6 +324,7 @@ impl Session {
             n_video_pads: AtomicU16::new(0),
             n_audio_pads: AtomicU16::new(0),
             flow_combiner: Mutex::new(gst_base::UniqueFlowCombiner::new()),
+            jitterbuffers: Default::default(),
         })
     }
 
@@ -472,6 +485,23 @@ impl Session {
         }
 
         if let Some(srcpad) = srcpad {
+            if let Some(caps) = webrtcbin_pad.current_caps() {
+                configure_jitterbuffer(&self.jitterbuffers, &srcpad, &caps, element);
+            }
+            webrtcbin_pad.add_probe(
+                gst::PadProbeType::EVENT_DOWNSTREAM,
+                glib::clone!(@weak element, @weak srcpad, @strong self.jitterbuffers as jitterbuffers => @default-return gst::PadProbeReturn::Remove, move |_pad, info| {
+                    let Some(ev) = info.event() else {
+                        return gst::PadProbeReturn::Ok;
+                    };
+                    if let gst::EventView::Caps(ev) = ev.view() {
+                        configure_jitterbuffer(&jitterbuffers, &srcpad, ev.caps(), &element);
+                    }
+
+                    gst::PadProbeReturn::Ok
+                }),
+            );
+
             let signaller = element.imp().signaller();
 
             // Signalers like WhipServer do not need a peer producer id as they run as a server
@@ -594,17 +624,14 @@ impl Session {
         let direction = gst_webrtc::WebRTCRTPTransceiverDirection::Recvonly;
         let webrtcbin = self.webrtcbin();
         for (i, media) in sdp.medias().enumerate() {
-            let (codec_names, do_retransmission) = {
+            let codec_names = {
                 let settings = element.imp().settings.lock().unwrap();
-                (
-                    settings
-                        .video_codecs
-                        .iter()
-                        .chain(settings.audio_codecs.iter())
-                        .map(|codec| codec.name.clone())
-                        .collect::<HashSet<String>>(),
-                    settings.do_retransmission,
-                )
+                settings
+                    .video_codecs
+                    .iter()
+                    .chain(settings.audio_codecs.iter())
+                    .map(|codec| codec.name.clone())
+                    .collect::<HashSet<String>>()
             };
             let caps = media
                 .formats()
@@ -654,7 +681,7 @@ impl Session {
 
             if !caps.is_empty() {
                 let stream_id = self.get_stream_id(None, Some(i as u32)).unwrap();
-                if element
+                if let Some(srcpad) = element
                     .imp()
                     .create_and_probe_src_pad(&caps, &stream_id, self)
                 {
@@ -668,7 +695,8 @@ impl Session {
                         &[&direction, &caps],
                     );
 
-                    transceiver.set_property("do_nack", do_retransmission);
+                    // The settings of the pad were possibly updated from `pad-added`
+                    transceiver.set_property("do_nack", srcpad.imp().do_retransmission());
                     transceiver.set_property("fec-type", gst_webrtc::WebRTCFECType::UlpRed);
                 }
             } else {
@@ -893,6 +921,9 @@ impl BaseWebRTCSrc {
                     {
                         this.state.lock().unwrap().sessions.remove(session_id);
                     }
+
+                    instance.emit_by_name::<()>("session-ended", &[&session_id]);
+
                     true
                 }),
             ),
@@ -954,14 +985,14 @@ impl BaseWebRTCSrc {
         // previous signals are disconnected when dropping the old structure
     }
 
-    // Creates and adds our `WebRTCSrcPad` source pad, returning caps accepted
+    // Creates and adds our `WebRTCSrcPad` source pad, probing the caps accepted
     // downstream
     fn create_and_probe_src_pad(
         &self,
         caps: &gst::Caps,
         stream_id: &str,
         session: &Session,
-    ) -> bool {
+    ) -> Option<WebRTCSrcPad> {
         gst::log!(CAT, "Creating pad for {caps:?}, stream: {stream_id}");
 
         let obj = self.obj();
@@ -994,7 +1025,7 @@ impl BaseWebRTCSrc {
         } else {
             gst::info!(CAT, imp: self, "Not an audio or video media {media_type:?}");
 
-            return false;
+            return None;
         };
 
         let caps_with_raw = [caps.clone(), raw_caps.clone()]
@@ -1006,6 +1037,11 @@ impl BaseWebRTCSrc {
             .downcast::<WebRTCSrcPad>()
             .unwrap();
         ghost.imp().set_stream_id(stream_id);
+        ghost.imp().set_session_id(&session.id);
+        ghost.set_property(
+            "do-retransmission",
+            self.settings.lock().unwrap().do_retransmission,
+        );
         obj.add_pad(&ghost)
             .expect("Adding ghost pad should never fail");
 
@@ -1016,7 +1052,7 @@ impl BaseWebRTCSrc {
             }
         }
 
-        true
+        Some(ghost)
     }
 
     fn maybe_start_signaller(&self) {
@@ -1077,6 +1113,23 @@ impl BaseWebRTCSrc {
             }
         }
 
+        if let Some(rtpbin) = webrtcbin.by_name("rtpbin") {
+            let jitterbuffers = session.jitterbuffers.clone();
+            rtpbin.connect_closure(
+                "new-jitterbuffer",
+                false,
+                glib::closure!(move |_rtpbin: gst::Element,
+                                     jitterbuffer: gst::Element,
+                                     _session: u32,
+                                     ssrc: u32| {
+                    jitterbuffers
+                        .lock()
+                        .unwrap()
+                        .insert(ssrc, jitterbuffer.downgrade());
+                }),
+            );
+        }
+
         let bin = gst::Bin::new();
 
         bin.connect_pad_removed(
@@ -1347,6 +1400,8 @@ struct Session {
     n_video_pads: AtomicU16,
     n_audio_pads: AtomicU16,
     flow_combiner: Mutex<gst_base::UniqueFlowCombiner>,
+    // Jitterbuffers created by the `rtpbin`, by SSRC
+    jitterbuffers: Arc<Mutex<HashMap<u32, glib::WeakRef<gst::Element>>>>,
 }
 struct State {
     sessions: HashMap<String, Session>,
@@ -1364,6 +1419,35 @@ impl Default for State {
     }
 }
 
+// Applies the settings of `srcpad` to the jitterbuffer handling the SSRC from `caps`
+fn configure_jitterbuffer(
+    jitterbuffers: &Mutex<HashMap<u32, glib::WeakRef<gst::Element>>>,
+    srcpad: &WebRTCSrcPad,
+    caps: &gst::CapsRef,
+    element: &super::BaseWebRTCSrc,
+) {
+    let Some(ssrc) = caps.structure(0).and_then(|s| s.get::<u32>("ssrc").ok()) else {
+        return;
+    };
+
+    let jitterbuffer = jitterbuffers
+        .lock()
+        .unwrap()
+        .get(&ssrc)
+        .and_then(|jitterbuffer| jitterbuffer.upgrade());
+    if let Some(jitterbuffer) = jitterbuffer {
+        gst::debug!(
+            CAT,
+            obj: element,
+            "Configuring jitterbuffer for ssrc {ssrc} from {}",
+            srcpad.name()
+        );
+        srcpad.imp().set_jitterbuffer(&jitterbuffer);
+    } else {
+        gst::debug!(CAT, obj: element, "No jitterbuffer for ssrc {ssrc}");
+    }
+}
+
 #[derive(Default)]
 pub struct WebRTCSrc {}
 
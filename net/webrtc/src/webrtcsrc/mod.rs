@@ -31,6 +31,14 @@
  * in `decodebinX` but for the case where a `videoconvert` is placed after a `video_XX` pad,
  * decoding will happen inside `webrtcsrc`.
  *
+ * ## Jitterbuffer configuration
+ *
+ * The jitterbuffer of each incoming stream can be configured through the `latency`,
+ * `drop-on-latency` and `do-retransmission` properties of the matching #GstWebRTCSrcPad,
+ * e.g. to buffer audio and video differently. The properties should be set from a
+ * `pad-added` handler so that they are taken into account during SDP negotiation,
+ * they can still be updated afterwards.
+ *
  * Since: 0.10
  */
 mod imp;
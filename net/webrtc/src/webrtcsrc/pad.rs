@@ -1,15 +1,42 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use gst::glib;
+use gst::prelude::*;
 use gst::subclass::prelude::*;
+use once_cell::sync::Lazy;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Mutex;
 
+// Same defaults as rtpjitterbuffer
+const DEFAULT_LATENCY: u32 = 200;
+const DEFAULT_DROP_ON_LATENCY: bool = false;
+const DEFAULT_DO_RETRANSMISSION: bool = true;
+
+#[derive(Debug, Clone)]
+struct JitterBufferSettings {
+    latency: u32,
+    drop_on_latency: bool,
+    do_retransmission: bool,
+}
+
+impl Default for JitterBufferSettings {
+    fn default() -> Self {
+        Self {
+            latency: DEFAULT_LATENCY,
+            drop_on_latency: DEFAULT_DROP_ON_LATENCY,
+            do_retransmission: DEFAULT_DO_RETRANSMISSION,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct WebRTCSrcPad {
     needs_raw: AtomicBool,
     stream_id: Mutex<Option<String>>,
+    session_id: Mutex<Option<String>>,
+    settings: Mutex<JitterBufferSettings>,
+    jitterbuffer: Mutex<Option<glib::WeakRef<gst::Element>>>,
 }
 
 impl WebRTCSrcPad {
@@ -29,6 +56,30 @@ impl WebRTCSrcPad {
         let stream_id = self.stream_id.lock().unwrap();
         stream_id.as_ref().unwrap().clone()
     }
+
+    pub fn set_session_id(&self, session_id: &str) {
+        *self.session_id.lock().unwrap() = Some(session_id.to_string());
+    }
+
+    pub fn do_retransmission(&self) -> bool {
+        self.settings.lock().unwrap().do_retransmission
+    }
+
+    /// Applies the settings of this pad to `jitterbuffer` and keeps
+    /// it up to date with further changes.
+    pub fn set_jitterbuffer(&self, jitterbuffer: &gst::Element) {
+        let settings = self.settings.lock().unwrap();
+        Self::configure_jitterbuffer(jitterbuffer, &settings);
+        *self.jitterbuffer.lock().unwrap() = Some(jitterbuffer.downgrade());
+    }
+
+    fn configure_jitterbuffer(jitterbuffer: &gst::Element, settings: &JitterBufferSettings) {
+        jitterbuffer.set_properties(&[
+            ("latency", &settings.latency),
+            ("drop-on-latency", &settings.drop_on_latency),
+            ("do-retransmission", &settings.do_retransmission),
+        ]);
+    }
 }
 
 #[glib::object_subclass]
@@ -38,7 +89,80 @@ impl ObjectSubclass for WebRTCSrcPad {
     type ParentType = gst::GhostPad;
 }
 
-impl ObjectImpl for WebRTCSrcPad {}
+impl ObjectImpl for WebRTCSrcPad {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPS: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt::builder("latency")
+                    .nick("Latency")
+                    .blurb("Amount of ms to buffer in the jitterbuffer of this stream")
+                    .default_value(DEFAULT_LATENCY)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("drop-on-latency")
+                    .nick("Drop buffers when maximum latency is reached")
+                    .blurb("Tells the jitterbuffer of this stream to never exceed the given latency in size")
+                    .default_value(DEFAULT_DROP_ON_LATENCY)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("do-retransmission")
+                    .nick("Enable retransmission")
+                    .blurb("Send retransmission events upstream when a packet of this stream is late")
+                    .default_value(DEFAULT_DO_RETRANSMISSION)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("session-id")
+                    .nick("Session ID")
+                    .blurb("Identifier of the session this pad belongs to")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPS.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "latency" => {
+                settings.latency = value.get::<u32>().expect("type checked upstream");
+            }
+            "drop-on-latency" => {
+                settings.drop_on_latency = value.get::<bool>().expect("type checked upstream");
+            }
+            "do-retransmission" => {
+                settings.do_retransmission = value.get::<bool>().expect("type checked upstream");
+            }
+            name => panic!("no writable property {name:?}"),
+        }
+
+        let jitterbuffer = self
+            .jitterbuffer
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|jitterbuffer| jitterbuffer.upgrade());
+        if let Some(jitterbuffer) = jitterbuffer {
+            Self::configure_jitterbuffer(&jitterbuffer, &settings);
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        if pspec.name() == "session-id" {
+            return self.session_id.lock().unwrap().to_value();
+        }
+
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "latency" => settings.latency.to_value(),
+            "drop-on-latency" => settings.drop_on_latency.to_value(),
+            "do-retransmission" => settings.do_retransmission.to_value(),
+            name => panic!("no readable property {name:?}"),
+        }
+    }
+}
+
 impl GstObjectImpl for WebRTCSrcPad {}
 impl PadImpl for WebRTCSrcPad {}
 impl ProxyPadImpl for WebRTCSrcPad {}
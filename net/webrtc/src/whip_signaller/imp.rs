@@ -16,6 +16,7 @@ use once_cell::sync::Lazy;
 use reqwest::header::HeaderMap;
 use reqwest::header::HeaderValue;
 use reqwest::StatusCode;
+use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
 
 use std::net::SocketAddr;
@@ -40,6 +41,7 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 
 const MAX_REDIRECTS: u8 = 10;
 const DEFAULT_TIMEOUT: u32 = 15;
+const DEFAULT_MAX_SESSIONS: u32 = 0;
 
 const ROOT: &str = "whip";
 const ENDPOINT_PATH: &str = "endpoint";
@@ -52,7 +54,7 @@ const CONTENT_TRICKLE_ICE: &str = "application/trickle-ice-sdpfrag";
 #[derive(Debug)]
 enum WhipClientState {
     Stopped,
-    Post { redirects: u8 },
+    Post { redirects: u8, auth_retried: bool },
     Running { whip_resource_url: String },
 }
 
@@ -103,10 +105,32 @@ impl WhipClient {
         };
     }
 
+    /// Asks the application for a new authentication token.
+    ///
+    /// `status` is the HTTP status returned by the server, or `0` before sending a request.
+    /// Returns `true` if a new token was provided, in which case it replaces `auth-token`.
+    fn refresh_auth_token(&self, status: u32) -> bool {
+        let token = self
+            .obj()
+            .emit_by_name::<Option<String>>("refresh-auth-token", &[&status]);
+
+        match token {
+            Some(token) => {
+                gst::debug!(CAT, imp: self, "Got new authentication token");
+                self.settings.lock().unwrap().auth_token = Some(token);
+                true
+            }
+            None => false,
+        }
+    }
+
     async fn send_offer(&self, webrtcbin: &gst::Element) {
         {
             let mut state = self.state.lock().unwrap();
-            *state = WhipClientState::Post { redirects: 0 };
+            *state = WhipClientState::Post {
+                redirects: 0,
+                auth_retried: false,
+            };
             drop(state);
         }
 
@@ -156,6 +180,9 @@ impl WhipClient {
         webrtcbin: &gst::Element,
         endpoint: reqwest::Url,
     ) {
+        // Give the application a chance to provide an up to date token
+        self.refresh_auth_token(0);
+
         let auth_token;
 
         {
@@ -170,7 +197,7 @@ impl WhipClient {
         {
             let state = self.state.lock().unwrap();
             redirects = match *state {
-                WhipClientState::Post { redirects } => redirects,
+                WhipClientState::Post { redirects, .. } => redirects,
                 _ => {
                     self.raise_error("Trying to do POST in unexpected state".to_string());
                     return;
@@ -290,7 +317,7 @@ impl WhipClient {
                 {
                     let mut state = self.state.lock().unwrap();
                     *state = match *state {
-                        WhipClientState::Post { redirects: _r } => WhipClientState::Running {
+                        WhipClientState::Post { .. } => WhipClientState::Running {
                             whip_resource_url: url.to_string(),
                         },
                         _ => {
@@ -304,6 +331,11 @@ impl WhipClient {
                 match resp.bytes().await {
                     Ok(ans_bytes) => match gst_sdp::SDPMessage::parse_buffer(&ans_bytes) {
                         Ok(ans_sdp) => {
+                            if let Err(err) = self.check_answer(&offer, &ans_sdp) {
+                                self.raise_error(err);
+                                return;
+                            }
+
                             let answer = gst_webrtc::WebRTCSessionDescription::new(
                                 gst_webrtc::WebRTCSDPType::Answer,
                                 ans_sdp,
@@ -328,9 +360,10 @@ impl WhipClient {
                             {
                                 let mut state = self.state.lock().unwrap();
                                 *state = match *state {
-                                    WhipClientState::Post { redirects: _r } => {
+                                    WhipClientState::Post { auth_retried, .. } => {
                                         WhipClientState::Post {
                                             redirects: redirects + 1,
+                                            auth_retried,
                                         }
                                     }
                                     /*
@@ -366,6 +399,31 @@ impl WhipClient {
                 }
             }
 
+            s @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) if !self.auth_retried() => {
+                let status = s.as_u16() as u32;
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "POST rejected with status {status}, refreshing authentication"
+                );
+
+                if !self.refresh_auth_token(status) {
+                    self.raise_error(format!("Authentication failed: {}", s.as_str()));
+                    return;
+                }
+
+                {
+                    let mut state = self.state.lock().unwrap();
+                    if let WhipClientState::Post { auth_retried, .. } = &mut *state {
+                        *auth_retried = true;
+                    }
+                }
+
+                // Retry the same endpoint, which might result from a redirection
+                let endpoint = resp.url().clone();
+                self.do_post(offer, webrtcbin, endpoint).await
+            }
+
             s => {
                 match resp.bytes().await {
                     Ok(r) => {
@@ -380,7 +438,61 @@ impl WhipClient {
         }
     }
 
+    /// Checks that the answer covers all the media of the offer.
+    ///
+    /// Endpoints may reject some of the media, e.g. an audio-only ingest rejecting video,
+    /// in which case the other media are still sent.
+    fn check_answer(
+        &self,
+        offer: &gst_webrtc::WebRTCSessionDescription,
+        answer: &gst_sdp::SDPMessage,
+    ) -> Result<(), String> {
+        let offer = offer.sdp();
+
+        if answer.medias_len() != offer.medias_len() {
+            return Err(format!(
+                "Answer has {} media sections while the offer has {}",
+                answer.medias_len(),
+                offer.medias_len()
+            ));
+        }
+
+        let mut accepted = 0;
+        for (idx, media) in answer.medias().enumerate() {
+            if media.port() == 0 {
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Endpoint rejected {} media {} (mid {})",
+                    media.media().unwrap_or("unknown"),
+                    idx,
+                    media.attribute_val("mid").unwrap_or("none"),
+                );
+            } else {
+                accepted += 1;
+            }
+        }
+
+        if accepted == 0 {
+            return Err("Endpoint rejected all media".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn auth_retried(&self) -> bool {
+        matches!(
+            *self.state.lock().unwrap(),
+            WhipClientState::Post {
+                auth_retried: true,
+                ..
+            }
+        )
+    }
+
     fn terminate_session(&self) {
+        self.refresh_auth_token(0);
+
         let settings = self.settings.lock().unwrap();
         let state = self.state.lock().unwrap();
         let timeout = settings.timeout;
@@ -573,6 +685,31 @@ impl ObjectImpl for WhipClient {
         PROPERTIES.as_ref()
     }
 
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![
+                /**
+                 * GstWhipClientSignaller::refresh-auth-token:
+                 * @status: The HTTP status returned by the server (401 or 403), or 0
+                 *   when the signaller is about to send a request.
+                 *
+                 * This signal can be used to supply an up to date authentication token,
+                 * e.g. before the current one expires. When the server rejects the offer
+                 * with @status, the offer is sent once more with the new token.
+                 *
+                 * Returns: (nullable): the token replacing #GstWhipClientSignaller:auth-token,
+                 * or %NULL to keep the current one.
+                 */
+                glib::subclass::Signal::builder("refresh-auth-token")
+                    .param_types([u32::static_type()])
+                    .return_type::<Option<String>>()
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
         match pspec.name() {
             "whip-endpoint" => {
@@ -627,9 +764,13 @@ struct WhipServerSettings {
     turn_servers: gst::Array,
     host_addr: Url,
     timeout: u32,
+    max_sessions: u32,
     shutdown_signal: Option<tokio::sync::oneshot::Sender<()>>,
     server_handle: Option<tokio::task::JoinHandle<()>>,
-    sdp_answer: Option<mpsc::Sender<Option<SDPMessage>>>,
+    /// Pending SDP answers, by session id
+    sdp_answers: HashMap<String, mpsc::Sender<Option<SDPMessage>>>,
+    /// Ids of the sessions with a WHIP resource
+    sessions: HashSet<String>,
 }
 
 impl Default for WhipServerSettings {
@@ -639,9 +780,11 @@ impl Default for WhipServerSettings {
             stun_server: DEFAULT_STUN_SERVER.map(String::from),
             turn_servers: gst::Array::new(Vec::new() as Vec<glib::SendValue>),
             timeout: DEFAULT_TIMEOUT,
+            max_sessions: DEFAULT_MAX_SESSIONS,
             shutdown_signal: None,
             server_handle: None,
-            sdp_answer: None,
+            sdp_answers: HashMap::new(),
+            sessions: HashSet::new(),
         }
     }
 }
@@ -649,15 +792,15 @@ impl Default for WhipServerSettings {
 #[derive(Default)]
 pub struct WhipServer {
     settings: Mutex<WhipServerSettings>,
-    canceller: Mutex<Option<futures::future::AbortHandle>>,
 }
 
 impl WhipServer {
     pub fn on_webrtcbin_ready(&self) -> RustClosure {
         glib::closure!(|signaller: &super::WhipServerSignaller,
-                        _producer_identifier: &str,
+                        session_id: &str,
                         webrtcbin: &gst::Element| {
             let obj_weak = signaller.downgrade();
+            let session_id = session_id.to_string();
             webrtcbin.connect_notify(Some("ice-gathering-state"), move |webrtcbin, _pspec| {
                 let obj = match obj_weak.upgrade() {
                     Some(obj) => obj,
@@ -681,10 +824,10 @@ impl WhipServer {
                         } else {
                             ans = None;
                         }
-                        let tx = settings
-                            .sdp_answer
-                            .take()
-                            .expect("SDP answer Sender needs to be valid");
+                        let Some(tx) = settings.sdp_answers.remove(&session_id) else {
+                            gst::error!(CAT, obj: obj, "No pending answer for session {session_id}");
+                            return;
+                        };
 
                         let obj_weak = obj.downgrade();
                         RUNTIME.spawn(async move {
@@ -715,6 +858,13 @@ impl WhipServer {
     }
 
     async fn delete_handler(&self, id: String) -> Result<impl warp::Reply, warp::Rejection> {
+        if !self.settings.lock().unwrap().sessions.remove(&id) {
+            gst::info!(CAT, imp: self, "Unknown session {id}");
+            let reply = warp::reply::reply();
+            let res = warp::reply::with_status(reply, http::StatusCode::NOT_FOUND);
+            return Ok(res.into_response());
+        }
+
         if self
             .obj()
             .emit_by_name::<bool>("session-ended", &[&id.as_str()])
@@ -782,8 +932,23 @@ impl WhipServer {
         let (tx, mut rx) = mpsc::channel::<Option<SDPMessage>>(1);
         let wait_timeout = {
             let mut settings = self.settings.lock().unwrap();
+            let max_sessions = settings.max_sessions as usize;
+            if max_sessions > 0 && settings.sessions.len() >= max_sessions {
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Rejecting new session: {max_sessions} sessions already running"
+                );
+                let res = http::Response::builder()
+                    .status(http::StatusCode::SERVICE_UNAVAILABLE)
+                    .body(Body::from("Too many sessions"))
+                    .unwrap();
+                return Ok(res);
+            }
+
             let wait_timeout = settings.timeout;
-            settings.sdp_answer = Some(tx);
+            settings.sdp_answers.insert(session_id.clone(), tx);
+            settings.sessions.insert(session_id.clone());
             drop(settings);
             wait_timeout
         };
@@ -802,18 +967,22 @@ impl WhipServer {
             }
             Err(err) => {
                 gst::error!(CAT, imp: self, "Could not parse offer SDP: {err}");
+                self.remove_session(&session_id);
                 let reply = warp::reply::reply();
                 let res = warp::reply::with_status(reply, http::StatusCode::NOT_ACCEPTABLE);
                 return Ok(res.into_response());
             }
         }
 
-        let result = wait_async(&self.canceller, rx.recv(), wait_timeout).await;
+        // Each session waits for its own answer
+        let canceller = Mutex::new(None);
+        let result = wait_async(&canceller, rx.recv(), wait_timeout).await;
 
         let answer = match result {
             Ok(ans) => match ans {
                 Some(a) => a,
                 None => {
+                    self.remove_session(&session_id);
                     let err = "Channel closed, can't receive SDP".to_owned();
                     let res = http::Response::builder()
                         .status(http::StatusCode::INTERNAL_SERVER_ERROR)
@@ -824,6 +993,7 @@ impl WhipServer {
                 }
             },
             Err(e) => {
+                self.remove_session(&session_id);
                 let err = match e {
                     WaitError::FutureAborted => "Aborted".to_owned(),
                     WaitError::FutureError(err) => err.to_string(),
@@ -891,8 +1061,11 @@ impl WhipServer {
             ans_text = Err(e);
         }
 
+        drop(settings);
+
         // If ans_text is an error. Send error code and error string in the response
         if let Err(e) = ans_text {
+            self.remove_session(&session_id);
             let res = http::Response::builder()
                 .status(http::StatusCode::INTERNAL_SERVER_ERROR)
                 .body(Body::from(e))
@@ -900,8 +1073,6 @@ impl WhipServer {
             return Ok(res);
         }
 
-        drop(settings);
-
         // Got SDP answer, send answer in the response
         let resource_url = "/".to_owned() + ROOT + "/" + RESOURCE_PATH + "/" + &session_id;
         let mut res = http::Response::builder()
@@ -1027,6 +1198,12 @@ impl WhipServer {
         Some(jh)
     }
 
+    fn remove_session(&self, session_id: &str) {
+        let mut settings = self.settings.lock().unwrap();
+        settings.sdp_answers.remove(session_id);
+        settings.sessions.remove(session_id);
+    }
+
     fn set_host_addr(&self, host_addr: &str) -> Result<(), url::ParseError> {
         let mut settings = self.settings.lock().unwrap();
         settings.host_addr = Url::parse(host_addr)?;
@@ -1071,6 +1248,7 @@ impl SignallableImpl for WhipServer {
 
     fn end_session(&self, session_id: &str) {
         gst::info!(CAT, imp: self, "Session {session_id} ended");
+        self.remove_session(session_id);
         //FIXME: send any events to the client
     }
 }
@@ -1120,6 +1298,11 @@ impl ObjectImpl for WhipServer {
                     .maximum(3600)
                     .default_value(DEFAULT_TIMEOUT)
                     .build(),
+                glib::ParamSpecUInt::builder("max-sessions")
+                    .nick("Maximum sessions")
+                    .blurb("Maximum number of simultaneous producers (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_SESSIONS)
+                    .build(),
             ]
         });
         PROPERTIES.as_ref()
@@ -1148,6 +1331,10 @@ impl ObjectImpl for WhipServer {
                 let mut settings = self.settings.lock().unwrap();
                 settings.timeout = value.get().unwrap();
             }
+            "max-sessions" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_sessions = value.get().unwrap();
+            }
             _ => unimplemented!(),
         }
     }
@@ -1160,6 +1347,7 @@ impl ObjectImpl for WhipServer {
             "stun-server" => settings.stun_server.to_value(),
             "turn-servers" => settings.turn_servers.to_value(),
             "timeout" => settings.timeout.to_value(),
+            "max-sessions" => settings.max_sessions.to_value(),
             _ => unimplemented!(),
         }
     }
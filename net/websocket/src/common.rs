@@ -0,0 +1,619 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use async_tungstenite::tokio::TokioAdapter;
+use async_tungstenite::WebSocketStream;
+use futures::future;
+use futures::prelude::*;
+use gst::glib;
+use gst::prelude::*;
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime;
+use tokio::sync::watch;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use url::Url;
+
+pub(crate) const DEFAULT_MODE: WebSocketMode = WebSocketMode::Client;
+pub(crate) const DEFAULT_ADDRESS: &str = "0.0.0.0";
+pub(crate) const DEFAULT_PORT: u16 = 8080;
+pub(crate) const DEFAULT_PING_INTERVAL: u32 = 30;
+pub(crate) const DEFAULT_RECONNECT_INTERVAL: u32 = 1000;
+
+/// Maximum duration of the TLS and WebSocket handshakes of incoming connections.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub(crate) static RUNTIME: Lazy<runtime::Runtime> = Lazy::new(|| {
+    runtime::Builder::new_multi_thread()
+        .enable_all()
+        .worker_threads(1)
+        .thread_name("gst-websocket-runtime")
+        .build()
+        .unwrap()
+});
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstWebSocketMode")]
+pub enum WebSocketMode {
+    #[enum_value(name = "Client: Connect to the location URL.", nick = "client")]
+    Client = 0,
+
+    #[enum_value(
+        name = "Server: Accept connections on the address and port.",
+        nick = "server"
+    )]
+    Server = 1,
+}
+
+pub(crate) trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// A client or server WebSocket, over TCP or TLS.
+pub(crate) type WebSocket = WebSocketStream<TokioAdapter<Box<dyn Io>>>;
+
+#[derive(Default)]
+pub(crate) enum Canceller {
+    #[default]
+    None,
+    Handle(future::AbortHandle),
+    Cancelled,
+}
+
+impl Canceller {
+    pub fn abort(&mut self) {
+        if let Canceller::Handle(ref canceller) = *self {
+            canceller.abort();
+        }
+
+        *self = Canceller::Cancelled;
+    }
+}
+
+/// Runs the future to completion on the runtime, unless aborted with the
+/// canceller.
+pub(crate) fn wait<F, T>(
+    canceller_mutex: &Mutex<Canceller>,
+    future: F,
+) -> Result<T, future::Aborted>
+where
+    F: Send + Future<Output = T>,
+    T: Send + 'static,
+{
+    let mut canceller = canceller_mutex.lock().unwrap();
+    if matches!(*canceller, Canceller::Cancelled) {
+        return Err(future::Aborted);
+    }
+    let (abort_handle, abort_registration) = future::AbortHandle::new_pair();
+    *canceller = Canceller::Handle(abort_handle);
+    drop(canceller);
+
+    let res = RUNTIME.block_on(future::Abortable::new(future, abort_registration));
+
+    let mut canceller = canceller_mutex.lock().unwrap();
+    if matches!(*canceller, Canceller::Cancelled) {
+        return Err(future::Aborted);
+    }
+    *canceller = Canceller::None;
+
+    res
+}
+
+/// Connection settings, common to the source and the sink.
+#[derive(Clone, Debug)]
+pub(crate) struct ConnectionSettings {
+    pub mode: WebSocketMode,
+    pub location: Option<String>,
+    pub address: String,
+    pub port: u16,
+    pub ca_file: Option<PathBuf>,
+    pub certificate_file: Option<PathBuf>,
+    pub certificate_password: Option<String>,
+    pub ping_interval: u32,
+    pub reconnect_interval: u32,
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        ConnectionSettings {
+            mode: DEFAULT_MODE,
+            location: None,
+            address: DEFAULT_ADDRESS.to_string(),
+            port: DEFAULT_PORT,
+            ca_file: None,
+            certificate_file: None,
+            certificate_password: None,
+            ping_interval: DEFAULT_PING_INTERVAL,
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+        }
+    }
+}
+
+/// Where connections come from, set up from the settings when starting.
+pub(crate) enum Endpoint {
+    Client {
+        location: Url,
+        connector: tokio_native_tls::TlsConnector,
+    },
+    Server {
+        listener: TcpListener,
+        acceptor: Option<tokio_native_tls::TlsAcceptor>,
+    },
+}
+
+impl ConnectionSettings {
+    pub fn properties() -> Vec<glib::ParamSpec> {
+        vec![
+            glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                .nick("Mode")
+                .blurb("Whether to connect to a server or to accept connections from clients")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("location")
+                .nick("Location")
+                .blurb("ws:// or wss:// URL to connect to in client mode")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("address")
+                .nick("Address")
+                .blurb("Address to listen on in server mode")
+                .default_value(Some(DEFAULT_ADDRESS))
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("port")
+                .nick("Port")
+                .blurb("Port to listen on in server mode (0 = any free port)")
+                .maximum(u16::MAX as u32)
+                .default_value(DEFAULT_PORT as u32)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("ca-file")
+                .nick("CA file")
+                .blurb("PEM file with an additional certificate authority to verify the server with in client mode")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("certificate-file")
+                .nick("Certificate file")
+                .blurb("PKCS #12 file with the server certificate and private key, enabling TLS in server mode")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecString::builder("certificate-password")
+                .nick("Certificate password")
+                .blurb("Password of the PKCS #12 certificate file")
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("ping-interval")
+                .nick("Ping interval")
+                .blurb("Interval in seconds between pings, connections are closed if the peer doesn't respond within twice the interval (0 = disabled)")
+                .default_value(DEFAULT_PING_INTERVAL)
+                .mutable_ready()
+                .build(),
+            glib::ParamSpecUInt::builder("reconnect-interval")
+                .nick("Reconnect interval")
+                .blurb("Time in ms to wait before reconnecting in client mode (0 = don't reconnect)")
+                .default_value(DEFAULT_RECONNECT_INTERVAL)
+                .mutable_ready()
+                .build(),
+        ]
+    }
+
+    /// Sets the property if it's a connection property, returns `false` otherwise.
+    pub fn set_property(&mut self, value: &glib::Value, pspec: &glib::ParamSpec) -> bool {
+        match pspec.name() {
+            "mode" => {
+                self.mode = value.get().expect("type checked upstream");
+            }
+            "location" => {
+                self.location = value.get().expect("type checked upstream");
+            }
+            "address" => {
+                self.address = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+            }
+            "port" => {
+                self.port = value.get::<u32>().expect("type checked upstream") as u16;
+            }
+            "ca-file" => {
+                self.ca_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "certificate-file" => {
+                self.certificate_file = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "certificate-password" => {
+                self.certificate_password = value.get().expect("type checked upstream");
+            }
+            "ping-interval" => {
+                self.ping_interval = value.get().expect("type checked upstream");
+            }
+            "reconnect-interval" => {
+                self.reconnect_interval = value.get().expect("type checked upstream");
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub fn property(&self, pspec: &glib::ParamSpec) -> Option<glib::Value> {
+        let value = match pspec.name() {
+            "mode" => self.mode.to_value(),
+            "location" => self.location.to_value(),
+            "address" => self.address.to_value(),
+            "port" => (self.port as u32).to_value(),
+            "ca-file" => self.ca_file.as_ref().and_then(|f| f.to_str()).to_value(),
+            "certificate-file" => self
+                .certificate_file
+                .as_ref()
+                .and_then(|f| f.to_str())
+                .to_value(),
+            "certificate-password" => self.certificate_password.to_value(),
+            "ping-interval" => self.ping_interval.to_value(),
+            "reconnect-interval" => self.reconnect_interval.to_value(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    fn read_file(path: &PathBuf) -> Result<Vec<u8>, gst::ErrorMessage> {
+        std::fs::read(path).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenRead,
+                ["Failed to read {}: {}", path.display(), err]
+            )
+        })
+    }
+
+    fn tls_error(err: tokio_native_tls::native_tls::Error) -> gst::ErrorMessage {
+        gst::error_msg!(
+            gst::ResourceError::Settings,
+            ["Failed to configure TLS: {}", err]
+        )
+    }
+
+    /// Validates the settings and sets up the endpoint, binding the listening
+    /// socket in server mode.
+    pub fn endpoint(&self) -> Result<Endpoint, gst::ErrorMessage> {
+        match self.mode {
+            WebSocketMode::Client => {
+                let Some(ref location) = self.location else {
+                    return Err(gst::error_msg!(
+                        gst::ResourceError::Settings,
+                        ["No location specified"]
+                    ));
+                };
+
+                let location = Url::parse(location)
+                    .ok()
+                    .filter(|url| matches!(url.scheme(), "ws" | "wss") && url.has_host())
+                    .ok_or_else(|| {
+                        gst::error_msg!(
+                            gst::ResourceError::Settings,
+                            ["Invalid location {}", location]
+                        )
+                    })?;
+
+                let mut builder = tokio_native_tls::native_tls::TlsConnector::builder();
+                if let Some(ref ca_file) = self.ca_file {
+                    let cert = tokio_native_tls::native_tls::Certificate::from_pem(
+                        &Self::read_file(ca_file)?,
+                    )
+                    .map_err(Self::tls_error)?;
+                    builder.add_root_certificate(cert);
+                }
+                let connector = builder.build().map_err(Self::tls_error)?;
+
+                Ok(Endpoint::Client {
+                    location,
+                    connector: connector.into(),
+                })
+            }
+            WebSocketMode::Server => {
+                let acceptor = match self.certificate_file {
+                    Some(ref certificate_file) => {
+                        let identity = tokio_native_tls::native_tls::Identity::from_pkcs12(
+                            &Self::read_file(certificate_file)?,
+                            self.certificate_password.as_deref().unwrap_or(""),
+                        )
+                        .map_err(Self::tls_error)?;
+                        let acceptor = tokio_native_tls::native_tls::TlsAcceptor::new(identity)
+                            .map_err(Self::tls_error)?;
+
+                        Some(acceptor.into())
+                    }
+                    None => None,
+                };
+
+                let listener = RUNTIME
+                    .block_on(TcpListener::bind((self.address.as_str(), self.port)))
+                    .map_err(|err| {
+                        gst::error_msg!(
+                            gst::ResourceError::OpenReadWrite,
+                            [
+                                "Failed to listen on {}:{}: {}",
+                                self.address,
+                                self.port,
+                                err
+                            ]
+                        )
+                    })?;
+
+                Ok(Endpoint::Server { listener, acceptor })
+            }
+        }
+    }
+
+    pub fn ping_interval(&self) -> Option<Duration> {
+        (self.ping_interval > 0).then(|| Duration::from_secs(self.ping_interval.into()))
+    }
+
+    pub fn reconnect_interval(&self) -> Option<Duration> {
+        (self.reconnect_interval > 0).then(|| Duration::from_millis(self.reconnect_interval.into()))
+    }
+}
+
+impl Endpoint {
+    /// Port the server is listening on, useful when binding to port 0.
+    pub fn local_port(&self) -> Option<u16> {
+        match self {
+            Endpoint::Server { listener, .. } => listener.local_addr().ok().map(|addr| addr.port()),
+            Endpoint::Client { .. } => None,
+        }
+    }
+}
+
+/// Pings the peer at a regular interval, and detects when it stops
+/// responding.
+pub(crate) struct Keepalive {
+    interval: Option<Interval>,
+    last_seen: Instant,
+}
+
+impl Keepalive {
+    pub fn new(ping_interval: Option<Duration>) -> Self {
+        let interval = ping_interval.map(|period| {
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+
+        Keepalive {
+            interval,
+            last_seen: Instant::now(),
+        }
+    }
+
+    /// Waits until the next ping is due, never returns if disabled.
+    pub async fn tick(&mut self) {
+        match self.interval {
+            Some(ref mut interval) => {
+                interval.tick().await;
+            }
+            None => future::pending().await,
+        }
+    }
+
+    /// Records that something was received from the peer.
+    pub fn seen(&mut self) {
+        self.last_seen = Instant::now();
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.interval
+            .as_ref()
+            .is_some_and(|interval| self.last_seen.elapsed() > 2 * interval.period())
+    }
+}
+
+async fn connect(
+    location: &Url,
+    connector: &tokio_native_tls::TlsConnector,
+) -> Result<WebSocket, BoxError> {
+    let host = location.host_str().ok_or("No host")?;
+    let port = location.port_or_known_default().ok_or("No port")?;
+
+    let tcp = TcpStream::connect((host, port)).await?;
+    tcp.set_nodelay(true)?;
+
+    let stream: Box<dyn Io> = if location.scheme() == "wss" {
+        Box::new(connector.connect(host, tcp).await?)
+    } else {
+        Box::new(tcp)
+    };
+
+    let (ws, _) = async_tungstenite::tokio::client_async(location.as_str(), stream).await?;
+
+    Ok(ws)
+}
+
+async fn accept(
+    tcp: TcpStream,
+    acceptor: Option<&tokio_native_tls::TlsAcceptor>,
+) -> Result<WebSocket, BoxError> {
+    tcp.set_nodelay(true)?;
+
+    let stream: Box<dyn Io> = match acceptor {
+        Some(acceptor) => Box::new(acceptor.accept(tcp).await?),
+        None => Box::new(tcp),
+    };
+
+    Ok(async_tungstenite::tokio::accept_async(stream).await?)
+}
+
+/// Establishes the connections of the endpoint in the background, and runs
+/// `handle_connection` for each of them.
+///
+/// In client mode, the connection is reestablished after `reconnect_interval`
+/// once lost. In server mode, connections are handled concurrently. Everything
+/// stops once the `shutdown` sender is dropped, after which `handle_connection`
+/// is expected to close the connection.
+pub(crate) fn spawn_connections<F, Fut>(
+    cat: gst::DebugCategory,
+    element: &gst::Element,
+    endpoint: Endpoint,
+    reconnect_interval: Option<Duration>,
+    mut shutdown: watch::Receiver<()>,
+    handle_connection: F,
+) where
+    F: Fn(gst::Element, WebSocket, watch::Receiver<()>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), BoxError>> + Send + 'static,
+{
+    let element_weak = element.downgrade();
+
+    match endpoint {
+        Endpoint::Client {
+            location,
+            connector,
+        } => {
+            RUNTIME.spawn(async move {
+                loop {
+                    let res = tokio::select! {
+                        _ = shutdown.changed() => break,
+                        res = connect(&location, &connector) => res,
+                    };
+
+                    let Some(element) = element_weak.upgrade() else {
+                        break;
+                    };
+
+                    let res = match res {
+                        Ok(ws) => {
+                            gst::info!(cat, obj: element, "Connected to {}", location);
+
+                            let res =
+                                handle_connection(element.clone(), ws, shutdown.clone()).await;
+                            match res {
+                                Ok(()) => {
+                                    gst::info!(cat, obj: element, "Connection closed");
+                                }
+                                Err(ref err) => {
+                                    gst::warning!(cat, obj: element, "Connection lost: {}", err);
+                                }
+                            }
+
+                            res
+                        }
+                        Err(err) => {
+                            gst::warning!(
+                                cat,
+                                obj: element,
+                                "Failed to connect to {}: {}",
+                                location,
+                                err
+                            );
+
+                            Err(err)
+                        }
+                    };
+
+                    if shutdown.has_changed().is_err() {
+                        break;
+                    }
+
+                    let Some(reconnect_interval) = reconnect_interval else {
+                        if let Err(err) = res {
+                            gst::element_error!(
+                                element,
+                                gst::ResourceError::OpenReadWrite,
+                                ["Connection to {} failed: {}", location, err]
+                            );
+                        }
+                        break;
+                    };
+                    drop(element);
+
+                    tokio::select! {
+                        _ = shutdown.changed() => break,
+                        _ = tokio::time::sleep(reconnect_interval) => (),
+                    }
+                }
+
+                gst::debug!(cat, "Connection task stopped");
+            });
+        }
+        Endpoint::Server { listener, acceptor } => {
+            let handle_connection = std::sync::Arc::new(handle_connection);
+
+            RUNTIME.spawn(async move {
+                loop {
+                    let res = tokio::select! {
+                        _ = shutdown.changed() => break,
+                        res = listener.accept() => res,
+                    };
+
+                    let Some(element) = element_weak.upgrade() else {
+                        break;
+                    };
+
+                    let (tcp, addr) = match res {
+                        Ok(res) => res,
+                        Err(err) => {
+                            gst::warning!(cat, obj: element, "Failed to accept connection: {}", err);
+                            continue;
+                        }
+                    };
+
+                    let acceptor = acceptor.clone();
+                    let handle_connection = handle_connection.clone();
+                    let shutdown = shutdown.clone();
+
+                    RUNTIME.spawn(async move {
+                        let ws = match tokio::time::timeout(
+                            HANDSHAKE_TIMEOUT,
+                            accept(tcp, acceptor.as_ref()),
+                        )
+                        .await
+                        {
+                            Ok(Ok(ws)) => ws,
+                            Ok(Err(err)) => {
+                                gst::warning!(cat, obj: element, "Handshake with {} failed: {}", addr, err);
+                                return;
+                            }
+                            Err(_) => {
+                                gst::warning!(cat, obj: element, "Handshake with {} timed out", addr);
+                                return;
+                            }
+                        };
+
+                        gst::info!(cat, obj: element, "Accepted connection from {}", addr);
+
+                        match handle_connection(element.clone(), ws, shutdown).await {
+                            Ok(()) => {
+                                gst::info!(cat, obj: element, "Connection from {} closed", addr);
+                            }
+                            Err(err) => {
+                                gst::warning!(
+                                    cat,
+                                    obj: element,
+                                    "Connection from {} lost: {}",
+                                    addr,
+                                    err
+                                );
+                            }
+                        }
+                    });
+                }
+
+                gst::debug!(cat, "Listening task stopped");
+            });
+        }
+    }
+}
@@ -0,0 +1,305 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use async_tungstenite::tungstenite::Message;
+use futures::prelude::*;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, watch};
+
+use crate::common::{self, BoxError, ConnectionSettings, Keepalive, WebSocket};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "wssink",
+        gst::DebugColorFlags::empty(),
+        Some("WebSocket Sink"),
+    )
+});
+
+/// Number of buffers a connection can lag behind before it's closed.
+const MAX_LAGGING_BUFFERS: usize = 64;
+
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    connection: ConnectionSettings,
+}
+
+/// Buffers to send, shared with the connections.
+struct Stream {
+    sender: broadcast::Sender<gst::Buffer>,
+    /// Sent first on new connections
+    headers: Vec<gst::Buffer>,
+    /// Whether the last buffer was a header
+    in_headers: bool,
+}
+
+impl Default for Stream {
+    fn default() -> Self {
+        Stream {
+            sender: broadcast::channel(MAX_LAGGING_BUFFERS).0,
+            headers: Vec::new(),
+            in_headers: false,
+        }
+    }
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started {
+        // Stops the connections once dropped
+        _shutdown: watch::Sender<()>,
+    },
+}
+
+#[derive(Default)]
+pub struct WebSocketSink {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    stream: Arc<Mutex<Stream>>,
+}
+
+fn buffer_message(buffer: &gst::Buffer) -> Result<Message, BoxError> {
+    let map = buffer.map_readable()?;
+
+    Ok(Message::Binary(map.to_vec()))
+}
+
+/// Sends the stream headers and then all buffers from the next keyframe on,
+/// until the connection is closed or lags behind.
+async fn run_connection(
+    element: gst::Element,
+    ws: WebSocket,
+    stream: Arc<Mutex<Stream>>,
+    mut keepalive: Keepalive,
+    mut shutdown: watch::Receiver<()>,
+) -> Result<(), BoxError> {
+    let (mut sink, mut messages) = ws.split();
+
+    let (headers, mut receiver) = {
+        let stream = stream.lock().unwrap();
+        (stream.headers.clone(), stream.sender.subscribe())
+    };
+
+    for header in &headers {
+        sink.send(buffer_message(header)?).await?;
+    }
+
+    let mut waiting_for_keyframe = true;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                sink.send(Message::Close(None)).await?;
+                return Ok(());
+            }
+            res = receiver.recv() => {
+                let buffer = match res {
+                    Ok(buffer) => buffer,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        return Err(format!("Too slow, {skipped} buffers skipped").into());
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                };
+
+                let flags = buffer.flags();
+                if waiting_for_keyframe && !flags.contains(gst::BufferFlags::HEADER) {
+                    if flags.contains(gst::BufferFlags::DELTA_UNIT) {
+                        gst::trace!(CAT, obj: element, "Waiting for keyframe, skipping {:?}", buffer);
+                        continue;
+                    }
+                    waiting_for_keyframe = false;
+                }
+
+                sink.send(buffer_message(&buffer)?).await?;
+            }
+            _ = keepalive.tick() => {
+                if keepalive.timed_out() {
+                    return Err("Ping timeout".into());
+                }
+                sink.send(Message::Ping(Vec::new())).await?;
+            }
+            msg = messages.next() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => keepalive.seen(),
+                    Some(Err(err)) => return Err(err.into()),
+                }
+            }
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for WebSocketSink {
+    const NAME: &'static str = "GstWebSocketSink";
+    type Type = super::WebSocketSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for WebSocketSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(ConnectionSettings::properties);
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        if !settings.connection.set_property(value, pspec) {
+            unimplemented!();
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        settings
+            .connection
+            .property(pspec)
+            .unwrap_or_else(|| unimplemented!())
+    }
+}
+
+impl GstObjectImpl for WebSocketSink {}
+
+impl ElementImpl for WebSocketSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "WebSocket Sink",
+                "Sink/Network",
+                "Sends buffers as binary WebSocket messages",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for WebSocketSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let endpoint = settings.connection.endpoint()?;
+        if let Some(port) = endpoint.local_port() {
+            gst::info!(CAT, imp: self, "Listening on port {}", port);
+        }
+
+        let (shutdown_sender, shutdown) = watch::channel(());
+        let stream = self.stream.clone();
+        let ping_interval = settings.connection.ping_interval();
+
+        common::spawn_connections(
+            *CAT,
+            self.obj().upcast_ref(),
+            endpoint,
+            settings.connection.reconnect_interval(),
+            shutdown,
+            move |element, ws, shutdown| {
+                run_connection(
+                    element,
+                    ws,
+                    stream.clone(),
+                    Keepalive::new(ping_interval),
+                    shutdown,
+                )
+            },
+        );
+
+        *self.state.lock().unwrap() = State::Started {
+            _shutdown: shutdown_sender,
+        };
+
+        gst::info!(CAT, imp: self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::Stopped;
+
+        let mut stream = self.stream.lock().unwrap();
+        stream.headers.clear();
+        stream.in_headers = false;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let headers = caps
+            .structure(0)
+            .and_then(|s| s.get::<gst::ArrayRef>("streamheader").ok())
+            .map(|streamheader| {
+                streamheader
+                    .iter()
+                    .filter_map(|header| header.get::<gst::Buffer>().ok())
+                    .collect::<Vec<_>>()
+            });
+
+        if let Some(headers) = headers {
+            gst::debug!(CAT, imp: self, "Got {} stream headers from caps", headers.len());
+            self.stream.lock().unwrap().headers = headers;
+        }
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut stream = self.stream.lock().unwrap();
+
+        // Header buffers replace the previous headers sent to new connections
+        if buffer.flags().contains(gst::BufferFlags::HEADER) {
+            if !stream.in_headers {
+                stream.headers.clear();
+                stream.in_headers = true;
+            }
+            stream.headers.push(buffer.clone());
+        } else {
+            stream.in_headers = false;
+        }
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Sending {:?} to {} connections",
+            buffer,
+            stream.sender.receiver_count()
+        );
+
+        // Fails if there are no connections, the buffer is dropped then
+        let _ = stream.sender.send(buffer.clone());
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
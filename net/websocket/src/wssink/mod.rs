@@ -0,0 +1,53 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-wssink:
+ *
+ * `wssink` sends each buffer as a binary WebSocket message.
+ *
+ * In `client` mode it connects to the `location` URL, and reconnects
+ * `reconnect-interval` milliseconds after the connection is lost. In `server`
+ * mode it accepts connections on `address` and `port`, with TLS if a
+ * `certificate-file` is set, and sends the buffers to all connected clients.
+ *
+ * New connections first get the stream headers, from the `streamheader` field
+ * of the caps or buffers flagged as headers, and then the buffers from the
+ * next keyframe on. This allows e.g. browsers to play the fragmented MP4
+ * stream of `fmp4mux` with Media Source Extensions, whenever they connect.
+ * Buffers are dropped while there are no connections, and connections that
+ * can't keep up with the stream are closed.
+ *
+ * The peers are pinged every `ping-interval` seconds, and connections are
+ * closed if they don't respond within twice the interval.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 videotestsrc is-live=true ! x264enc tune=zerolatency key-int-max=30 ! \
+ *     h264parse ! cmafmux fragment-duration=1000000000 header-update-mode=update ! \
+ *     wssink mode=server port=8080
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct WebSocketSink(ObjectSubclass<imp::WebSocketSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "wssink",
+        gst::Rank::NONE,
+        WebSocketSink::static_type(),
+    )
+}
@@ -0,0 +1,318 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use async_tungstenite::tungstenite::Message;
+use futures::prelude::*;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use tokio::sync::{mpsc, watch};
+
+use crate::common::{self, BoxError, Canceller, ConnectionSettings, Keepalive, WebSocket};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "wssrc",
+        gst::DebugColorFlags::empty(),
+        Some("WebSocket Source"),
+    )
+});
+
+/// Number of received messages waiting to be output before the connections
+/// stop reading.
+const MAX_QUEUED_MESSAGES: usize = 16;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    connection: ConnectionSettings,
+    caps: gst::Caps,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            connection: ConnectionSettings::default(),
+            caps: gst::Caps::new_any(),
+        }
+    }
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started {
+        // Stops the connections once dropped
+        _shutdown: watch::Sender<()>,
+    },
+}
+
+#[derive(Default)]
+pub struct WebSocketSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    receiver: Mutex<Option<mpsc::Receiver<Vec<u8>>>>,
+    canceller: Mutex<Canceller>,
+}
+
+/// Forwards the received binary and text messages until the connection is
+/// closed.
+async fn run_connection(
+    element: gst::Element,
+    ws: WebSocket,
+    sender: mpsc::Sender<Vec<u8>>,
+    mut keepalive: Keepalive,
+    mut shutdown: watch::Receiver<()>,
+) -> Result<(), BoxError> {
+    let (mut sink, mut messages) = ws.split();
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                sink.send(Message::Close(None)).await?;
+                return Ok(());
+            }
+            _ = keepalive.tick() => {
+                if keepalive.timed_out() {
+                    return Err("Ping timeout".into());
+                }
+                sink.send(Message::Ping(Vec::new())).await?;
+            }
+            msg = messages.next() => {
+                let data = match msg {
+                    Some(Ok(Message::Binary(data))) => data,
+                    Some(Ok(Message::Text(text))) => text.into_bytes(),
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => {
+                        keepalive.seen();
+                        continue;
+                    }
+                    Some(Err(err)) => return Err(err.into()),
+                };
+                keepalive.seen();
+
+                gst::trace!(CAT, obj: element, "Received message of {} bytes", data.len());
+
+                // Fails once stopped
+                if sender.send(data).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for WebSocketSrc {
+    const NAME: &'static str = "GstWebSocketSrc";
+    type Type = super::WebSocketSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for WebSocketSrc {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(true);
+        obj.set_format(gst::Format::Time);
+        obj.set_do_timestamp(true);
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            let mut props = ConnectionSettings::properties();
+            props.push(
+                glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
+                    .nick("Caps")
+                    .blurb("The caps of the source pad")
+                    .mutable_ready()
+                    .build(),
+            );
+            props
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        if settings.connection.set_property(value, pspec) {
+            return;
+        }
+
+        match pspec.name() {
+            "caps" => {
+                settings.caps = value
+                    .get::<Option<gst::Caps>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(gst::Caps::new_any);
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        if let Some(value) = settings.connection.property(pspec) {
+            return value;
+        }
+
+        match pspec.name() {
+            "caps" => settings.caps.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for WebSocketSrc {}
+
+impl ElementImpl for WebSocketSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "WebSocket Source",
+                "Source/Network",
+                "Receives WebSocket messages as buffers",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for WebSocketSrc {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let endpoint = settings.connection.endpoint()?;
+        if let Some(port) = endpoint.local_port() {
+            gst::info!(CAT, imp: self, "Listening on port {}", port);
+        }
+
+        let (shutdown_sender, shutdown) = watch::channel(());
+        let (sender, receiver) = mpsc::channel(MAX_QUEUED_MESSAGES);
+        let ping_interval = settings.connection.ping_interval();
+
+        common::spawn_connections(
+            *CAT,
+            self.obj().upcast_ref(),
+            endpoint,
+            settings.connection.reconnect_interval(),
+            shutdown,
+            move |element, ws, shutdown| {
+                run_connection(
+                    element,
+                    ws,
+                    sender.clone(),
+                    Keepalive::new(ping_interval),
+                    shutdown,
+                )
+            },
+        );
+
+        *self.receiver.lock().unwrap() = Some(receiver);
+        *self.state.lock().unwrap() = State::Started {
+            _shutdown: shutdown_sender,
+        };
+
+        gst::info!(CAT, imp: self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::Stopped;
+        *self.receiver.lock().unwrap() = None;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn caps(&self, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
+        let caps = self.settings.lock().unwrap().caps.clone();
+
+        match filter {
+            Some(filter) => Some(filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First)),
+            None => Some(caps),
+        }
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.canceller.lock().unwrap().abort();
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        let mut canceller = self.canceller.lock().unwrap();
+        if matches!(*canceller, Canceller::Cancelled) {
+            *canceller = Canceller::None;
+        }
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for WebSocketSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let mut receiver = self.receiver.lock().unwrap();
+        let Some(receiver) = receiver.as_mut() else {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        match common::wait(&self.canceller, receiver.recv()) {
+            Ok(Some(data)) => {
+                gst::log!(CAT, imp: self, "Outputting message of {} bytes", data.len());
+
+                Ok(CreateSuccess::NewBuffer(gst::Buffer::from_mut_slice(data)))
+            }
+            // All connections are done, which only happens without reconnecting
+            Ok(None) => {
+                gst::debug!(CAT, imp: self, "Connection closed");
+                Err(gst::FlowError::Eos)
+            }
+            Err(_) => {
+                gst::debug!(CAT, imp: self, "Flushing");
+                Err(gst::FlowError::Flushing)
+            }
+        }
+    }
+}
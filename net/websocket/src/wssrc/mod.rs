@@ -0,0 +1,48 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-wssrc:
+ *
+ * `wssrc` is a live source outputting each received binary or text WebSocket
+ * message as a buffer, timestamped with its reception time. The source pad
+ * caps are set with the `caps` property.
+ *
+ * In `client` mode it connects to the `location` URL, and reconnects
+ * `reconnect-interval` milliseconds after the connection is lost. Without
+ * reconnecting, the source goes EOS once the connection is closed. In
+ * `server` mode it accepts connections on `address` and `port`, with TLS if a
+ * `certificate-file` is set, and outputs the messages of all connected
+ * clients.
+ *
+ * See `wssink` for the keepalive properties.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 wssrc location=ws://127.0.0.1:8080/ caps=video/quicktime,variant=iso-fragmented ! \
+ *     qtdemux ! decodebin3 ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct WebSocketSrc(ObjectSubclass<imp::WebSocketSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "wssrc",
+        gst::Rank::NONE,
+        WebSocketSrc::static_type(),
+    )
+}
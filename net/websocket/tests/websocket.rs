@@ -0,0 +1,122 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use std::time::Duration;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstwebsocket::plugin_register_static().expect("websocket test");
+    });
+}
+
+#[test]
+fn test_requires_location() {
+    init();
+
+    let sink = gst::ElementFactory::make("wssink").build().unwrap();
+    assert!(sink.set_state(gst::State::Paused).is_err());
+    sink.set_state(gst::State::Null).unwrap();
+
+    let src = gst::ElementFactory::make("wssrc")
+        .property("location", "http://127.0.0.1/")
+        .build()
+        .unwrap();
+    assert!(src.set_state(gst::State::Paused).is_err());
+    src.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_server_to_client() {
+    init();
+
+    let mut h1 = gst_check::Harness::new("wssink");
+    {
+        let sink = h1.element().unwrap();
+        sink.set_property_from_str("mode", "server");
+        sink.set_property("address", "127.0.0.1");
+        sink.set_property("port", 5010u32);
+    }
+    h1.set_src_caps_str("application/x-test");
+    h1.play();
+
+    let mut h2 = gst_check::Harness::new("wssrc");
+    {
+        let src = h2.element().unwrap();
+        src.set_property("location", "ws://127.0.0.1:5010/");
+        src.set_property("caps", gst::Caps::new_empty_simple("application/x-test"));
+    }
+    h2.play();
+
+    // Headers are sent to new connections, delta units are skipped until the
+    // next keyframe
+    let mut header = gst::Buffer::from_slice(b"header");
+    header
+        .get_mut()
+        .unwrap()
+        .set_flags(gst::BufferFlags::HEADER);
+    h1.push(header).unwrap();
+
+    // Wait for the client to connect
+    std::thread::sleep(Duration::from_millis(500));
+
+    let mut delta = gst::Buffer::from_slice(b"delta");
+    delta
+        .get_mut()
+        .unwrap()
+        .set_flags(gst::BufferFlags::DELTA_UNIT);
+    h1.push(delta).unwrap();
+    h1.push(gst::Buffer::from_slice(b"keyframe")).unwrap();
+
+    for expected in [&b"header"[..], &b"keyframe"[..]] {
+        let buffer = h2.pull().unwrap();
+        assert_eq!(buffer.map_readable().unwrap().as_slice(), expected);
+        assert!(buffer.pts().is_some());
+    }
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+    h1.element().unwrap().set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_client_to_server() {
+    init();
+
+    let mut h1 = gst_check::Harness::new("wssrc");
+    {
+        let src = h1.element().unwrap();
+        src.set_property_from_str("mode", "server");
+        src.set_property("address", "127.0.0.1");
+        src.set_property("port", 5011u32);
+    }
+    h1.play();
+
+    let mut h2 = gst_check::Harness::new("wssink");
+    h2.element()
+        .unwrap()
+        .set_property("location", "ws://127.0.0.1:5011/");
+    h2.set_src_caps_str("application/x-test");
+    h2.play();
+
+    // Buffers are dropped until connected
+    std::thread::sleep(Duration::from_millis(500));
+
+    for i in 0..5u8 {
+        h2.push(gst::Buffer::from_slice([i])).unwrap();
+    }
+
+    for i in 0..5u8 {
+        let buffer = h1.pull().unwrap();
+        assert_eq!(buffer.map_readable().unwrap().as_slice(), &[i]);
+    }
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+    h1.element().unwrap().set_state(gst::State::Null).unwrap();
+}
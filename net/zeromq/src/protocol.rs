@@ -0,0 +1,97 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Messages exchanged between `zmqsink` and `zmqsrc`.
+//!
+//! Every message is a multipart message starting with the topic frame,
+//! followed by the kind of message:
+//!
+//! * `caps`: the caps as a string.
+//! * `buffer`: a header frame with the big-endian PTS, DTS, duration (all ones
+//!   if unset) and flags, then the buffer data.
+
+use gst::glib;
+use gst::prelude::*;
+
+pub const CAPS: &[u8] = b"caps";
+pub const BUFFER: &[u8] = b"buffer";
+
+const HEADER_SIZE: usize = 3 * 8 + 4;
+
+/// Buffer flags carried over the connection.
+const FLAGS: gst::BufferFlags = gst::BufferFlags::from_bits_truncate(
+    gst::BufferFlags::DELTA_UNIT.bits()
+        | gst::BufferFlags::HEADER.bits()
+        | gst::BufferFlags::DISCONT.bits()
+        | gst::BufferFlags::GAP.bits()
+        | gst::BufferFlags::DROPPABLE.bits()
+        | gst::BufferFlags::MARKER.bits(),
+);
+
+#[derive(Debug)]
+pub enum Message {
+    Caps(gst::Caps),
+    Buffer(gst::Buffer),
+}
+
+pub fn caps_message(topic: &[u8], caps: &gst::Caps) -> Vec<Vec<u8>> {
+    vec![topic.to_vec(), CAPS.to_vec(), caps.to_string().into_bytes()]
+}
+
+pub fn buffer_message(
+    topic: &[u8],
+    buffer: &gst::BufferRef,
+) -> Result<Vec<Vec<u8>>, glib::BoolError> {
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    for ts in [buffer.pts(), buffer.dts(), buffer.duration()] {
+        header.extend_from_slice(&ts.map_or(u64::MAX, gst::ClockTime::nseconds).to_be_bytes());
+    }
+    header.extend_from_slice(&(buffer.flags() & FLAGS).bits().to_be_bytes());
+
+    let map = buffer.map_readable()?;
+
+    Ok(vec![topic.to_vec(), BUFFER.to_vec(), header, map.to_vec()])
+}
+
+/// Parses a received multipart message, `None` if it's invalid.
+pub fn parse(mut frames: Vec<Vec<u8>>) -> Option<Message> {
+    if frames.len() < 3 {
+        return None;
+    }
+
+    match frames[1].as_slice() {
+        CAPS => {
+            let caps = std::str::from_utf8(&frames[2]).ok()?;
+
+            caps.parse::<gst::Caps>().ok().map(Message::Caps)
+        }
+        BUFFER if frames.len() == 4 => {
+            let header = &frames[2];
+            if header.len() != HEADER_SIZE {
+                return None;
+            }
+
+            let ts = |i: usize| {
+                let ts = u64::from_be_bytes(header[i * 8..(i + 1) * 8].try_into().unwrap());
+                (ts != u64::MAX).then(|| gst::ClockTime::from_nseconds(ts))
+            };
+            let (pts, dts, duration) = (ts(0), ts(1), ts(2));
+            let flags = u32::from_be_bytes(header[24..].try_into().unwrap());
+
+            let mut buffer = gst::Buffer::from_mut_slice(frames.pop().unwrap());
+            {
+                let buffer = buffer.get_mut().unwrap();
+                buffer.set_pts(pts);
+                buffer.set_dts(dts);
+                buffer.set_duration(duration);
+                buffer.set_flags(gst::BufferFlags::from_bits_truncate(flags) & FLAGS);
+            }
+
+            Some(Message::Buffer(buffer))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,354 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::ZmqSinkSocketType;
+use crate::protocol;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "zmqsink",
+        gst::DebugColorFlags::empty(),
+        Some("ZeroMQ Sink"),
+    )
+});
+
+const DEFAULT_SOCKET_TYPE: ZmqSinkSocketType = ZmqSinkSocketType::Pub;
+const DEFAULT_ENDPOINT: &str = "tcp://*:5555";
+const DEFAULT_BIND: bool = true;
+const DEFAULT_CAPS_INTERVAL: u32 = 1000;
+const DEFAULT_HIGH_WATER_MARK: u32 = 1000;
+
+/// Timeout in ms of each wait for the socket to accept messages, after which
+/// flushing is checked.
+const POLL_TIMEOUT: i64 = 100;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    socket_type: ZmqSinkSocketType,
+    endpoint: String,
+    bind: bool,
+    topic: String,
+    caps_interval: u32,
+    high_water_mark: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            socket_type: DEFAULT_SOCKET_TYPE,
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            bind: DEFAULT_BIND,
+            topic: String::new(),
+            caps_interval: DEFAULT_CAPS_INTERVAL,
+            high_water_mark: DEFAULT_HIGH_WATER_MARK,
+        }
+    }
+}
+
+struct Started {
+    socket: zmq::Socket,
+    topic: Vec<u8>,
+    caps_interval: Option<Duration>,
+    caps: Option<gst::Caps>,
+    /// When the caps were last sent, `None` if they changed since
+    caps_sent: Option<Instant>,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Stopped,
+    Started(Started),
+}
+
+#[derive(Default)]
+pub struct ZmqSink {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    flushing: AtomicBool,
+}
+
+impl ZmqSink {
+    fn send(&self, socket: &zmq::Socket, frames: Vec<Vec<u8>>) -> Result<(), gst::FlowError> {
+        let error = |err: zmq::Error| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Write,
+                ["Failed to send message: {}", err]
+            );
+            gst::FlowError::Error
+        };
+
+        // PUSH sockets block without peers or once the high water mark is
+        // reached, PUB sockets drop the messages instead
+        loop {
+            if self.flushing.load(Ordering::SeqCst) {
+                return Err(gst::FlowError::Flushing);
+            }
+
+            if socket.poll(zmq::POLLOUT, POLL_TIMEOUT).map_err(error)? > 0 {
+                break;
+            }
+        }
+
+        socket.send_multipart(frames, zmq::DONTWAIT).map_err(error)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ZmqSink {
+    const NAME: &'static str = "GstZmqSink";
+    type Type = super::ZmqSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for ZmqSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("socket-type", DEFAULT_SOCKET_TYPE)
+                    .nick("Socket type")
+                    .blurb("Type of the ZeroMQ socket")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("endpoint")
+                    .nick("Endpoint")
+                    .blurb("ZeroMQ endpoint to bind or connect to, e.g. tcp://*:5555 or ipc:///tmp/stream")
+                    .default_value(Some(DEFAULT_ENDPOINT))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("bind")
+                    .nick("Bind")
+                    .blurb("Bind to the endpoint instead of connecting to it")
+                    .default_value(DEFAULT_BIND)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("topic")
+                    .nick("Topic")
+                    .blurb("Topic of the messages, for subscribers to filter on")
+                    .default_value(Some(""))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("caps-interval")
+                    .nick("Caps interval")
+                    .blurb("Interval in ms at which the caps are sent again for new peers (0 = only when changed)")
+                    .default_value(DEFAULT_CAPS_INTERVAL)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("high-water-mark")
+                    .nick("High water mark")
+                    .blurb("Maximum number of messages queued per peer (0 = unlimited)")
+                    .maximum(i32::MAX as u32)
+                    .default_value(DEFAULT_HIGH_WATER_MARK)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "socket-type" => {
+                settings.socket_type = value.get().expect("type checked upstream");
+            }
+            "endpoint" => {
+                settings.endpoint = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+            }
+            "bind" => {
+                settings.bind = value.get().expect("type checked upstream");
+            }
+            "topic" => {
+                settings.topic = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_default();
+            }
+            "caps-interval" => {
+                settings.caps_interval = value.get().expect("type checked upstream");
+            }
+            "high-water-mark" => {
+                settings.high_water_mark = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "socket-type" => settings.socket_type.to_value(),
+            "endpoint" => settings.endpoint.to_value(),
+            "bind" => settings.bind.to_value(),
+            "topic" => settings.topic.to_value(),
+            "caps-interval" => settings.caps_interval.to_value(),
+            "high-water-mark" => settings.high_water_mark.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for ZmqSink {}
+
+impl ElementImpl for ZmqSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "ZeroMQ Sink",
+                "Sink/Network",
+                "Sends buffers and caps over a ZeroMQ PUB or PUSH socket",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for ZmqSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let context = zmq::Context::new();
+        let socket = context
+            .socket(settings.socket_type.into())
+            .and_then(|socket| {
+                socket.set_sndhwm(settings.high_water_mark as i32)?;
+                // Don't block on unsent messages when stopping
+                socket.set_linger(0)?;
+
+                if settings.bind {
+                    socket.bind(&settings.endpoint)?;
+                } else {
+                    socket.connect(&settings.endpoint)?;
+                }
+
+                Ok(socket)
+            })
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenWrite,
+                    [
+                        "Failed to {} {}: {}",
+                        if settings.bind { "bind" } else { "connect to" },
+                        settings.endpoint,
+                        err
+                    ]
+                )
+            })?;
+
+        *self.state.lock().unwrap() = State::Started(Started {
+            socket,
+            topic: settings.topic.into_bytes(),
+            caps_interval: (settings.caps_interval > 0)
+                .then(|| Duration::from_millis(settings.caps_interval.into())),
+            caps: None,
+            caps_sent: None,
+        });
+
+        gst::info!(CAT, imp: self, "Started on {}", settings.endpoint);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::Stopped;
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let mut state = self.state.lock().unwrap();
+        let State::Started(ref mut state) = *state else {
+            return Err(gst::loggable_error!(CAT, "Not started yet"));
+        };
+
+        gst::debug!(CAT, imp: self, "Setting caps {}", caps);
+
+        // Sent with the next buffer
+        state.caps = Some(caps.clone());
+        state.caps_sent = None;
+
+        Ok(())
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let State::Started(ref mut state) = *state else {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        if let Some(ref caps) = state.caps {
+            let resend = match (state.caps_sent, state.caps_interval) {
+                (None, _) => true,
+                (Some(sent), Some(interval)) => sent.elapsed() >= interval,
+                (Some(_), None) => false,
+            };
+
+            if resend {
+                gst::trace!(CAT, imp: self, "Sending caps");
+                self.send(&state.socket, protocol::caps_message(&state.topic, caps))?;
+                state.caps_sent = Some(Instant::now());
+            }
+        }
+
+        let frames = protocol::buffer_message(&state.topic, buffer).map_err(|_| {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Failed to map buffer"]);
+            gst::FlowError::Error
+        })?;
+
+        gst::trace!(CAT, imp: self, "Sending {:?}", buffer);
+        self.send(&state.socket, frames)?;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
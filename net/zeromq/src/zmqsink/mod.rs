@@ -0,0 +1,69 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-zmqsink:
+ *
+ * `zmqsink` sends buffers over a ZeroMQ `PUB` or `PUSH` socket, to be received
+ * by `zmqsrc`. The caps are sent before the first buffer and whenever they
+ * change, and additionally every `caps-interval` milliseconds so that
+ * subscribers joining later can start receiving. The buffer timestamps and
+ * flags are carried along with the data.
+ *
+ * All messages are prefixed with `topic`, which subscribers can filter on.
+ *
+ * `PUB` sockets drop the messages for peers that are not keeping up, while
+ * `PUSH` sockets distribute the messages among the connected peers and block
+ * once `high-water-mark` messages are queued, or while no peer is connected.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 videotestsrc is-live=true ! x264enc tune=zerolatency key-int-max=30 ! \
+ *     zmqsink endpoint=tcp://0.0.0.0:5555 topic=camera1
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstZmqSinkSocketType")]
+pub enum ZmqSinkSocketType {
+    #[enum_value(name = "Pub: Publish to all subscribers.", nick = "pub")]
+    Pub = 0,
+    #[enum_value(name = "Push: Distribute among the peers.", nick = "push")]
+    Push = 1,
+}
+
+impl From<ZmqSinkSocketType> for zmq::SocketType {
+    fn from(socket_type: ZmqSinkSocketType) -> Self {
+        match socket_type {
+            ZmqSinkSocketType::Pub => zmq::PUB,
+            ZmqSinkSocketType::Push => zmq::PUSH,
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct ZmqSink(ObjectSubclass<imp::ZmqSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    ZmqSinkSocketType::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "zmqsink",
+        gst::Rank::NONE,
+        ZmqSink::static_type(),
+    )
+}
@@ -0,0 +1,382 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use super::ZmqSrcSocketType;
+use crate::protocol::{self, Message};
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "zmqsrc",
+        gst::DebugColorFlags::empty(),
+        Some("ZeroMQ Source"),
+    )
+});
+
+const DEFAULT_SOCKET_TYPE: ZmqSrcSocketType = ZmqSrcSocketType::Sub;
+const DEFAULT_ENDPOINT: &str = "tcp://localhost:5555";
+const DEFAULT_BIND: bool = false;
+const DEFAULT_HIGH_WATER_MARK: u32 = 1000;
+
+/// Timeout in ms of each wait for messages, after which flushing is checked.
+const POLL_TIMEOUT: i64 = 100;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    socket_type: ZmqSrcSocketType,
+    endpoint: String,
+    bind: bool,
+    topic: String,
+    high_water_mark: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            socket_type: DEFAULT_SOCKET_TYPE,
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            bind: DEFAULT_BIND,
+            topic: String::new(),
+            high_water_mark: DEFAULT_HIGH_WATER_MARK,
+        }
+    }
+}
+
+struct State {
+    caps: Option<gst::Caps>,
+    /// Whether delta units are dropped, until the first keyframe after
+    /// receiving the caps
+    waiting_for_keyframe: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            caps: None,
+            waiting_for_keyframe: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ZmqSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    // Separate from the state, as it's held while waiting for messages
+    socket: Mutex<Option<zmq::Socket>>,
+    flushing: AtomicBool,
+}
+
+impl ZmqSrc {
+    fn receive(&self, socket: &zmq::Socket) -> Result<Vec<Vec<u8>>, gst::FlowError> {
+        let error = |err: zmq::Error| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Read,
+                ["Failed to receive message: {}", err]
+            );
+            gst::FlowError::Error
+        };
+
+        loop {
+            if self.flushing.load(Ordering::SeqCst) {
+                gst::debug!(CAT, imp: self, "Flushing");
+                return Err(gst::FlowError::Flushing);
+            }
+
+            if socket.poll(zmq::POLLIN, POLL_TIMEOUT).map_err(error)? > 0 {
+                break;
+            }
+        }
+
+        socket.recv_multipart(zmq::DONTWAIT).map_err(error)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ZmqSrc {
+    const NAME: &'static str = "GstZmqSrc";
+    type Type = super::ZmqSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for ZmqSrc {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(true);
+        obj.set_format(gst::Format::Time);
+        obj.set_do_timestamp(true);
+    }
+
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("socket-type", DEFAULT_SOCKET_TYPE)
+                    .nick("Socket type")
+                    .blurb("Type of the ZeroMQ socket")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("endpoint")
+                    .nick("Endpoint")
+                    .blurb("ZeroMQ endpoint to bind or connect to, e.g. tcp://host:5555 or ipc:///tmp/stream")
+                    .default_value(Some(DEFAULT_ENDPOINT))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("bind")
+                    .nick("Bind")
+                    .blurb("Bind to the endpoint instead of connecting to it")
+                    .default_value(DEFAULT_BIND)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("topic")
+                    .nick("Topic")
+                    .blurb("Prefix of the topics to subscribe to with a SUB socket (empty = all)")
+                    .default_value(Some(""))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("high-water-mark")
+                    .nick("High water mark")
+                    .blurb("Maximum number of messages queued per peer (0 = unlimited)")
+                    .maximum(i32::MAX as u32)
+                    .default_value(DEFAULT_HIGH_WATER_MARK)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "socket-type" => {
+                settings.socket_type = value.get().expect("type checked upstream");
+            }
+            "endpoint" => {
+                settings.endpoint = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+            }
+            "bind" => {
+                settings.bind = value.get().expect("type checked upstream");
+            }
+            "topic" => {
+                settings.topic = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_default();
+            }
+            "high-water-mark" => {
+                settings.high_water_mark = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "socket-type" => settings.socket_type.to_value(),
+            "endpoint" => settings.endpoint.to_value(),
+            "bind" => settings.bind.to_value(),
+            "topic" => settings.topic.to_value(),
+            "high-water-mark" => settings.high_water_mark.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for ZmqSrc {}
+
+impl ElementImpl for ZmqSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "ZeroMQ Source",
+                "Source/Network",
+                "Receives buffers and caps from a ZeroMQ SUB or PULL socket",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for ZmqSrc {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let context = zmq::Context::new();
+        let socket = context
+            .socket(settings.socket_type.into())
+            .and_then(|socket| {
+                socket.set_rcvhwm(settings.high_water_mark as i32)?;
+                if settings.socket_type == ZmqSrcSocketType::Sub {
+                    socket.set_subscribe(settings.topic.as_bytes())?;
+                }
+
+                if settings.bind {
+                    socket.bind(&settings.endpoint)?;
+                } else {
+                    socket.connect(&settings.endpoint)?;
+                }
+
+                Ok(socket)
+            })
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    [
+                        "Failed to {} {}: {}",
+                        if settings.bind { "bind" } else { "connect to" },
+                        settings.endpoint,
+                        err
+                    ]
+                )
+            })?;
+
+        *self.socket.lock().unwrap() = Some(socket);
+        *self.state.lock().unwrap() = State::default();
+
+        gst::info!(CAT, imp: self, "Started on {}", settings.endpoint);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.socket.lock().unwrap() = None;
+        *self.state.lock().unwrap() = State::default();
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn caps(&self, filter: Option<&gst::Caps>) -> Option<gst::Caps> {
+        let caps = self
+            .state
+            .lock()
+            .unwrap()
+            .caps
+            .clone()
+            .unwrap_or_else(gst::Caps::new_any);
+
+        match filter {
+            Some(filter) => Some(filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First)),
+            None => Some(caps),
+        }
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        self.flushing.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for ZmqSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let socket = self.socket.lock().unwrap();
+        let Some(ref socket) = *socket else {
+            gst::element_imp_error!(self, gst::CoreError::Failed, ["Not started yet"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        loop {
+            let frames = self.receive(socket)?;
+            let mut state = self.state.lock().unwrap();
+
+            match protocol::parse(frames) {
+                Some(Message::Caps(caps)) => {
+                    if state.caps.as_ref() != Some(&caps) {
+                        gst::debug!(CAT, imp: self, "Received caps {}", caps);
+                        state.caps = Some(caps);
+                    }
+                }
+                Some(Message::Buffer(mut buffer)) => {
+                    let Some(caps) = state.caps.clone() else {
+                        gst::trace!(CAT, imp: self, "Dropping buffer received before the caps");
+                        continue;
+                    };
+
+                    let flags = buffer.flags();
+                    if state.waiting_for_keyframe && !flags.contains(gst::BufferFlags::HEADER) {
+                        if flags.contains(gst::BufferFlags::DELTA_UNIT) {
+                            gst::trace!(CAT, imp: self, "Waiting for keyframe, dropping {:?}", buffer);
+                            continue;
+                        }
+                        state.waiting_for_keyframe = false;
+                    }
+                    drop(state);
+
+                    let obj = self.obj();
+                    if obj.src_pad().current_caps().as_ref() != Some(&caps) {
+                        obj.set_caps(&caps)
+                            .map_err(|_| gst::FlowError::NotNegotiated)?;
+                    }
+
+                    // Timestamped by the base class otherwise
+                    if obj.do_timestamp() {
+                        let buffer = buffer.make_mut();
+                        buffer.set_pts(gst::ClockTime::NONE);
+                        buffer.set_dts(gst::ClockTime::NONE);
+                    }
+
+                    gst::log!(CAT, imp: self, "Outputting {:?}", buffer);
+
+                    return Ok(CreateSuccess::NewBuffer(buffer));
+                }
+                None => {
+                    gst::warning!(CAT, imp: self, "Dropping invalid message");
+                }
+            }
+        }
+    }
+}
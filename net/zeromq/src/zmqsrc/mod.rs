@@ -0,0 +1,67 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * element-zmqsrc:
+ *
+ * `zmqsrc` receives buffers sent by `zmqsink` over a ZeroMQ `SUB` or `PULL`
+ * socket. Buffers are dropped until the caps are received, and delta units
+ * until the first keyframe, so that decoding can start cleanly when joining a
+ * running stream.
+ *
+ * `SUB` sockets only receive the messages whose topic starts with `topic`.
+ *
+ * The buffers are timestamped with the running time of their arrival. With
+ * `do-timestamp=false`, the timestamps of the sent buffers are kept instead.
+ *
+ * ## Example pipeline
+ *
+ * ```bash
+ * gst-launch-1.0 zmqsrc endpoint=tcp://camera.local:5555 topic=camera1 ! h264parse ! \
+ *     avdec_h264 ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstZmqSrcSocketType")]
+pub enum ZmqSrcSocketType {
+    #[enum_value(name = "Sub: Subscribe to a publisher.", nick = "sub")]
+    Sub = 0,
+    #[enum_value(name = "Pull: Receive from pushing peers.", nick = "pull")]
+    Pull = 1,
+}
+
+impl From<ZmqSrcSocketType> for zmq::SocketType {
+    fn from(socket_type: ZmqSrcSocketType) -> Self {
+        match socket_type {
+            ZmqSrcSocketType::Sub => zmq::SUB,
+            ZmqSrcSocketType::Pull => zmq::PULL,
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct ZmqSrc(ObjectSubclass<imp::ZmqSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    ZmqSrcSocketType::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "zmqsrc",
+        gst::Rank::NONE,
+        ZmqSrc::static_type(),
+    )
+}
@@ -0,0 +1,110 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use std::time::Duration;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstzeromq::plugin_register_static().expect("zeromq test");
+    });
+}
+
+#[test]
+fn test_pub_sub() {
+    init();
+
+    let mut h1 = gst_check::Harness::new("zmqsink");
+    {
+        let sink = h1.element().unwrap();
+        sink.set_property("endpoint", "tcp://127.0.0.1:5020");
+        sink.set_property("topic", "test");
+    }
+    h1.set_src_caps_str("application/x-test");
+    h1.play();
+
+    let mut h2 = gst_check::Harness::new("zmqsrc");
+    {
+        let src = h2.element().unwrap();
+        src.set_property("endpoint", "tcp://127.0.0.1:5020");
+        src.set_property("topic", "test");
+        src.set_property("do-timestamp", false);
+    }
+    h2.play();
+
+    // Wait for the subscription to reach the publisher
+    std::thread::sleep(Duration::from_millis(500));
+
+    // Delta units are dropped until the first keyframe
+    let mut delta = gst::Buffer::from_slice(b"delta");
+    delta
+        .get_mut()
+        .unwrap()
+        .set_flags(gst::BufferFlags::DELTA_UNIT);
+    h1.push(delta).unwrap();
+
+    let mut keyframe = gst::Buffer::from_slice(b"keyframe");
+    {
+        let keyframe = keyframe.get_mut().unwrap();
+        keyframe.set_pts(gst::ClockTime::from_seconds(1));
+        keyframe.set_duration(gst::ClockTime::from_mseconds(40));
+    }
+    h1.push(keyframe).unwrap();
+
+    let buffer = h2.pull().unwrap();
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), b"keyframe");
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_seconds(1)));
+    assert_eq!(buffer.dts(), None);
+    assert_eq!(buffer.duration(), Some(gst::ClockTime::from_mseconds(40)));
+
+    let caps = h2.sinkpad().unwrap().current_caps().unwrap();
+    assert_eq!(caps, gst::Caps::new_empty_simple("application/x-test"));
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+    h1.element().unwrap().set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_push_pull() {
+    init();
+
+    let mut h1 = gst_check::Harness::new("zmqsrc");
+    {
+        let src = h1.element().unwrap();
+        src.set_property_from_str("socket-type", "pull");
+        src.set_property("endpoint", "tcp://127.0.0.1:5021");
+        src.set_property("bind", true);
+    }
+    h1.play();
+
+    let mut h2 = gst_check::Harness::new("zmqsink");
+    {
+        let sink = h2.element().unwrap();
+        sink.set_property_from_str("socket-type", "push");
+        sink.set_property("endpoint", "tcp://127.0.0.1:5021");
+        sink.set_property("bind", false);
+    }
+    h2.set_src_caps_str("application/x-test");
+    h2.play();
+
+    // PUSH sockets queue the messages until connected
+    for i in 0..5u8 {
+        h2.push(gst::Buffer::from_slice([i])).unwrap();
+    }
+
+    for i in 0..5u8 {
+        let buffer = h1.pull().unwrap();
+        assert_eq!(buffer.map_readable().unwrap().as_slice(), &[i]);
+        assert!(buffer.pts().is_some());
+    }
+
+    h2.element().unwrap().set_state(gst::State::Null).unwrap();
+    h1.element().unwrap().set_state(gst::State::Null).unwrap();
+}
@@ -31,6 +31,9 @@ struct Settings {
     buffer_start_segment: bool,
     n_previous: u32,
     previous_attributes: String,
+    current_template: String,
+    ahead_template: String,
+    previous_template: String,
 }
 
 impl Default for Settings {
@@ -43,6 +46,9 @@ impl Default for Settings {
             buffer_start_segment: false,
             n_previous: 0,
             previous_attributes: "size=\"smaller\"".to_string(),
+            current_template: String::new(),
+            ahead_template: String::new(),
+            previous_template: String::new(),
         }
     }
 }
@@ -58,6 +64,8 @@ struct State {
     previous: VecDeque<Input>,
     pending: Vec<Input>,
     done: bool,
+    /// Whether the input is already Pango markup, otherwise it is escaped.
+    markup: bool,
     /// Segment for which we should send a buffer with ahead text. Only set if `Settings.buffer_start_segment` is set.
     pending_segment: Option<gst::FormattedSegment<gst::format::Time>>,
 }
@@ -156,6 +164,24 @@ impl ObjectImpl for TextAhead {
                     .default_value(&*default.previous_attributes)
                     .mutable_playing()
                     .build(),
+                glib::ParamSpecString::builder("current-template")
+                    .nick("Current template")
+                    .blurb("Pango markup template for the text from the current buffer, {text} being replaced by the text. Takes precedence over current-attributes")
+                    .default_value(&*default.current_template)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("ahead-template")
+                    .nick("Ahead template")
+                    .blurb("Pango markup template for the ahead text, {text} being replaced by the text. Takes precedence over ahead-attributes")
+                    .default_value(&*default.ahead_template)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("previous-template")
+                    .nick("Previous template")
+                    .blurb("Pango markup template for the previous text, {text} being replaced by the text. Takes precedence over previous-attributes")
+                    .default_value(&*default.previous_template)
+                    .mutable_playing()
+                    .build(),
             ]
         });
 
@@ -187,6 +213,15 @@ impl ObjectImpl for TextAhead {
             "previous-attributes" => {
                 settings.previous_attributes = value.get().expect("type checked upstream");
             }
+            "current-template" => {
+                settings.current_template = value.get().expect("type checked upstream");
+            }
+            "ahead-template" => {
+                settings.ahead_template = value.get().expect("type checked upstream");
+            }
+            "previous-template" => {
+                settings.previous_template = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -202,6 +237,9 @@ impl ObjectImpl for TextAhead {
             "buffer-start-segment" => settings.buffer_start_segment.to_value(),
             "n-previous" => settings.n_previous.to_value(),
             "previous-attributes" => settings.previous_attributes.to_value(),
+            "current-template" => settings.current_template.to_value(),
+            "ahead-template" => settings.ahead_template.to_value(),
+            "previous-template" => settings.previous_template.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -298,6 +336,13 @@ impl TextAhead {
         // queue buffer
         let mut state = self.state.lock().unwrap();
 
+        // plain text has to be escaped as it is inserted into markup
+        let text = if state.markup {
+            text
+        } else {
+            glib::markup_escape_text(&text).to_string()
+        };
+
         gst::log!(CAT, imp: self, "input {:?}: {}", pts, text);
 
         state.pending.push(Input {
@@ -332,7 +377,14 @@ impl TextAhead {
                 }
                 gst::Pad::event_default(pad, Some(&*self.obj()), event)
             }
-            gst::EventView::Caps(_caps) => {
+            gst::EventView::Caps(caps) => {
+                let markup = caps
+                    .caps()
+                    .structure(0)
+                    .and_then(|s| s.get::<&str>("format").ok())
+                    == Some("pango-markup");
+                self.state.lock().unwrap().markup = markup;
+
                 // set caps on src pad
                 let element = self.obj();
                 let templ = element.class().pad_template("src").unwrap();
@@ -386,18 +438,12 @@ impl TextAhead {
                     text.push_str(&settings.separator);
                 }
 
-                if settings.ahead_attributes.is_empty() {
-                    text.push_str(&previous.text);
-                } else {
-                    use std::fmt::Write;
-
-                    write!(
-                        &mut text,
-                        "<span {}>{}</span>",
-                        settings.previous_attributes, previous.text,
-                    )
-                    .unwrap();
-                }
+                format_text(
+                    &mut text,
+                    &previous.text,
+                    &settings.previous_template,
+                    &settings.previous_attributes,
+                );
 
                 first_buffer = false;
             }
@@ -409,18 +455,12 @@ impl TextAhead {
                 text.push_str(&settings.separator);
             }
 
-            if settings.current_attributes.is_empty() {
-                text.push_str(&current.text);
-            } else {
-                use std::fmt::Write;
-
-                write!(
-                    &mut text,
-                    "<span {}>{}</span>",
-                    settings.current_attributes, current.text
-                )
-                .unwrap();
-            }
+            format_text(
+                &mut text,
+                &current.text,
+                &settings.current_template,
+                &settings.current_attributes,
+            );
 
             let pts = current.pts;
             let duration = current.duration;
@@ -442,18 +482,12 @@ impl TextAhead {
                 text.push_str(&settings.separator);
             }
 
-            if settings.ahead_attributes.is_empty() {
-                text.push_str(&input.text);
-            } else {
-                use std::fmt::Write;
-
-                write!(
-                    &mut text,
-                    "<span {}>{}</span>",
-                    settings.ahead_attributes, input.text,
-                )
-                .unwrap();
-            }
+            format_text(
+                &mut text,
+                &input.text,
+                &settings.ahead_template,
+                &settings.ahead_attributes,
+            );
         }
 
         gst::log!(CAT, imp: self, "output {:?}: {}", pts, text);
@@ -469,3 +503,16 @@ impl TextAhead {
         self.src_pad.push(output)
     }
 }
+
+/// Appends `input` to `text`, styled using the markup template if any, or else using the span attributes
+fn format_text(text: &mut String, input: &str, template: &str, attributes: &str) {
+    if !template.is_empty() {
+        text.push_str(&template.replace("{text}", input));
+    } else if attributes.is_empty() {
+        text.push_str(input);
+    } else {
+        use std::fmt::Write;
+
+        write!(text, "<span {attributes}>{input}</span>").unwrap();
+    }
+}
@@ -0,0 +1,436 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+use crate::line_reader::LineReader;
+
+use super::TimestampUnit;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "jsonlinesparse",
+        gst::DebugColorFlags::empty(),
+        Some("JSON Lines Parser Element"),
+    )
+});
+
+const DEFAULT_TIMESTAMP_FIELD: Option<String> = None;
+const DEFAULT_TIMESTAMP_UNIT: TimestampUnit = TimestampUnit::Seconds;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    timestamp_field: Option<String>,
+    timestamp_unit: TimestampUnit,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            timestamp_field: DEFAULT_TIMESTAMP_FIELD,
+            timestamp_unit: DEFAULT_TIMESTAMP_UNIT,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    reader: LineReader<gst::MappedBuffer<gst::buffer::Readable>>,
+    // Timestamp of the last input buffer, used if no timestamp field is configured
+    last_pts: Option<gst::ClockTime>,
+    discont: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            reader: LineReader::new(),
+            last_pts: None,
+            discont: true,
+        }
+    }
+}
+
+pub struct JsonLinesParse {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+// Strips leading and trailing whitespace, including the `\r` of CRLF line endings
+fn trim(mut line: &[u8]) -> &[u8] {
+    while let [first, rest @ ..] = line {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        line = rest;
+    }
+    while let [rest @ .., last] = line {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        line = rest;
+    }
+
+    line
+}
+
+/// Looks up `field` in `document`, either as a JSON pointer if it starts with a `/` or as a
+/// top-level key otherwise, and converts it to a timestamp.
+fn parse_timestamp(
+    document: &serde_json::Value,
+    field: &str,
+    unit: TimestampUnit,
+) -> Option<gst::ClockTime> {
+    let value = if field.starts_with('/') {
+        document.pointer(field)?
+    } else {
+        document.get(field)?
+    };
+
+    let value = match value {
+        serde_json::Value::Number(number) => number.as_f64()?,
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok()?,
+        _ => return None,
+    };
+
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+
+    let scale = match unit {
+        TimestampUnit::Seconds => 1_000_000_000.0,
+        TimestampUnit::Milliseconds => 1_000_000.0,
+        TimestampUnit::Microseconds => 1_000.0,
+        TimestampUnit::Nanoseconds => 1.0,
+    };
+
+    Some(gst::ClockTime::from_nseconds((value * scale).round() as u64))
+}
+
+impl JsonLinesParse {
+    fn handle_buffer(
+        &self,
+        buffer: Option<gst::Buffer>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+
+        let drain = if let Some(buffer) = buffer {
+            if buffer.flags().contains(gst::BufferFlags::DISCONT) {
+                state.discont = true;
+            }
+            if buffer.pts().is_some() {
+                state.last_pts = buffer.pts();
+            }
+
+            let buffer = buffer.into_mapped_buffer_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+
+                gst::FlowError::Error
+            })?;
+
+            state.reader.push(buffer);
+            false
+        } else {
+            true
+        };
+
+        let mut outbufs = Vec::new();
+        while let Some(line) = state.reader.line_with_drain(drain) {
+            let line = trim(line);
+            if line.is_empty() {
+                continue;
+            }
+
+            let document: serde_json::Value = match serde_json::from_slice(line) {
+                Ok(document) => document,
+                Err(err) => {
+                    gst::warning!(
+                        CAT,
+                        imp: self,
+                        "Skipping invalid line '{:?}': {}",
+                        std::str::from_utf8(line),
+                        err
+                    );
+                    gst::element_imp_warning!(
+                        self,
+                        gst::StreamError::Decode,
+                        ["Skipping invalid line: {}", err]
+                    );
+                    continue;
+                }
+            };
+
+            let pts = match settings.timestamp_field {
+                Some(ref field) => {
+                    let pts = parse_timestamp(&document, field, settings.timestamp_unit);
+                    if pts.is_none() {
+                        gst::warning!(
+                            CAT,
+                            imp: self,
+                            "No valid timestamp in field {} of document",
+                            field
+                        );
+                    }
+                    pts
+                }
+                None => state.last_pts,
+            };
+
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Got document of {} bytes with timestamp {}",
+                line.len(),
+                pts.display()
+            );
+
+            let mut buffer = gst::Buffer::from_mut_slice(line.to_vec());
+            {
+                let buffer = buffer.get_mut().unwrap();
+                buffer.set_pts(pts);
+                if state.discont {
+                    buffer.set_flags(gst::BufferFlags::DISCONT);
+                    state.discont = false;
+                }
+            }
+            outbufs.push(buffer);
+        }
+        drop(guard);
+
+        for buffer in outbufs {
+            self.srcpad.push(buffer).map_err(|err| {
+                if err != gst::FlowError::Flushing {
+                    gst::error!(CAT, imp: self, "Pushing buffer returned {:?}", err);
+                }
+                err
+            })?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        self.handle_buffer(Some(buffer))
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                // We send our own caps downstream
+                let caps = gst::Caps::builder("application/x-json").build();
+                self.srcpad.push_event(gst::event::Caps::new(&caps))
+            }
+            EventView::Segment(e) => {
+                let has_timestamp_field = self.settings.lock().unwrap().timestamp_field.is_some();
+
+                // Upstream time segments are kept if the timestamps come from upstream,
+                // otherwise timestamps are stream times parsed from the documents
+                if !has_timestamp_field && e.segment().format() == gst::Format::Time {
+                    gst::Pad::event_default(pad, Some(&*self.obj()), event)
+                } else {
+                    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+                    self.srcpad.push_event(
+                        gst::event::Segment::builder(&segment)
+                            .seqnum(event.seqnum())
+                            .build(),
+                    )
+                }
+            }
+            EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.reader.clear();
+                state.last_pts = None;
+                state.discont = true;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                gst::log!(CAT, obj: pad, "Draining");
+                if let Err(err) = self.handle_buffer(None) {
+                    gst::error!(CAT, obj: pad, "Failed to drain parser: {:?}", err);
+                }
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for JsonLinesParse {
+    const NAME: &'static str = "GstJsonLinesParse";
+    type Type = super::JsonLinesParse;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                JsonLinesParse::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |parse| parse.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                JsonLinesParse::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |parse| parse.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::from_template(&templ);
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for JsonLinesParse {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("timestamp-field")
+                    .nick("Timestamp Field")
+                    .blurb("Field of the documents containing their timestamp, either a top-level key or a JSON pointer starting with / (NULL=use the input timestamps)")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("timestamp-unit", DEFAULT_TIMESTAMP_UNIT)
+                    .nick("Timestamp Unit")
+                    .blurb("Unit of the timestamps in the timestamp field")
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "timestamp-field" => {
+                settings.timestamp_field = value.get().expect("type checked upstream");
+            }
+            "timestamp-unit" => {
+                settings.timestamp_unit = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "timestamp-field" => settings.timestamp_field.to_value(),
+            "timestamp-unit" => settings.timestamp_unit.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for JsonLinesParse {}
+
+impl ElementImpl for JsonLinesParse {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "JSON Lines parser",
+                "Parser/JSON",
+                "Splits a JSON Lines byte stream into one complete JSON document per buffer",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("application/x-json").build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::new_any();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady => {
+                // Reset the whole state
+                let mut state = self.state.lock().unwrap();
+                *state = State::default();
+            }
+            _ => (),
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,40 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstJsonLinesTimestampUnit")]
+pub enum TimestampUnit {
+    #[enum_value(name = "Seconds", nick = "seconds")]
+    Seconds,
+    #[enum_value(name = "Milliseconds", nick = "milliseconds")]
+    Milliseconds,
+    #[enum_value(name = "Microseconds", nick = "microseconds")]
+    Microseconds,
+    #[enum_value(name = "Nanoseconds", nick = "nanoseconds")]
+    Nanoseconds,
+}
+
+glib::wrapper! {
+    pub struct JsonLinesParse(ObjectSubclass<imp::JsonLinesParse>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    TimestampUnit::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "jsonlinesparse",
+        gst::Rank::NONE,
+        JsonLinesParse::static_type(),
+    )
+}
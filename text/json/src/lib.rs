@@ -17,11 +17,13 @@ use gst::glib;
 
 mod jsongstenc;
 mod jsongstparse;
+mod jsonlinesparse;
 mod line_reader;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     jsongstparse::register(plugin)?;
     jsongstenc::register(plugin)?;
+    jsonlinesparse::register(plugin)?;
     Ok(())
 }
 
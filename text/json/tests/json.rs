@@ -98,3 +98,40 @@ fn test_parse() {
     assert_eq!(buf.duration(), Some(2.seconds()));
     assert_eq!(std::str::from_utf8(map.as_ref()), Ok("{\"foo\":42}"));
 }
+
+#[test]
+fn test_lines_parse() {
+    init();
+
+    let input = [
+        "{\"ts\":1.5,\"a\":1}\n{\"ts\":2",
+        ".5,\"b\":[1,2]}\r\n\nnot json\n",
+        "  {\"ts\":\"3\"}",
+    ];
+
+    let mut h = gst_check::Harness::new("jsonlinesparse");
+    h.element().unwrap().set_property("timestamp-field", "ts");
+
+    h.set_src_caps_str("application/octet-stream");
+
+    for input in &input {
+        let buf = gst::Buffer::from_mut_slice(Vec::from(&input[..]));
+        assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let expected = [
+        (1500.mseconds(), "{\"ts\":1.5,\"a\":1}"),
+        (2500.mseconds(), "{\"ts\":2.5,\"b\":[1,2]}"),
+        (3.seconds(), "{\"ts\":\"3\"}"),
+    ];
+
+    for (pts, data) in expected {
+        let buf = h.pull().expect("Couldn't pull buffer");
+        let map = buf.map_readable().expect("Couldn't map buffer readable");
+        assert_eq!(buf.pts(), Some(pts));
+        assert_eq!(std::str::from_utf8(map.as_ref()), Ok(data));
+    }
+
+    assert_eq!(h.buffers_in_queue(), 0);
+}
@@ -10,9 +10,12 @@ use gst::glib;
 use gst::prelude::*;
 use gst::subclass::prelude::*;
 
-use regex::{Regex, RegexBuilder};
+use regex::{NoExpand, Regex, RegexBuilder};
 use std::default::Default;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Mutex;
+use std::time::SystemTime;
 
 use once_cell::sync::Lazy;
 
@@ -26,17 +29,69 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 
 enum Operation {
     ReplaceAll(String),
+    Replace(String, usize),
 }
 
 struct Command {
     pattern: String,
     regex: Regex,
     operation: Operation,
+    // Whether `$1` / `${name}` in the replacement refer to capture groups
+    expand: bool,
+    enabled: bool,
+}
+
+impl Command {
+    fn apply(&self, data: &str) -> String {
+        match self.operation {
+            Operation::ReplaceAll(ref replacement) => {
+                if self.expand {
+                    self.regex.replace_all(data, replacement.as_str())
+                } else {
+                    self.regex.replace_all(data, NoExpand(replacement))
+                }
+            }
+            Operation::Replace(ref replacement, limit) => {
+                if self.expand {
+                    self.regex.replacen(data, limit, replacement.as_str())
+                } else {
+                    self.regex.replacen(data, limit, NoExpand(replacement))
+                }
+            }
+        }
+        .to_string()
+    }
+
+    fn to_structure(&self) -> gst::Structure {
+        let builder = match self.operation {
+            Operation::ReplaceAll(ref replacement) => gst::Structure::builder("replace-all")
+                .field("pattern", &self.pattern)
+                .field("replacement", replacement),
+            Operation::Replace(ref replacement, limit) => gst::Structure::builder("replace")
+                .field("pattern", &self.pattern)
+                .field("replacement", replacement)
+                .field("limit", limit as u32),
+        };
+
+        builder
+            .field("expand", self.expand)
+            .field("enabled", self.enabled)
+            .build()
+    }
+}
+
+#[derive(Default)]
+struct RulesFile {
+    path: PathBuf,
+    // Modification time and size of the file when it was last loaded
+    loaded: Option<(SystemTime, u64)>,
+    commands: Vec<Command>,
 }
 
 #[derive(Default)]
 struct State {
     commands: Vec<Command>,
+    rules_file: Option<RulesFile>,
 }
 
 pub struct RegEx {
@@ -70,16 +125,20 @@ impl RegEx {
             })?
             .to_string();
 
-        let state = self.state.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
 
-        for command in &state.commands {
-            match &command.operation {
-                Operation::ReplaceAll(replacement) => {
-                    data = command
-                        .regex
-                        .replace_all(&data, replacement.as_str())
-                        .to_string();
-                }
+        if let Some(ref mut rules_file) = state.rules_file {
+            self.reload_rules_file(rules_file, false);
+        }
+
+        let file_commands = state
+            .rules_file
+            .iter()
+            .flat_map(|rules_file| rules_file.commands.iter());
+
+        for command in state.commands.iter().chain(file_commands) {
+            if command.enabled {
+                data = command.apply(&data);
             }
         }
 
@@ -100,6 +159,170 @@ impl RegEx {
 
         self.srcpad.push(outbuf)
     }
+
+    fn parse_command(&self, s: &gst::StructureRef) -> Option<Command> {
+        let operation = s.name();
+
+        let pattern = match s.get::<Option<String>>("pattern") {
+            Ok(Some(pattern)) => pattern,
+            Ok(None) | Err(_) => {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "All commands require a pattern field as a string"
+                );
+                return None;
+            }
+        };
+
+        let mut builder = RegexBuilder::new(&pattern);
+        builder
+            .unicode(s.get::<bool>("unicode").unwrap_or(true))
+            .case_insensitive(s.get::<bool>("case-insensitive").unwrap_or(false))
+            .multi_line(s.get::<bool>("multi-line").unwrap_or(false))
+            .dot_matches_new_line(s.get::<bool>("dot-matches-new-line").unwrap_or(false))
+            .crlf(s.get::<bool>("crlf").unwrap_or(false))
+            .line_terminator(s.get::<u8>("line-terminator").unwrap_or(b'\n'))
+            .swap_greed(s.get::<bool>("swap-greed").unwrap_or(false))
+            .ignore_whitespace(s.get::<bool>("ignore-whitespace").unwrap_or(false))
+            .octal(s.get::<bool>("octal").unwrap_or(false));
+
+        if let Ok(limit) = s.get::<u64>("size-limit") {
+            builder.size_limit(limit as usize);
+        }
+
+        if let Ok(limit) = s.get::<u64>("dfa-size-limit") {
+            builder.dfa_size_limit(limit as usize);
+        }
+
+        if let Ok(limit) = s.get::<u32>("nest-limit") {
+            builder.nest_limit(limit);
+        }
+
+        let regex = match builder.build() {
+            Ok(regex) => regex,
+            Err(err) => {
+                gst::error!(CAT, imp: self, "Failed to compile regex: {:?}", err);
+                return None;
+            }
+        };
+
+        let replacement = match s.get::<Option<String>>("replacement") {
+            Ok(Some(replacement)) => replacement,
+            Ok(None) | Err(_) => {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Replace operations require a replacement field as a string"
+                );
+                return None;
+            }
+        };
+
+        let operation = match operation.as_str() {
+            "replace-all" | "replace_all" => Operation::ReplaceAll(replacement),
+            "replace" => {
+                let limit = s.get::<u32>("limit").unwrap_or(1);
+                Operation::Replace(replacement, limit as usize)
+            }
+            val => {
+                gst::error!(CAT, imp: self, "Unknown operation {}", val);
+                return None;
+            }
+        };
+
+        Some(Command {
+            pattern,
+            regex,
+            operation,
+            expand: s.get::<bool>("expand").unwrap_or(true),
+            enabled: s.get::<bool>("enabled").unwrap_or(true),
+        })
+    }
+
+    // Parses a rules file: one command per line, serialized as a `GstStructure`. Empty lines
+    // and lines starting with `#` are ignored.
+    fn parse_rules_file(&self, path: &Path) -> Result<Vec<Command>, std::io::Error> {
+        let contents = std::fs::read_to_string(path)?;
+
+        let mut commands = vec![];
+        for (lineno, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match gst::Structure::from_str(line) {
+                Ok(s) => {
+                    if let Some(command) = self.parse_command(&s) {
+                        commands.push(command);
+                    }
+                }
+                Err(err) => {
+                    gst::error!(
+                        CAT,
+                        imp: self,
+                        "Failed to parse rule at {}:{}: {}",
+                        path.display(),
+                        lineno + 1,
+                        err
+                    );
+                }
+            }
+        }
+
+        Ok(commands)
+    }
+
+    // (Re)loads the rules file if it changed since it was last loaded, keeping the previous
+    // rules if it can't be read
+    fn reload_rules_file(&self, rules_file: &mut RulesFile, force: bool) {
+        let stamp = match std::fs::metadata(&rules_file.path)
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+        {
+            Ok(stamp) => stamp,
+            Err(err) => {
+                if force || rules_file.loaded.is_some() {
+                    gst::warning!(
+                        CAT,
+                        imp: self,
+                        "Failed to access rules file {}: {}",
+                        rules_file.path.display(),
+                        err
+                    );
+                }
+                rules_file.loaded = None;
+                return;
+            }
+        };
+
+        if !force && rules_file.loaded == Some(stamp) {
+            return;
+        }
+
+        match self.parse_rules_file(&rules_file.path) {
+            Ok(commands) => {
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Loaded {} rules from {}",
+                    commands.len(),
+                    rules_file.path.display()
+                );
+                rules_file.commands = commands;
+            }
+            Err(err) => {
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Failed to read rules file {}: {}",
+                    rules_file.path.display(),
+                    err
+                );
+            }
+        }
+        rules_file.loaded = Some(stamp);
+    }
 }
 
 #[glib::object_subclass]
@@ -139,17 +362,24 @@ impl ObjectSubclass for RegEx {
 impl ObjectImpl for RegEx {
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-            vec![gst::ParamSpecArray::builder("commands")
-                .nick("Commands")
-                .blurb("A set of commands to apply on input text")
-                .element_spec(
-                    &glib::ParamSpecBoxed::builder::<gst::Structure>("command")
-                        .nick("Command")
-                        .blurb("A command to apply on input text")
-                        .build(),
-                )
-                .mutable_playing()
-                .build()]
+            vec![
+                gst::ParamSpecArray::builder("commands")
+                    .nick("Commands")
+                    .blurb("A set of commands to apply on input text")
+                    .element_spec(
+                        &glib::ParamSpecBoxed::builder::<gst::Structure>("command")
+                            .nick("Command")
+                            .blurb("A command to apply on input text")
+                            .build(),
+                    )
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("rules-file")
+                    .nick("Rules File")
+                    .blurb("File with one command per line, applied after the commands property and reloaded whenever it changes")
+                    .mutable_playing()
+                    .build(),
+            ]
         });
 
         PROPERTIES.as_ref()
@@ -179,79 +409,25 @@ impl ObjectImpl for RegEx {
                             continue;
                         }
                     };
-                    let operation = s.name();
-
-                    let pattern = match s.get::<Option<String>>("pattern") {
-                        Ok(Some(pattern)) => pattern,
-                        Ok(None) | Err(_) => {
-                            gst::error!(
-                                CAT,
-                                imp: self,
-                                "All commands require a pattern field as a string"
-                            );
-                            continue;
-                        }
-                    };
-
-                    let mut builder = RegexBuilder::new(&pattern);
-                    builder
-                        .unicode(s.get::<bool>("unicode").unwrap_or(true))
-                        .case_insensitive(s.get::<bool>("case-insensitive").unwrap_or(false))
-                        .multi_line(s.get::<bool>("multi-line").unwrap_or(false))
-                        .dot_matches_new_line(
-                            s.get::<bool>("dot-matches-new-line").unwrap_or(false),
-                        )
-                        .crlf(s.get::<bool>("crlf").unwrap_or(false))
-                        .line_terminator(s.get::<u8>("line-terminator").unwrap_or(b'\n'))
-                        .swap_greed(s.get::<bool>("swap-greed").unwrap_or(false))
-                        .ignore_whitespace(s.get::<bool>("ignore-whitespace").unwrap_or(false))
-                        .octal(s.get::<bool>("octal").unwrap_or(false));
-
-                    if let Ok(limit) = s.get::<u64>("size-limit") {
-                        builder.size_limit(limit as usize);
-                    }
-
-                    if let Ok(limit) = s.get::<u64>("dfa-size-limit") {
-                        builder.dfa_size_limit(limit as usize);
-                    }
-
-                    if let Ok(limit) = s.get::<u32>("nest-limit") {
-                        builder.nest_limit(limit);
-                    }
-
-                    let regex = match builder.build() {
-                        Ok(regex) => regex,
-                        Err(err) => {
-                            gst::error!(CAT, imp: self, "Failed to compile regex: {:?}", err);
-                            continue;
-                        }
-                    };
-
-                    match operation.as_str() {
-                        "replace-all" | "replace_all" => {
-                            let replacement = match s.get::<Option<String>>("replacement") {
-                                Ok(Some(pattern)) => pattern,
-                                Ok(None) | Err(_) => {
-                                    gst::error!(
-                                        CAT,
-                                        imp: self,
-                                        "Replace operations require a replacement field as a string"
-                                    );
-                                    continue;
-                                }
-                            };
-                            state.commands.push(Command {
-                                pattern,
-                                regex,
-                                operation: Operation::ReplaceAll(replacement),
-                            });
-                        }
-                        val => {
-                            gst::error!(CAT, imp: self, "Unknown operation {}", val);
-                        }
+                    if let Some(command) = self.parse_command(&s) {
+                        state.commands.push(command);
                     }
                 }
             }
+            "rules-file" => {
+                let path = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+                let mut state = self.state.lock().unwrap();
+                state.rules_file = path.map(|path| {
+                    let mut rules_file = RulesFile {
+                        path: PathBuf::from(path),
+                        ..Default::default()
+                    };
+                    self.reload_rules_file(&mut rules_file, true);
+                    rules_file
+                });
+            }
             _ => unimplemented!(),
         }
     }
@@ -262,19 +438,18 @@ impl ObjectImpl for RegEx {
                 let state = self.state.lock().unwrap();
                 let mut commands = gst::Array::default();
                 for command in &state.commands {
-                    match command.operation {
-                        Operation::ReplaceAll(ref replacement) => {
-                            commands.append(
-                                gst::Structure::builder("replace-all")
-                                    .field("pattern", &command.pattern)
-                                    .field("replacement", replacement)
-                                    .build(),
-                            );
-                        }
-                    }
+                    commands.append(command.to_structure());
                 }
                 commands.to_value()
             }
+            "rules-file" => {
+                let state = self.state.lock().unwrap();
+                state
+                    .rules_file
+                    .as_ref()
+                    .and_then(|rules_file| rules_file.path.to_str())
+                    .to_value()
+            }
             _ => unimplemented!(),
         }
     }
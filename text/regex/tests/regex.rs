@@ -65,3 +65,103 @@ fn test_replace_all() {
         std::str::from_utf8(expected_output.as_ref())
     );
 }
+
+fn push_text(h: &mut gst_check::Harness, text: &str) -> String {
+    let buf = gst::Buffer::from_mut_slice(Vec::from(text.as_bytes()));
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    let buf = h.pull().expect("Couldn't pull buffer");
+    let map = buf.map_readable().expect("Couldn't map buffer readable");
+
+    std::str::from_utf8(map.as_ref()).unwrap().to_string()
+}
+
+#[test]
+fn test_capture_groups() {
+    init();
+
+    let mut h = gst_check::Harness::new("regex");
+
+    {
+        let regex = h.element().expect("Could not create regex");
+
+        let commands = gst::Array::new([
+            gst::Structure::builder("replace-all")
+                .field("pattern", r"(\w+), (?P<first>\w+)")
+                .field("replacement", "${first} $1")
+                .build(),
+            gst::Structure::builder("replace")
+                .field("pattern", "o")
+                .field("replacement", "0")
+                .field("limit", 2u32)
+                .build(),
+            gst::Structure::builder("replace-all")
+                .field("pattern", "D0e")
+                .field("replacement", "$1")
+                .field("expand", false)
+                .build(),
+            gst::Structure::builder("replace-all")
+                .field("pattern", ".*")
+                .field("replacement", "")
+                .field("enabled", false)
+                .build(),
+        ]);
+
+        regex.set_property("commands", &commands);
+    }
+
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    assert_eq!(push_text(&mut h, "Doe, John"), "J0hn $1");
+    assert_eq!(push_text(&mut h, "Poe, Edgar foo"), "Edgar P0e f0o");
+}
+
+#[test]
+fn test_rules_file() {
+    init();
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    {
+        use std::io::Write;
+
+        writeln!(file, "# Swap words").unwrap();
+        writeln!(
+            file,
+            r#"replace-all, pattern="(\\w+) (\\w+)", replacement="$2 $1""#
+        )
+        .unwrap();
+        writeln!(file).unwrap();
+        writeln!(
+            file,
+            "replace-all, pattern=world, replacement=there, enabled=false"
+        )
+        .unwrap();
+        file.flush().unwrap();
+    }
+
+    let mut h = gst_check::Harness::new("regex");
+
+    {
+        let regex = h.element().expect("Could not create regex");
+        regex.set_property("rules-file", file.path().to_str().unwrap());
+
+        let commands = gst::Array::new([gst::Structure::builder("replace-all")
+            .field("pattern", "hello")
+            .field("replacement", "hi")
+            .build()]);
+        regex.set_property("commands", &commands);
+    }
+
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    assert_eq!(push_text(&mut h, "hello world"), "world hi");
+
+    // Rewriting the file reloads the rules
+    std::fs::write(
+        file.path(),
+        "replace-all, pattern=world, replacement=\"everyone out there\"\n",
+    )
+    .unwrap();
+
+    assert_eq!(push_text(&mut h, "hello world"), "hi everyone out there");
+}
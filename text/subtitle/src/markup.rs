@@ -0,0 +1,428 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Conversion between the HTML-like formatting tags of subtitle formats and Pango markup.
+
+use std::fmt::Write;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Tag {
+    closing: bool,
+    // Lowercase tag name
+    name: String,
+    // Lowercase attribute names and their values
+    attributes: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn attribute(&self, names: &[&str]) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| names.contains(&name.as_str()))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Text(&'a str),
+    Tag(Tag),
+}
+
+// Parses the content between `<` and `>`, returns `None` if it doesn't look like a tag
+fn parse_tag(content: &str) -> Option<Tag> {
+    let (closing, content) = match content.strip_prefix('/') {
+        Some(content) => (true, content),
+        None => (false, content),
+    };
+    let content = content.trim_end_matches('/').trim_end();
+
+    let name_end = content
+        .find(|c: char| c.is_whitespace())
+        .unwrap_or(content.len());
+    let name = &content[..name_end];
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !name.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+
+    let mut attributes = vec![];
+    let mut rest = content[name_end..].trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = rest[..key_end].to_ascii_lowercase();
+        rest = rest[key_end..].trim_start();
+
+        let value = if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let value = &value[1..];
+                    let end = value.find(quote).unwrap_or(value.len());
+                    rest = value.get((end + 1)..).unwrap_or("");
+                    &value[..end]
+                }
+                _ => {
+                    let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                    rest = &value[end..];
+                    &value[..end]
+                }
+            }
+        } else {
+            ""
+        };
+
+        attributes.push((key, value.to_string()));
+        rest = rest.trim_start();
+    }
+
+    Some(Tag {
+        closing,
+        name: name.to_ascii_lowercase(),
+        attributes,
+    })
+}
+
+// Splits text into runs of text and tags. Anything that can't be parsed as a tag is text.
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut pos = 0;
+    let mut text_start = 0;
+
+    while let Some(offset) = text[pos..].find('<') {
+        let start = pos + offset;
+        let content = &text[(start + 1)..];
+
+        if let Some(len) = content.find(|c| c == '<' || c == '>') {
+            if content.as_bytes()[len] == b'>' {
+                if let Some(tag) = parse_tag(&content[..len]) {
+                    if text_start < start {
+                        tokens.push(Token::Text(&text[text_start..start]));
+                    }
+                    tokens.push(Token::Tag(tag));
+                    pos = start + len + 2;
+                    text_start = pos;
+                    continue;
+                }
+            }
+        }
+
+        pos = start + 1;
+    }
+
+    if text_start < text.len() {
+        tokens.push(Token::Text(&text[text_start..]));
+    }
+
+    tokens
+}
+
+// Removes ASS style override blocks like `{\an8}` that some subtitle files contain
+fn strip_overrides(text: &str) -> std::borrow::Cow<'_, str> {
+    if !text.contains("{\\") {
+        return text.into();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{\\") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find('}') {
+            Some(end) => rest = &rest[(start + end + 1)..],
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out.into()
+}
+
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let code = if let Some(hex) = entity
+                        .strip_prefix("#x")
+                        .or_else(|| entity.strip_prefix("#X"))
+                    {
+                        u32::from_str_radix(hex, 16).ok()?
+                    } else {
+                        entity.strip_prefix('#')?.parse::<u32>().ok()?
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[(end + 1)..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out
+}
+
+/// Converts subtitle text with `<b>`, `<i>`, `<u>`, `<s>` and `<font>` tags to Pango markup.
+///
+/// Unknown tags are dropped and the tags are balanced so that the result is always valid
+/// markup.
+pub fn tags_to_pango(text: &str) -> String {
+    let text = strip_overrides(text);
+    let mut out = String::with_capacity(text.len());
+    // Open tags with their Pango opening and closing tags
+    let mut stack: Vec<(String, String, String)> = vec![];
+
+    for token in tokenize(&text) {
+        match token {
+            Token::Text(text) => out.push_str(&escape(text)),
+            Token::Tag(tag) if !tag.closing => {
+                let (open, close) = match tag.name.as_str() {
+                    "b" | "i" | "u" | "s" => {
+                        (format!("<{}>", tag.name), format!("</{}>", tag.name))
+                    }
+                    "font" => {
+                        let mut open = String::from("<span");
+                        if let Some(color) = tag.attribute(&["color"]) {
+                            let _ = write!(open, " foreground=\"{}\"", escape(color));
+                        }
+                        if let Some(face) = tag.attribute(&["face"]) {
+                            let _ = write!(open, " font_family=\"{}\"", escape(face));
+                        }
+                        open.push('>');
+                        (open, String::from("</span>"))
+                    }
+                    _ => continue,
+                };
+
+                out.push_str(&open);
+                stack.push((tag.name, open, close));
+            }
+            Token::Tag(tag) => {
+                let Some(pos) = stack.iter().rposition(|(name, ..)| *name == tag.name) else {
+                    continue;
+                };
+
+                // Close the tags opened in between and reopen them afterwards
+                for (_, _, close) in stack[pos..].iter().rev() {
+                    out.push_str(close);
+                }
+                let reopen = stack.split_off(pos + 1);
+                stack.pop();
+                for (_, open, _) in &reopen {
+                    out.push_str(open);
+                }
+                stack.extend(reopen);
+            }
+        }
+    }
+
+    for (_, _, close) in stack.iter().rev() {
+        out.push_str(close);
+    }
+
+    out
+}
+
+/// Removes all formatting tags from subtitle text.
+pub fn strip_tags(text: &str) -> String {
+    tokenize(&strip_overrides(text))
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Text(text) => Some(text),
+            Token::Tag(_) => None,
+        })
+        .collect()
+}
+
+// Converts Pango markup, keeping the tags in `keep` and converting spans to the opening and
+// closing tags returned by `span`. Everything else that can't be represented is dropped.
+fn convert_pango(
+    markup: &str,
+    keep: &[&str],
+    escape_text: impl Fn(&str) -> String,
+    span: impl Fn(&Tag) -> Option<(String, String)>,
+) -> String {
+    let mut out = String::with_capacity(markup.len());
+    // Open Pango tags with the closing tag they were converted to
+    let mut stack: Vec<(String, Option<String>)> = vec![];
+
+    for token in tokenize(markup) {
+        match token {
+            Token::Text(text) => out.push_str(&escape_text(&unescape(text))),
+            Token::Tag(tag) if !tag.closing => {
+                let converted = if keep.contains(&tag.name.as_str()) {
+                    Some((format!("<{}>", tag.name), format!("</{}>", tag.name)))
+                } else if tag.name == "span" {
+                    span(&tag)
+                } else {
+                    None
+                };
+
+                let close = converted.map(|(open, close)| {
+                    out.push_str(&open);
+                    close
+                });
+                stack.push((tag.name, close));
+            }
+            Token::Tag(tag) => {
+                if let Some(pos) = stack.iter().rposition(|(name, _)| *name == tag.name) {
+                    for (_, close) in stack.drain(pos..).rev() {
+                        out.extend(close);
+                    }
+                }
+            }
+        }
+    }
+
+    for (_, close) in stack.into_iter().rev() {
+        out.extend(close);
+    }
+
+    out
+}
+
+/// Converts Pango markup to subtitle text with `<b>`, `<i>`, `<u>`, `<s>` and `<font>` tags.
+///
+/// Spans are converted to `<font>` tags if they set a foreground color and everything else
+/// that can't be represented is dropped.
+pub fn pango_to_tags(markup: &str) -> String {
+    convert_pango(
+        markup,
+        &["b", "i", "u", "s"],
+        |text| text.to_string(),
+        |tag| {
+            tag.attribute(&["foreground", "fgcolor", "color"])
+                .map(|color| (format!("<font color=\"{color}\">"), String::from("</font>")))
+        },
+    )
+}
+
+// Colors that have a default class in WebVTT
+const VTT_COLORS: &[(&str, &str)] = &[
+    ("white", "#ffffff"),
+    ("lime", "#00ff00"),
+    ("cyan", "#00ffff"),
+    ("red", "#ff0000"),
+    ("yellow", "#ffff00"),
+    ("magenta", "#ff00ff"),
+    ("blue", "#0000ff"),
+    ("black", "#000000"),
+];
+
+/// Escapes the characters that are not allowed in WebVTT cue text.
+pub fn escape_vtt(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Converts Pango markup to WebVTT cue text with `<b>`, `<i>`, `<u>` and `<c>` tags.
+///
+/// Spans are converted to `<c>` tags with the matching default color class if their foreground
+/// color has one and everything else that can't be represented is dropped.
+pub fn pango_to_vtt(markup: &str) -> String {
+    convert_pango(markup, &["b", "i", "u"], escape_vtt, |tag| {
+        let color = tag
+            .attribute(&["foreground", "fgcolor", "color"])?
+            .to_ascii_lowercase();
+        let (class, _) = VTT_COLORS
+            .iter()
+            .find(|(name, hex)| *name == color || *hex == color)?;
+
+        Some((format!("<c.{class}>"), String::from("</c>")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_to_pango() {
+        assert_eq!(
+            tags_to_pango("<b>bold</b> & <font color=\"#ff0000\">red</font>"),
+            "<b>bold</b> &amp; <span foreground=\"#ff0000\">red</span>"
+        );
+        assert_eq!(
+            tags_to_pango("<b>a<i>b</b>c</i>"),
+            "<b>a<i>b</i></b><i>c</i>"
+        );
+        assert_eq!(tags_to_pango("{\\an8}<i>top"), "<i>top</i>");
+        assert_eq!(tags_to_pango("1 < 2 <3 <foo>x"), "1 &lt; 2 &lt;3 x");
+    }
+
+    #[test]
+    fn test_strip_tags() {
+        assert_eq!(
+            strip_tags("<b>bold</b> <font color=red>red</font>"),
+            "bold red"
+        );
+    }
+
+    #[test]
+    fn test_pango_to_tags() {
+        assert_eq!(
+            pango_to_tags("<b>bold</b> &amp; <span foreground='red' size='large'>red</span>"),
+            "<b>bold</b> & <font color=\"red\">red</font>"
+        );
+        assert_eq!(
+            pango_to_tags("<span size=\"large\"><i>x</i></span>&#65;"),
+            "<i>x</i>A"
+        );
+    }
+
+    #[test]
+    fn test_pango_to_vtt() {
+        assert_eq!(
+            pango_to_vtt(
+                "<s>a</s> &lt;<span foreground=\"#FF0000\">b</span><span color=\"pink\">c</span>"
+            ),
+            "a &lt;<c.red>b</c>c"
+        );
+    }
+}
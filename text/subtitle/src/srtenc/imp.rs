@@ -0,0 +1,366 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+use crate::markup;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "srtenc",
+        gst::DebugColorFlags::empty(),
+        Some("SubRip Encoder Element"),
+    )
+});
+
+// Duration of a cue without duration that is not followed by any other buffer
+const FALLBACK_CUE_DURATION: gst::ClockTime = gst::ClockTime::from_seconds(4);
+
+// Input buffer without duration, waiting for the next buffer to know when it ends
+#[derive(Debug)]
+struct PendingCue {
+    buffer: gst::Buffer,
+    start: gst::ClockTime,
+    text: String,
+}
+
+#[derive(Debug)]
+struct State {
+    markup: bool,
+    segment: gst::FormattedSegment<gst::ClockTime>,
+    // Index of the next cue
+    index: u64,
+    pending: Option<PendingCue>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            markup: false,
+            segment: gst::FormattedSegment::new(),
+            index: 1,
+            pending: None,
+        }
+    }
+}
+
+fn format_timestamp(ts: gst::ClockTime) -> String {
+    let ms = ts.mseconds();
+
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1_000) % 60,
+        ms % 1_000
+    )
+}
+
+impl State {
+    fn encode_cue(
+        &mut self,
+        buffer: &gst::Buffer,
+        start: gst::ClockTime,
+        end: gst::ClockTime,
+        text: &str,
+    ) -> gst::Buffer {
+        let cue = format!(
+            "{}\n{} --> {}\n{}\n\n",
+            self.index,
+            format_timestamp(start),
+            format_timestamp(end.max(start)),
+            text
+        );
+        self.index += 1;
+
+        let mut outbuf = gst::Buffer::from_mut_slice(cue.into_bytes());
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            let _ = buffer.copy_into(outbuf, gst::BUFFER_COPY_METADATA, ..);
+            outbuf.set_duration(end.checked_sub(start));
+        }
+
+        outbuf
+    }
+
+    // Finishes the pending cue, if any, at the given running time
+    fn finish_pending(&mut self, end: Option<gst::ClockTime>) -> Option<gst::Buffer> {
+        let pending = self.pending.take()?;
+        let end = end.unwrap_or(pending.start + FALLBACK_CUE_DURATION);
+
+        Some(self.encode_cue(&pending.buffer, pending.start, end, &pending.text))
+    }
+}
+
+pub struct SrtEnc {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    state: Mutex<State>,
+}
+
+impl SrtEnc {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let Some(pts) = buffer.pts() else {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Timed text buffers require a timestamp"]
+            );
+            return Err(gst::FlowError::Error);
+        };
+
+        let data = buffer.map_readable().map_err(|_| {
+            gst::error!(CAT, obj: pad, "Can't map buffer readable");
+            gst::FlowError::Error
+        })?;
+
+        let text = std::str::from_utf8(&data).map_err(|err| {
+            gst::error!(CAT, obj: pad, "Can't decode utf8: {}", err);
+            gst::FlowError::Error
+        })?;
+
+        let mut state = self.state.lock().unwrap();
+
+        let text = if state.markup {
+            markup::pango_to_tags(text)
+        } else {
+            text.to_string()
+        };
+        drop(data);
+
+        // Blank lines would end the cue early
+        let text = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut outbufs = vec![];
+
+        let stop = buffer.duration().map(|duration| pts + duration);
+        let Some((start, stop)) = state.segment.clip(pts, stop) else {
+            gst::debug!(CAT, obj: pad, "Dropping buffer outside the segment");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+        let start = state.segment.to_running_time(start);
+        let stop = state.segment.to_running_time(stop);
+
+        outbufs.extend(state.finish_pending(start));
+
+        if let Some(start) = start {
+            if text.is_empty() {
+                gst::trace!(CAT, obj: pad, "Skipping empty cue");
+            } else if let Some(stop) = stop {
+                outbufs.push(state.encode_cue(&buffer, start, stop, &text));
+            } else {
+                state.pending = Some(PendingCue {
+                    buffer,
+                    start,
+                    text,
+                });
+            }
+        }
+        drop(state);
+
+        for outbuf in outbufs {
+            gst::trace!(CAT, obj: pad, "Pushing buffer {:?} to the pad", outbuf);
+            self.srcpad.push(outbuf)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(ev) => {
+                let markup = ev
+                    .caps()
+                    .structure(0)
+                    .and_then(|s| s.get::<&str>("format").ok())
+                    == Some("pango-markup");
+                self.state.lock().unwrap().markup = markup;
+
+                // We send our own caps downstream
+                let caps = gst::Caps::builder("application/x-subtitle").build();
+                self.srcpad.push_event(gst::event::Caps::new(&caps))
+            }
+            EventView::Segment(ev) => {
+                let segment = match ev.segment().clone().downcast::<gst::ClockTime>() {
+                    Ok(segment) => segment,
+                    Err(_) => {
+                        gst::element_imp_error!(
+                            self,
+                            gst::CoreError::Event,
+                            ["Only time segments are supported"]
+                        );
+                        return false;
+                    }
+                };
+                self.state.lock().unwrap().segment = segment;
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Gap(ev) => {
+                let mut state = self.state.lock().unwrap();
+                let (timestamp, _) = ev.get();
+                let end = state.segment.to_running_time(timestamp);
+                let outbuf = state.finish_pending(end);
+                drop(state);
+
+                if let Some(outbuf) = outbuf {
+                    if self.srcpad.push(outbuf).is_err() {
+                        gst::error!(CAT, obj: pad, "Failed to push buffer to the pad");
+                        return false;
+                    }
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::FlushStop(_) => {
+                self.state.lock().unwrap().pending = None;
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                let mut state = self.state.lock().unwrap();
+                let outbuf = state.finish_pending(None);
+                drop(state);
+
+                if let Some(outbuf) = outbuf {
+                    gst::trace!(CAT, obj: pad, "Pushing buffer {:?} to the pad", outbuf);
+                    if self.srcpad.push(outbuf).is_err() {
+                        gst::error!(CAT, obj: pad, "Failed to push buffer to the pad");
+                        return false;
+                    }
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SrtEnc {
+    const NAME: &'static str = "GstSrtEnc";
+    type Type = super::SrtEnc;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                SrtEnc::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |enc| enc.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                SrtEnc::catch_panic_pad_function(parent, || false, |enc| enc.sink_event(pad, event))
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::from_template(&templ);
+
+        Self {
+            srcpad,
+            sinkpad,
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for SrtEnc {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for SrtEnc {}
+
+impl ElementImpl for SrtEnc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "SubRip Encoder",
+                "Encoder/Subtitle",
+                "Encodes timed text into SubRip (.srt) subtitles",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", gst::List::new(["utf8", "pango-markup"]))
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("application/x-subtitle").build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady => {
+                // Reset the whole state
+                let mut state = self.state.lock().unwrap();
+                *state = State::default();
+            }
+            _ => (),
+        }
+
+        self.parent_change_state(transition)
+    }
+}
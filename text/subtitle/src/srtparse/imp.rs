@@ -0,0 +1,583 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+use crate::line_reader::LineReader;
+use crate::markup;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "srtparse",
+        gst::DebugColorFlags::empty(),
+        Some("SubRip Parser Element"),
+    )
+});
+
+// Cue whose text lines are being collected
+#[derive(Debug)]
+struct Cue {
+    start: gst::ClockTime,
+    end: gst::ClockTime,
+    lines: Vec<String>,
+}
+
+#[derive(Debug)]
+struct State {
+    reader: LineReader<gst::MappedBuffer<gst::buffer::Readable>>,
+    cue: Option<Cue>,
+    first_line: bool,
+    // Whether downstream gets Pango markup or plain text, once negotiated
+    markup: Option<bool>,
+    need_segment: bool,
+    segment: gst::FormattedSegment<gst::ClockTime>,
+    segment_seqnum: gst::Seqnum,
+    // Segment to use after the flush caused by a seek
+    pending_seek: Option<(gst::FormattedSegment<gst::ClockTime>, gst::Seqnum)>,
+    discont: bool,
+    last_position: Option<gst::ClockTime>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            reader: LineReader::new(),
+            cue: None,
+            first_line: true,
+            markup: None,
+            need_segment: true,
+            segment: gst::FormattedSegment::new(),
+            segment_seqnum: gst::Seqnum::next(),
+            pending_seek: None,
+            discont: true,
+            last_position: None,
+        }
+    }
+}
+
+impl State {
+    fn flush(&mut self) {
+        self.reader.clear();
+        self.cue = None;
+        self.first_line = true;
+        self.need_segment = true;
+        self.discont = true;
+        self.last_position = None;
+
+        if let Some((segment, seqnum)) = self.pending_seek.take() {
+            self.segment = segment;
+            self.segment_seqnum = seqnum;
+        }
+    }
+}
+
+// Parses `[HH:]MM:SS,mmm`, also accepting `.` as decimal separator
+fn parse_timestamp(s: &str) -> Option<gst::ClockTime> {
+    let s = s.trim();
+    let (hms, frac) = s.split_once(|c| c == ',' || c == '.').unwrap_or((s, "0"));
+
+    let mut parts = hms.rsplit(':');
+    let seconds = parts.next()?.parse::<u64>().ok()?;
+    let minutes = parts.next()?.parse::<u64>().ok()?;
+    let hours = match parts.next() {
+        Some(hours) => hours.parse::<u64>().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() || seconds >= 60 || minutes >= 60 {
+        return None;
+    }
+
+    if frac.is_empty() || frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let millis = frac.parse::<u64>().ok()? * 10u64.pow(3 - frac.len() as u32);
+
+    Some(
+        gst::ClockTime::from_seconds(hours * 3600 + minutes * 60 + seconds)
+            + gst::ClockTime::from_mseconds(millis),
+    )
+}
+
+// Parses `start --> end`, ignoring any position coordinates after the end timestamp
+fn parse_timing(line: &str) -> Option<(gst::ClockTime, gst::ClockTime)> {
+    let (start, end) = line.split_once("-->")?;
+    let end = end.split_whitespace().next()?;
+
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+pub struct SrtParse {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    state: Mutex<State>,
+}
+
+impl SrtParse {
+    fn negotiate(&self, state: &mut State) -> Result<bool, gst::FlowError> {
+        if let Some(markup) = state.markup {
+            return Ok(markup);
+        }
+
+        let templ_caps = self.srcpad.pad_template_caps();
+        let mut caps = self.srcpad.peer_query_caps(Some(&templ_caps));
+        if caps.is_empty() {
+            gst::error!(CAT, imp: self, "No common caps with downstream");
+            return Err(gst::FlowError::NotNegotiated);
+        }
+        caps.fixate();
+
+        let markup = caps
+            .structure(0)
+            .and_then(|s| s.get::<&str>("format").ok())
+            .map_or(true, |format| format == "pango-markup");
+
+        gst::debug!(CAT, imp: self, "Negotiated caps {}", caps);
+        state.markup = Some(markup);
+
+        Ok(markup)
+    }
+
+    // Events that have to be sent before the next buffer
+    fn pending_events(&self, state: &mut State) -> Result<Vec<gst::Event>, gst::FlowError> {
+        let mut events = vec![];
+
+        if state.markup.is_none() {
+            let markup = self.negotiate(state)?;
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", if markup { "pango-markup" } else { "utf8" })
+                .build();
+            events.push(gst::event::Caps::new(&caps));
+        }
+
+        if state.need_segment {
+            events.push(
+                gst::event::Segment::builder(&state.segment)
+                    .seqnum(state.segment_seqnum)
+                    .build(),
+            );
+            state.need_segment = false;
+        }
+
+        Ok(events)
+    }
+
+    fn finish_cue(&self, state: &mut State, cue: Cue) -> Option<gst::Buffer> {
+        let text = cue.lines.join("\n");
+        let text = if state.markup.unwrap_or(true) {
+            markup::tags_to_pango(&text)
+        } else {
+            markup::strip_tags(&text)
+        };
+
+        let Some((start, end)) = state.segment.clip(cue.start, cue.end.max(cue.start)) else {
+            gst::trace!(
+                CAT,
+                imp: self,
+                "Dropping cue {}-{} outside the segment",
+                cue.start,
+                cue.end
+            );
+            return None;
+        };
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Cue {}-{}: {}",
+            start.display(),
+            end.display(),
+            text
+        );
+
+        let mut buffer = gst::Buffer::from_mut_slice(text.into_bytes());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(start);
+            buffer.set_duration(end.opt_checked_sub(start).ok().flatten());
+            if state.discont {
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+                state.discont = false;
+            }
+        }
+
+        state.last_position = end.or(start);
+
+        Some(buffer)
+    }
+
+    fn handle_line(&self, state: &mut State, line: &str) -> Option<gst::Buffer> {
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+
+        match state.cue {
+            Some(ref mut cue) => {
+                if line.trim().is_empty() {
+                    let cue = state.cue.take().unwrap();
+                    return self.finish_cue(state, cue);
+                }
+
+                cue.lines.push(line.to_string());
+            }
+            None => {
+                if line.trim().is_empty() {
+                    return None;
+                }
+
+                // The cue index is optional in practice, only the timing line matters
+                if let Some((start, end)) = parse_timing(line) {
+                    state.cue = Some(Cue {
+                        start,
+                        end,
+                        lines: vec![],
+                    });
+                } else if line.trim().parse::<u64>().is_err() {
+                    gst::warning!(CAT, imp: self, "Skipping unexpected line '{}'", line);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn handle_buffer(
+        &self,
+        buffer: Option<gst::Buffer>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+
+        let drain = if let Some(buffer) = buffer {
+            if buffer.flags().contains(gst::BufferFlags::DISCONT) {
+                state.discont = true;
+            }
+
+            let buffer = buffer.into_mapped_buffer_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+
+                gst::FlowError::Error
+            })?;
+
+            state.reader.push(buffer);
+            false
+        } else {
+            true
+        };
+
+        let mut events = self.pending_events(&mut state)?;
+
+        let mut buffers = vec![];
+        loop {
+            let Some(line) = state.reader.line_with_drain(drain) else {
+                break;
+            };
+
+            let mut line = String::from_utf8_lossy(line).into_owned();
+            if state.first_line {
+                state.first_line = false;
+                if let Some(stripped) = line.strip_prefix('\u{feff}') {
+                    line = stripped.to_string();
+                }
+            }
+
+            buffers.extend(self.handle_line(&mut state, &line));
+        }
+
+        if drain {
+            if let Some(cue) = state.cue.take() {
+                buffers.extend(self.finish_cue(&mut state, cue));
+            }
+        }
+        drop(state);
+
+        for event in events.drain(..) {
+            self.srcpad.push_event(event);
+        }
+
+        for buffer in buffers {
+            self.srcpad.push(buffer).map_err(|err| {
+                if err != gst::FlowError::Flushing {
+                    gst::error!(CAT, imp: self, "Pushing buffer returned {:?}", err);
+                }
+                err
+            })?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        self.handle_buffer(Some(buffer))
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                // We send a proper caps event from the chain function later
+                gst::log!(CAT, obj: pad, "Dropping caps event");
+                true
+            }
+            EventView::Segment(_) => {
+                // We send a gst::Format::Time segment event later when needed
+                gst::log!(CAT, obj: pad, "Dropping segment event");
+                true
+            }
+            EventView::FlushStop(_) => {
+                self.state.lock().unwrap().flush();
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                gst::log!(CAT, obj: pad, "Draining");
+                if let Err(err) = self.handle_buffer(None) {
+                    gst::error!(CAT, obj: pad, "Failed to drain parser: {:?}", err);
+                }
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn perform_seek(&self, event: &gst::event::Seek) -> bool {
+        let (rate, flags, start_type, start, stop_type, stop) = event.get();
+
+        let (start, stop): (Option<gst::ClockTime>, Option<gst::ClockTime>) =
+            match (start.try_into(), stop.try_into()) {
+                (Ok(start), Ok(stop)) => (start, stop),
+                _ => {
+                    gst::error!(CAT, imp: self, "seek has invalid format");
+                    return false;
+                }
+            };
+
+        if !flags.contains(gst::SeekFlags::FLUSH) {
+            gst::error!(CAT, imp: self, "only flushing seeks are supported");
+            return false;
+        }
+
+        if rate <= 0.0 {
+            gst::error!(CAT, imp: self, "only forward playback is supported");
+            return false;
+        }
+
+        if start_type == gst::SeekType::End || stop_type == gst::SeekType::End {
+            gst::error!(CAT, imp: self, "Relative seeks are not supported");
+            return false;
+        }
+
+        let seek_seqnum = event.seqnum();
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let mut segment = state.segment.clone();
+            segment.do_seek(rate, flags, start_type, start, stop_type, stop);
+            state.pending_seek = Some((segment, seek_seqnum));
+        }
+
+        // Cues are not indexed, so parse the whole file again from the beginning and drop
+        // everything before the new segment
+        let event = gst::event::Seek::builder(
+            1.0,
+            flags,
+            gst::SeekType::Set,
+            Some(gst::format::Bytes::ZERO),
+            gst::SeekType::None,
+            None::<gst::format::Bytes>,
+        )
+        .seqnum(seek_seqnum)
+        .build();
+
+        if !self.sinkpad.push_event(event) {
+            gst::error!(CAT, imp: self, "Upstream failed to seek to the start");
+            self.state.lock().unwrap().pending_seek = None;
+            return false;
+        }
+
+        true
+    }
+
+    fn src_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+        match event.view() {
+            EventView::Seek(e) => self.perform_seek(e),
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj: pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            QueryViewMut::Seeking(q) => {
+                if q.format() != gst::Format::Time {
+                    return false;
+                }
+
+                let mut peer_query = gst::query::Seeking::new(gst::Format::Bytes);
+                let seekable = self.sinkpad.peer_query(&mut peer_query) && peer_query.result().0;
+
+                q.set(seekable, gst::ClockTime::ZERO, gst::ClockTime::NONE);
+                true
+            }
+            QueryViewMut::Position(q) => {
+                // For Time answer ourselves, otherwise forward
+                if q.format() == gst::Format::Time {
+                    let state = self.state.lock().unwrap();
+                    q.set(state.last_position);
+                    true
+                } else {
+                    self.sinkpad.peer_query(query)
+                }
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SrtParse {
+    const NAME: &'static str = "GstSrtParse";
+    type Type = super::SrtParse;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                SrtParse::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |parse| parse.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                SrtParse::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |parse| parse.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .event_function(|pad, parent, event| {
+                SrtParse::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |parse| parse.src_event(pad, event),
+                )
+            })
+            .query_function(|pad, parent, query| {
+                SrtParse::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |parse| parse.src_query(pad, query),
+                )
+            })
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for SrtParse {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for SrtParse {}
+
+impl ElementImpl for SrtParse {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "SubRip Parser",
+                "Parser/Subtitle",
+                "Parses SubRip (.srt) subtitle files into timed text",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", gst::List::new(["pango-markup", "utf8"]))
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("application/x-subtitle").build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused | gst::StateChange::PausedToReady => {
+                // Reset the whole state
+                let mut state = self.state.lock().unwrap();
+                *state = State::default();
+            }
+            _ => (),
+        }
+
+        self.parent_change_state(transition)
+    }
+}
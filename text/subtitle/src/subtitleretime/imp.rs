@@ -0,0 +1,247 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use std::sync::Mutex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "subtitleretime",
+        gst::DebugColorFlags::empty(),
+        Some("Subtitle Retiming Element"),
+    )
+});
+
+const DEFAULT_OFFSET: i64 = 0;
+const DEFAULT_STRETCH: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    offset: i64,
+    stretch: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            offset: DEFAULT_OFFSET,
+            stretch: DEFAULT_STRETCH,
+        }
+    }
+}
+
+impl Settings {
+    fn retime(&self, ts: gst::ClockTime) -> i64 {
+        let stretched = (ts.nseconds() as f64 * self.stretch).round();
+        (stretched as i64).saturating_add(self.offset)
+    }
+
+    // Retimes an interval, clipping it at zero. Returns `None` if it ends up
+    // completely before zero.
+    fn retime_interval(
+        &self,
+        start: gst::ClockTime,
+        duration: Option<gst::ClockTime>,
+    ) -> Option<(gst::ClockTime, Option<gst::ClockTime>)> {
+        let new_start = self.retime(start);
+
+        match duration {
+            Some(duration) => {
+                let new_end = self.retime(start + duration);
+                if new_end <= 0 {
+                    return None;
+                }
+                let new_start = new_start.max(0);
+
+                Some((
+                    gst::ClockTime::from_nseconds(new_start as u64),
+                    Some(gst::ClockTime::from_nseconds((new_end - new_start) as u64)),
+                ))
+            }
+            None if new_start < 0 => None,
+            None => Some((gst::ClockTime::from_nseconds(new_start as u64), None)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SubtitleRetime {
+    settings: Mutex<Settings>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SubtitleRetime {
+    const NAME: &'static str = "GstSubtitleRetime";
+    type Type = super::SubtitleRetime;
+    type ParentType = gst_base::BaseTransform;
+}
+
+impl ObjectImpl for SubtitleRetime {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecInt64::builder("offset")
+                    .nick("Offset")
+                    .blurb("Offset (in ns) added to the timestamps after stretching")
+                    .default_value(DEFAULT_OFFSET)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("stretch")
+                    .nick("Stretch")
+                    .blurb("Factor the timestamps and durations are multiplied with")
+                    .minimum(0.01)
+                    .maximum(100.0)
+                    .default_value(DEFAULT_STRETCH)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "offset" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.offset = value.get().expect("type checked upstream");
+                gst::debug!(CAT, imp: self, "Offset changed to {}", settings.offset);
+            }
+            "stretch" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.stretch = value.get().expect("type checked upstream");
+                gst::debug!(CAT, imp: self, "Stretch changed to {}", settings.stretch);
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "offset" => {
+                let settings = self.settings.lock().unwrap();
+                settings.offset.to_value()
+            }
+            "stretch" => {
+                let settings = self.settings.lock().unwrap();
+                settings.stretch.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for SubtitleRetime {}
+
+impl ElementImpl for SubtitleRetime {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Subtitle Retime",
+                "Filter/Subtitle",
+                "Applies an offset and stretch factor to timed text timestamps",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for SubtitleRetime {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn transform_ip(&self, buf: &mut gst::BufferRef) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let Some(pts) = buf.pts() else {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Timed text buffers require a timestamp"]
+            );
+            return Err(gst::FlowError::Error);
+        };
+
+        let settings = *self.settings.lock().unwrap();
+
+        let Some((new_pts, new_duration)) = settings.retime_interval(pts, buf.duration()) else {
+            gst::debug!(CAT, imp: self, "Dropping buffer retimed before zero");
+            return Ok(gst_base::BASE_TRANSFORM_FLOW_DROPPED);
+        };
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Retimed {} + {} to {} + {}",
+            pts,
+            buf.duration().display(),
+            new_pts,
+            new_duration.display(),
+        );
+
+        buf.set_pts(new_pts);
+        buf.set_duration(new_duration);
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        match event.view() {
+            EventView::Gap(ev) => {
+                let settings = *self.settings.lock().unwrap();
+
+                let (timestamp, duration) = ev.get();
+                let Some((timestamp, duration)) = settings.retime_interval(timestamp, duration)
+                else {
+                    gst::debug!(CAT, imp: self, "Dropping gap retimed before zero");
+                    return true;
+                };
+
+                let gap = gst::event::Gap::builder(timestamp)
+                    .duration(duration)
+                    .seqnum(event.seqnum())
+                    .build();
+                self.parent_sink_event(gap)
+            }
+            _ => self.parent_sink_event(event),
+        }
+    }
+}
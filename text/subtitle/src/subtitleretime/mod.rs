@@ -0,0 +1,43 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+/**
+ * element-subtitleretime:
+ *
+ * Applies an offset and a linear stretch factor to the timestamps of timed
+ * text buffers, for correcting subtitles that are out of sync with the media:
+ * `new timestamp = timestamp * stretch + offset`.
+ *
+ * Both properties can be changed while playing.
+ *
+ * ## Example pipeline
+ *
+ * Correct subtitles authored for 23.976 fps and shown one second too early:
+ *
+ * ```bash
+ * gst-launch-1.0 filesrc location=movie.srt ! srtparse ! subtitleretime stretch=1.0427 offset=1000000000 ! fakesink dump=true
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+
+glib::wrapper! {
+    pub struct SubtitleRetime(ObjectSubclass<imp::SubtitleRetime>) @extends gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "subtitleretime",
+        gst::Rank::NONE,
+        SubtitleRetime::static_type(),
+    )
+}
@@ -0,0 +1,678 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::markup;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "vttenc",
+        gst::DebugColorFlags::empty(),
+        Some("WebVTT Encoder Element"),
+    )
+});
+
+// Duration of a cue without duration that is not followed by any other buffer
+const FALLBACK_CUE_DURATION: gst::ClockTime = gst::ClockTime::from_seconds(4);
+
+// CEA-608 caption grid
+const CEA608_ROWS: f64 = 15.0;
+const CEA608_COLUMNS: f64 = 32.0;
+
+// Regions used for the CEA-608 roll-up modes, declared in the header of JSON input
+const ROLLUP_REGIONS: &[(&str, u32)] = &[("rollup2", 2), ("rollup3", 3), ("rollup4", 4)];
+
+// Subset of the CEA-608 JSON format output by cea608tojson
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, Eq)]
+enum TextStyle {
+    White,
+    Green,
+    Blue,
+    Cyan,
+    Red,
+    Yellow,
+    Magenta,
+    ItalicWhite,
+}
+
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, Eq)]
+enum Mode {
+    PopOn,
+    PaintOn,
+    RollUp2,
+    RollUp3,
+    RollUp4,
+}
+
+#[derive(Deserialize, Debug)]
+struct Chunk {
+    style: TextStyle,
+    underline: bool,
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Line {
+    column: Option<u32>,
+    row: Option<u32>,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Lines {
+    lines: Vec<Line>,
+    mode: Option<Mode>,
+}
+
+impl Lines {
+    fn text(&self) -> String {
+        let mut text = String::new();
+
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+            }
+
+            let mut line_text = String::new();
+            for chunk in &line.chunks {
+                let class = match chunk.style {
+                    TextStyle::White | TextStyle::ItalicWhite => None,
+                    TextStyle::Green => Some("lime"),
+                    TextStyle::Blue => Some("blue"),
+                    TextStyle::Cyan => Some("cyan"),
+                    TextStyle::Red => Some("red"),
+                    TextStyle::Yellow => Some("yellow"),
+                    TextStyle::Magenta => Some("magenta"),
+                };
+                let italic = chunk.style == TextStyle::ItalicWhite;
+
+                if let Some(class) = class {
+                    let _ = write!(line_text, "<c.{class}>");
+                }
+                if italic {
+                    line_text.push_str("<i>");
+                }
+                if chunk.underline {
+                    line_text.push_str("<u>");
+                }
+                line_text.push_str(&markup::escape_vtt(&chunk.text));
+                if chunk.underline {
+                    line_text.push_str("</u>");
+                }
+                if italic {
+                    line_text.push_str("</i>");
+                }
+                if class.is_some() {
+                    line_text.push_str("</c>");
+                }
+            }
+            text.push_str(line_text.trim_end());
+        }
+
+        text
+    }
+
+    // Cue settings placing the cue where the captions are on the CEA-608 grid
+    fn cue_settings(&self) -> Option<String> {
+        let region = match self.mode {
+            Some(Mode::RollUp2) => Some("rollup2"),
+            Some(Mode::RollUp3) => Some("rollup3"),
+            Some(Mode::RollUp4) => Some("rollup4"),
+            _ => None,
+        };
+        if let Some(region) = region {
+            return Some(format!("region:{region} align:left"));
+        }
+
+        let row = self.lines.iter().find_map(|line| line.row)?;
+        let column = self
+            .lines
+            .iter()
+            .filter_map(|line| line.column)
+            .min()
+            .unwrap_or(0);
+
+        // Map the grid to the 80% of the viewport that make up the title-safe area
+        let line = 10.0 + row as f64 * 80.0 / CEA608_ROWS;
+        let position = 10.0 + column as f64 * 80.0 / CEA608_COLUMNS;
+
+        Some(format!(
+            "line:{}% position:{}%,line-left align:left",
+            format_percentage(line),
+            format_percentage(position)
+        ))
+    }
+}
+
+fn format_percentage(value: f64) -> String {
+    let value = format!("{value:.2}");
+    value
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn format_timestamp(ts: gst::ClockTime) -> String {
+    let ms = ts.mseconds();
+
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1_000) % 60,
+        ms % 1_000
+    )
+}
+
+// Keeps the valid settings of a WebVTT cue settings list
+fn validate_cue_settings(settings: &str) -> Result<String, String> {
+    let mut valid = vec![];
+
+    for setting in settings.split_whitespace() {
+        let Some((name, value)) = setting.split_once(':') else {
+            return Err(format!("Invalid cue setting '{setting}'"));
+        };
+
+        let value_valid = !value.is_empty()
+            && !value.contains("-->")
+            && match name {
+                "vertical" => matches!(value, "rl" | "lr"),
+                "align" => matches!(value, "start" | "center" | "end" | "left" | "right"),
+                "line" | "position" | "size" | "region" => true,
+                _ => return Err(format!("Unknown cue setting '{name}'")),
+            };
+        if !value_valid {
+            return Err(format!("Invalid value for cue setting '{name}': '{value}'"));
+        }
+
+        valid.push(setting);
+    }
+
+    Ok(valid.join(" "))
+}
+
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    cue_settings: Option<String>,
+}
+
+// Input buffer without duration, waiting for the next buffer to know when it ends
+#[derive(Debug)]
+struct PendingCue {
+    buffer: gst::Buffer,
+    start: gst::ClockTime,
+    text: String,
+    settings: Option<String>,
+}
+
+#[derive(Debug)]
+enum Input {
+    Text,
+    Markup,
+    Cea608Json,
+}
+
+#[derive(Debug)]
+struct State {
+    input: Input,
+    segment: gst::FormattedSegment<gst::ClockTime>,
+    need_header: bool,
+    pending: Option<PendingCue>,
+    settings: Settings,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            input: Input::Text,
+            segment: gst::FormattedSegment::new(),
+            need_header: true,
+            pending: None,
+            settings: Settings::default(),
+        }
+    }
+}
+
+impl State {
+    fn header(&mut self, timestamp: gst::ClockTime) -> Option<gst::Buffer> {
+        if !self.need_header {
+            return None;
+        }
+        self.need_header = false;
+
+        let mut header = String::from("WEBVTT\n\n");
+        if matches!(self.input, Input::Cea608Json) {
+            for (id, lines) in ROLLUP_REGIONS {
+                let _ = write!(
+                    header,
+                    "REGION\nid:{id} width:80% lines:{lines} regionanchor:0%,100% viewportanchor:10%,90% scroll:up\n\n"
+                );
+            }
+        }
+
+        let mut buffer = gst::Buffer::from_mut_slice(header.into_bytes());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(timestamp);
+            buffer.set_flags(gst::BufferFlags::HEADER);
+        }
+
+        Some(buffer)
+    }
+
+    fn encode_cue(
+        &mut self,
+        buffer: &gst::Buffer,
+        start: gst::ClockTime,
+        end: gst::ClockTime,
+        text: &str,
+        settings: Option<&str>,
+    ) -> Vec<gst::Buffer> {
+        // Rounding to the millisecond might result in zero-duration cues, which we skip as
+        // some players interpret those in a special way
+        if end.mseconds() <= start.mseconds() {
+            return vec![];
+        }
+
+        let mut outbufs = vec![];
+        outbufs.extend(self.header(start));
+
+        let mut cue = format!("{} --> {}", format_timestamp(start), format_timestamp(end));
+        if let Some(settings) = settings.filter(|settings| !settings.is_empty()) {
+            cue.push(' ');
+            cue.push_str(settings);
+        }
+        let _ = write!(cue, "\n{text}\n\n");
+
+        let mut outbuf = gst::Buffer::from_mut_slice(cue.into_bytes());
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            let _ = buffer.copy_into(outbuf, gst::BUFFER_COPY_METADATA, ..);
+            outbuf.set_duration(end - start);
+            outbuf.set_flags(gst::BufferFlags::DELTA_UNIT);
+        }
+        outbufs.push(outbuf);
+
+        outbufs
+    }
+
+    // Finishes the pending cue, if any, at the given running time
+    fn finish_pending(&mut self, end: Option<gst::ClockTime>) -> Vec<gst::Buffer> {
+        let Some(pending) = self.pending.take() else {
+            return vec![];
+        };
+        let end = end.unwrap_or(pending.start + FALLBACK_CUE_DURATION);
+
+        self.encode_cue(
+            &pending.buffer,
+            pending.start,
+            end,
+            &pending.text,
+            pending.settings.as_deref(),
+        )
+    }
+
+    // Converts the input to cue text and cue settings
+    fn cue_text(&self, data: &[u8]) -> Result<(String, Option<String>), String> {
+        match self.input {
+            Input::Cea608Json => {
+                let lines = serde_json::from_slice::<Lines>(data)
+                    .map_err(|err| format!("Failed to parse input as JSON: {err}"))?;
+
+                let settings = lines
+                    .cue_settings()
+                    .or_else(|| self.settings.cue_settings.clone());
+
+                Ok((lines.text(), settings))
+            }
+            Input::Markup | Input::Text => {
+                let text = std::str::from_utf8(data)
+                    .map_err(|err| format!("Failed to decode utf8: {err}"))?;
+
+                let text = if matches!(self.input, Input::Markup) {
+                    markup::pango_to_vtt(text)
+                } else {
+                    markup::escape_vtt(text)
+                };
+
+                // Blank lines would end the cue early
+                let text = text
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                Ok((text, self.settings.cue_settings.clone()))
+            }
+        }
+    }
+}
+
+pub struct VttEnc {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    state: Mutex<State>,
+    settings: Mutex<Settings>,
+}
+
+impl VttEnc {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        let Some(pts) = buffer.pts() else {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Timed text buffers require a timestamp"]
+            );
+            return Err(gst::FlowError::Error);
+        };
+
+        let mut state = self.state.lock().unwrap();
+
+        let (text, settings) = {
+            let data = buffer.map_readable().map_err(|_| {
+                gst::error!(CAT, obj: pad, "Can't map buffer readable");
+                gst::FlowError::Error
+            })?;
+
+            state.cue_text(&data).map_err(|err| {
+                gst::element_imp_error!(self, gst::StreamError::Decode, ["{}", err]);
+                gst::FlowError::Error
+            })?
+        };
+
+        let stop = buffer.duration().map(|duration| pts + duration);
+        let Some((start, stop)) = state.segment.clip(pts, stop) else {
+            gst::debug!(CAT, obj: pad, "Dropping buffer outside the segment");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+        let start = state.segment.to_running_time(start);
+        let stop = state.segment.to_running_time(stop);
+
+        let mut outbufs = state.finish_pending(start);
+
+        if let Some(start) = start {
+            if text.is_empty() {
+                gst::trace!(CAT, obj: pad, "Skipping empty cue");
+            } else if let Some(stop) = stop {
+                outbufs.extend(state.encode_cue(&buffer, start, stop, &text, settings.as_deref()));
+            } else {
+                state.pending = Some(PendingCue {
+                    buffer,
+                    start,
+                    text,
+                    settings,
+                });
+            }
+        }
+        drop(state);
+
+        for outbuf in outbufs {
+            gst::trace!(CAT, obj: pad, "Pushing buffer {:?} to the pad", outbuf);
+            self.srcpad.push(outbuf)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn push_buffers(&self, pad: &gst::Pad, outbufs: Vec<gst::Buffer>) -> bool {
+        for outbuf in outbufs {
+            gst::trace!(CAT, obj: pad, "Pushing buffer {:?} to the pad", outbuf);
+            if self.srcpad.push(outbuf).is_err() {
+                gst::error!(CAT, obj: pad, "Failed to push buffer to the pad");
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(ev) => {
+                let s = ev.caps().structure(0).unwrap();
+                let input = match (s.name().as_str(), s.get::<&str>("format").ok()) {
+                    ("application/x-json", _) => Input::Cea608Json,
+                    (_, Some("pango-markup")) => Input::Markup,
+                    _ => Input::Text,
+                };
+                self.state.lock().unwrap().input = input;
+
+                // We send our own caps downstream
+                let caps = gst::Caps::builder("application/x-subtitle-vtt").build();
+                self.srcpad.push_event(gst::event::Caps::new(&caps))
+            }
+            EventView::Segment(ev) => {
+                let segment = match ev.segment().clone().downcast::<gst::ClockTime>() {
+                    Ok(segment) => segment,
+                    Err(_) => {
+                        gst::element_imp_error!(
+                            self,
+                            gst::CoreError::Event,
+                            ["Only time segments are supported"]
+                        );
+                        return false;
+                    }
+                };
+                self.state.lock().unwrap().segment = segment;
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Gap(ev) => {
+                let mut state = self.state.lock().unwrap();
+                let (timestamp, _) = ev.get();
+                let end = state.segment.to_running_time(timestamp);
+                let outbufs = state.finish_pending(end);
+                drop(state);
+
+                if !self.push_buffers(pad, outbufs) {
+                    return false;
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::FlushStop(_) => {
+                self.state.lock().unwrap().pending = None;
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                let mut state = self.state.lock().unwrap();
+                let outbufs = state.finish_pending(None);
+                drop(state);
+
+                if !self.push_buffers(pad, outbufs) {
+                    return false;
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for VttEnc {
+    const NAME: &'static str = "GstVttEnc";
+    type Type = super::VttEnc;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                VttEnc::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |enc| enc.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                VttEnc::catch_panic_pad_function(parent, || false, |enc| enc.sink_event(pad, event))
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::from_template(&templ);
+
+        Self {
+            srcpad,
+            sinkpad,
+            state: Mutex::new(State::default()),
+            settings: Mutex::new(Settings::default()),
+        }
+    }
+}
+
+impl ObjectImpl for VttEnc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecString::builder("cue-settings")
+                .nick("Cue Settings")
+                .blurb("WebVTT cue settings for cues without position information from upstream, e.g. \"line:85% align:center\"")
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "cue-settings" => {
+                let cue_settings = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+                let cue_settings = match cue_settings.as_deref().map(validate_cue_settings) {
+                    Some(Ok(cue_settings)) => Some(cue_settings),
+                    Some(Err(err)) => {
+                        gst::error!(CAT, imp: self, "Ignoring cue settings: {}", err);
+                        return;
+                    }
+                    None => None,
+                };
+
+                self.settings.lock().unwrap().cue_settings = cue_settings;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "cue-settings" => {
+                let settings = self.settings.lock().unwrap();
+                settings.cue_settings.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for VttEnc {}
+
+impl ElementImpl for VttEnc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "WebVTT Encoder",
+                "Encoder/Subtitle",
+                "Encodes timed text into WebVTT subtitles",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::builder_full()
+                .structure(
+                    gst::Structure::builder("text/x-raw")
+                        .field("format", gst::List::new(["utf8", "pango-markup"]))
+                        .build(),
+                )
+                .structure(
+                    gst::Structure::builder("application/x-json")
+                        .field("format", "cea608")
+                        .build(),
+                )
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("application/x-subtitle-vtt").build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                // Reset the whole state
+                let mut state = self.state.lock().unwrap();
+                *state = State::default();
+                state.settings = self.settings.lock().unwrap().clone();
+            }
+            gst::StateChange::PausedToReady => {
+                // Reset the whole state
+                let mut state = self.state.lock().unwrap();
+                *state = State::default();
+            }
+            _ => (),
+        }
+
+        self.parent_change_state(transition)
+    }
+}
@@ -0,0 +1,90 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsubtitle::plugin_register_static().unwrap();
+    });
+}
+
+fn new_buffer(text: &str, pts: gst::ClockTime, duration: Option<gst::ClockTime>) -> gst::Buffer {
+    let mut buf = gst::Buffer::from_mut_slice(text.as_bytes().to_vec());
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+        buf.set_duration(duration);
+    }
+
+    buf
+}
+
+fn pull_output(h: &mut gst_check::Harness) -> String {
+    let mut output = String::new();
+    while let Some(buf) = h.pull_until_eos().unwrap() {
+        let map = buf.map_readable().unwrap();
+        output.push_str(std::str::from_utf8(&map).unwrap());
+    }
+
+    output
+}
+
+#[test]
+fn test_encode() {
+    init();
+
+    let mut h = gst_check::Harness::new("srtenc");
+    h.set_src_caps_str("text/x-raw, format=pango-markup");
+
+    let inputs = [
+        (
+            "<b>Hello</b> &amp; <span foreground=\"red\" size=\"large\">welcome</span>\n\nthere",
+            1500.mseconds(),
+            Some(1500.mseconds()),
+        ),
+        ("", 4.seconds(), Some(1.seconds())),
+        ("no duration", 3723.seconds() + 4.mseconds(), None),
+        ("last", 3725.seconds(), None),
+    ];
+    for (text, pts, duration) in inputs {
+        assert_eq!(
+            h.push(new_buffer(text, pts, duration)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    h.push_event(gst::event::Eos::new());
+
+    assert_eq!(
+        pull_output(&mut h),
+        "1\n00:00:01,500 --> 00:00:03,000\n\
+         <b>Hello</b> & <font color=\"red\">welcome</font>\nthere\n\n\
+         2\n01:02:03,004 --> 01:02:05,000\nno duration\n\n\
+         3\n01:02:05,000 --> 01:02:09,000\nlast\n\n"
+    );
+}
+
+#[test]
+fn test_round_trip() {
+    init();
+
+    let srt = "1\n00:00:01,000 --> 00:00:02,500\n<i>one</i>\n\n\
+               2\n00:00:03,000 --> 00:00:04,000\n<b>two</b> & <font color=\"blue\">three</font>\nfour\n\n";
+
+    let mut h = gst_check::Harness::new_parse("srtparse ! srtenc");
+    h.set_src_caps_str("application/x-subtitle");
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(srt)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    h.push_event(gst::event::Eos::new());
+
+    assert_eq!(pull_output(&mut h), srt);
+}
@@ -0,0 +1,107 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsubtitle::plugin_register_static().unwrap();
+    });
+}
+
+const SRT: &str = "\u{feff}1\r\n\
+    00:00:01,500 --> 00:00:03,000\r\n\
+    <b>Hello</b> & welcome\r\n\
+    <i>second line\r\n\
+    \r\n\
+    2\r\n\
+    00:01:02,25 --> 00:01:04,000 X1:10 X2:20 Y1:10 Y2:20\r\n\
+    <font color=\"#00ff00\">green</font>\r\n\
+    \r\n\
+    3\r\n\
+    01:00:00,000 --> 01:00:01,000\r\n\
+    last";
+
+fn pull_cues(h: &mut gst_check::Harness) -> Vec<(gst::ClockTime, gst::ClockTime, String)> {
+    let mut cues = vec![];
+    while let Some(buf) = h.pull_until_eos().unwrap() {
+        let map = buf.map_readable().unwrap();
+        cues.push((
+            buf.pts().unwrap(),
+            buf.duration().unwrap(),
+            std::str::from_utf8(&map).unwrap().to_string(),
+        ));
+    }
+
+    cues
+}
+
+fn push_srt(h: &mut gst_check::Harness) {
+    // Split at arbitrary positions to check that cues spanning buffers are handled
+    for chunk in SRT.as_bytes().chunks(7) {
+        let buf = gst::Buffer::from_mut_slice(chunk.to_vec());
+        assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+}
+
+#[test]
+fn test_parse_markup() {
+    init();
+
+    let mut h = gst_check::Harness::new("srtparse");
+    h.set_src_caps_str("application/x-subtitle");
+    push_srt(&mut h);
+
+    let caps = h
+        .sinkpad()
+        .expect("harness has no sinkpad")
+        .current_caps()
+        .expect("pad has no caps");
+    assert_eq!(
+        caps,
+        gst::Caps::builder("text/x-raw")
+            .field("format", "pango-markup")
+            .build()
+    );
+
+    assert_eq!(
+        pull_cues(&mut h),
+        vec![
+            (
+                1500.mseconds(),
+                1500.mseconds(),
+                "<b>Hello</b> &amp; welcome\n<i>second line</i>".to_string()
+            ),
+            (
+                62_250.mseconds(),
+                1750.mseconds(),
+                "<span foreground=\"#00ff00\">green</span>".to_string()
+            ),
+            (3600.seconds(), 1.seconds(), "last".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_plain() {
+    init();
+
+    let mut h = gst_check::Harness::new("srtparse");
+    h.set_src_caps_str("application/x-subtitle");
+    h.set_sink_caps_str("text/x-raw, format=utf8");
+    push_srt(&mut h);
+
+    let cues = pull_cues(&mut h)
+        .into_iter()
+        .map(|(_, _, text)| text)
+        .collect::<Vec<_>>();
+    assert_eq!(cues, vec!["Hello & welcome\nsecond line", "green", "last"]);
+}
@@ -0,0 +1,107 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsubtitle::plugin_register_static().unwrap();
+    });
+}
+
+fn new_buffer(text: &str, pts: gst::ClockTime, duration: Option<gst::ClockTime>) -> gst::Buffer {
+    let mut buf = gst::Buffer::from_mut_slice(text.as_bytes().to_vec());
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+        buf.set_duration(duration);
+    }
+
+    buf
+}
+
+#[test]
+fn test_retime() {
+    init();
+
+    let mut h = gst_check::Harness::new("subtitleretime");
+    {
+        let retime = h.element().unwrap();
+        retime.set_property("offset", -5_000_000_000i64);
+        retime.set_property("stretch", 1.5f64);
+    }
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    // Ends before zero after retiming
+    assert_eq!(
+        h.push(new_buffer("dropped", 1.seconds(), Some(1.seconds()))),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    // Clipped at zero
+    assert_eq!(
+        h.push(new_buffer("clipped", 2.seconds(), Some(2.seconds()))),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        h.push(new_buffer("stretched", 10.seconds(), Some(2.seconds()))),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        h.push(new_buffer("no duration", 20.seconds(), None)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    // Live update
+    h.element().unwrap().set_property("stretch", 1.0f64);
+    assert_eq!(
+        h.push(new_buffer("updated", 30.seconds(), Some(1.seconds()))),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    let expected = [
+        ("clipped", gst::ClockTime::ZERO, Some(1.seconds())),
+        ("stretched", 10.seconds(), Some(3.seconds())),
+        ("no duration", 25.seconds(), None),
+        ("updated", 25.seconds(), Some(1.seconds())),
+    ];
+    for (text, pts, duration) in expected {
+        let buf = h.pull().unwrap();
+        let map = buf.map_readable().unwrap();
+        assert_eq!(std::str::from_utf8(&map).unwrap(), text);
+        assert_eq!(buf.pts(), Some(pts));
+        assert_eq!(buf.duration(), duration);
+    }
+    assert_eq!(h.buffers_in_queue(), 0);
+}
+
+#[test]
+fn test_retime_gap() {
+    init();
+
+    let mut h = gst_check::Harness::new("subtitleretime");
+    h.element()
+        .unwrap()
+        .set_property("offset", 2_000_000_000i64);
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    h.push_event(
+        gst::event::Gap::builder(1.seconds())
+            .duration(1.seconds())
+            .build(),
+    );
+
+    loop {
+        let event = h.pull_event().unwrap();
+        if let gst::EventView::Gap(ev) = event.view() {
+            assert_eq!(ev.get(), (3.seconds(), Some(1.seconds())));
+            break;
+        }
+    }
+}
@@ -0,0 +1,111 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsubtitle::plugin_register_static().unwrap();
+    });
+}
+
+fn new_buffer(text: &str, pts: gst::ClockTime, duration: Option<gst::ClockTime>) -> gst::Buffer {
+    let mut buf = gst::Buffer::from_mut_slice(text.as_bytes().to_vec());
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+        buf.set_duration(duration);
+    }
+
+    buf
+}
+
+fn pull_output(h: &mut gst_check::Harness) -> String {
+    let mut output = String::new();
+    while let Some(buf) = h.pull_until_eos().unwrap() {
+        let map = buf.map_readable().unwrap();
+        output.push_str(std::str::from_utf8(&map).unwrap());
+    }
+
+    output
+}
+
+#[test]
+fn test_encode_text() {
+    init();
+
+    let mut h = gst_check::Harness::new("vttenc");
+    h.element()
+        .unwrap()
+        .set_property("cue-settings", "line:85% align:center");
+    h.set_src_caps_str("text/x-raw, format=pango-markup");
+
+    let inputs = [
+        (
+            "<b>Hello</b> &amp; <span foreground=\"yellow\">welcome</span>",
+            1500.mseconds(),
+            Some(1500.mseconds()),
+        ),
+        ("a &lt; b", 3723.seconds() + 4.mseconds(), None),
+    ];
+    for (text, pts, duration) in inputs {
+        assert_eq!(
+            h.push(new_buffer(text, pts, duration)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    h.push_event(gst::event::Eos::new());
+
+    assert_eq!(
+        pull_output(&mut h),
+        "WEBVTT\n\n\
+         00:00:01.500 --> 00:00:03.000 line:85% align:center\n\
+         <b>Hello</b> &amp; <c.yellow>welcome</c>\n\n\
+         01:02:03.004 --> 01:02:07.004 line:85% align:center\n\
+         a &lt; b\n\n"
+    );
+}
+
+#[test]
+fn test_encode_cea608_json() {
+    init();
+
+    let mut h = gst_check::Harness::new("vttenc");
+    h.set_src_caps_str("application/x-json, format=cea608");
+
+    let inputs = [
+        (
+            r#"{"lines":[{"column":4,"row":13,"chunks":[{"style":"ItalicWhite","underline":false,"text":"pop "},{"style":"Green","underline":true,"text":"on"}],"carriage_return":null}],"mode":"PopOn","clear":null}"#,
+            1.seconds(),
+            Some(2.seconds()),
+        ),
+        (
+            r#"{"lines":[{"column":0,"row":14,"chunks":[{"style":"White","underline":false,"text":"roll <up>  "}],"carriage_return":null}],"mode":"RollUp2","clear":null}"#,
+            3.seconds(),
+            Some(1.seconds()),
+        ),
+    ];
+    for (text, pts, duration) in inputs {
+        assert_eq!(
+            h.push(new_buffer(text, pts, duration)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let output = pull_output(&mut h);
+    assert!(output.starts_with("WEBVTT\n\nREGION\nid:rollup2 width:80% lines:2 "));
+    assert!(output.ends_with(
+        "00:00:01.000 --> 00:00:03.000 line:79.33% position:20%,line-left align:left\n\
+         <i>pop </i><c.lime><u>on</u></c>\n\n\
+         00:00:03.000 --> 00:00:04.000 region:rollup2 align:left\n\
+         roll &lt;up&gt;\n\n"
+    ));
+}
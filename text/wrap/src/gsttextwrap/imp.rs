@@ -14,6 +14,7 @@ use std::default::Default;
 use std::fs::File;
 use std::io;
 use std::mem;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
 use once_cell::sync::Lazy;
@@ -29,6 +30,8 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 });
 
 const DEFAULT_DICTIONARY: Option<String> = None;
+const DEFAULT_DICTIONARY_DIR: Option<String> = None;
+const DEFAULT_LANGUAGE: Option<String> = None;
 const DEFAULT_COLUMNS: u32 = 32; /* CEA 608 max columns */
 const DEFAULT_LINES: u32 = 0;
 const DEFAULT_ACCUMULATE: gst::ClockTime = gst::ClockTime::ZERO;
@@ -36,6 +39,8 @@ const DEFAULT_ACCUMULATE: gst::ClockTime = gst::ClockTime::ZERO;
 #[derive(Debug, Clone)]
 struct Settings {
     dictionary: Option<String>,
+    dictionary_dir: Option<String>,
+    language: Option<String>,
     columns: u32,
     lines: u32,
     accumulate_time: gst::ClockTime,
@@ -45,6 +50,8 @@ impl Default for Settings {
     fn default() -> Self {
         Self {
             dictionary: DEFAULT_DICTIONARY,
+            dictionary_dir: DEFAULT_DICTIONARY_DIR,
+            language: DEFAULT_LANGUAGE,
             columns: DEFAULT_COLUMNS, /* CEA 608 max columns */
             lines: DEFAULT_LINES,
             accumulate_time: DEFAULT_ACCUMULATE,
@@ -54,6 +61,8 @@ impl Default for Settings {
 
 struct State {
     options: Option<textwrap::Options<'static>>,
+    /* Language of the stream from the tags */
+    language: Option<String>,
 
     current_text: String,
     start_ts: Option<gst::ClockTime>,
@@ -64,6 +73,7 @@ impl Default for State {
     fn default() -> Self {
         Self {
             options: None,
+            language: None,
 
             current_text: "".to_string(),
             start_ts: None,
@@ -83,6 +93,67 @@ fn is_punctuation(word: &str) -> bool {
     word == "." || word == "," || word == "?" || word == "!" || word == ";" || word == ":"
 }
 
+/* Chinese and Japanese scripts, which are written without spaces between words */
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x2E80..=0x2FFF     /* Radicals */
+        | 0x3000..=0x303F   /* Symbols and punctuation */
+        | 0x3040..=0x30FF   /* Hiragana and Katakana */
+        | 0x3100..=0x31FF   /* Bopomofo, Katakana extensions */
+        | 0x3400..=0x4DBF   /* Ideographs extension A */
+        | 0x4E00..=0x9FFF   /* Ideographs */
+        | 0xF900..=0xFAFF   /* Compatibility ideographs */
+        | 0xFF00..=0xFFEF   /* Halfwidth and fullwidth forms */
+        | 0x20000..=0x3FFFF /* Supplementary ideographic planes */
+    )
+}
+
+/* Splits text at the line break opportunities of the Unicode line breaking
+ * algorithm, returning each word and whether it was followed by whitespace.
+ * Each CJK character is a word on its own. */
+fn split_words(text: &str) -> Vec<(&str, bool)> {
+    textwrap::WordSeparator::UnicodeBreakProperties
+        .find_words(text)
+        .filter(|word| !word.word.is_empty())
+        .map(|word| (word.word, !word.whitespace.is_empty()))
+        .collect()
+}
+
+/* Whether a space is needed when appending a word from a new buffer */
+fn needs_space(text: &str, word: &str) -> bool {
+    match (text.chars().last(), word.chars().next()) {
+        (Some(last), Some(first)) => !is_punctuation(word) && !is_cjk(last) && !is_cjk(first),
+        _ => false,
+    }
+}
+
+/* Finds the hyphenation dictionary for a language in a directory, either
+ * named after the exact language code or after one of its variants,
+ * e.g. de-1996.standard.bincode for de */
+fn find_dictionary(dir: &Path, language: &str) -> Option<PathBuf> {
+    let language = language.to_lowercase();
+    let exact = dir.join(format!("{language}.standard.bincode"));
+    if exact.is_file() {
+        return Some(exact);
+    }
+
+    let prefix = format!("{language}-");
+    let mut candidates = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.starts_with(&prefix) && name.ends_with(".standard.bincode"))
+                .unwrap_or(false)
+        })
+        .collect::<Vec<_>>();
+    candidates.sort();
+
+    candidates.into_iter().next()
+}
+
 impl TextWrap {
     fn update_wrapper(&self) {
         let settings = self.settings.lock().unwrap();
@@ -92,9 +163,36 @@ impl TextWrap {
             return;
         }
 
-        let mut options = textwrap::Options::new(settings.columns as usize);
+        let mut options = textwrap::Options::new(settings.columns as usize)
+            .word_separator(textwrap::WordSeparator::UnicodeBreakProperties);
+
+        let dictionary = match (&settings.dictionary, &settings.dictionary_dir) {
+            (Some(dictionary), _) => Some(PathBuf::from(dictionary)),
+            (None, Some(dir)) => match settings.language.as_ref().or(state.language.as_ref()) {
+                Some(language) => {
+                    let dictionary = find_dictionary(Path::new(dir), language);
+                    if dictionary.is_none() {
+                        gst::warning!(
+                            CAT,
+                            imp: self,
+                            "No dictionary for language {} in {}",
+                            language,
+                            dir
+                        );
+                    }
+                    dictionary
+                }
+                None => {
+                    gst::debug!(CAT, imp: self, "No language known yet");
+                    None
+                }
+            },
+            (None, None) => None,
+        };
+
+        if let Some(dictionary) = dictionary {
+            gst::debug!(CAT, imp: self, "Loading dictionary {}", dictionary.display());
 
-        if let Some(dictionary) = &settings.dictionary {
             let dict_file = match File::open(dictionary) {
                 Err(err) => {
                     gst::error!(CAT, imp: self, "Failed to open dictionary file: {}", err);
@@ -190,7 +288,8 @@ impl TextWrap {
                 state.end_ts = None;
             }
 
-            let num_words = data.split_whitespace().count() as u64;
+            let words = split_words(data);
+            let num_words = words.len() as u64;
             let duration_per_word = (num_words != 0).then(|| duration / num_words);
 
             if state.start_ts.is_none() {
@@ -199,14 +298,20 @@ impl TextWrap {
 
             state.end_ts = buffer.pts();
 
-            let words = data.split_ascii_whitespace();
             let mut current_text = state.current_text.to_string();
-
-            for word in words {
-                if !current_text.is_empty() && !is_punctuation(word) {
+            let mut prev_whitespace = None;
+
+            for (word, whitespace) in words {
+                /* Keep the spacing inside a buffer, and guess it between buffers */
+                let space = match prev_whitespace {
+                    Some(prev_whitespace) => prev_whitespace && !is_punctuation(word),
+                    None => needs_space(&current_text, word),
+                };
+                if !current_text.is_empty() && space {
                     current_text.push(' ');
                 }
                 current_text.push_str(word);
+                prev_whitespace = Some(whitespace);
 
                 let options = state
                     .options
@@ -288,12 +393,12 @@ impl TextWrap {
             if lines > 0 {
                 let mut bufferlist = gst::BufferList::new();
                 let duration_per_word: gst::ClockTime =
-                    duration / data.split_whitespace().count() as u64;
+                    duration / std::cmp::max(split_words(&data).len() as u64, 1);
 
                 for chunk in data.lines().collect::<Vec<&str>>().chunks(lines as usize) {
                     let data = chunk.join("\n");
                     let duration: gst::ClockTime =
-                        duration_per_word * data.split_whitespace().count() as u64;
+                        duration_per_word * split_words(&data).len() as u64;
                     gst::info!(CAT, "Pushing lines {}", data);
                     let mut buf = gst::Buffer::from_mut_slice(data.into_bytes());
 
@@ -360,11 +465,25 @@ impl TextWrap {
                     gst::Pad::event_default(pad, Some(&*self.obj()), event)
                 }
             }
+            EventView::Tag(tag) => {
+                if let Some(language) = tag.tag().get::<gst::tags::LanguageCode>() {
+                    let language = language.get().to_string();
+                    let mut state = self.state.lock().unwrap();
+                    if state.language.as_ref() != Some(&language) {
+                        gst::debug!(CAT, imp: self, "Stream language is now {}", language);
+                        state.language = Some(language);
+                        state.options = None;
+                    }
+                }
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
             EventView::FlushStart(_) => {
                 let mut state = self.state.lock().unwrap();
                 let options = state.options.take();
+                let language = state.language.take();
                 *state = State::default();
                 state.options = options;
+                state.language = language;
                 drop(state);
                 gst::Pad::event_default(pad, Some(&*self.obj()), event)
             }
@@ -487,6 +606,19 @@ impl ObjectImpl for TextWrap {
                         <https://docs.rs/crate/hyphenation/0.7.1> for more information")
                     .mutable_playing()
                     .build(),
+                glib::ParamSpecString::builder("dictionary-dir")
+                    .nick("Dictionary Directory")
+                    .blurb("Directory containing dictionaries named after their language, e.g. \
+                        de-1996.standard.bincode, used to perform hyphenation when no \
+                        dictionary is set")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("language")
+                    .nick("Language")
+                    .blurb("Language code used to select a dictionary from dictionary-dir \
+                        (NULL=use the language from the stream tags)")
+                    .mutable_playing()
+                    .build(),
                 glib::ParamSpecUInt::builder("columns")
                     .nick("Columns")
                     .blurb("Maximum number of columns for any given line")
@@ -529,6 +661,18 @@ impl ObjectImpl for TextWrap {
                 settings.dictionary = value.get().expect("type checked upstream");
                 state.options = None;
             }
+            "dictionary-dir" => {
+                let mut settings = self.settings.lock().unwrap();
+                let mut state = self.state.lock().unwrap();
+                settings.dictionary_dir = value.get().expect("type checked upstream");
+                state.options = None;
+            }
+            "language" => {
+                let mut settings = self.settings.lock().unwrap();
+                let mut state = self.state.lock().unwrap();
+                settings.language = value.get().expect("type checked upstream");
+                state.options = None;
+            }
             "columns" => {
                 let mut settings = self.settings.lock().unwrap();
                 let mut state = self.state.lock().unwrap();
@@ -566,6 +710,14 @@ impl ObjectImpl for TextWrap {
                 let settings = self.settings.lock().unwrap();
                 settings.dictionary.to_value()
             }
+            "dictionary-dir" => {
+                let settings = self.settings.lock().unwrap();
+                settings.dictionary_dir.to_value()
+            }
+            "language" => {
+                let settings = self.settings.lock().unwrap();
+                settings.language.to_value()
+            }
             "columns" => {
                 let settings = self.settings.lock().unwrap();
                 settings.columns.to_value()
@@ -112,3 +112,75 @@ fn test_lines() {
         std::str::from_utf8(expected_output.as_ref())
     );
 }
+
+#[test]
+fn test_cjk_columns() {
+    init();
+
+    let input = "日本語のテキストを分割します";
+
+    // Each character is two columns wide and a line break opportunity
+    let expected_output = "日本語の\nテキスト\nを分割し\nます";
+
+    let mut h = gst_check::Harness::new("textwrap");
+
+    {
+        let wrap = h.element().expect("Could not create textwrap");
+        wrap.set_property("columns", 8u32);
+    }
+
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    let buf = {
+        let mut buf = gst::Buffer::from_mut_slice(Vec::from(input.as_bytes()));
+        let buf_ref = buf.get_mut().unwrap();
+        buf_ref.set_pts(gst::ClockTime::ZERO);
+        buf_ref.set_duration(2.seconds());
+        buf
+    };
+
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    let buf = h.pull().expect("Couldn't pull buffer");
+
+    assert_eq!(buf.pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(buf.duration(), Some(2.seconds()));
+
+    let map = buf.map_readable().expect("Couldn't map buffer readable");
+
+    assert_eq!(std::str::from_utf8(map.as_ref()), Ok(expected_output));
+}
+
+#[test]
+fn test_cjk_accumulate() {
+    init();
+
+    let mut h = gst_check::Harness::new("textwrap");
+
+    {
+        let wrap = h.element().expect("Could not create textwrap");
+        wrap.set_property("accumulate-time", 5.seconds().nseconds());
+    }
+
+    h.set_src_caps_str("text/x-raw, format=utf8");
+
+    for (i, input) in ["今日は", "いい天気"].iter().enumerate() {
+        let mut buf = gst::Buffer::from_mut_slice(Vec::from(input.as_bytes()));
+        let buf_ref = buf.get_mut().unwrap();
+        buf_ref.set_pts((i as u64).seconds());
+        buf_ref.set_duration(gst::ClockTime::SECOND);
+
+        assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+    }
+
+    h.push_event(gst::event::Eos::new());
+
+    let buf = h.pull().expect("Couldn't pull buffer");
+
+    assert_eq!(buf.pts(), Some(gst::ClockTime::ZERO));
+
+    // No spaces are inserted between the CJK text of both buffers
+    let map = buf.map_readable().expect("Couldn't map buffer readable");
+
+    assert_eq!(std::str::from_utf8(map.as_ref()), Ok("今日はいい天気"));
+}
@@ -76,6 +76,8 @@ struct Settings {
     uri: Option<String>,
     source: Option<gst::Element>,
     fallback_uri: Option<String>,
+    fallback_source: Option<gst::Element>,
+    fallback_image: Option<String>,
     timeout: gst::ClockTime,
     restart_timeout: gst::ClockTime,
     retry_timeout: gst::ClockTime,
@@ -96,6 +98,8 @@ impl Default for Settings {
             uri: None,
             source: None,
             fallback_uri: None,
+            fallback_source: None,
+            fallback_image: None,
             timeout: 5.seconds(),
             restart_timeout: 5.seconds(),
             retry_timeout: 60.seconds(),
@@ -204,6 +208,7 @@ struct State {
     // Configure settings
     settings: Settings,
     configured_source: Source,
+    configured_fallback_source: Option<Source>,
 
     // Statistics
     stats: Stats,
@@ -259,6 +264,16 @@ impl ObjectImpl for FallbackSrc {
                     .blurb("Fallback URI to use for video in case the main stream doesn't work")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecObject::builder::<gst::Element>("fallback-source")
+                    .nick("Fallback Source")
+                    .blurb("Fallback source to use instead of the fallback URI or image")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("fallback-image")
+                    .nick("Fallback Image")
+                    .blurb("Image file (e.g. PNG or JPEG) to show in case the main stream doesn't work, if no fallback URI or source is set")
+                    .mutable_ready()
+                    .build(),
                 glib::ParamSpecUInt64::builder("timeout")
                     .nick("Timeout")
                     .blurb("Timeout for switching to the fallback URI")
@@ -402,6 +417,30 @@ impl ObjectImpl for FallbackSrc {
                 );
                 settings.fallback_uri = new_value;
             }
+            "fallback-source" => {
+                let mut settings = self.settings.lock();
+                let new_value = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing fallback source from {:?} to {:?}",
+                    settings.fallback_source,
+                    new_value,
+                );
+                settings.fallback_source = new_value;
+            }
+            "fallback-image" => {
+                let mut settings = self.settings.lock();
+                let new_value = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing Fallback Image from {:?} to {:?}",
+                    settings.fallback_image,
+                    new_value,
+                );
+                settings.fallback_image = new_value;
+            }
             "timeout" => {
                 let mut settings = self.settings.lock();
                 let new_value = value.get().expect("type checked upstream");
@@ -557,6 +596,14 @@ impl ObjectImpl for FallbackSrc {
                 let settings = self.settings.lock();
                 settings.fallback_uri.to_value()
             }
+            "fallback-source" => {
+                let settings = self.settings.lock();
+                settings.fallback_source.to_value()
+            }
+            "fallback-image" => {
+                let settings = self.settings.lock();
+                settings.fallback_image.to_value()
+            }
             "timeout" => {
                 let settings = self.settings.lock();
                 settings.timeout.to_value()
@@ -1084,11 +1131,11 @@ impl FallbackSrc {
 
     fn create_fallback_input(
         &self,
-        fallback_uri: Option<&str>,
+        fallback_source: Option<&Source>,
         buffer_duration: i64,
     ) -> Option<SourceBin> {
-        let source: gst::Element = match fallback_uri {
-            Some(uri) => {
+        let source: gst::Element = match fallback_source {
+            Some(Source::Uri(uri)) => {
                 let dbin = gst::ElementFactory::make("uridecodebin3")
                     .name("uridecodebin")
                     .property("uri", uri)
@@ -1099,6 +1146,7 @@ impl FallbackSrc {
 
                 dbin
             }
+            Some(Source::Element(source)) => CustomSource::new(source).upcast(),
             None => return None,
         };
 
@@ -1257,14 +1305,36 @@ impl FallbackSrc {
             }
         };
 
-        let fallback_uri = &settings.fallback_uri;
+        let configured_fallback_source = match (
+            &settings.fallback_source,
+            &settings.fallback_uri,
+            &settings.fallback_image,
+        ) {
+            (Some(source), _, _) => Some(Source::Element(source.clone())),
+            (None, Some(uri), _) => Some(Source::Uri(uri.clone())),
+            (None, None, Some(image)) => match gst::filename_to_uri(image) {
+                Ok(uri) => Some(Source::Uri(uri.to_string())),
+                Err(err) => {
+                    gst::error!(CAT, imp: self, "Invalid fallback image {image}: {err}");
+                    gst::element_imp_error!(
+                        self,
+                        gst::LibraryError::Settings,
+                        ["Invalid fallback image {}: {}", image, err]
+                    );
+                    return Err(gst::StateChangeError);
+                }
+            },
+            (None, None, None) => None,
+        };
 
         // Create main input
         let source = self.create_main_input(&configured_source, settings.buffer_duration);
 
         // Create fallback input
-        let fallback_source =
-            self.create_fallback_input(fallback_uri.as_deref(), settings.buffer_duration);
+        let fallback_source = self.create_fallback_input(
+            configured_fallback_source.as_ref(),
+            settings.buffer_duration,
+        );
 
         let mut flow_combiner = gst_base::UniqueFlowCombiner::new();
 
@@ -1328,6 +1398,7 @@ impl FallbackSrc {
             fallback_last_buffering_update: None,
             settings,
             configured_source,
+            configured_fallback_source,
             stats: Stats::default(),
             manually_blocked,
             schedule_restart_on_unblock: false,
@@ -1394,6 +1465,20 @@ impl FallbackSrc {
             }
         }
 
+        if let (Some(Source::Element(ref source)), Some(ref fallback_source)) =
+            (&state.configured_fallback_source, &state.fallback_source)
+        {
+            // Same as above for the fallback source element
+            if source.has_as_parent(&fallback_source.source) {
+                let _ = source.set_state(gst::State::Null);
+                let _ = fallback_source
+                    .source
+                    .downcast_ref::<gst::Bin>()
+                    .unwrap()
+                    .remove(source);
+            }
+        }
+
         for source in [Some(&mut state.source), state.fallback_source.as_mut()]
             .iter_mut()
             .flatten()
@@ -1578,16 +1663,28 @@ impl FallbackSrc {
         &self,
         filter_caps: &gst::Caps,
         fallback_source: bool,
+        main_framerate: Option<gst::Fraction>,
     ) -> gst::Element {
         let imagefreeze = gst::ElementFactory::make("imagefreeze")
             .property("is-live", true)
             .build()
             .expect("No imagefreeze found");
 
-        if !fallback_source || filter_caps.is_any() {
+        if !fallback_source {
             return imagefreeze;
         }
 
+        // Without fallback caps, repeat the image at the framerate of the main stream to avoid
+        // framerate changes when switching
+        let filter_caps = match main_framerate {
+            Some(framerate) if filter_caps.is_any() => gst::Caps::builder("video/x-raw")
+                .field("framerate", framerate)
+                .build(),
+            _ if filter_caps.is_any() => return imagefreeze,
+            _ => filter_caps.clone(),
+        };
+        let filter_caps = &filter_caps;
+
         let bin = gst::Bin::default();
         let videoconvert = gst::ElementFactory::make("videoconvert")
             .name("video_videoconvert")
@@ -1745,6 +1842,17 @@ impl FallbackSrc {
             Some(state) => state,
         };
 
+        let main_framerate = state
+            .video_stream
+            .as_ref()
+            .and_then(|stream| stream.main_branch.as_ref())
+            .and_then(|branch| branch.queue_srcpad.current_caps())
+            .and_then(|caps| {
+                caps.structure(0)
+                    .and_then(|s| s.get::<gst::Fraction>("framerate").ok())
+            })
+            .filter(|framerate| framerate.numer() > 0);
+
         let source = if fallback_source {
             if let Some(ref mut source) = state.fallback_source {
                 source
@@ -1836,7 +1944,7 @@ impl FallbackSrc {
         // Configure conversion elements only for fallback stream
         // (if fallback caps is not ANY) or image source.
         let converters = if is_image {
-            self.create_image_converts(filter_caps, fallback_source)
+            self.create_image_converts(filter_caps, fallback_source, main_framerate)
         } else if is_video {
             self.create_video_converts(filter_caps, fallback_source)
         } else {
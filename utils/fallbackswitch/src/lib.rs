@@ -16,12 +16,15 @@ use gst::glib;
 
 mod fallbacksrc;
 mod fallbackswitch;
+mod watchdog;
 
 pub use fallbacksrc::{RetryReason, Status};
+pub use watchdog::WatchdogRecovery;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     fallbacksrc::register(plugin)?;
     fallbackswitch::register(plugin)?;
+    watchdog::register(plugin)?;
     Ok(())
 }
 
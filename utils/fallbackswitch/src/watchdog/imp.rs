@@ -0,0 +1,536 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+
+use parking_lot::Mutex;
+
+use super::WatchdogRecovery;
+
+const PROP_TIMEOUT: &str = "timeout";
+const PROP_ERROR_TIMEOUT: &str = "error-timeout";
+const PROP_RECOVERY: &str = "recovery";
+
+const DEFAULT_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(5);
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "watchdog",
+        gst::DebugColorFlags::empty(),
+        Some("Data flow watchdog"),
+    )
+});
+
+/* Mutex locking ordering:
+    - self.settings
+    - self.state
+*/
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    timeout: gst::ClockTime,
+    error_timeout: Option<gst::ClockTime>,
+    recovery: WatchdogRecovery,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            timeout: DEFAULT_TIMEOUT,
+            error_timeout: None,
+            recovery: WatchdogRecovery::None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    playing: bool,
+    eos: bool,
+    /* System clock time when a buffer or gap event was last seen */
+    last_activity: Option<gst::ClockTime>,
+    last_pts: Option<gst::ClockTime>,
+    segment: Option<gst::FormattedSegment<gst::ClockTime>>,
+    /* Number of timeouts since data was last seen */
+    stall_count: u32,
+    timeout_id: Option<gst::ClockId>,
+}
+
+impl State {
+    fn cancel_timeout(&mut self) {
+        if let Some(timeout_id) = self.timeout_id.take() {
+            timeout_id.unschedule();
+        }
+    }
+}
+
+pub struct Watchdog {
+    sinkpad: gst::Pad,
+    srcpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Watchdog {
+    const NAME: &'static str = "GstWatchdog";
+    type Type = super::Watchdog;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let sinkpad = gst::Pad::builder_from_template(&klass.pad_template("sink").unwrap())
+            .chain_function(|pad, parent, buffer| {
+                Watchdog::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |watchdog| watchdog.sink_chain(pad, buffer),
+                )
+            })
+            .chain_list_function(|pad, parent, list| {
+                Watchdog::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |watchdog| watchdog.sink_chain_list(pad, list),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Watchdog::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |watchdog| watchdog.sink_event(pad, event),
+                )
+            })
+            .flags(
+                gst::PadFlags::PROXY_CAPS
+                    | gst::PadFlags::PROXY_ALLOCATION
+                    | gst::PadFlags::PROXY_SCHEDULING,
+            )
+            .build();
+
+        let srcpad = gst::Pad::builder_from_template(&klass.pad_template("src").unwrap())
+            .flags(
+                gst::PadFlags::PROXY_CAPS
+                    | gst::PadFlags::PROXY_ALLOCATION
+                    | gst::PadFlags::PROXY_SCHEDULING,
+            )
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for Watchdog {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecUInt64::builder(PROP_TIMEOUT)
+                    .nick("Timeout")
+                    .blurb("Time without data after which the stream is considered stalled")
+                    .minimum(1)
+                    .default_value(DEFAULT_TIMEOUT.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder(PROP_ERROR_TIMEOUT)
+                    .nick("Error Timeout")
+                    .blurb("Time without data after which an error is posted (0 = never)")
+                    .default_value(0)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default(PROP_RECOVERY, WatchdogRecovery::None)
+                    .nick("Recovery")
+                    .blurb("Recovery to attempt upstream when the stream is stalled")
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![glib::subclass::Signal::builder("stalled")
+                .param_types([gst::Structure::static_type()])
+                .return_type::<bool>()
+                .accumulator(|_hint, acc, val| {
+                    let handled = val.get::<bool>().unwrap_or(false);
+                    *acc = val.clone();
+                    !handled
+                })
+                .build()]
+        });
+
+        SIGNALS.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock();
+        match pspec.name() {
+            PROP_TIMEOUT => {
+                let timeout = value.get::<u64>().expect("type checked upstream");
+                settings.timeout = gst::ClockTime::from_nseconds(timeout);
+            }
+            PROP_ERROR_TIMEOUT => {
+                let timeout = value.get::<u64>().expect("type checked upstream");
+                settings.error_timeout = if timeout == 0 {
+                    None
+                } else {
+                    Some(gst::ClockTime::from_nseconds(timeout))
+                };
+            }
+            PROP_RECOVERY => {
+                settings.recovery = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock();
+        match pspec.name() {
+            PROP_TIMEOUT => settings.timeout.nseconds().to_value(),
+            PROP_ERROR_TIMEOUT => settings
+                .error_timeout
+                .map_or(0, gst::ClockTime::nseconds)
+                .to_value(),
+            PROP_RECOVERY => settings.recovery.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for Watchdog {}
+
+impl ElementImpl for Watchdog {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Watchdog",
+                "Generic",
+                "Reports and recovers stalled data flow",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst::Caps::new_any();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp: self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                *self.state.lock() = State::default();
+            }
+            gst::StateChange::PausedToPlaying => {
+                let timeout = self.settings.lock().timeout;
+                let mut state = self.state.lock();
+                state.playing = true;
+
+                // Time spent in PAUSED doesn't count as stalled
+                let now = gst::SystemClock::obtain().time();
+                state.last_activity = now;
+                state.stall_count = 0;
+                if let Some(now) = now {
+                    if !state.eos {
+                        self.schedule_timeout(&mut state, now + timeout);
+                    }
+                }
+            }
+            gst::StateChange::PlayingToPaused => {
+                let mut state = self.state.lock();
+                state.playing = false;
+                state.cancel_timeout();
+            }
+            _ => (),
+        }
+
+        let res = self.parent_change_state(transition);
+
+        if transition == gst::StateChange::PausedToReady {
+            let mut state = self.state.lock();
+            state.cancel_timeout();
+            *state = State::default();
+        }
+
+        res
+    }
+}
+
+impl Watchdog {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer {:?}", buffer);
+
+        self.handle_activity(buffer.pts());
+        self.srcpad.push(buffer)
+    }
+
+    fn sink_chain_list(
+        &self,
+        pad: &gst::Pad,
+        list: gst::BufferList,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: pad, "Handling buffer list {:?}", list);
+
+        self.handle_activity(list.iter().filter_map(|buffer| buffer.pts()).last());
+        self.srcpad.push_list(list)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        gst::log!(CAT, obj: pad, "Handling event {:?}", event);
+
+        match event.view() {
+            gst::EventView::Gap(gap) => {
+                let (timestamp, _duration) = gap.get();
+                self.handle_activity(Some(timestamp));
+            }
+            gst::EventView::Segment(segment) => {
+                let mut state = self.state.lock();
+                state.segment = segment.segment().clone().downcast::<gst::ClockTime>().ok();
+            }
+            gst::EventView::Eos(_) => {
+                let mut state = self.state.lock();
+                state.eos = true;
+                state.cancel_timeout();
+            }
+            gst::EventView::FlushStop(_) => {
+                let timeout = self.settings.lock().timeout;
+                let mut state = self.state.lock();
+                // Flushes don't count as data flow, but resume monitoring after EOS
+                if state.eos {
+                    state.eos = false;
+                    let now = gst::SystemClock::obtain().time();
+                    state.last_activity = now;
+                    state.stall_count = 0;
+                    if let Some(now) = now {
+                        if state.playing {
+                            self.schedule_timeout(&mut state, now + timeout);
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    fn handle_activity(&self, pts: Option<gst::ClockTime>) {
+        let timeout = self.settings.lock().timeout;
+        let mut state = self.state.lock();
+
+        let now = gst::SystemClock::obtain().time();
+        state.last_activity = now;
+        if pts.is_some() {
+            state.last_pts = pts;
+        }
+
+        if state.stall_count > 0 {
+            gst::info!(
+                CAT,
+                imp: self,
+                "Data flow resumed after {} timeouts",
+                state.stall_count
+            );
+            state.stall_count = 0;
+        }
+
+        // The timeout is stopped after an error was posted
+        if state.timeout_id.is_none() && state.playing && !state.eos {
+            if let Some(now) = now {
+                self.schedule_timeout(&mut state, now + timeout);
+            }
+        }
+    }
+
+    fn schedule_timeout(&self, state: &mut State, wait_until: gst::ClockTime) {
+        state.cancel_timeout();
+
+        gst::trace!(CAT, imp: self, "Scheduling timeout for {}", wait_until);
+
+        let clock = gst::SystemClock::obtain();
+        let timeout_id = clock.new_single_shot_id(wait_until);
+        state.timeout_id = Some(timeout_id.clone().into());
+
+        let imp_weak = self.downgrade();
+        timeout_id
+            .wait_async(move |_clock, _time, clock_id| {
+                let Some(imp) = imp_weak.upgrade() else {
+                    return;
+                };
+
+                // Signal handlers and recovery might block, so don't run them on the clock
+                // thread
+                let clock_id = clock_id.clone();
+                imp.obj().call_async(move |element| {
+                    element.imp().on_timeout(&clock_id);
+                });
+            })
+            .expect("Failed to wait async");
+    }
+
+    fn on_timeout(&self, clock_id: &gst::ClockId) {
+        let settings = *self.settings.lock();
+        let mut state = self.state.lock();
+
+        if state.timeout_id.as_ref() != Some(clock_id) {
+            gst::debug!(CAT, imp: self, "Late timeout callback. Ignoring");
+            return;
+        }
+        state.timeout_id = None;
+
+        if !state.playing || state.eos {
+            return;
+        }
+
+        let (Some(now), Some(last_activity)) =
+            (gst::SystemClock::obtain().time(), state.last_activity)
+        else {
+            return;
+        };
+
+        let elapsed = now.saturating_sub(last_activity);
+        if elapsed < settings.timeout {
+            // Data arrived since the timeout was scheduled
+            self.schedule_timeout(&mut state, last_activity + settings.timeout);
+            return;
+        }
+
+        state.stall_count += 1;
+        let count = state.stall_count;
+
+        let is_error = settings
+            .error_timeout
+            .map_or(false, |error_timeout| elapsed >= error_timeout);
+        if !is_error {
+            let mut wait_until = now + settings.timeout;
+            if let Some(error_timeout) = settings.error_timeout {
+                wait_until = wait_until.min(last_activity + error_timeout);
+            }
+            self.schedule_timeout(&mut state, wait_until);
+        }
+
+        let last_pts = state.last_pts;
+        let rate = state.segment.as_ref().map_or(1.0, |segment| segment.rate());
+        drop(state);
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "No data for {elapsed} ({count} timeouts), last pts {}",
+            last_pts.display()
+        );
+
+        let context = gst::Structure::builder("watchdog-stalled")
+            .field("elapsed", elapsed)
+            .field("count", count)
+            .field("last-pts", last_pts)
+            .field("error", is_error)
+            .build();
+
+        let handled = self.obj().emit_by_name::<bool>("stalled", &[&context]);
+        if handled {
+            gst::debug!(CAT, imp: self, "Stall handled by application");
+            return;
+        }
+
+        if is_error {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Failed,
+                ["No data for {}", elapsed],
+                details: context
+            );
+            return;
+        }
+
+        if count == 1 {
+            gst::element_imp_warning!(
+                self,
+                gst::StreamError::Failed,
+                ["No data for {}", elapsed],
+                details: context
+            );
+        }
+
+        match settings.recovery {
+            WatchdogRecovery::None => (),
+            WatchdogRecovery::Flush => {
+                gst::info!(CAT, imp: self, "Flushing upstream");
+                self.sinkpad.push_event(gst::event::FlushStart::new());
+                self.sinkpad.push_event(gst::event::FlushStop::new(true));
+            }
+            WatchdogRecovery::Seek => {
+                let Some(position) = last_pts else {
+                    gst::warning!(CAT, imp: self, "No position to seek to yet");
+                    return;
+                };
+
+                gst::info!(CAT, imp: self, "Seeking upstream to {}", position);
+                let seek = gst::event::Seek::new(
+                    rate,
+                    gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE,
+                    gst::SeekType::Set,
+                    Some(position),
+                    gst::SeekType::None,
+                    gst::ClockTime::NONE,
+                );
+                if !self.sinkpad.push_event(seek) {
+                    gst::warning!(CAT, imp: self, "Upstream didn't handle seek");
+                }
+            }
+        }
+    }
+}
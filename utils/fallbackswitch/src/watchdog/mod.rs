@@ -0,0 +1,67 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-watchdog:
+ * @short_description: Monitors data flow and reports or recovers stalled streams.
+ *
+ * Passes through all data unchanged and keeps track of the last time a buffer or gap event
+ * arrived while in `PLAYING`. Once no data arrived for `timeout`, the `stalled` signal is emitted
+ * with a `watchdog-stalled` structure containing the time since the last data (`elapsed`), the
+ * number of consecutive timeouts (`count`) and the timestamp of the last buffer (`last-pts`).
+ *
+ * Unless a signal handler returns %TRUE, a warning message is posted on the first timeout and
+ * the configured `recovery` is attempted on every timeout: a flush of the upstream elements or a
+ * flushing seek to the last position. If no data arrived for `error-timeout`, an error message
+ * is posted instead and the watchdog stops until data flows again.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 rtspsrc location=rtsp://camera/stream ! rtph264depay ! \
+ *   watchdog timeout=2000000000 error-timeout=10000000000 ! h264parse ! fakesink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstWatchdogRecovery")]
+pub enum WatchdogRecovery {
+    #[enum_value(name = "None: Don't attempt to recover.", nick = "none")]
+    None = 0,
+
+    #[enum_value(
+        name = "Flush: Send flush-start and flush-stop events upstream.",
+        nick = "flush"
+    )]
+    Flush = 1,
+
+    #[enum_value(
+        name = "Seek: Send a flushing seek to the last position upstream.",
+        nick = "seek"
+    )]
+    Seek = 2,
+}
+
+glib::wrapper! {
+    pub struct Watchdog(ObjectSubclass<imp::Watchdog>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    WatchdogRecovery::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "watchdog",
+        gst::Rank::NONE,
+        Watchdog::static_type(),
+    )
+}
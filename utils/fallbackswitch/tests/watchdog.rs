@@ -0,0 +1,134 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use std::sync::mpsc;
+
+const TIMEOUT: gst::ClockTime = gst::ClockTime::from_mseconds(50);
+const ERROR_TIMEOUT: gst::ClockTime = gst::ClockTime::from_mseconds(200);
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstfallbackswitch::plugin_register_static().expect("gstfallbackswitch test");
+    });
+}
+
+fn setup_pipeline() -> (gst::Pipeline, gst_app::AppSrc, gst::Element) {
+    init();
+
+    let pipeline = gst::Pipeline::new();
+    let src = gst_app::AppSrc::builder()
+        .is_live(true)
+        .format(gst::Format::Time)
+        .build();
+    let watchdog = gst::ElementFactory::make("watchdog")
+        .property("timeout", TIMEOUT.nseconds())
+        .property("error-timeout", ERROR_TIMEOUT.nseconds())
+        .build()
+        .unwrap();
+    let sink = gst::ElementFactory::make("fakesink")
+        .property("sync", false)
+        .build()
+        .unwrap();
+
+    pipeline
+        .add_many([src.upcast_ref(), &watchdog, &sink])
+        .unwrap();
+    gst::Element::link_many([src.upcast_ref(), &watchdog, &sink]).unwrap();
+
+    (pipeline, src, watchdog)
+}
+
+fn push_buffer(src: &gst_app::AppSrc) {
+    let mut buffer = gst::Buffer::with_size(1).unwrap();
+    buffer.get_mut().unwrap().set_pts(gst::ClockTime::ZERO);
+    src.push_buffer(buffer).unwrap();
+}
+
+#[test]
+fn test_warning_then_error() {
+    let (pipeline, src, _watchdog) = setup_pipeline();
+    let bus = pipeline.bus().unwrap();
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+    push_buffer(&src);
+
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(5),
+            &[gst::MessageType::Warning, gst::MessageType::Error],
+        )
+        .expect("No warning");
+    let gst::MessageView::Warning(warning) = msg.view() else {
+        panic!("Unexpected message {msg:?}");
+    };
+    let details = warning.details().unwrap();
+    assert_eq!(details.name(), "watchdog-stalled");
+    assert_eq!(details.get::<u32>("count").unwrap(), 1);
+    assert!(!details.get::<bool>("error").unwrap());
+
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(5),
+            &[gst::MessageType::Warning, gst::MessageType::Error],
+        )
+        .expect("No error");
+    let gst::MessageView::Error(error) = msg.view() else {
+        panic!("Unexpected message {msg:?}");
+    };
+    let details = error.details().unwrap();
+    assert!(details.get::<bool>("error").unwrap());
+    assert!(details.get::<gst::ClockTime>("elapsed").unwrap() >= ERROR_TIMEOUT);
+    assert_eq!(
+        details.get::<Option<gst::ClockTime>>("last-pts").unwrap(),
+        Some(gst::ClockTime::ZERO)
+    );
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_stalled_handled() {
+    let (pipeline, src, watchdog) = setup_pipeline();
+    let bus = pipeline.bus().unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let sender = std::sync::Mutex::new(sender);
+    watchdog.connect("stalled", false, move |args| {
+        let context = args[1].get::<gst::Structure>().unwrap();
+        let _ = sender.lock().unwrap().send(context);
+        Some(true.to_value())
+    });
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+    push_buffer(&src);
+
+    let mut last_count = 0;
+    loop {
+        let context = receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .expect("No stalled signal");
+        let count = context.get::<u32>("count").unwrap();
+        assert_eq!(count, last_count + 1);
+        last_count = count;
+
+        if context.get::<bool>("error").unwrap() {
+            break;
+        }
+    }
+    assert!(last_count > 1);
+
+    // Handled stalls don't post any messages
+    assert!(bus
+        .pop_filtered(&[gst::MessageType::Warning, gst::MessageType::Error])
+        .is_none());
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
@@ -89,6 +89,12 @@ struct State {
     /// See `PROP_SINGLE_SEGMENT`
     single_segment: bool,
 
+    /// See `PROP_SPARSE`
+    sparse: bool,
+
+    /// Whether the stream-start event of our sinkpad flagged the stream as sparse
+    in_sparse: bool,
+
     /// Latency reported by upstream
     upstream_latency: Option<gst::ClockTime>,
 
@@ -160,16 +166,21 @@ struct State {
 
     /// See `PROP_DUPLICATE`
     num_duplicate: u64,
+
+    /// See `PROP_GAP`
+    num_gap: u64,
 }
 
 const PROP_LATENCY: &str = "latency";
 const PROP_LATE_THRESHOLD: &str = "late-threshold";
 const PROP_SINGLE_SEGMENT: &str = "single-segment";
+const PROP_SPARSE: &str = "sparse";
 
 const PROP_IN: &str = "in";
 const PROP_DROP: &str = "drop";
 const PROP_OUT: &str = "out";
 const PROP_DUPLICATE: &str = "duplicate";
+const PROP_GAP: &str = "gap";
 
 const DEFAULT_LATENCY: gst::ClockTime = gst::ClockTime::ZERO;
 const MINIMUM_DURATION: gst::ClockTime = gst::ClockTime::from_mseconds(8);
@@ -184,6 +195,8 @@ impl Default for State {
             latency: DEFAULT_LATENCY,
             late_threshold: DEFAULT_LATE_THRESHOLD,
             single_segment: false,
+            sparse: false,
+            in_sparse: false,
             upstream_latency: None,
             playing: false,
             eos: false,
@@ -208,6 +221,7 @@ impl Default for State {
             num_drop: 0,
             num_out: 0,
             num_duplicate: 0,
+            num_gap: 0,
         }
     }
 }
@@ -295,7 +309,7 @@ impl ObjectSubclass for LiveSync {
 
 impl ObjectImpl for LiveSync {
     fn properties() -> &'static [glib::ParamSpec] {
-        static PROPERTIES: Lazy<[glib::ParamSpec; 7]> = Lazy::new(|| {
+        static PROPERTIES: Lazy<[glib::ParamSpec; 9]> = Lazy::new(|| {
             [
                 glib::ParamSpecUInt64::builder(PROP_LATENCY)
                     .nick("Latency")
@@ -322,6 +336,14 @@ impl ObjectImpl for LiveSync {
                     .blurb("Timestamp buffers and eat segments so as to appear as one segment")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoolean::builder(PROP_SPARSE)
+                    .nick("Sparse")
+                    .blurb(
+                        "Send gap events instead of repeating stale buffers, \
+                         as for streams flagged sparse by upstream",
+                    )
+                    .mutable_playing()
+                    .build(),
                 glib::ParamSpecUInt64::builder(PROP_IN)
                     .nick("Frames input")
                     .blurb("Number of incoming frames accepted")
@@ -342,6 +364,11 @@ impl ObjectImpl for LiveSync {
                     .blurb("Number of outgoing frames duplicated")
                     .read_only()
                     .build(),
+                glib::ParamSpecUInt64::builder(PROP_GAP)
+                    .nick("Gaps")
+                    .blurb("Number of outgoing gap events produced for sparse streams")
+                    .read_only()
+                    .build(),
             ]
         });
 
@@ -373,6 +400,10 @@ impl ObjectImpl for LiveSync {
                 state.single_segment = value.get().unwrap();
             }
 
+            PROP_SPARSE => {
+                state.sparse = value.get().unwrap();
+            }
+
             _ => unimplemented!(),
         }
     }
@@ -383,10 +414,12 @@ impl ObjectImpl for LiveSync {
             PROP_LATENCY => state.latency.to_value(),
             PROP_LATE_THRESHOLD => state.late_threshold.to_value(),
             PROP_SINGLE_SEGMENT => state.single_segment.to_value(),
+            PROP_SPARSE => state.sparse.to_value(),
             PROP_IN => state.num_in.to_value(),
             PROP_DROP => state.num_drop.to_value(),
             PROP_OUT => state.num_out.to_value(),
             PROP_DUPLICATE => state.num_duplicate.to_value(),
+            PROP_GAP => state.num_gap.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -459,6 +492,7 @@ impl ElementImpl for LiveSync {
                 state.num_drop = 0;
                 state.num_out = 0;
                 state.num_duplicate = 0;
+                state.num_gap = 0;
             }
 
             _ => {}
@@ -506,6 +540,10 @@ impl State {
     fn pending_events(&self) -> bool {
         self.pending_caps.is_some() || self.pending_segment.is_some()
     }
+
+    fn is_sparse(&self) -> bool {
+        self.sparse || self.in_sparse
+    }
 }
 
 impl LiveSync {
@@ -575,6 +613,7 @@ impl LiveSync {
         state.in_audio_info = None;
         state.in_duration = None;
         state.in_timestamp = None;
+        state.in_sparse = false;
     }
 
     fn src_reset(&self, state: &mut State) {
@@ -630,7 +669,13 @@ impl LiveSync {
                 return ret;
             }
 
-            gst::EventView::StreamStart(_) => is_restart = true,
+            gst::EventView::StreamStart(e) => {
+                is_restart = true;
+
+                let sparse = e.stream_flags().contains(gst::StreamFlags::SPARSE);
+                gst::debug!(CAT, imp: self, "Stream is sparse: {sparse}");
+                self.state.lock().in_sparse = sparse;
+            }
 
             gst::EventView::Segment(e) => {
                 is_restart = true;
@@ -1100,6 +1145,7 @@ impl LiveSync {
 
         let mut caps = None;
         let mut segment = None;
+        let mut repeat = false;
 
         match in_buffer {
             Some((mut buffer, BufferLateness::OnTime)) => {
@@ -1131,10 +1177,12 @@ impl LiveSync {
                 state.num_drop += 1;
 
                 self.patch_output_buffer(&mut state, None)?;
+                repeat = true;
             }
 
             None => {
                 self.patch_output_buffer(&mut state, None)?;
+                repeat = true;
             }
 
             Some((_, BufferLateness::LateUnderThreshold)) => {
@@ -1144,6 +1192,20 @@ impl LiveSync {
         }
 
         let buffer = state.out_buffer.clone().unwrap();
+
+        if repeat && state.is_sparse() {
+            // Don't repeat stale data of sparse streams, only advance time downstream
+            let event = gst::event::Gap::builder(buffer.pts().unwrap())
+                .duration(buffer.duration())
+                .build();
+
+            drop(state);
+
+            gst::trace!(CAT, imp: self, "Pushing {event:?}");
+            self.srcpad.push_event(event);
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
         let sync_ts = state
             .out_timestamp
             .map_or(gst::ClockTime::ZERO, |t| t.start);
@@ -1293,6 +1355,7 @@ impl LiveSync {
     ) -> Result<(), gst::FlowError> {
         let out_buffer = state.out_buffer.as_mut().unwrap();
         let mut duplicate = state.out_buffer_duplicate;
+        let gap = source.is_none() && state.is_sparse();
 
         let duration = out_buffer.duration().unwrap();
         let dts = out_buffer.dts().map(|t| t + duration);
@@ -1358,7 +1421,11 @@ impl LiveSync {
             state.out_buffer.as_ref().unwrap(),
             state.out_segment.as_ref().unwrap(),
         );
-        state.num_duplicate += 1;
+        if gap {
+            state.num_gap += 1;
+        } else {
+            state.num_duplicate += 1;
+        }
         Ok(())
     }
 }
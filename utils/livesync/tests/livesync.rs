@@ -202,3 +202,46 @@ fn test_livesync(h: &mut gst_check::Harness, o: u64, singlesegment: bool) {
     assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::Eos);
     assert_eq!(h.try_pull(), None);
 }
+
+#[test]
+fn test_video_sparse() {
+    init();
+
+    let mut h = gst_check::Harness::new("livesync");
+    h.add_src_parse(
+        r"videotestsrc is-live=1
+          ! capsfilter caps=video/x-raw,framerate=10/1
+        ",
+        true,
+    );
+
+    let element = h.element().unwrap();
+    element.set_property("latency", LATENCY);
+    element.set_property("single-segment", true);
+    element.set_property("sparse", true);
+
+    // Push frames 0-1, pull frames 0-1
+    h.push_from_src().unwrap();
+    h.push_from_src().unwrap();
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::StreamStart);
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::Caps);
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::Segment);
+    assert_crank_pull(&mut h, 1, 0, 0, gst::BufferFlags::DISCONT, true);
+    assert_crank_pull(&mut h, 1, 1, 1, gst::BufferFlags::empty(), true);
+
+    // Bridging gap with gap events instead of repeated frames
+    for i in 2..=4 {
+        h.crank_single_clock_wait().unwrap();
+        let event = h.pull_event().unwrap();
+        let gst::EventView::Gap(gap) = event.view() else {
+            panic!("Expected gap event, got {event:?}");
+        };
+        let (pts, duration) = gap.get();
+        assert_eq!(pts, LATENCY + DURATION * i + SEGMENT_OFFSET);
+        assert_eq!(duration, Some(DURATION));
+    }
+    assert_eq!(h.try_pull(), None);
+
+    assert_eq!(element.property::<u64>("gap"), 3);
+    assert_eq!(element.property::<u64>("duplicate"), 0);
+}
@@ -28,16 +28,12 @@ const DEFAULT_LIVE: bool = false;
 
 #[derive(Debug, Clone, Copy)]
 struct Settings {
-    record: bool,
     live: bool,
 }
 
 impl Default for Settings {
     fn default() -> Self {
-        Settings {
-            record: DEFAULT_RECORD,
-            live: DEFAULT_LIVE,
-        }
+        Settings { live: DEFAULT_LIVE }
     }
 }
 
@@ -160,6 +156,45 @@ impl Default for State {
     }
 }
 
+// A main stream and the secondary streams that are started/stopped together with it.
+//
+// The always pads form the default group, request pads can be moved to other named groups
+// via their "group" property. The first stream that is moved to a group is its main stream.
+struct Group {
+    // None for the default group
+    name: Option<String>,
+    record: Mutex<bool>,
+    state: Mutex<State>,
+    main_stream: Stream,
+    // Always must have main_stream.state locked!
+    // If multiple stream states have to be locked, the
+    // main_stream always comes first
+    main_stream_cond: Condvar,
+    other_streams: Mutex<Vec<Stream>>,
+}
+
+impl Group {
+    fn new(name: Option<String>, main_stream: Stream) -> Self {
+        Self {
+            name,
+            record: Mutex::new(DEFAULT_RECORD),
+            state: Mutex::new(State::default()),
+            main_stream,
+            main_stream_cond: Condvar::new(),
+            other_streams: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn streams(&self) -> Vec<Stream> {
+        self.other_streams
+            .lock()
+            .iter()
+            .cloned()
+            .chain(iter::once(self.main_stream.clone()))
+            .collect()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum HandleResult<T> {
     Pass(T),
@@ -326,16 +361,77 @@ impl HandleData for gst::Buffer {
     }
 }
 
+#[derive(Debug, Default)]
+pub struct ToggleRecordSinkPad {
+    group: Mutex<Option<String>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ToggleRecordSinkPad {
+    const NAME: &'static str = "GstToggleRecordSinkPad";
+    type Type = super::ToggleRecordSinkPad;
+    type ParentType = gst::Pad;
+}
+
+impl GstObjectImpl for ToggleRecordSinkPad {}
+
+impl ObjectImpl for ToggleRecordSinkPad {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![glib::ParamSpecString::builder("group")
+                .nick("Group")
+                .blurb(
+                    "Recording group of this stream, NULL for the default group. \
+                    The first stream of a group is its main stream",
+                )
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "group" => {
+                let group = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .filter(|group| !group.is_empty());
+
+                let pad = self.obj();
+                let Some(togglerecord) = pad.parent().and_downcast::<super::ToggleRecord>() else {
+                    gst::warning!(CAT, obj: pad, "Can't set group without parent element");
+                    return;
+                };
+
+                if togglerecord
+                    .imp()
+                    .set_stream_group(pad.upcast_ref(), group.as_deref())
+                {
+                    *self.group.lock() = group;
+                }
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "group" => self.group.lock().to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl PadImpl for ToggleRecordSinkPad {}
+
 pub struct ToggleRecord {
     settings: Mutex<Settings>,
-    state: Mutex<State>,
-    main_stream: Stream,
-    // Always must have main_stream.state locked!
-    // If multiple stream states have to be locked, the
-    // main_stream always comes first
-    main_stream_cond: Condvar,
-    other_streams: Mutex<(Vec<Stream>, u32)>,
-    pads: Mutex<HashMap<gst::Pad, Stream>>,
+    default_group: Arc<Group>,
+    groups: Mutex<HashMap<String, Arc<Group>>>,
+    pad_count: Mutex<u32>,
+    pads: Mutex<HashMap<gst::Pad, (Stream, Arc<Group>)>>,
 }
 
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
@@ -347,26 +443,129 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
 });
 
 impl ToggleRecord {
+    fn notify_recording(&self, group: &Group) {
+        match group.name {
+            None => self.obj().notify("recording"),
+            Some(ref name) => {
+                let recording = group.state.lock().recording_state == RecordingState::Recording;
+                self.obj()
+                    .emit_by_name::<()>("group-recording-changed", &[name, &recording]);
+            }
+        }
+    }
+
+    fn group(&self, name: Option<&str>) -> Option<Arc<Group>> {
+        match name {
+            None => Some(self.default_group.clone()),
+            Some(name) => self.groups.lock().get(name).cloned(),
+        }
+    }
+
+    fn all_groups(&self) -> Vec<Arc<Group>> {
+        iter::once(self.default_group.clone())
+            .chain(self.groups.lock().values().cloned())
+            .collect()
+    }
+
+    fn set_group_record(&self, name: Option<&str>, record: bool) -> bool {
+        let Some(group) = self.group(name) else {
+            gst::warning!(CAT, imp: self, "No group {:?}", name);
+            return false;
+        };
+
+        let mut group_record = group.record.lock();
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Setting record of group {:?} from {:?} to {:?}",
+            name,
+            *group_record,
+            record
+        );
+
+        *group_record = record;
+        drop(group_record);
+        group.main_stream_cond.notify_all();
+
+        true
+    }
+
+    fn set_stream_group(&self, pad: &gst::Pad, name: Option<&str>) -> bool {
+        if self.obj().current_state() > gst::State::Ready {
+            gst::warning!(CAT, obj: pad, "Can only change group in NULL or READY state");
+            return false;
+        }
+
+        let mut groups = self.groups.lock();
+        let mut pads = self.pads.lock();
+
+        let Some((stream, old_group)) = pads.get(pad).cloned() else {
+            return false;
+        };
+
+        if old_group.name.as_deref() == name {
+            return true;
+        }
+
+        if stream == old_group.main_stream {
+            if old_group.name.is_none() || !old_group.other_streams.lock().is_empty() {
+                gst::warning!(
+                    CAT,
+                    obj: pad,
+                    "Can't move main stream of group {:?} with secondary streams",
+                    old_group.name,
+                );
+                return false;
+            }
+
+            groups.remove(old_group.name.as_ref().unwrap());
+        } else {
+            old_group.other_streams.lock().retain(|s| *s != stream);
+        }
+
+        let group = match name {
+            None => self.default_group.clone(),
+            Some(name) => groups
+                .entry(name.to_string())
+                .or_insert_with(|| {
+                    gst::debug!(CAT, obj: pad, "Creating group {name}");
+                    Arc::new(Group::new(Some(name.to_string()), stream.clone()))
+                })
+                .clone(),
+        };
+
+        if stream != group.main_stream {
+            group.other_streams.lock().push(stream.clone());
+        }
+
+        gst::debug!(CAT, obj: pad, "Moved from group {:?} to {:?}", old_group.name, name);
+
+        pads.insert(stream.sinkpad.clone(), (stream.clone(), group.clone()));
+        pads.insert(stream.srcpad.clone(), (stream, group));
+
+        true
+    }
+
     fn block_if_upstream_not_live(
         &self,
         pad: &gst::Pad,
-        mut settings: Settings,
+        group: &Group,
+        settings: Settings,
         state: &mut MutexGuard<StreamState>,
         upstream_live: bool,
     ) -> Result<bool, gst::FlowError> {
         if !upstream_live {
             let clock = self.obj().clock();
-            let mut rec_state = self.state.lock();
+            let mut rec_state = group.state.lock();
             if rec_state.time_start_block.is_none() {
                 rec_state.time_start_block = clock
                     .as_ref()
                     .map_or(state.current_running_time, |c| c.time());
             }
             drop(rec_state);
-            while !settings.record && !state.flushing {
+            while !*group.record.lock() && !state.flushing {
                 gst::debug!(CAT, obj: pad, "Waiting for record=true");
-                self.main_stream_cond.wait(state);
-                settings = *self.settings.lock();
+                group.main_stream_cond.wait(state);
             }
             if state.flushing {
                 gst::debug!(CAT, obj: pad, "Flushing");
@@ -374,12 +573,12 @@ impl ToggleRecord {
             }
             state.segment_pending = true;
             state.discont_pending = true;
-            for other_stream in &self.other_streams.lock().0 {
+            for other_stream in group.other_streams.lock().iter() {
                 let mut other_state = other_stream.state.lock();
                 other_state.segment_pending = true;
                 other_state.discont_pending = true;
             }
-            let mut rec_state = self.state.lock();
+            let mut rec_state = group.state.lock();
             if let Some(time_start_block) = rec_state.time_start_block {
                 // If we have a time_start_block it means the clock is there
                 let clock = clock.expect("Cannot find pipeline clock");
@@ -408,6 +607,7 @@ impl ToggleRecord {
     fn handle_main_stream<T: HandleData>(
         &self,
         pad: &gst::Pad,
+        group: &Group,
         stream: &Stream,
         data: T,
         upstream_live: bool,
@@ -455,7 +655,7 @@ impl ToggleRecord {
         // Important: They will only be able to advance once we're done with this
         // function or waiting for them to catch up below, otherwise they might
         // get the wrong state
-        self.main_stream_cond.notify_all();
+        group.main_stream_cond.notify_all();
 
         gst::log!(
             CAT,
@@ -468,20 +668,21 @@ impl ToggleRecord {
         );
 
         let settings = *self.settings.lock();
+        let record = *group.record.lock();
 
         // First check if we need to block for non-live input
-        let mut rec_state = self.state.lock();
+        let mut rec_state = group.state.lock();
 
         // Check if we have to update our recording state
         let settings_changed = match rec_state.recording_state {
-            RecordingState::Recording if !settings.record => {
+            RecordingState::Recording if !record => {
                 let clock = self.obj().clock().expect("Cannot find pipeline clock");
                 rec_state.time_start_block = Some(clock.time().unwrap());
                 gst::debug!(CAT, obj: pad, "Stopping recording");
                 rec_state.recording_state = RecordingState::Stopping;
                 true
             }
-            RecordingState::Stopped if settings.record => {
+            RecordingState::Stopped if record => {
                 gst::debug!(CAT, obj: pad, "Starting recording");
                 rec_state.recording_state = RecordingState::Starting;
                 true
@@ -537,7 +738,7 @@ impl ToggleRecord {
                 drop(rec_state);
 
                 while !state.flushing
-                    && !self.other_streams.lock().0.iter().all(|s| {
+                    && !group.other_streams.lock().iter().all(|s| {
                         let s = s.state.lock();
                         s.eos
                             || s.current_running_time
@@ -546,7 +747,7 @@ impl ToggleRecord {
                     })
                 {
                     gst::log!(CAT, obj: pad, "Waiting for other streams to stop");
-                    self.main_stream_cond.wait(&mut state);
+                    group.main_stream_cond.wait(&mut state);
                 }
 
                 if state.flushing {
@@ -554,7 +755,7 @@ impl ToggleRecord {
                     return Err(gst::FlowError::Flushing);
                 }
 
-                let mut rec_state = self.state.lock();
+                let mut rec_state = group.state.lock();
                 rec_state.recording_state = RecordingState::Stopped;
                 rec_state.recording_duration +=
                     last_recording_duration.unwrap_or(gst::ClockTime::ZERO);
@@ -573,10 +774,15 @@ impl ToggleRecord {
                 // a keyframe
                 drop(rec_state);
 
-                let ret =
-                    self.block_if_upstream_not_live(pad, settings, &mut state, upstream_live)?;
+                let ret = self.block_if_upstream_not_live(
+                    pad,
+                    group,
+                    settings,
+                    &mut state,
+                    upstream_live,
+                )?;
                 drop(state);
-                self.obj().notify("recording");
+                self.notify_recording(group);
 
                 if ret {
                     Ok(HandleResult::Pass(data))
@@ -589,7 +795,13 @@ impl ToggleRecord {
                     rec_state.recording_state = RecordingState::Starting;
                 }
                 drop(rec_state);
-                if self.block_if_upstream_not_live(pad, settings, &mut state, upstream_live)? {
+                if self.block_if_upstream_not_live(
+                    pad,
+                    group,
+                    settings,
+                    &mut state,
+                    upstream_live,
+                )? {
                     Ok(HandleResult::Pass(data))
                 } else {
                     Ok(HandleResult::Drop)
@@ -642,7 +854,7 @@ impl ToggleRecord {
 
                 state.segment_pending = true;
                 state.discont_pending = true;
-                for other_stream in &self.other_streams.lock().0 {
+                for other_stream in group.other_streams.lock().iter() {
                     let mut other_state = other_stream.state.lock();
                     other_state.segment_pending = true;
                     other_state.discont_pending = true;
@@ -654,7 +866,7 @@ impl ToggleRecord {
                 drop(rec_state);
 
                 while !state.flushing
-                    && !self.other_streams.lock().0.iter().all(|s| {
+                    && !group.other_streams.lock().iter().all(|s| {
                         let s = s.state.lock();
                         s.eos
                             || s.current_running_time
@@ -663,7 +875,7 @@ impl ToggleRecord {
                     })
                 {
                     gst::log!(CAT, obj: pad, "Waiting for other streams to start");
-                    self.main_stream_cond.wait(&mut state);
+                    group.main_stream_cond.wait(&mut state);
                 }
 
                 if state.flushing {
@@ -671,7 +883,7 @@ impl ToggleRecord {
                     return Err(gst::FlowError::Flushing);
                 }
 
-                let mut rec_state = self.state.lock();
+                let mut rec_state = group.state.lock();
                 rec_state.recording_state = RecordingState::Recording;
                 gst::debug!(
                     CAT,
@@ -685,7 +897,7 @@ impl ToggleRecord {
 
                 drop(rec_state);
                 drop(state);
-                self.obj().notify("recording");
+                self.notify_recording(group);
 
                 Ok(HandleResult::Pass(data))
             }
@@ -696,6 +908,7 @@ impl ToggleRecord {
     fn handle_secondary_stream<T: HandleData>(
         &self,
         pad: &gst::Pad,
+        group: &Group,
         stream: &Stream,
         data: T,
         upstream_live: bool,
@@ -765,15 +978,15 @@ impl ToggleRecord {
 
         drop(state);
 
-        let mut main_state = self.main_stream.state.lock();
+        let mut main_state = group.main_stream.state.lock();
 
         // Wake up, in case the main stream is waiting for us to progress up to here. We progressed
         // above but all notifying must happen while the main_stream state is locked as per above.
-        self.main_stream_cond.notify_all();
+        group.main_stream_cond.notify_all();
 
         state = stream.state.lock();
 
-        let mut rec_state = self.state.lock();
+        let mut rec_state = group.state.lock();
 
         // Wait until the main stream advanced completely past our current running time in
         // Recording/Stopped modes to make sure we're not already outputting/dropping data that
@@ -818,9 +1031,9 @@ impl ToggleRecord {
 
             drop(rec_state);
             drop(state);
-            self.main_stream_cond.wait(&mut main_state);
+            group.main_stream_cond.wait(&mut main_state);
             state = stream.state.lock();
-            rec_state = self.state.lock();
+            rec_state = group.state.lock();
         }
 
         if state.flushing {
@@ -1172,6 +1385,7 @@ impl ToggleRecord {
     fn check_and_update_eos(
         &self,
         pad: &gst::Pad,
+        group: &Group,
         stream: &Stream,
         stream_state: &mut StreamState,
         rec_state: &mut State,
@@ -1184,7 +1398,7 @@ impl ToggleRecord {
             let mut all_others_eos = true;
 
             // Check eos state of all secondary streams
-            self.other_streams.lock().0.iter().all(|s| {
+            group.other_streams.lock().iter().all(|s| {
                 if s == stream {
                     return true;
                 }
@@ -1215,6 +1429,7 @@ impl ToggleRecord {
     fn check_and_update_stream_start(
         &self,
         pad: &gst::Pad,
+        group: &Group,
         stream: &Stream,
         stream_state: &mut StreamState,
         rec_state: &mut State,
@@ -1227,7 +1442,7 @@ impl ToggleRecord {
             let mut all_others_not_eos = false;
 
             // Check eos state of all secondary streams
-            self.other_streams.lock().0.iter().any(|s| {
+            group.other_streams.lock().iter().any(|s| {
                 if s == stream {
                     return false;
                 }
@@ -1240,8 +1455,7 @@ impl ToggleRecord {
             });
 
             if !all_others_not_eos {
-                let settings = self.settings.lock();
-                if settings.record {
+                if *group.record.lock() {
                     gst::debug!(CAT, obj: pad, "Restarting recording after EOS");
                     rec_state.recording_state = RecordingState::Starting;
                 }
@@ -1256,7 +1470,7 @@ impl ToggleRecord {
         pad: &gst::Pad,
         buffer: gst::Buffer,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
-        let stream = self.pads.lock().get(pad).cloned().ok_or_else(|| {
+        let (stream, group) = self.pads.lock().get(pad).cloned().ok_or_else(|| {
             gst::element_imp_error!(self, gst::CoreError::Pad, ["Unknown pad {:?}", pad.name()]);
             gst::FlowError::Error
         })?;
@@ -1293,10 +1507,10 @@ impl ToggleRecord {
             }
         }
 
-        let handle_result = if stream != self.main_stream {
-            self.handle_secondary_stream(pad, &stream, buffer, upstream_live)
+        let handle_result = if stream != group.main_stream {
+            self.handle_secondary_stream(pad, &group, &stream, buffer, upstream_live)
         } else {
-            self.handle_main_stream(pad, &stream, buffer, upstream_live)
+            self.handle_main_stream(pad, &group, &stream, buffer, upstream_live)
         }?;
 
         let mut buffer = match handle_result {
@@ -1311,7 +1525,7 @@ impl ToggleRecord {
                 );
 
                 if recording_state_updated {
-                    self.obj().notify("recording");
+                    self.notify_recording(&group);
                 }
 
                 return Err(gst::FlowError::Eos);
@@ -1323,8 +1537,8 @@ impl ToggleRecord {
         };
 
         let out_running_time = {
-            let main_state = if stream != self.main_stream {
-                Some(self.main_stream.state.lock())
+            let main_state = if stream != group.main_stream {
+                Some(group.main_stream.state.lock())
             } else {
                 None
             };
@@ -1341,7 +1555,7 @@ impl ToggleRecord {
             let mut events = Vec::with_capacity(state.pending_events.len() + 1);
 
             if state.segment_pending {
-                let rec_state = self.state.lock();
+                let rec_state = group.state.lock();
 
                 // Adjust so that last_recording_start has running time of
                 // recording_duration
@@ -1398,7 +1612,7 @@ impl ToggleRecord {
     fn sink_event(&self, pad: &gst::Pad, mut event: gst::Event) -> bool {
         use gst::EventView;
 
-        let stream = match self.pads.lock().get(pad) {
+        let (stream, group) = match self.pads.lock().get(pad) {
             None => {
                 gst::element_imp_error!(
                     self,
@@ -1407,7 +1621,7 @@ impl ToggleRecord {
                 );
                 return false;
             }
-            Some(stream) => stream.clone(),
+            Some((stream, group)) => (stream.clone(), group.clone()),
         };
 
         gst::log!(CAT, obj: pad, "Handling event {:?}", event);
@@ -1418,15 +1632,15 @@ impl ToggleRecord {
 
         match event.view() {
             EventView::FlushStart(..) => {
-                let _main_state = if stream != self.main_stream {
-                    Some(self.main_stream.state.lock())
+                let _main_state = if stream != group.main_stream {
+                    Some(group.main_stream.state.lock())
                 } else {
                     None
                 };
                 let mut state = stream.state.lock();
 
                 state.flushing = true;
-                self.main_stream_cond.notify_all();
+                group.main_stream_cond.notify_all();
             }
             EventView::FlushStop(..) => {
                 let mut state = stream.state.lock();
@@ -1520,10 +1734,16 @@ impl ToggleRecord {
                         Some(is_live) => upstream_live = is_live,
                     }
                 }
-                let handle_result = if stream == self.main_stream {
-                    self.handle_main_stream(pad, &stream, (pts, duration), upstream_live)
+                let handle_result = if stream == group.main_stream {
+                    self.handle_main_stream(pad, &group, &stream, (pts, duration), upstream_live)
                 } else {
-                    self.handle_secondary_stream(pad, &stream, (pts, duration), upstream_live)
+                    self.handle_secondary_stream(
+                        pad,
+                        &group,
+                        &stream,
+                        (pts, duration),
+                        upstream_live,
+                    )
                 };
 
                 forward = match handle_result {
@@ -1539,8 +1759,8 @@ impl ToggleRecord {
                 };
             }
             EventView::StreamStart(..) => {
-                let main_state = if stream != self.main_stream {
-                    Some(self.main_stream.state.lock())
+                let main_state = if stream != group.main_stream {
+                    Some(group.main_stream.state.lock())
                 } else {
                     None
                 };
@@ -1552,21 +1772,22 @@ impl ToggleRecord {
                     .map_or(false, |main_state| main_state.eos);
 
                 if !main_is_eos {
-                    let mut rec_state = self.state.lock();
+                    let mut rec_state = group.state.lock();
                     recording_state_changed = self.check_and_update_stream_start(
                         pad,
+                        &group,
                         &stream,
                         &mut state,
                         &mut rec_state,
                     );
                 }
 
-                self.main_stream_cond.notify_all();
+                group.main_stream_cond.notify_all();
                 gst::debug!(CAT, obj: pad, "Stream is not EOS now");
             }
             EventView::Eos(..) => {
-                let main_state = if stream != self.main_stream {
-                    Some(self.main_stream.state.lock())
+                let main_state = if stream != group.main_stream {
+                    Some(group.main_stream.state.lock())
                 } else {
                     None
                 };
@@ -1578,12 +1799,12 @@ impl ToggleRecord {
                     .map_or(true, |main_state| main_state.eos);
 
                 if main_is_eos {
-                    let mut rec_state = self.state.lock();
+                    let mut rec_state = group.state.lock();
                     recording_state_changed =
-                        self.check_and_update_eos(pad, &stream, &mut state, &mut rec_state);
+                        self.check_and_update_eos(pad, &group, &stream, &mut state, &mut rec_state);
                 }
 
-                self.main_stream_cond.notify_all();
+                group.main_stream_cond.notify_all();
                 gst::debug!(
                     CAT,
                     obj: pad,
@@ -1596,7 +1817,7 @@ impl ToggleRecord {
         };
 
         if recording_state_changed {
-            self.obj().notify("recording");
+            self.notify_recording(&group);
         }
 
         // If a serialized event and coming after Segment and a new Segment is pending,
@@ -1655,7 +1876,7 @@ impl ToggleRecord {
                 );
                 return false;
             }
-            Some(stream) => stream.clone(),
+            Some((stream, _)) => stream.clone(),
         };
 
         gst::log!(CAT, obj: pad, "Handling query {:?}", query);
@@ -1681,7 +1902,7 @@ impl ToggleRecord {
     fn src_event(&self, pad: &gst::Pad, mut event: gst::Event) -> bool {
         use gst::EventView;
 
-        let stream = match self.pads.lock().get(pad) {
+        let (stream, group) = match self.pads.lock().get(pad) {
             None => {
                 gst::element_imp_error!(
                     self,
@@ -1690,14 +1911,14 @@ impl ToggleRecord {
                 );
                 return false;
             }
-            Some(stream) => stream.clone(),
+            Some((stream, group)) => (stream.clone(), group.clone()),
         };
 
         gst::log!(CAT, obj: pad, "Handling event {:?}", event);
 
         let forward = !matches!(event.view(), EventView::Seek(..));
 
-        let rec_state = self.state.lock();
+        let rec_state = group.state.lock();
         let offset = event.running_time_offset();
         event
             .make_mut()
@@ -1716,7 +1937,7 @@ impl ToggleRecord {
     fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
         use gst::QueryViewMut;
 
-        let stream = match self.pads.lock().get(pad) {
+        let (stream, group) = match self.pads.lock().get(pad) {
             None => {
                 gst::element_imp_error!(
                     self,
@@ -1725,7 +1946,7 @@ impl ToggleRecord {
                 );
                 return false;
             }
-            Some(stream) => stream.clone(),
+            Some((stream, group)) => (stream.clone(), group.clone()),
         };
 
         gst::log!(CAT, obj: pad, "Handling query {:?}", query);
@@ -1768,7 +1989,7 @@ impl ToggleRecord {
             QueryViewMut::Position(q) => {
                 if q.format() == gst::Format::Time {
                     let state = stream.state.lock();
-                    let rec_state = self.state.lock();
+                    let rec_state = group.state.lock();
                     let mut recording_duration = rec_state.recording_duration;
                     if rec_state.recording_state == RecordingState::Recording
                         || rec_state.recording_state == RecordingState::Stopping
@@ -1802,7 +2023,7 @@ impl ToggleRecord {
             QueryViewMut::Duration(q) => {
                 if q.format() == gst::Format::Time {
                     let state = stream.state.lock();
-                    let rec_state = self.state.lock();
+                    let rec_state = group.state.lock();
                     let mut recording_duration = rec_state.recording_duration;
                     if rec_state.recording_state == RecordingState::Recording
                         || rec_state.recording_state == RecordingState::Stopping
@@ -1850,7 +2071,7 @@ impl ToggleRecord {
                 );
                 return gst::Iterator::from_vec(vec![]);
             }
-            Some(stream) => stream.clone(),
+            Some((stream, _)) => stream.clone(),
         };
 
         if pad == &stream.srcpad {
@@ -1926,17 +2147,23 @@ impl ObjectSubclass for ToggleRecord {
             .build();
 
         let main_stream = Stream::new(sinkpad, srcpad);
+        let default_group = Arc::new(Group::new(None, main_stream.clone()));
 
         let mut pads = HashMap::new();
-        pads.insert(main_stream.sinkpad.clone(), main_stream.clone());
-        pads.insert(main_stream.srcpad.clone(), main_stream.clone());
+        pads.insert(
+            main_stream.sinkpad.clone(),
+            (main_stream.clone(), default_group.clone()),
+        );
+        pads.insert(
+            main_stream.srcpad.clone(),
+            (main_stream, default_group.clone()),
+        );
 
         Self {
             settings: Mutex::new(Settings::default()),
-            state: Mutex::new(State::default()),
-            main_stream,
-            main_stream_cond: Condvar::new(),
-            other_streams: Mutex::new((Vec::new(), 0)),
+            default_group,
+            groups: Mutex::new(HashMap::new()),
+            pad_count: Mutex::new(0),
             pads: Mutex::new(pads),
         }
     }
@@ -1974,21 +2201,50 @@ impl ObjectImpl for ToggleRecord {
         PROPERTIES.as_ref()
     }
 
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![
+                glib::subclass::Signal::builder("set-group-record")
+                    .param_types([String::static_type(), bool::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::ToggleRecord>().expect("signal arg");
+                        let group = args[1].get::<String>().expect("signal arg");
+                        let record = args[2].get::<bool>().expect("signal arg");
+
+                        Some(elem.imp().set_group_record(Some(&group), record).to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("is-group-recording")
+                    .param_types([String::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::ToggleRecord>().expect("signal arg");
+                        let group = args[1].get::<String>().expect("signal arg");
+
+                        let recording = elem.imp().group(Some(&group)).map_or(false, |group| {
+                            group.state.lock().recording_state == RecordingState::Recording
+                        });
+
+                        Some(recording.to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("group-recording-changed")
+                    .param_types([String::static_type(), bool::static_type()])
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
         match pspec.name() {
             "record" => {
-                let mut settings = self.settings.lock();
                 let record = value.get().expect("type checked upstream");
-                gst::debug!(
-                    CAT,
-                    imp: self,
-                    "Setting record from {:?} to {:?}",
-                    settings.record,
-                    record
-                );
-
-                settings.record = record;
-                self.main_stream_cond.notify_all();
+                self.set_group_record(None, record);
             }
             "is-live" => {
                 let mut settings = self.settings.lock();
@@ -2010,11 +2266,11 @@ impl ObjectImpl for ToggleRecord {
     fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
         match pspec.name() {
             "record" => {
-                let settings = self.settings.lock();
-                settings.record.to_value()
+                let record = self.default_group.record.lock();
+                record.to_value()
             }
             "recording" => {
-                let rec_state = self.state.lock();
+                let rec_state = self.default_group.state.lock();
                 (rec_state.recording_state == RecordingState::Recording).to_value()
             }
             "is-live" => {
@@ -2029,8 +2285,9 @@ impl ObjectImpl for ToggleRecord {
         self.parent_constructed();
 
         let obj = self.obj();
-        obj.add_pad(&self.main_stream.sinkpad).unwrap();
-        obj.add_pad(&self.main_stream.srcpad).unwrap();
+        obj.add_pad(&self.default_group.main_stream.sinkpad)
+            .unwrap();
+        obj.add_pad(&self.default_group.main_stream.srcpad).unwrap();
     }
 }
 
@@ -2079,11 +2336,12 @@ impl ElementImpl for ToggleRecord {
             )
             .unwrap();
 
-            let secondary_sink_pad_template = gst::PadTemplate::new(
+            let secondary_sink_pad_template = gst::PadTemplate::with_gtype(
                 "sink_%u",
                 gst::PadDirection::Sink,
                 gst::PadPresence::Request,
                 &caps,
+                super::ToggleRecordSinkPad::static_type(),
             )
             .unwrap();
 
@@ -2106,32 +2364,30 @@ impl ElementImpl for ToggleRecord {
 
         match transition {
             gst::StateChange::ReadyToPaused => {
-                for s in self
-                    .other_streams
-                    .lock()
-                    .0
-                    .iter()
-                    .chain(iter::once(&self.main_stream))
-                {
-                    let mut state = s.state.lock();
-                    *state = StreamState::default();
-                }
+                let settings = *self.settings.lock();
 
-                let mut rec_state = self.state.lock();
-                *rec_state = State::default();
+                for group in self.all_groups() {
+                    for s in group.streams() {
+                        let mut state = s.state.lock();
+                        *state = StreamState::default();
+                    }
 
-                let settings = *self.settings.lock();
-                rec_state.live = settings.live;
+                    let mut rec_state = group.state.lock();
+                    *rec_state = State::default();
+                    rec_state.live = settings.live;
+                }
             }
             gst::StateChange::PausedToReady => {
-                for s in &self.other_streams.lock().0 {
-                    let mut state = s.state.lock();
+                for group in self.all_groups() {
+                    for s in group.other_streams.lock().iter() {
+                        let mut state = s.state.lock();
+                        state.flushing = true;
+                    }
+
+                    let mut state = group.main_stream.state.lock();
                     state.flushing = true;
+                    group.main_stream_cond.notify_all();
                 }
-
-                let mut state = self.main_stream.state.lock();
-                state.flushing = true;
-                self.main_stream_cond.notify_all();
             }
             _ => (),
         }
@@ -2139,22 +2395,18 @@ impl ElementImpl for ToggleRecord {
         let success = self.parent_change_state(transition)?;
 
         if transition == gst::StateChange::PausedToReady {
-            for s in self
-                .other_streams
-                .lock()
-                .0
-                .iter()
-                .chain(iter::once(&self.main_stream))
-            {
-                let mut state = s.state.lock();
+            for group in self.all_groups() {
+                for s in group.streams() {
+                    let mut state = s.state.lock();
 
-                state.pending_events.clear();
-            }
+                    state.pending_events.clear();
+                }
 
-            let mut rec_state = self.state.lock();
-            *rec_state = State::default();
-            drop(rec_state);
-            self.obj().notify("recording");
+                let mut rec_state = group.state.lock();
+                *rec_state = State::default();
+                drop(rec_state);
+                self.notify_recording(&group);
+            }
         }
 
         Ok(success)
@@ -2166,45 +2418,48 @@ impl ElementImpl for ToggleRecord {
         _name: Option<&str>,
         _caps: Option<&gst::Caps>,
     ) -> Option<gst::Pad> {
-        let mut other_streams_guard = self.other_streams.lock();
-        let (ref mut other_streams, ref mut pad_count) = *other_streams_guard;
         let mut pads = self.pads.lock();
 
-        let id = *pad_count;
-        *pad_count += 1;
+        let id = {
+            let mut pad_count = self.pad_count.lock();
+            let id = *pad_count;
+            *pad_count += 1;
+            id
+        };
 
         let templ = self.obj().pad_template("sink_%u").unwrap();
-        let sinkpad = gst::Pad::builder_from_template(&templ)
+        let sinkpad = gst::PadBuilder::<super::ToggleRecordSinkPad>::from_template(&templ)
             .name(format!("sink_{id}").as_str())
             .chain_function(|pad, parent, buffer| {
                 ToggleRecord::catch_panic_pad_function(
                     parent,
                     || Err(gst::FlowError::Error),
-                    |togglerecord| togglerecord.sink_chain(pad, buffer),
+                    |togglerecord| togglerecord.sink_chain(pad.upcast_ref(), buffer),
                 )
             })
             .event_function(|pad, parent, event| {
                 ToggleRecord::catch_panic_pad_function(
                     parent,
                     || false,
-                    |togglerecord| togglerecord.sink_event(pad, event),
+                    |togglerecord| togglerecord.sink_event(pad.upcast_ref(), event),
                 )
             })
             .query_function(|pad, parent, query| {
                 ToggleRecord::catch_panic_pad_function(
                     parent,
                     || false,
-                    |togglerecord| togglerecord.sink_query(pad, query),
+                    |togglerecord| togglerecord.sink_query(pad.upcast_ref(), query),
                 )
             })
             .iterate_internal_links_function(|pad, parent| {
                 ToggleRecord::catch_panic_pad_function(
                     parent,
                     || gst::Iterator::from_vec(vec![]),
-                    |togglerecord| togglerecord.iterate_internal_links(pad),
+                    |togglerecord| togglerecord.iterate_internal_links(pad.upcast_ref()),
                 )
             })
-            .build();
+            .build()
+            .upcast::<gst::Pad>();
 
         let templ = self.obj().pad_template("src_%u").unwrap();
         let srcpad = gst::Pad::builder_from_template(&templ)
@@ -2237,13 +2492,18 @@ impl ElementImpl for ToggleRecord {
 
         let stream = Stream::new(sinkpad.clone(), srcpad.clone());
 
-        pads.insert(stream.sinkpad.clone(), stream.clone());
-        pads.insert(stream.srcpad.clone(), stream.clone());
+        pads.insert(
+            stream.sinkpad.clone(),
+            (stream.clone(), self.default_group.clone()),
+        );
+        pads.insert(
+            stream.srcpad.clone(),
+            (stream.clone(), self.default_group.clone()),
+        );
 
-        other_streams.push(stream);
+        self.default_group.other_streams.lock().push(stream);
 
         drop(pads);
-        drop(other_streams_guard);
 
         self.obj().add_pad(&sinkpad).unwrap();
         self.obj().add_pad(&srcpad).unwrap();
@@ -2252,27 +2512,31 @@ impl ElementImpl for ToggleRecord {
     }
 
     fn release_pad(&self, pad: &gst::Pad) {
-        let mut other_streams_guard = self.other_streams.lock();
-        let (ref mut other_streams, _) = *other_streams_guard;
+        let mut groups = self.groups.lock();
         let mut pads = self.pads.lock();
 
-        let stream = match pads.get(pad) {
+        let (stream, group) = match pads.get(pad) {
             None => return,
-            Some(stream) => stream.clone(),
+            Some((stream, group)) => (stream.clone(), group.clone()),
         };
 
         pads.remove(&stream.sinkpad).unwrap();
         pads.remove(&stream.srcpad).unwrap();
 
-        // TODO: Replace with Vec::remove_item() once stable
-        let pos = other_streams.iter().position(|x| *x == stream);
-        pos.map(|pos| other_streams.swap_remove(pos));
+        if stream == group.main_stream {
+            // Releasing the main stream ends the group, let the secondary streams go EOS
+            gst::debug!(CAT, obj: pad, "Removing group {:?}", group.name);
+            groups.remove(group.name.as_ref().expect("named group"));
+            stream.state.lock().eos = true;
+        } else {
+            group.other_streams.lock().retain(|s| *s != stream);
+        }
 
         drop(pads);
-        drop(other_streams_guard);
+        drop(groups);
 
-        let main_state = self.main_stream.state.lock();
-        self.main_stream_cond.notify_all();
+        let main_state = group.main_stream.state.lock();
+        group.main_stream_cond.notify_all();
         drop(main_state);
 
         stream.srcpad.set_active(false).unwrap();
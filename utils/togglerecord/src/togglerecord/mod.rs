@@ -15,7 +15,14 @@ glib::wrapper! {
     pub struct ToggleRecord(ObjectSubclass<imp::ToggleRecord>) @extends gst::Element, gst::Object;
 }
 
+glib::wrapper! {
+    pub struct ToggleRecordSinkPad(ObjectSubclass<imp::ToggleRecordSinkPad>) @extends gst::Pad, gst::Object;
+}
+
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    ToggleRecordSinkPad::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
     gst::Element::register(
         Some(plugin),
         "togglerecord",
@@ -43,6 +43,23 @@ fn setup_sender_receiver(
     mpsc::Receiver<()>,
     mpsc::Receiver<Either<gst::Buffer, gst::Event>>,
     thread::JoinHandle<()>,
+) {
+    setup_sender_receiver_with_group(pipeline, togglerecord, pad, None, offset, live)
+}
+
+#[allow(clippy::type_complexity)]
+fn setup_sender_receiver_with_group(
+    pipeline: &gst::Pipeline,
+    togglerecord: &gst::Element,
+    pad: &str,
+    group: Option<&str>,
+    offset: gst::ClockTime,
+    live: bool,
+) -> (
+    mpsc::Sender<SendData>,
+    mpsc::Receiver<()>,
+    mpsc::Receiver<Either<gst::Buffer, gst::Event>>,
+    thread::JoinHandle<()>,
 ) {
     let fakesink = gst::ElementFactory::make("fakesink")
         .property("async", false)
@@ -59,6 +76,9 @@ fn setup_sender_receiver(
         )
     } else {
         let sinkpad = togglerecord.request_pad_simple("sink_%u").unwrap();
+        if let Some(group) = group {
+            sinkpad.set_property("group", group);
+        }
         let srcpad = sinkpad.iterate_internal_links().next().unwrap().unwrap();
         (srcpad, sinkpad)
     };
@@ -2258,3 +2278,87 @@ fn test_three_stream_secondary_eos_first() {
 
     pipeline.set_state(gst::State::Null).unwrap();
 }
+
+#[test]
+fn test_groups_independent() {
+    init();
+
+    let pipeline = gst::Pipeline::default();
+    let togglerecord = gst::ElementFactory::make("togglerecord").build().unwrap();
+    pipeline.add(&togglerecord).unwrap();
+
+    let (sender_input_1, receiver_input_done_1, receiver_output_1, thread_1) =
+        setup_sender_receiver(&pipeline, &togglerecord, "src", gst::ClockTime::ZERO, true);
+    let (sender_input_2, receiver_input_done_2, receiver_output_2, thread_2) =
+        setup_sender_receiver_with_group(
+            &pipeline,
+            &togglerecord,
+            "src_%u",
+            Some("group"),
+            gst::ClockTime::ZERO,
+            true,
+        );
+    let (sender_input_3, receiver_input_done_3, receiver_output_3, thread_3) =
+        setup_sender_receiver_with_group(
+            &pipeline,
+            &togglerecord,
+            "src_%u",
+            Some("group"),
+            gst::ClockTime::ZERO,
+            true,
+        );
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    // Only record the second group, the default group stays idle
+    assert!(togglerecord.emit_by_name::<bool>("set-group-record", &[&"group", &true]));
+    assert!(!togglerecord.emit_by_name::<bool>("set-group-record", &[&"unknown", &true]));
+
+    sender_input_1.send(SendData::Buffers(10)).unwrap();
+    sender_input_2.send(SendData::Buffers(10)).unwrap();
+    sender_input_3.send(SendData::Buffers(11)).unwrap();
+    receiver_input_done_1.recv().unwrap();
+    receiver_input_done_2.recv().unwrap();
+
+    assert!(!togglerecord.property::<bool>("recording"));
+    assert!(togglerecord.emit_by_name::<bool>("is-group-recording", &[&"group"]));
+
+    sender_input_1.send(SendData::Eos).unwrap();
+    receiver_input_done_1.recv().unwrap();
+    sender_input_2.send(SendData::Eos).unwrap();
+    receiver_input_done_2.recv().unwrap();
+    sender_input_3.send(SendData::Eos).unwrap();
+    receiver_input_done_3.recv().unwrap();
+    receiver_input_done_3.recv().unwrap();
+
+    let mut segment_1 = gst::FormattedSegment::<gst::ClockTime>::new();
+    let (buffers_1, _) = recv_buffers(&receiver_output_1, &mut segment_1, 0);
+    assert!(buffers_1.is_empty());
+
+    let mut segment_2 = gst::FormattedSegment::<gst::ClockTime>::new();
+    let (buffers_2, _) = recv_buffers(&receiver_output_2, &mut segment_2, 0);
+    for (index, &(running_time, pts, duration)) in buffers_2.iter().enumerate() {
+        let index = index as u64;
+        assert_eq!(running_time.unwrap(), index * 20.mseconds());
+        assert_eq!(pts.unwrap(), index * 20.mseconds());
+        assert_eq!(duration.unwrap(), 20.mseconds());
+    }
+    assert_eq!(buffers_2.len(), 10);
+
+    // Last buffer should be dropped from the secondary stream of the group
+    let mut segment_3 = gst::FormattedSegment::<gst::ClockTime>::new();
+    let (buffers_3, _) = recv_buffers(&receiver_output_3, &mut segment_3, 0);
+    for (index, &(running_time, pts, duration)) in buffers_3.iter().enumerate() {
+        let index = index as u64;
+        assert_eq!(running_time.unwrap(), index * 20.mseconds());
+        assert_eq!(pts.unwrap(), index * 20.mseconds());
+        assert_eq!(duration.unwrap(), 20.mseconds());
+    }
+    assert_eq!(buffers_3.len(), 10);
+
+    thread_1.join().unwrap();
+    thread_2.join().unwrap();
+    thread_3.join().unwrap();
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
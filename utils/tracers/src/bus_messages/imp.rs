@@ -0,0 +1,363 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * tracer-bus-messages:
+ *
+ * This tracer records all messages posted by elements, e.g. errors, warnings, state changes and
+ * element messages, to give context for post-mortem analysis of any pipeline without having to
+ * modify the application.
+ *
+ * Example:
+ *
+ * ```console
+ * $ GST_TRACERS='bus-messages(file="/tmp/bus_messages.log",mode=ring-buffer)' gst-launch-1.0 audiotestsrc ! fakesink
+ * ```
+ *
+ * The generated file contains one JSON object per line and message, for example
+ *
+ * ```json
+ * {"ts":1234567,"type":"state-changed","src":"/GstPipeline:pipeline0/GstFakeSink:fakesink0","seqnum":42,"old":"Ready","new":"Paused","pending":"VoidPending"}
+ * ```
+ *
+ * Every line contains the tracer timestamp in nanoseconds (`ts`), the message type (`type`), the
+ * path of the source object (`src`) and the sequence number (`seqnum`) of the message.
+ * Error, warning and info messages additionally contain the error `message` and `debug`
+ * information, state changed messages the `old`, `new` and `pending` states, and all other
+ * messages their serialized `structure` if they have one.
+ *
+ * Messages that are forwarded by bins to their parent are only recorded once, and messages that
+ * are posted directly on a bus instead of by an element are not recorded.
+ *
+ * ## Parameters
+ *
+ * ### `file`
+ *
+ * Specifies the path to the file that will collect the messages.
+ *
+ * By default the file is written to `/tmp/bus_messages.log`.
+ *
+ * ### `mode`
+ *
+ * Either `file` for writing every message to the file immediately, or `ring-buffer` for keeping
+ * only the last messages in memory and writing them to the file whenever an error message is
+ * posted.
+ *
+ * By default this is `file`.
+ *
+ * ### `ring-buffer-size`
+ *
+ * Number of messages kept in memory in `ring-buffer` mode.
+ *
+ * By default this is 100.
+ *
+ * ### `include-filter`
+ *
+ * Specifies a regular expression for the source object paths that should be included.
+ *
+ * By default this is not set.
+ *
+ * ### `exclude-filter`
+ *
+ * Specifies a regular expression for the source object paths that should **not** be included.
+ *
+ * By default this is not set.
+ */
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::LineWriter;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use gst::glib;
+use gst::glib::translate::IntoGlib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "bus-messages",
+        gst::DebugColorFlags::empty(),
+        Some("Tracer to record bus messages"),
+    )
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    File,
+    RingBuffer,
+}
+
+#[derive(Debug)]
+struct Settings {
+    file: PathBuf,
+    mode: Mode,
+    ring_buffer_size: usize,
+    include_filter: Option<Regex>,
+    exclude_filter: Option<Regex>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let mut file = glib::tmp_dir();
+        file.push("bus_messages.log");
+
+        Self {
+            file,
+            mode: Mode::File,
+            ring_buffer_size: 100,
+            include_filter: None,
+            exclude_filter: None,
+        }
+    }
+}
+
+impl Settings {
+    fn update_from_params(&mut self, imp: &BusMessages, params: String) {
+        let s = match gst::Structure::from_str(&format!("bus-messages,{params}")) {
+            Ok(s) => s,
+            Err(err) => {
+                gst::warning!(CAT, imp: imp, "failed to parse tracer parameters: {}", err);
+                return;
+            }
+        };
+
+        if let Ok(file) = s.get::<&str>("file") {
+            gst::log!(CAT, imp: imp, "file= {}", file);
+            self.file = PathBuf::from(file);
+        }
+
+        if let Ok(mode) = s.get::<&str>("mode") {
+            gst::log!(CAT, imp: imp, "mode= {}", mode);
+            match mode {
+                "file" => self.mode = Mode::File,
+                "ring-buffer" => self.mode = Mode::RingBuffer,
+                _ => gst::error!(CAT, imp: imp, "Unsupported mode {}", mode),
+            }
+        }
+
+        if let Ok(size) = s.get::<i32>("ring-buffer-size") {
+            gst::log!(CAT, imp: imp, "ring-buffer-size= {}", size);
+            if size > 0 {
+                self.ring_buffer_size = size as usize;
+            } else {
+                gst::error!(CAT, imp: imp, "Invalid ring-buffer-size {}", size);
+            }
+        }
+
+        if let Ok(filter) = s.get::<&str>("include-filter") {
+            gst::log!(CAT, imp: imp, "include filter= {}", filter);
+            let filter = match Regex::new(filter) {
+                Ok(filter) => Some(filter),
+                Err(err) => {
+                    gst::error!(
+                        CAT,
+                        imp: imp,
+                        "Failed to compile include-filter regex: {}",
+                        err
+                    );
+                    None
+                }
+            };
+            self.include_filter = filter;
+        }
+
+        if let Ok(filter) = s.get::<&str>("exclude-filter") {
+            gst::log!(CAT, imp: imp, "exclude filter= {}", filter);
+            let filter = match Regex::new(filter) {
+                Ok(filter) => Some(filter),
+                Err(err) => {
+                    gst::error!(
+                        CAT,
+                        imp: imp,
+                        "Failed to compile exclude-filter regex: {}",
+                        err
+                    );
+                    None
+                }
+            };
+            self.exclude_filter = filter;
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    settings: Settings,
+    /// Output file, `None` if it could not be created
+    file: Option<LineWriter<File>>,
+    /// Last messages in ring buffer mode
+    ring_buffer: VecDeque<String>,
+}
+
+impl State {
+    fn write(&mut self, imp: &BusMessages, line: &str) {
+        let Some(ref mut file) = self.file else {
+            return;
+        };
+
+        if let Err(err) = writeln!(file, "{line}") {
+            gst::error!(CAT, imp: imp, "Failed to write to file: {err}");
+            self.file = None;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BusMessages {
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for BusMessages {
+    const NAME: &'static str = "GstBusMessages";
+    type Type = super::BusMessages;
+    type ParentType = gst::Tracer;
+}
+
+impl ObjectImpl for BusMessages {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(params) = self.obj().property::<Option<String>>("params") {
+            state.settings.update_from_params(self, params);
+        }
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Writing file {}",
+            state.settings.file.display()
+        );
+
+        state.file = match File::create(&state.settings.file) {
+            Ok(file) => Some(LineWriter::new(file)),
+            Err(err) => {
+                gst::error!(CAT, imp: self, "Failed to create file: {err}");
+                None
+            }
+        };
+        drop(state);
+
+        self.register_hook(TracerHook::ElementPostMessagePre);
+    }
+}
+
+impl GstObjectImpl for BusMessages {}
+
+impl TracerImpl for BusMessages {
+    fn element_post_message_pre(&self, ts: u64, element: &gst::Element, msg: &gst::Message) {
+        // Bins post the messages of their children again on themselves, only record the
+        // message when it is posted by its source
+        let Some(src) = msg.src() else {
+            return;
+        };
+        if src != element.upcast_ref::<gst::Object>() {
+            return;
+        }
+
+        let path = src.path_string();
+
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(ref filter) = state.settings.include_filter {
+            if !filter.is_match(&path) {
+                return;
+            }
+        }
+        if let Some(ref filter) = state.settings.exclude_filter {
+            if filter.is_match(&path) {
+                return;
+            }
+        }
+
+        let line = message_to_json(ts, &path, msg).to_string();
+
+        match state.settings.mode {
+            Mode::File => state.write(self, &line),
+            Mode::RingBuffer => {
+                if state.ring_buffer.len() >= state.settings.ring_buffer_size {
+                    state.ring_buffer.pop_front();
+                }
+                state.ring_buffer.push_back(line);
+
+                if msg.type_() == gst::MessageType::Error {
+                    gst::debug!(
+                        CAT,
+                        imp: self,
+                        "Dumping {} messages after error from {}",
+                        state.ring_buffer.len(),
+                        path
+                    );
+
+                    let lines = std::mem::take(&mut state.ring_buffer);
+                    for line in lines {
+                        state.write(self, &line);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn message_to_json(ts: u64, path: &str, msg: &gst::Message) -> serde_json::Value {
+    // SAFETY: Returns a static string for every message type
+    let type_ = unsafe {
+        std::ffi::CStr::from_ptr(gst::ffi::gst_message_type_get_name(msg.type_().into_glib()))
+            .to_string_lossy()
+    };
+
+    let mut json = serde_json::json!({
+        "ts": ts,
+        "type": type_,
+        "src": path,
+        "seqnum": msg.seqnum().into_glib(),
+    });
+    let obj = json.as_object_mut().unwrap();
+
+    use gst::MessageView;
+    match msg.view() {
+        MessageView::Error(err) => {
+            obj.insert("message".into(), err.error().to_string().into());
+            obj.insert("debug".into(), err.debug().map(|d| d.to_string()).into());
+        }
+        MessageView::Warning(warning) => {
+            obj.insert("message".into(), warning.error().to_string().into());
+            obj.insert(
+                "debug".into(),
+                warning.debug().map(|d| d.to_string()).into(),
+            );
+        }
+        MessageView::Info(info) => {
+            obj.insert("message".into(), info.error().to_string().into());
+            obj.insert("debug".into(), info.debug().map(|d| d.to_string()).into());
+        }
+        MessageView::StateChanged(state_changed) => {
+            obj.insert("old".into(), format!("{:?}", state_changed.old()).into());
+            obj.insert(
+                "new".into(),
+                format!("{:?}", state_changed.current()).into(),
+            );
+            obj.insert(
+                "pending".into(),
+                format!("{:?}", state_changed.pending()).into(),
+            );
+        }
+        _ => {
+            if let Some(s) = msg.structure() {
+                obj.insert("structure".into(), s.to_string().into());
+            }
+        }
+    }
+
+    json
+}
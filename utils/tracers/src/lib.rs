@@ -15,6 +15,8 @@
 use gst::glib;
 
 mod buffer_lateness;
+mod bus_messages;
+mod metrics_export;
 mod pad_push_timings;
 #[cfg(unix)]
 mod pipeline_snapshot;
@@ -25,7 +27,9 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     pipeline_snapshot::register(plugin)?;
     queue_levels::register(plugin)?;
     buffer_lateness::register(plugin)?;
+    bus_messages::register(plugin)?;
     pad_push_timings::register(plugin)?;
+    metrics_export::register(plugin)?;
     Ok(())
 }
 
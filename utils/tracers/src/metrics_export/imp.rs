@@ -0,0 +1,730 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * tracer-metrics-export:
+ *
+ * This tracer periodically pushes aggregated pipeline metrics to a statsd or Graphite server.
+ *
+ * Example:
+ *
+ * ```console
+ * $ GST_TRACERS='metrics-export(protocol=graphite,host=graphite.local,prefix=encoder1)' gst-launch-1.0 audiotestsrc is-live=true ! queue ! fakesink
+ * ```
+ *
+ * The following metrics are collected for each source pad and reported for each flush interval
+ * below `<prefix>.<metric>.<element>.<pad>`:
+ *
+ * - `push-duration`: time spent in pushing buffers and buffer lists downstream, in nanoseconds.
+ *   Reported as `count` counter and `mean` and `max` gauges.
+ * - `lateness`: lateness of buffers compared to the pipeline clock when they are pushed, in
+ *   nanoseconds. Only reported for live pipelines, as `count` counter and `mean` and `max` gauges.
+ *
+ * Additionally the last fill level of each `queue` and `queue2` is reported below
+ * `<prefix>.queue-level.<element>` as `bytes`, `time` (in nanoseconds) and `buffers` gauges.
+ *
+ * Characters other than ASCII letters, digits, `-` and `_` in element and pad names are replaced
+ * by `_`.
+ *
+ * ## Parameters
+ *
+ * ### `protocol`
+ *
+ * Either `statsd` for sending the metrics via UDP in the statsd line protocol, or `graphite` for
+ * sending them via TCP in the Graphite plaintext protocol.
+ *
+ * By default this is `statsd`.
+ *
+ * ### `host`
+ *
+ * Host name or address of the server.
+ *
+ * By default this is `localhost`.
+ *
+ * ### `port`
+ *
+ * Port of the server.
+ *
+ * By default this is 8125 for statsd and 2003 for Graphite.
+ *
+ * ### `prefix`
+ *
+ * Prefix for all metric names.
+ *
+ * By default this is `gstreamer`.
+ *
+ * ### `flush-interval`
+ *
+ * Interval in milliseconds in which the metrics are aggregated and sent to the server.
+ *
+ * By default this is 10000.
+ *
+ * ### `include-filter`
+ *
+ * Specifies a regular expression for the `element:pad` names that should be included.
+ *
+ * By default this is not set.
+ *
+ * ### `exclude-filter`
+ *
+ * Specifies a regular expression for the `element:pad` names that should **not** be included.
+ *
+ * By default this is not set.
+ */
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "metrics-export",
+        gst::DebugColorFlags::empty(),
+        Some("Tracer to export metrics to statsd or Graphite"),
+    )
+});
+
+// Maximum payload size of a single statsd datagram to avoid fragmentation
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Statsd,
+    Graphite,
+}
+
+#[derive(Debug)]
+struct Settings {
+    protocol: Protocol,
+    host: String,
+    port: Option<u16>,
+    prefix: String,
+    flush_interval: Duration,
+    include_filter: Option<Regex>,
+    exclude_filter: Option<Regex>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            protocol: Protocol::Statsd,
+            host: String::from("localhost"),
+            port: None,
+            prefix: String::from("gstreamer"),
+            flush_interval: Duration::from_secs(10),
+            include_filter: None,
+            exclude_filter: None,
+        }
+    }
+}
+
+impl Settings {
+    fn update_from_params(&mut self, imp: &MetricsExport, params: String) {
+        let s = match gst::Structure::from_str(&format!("metrics-export,{params}")) {
+            Ok(s) => s,
+            Err(err) => {
+                gst::warning!(CAT, imp: imp, "failed to parse tracer parameters: {}", err);
+                return;
+            }
+        };
+
+        if let Ok(protocol) = s.get::<&str>("protocol") {
+            gst::log!(CAT, imp: imp, "protocol= {}", protocol);
+            match protocol {
+                "statsd" => self.protocol = Protocol::Statsd,
+                "graphite" => self.protocol = Protocol::Graphite,
+                _ => gst::error!(CAT, imp: imp, "Unsupported protocol {}", protocol),
+            }
+        }
+
+        if let Ok(host) = s.get::<&str>("host") {
+            gst::log!(CAT, imp: imp, "host= {}", host);
+            self.host = String::from(host);
+        }
+
+        if let Ok(port) = s.get::<i32>("port") {
+            gst::log!(CAT, imp: imp, "port= {}", port);
+            match u16::try_from(port) {
+                Ok(port) if port != 0 => self.port = Some(port),
+                _ => gst::error!(CAT, imp: imp, "Invalid port {}", port),
+            }
+        }
+
+        if let Ok(prefix) = s.get::<&str>("prefix") {
+            gst::log!(CAT, imp: imp, "prefix= {}", prefix);
+            self.prefix = String::from(prefix);
+        }
+
+        if let Ok(interval) = s.get::<i32>("flush-interval") {
+            gst::log!(CAT, imp: imp, "flush-interval= {}", interval);
+            if interval > 0 {
+                self.flush_interval = Duration::from_millis(interval as u64);
+            } else {
+                gst::error!(CAT, imp: imp, "Invalid flush-interval {}", interval);
+            }
+        }
+
+        if let Ok(filter) = s.get::<&str>("include-filter") {
+            gst::log!(CAT, imp: imp, "include filter= {}", filter);
+            let filter = match Regex::new(filter) {
+                Ok(filter) => Some(filter),
+                Err(err) => {
+                    gst::error!(
+                        CAT,
+                        imp: imp,
+                        "Failed to compile include-filter regex: {}",
+                        err
+                    );
+                    None
+                }
+            };
+            self.include_filter = filter;
+        }
+
+        if let Ok(filter) = s.get::<&str>("exclude-filter") {
+            gst::log!(CAT, imp: imp, "exclude filter= {}", filter);
+            let filter = match Regex::new(filter) {
+                Ok(filter) => Some(filter),
+                Err(err) => {
+                    gst::error!(
+                        CAT,
+                        imp: imp,
+                        "Failed to compile exclude-filter regex: {}",
+                        err
+                    );
+                    None
+                }
+            };
+            self.exclude_filter = filter;
+        }
+    }
+
+    fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.protocol {
+            Protocol::Statsd => 8125,
+            Protocol::Graphite => 2003,
+        })
+    }
+}
+
+struct Pad {
+    /// Metric name of the pad, or `None` if it is filtered out
+    name: Option<Arc<str>>,
+    /// Metric name of the queue if this is the source pad of a queue
+    queue: Option<Arc<str>>,
+    pending_push_start: Option<u64>,
+}
+
+#[derive(Default)]
+struct State {
+    pads: HashMap<usize, Pad>,
+    settings: Settings,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Stat {
+    count: u64,
+    sum: i64,
+    max: i64,
+}
+
+impl Stat {
+    fn add(&mut self, value: i64) {
+        if self.count == 0 || value > self.max {
+            self.max = value;
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct QueueLevel {
+    bytes: u64,
+    time: u64,
+    buffers: u64,
+}
+
+/// Metrics aggregated since the last flush
+#[derive(Debug, Default)]
+struct Metrics {
+    push_durations: HashMap<Arc<str>, Stat>,
+    lateness: HashMap<Arc<str>, Stat>,
+    queue_levels: HashMap<Arc<str>, QueueLevel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetricType {
+    Counter,
+    Gauge,
+}
+
+impl Metrics {
+    fn is_empty(&self) -> bool {
+        self.push_durations.is_empty() && self.lateness.is_empty() && self.queue_levels.is_empty()
+    }
+
+    fn values(&self, prefix: &str) -> Vec<(String, i64, MetricType)> {
+        let mut values = Vec::new();
+
+        for (group, stats) in [
+            ("push-duration", &self.push_durations),
+            ("lateness", &self.lateness),
+        ] {
+            for (name, stat) in stats {
+                values.push((
+                    format!("{prefix}.{group}.{name}.count"),
+                    stat.count as i64,
+                    MetricType::Counter,
+                ));
+                values.push((
+                    format!("{prefix}.{group}.{name}.mean"),
+                    stat.sum / stat.count as i64,
+                    MetricType::Gauge,
+                ));
+                values.push((
+                    format!("{prefix}.{group}.{name}.max"),
+                    stat.max,
+                    MetricType::Gauge,
+                ));
+            }
+        }
+
+        for (name, level) in &self.queue_levels {
+            for (field, value) in [
+                ("bytes", level.bytes),
+                ("time", level.time),
+                ("buffers", level.buffers),
+            ] {
+                values.push((
+                    format!("{prefix}.queue-level.{name}.{field}"),
+                    value as i64,
+                    MetricType::Gauge,
+                ));
+            }
+        }
+
+        values
+    }
+}
+
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Formats the values as statsd lines
+fn format_statsd(values: &[(String, i64, MetricType)]) -> Vec<String> {
+    let mut lines = Vec::with_capacity(values.len());
+    for (name, value, type_) in values {
+        match type_ {
+            MetricType::Counter => lines.push(format!("{name}:{value}|c")),
+            // A leading sign makes a gauge value relative to the previous one, so negative
+            // values have to be sent as a delta from zero
+            MetricType::Gauge if *value < 0 => {
+                lines.push(format!("{name}:0|g"));
+                lines.push(format!("{name}:{value}|g"));
+            }
+            MetricType::Gauge => lines.push(format!("{name}:{value}|g")),
+        }
+    }
+    lines
+}
+
+/// Formats the values as Graphite plaintext protocol lines
+fn format_graphite(values: &[(String, i64, MetricType)], timestamp: u64) -> Vec<String> {
+    values
+        .iter()
+        .map(|(name, value, _)| format!("{name} {value} {timestamp}"))
+        .collect()
+}
+
+enum Output {
+    Statsd {
+        socket: UdpSocket,
+        addr: SocketAddr,
+    },
+    Graphite {
+        addr: SocketAddr,
+        stream: Option<TcpStream>,
+    },
+}
+
+impl Output {
+    fn new(protocol: Protocol, host: &str, port: u16) -> Result<Self, std::io::Error> {
+        let addr = (host, port).to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Can't resolve {host}"),
+            )
+        })?;
+
+        match protocol {
+            Protocol::Statsd => {
+                let socket = if addr.is_ipv4() {
+                    UdpSocket::bind("0.0.0.0:0")?
+                } else {
+                    UdpSocket::bind("[::]:0")?
+                };
+                Ok(Output::Statsd { socket, addr })
+            }
+            Protocol::Graphite => Ok(Output::Graphite { addr, stream: None }),
+        }
+    }
+
+    fn send(&mut self, values: &[(String, i64, MetricType)]) -> Result<(), std::io::Error> {
+        match self {
+            Output::Statsd { socket, addr } => {
+                let mut datagram = String::new();
+                for line in format_statsd(values) {
+                    if !datagram.is_empty() && datagram.len() + line.len() + 1 > MAX_DATAGRAM_SIZE {
+                        socket.send_to(datagram.as_bytes(), *addr)?;
+                        datagram.clear();
+                    }
+                    if !datagram.is_empty() {
+                        datagram.push('\n');
+                    }
+                    datagram.push_str(&line);
+                }
+                if !datagram.is_empty() {
+                    socket.send_to(datagram.as_bytes(), *addr)?;
+                }
+            }
+            Output::Graphite { addr, stream } => {
+                let timestamp = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+
+                let mut data = String::new();
+                for line in format_graphite(values, timestamp) {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+
+                if stream.is_none() {
+                    let s = TcpStream::connect_timeout(addr, Duration::from_secs(5))?;
+                    s.set_write_timeout(Some(Duration::from_secs(5)))?;
+                    *stream = Some(s);
+                }
+
+                // Reconnect on the next flush if the connection was closed
+                if let Err(err) = stream.as_mut().unwrap().write_all(data.as_bytes()) {
+                    *stream = None;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct FlushThread {
+    sender: mpsc::Sender<()>,
+    handle: std::thread::JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct MetricsExport {
+    state: Mutex<State>,
+    metrics: Arc<Mutex<Metrics>>,
+    flush_thread: Mutex<Option<FlushThread>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MetricsExport {
+    const NAME: &'static str = "GstMetricsExport";
+    type Type = super::MetricsExport;
+    type ParentType = gst::Tracer;
+}
+
+impl ObjectImpl for MetricsExport {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        if let Some(params) = self.obj().property::<Option<String>>("params") {
+            let mut state = self.state.lock().unwrap();
+            state.settings.update_from_params(self, params);
+        }
+
+        self.start_flush_thread();
+
+        self.register_hook(TracerHook::PadPushPre);
+        self.register_hook(TracerHook::PadPushListPre);
+        self.register_hook(TracerHook::PadPushPost);
+        self.register_hook(TracerHook::PadPushListPost);
+        self.register_hook(TracerHook::ObjectDestroyed);
+    }
+
+    fn dispose(&self) {
+        // Dropping the sender wakes up the thread, which then sends the remaining metrics
+        if let Some(FlushThread { sender, handle }) = self.flush_thread.lock().unwrap().take() {
+            drop(sender);
+            let _ = handle.join();
+        }
+    }
+}
+
+impl GstObjectImpl for MetricsExport {}
+
+impl TracerImpl for MetricsExport {
+    fn pad_push_pre(&self, ts: u64, pad: &gst::Pad, buffer: &gst::Buffer) {
+        let Some(element) = pad.parent().and_then(|p| p.downcast::<gst::Element>().ok()) else {
+            return;
+        };
+
+        let lateness = Self::lateness(&element, pad, buffer);
+        self.push_pre(ts, pad, &element, lateness);
+    }
+
+    fn pad_push_list_pre(&self, ts: u64, pad: &gst::Pad, list: &gst::BufferList) {
+        let Some(element) = pad.parent().and_then(|p| p.downcast::<gst::Element>().ok()) else {
+            return;
+        };
+
+        let lateness = list
+            .iter()
+            .filter_map(|buffer| Self::lateness(&element, pad, buffer));
+        self.push_pre(ts, pad, &element, lateness);
+    }
+
+    fn pad_push_post(
+        &self,
+        ts: u64,
+        pad: &gst::Pad,
+        _result: Result<gst::FlowSuccess, gst::FlowError>,
+    ) {
+        self.push_post(ts, pad);
+    }
+
+    fn pad_push_list_post(
+        &self,
+        ts: u64,
+        pad: &gst::Pad,
+        _result: Result<gst::FlowSuccess, gst::FlowError>,
+    ) {
+        self.push_post(ts, pad);
+    }
+
+    fn object_destroyed(&self, _ts: u64, object: std::ptr::NonNull<gst::ffi::GstObject>) {
+        let ptr = object.as_ptr() as usize;
+        let mut state = self.state.lock().unwrap();
+        state.pads.remove(&ptr);
+    }
+}
+
+impl MetricsExport {
+    fn start_flush_thread(&self) {
+        let (protocol, host, port, prefix, flush_interval) = {
+            let state = self.state.lock().unwrap();
+            let settings = &state.settings;
+            (
+                settings.protocol,
+                settings.host.clone(),
+                settings.port(),
+                settings.prefix.clone(),
+                settings.flush_interval,
+            )
+        };
+
+        let mut output = match Output::new(protocol, &host, port) {
+            Ok(output) => output,
+            Err(err) => {
+                gst::error!(CAT, imp: self, "Failed to set up output to {host}:{port}: {err}");
+                return;
+            }
+        };
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Sending metrics to {host}:{port} every {flush_interval:?}"
+        );
+
+        let metrics = self.metrics.clone();
+        let (sender, receiver) = mpsc::channel();
+        let res = std::thread::Builder::new()
+            .name("metrics-export".into())
+            .spawn(move || loop {
+                let finished = !matches!(
+                    receiver.recv_timeout(flush_interval),
+                    Err(mpsc::RecvTimeoutError::Timeout)
+                );
+
+                let metrics = std::mem::take(&mut *metrics.lock().unwrap());
+                if !metrics.is_empty() {
+                    if let Err(err) = output.send(&metrics.values(&prefix)) {
+                        gst::warning!(CAT, "Failed to send metrics to {host}:{port}: {err}");
+                    }
+                }
+
+                if finished {
+                    break;
+                }
+            });
+
+        match res {
+            Ok(handle) => {
+                *self.flush_thread.lock().unwrap() = Some(FlushThread { sender, handle });
+            }
+            Err(err) => {
+                gst::error!(CAT, imp: self, "Failed to spawn flush thread: {err}");
+            }
+        }
+    }
+
+    /// Lateness of the buffer compared to the current clock time in live pipelines
+    fn lateness(element: &gst::Element, pad: &gst::Pad, buffer: &gst::BufferRef) -> Option<i64> {
+        let timestamp = buffer.dts_or_pts()?;
+        let clock = element.clock()?;
+
+        let base_time = match element.base_time() {
+            // FIXME: Workaround for base time being set to 0 initially instead of None
+            Some(base_time)
+                if base_time == gst::ClockTime::ZERO && element.start_time().is_some() =>
+            {
+                return None
+            }
+            base_time => base_time?,
+        };
+
+        let segment = pad
+            .sticky_event::<gst::event::Segment>(0)
+            .map(|s| s.segment().clone())
+            .and_then(|s| s.downcast::<gst::ClockTime>().ok())?;
+
+        let buffer_clock_time = segment.to_running_time(timestamp)? + base_time;
+        let clock_time = clock.time()?;
+
+        Some(clock_time.nseconds() as i64 - buffer_clock_time.nseconds() as i64)
+    }
+
+    fn push_pre(
+        &self,
+        ts: u64,
+        pad: &gst::Pad,
+        element: &gst::Element,
+        lateness: impl IntoIterator<Item = i64>,
+    ) {
+        let ptr = pad.as_ptr() as usize;
+
+        let (name, queue) = {
+            let mut state = self.state.lock().unwrap();
+            let State {
+                ref mut pads,
+                ref settings,
+            } = &mut *state;
+
+            let pad = pads.entry(ptr).or_insert_with(|| {
+                let element_name = element.name();
+                let pad_name = pad.name();
+
+                let filter_name = format!("{element_name}:{pad_name}");
+                let mut include = true;
+                if let Some(ref filter) = settings.include_filter {
+                    if !filter.is_match(&filter_name) {
+                        include = false;
+                    }
+                }
+                if let Some(ref filter) = settings.exclude_filter {
+                    if filter.is_match(&filter_name) {
+                        include = false;
+                    }
+                }
+
+                let is_queue = pad.direction() == gst::PadDirection::Src
+                    && element
+                        .factory()
+                        .map_or(false, |f| matches!(f.name().as_str(), "queue" | "queue2"));
+
+                Pad {
+                    name: include.then(|| {
+                        Arc::from(format!(
+                            "{}.{}",
+                            sanitize_name(&element_name),
+                            sanitize_name(&pad_name)
+                        ))
+                    }),
+                    queue: (include && is_queue).then(|| Arc::from(sanitize_name(&element_name))),
+                    pending_push_start: None,
+                }
+            });
+
+            let Some(ref name) = pad.name else {
+                return;
+            };
+
+            pad.pending_push_start = Some(ts);
+            (name.clone(), pad.queue.clone())
+        };
+
+        // Queue levels are read without holding any of our locks as this takes the queue lock
+        let queue_level = queue.map(|queue| {
+            (
+                queue,
+                QueueLevel {
+                    bytes: element.property::<u32>("current-level-bytes") as u64,
+                    time: element.property::<u64>("current-level-time"),
+                    buffers: element.property::<u32>("current-level-buffers") as u64,
+                },
+            )
+        });
+
+        let mut metrics = self.metrics.lock().unwrap();
+        for lateness in lateness {
+            metrics
+                .lateness
+                .entry(name.clone())
+                .or_default()
+                .add(lateness);
+        }
+        if let Some((queue, level)) = queue_level {
+            metrics.queue_levels.insert(queue, level);
+        }
+    }
+
+    fn push_post(&self, ts: u64, pad: &gst::Pad) {
+        let ptr = pad.as_ptr() as usize;
+
+        let (name, push_start) = {
+            let mut state = self.state.lock().unwrap();
+            let Some(pad) = state.pads.get_mut(&ptr) else {
+                return;
+            };
+            let Some(ref name) = pad.name else {
+                return;
+            };
+            let Some(push_start) = pad.pending_push_start.take() else {
+                return;
+            };
+
+            (name.clone(), push_start)
+        };
+
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics
+            .push_durations
+            .entry(name)
+            .or_default()
+            .add(ts.saturating_sub(push_start) as i64);
+    }
+}
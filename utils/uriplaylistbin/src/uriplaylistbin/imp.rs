@@ -31,10 +31,17 @@ enum PlaylistError {
     PluginMissing { error: anyhow::Error },
 }
 
+const DEFAULT_PREFETCH_SIZE: i32 = -1;
+const DEFAULT_PREFETCH_DURATION: i64 = -1;
+const DEFAULT_CACHE: bool = false;
+
 #[derive(Debug, Clone)]
 struct Settings {
     uris: Vec<String>,
     iterations: u32,
+    prefetch_size: i32,
+    prefetch_duration: i64,
+    cache: bool,
 }
 
 impl Default for Settings {
@@ -42,6 +49,9 @@ impl Default for Settings {
         Self {
             uris: vec![],
             iterations: 1,
+            prefetch_size: DEFAULT_PREFETCH_SIZE,
+            prefetch_duration: DEFAULT_PREFETCH_DURATION,
+            cache: DEFAULT_CACHE,
         }
     }
 }
@@ -205,6 +215,26 @@ impl ObjectImpl for UriPlaylistBin {
                     .blurb("The index from the uris property of the current URI being played")
                     .read_only()
                     .build(),
+                glib::ParamSpecInt::builder("prefetch-size")
+                    .nick("Prefetch size")
+                    .blurb("Maximum amount of bytes buffered ahead for network items, allowing the next item to be pre-rolled while the buffered data is played (-1 = default)")
+                    .minimum(-1)
+                    .default_value(DEFAULT_PREFETCH_SIZE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecInt64::builder("prefetch-duration")
+                    .nick("Prefetch duration")
+                    .blurb("Maximum duration in nanoseconds buffered ahead for network items, allowing the next item to be pre-rolled while the buffered data is played (-1 = default)")
+                    .minimum(-1)
+                    .default_value(DEFAULT_PREFETCH_DURATION)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("cache")
+                    .nick("Cache")
+                    .blurb("Download network items to disk instead of buffering them in memory")
+                    .default_value(DEFAULT_CACHE)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -246,6 +276,42 @@ impl ObjectImpl for UriPlaylistBin {
                     }
                 }
             }
+            "prefetch-size" => {
+                let mut settings = self.settings.lock().unwrap();
+                let new_value = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing prefetch-size from {} to {}",
+                    settings.prefetch_size,
+                    new_value,
+                );
+                settings.prefetch_size = new_value;
+            }
+            "prefetch-duration" => {
+                let mut settings = self.settings.lock().unwrap();
+                let new_value = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing prefetch-duration from {} to {}",
+                    settings.prefetch_duration,
+                    new_value,
+                );
+                settings.prefetch_duration = new_value;
+            }
+            "cache" => {
+                let mut settings = self.settings.lock().unwrap();
+                let new_value = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing cache from {} to {}",
+                    settings.cache,
+                    new_value,
+                );
+                settings.cache = new_value;
+            }
             _ => unimplemented!(),
         }
     }
@@ -276,6 +342,18 @@ impl ObjectImpl for UriPlaylistBin {
                     .unwrap_or(0)
                     .to_value()
             }
+            "prefetch-size" => {
+                let settings = self.settings.lock().unwrap();
+                settings.prefetch_size.to_value()
+            }
+            "prefetch-duration" => {
+                let settings = self.settings.lock().unwrap();
+                settings.prefetch_duration.to_value()
+            }
+            "cache" => {
+                let settings = self.settings.lock().unwrap();
+                settings.cache.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -411,8 +489,16 @@ impl UriPlaylistBin {
             let mut state_guard = self.state.lock().unwrap();
             assert!(state_guard.is_none());
 
+            let settings = self.settings.lock().unwrap().clone();
+
+            // The next item is queued when uridecodebin3 is about to finish the current one, which
+            // happens once all its data has been buffered. Buffering ahead thus gives slow remote
+            // items time to pre-roll before the current item is done playing.
             let uridecodebin = gst::ElementFactory::make("uridecodebin3")
                 .name("playlist-uridecodebin")
+                .property("buffer-size", settings.prefetch_size)
+                .property("buffer-duration", settings.prefetch_duration)
+                .property("download", settings.cache)
                 .build()
                 .map_err(|e| PlaylistError::PluginMissing { error: e.into() })?;
 
@@ -521,13 +607,7 @@ impl UriPlaylistBin {
                 }
             });
 
-            let settings = self.settings.lock().unwrap();
-
-            *state_guard = Some(State::new(
-                settings.uris.clone(),
-                settings.iterations,
-                uridecodebin,
-            ));
+            *state_guard = Some(State::new(settings.uris, settings.iterations, uridecodebin));
         }
 
         self.start_next_item()?;
@@ -418,3 +418,33 @@ fn infinite_to_finite() {
     assert_eq!(current_iteration, 3);
     assert_eq!(current_uri_index, 0);
 }
+
+#[test]
+fn prefetch_settings() {
+    init();
+
+    let playlist = gst::ElementFactory::make("uriplaylistbin")
+        .property("uris", vec![TestMedia::ogg().uri])
+        .property("prefetch-size", 4 * 1024 * 1024)
+        .property(
+            "prefetch-duration",
+            10 * gst::ClockTime::SECOND.nseconds() as i64,
+        )
+        .property("cache", true)
+        .build()
+        .unwrap()
+        .downcast::<gst::Bin>()
+        .unwrap();
+
+    playlist.set_state(gst::State::Ready).unwrap();
+
+    let uridecodebin = playlist.by_name("playlist-uridecodebin").unwrap();
+    assert_eq!(uridecodebin.property::<i32>("buffer-size"), 4 * 1024 * 1024);
+    assert_eq!(
+        uridecodebin.property::<i64>("buffer-duration"),
+        10 * gst::ClockTime::SECOND.nseconds() as i64
+    );
+    assert!(uridecodebin.property::<bool>("download"));
+
+    playlist.set_state(gst::State::Null).unwrap();
+}
@@ -62,6 +62,7 @@ struct State {
     framerate: gst::Fraction,
     last_frame_no: u64,
     max_frame_no: u64,
+    json_input: bool,
     force_clear: bool,
 }
 
@@ -72,6 +73,7 @@ impl Default for State {
             framerate: gst::Fraction::new(DEFAULT_FPS_N, DEFAULT_FPS_D),
             last_frame_no: 0,
             max_frame_no: 0,
+            json_input: false,
             force_clear: false,
         }
     }
@@ -202,36 +204,54 @@ impl TtToCea708 {
             clear: Some(state.force_clear),
         };
         state.force_clear = false;
-        let data = std::str::from_utf8(&data).map_err(|err| {
-            gst::error!(CAT, obj: pad, "Can't decode utf8: {}", err);
-
-            gst::FlowError::Error
-        })?;
+        match state.json_input {
+            false => {
+                let data = std::str::from_utf8(&data).map_err(|err| {
+                    gst::error!(CAT, obj: pad, "Can't decode utf8: {}", err);
+
+                    gst::FlowError::Error
+                })?;
+
+                let phrases: Vec<&str> = data.split('\n').collect();
+                let mut row = match settings.origin_row {
+                    -1 => match settings.mode {
+                        Cea708Mode::PopOn | Cea708Mode::PaintOn => {
+                            15u32.saturating_sub(phrases.len() as u32)
+                        }
+                        Cea708Mode::RollUp => 14,
+                    },
+                    _ => settings.origin_row as u32,
+                };
 
-        let phrases: Vec<&str> = data.split('\n').collect();
-        let mut row = match settings.origin_row {
-            -1 => match settings.mode {
-                Cea708Mode::PopOn | Cea708Mode::PaintOn => {
-                    15u32.saturating_sub(phrases.len() as u32)
+                for phrase in &phrases {
+                    lines.lines.push(Line {
+                        carriage_return: None,
+                        column: None,
+                        row: Some(row),
+                        chunks: vec![Chunk {
+                            style: TextStyle::White,
+                            underline: false,
+                            text: phrase.to_string(),
+                        }],
+                    });
+                    if settings.mode == Cea708Mode::PopOn || settings.mode == Cea708Mode::PaintOn {
+                        row += 1;
+                    }
                 }
-                Cea708Mode::RollUp => 14,
-            },
-            _ => settings.origin_row as u32,
-        };
-
-        for phrase in &phrases {
-            lines.lines.push(Line {
-                carriage_return: None,
-                column: None,
-                row: Some(row),
-                chunks: vec![Chunk {
-                    style: TextStyle::White,
-                    underline: false,
-                    text: phrase.to_string(),
-                }],
-            });
-            if settings.mode == Cea708Mode::PopOn || settings.mode == Cea708Mode::PaintOn {
-                row += 1;
+            }
+            true => {
+                let json_lines: Lines = serde_json::from_slice(&data).map_err(|err| {
+                    gst::error!(CAT, obj: pad, "Failed to parse input as json: {}", err);
+
+                    gst::FlowError::Error
+                })?;
+
+                // Fall back to the configured mode when the input doesn't specify one
+                lines = Lines {
+                    mode: json_lines.mode.or(lines.mode),
+                    clear: json_lines.clear.or(lines.clear),
+                    lines: json_lines.lines,
+                };
             }
         }
         drop(settings);
@@ -250,7 +270,7 @@ impl TtToCea708 {
         use gst::EventView;
 
         match event.view() {
-            EventView::Caps(_e) => {
+            EventView::Caps(e) => {
                 let mut downstream_caps = match self.srcpad.allowed_caps() {
                     None => self.srcpad.pad_template_caps(),
                     Some(caps) => caps,
@@ -277,6 +297,10 @@ impl TtToCea708 {
                 state.framerate = framerate;
                 state.translator.set_framerate(framerate);
 
+                let upstream_caps = e.caps();
+                let s = upstream_caps.structure(0).unwrap();
+                state.json_input = s.name() == "application/x-json";
+
                 gst::debug!(CAT, obj: pad, "Pushing caps {}", caps);
 
                 let new_event = gst::event::Caps::new(&caps);
@@ -614,6 +638,11 @@ impl ElementImpl for TtToCea708 {
 
                 let s = gst::Structure::builder("text/x-raw").build();
                 caps.append_structure(s);
+
+                let s = gst::Structure::builder("application/x-json")
+                    .field("format", "cea608")
+                    .build();
+                caps.append_structure(s);
             }
 
             let sink_pad_template = gst::PadTemplate::new(
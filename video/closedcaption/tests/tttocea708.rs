@@ -0,0 +1,160 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst::ClockTime;
+
+use cea708_types::tables::*;
+use cea708_types::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsclosedcaption::plugin_register_static().unwrap();
+    });
+}
+
+fn new_timed_buffer<T: AsRef<[u8]> + Send + 'static>(
+    slice: T,
+    timestamp: ClockTime,
+    duration: ClockTime,
+) -> gst::buffer::Buffer {
+    let mut buf = gst::Buffer::from_slice(slice);
+    let buf_ref = buf.get_mut().unwrap();
+    buf_ref.set_pts(timestamp);
+    buf_ref.set_duration(duration);
+    buf
+}
+
+fn cc_data_to_cea708_types(cc_data: &[u8]) -> Vec<u8> {
+    let mut ret = vec![0; 2];
+    ret[0] = 0x80 | 0x40 | ((cc_data.len() / 3) & 0x1f) as u8;
+    ret[1] = 0xFF;
+    ret.extend(cc_data);
+    ret
+}
+
+// Pulls all pending buffers and returns the codes of the given service
+fn pull_codes(h: &mut gst_check::Harness, service_no: u8) -> Vec<Code> {
+    let mut parser = CCDataParser::new();
+    let mut codes = vec![];
+
+    while let Some(outbuf) = h.try_pull() {
+        let readable = outbuf.map_readable().unwrap();
+        parser.push(&cc_data_to_cea708_types(&readable)).unwrap();
+
+        while let Some(packet) = parser.pop_packet() {
+            for service in packet.services() {
+                assert_eq!(service.number(), service_no);
+                codes.extend(service.codes().iter().cloned());
+            }
+        }
+    }
+
+    codes
+}
+
+fn text_codes(text: &str) -> Vec<Code> {
+    text.chars().map(|c| Code::from_char(c).unwrap()).collect()
+}
+
+fn contains_codes(codes: &[Code], expected: &[Code]) -> bool {
+    codes.windows(expected.len()).any(|w| w == expected)
+}
+
+#[test]
+fn test_non_timed_buffer() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse("tttocea708 mode=pop-on");
+    h.set_src_caps_str("text/x-raw");
+
+    let inbuf = gst::Buffer::from_slice("Hello");
+
+    assert_eq!(h.push(inbuf), Err(gst::FlowError::Error));
+}
+
+/* Check that a pop-on caption defines a window, fills it and toggles it on */
+#[test]
+fn test_pop_on() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse("tttocea708 mode=pop-on service-number=2");
+    h.set_src_caps_str("text/x-raw");
+
+    while h.events_in_queue() != 0 {
+        let _event = h.pull_event().unwrap();
+    }
+
+    let inbuf = new_timed_buffer("Hello", ClockTime::SECOND, ClockTime::SECOND);
+    assert_eq!(h.push(inbuf), Ok(gst::FlowSuccess::Ok));
+    h.push_event(gst::event::Eos::new());
+
+    let codes = pull_codes(&mut h, 2);
+    let hello = text_codes(" Hello");
+
+    let define_window = codes
+        .iter()
+        .position(|c| matches!(c, Code::DefineWindow(_)))
+        .expect("No window defined");
+    let text = codes
+        .windows(hello.len())
+        .position(|w| w == hello.as_slice())
+        .expect("No text written");
+    let toggle = codes
+        .iter()
+        .position(|c| matches!(c, Code::ToggleWindows(_)))
+        .expect("No window displayed");
+
+    assert!(define_window < text);
+    assert!(text < toggle);
+}
+
+/* Check that styled JSON input is translated into pen attributes and colors */
+#[test]
+fn test_json_pen_styling() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse("tttocea708 mode=pop-on");
+    h.set_src_caps_str("application/x-json,format=cea608");
+
+    while h.events_in_queue() != 0 {
+        let _event = h.pull_event().unwrap();
+    }
+
+    let json = r#"{
+        "lines": [{
+            "column": null,
+            "row": 14,
+            "chunks": [
+                {"style": "ItalicWhite", "underline": false, "text": "Hello"},
+                {"style": "Red", "underline": true, "text": "World"}
+            ],
+            "carriage_return": null
+        }],
+        "mode": "PopOn",
+        "clear": null
+    }"#;
+
+    let inbuf = new_timed_buffer(json, ClockTime::SECOND, ClockTime::SECOND);
+    assert_eq!(h.push(inbuf), Ok(gst::FlowSuccess::Ok));
+    h.push_event(gst::event::Eos::new());
+
+    let codes = pull_codes(&mut h, 1);
+
+    assert!(codes
+        .iter()
+        .any(|c| matches!(c, Code::SetPenAttributes(args) if args.italics && !args.underline)));
+    assert!(codes
+        .iter()
+        .any(|c| matches!(c, Code::SetPenAttributes(args) if !args.italics && args.underline)));
+    assert!(codes.iter().any(|c| matches!(c, Code::SetPenColor(_))));
+    assert!(contains_codes(&codes, &text_codes(" Hello")));
+    assert!(contains_codes(&codes, &text_codes("World")));
+}
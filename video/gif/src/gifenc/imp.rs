@@ -15,14 +15,17 @@ use gst_video::prelude::*;
 use gst_video::subclass::prelude::*;
 use gst_video::VideoFormat;
 use once_cell::sync::Lazy;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use std::{
+    collections::VecDeque,
     io,
     io::Write,
-    sync::{Arc, Mutex},
+    sync::{mpsc, Arc, Mutex},
 };
 
 const DEFAULT_REPEAT: i32 = 0;
 const DEFAULT_SPEED: i32 = 10;
+const DEFAULT_MAX_THREADS: u32 = 1;
 
 /// The gif::Encoder requires a std::io::Write implementation, to which it
 /// can save the generated gif. This struct is used as a temporary cache, into
@@ -74,6 +77,7 @@ impl Write for CacheBufferWriter {
 struct Settings {
     repeat: i32,
     speed: i32,
+    max_threads: u32,
 }
 
 impl Default for Settings {
@@ -81,30 +85,85 @@ impl Default for Settings {
         Settings {
             repeat: DEFAULT_REPEAT,
             speed: DEFAULT_SPEED,
+            max_threads: DEFAULT_MAX_THREADS,
         }
     }
 }
 
+impl Settings {
+    /// Number of frames that are quantized concurrently
+    fn n_threads(&self) -> usize {
+        match self.max_threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n as usize,
+        }
+    }
+}
+
+/// Tightly packed input frame, to be converted to a palettized gif::Frame.
+/// This is the expensive part of the encoding and is done on the thread pool.
+struct QuantizeJob {
+    format: VideoFormat,
+    width: u16,
+    height: u16,
+    data: Vec<u8>,
+    speed: i32,
+}
+
+impl QuantizeJob {
+    fn quantize(mut self) -> gif::Frame<'static> {
+        match self.format {
+            VideoFormat::Rgb => {
+                gif::Frame::from_rgb_speed(self.width, self.height, &self.data, self.speed)
+            }
+            VideoFormat::Rgba => {
+                gif::Frame::from_rgba_speed(self.width, self.height, &mut self.data, self.speed)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Frame that is being quantized, waiting to be written to the encoder in order
+struct PendingFrame {
+    system_frame_number: u32,
+    delay: u16,
+    receiver: mpsc::Receiver<gif::Frame<'static>>,
+}
+
 struct State {
     video_info: gst_video::VideoInfo,
     cache: Arc<CacheBuffer>,
     gif_pts: Option<gst::ClockTime>,
     last_actual_pts: Option<gst::ClockTime>,
     context: Option<gif::Encoder<CacheBufferWriter>>,
+    // None if frames are quantized on the streaming thread
+    thread_pool: Option<ThreadPool>,
+    // Number of frames that may still be quantized after handle_frame() returns
+    max_pending: usize,
+    pending: VecDeque<PendingFrame>,
 }
 
 impl State {
-    pub fn new(video_info: gst_video::VideoInfo) -> Self {
+    pub fn new(
+        video_info: gst_video::VideoInfo,
+        thread_pool: Option<ThreadPool>,
+        max_pending: usize,
+    ) -> Self {
         Self {
             video_info,
             cache: Arc::new(CacheBuffer::new()),
             gif_pts: None,
             last_actual_pts: None,
             context: None,
+            thread_pool,
+            max_pending,
+            pending: VecDeque::new(),
         }
     }
     pub fn reset(&mut self, settings: Settings) {
         self.cache.clear();
+        self.pending.clear();
         self.gif_pts = None;
         self.last_actual_pts = None;
         // initialize and configure encoder with a CacheBufferWriter pointing
@@ -162,6 +221,12 @@ impl ObjectImpl for GifEnc {
                     .default_value(DEFAULT_SPEED)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecUInt::builder("max-threads")
+                    .nick("Maximum Threads")
+                    .blurb("Maximum number of frames quantized in parallel (0 = number of CPUs, 1 = quantize on the streaming thread)")
+                    .default_value(DEFAULT_MAX_THREADS)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -178,6 +243,10 @@ impl ObjectImpl for GifEnc {
                 let mut settings = self.settings.lock().unwrap();
                 settings.speed = value.get().expect("type checked upstream");
             }
+            "max-threads" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_threads = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -192,6 +261,10 @@ impl ObjectImpl for GifEnc {
                 let settings = self.settings.lock().unwrap();
                 settings.speed.to_value()
             }
+            "max-threads" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_threads.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -250,6 +323,15 @@ impl VideoEncoderImpl for GifEnc {
         Ok(())
     }
 
+    fn flush(&self) -> bool {
+        // Frames still being quantized are dropped together with their codec frames
+        if let Some(state) = self.state.borrow_mut().as_mut() {
+            state.pending.clear();
+        }
+
+        true
+    }
+
     fn propose_allocation(
         &self,
         query: &mut gst::query::Allocation,
@@ -268,10 +350,34 @@ impl VideoEncoderImpl for GifEnc {
         let video_info = state.info();
         gst::debug!(CAT, imp: self, "Setting format {:?}", video_info);
 
+        let settings = *self.settings.lock().unwrap();
+        let n_threads = settings.n_threads();
+        let thread_pool = if n_threads > 1 {
+            let thread_pool = ThreadPoolBuilder::new()
+                .num_threads(n_threads)
+                .thread_name(|i| format!("gifenc-{i}"))
+                .build()
+                .map_err(|err| {
+                    gst::loggable_error!(CAT, "Could not create rayon thread pool: {err}")
+                })?;
+            Some(thread_pool)
+        } else {
+            None
+        };
+        gst::debug!(CAT, imp: self, "Quantizing up to {n_threads} frames in parallel");
+
+        let frame_duration = (video_info.fps().numer() > 0).then(|| {
+            gst::ClockTime::SECOND
+                .mul_div_floor(
+                    video_info.fps().denom() as u64,
+                    video_info.fps().numer() as u64,
+                )
+                .unwrap()
+        });
+
         {
-            let mut state = State::new(video_info);
-            let settings = self.settings.lock().unwrap();
-            state.reset(*settings);
+            let mut state = State::new(video_info, thread_pool, n_threads - 1);
+            state.reset(settings);
             *self.state.borrow_mut() = Some(state);
         }
 
@@ -283,6 +389,12 @@ impl VideoEncoderImpl for GifEnc {
             .negotiate(output_state)
             .map_err(|_| gst::loggable_error!(CAT, "Failed to negotiate"))?;
 
+        // Up to one frame per additional thread is queued before output is produced
+        if let Some(frame_duration) = frame_duration {
+            let latency = frame_duration * (n_threads as u64 - 1);
+            instance.set_latency(latency, latency);
+        }
+
         self.parent_set_format(state)
     }
 
@@ -292,7 +404,7 @@ impl VideoEncoderImpl for GifEnc {
 
     fn handle_frame(
         &self,
-        mut frame: gst_video::VideoCodecFrame,
+        frame: gst_video::VideoCodecFrame,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
         let mut state_guard = self.state.borrow_mut();
         let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
@@ -306,7 +418,7 @@ impl VideoEncoderImpl for GifEnc {
 
         let input_buffer = frame.input_buffer().expect("frame without input buffer");
 
-        {
+        let (job, delay) = {
             let in_frame =
                 gst_video::VideoFrameRef::from_buffer_ref_readable(input_buffer, &state.video_info)
                     .map_err(|_| {
@@ -349,33 +461,83 @@ impl VideoEncoderImpl for GifEnc {
                     gst::FlowError::Error
                 })?;
 
-            let settings = self.settings.lock().unwrap();
-
-            let mut raw_frame = tightly_packed_framebuffer(&in_frame);
-            let mut gif_frame = match in_frame.info().format() {
-                gst_video::VideoFormat::Rgb => gif::Frame::from_rgb_speed(
-                    frame_width as u16,
-                    frame_height as u16,
-                    &raw_frame,
-                    settings.speed,
-                ),
-                gst_video::VideoFormat::Rgba => gif::Frame::from_rgba_speed(
-                    frame_width as u16,
-                    frame_height as u16,
-                    &mut raw_frame,
-                    settings.speed,
-                ),
-                _ => unreachable!(),
-            };
-
-            // apply encoding settings to frame (gif uses multiples of 10ms as frame_delay)
+            // gif uses multiples of 10ms as frame_delay.
             // use float arithmetic with rounding for this calculation, since small stuttering
             // is probably less visible than the large stuttering when a complete 10ms have to
             // "catch up".
-            gif_frame.delay = (frame_delay.mseconds() as f32 / 10.0).round() as u16;
-            state.gif_pts = state
-                .gif_pts
-                .opt_add((gif_frame.delay as u64 * 10).mseconds());
+            let delay = (frame_delay.mseconds() as f32 / 10.0).round() as u16;
+            state.gif_pts = state.gif_pts.opt_add((delay as u64 * 10).mseconds());
+
+            let job = QuantizeJob {
+                format: in_frame.info().format(),
+                width: frame_width as u16,
+                height: frame_height as u16,
+                data: tightly_packed_framebuffer(&in_frame),
+                speed: self.settings.lock().unwrap().speed,
+            };
+
+            (job, delay)
+        };
+
+        // Quantize the frame on the thread pool if there is one. The results are written to the
+        // encoder in order once available.
+        let (sender, receiver) = mpsc::sync_channel(1);
+        match state.thread_pool {
+            Some(ref thread_pool) => thread_pool.spawn(move || {
+                let _ = sender.send(job.quantize());
+            }),
+            None => {
+                let _ = sender.send(job.quantize());
+            }
+        }
+        state.pending.push_back(PendingFrame {
+            system_frame_number: frame.system_frame_number(),
+            delay,
+            receiver,
+        });
+        drop(frame);
+
+        let max_pending = state.max_pending;
+        let encoded = self.write_pending(state, max_pending)?;
+
+        // Avoid keeping the state locked while calling finish_frame()
+        drop(state_guard);
+
+        self.finish_frames(encoded)
+    }
+}
+
+impl GifEnc {
+    /// Writes quantized frames to the encoder in order, waiting for the oldest ones until at
+    /// most `max_pending` frames are left. Returns the encoded data per system frame number.
+    fn write_pending(
+        &self,
+        state: &mut State,
+        max_pending: usize,
+    ) -> Result<Vec<(u32, Vec<u8>)>, gst::FlowError> {
+        let mut encoded = Vec::new();
+
+        while let Some(pending) = state.pending.front() {
+            let res = if state.pending.len() > max_pending {
+                pending.receiver.recv().ok()
+            } else {
+                match pending.receiver.try_recv() {
+                    Ok(gif_frame) => Some(gif_frame),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => None,
+                }
+            };
+
+            let pending = state.pending.pop_front().unwrap();
+            let Some(mut gif_frame) = res else {
+                gst::element_imp_error!(
+                    self,
+                    gst::CoreError::Failed,
+                    ["Failed to quantize frame {}", pending.system_frame_number]
+                );
+                return Err(gst::FlowError::Error);
+            };
+            gif_frame.delay = pending.delay;
 
             // encode new frame
             let context = state.context.as_mut().unwrap();
@@ -383,28 +545,50 @@ impl VideoEncoderImpl for GifEnc {
                 gst::element_imp_error!(self, gst::CoreError::Failed, ["{e}"]);
                 return Err(gst::FlowError::Error);
             }
+
+            // The encoder directly outputs one frame for each input frame
+            encoded.push((pending.system_frame_number, state.cache.consume()));
         }
 
-        // The encoder directly outputs one frame for each input frame
-        // Since the output is directly available, we can re-use the input frame
-        // to push results to the pipeline
-        let buffer = state.cache.consume();
+        Ok(encoded)
+    }
 
-        // Avoid keeping the state locked while calling finish_frame()
-        drop(state_guard);
+    fn finish_frames(
+        &self,
+        encoded: Vec<(u32, Vec<u8>)>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let instance = self.obj();
 
-        let output_buffer = gst::Buffer::from_mut_slice(buffer);
-        // Currently not using incremental frames -> every frame is a keyframe
-        frame.set_flags(gst_video::VideoCodecFrameFlags::SYNC_POINT);
-        frame.set_output_buffer(output_buffer);
-        self.obj().finish_frame(frame)
+        for (system_frame_number, buffer) in encoded {
+            let Some(mut frame) = instance.frame(system_frame_number as i32) else {
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Frame {system_frame_number} not pending anymore"
+                );
+                continue;
+            };
+
+            let output_buffer = gst::Buffer::from_mut_slice(buffer);
+            // Currently not using incremental frames -> every frame is a keyframe
+            frame.set_flags(gst_video::VideoCodecFrameFlags::SYNC_POINT);
+            frame.set_output_buffer(output_buffer);
+            instance.finish_frame(frame)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
     }
-}
 
-impl GifEnc {
     fn flush_encoder(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
         gst::debug!(CAT, imp: self, "Flushing");
 
+        // Wait for all frames that are still being quantized
+        let encoded = match self.state.borrow_mut().as_mut() {
+            Some(state) => self.write_pending(state, 0)?,
+            None => Vec::new(),
+        };
+        self.finish_frames(encoded)?;
+
         let trailer_buffer = self.state.borrow_mut().as_mut().map(|state| {
             // Drop encoder to flush and take flushed data (gif trailer)
             state.context = None;
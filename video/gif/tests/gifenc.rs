@@ -70,3 +70,46 @@ fn test_encode(video_info: &gst_video::VideoInfo) {
         assert!(!buffer.flags().contains(gst::BufferFlags::DELTA_UNIT))
     }
 }
+
+#[test]
+fn test_encode_parallel() {
+    init();
+
+    let video_info = gst_video::VideoInfo::builder(gst_video::VideoFormat::Rgba, 160, 120)
+        .fps((30, 1))
+        .build()
+        .unwrap();
+
+    let mut h = gst_check::Harness::new("gifenc");
+    h.element().unwrap().set_property("max-threads", 4u32);
+    h.set_src_caps(video_info.to_caps().unwrap());
+
+    for pts in 0..10 {
+        let buffer = {
+            let mut buffer = gst::Buffer::with_size(video_info.size()).unwrap();
+            {
+                let buffer = buffer.get_mut().unwrap();
+                buffer.set_pts(pts.seconds());
+            }
+            let mut vframe =
+                gst_video::VideoFrame::from_buffer_writable(buffer, &video_info).unwrap();
+            // Different content per frame so that the quantization takes different amounts of time
+            for (i, v) in vframe.plane_data_mut(0).unwrap().iter_mut().enumerate() {
+                *v = ((i as u64 * (pts + 1)) % 256) as u8;
+            }
+            vframe.into_buffer()
+        };
+        h.push(buffer).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // Frames are output in order with their original pts, followed by the GIF trailer
+    for pts in 0..10u64 {
+        let buffer = h.pull().unwrap();
+        assert!(!buffer.flags().contains(gst::BufferFlags::DELTA_UNIT));
+        assert_eq!(buffer.pts(), Some(pts.seconds()));
+    }
+    let trailer = h.pull().unwrap();
+    assert_eq!(trailer.pts(), Some(9.seconds()));
+    assert!(h.try_pull().is_none());
+}
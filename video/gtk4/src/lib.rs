@@ -18,8 +18,10 @@
 use gst::glib;
 
 mod sink;
+mod source;
 mod utils;
 pub use sink::PaintableSink;
+pub use source::PaintableSrc;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     #[cfg(not(feature = "gtk_v4_10"))]
@@ -29,7 +31,8 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
         }
     }
 
-    sink::register(plugin)
+    sink::register(plugin)?;
+    source::register(plugin)
 }
 
 gst::plugin_define!(
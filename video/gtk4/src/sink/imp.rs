@@ -12,6 +12,7 @@
 use super::SinkEvent;
 use crate::sink::frame::Frame;
 use crate::sink::paintable::Paintable;
+use crate::sink::render_widget::RenderWidget;
 
 use glib::thread_guard::ThreadGuard;
 use gtk::prelude::*;
@@ -594,6 +595,15 @@ impl VideoSinkImpl for PaintableSink {
 }
 
 impl PaintableSink {
+    /// Size of the video frames in pixels, used as coordinate space for navigation events
+    pub(super) fn stream_size(&self) -> Option<(u32, u32)> {
+        self.info
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|info| (info.width(), info.height()))
+    }
+
     fn pending_frame(&self) -> Option<Frame> {
         self.pending_frame.lock().unwrap().take()
     }
@@ -700,19 +710,8 @@ impl PaintableSink {
             };
 
             let window = gtk::Window::new();
-            let picture = gtk::Picture::new();
-            picture.set_paintable(Some(&paintable));
-
-            #[cfg(feature = "gtk_v4_14")]
-            {
-                let offload = gtk::GraphicsOffload::new(Some(&picture));
-                offload.set_enabled(gtk::GraphicsOffloadEnabled::Enabled);
-                window.set_child(Some(&offload));
-            }
-            #[cfg(not(feature = "gtk_v4_14"))]
-            {
-                window.set_child(Some(&picture));
-            }
+            let widget = RenderWidget::new(&self_.obj(), paintable.upcast_ref());
+            window.set_child(Some(&widget));
             window.set_default_size(640, 480);
             if std::env::var("GST_GTK4_WINDOW_FULLSCREEN").as_deref() == Ok("1") {
                 window.set_fullscreened(true);
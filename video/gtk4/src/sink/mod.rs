@@ -33,6 +33,11 @@
  * or if the environment variable `GST_GTK4_WINDOW=1` is set. Setting `GST_GTK4_WINDOW_FULLSCREEN=1`
  * will make the window launch in fullscreen mode.
  *
+ * Mouse movement, mouse button and key events on the test window are sent upstream as navigation
+ * events in video frame coordinates, e.g. for interactive menus or remote control. Applications
+ * that embed the paintable in their own widgets can do the same by sending
+ * `gst_video::NavigationEvent`s to the sink.
+ *
  * {{ videos/gtk4/examples/gtksink.rs }}
  */
 use gtk::glib;
@@ -41,6 +46,7 @@ use gtk::glib::prelude::*;
 mod frame;
 pub(super) mod imp;
 mod paintable;
+mod render_widget;
 
 enum SinkEvent {
     FrameChanged,
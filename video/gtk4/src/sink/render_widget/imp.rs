@@ -0,0 +1,209 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, glib};
+
+use gst::prelude::*;
+
+use std::cell::{OnceCell, RefCell};
+
+use once_cell::sync::Lazy;
+
+use crate::sink::imp::CAT;
+
+#[derive(Default)]
+pub struct RenderWidget {
+    element: glib::WeakRef<crate::PaintableSink>,
+    picture: OnceCell<gtk::Picture>,
+    child: RefCell<Option<gtk::Widget>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RenderWidget {
+    const NAME: &'static str = "GstGtk4RenderWidget";
+    type Type = super::RenderWidget;
+    type ParentType = gtk::Widget;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_layout_manager_type::<gtk::BinLayout>();
+    }
+}
+
+impl ObjectImpl for RenderWidget {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecObject::builder::<crate::PaintableSink>("element")
+                    .nick("Element")
+                    .blurb("The sink the navigation events are sent to")
+                    .construct_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "element" => self.element.upgrade().to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "element" => {
+                let element = value
+                    .get::<Option<crate::PaintableSink>>()
+                    .expect("type checked upstream");
+                self.element.set(element.as_ref());
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj().clone();
+        obj.set_focusable(true);
+
+        let picture = gtk::Picture::new();
+        #[cfg(feature = "gtk_v4_14")]
+        let child = {
+            let offload = gtk::GraphicsOffload::new(Some(&picture));
+            offload.set_enabled(gtk::GraphicsOffloadEnabled::Enabled);
+            offload.upcast::<gtk::Widget>()
+        };
+        #[cfg(not(feature = "gtk_v4_14"))]
+        let child = picture.clone().upcast::<gtk::Widget>();
+
+        child.set_parent(&obj);
+        *self.child.borrow_mut() = Some(child);
+        self.picture.set(picture).unwrap();
+
+        let motion = gtk::EventControllerMotion::new();
+        motion.connect_motion(glib::clone!(@weak obj => move |_motion, x, y| {
+            let imp = obj.imp();
+            let Some((x, y)) = imp.stream_coordinates(x, y) else {
+                return;
+            };
+            imp.send_event(gst_video::NavigationEvent::new_mouse_move(x, y));
+        }));
+        obj.add_controller(motion);
+
+        let click = gtk::GestureClick::new();
+        // Handle all mouse buttons and not only the primary one
+        click.set_button(0);
+        click.connect_pressed(glib::clone!(@weak obj => move |gesture, _n_press, x, y| {
+            obj.grab_focus();
+
+            let imp = obj.imp();
+            let Some((x, y)) = imp.stream_coordinates(x, y) else {
+                return;
+            };
+            imp.send_event(gst_video::NavigationEvent::new_mouse_button_press(
+                gesture.current_button() as i32,
+                x,
+                y,
+            ));
+        }));
+        click.connect_released(glib::clone!(@weak obj => move |gesture, _n_press, x, y| {
+            let imp = obj.imp();
+            let Some((x, y)) = imp.stream_coordinates(x, y) else {
+                return;
+            };
+            imp.send_event(gst_video::NavigationEvent::new_mouse_button_release(
+                gesture.current_button() as i32,
+                x,
+                y,
+            ));
+        }));
+        obj.add_controller(click);
+
+        let key = gtk::EventControllerKey::new();
+        key.connect_key_pressed(
+            glib::clone!(@weak obj => @default-return glib::Propagation::Proceed, move |_key, keyval, _keycode, _state| {
+                let Some(name) = keyval.name() else {
+                    return glib::Propagation::Proceed;
+                };
+                obj.imp().send_event(gst_video::NavigationEvent::new_key_press(&name));
+
+                glib::Propagation::Stop
+            }),
+        );
+        key.connect_key_released(
+            glib::clone!(@weak obj => move |_key, keyval, _keycode, _state| {
+                if let Some(name) = keyval.name() {
+                    obj.imp().send_event(gst_video::NavigationEvent::new_key_release(&name));
+                }
+            }),
+        );
+        obj.add_controller(key);
+    }
+
+    fn dispose(&self) {
+        if let Some(child) = self.child.borrow_mut().take() {
+            child.unparent();
+        }
+    }
+}
+
+impl WidgetImpl for RenderWidget {}
+
+impl RenderWidget {
+    pub(super) fn set_paintable(&self, paintable: &gdk::Paintable) {
+        self.picture.get().unwrap().set_paintable(Some(paintable));
+    }
+
+    fn send_event(&self, event: gst_video::NavigationEvent) {
+        let Some(element) = self.element.upgrade() else {
+            return;
+        };
+
+        gst::trace!(CAT, obj: element, "Sending navigation event {event:?}");
+        element.send_event(event.build());
+    }
+
+    // Converts widget coordinates to coordinates in the video frames of the sink. The picture
+    // scales the paintable to the widget size while keeping the display aspect ratio.
+    fn stream_coordinates(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let element = self.element.upgrade()?;
+        let (stream_width, stream_height) = element.imp().stream_size()?;
+
+        let obj = self.obj();
+        let width = obj.width() as f64;
+        let height = obj.height() as f64;
+        if width <= 0. || height <= 0. {
+            return None;
+        }
+
+        let aspect = self
+            .picture
+            .get()
+            .and_then(|picture| picture.paintable())
+            .map_or(0., |paintable| paintable.intrinsic_aspect_ratio());
+        let (display_width, display_height) = if aspect <= 0. {
+            (width, height)
+        } else if width / height > aspect {
+            (height * aspect, height)
+        } else {
+            (width, width / aspect)
+        };
+
+        let x = (x - (width - display_width) / 2.) * stream_width as f64 / display_width;
+        let y = (y - (height - display_height) / 2.) * stream_height as f64 / display_height;
+
+        Some((
+            x.clamp(0., stream_width as f64),
+            y.clamp(0., stream_height as f64),
+        ))
+    }
+}
@@ -0,0 +1,29 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gtk::glib;
+use gtk::subclass::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct RenderWidget(ObjectSubclass<imp::RenderWidget>)
+        @extends gtk::Widget;
+}
+
+impl RenderWidget {
+    /// Widget showing the paintable of `element` that forwards pointer and keyboard input to
+    /// `element` as navigation events
+    pub fn new(element: &crate::PaintableSink, paintable: &gtk::gdk::Paintable) -> Self {
+        let widget = glib::Object::builder::<Self>()
+            .property("element", element)
+            .build();
+        widget.imp().set_paintable(paintable);
+
+        widget
+    }
+}
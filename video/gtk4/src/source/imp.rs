@@ -0,0 +1,544 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use glib::thread_guard::ThreadGuard;
+use gtk::prelude::*;
+use gtk::{gdk, glib, graphene, gsk};
+
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use crate::utils;
+
+pub(crate) static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "gtk4paintablesrc",
+        gst::DebugColorFlags::empty(),
+        Some("GTK4 Paintable source"),
+    )
+});
+
+// Memory layout of the textures downloaded from GDK
+#[cfg(target_endian = "little")]
+const VIDEO_FORMAT: gst_video::VideoFormat = gst_video::VideoFormat::Bgra;
+#[cfg(target_endian = "big")]
+const VIDEO_FORMAT: gst_video::VideoFormat = gst_video::VideoFormat::Argb;
+#[cfg(all(feature = "gtk_v4_10", target_endian = "little"))]
+const MEMORY_FORMAT: gdk::MemoryFormat = gdk::MemoryFormat::B8g8r8a8;
+#[cfg(all(feature = "gtk_v4_10", target_endian = "big"))]
+const MEMORY_FORMAT: gdk::MemoryFormat = gdk::MemoryFormat::A8r8g8b8;
+
+const DEFAULT_WIDTH: i32 = 320;
+const DEFAULT_HEIGHT: i32 = 240;
+const DEFAULT_FPS_N: i32 = 30;
+
+#[derive(Default)]
+struct State {
+    info: Option<gst_video::VideoInfo>,
+    start_time: Option<gst::ClockTime>,
+    n_frames: u64,
+}
+
+struct ClockWait {
+    clock_id: Option<gst::SingleShotClockId>,
+    flushing: bool,
+}
+
+impl Default for ClockWait {
+    fn default() -> ClockWait {
+        ClockWait {
+            clock_id: None,
+            flushing: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PaintableSrc {
+    paintable: Mutex<Option<ThreadGuard<gdk::Paintable>>>,
+    renderer: Mutex<Option<ThreadGuard<gsk::Renderer>>>,
+    state: Mutex<State>,
+    clock_wait: Mutex<ClockWait>,
+}
+
+impl Drop for PaintableSrc {
+    fn drop(&mut self) {
+        let paintable = self.paintable.get_mut().unwrap().take();
+        let renderer = self.renderer.get_mut().unwrap().take();
+        if paintable.is_some() || renderer.is_some() {
+            glib::MainContext::default().invoke(move || {
+                if let Some(renderer) = renderer {
+                    renderer.get_ref().unrealize();
+                }
+                drop(paintable);
+            })
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for PaintableSrc {
+    const NAME: &'static str = "GstGtk4PaintableSrc";
+    type Type = super::PaintableSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for PaintableSrc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecObject::builder::<gdk::Paintable>("paintable")
+                    .nick("Paintable")
+                    .blurb("The Paintable to render into video frames")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecObject::builder::<gtk::Widget>("widget")
+                    .nick("Widget")
+                    .blurb("The Widget to render into video frames")
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(true);
+        obj.set_format(gst::Format::Time);
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "paintable" => {
+                let paintable = value
+                    .get::<Option<gdk::Paintable>>()
+                    .expect("type checked upstream");
+                self.set_paintable(paintable);
+            }
+            "widget" => {
+                let widget = value
+                    .get::<Option<gtk::Widget>>()
+                    .expect("type checked upstream");
+                self.set_paintable(
+                    widget.map(|widget| gtk::WidgetPaintable::new(Some(&widget)).upcast()),
+                );
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let paintable = self.paintable.lock().unwrap();
+        let paintable = match &*paintable {
+            Some(paintable) if paintable.is_owner() => Some(paintable.get_ref().clone()),
+            Some(_) => {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Can't retrieve Paintable from non-main thread"
+                );
+                None
+            }
+            None => None,
+        };
+
+        match pspec.name() {
+            "paintable" => paintable.to_value(),
+            "widget" => paintable
+                .and_then(|paintable| paintable.downcast::<gtk::WidgetPaintable>().ok())
+                .and_then(|paintable| paintable.widget())
+                .to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for PaintableSrc {}
+
+impl ElementImpl for PaintableSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "GTK 4 Paintable Source",
+                "Source/Video",
+                "Renders a GTK 4 Paintable or Widget into video frames",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format(VIDEO_FORMAT)
+                .pixel_aspect_ratio(gst::Fraction::new(1, 1))
+                .framerate_range(gst::Fraction::new(1, 1)..=gst::Fraction::new(i32::MAX, 1))
+                .build();
+
+            vec![gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap()]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSrcImpl for PaintableSrc {
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let info = gst_video::VideoInfo::from_caps(caps)
+            .map_err(|_| gst::loggable_error!(CAT, "Invalid caps {caps}"))?;
+
+        gst::debug!(CAT, imp: self, "Configuring for caps {caps}");
+
+        let mut state = self.state.lock().unwrap();
+        state.info = Some(info);
+        drop(state);
+
+        let _ = self
+            .obj()
+            .post_message(gst::message::Latency::builder().src(&*self.obj()).build());
+
+        Ok(())
+    }
+
+    fn fixate(&self, mut caps: gst::Caps) -> gst::Caps {
+        let (width, height) = self
+            .intrinsic_size()
+            .unwrap_or((DEFAULT_WIDTH, DEFAULT_HEIGHT));
+
+        gst::debug!(CAT, imp: self, "Fixating to preferred size {width}x{height}");
+
+        caps.truncate();
+        {
+            let caps = caps.make_mut();
+            let s = caps.structure_mut(0).unwrap();
+            s.fixate_field_nearest_int("width", width);
+            s.fixate_field_nearest_int("height", height);
+            s.fixate_field_nearest_fraction("framerate", gst::Fraction::new(DEFAULT_FPS_N, 1));
+        }
+
+        self.parent_fixate(caps)
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = Default::default();
+        self.unlock_stop()?;
+
+        gst::info!(CAT, imp: self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = Default::default();
+        self.unlock()?;
+
+        if let Some(renderer) = self.renderer.lock().unwrap().take() {
+            glib::MainContext::default().invoke(move || {
+                renderer.get_ref().unrealize();
+            });
+        }
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+
+    fn query(&self, query: &mut gst::QueryRef) -> bool {
+        match query.view_mut() {
+            gst::QueryViewMut::Latency(q) => {
+                let state = self.state.lock().unwrap();
+
+                let Some(ref info) = state.info else {
+                    return false;
+                };
+
+                let latency = gst::ClockTime::SECOND
+                    .mul_div_floor(info.fps().denom() as u64, info.fps().numer() as u64)
+                    .unwrap_or(gst::ClockTime::ZERO);
+                gst::debug!(CAT, imp: self, "Returning latency {latency}");
+                q.set(true, latency, gst::ClockTime::NONE);
+
+                true
+            }
+            _ => BaseSrcImplExt::parent_query(self, query),
+        }
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Unlocking");
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if let Some(clock_id) = clock_wait.clock_id.take() {
+            clock_id.unschedule();
+        }
+        clock_wait.flushing = true;
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Unlock stop");
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        clock_wait.flushing = false;
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for PaintableSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let obj = self.obj();
+        let clock = Option::zip(obj.clock(), obj.base_time());
+
+        let mut state = self.state.lock().unwrap();
+        let info = match state.info {
+            None => {
+                gst::element_imp_error!(self, gst::CoreError::Negotiation, ["Have no caps yet"]);
+                return Err(gst::FlowError::NotNegotiated);
+            }
+            Some(ref info) => info.clone(),
+        };
+
+        let fps_n = info.fps().numer() as u64;
+        let fps_d = info.fps().denom() as u64;
+
+        let now = clock
+            .as_ref()
+            .and_then(|(clock, base_time)| clock.time().opt_checked_sub(*base_time).ok())
+            .flatten();
+        let start_time = *state
+            .start_time
+            .get_or_insert(now.unwrap_or(gst::ClockTime::ZERO));
+
+        // Skip frames we're too late for instead of rendering them all in a burst
+        if let Some(now) = now {
+            let current_frame = now
+                .saturating_sub(start_time)
+                .nseconds()
+                .mul_div_floor(fps_n, fps_d * *gst::ClockTime::SECOND)
+                .unwrap_or(0);
+            if current_frame > state.n_frames {
+                gst::debug!(
+                    CAT,
+                    imp: self,
+                    "Skipping {} frames",
+                    current_frame - state.n_frames
+                );
+                state.n_frames = current_frame;
+            }
+        }
+
+        let n_frames = state.n_frames;
+        state.n_frames += 1;
+        drop(state);
+
+        let pts = start_time
+            + gst::ClockTime::SECOND
+                .mul_div_floor(n_frames * fps_d, fps_n)
+                .unwrap();
+        let next_pts = start_time
+            + gst::ClockTime::SECOND
+                .mul_div_floor((n_frames + 1) * fps_d, fps_n)
+                .unwrap();
+
+        // Wait until the frame is due so the paintable is captured at the right time
+        if let Some((clock, base_time)) = clock {
+            let wait_until = pts + base_time;
+
+            let mut clock_wait = self.clock_wait.lock().unwrap();
+            if clock_wait.flushing {
+                gst::debug!(CAT, imp: self, "Flushing");
+                return Err(gst::FlowError::Flushing);
+            }
+
+            let id = clock.new_single_shot_id(wait_until);
+            clock_wait.clock_id = Some(id.clone());
+            drop(clock_wait);
+
+            gst::log!(
+                CAT,
+                imp: self,
+                "Waiting until {}, now {}",
+                wait_until,
+                clock.time().display(),
+            );
+            let (res, jitter) = id.wait();
+            gst::log!(CAT, imp: self, "Waited res {:?} jitter {}", res, jitter);
+            self.clock_wait.lock().unwrap().clock_id.take();
+
+            if res == Err(gst::ClockError::Unscheduled) {
+                gst::debug!(CAT, imp: self, "Flushing");
+                return Err(gst::FlowError::Flushing);
+            }
+        }
+
+        let width = info.width();
+        let height = info.height();
+        let stride = info.stride()[0] as usize;
+        let size = info.size();
+
+        let obj = obj.clone();
+        let data =
+            utils::invoke_on_main_thread(move || obj.imp().render(width, height, stride, size));
+
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(pts);
+            buffer.set_duration(next_pts - pts);
+            buffer.set_offset(n_frames);
+            buffer.set_offset_end(n_frames + 1);
+        }
+
+        gst_video::VideoMeta::add(
+            buffer.get_mut().unwrap(),
+            gst_video::VideoFrameFlags::empty(),
+            info.format(),
+            width,
+            height,
+        )
+        .map_err(|_| gst::FlowError::Error)?;
+
+        gst::trace!(CAT, imp: self, "Produced buffer {buffer:?}");
+
+        Ok(CreateSuccess::NewBuffer(buffer))
+    }
+}
+
+impl PaintableSrc {
+    fn set_paintable(&self, paintable: Option<gdk::Paintable>) {
+        let mut paintable_guard = self.paintable.lock().unwrap();
+        let old_paintable =
+            std::mem::replace(&mut *paintable_guard, paintable.map(ThreadGuard::new));
+        drop(paintable_guard);
+
+        // The old paintable might belong to another thread, in which case it has to be released
+        // from the main thread.
+        if let Some(old_paintable) = old_paintable {
+            if !old_paintable.is_owner() {
+                glib::MainContext::default().invoke(move || drop(old_paintable));
+            }
+        }
+    }
+
+    fn intrinsic_size(&self) -> Option<(i32, i32)> {
+        let obj = self.obj().clone();
+        utils::invoke_on_main_thread(move || {
+            let imp = obj.imp();
+            let paintable = imp.paintable.lock().unwrap();
+            let paintable = paintable.as_ref().filter(|p| p.is_owner())?.get_ref();
+
+            let width = paintable.intrinsic_width();
+            let height = paintable.intrinsic_height();
+
+            (width > 0 && height > 0).then_some((width, height))
+        })
+    }
+
+    // Must be called from the main thread. Returns a fully transparent frame if there's nothing
+    // to render.
+    fn render(&self, width: u32, height: u32, stride: usize, size: usize) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+
+        let paintable = self.paintable.lock().unwrap();
+        let Some(paintable) = paintable.as_ref().filter(|p| p.is_owner()) else {
+            gst::trace!(CAT, imp: self, "No paintable to render");
+            return data;
+        };
+
+        let snapshot = gtk::Snapshot::new();
+        paintable
+            .get_ref()
+            .snapshot(&snapshot, width as f64, height as f64);
+        let Some(node) = snapshot.to_node() else {
+            gst::trace!(CAT, imp: self, "Paintable rendered nothing");
+            return data;
+        };
+
+        let mut renderer_guard = self.renderer.lock().unwrap();
+        if renderer_guard.is_none() {
+            let renderer = gsk::CairoRenderer::new();
+            if let Err(err) = renderer.realize(None) {
+                gst::error!(CAT, imp: self, "Failed to realize renderer: {err}");
+                return data;
+            }
+            *renderer_guard = Some(ThreadGuard::new(renderer.upcast()));
+        }
+        let renderer = renderer_guard.as_ref().unwrap().get_ref();
+
+        let texture = renderer.render_texture(
+            &node,
+            Some(&graphene::Rect::new(0.0, 0.0, width as f32, height as f32)),
+        );
+
+        #[cfg(feature = "gtk_v4_10")]
+        {
+            let mut downloader = gdk::TextureDownloader::new(&texture);
+            downloader.set_format(MEMORY_FORMAT);
+            let (bytes, texture_stride) = downloader.download_bytes();
+
+            let row_size = 4 * width as usize;
+            for (dest, src) in data
+                .chunks_exact_mut(stride)
+                .zip(bytes.chunks_exact(texture_stride))
+                .take(height as usize)
+            {
+                dest[..row_size].copy_from_slice(&src[..row_size]);
+            }
+        }
+        #[cfg(not(feature = "gtk_v4_10"))]
+        {
+            texture.download(&mut data, stride);
+            unpremultiply(&mut data, width as usize, height as usize, stride);
+        }
+
+        data
+    }
+}
+
+// Textures are downloaded with premultiplied alpha but raw video is expected to have straight
+// alpha.
+#[cfg(not(feature = "gtk_v4_10"))]
+fn unpremultiply(data: &mut [u8], width: usize, height: usize, stride: usize) {
+    #[cfg(target_endian = "little")]
+    const ALPHA: usize = 3;
+    #[cfg(target_endian = "big")]
+    const ALPHA: usize = 0;
+
+    for row in data.chunks_exact_mut(stride).take(height) {
+        for pixel in row[..4 * width].chunks_exact_mut(4) {
+            let alpha = pixel[ALPHA] as u32;
+            if alpha == 0 || alpha == 255 {
+                continue;
+            }
+
+            for (idx, c) in pixel.iter_mut().enumerate() {
+                if idx != ALPHA {
+                    *c = ((*c as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-gtk4paintablesrc
+ *
+ * Renders a `gdk::Paintable` into raw video frames at the negotiated framerate. This allows
+ * compositing GTK-rendered overlays and user interfaces into a pipeline.
+ *
+ * Either the `paintable` or the `widget` property has to be set from the main thread. A widget is
+ * wrapped in a `gtk::WidgetPaintable` and hence only produces content while it is mapped, e.g.
+ * as part of a (possibly offscreen) window.
+ *
+ * Rendering happens on the default main context, which must be running while the element is in
+ * `PLAYING`. The output size defaults to the intrinsic size of the paintable and can be selected
+ * via downstream caps.
+ */
+use gtk::glib;
+use gtk::glib::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct PaintableSrc(ObjectSubclass<imp::PaintableSrc>)
+        @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object;
+}
+
+impl PaintableSrc {
+    pub fn new(name: Option<&str>) -> Self {
+        glib::Object::builder().property("name", name).build()
+    }
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "gtk4paintablesrc",
+        gst::Rank::NONE,
+        PaintableSrc::static_type(),
+    )
+}
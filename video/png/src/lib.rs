@@ -16,9 +16,11 @@
  */
 use gst::glib;
 
+mod pngdec;
 mod pngenc;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    pngdec::register(plugin)?;
     pngenc::register(plugin)?;
     Ok(())
 }
@@ -0,0 +1,320 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::glib;
+use gst::subclass::prelude::*;
+use gst_video::prelude::*;
+use gst_video::subclass::prelude::*;
+use gst_video::VideoFormat;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rspngdec",
+        gst::DebugColorFlags::empty(),
+        Some("PNG decoder"),
+    )
+});
+
+struct State {
+    input_state: gst_video::VideoCodecState<'static, gst_video::video_codec_state::Readable>,
+    output_info: Option<gst_video::VideoInfo>,
+}
+
+#[derive(Default)]
+pub struct PngDecoder {
+    state: Mutex<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for PngDecoder {
+    const NAME: &'static str = "GstRsPngDec";
+    type Type = super::PngDecoder;
+    type ParentType = gst_video::VideoDecoder;
+}
+
+/// Output format for the color type and bit depth of the decoded image, after expanding palette,
+/// transparency and low bit depths
+fn output_format(color: png::ColorType, depth: png::BitDepth) -> Option<VideoFormat> {
+    match (color, depth) {
+        (png::ColorType::Grayscale, png::BitDepth::Eight) => Some(VideoFormat::Gray8),
+        (png::ColorType::Grayscale, png::BitDepth::Sixteen) => Some(VideoFormat::Gray16Be),
+        (png::ColorType::Rgb, png::BitDepth::Eight) => Some(VideoFormat::Rgb),
+        (png::ColorType::Rgba | png::ColorType::GrayscaleAlpha, png::BitDepth::Eight) => {
+            Some(VideoFormat::Rgba)
+        }
+        (
+            png::ColorType::Rgb | png::ColorType::Rgba | png::ColorType::GrayscaleAlpha,
+            png::BitDepth::Sixteen,
+        ) => Some(VideoFormat::Rgba64Be),
+        _ => None,
+    }
+}
+
+/// Copies a decoded line into an output line of the format returned by `output_format()`
+fn convert_line(color: png::ColorType, depth: png::BitDepth, src: &[u8], dst: &mut [u8]) {
+    match (color, depth) {
+        (png::ColorType::GrayscaleAlpha, png::BitDepth::Eight) => {
+            for (src, dst) in src.chunks_exact(2).zip(dst.chunks_exact_mut(4)) {
+                dst.copy_from_slice(&[src[0], src[0], src[0], src[1]]);
+            }
+        }
+        (png::ColorType::GrayscaleAlpha, png::BitDepth::Sixteen) => {
+            for (src, dst) in src.chunks_exact(4).zip(dst.chunks_exact_mut(8)) {
+                dst.copy_from_slice(&[
+                    src[0], src[1], src[0], src[1], src[0], src[1], src[2], src[3],
+                ]);
+            }
+        }
+        (png::ColorType::Rgb, png::BitDepth::Sixteen) => {
+            for (src, dst) in src.chunks_exact(6).zip(dst.chunks_exact_mut(8)) {
+                dst[..6].copy_from_slice(src);
+                dst[6..].copy_from_slice(&[0xff, 0xff]);
+            }
+        }
+        _ => {
+            dst[..src.len()].copy_from_slice(src);
+        }
+    }
+}
+
+impl ObjectImpl for PngDecoder {}
+
+impl GstObjectImpl for PngDecoder {}
+
+impl ElementImpl for PngDecoder {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "PNG decoder",
+                "Codec/Decoder/Image",
+                "PNG decoder",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let sink_caps = gst::Caps::builder("image/png").build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &sink_caps,
+            )
+            .unwrap();
+
+            let src_caps = gst_video::VideoCapsBuilder::new()
+                .format_list([
+                    VideoFormat::Gray8,
+                    VideoFormat::Gray16Be,
+                    VideoFormat::Rgb,
+                    VideoFormat::Rgba,
+                    VideoFormat::Rgba64Be,
+                ])
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &src_caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl VideoDecoderImpl for PngDecoder {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        // Every input buffer is a complete image
+        self.obj().set_packetized(true);
+
+        self.parent_start()
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock() = None;
+
+        self.parent_stop()
+    }
+
+    fn set_format(
+        &self,
+        state: &gst_video::VideoCodecState<'static, gst_video::video_codec_state::Readable>,
+    ) -> Result<(), gst::LoggableError> {
+        gst::debug!(CAT, imp: self, "Setting format {:?}", state.caps());
+
+        // The output format is only known once the first image is decoded
+        *self.state.lock() = Some(State {
+            input_state: state.clone(),
+            output_info: None,
+        });
+
+        self.parent_set_format(state)
+    }
+
+    fn handle_frame(
+        &self,
+        mut frame: gst_video::VideoCodecFrame,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Decoding frame {}",
+            frame.system_frame_number()
+        );
+
+        let (color, depth, width, height, line_size, data) = {
+            let input_buffer = frame.input_buffer().expect("frame without input buffer");
+            let input_map = input_buffer.map_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::CoreError::Failed,
+                    ["Failed to map input buffer readable"]
+                );
+                gst::FlowError::Error
+            })?;
+
+            let mut decoder = png::Decoder::new(input_map.as_slice());
+            // Expand palette and low bit depth images to 8 bit, and transparency chunks to an
+            // alpha channel. Interlaced images are deinterlaced by the decoder.
+            decoder.set_transformations(png::Transformations::EXPAND);
+
+            let decode_error = |e: png::DecodingError| {
+                gst::warning!(CAT, imp: self, "Failed to decode image: {e}");
+                gst::element_imp_error!(self, gst::StreamError::Decode, ["{e}"]);
+                gst::FlowError::Error
+            };
+
+            let mut reader = decoder.read_info().map_err(decode_error)?;
+            let mut data = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut data).map_err(decode_error)?;
+
+            (
+                info.color_type,
+                info.bit_depth,
+                info.width,
+                info.height,
+                info.line_size,
+                data,
+            )
+        };
+
+        let format = output_format(color, depth).ok_or_else(|| {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Unsupported color type {color:?} with depth {depth:?}"]
+            );
+            gst::FlowError::NotNegotiated
+        })?;
+
+        let output_info = {
+            let mut state_guard = self.state.lock();
+            let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
+
+            match state.output_info {
+                Some(ref info)
+                    if info.format() == format
+                        && info.width() == width
+                        && info.height() == height =>
+                {
+                    info.clone()
+                }
+                _ => {
+                    gst::debug!(
+                        CAT,
+                        imp: self,
+                        "Output format {format:?} {width}x{height}"
+                    );
+
+                    let instance = self.obj();
+                    let output_state = instance.set_output_state(
+                        format,
+                        width,
+                        height,
+                        Some(&state.input_state),
+                    )?;
+                    let info = output_state.info();
+                    state.output_info = Some(info.clone());
+                    drop(state_guard);
+
+                    instance.negotiate(output_state)?;
+
+                    info
+                }
+            }
+        };
+
+        self.obj().allocate_output_frame(&mut frame, None)?;
+        {
+            let output = frame.output_buffer_mut().unwrap();
+            let mut out_frame =
+                gst_video::VideoFrameRef::from_buffer_ref_writable(output, &output_info).map_err(
+                    |_| {
+                        gst::element_imp_error!(
+                            self,
+                            gst::CoreError::Failed,
+                            ["Failed to map output buffer writable"]
+                        );
+                        gst::FlowError::Error
+                    },
+                )?;
+
+            let out_stride = out_frame.plane_stride()[0] as usize;
+            for (src, dst) in data.chunks_exact(line_size).zip(
+                out_frame
+                    .plane_data_mut(0)
+                    .unwrap()
+                    .chunks_mut(out_stride)
+                    .take(height as usize),
+            ) {
+                convert_line(color, depth, src, dst);
+            }
+        }
+
+        // There are no such incremental frames in the png format
+        frame.set_flags(gst_video::VideoCodecFrameFlags::SYNC_POINT);
+        self.obj().finish_frame(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_line() {
+        let mut dst = [0u8; 8];
+        convert_line(
+            png::ColorType::GrayscaleAlpha,
+            png::BitDepth::Eight,
+            &[10, 20, 30, 40],
+            &mut dst,
+        );
+        assert_eq!(dst, [10, 10, 10, 20, 30, 30, 30, 40]);
+
+        let mut dst = [0u8; 8];
+        convert_line(
+            png::ColorType::Rgb,
+            png::BitDepth::Sixteen,
+            &[1, 2, 3, 4, 5, 6],
+            &mut dst,
+        );
+        assert_eq!(dst, [1, 2, 3, 4, 5, 6, 0xff, 0xff]);
+    }
+}
@@ -0,0 +1,45 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+/**
+ * element-rspngdec:
+ * @short_description: PNG decoder based on the png crate.
+ *
+ * Decodes PNG images, including interlaced (Adam7), 16 bit and indexed images with
+ * transparency, in memory-safe Rust code.
+ *
+ * Grayscale and RGB images are output as `GRAY8`/`GRAY16_BE` and `RGB`, images with an alpha
+ * channel or transparency information as `RGBA`. 16 bit color images are output as `RGBA64_BE`.
+ * Palette and low bit depth images are expanded to 8 bits per component.
+ *
+ * Each input buffer must contain a complete PNG image, as produced by `multifilesrc` or
+ * `pngparse`.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 filesrc location=image.png ! pngparse ! rspngdec ! imagefreeze ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct PngDecoder(ObjectSubclass<imp::PngDecoder>) @extends gst_video::VideoDecoder, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rspngdec",
+        gst::Rank::SECONDARY,
+        PngDecoder::static_type(),
+    )
+}
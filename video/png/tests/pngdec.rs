@@ -0,0 +1,162 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrspng::plugin_register_static().expect("Failed to register rspng plugin");
+    });
+}
+
+fn encode(
+    width: u32,
+    height: u32,
+    color: png::ColorType,
+    depth: png::BitDepth,
+    palette: Option<(&[u8], &[u8])>,
+    data: &[u8],
+) -> gst::Buffer {
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buffer, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        if let Some((palette, trns)) = palette {
+            encoder.set_palette(palette);
+            encoder.set_trns(trns);
+        }
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+    }
+
+    let mut buffer = gst::Buffer::from_mut_slice(buffer);
+    buffer.get_mut().unwrap().set_pts(gst::ClockTime::ZERO);
+    buffer
+}
+
+fn decode(buffer: gst::Buffer) -> (gst_video::VideoInfo, Vec<u8>) {
+    let mut h = gst_check::Harness::new("rspngdec");
+    h.set_src_caps_str("image/png,framerate=0/1");
+    h.play();
+
+    let buffer = h.push_and_pull(buffer).unwrap();
+    let caps = h.sinkpad().unwrap().current_caps().unwrap();
+    let info = gst_video::VideoInfo::from_caps(&caps).unwrap();
+
+    let frame = gst_video::VideoFrame::from_buffer_readable(buffer, &info).unwrap();
+    let line_size = info.width() as usize * info.format_info().pixel_stride()[0] as usize;
+    let data = frame
+        .plane_data(0)
+        .unwrap()
+        .chunks(frame.plane_stride()[0] as usize)
+        .flat_map(|line| &line[..line_size])
+        .copied()
+        .collect();
+
+    (info, data)
+}
+
+#[test]
+fn test_png_decode_rgb() {
+    init();
+
+    let data = (0..2 * 2 * 3).collect::<Vec<u8>>();
+    let (info, out) = decode(encode(
+        2,
+        2,
+        png::ColorType::Rgb,
+        png::BitDepth::Eight,
+        None,
+        &data,
+    ));
+
+    assert_eq!(info.format(), gst_video::VideoFormat::Rgb);
+    assert_eq!((info.width(), info.height()), (2, 2));
+    assert_eq!(out, data);
+}
+
+#[test]
+fn test_png_decode_rgb16() {
+    init();
+
+    let data = (0..2 * 3 * 2).collect::<Vec<u8>>();
+    let (info, out) = decode(encode(
+        2,
+        1,
+        png::ColorType::Rgb,
+        png::BitDepth::Sixteen,
+        None,
+        &data,
+    ));
+
+    assert_eq!(info.format(), gst_video::VideoFormat::Rgba64Be);
+    assert_eq!(
+        out,
+        [0, 1, 2, 3, 4, 5, 0xff, 0xff, 6, 7, 8, 9, 10, 11, 0xff, 0xff]
+    );
+}
+
+#[test]
+fn test_png_decode_indexed_transparency() {
+    init();
+
+    // Two palette entries, the second one fully transparent
+    let palette = [255, 0, 0, 0, 255, 0];
+    let trns = [255, 0];
+    let (info, out) = decode(encode(
+        3,
+        1,
+        png::ColorType::Indexed,
+        png::BitDepth::Eight,
+        Some((&palette, &trns)),
+        &[0, 1, 0],
+    ));
+
+    assert_eq!(info.format(), gst_video::VideoFormat::Rgba);
+    assert_eq!(out, [255, 0, 0, 255, 0, 255, 0, 0, 255, 0, 0, 255]);
+}
+
+#[test]
+fn test_png_roundtrip_gray16() {
+    init();
+
+    let video_info = gst_video::VideoInfo::builder(gst_video::VideoFormat::Gray16Be, 16, 8)
+        .fps((0, 1))
+        .build()
+        .unwrap();
+
+    let mut h = gst_check::Harness::new_parse("rspngenc ! rspngdec");
+    h.set_src_caps(video_info.to_caps().unwrap());
+    h.play();
+
+    let mut buffer = gst::Buffer::with_size(video_info.size()).unwrap();
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::ZERO);
+        let mut map = buffer.map_writable().unwrap();
+        for (i, v) in map.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+    }
+
+    let out = h.push_and_pull(buffer.clone()).unwrap();
+    let caps = h.sinkpad().unwrap().current_caps().unwrap();
+    assert_eq!(
+        gst_video::VideoInfo::from_caps(&caps).unwrap().format(),
+        gst_video::VideoFormat::Gray16Be
+    );
+    assert_eq!(
+        out.map_readable().unwrap().as_slice(),
+        buffer.map_readable().unwrap().as_slice()
+    );
+}
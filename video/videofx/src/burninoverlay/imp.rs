@@ -0,0 +1,529 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+use gst_video::subclass::prelude::*;
+use pango::prelude::*;
+
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+
+use super::{BurnInClockSource, BurnInPosition};
+
+const DEFAULT_TEXT: &str = "{timecode}\n{running-time}";
+const DEFAULT_POSITION: BurnInPosition = BurnInPosition::TopLeft;
+const DEFAULT_CLOCK_SOURCE: BurnInClockSource = BurnInClockSource::System;
+const DEFAULT_FONT_DESC: &str = "monospace 18";
+const DEFAULT_PADDING: u32 = 16;
+
+// Seconds between the NTP epoch (1900-01-01) and the UNIX epoch (1970-01-01)
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+// Width of the outline around the glyphs
+const OUTLINE_WIDTH: f64 = 2.;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "burninoverlay",
+        gst::DebugColorFlags::empty(),
+        Some("Time and timecode burn-in overlay"),
+    )
+});
+
+#[derive(Debug, Clone)]
+struct Settings {
+    text: String,
+    position: BurnInPosition,
+    clock_source: BurnInClockSource,
+    font_desc: String,
+    padding: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            text: String::from(DEFAULT_TEXT),
+            position: DEFAULT_POSITION,
+            clock_source: DEFAULT_CLOCK_SOURCE,
+            font_desc: String::from(DEFAULT_FONT_DESC),
+            padding: DEFAULT_PADDING,
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    layout: Option<pango::Layout>,
+}
+
+// SAFETY: Required because `pango::Layout` is not `Send` but the whole `State` needs to be.
+// We ensure that no additional references to the layout are ever created, which makes it safe
+// to send it to other threads as long as only a single thread uses it concurrently.
+unsafe impl Send for State {}
+
+#[derive(Default)]
+pub struct BurnInOverlay {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for BurnInOverlay {
+    const NAME: &'static str = "GstBurnInOverlay";
+    type Type = super::BurnInOverlay;
+    type ParentType = gst_video::VideoFilter;
+}
+
+// Formats a duration as `H:MM:SS.mmm`
+fn format_time(t: gst::ClockTime) -> String {
+    let ms = t.mseconds();
+
+    format!(
+        "{}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1_000) % 60,
+        ms % 1_000
+    )
+}
+
+// Formats nanoseconds since the UNIX epoch as `YYYY-MM-DD HH:MM:SS.mmm` in UTC
+fn format_clock_time(unix_ns: u64) -> String {
+    let ms = unix_ns / 1_000_000;
+    let days = (ms / 86_400_000) as i64;
+    let ms_of_day = ms % 86_400_000;
+
+    // Civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}.{:03}",
+        ms_of_day / 3_600_000,
+        (ms_of_day / 60_000) % 60,
+        (ms_of_day / 1_000) % 60,
+        ms_of_day % 1_000
+    )
+}
+
+impl BurnInOverlay {
+    // Wall clock time of the frame in nanoseconds since the UNIX epoch
+    fn clock_time(&self, buffer: &gst::BufferRef, clock_source: BurnInClockSource) -> Option<u64> {
+        if clock_source == BurnInClockSource::ReferenceTimestamp {
+            for meta in buffer.iter_meta::<gst::ReferenceTimestampMeta>() {
+                let Some(s) = meta.reference().structure(0) else {
+                    continue;
+                };
+
+                let timestamp = meta.timestamp().nseconds();
+                match s.name().as_str() {
+                    "timestamp/x-ntp" => {
+                        return timestamp.checked_sub(NTP_UNIX_OFFSET * 1_000_000_000);
+                    }
+                    "timestamp/x-unix" => return Some(timestamp),
+                    _ => (),
+                }
+            }
+
+            gst::trace!(
+                CAT,
+                imp: self,
+                "No NTP or UNIX reference timestamp on buffer, using system time"
+            );
+        }
+
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_nanos() as u64)
+    }
+
+    fn format_text(&self, buffer: &gst::BufferRef, settings: &Settings) -> String {
+        let mut text = settings.text.clone();
+
+        if text.contains("{running-time}") {
+            let running_time = self
+                .obj()
+                .segment()
+                .downcast_ref::<gst::ClockTime>()
+                .and_then(|segment| segment.to_running_time(buffer.pts()));
+            let running_time = running_time.map_or_else(|| String::from("--"), format_time);
+            text = text.replace("{running-time}", &running_time);
+        }
+
+        if text.contains("{pts}") {
+            let pts = buffer.pts().map_or_else(|| String::from("--"), format_time);
+            text = text.replace("{pts}", &pts);
+        }
+
+        if text.contains("{clock-time}") {
+            let clock_time = self
+                .clock_time(buffer, settings.clock_source)
+                .map_or_else(|| String::from("--"), format_clock_time);
+            text = text.replace("{clock-time}", &clock_time);
+        }
+
+        if text.contains("{timecode}") {
+            let timecode = buffer
+                .meta::<gst_video::VideoTimeCodeMeta>()
+                .map_or_else(|| String::from("--:--:--:--"), |meta| meta.tc().to_string());
+            text = text.replace("{timecode}", &timecode);
+        }
+
+        text
+    }
+
+    // Renders the text into an ARGB buffer for an overlay rectangle
+    fn render_text_buffer(
+        &self,
+        state: &mut State,
+        font_desc: &str,
+        text: &str,
+    ) -> Option<(gst::Buffer, u32, u32)> {
+        let layout = state.layout.get_or_insert_with(|| {
+            let fontmap = pangocairo::FontMap::new();
+            let context = fontmap.create_context();
+            context.set_language(Some(&pango::Language::from_string("en_US")));
+            context.set_base_dir(pango::Direction::Ltr);
+            let layout = pango::Layout::new(&context);
+            layout.set_alignment(pango::Alignment::Left);
+            let font_desc = pango::FontDescription::from_string(font_desc);
+            layout.set_font_description(Some(&font_desc));
+
+            layout
+        });
+
+        layout.set_text(text);
+        let (_ink_rect, logical_rect) = layout.extents();
+
+        let margin = OUTLINE_WIDTH.ceil() as i32;
+        let width = (logical_rect.width() / pango::SCALE + 2 * margin).max(1) as u32;
+        let height = (logical_rect.height() / pango::SCALE + 2 * margin).max(1) as u32;
+
+        let mut buffer = gst::Buffer::with_size((width * height) as usize * 4).ok()?;
+
+        gst_video::VideoMeta::add(
+            buffer.get_mut().unwrap(),
+            gst_video::VideoFrameFlags::empty(),
+            #[cfg(target_endian = "little")]
+            gst_video::VideoFormat::Bgra,
+            #[cfg(target_endian = "big")]
+            gst_video::VideoFormat::Argb,
+            width,
+            height,
+        )
+        .ok()?;
+
+        let buffer = buffer.into_mapped_buffer_writable().unwrap();
+
+        // Pass ownership of the buffer to the cairo surface but keep around
+        // a raw pointer so we can later retrieve it again when the surface
+        // is done
+        let buffer_ptr = buffer.buffer().as_ptr();
+        let surface = cairo::ImageSurface::create_for_data(
+            buffer,
+            cairo::Format::ARgb32,
+            width as i32,
+            height as i32,
+            width as i32 * 4,
+        )
+        .ok()?;
+
+        let cr = cairo::Context::new(&surface).ok()?;
+
+        // Clear background
+        cr.set_operator(cairo::Operator::Source);
+        cr.set_source_rgba(0.0, 0.0, 0.0, 0.0);
+        cr.paint().ok()?;
+
+        // White outline and black glyphs keep the text readable on any content
+        cr.set_operator(cairo::Operator::Over);
+        cr.save().ok()?;
+        cr.move_to(margin as f64, margin as f64);
+        cr.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+        cr.set_line_width(2. * OUTLINE_WIDTH);
+        pangocairo::functions::layout_path(&cr, layout);
+        cr.stroke().ok()?;
+        cr.restore().ok()?;
+
+        cr.save().ok()?;
+        cr.move_to(margin as f64, margin as f64);
+        cr.set_source_rgba(0.0, 0.0, 0.0, 1.0);
+        pangocairo::functions::show_layout(&cr, layout);
+        cr.restore().ok()?;
+
+        drop(cr);
+
+        // Safety: The surface still owns a mutable reference to the buffer but our reference
+        // to the surface here is the last one. After dropping the surface the buffer would be
+        // freed, so we keep an additional strong reference here before dropping the surface,
+        // which is then returned. As such it's guaranteed that nothing is using the buffer
+        // anymore mutably.
+        unsafe {
+            assert_eq!(
+                cairo::ffi::cairo_surface_get_reference_count(surface.to_raw_none()),
+                1
+            );
+            let buffer = glib::translate::from_glib_none(buffer_ptr);
+            drop(surface);
+            Some((buffer, width, height))
+        }
+    }
+}
+
+impl ObjectImpl for BurnInOverlay {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("text")
+                    .nick("Text")
+                    .blurb("Text to render, {running-time}, {pts}, {clock-time} and {timecode} are replaced by their values")
+                    .default_value(Some(DEFAULT_TEXT))
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("position", DEFAULT_POSITION)
+                    .nick("Position")
+                    .blurb("Position of the text in the frame")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("clock-source", DEFAULT_CLOCK_SOURCE)
+                    .nick("Clock Source")
+                    .blurb("Source of the wall clock time for {clock-time}")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("font-desc")
+                    .nick("Font Description")
+                    .blurb("Pango font description of font to be used for rendering")
+                    .default_value(Some(DEFAULT_FONT_DESC))
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("padding")
+                    .nick("Padding")
+                    .blurb("Distance in pixels of the text from the edges of the frame")
+                    .default_value(DEFAULT_PADDING)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "text" => {
+                settings.text = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_default();
+            }
+            "position" => {
+                settings.position = value.get().expect("type checked upstream");
+            }
+            "clock-source" => {
+                settings.clock_source = value.get().expect("type checked upstream");
+            }
+            "font-desc" => {
+                settings.font_desc = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| String::from(DEFAULT_FONT_DESC));
+                drop(settings);
+
+                // Recreated with the new font on the next frame
+                self.state.lock().unwrap().layout = None;
+            }
+            "padding" => {
+                settings.padding = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "text" => settings.text.to_value(),
+            "position" => settings.position.to_value(),
+            "clock-source" => settings.clock_source.to_value(),
+            "font-desc" => settings.font_desc.to_value(),
+            "padding" => settings.padding.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for BurnInOverlay {}
+
+impl ElementImpl for BurnInOverlay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Burn-in overlay",
+                "Filter/Editor/Video",
+                "Burns running time, clock time and timecode into video frames",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format_list([
+                    gst_video::VideoFormat::I420,
+                    gst_video::VideoFormat::Yv12,
+                    gst_video::VideoFormat::Nv12,
+                    gst_video::VideoFormat::Nv21,
+                    gst_video::VideoFormat::Y444,
+                    gst_video::VideoFormat::Y42b,
+                    gst_video::VideoFormat::Yuy2,
+                    gst_video::VideoFormat::Uyvy,
+                    gst_video::VideoFormat::Ayuv,
+                    gst_video::VideoFormat::Rgbx,
+                    gst_video::VideoFormat::Xrgb,
+                    gst_video::VideoFormat::Bgrx,
+                    gst_video::VideoFormat::Xbgr,
+                    gst_video::VideoFormat::Rgba,
+                    gst_video::VideoFormat::Argb,
+                    gst_video::VideoFormat::Bgra,
+                    gst_video::VideoFormat::Abgr,
+                    gst_video::VideoFormat::Rgb,
+                    gst_video::VideoFormat::Bgr,
+                ])
+                .build();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for BurnInOverlay {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+}
+
+impl VideoFilterImpl for BurnInOverlay {
+    fn transform_frame_ip(
+        &self,
+        frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = self.settings.lock().unwrap().clone();
+        if settings.text.is_empty() {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let text = self.format_text(frame.buffer(), &settings);
+        gst::trace!(CAT, imp: self, "Rendering {text:?}");
+
+        let mut state = self.state.lock().unwrap();
+        let Some((buffer, width, height)) =
+            self.render_text_buffer(&mut state, &settings.font_desc, &text)
+        else {
+            gst::warning!(CAT, imp: self, "Failed to render text {text:?}");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+        drop(state);
+
+        let frame_width = frame.width() as i32;
+        let frame_height = frame.height() as i32;
+        let padding = settings.padding as i32;
+        let (width, height) = (width as i32, height as i32);
+
+        let (x, y) = match settings.position {
+            BurnInPosition::TopLeft => (padding, padding),
+            BurnInPosition::TopRight => (frame_width - width - padding, padding),
+            BurnInPosition::BottomLeft => (padding, frame_height - height - padding),
+            BurnInPosition::BottomRight => (
+                frame_width - width - padding,
+                frame_height - height - padding,
+            ),
+            _ => ((frame_width - width) / 2, (frame_height - height) / 2),
+        };
+
+        let rect = gst_video::VideoOverlayRectangle::new_raw(
+            &buffer,
+            x,
+            y,
+            width as u32,
+            height as u32,
+            gst_video::VideoOverlayFormatFlags::PREMULTIPLIED_ALPHA,
+        );
+        let composition = gst_video::VideoOverlayComposition::new(Some(&rect)).unwrap();
+
+        composition.blend(frame).map_err(|err| {
+            gst::error!(CAT, imp: self, "Failed to blend text: {err}");
+            gst::FlowError::Error
+        })?;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_time(gst::ClockTime::ZERO), "0:00:00.000");
+        assert_eq!(
+            format_time(gst::ClockTime::from_mseconds(3_723_456)),
+            "1:02:03.456"
+        );
+
+        assert_eq!(format_clock_time(0), "1970-01-01 00:00:00.000");
+        // 2024-02-29 12:34:56.789 UTC
+        assert_eq!(
+            format_clock_time(1_709_210_096_789_000_000),
+            "2024-02-29 12:34:56.789"
+        );
+    }
+}
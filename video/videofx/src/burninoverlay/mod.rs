@@ -0,0 +1,90 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-burninoverlay:
+ * @short_description: Burns running time, clock time and SMPTE timecode into video frames.
+ *
+ * Renders a line of text per frame from the `text` template and blends it into the frame. The
+ * following placeholders are replaced:
+ *
+ * - `{running-time}`: running time of the frame as `H:MM:SS.mmm`
+ * - `{pts}`: timestamp of the frame as `H:MM:SS.mmm`
+ * - `{clock-time}`: wall clock time as `YYYY-MM-DD HH:MM:SS.mmm` in UTC
+ * - `{timecode}`: SMPTE timecode from the `GstVideoTimeCodeMeta` of the frame, e.g. as added by
+ *   `timecodestamper`, or `--:--:--:--` if there is none
+ *
+ * With `clock-source=system`, the clock time is the system time when the frame is processed.
+ * With `clock-source=reference-timestamp`, it is taken from the `GstReferenceTimestampMeta` of the
+ * frame with `timestamp/x-ntp` or `timestamp/x-unix` caps, e.g. as added by `rtpjitterbuffer`
+ * with `add-reference-timestamp-meta` from the NTP synchronized capture time of the sender.
+ *
+ * Burning the system clock time on the sender and on the receiver of an NTP synchronized setup
+ * allows reading the end-to-end latency from a single picture of both displays.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 videotestsrc ! timecodestamper ! \
+ *   burninoverlay text="{timecode} {clock-time}" position=bottom-left ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct BurnInOverlay(ObjectSubclass<imp::BurnInOverlay>) @extends gst_video::VideoFilter, gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "burninoverlay",
+        gst::Rank::NONE,
+        BurnInOverlay::static_type(),
+    )
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstBurnInPosition")]
+#[non_exhaustive]
+pub enum BurnInPosition {
+    #[enum_value(name = "TopLeft: Top left corner.", nick = "top-left")]
+    TopLeft = 0,
+
+    #[enum_value(name = "TopRight: Top right corner.", nick = "top-right")]
+    TopRight = 1,
+
+    #[enum_value(name = "BottomLeft: Bottom left corner.", nick = "bottom-left")]
+    BottomLeft = 2,
+
+    #[enum_value(name = "BottomRight: Bottom right corner.", nick = "bottom-right")]
+    BottomRight = 3,
+
+    #[enum_value(name = "Center: Center of the frame.", nick = "center")]
+    Center = 4,
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstBurnInClockSource")]
+#[non_exhaustive]
+pub enum BurnInClockSource {
+    #[enum_value(
+        name = "System: System time when the frame is processed.",
+        nick = "system"
+    )]
+    System = 0,
+
+    #[enum_value(
+        name = "ReferenceTimestamp: NTP or UNIX time from the reference timestamp meta of the frame.",
+        nick = "reference-timestamp"
+    )]
+    ReferenceTimestamp = 1,
+}
@@ -0,0 +1,167 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+// Per plane field interpolation. All bytes of a line are handled independently, so that the
+// inner loops work the same for all 8 bit formats and can be vectorized by the compiler.
+
+use super::DeinterlaceMode;
+use crate::plane::{Plane, PlaneMut};
+
+// The field of a frame to reconstruct and its surroundings
+pub struct Field<'a> {
+    pub prev: &'a Plane<'a>,
+    pub cur: &'a Plane<'a>,
+    pub next: &'a Plane<'a>,
+    // Parity of the lines of the field: 0 for the top field, 1 for the bottom field
+    pub parity: usize,
+    // Whether this is the first field of the frame in time
+    pub first: bool,
+}
+
+fn line<'a>(plane: &'a Plane, y: usize) -> &'a [u8] {
+    let len = plane.width * plane.pstride;
+    &plane.data[y * plane.stride..][..len]
+}
+
+// Line `y` clamped to the plane, keeping the parity of `y`. Requires a height of at least 2.
+fn clamp_line(y: isize, height: usize) -> usize {
+    let last = height as isize - 1;
+
+    if y < 0 {
+        y.rem_euclid(2) as usize
+    } else if y > last {
+        (last - (y - last) % 2) as usize
+    } else {
+        y as usize
+    }
+}
+
+pub fn deinterlace_plane(field: &Field, out: &mut PlaneMut, mode: DeinterlaceMode) {
+    let height = field.cur.height;
+    let len = field.cur.width * field.cur.pstride;
+
+    for y in 0..height {
+        let out_line = &mut out.data[y * out.stride..][..len];
+
+        if y % 2 == field.parity || height < 3 || mode == DeinterlaceMode::Weave {
+            out_line.copy_from_slice(line(field.cur, y));
+            continue;
+        }
+
+        let above = clamp_line(y as isize - 1, height);
+        let below = clamp_line(y as isize + 1, height);
+
+        match mode {
+            DeinterlaceMode::Bob => {
+                bob_line(out_line, line(field.cur, above), line(field.cur, below));
+            }
+            _ => {
+                // Frames containing the other field right before and after this field
+                let (prev2, next2) = if field.first {
+                    (field.prev, field.cur)
+                } else {
+                    (field.cur, field.next)
+                };
+                let above2 = clamp_line(y as isize - 2, height);
+                let below2 = clamp_line(y as isize + 2, height);
+
+                adaptive_line(
+                    out_line,
+                    AdaptiveLines {
+                        c: line(field.cur, above),
+                        e: line(field.cur, below),
+                        prev_c: line(field.prev, above),
+                        prev_e: line(field.prev, below),
+                        next_c: line(field.next, above),
+                        next_e: line(field.next, below),
+                        prev2: line(prev2, y),
+                        next2: line(next2, y),
+                        prev2_b: line(prev2, above2),
+                        next2_b: line(next2, above2),
+                        prev2_f: line(prev2, below2),
+                        next2_f: line(next2, below2),
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn bob_line(out: &mut [u8], above: &[u8], below: &[u8]) {
+    for ((out, &above), &below) in out.iter_mut().zip(above).zip(below) {
+        *out = ((above as u16 + below as u16 + 1) >> 1) as u8;
+    }
+}
+
+// Lines around a missing line, named after the yadif filter
+struct AdaptiveLines<'a> {
+    // Lines above and below in the current field
+    c: &'a [u8],
+    e: &'a [u8],
+    // Same lines in the previous and next frames
+    prev_c: &'a [u8],
+    prev_e: &'a [u8],
+    next_c: &'a [u8],
+    next_e: &'a [u8],
+    // The missing line and the ones two lines above and below in the other field, before and
+    // after the current field
+    prev2: &'a [u8],
+    next2: &'a [u8],
+    prev2_b: &'a [u8],
+    next2_b: &'a [u8],
+    prev2_f: &'a [u8],
+    next2_f: &'a [u8],
+}
+
+fn adaptive_line(out: &mut [u8], l: AdaptiveLines) {
+    let len = out.len();
+    let (c, e) = (&l.c[..len], &l.e[..len]);
+    let (prev_c, prev_e) = (&l.prev_c[..len], &l.prev_e[..len]);
+    let (next_c, next_e) = (&l.next_c[..len], &l.next_e[..len]);
+    let (prev2, next2) = (&l.prev2[..len], &l.next2[..len]);
+    let (prev2_b, next2_b) = (&l.prev2_b[..len], &l.next2_b[..len]);
+    let (prev2_f, next2_f) = (&l.prev2_f[..len], &l.next2_f[..len]);
+
+    for x in 0..len {
+        let c_x = c[x] as i32;
+        let e_x = e[x] as i32;
+
+        // Temporal prediction and how much the picture changes around it
+        let d = (prev2[x] as i32 + next2[x] as i32) >> 1;
+        let temporal_diff0 = (prev2[x] as i32 - next2[x] as i32).abs();
+        let temporal_diff1 = ((prev_c[x] as i32 - c_x).abs() + (prev_e[x] as i32 - e_x).abs()) >> 1;
+        let temporal_diff2 = ((next_c[x] as i32 - c_x).abs() + (next_e[x] as i32 - e_x).abs()) >> 1;
+        let mut diff = (temporal_diff0 >> 1)
+            .max(temporal_diff1)
+            .max(temporal_diff2);
+
+        // Allow more spatial interpolation where the temporal prediction would create combing
+        let b = (prev2_b[x] as i32 + next2_b[x] as i32) >> 1;
+        let f = (prev2_f[x] as i32 + next2_f[x] as i32) >> 1;
+        let max = (d - e_x).max(d - c_x).max((b - c_x).min(f - e_x));
+        let min = (d - e_x).min(d - c_x).min((b - c_x).max(f - e_x));
+        diff = diff.max(min).max(-max);
+
+        let spatial = (c_x + e_x) >> 1;
+        out[x] = spatial.clamp(d - diff, d + diff) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clamp_line() {
+        assert_eq!(clamp_line(-1, 8), 1);
+        assert_eq!(clamp_line(-2, 8), 0);
+        assert_eq!(clamp_line(3, 8), 3);
+        assert_eq!(clamp_line(8, 8), 6);
+        assert_eq!(clamp_line(9, 8), 7);
+        assert_eq!(clamp_line(8, 9), 8);
+        assert_eq!(clamp_line(9, 9), 7);
+    }
+}
@@ -0,0 +1,619 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_transform::GenerateOutputSuccess;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use super::filter::{self, Field};
+use super::DeinterlaceMode;
+use crate::plane::{Plane, PlaneMut};
+
+const DEFAULT_MODE: DeinterlaceMode = DeinterlaceMode::Adaptive;
+const DEFAULT_DOUBLE_RATE: bool = false;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "rsdeinterlace",
+        gst::DebugColorFlags::empty(),
+        Some("Motion adaptive deinterlacer"),
+    )
+});
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    mode: DeinterlaceMode,
+    double_rate: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: DEFAULT_MODE,
+            double_rate: DEFAULT_DOUBLE_RATE,
+        }
+    }
+}
+
+struct State {
+    in_info: gst_video::VideoInfo,
+    out_info: gst_video::VideoInfo,
+    // The frame to deinterlace next and the one before it
+    prev: Option<gst::Buffer>,
+    cur: Option<gst::Buffer>,
+    // Deinterlaced frames waiting to be output
+    pending: VecDeque<gst::Buffer>,
+}
+
+impl State {
+    fn new(in_info: gst_video::VideoInfo, out_info: gst_video::VideoInfo) -> Self {
+        State {
+            in_info,
+            out_info,
+            prev: None,
+            cur: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.prev = None;
+        self.cur = None;
+        self.pending.clear();
+    }
+
+    fn frame_duration(&self, buffer: &gst::Buffer) -> Option<gst::ClockTime> {
+        let fps = self.in_info.fps();
+
+        buffer.duration().or_else(|| {
+            (fps.numer() > 0).then(|| {
+                gst::ClockTime::SECOND
+                    .mul_div_round(fps.denom() as u64, fps.numer() as u64)
+                    .unwrap()
+            })
+        })
+    }
+
+    // Whether `buffer` is interlaced and its top field is the first in time
+    fn field_info(&self, buffer: &gst::Buffer) -> (bool, bool) {
+        let flags = gst_video::VideoBufferFlags::from_bits_truncate(buffer.flags().bits());
+
+        match self.in_info.interlace_mode() {
+            gst_video::VideoInterlaceMode::Mixed => (
+                flags.contains(gst_video::VideoBufferFlags::INTERLACED),
+                flags.contains(gst_video::VideoBufferFlags::TFF),
+            ),
+            gst_video::VideoInterlaceMode::Progressive => (false, true),
+            _ => (
+                true,
+                self.in_info.field_order() != gst_video::VideoFieldOrder::BottomFieldFirst,
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Deinterlace {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+impl Deinterlace {
+    // Reconstructs the field of `cur` with lines of `parity` into a progressive frame
+    fn deinterlace_field(
+        &self,
+        state: &State,
+        mode: DeinterlaceMode,
+        next: &gst::Buffer,
+        parity: usize,
+        first: bool,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let cur = state.cur.as_ref().unwrap();
+        let prev = state.prev.as_ref().unwrap_or(cur);
+
+        let map = |buffer: &gst::Buffer| {
+            gst_video::VideoFrameRef::from_buffer_ref_readable(buffer.as_ref(), &state.in_info)
+                .map_err(|_| {
+                    gst::error!(CAT, imp: self, "Failed to map input frame");
+                    gst::FlowError::Error
+                })
+        };
+        let prev_frame = map(prev)?;
+        let cur_frame = map(cur)?;
+        let next_frame = map(next)?;
+
+        let mut outbuf =
+            gst::Buffer::with_size(state.out_info.size()).map_err(|_| gst::FlowError::Error)?;
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            let mut out_frame =
+                gst_video::VideoFrameRef::from_buffer_ref_writable(outbuf, &state.out_info)
+                    .unwrap();
+
+            for p in 0..state.in_info.n_planes() {
+                let prev_plane = Plane::new(&prev_frame, p);
+                let cur_plane = Plane::new(&cur_frame, p);
+                let next_plane = Plane::new(&next_frame, p);
+                let mut out_plane = PlaneMut::new(&mut out_frame, p);
+
+                let field = Field {
+                    prev: &prev_plane,
+                    cur: &cur_plane,
+                    next: &next_plane,
+                    parity,
+                    first,
+                };
+                filter::deinterlace_plane(&field, &mut out_plane, mode);
+            }
+        }
+
+        Ok(outbuf)
+    }
+
+    // Deinterlaces the current frame into one or two pending output frames
+    fn process_current(
+        &self,
+        state: &mut State,
+        settings: &Settings,
+        next: Option<&gst::Buffer>,
+    ) -> Result<(), gst::FlowError> {
+        let Some(cur) = state.cur.clone() else {
+            return Ok(());
+        };
+        let next = next.unwrap_or(&cur);
+
+        let (interlaced, tff) = state.field_info(&cur);
+        let n_fields = if settings.double_rate { 2 } else { 1 };
+        let duration = state.frame_duration(&cur);
+        let field_duration = duration.map(|duration| duration / n_fields);
+
+        for i in 0..n_fields {
+            // Top field has parity 0, and the first field in time is output first
+            let first = i == 0;
+            let parity = if first == tff { 0 } else { 1 };
+
+            let mut outbuf = if interlaced {
+                let mut outbuf =
+                    self.deinterlace_field(state, settings.mode, next, parity, first)?;
+                cur.copy_into(
+                    outbuf.get_mut().unwrap(),
+                    gst::BufferCopyFlags::FLAGS | gst::BufferCopyFlags::TIMESTAMPS,
+                    ..,
+                )
+                .map_err(|_| gst::FlowError::Error)?;
+                outbuf
+            } else {
+                cur.copy()
+            };
+
+            {
+                let outbuf = outbuf.get_mut().unwrap();
+                outbuf.unset_flags(gst::BufferFlags::from_bits_truncate(
+                    (gst_video::VideoBufferFlags::INTERLACED
+                        | gst_video::VideoBufferFlags::TFF
+                        | gst_video::VideoBufferFlags::RFF
+                        | gst_video::VideoBufferFlags::ONEFIELD)
+                        .bits(),
+                ));
+
+                if settings.double_rate {
+                    let offset = field_duration.map(|d| d * i);
+                    outbuf.set_pts(cur.pts().opt_add(offset));
+                    outbuf.set_dts(gst::ClockTime::NONE);
+                    outbuf.set_duration(field_duration);
+                    outbuf.set_offset(gst::BUFFER_OFFSET_NONE);
+                    outbuf.set_offset_end(gst::BUFFER_OFFSET_NONE);
+                    if !first {
+                        outbuf.unset_flags(gst::BufferFlags::DISCONT);
+                    }
+                }
+            }
+
+            gst::trace!(
+                CAT,
+                imp: self,
+                "Output field {} of frame {} at {}",
+                i,
+                cur.pts().display(),
+                outbuf.pts().display(),
+            );
+
+            state.pending.push_back(outbuf);
+        }
+
+        Ok(())
+    }
+
+    // Outputs the last frame
+    fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+        let buffers = {
+            let mut state_guard = self.state.lock().unwrap();
+            let Some(state) = state_guard.as_mut() else {
+                return Ok(gst::FlowSuccess::Ok);
+            };
+
+            self.process_current(state, &settings, None)?;
+            let buffers = std::mem::take(&mut state.pending);
+            state.reset();
+
+            buffers
+        };
+
+        gst::debug!(CAT, imp: self, "Draining {} frames", buffers.len());
+
+        for buffer in buffers {
+            self.obj().src_pad().push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Deinterlace {
+    const NAME: &'static str = "GstRsDeinterlace";
+    type Type = super::Deinterlace;
+    type ParentType = gst_base::BaseTransform;
+}
+
+impl ObjectImpl for Deinterlace {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("How the missing lines of each field are reconstructed")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("double-rate")
+                    .nick("Double Rate")
+                    .blurb("Output one frame per field instead of one per frame")
+                    .default_value(DEFAULT_DOUBLE_RATE)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "mode" => {
+                let mut settings = self.settings.lock().unwrap();
+                let mode = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing mode from {:?} to {:?}",
+                    settings.mode,
+                    mode
+                );
+                settings.mode = mode;
+            }
+            "double-rate" => {
+                let mut settings = self.settings.lock().unwrap();
+                let double_rate = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing double-rate from {} to {}",
+                    settings.double_rate,
+                    double_rate
+                );
+                settings.double_rate = double_rate;
+                drop(settings);
+
+                self.obj().reconfigure_src();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "mode" => {
+                let settings = self.settings.lock().unwrap();
+                settings.mode.to_value()
+            }
+            "double-rate" => {
+                let settings = self.settings.lock().unwrap();
+                settings.double_rate.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for Deinterlace {}
+
+impl ElementImpl for Deinterlace {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Deinterlacer",
+                "Filter/Effect/Video/Deinterlace",
+                "Deinterlaces video by weaving, bobbing or motion adaptive interpolation",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let formats = [
+                gst_video::VideoFormat::I420,
+                gst_video::VideoFormat::Yv12,
+                gst_video::VideoFormat::Y42b,
+                gst_video::VideoFormat::Y444,
+                gst_video::VideoFormat::Nv12,
+                gst_video::VideoFormat::Nv21,
+                gst_video::VideoFormat::Yuy2,
+                gst_video::VideoFormat::Uyvy,
+                gst_video::VideoFormat::Ayuv,
+                gst_video::VideoFormat::Gray8,
+                gst_video::VideoFormat::Rgbx,
+                gst_video::VideoFormat::Xrgb,
+                gst_video::VideoFormat::Bgrx,
+                gst_video::VideoFormat::Xbgr,
+                gst_video::VideoFormat::Rgba,
+                gst_video::VideoFormat::Argb,
+                gst_video::VideoFormat::Bgra,
+                gst_video::VideoFormat::Abgr,
+                gst_video::VideoFormat::Rgb,
+                gst_video::VideoFormat::Bgr,
+            ];
+
+            let sink_caps = gst_video::VideoCapsBuilder::new()
+                .format_list(formats)
+                .field(
+                    "interlace-mode",
+                    gst::List::new(["progressive", "interleaved", "mixed"]),
+                )
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &sink_caps,
+            )
+            .unwrap();
+
+            let src_caps = gst_video::VideoCapsBuilder::new()
+                .format_list(formats)
+                .field("interlace-mode", "progressive")
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &src_caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+// Scales a fixed framerate by `numer / denom`, leaving other values unchanged
+fn scale_framerate(s: &mut gst::StructureRef, numer: i32, denom: i32) {
+    if let Ok(framerate) = s.get::<gst::Fraction>("framerate") {
+        if framerate.numer() > 0 {
+            s.set("framerate", framerate * gst::Fraction::new(numer, denom));
+        }
+    }
+}
+
+impl BaseTransformImpl for Deinterlace {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::NeverInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = true;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn transform_caps(
+        &self,
+        direction: gst::PadDirection,
+        caps: &gst::Caps,
+        filter: Option<&gst::Caps>,
+    ) -> Option<gst::Caps> {
+        let double_rate = self.settings.lock().unwrap().double_rate;
+
+        let mut other_caps = gst::Caps::new_empty();
+        {
+            let other_caps = other_caps.get_mut().unwrap();
+
+            for (s, features) in caps.iter_with_features() {
+                if direction == gst::PadDirection::Sink {
+                    let progressive = s
+                        .get::<&str>("interlace-mode")
+                        .map_or(true, |mode| mode == "progressive");
+
+                    let mut s = s.to_owned();
+                    if !progressive {
+                        s.set("interlace-mode", "progressive");
+                        s.remove_field("field-order");
+                        if double_rate {
+                            scale_framerate(&mut s, 2, 1);
+                        }
+                    }
+                    other_caps.append_structure_full(s, Some(features.to_owned()));
+                } else {
+                    // Progressive input is passed through
+                    other_caps.append_structure_full(s.to_owned(), Some(features.to_owned()));
+
+                    let mut s = s.to_owned();
+                    s.set("interlace-mode", gst::List::new(["interleaved", "mixed"]));
+                    if double_rate {
+                        scale_framerate(&mut s, 1, 2);
+                    }
+                    other_caps.append_structure_full(s, Some(features.to_owned()));
+                }
+            }
+        }
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Transformed caps from {} to {} in direction {:?}",
+            caps,
+            other_caps,
+            direction
+        );
+
+        if let Some(filter) = filter {
+            other_caps = filter.intersect_with_mode(&other_caps, gst::CapsIntersectMode::First);
+        }
+
+        Some(other_caps)
+    }
+
+    fn set_caps(&self, incaps: &gst::Caps, outcaps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let in_info = gst_video::VideoInfo::from_caps(incaps)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to parse input caps"))?;
+        let out_info = gst_video::VideoInfo::from_caps(outcaps)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to parse output caps"))?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Configured for caps {} to {}",
+            incaps,
+            outcaps
+        );
+
+        *self.state.lock().unwrap() = Some(State::new(in_info, out_info));
+
+        Ok(())
+    }
+
+    fn generate_output(&self) -> Result<GenerateOutputSuccess, gst::FlowError> {
+        let queued = self.take_queued_buffer();
+
+        if self.obj().is_passthrough() {
+            return Ok(match queued {
+                Some(buffer) => GenerateOutputSuccess::Buffer(buffer),
+                None => GenerateOutputSuccess::NoOutput,
+            });
+        }
+
+        let settings = *self.settings.lock().unwrap();
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(
+                self,
+                gst::CoreError::Negotiation,
+                ["Can not generate an output without State"]
+            );
+            gst::FlowError::NotNegotiated
+        })?;
+
+        if let Some(buffer) = queued {
+            if buffer.flags().contains(gst::BufferFlags::DISCONT) && state.cur.is_some() {
+                gst::debug!(CAT, imp: self, "Discontinuity, restarting");
+                // Finish the frame before the discontinuity without looking across it
+                self.process_current(state, &settings, None)?;
+                state.prev = None;
+                state.cur = None;
+            }
+
+            if state.cur.is_some() {
+                self.process_current(state, &settings, Some(&buffer))?;
+                state.prev = state.cur.take();
+            }
+            state.cur = Some(buffer);
+        }
+
+        Ok(match state.pending.pop_front() {
+            Some(buffer) => GenerateOutputSuccess::Buffer(buffer),
+            None => GenerateOutputSuccess::NoOutput,
+        })
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        match event.view() {
+            EventView::Eos(_) | EventView::Segment(_) => {
+                gst::debug!(CAT, imp: self, "Draining on {:?}", event.type_());
+                if self.drain().is_err() {
+                    return false;
+                }
+            }
+            EventView::FlushStop(_) => {
+                if let Some(state) = self.state.lock().unwrap().as_mut() {
+                    state.reset();
+                }
+            }
+            _ => (),
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn query(&self, direction: gst::PadDirection, query: &mut gst::QueryRef) -> bool {
+        if direction == gst::PadDirection::Src {
+            if let gst::QueryViewMut::Latency(q) = query.view_mut() {
+                let mut upstream_query = gst::query::Latency::new();
+                if self.obj().sink_pad().peer_query(&mut upstream_query) {
+                    let (live, mut min, mut max) = upstream_query.result();
+                    gst::debug!(
+                        CAT,
+                        imp: self,
+                        "Peer latency: live {} min {} max {}",
+                        live,
+                        min,
+                        max.display(),
+                    );
+
+                    // Need to wait for the next input frame before deinterlacing
+                    if !self.obj().is_passthrough() {
+                        let in_fps = self
+                            .state
+                            .lock()
+                            .unwrap()
+                            .as_ref()
+                            .map(|state| state.in_info.fps());
+                        if let Some(frame_duration) =
+                            in_fps.filter(|fps| fps.numer() > 0).and_then(|fps| {
+                                gst::ClockTime::SECOND
+                                    .mul_div_ceil(fps.denom() as u64, fps.numer() as u64)
+                            })
+                        {
+                            min += frame_duration;
+                            max = max.opt_add(frame_duration);
+                        }
+                    }
+
+                    q.set(live, min, max);
+                    return true;
+                }
+            }
+        }
+
+        BaseTransformImplExt::parent_query(self, direction, query)
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let _ = self.state.lock().unwrap().take();
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+}
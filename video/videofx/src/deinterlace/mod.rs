@@ -0,0 +1,76 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-rsdeinterlace:
+ * @short_description: Deinterlaces video with a motion adaptive (yadif-style) filter.
+ *
+ * The fields of interlaced frames are reconstructed to full progressive frames. The missing lines
+ * of a field are either taken unchanged from the other field of the same frame (`weave`),
+ * interpolated from the lines above and below (`bob`), or, in `adaptive` mode, interpolated
+ * temporally from the previous and next fields where the picture is static and spatially where
+ * it moves, like the yadif filter.
+ *
+ * The field order and which frames are interlaced are taken from the `interlace-mode` and
+ * `field-order` of the caps, and from the buffer flags for `mixed` content. Progressive input
+ * is passed through.
+ *
+ * By default one frame is output per input frame, built from the first field in time. With
+ * `double-rate`, one frame is output per field, doubling the framerate and preserving the
+ * temporal resolution of the interlaced content.
+ *
+ * The element has a latency of one input frame as it needs the next frame for the temporal
+ * interpolation.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 videotestsrc pattern=ball ! video/x-raw,interlace-mode=interleaved ! \
+ *   rsdeinterlace mode=adaptive double-rate=true ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod filter;
+mod imp;
+
+glib::wrapper! {
+    pub struct Deinterlace(ObjectSubclass<imp::Deinterlace>) @extends gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsdeinterlace",
+        gst::Rank::NONE,
+        Deinterlace::static_type(),
+    )
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRsDeinterlaceMode")]
+#[non_exhaustive]
+pub enum DeinterlaceMode {
+    #[enum_value(
+        name = "Weave: Take the missing lines from the other field of the frame.",
+        nick = "weave"
+    )]
+    Weave = 0,
+
+    #[enum_value(
+        name = "Bob: Interpolate the missing lines from the lines above and below.",
+        nick = "bob"
+    )]
+    Bob = 1,
+
+    #[enum_value(
+        name = "Adaptive: Interpolate temporally in static and spatially in moving areas.",
+        nick = "adaptive"
+    )]
+    Adaptive = 2,
+}
@@ -0,0 +1,640 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_transform::GenerateOutputSuccess;
+use gst_base::subclass::prelude::*;
+
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use super::motion::{self, MotionField};
+use super::FramerateConvertMode;
+use crate::plane::{Plane, PlaneMut};
+
+const DEFAULT_MODE: FramerateConvertMode = FramerateConvertMode::Blend;
+const DEFAULT_SEARCH_RANGE: u32 = 16;
+const DEFAULT_FPS_N: i32 = 30;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "framerateconvert",
+        gst::DebugColorFlags::empty(),
+        Some("Interpolating framerate converter"),
+    )
+});
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    mode: FramerateConvertMode,
+    search_range: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: DEFAULT_MODE,
+            search_range: DEFAULT_SEARCH_RANGE,
+        }
+    }
+}
+
+struct State {
+    in_info: gst_video::VideoInfo,
+    out_info: gst_video::VideoInfo,
+    // The two input frames surrounding the next output frame
+    prev: Option<gst::Buffer>,
+    next: Option<gst::Buffer>,
+    // Timestamp of the first output frame and number of frames output since then
+    base_pts: Option<gst::ClockTime>,
+    n_out: u64,
+    discont: bool,
+    // Motion between `prev` and `next`, estimated on first use
+    motion: Option<MotionField>,
+}
+
+impl State {
+    fn new(in_info: gst_video::VideoInfo, out_info: gst_video::VideoInfo) -> Self {
+        State {
+            in_info,
+            out_info,
+            prev: None,
+            next: None,
+            base_pts: None,
+            n_out: 0,
+            discont: true,
+            motion: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.prev = None;
+        self.next = None;
+        self.base_pts = None;
+        self.n_out = 0;
+        self.discont = true;
+        self.motion = None;
+    }
+
+    fn out_pts(&self, n: u64) -> Option<gst::ClockTime> {
+        let fps = self.out_info.fps();
+
+        self.base_pts.map(|base_pts| {
+            base_pts
+                + gst::ClockTime::SECOND
+                    .mul_div_round(n * fps.denom() as u64, fps.numer() as u64)
+                    .unwrap()
+        })
+    }
+
+    // Creates the next output frame from `buffer`, only updating the metadata
+    fn output_copy(&mut self, buffer: &gst::Buffer) -> gst::Buffer {
+        let mut outbuf = buffer.copy();
+        self.set_output_metadata(outbuf.get_mut().unwrap());
+        outbuf
+    }
+
+    fn set_output_metadata(&mut self, outbuf: &mut gst::BufferRef) {
+        let pts = self.out_pts(self.n_out);
+        let next_pts = self.out_pts(self.n_out + 1);
+
+        outbuf.set_pts(pts);
+        outbuf.set_dts(gst::ClockTime::NONE);
+        outbuf.set_duration(next_pts.opt_checked_sub(pts).ok().flatten());
+        outbuf.set_offset(self.n_out);
+        outbuf.set_offset_end(self.n_out + 1);
+        if self.discont {
+            outbuf.set_flags(gst::BufferFlags::DISCONT);
+            self.discont = false;
+        } else {
+            outbuf.unset_flags(gst::BufferFlags::DISCONT);
+        }
+
+        self.n_out += 1;
+    }
+}
+
+#[derive(Default)]
+pub struct FramerateConvert {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+impl FramerateConvert {
+    // Interpolates a new frame at position `t` between the previous and next input frames
+    fn interpolate(
+        &self,
+        state: &mut State,
+        settings: &Settings,
+        t: f64,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let prev = state.prev.as_ref().unwrap();
+        let next = state.next.as_ref().unwrap();
+
+        let prev_frame =
+            gst_video::VideoFrameRef::from_buffer_ref_readable(prev.as_ref(), &state.in_info)
+                .map_err(|_| {
+                    gst::error!(CAT, imp: self, "Failed to map previous frame");
+                    gst::FlowError::Error
+                })?;
+        let next_frame =
+            gst_video::VideoFrameRef::from_buffer_ref_readable(next.as_ref(), &state.in_info)
+                .map_err(|_| {
+                    gst::error!(CAT, imp: self, "Failed to map next frame");
+                    gst::FlowError::Error
+                })?;
+
+        if settings.mode == FramerateConvertMode::MotionCompensated && state.motion.is_none() {
+            let prev_plane = Plane::new(&prev_frame, 0);
+            let next_plane = Plane::new(&next_frame, 0);
+            let field = MotionField::estimate(&prev_plane, &next_plane, settings.search_range);
+            gst::trace!(CAT, imp: self, "Estimated motion {field:?}");
+            state.motion = Some(field);
+        }
+
+        let mut outbuf =
+            gst::Buffer::with_size(state.out_info.size()).map_err(|_| gst::FlowError::Error)?;
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            let mut out_frame =
+                gst_video::VideoFrameRef::from_buffer_ref_writable(outbuf, &state.out_info)
+                    .unwrap();
+
+            for p in 0..state.in_info.n_planes() {
+                let prev_plane = Plane::new(&prev_frame, p);
+                let next_plane = Plane::new(&next_frame, p);
+                let mut out_plane = PlaneMut::new(&mut out_frame, p);
+
+                match state.motion {
+                    Some(ref field) if settings.mode == FramerateConvertMode::MotionCompensated => {
+                        motion::interpolate_plane(
+                            &prev_plane,
+                            &next_plane,
+                            &mut out_plane,
+                            field,
+                            t,
+                        );
+                    }
+                    _ => {
+                        motion::blend_plane(
+                            &prev_plane,
+                            &next_plane,
+                            &mut out_plane,
+                            (t * 256.0).round() as u32,
+                        );
+                    }
+                }
+            }
+        }
+
+        drop(prev_frame);
+        drop(next_frame);
+
+        state.set_output_metadata(outbuf.get_mut().unwrap());
+
+        Ok(outbuf)
+    }
+
+    // Outputs the remaining frames up to the end of the last input frame
+    fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut buffers = vec![];
+        {
+            let mut state_guard = self.state.lock().unwrap();
+            let Some(state) = state_guard.as_mut() else {
+                return Ok(gst::FlowSuccess::Ok);
+            };
+
+            if let Some(last) = state.next.take().or_else(|| state.prev.take()) {
+                let in_fps = state.in_info.fps();
+                let duration = last.duration().or_else(|| {
+                    (in_fps.numer() > 0).then(|| {
+                        gst::ClockTime::SECOND
+                            .mul_div_round(in_fps.denom() as u64, in_fps.numer() as u64)
+                            .unwrap()
+                    })
+                });
+                let end = last.pts().opt_add(duration);
+
+                while let Some(out_pts) = state.out_pts(state.n_out) {
+                    if end.map_or(true, |end| out_pts >= end) {
+                        break;
+                    }
+                    buffers.push(state.output_copy(&last));
+                }
+            }
+
+            state.reset();
+        }
+
+        gst::debug!(CAT, imp: self, "Draining {} frames", buffers.len());
+
+        for buffer in buffers {
+            self.obj().src_pad().push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FramerateConvert {
+    const NAME: &'static str = "GstFramerateConvert";
+    type Type = super::FramerateConvert;
+    type ParentType = gst_base::BaseTransform;
+}
+
+impl ObjectImpl for FramerateConvert {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("How intermediate frames are generated")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("search-range")
+                    .nick("Search Range")
+                    .blurb("Maximum motion in pixels searched for in motion-compensated mode")
+                    .minimum(1)
+                    .maximum(128)
+                    .default_value(DEFAULT_SEARCH_RANGE)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "mode" => {
+                let mut settings = self.settings.lock().unwrap();
+                let mode = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing mode from {:?} to {:?}",
+                    settings.mode,
+                    mode
+                );
+                settings.mode = mode;
+            }
+            "search-range" => {
+                let mut settings = self.settings.lock().unwrap();
+                let search_range = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing search-range from {} to {}",
+                    settings.search_range,
+                    search_range
+                );
+                settings.search_range = search_range;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "mode" => {
+                let settings = self.settings.lock().unwrap();
+                settings.mode.to_value()
+            }
+            "search-range" => {
+                let settings = self.settings.lock().unwrap();
+                settings.search_range.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for FramerateConvert {}
+
+impl ElementImpl for FramerateConvert {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Framerate converter",
+                "Filter/Effect/Converter/Video",
+                "Converts the framerate by blending or motion compensated interpolation",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let formats = [
+                gst_video::VideoFormat::I420,
+                gst_video::VideoFormat::Yv12,
+                gst_video::VideoFormat::Y42b,
+                gst_video::VideoFormat::Y444,
+                gst_video::VideoFormat::Nv12,
+                gst_video::VideoFormat::Nv21,
+                gst_video::VideoFormat::Ayuv,
+                gst_video::VideoFormat::Gray8,
+                gst_video::VideoFormat::Rgbx,
+                gst_video::VideoFormat::Xrgb,
+                gst_video::VideoFormat::Bgrx,
+                gst_video::VideoFormat::Xbgr,
+                gst_video::VideoFormat::Rgba,
+                gst_video::VideoFormat::Argb,
+                gst_video::VideoFormat::Bgra,
+                gst_video::VideoFormat::Abgr,
+                gst_video::VideoFormat::Rgb,
+                gst_video::VideoFormat::Bgr,
+            ];
+
+            let sink_caps = gst_video::VideoCapsBuilder::new()
+                .format_list(formats)
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &sink_caps,
+            )
+            .unwrap();
+
+            let src_caps = gst_video::VideoCapsBuilder::new()
+                .format_list(formats)
+                .framerate_range(gst::Fraction::new(1, i32::MAX)..=gst::Fraction::new(i32::MAX, 1))
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &src_caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for FramerateConvert {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::NeverInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = true;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn transform_caps(
+        &self,
+        direction: gst::PadDirection,
+        caps: &gst::Caps,
+        filter: Option<&gst::Caps>,
+    ) -> Option<gst::Caps> {
+        let mut other_caps = caps.clone();
+        {
+            let min_framerate = if direction == gst::PadDirection::Sink {
+                gst::Fraction::new(1, i32::MAX)
+            } else {
+                gst::Fraction::new(0, 1)
+            };
+
+            for s in other_caps.make_mut().iter_mut() {
+                s.set(
+                    "framerate",
+                    gst::FractionRange::new(min_framerate, gst::Fraction::new(i32::MAX, 1)),
+                );
+            }
+        }
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Transformed caps from {} to {} in direction {:?}",
+            caps,
+            other_caps,
+            direction
+        );
+
+        if let Some(filter) = filter {
+            other_caps = filter.intersect_with_mode(&other_caps, gst::CapsIntersectMode::First);
+        }
+
+        Some(other_caps)
+    }
+
+    fn fixate_caps(
+        &self,
+        direction: gst::PadDirection,
+        caps: &gst::Caps,
+        mut othercaps: gst::Caps,
+    ) -> gst::Caps {
+        // Prefer keeping the framerate of the other side
+        let framerate = caps
+            .structure(0)
+            .and_then(|s| s.get::<gst::Fraction>("framerate").ok())
+            .filter(|framerate| framerate.numer() > 0)
+            .unwrap_or_else(|| gst::Fraction::new(DEFAULT_FPS_N, 1));
+
+        othercaps.truncate();
+        {
+            let othercaps = othercaps.make_mut();
+            let s = othercaps.structure_mut(0).unwrap();
+            s.fixate_field_nearest_fraction("framerate", framerate);
+        }
+
+        self.parent_fixate_caps(direction, caps, othercaps)
+    }
+
+    fn set_caps(&self, incaps: &gst::Caps, outcaps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let in_info = gst_video::VideoInfo::from_caps(incaps)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to parse input caps"))?;
+        let out_info = gst_video::VideoInfo::from_caps(outcaps)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to parse output caps"))?;
+
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Configured for caps {} to {}",
+            incaps,
+            outcaps
+        );
+
+        *self.state.lock().unwrap() = Some(State::new(in_info, out_info));
+
+        Ok(())
+    }
+
+    fn generate_output(&self) -> Result<GenerateOutputSuccess, gst::FlowError> {
+        let queued = self.take_queued_buffer();
+
+        if self.obj().is_passthrough() {
+            return Ok(match queued {
+                Some(buffer) => GenerateOutputSuccess::Buffer(buffer),
+                None => GenerateOutputSuccess::NoOutput,
+            });
+        }
+
+        let settings = *self.settings.lock().unwrap();
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(
+                self,
+                gst::CoreError::Negotiation,
+                ["Can not generate an output without State"]
+            );
+            gst::FlowError::NotNegotiated
+        })?;
+
+        if let Some(buffer) = queued {
+            let Some(pts) = buffer.pts() else {
+                gst::warning!(CAT, imp: self, "Dropping buffer without PTS");
+                return Ok(GenerateOutputSuccess::NoOutput);
+            };
+
+            if buffer.flags().contains(gst::BufferFlags::DISCONT) {
+                gst::debug!(CAT, imp: self, "Discontinuity, restarting at {pts}");
+                state.reset();
+            }
+
+            let last_pts = state
+                .next
+                .as_ref()
+                .or(state.prev.as_ref())
+                .and_then(|buffer| buffer.pts());
+            if last_pts.is_some_and(|last_pts| pts <= last_pts) {
+                gst::warning!(CAT, imp: self, "Dropping buffer with non-increasing PTS {pts}");
+                return Ok(GenerateOutputSuccess::NoOutput);
+            }
+
+            if state.prev.is_none() {
+                state.base_pts.get_or_insert(pts);
+                state.prev = Some(buffer);
+            } else {
+                state.next = Some(buffer);
+                state.motion = None;
+            }
+        }
+
+        let (Some(prev_pts), Some(next_pts)) = (
+            state.prev.as_ref().and_then(|buffer| buffer.pts()),
+            state.next.as_ref().and_then(|buffer| buffer.pts()),
+        ) else {
+            return Ok(GenerateOutputSuccess::NoOutput);
+        };
+
+        let out_pts = state.out_pts(state.n_out).unwrap();
+        if out_pts >= next_pts {
+            // All output frames before the next input frame are done, wait for more input
+            state.prev = state.next.take();
+            state.motion = None;
+            return Ok(GenerateOutputSuccess::NoOutput);
+        }
+
+        let t = out_pts.saturating_sub(prev_pts).nseconds() as f64
+            / (next_pts - prev_pts).nseconds() as f64;
+
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Generating frame {} at {} (position {:.3} between {} and {})",
+            state.n_out,
+            out_pts,
+            t,
+            prev_pts,
+            next_pts,
+        );
+
+        let outbuf = if settings.mode == FramerateConvertMode::Nearest || t == 0.0 {
+            let input = if t < 0.5 {
+                state.prev.clone().unwrap()
+            } else {
+                state.next.clone().unwrap()
+            };
+            state.output_copy(&input)
+        } else {
+            self.interpolate(state, &settings, t)?
+        };
+
+        Ok(GenerateOutputSuccess::Buffer(outbuf))
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        match event.view() {
+            EventView::Eos(_) | EventView::Segment(_) => {
+                gst::debug!(CAT, imp: self, "Draining on {:?}", event.type_());
+                if self.drain().is_err() {
+                    return false;
+                }
+            }
+            EventView::FlushStop(_) => {
+                if let Some(state) = self.state.lock().unwrap().as_mut() {
+                    state.reset();
+                }
+            }
+            _ => (),
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn query(&self, direction: gst::PadDirection, query: &mut gst::QueryRef) -> bool {
+        if direction == gst::PadDirection::Src {
+            if let gst::QueryViewMut::Latency(q) = query.view_mut() {
+                let mut upstream_query = gst::query::Latency::new();
+                if self.obj().sink_pad().peer_query(&mut upstream_query) {
+                    let (live, mut min, mut max) = upstream_query.result();
+                    gst::debug!(
+                        CAT,
+                        imp: self,
+                        "Peer latency: live {} min {} max {}",
+                        live,
+                        min,
+                        max.display(),
+                    );
+
+                    // Need to wait for the next input frame before interpolating
+                    if !self.obj().is_passthrough() {
+                        let in_fps = self
+                            .state
+                            .lock()
+                            .unwrap()
+                            .as_ref()
+                            .map(|state| state.in_info.fps());
+                        if let Some(frame_duration) =
+                            in_fps.filter(|fps| fps.numer() > 0).and_then(|fps| {
+                                gst::ClockTime::SECOND
+                                    .mul_div_ceil(fps.denom() as u64, fps.numer() as u64)
+                            })
+                        {
+                            min += frame_duration;
+                            max = max.opt_add(frame_duration);
+                        }
+                    }
+
+                    q.set(live, min, max);
+                    return true;
+                }
+            }
+        }
+
+        BaseTransformImplExt::parent_query(self, direction, query)
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let _ = self.state.lock().unwrap().take();
+
+        gst::info!(CAT, imp: self, "Stopped");
+
+        Ok(())
+    }
+}
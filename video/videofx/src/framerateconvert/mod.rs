@@ -0,0 +1,65 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-framerateconvert:
+ * @short_description: Converts the framerate of a video stream by interpolating frames.
+ *
+ * Unlike `videorate`, which drops or duplicates frames, this element can generate intermediate
+ * frames by blending the two surrounding input frames or by simple block based motion compensated
+ * interpolation. This avoids the hard judder of e.g. 25 to 30 fps conversion for contribution
+ * feeds.
+ *
+ * Output frames are timestamped at a fixed rate starting at the timestamp of the first input
+ * frame. The element has a latency of one input frame as it needs to wait for the next input
+ * frame before it can interpolate.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 videotestsrc pattern=ball ! video/x-raw,framerate=25/1 ! \
+ *   framerateconvert mode=motion-compensated ! video/x-raw,framerate=30/1 ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod motion;
+
+glib::wrapper! {
+    pub struct FramerateConvert(ObjectSubclass<imp::FramerateConvert>) @extends gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "framerateconvert",
+        gst::Rank::NONE,
+        FramerateConvert::static_type(),
+    )
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstFramerateConvertMode")]
+#[non_exhaustive]
+pub enum FramerateConvertMode {
+    #[enum_value(
+        name = "Nearest: Drop or duplicate the nearest input frame.",
+        nick = "nearest"
+    )]
+    Nearest = 0,
+
+    #[enum_value(name = "Blend: Blend the surrounding input frames.", nick = "blend")]
+    Blend = 1,
+
+    #[enum_value(
+        name = "MotionCompensated: Interpolate along estimated block motion.",
+        nick = "motion-compensated"
+    )]
+    MotionCompensated = 2,
+}
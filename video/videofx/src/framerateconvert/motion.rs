@@ -0,0 +1,191 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+// Block based motion estimation and motion compensated interpolation between two frames.
+//
+// Motion is estimated on the first plane only, with one vector per block of `BLOCK_SIZE` x
+// `BLOCK_SIZE` pixels. Each vector points from a block of the next frame to the best matching
+// position in the previous frame, so content at position `p + v` in the previous frame moved to
+// `p` in the next frame.
+
+use crate::plane::{Plane, PlaneMut};
+
+pub const BLOCK_SIZE: usize = 16;
+
+#[derive(Debug)]
+pub struct MotionField {
+    blocks_x: usize,
+    blocks_y: usize,
+    vectors: Vec<(i32, i32)>,
+}
+
+#[inline]
+fn blend(a: u8, b: u8, weight: u32) -> u8 {
+    ((a as u32 * (256 - weight) + b as u32 * weight + 128) >> 8) as u8
+}
+
+// Sum of absolute differences between the block at `x`/`y` in `next` and the block displaced by
+// `dx`/`dy` in `prev`. The displaced block must be inside the frame.
+fn sad(
+    prev: &Plane,
+    next: &Plane,
+    (x, y): (usize, usize),
+    (w, h): (usize, usize),
+    (dx, dy): (i32, i32),
+) -> u32 {
+    let px = (x as i32 + dx) as usize;
+    let py = (y as i32 + dy) as usize;
+
+    let mut sad = 0;
+    for row in 0..h {
+        let next_row = &next.data[(y + row) * next.stride + x * next.pstride..][..w * next.pstride];
+        let prev_row =
+            &prev.data[(py + row) * prev.stride + px * prev.pstride..][..w * prev.pstride];
+
+        sad += next_row
+            .iter()
+            .zip(prev_row)
+            .map(|(a, b)| a.abs_diff(*b) as u32)
+            .sum::<u32>();
+    }
+
+    sad
+}
+
+impl MotionField {
+    // Three-step search around the zero vector for every block
+    pub fn estimate(prev: &Plane, next: &Plane, search_range: u32) -> Self {
+        let blocks_x = (next.width + BLOCK_SIZE - 1) / BLOCK_SIZE;
+        let blocks_y = (next.height + BLOCK_SIZE - 1) / BLOCK_SIZE;
+        let range = search_range as i32;
+
+        let mut vectors = Vec::with_capacity(blocks_x * blocks_y);
+        for by in 0..blocks_y {
+            for bx in 0..blocks_x {
+                let pos = (bx * BLOCK_SIZE, by * BLOCK_SIZE);
+                let size = (
+                    usize::min(BLOCK_SIZE, next.width - pos.0),
+                    usize::min(BLOCK_SIZE, next.height - pos.1),
+                );
+
+                let in_bounds = |(dx, dy): (i32, i32)| {
+                    dx.abs() <= range
+                        && dy.abs() <= range
+                        && pos.0 as i32 + dx >= 0
+                        && pos.1 as i32 + dy >= 0
+                        && pos.0 as i32 + dx + size.0 as i32 <= prev.width as i32
+                        && pos.1 as i32 + dy + size.1 as i32 <= prev.height as i32
+                };
+
+                let mut best = (0, 0);
+                let mut best_sad = sad(prev, next, pos, size, best);
+                let mut step = (range + 1) / 2;
+                while step >= 1 && best_sad > 0 {
+                    let center = best;
+                    for (sx, sy) in [
+                        (-1, -1),
+                        (0, -1),
+                        (1, -1),
+                        (-1, 0),
+                        (1, 0),
+                        (-1, 1),
+                        (0, 1),
+                        (1, 1),
+                    ] {
+                        let candidate = (center.0 + sx * step, center.1 + sy * step);
+                        if !in_bounds(candidate) {
+                            continue;
+                        }
+
+                        let candidate_sad = sad(prev, next, pos, size, candidate);
+                        if candidate_sad < best_sad {
+                            best = candidate;
+                            best_sad = candidate_sad;
+                        }
+                    }
+                    step /= 2;
+                }
+
+                vectors.push(best);
+            }
+        }
+
+        MotionField {
+            blocks_x,
+            blocks_y,
+            vectors,
+        }
+    }
+}
+
+// Plain linear blend of two planes, `weight` is the weight of `next` in 1/256 units
+pub fn blend_plane(prev: &Plane, next: &Plane, out: &mut PlaneMut, weight: u32) {
+    let row_size = out.width * out.pstride;
+
+    for y in 0..out.height {
+        let prev_row = &prev.data[y * prev.stride..][..row_size];
+        let next_row = &next.data[y * next.stride..][..row_size];
+        let out_row = &mut out.data[y * out.stride..][..row_size];
+
+        for ((o, a), b) in out_row.iter_mut().zip(prev_row).zip(next_row) {
+            *o = blend(*a, *b, weight);
+        }
+    }
+}
+
+// Interpolates a plane at position `t` between `prev` and `next` along the motion field
+pub fn interpolate_plane(
+    prev: &Plane,
+    next: &Plane,
+    out: &mut PlaneMut,
+    field: &MotionField,
+    t: f64,
+) {
+    let (sub_x, sub_y) = out.sub;
+    let weight = (t * 256.0).round() as u32;
+    let pstride = out.pstride;
+
+    // Per block pixel offsets into the previous and next frame for this plane
+    let offsets = field
+        .vectors
+        .iter()
+        .map(|&(dx, dy)| {
+            let dx = dx as f64 / (1 << sub_x) as f64;
+            let dy = dy as f64 / (1 << sub_y) as f64;
+            (
+                (t * dx).round() as i32,
+                (t * dy).round() as i32,
+                (-(1.0 - t) * dx).round() as i32,
+                (-(1.0 - t) * dy).round() as i32,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let max_x = out.width as i32 - 1;
+    let max_y = out.height as i32 - 1;
+
+    for y in 0..out.height {
+        let by = usize::min((y << sub_y) / BLOCK_SIZE, field.blocks_y - 1);
+        let out_row = &mut out.data[y * out.stride..][..out.width * pstride];
+
+        for (x, o) in out_row.chunks_exact_mut(pstride).enumerate() {
+            let bx = usize::min((x << sub_x) / BLOCK_SIZE, field.blocks_x - 1);
+            let (prev_dx, prev_dy, next_dx, next_dy) = offsets[by * field.blocks_x + bx];
+
+            let prev_x = (x as i32 + prev_dx).clamp(0, max_x) as usize;
+            let prev_y = (y as i32 + prev_dy).clamp(0, max_y) as usize;
+            let next_x = (x as i32 + next_dx).clamp(0, max_x) as usize;
+            let next_y = (y as i32 + next_dy).clamp(0, max_y) as usize;
+
+            let a = &prev.data[prev_y * prev.stride + prev_x * pstride..][..pstride];
+            let b = &next.data[next_y * next.stride + next_x * pstride..][..pstride];
+
+            for ((o, a), b) in o.iter_mut().zip(a).zip(b) {
+                *o = blend(*a, *b, weight);
+            }
+        }
+    }
+}
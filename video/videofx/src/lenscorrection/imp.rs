@@ -0,0 +1,498 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+use gst_video::prelude::*;
+use gst_video::subclass::prelude::*;
+
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::plane::{plane_component, Plane, PlaneMut};
+
+const DEFAULT_COEFFICIENT: f64 = 0.0;
+const DEFAULT_CENTER: f64 = 0.5;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "lenscorrection",
+        gst::DebugColorFlags::empty(),
+        Some("Lens distortion correction filter"),
+    )
+});
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Coefficients {
+    k1: f64,
+    k2: f64,
+    k3: f64,
+    p1: f64,
+    p2: f64,
+    center_x: f64,
+    center_y: f64,
+}
+
+impl Default for Coefficients {
+    fn default() -> Self {
+        Coefficients {
+            k1: DEFAULT_COEFFICIENT,
+            k2: DEFAULT_COEFFICIENT,
+            k3: DEFAULT_COEFFICIENT,
+            p1: DEFAULT_COEFFICIENT,
+            p2: DEFAULT_COEFFICIENT,
+            center_x: DEFAULT_CENTER,
+            center_y: DEFAULT_CENTER,
+        }
+    }
+}
+
+impl Coefficients {
+    fn from_profile(location: &str, profile: &str) -> Result<Self, glib::Error> {
+        let key_file = glib::KeyFile::new();
+        key_file.load_from_file(location, glib::KeyFileFlags::NONE)?;
+
+        let value = |key: &str, default: f64| -> Result<f64, glib::Error> {
+            match key_file.double(profile, key) {
+                Ok(value) => Ok(value),
+                Err(err) if err.matches(glib::KeyFileError::KeyNotFound) => Ok(default),
+                Err(err) => Err(err),
+            }
+        };
+
+        // Fail early if the group doesn't exist instead of silently using the defaults
+        key_file.keys(profile)?;
+
+        Ok(Coefficients {
+            k1: value("k1", DEFAULT_COEFFICIENT)?,
+            k2: value("k2", DEFAULT_COEFFICIENT)?,
+            k3: value("k3", DEFAULT_COEFFICIENT)?,
+            p1: value("p1", DEFAULT_COEFFICIENT)?,
+            p2: value("p2", DEFAULT_COEFFICIENT)?,
+            center_x: value("center-x", DEFAULT_CENTER)?,
+            center_y: value("center-y", DEFAULT_CENTER)?,
+        })
+    }
+
+    fn is_identity(&self) -> bool {
+        self.k1 == 0.0 && self.k2 == 0.0 && self.k3 == 0.0 && self.p1 == 0.0 && self.p2 == 0.0
+    }
+
+    // Applies the Brown–Conrady model to a position normalized to the half diagonal, i.e. maps
+    // a position in the corrected frame to its position in the distorted input frame
+    fn distort(&self, x: f64, y: f64) -> (f64, f64) {
+        let r2 = x * x + y * y;
+        let radial = 1.0 + r2 * (self.k1 + r2 * (self.k2 + r2 * self.k3));
+
+        (
+            x * radial + 2.0 * self.p1 * x * y + self.p2 * (r2 + 2.0 * x * x),
+            y * radial + self.p1 * (r2 + 2.0 * y * y) + 2.0 * self.p2 * x * y,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    coefficients: Coefficients,
+    profile_location: Option<String>,
+    profile: Option<String>,
+}
+
+// Source position for every pixel of a plane, in plane coordinates
+struct Map {
+    width: usize,
+    positions: Vec<(f32, f32)>,
+}
+
+impl Map {
+    fn new(info: &gst_video::VideoInfo, plane: u32, coefficients: &Coefficients) -> Self {
+        let finfo = info.format_info();
+        let comp = plane_component(&finfo, plane);
+        let width = info.comp_width(comp as u8) as usize;
+        let height = info.comp_height(comp as u8) as usize;
+        let (sub_x, sub_y) = (finfo.w_sub()[comp], finfo.h_sub()[comp]);
+
+        let full_width = info.width() as f64;
+        let full_height = info.height() as f64;
+        let radius = (full_width * full_width + full_height * full_height).sqrt() / 2.0;
+        let center_x = coefficients.center_x * full_width;
+        let center_y = coefficients.center_y * full_height;
+        let scale_x = (1 << sub_x) as f64;
+        let scale_y = (1 << sub_y) as f64;
+
+        let mut positions = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let full_y = (y as f64 + 0.5) * scale_y;
+            for x in 0..width {
+                let full_x = (x as f64 + 0.5) * scale_x;
+
+                let (src_x, src_y) = coefficients
+                    .distort((full_x - center_x) / radius, (full_y - center_y) / radius);
+
+                positions.push((
+                    ((src_x * radius + center_x) / scale_x - 0.5) as f32,
+                    ((src_y * radius + center_y) / scale_y - 0.5) as f32,
+                ));
+            }
+        }
+
+        Map { width, positions }
+    }
+
+    fn apply(&self, in_plane: &Plane, out_plane: &mut PlaneMut) {
+        let pstride = out_plane.pstride;
+        let max_x = in_plane.width as f32 - 1.0;
+        let max_y = in_plane.height as f32 - 1.0;
+
+        for (out_line, positions) in out_plane
+            .data
+            .chunks_mut(out_plane.stride)
+            .zip(self.positions.chunks_exact(self.width))
+        {
+            for (out, &(x, y)) in out_line[..self.width * pstride]
+                .chunks_exact_mut(pstride)
+                .zip(positions)
+            {
+                // Bilinear sampling, clamped to the edges of the input frame
+                let x = x.clamp(0.0, max_x);
+                let y = y.clamp(0.0, max_y);
+                let x0 = x as usize;
+                let y0 = y as usize;
+                let x1 = (x0 + 1).min(in_plane.width - 1);
+                let y1 = (y0 + 1).min(in_plane.height - 1);
+                let fx = x - x0 as f32;
+                let fy = y - y0 as f32;
+
+                let p00 = y0 * in_plane.stride + x0 * pstride;
+                let p01 = y0 * in_plane.stride + x1 * pstride;
+                let p10 = y1 * in_plane.stride + x0 * pstride;
+                let p11 = y1 * in_plane.stride + x1 * pstride;
+
+                for (i, out) in out.iter_mut().enumerate() {
+                    let top = in_plane.data[p00 + i] as f32 * (1.0 - fx)
+                        + in_plane.data[p01 + i] as f32 * fx;
+                    let bottom = in_plane.data[p10 + i] as f32 * (1.0 - fx)
+                        + in_plane.data[p11 + i] as f32 * fx;
+
+                    *out = (top * (1.0 - fy) + bottom * fy + 0.5) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LensCorrection {
+    settings: Mutex<Settings>,
+    // Per-plane maps, invalidated whenever the coefficients or caps change
+    maps: Mutex<Option<Vec<Map>>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for LensCorrection {
+    const NAME: &'static str = "GstLensCorrection";
+    type Type = super::LensCorrection;
+    type ParentType = gst_video::VideoFilter;
+}
+
+impl LensCorrection {
+    fn coefficients_changed(&self, coefficients: &Coefficients) {
+        *self.maps.lock().unwrap() = None;
+        self.obj().set_passthrough(coefficients.is_identity());
+    }
+
+    fn load_profile(&self) {
+        let settings = self.settings.lock().unwrap();
+        let (Some(location), Some(profile)) =
+            (settings.profile_location.clone(), settings.profile.clone())
+        else {
+            return;
+        };
+        drop(settings);
+
+        let coefficients = match Coefficients::from_profile(&location, &profile) {
+            Ok(coefficients) => coefficients,
+            Err(err) => {
+                gst::element_imp_warning!(
+                    self,
+                    gst::ResourceError::OpenRead,
+                    [
+                        "Failed to load profile {} from {}: {}",
+                        profile,
+                        location,
+                        err
+                    ]
+                );
+                return;
+            }
+        };
+
+        gst::info!(
+            CAT,
+            imp: self,
+            "Loaded profile {profile} from {location}: {coefficients:?}"
+        );
+
+        self.settings.lock().unwrap().coefficients = coefficients;
+        self.coefficients_changed(&coefficients);
+
+        let obj = self.obj();
+        for name in ["k1", "k2", "k3", "p1", "p2", "center-x", "center-y"] {
+            obj.notify(name);
+        }
+    }
+}
+
+impl ObjectImpl for LensCorrection {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            let coefficient = |name: &'static str, nick: &'static str, blurb: &'static str| {
+                glib::ParamSpecDouble::builder(name)
+                    .nick(nick)
+                    .blurb(blurb)
+                    .minimum(-100.0)
+                    .maximum(100.0)
+                    .default_value(DEFAULT_COEFFICIENT)
+                    .mutable_playing()
+                    .build()
+            };
+
+            vec![
+                coefficient("k1", "K1", "First radial distortion coefficient"),
+                coefficient("k2", "K2", "Second radial distortion coefficient"),
+                coefficient("k3", "K3", "Third radial distortion coefficient"),
+                coefficient("p1", "P1", "First tangential distortion coefficient"),
+                coefficient("p2", "P2", "Second tangential distortion coefficient"),
+                glib::ParamSpecDouble::builder("center-x")
+                    .nick("Center X")
+                    .blurb("Horizontal position of the distortion center relative to the width")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_CENTER)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("center-y")
+                    .nick("Center Y")
+                    .blurb("Vertical position of the distortion center relative to the height")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_CENTER)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("profile-location")
+                    .nick("Profile Location")
+                    .blurb("Location of a key file with per-camera distortion profiles")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("profile")
+                    .nick("Profile")
+                    .blurb("Name of the camera profile to load from the profile location")
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().set_passthrough(true);
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "profile-location" => {
+                let mut settings = self.settings.lock().unwrap();
+                let location = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing profile location from {:?} to {:?}",
+                    settings.profile_location,
+                    location
+                );
+                settings.profile_location = location;
+                drop(settings);
+
+                self.load_profile();
+            }
+            "profile" => {
+                let mut settings = self.settings.lock().unwrap();
+                let profile = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing profile from {:?} to {:?}",
+                    settings.profile,
+                    profile
+                );
+                settings.profile = profile;
+                drop(settings);
+
+                self.load_profile();
+            }
+            name => {
+                let mut settings = self.settings.lock().unwrap();
+                let value = value.get::<f64>().expect("type checked upstream");
+                let coefficient = match name {
+                    "k1" => &mut settings.coefficients.k1,
+                    "k2" => &mut settings.coefficients.k2,
+                    "k3" => &mut settings.coefficients.k3,
+                    "p1" => &mut settings.coefficients.p1,
+                    "p2" => &mut settings.coefficients.p2,
+                    "center-x" => &mut settings.coefficients.center_x,
+                    "center-y" => &mut settings.coefficients.center_y,
+                    _ => unimplemented!(),
+                };
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing {} from {} to {}",
+                    name,
+                    coefficient,
+                    value
+                );
+                *coefficient = value;
+                let coefficients = settings.coefficients;
+                drop(settings);
+
+                self.coefficients_changed(&coefficients);
+            }
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "k1" => settings.coefficients.k1.to_value(),
+            "k2" => settings.coefficients.k2.to_value(),
+            "k3" => settings.coefficients.k3.to_value(),
+            "p1" => settings.coefficients.p1.to_value(),
+            "p2" => settings.coefficients.p2.to_value(),
+            "center-x" => settings.coefficients.center_x.to_value(),
+            "center-y" => settings.coefficients.center_y.to_value(),
+            "profile-location" => settings.profile_location.to_value(),
+            "profile" => settings.profile.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for LensCorrection {}
+
+impl ElementImpl for LensCorrection {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Lens distortion correction",
+                "Filter/Effect/Video",
+                "Corrects barrel and pincushion lens distortion",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format_list([
+                    gst_video::VideoFormat::I420,
+                    gst_video::VideoFormat::Yv12,
+                    gst_video::VideoFormat::Y42b,
+                    gst_video::VideoFormat::Y444,
+                    gst_video::VideoFormat::Nv12,
+                    gst_video::VideoFormat::Nv21,
+                    gst_video::VideoFormat::Ayuv,
+                    gst_video::VideoFormat::Gray8,
+                    gst_video::VideoFormat::Rgbx,
+                    gst_video::VideoFormat::Xrgb,
+                    gst_video::VideoFormat::Bgrx,
+                    gst_video::VideoFormat::Xbgr,
+                    gst_video::VideoFormat::Rgba,
+                    gst_video::VideoFormat::Argb,
+                    gst_video::VideoFormat::Bgra,
+                    gst_video::VideoFormat::Abgr,
+                    gst_video::VideoFormat::Rgb,
+                    gst_video::VideoFormat::Bgr,
+                ])
+                .build();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for LensCorrection {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::NeverInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+}
+
+impl VideoFilterImpl for LensCorrection {
+    fn set_info(
+        &self,
+        incaps: &gst::Caps,
+        in_info: &gst_video::VideoInfo,
+        outcaps: &gst::Caps,
+        out_info: &gst_video::VideoInfo,
+    ) -> Result<(), gst::LoggableError> {
+        *self.maps.lock().unwrap() = None;
+
+        self.parent_set_info(incaps, in_info, outcaps, out_info)
+    }
+
+    fn transform_frame(
+        &self,
+        in_frame: &gst_video::VideoFrameRef<&gst::BufferRef>,
+        out_frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut maps = self.maps.lock().unwrap();
+        let maps = maps.get_or_insert_with(|| {
+            let coefficients = self.settings.lock().unwrap().coefficients;
+            gst::debug!(CAT, imp: self, "Creating maps for {coefficients:?}");
+
+            (0..in_frame.n_planes())
+                .map(|p| Map::new(in_frame.info(), p, &coefficients))
+                .collect()
+        });
+
+        for (p, map) in maps.iter().enumerate() {
+            let in_plane = Plane::new(in_frame, p as u32);
+            let mut out_plane = PlaneMut::new(out_frame, p as u32);
+
+            map.apply(&in_plane, &mut out_plane);
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
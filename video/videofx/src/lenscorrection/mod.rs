@@ -0,0 +1,52 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-lenscorrection:
+ * @short_description: Corrects barrel and pincushion lens distortion.
+ *
+ * Removes the geometric distortion introduced by wide-angle and action camera lenses using the
+ * Brown–Conrady model. The radial coefficients `k1`, `k2` and `k3` and the tangential
+ * coefficients `p1` and `p2` are given relative to the half diagonal of the frame, so the same
+ * values apply to all resolutions of a camera.
+ *
+ * Coefficients for specific cameras can be stored in a profile file in the GKeyFile format, with
+ * one group per camera. Setting `profile-location` and `profile` loads the coefficients of the
+ * selected group into the corresponding properties:
+ *
+ * ```ini
+ * [gopro-hero5-wide]
+ * k1=-0.28
+ * k2=0.09
+ * ```
+ *
+ * Keys that are missing in the group default to 0, apart from `center-x` and `center-y` which
+ * default to 0.5.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 v4l2src ! videoconvert ! lenscorrection profile-location=cameras.ini \
+ *   profile=gopro-hero5-wide ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct LensCorrection(ObjectSubclass<imp::LensCorrection>) @extends gst_video::VideoFilter, gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "lenscorrection",
+        gst::Rank::NONE,
+        LensCorrection::static_type(),
+    )
+}
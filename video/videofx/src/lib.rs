@@ -17,17 +17,43 @@
 use gst::prelude::*;
 
 mod border;
+mod burninoverlay;
 mod colordetect;
+mod deinterlace;
+mod framerateconvert;
+mod lenscorrection;
+mod lut3d;
+mod plane;
+mod timingmark;
 mod videocompare;
 
+pub use burninoverlay::{BurnInClockSource, BurnInPosition};
+pub use deinterlace::DeinterlaceMode;
+pub use framerateconvert::FramerateConvertMode;
+pub use lut3d::Lut3dInterpolation;
+pub use timingmark::TimingMarkMode;
 pub use videocompare::{HashAlgorithm, PadDistance, VideoCompareMessage};
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), gst::glib::BoolError> {
     #[cfg(feature = "doc")]
-    HashAlgorithm::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    {
+        BurnInClockSource::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        BurnInPosition::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        DeinterlaceMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        FramerateConvertMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        HashAlgorithm::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        Lut3dInterpolation::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        TimingMarkMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
 
     border::register(plugin)?;
+    burninoverlay::register(plugin)?;
     colordetect::register(plugin)?;
+    deinterlace::register(plugin)?;
+    framerateconvert::register(plugin)?;
+    lenscorrection::register(plugin)?;
+    lut3d::register(plugin)?;
+    timingmark::register(plugin)?;
     videocompare::register(plugin)
 }
 
@@ -0,0 +1,272 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+// Parser and lookup for 3D LUTs in the Adobe / Resolve `.cube` format.
+//
+// Only 3D LUTs are supported. Table entries are ordered with the red index changing fastest,
+// followed by green and then blue.
+
+use anyhow::{anyhow, bail, Context};
+use std::path::Path;
+
+const MAX_LUT_SIZE: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct CubeLut {
+    pub title: Option<String>,
+    size: usize,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    table: Vec<[f32; 3]>,
+}
+
+fn parse_triplet<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<[f32; 3], anyhow::Error> {
+    let mut res = [0.0; 3];
+    for v in res.iter_mut() {
+        let token = tokens.next().ok_or_else(|| anyhow!("Expected 3 values"))?;
+        *v = token
+            .parse::<f32>()
+            .with_context(|| format!("Invalid value '{token}'"))?;
+    }
+
+    if tokens.next().is_some() {
+        bail!("Expected 3 values");
+    }
+
+    Ok(res)
+}
+
+impl CubeLut {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        Self::parse(&s)
+    }
+
+    pub fn parse(s: &str) -> Result<Self, anyhow::Error> {
+        let mut title = None;
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next().unwrap();
+
+            let res = match keyword {
+                "TITLE" => {
+                    title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string());
+                    Ok(())
+                }
+                "LUT_3D_SIZE" => tokens
+                    .next()
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .filter(|v| (2..=MAX_LUT_SIZE).contains(v))
+                    .map(|v| {
+                        size = Some(v);
+                        table.reserve_exact(v * v * v);
+                    })
+                    .ok_or_else(|| anyhow!("Invalid LUT size")),
+                "DOMAIN_MIN" => parse_triplet(tokens).map(|v| domain_min = v),
+                "DOMAIN_MAX" => parse_triplet(tokens).map(|v| domain_max = v),
+                "LUT_1D_SIZE" | "LUT_1D_INPUT_RANGE" => Err(anyhow!("1D LUTs are not supported")),
+                "LUT_3D_INPUT_RANGE" => {
+                    let range = tokens
+                        .map(|v| v.parse::<f32>())
+                        .collect::<Result<Vec<_>, _>>();
+                    match range.as_deref() {
+                        Ok([min, max]) => {
+                            domain_min = [*min; 3];
+                            domain_max = [*max; 3];
+                            Ok(())
+                        }
+                        _ => Err(anyhow!("Invalid input range")),
+                    }
+                }
+                _ if keyword
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_ascii_digit() || c == '-' || c == '.') =>
+                {
+                    if size.is_none() {
+                        Err(anyhow!("Table data before LUT_3D_SIZE"))
+                    } else {
+                        parse_triplet(std::iter::once(keyword).chain(tokens)).map(|v| table.push(v))
+                    }
+                }
+                // Unknown keywords are ignored as some tools add their own metadata
+                _ => Ok(()),
+            };
+
+            res.with_context(|| format!("Line {}", idx + 1))?;
+        }
+
+        let size = size.ok_or_else(|| anyhow!("No LUT_3D_SIZE"))?;
+        if table.len() != size * size * size {
+            bail!(
+                "Expected {} table entries but got {}",
+                size * size * size,
+                table.len()
+            );
+        }
+
+        if domain_min
+            .iter()
+            .zip(domain_max.iter())
+            .any(|(min, max)| min >= max)
+        {
+            bail!("Invalid domain");
+        }
+
+        Ok(CubeLut {
+            title,
+            size,
+            domain_min,
+            domain_max,
+            table,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    #[inline]
+    fn at(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.table[r + self.size * (g + self.size * b)]
+    }
+
+    // Returns the lower lattice index and the fractional position towards the next one for each
+    // component
+    #[inline]
+    fn position(&self, rgb: [f32; 3]) -> ([usize; 3], [usize; 3], [f32; 3]) {
+        let max_idx = (self.size - 1) as f32;
+
+        let mut lo = [0; 3];
+        let mut hi = [0; 3];
+        let mut frac = [0.0; 3];
+        for c in 0..3 {
+            let x = ((rgb[c] - self.domain_min[c]) / (self.domain_max[c] - self.domain_min[c])
+                * max_idx)
+                .clamp(0.0, max_idx);
+            let x0 = x.floor();
+            lo[c] = x0 as usize;
+            hi[c] = usize::min(lo[c] + 1, self.size - 1);
+            frac[c] = x - x0;
+        }
+
+        (lo, hi, frac)
+    }
+
+    pub fn lookup_trilinear(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let ([r0, g0, b0], [r1, g1, b1], [fr, fg, fb]) = self.position(rgb);
+
+        let c000 = self.at(r0, g0, b0);
+        let c100 = self.at(r1, g0, b0);
+        let c010 = self.at(r0, g1, b0);
+        let c110 = self.at(r1, g1, b0);
+        let c001 = self.at(r0, g0, b1);
+        let c101 = self.at(r1, g0, b1);
+        let c011 = self.at(r0, g1, b1);
+        let c111 = self.at(r1, g1, b1);
+
+        let mut res = [0.0; 3];
+        for c in 0..3 {
+            let c00 = c000[c] + (c100[c] - c000[c]) * fr;
+            let c10 = c010[c] + (c110[c] - c010[c]) * fr;
+            let c01 = c001[c] + (c101[c] - c001[c]) * fr;
+            let c11 = c011[c] + (c111[c] - c011[c]) * fr;
+
+            let c0 = c00 + (c10 - c00) * fg;
+            let c1 = c01 + (c11 - c01) * fg;
+
+            res[c] = c0 + (c1 - c0) * fb;
+        }
+
+        res
+    }
+
+    pub fn lookup_tetrahedral(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let ([r0, g0, b0], [r1, g1, b1], [fr, fg, fb]) = self.position(rgb);
+
+        let c000 = self.at(r0, g0, b0);
+        let c111 = self.at(r1, g1, b1);
+
+        // Select the tetrahedron containing the point and its weights
+        let (w0, ca, wa, cb, wb, w1) = if fr > fg {
+            if fg > fb {
+                (
+                    1.0 - fr,
+                    self.at(r1, g0, b0),
+                    fr - fg,
+                    self.at(r1, g1, b0),
+                    fg - fb,
+                    fb,
+                )
+            } else if fr > fb {
+                (
+                    1.0 - fr,
+                    self.at(r1, g0, b0),
+                    fr - fb,
+                    self.at(r1, g0, b1),
+                    fb - fg,
+                    fg,
+                )
+            } else {
+                (
+                    1.0 - fb,
+                    self.at(r0, g0, b1),
+                    fb - fr,
+                    self.at(r1, g0, b1),
+                    fr - fg,
+                    fg,
+                )
+            }
+        } else if fb > fg {
+            (
+                1.0 - fb,
+                self.at(r0, g0, b1),
+                fb - fg,
+                self.at(r0, g1, b1),
+                fg - fr,
+                fr,
+            )
+        } else if fb > fr {
+            (
+                1.0 - fg,
+                self.at(r0, g1, b0),
+                fg - fb,
+                self.at(r0, g1, b1),
+                fb - fr,
+                fr,
+            )
+        } else {
+            (
+                1.0 - fg,
+                self.at(r0, g1, b0),
+                fg - fr,
+                self.at(r1, g1, b0),
+                fr - fb,
+                fb,
+            )
+        };
+
+        let mut res = [0.0; 3];
+        for c in 0..3 {
+            res[c] = w0 * c000[c] + wa * ca[c] + wb * cb[c] + w1 * c111[c];
+        }
+
+        res
+    }
+}
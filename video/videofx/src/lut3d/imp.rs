@@ -0,0 +1,293 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+use gst_video::prelude::*;
+use gst_video::subclass::prelude::*;
+
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+
+use super::cube::CubeLut;
+use super::Lut3dInterpolation;
+
+const DEFAULT_INTERPOLATION: Lut3dInterpolation = Lut3dInterpolation::Tetrahedral;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "lut3d",
+        gst::DebugColorFlags::empty(),
+        Some("3D LUT color filter"),
+    )
+});
+
+#[derive(Debug, Clone)]
+struct Settings {
+    location: Option<String>,
+    interpolation: Lut3dInterpolation,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            location: None,
+            interpolation: DEFAULT_INTERPOLATION,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Lut3d {
+    settings: Mutex<Settings>,
+    lut: Mutex<Option<Arc<CubeLut>>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Lut3d {
+    const NAME: &'static str = "GstLut3d";
+    type Type = super::Lut3d;
+    type ParentType = gst_video::VideoFilter;
+}
+
+impl Lut3d {
+    fn load_lut(&self, location: Option<&str>) {
+        let Some(location) = location else {
+            gst::debug!(CAT, imp: self, "Unsetting LUT");
+            *self.lut.lock().unwrap() = None;
+            self.obj().set_passthrough(true);
+            return;
+        };
+
+        match CubeLut::from_file(location) {
+            Ok(lut) => {
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Loaded LUT {:?} of size {} from {location}",
+                    lut.title,
+                    lut.size(),
+                );
+                *self.lut.lock().unwrap() = Some(Arc::new(lut));
+                self.obj().set_passthrough(false);
+            }
+            Err(err) => {
+                gst::element_imp_warning!(
+                    self,
+                    gst::ResourceError::OpenRead,
+                    ["Failed to load LUT from {}: {:?}", location, err]
+                );
+            }
+        }
+    }
+
+    #[inline]
+    fn apply_lut(
+        frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+        lut: &CubeLut,
+        interpolation: Lut3dInterpolation,
+        [r_idx, g_idx, b_idx]: [usize; 3],
+    ) {
+        let width = frame.width() as usize;
+        let stride = frame.plane_stride()[0] as usize;
+        let nb_channels = frame.format_info().pixel_stride()[0] as usize;
+        let data = frame.plane_data_mut(0).unwrap();
+
+        let line_bytes = width * nb_channels;
+
+        let lookup = match interpolation {
+            Lut3dInterpolation::Trilinear => CubeLut::lookup_trilinear,
+            _ => CubeLut::lookup_tetrahedral,
+        };
+
+        for line in data.chunks_exact_mut(stride) {
+            for p in line[..line_bytes].chunks_exact_mut(nb_channels) {
+                let rgb = [
+                    p[r_idx] as f32 / 255.0,
+                    p[g_idx] as f32 / 255.0,
+                    p[b_idx] as f32 / 255.0,
+                ];
+
+                let res = lookup(lut, rgb);
+
+                p[r_idx] = (res[0] * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
+                p[g_idx] = (res[1] * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
+                p[b_idx] = (res[2] * 255.0 + 0.5).clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+impl ObjectImpl for Lut3d {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("Location")
+                    .blurb("Location of the .cube LUT file to apply")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("interpolation", DEFAULT_INTERPOLATION)
+                    .nick("Interpolation")
+                    .blurb("Interpolation method used for looking up values between LUT entries")
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().set_passthrough(true);
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "location" => {
+                let mut settings = self.settings.lock().unwrap();
+                let location = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing location from {:?} to {:?}",
+                    settings.location,
+                    location
+                );
+                settings.location = location.clone();
+                drop(settings);
+
+                self.load_lut(location.as_deref());
+            }
+            "interpolation" => {
+                let mut settings = self.settings.lock().unwrap();
+                let interpolation = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing interpolation from {:?} to {:?}",
+                    settings.interpolation,
+                    interpolation
+                );
+                settings.interpolation = interpolation;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "location" => {
+                let settings = self.settings.lock().unwrap();
+                settings.location.to_value()
+            }
+            "interpolation" => {
+                let settings = self.settings.lock().unwrap();
+                settings.interpolation.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for Lut3d {}
+
+impl ElementImpl for Lut3d {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "3D LUT filter",
+                "Filter/Effect/Converter/Video",
+                "Applies a 3D LUT loaded from a .cube file to incoming frames",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format_list([
+                    gst_video::VideoFormat::Rgbx,
+                    gst_video::VideoFormat::Xrgb,
+                    gst_video::VideoFormat::Bgrx,
+                    gst_video::VideoFormat::Xbgr,
+                    gst_video::VideoFormat::Rgba,
+                    gst_video::VideoFormat::Argb,
+                    gst_video::VideoFormat::Bgra,
+                    gst_video::VideoFormat::Abgr,
+                    gst_video::VideoFormat::Rgb,
+                    gst_video::VideoFormat::Bgr,
+                ])
+                .build();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for Lut3d {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+}
+
+impl VideoFilterImpl for Lut3d {
+    fn transform_frame_ip(
+        &self,
+        frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let interpolation = self.settings.lock().unwrap().interpolation;
+        let Some(lut) = self.lut.lock().unwrap().clone() else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let components = match frame.format() {
+            gst_video::VideoFormat::Rgbx
+            | gst_video::VideoFormat::Rgba
+            | gst_video::VideoFormat::Rgb => [0, 1, 2],
+            gst_video::VideoFormat::Xrgb | gst_video::VideoFormat::Argb => [1, 2, 3],
+            gst_video::VideoFormat::Bgrx
+            | gst_video::VideoFormat::Bgra
+            | gst_video::VideoFormat::Bgr => [2, 1, 0],
+            gst_video::VideoFormat::Xbgr | gst_video::VideoFormat::Abgr => [3, 2, 1],
+            _ => unreachable!(),
+        };
+
+        Self::apply_lut(frame, &lut, interpolation, components);
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,53 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-lut3d:
+ * @short_description: Applies a 3D LUT loaded from a `.cube` file to video frames.
+ *
+ * Loads 3D LUTs in the `.cube` format, as exported by most color grading tools, and applies them
+ * to RGB video frames. This is useful e.g. for converting camera log footage to Rec.709 in live
+ * production.
+ *
+ * The LUT can be switched at runtime by changing the `location` property. If the new file can't
+ * be loaded, a warning is posted and the previous LUT is kept. Without a LUT the element operates
+ * in passthrough mode.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 videotestsrc ! videoconvert ! lut3d location=log-to-rec709.cube \
+ *   interpolation=tetrahedral ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod cube;
+mod imp;
+
+glib::wrapper! {
+    pub struct Lut3d(ObjectSubclass<imp::Lut3d>) @extends gst_video::VideoFilter, gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(Some(plugin), "lut3d", gst::Rank::NONE, Lut3d::static_type())
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstLut3dInterpolation")]
+#[non_exhaustive]
+pub enum Lut3dInterpolation {
+    #[enum_value(name = "Trilinear: Trilinear interpolation.", nick = "trilinear")]
+    Trilinear = 0,
+
+    #[enum_value(
+        name = "Tetrahedral: Tetrahedral interpolation, more accurate for hue preservation.",
+        nick = "tetrahedral"
+    )]
+    Tetrahedral = 1,
+}
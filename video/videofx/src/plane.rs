@@ -0,0 +1,71 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+// Format independent access to the planes of 8 bit video frames. All components of a pixel in a
+// plane are handled together, e.g. the 4 bytes of an RGBA pixel or the 2 bytes of an NV12 chroma
+// sample.
+
+// Index of the first component stored in `plane`
+pub fn plane_component(finfo: &gst_video::VideoFormatInfo, plane: u32) -> usize {
+    (0..finfo.n_components())
+        .find(|&c| finfo.plane()[c as usize] == plane)
+        .unwrap() as usize
+}
+
+pub struct Plane<'a> {
+    pub data: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+    // Bytes per pixel
+    pub pstride: usize,
+    // Subsampling shifts relative to the first plane
+    pub sub: (u32, u32),
+}
+
+impl<'a> Plane<'a> {
+    pub fn new(frame: &'a gst_video::VideoFrameRef<&gst::BufferRef>, plane: u32) -> Self {
+        let finfo = frame.format_info();
+        let comp = plane_component(&finfo, plane);
+
+        Plane {
+            data: frame.plane_data(plane).unwrap(),
+            width: frame.comp_width(comp as u32) as usize,
+            height: frame.comp_height(comp as u32) as usize,
+            stride: frame.plane_stride()[plane as usize] as usize,
+            pstride: finfo.pixel_stride()[comp] as usize,
+            sub: (finfo.w_sub()[comp], finfo.h_sub()[comp]),
+        }
+    }
+}
+
+pub struct PlaneMut<'a> {
+    pub data: &'a mut [u8],
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+    pub pstride: usize,
+    pub sub: (u32, u32),
+}
+
+impl<'a> PlaneMut<'a> {
+    pub fn new(frame: &'a mut gst_video::VideoFrameRef<&mut gst::BufferRef>, plane: u32) -> Self {
+        let finfo = frame.format_info();
+        let comp = plane_component(&finfo, plane);
+        let width = frame.comp_width(comp as u32) as usize;
+        let height = frame.comp_height(comp as u32) as usize;
+        let stride = frame.plane_stride()[plane as usize] as usize;
+
+        PlaneMut {
+            data: frame.plane_data_mut(plane).unwrap(),
+            width,
+            height,
+            stride,
+            pstride: finfo.pixel_stride()[comp] as usize,
+            sub: (finfo.w_sub()[comp], finfo.h_sub()[comp]),
+        }
+    }
+}
@@ -0,0 +1,556 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::prelude::*;
+use gst_video::subclass::prelude::*;
+
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+
+use super::TimingMarkMode;
+
+const DEFAULT_MODE: TimingMarkMode = TimingMarkMode::Embed;
+const DEFAULT_MARK_HEIGHT: u32 = 16;
+
+// Layout of the cells: start guard, 48 bits clock time in milliseconds, 16 bits frame counter,
+// 8 bits CRC and end guard
+const START_GUARD: [bool; 2] = [true, false];
+const END_GUARD: [bool; 2] = [false, true];
+const PAYLOAD_BITS: usize = 64;
+const CRC_BITS: usize = 8;
+const NUM_CELLS: usize = START_GUARD.len() + PAYLOAD_BITS + CRC_BITS + END_GUARD.len();
+
+const CLOCK_TIME_MASK: u64 = (1 << 48) - 1;
+
+// Luma values of the cells, in video range so they survive range conversions
+const BLACK: u8 = 16;
+const WHITE: u8 = 235;
+const THRESHOLD: u32 = 128;
+
+static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
+    gst::DebugCategory::new(
+        "timingmark",
+        gst::DebugColorFlags::empty(),
+        Some("Timing mark embedding and analysis"),
+    )
+});
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    mode: TimingMarkMode,
+    mark_height: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: DEFAULT_MODE,
+            mark_height: DEFAULT_MARK_HEIGHT,
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // Counter embedded into the next frame
+    frame_counter: u16,
+    // Counter of the last analyzed frame
+    last_frame_number: Option<u16>,
+    total_dropped: u64,
+}
+
+#[derive(Default)]
+pub struct TimingMark {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TimingMark {
+    const NAME: &'static str = "GstTimingMark";
+    type Type = super::TimingMark;
+    type ParentType = gst_video::VideoFilter;
+}
+
+// CRC-8 with polynomial 0x07
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+// Encodes clock time in milliseconds and frame counter into the cell values
+fn encode(clock_time_ms: u64, frame_number: u16) -> [bool; NUM_CELLS] {
+    let payload = ((clock_time_ms & CLOCK_TIME_MASK) << 16) | frame_number as u64;
+    let crc = crc8(&payload.to_be_bytes());
+
+    let mut cells = [false; NUM_CELLS];
+    let bits = START_GUARD
+        .into_iter()
+        .chain((0..PAYLOAD_BITS).rev().map(|i| (payload >> i) & 1 == 1))
+        .chain((0..CRC_BITS).rev().map(|i| (crc >> i) & 1 == 1))
+        .chain(END_GUARD);
+    for (cell, bit) in cells.iter_mut().zip(bits) {
+        *cell = bit;
+    }
+
+    cells
+}
+
+// Decodes the cell values into clock time in milliseconds and frame counter
+fn decode(cells: &[bool; NUM_CELLS]) -> Option<(u64, u16)> {
+    let (start, rest) = cells.split_at(START_GUARD.len());
+    let (payload_bits, rest) = rest.split_at(PAYLOAD_BITS);
+    let (crc_bits, end) = rest.split_at(CRC_BITS);
+
+    if start != START_GUARD || end != END_GUARD {
+        return None;
+    }
+
+    let payload = payload_bits
+        .iter()
+        .fold(0u64, |acc, bit| (acc << 1) | *bit as u64);
+    let crc = crc_bits
+        .iter()
+        .fold(0u8, |acc, bit| (acc << 1) | *bit as u8);
+
+    if crc8(&payload.to_be_bytes()) != crc {
+        return None;
+    }
+
+    Some((payload >> 16, (payload & 0xffff) as u16))
+}
+
+fn system_time_ns() -> Option<u64> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_nanos() as u64)
+}
+
+// Width of a cell in pixels and height of the mark strip, or `None` if the frame is too small
+fn mark_geometry(width: u32, height: u32, mark_height: u32) -> Option<(usize, usize)> {
+    let cell_width = width as usize / NUM_CELLS;
+    let mark_height = mark_height.min(height) as usize;
+
+    if cell_width < 2 || mark_height < 2 {
+        None
+    } else {
+        Some((cell_width, mark_height))
+    }
+}
+
+impl TimingMark {
+    fn draw_marks(
+        &self,
+        frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+        cells: &[bool; NUM_CELLS],
+        cell_width: usize,
+        mark_height: usize,
+    ) -> Result<(), gst::FlowError> {
+        let format_info = frame.format_info();
+        let strides = frame.plane_stride().to_vec();
+
+        if format_info.is_yuv() {
+            let stride = strides[0] as usize;
+            let data = frame.plane_data_mut(0).map_err(|_| gst::FlowError::Error)?;
+            for line in data.chunks_exact_mut(stride).take(mark_height) {
+                for (cell, pixels) in cells.iter().zip(line.chunks_exact_mut(cell_width)) {
+                    pixels.fill(if *cell { WHITE } else { BLACK });
+                }
+            }
+
+            // Make the strip neutral grey in the chroma components, which can be interleaved in
+            // the same plane
+            for component in 1..format_info.n_components() as usize {
+                let plane = format_info.plane()[component];
+                let offset = format_info.poffset()[component] as usize;
+                let pixel_stride = format_info.pixel_stride()[component] as usize;
+                let width = format_info
+                    .scale_width(component as u8, (NUM_CELLS * cell_width) as u32)
+                    as usize;
+                let rows = format_info.scale_height(component as u8, mark_height as u32) as usize;
+
+                let stride = strides[plane as usize] as usize;
+                let data = frame
+                    .plane_data_mut(plane)
+                    .map_err(|_| gst::FlowError::Error)?;
+                for line in data.chunks_exact_mut(stride).take(rows) {
+                    line[offset..]
+                        .iter_mut()
+                        .step_by(pixel_stride)
+                        .take(width)
+                        .for_each(|value| *value = 128);
+                }
+            }
+        } else {
+            let pixel_stride = format_info.pixel_stride()[0] as usize;
+            let alpha_offset = format_info
+                .has_alpha()
+                .then(|| format_info.poffset()[3] as usize);
+
+            let stride = strides[0] as usize;
+            let data = frame.plane_data_mut(0).map_err(|_| gst::FlowError::Error)?;
+            for line in data.chunks_exact_mut(stride).take(mark_height) {
+                for (cell, pixels) in cells
+                    .iter()
+                    .zip(line.chunks_exact_mut(cell_width * pixel_stride))
+                {
+                    pixels.fill(if *cell { WHITE } else { BLACK });
+                    if let Some(alpha_offset) = alpha_offset {
+                        for pixel in pixels.chunks_exact_mut(pixel_stride) {
+                            pixel[alpha_offset] = 255;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_marks(
+        &self,
+        frame: &gst_video::VideoFrameRef<&gst::BufferRef>,
+        cell_width: usize,
+        mark_height: usize,
+    ) -> Result<[bool; NUM_CELLS], gst::FlowError> {
+        let format_info = frame.format_info();
+        let stride = frame.plane_stride()[0] as usize;
+        let data = frame.plane_data(0).map_err(|_| gst::FlowError::Error)?;
+
+        // Luma of a pixel, approximated by the mean of the color components for RGB
+        let (pixel_stride, offsets) = if format_info.is_yuv() {
+            (1, vec![0])
+        } else {
+            (
+                format_info.pixel_stride()[0] as usize,
+                format_info.poffset()[..3]
+                    .iter()
+                    .map(|o| *o as usize)
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        // Only sample the center of each cell to be robust against scaling and blurring
+        let rows = (mark_height / 4)..(mark_height - mark_height / 4);
+        let columns = (cell_width / 4)..(cell_width - cell_width / 4);
+
+        let mut cells = [false; NUM_CELLS];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let mut sum = 0u32;
+            let mut count = 0u32;
+
+            for y in rows.clone() {
+                let line = &data[y * stride..];
+                for x in columns.clone() {
+                    let pixel = &line[(i * cell_width + x) * pixel_stride..];
+                    for offset in &offsets {
+                        sum += pixel[*offset] as u32;
+                        count += 1;
+                    }
+                }
+            }
+
+            *cell = sum > THRESHOLD * count;
+        }
+
+        Ok(cells)
+    }
+
+    fn post_measurement(&self, pts: Option<gst::ClockTime>, clock_time_ms: u64, frame_number: u16) {
+        let Some(now) = system_time_ns() else {
+            gst::warning!(CAT, imp: self, "Failed to get system time");
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let dropped = match state.last_frame_number {
+            Some(last_frame_number) => {
+                let dropped = frame_number.wrapping_sub(last_frame_number.wrapping_add(1));
+                // Large differences mean the frames were repeated or the sender restarted
+                if dropped < u16::MAX / 2 {
+                    dropped as u32
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        };
+        state.last_frame_number = Some(frame_number);
+        state.total_dropped += dropped as u64;
+        let total_dropped = state.total_dropped;
+        drop(state);
+
+        let capture_time = clock_time_ms * 1_000_000;
+        let latency = now as i64 - capture_time as i64;
+
+        if dropped > 0 {
+            gst::debug!(
+                CAT,
+                imp: self,
+                "Dropped {dropped} frames before frame {frame_number}"
+            );
+        }
+        gst::trace!(
+            CAT,
+            imp: self,
+            "Frame {frame_number} with latency {}ms",
+            latency / 1_000_000
+        );
+
+        let running_time = self
+            .obj()
+            .segment()
+            .downcast_ref::<gst::ClockTime>()
+            .and_then(|segment| segment.to_running_time(pts));
+
+        let s = gst::Structure::builder("timingmark")
+            .field("running-time", running_time)
+            .field("capture-time", capture_time)
+            .field("latency", latency)
+            .field("frame-number", frame_number as u32)
+            .field("dropped", dropped)
+            .field("total-dropped", total_dropped)
+            .build();
+
+        let _ = self
+            .obj()
+            .post_message(gst::message::Element::builder(s).build());
+    }
+}
+
+impl ObjectImpl for TimingMark {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("Whether to embed or to analyze timing marks")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("mark-height")
+                    .nick("Mark Height")
+                    .blurb("Height in pixels of the timing mark strip at the top of the frame")
+                    .minimum(2)
+                    .default_value(DEFAULT_MARK_HEIGHT)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "mode" => {
+                let mode = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp: self,
+                    "Changing mode from {:?} to {:?}",
+                    settings.mode,
+                    mode
+                );
+                settings.mode = mode;
+                drop(settings);
+
+                self.obj().set_passthrough(mode == TimingMarkMode::Analyze);
+            }
+            "mark-height" => {
+                settings.mark_height = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "mode" => settings.mode.to_value(),
+            "mark-height" => settings.mark_height.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for TimingMark {}
+
+impl ElementImpl for TimingMark {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: Lazy<gst::subclass::ElementMetadata> = Lazy::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Timing mark",
+                "Filter/Analyzer/Video",
+                "Embeds timing marks into video frames and measures latency and frame drops from them",
+                "agent <agent@local>",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: Lazy<Vec<gst::PadTemplate>> = Lazy::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format_list([
+                    gst_video::VideoFormat::I420,
+                    gst_video::VideoFormat::Yv12,
+                    gst_video::VideoFormat::Nv12,
+                    gst_video::VideoFormat::Nv21,
+                    gst_video::VideoFormat::Y444,
+                    gst_video::VideoFormat::Y42b,
+                    gst_video::VideoFormat::Rgbx,
+                    gst_video::VideoFormat::Xrgb,
+                    gst_video::VideoFormat::Bgrx,
+                    gst_video::VideoFormat::Xbgr,
+                    gst_video::VideoFormat::Rgba,
+                    gst_video::VideoFormat::Argb,
+                    gst_video::VideoFormat::Bgra,
+                    gst_video::VideoFormat::Abgr,
+                    gst_video::VideoFormat::Rgb,
+                    gst_video::VideoFormat::Bgr,
+                ])
+                .build();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for TimingMark {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = true;
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let mode = self.settings.lock().unwrap().mode;
+        self.obj().set_passthrough(mode == TimingMarkMode::Analyze);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+}
+
+impl VideoFilterImpl for TimingMark {
+    fn transform_frame_ip(
+        &self,
+        frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+
+        let Some((cell_width, mark_height)) =
+            mark_geometry(frame.width(), frame.height(), settings.mark_height)
+        else {
+            gst::warning!(CAT, imp: self, "Frame too small for timing marks");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let Some(now) = system_time_ns() else {
+            gst::warning!(CAT, imp: self, "Failed to get system time");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let frame_number = {
+            let mut state = self.state.lock().unwrap();
+            let frame_number = state.frame_counter;
+            state.frame_counter = state.frame_counter.wrapping_add(1);
+            frame_number
+        };
+
+        gst::trace!(CAT, imp: self, "Embedding frame {frame_number}");
+
+        let cells = encode(now / 1_000_000, frame_number);
+        self.draw_marks(frame, &cells, cell_width, mark_height)?;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn transform_frame_ip_passthrough(
+        &self,
+        frame: &gst_video::VideoFrameRef<&gst::BufferRef>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+
+        let Some((cell_width, mark_height)) =
+            mark_geometry(frame.width(), frame.height(), settings.mark_height)
+        else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let cells = self.read_marks(frame, cell_width, mark_height)?;
+        let Some((clock_time_ms, frame_number)) = decode(&cells) else {
+            gst::trace!(CAT, imp: self, "No valid timing mark in frame");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        self.post_measurement(frame.buffer().pts(), clock_time_ms, frame_number);
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let clock_time_ms = 1_709_210_096_789;
+        let cells = encode(clock_time_ms, 4321);
+        assert_eq!(decode(&cells), Some((clock_time_ms, 4321)));
+
+        let mut corrupted = cells;
+        corrupted[10] = !corrupted[10];
+        assert_eq!(decode(&corrupted), None);
+
+        assert_eq!(decode(&[false; NUM_CELLS]), None);
+    }
+}
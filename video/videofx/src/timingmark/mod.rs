@@ -0,0 +1,77 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-timingmark:
+ * @short_description: Embeds machine readable timing marks into video frames and measures latency and frame drops from them.
+ *
+ * In `embed` mode, a strip of black and white cells is drawn at the top of every frame. The cells
+ * encode the wall clock time in milliseconds when the frame was processed and a frame counter,
+ * protected by a checksum.
+ *
+ * In `analyze` mode, the element operates in passthrough mode and decodes the timing marks of the
+ * incoming frames. For every frame with a valid timing mark an element message is posted that
+ * contains the latency between embedding and analyzing the frame, and the number of frames that
+ * were dropped since the previous timing mark. Frames whose marks can't be decoded are ignored.
+ *
+ * Placing an embedding element right after the capture and an analyzing element right before the
+ * sink allows measuring the end-to-end latency and frame drops of a pipeline, e.g. in CI tests.
+ * When embedding and analyzing on different machines, the system clocks must be synchronized.
+ *
+ * The timing marks survive scaling and lossy encoding as long as each cell is at least a few pixels
+ * wide and the cells are not too blurred.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 -m videotestsrc is-live=true ! timingmark mode=embed ! x264enc tune=zerolatency \
+ *   ! avdec_h264 ! timingmark mode=analyze ! autovideosink
+ * ```
+ *
+ * The message posted to the application contains a structure that looks like:
+ *
+ * ```ignore
+ * timingmark,
+ *   running-time=(guint64)1000000000,
+ *   capture-time=(guint64)1717171717123000000,
+ *   latency=(gint64)35000000,
+ *   frame-number=(uint)30,
+ *   dropped=(uint)0,
+ *   total-dropped=(guint64)0;
+ * ```
+ *
+ * Since: plugins-rs-0.13.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct TimingMark(ObjectSubclass<imp::TimingMark>) @extends gst_video::VideoFilter, gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "timingmark",
+        gst::Rank::NONE,
+        TimingMark::static_type(),
+    )
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTimingMarkMode")]
+#[non_exhaustive]
+pub enum TimingMarkMode {
+    #[enum_value(name = "Embed: Draw timing marks into the frames.", nick = "embed")]
+    Embed = 0,
+
+    #[enum_value(
+        name = "Analyze: Decode timing marks and post latency and frame drop messages.",
+        nick = "analyze"
+    )]
+    Analyze = 1,
+}
@@ -0,0 +1,86 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gstrsvideofx::BurnInPosition;
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsvideofx::plugin_register_static().expect("Failed to register videofx plugin");
+    });
+}
+
+fn setup_harness(text: &str, position: BurnInPosition) -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("burninoverlay");
+    h.element().unwrap().set_property("text", text);
+    h.element().unwrap().set_property("position", position);
+    h.set_src_caps_str(&format!(
+        "video/x-raw,format=BGRx,width={WIDTH},height={HEIGHT},framerate=25/1"
+    ));
+
+    h
+}
+
+fn push_pull(h: &mut gst_check::Harness) -> Vec<u8> {
+    let mut buffer = gst::Buffer::from_slice(vec![0u8; WIDTH * HEIGHT * 4]);
+    buffer.get_mut().unwrap().set_pts(gst::ClockTime::ZERO);
+
+    let buffer = h.push_and_pull(buffer).unwrap();
+    let map = buffer.map_readable().unwrap();
+    map.to_vec()
+}
+
+// Whether any pixel in the given quadrant of the frame was changed
+fn quadrant_changed(data: &[u8], right: bool, bottom: bool) -> bool {
+    let (x0, y0) = (
+        if right { WIDTH / 2 } else { 0 },
+        if bottom { HEIGHT / 2 } else { 0 },
+    );
+
+    (y0..y0 + HEIGHT / 2).any(|y| {
+        let line = &data[(y * WIDTH + x0) * 4..][..WIDTH / 2 * 4];
+        line.iter().any(|&v| v != 0)
+    })
+}
+
+#[test]
+fn test_position() {
+    init();
+
+    for (position, right, bottom) in [
+        (BurnInPosition::TopLeft, false, false),
+        (BurnInPosition::TopRight, true, false),
+        (BurnInPosition::BottomLeft, false, true),
+        (BurnInPosition::BottomRight, true, true),
+    ] {
+        let mut h = setup_harness("{running-time}", position);
+        let data = push_pull(&mut h);
+
+        for (r, b) in [(false, false), (true, false), (false, true), (true, true)] {
+            assert_eq!(
+                quadrant_changed(&data, r, b),
+                (r, b) == (right, bottom),
+                "{position:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_empty_text() {
+    init();
+
+    let mut h = setup_harness("", BurnInPosition::Center);
+    let data = push_pull(&mut h);
+    assert!(data.iter().all(|&v| v == 0));
+}
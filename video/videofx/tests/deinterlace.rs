@@ -0,0 +1,144 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gstrsvideofx::DeinterlaceMode;
+
+const WIDTH: usize = 16;
+const HEIGHT: usize = 16;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsvideofx::plugin_register_static().expect("Failed to register videofx plugin");
+    });
+}
+
+fn setup_harness(mode: DeinterlaceMode, double_rate: bool) -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("rsdeinterlace");
+    h.element().unwrap().set_property("mode", mode);
+    h.element()
+        .unwrap()
+        .set_property("double-rate", double_rate);
+    h.set_src_caps_str(&format!(
+        "video/x-raw,format=GRAY8,width={WIDTH},height={HEIGHT},framerate=25/1,interlace-mode=interleaved,field-order=top-field-first"
+    ));
+
+    h
+}
+
+fn frame(pts: u64, line: impl Fn(usize) -> u8) -> gst::Buffer {
+    let data = (0..HEIGHT)
+        .flat_map(|y| std::iter::repeat(line(y)).take(WIDTH))
+        .collect::<Vec<_>>();
+
+    let mut buffer = gst::Buffer::from_slice(data);
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(pts.mseconds());
+        buffer.set_duration(40.mseconds());
+    }
+
+    buffer
+}
+
+// Returns the first pixel of each line
+fn pull(h: &mut gst_check::Harness, pts: u64) -> Vec<u8> {
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(pts.mseconds()));
+
+    let map = buffer.map_readable().unwrap();
+    map.chunks(WIDTH).map(|line| line[0]).collect()
+}
+
+// Top field lines at 200, bottom field lines at 50
+fn combed(y: usize) -> u8 {
+    if y % 2 == 0 {
+        200
+    } else {
+        50
+    }
+}
+
+#[test]
+fn test_caps() {
+    init();
+
+    let mut h = setup_harness(DeinterlaceMode::Bob, true);
+    h.push(frame(0, combed)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let caps = h.sinkpad().unwrap().current_caps().unwrap();
+    let s = caps.structure(0).unwrap();
+    assert_eq!(s.get::<&str>("interlace-mode").unwrap(), "progressive");
+    assert_eq!(
+        s.get::<gst::Fraction>("framerate").unwrap(),
+        gst::Fraction::new(50, 1)
+    );
+    assert!(!s.has_field("field-order"));
+}
+
+#[test]
+fn test_weave() {
+    init();
+
+    let mut h = setup_harness(DeinterlaceMode::Weave, false);
+    h.push(frame(0, combed)).unwrap();
+    h.push(frame(40, combed)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let expected = (0..HEIGHT).map(combed).collect::<Vec<_>>();
+    assert_eq!(pull(&mut h, 0), expected);
+    assert_eq!(pull(&mut h, 40), expected);
+}
+
+#[test]
+fn test_bob_double_rate() {
+    init();
+
+    let mut h = setup_harness(DeinterlaceMode::Bob, true);
+    h.push(frame(0, combed)).unwrap();
+    h.push(frame(40, combed)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    // Each field is interpolated to a full frame
+    for pts in [0, 40] {
+        assert_eq!(pull(&mut h, pts), vec![200; HEIGHT]);
+        assert_eq!(pull(&mut h, pts + 20), vec![50; HEIGHT]);
+    }
+}
+
+#[test]
+fn test_adaptive_static() {
+    init();
+
+    // Smooth static content is reconstructed from the other field
+    let line = |y: usize| (y * y) as u8;
+
+    let mut h = setup_harness(DeinterlaceMode::Adaptive, false);
+    for i in 0..3 {
+        h.push(frame(i * 40, line)).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let expected = (0..HEIGHT).map(line).collect::<Vec<_>>();
+    for i in 0..3 {
+        let out = pull(&mut h, i * 40);
+        // The last line is outside the field and only interpolated spatially
+        assert_eq!(out[..HEIGHT - 1], expected[..HEIGHT - 1]);
+    }
+
+    // Bob on the other hand always interpolates spatially
+    let mut h = setup_harness(DeinterlaceMode::Bob, false);
+    h.push(frame(0, line)).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let out = pull(&mut h, 0);
+    assert_eq!(out[1], 2);
+}
@@ -0,0 +1,120 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gstrsvideofx::FramerateConvertMode;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 64;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsvideofx::plugin_register_static().expect("Failed to register videofx plugin");
+    });
+}
+
+fn setup_harness(mode: FramerateConvertMode) -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("framerateconvert");
+    h.element().unwrap().set_property("mode", mode);
+    h.set_caps_str(
+        &format!("video/x-raw,format=GRAY8,width={WIDTH},height={HEIGHT},framerate=10/1"),
+        &format!("video/x-raw,format=GRAY8,width={WIDTH},height={HEIGHT},framerate=20/1"),
+    );
+
+    h
+}
+
+fn frame(pts: u64, data: Vec<u8>) -> gst::Buffer {
+    let mut buffer = gst::Buffer::from_slice(data);
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(pts.mseconds());
+        buffer.set_duration(100.mseconds());
+    }
+
+    buffer
+}
+
+fn pull(h: &mut gst_check::Harness, pts: u64) -> Vec<u8> {
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(pts.mseconds()));
+    assert_eq!(buffer.duration(), Some(50.mseconds()));
+
+    let map = buffer.map_readable().unwrap();
+    map.as_slice().to_vec()
+}
+
+#[test]
+fn test_blend() {
+    init();
+
+    let mut h = setup_harness(FramerateConvertMode::Blend);
+
+    h.push(frame(0, vec![0; WIDTH * HEIGHT])).unwrap();
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    h.push(frame(100, vec![255; WIDTH * HEIGHT])).unwrap();
+    assert_eq!(pull(&mut h, 0), vec![0; WIDTH * HEIGHT]);
+    assert_eq!(pull(&mut h, 50), vec![128; WIDTH * HEIGHT]);
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    // The last frame is repeated until its end
+    h.push_event(gst::event::Eos::new());
+    assert_eq!(pull(&mut h, 100), vec![255; WIDTH * HEIGHT]);
+    assert_eq!(pull(&mut h, 150), vec![255; WIDTH * HEIGHT]);
+    assert_eq!(h.buffers_in_queue(), 0);
+}
+
+#[test]
+fn test_nearest() {
+    init();
+
+    let mut h = setup_harness(FramerateConvertMode::Nearest);
+
+    h.push(frame(0, vec![0; WIDTH * HEIGHT])).unwrap();
+    h.push(frame(100, vec![255; WIDTH * HEIGHT])).unwrap();
+    assert_eq!(pull(&mut h, 0), vec![0; WIDTH * HEIGHT]);
+    assert_eq!(pull(&mut h, 50), vec![255; WIDTH * HEIGHT]);
+}
+
+#[test]
+fn test_motion_compensated() {
+    init();
+
+    let texture = |x: usize, y: usize| (((x * 37 + y * 91) ^ (x * y)) & 0xff) as u8;
+
+    // Second frame is the first one moved 8 pixels to the right
+    let mut first = vec![0; WIDTH * HEIGHT];
+    let mut second = vec![0; WIDTH * HEIGHT];
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            first[y * WIDTH + x] = texture(x + 8, y);
+            second[y * WIDTH + x] = texture(x, y);
+        }
+    }
+
+    let mut h = setup_harness(FramerateConvertMode::MotionCompensated);
+    h.push(frame(0, first)).unwrap();
+    h.push(frame(100, second)).unwrap();
+
+    pull(&mut h, 0);
+    let interpolated = pull(&mut h, 50);
+
+    // Away from the borders the content is expected to be moved by half the distance
+    for y in 0..HEIGHT {
+        for x in 16..48 {
+            assert_eq!(
+                interpolated[y * WIDTH + x],
+                texture(x + 4, y),
+                "mismatch at {x}x{y}"
+            );
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use std::io::Write;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsvideofx::plugin_register_static().expect("Failed to register videofx plugin");
+    });
+}
+
+const WIDTH: usize = 16;
+const HEIGHT: usize = 16;
+
+// GRAY8 frame with a horizontal gradient
+fn gradient() -> Vec<u8> {
+    (0..HEIGHT)
+        .flat_map(|_| (0..WIDTH).map(|x| (x * 16) as u8))
+        .collect()
+}
+
+fn run(h: &mut gst_check::Harness, input: &[u8]) -> Vec<u8> {
+    h.set_src_caps_str(&format!(
+        "video/x-raw,format=GRAY8,width={WIDTH},height={HEIGHT},framerate=30/1"
+    ));
+
+    let buffer = gst::Buffer::from_slice(input.to_vec());
+    let buffer = h.push_and_pull(buffer).unwrap();
+    let map = buffer.map_readable().unwrap();
+
+    map.as_slice().to_vec()
+}
+
+#[test]
+fn test_identity() {
+    init();
+
+    let mut h = gst_check::Harness::new("lenscorrection");
+    let input = gradient();
+
+    assert_eq!(run(&mut h, &input), input);
+}
+
+#[test]
+fn test_barrel() {
+    init();
+
+    let mut h = gst_check::Harness::new("lenscorrection");
+    h.element().unwrap().set_property("k1", -0.3f64);
+
+    let input = gradient();
+    let output = run(&mut h, &input);
+
+    assert_ne!(output, input);
+
+    // Positions close to the center are barely moved
+    let center = (HEIGHT / 2) * WIDTH + WIDTH / 2;
+    assert!((output[center] as i32 - input[center] as i32).abs() <= 2);
+
+    // Negative k1 samples closer to the center, so the left edge gets brighter
+    assert!(output[(HEIGHT / 2) * WIDTH] > input[(HEIGHT / 2) * WIDTH]);
+}
+
+#[test]
+fn test_profile() {
+    init();
+
+    let path = std::env::temp_dir().join(format!("lenscorrection-{}.ini", std::process::id()));
+    {
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "[camera-a]").unwrap();
+        writeln!(file, "k1=-0.25").unwrap();
+        writeln!(file, "k2=0.05").unwrap();
+        writeln!(file, "center-x=0.4").unwrap();
+        writeln!(file, "[camera-b]").unwrap();
+        writeln!(file, "p1=0.01").unwrap();
+    }
+
+    let lenscorrection = gst::ElementFactory::make("lenscorrection").build().unwrap();
+    lenscorrection.set_property("profile-location", path.to_str().unwrap());
+    lenscorrection.set_property("profile", "camera-a");
+
+    assert_eq!(lenscorrection.property::<f64>("k1"), -0.25);
+    assert_eq!(lenscorrection.property::<f64>("k2"), 0.05);
+    assert_eq!(lenscorrection.property::<f64>("k3"), 0.0);
+    assert_eq!(lenscorrection.property::<f64>("center-x"), 0.4);
+    assert_eq!(lenscorrection.property::<f64>("center-y"), 0.5);
+
+    lenscorrection.set_property("profile", "camera-b");
+    assert_eq!(lenscorrection.property::<f64>("k1"), 0.0);
+    assert_eq!(lenscorrection.property::<f64>("p1"), 0.01);
+
+    // Unknown profiles keep the current coefficients
+    lenscorrection.set_property("profile", "camera-c");
+    assert_eq!(lenscorrection.property::<f64>("p1"), 0.01);
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,137 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gstrsvideofx::Lut3dInterpolation;
+
+use std::io::Write;
+use std::path::PathBuf;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsvideofx::plugin_register_static().expect("Failed to register videofx plugin");
+    });
+}
+
+// Writes a LUT of size 2 to a temporary file, mapping each lattice point with `f`
+fn write_lut(name: &str, f: impl Fn([f32; 3]) -> [f32; 3]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("lut3d-{}-{name}.cube", std::process::id()));
+    let mut file = std::fs::File::create(&path).unwrap();
+
+    writeln!(file, "# Test LUT").unwrap();
+    writeln!(file, "TITLE \"{name}\"").unwrap();
+    writeln!(file, "LUT_3D_SIZE 2").unwrap();
+    for b in [0.0, 1.0] {
+        for g in [0.0, 1.0] {
+            for r in [0.0, 1.0] {
+                let [r, g, b] = f([r, g, b]);
+                writeln!(file, "{r:.6} {g:.6} {b:.6}").unwrap();
+            }
+        }
+    }
+
+    path
+}
+
+fn run(location: Option<&PathBuf>, interpolation: Lut3dInterpolation, input: &[u8]) -> Vec<u8> {
+    let mut h = gst_check::Harness::new("lut3d");
+    {
+        let lut3d = h.element().unwrap();
+        lut3d.set_property("location", location.map(|l| l.to_str().unwrap()));
+        lut3d.set_property("interpolation", interpolation);
+    }
+
+    h.set_src_caps_str("video/x-raw,format=RGBx,width=2,height=1,framerate=30/1");
+
+    let buffer = gst::Buffer::from_slice(input.to_vec());
+    let buffer = h.push_and_pull(buffer).unwrap();
+    let map = buffer.map_readable().unwrap();
+
+    map.as_slice().to_vec()
+}
+
+#[test]
+fn test_identity() {
+    init();
+
+    let path = write_lut("identity", |rgb| rgb);
+    let input = [0, 64, 128, 0, 255, 200, 17, 0];
+
+    for interpolation in [
+        Lut3dInterpolation::Trilinear,
+        Lut3dInterpolation::Tetrahedral,
+    ] {
+        assert_eq!(run(Some(&path), interpolation, &input), input);
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_invert() {
+    init();
+
+    let path = write_lut("invert", |[r, g, b]| [1.0 - r, 1.0 - g, 1.0 - b]);
+    let input = [0, 64, 128, 0, 255, 200, 17, 0];
+
+    for interpolation in [
+        Lut3dInterpolation::Trilinear,
+        Lut3dInterpolation::Tetrahedral,
+    ] {
+        assert_eq!(
+            run(Some(&path), interpolation, &input),
+            [255, 191, 127, 0, 0, 55, 238, 0]
+        );
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_passthrough_without_lut() {
+    init();
+
+    let input = [0, 64, 128, 0, 255, 200, 17, 0];
+    assert_eq!(run(None, Lut3dInterpolation::Trilinear, &input), input);
+}
+
+#[test]
+fn test_switch_lut() {
+    init();
+
+    let identity = write_lut("switch-identity", |rgb| rgb);
+    let invert = write_lut("switch-invert", |[r, g, b]| [1.0 - r, 1.0 - g, 1.0 - b]);
+
+    let mut h = gst_check::Harness::new("lut3d");
+    h.element()
+        .unwrap()
+        .set_property("location", identity.to_str().unwrap());
+    h.set_src_caps_str("video/x-raw,format=RGBx,width=1,height=1,framerate=30/1");
+
+    let buffer = h
+        .push_and_pull(gst::Buffer::from_slice([10u8, 20, 30, 0]))
+        .unwrap();
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), [10, 20, 30, 0]);
+
+    h.element()
+        .unwrap()
+        .set_property("location", invert.to_str().unwrap());
+
+    let buffer = h
+        .push_and_pull(gst::Buffer::from_slice([10u8, 20, 30, 0]))
+        .unwrap();
+    assert_eq!(
+        buffer.map_readable().unwrap().as_slice(),
+        [245, 235, 225, 0]
+    );
+
+    std::fs::remove_file(identity).unwrap();
+    std::fs::remove_file(invert).unwrap();
+}
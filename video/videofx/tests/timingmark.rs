@@ -0,0 +1,122 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gstrsvideofx::TimingMarkMode;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsvideofx::plugin_register_static().expect("Failed to register rsvideofx plugin");
+    });
+}
+
+#[test]
+fn test_latency_and_drops() {
+    init();
+    let pipeline = gst::Pipeline::default();
+
+    let src = gst::ElementFactory::make("videotestsrc")
+        .property("num-buffers", 10i32)
+        .build()
+        .unwrap();
+    let capsfilter = gst::ElementFactory::make("capsfilter")
+        .property(
+            "caps",
+            gst_video::VideoCapsBuilder::new()
+                .format(gst_video::VideoFormat::I420)
+                .width(320)
+                .height(240)
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let embed = gst::ElementFactory::make("timingmark")
+        .property("mode", TimingMarkMode::Embed)
+        .build()
+        .unwrap();
+    let convert = gst::ElementFactory::make("videoconvert").build().unwrap();
+    let rgb_capsfilter = gst::ElementFactory::make("capsfilter")
+        .property(
+            "caps",
+            gst_video::VideoCapsBuilder::new()
+                .format(gst_video::VideoFormat::Rgba)
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let analyze = gst::ElementFactory::make("timingmark")
+        .property("mode", TimingMarkMode::Analyze)
+        .build()
+        .unwrap();
+    let sink = gst::ElementFactory::make("fakevideosink").build().unwrap();
+
+    let elements = [
+        &src,
+        &capsfilter,
+        &embed,
+        &convert,
+        &rgb_capsfilter,
+        &analyze,
+        &sink,
+    ];
+    pipeline
+        .add_many(elements)
+        .expect("failed to add elements to the pipeline");
+    gst::Element::link_many(elements).expect("failed to link the elements");
+
+    // Drop the 4th and 5th frame before analyzing
+    let count = Arc::new(AtomicU32::new(0));
+    analyze.static_pad("sink").unwrap().add_probe(
+        gst::PadProbeType::BUFFER,
+        move |_, _| match count.fetch_add(1, Ordering::SeqCst) {
+            3 | 4 => gst::PadProbeReturn::Drop,
+            _ => gst::PadProbeReturn::Ok,
+        },
+    );
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .expect("Unable to set the pipeline to the `Playing` state");
+
+    let mut frame_numbers = Vec::new();
+    let mut total_dropped = 0;
+    let bus = pipeline.bus().unwrap();
+    for msg in bus.iter_timed(gst::ClockTime::NONE) {
+        use gst::MessageView;
+        match msg.view() {
+            MessageView::Element(elt) => {
+                let Some(s) = elt.structure() else {
+                    continue;
+                };
+                if s.name() != "timingmark" {
+                    continue;
+                }
+
+                let latency = s.get::<i64>("latency").unwrap();
+                assert!(latency >= 0);
+                assert!(latency < gst::ClockTime::from_seconds(10).nseconds() as i64);
+
+                frame_numbers.push(s.get::<u32>("frame-number").unwrap());
+                total_dropped = s.get::<u64>("total-dropped").unwrap();
+            }
+            MessageView::Eos(..) => break,
+            MessageView::Error(err) => panic!("{err:?}"),
+            _ => (),
+        }
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    assert_eq!(frame_numbers, [0, 1, 2, 5, 6, 7, 8, 9]);
+    assert_eq!(total_dropped, 2);
+}
@@ -17,6 +17,11 @@ use std::sync::Mutex;
 
 use std::marker::PhantomData;
 
+use super::WebPDecOutput;
+
+const DEFAULT_OUTPUT: WebPDecOutput = WebPDecOutput::Animation;
+const DEFAULT_LOOP_COUNT: i32 = 1;
+
 static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     gst::DebugCategory::new(
         "rswebpdec",
@@ -25,6 +30,21 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     )
 });
 
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    output: WebPDecOutput,
+    loop_count: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            output: DEFAULT_OUTPUT,
+            loop_count: DEFAULT_LOOP_COUNT,
+        }
+    }
+}
+
 #[derive(Default)]
 struct State {
     buffers: Vec<gst::Buffer>,
@@ -45,6 +65,8 @@ struct Info {
     width: u32,
     height: u32,
     frame_count: u32,
+    // 0 means forever
+    loop_count: u32,
 }
 
 impl<'a> Decoder<'_> {
@@ -92,10 +114,15 @@ impl<'a> Decoder<'_> {
                 width: info.canvas_width,
                 height: info.canvas_height,
                 frame_count: info.frame_count,
+                loop_count: info.loop_count,
             })
         }
     }
 
+    fn reset(&mut self) {
+        unsafe { ffi::WebPAnimDecoderReset(self.decoder) }
+    }
+
     fn next(&mut self) -> Option<Frame> {
         let mut buf = std::ptr::null_mut();
         let buf_ptr: *mut *mut u8 = &mut buf;
@@ -130,6 +157,7 @@ pub struct WebPDec {
     srcpad: gst::Pad,
     sinkpad: gst::Pad,
     state: Mutex<State>,
+    settings: Mutex<Settings>,
 }
 
 impl WebPDec {
@@ -149,7 +177,7 @@ impl WebPDec {
     }
 
     fn decode(&self) -> Result<(), gst::ErrorMessage> {
-        let mut prev_timestamp: Option<gst::ClockTime> = Some(gst::ClockTime::ZERO);
+        let settings = *self.settings.lock().unwrap();
         let mut state = self.state.lock().unwrap();
 
         if state.buffers.is_empty() {
@@ -198,41 +226,103 @@ impl WebPDec {
         let _ = self.srcpad.push_event(gst::event::Caps::new(&caps));
         let _ = self.srcpad.push_event(gst::event::Segment::new(&segment));
 
-        while decoder.has_more_frames() {
+        if settings.output == WebPDecOutput::Still {
+            gst::debug!(CAT, imp: self, "Outputting first frame only");
+
             let frame = decoder.next().ok_or_else(|| {
                 gst::error_msg!(gst::StreamError::Decode, ["Failed to get next frame"])
             })?;
 
-            let timestamp = (frame.timestamp as u64).mseconds();
-            let duration =
-                prev_timestamp.and_then(|prev_timestamp| timestamp.checked_sub(prev_timestamp));
-
             let mut out_buf =
                 gst::Buffer::with_size((info.width * info.height * 4) as usize).unwrap();
             {
                 let out_buf_mut = out_buf.get_mut().unwrap();
                 out_buf_mut.copy_from_slice(0, frame.buf).unwrap();
-                out_buf_mut.set_pts(prev_timestamp);
-                out_buf_mut.set_duration(duration);
+                out_buf_mut.set_pts(gst::ClockTime::ZERO);
             }
 
-            prev_timestamp = Some(timestamp);
+            return self.push(out_buf).map(|_| ());
+        }
 
-            match self.srcpad.push(out_buf) {
-                Ok(_) => (),
-                Err(gst::FlowError::Flushing) | Err(gst::FlowError::Eos) => break,
-                Err(flow) => {
-                    return Err(gst::error_msg!(
-                        gst::StreamError::Failed,
-                        ["Failed to push buffers: {:?}", flow]
-                    ));
+        // Number of times to play the animation, None for forever
+        let loop_count = match settings.loop_count {
+            -1 => (info.loop_count > 0).then_some(info.loop_count),
+            0 => None,
+            n => Some(n as u32),
+        };
+        // A still image is never repeated
+        let loop_count = if info.frame_count == 1 {
+            Some(1)
+        } else {
+            loop_count
+        };
+        gst::debug!(
+            CAT,
+            imp: self,
+            "Playing {} frames {loop_count:?} times, loop count in file {}",
+            info.frame_count,
+            info.loop_count,
+        );
+
+        // The timestamps of the decoder are the end times of the frames since the start of the
+        // animation, which are offset by the duration of all previous iterations
+        let mut offset = gst::ClockTime::ZERO;
+        let mut iteration = 0;
+
+        loop {
+            let mut prev_timestamp = gst::ClockTime::ZERO;
+
+            while decoder.has_more_frames() {
+                let frame = decoder.next().ok_or_else(|| {
+                    gst::error_msg!(gst::StreamError::Decode, ["Failed to get next frame"])
+                })?;
+
+                let timestamp = (frame.timestamp as u64).mseconds();
+                let duration = timestamp.checked_sub(prev_timestamp);
+
+                let mut out_buf =
+                    gst::Buffer::with_size((info.width * info.height * 4) as usize).unwrap();
+                {
+                    let out_buf_mut = out_buf.get_mut().unwrap();
+                    out_buf_mut.copy_from_slice(0, frame.buf).unwrap();
+                    out_buf_mut.set_pts(offset + prev_timestamp);
+                    out_buf_mut.set_duration(duration);
+                    if iteration > 0 && prev_timestamp == gst::ClockTime::ZERO {
+                        out_buf_mut.set_flags(gst::BufferFlags::DISCONT);
+                    }
                 }
+
+                prev_timestamp = timestamp;
+
+                if !self.push(out_buf)? {
+                    return Ok(());
+                }
+            }
+
+            iteration += 1;
+            if loop_count.map_or(false, |loop_count| iteration >= loop_count) {
+                break;
             }
+
+            offset += prev_timestamp;
+            decoder.reset();
         }
 
         Ok(())
     }
 
+    // Returns false if the decoding should stop
+    fn push(&self, buffer: gst::Buffer) -> Result<bool, gst::ErrorMessage> {
+        match self.srcpad.push(buffer) {
+            Ok(_) => Ok(true),
+            Err(gst::FlowError::Flushing) | Err(gst::FlowError::Eos) => Ok(false),
+            Err(flow) => Err(gst::error_msg!(
+                gst::StreamError::Failed,
+                ["Failed to push buffers: {:?}", flow]
+            )),
+        }
+    }
+
     fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
         use gst::EventView;
 
@@ -301,11 +391,61 @@ impl ObjectSubclass for WebPDec {
             srcpad,
             sinkpad,
             state: Mutex::new(State::default()),
+            settings: Mutex::new(Settings::default()),
         }
     }
 }
 
 impl ObjectImpl for WebPDec {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("output", DEFAULT_OUTPUT)
+                    .nick("Output")
+                    .blurb("Whether to output all frames of an animation or only the first one")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecInt::builder("loop-count")
+                    .nick("Loop Count")
+                    .blurb("Number of times to play animations (0 = forever, -1 = as specified in the file)")
+                    .minimum(-1)
+                    .default_value(DEFAULT_LOOP_COUNT)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "output" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output = value.get().expect("type checked upstream");
+            }
+            "loop-count" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.loop_count = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "output" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output.to_value()
+            }
+            "loop-count" => {
+                let settings = self.settings.lock().unwrap();
+                settings.loop_count.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
     fn constructed(&self) {
         self.parent_constructed();
 
@@ -9,17 +9,36 @@
 // Example command-line:
 //
 // gst-launch-1.0 filesrc location=animated.webp ! rswebpdec ! videoconvert ! autovideosink
+//
+// Each frame of an animation is output with its own duration. With `loop-count` the animation
+// is repeated with continuous timestamps, and `output=still` outputs only the first frame.
 
 use gst::glib;
 use gst::prelude::*;
 
 mod imp;
 
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRsWebPDecOutput")]
+pub(crate) enum WebPDecOutput {
+    #[enum_value(
+        name = "Animation: Output all frames of animations.",
+        nick = "animation"
+    )]
+    Animation,
+    #[enum_value(name = "Still: Output only the first frame.", nick = "still")]
+    Still,
+}
+
 glib::wrapper! {
     pub struct WebPDec(ObjectSubclass<imp::WebPDec>) @extends gst::Element, gst::Object;
 }
 
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    WebPDecOutput::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
     gst::Element::register(
         Some(plugin),
         "rswebpdec",
@@ -60,3 +60,57 @@ fn test_decode() {
             .unwrap()
     );
 }
+
+#[test]
+fn test_decode_loop() {
+    init();
+    let data = include_bytes!("animated.webp").as_ref();
+    let mut h = gst_check::Harness::new("rswebpdec");
+    h.element().unwrap().set_property("loop-count", 3i32);
+
+    h.set_src_caps_str("image/webp");
+
+    let buf = gst::Buffer::from_slice(data);
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+    h.push_event(gst::event::Eos::new());
+
+    let mut expected_timestamp: Option<gst::ClockTime> = Some(gst::ClockTime::ZERO);
+    let mut count = 0;
+    let expected_duration: Option<gst::ClockTime> = Some(40_000_000.nseconds());
+
+    while let Some(buf) = h.try_pull() {
+        // Timestamps continue across iterations
+        assert_eq!(buf.pts(), expected_timestamp);
+        assert_eq!(buf.duration(), expected_duration);
+        assert_eq!(
+            buf.flags().contains(gst::BufferFlags::DISCONT),
+            count > 0 && count % 10 == 0
+        );
+
+        expected_timestamp = expected_timestamp.opt_add(expected_duration);
+        count += 1;
+    }
+
+    assert_eq!(count, 30);
+}
+
+#[test]
+fn test_decode_still() {
+    init();
+    let data = include_bytes!("animated.webp").as_ref();
+    let mut h = gst_check::Harness::new("rswebpdec");
+    h.element()
+        .unwrap()
+        .set_property_from_str("output", "still");
+
+    h.set_src_caps_str("image/webp");
+
+    let buf = gst::Buffer::from_slice(data);
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+    h.push_event(gst::event::Eos::new());
+
+    let buf = h.try_pull().unwrap();
+    assert_eq!(buf.pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(buf.duration(), None);
+    assert!(h.try_pull().is_none());
+}